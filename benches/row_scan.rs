@@ -0,0 +1,242 @@
+//! Allocations and time of scanning many rows through the file and mock connectors.
+//!
+//! Run with `cargo bench --bench row_scan`; `NIRV_BENCH_ROWS` sets the rows scanned for the
+//! allocation report (a million by default). Each report line compares the connector with
+//! the row-at-a-time parse it replaced: a `StringRecord` and a `Vec<Value>` per row, with
+//! predicates applied once every row is built.
+//!
+//! The `executor_100k_rows` group times a filter, sort, projection and limit over a scanned
+//! mock source, excluding the scan: once over batches of rows, as the executor runs them,
+//! and once row at a time with the whole-result functions, projecting by cloning values.
+
+use criterion::{BatchSize, Criterion};
+use csv::ReaderBuilder;
+use nirv_engine::connectors::{Connector, ConnectorInitConfig, ConnectorRegistry, FileConnector, MockConnector};
+use nirv_engine::engine::{filter_rows, sort_rows, DefaultQueryExecutor, ExecutionPlan, PlanNode, QueryExecutor, RowBatches};
+use nirv_engine::utils::collation::Collation;
+use nirv_engine::utils::identifier::IdentifierCase;
+use nirv_engine::utils::types::{
+    Column, ConnectorQuery, ConnectorType, DataSource, InternalQuery, OrderBy, OrderColumn, OrderDirection,
+    Predicate, PredicateOperator, PredicateValue, QueryOperation, QueryResult, Row, Value,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+
+/// Counts every allocation and reallocation made by the benchmark
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const STATUSES: [&str; 8] = ["new", "paid", "shipped", "delivered", "returned", "refunded", "held", "void"];
+
+/// Allocations made while running `f`, and its result
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    (ALLOCATIONS.load(Ordering::Relaxed) - before, result)
+}
+
+/// A CSV of orders whose text columns repeat a few categorical values
+fn write_orders(directory: &TempDir, rows: usize) -> String {
+    let mut content = String::from("id,region,status,amount\n");
+    for i in 0..rows {
+        content.push_str(&format!("{},region-{},{},{}.{:02}\n", i, i % 5, STATUSES[i % STATUSES.len()], i % 1000, i % 100));
+    }
+    let name = format!("orders_{}.csv", rows);
+    std::fs::write(directory.path().join(&name), content).unwrap();
+    name
+}
+
+fn query(identifier: &str, predicates: Vec<Predicate>, limit: Option<u64>) -> ConnectorQuery {
+    let mut query = InternalQuery::new(QueryOperation::Select);
+    query.sources.push(DataSource { object_type: "file".to_string(), identifier: identifier.to_string(), alias: None });
+    query.predicates = predicates;
+    query.limit = limit;
+    ConnectorQuery { connector_type: ConnectorType::File, query, connection_params: HashMap::new() }
+}
+
+fn refunded() -> Vec<Predicate> {
+    vec![Predicate {
+        column: "status".to_string(),
+        operator: PredicateOperator::Equal,
+        value: PredicateValue::String("refunded".to_string()),
+    }]
+}
+
+/// The row-at-a-time parse: a record and a row allocated for every line, filtered afterwards
+fn row_at_a_time_scan(content: &[u8], status: Option<&str>) -> Vec<Row> {
+    let mut reader = ReaderBuilder::new().from_reader(content);
+    let rows: Vec<Row> = reader.records()
+        .map(|record| Row::new(record.unwrap().iter().map(|field| {
+            if let Ok(i) = field.parse::<i64>() {
+                Value::Integer(i)
+            } else if let Ok(f) = field.parse::<f64>() {
+                Value::Float(f)
+            } else {
+                Value::Text(field.to_string())
+            }
+        }).collect()))
+        .collect();
+    match status {
+        Some(status) => rows.into_iter().filter(|row| row.values[2] == Value::Text(status.to_string())).collect(),
+        None => rows,
+    }
+}
+
+async fn file_connector(directory: &TempDir) -> FileConnector {
+    let mut connector = FileConnector::new();
+    connector.connect(ConnectorInitConfig::new()
+        .with_param("base_path", directory.path().to_str().unwrap())
+        .with_param("file_extensions", "csv")).await.unwrap();
+    connector
+}
+
+async fn mock_connector(rows: usize) -> MockConnector {
+    let mut connector = MockConnector::new();
+    let data = (0..rows)
+        .map(|i| vec![Value::Integer(i as i64), Value::Text(STATUSES[i % STATUSES.len()].to_string())])
+        .collect();
+    connector.add_test_data_with_columns("orders", vec!["id", "status"], data);
+    connector.connect(ConnectorInitConfig::new()).await.unwrap();
+    connector
+}
+
+/// `SELECT status AS state, id FROM orders WHERE status = 'refunded' ORDER BY id DESC LIMIT 5000`
+fn executor_plan() -> ExecutionPlan {
+    let scan = PlanNode::TableScan {
+        source: DataSource { object_type: "mock".to_string(), identifier: "orders".to_string(), alias: None },
+        projections: Vec::new(),
+        predicates: Vec::new(),
+    };
+    let filter = PlanNode::Filter { predicates: refunded(), input: Box::new(scan) };
+    let sort = PlanNode::Sort { order_by: by_id_descending(), input: Box::new(filter) };
+    let projection = PlanNode::Projection { columns: state_and_id(), input: Box::new(sort) };
+    let mut plan = ExecutionPlan::new();
+    plan.add_node(PlanNode::Limit { count: 5000, input: Box::new(projection) });
+    plan
+}
+
+fn by_id_descending() -> OrderBy {
+    OrderBy { columns: vec![OrderColumn { column: "id".to_string(), direction: OrderDirection::Descending, nulls: None }] }
+}
+
+fn state_and_id() -> Vec<Column> {
+    vec![
+        Column { name: "status".to_string(), alias: Some("state".to_string()), source: None },
+        Column { name: "id".to_string(), alias: None, source: None },
+    ]
+}
+
+/// The plan's operators applied one whole result after another, as the executor did before
+/// it passed batches
+fn row_at_a_time_operators(scanned: QueryResult) -> QueryResult {
+    let filtered = filter_rows(scanned, &refunded(), IdentifierCase::default(), Collation::default()).unwrap();
+    let mut sorted = sort_rows(filtered, &by_id_descending(), IdentifierCase::default(), Collation::default()).unwrap();
+    let indexes: Vec<usize> = ["status", "id"].iter()
+        .map(|name| sorted.columns.iter().position(|col| col.name == *name).unwrap())
+        .collect();
+    sorted.rows = sorted.rows.iter()
+        .map(|row| Row::new(indexes.iter().map(|i| row.values[*i].clone()).collect()))
+        .collect();
+    sorted.rows.truncate(5000);
+    sorted
+}
+
+/// The plan's operators over batches, as the executor applies them to a scan
+fn batched_operators(scanned: QueryResult) -> QueryResult {
+    RowBatches::from_filtered_result(scanned, &refunded(), IdentifierCase::default(), Collation::default()).unwrap()
+        .sort(&by_id_descending(), IdentifierCase::default(), Collation::default()).unwrap()
+        .project(&state_and_id(), IdentifierCase::default()).unwrap()
+        .limit(5000)
+        .into_result()
+}
+
+fn report_allocations(runtime: &Runtime, directory: &TempDir, rows: usize) {
+    let file = write_orders(directory, rows);
+    let content = std::fs::read(directory.path().join(&file)).unwrap();
+    let connector = runtime.block_on(file_connector(directory));
+    let per_row = |allocations: usize| allocations as f64 / rows as f64;
+
+    println!("Allocations per row scanned, {} rows:", rows);
+    let scans: [(&str, Vec<Predicate>, Option<&str>); 2] = [
+        ("full scan", Vec::new(), None),
+        ("status = 'refunded'", refunded(), Some("refunded")),
+    ];
+    for (name, predicates, status) in scans {
+        let (before, expected) = count_allocations(|| row_at_a_time_scan(&content, status));
+        let (after, result) = count_allocations(|| runtime.block_on(connector.execute_query(query(&file, predicates, None))).unwrap());
+        assert_eq!(result.rows.len(), expected.len());
+        println!("  {:<24} row at a time {:>6.2}   file connector {:>6.2}", name, per_row(before), per_row(after));
+    }
+
+    let mock = runtime.block_on(mock_connector(rows));
+    let (allocations, result) = count_allocations(|| runtime.block_on(mock.execute_query(query("orders", refunded(), Some(100)))).unwrap());
+    assert_eq!(result.rows.len(), 100);
+    println!("  {:<24} mock connector {} allocations in total", "LIMIT 100 of a filter", allocations);
+}
+
+fn main() {
+    let runtime = Runtime::new().unwrap();
+    let directory = TempDir::new().unwrap();
+    let rows = std::env::var("NIRV_BENCH_ROWS").ok().and_then(|rows| rows.parse().ok()).unwrap_or(1_000_000);
+    report_allocations(&runtime, &directory, rows);
+
+    let mut criterion = Criterion::default()
+        .sample_size(10)
+        .measurement_time(Duration::from_secs(5))
+        .configure_from_args();
+    let file = write_orders(&directory, 100_000);
+    let connector = runtime.block_on(file_connector(&directory));
+    let mut group = criterion.benchmark_group("file_connector_100k_rows");
+    group.bench_function("full_scan", |b| {
+        b.iter(|| runtime.block_on(connector.execute_query(query(&file, Vec::new(), None))).unwrap())
+    });
+    group.bench_function("filtered_scan", |b| {
+        b.iter(|| runtime.block_on(connector.execute_query(query(&file, refunded(), None))).unwrap())
+    });
+    group.finish();
+
+    let mut registry = ConnectorRegistry::new();
+    registry.register("mock".to_string(), Box::new(runtime.block_on(mock_connector(100_000)))).unwrap();
+    let executor = DefaultQueryExecutor::with_connector_registry(registry);
+    let scanned = runtime.block_on(executor.execute_node(&PlanNode::TableScan {
+        source: DataSource { object_type: "mock".to_string(), identifier: "orders".to_string(), alias: None },
+        projections: Vec::new(),
+        predicates: Vec::new(),
+    })).unwrap();
+    let values = |result: QueryResult| result.rows.into_iter().map(|row| row.values).collect::<Vec<_>>();
+    let executed = runtime.block_on(executor.execute_plan(&executor_plan())).unwrap();
+    assert_eq!(values(executed), values(row_at_a_time_operators(scanned.clone())));
+    let mut group = criterion.benchmark_group("executor_100k_rows");
+    group.bench_function("row_at_a_time", |b| {
+        b.iter_batched(|| scanned.clone(), row_at_a_time_operators, BatchSize::LargeInput)
+    });
+    group.bench_function("batched", |b| {
+        b.iter_batched(|| scanned.clone(), batched_operators, BatchSize::LargeInput)
+    });
+    group.finish();
+    criterion.final_summary();
+}
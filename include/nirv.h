@@ -0,0 +1,50 @@
+/*
+ * C API of the NIRV engine, for embedding it in services written in other languages.
+ *
+ * Build the library with the `ffi` feature:
+ *
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * and link against target/release/libnirv_engine.so (.dylib on macOS, .dll on Windows),
+ * or pass `--crate-type staticlib` for libnirv_engine.a.
+ *
+ * Every string is NUL-terminated UTF-8. Strings the library returns belong to the caller,
+ * who frees them with nirv_free_result. An engine handle may be used from several threads
+ * at once; queries on it run concurrently.
+ */
+
+#ifndef NIRV_H
+#define NIRV_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An engine and the runtime its queries run on */
+typedef struct NirvEngine NirvEngine;
+
+/*
+ * Create an engine from a JSON configuration, in the format of `nirv --config`, or with the
+ * defaults, no protocol servers and usage tracked in system.usage when config_json is NULL.
+ * Returns NULL on failure, with the reason in *error when error is not NULL; free it with
+ * nirv_free_result.
+ */
+NirvEngine *nirv_engine_new(const char *config_json, char **error);
+
+/*
+ * Execute a query and return its result as JSON: {"data": [...], "metadata": {...}}, or
+ * {"error": "..."} when it fails. Never returns NULL; free the result with nirv_free_result.
+ */
+char *nirv_query_json(const NirvEngine *engine, const char *sql);
+
+/* Free a string returned by the library; NULL is ignored */
+void nirv_free_result(char *result);
+
+/* Shut an engine down and free it; NULL is ignored */
+void nirv_engine_free(NirvEngine *engine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NIRV_H */
@@ -1,4 +1,8 @@
 use clap::{Parser, Subcommand, ValueEnum};
+use std::str::FromStr;
+
+use crate::engine::ClientDialect;
+use crate::utils::types::Value;
 
 /// NIRV Engine CLI - Universal data virtualization and compute orchestration
 #[derive(Parser, Debug)]
@@ -8,6 +12,10 @@ use clap::{Parser, Subcommand, ValueEnum};
 pub struct CliArgs {
     #[command(subcommand)]
     pub command: Commands,
+    
+    /// Source profile applied to the configuration file (defaults to NIRV_PROFILE)
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
 }
 
 /// Available CLI commands
@@ -30,6 +38,39 @@ pub enum Commands {
         /// Enable verbose output
         #[arg(short, long)]
         verbose: bool,
+        
+        /// Bind a `:name` placeholder, as `name=value` or `name:type=value`
+        /// (types: int, float, bool, text, date, datetime, json, null)
+        #[arg(short, long = "param", value_name = "NAME=VALUE")]
+        params: Vec<QueryParameter>,
+        
+        /// Validate the query and show its plan without executing it
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Print rows scanned, bytes fetched and time per step to stderr
+        #[arg(long)]
+        stats: bool,
+        
+        /// SQL dialect the query is written in: postgresql, mysql, sqlite or sqlserver.
+        /// `auto` tries the dialect the query looks like first, then each in turn.
+        #[arg(long, default_value = "auto")]
+        dialect: ClientDialect,
+    },
+    
+    /// Execute two queries and compare their results row by row
+    Diff {
+        /// Query whose rows are expected
+        #[arg(value_name = "LEFT_SQL")]
+        left: String,
+        
+        /// Query compared against the left one
+        #[arg(value_name = "RIGHT_SQL")]
+        right: String,
+        
+        /// Column aligning rows between the two results
+        #[arg(short, long, default_value = "id")]
+        key: String,
     },
     
     /// List available data sources
@@ -44,6 +85,172 @@ pub enum Commands {
         /// Data source identifier (e.g., "postgres.users")
         source: String,
     },
+    
+    /// Summarize every column of a source: nulls, distinct values, range, histogram and samples
+    Profile {
+        /// Source to profile, as `source('file.users.csv')` or `file.users.csv`
+        #[arg(value_name = "SOURCE")]
+        source: String,
+
+        /// Output format
+        #[arg(short, long, default_value = "table")]
+        format: OutputFormat,
+
+        /// Histogram buckets per column
+        #[arg(short, long, default_value = "10")]
+        buckets: usize,
+
+        /// Sample values shown per column
+        #[arg(short, long, default_value = "5")]
+        samples: usize,
+    },
+
+    /// Suggest join conditions between two sources from their keys and column names
+    Joins {
+        /// Left source, as `source('postgres.orders')` or `postgres.orders`
+        #[arg(value_name = "LEFT")]
+        left: String,
+
+        /// Right source
+        #[arg(value_name = "RIGHT")]
+        right: String,
+
+        /// Output format
+        #[arg(short, long, default_value = "table")]
+        format: OutputFormat,
+    },
+
+    /// Browse and re-run previously executed queries
+    History {
+        #[command(subcommand)]
+        action: HistoryCommand,
+    },
+    
+    /// Check configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+/// Configuration file actions
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Report every problem in a configuration file, with the selected profile applied
+    Validate {
+        /// Configuration file to check
+        #[arg(value_name = "PATH")]
+        path: String,
+    },
+}
+
+/// Query history actions
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// Show the most recent queries
+    List {
+        /// Number of queries to show
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
+    
+    /// Find queries whose SQL contains the text, ignoring case
+    Search {
+        text: String,
+    },
+    
+    /// Execute a query from the history again
+    Rerun {
+        /// Id shown by `history list`
+        id: u64,
+        
+        /// Output format
+        #[arg(short, long, default_value = "table")]
+        format: OutputFormat,
+    },
+}
+
+/// A named query parameter given on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryParameter {
+    pub name: String,
+    pub value: Value,
+}
+
+impl FromStr for QueryParameter {
+    type Err = String;
+
+    /// Parse `name=value`, inferring the type, or `name:type=value`. Quoted values are text.
+    fn from_str(param: &str) -> Result<Self, Self::Err> {
+        let (target, raw) = param.split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", param))?;
+        let (name, type_name) = match target.split_once(':') {
+            Some((name, type_name)) => (name.trim(), Some(type_name.trim().to_lowercase())),
+            None => (target.trim(), None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("invalid parameter name '{}'", name));
+        }
+
+        let invalid = |type_name: &str| format!("invalid {} value '{}' for parameter {}", type_name, raw, name);
+        let value = match type_name.as_deref() {
+            None => infer_parameter_value(raw),
+            Some("int" | "integer") => Value::Integer(raw.parse().map_err(|_| invalid("int"))?),
+            Some("float" | "double") => Value::Float(raw.parse().map_err(|_| invalid("float"))?),
+            Some("bool" | "boolean") => Value::Boolean(parse_bool(raw).ok_or_else(|| invalid("bool"))?),
+            Some("text" | "string") => Value::Text(raw.to_string()),
+            Some("date") => {
+                chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| invalid("date"))?;
+                Value::Date(raw.to_string())
+            }
+            Some("datetime" | "timestamp") => {
+                chrono::NaiveDateTime::parse_from_str(&raw.replace('T', " "), "%Y-%m-%d %H:%M:%S%.f")
+                    .map_err(|_| invalid("datetime"))?;
+                Value::DateTime(raw.to_string())
+            }
+            Some("json") => {
+                serde_json::from_str::<serde_json::Value>(raw).map_err(|_| invalid("json"))?;
+                Value::Json(raw.to_string())
+            }
+            Some("null") => Value::Null,
+            Some(other) => return Err(format!("unknown parameter type '{}'", other)),
+        };
+
+        Ok(Self { name: name.to_string(), value })
+    }
+}
+
+/// Type an untyped parameter value: integers, floats, booleans and `null` are recognized,
+/// anything else (including quoted values) is text
+fn infer_parameter_value(raw: &str) -> Value {
+    let quoted = raw.len() >= 2
+        && ((raw.starts_with('\'') && raw.ends_with('\'')) || (raw.starts_with('"') && raw.ends_with('"')));
+    if quoted {
+        return Value::Text(raw[1..raw.len() - 1].to_string());
+    }
+    if let Ok(i) = raw.parse::<i64>() {
+        return Value::Integer(i);
+    }
+    if let Ok(f) = raw.parse::<f64>() {
+        if f.is_finite() {
+            return Value::Float(f);
+        }
+    }
+    if let Some(b) = parse_bool(raw) {
+        return Value::Boolean(b);
+    }
+    if raw.eq_ignore_ascii_case("null") {
+        return Value::Null;
+    }
+    Value::Text(raw.to_string())
+}
+
+fn parse_bool(raw: &str) -> Option<bool> {
+    match raw.to_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
 }
 
 /// Output format options
@@ -65,4 +272,30 @@ impl std::fmt::Display for OutputFormat {
             OutputFormat::Csv => write!(f, "csv"),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_parameter_parsing() {
+        let parse = |param: &str| param.parse::<QueryParameter>().map(|p| (p.name, p.value));
+
+        assert_eq!(parse("id=42"), Ok(("id".to_string(), Value::Integer(42))));
+        assert_eq!(parse("ratio=0.5").unwrap().1, Value::Float(0.5));
+        assert_eq!(parse("active=TRUE").unwrap().1, Value::Boolean(true));
+        assert_eq!(parse("name=Ann Lee").unwrap().1, Value::Text("Ann Lee".to_string()));
+        assert_eq!(parse("zip='0150'").unwrap().1, Value::Text("0150".to_string()));
+        assert_eq!(parse("zip:text=0150").unwrap().1, Value::Text("0150".to_string()));
+        assert_eq!(parse("day:date=2024-02-29").unwrap().1, Value::Date("2024-02-29".to_string()));
+        assert_eq!(parse("expr=a=b").unwrap().1, Value::Text("a=b".to_string()));
+        assert_eq!(parse("gone=null").unwrap().1, Value::Null);
+
+        assert!(parse("id").is_err());
+        assert!(parse("id:int=abc").is_err());
+        assert!(parse("day:date=2023-02-29").is_err());
+        assert!(parse("id:uuid=1").is_err());
+        assert!(parse("bad name=1").is_err());
+    }
+}
@@ -1,9 +1,15 @@
 use clap::Parser;
 use colored::*;
-use crate::cli::{CliArgs, Commands, OutputFormatter};
-use crate::engine::{DefaultQueryParser, DefaultQueryExecutor, DefaultDispatcher, Dispatcher};
-use crate::connectors::{MockConnector, Connector};
-use crate::utils::error::NirvResult;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::cli::{CliArgs, Commands, ConfigCommand, HistoryCommand, HistoryEntry, OutputFormat, OutputFormatter, ProfileOptions, QueryHistory, QueryParameter, ResultDiff, SourceProfile, diff_results, profile_result};
+use crate::engine::{ClientDialect, DefaultQueryParser, DefaultQueryPlanner, DefaultQueryExecutor, DefaultDispatcher, Dispatcher, DryRunReport, dry_run_query, suggest_joins, join_suggestions_result};
+use crate::connectors::{MockConnector, Connector, ConnectorInitConfig, FileConnector, StdinConnector, InlineConnector, INLINE_OBJECT_TYPE, FsMetaConnector, FSMETA_OBJECT_TYPE, SysConnector, SYS_OBJECT_TYPE, configure_parse_pool, connector_debug_log};
+use crate::connectors::debug_log::{tag_result, with_query_id};
+use crate::protocol::bind_named_parameters;
+use crate::utils::config::{EngineConfig, HistoryConfig, select_profile};
+use crate::utils::error::{NirvError, NirvResult};
+use crate::utils::types::{ExecutionStats, QueryResult, SourceUri};
 
 /// Main CLI runner that handles command execution
 pub struct CliRunner {
@@ -11,6 +17,10 @@ pub struct CliRunner {
     #[allow(dead_code)]
     query_executor: DefaultQueryExecutor,
     dispatcher: DefaultDispatcher,
+    /// Where executed queries are recorded, when enabled
+    history: Option<QueryHistory>,
+    /// Dialect queries are parsed in
+    dialect: ClientDialect,
 }
 
 impl CliRunner {
@@ -21,27 +31,97 @@ impl CliRunner {
         
         // Register and connect mock connector for testing
         let mut mock_connector = Box::new(MockConnector::new());
-        let config = crate::connectors::ConnectorInitConfig::new();
+        let config = ConnectorInitConfig::new();
         mock_connector.connect(config).await?;
         dispatcher.register_connector("mock", mock_connector).await?;
         
+        // Data piped to the CLI, and tables written inline with VALUES
+        dispatcher.register_connector("stdin", Box::new(StdinConnector::new())).await?;
+        dispatcher.register_connector(INLINE_OBJECT_TYPE, Box::new(InlineConnector::new())).await?;
+
+        // File metadata by glob, as `fsmeta./var/log/**`
+        let mut fsmeta_connector = Box::new(FsMetaConnector::new());
+        fsmeta_connector.connect(ConnectorInitConfig::new()).await?;
+        dispatcher.register_connector(FSMETA_OBJECT_TYPE, fsmeta_connector).await?;
+
+        // Runtime context, as `sys.env`, `sys.process` and `sys.host`
+        let mut sys_connector = Box::new(SysConnector::new());
+        sys_connector.connect(ConnectorInitConfig::new()).await?;
+        dispatcher.register_connector(SYS_OBJECT_TYPE, sys_connector).await?;
+        
+        // Files under the working directory, as `file.users.csv`
+        let mut file_connector = Box::new(FileConnector::new());
+        file_connector.connect(ConnectorInitConfig::new().with_param("base_path", ".")).await?;
+        dispatcher.register_connector("file", file_connector).await?;
+        
         let query_executor = DefaultQueryExecutor::new();
         
         Ok(Self {
             query_parser,
             query_executor,
             dispatcher,
+            history: None,
+            dialect: ClientDialect::Auto,
         })
     }
     
+    /// Record executed queries in the given history
+    pub fn with_history(mut self, history: Option<QueryHistory>) -> Self {
+        self.history = history;
+        self
+    }
+    
+    /// Parse queries in the given dialect
+    pub fn with_dialect(mut self, dialect: ClientDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+    
+    /// Check every query against the policies of the configured sources, and describe
+    /// their columns with the configured details
+    pub fn apply_source_policies(&mut self, config: &EngineConfig) {
+        for (name, connector) in &config.connectors {
+            self.dispatcher.set_source_policy(name, connector.policy.clone());
+            self.dispatcher.set_column_details(name, connector.columns.clone());
+        }
+    }
+    
     /// Execute a SQL query and return formatted results
     pub async fn execute_query(&self, sql: &str, format: &crate::cli::OutputFormat, verbose: bool) -> NirvResult<String> {
+        self.execute_query_with_params(sql, &[], format, verbose, false).await
+    }
+    
+    /// Execute a SQL query after binding its `:name` placeholders to the given parameters,
+    /// printing its execution statistics to stderr when `stats` is set
+    pub async fn execute_query_with_params(&self, sql: &str, params: &[QueryParameter], format: &crate::cli::OutputFormat, verbose: bool, stats: bool) -> NirvResult<String> {
+        let sql = &self.bind_params(sql, params)?;
+        
+        let start_time = Instant::now();
+        // Sources see the query under its tag
+        let query_id = connector_debug_log().next_query_id();
+        let outcome = with_query_id(query_id, self.execute_bound_query(sql, verbose)).await
+            .map(|result| tag_result(result, query_id));
+        self.record_history(sql, start_time.elapsed(), &outcome);
+        let result = outcome?;
+        
+        for warning in &result.warnings {
+            eprintln!("{}", OutputFormatter::format_warning(warning));
+        }
+        if stats {
+            eprintln!("{}", OutputFormatter::format_stats(result.stats.as_ref().unwrap_or(&ExecutionStats::default())));
+        }
+        
+        // Format the results
+        Ok(OutputFormatter::format_result(&result, format))
+    }
+    
+    async fn execute_bound_query(&self, sql: &str, verbose: bool) -> NirvResult<QueryResult> {
         if verbose {
             eprintln!("{}", OutputFormatter::format_info(&format!("Parsing query: {}", sql)));
         }
         
         // Parse the SQL query
-        let internal_query = self.query_parser.parse(sql)?;
+        let internal_query = self.query_parser.parse_as(sql, self.dialect)?;
         
         if verbose {
             eprintln!("{}", OutputFormatter::format_info(&format!("Query parsed successfully. Sources: {:?}", 
@@ -56,14 +136,92 @@ impl CliRunner {
         }
         
         // Execute the distributed query
-        let result = self.dispatcher.execute_distributed_query(connector_queries).await?;
+        let mut result = self.dispatcher.execute_distributed_query(connector_queries).await?;
+        if let Some(target) = &internal_query.target {
+            let written = self.dispatcher.write_result(target, &result).await?;
+            result = result.written(written);
+        }
         
         if verbose {
             eprintln!("{}", OutputFormatter::format_info(&format!("Query executed successfully. {} rows returned", result.row_count())));
         }
-        
-        // Format the results
-        Ok(OutputFormatter::format_result(&result, format))
+        Ok(result)
+    }
+    
+    /// Record an executed query; a history that cannot be written never fails the query
+    fn record_history(&self, sql: &str, duration: Duration, outcome: &NirvResult<QueryResult>) {
+        let Some(history) = &self.history else { return };
+        let error;
+        let outcome = match outcome {
+            Ok(result) => Ok(result.row_count() as u64),
+            Err(e) => {
+                error = e.to_string();
+                Err(error.as_str())
+            }
+        };
+        if let Err(e) = history.record(sql, duration, outcome) {
+            eprintln!("{}", OutputFormatter::format_info(&format!("Query not saved to history: {}", e)));
+        }
+    }
+    
+    /// The query history, or an error when it is disabled
+    pub fn history(&self) -> NirvResult<&QueryHistory> {
+        self.history.as_ref().ok_or_else(|| NirvError::Configuration("Query history is disabled".to_string()))
+    }
+    
+    /// Execute a query from the history again, recording it as a new entry
+    pub async fn rerun_query(&self, id: u64, format: &crate::cli::OutputFormat) -> NirvResult<String> {
+        let entry = self.history()?.get(id)?;
+        eprintln!("{}", OutputFormatter::format_info(&format!("Running: {}", entry.sql)));
+        self.execute_query_with_params(&entry.sql, &[], format, false, false).await
+    }
+    
+    /// Execute two queries and compare their rows, aligned by the key column
+    pub async fn diff_queries(&self, left: &str, right: &str, key: &str) -> NirvResult<ResultDiff> {
+        let left = self.run_query(left).await?;
+        let right = self.run_query(right).await?;
+        diff_results(&left, &right, key)
+    }
+    
+    /// Profile every column of a source, given as `source('type.identifier')` or `type.identifier`
+    pub async fn profile_source(&self, source: &str, options: ProfileOptions) -> NirvResult<SourceProfile> {
+        let identifier = source_spec(source);
+        let result = self.run_query(&format!("SELECT * FROM source('{}')", identifier.replace('\'', "''"))).await?;
+        Ok(profile_result(identifier, &result, options))
+    }
+    
+    /// Join conditions between two sources, each given as `source('type.identifier')` or
+    /// `type.identifier`, one row per suggestion
+    pub async fn suggest_joins(&self, left: &str, right: &str) -> NirvResult<QueryResult> {
+        let left = SourceUri::parse(source_spec(left))?.to_data_source(None);
+        let right = SourceUri::parse(source_spec(right))?.to_data_source(None);
+        let left_schema = self.dispatcher.describe_object(&left.object_type, &left.identifier).await?;
+        let right_schema = self.dispatcher.describe_object(&right.object_type, &right.identifier).await?;
+        let suggestions = suggest_joins(&left, &left_schema, &right, &right_schema);
+        Ok(join_suggestions_result(&suggestions, &left, &right))
+    }
+    
+    async fn run_query(&self, sql: &str) -> NirvResult<QueryResult> {
+        let internal_query = self.query_parser.parse_as(sql, self.dialect)?;
+        let connector_queries = self.dispatcher.route_query(&internal_query).await?;
+        self.dispatcher.execute_distributed_query(connector_queries).await
+    }
+    
+    /// Validate a query against the registered sources and plan it without executing it
+    pub async fn dry_run(&self, sql: &str, params: &[QueryParameter]) -> DryRunReport {
+        let query = match self.bind_params(sql, params).and_then(|sql| self.query_parser.parse_as(&sql, self.dialect)) {
+            Ok(query) => query,
+            Err(e) => return DryRunReport::failed(&e),
+        };
+        dry_run_query(&query, &DefaultQueryPlanner::new(), &self.dispatcher, Default::default()).await
+    }
+    
+    /// Bind `:name` placeholders to the given parameters
+    fn bind_params(&self, sql: &str, params: &[QueryParameter]) -> NirvResult<String> {
+        let values: HashMap<String, crate::utils::types::Value> = params.iter()
+            .map(|param| (param.name.clone(), param.value.clone()))
+            .collect();
+        bind_named_parameters(sql, &values)
     }
     
     /// List available data sources
@@ -127,8 +285,8 @@ impl CliRunner {
         }
         
         // Get the connector and retrieve schema
-        if let Some(connector) = self.dispatcher.get_connector(object_type) {
-            let schema = connector.get_schema(identifier).await?;
+        if self.dispatcher.get_connector(object_type).is_some() {
+            let schema = self.dispatcher.describe_object(object_type, identifier).await?;
             
             let mut output = String::new();
             output.push_str(&format!("{} {}\n", "Schema for".bold(), source.cyan().bold()));
@@ -147,12 +305,24 @@ impl CliRunner {
                     nullable_str.green() 
                 };
                 
-                output.push_str(&format!("  {} {} {} {}\n", 
+                output.push_str(&format!("  {} {} {} {}", 
                     "•".green(),
                     col.name.cyan().bold(),
                     format!("{:?}", col.data_type).blue(),
                     nullable_colored
                 ));
+                if let Some(details) = schema.column_details(&col.name) {
+                    if let Some(native_type) = &details.native_type {
+                        output.push_str(&format!(" ({})", native_type));
+                    }
+                    if !details.tags.is_empty() {
+                        output.push_str(&format!(" [{}]", details.tags.join(", ").magenta()));
+                    }
+                    if let Some(description) = &details.description {
+                        output.push_str(&format!(" - {}", description));
+                    }
+                }
+                output.push('\n');
             }
             
             if !schema.indexes.is_empty() {
@@ -177,12 +347,21 @@ impl CliRunner {
     }
 }
 
+/// The `type.identifier` of a source given as `source('type.identifier')` or as is
+fn source_spec(source: &str) -> &str {
+    let source = source.trim();
+    source.strip_prefix("source(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .map(|quoted| quoted.trim().trim_matches(|c| c == '\'' || c == '"'))
+        .unwrap_or(source)
+}
+
 /// Main entry point for CLI execution
 pub async fn run_cli() -> anyhow::Result<()> {
     let args = CliArgs::parse();
     
     // Initialize CLI runner
-    let runner = match CliRunner::new().await {
+    let mut runner = match CliRunner::new().await {
         Ok(runner) => runner,
         Err(e) => {
             eprintln!("{}", OutputFormatter::format_error(&e));
@@ -190,10 +369,54 @@ pub async fn run_cli() -> anyhow::Result<()> {
         }
     };
     
+    // Apply the selected profile to the configuration file so a mistyped or missing
+    // profile stops the command before any source is queried
+    let profile = select_profile(args.profile.as_deref());
+    let mut history_config = HistoryConfig::default();
+    if let Commands::Query { config, verbose, dialect, .. } = &args.command {
+        runner = runner.with_dialect(*dialect);
+        let loaded = match (config, &profile) {
+            (Some(path), profile) => EngineConfig::load(path, profile.as_deref())
+                .and_then(|config| config.validate().into_result().map(|_| Some(config))),
+            (None, Some(_)) if args.profile.is_some() => Err(NirvError::Configuration(
+                "--profile requires a configuration file (--config)".to_string()
+            )),
+            (None, _) => Ok(None),
+        };
+        match loaded {
+            Ok(Some(config)) => {
+                if *verbose {
+                    let profile = profile.as_deref().unwrap_or("default");
+                    eprintln!("{}", OutputFormatter::format_info(&format!("Using profile '{}'", profile)));
+                }
+                runner.apply_source_policies(&config);
+                configure_parse_pool(config.runtime.parse_threads);
+                history_config = config.history;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{}", OutputFormatter::format_error(&e));
+                std::process::exit(1);
+            }
+        }
+    }
+    runner = runner.with_history(QueryHistory::from_config(&history_config));
+    
     // Execute the command
     let result = match args.command {
-        Commands::Query { sql, format, config: _, verbose } => {
-            match runner.execute_query(&sql, &format, verbose).await {
+        Commands::Query { sql, format: _, config: _, verbose: _, params, dry_run: true, stats: _, dialect: _ } => {
+            let report = runner.dry_run(&sql, &params).await;
+            if report.is_valid() {
+                println!("{}", report);
+                Ok(())
+            } else {
+                eprintln!("{}", report);
+                std::process::exit(1);
+            }
+        }
+        
+        Commands::Query { sql, format, config: _, verbose, params, dry_run: false, stats, dialect: _ } => {
+            match runner.execute_query_with_params(&sql, &params, &format, verbose, stats).await {
                 Ok(output) => {
                     println!("{}", output);
                     Ok(())
@@ -205,6 +428,24 @@ pub async fn run_cli() -> anyhow::Result<()> {
             }
         }
         
+        Commands::Diff { left, right, key } => {
+            // Exits with 1 when the results differ, as diff(1) does
+            match runner.diff_queries(&left, &right, &key).await {
+                Ok(diff) if diff.is_identical() => {
+                    println!("{}", diff);
+                    Ok(())
+                }
+                Ok(diff) => {
+                    println!("{}", diff);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("{}", OutputFormatter::format_error(&e));
+                    std::process::exit(1);
+                }
+            }
+        }
+        
         Commands::Sources { detailed } => {
             let output = runner.list_sources(detailed);
             println!("{}", output);
@@ -223,7 +464,89 @@ pub async fn run_cli() -> anyhow::Result<()> {
                 }
             }
         }
+        
+        Commands::Config { action: ConfigCommand::Validate { path } } => {
+            // Exits with 1 when the file has problems, listing all of them
+            let report = EngineConfig::load(&path, profile.as_deref()).map(|config| (config.validate(), config));
+            match report {
+                Ok((report, config)) if report.is_valid() => {
+                    println!("{}", OutputFormatter::format_success(&format!(
+                        "Configuration '{}' is valid ({} connectors, {} protocol adapters)",
+                        path, config.connectors.len(), config.protocol_adapters.len()
+                    )));
+                    Ok(())
+                }
+                Ok((report, _)) => {
+                    eprintln!("{}", OutputFormatter::format_error(&NirvError::Configuration(report.to_string())));
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("{}", OutputFormatter::format_error(&e));
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::Profile { source, format, buckets, samples } => {
+            match runner.profile_source(&source, ProfileOptions { buckets, samples }).await {
+                Ok(profile) => {
+                    match format {
+                        OutputFormat::Table => println!("{}", profile),
+                        OutputFormat::Json => println!("{}", profile.to_json()),
+                        OutputFormat::Csv => println!("{}", OutputFormatter::format_result(&profile.summary(), &format)),
+                    }
+                    Ok(())
+                }
+                Err(e) => {
+                    eprintln!("{}", OutputFormatter::format_error(&e));
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::Joins { left, right, format } => {
+            match runner.suggest_joins(&left, &right).await {
+                Ok(result) => {
+                    println!("{}", OutputFormatter::format_result(&result, &format));
+                    Ok(())
+                }
+                Err(e) => {
+                    eprintln!("{}", OutputFormatter::format_error(&e));
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::History { action } => {
+            let output = match action {
+                HistoryCommand::List { limit } => runner.history()
+                    .and_then(|history| history.list(limit))
+                    .map(|entries| format_history(&entries)),
+                HistoryCommand::Search { text } => runner.history()
+                    .and_then(|history| history.search(&text))
+                    .map(|entries| format_history(&entries)),
+                HistoryCommand::Rerun { id, format } => runner.rerun_query(id, &format).await,
+            };
+            match output {
+                Ok(output) => {
+                    println!("{}", output);
+                    Ok(())
+                }
+                Err(e) => {
+                    eprintln!("{}", OutputFormatter::format_error(&e));
+                    std::process::exit(1);
+                }
+            }
+        }
     };
     
     result
-}
\ No newline at end of file
+}
+
+/// One history entry per line, or a note when there are none
+fn format_history(entries: &[HistoryEntry]) -> String {
+    if entries.is_empty() {
+        return "No queries in history".to_string();
+    }
+    entries.iter().map(|entry| entry.to_string()).collect::<Vec<_>>().join("\n")
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::cli::OutputFormatter;
+use crate::engine::compare_values;
+use crate::utils::types::{ColumnMetadata, DataType, QueryResult, Row, Value};
+
+/// Width of the longest histogram bar in the table form
+const HISTOGRAM_BAR_WIDTH: u64 = 30;
+
+/// How many histogram buckets and sample values a profile keeps per column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileOptions {
+    pub buckets: usize,
+    pub samples: usize,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self { buckets: 10, samples: 5 }
+    }
+}
+
+/// A numeric range, or for non-numeric columns one of the most frequent values
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBucket {
+    pub label: String,
+    pub count: u64,
+}
+
+/// Data quality summary of one column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub data_type: DataType,
+    pub nulls: u64,
+    pub distinct: u64,
+    /// Smallest and largest non-null values, `None` when every value is NULL
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    pub histogram: Vec<HistogramBucket>,
+    /// The first distinct non-null values, in the order they were read
+    pub samples: Vec<Value>,
+}
+
+/// Profiles of every column of a source
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceProfile {
+    pub source: String,
+    pub rows: u64,
+    pub columns: Vec<ColumnProfile>,
+}
+
+/// Builds a source profile one row at a time, keeping a count per distinct value of each
+/// column rather than the rows themselves
+pub struct Profiler {
+    columns: Vec<ColumnProfiler>,
+    rows: u64,
+    options: ProfileOptions,
+}
+
+struct ColumnProfiler {
+    name: String,
+    data_type: DataType,
+    nulls: u64,
+    min: Option<Value>,
+    max: Option<Value>,
+    /// Occurrences of each distinct value, keyed by its debug form
+    counts: HashMap<String, (Value, u64)>,
+    samples: Vec<Value>,
+}
+
+impl Profiler {
+    pub fn new(columns: &[ColumnMetadata], options: ProfileOptions) -> Self {
+        let columns = columns.iter()
+            .map(|col| ColumnProfiler {
+                name: col.name.clone(),
+                data_type: col.data_type.clone(),
+                nulls: 0,
+                min: None,
+                max: None,
+                counts: HashMap::new(),
+                samples: Vec::new(),
+            })
+            .collect();
+        Self { columns, rows: 0, options }
+    }
+
+    /// Account for one row; missing trailing values count as NULL
+    pub fn observe(&mut self, row: &Row) {
+        self.rows += 1;
+        for (i, column) in self.columns.iter_mut().enumerate() {
+            column.observe(row.get(i).unwrap_or(&Value::Null), self.options.samples);
+        }
+    }
+
+    pub fn finish(self, source: &str) -> SourceProfile {
+        let options = self.options;
+        SourceProfile {
+            source: source.to_string(),
+            rows: self.rows,
+            columns: self.columns.into_iter().map(|column| column.finish(options.buckets)).collect(),
+        }
+    }
+}
+
+impl ColumnProfiler {
+    fn observe(&mut self, value: &Value, sample_limit: usize) {
+        if matches!(value, Value::Null) {
+            self.nulls += 1;
+            return;
+        }
+        if self.min.as_ref().is_none_or(|min| compare_values(value, min).is_lt()) {
+            self.min = Some(value.clone());
+        }
+        if self.max.as_ref().is_none_or(|max| compare_values(value, max).is_gt()) {
+            self.max = Some(value.clone());
+        }
+
+        let entry = self.counts.entry(format!("{:?}", value)).or_insert_with(|| (value.clone(), 0));
+        entry.1 += 1;
+        if entry.1 == 1 && self.samples.len() < sample_limit {
+            self.samples.push(value.clone());
+        }
+    }
+
+    fn finish(self, buckets: usize) -> ColumnProfile {
+        let histogram = numeric_histogram(&self.counts, buckets)
+            .unwrap_or_else(|| frequency_histogram(&self.counts, buckets));
+        ColumnProfile {
+            name: self.name,
+            data_type: self.data_type,
+            nulls: self.nulls,
+            distinct: self.counts.len() as u64,
+            min: self.min,
+            max: self.max,
+            histogram,
+            samples: self.samples,
+        }
+    }
+}
+
+/// Equal-width ranges between the smallest and largest value, when every value is a number.
+/// Ranges include their lower bound; the last one also includes the largest value.
+fn numeric_histogram(counts: &HashMap<String, (Value, u64)>, buckets: usize) -> Option<Vec<HistogramBucket>> {
+    let numbers = counts.values()
+        .map(|(value, count)| match value {
+            Value::Integer(i) => Some((*i as f64, *count)),
+            Value::Float(f) if f.is_finite() => Some((*f, *count)),
+            _ => None,
+        })
+        .collect::<Option<Vec<(f64, u64)>>>()?;
+    let low = numbers.iter().map(|(n, _)| *n).reduce(f64::min)?;
+    let high = numbers.iter().map(|(n, _)| *n).reduce(f64::max)?;
+    if buckets == 0 {
+        return Some(Vec::new());
+    }
+    if low == high {
+        let count = numbers.iter().map(|(_, count)| count).sum();
+        return Some(vec![HistogramBucket { label: format_bound(low), count }]);
+    }
+
+    let width = (high - low) / buckets as f64;
+    let mut histogram: Vec<HistogramBucket> = (0..buckets)
+        .map(|i| {
+            let start = low + width * i as f64;
+            let (end, close) = if i + 1 == buckets { (high, ']') } else { (start + width, ')') };
+            HistogramBucket { label: format!("[{}, {}{}", format_bound(start), format_bound(end), close), count: 0 }
+        })
+        .collect();
+    for (number, count) in numbers {
+        let index = (((number - low) / width) as usize).min(buckets - 1);
+        histogram[index].count += count;
+    }
+    Some(histogram)
+}
+
+/// The most frequent values, ties in label order, with the rest counted as `(other)`
+fn frequency_histogram(counts: &HashMap<String, (Value, u64)>, buckets: usize) -> Vec<HistogramBucket> {
+    let mut values: Vec<HistogramBucket> = counts.values()
+        .map(|(value, count)| HistogramBucket { label: OutputFormatter::value_to_string(value), count: *count })
+        .collect();
+    values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.label.cmp(&b.label)));
+    let other: u64 = values.iter().skip(buckets).map(|bucket| bucket.count).sum();
+    values.truncate(buckets);
+    if other > 0 {
+        values.push(HistogramBucket { label: "(other)".to_string(), count: other });
+    }
+    values
+}
+
+/// Range bounds rounded to four decimals, whole numbers without a fraction
+fn format_bound(bound: f64) -> String {
+    ((bound * 10_000.0).round() / 10_000.0).to_string()
+}
+
+/// Profile every row of a query result
+pub fn profile_result(source: &str, result: &QueryResult, options: ProfileOptions) -> SourceProfile {
+    let mut profiler = Profiler::new(&result.columns, options);
+    for row in &result.rows {
+        profiler.observe(row);
+    }
+    profiler.finish(source)
+}
+
+impl SourceProfile {
+    /// One row per column: its type, null and distinct counts, range and samples
+    pub fn summary(&self) -> QueryResult {
+        let text = |name: &str| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true };
+        let integer = |name: &str| ColumnMetadata { name: name.to_string(), data_type: DataType::Integer, nullable: false };
+        QueryResult {
+            columns: vec![
+                text("column"), text("type"), integer("nulls"), integer("distinct"),
+                text("min"), text("max"), text("samples"),
+            ],
+            rows: self.columns.iter()
+                .map(|column| Row::new(vec![
+                    Value::Text(column.name.clone()),
+                    Value::Text(format!("{:?}", column.data_type)),
+                    Value::Integer(column.nulls as i64),
+                    Value::Integer(column.distinct as i64),
+                    column.min.clone().unwrap_or(Value::Null),
+                    column.max.clone().unwrap_or(Value::Null),
+                    Value::Text(column.sample_text()),
+                ]))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let optional = |value: &Option<Value>| value.as_ref().map_or(JsonValue::Null, OutputFormatter::value_to_json);
+        let output = json!({
+            "source": self.source,
+            "rows": self.rows,
+            "columns": self.columns.iter().map(|column| json!({
+                "name": column.name,
+                "type": format!("{:?}", column.data_type),
+                "nulls": column.nulls,
+                "distinct": column.distinct,
+                "min": optional(&column.min),
+                "max": optional(&column.max),
+                "histogram": column.histogram.iter()
+                    .map(|bucket| json!({ "label": bucket.label, "count": bucket.count }))
+                    .collect::<Vec<_>>(),
+                "samples": column.samples.iter().map(OutputFormatter::value_to_json).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        });
+        serde_json::to_string_pretty(&output).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+impl ColumnProfile {
+    fn sample_text(&self) -> String {
+        self.samples.iter().map(OutputFormatter::value_to_string).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// The summary as an aligned table followed by each column's histogram
+impl fmt::Display for SourceProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Profile of {}: {} {}", self.source, self.rows, if self.rows == 1 { "row" } else { "rows" })?;
+
+        let summary = self.summary();
+        let cells: Vec<Vec<String>> = std::iter::once(summary.columns.iter().map(|col| col.name.clone()).collect())
+            .chain(summary.rows.iter().map(|row| row.values.iter().map(OutputFormatter::value_to_string).collect()))
+            .collect();
+        let widths: Vec<usize> = (0..summary.columns.len())
+            .map(|i| cells.iter().map(|row| row[i].chars().count()).max().unwrap_or(0))
+            .collect();
+        let line = |row: &[String]| row.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(f, "\n")?;
+        writeln!(f, "{}", line(&cells[0]).trim_end())?;
+        writeln!(f, "{}", widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().join("  "))?;
+        for row in &cells[1..] {
+            writeln!(f, "{}", line(row).trim_end())?;
+        }
+
+        for column in self.columns.iter().filter(|column| !column.histogram.is_empty()) {
+            write!(f, "\nHistogram of {}", column.name)?;
+            let label_width = column.histogram.iter().map(|bucket| bucket.label.chars().count()).max().unwrap_or(0);
+            let largest = column.histogram.iter().map(|bucket| bucket.count).max().unwrap_or(0).max(1);
+            let count_width = largest.to_string().len();
+            for bucket in &column.histogram {
+                let bar = "#".repeat((bucket.count * HISTOGRAM_BAR_WIDTH).div_ceil(largest) as usize);
+                write!(f, "\n  {:<label_width$}  {:>count_width$}  {}", bucket.label, bucket.count, bar)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: DataType) -> ColumnMetadata {
+        ColumnMetadata { name: name.to_string(), data_type, nullable: true }
+    }
+
+    #[test]
+    fn test_profile_counts_ranges_and_histograms() {
+        let text = |s: &str| Value::Text(s.to_string());
+        let result = QueryResult {
+            columns: vec![column("age", DataType::Integer), column("city", DataType::Text)],
+            rows: vec![
+                Row::new(vec![Value::Integer(20), text("Oslo")]),
+                Row::new(vec![Value::Integer(30), text("Rome")]),
+                Row::new(vec![Value::Null, text("Oslo")]),
+                Row::new(vec![Value::Integer(40), Value::Null]),
+                Row::new(vec![Value::Integer(20)]),
+            ],
+            ..Default::default()
+        };
+
+        let profile = profile_result("mock.people", &result, ProfileOptions { buckets: 2, samples: 1 });
+        assert_eq!(profile.rows, 5);
+        let age = &profile.columns[0];
+        assert_eq!((age.nulls, age.distinct), (1, 3));
+        assert_eq!((age.min.clone(), age.max.clone()), (Some(Value::Integer(20)), Some(Value::Integer(40))));
+        assert_eq!(age.histogram, vec![
+            HistogramBucket { label: "[20, 30)".to_string(), count: 2 },
+            HistogramBucket { label: "[30, 40]".to_string(), count: 2 },
+        ]);
+        assert_eq!(age.samples, vec![Value::Integer(20)]);
+
+        let city = &profile.columns[1];
+        assert_eq!((city.nulls, city.distinct), (2, 2));
+        assert_eq!(city.max, Some(text("Rome")));
+        assert_eq!(city.histogram[0], HistogramBucket { label: "Oslo".to_string(), count: 2 });
+
+        let table = profile.to_string();
+        assert!(table.contains("Profile of mock.people: 5 rows"), "{}", table);
+        assert!(table.contains("Histogram of city"), "{}", table);
+        let json: serde_json::Value = serde_json::from_str(&profile.to_json()).unwrap();
+        assert_eq!(json["columns"][0]["histogram"][1]["count"], 2);
+        assert_eq!(json["columns"][1]["min"], "Oslo");
+
+        let narrow = profile_result("mock.people", &result, ProfileOptions { buckets: 1, samples: 5 });
+        assert_eq!(narrow.columns[1].histogram[1], HistogramBucket { label: "(other)".to_string(), count: 1 });
+    }
+}
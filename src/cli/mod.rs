@@ -1,8 +1,14 @@
 // CLI interface components
 pub mod cli_args;
 pub mod cli_runner;
+pub mod column_profile;
 pub mod output_formatter;
+pub mod query_history;
+pub mod result_diff;
 
 pub use cli_args::*;
 pub use cli_runner::*;
-pub use output_formatter::*;
\ No newline at end of file
+pub use column_profile::*;
+pub use output_formatter::*;
+pub use query_history::*;
+pub use result_diff::*;
\ No newline at end of file
@@ -1,7 +1,7 @@
 use colored::*;
 use serde_json::{json, Value as JsonValue};
 use base64::prelude::*;
-use crate::utils::types::{QueryResult, Value};
+use crate::utils::types::{ExecutionStats, QueryResult, QueryWarning, Value};
 use crate::cli::cli_args::OutputFormat;
 
 /// Formats query results for CLI output
@@ -110,7 +110,7 @@ impl OutputFormatter {
     }
     
     /// Format results as JSON
-    fn format_json(result: &QueryResult) -> String {
+    pub(crate) fn format_json(result: &QueryResult) -> String {
         let mut rows = Vec::new();
         
         for row in &result.rows {
@@ -126,7 +126,7 @@ impl OutputFormatter {
             rows.push(JsonValue::Object(row_obj));
         }
         
-        let output = json!({
+        let mut output = json!({
             "data": rows,
             "metadata": {
                 "columns": result.columns.iter().map(|col| {
@@ -140,6 +140,13 @@ impl OutputFormatter {
                 "execution_time_ms": result.execution_time.as_millis()
             }
         });
+        if !result.warnings.is_empty() {
+            output["metadata"]["warnings"] = result.warnings.iter().map(|warning| json!({
+                "kind": warning.kind.as_str(),
+                "source": warning.source,
+                "message": warning.message
+            })).collect();
+        }
         
         serde_json::to_string_pretty(&output).unwrap_or_else(|_| "{}".to_string())
     }
@@ -168,7 +175,7 @@ impl OutputFormatter {
     }
     
     /// Convert a Value to a display string
-    fn value_to_string(value: &Value) -> String {
+    pub(crate) fn value_to_string(value: &Value) -> String {
         match value {
             Value::Text(s) => s.clone(),
             Value::Integer(i) => i.to_string(),
@@ -199,7 +206,7 @@ impl OutputFormatter {
     }
     
     /// Convert a Value to JSON
-    fn value_to_json(value: &Value) -> JsonValue {
+    pub(crate) fn value_to_json(value: &Value) -> JsonValue {
         match value {
             Value::Text(s) => JsonValue::String(s.clone()),
             Value::Integer(i) => JsonValue::Number((*i).into()),
@@ -240,6 +247,16 @@ impl OutputFormatter {
         format!("{} {}", "Success:".green().bold(), message)
     }
     
+    /// Format execution statistics for CLI display
+    pub fn format_stats(stats: &ExecutionStats) -> String {
+        format!("{} {}", "Stats:".blue().bold(), stats)
+    }
+    
+    /// Format a warning about a partial result for CLI display
+    pub fn format_warning(warning: &QueryWarning) -> String {
+        format!("{} {}", "Warning:".yellow().bold(), warning)
+    }
+    
     /// Format info message for CLI display
     pub fn format_info(message: &str) -> String {
         format!("{} {}", "Info:".blue().bold(), message)
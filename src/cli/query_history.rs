@@ -0,0 +1,314 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    config::HistoryConfig,
+    error::{NirvError, NirvResult},
+};
+
+/// Environment variable naming the history file when the configuration does not
+pub const HISTORY_ENV_VAR: &str = "NIRV_HISTORY";
+
+/// Outcome of a recorded query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryStatus {
+    Success,
+    Failed,
+}
+
+/// A query run from the CLI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub sql: String,
+    pub executed_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub status: HistoryStatus,
+    /// Rows returned by a successful query
+    pub row_count: Option<u64>,
+    /// Error of a failed query
+    pub error: Option<String>,
+}
+
+/// Executed queries, stored one JSON object per line so recording a query only appends.
+/// Processes sharing the file take turns through a lock file, and ids come from a counter
+/// file beside it, so each id names one query.
+#[derive(Debug, Clone)]
+pub struct QueryHistory {
+    path: PathBuf,
+    max_entries: Option<usize>,
+    max_age: Option<chrono::Duration>,
+}
+
+impl QueryHistory {
+    /// A history without retention limits
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_entries: None,
+            max_age: None,
+        }
+    }
+
+    /// The configured history, or `None` when it is disabled or no location is known
+    pub fn from_config(config: &HistoryConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let path = config.path.clone()
+            .or_else(|| std::env::var(HISTORY_ENV_VAR).ok().filter(|path| !path.is_empty()))
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".nirv").join("history.jsonl")))?;
+
+        let mut history = Self::new(path);
+        if let Some(max_entries) = config.max_entries {
+            history = history.with_max_entries(max_entries);
+        }
+        if let Some(days) = config.max_age_days {
+            history = history.with_max_age(chrono::Duration::days(days as i64));
+        }
+        Some(history)
+    }
+
+    /// Keep only the most recent entries
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Remove entries older than the given age
+    pub fn with_max_age(mut self, max_age: chrono::Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a query and apply retention, returning the new entry. The history is read
+    /// only when retention is configured.
+    pub fn record(&self, sql: &str, duration: Duration, outcome: Result<u64, &str>) -> NirvResult<HistoryEntry> {
+        self.create_parent()?;
+        let _lock = self.lock()?;
+        let (status, row_count, error) = match outcome {
+            Ok(rows) => (HistoryStatus::Success, Some(rows), None),
+            Err(error) => (HistoryStatus::Failed, None, Some(error.to_string())),
+        };
+        let entry = HistoryEntry {
+            id: self.next_id()?,
+            sql: sql.to_string(),
+            executed_at: Utc::now(),
+            duration_ms: duration.as_millis() as u64,
+            status,
+            row_count,
+            error,
+        };
+
+        if self.max_entries.is_none() && self.max_age.is_none() {
+            self.append(&entry)?;
+            return Ok(entry);
+        }
+        let entries = self.entries()?;
+        let retained = self.retain(&entries, entry.executed_at);
+        if retained.len() == entries.len() {
+            self.append(&entry)?;
+        } else {
+            let mut kept: Vec<HistoryEntry> = retained.into_iter().cloned().collect();
+            kept.push(entry.clone());
+            self.rewrite(&kept)?;
+        }
+        Ok(entry)
+    }
+
+    /// All entries, oldest first. A missing file is an empty history.
+    pub fn entries(&self) -> NirvResult<Vec<HistoryEntry>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.error("read", e)),
+        };
+        // A line cut short by an interrupted write is skipped rather than losing the history
+        Ok(contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// The most recent entries, newest first
+    pub fn list(&self, limit: usize) -> NirvResult<Vec<HistoryEntry>> {
+        Ok(self.entries()?.into_iter().rev().take(limit).collect())
+    }
+
+    /// Entries whose SQL contains the text, ignoring case, newest first
+    pub fn search(&self, text: &str) -> NirvResult<Vec<HistoryEntry>> {
+        let text = text.to_lowercase();
+        Ok(self.entries()?.into_iter()
+            .rev()
+            .filter(|entry| entry.sql.to_lowercase().contains(&text))
+            .collect())
+    }
+
+    pub fn get(&self, id: u64) -> NirvResult<HistoryEntry> {
+        self.entries()?.into_iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| NirvError::Internal(format!("No query with id {} in history", id)))
+    }
+
+    /// Entries kept once a new entry is added at `now`
+    fn retain<'a>(&self, entries: &'a [HistoryEntry], now: DateTime<Utc>) -> Vec<&'a HistoryEntry> {
+        let mut kept: Vec<&HistoryEntry> = entries.iter()
+            .filter(|entry| self.max_age.is_none_or(|max_age| now - entry.executed_at <= max_age))
+            .collect();
+        if let Some(max_entries) = self.max_entries {
+            let excess = (kept.len() + 1).saturating_sub(max_entries);
+            kept.drain(..excess.min(kept.len()));
+        }
+        kept
+    }
+
+    /// Hold the history's lock file until the returned handle is dropped
+    fn lock(&self) -> NirvResult<File> {
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_extension("jsonl.lock"))
+            .and_then(|file| file.lock().map(|_| file))
+            .map_err(|e| self.error("lock", e))
+    }
+
+    /// Take the id after the last one handed out, continuing from the history itself when
+    /// the counter file is missing
+    fn next_id(&self) -> NirvResult<u64> {
+        let counter = self.path.with_extension("jsonl.seq");
+        let last = match fs::read_to_string(&counter) {
+            Ok(text) => text.trim().parse::<u64>().ok(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(self.error("read the id counter of", e)),
+        };
+        let last = match last {
+            Some(last) => last,
+            None => self.entries()?.iter().map(|entry| entry.id).max().unwrap_or(0),
+        };
+        fs::write(&counter, (last + 1).to_string()).map_err(|e| self.error("write the id counter of", e))?;
+        Ok(last + 1)
+    }
+
+    fn append(&self, entry: &HistoryEntry) -> NirvResult<()> {
+        let mut line = serde_json::to_string(entry).map_err(|e| NirvError::Internal(e.to_string()))?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| self.error("write", e))
+    }
+
+    /// Replace the file through a temporary one, so readers never see a partial history
+    fn rewrite(&self, entries: &[HistoryEntry]) -> NirvResult<()> {
+        let mut contents = String::new();
+        for entry in entries {
+            contents.push_str(&serde_json::to_string(entry).map_err(|e| NirvError::Internal(e.to_string()))?);
+            contents.push('\n');
+        }
+        let temp = self.path.with_extension("jsonl.tmp");
+        fs::write(&temp, contents)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| self.error("write", e))
+    }
+
+    fn create_parent(&self) -> NirvResult<()> {
+        match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                fs::create_dir_all(parent).map_err(|e| self.error("create the directory of", e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn error(&self, action: &str, error: std::io::Error) -> NirvError {
+        NirvError::Internal(format!("Failed to {} query history '{}': {}", action, self.path.display(), error))
+    }
+}
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>4}  {}  {:>6} ms  ", self.id, self.executed_at.format("%Y-%m-%d %H:%M:%S"), self.duration_ms)?;
+        match (&self.status, self.row_count) {
+            (HistoryStatus::Success, Some(rows)) => write!(f, "{} rows", rows)?,
+            (HistoryStatus::Success, None) => write!(f, "ok")?,
+            (HistoryStatus::Failed, _) => write!(f, "failed")?,
+        }
+        write!(f, "  {}", self.sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_records_searches_and_retains() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = QueryHistory::new(dir.path().join("nested").join("history.jsonl")).with_max_entries(3);
+
+        history.record("SELECT * FROM source('mock.users')", Duration::from_millis(12), Ok(3)).unwrap();
+        history.record("SELECT nope", Duration::from_millis(1), Err("parse error")).unwrap();
+        for table in ["orders", "products"] {
+            history.record(&format!("SELECT id FROM source('mock.{}')", table), Duration::ZERO, Ok(1)).unwrap();
+        }
+
+        // The oldest entry was dropped, and ids keep increasing
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(entries[0].status, HistoryStatus::Failed);
+        assert_eq!(entries[0].error.as_deref(), Some("parse error"));
+
+        assert_eq!(history.list(1).unwrap()[0].id, 4);
+        let found = history.search("MOCK.ORDERS").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(history.get(found[0].id).unwrap().row_count, Some(1));
+        assert!(history.get(1).is_err());
+        assert!(found[0].to_string().contains("1 rows  SELECT id FROM source('mock.orders')"));
+    }
+
+    #[test]
+    fn test_concurrent_recorders_get_distinct_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        let recorders: Vec<_> = (0..4).map(|recorder| {
+            let history = QueryHistory::new(&path);
+            std::thread::spawn(move || {
+                (0..25).map(|query| history.record(&format!("SELECT {}, {}", recorder, query), Duration::ZERO, Ok(1)).unwrap().id).collect::<Vec<_>>()
+            })
+        }).collect();
+        let mut ids: Vec<u64> = recorders.into_iter().flat_map(|recorder| recorder.join().unwrap()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (1..=100).collect::<Vec<_>>());
+        assert_eq!(QueryHistory::new(&path).entries().unwrap().len(), 100);
+    }
+
+    #[test]
+    fn test_history_drops_expired_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = QueryHistory::new(dir.path().join("history.jsonl"));
+        let mut old = history.record("SELECT 1", Duration::ZERO, Ok(1)).unwrap();
+        old.executed_at -= chrono::Duration::days(10);
+        history.rewrite(&[old]).unwrap();
+
+        let history = history.with_max_age(chrono::Duration::days(7));
+        history.record("SELECT 2", Duration::ZERO, Ok(1)).unwrap();
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.sql.as_str()).collect::<Vec<_>>(), vec!["SELECT 2"]);
+        assert_eq!(entries[0].id, 2);
+    }
+}
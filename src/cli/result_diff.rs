@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::utils::{
+    error::{NirvError, NirvResult},
+    hash_function::canonical_text,
+    identifier::IdentifierCase,
+    types::{QueryResult, Row, Value},
+};
+
+/// A column whose value differs between the two sides of a diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnChange {
+    pub column: String,
+    pub left: Value,
+    pub right: Value,
+}
+
+/// A row present on both sides with different values
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedRow {
+    pub key: String,
+    pub changes: Vec<ColumnChange>,
+}
+
+/// Differences between two query results whose rows are aligned by a key column
+#[derive(Debug, Clone, Default)]
+pub struct ResultDiff {
+    pub key_column: String,
+    /// Rows only in the left result, with their keys
+    pub missing: Vec<(String, Row)>,
+    /// Rows only in the right result, with their keys
+    pub extra: Vec<(String, Row)>,
+    pub changed: Vec<ChangedRow>,
+    /// Rows present on both sides with equal values
+    pub matching: usize,
+    /// Columns present on only one side, which are not compared
+    pub left_only_columns: Vec<String>,
+    pub right_only_columns: Vec<String>,
+}
+
+impl ResultDiff {
+    pub fn is_identical(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Align the rows of two results by a key column and compare the columns they share.
+/// Values compare by content, so `1`, `1.0` and `'1'` from different sources are equal.
+pub fn diff_results(left: &QueryResult, right: &QueryResult, key_column: &str) -> NirvResult<ResultDiff> {
+    let case = IdentifierCase::default();
+    let column_names = |result: &QueryResult| -> Vec<String> {
+        result.columns.iter().map(|col| col.name.clone()).collect()
+    };
+    let (left_columns, right_columns) = (column_names(left), column_names(right));
+
+    let key_index = |columns: &[String], side: &str| {
+        case.resolve(key_column, columns.iter().map(String::as_str)).ok_or_else(|| NirvError::Internal(
+            format!("Key column '{}' is not in the {} result", key_column, side)
+        ))
+    };
+    let left_key = key_index(&left_columns, "left")?;
+    let right_key = key_index(&right_columns, "right")?;
+
+    // Shared columns by position on each side, in left column order
+    let mut shared = Vec::new();
+    let mut diff = ResultDiff { key_column: left_columns[left_key].clone(), ..Default::default() };
+    for (i, name) in left_columns.iter().enumerate() {
+        match right_columns.iter().position(|other| case.matches(name, other)) {
+            Some(j) => shared.push((name.clone(), i, j)),
+            None => diff.left_only_columns.push(name.clone()),
+        }
+    }
+    diff.right_only_columns = right_columns.iter()
+        .filter(|name| !left_columns.iter().any(|other| case.matches(other, name)))
+        .cloned()
+        .collect();
+
+    let right_rows = index_rows(right, right_key, "right")?;
+    let mut seen = HashSet::new();
+    for row in &left.rows {
+        let key = canonical_value(row.get(left_key).unwrap_or(&Value::Null));
+        if !seen.insert(key.clone()) {
+            return Err(NirvError::Internal(format!("Key '{}' is not unique in the left result", key)));
+        }
+        let Some(other) = right_rows.get(&key) else {
+            diff.missing.push((key, row.clone()));
+            continue;
+        };
+
+        let changes: Vec<ColumnChange> = shared.iter()
+            .filter_map(|(name, i, j)| {
+                let left_value = row.get(*i).cloned().unwrap_or(Value::Null);
+                let right_value = other.get(*j).cloned().unwrap_or(Value::Null);
+                (!values_equal(&left_value, &right_value)).then(|| ColumnChange {
+                    column: name.clone(),
+                    left: left_value,
+                    right: right_value,
+                })
+            })
+            .collect();
+        if changes.is_empty() {
+            diff.matching += 1;
+        } else {
+            diff.changed.push(ChangedRow { key, changes });
+        }
+    }
+
+    // Right rows keep their original order
+    for row in &right.rows {
+        let key = canonical_value(row.get(right_key).unwrap_or(&Value::Null));
+        if !seen.contains(&key) {
+            diff.extra.push((key, row.clone()));
+        }
+    }
+
+    Ok(diff)
+}
+
+fn index_rows<'a>(result: &'a QueryResult, key_index: usize, side: &str) -> NirvResult<HashMap<String, &'a Row>> {
+    let mut rows = HashMap::new();
+    for row in &result.rows {
+        let key = canonical_value(row.get(key_index).unwrap_or(&Value::Null));
+        if rows.insert(key.clone(), row).is_some() {
+            return Err(NirvError::Internal(format!("Key '{}' is not unique in the {} result", key, side)));
+        }
+    }
+    Ok(rows)
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    left == right || canonical_value(left) == canonical_value(right)
+}
+
+/// Text form used to compare values across sources; whole floats print as integers
+fn canonical_value(value: &Value) -> String {
+    canonical_text(value).unwrap_or_else(|| "NULL".to_string())
+}
+
+impl fmt::Display for ResultDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Compared on '{}': {} matching, {} missing, {} extra, {} changed",
+            self.key_column, self.matching, self.missing.len(), self.extra.len(), self.changed.len())?;
+        if !self.left_only_columns.is_empty() {
+            write!(f, "\nColumns only in left: {}", self.left_only_columns.join(", "))?;
+        }
+        if !self.right_only_columns.is_empty() {
+            write!(f, "\nColumns only in right: {}", self.right_only_columns.join(", "))?;
+        }
+        for (key, _) in &self.missing {
+            write!(f, "\n- {}={} missing from right", self.key_column, key)?;
+        }
+        for (key, _) in &self.extra {
+            write!(f, "\n+ {}={} only in right", self.key_column, key)?;
+        }
+        for row in &self.changed {
+            let changes: Vec<String> = row.changes.iter()
+                .map(|change| format!("{}: {} -> {}", change.column, canonical_value(&change.left), canonical_value(&change.right)))
+                .collect();
+            write!(f, "\n~ {}={} {}", self.key_column, row.key, changes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{ColumnMetadata, DataType};
+
+    fn result(columns: &[&str], rows: Vec<Vec<Value>>) -> QueryResult {
+        QueryResult {
+            columns: columns.iter()
+                .map(|name| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true })
+                .collect(),
+            rows: rows.into_iter().map(Row::new).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_missing_extra_and_changed_rows() {
+        let text = |s: &str| Value::Text(s.to_string());
+        let left = result(&["id", "name", "score"], vec![
+            vec![Value::Integer(1), text("Ann"), Value::Integer(10)],
+            vec![Value::Integer(2), text("Bo"), Value::Integer(20)],
+            vec![Value::Integer(3), text("Cy"), Value::Integer(30)],
+        ]);
+        let right = result(&["ID", "name", "score", "migrated"], vec![
+            vec![text("4"), text("Di"), Value::Float(40.0), Value::Boolean(true)],
+            vec![text("2"), text("Bob"), Value::Float(20.0), Value::Boolean(true)],
+            vec![text("1"), text("Ann"), Value::Float(10.0), Value::Boolean(true)],
+        ]);
+
+        let diff = diff_results(&left, &right, "id").unwrap();
+        assert!(!diff.is_identical());
+        assert_eq!(diff.matching, 1);
+        assert_eq!(diff.missing.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["3"]);
+        assert_eq!(diff.extra.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["4"]);
+        assert_eq!(diff.changed, vec![ChangedRow {
+            key: "2".to_string(),
+            changes: vec![ColumnChange { column: "name".to_string(), left: text("Bo"), right: text("Bob") }],
+        }]);
+        assert_eq!(diff.right_only_columns, vec!["migrated".to_string()]);
+        assert!(diff.to_string().contains("~ id=2 name: Bo -> Bob"));
+
+        assert!(diff_results(&left, &right, "uuid").is_err());
+        let duplicated = result(&["id"], vec![vec![Value::Integer(1)], vec![Value::Integer(1)]]);
+        assert!(diff_results(&duplicated, &left, "id").is_err());
+    }
+}
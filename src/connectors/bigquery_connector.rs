@@ -0,0 +1,481 @@
+use async_trait::async_trait;
+use base64::prelude::*;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::debug_log::{connector_debug_log, tag_sql};
+use crate::connectors::network::with_timeout;
+use crate::connectors::warehouse::{epoch_micros_to_datetime, send_json, RsaSigningKey, TokenCache};
+use crate::utils::{
+    types::{
+        ConnectorType, ConnectorQuery, InternalQuery, QueryResult, Schema, ColumnMetadata, DataType,
+        Row, Value, PredicateValue, Column, COUNT_ALL
+    },
+    error::{ConnectorError, NirvResult},
+    connector_settings::{BigQuerySettings, issues_error},
+    sql_dialect::{SqlDialect, SqlStatement},
+};
+
+/// OAuth scope of the BigQuery API
+const BIGQUERY_SCOPE: &str = "https://www.googleapis.com/auth/bigquery";
+
+/// How long a request waits for its query job before returning to be polled again
+const QUERY_WAIT_MS: u64 = 10_000;
+
+/// The parts of a service account's JSON key the connector signs in with
+#[derive(Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+impl fmt::Debug for ServiceAccountKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceAccountKey")
+            .field("client_email", &self.client_email)
+            .field("token_uri", &self.token_uri)
+            .finish()
+    }
+}
+
+/// BigQuery connector over the REST API, signed in as a service account. A query runs
+/// whole as one GoogleSQL statement, with its values as positional parameters.
+pub struct BigQueryConnector {
+    client: Option<Client>,
+    settings: Option<BigQuerySettings>,
+    service_account: Option<(ServiceAccountKey, RsaSigningKey)>,
+    token: TokenCache,
+    read_timeout: Duration,
+    connected: bool,
+}
+
+impl fmt::Debug for BigQueryConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BigQueryConnector")
+            .field("connected", &self.connected)
+            .finish()
+    }
+}
+
+impl BigQueryConnector {
+    /// Create a new BigQuery connector
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            settings: None,
+            service_account: None,
+            token: TokenCache::default(),
+            read_timeout: Duration::from_secs(30),
+            connected: false,
+        }
+    }
+
+    /// Build the GoogleSQL statement for a query, with predicate values as `?` parameters
+    pub fn build_sql_statement(&self, query: &InternalQuery) -> NirvResult<SqlStatement> {
+        SqlDialect::BigQuery.select_statement(query)
+    }
+
+    /// Convert a BigQuery column type to internal DataType
+    pub fn bigquery_type_to_data_type(&self, bigquery_type: &str) -> DataType {
+        match bigquery_type.to_uppercase().as_str() {
+            "INTEGER" | "INT64" => DataType::Integer,
+            "FLOAT" | "FLOAT64" | "NUMERIC" | "BIGNUMERIC" => DataType::Float,
+            "BOOLEAN" | "BOOL" => DataType::Boolean,
+            "DATE" => DataType::Date,
+            "DATETIME" | "TIMESTAMP" => DataType::DateTime,
+            "BYTES" => DataType::Binary,
+            "JSON" | "RECORD" | "STRUCT" => DataType::Json,
+            _ => DataType::Text,
+        }
+    }
+
+    /// A positional query parameter in the form the API takes
+    fn query_parameter(value: &PredicateValue) -> JsonValue {
+        let (parameter_type, value) = match value {
+            PredicateValue::String(text) => ("STRING", text.clone()),
+            PredicateValue::Integer(number) => ("INT64", number.to_string()),
+            PredicateValue::Number(number) => ("FLOAT64", number.to_string()),
+            PredicateValue::Boolean(flag) => ("BOOL", flag.to_string()),
+            // Only scalar values are bound; NULL is written into the statement
+            PredicateValue::Null | PredicateValue::List(_) => ("STRING", String::new()),
+        };
+        json!({ "parameterType": { "type": parameter_type }, "parameterValue": { "value": value } })
+    }
+
+    fn client(&self) -> NirvResult<(&Client, &BigQuerySettings)> {
+        match (&self.client, &self.settings) {
+            (Some(client), Some(settings)) if self.connected => Ok((client, settings)),
+            _ => Err(ConnectorError::ConnectionFailed("Not connected to BigQuery".to_string()).into()),
+        }
+    }
+
+    /// URL of a resource under the connector's project
+    fn project_url(settings: &BigQuerySettings, project: &str, path: &str) -> String {
+        format!("{}/bigquery/v2/projects/{}{}", settings.api_url.trim_end_matches('/'), project, path)
+    }
+
+    /// An access token for the service account, exchanged for a signed assertion when the
+    /// one held is about to expire
+    async fn access_token(&self) -> NirvResult<String> {
+        let client = self.client.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected to BigQuery".to_string()))?;
+        let (account, key) = self.service_account.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No BigQuery credentials".to_string()))?;
+        self.token.get(|| async {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let assertion = key.sign_jwt(&json!({
+                "iss": account.client_email,
+                "scope": BIGQUERY_SCOPE,
+                "aud": account.token_uri,
+                "iat": now,
+                "exp": now + 3600,
+            }))?;
+            let request = client.post(&account.token_uri)
+                .form(&[("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())]);
+            let (_, body) = send_json("bigquery", "token request", request).await?;
+            let token = body["access_token"].as_str()
+                .ok_or_else(|| ConnectorError::ConnectionFailed("BigQuery token response has no access_token".to_string()))?;
+            Ok((token.to_string(), Duration::from_secs(body["expires_in"].as_u64().unwrap_or(3600))))
+        }).await
+    }
+
+    /// Run a statement and read every page of its results, polling until its job completes
+    async fn run_statement(&self, statement: &SqlStatement) -> NirvResult<QueryResult> {
+        with_timeout(self.read_timeout, "BigQuery query", self.query_pages(statement)).await
+    }
+
+    async fn query_pages(&self, statement: &SqlStatement) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+        let (client, settings) = self.client()?;
+        let sql = tag_sql(&statement.sql());
+        let mut body = json!({
+            "query": sql,
+            "useLegacySql": false,
+            "timeoutMs": QUERY_WAIT_MS,
+            "formatOptions": { "useInt64Timestamp": true },
+        });
+        if !statement.params().is_empty() {
+            body["parameterMode"] = json!("POSITIONAL");
+            body["queryParameters"] = statement.params().iter().map(Self::query_parameter).collect();
+        }
+        if let Some(dataset) = &settings.dataset {
+            body["defaultDataset"] = json!({ "projectId": settings.project_id, "datasetId": dataset });
+        }
+        if let Some(location) = &settings.location {
+            body["location"] = json!(location);
+        }
+
+        let token = self.access_token().await?;
+        let request = client.post(Self::project_url(settings, &settings.project_id, "/queries")).bearer_auth(&token).json(&body);
+        let (_, mut response) = send_json("bigquery", &format!("query {} {:?}", sql, statement.params()), request).await?;
+        let job_id = response.pointer("/jobReference/jobId").and_then(JsonValue::as_str).unwrap_or_default().to_string();
+        let location = response.pointer("/jobReference/location").and_then(JsonValue::as_str).map(str::to_string);
+
+        let mut fields: Option<Vec<JsonValue>> = None;
+        let mut rows = Vec::new();
+        loop {
+            let mut page_token = None;
+            if response["jobComplete"].as_bool().unwrap_or(false) {
+                let fields = fields.get_or_insert_with(|| {
+                    response.pointer("/schema/fields").and_then(JsonValue::as_array).cloned().unwrap_or_default()
+                });
+                for row in response["rows"].as_array().into_iter().flatten() {
+                    let cells = row["f"].as_array().map(Vec::as_slice).unwrap_or_default();
+                    rows.push(Row::new(fields.iter().zip(cells).map(|(field, cell)| Self::cell_value(&cell["v"], field)).collect()));
+                }
+                match response["pageToken"].as_str() {
+                    Some(token) => page_token = Some(token.to_string()),
+                    None => break,
+                }
+            }
+            if job_id.is_empty() {
+                return Err(ConnectorError::QueryExecutionFailed("BigQuery response has no job reference".to_string()).into());
+            }
+
+            let mut params = vec![
+                ("timeoutMs", QUERY_WAIT_MS.to_string()),
+                ("formatOptions.useInt64Timestamp", "true".to_string()),
+            ];
+            params.extend(page_token.map(|token| ("pageToken", token)));
+            params.extend(location.clone().map(|location| ("location", location)));
+            let request = client.get(Self::project_url(settings, &settings.project_id, &format!("/queries/{}", job_id)))
+                .bearer_auth(&token)
+                .query(&params);
+            response = send_json("bigquery", &format!("results of job {}", job_id), request).await?.1;
+        }
+
+        let columns = fields.unwrap_or_default().iter().map(|field| self.field_column(field)).collect();
+        let result = QueryResult {
+            columns,
+            rows,
+            affected_rows: None,
+            execution_time: start_time.elapsed(),
+            stats: None,
+            warnings: Vec::new(),
+        };
+        connector_debug_log().result("bigquery", &result);
+        Ok(result)
+    }
+
+    fn field_column(&self, field: &JsonValue) -> ColumnMetadata {
+        let repeated = field["mode"].as_str() == Some("REPEATED");
+        ColumnMetadata {
+            name: field["name"].as_str().unwrap_or_default().to_string(),
+            data_type: if repeated { DataType::Json } else { self.bigquery_type_to_data_type(field["type"].as_str().unwrap_or_default()) },
+            nullable: field["mode"].as_str() != Some("REQUIRED"),
+        }
+    }
+
+    /// A cell's value by its field's type. Values arrive as text; records and repeated
+    /// fields become JSON.
+    fn cell_value(cell: &JsonValue, field: &JsonValue) -> Value {
+        if cell.is_null() {
+            return Value::Null;
+        }
+        let field_type = field["type"].as_str().unwrap_or_default().to_uppercase();
+        if field["mode"].as_str() == Some("REPEATED") || matches!(field_type.as_str(), "RECORD" | "STRUCT") {
+            return Value::Json(Self::plain_json(cell, field).to_string());
+        }
+        let Some(text) = cell.as_str() else {
+            return Value::Json(cell.to_string());
+        };
+        let typed = match field_type.as_str() {
+            "INTEGER" | "INT64" => text.parse().ok().map(Value::Integer),
+            "FLOAT" | "FLOAT64" | "NUMERIC" | "BIGNUMERIC" => text.parse().ok().map(Value::Float),
+            "BOOLEAN" | "BOOL" => text.parse().ok().map(Value::Boolean),
+            "DATE" => Some(Value::Date(text.to_string())),
+            "DATETIME" => Some(Value::DateTime(text.replace('T', " "))),
+            "TIMESTAMP" => epoch_micros_to_datetime(text).map(Value::DateTime),
+            "BYTES" => BASE64_STANDARD.decode(text).ok().map(Value::Binary),
+            "JSON" => Some(Value::Json(text.to_string())),
+            _ => None,
+        };
+        typed.unwrap_or_else(|| Value::Text(text.to_string()))
+    }
+
+    /// A record or repeated value as plain JSON, from the `{"f": [{"v": ...}]}` and
+    /// `[{"v": ...}]` shapes the API nests them in
+    fn plain_json(cell: &JsonValue, field: &JsonValue) -> JsonValue {
+        if field["mode"].as_str() == Some("REPEATED") {
+            let mut item_field = field.clone();
+            item_field["mode"] = json!("NULLABLE");
+            return cell.as_array().into_iter().flatten()
+                .map(|item| Self::plain_json(&item["v"], &item_field))
+                .collect();
+        }
+        if let (Some(subfields), Some(cells)) = (field["fields"].as_array(), cell["f"].as_array()) {
+            return subfields.iter().zip(cells)
+                .map(|(subfield, cell)| (subfield["name"].as_str().unwrap_or_default().to_string(), Self::plain_json(&cell["v"], subfield)))
+                .collect::<serde_json::Map<_, _>>()
+                .into();
+        }
+        match Self::cell_value(cell, field) {
+            Value::Integer(number) => json!(number),
+            Value::Float(number) => json!(number),
+            Value::Boolean(flag) => json!(flag),
+            _ => cell.clone(),
+        }
+    }
+
+    /// Project, dataset and table of `table`, `dataset.table` or `project.dataset.table`
+    fn table_reference(&self, object_name: &str) -> NirvResult<(String, String, String)> {
+        let (_, settings) = self.client()?;
+        let parts: Vec<&str> = object_name.split('.').map(|part| part.trim_matches(|c| c == '`' || c == '"')).collect();
+        match parts.as_slice() {
+            [project, dataset, table] => Ok((project.to_string(), dataset.to_string(), table.to_string())),
+            [dataset, table] => Ok((settings.project_id.clone(), dataset.to_string(), table.to_string())),
+            [table] => match &settings.dataset {
+                Some(dataset) => Ok((settings.project_id.clone(), dataset.clone(), table.to_string())),
+                None => Err(ConnectorError::SchemaRetrievalFailed(
+                    format!("Table '{}' names no dataset and the connector has no default dataset", object_name)
+                ).into()),
+            },
+            _ => Err(ConnectorError::SchemaRetrievalFailed(format!("Invalid table name: '{}'", object_name)).into()),
+        }
+    }
+}
+
+impl Default for BigQueryConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Connector for BigQueryConnector {
+    async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
+        let settings = BigQuerySettings::from_params(&config.connection_params).map_err(issues_error)?;
+        let key_json = match (&settings.credentials_json, &settings.credentials_path) {
+            (Some(json), _) => json.clone(),
+            (None, Some(path)) => fs::read_to_string(path)
+                .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to read BigQuery credentials {}: {}", path, e)))?,
+            (None, None) => unreachable!("settings require credentials"),
+        };
+        let account: ServiceAccountKey = serde_json::from_str(&key_json)
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Invalid service account key: {}", e)))?;
+        let key = RsaSigningKey::from_pem(&account.private_key, None)?;
+
+        let client = Client::builder()
+            .connect_timeout(config.connect_timeout())
+            .timeout(config.read_timeout())
+            .build()
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to create HTTP client: {}", e)))?;
+
+        self.client = Some(client);
+        self.settings = Some(settings);
+        self.service_account = Some((account, key));
+        self.token.clear().await;
+        self.read_timeout = config.read_timeout();
+
+        // Signing in checks the credentials before any query needs them
+        if let Err(e) = self.access_token().await {
+            self.client = None;
+            return Err(e);
+        }
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let statement = self.build_sql_statement(&query.query)?;
+        self.run_statement(&statement).await
+    }
+
+    async fn count_rows(&self, mut query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        // BigQuery counts the rows, so only the count is sent back
+        query.query.projections = vec![Column { name: COUNT_ALL.to_string(), alias: None, source: None }];
+        query.query.ordering = None;
+        query.query.limit = None;
+        Ok(self.execute_query(query).await?.count_value())
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        let (client, settings) = self.client()?;
+        let (project, dataset, table) = self.table_reference(object_name)?;
+        let token = self.access_token().await?;
+        let request = client.get(Self::project_url(settings, &project, &format!("/datasets/{}/tables/{}", dataset, table))).bearer_auth(&token);
+        let (_, body) = send_json("bigquery", &format!("table {}.{}.{}", project, dataset, table), request).await?;
+
+        let columns = body.pointer("/schema/fields").and_then(JsonValue::as_array).into_iter().flatten()
+            .map(|field| self.field_column(field))
+            .collect();
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns,
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    /// Tables of the default dataset
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        let (client, settings) = self.client()?;
+        let Some(dataset) = &settings.dataset else {
+            return Ok(Vec::new());
+        };
+        let token = self.access_token().await?;
+        let mut tables = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut request = client.get(Self::project_url(settings, &settings.project_id, &format!("/datasets/{}/tables", dataset))).bearer_auth(&token);
+            if let Some(page_token) = &page_token {
+                request = request.query(&[("pageToken", page_token)]);
+            }
+            let (_, body) = send_json("bigquery", &format!("tables of {}", dataset), request).await?;
+            tables.extend(body["tables"].as_array().into_iter().flatten()
+                .filter_map(|table| table.pointer("/tableReference/tableId").and_then(JsonValue::as_str))
+                .map(str::to_string));
+            match body["nextPageToken"].as_str() {
+                Some(next) => page_token = Some(next.to_string()),
+                None => return Ok(tables),
+            }
+        }
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.token.clear().await;
+        self.client = None;
+        self.service_account = None;
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::BigQuery
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// JSON functions map to `JSON_VALUE`
+    fn supports_json_functions(&self) -> bool {
+        true
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities {
+            supports_joins: false,
+            supports_aggregations: true,
+            supports_subqueries: false,
+            supports_transactions: false,
+            supports_schema_introspection: true,
+            // BigQuery runs up to 100 interactive queries per project at once
+            max_concurrent_queries: Some(100),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_values_by_field_type() {
+        let field = |name: &str, field_type: &str, mode: &str| json!({ "name": name, "type": field_type, "mode": mode });
+        assert_eq!(BigQueryConnector::cell_value(&json!("42"), &field("id", "INTEGER", "REQUIRED")), Value::Integer(42));
+        assert_eq!(BigQueryConnector::cell_value(&json!("1714557600000000"), &field("at", "TIMESTAMP", "NULLABLE")), Value::DateTime("2024-05-01 10:00:00".to_string()));
+        assert_eq!(BigQueryConnector::cell_value(&json!("2024-05-01T10:00:00"), &field("at", "DATETIME", "NULLABLE")), Value::DateTime("2024-05-01 10:00:00".to_string()));
+        assert_eq!(BigQueryConnector::cell_value(&json!("aGk="), &field("raw", "BYTES", "NULLABLE")), Value::Binary(b"hi".to_vec()));
+        assert_eq!(BigQueryConnector::cell_value(&JsonValue::Null, &field("name", "STRING", "NULLABLE")), Value::Null);
+
+        let tags = field("tags", "INT64", "REPEATED");
+        assert_eq!(BigQueryConnector::cell_value(&json!([{ "v": "1" }, { "v": "2" }]), &tags), Value::Json("[1,2]".to_string()));
+        let address = json!({ "name": "address", "type": "RECORD", "mode": "NULLABLE", "fields": [
+            { "name": "city", "type": "STRING" },
+            { "name": "zip", "type": "INTEGER" },
+        ] });
+        let cell = json!({ "f": [{ "v": "Lyon" }, { "v": "69001" }] });
+        assert_eq!(BigQueryConnector::cell_value(&cell, &address), Value::Json(r#"{"city":"Lyon","zip":69001}"#.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_query_parameters_and_types() {
+        assert_eq!(
+            BigQueryConnector::query_parameter(&PredicateValue::Integer(8)),
+            json!({ "parameterType": { "type": "INT64" }, "parameterValue": { "value": "8" } })
+        );
+        let connector = BigQueryConnector::new();
+        assert_eq!(connector.bigquery_type_to_data_type("numeric"), DataType::Float);
+        assert_eq!(connector.bigquery_type_to_data_type("GEOGRAPHY"), DataType::Text);
+        assert!(connector.get_schema("orders").await.is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use serde_json::{Map, Value as JsonValue};
+
+use crate::utils::{
+    types::{Predicate, PredicateOperator, PredicateValue},
+    error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
+};
+
+/// Content type of JSON body templates
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+/// The body an endpoint is requested with, for search APIs queried by POST. Placeholders
+/// `{{column}}` take the value a query compares the column to with `=`, or the list of an
+/// `IN`, and `{{limit}}` the query's limit; `{{name|default}}` gives a value for queries
+/// without one. In a JSON body a string that is only a placeholder becomes the typed value,
+/// and an object member or array element whose placeholder has no value is left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyTemplate {
+    template: String,
+    content_type: String,
+}
+
+/// A request body rendered for one query
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestBody {
+    pub content_type: String,
+    pub text: String,
+}
+
+impl BodyTemplate {
+    /// A JSON body template, checked to be valid JSON
+    pub fn json(template: &str) -> NirvResult<Self> {
+        Self::new(template, JSON_CONTENT_TYPE)
+    }
+
+    pub fn new(template: &str, content_type: &str) -> NirvResult<Self> {
+        let template = Self { template: template.to_string(), content_type: content_type.to_string() };
+        if template.is_json() {
+            serde_json::from_str::<JsonValue>(&template.template).map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Invalid JSON body template: {}", e)
+            ))?;
+        }
+        Ok(template)
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    fn is_json(&self) -> bool {
+        let media_type = self.content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+        media_type == JSON_CONTENT_TYPE || media_type.ends_with("+json")
+    }
+
+    /// Fill the placeholders from a query's predicates and limit. The limit is only sent
+    /// when every predicate fills a placeholder, since the rest are applied to the rows
+    /// the API returns and a limited response could miss matches.
+    pub fn render(&self, predicates: &[Predicate], limit: Option<u64>, identifier_case: IdentifierCase) -> RequestBody {
+        let names = placeholder_names(&self.template);
+        let filled = |predicate: &Predicate| {
+            matches!(predicate.operator, PredicateOperator::Equal | PredicateOperator::In)
+                && names.iter().any(|name| identifier_case.matches(name, &predicate.column))
+        };
+        let limit = limit.filter(|_| predicates.iter().all(filled));
+        let lookup = |name: &str| -> Option<JsonValue> {
+            if name.eq_ignore_ascii_case("limit") {
+                return limit.map(JsonValue::from);
+            }
+            predicates.iter()
+                .filter(|predicate| matches!(predicate.operator, PredicateOperator::Equal | PredicateOperator::In))
+                .find(|predicate| identifier_case.matches(name, &predicate.column))
+                .map(|predicate| predicate_json(&predicate.value))
+        };
+
+        let text = if self.is_json() {
+            let template: JsonValue = serde_json::from_str(&self.template).unwrap_or(JsonValue::Null);
+            fill_json(template, &lookup).unwrap_or(JsonValue::Null).to_string()
+        } else {
+            let form = self.content_type.to_lowercase().starts_with(FORM_CONTENT_TYPE);
+            fill_text(&self.template, &mut |placeholder| {
+                let text = resolve(placeholder, &lookup).map(|value| plain_text(&value)).unwrap_or_default();
+                if form { url::form_urlencoded::byte_serialize(text.as_bytes()).collect() } else { text }
+            })
+        };
+        RequestBody { content_type: self.content_type.clone(), text }
+    }
+}
+
+/// The value of a placeholder `name` or `name|default`; defaults are JSON when they parse
+fn resolve(placeholder: &str, lookup: &impl Fn(&str) -> Option<JsonValue>) -> Option<JsonValue> {
+    let (name, default) = match placeholder.split_once('|') {
+        Some((name, default)) => (name.trim(), Some(default.trim())),
+        None => (placeholder.trim(), None),
+    };
+    lookup(name).or_else(|| default.map(|default| {
+        serde_json::from_str(default).unwrap_or_else(|_| JsonValue::String(default.to_string()))
+    }))
+}
+
+fn placeholder_names(template: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    fill_text(template, &mut |placeholder| {
+        names.push(placeholder.split('|').next().unwrap_or_default().trim().to_string());
+        String::new()
+    });
+    names
+}
+
+/// Replace every `{{placeholder}}` in a text
+fn fill_text(template: &str, fill: &mut impl FnMut(&str) -> String) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else { break };
+        text.push_str(&rest[..start]);
+        text.push_str(&fill(&rest[start + 2..start + 2 + end]));
+        rest = &rest[start + 2 + end + 2..];
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Fill a JSON template; None when it is a lone placeholder without a value
+fn fill_json(template: JsonValue, lookup: &impl Fn(&str) -> Option<JsonValue>) -> Option<JsonValue> {
+    match template {
+        JsonValue::String(text) => {
+            let trimmed = text.trim();
+            let lone = trimmed.strip_prefix("{{").and_then(|rest| rest.strip_suffix("}}")).filter(|inner| !inner.contains("{{"));
+            match lone {
+                Some(placeholder) => resolve(placeholder, lookup),
+                None => Some(JsonValue::String(fill_text(&text, &mut |placeholder| {
+                    resolve(placeholder, lookup).map(|value| plain_text(&value)).unwrap_or_default()
+                }))),
+            }
+        }
+        JsonValue::Array(items) => Some(JsonValue::Array(items.into_iter().filter_map(|item| fill_json(item, lookup)).collect())),
+        JsonValue::Object(members) => Some(JsonValue::Object(members.into_iter()
+            .filter_map(|(key, value)| Some((key, fill_json(value, lookup)?)))
+            .collect::<Map<_, _>>())),
+        other => Some(other),
+    }
+}
+
+fn predicate_json(value: &PredicateValue) -> JsonValue {
+    match value {
+        PredicateValue::String(text) => JsonValue::String(text.clone()),
+        PredicateValue::Number(number) => JsonValue::from(*number),
+        PredicateValue::Integer(integer) => JsonValue::from(*integer),
+        PredicateValue::Boolean(boolean) => JsonValue::Bool(*boolean),
+        PredicateValue::Null => JsonValue::Null,
+        PredicateValue::List(items) => JsonValue::Array(items.iter().map(predicate_json).collect()),
+    }
+}
+
+/// A value written into text: strings without quotes, lists joined by commas
+fn plain_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(text) => text.clone(),
+        JsonValue::Array(items) => items.iter().map(plain_text).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn predicate(column: &str, operator: PredicateOperator, value: PredicateValue) -> Predicate {
+        Predicate { column: column.to_string(), operator, value }
+    }
+
+    #[test]
+    fn test_json_body_from_predicates_and_limit() {
+        let template = BodyTemplate::json(r#"{"filter": {"status": "{{status}}", "region": "{{region}}"}, "ids": "{{id}}", "size": "{{limit|100}}", "q": "owner:{{owner}}"}"#).unwrap();
+        let predicates = vec![
+            predicate("status", PredicateOperator::Equal, PredicateValue::String("open".to_string())),
+            predicate("ID", PredicateOperator::In, PredicateValue::List(vec![PredicateValue::Integer(1), PredicateValue::Integer(2)])),
+        ];
+        let body = template.render(&predicates, Some(20), IdentifierCase::default());
+        let sent: JsonValue = serde_json::from_str(&body.text).unwrap();
+        assert_eq!(sent, json!({ "filter": { "status": "open" }, "ids": [1, 2], "size": 20, "q": "owner:" }));
+
+        // A predicate no placeholder takes keeps the limit from being sent
+        let mut predicates = predicates;
+        predicates.push(predicate("age", PredicateOperator::GreaterThan, PredicateValue::Integer(30)));
+        let body = template.render(&predicates, Some(20), IdentifierCase::default());
+        let sent: JsonValue = serde_json::from_str(&body.text).unwrap();
+        assert_eq!(sent["size"], json!(100));
+
+        assert!(BodyTemplate::json("{\"size\": ").is_err());
+    }
+
+    #[test]
+    fn test_form_body_is_encoded() {
+        let template = BodyTemplate::new("q={{name}}&rows={{limit|10}}&tag={{tag}}", "application/x-www-form-urlencoded").unwrap();
+        let predicates = vec![predicate("name", PredicateOperator::Equal, PredicateValue::String("a&b c".to_string()))];
+        let body = template.render(&predicates, None, IdentifierCase::default());
+        assert_eq!(body.text, "q=a%26b+c&rows=10&tag=");
+        assert_eq!(body.content_type, "application/x-www-form-urlencoded");
+    }
+}
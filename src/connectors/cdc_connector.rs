@@ -0,0 +1,778 @@
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_postgres::{Client, NoTls};
+
+use crate::connectors::connector_trait::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::network::{connect_with_retry, resolve_host, with_timeout};
+use crate::engine::describe_predicate;
+use crate::utils::{
+    types::{
+        ConnectorType, ConnectorQuery, QueryResult, QueryWarning, Schema, ColumnMetadata, DataType, Row, Value,
+    },
+    error::{ConnectorError, NirvResult},
+};
+
+/// Position in the PostgreSQL write-ahead log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lsn(pub u64);
+
+impl Lsn {
+    /// Parse the textual `XXXXXXXX/XXXXXXXX` form used by PostgreSQL
+    pub fn parse(text: &str) -> NirvResult<Self> {
+        let invalid = || ConnectorError::QueryExecutionFailed(format!("Invalid LSN: {}", text));
+        let (high, low) = text.trim().split_once('/').ok_or_else(invalid)?;
+        let high = u64::from_str_radix(high, 16).map_err(|_| invalid())?;
+        let low = u64::from_str_radix(low, 16).map_err(|_| invalid())?;
+        Ok(Lsn((high << 32) | low))
+    }
+}
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 & 0xFFFF_FFFF)
+    }
+}
+
+/// Kind of row change captured from the replication slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeOperation {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl ChangeOperation {
+    /// Operation name as reported in query results
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeOperation::Insert => "INSERT",
+            ChangeOperation::Update => "UPDATE",
+            ChangeOperation::Delete => "DELETE",
+        }
+    }
+}
+
+/// A single row change decoded from the replication slot
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    pub lsn: Lsn,
+    pub xid: i64,
+    pub schema: String,
+    pub table: String,
+    pub operation: ChangeOperation,
+    /// New row values, or the key values for deletes
+    pub columns: Vec<(String, Value)>,
+}
+
+impl ChangeEvent {
+    /// Check if this event belongs to `table` or `schema.table`
+    pub fn matches(&self, name: &str) -> bool {
+        match name.split_once('.') {
+            Some((schema, table)) => self.schema == schema && self.table == table,
+            None => self.table == name,
+        }
+    }
+}
+
+/// Parse one `test_decoding` output line; transaction BEGIN/COMMIT lines yield `None`
+pub fn parse_test_decoding(lsn: Lsn, xid: i64, data: &str) -> NirvResult<Option<ChangeEvent>> {
+    let Some(rest) = data.strip_prefix("table ") else {
+        return Ok(None);
+    };
+    let malformed = || ConnectorError::QueryExecutionFailed(format!("Malformed change record: {}", data));
+
+    let (qualified, rest) = rest.split_once(": ").ok_or_else(malformed)?;
+    let (operation, tuple) = rest.split_once(':').ok_or_else(malformed)?;
+    let operation = match operation {
+        "INSERT" => ChangeOperation::Insert,
+        "UPDATE" => ChangeOperation::Update,
+        "DELETE" => ChangeOperation::Delete,
+        // TRUNCATE and other messages carry no row data
+        _ => return Ok(None),
+    };
+
+    let (schema, table) = qualified.split_once('.').unwrap_or(("public", qualified));
+    let tuple = tuple.trim_start();
+    // Updates that change the key carry the old key first; only the new tuple matters
+    let tuple = match tuple.split_once("new-tuple: ") {
+        Some((_, new_tuple)) => new_tuple,
+        None => tuple,
+    };
+    let columns = if tuple.starts_with("(no-tuple-data)") {
+        Vec::new()
+    } else {
+        parse_tuple(tuple).ok_or_else(malformed)?
+    };
+
+    Ok(Some(ChangeEvent {
+        lsn,
+        xid,
+        schema: strip_identifier_quotes(schema),
+        table: strip_identifier_quotes(table),
+        operation,
+        columns,
+    }))
+}
+
+fn strip_identifier_quotes(identifier: &str) -> String {
+    identifier.trim_matches('"').replace("\"\"", "\"")
+}
+
+/// Parse `name[type]:value` pairs separated by spaces
+fn parse_tuple(tuple: &str) -> Option<Vec<(String, Value)>> {
+    let mut columns = Vec::new();
+    let mut chars = tuple.char_indices().peekable();
+
+    while let Some(&(start, _)) = chars.peek() {
+        // Column name runs up to the opening bracket of the type
+        let bracket = tuple[start..].find('[')? + start;
+        let name = tuple[start..bracket].trim().to_string();
+
+        // Array types nest brackets, e.g. tags[text[]]
+        let mut depth = 0;
+        let mut type_end = None;
+        for (i, c) in tuple[bracket..].char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        type_end = Some(bracket + i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let type_end = type_end?;
+        let type_name = &tuple[bracket + 1..type_end];
+        if tuple[type_end + 1..].chars().next()? != ':' {
+            return None;
+        }
+
+        let value_start = type_end + 2;
+        let (raw, quoted, value_end) = if tuple[value_start..].starts_with('\'') {
+            let mut value = String::new();
+            let mut end = None;
+            let mut iter = tuple[value_start + 1..].char_indices().peekable();
+            while let Some((i, c)) = iter.next() {
+                if c == '\'' {
+                    if matches!(iter.peek(), Some((_, '\''))) {
+                        value.push('\'');
+                        iter.next();
+                    } else {
+                        end = Some(value_start + 1 + i + 1);
+                        break;
+                    }
+                } else {
+                    value.push(c);
+                }
+            }
+            (value, true, end?)
+        } else {
+            let end = tuple[value_start..].find(' ').map_or(tuple.len(), |i| value_start + i);
+            (tuple[value_start..end].to_string(), false, end)
+        };
+
+        columns.push((name, decode_value(type_name, &raw, quoted)));
+
+        // Skip to the next column
+        while matches!(chars.peek(), Some(&(i, _)) if i < value_end) {
+            chars.next();
+        }
+        while matches!(chars.peek(), Some(&(_, ' '))) {
+            chars.next();
+        }
+    }
+
+    Some(columns)
+}
+
+fn decode_value(type_name: &str, raw: &str, quoted: bool) -> Value {
+    if !quoted && raw == "null" {
+        return Value::Null;
+    }
+    match type_name {
+        "smallint" | "integer" | "bigint" => raw.parse().map(Value::Integer).unwrap_or_else(|_| Value::Text(raw.to_string())),
+        "real" | "double precision" | "numeric" => raw.parse().map(Value::Float).unwrap_or_else(|_| Value::Text(raw.to_string())),
+        "boolean" => Value::Boolean(raw == "true"),
+        "date" => Value::Date(raw.to_string()),
+        t if t.starts_with("timestamp") => Value::DateTime(raw.to_string()),
+        "json" | "jsonb" => Value::Json(raw.to_string()),
+        _ => Value::Text(raw.to_string()),
+    }
+}
+
+/// Bounded buffer of the most recent change events
+#[derive(Debug)]
+pub struct ChangeBuffer {
+    retention: usize,
+    events: VecDeque<ChangeEvent>,
+}
+
+impl ChangeBuffer {
+    /// Create a buffer retaining at most `retention` events
+    pub fn new(retention: usize) -> Self {
+        Self {
+            retention: retention.max(1),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Append an event, evicting the oldest once full
+    pub fn push(&mut self, event: ChangeEvent) {
+        if self.events.len() == self.retention {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Retained events for a table, oldest first
+    pub fn for_table(&self, name: &str) -> Vec<ChangeEvent> {
+        self.events.iter().filter(|e| e.matches(name)).cloned().collect()
+    }
+
+    /// Number of retained events
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Check if no events are retained
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// File holding the last LSN whose changes have been consumed
+#[derive(Debug, Clone)]
+pub struct LsnCheckpoint {
+    path: PathBuf,
+}
+
+impl LsnCheckpoint {
+    /// Checkpoint stored at the given path
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+
+    /// Load the checkpointed LSN, if one was stored
+    pub fn load(&self) -> NirvResult<Option<Lsn>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Lsn::parse(&content).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ConnectorError::ConnectionFailed(
+                format!("Failed to read LSN checkpoint {}: {}", self.path.display(), e)
+            ).into()),
+        }
+    }
+
+    /// Atomically replace the checkpointed LSN
+    pub fn store(&self, lsn: Lsn) -> NirvResult<()> {
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, lsn.to_string())
+            .and_then(|_| fs::rename(&temp_path, &self.path))
+            .map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Failed to write LSN checkpoint {}: {}", self.path.display(), e)
+            ).into())
+    }
+}
+
+/// State shared between the connector and its background poller
+struct CdcStream {
+    client: Client,
+    slot: String,
+    batch_size: i32,
+    buffer: Mutex<ChangeBuffer>,
+    checkpoint: Option<LsnCheckpoint>,
+    confirmed: Mutex<Lsn>,
+    events: broadcast::Sender<ChangeEvent>,
+    read_timeout: Duration,
+    /// Why the feed last failed, cleared once a poll succeeds again
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+impl CdcStream {
+    /// Pull pending changes, recording whether the feed failed
+    async fn poll(&self) -> NirvResult<usize> {
+        let result = self.read_changes().await;
+        *self.failure.lock().unwrap() = result.as_ref().err().map(|e| e.to_string());
+        result
+    }
+
+    /// Pull pending changes from the slot, buffer them, then checkpoint and advance the slot
+    async fn read_changes(&self) -> NirvResult<usize> {
+        let rows = with_timeout(self.read_timeout, "Reading replication slot", async {
+            self.client.query(
+                "SELECT lsn::text, xid::text, data FROM pg_logical_slot_peek_changes($1, NULL, $2)",
+                &[&self.slot, &self.batch_size],
+            ).await.map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Failed to read replication slot '{}': {}", self.slot, e)
+            ).into())
+        }).await?;
+
+        let confirmed = *self.confirmed.lock().unwrap();
+        let mut latest = confirmed;
+        let mut captured = 0;
+
+        for row in &rows {
+            let lsn = Lsn::parse(row.get::<_, &str>(0))?;
+            // Changes at or before the checkpoint were consumed before a restart
+            if lsn <= confirmed {
+                continue;
+            }
+            latest = latest.max(lsn);
+
+            let xid = row.get::<_, &str>(1).parse().unwrap_or_default();
+            if let Some(event) = parse_test_decoding(lsn, xid, row.get(2))? {
+                self.buffer.lock().unwrap().push(event.clone());
+                // Sending fails only without subscribers; those falling behind are told how
+                // many events they missed by `RecvError::Lagged`
+                let _ = self.events.send(event);
+                captured += 1;
+            }
+        }
+
+        if latest > confirmed {
+            // Persist first so a crash before advancing only causes skipped duplicates
+            if let Some(checkpoint) = &self.checkpoint {
+                checkpoint.store(latest)?;
+            }
+            self.client.execute(
+                "SELECT pg_replication_slot_advance($1, $2::text::pg_lsn)",
+                &[&self.slot, &latest.to_string()],
+            ).await.map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Failed to advance replication slot '{}': {}", self.slot, e)
+            ))?;
+            *self.confirmed.lock().unwrap() = latest;
+        }
+
+        Ok(captured)
+    }
+}
+
+/// Longest wait between polls of a failing slot
+const MAX_POLL_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Wait before the next poll: the interval after a success, and twice the last wait after
+/// a failure, up to a minute or the interval when that is longer
+fn next_poll_delay(delay: Duration, interval: Duration, failed: bool) -> Duration {
+    match failed {
+        true => (delay * 2).min(MAX_POLL_BACKOFF.max(interval)),
+        false => interval,
+    }
+}
+
+/// Change data capture connector tailing a PostgreSQL logical replication slot
+/// decoded with the `test_decoding` plugin. Recent changes are retained in memory
+/// and queried with `source('cdc.orders')`.
+pub struct CdcConnector {
+    stream: Option<Arc<CdcStream>>,
+    poller: Option<JoinHandle<()>>,
+    connection_task: Option<JoinHandle<()>>,
+    connected: bool,
+}
+
+impl CdcConnector {
+    /// Create a new CDC connector
+    pub fn new() -> Self {
+        Self {
+            stream: None,
+            poller: None,
+            connection_task: None,
+            connected: false,
+        }
+    }
+
+    fn stream(&self) -> NirvResult<&Arc<CdcStream>> {
+        self.stream.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("CDC connector is not connected".to_string()).into())
+    }
+
+    /// Subscribe to change events as they are captured
+    pub fn subscribe(&self) -> NirvResult<broadcast::Receiver<ChangeEvent>> {
+        Ok(self.stream()?.events.subscribe())
+    }
+
+    /// Pull pending changes now, returning how many row changes were captured
+    pub async fn poll_changes(&self) -> NirvResult<usize> {
+        self.stream()?.poll().await
+    }
+
+    /// Last LSN whose changes have been consumed
+    pub fn confirmed_lsn(&self) -> Option<Lsn> {
+        self.stream.as_ref().map(|stream| *stream.confirmed.lock().unwrap())
+    }
+
+    /// Result columns: change metadata followed by the union of row columns
+    fn build_columns(events: &[ChangeEvent]) -> Vec<ColumnMetadata> {
+        let mut columns = vec![
+            ColumnMetadata { name: "_lsn".to_string(), data_type: DataType::Text, nullable: false },
+            ColumnMetadata { name: "_xid".to_string(), data_type: DataType::Integer, nullable: false },
+            ColumnMetadata { name: "_operation".to_string(), data_type: DataType::Text, nullable: false },
+        ];
+
+        for event in events {
+            for (name, value) in &event.columns {
+                if !columns.iter().any(|c| &c.name == name) {
+                    columns.push(ColumnMetadata {
+                        name: name.clone(),
+                        data_type: value_data_type(value),
+                        nullable: true,
+                    });
+                }
+            }
+        }
+
+        columns
+    }
+}
+
+fn value_data_type(value: &Value) -> DataType {
+    match value {
+        Value::Integer(_) => DataType::Integer,
+        Value::Float(_) => DataType::Float,
+        Value::Boolean(_) => DataType::Boolean,
+        Value::Date(_) => DataType::Date,
+        Value::DateTime(_) => DataType::DateTime,
+        Value::Json(_) => DataType::Json,
+        Value::Binary(_) => DataType::Binary,
+        Value::Text(_) | Value::Null => DataType::Text,
+    }
+}
+
+impl Default for CdcConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Connector for CdcConnector {
+    async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
+        let param = |key: &str, default: &str| {
+            config.connection_params.get(key).cloned().unwrap_or_else(|| default.to_string())
+        };
+        let parse_param = |key: &str, default: u64| -> NirvResult<u64> {
+            param(key, &default.to_string()).parse().map_err(|e| ConnectorError::ConnectionFailed(
+                format!("Invalid {}: {}", key, e)
+            ).into())
+        };
+
+        let slot = config.connection_params.get("slot")
+            .ok_or_else(|| ConnectorError::ConnectionFailed("slot parameter is required".to_string()))?
+            .clone();
+        let plugin = param("plugin", "test_decoding");
+        if plugin != "test_decoding" {
+            return Err(ConnectorError::UnsupportedOperation(
+                format!("Unsupported logical decoding plugin: {}", plugin)
+            ).into());
+        }
+
+        let host = param("host", "localhost");
+        let port = parse_param("port", 5432)? as u16;
+        let retention = parse_param("retention", 10_000)? as usize;
+        let batch_size = parse_param("batch_size", 1_000)?.min(i32::MAX as u64) as i32;
+        let poll_interval = Duration::from_millis(parse_param("poll_interval_ms", 1_000)?);
+
+        let connect_timeout = config.connect_timeout();
+        resolve_host(&host, port, connect_timeout).await?;
+
+        let mut pg_config = tokio_postgres::Config::new();
+        pg_config.host(&host)
+            .port(port)
+            .user(param("user", "postgres"))
+            .password(param("password", ""))
+            .dbname(param("dbname", "postgres"))
+            .connect_timeout(connect_timeout);
+
+        let description = format!("Connecting to PostgreSQL at {}:{} for CDC", host, port);
+        let (client, connection) = connect_with_retry(&config.retry_policy, connect_timeout, &description, || async {
+            pg_config.connect(NoTls).await.map_err(|e| ConnectorError::ConnectionFailed(format!(
+                "Failed to connect to PostgreSQL at {}:{} for CDC: {}", host, port, e
+            )).into())
+        }).await?;
+        let failure = Arc::new(Mutex::new(None));
+        let connection_failure = failure.clone();
+        let connection_slot = slot.clone();
+        let connection_task = tokio::spawn(async move {
+            let message = match connection.await {
+                Ok(()) => "Connection to PostgreSQL closed".to_string(),
+                Err(e) => format!("Connection to PostgreSQL failed: {}", e),
+            };
+            eprintln!("CDC feed of slot '{}' stopped: {}", connection_slot, message);
+            *connection_failure.lock().unwrap() = Some(message);
+        });
+
+        if param("create_slot", "false") == "true" {
+            client.execute(
+                "SELECT pg_create_logical_replication_slot($1, 'test_decoding') \
+                 WHERE NOT EXISTS (SELECT 1 FROM pg_replication_slots WHERE slot_name = $1)",
+                &[&slot],
+            ).await.map_err(|e| ConnectorError::ConnectionFailed(
+                format!("Failed to create replication slot '{}': {}", slot, e)
+            ))?;
+        }
+
+        let checkpoint = config.connection_params.get("checkpoint_path").map(LsnCheckpoint::new);
+        let confirmed = match &checkpoint {
+            Some(checkpoint) => checkpoint.load()?.unwrap_or_default(),
+            None => Lsn::default(),
+        };
+
+        let (events, _) = broadcast::channel(1024);
+        let stream = Arc::new(CdcStream {
+            client,
+            slot,
+            batch_size,
+            buffer: Mutex::new(ChangeBuffer::new(retention)),
+            checkpoint,
+            confirmed: Mutex::new(confirmed),
+            events,
+            read_timeout: config.read_timeout(),
+            failure,
+        });
+
+        // Fail fast on a missing slot or insufficient privileges
+        stream.poll().await?;
+
+        if !poll_interval.is_zero() {
+            let poller_stream = stream.clone();
+            self.poller = Some(tokio::spawn(async move {
+                let mut delay = poll_interval;
+                loop {
+                    tokio::time::sleep(delay).await;
+                    let result = poller_stream.poll().await;
+                    let next = next_poll_delay(delay, poll_interval, result.is_err());
+                    match result {
+                        Err(e) => eprintln!("CDC poll of slot '{}' failed, retrying in {}ms: {}", poller_stream.slot, next.as_millis(), e),
+                        Ok(_) if delay != poll_interval => eprintln!("CDC poll of slot '{}' recovered", poller_stream.slot),
+                        Ok(_) => {}
+                    }
+                    delay = next;
+                }
+            }));
+        }
+
+        self.stream = Some(stream);
+        self.connection_task = Some(connection_task);
+        self.connected = true;
+
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+        let stream = self.stream()?;
+
+        // Without a background poller, queries pull pending changes themselves
+        if self.poller.is_none() {
+            stream.poll().await?;
+        }
+
+        let source = query.query.sources.first()
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed("No data source specified in query".to_string()))?;
+        let events = stream.buffer.lock().unwrap().for_table(&source.identifier);
+        let columns = Self::build_columns(&events);
+
+        let mut rows: Vec<Row> = events.iter().map(|event| {
+            let mut values = vec![
+                Value::Text(event.lsn.to_string()),
+                Value::Integer(event.xid),
+                Value::Text(event.operation.as_str().to_string()),
+            ];
+            for column in &columns[3..] {
+                let value = event.columns.iter()
+                    .find(|(name, _)| name == &column.name)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or(Value::Null);
+                values.push(value);
+            }
+            Row::new(values)
+        }).collect();
+
+        // Keep the most recent changes when limited
+        if let Some(limit) = query.query.limit {
+            let skip = rows.len().saturating_sub(limit as usize);
+            rows.drain(..skip);
+        }
+
+        // Change events are returned as captured, unfiltered
+        let mut warnings: Vec<QueryWarning> = query.query.predicates.iter()
+            .map(|predicate| QueryWarning::predicate_ignored("", describe_predicate(predicate)))
+            .collect();
+        if let Some(failure) = self.health_error() {
+            warnings.push(QueryWarning::stale("", failure));
+        }
+
+        Ok(QueryResult {
+            columns,
+            rows,
+            affected_rows: None,
+            execution_time: start_time.elapsed(),
+            stats: None,
+            warnings,
+        })
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        let events = self.stream()?.buffer.lock().unwrap().for_table(object_name);
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns: Self::build_columns(&events),
+            primary_key: Some(vec!["_lsn".to_string()]),
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        if let Some(poller) = self.poller.take() {
+            poller.abort();
+        }
+        self.stream = None;
+        if let Some(connection_task) = self.connection_task.take() {
+            connection_task.abort();
+        }
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Cdc
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn health_error(&self) -> Option<String> {
+        self.stream.as_ref()?.failure.lock().unwrap().clone()
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities {
+            supports_joins: false,
+            supports_aggregations: false,
+            supports_subqueries: false,
+            supports_transactions: false,
+            supports_schema_introspection: true,
+            max_concurrent_queries: Some(10),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lsn_round_trip_and_ordering() {
+        let lsn = Lsn::parse("16/B374D848").unwrap();
+        assert_eq!(lsn.to_string(), "16/B374D848");
+        assert!(Lsn::parse("0/FFFFFFFF").unwrap() < Lsn::parse("1/0").unwrap());
+        assert!(Lsn::parse("garbage").is_err());
+    }
+
+    #[test]
+    fn test_parse_insert() {
+        let event = parse_test_decoding(
+            Lsn(1),
+            42,
+            "table public.orders: INSERT: id[integer]:1 customer[text]:'O''Brien says hi' total[numeric]:9.50 tags[text[]]:'{a,b}' note[text]:null shipped[boolean]:false",
+        ).unwrap().unwrap();
+
+        assert_eq!(event.schema, "public");
+        assert_eq!(event.table, "orders");
+        assert_eq!(event.operation, ChangeOperation::Insert);
+        assert_eq!(event.columns, vec![
+            ("id".to_string(), Value::Integer(1)),
+            ("customer".to_string(), Value::Text("O'Brien says hi".to_string())),
+            ("total".to_string(), Value::Float(9.5)),
+            ("tags".to_string(), Value::Text("{a,b}".to_string())),
+            ("note".to_string(), Value::Null),
+            ("shipped".to_string(), Value::Boolean(false)),
+        ]);
+        assert!(event.matches("orders") && event.matches("public.orders"));
+        assert!(!event.matches("sales.orders"));
+    }
+
+    #[test]
+    fn test_parse_update_with_old_key_and_delete() {
+        let update = parse_test_decoding(
+            Lsn(2), 1,
+            "table public.orders: UPDATE: old-key: id[integer]:1 new-tuple: id[integer]:2 customer[character varying]:'x'",
+        ).unwrap().unwrap();
+        assert_eq!(update.operation, ChangeOperation::Update);
+        assert_eq!(update.columns[0], ("id".to_string(), Value::Integer(2)));
+        assert_eq!(update.columns[1], ("customer".to_string(), Value::Text("x".to_string())));
+
+        let delete = parse_test_decoding(Lsn(3), 1, "table public.orders: DELETE: id[integer]:2").unwrap().unwrap();
+        assert_eq!(delete.operation, ChangeOperation::Delete);
+        assert_eq!(delete.columns.len(), 1);
+
+        assert!(parse_test_decoding(Lsn(4), 1, "BEGIN 529").unwrap().is_none());
+        assert!(parse_test_decoding(Lsn(5), 1, "COMMIT 529").unwrap().is_none());
+        assert!(parse_test_decoding(Lsn(6), 1, "table public.orders: INSERT: id[integer").is_err());
+    }
+
+    #[test]
+    fn test_change_buffer_retention() {
+        let mut buffer = ChangeBuffer::new(2);
+        for i in 1..=3 {
+            buffer.push(ChangeEvent {
+                lsn: Lsn(i),
+                xid: 1,
+                schema: "public".to_string(),
+                table: "orders".to_string(),
+                operation: ChangeOperation::Insert,
+                columns: Vec::new(),
+            });
+        }
+        let events = buffer.for_table("orders");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].lsn, Lsn(2));
+        assert!(buffer.for_table("customers").is_empty());
+    }
+
+    #[test]
+    fn test_lsn_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = LsnCheckpoint::new(dir.path().join("slot.lsn"));
+        assert_eq!(checkpoint.load().unwrap(), None);
+
+        checkpoint.store(Lsn::parse("0/16B3748").unwrap()).unwrap();
+        assert_eq!(checkpoint.load().unwrap(), Some(Lsn::parse("0/16B3748").unwrap()));
+    }
+
+    #[test]
+    fn test_poll_backoff() {
+        let interval = Duration::from_secs(1);
+        assert_eq!(next_poll_delay(interval, interval, true), Duration::from_secs(2));
+        assert_eq!(next_poll_delay(Duration::from_secs(40), interval, true), MAX_POLL_BACKOFF);
+        assert_eq!(next_poll_delay(MAX_POLL_BACKOFF, interval, false), interval);
+        // An interval longer than the cap is kept
+        let hourly = Duration::from_secs(3600);
+        assert_eq!(next_poll_delay(hourly, hourly, true), hourly);
+    }
+
+    #[tokio::test]
+    async fn test_connect_requires_slot() {
+        let mut connector = CdcConnector::new();
+        let result = connector.connect(ConnectorInitConfig::new()).await;
+        assert!(result.is_err());
+        assert!(!connector.is_connected());
+        assert!(connector.subscribe().is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::connectors::{Connector, ConnectorInitConfig};
+use crate::engine::{describe_predicate, filter_rows};
+use crate::utils::{
+    types::{
+        ColumnMetadata, Column, ConnectorQuery, DataSource, DataType, InternalQuery, Predicate,
+        PredicateOperator, PredicateValue, QueryOperation, QueryResult, Row, Value,
+    },
+    error::NirvError,
+    identifier::IdentifierCase,
+    collation::Collation,
+};
+
+/// Columns of the conformance table, in order
+pub const FIXTURE_COLUMNS: [&str; 3] = ["id", "name", "score"];
+
+/// Rows of the conformance table: ids 1 to 4, one without a score
+pub fn fixture_rows() -> Vec<Row> {
+    vec![
+        Row::new(vec![Value::Integer(1), Value::Text("Ann".to_string()), Value::Float(9.5)]),
+        Row::new(vec![Value::Integer(2), Value::Text("bob".to_string()), Value::Float(7.5)]),
+        Row::new(vec![Value::Integer(3), Value::Text("Cy".to_string()), Value::Null]),
+        Row::new(vec![Value::Integer(4), Value::Text("Dee".to_string()), Value::Float(7.25)]),
+    ]
+}
+
+/// The conformance table as CSV, for sources loaded from files
+pub fn fixture_csv() -> String {
+    "id,name,score\n1,Ann,9.5\n2,bob,7.5\n3,Cy,\n4,Dee,7.25\n".to_string()
+}
+
+/// Where the connector under test finds the conformance table. The source behind it must
+/// hold exactly `fixture_rows()` under the `FIXTURE_COLUMNS`.
+#[derive(Debug, Clone)]
+pub struct ConformanceFixture {
+    /// Configuration the connector is connected with
+    pub config: ConnectorInitConfig,
+    /// Data object type the connector is registered under, such as `file`
+    pub object_type: String,
+    /// Identifier of the conformance table, such as `people.csv`
+    pub table: String,
+    /// Identifier of an object the source does not have
+    pub missing_table: String,
+}
+
+impl ConformanceFixture {
+    pub fn new(config: ConnectorInitConfig, object_type: &str, table: &str, missing_table: &str) -> Self {
+        Self {
+            config,
+            object_type: object_type.to_string(),
+            table: table.to_string(),
+            missing_table: missing_table.to_string(),
+        }
+    }
+
+    fn query(&self, table: &str, connector: &dyn Connector, predicates: Vec<Predicate>, limit: Option<u64>) -> ConnectorQuery {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: self.object_type.clone(), identifier: table.to_string(), alias: None });
+        query.projections.push(Column { name: "*".to_string(), alias: None, source: None });
+        query.predicates = predicates;
+        query.limit = limit;
+        ConnectorQuery {
+            connector_type: connector.get_connector_type(),
+            query,
+            connection_params: HashMap::new(),
+        }
+    }
+}
+
+/// Outcome of one conformance check
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceCheck {
+    pub name: String,
+    /// Why the check failed; `None` when it passed
+    pub failure: Option<String>,
+}
+
+/// Outcomes of a conformance run, in the order the checks ran
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConformanceReport {
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl ConformanceReport {
+    fn record(&mut self, name: impl Into<String>, outcome: Result<(), String>) {
+        self.checks.push(ConformanceCheck { name: name.into(), failure: outcome.err() });
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCheck> {
+        self.checks.iter().filter(|check| check.failure.is_some())
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Panic listing the failed checks, for use in a connector's own tests
+    pub fn assert_passed(&self) {
+        assert!(self.passed(), "connector conformance failed:\n{}", self);
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.failure {
+                Some(failure) => writeln!(f, "FAIL {}: {}", check.name, failure)?,
+                None => writeln!(f, "ok   {}", check.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Conformance suite that connector implementations run against themselves, so that they
+/// behave like the built-in connectors wherever the engine relies on it: the connection
+/// lifecycle, schema introspection, predicate and limit semantics, and error mapping.
+///
+/// ```no_run
+/// # async fn run(mut connector: nirv_engine::connectors::FileConnector) {
+/// use nirv_engine::connectors::{ConnectorInitConfig, ConnectorTestKit, ConformanceFixture};
+///
+/// let config = ConnectorInitConfig::new().with_param("base_path", "/tmp/fixture");
+/// let fixture = ConformanceFixture::new(config, "file", "people.csv", "missing.csv");
+/// ConnectorTestKit::new(fixture).run(&mut connector).await.assert_passed();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectorTestKit {
+    fixture: ConformanceFixture,
+}
+
+impl ConnectorTestKit {
+    pub fn new(fixture: ConformanceFixture) -> Self {
+        Self { fixture }
+    }
+
+    /// Predicates checked against the rows the engine itself keeps for them
+    pub fn predicates() -> Vec<Predicate> {
+        let predicate = |column: &str, operator: PredicateOperator, value: PredicateValue| Predicate {
+            column: column.to_string(),
+            operator,
+            value,
+        };
+        vec![
+            predicate("id", PredicateOperator::Equal, PredicateValue::Integer(2)),
+            predicate("id", PredicateOperator::NotEqual, PredicateValue::Integer(2)),
+            predicate("id", PredicateOperator::GreaterThan, PredicateValue::Integer(2)),
+            predicate("id", PredicateOperator::GreaterThanOrEqual, PredicateValue::Integer(2)),
+            predicate("id", PredicateOperator::LessThan, PredicateValue::Integer(3)),
+            predicate("id", PredicateOperator::LessThanOrEqual, PredicateValue::Integer(3)),
+            predicate("id", PredicateOperator::In, PredicateValue::List(vec![PredicateValue::Integer(1), PredicateValue::Integer(3)])),
+            predicate("name", PredicateOperator::Equal, PredicateValue::String("Ann".to_string())),
+            predicate("name", PredicateOperator::Like, PredicateValue::String("D%".to_string())),
+            predicate("score", PredicateOperator::GreaterThan, PredicateValue::Number(7.5)),
+            predicate("score", PredicateOperator::IsNull, PredicateValue::Null),
+            predicate("score", PredicateOperator::IsNotNull, PredicateValue::Null),
+        ]
+    }
+
+    /// Run every check, connecting the connector first and disconnecting it last
+    pub async fn run(&self, connector: &mut dyn Connector) -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+
+        let before = connector.execute_query(self.fixture.query(&self.fixture.table, connector, Vec::new(), None)).await;
+        report.record("query before connect fails", match before {
+            Ok(_) if connector.is_connected() => Err("the connector reports being connected before connect".to_string()),
+            Ok(_) => Err("returned rows without being connected".to_string()),
+            Err(error) => expect_connector_error(&error),
+        });
+
+        if let Err(error) = connector.connect(self.fixture.config.clone()).await {
+            report.record("connect", Err(error.to_string()));
+            return report;
+        }
+        report.record("connect", check(connector.is_connected(), || "is_connected is false after connect".to_string()));
+
+        report.record("schema", self.check_schema(connector).await);
+        report.record("scan", self.check_rows(connector, Vec::new(), None).await);
+        for predicate in Self::predicates() {
+            let name = format!("predicate {}", describe_predicate(&predicate));
+            let outcome = if connector.supports_predicate(&predicate) {
+                self.check_rows(connector, vec![predicate], None).await
+            } else {
+                Ok(())
+            };
+            report.record(name, outcome);
+        }
+        for limit in [0, 2, 10] {
+            report.record(format!("limit {}", limit), self.check_limit(connector, limit).await);
+        }
+
+        let missing = connector.execute_query(self.fixture.query(&self.fixture.missing_table, connector, Vec::new(), None)).await;
+        report.record("query of a missing object fails", match missing {
+            Ok(result) => Err(format!("returned {} rows", result.rows.len())),
+            Err(error) => expect_connector_error(&error),
+        });
+        let missing = connector.get_schema(&self.fixture.missing_table).await;
+        report.record("schema of a missing object fails", match missing {
+            Ok(schema) => Err(format!("returned {} columns", schema.columns.len())),
+            Err(error) => expect_connector_error(&error),
+        });
+
+        let disconnected = match connector.disconnect().await {
+            Ok(()) => check(!connector.is_connected(), || "is_connected is true after disconnect".to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+        report.record("disconnect", disconnected);
+        let after = connector.execute_query(self.fixture.query(&self.fixture.table, connector, Vec::new(), None)).await;
+        report.record("query after disconnect fails", match after {
+            Ok(_) => Err("returned rows after disconnect".to_string()),
+            Err(error) => expect_connector_error(&error),
+        });
+
+        report
+    }
+
+    /// The schema names the fixture columns in order, with compatible types
+    async fn check_schema(&self, connector: &dyn Connector) -> Result<(), String> {
+        let schema = connector.get_schema(&self.fixture.table).await.map_err(|e| e.to_string())?;
+        let names: Vec<&str> = schema.columns.iter().map(|col| col.name.as_str()).collect();
+        let case = IdentifierCase::default();
+        if names.len() != FIXTURE_COLUMNS.len() || !names.iter().zip(FIXTURE_COLUMNS).all(|(name, expected)| case.matches(name, expected)) {
+            return Err(format!("columns are {:?}, expected {:?}", names, FIXTURE_COLUMNS));
+        }
+        let text_or = |column: &ColumnMetadata, types: &[DataType]| column.data_type == DataType::Text || types.contains(&column.data_type);
+        if !text_or(&schema.columns[0], &[DataType::Integer, DataType::Float]) || !text_or(&schema.columns[2], &[DataType::Float, DataType::Integer]) {
+            return Err(format!("id is {:?} and score {:?}, expected numbers or text", schema.columns[0].data_type, schema.columns[2].data_type));
+        }
+        Ok(())
+    }
+
+    /// The ids of the rows returned match those the engine keeps for the predicates
+    async fn check_rows(&self, connector: &dyn Connector, predicates: Vec<Predicate>, limit: Option<u64>) -> Result<(), String> {
+        let expected = filter_rows(fixture_result(), &predicates, IdentifierCase::default(), Collation::default())
+            .map_err(|e| e.to_string())?;
+        let result = connector.execute_query(self.fixture.query(&self.fixture.table, connector, predicates, limit)).await
+            .map_err(|e| e.to_string())?;
+        let (mut actual, mut expected) = (ids(&result)?, ids(&expected)?);
+        actual.sort_unstable();
+        expected.sort_unstable();
+        check(actual == expected, || format!("returned ids {:?}, expected {:?}", actual, expected))
+    }
+
+    /// A LIMIT returns that many of the table's rows, or all of them when it has fewer
+    async fn check_limit(&self, connector: &dyn Connector, limit: u64) -> Result<(), String> {
+        let result = connector.execute_query(self.fixture.query(&self.fixture.table, connector, Vec::new(), Some(limit))).await
+            .map_err(|e| e.to_string())?;
+        let expected = (limit as usize).min(fixture_rows().len());
+        check(result.rows.len() == expected, || format!("returned {} rows, expected {}", result.rows.len(), expected))?;
+        let all: Vec<i64> = (1..=fixture_rows().len() as i64).collect();
+        let returned = ids(&result)?;
+        check(returned.iter().all(|id| all.contains(id)), || format!("returned ids {:?} not in the table", returned))
+    }
+}
+
+fn check(ok: bool, failure: impl FnOnce() -> String) -> Result<(), String> {
+    if ok { Ok(()) } else { Err(failure()) }
+}
+
+/// Connector failures surface as connector errors, which the engine maps to client errors
+fn expect_connector_error(error: &NirvError) -> Result<(), String> {
+    check(matches!(error, NirvError::Connector(_)), || format!("failed with a non-connector error: {}", error))
+}
+
+fn fixture_result() -> QueryResult {
+    let mut result = QueryResult::new();
+    result.columns = vec![
+        ColumnMetadata { name: "id".to_string(), data_type: DataType::Integer, nullable: false },
+        ColumnMetadata { name: "name".to_string(), data_type: DataType::Text, nullable: false },
+        ColumnMetadata { name: "score".to_string(), data_type: DataType::Float, nullable: true },
+    ];
+    result.rows = fixture_rows();
+    result
+}
+
+/// The id of every row, read as an integer whichever type the connector returned it as
+fn ids(result: &QueryResult) -> Result<Vec<i64>, String> {
+    let index = IdentifierCase::default().resolve("id", result.columns.iter().map(|col| col.name.as_str()))
+        .ok_or_else(|| "the result has no id column".to_string())?;
+    result.rows.iter().map(|row| match row.values.get(index) {
+        Some(Value::Integer(id)) => Ok(*id),
+        Some(Value::Float(id)) if id.fract() == 0.0 => Ok(*id as i64),
+        Some(Value::Text(id)) => id.parse().map_err(|_| format!("id '{}' is not a number", id)),
+        other => Err(format!("id {:?} is not a number", other)),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_predicates_keep_rows() {
+        // Every predicate keeps some rows and drops others, so ignoring it is noticed
+        for predicate in ConnectorTestKit::predicates() {
+            let kept = filter_rows(fixture_result(), std::slice::from_ref(&predicate), IdentifierCase::default(), Collation::default()).unwrap();
+            assert!(!kept.rows.is_empty() && kept.rows.len() < fixture_rows().len(), "{}", describe_predicate(&predicate));
+        }
+    }
+}
@@ -1,16 +1,44 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::connectors::network::RetryPolicy;
 use crate::utils::{
-    types::{ConnectorType, ConnectorQuery, QueryResult, Schema},
-    error::NirvResult,
+    types::{ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, Predicate, PredicateOperator, Row, Sample},
+    error::{ConnectorError, NirvResult},
+    json_path::JsonExtract,
+    regex_function::RegexFunction,
+    hash_function::HashFunction,
+    date_function::DateTrunc,
+    config::PoolConfig,
 };
 
 /// Configuration for connector initialization
 #[derive(Debug, Clone)]
 pub struct ConnectorInitConfig {
     pub connection_params: HashMap<String, String>,
+    /// Fallback for any timeout not set explicitly
     pub timeout_seconds: Option<u64>,
     pub max_connections: Option<u32>,
+    /// Time allowed for DNS resolution and establishing a connection
+    pub connect_timeout_seconds: Option<u64>,
+    /// Time allowed to wait for query results
+    pub read_timeout_seconds: Option<u64>,
+    /// Time allowed for statements that return no rows (BEGIN, COMMIT, ...)
+    pub write_timeout_seconds: Option<u64>,
+    /// Retry policy for establishing connections
+    pub retry_policy: RetryPolicy,
+    /// Check pooled connections with a cheap query before handing them out again
+    pub validate_connections: bool,
+    /// Time allowed for that check before the connection is discarded
+    pub validation_timeout_seconds: Option<u64>,
+    /// Pooled connections older than this are closed instead of reused
+    pub max_connection_lifetime_seconds: Option<u64>,
+    /// Pooled connections unused for longer than this are closed instead of reused
+    pub idle_timeout_seconds: Option<u64>,
+    /// Rows read per round trip by connectors that fetch results incrementally; unset,
+    /// a result is read in one go
+    pub fetch_size: Option<u32>,
 }
 
 impl ConnectorInitConfig {
@@ -20,6 +48,15 @@ impl ConnectorInitConfig {
             connection_params: HashMap::new(),
             timeout_seconds: Some(30),
             max_connections: Some(10),
+            connect_timeout_seconds: None,
+            read_timeout_seconds: None,
+            write_timeout_seconds: None,
+            retry_policy: RetryPolicy::none(),
+            validate_connections: true,
+            validation_timeout_seconds: None,
+            max_connection_lifetime_seconds: None,
+            idle_timeout_seconds: None,
+            fetch_size: None,
         }
     }
     
@@ -40,6 +77,101 @@ impl ConnectorInitConfig {
         self.max_connections = Some(max);
         self
     }
+    
+    /// Set connect timeout in seconds
+    pub fn with_connect_timeout(mut self, seconds: u64) -> Self {
+        self.connect_timeout_seconds = Some(seconds);
+        self
+    }
+    
+    /// Set read timeout in seconds
+    pub fn with_read_timeout(mut self, seconds: u64) -> Self {
+        self.read_timeout_seconds = Some(seconds);
+        self
+    }
+    
+    /// Set write timeout in seconds
+    pub fn with_write_timeout(mut self, seconds: u64) -> Self {
+        self.write_timeout_seconds = Some(seconds);
+        self
+    }
+    
+    /// Set the connection retry policy
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+    
+    /// Enable or disable checking pooled connections before reuse
+    pub fn with_connection_validation(mut self, validate: bool) -> Self {
+        self.validate_connections = validate;
+        self
+    }
+    
+    /// Set the connection check timeout in seconds
+    pub fn with_validation_timeout(mut self, seconds: u64) -> Self {
+        self.validation_timeout_seconds = Some(seconds);
+        self
+    }
+    
+    /// Set the longest a pooled connection is reused, in seconds
+    pub fn with_max_connection_lifetime(mut self, seconds: u64) -> Self {
+        self.max_connection_lifetime_seconds = Some(seconds);
+        self
+    }
+    
+    /// Set the longest a pooled connection may sit unused, in seconds
+    pub fn with_idle_timeout(mut self, seconds: u64) -> Self {
+        self.idle_timeout_seconds = Some(seconds);
+        self
+    }
+    
+    /// Set the rows read per round trip
+    pub fn with_fetch_size(mut self, rows: u32) -> Self {
+        self.fetch_size = Some(rows);
+        self
+    }
+    
+    /// Take the pool size and recycling settings of a configured connector
+    pub fn with_pool_config(mut self, pool: &PoolConfig) -> Self {
+        self.max_connections = Some(pool.max_connections);
+        self.connect_timeout_seconds = Some(pool.connection_timeout);
+        self.validate_connections = pool.validate_connections;
+        self.validation_timeout_seconds = Some(pool.validation_timeout);
+        self.max_connection_lifetime_seconds = pool.max_lifetime;
+        self.idle_timeout_seconds = Some(pool.idle_timeout);
+        self
+    }
+    
+    /// Effective connect timeout
+    pub fn connect_timeout(&self) -> Duration {
+        self.resolve_timeout(self.connect_timeout_seconds)
+    }
+    
+    /// Effective read timeout
+    pub fn read_timeout(&self) -> Duration {
+        self.resolve_timeout(self.read_timeout_seconds)
+    }
+    
+    /// Effective write timeout
+    pub fn write_timeout(&self) -> Duration {
+        self.resolve_timeout(self.write_timeout_seconds)
+    }
+    
+    /// Effective connection check timeout, five seconds unless set
+    pub fn validation_timeout(&self) -> Duration {
+        Duration::from_secs(self.validation_timeout_seconds.unwrap_or(5))
+    }
+    
+    /// Whether a pooled connection of the given age, unused for `idle`, should be closed
+    pub fn is_connection_stale(&self, age: Duration, idle: Duration) -> bool {
+        self.max_connection_lifetime_seconds.is_some_and(|seconds| age > Duration::from_secs(seconds))
+            || self.idle_timeout_seconds.is_some_and(|seconds| idle > Duration::from_secs(seconds))
+    }
+    
+    fn resolve_timeout(&self, seconds: Option<u64>) -> Duration {
+        Duration::from_secs(seconds.or(self.timeout_seconds).unwrap_or(30))
+    }
 }
 
 impl Default for ConnectorInitConfig {
@@ -48,6 +180,9 @@ impl Default for ConnectorInitConfig {
     }
 }
 
+/// Receives the rows of a query a batch at a time, with the columns they are in
+pub type RowSink<'a> = dyn FnMut(&[ColumnMetadata], Vec<Row>) -> NirvResult<()> + Send + 'a;
+
 /// Base trait for all data source connectors
 #[async_trait]
 pub trait Connector: Send + Sync {
@@ -57,6 +192,27 @@ pub trait Connector: Send + Sync {
     /// Execute a query against the connected data source
     async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult>;
     
+    /// Execute a query, handing its rows to `on_batch` as they are fetched instead of
+    /// collecting them; the result returned holds no rows. Connectors that cannot fetch
+    /// incrementally hand over every row at once.
+    async fn execute_query_batched(&self, query: ConnectorQuery, on_batch: &mut RowSink<'_>) -> NirvResult<QueryResult> {
+        let mut result = self.execute_query(query).await?;
+        on_batch(&result.columns, std::mem::take(&mut result.rows))?;
+        Ok(result)
+    }
+    
+    /// Count the rows matching a `COUNT(*)` query's predicates without fetching them, where
+    /// the source can. `None` means the rows have to be fetched and counted instead.
+    async fn count_rows(&self, _query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        Ok(None)
+    }
+    
+    /// Drop what the connector cached for a data object, so the next query reads it from the
+    /// source. Gives the number of cache entries dropped; connectors without a cache drop none.
+    async fn refresh(&self, _object_name: &str) -> NirvResult<u64> {
+        Ok(0)
+    }
+    
     /// Retrieve schema information for a specific data object
     async fn get_schema(&self, object_name: &str) -> NirvResult<Schema>;
     
@@ -71,9 +227,112 @@ pub trait Connector: Send + Sync {
     
     /// Check if the connector is currently connected
     fn is_connected(&self) -> bool;
+
+    /// Why a connected connector is failing in the background, like a feed it can no
+    /// longer read, or None while it is healthy
+    fn health_error(&self) -> Option<String> {
+        None
+    }
     
     /// Get connector-specific capabilities
     fn get_capabilities(&self) -> ConnectorCapabilities;
+
+    /// Whether the connector evaluates `json_extract` projections and predicates itself;
+    /// otherwise the executor evaluates them over the fetched rows
+    fn supports_json_functions(&self) -> bool {
+        false
+    }
+
+    /// Whether the connector evaluates `REGEXP` predicates itself
+    fn supports_regex_predicates(&self) -> bool {
+        false
+    }
+
+    /// Whether the connector evaluates a predicate itself. Predicates it cannot evaluate
+    /// are left out of its queries and applied to the returned rows instead. Regular
+    /// expression and hash functions are always evaluated by the engine.
+    fn supports_predicate(&self, predicate: &Predicate) -> bool {
+        (predicate.operator != PredicateOperator::Regexp || self.supports_regex_predicates())
+            && RegexFunction::parse(&predicate.column).is_none()
+            && HashFunction::parse(&predicate.column).is_none()
+            && DateTrunc::parse(&predicate.column).is_none()
+            && (JsonExtract::parse(&predicate.column).is_none() || self.supports_json_functions())
+    }
+
+    /// Whether the connector samples rows itself when a query carries a sample;
+    /// otherwise the engine samples the rows it returns
+    fn supports_sample(&self, _sample: &Sample) -> bool {
+        false
+    }
+
+    /// List the data objects this connector exposes, for catalog browsing
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Create a data object with the given schema, failing if it already exists
+    async fn create_table(&self, object_name: &str, _schema: &Schema) -> NirvResult<()> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("{:?} connector cannot create '{}'", self.get_connector_type(), object_name)
+        ).into())
+    }
+
+    /// Append rows, whose values follow the given columns, to a data object and return
+    /// the number of rows written
+    async fn insert_rows(&self, object_name: &str, _columns: &[ColumnMetadata], _rows: &[Row]) -> NirvResult<u64> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("{:?} connector cannot write to '{}'", self.get_connector_type(), object_name)
+        ).into())
+    }
+
+    /// Begin a transaction pinned to a single backend connection
+    async fn begin_transaction(&self) -> NirvResult<u64> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("{:?} connector does not support transactions", self.get_connector_type())
+        ).into())
+    }
+
+    /// Execute a query inside an open transaction
+    async fn execute_in_transaction(&self, transaction_id: u64, _query: ConnectorQuery) -> NirvResult<QueryResult> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("Transaction {} is not open on this connector", transaction_id)
+        ).into())
+    }
+
+    /// Commit an open transaction and release its connection
+    async fn commit_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("Transaction {} is not open on this connector", transaction_id)
+        ).into())
+    }
+
+    /// Roll back an open transaction and release its connection
+    async fn rollback_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("Transaction {} is not open on this connector", transaction_id)
+        ).into())
+    }
+
+    /// Prepare an open transaction for two-phase commit under a global identifier
+    async fn prepare_transaction(&self, _transaction_id: u64, _global_id: &str) -> NirvResult<()> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("{:?} connector does not support two-phase commit", self.get_connector_type())
+        ).into())
+    }
+
+    /// Commit a transaction previously prepared under a global identifier
+    async fn commit_prepared(&self, global_id: &str) -> NirvResult<()> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("Prepared transaction '{}' is not known to this connector", global_id)
+        ).into())
+    }
+
+    /// Roll back a transaction previously prepared under a global identifier
+    async fn rollback_prepared(&self, global_id: &str) -> NirvResult<()> {
+        Err(ConnectorError::UnsupportedOperation(
+            format!("Prepared transaction '{}' is not known to this connector", global_id)
+        ).into())
+    }
 }
 
 /// Capabilities supported by a connector
@@ -100,9 +359,14 @@ impl Default for ConnectorCapabilities {
     }
 }
 
-/// Registry for managing connector instances
+/// Registry for managing connector instances.
+///
+/// Connectors are held behind `Arc`, so a handle taken with [`ConnectorRegistry::get_shared`]
+/// stays valid while the registry changes and many queries can run against the same
+/// instance at once. Cloning the registry shares its connectors rather than copying them.
+#[derive(Clone)]
 pub struct ConnectorRegistry {
-    connectors: HashMap<String, Box<dyn Connector>>,
+    connectors: HashMap<String, Arc<dyn Connector>>,
 }
 
 impl ConnectorRegistry {
@@ -115,6 +379,12 @@ impl ConnectorRegistry {
     
     /// Register a connector with a given name
     pub fn register(&mut self, name: String, connector: Box<dyn Connector>) -> NirvResult<()> {
+        self.register_shared(name, Arc::from(connector))
+    }
+    
+    /// Register a connector instance that may also be used elsewhere. Shared connectors
+    /// are connected before they are registered, since connecting needs exclusive access.
+    pub fn register_shared(&mut self, name: String, connector: Arc<dyn Connector>) -> NirvResult<()> {
         if self.connectors.contains_key(&name) {
             return Err(crate::utils::error::NirvError::Dispatcher(
                 crate::utils::error::DispatcherError::RegistrationFailed(
@@ -132,9 +402,28 @@ impl ConnectorRegistry {
         self.connectors.get(name).map(|c| c.as_ref())
     }
     
-    /// Get a mutable reference to a connector by name
-    pub fn get_mut(&mut self, name: &str) -> Option<&mut Box<dyn Connector>> {
-        self.connectors.get_mut(name)
+    /// Get a shared handle to a connector by name
+    pub fn get_shared(&self, name: &str) -> Option<Arc<dyn Connector>> {
+        self.connectors.get(name).cloned()
+    }
+    
+    /// Get a mutable reference to a connector by name. Only available while no other
+    /// handle to the connector exists.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut (dyn Connector + 'static)> {
+        self.connectors.get_mut(name).and_then(Arc::get_mut)
+    }
+    
+    /// Swap in a new instance under an existing name, for example after reconnecting.
+    /// Queries still running against the previous instance finish on it.
+    pub fn replace(&mut self, name: &str, connector: Arc<dyn Connector>) -> NirvResult<Arc<dyn Connector>> {
+        match self.connectors.get_mut(name) {
+            Some(current) => Ok(std::mem::replace(current, connector)),
+            None => Err(crate::utils::error::NirvError::Dispatcher(
+                crate::utils::error::DispatcherError::RegistrationFailed(
+                    format!("Connector '{}' is not registered", name)
+                )
+            )),
+        }
     }
     
     /// List all registered connector names
@@ -143,7 +432,7 @@ impl ConnectorRegistry {
     }
     
     /// Remove a connector from the registry
-    pub fn unregister(&mut self, name: &str) -> Option<Box<dyn Connector>> {
+    pub fn unregister(&mut self, name: &str) -> Option<Arc<dyn Connector>> {
         self.connectors.remove(name)
     }
     
@@ -196,6 +485,43 @@ mod tests {
         assert_eq!(config.max_connections, Some(20));
     }
 
+    #[test]
+    fn test_connector_init_config_timeouts() {
+        let config = ConnectorInitConfig::new()
+            .with_timeout(20)
+            .with_connect_timeout(5)
+            .with_read_timeout(60);
+        
+        assert_eq!(config.connect_timeout(), Duration::from_secs(5));
+        assert_eq!(config.read_timeout(), Duration::from_secs(60));
+        // Unset timeouts fall back to the general timeout
+        assert_eq!(config.write_timeout(), Duration::from_secs(20));
+        assert_eq!(config.retry_policy, RetryPolicy::none());
+    }
+
+    #[test]
+    fn test_connector_init_config_pool_recycling() {
+        let config = ConnectorInitConfig::new();
+        assert!(config.validate_connections);
+        assert_eq!(config.validation_timeout(), Duration::from_secs(5));
+        assert!(!config.is_connection_stale(Duration::from_secs(86_400), Duration::from_secs(86_400)));
+
+        let config = ConnectorInitConfig::new()
+            .with_validation_timeout(2)
+            .with_max_connection_lifetime(3600)
+            .with_idle_timeout(600);
+        assert_eq!(config.validation_timeout(), Duration::from_secs(2));
+        assert!(!config.is_connection_stale(Duration::from_secs(3000), Duration::from_secs(10)));
+        assert!(config.is_connection_stale(Duration::from_secs(3601), Duration::from_secs(10)));
+        assert!(config.is_connection_stale(Duration::from_secs(700), Duration::from_secs(601)));
+
+        let pool = PoolConfig { max_lifetime: None, validate_connections: false, ..PoolConfig::default() };
+        let config = ConnectorInitConfig::new().with_pool_config(&pool);
+        assert_eq!(config.max_connections, Some(10));
+        assert!(!config.validate_connections);
+        assert_eq!((config.max_connection_lifetime_seconds, config.idle_timeout_seconds), (None, Some(600)));
+    }
+
     #[test]
     fn test_connector_init_config_default() {
         let config = ConnectorInitConfig::default();
@@ -266,6 +592,8 @@ mod tests {
                 columns: vec![],
                 primary_key: None,
                 indexes: vec![],
+                column_details: HashMap::new(),
+                foreign_keys: Vec::new(),
             })
         }
 
@@ -291,6 +619,19 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_connector_transactions_unsupported_by_default() {
+        let connector = TestConnector::new(ConnectorType::Mock);
+
+        let result = connector.begin_transaction().await;
+        assert!(matches!(
+            result,
+            Err(crate::utils::error::NirvError::Connector(ConnectorError::UnsupportedOperation(_)))
+        ));
+        assert!(connector.commit_transaction(1).await.is_err());
+        assert!(connector.rollback_transaction(1).await.is_err());
+    }
+
     #[test]
     fn test_connector_registry_register_and_get() {
         let mut registry = ConnectorRegistry::new();
@@ -350,6 +691,25 @@ mod tests {
         assert!(non_existent.is_none());
     }
 
+    #[test]
+    fn test_connector_registry_shared_handles() {
+        let mut registry = ConnectorRegistry::new();
+        registry.register("test_connector".to_string(), Box::new(TestConnector::new(ConnectorType::Mock))).unwrap();
+        
+        // A shared handle outlives the registry entry and blocks exclusive access
+        let shared = registry.get_shared("test_connector").unwrap();
+        assert!(registry.get_mut("test_connector").is_none());
+        let clone = registry.clone();
+        assert!(Arc::ptr_eq(&shared, &clone.get_shared("test_connector").unwrap()));
+        
+        // Replacing swaps the instance without affecting existing handles
+        let previous = registry.replace("test_connector", Arc::new(TestConnector::new(ConnectorType::PostgreSQL))).unwrap();
+        assert!(Arc::ptr_eq(&shared, &previous));
+        assert_eq!(shared.get_connector_type(), ConnectorType::Mock);
+        assert_eq!(registry.get("test_connector").unwrap().get_connector_type(), ConnectorType::PostgreSQL);
+        assert!(registry.replace("non_existent", previous).is_err());
+    }
+
     #[test]
     fn test_connector_registry_unregister() {
         let mut registry = ConnectorRegistry::new();
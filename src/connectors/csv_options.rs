@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::{ByteRecord, ReaderBuilder, StringRecord};
+
+use crate::utils::{
+    row_buffer::RowBuffer,
+    time_zone::format_utc,
+    types::{ColumnMetadata, DataType, Row, Value},
+    error::{ConnectorError, NirvResult},
+};
+
+/// Character encoding of a text file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, where every byte is the character with the same code point
+    Latin1,
+}
+
+impl TextEncoding {
+    pub fn parse(name: &str) -> NirvResult<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(TextEncoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(TextEncoding::Latin1),
+            other => Err(ConnectorError::UnsupportedOperation(
+                format!("Unsupported encoding '{}'; expected utf-8 or latin1", other)
+            ).into()),
+        }
+    }
+
+    pub fn decode(&self, bytes: Vec<u8>) -> NirvResult<String> {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8(bytes).map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("File is not valid UTF-8 ({}); set encoding=latin1 for Latin-1 files", e)
+            ).into()),
+            TextEncoding::Latin1 => Ok(bytes.into_iter().map(char::from).collect()),
+        }
+    }
+}
+
+/// How CSV text is split into fields. Set for all files of a connector with connection
+/// parameters, and for one source with options after `?`, as in
+/// `source('file.export.csv?delimiter=;&has_headers=false')`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub quote: u8,
+    /// Whether the first record names the columns; otherwise they are column1, column2, ...
+    pub has_headers: bool,
+    pub encoding: TextEncoding,
+    /// Field text read as NULL, in addition to empty fields
+    pub null_token: Option<String>,
+    /// Character between the integer and fractional digits of numbers, as `,` in `1.234,56`
+    pub decimal_separator: char,
+    /// Character grouping the integer digits of numbers in threes, as `.` in `1.234,56`
+    pub thousands_separator: Option<char>,
+    /// chrono formats such as `%d/%m/%Y` that fields are tried against, in order, to read
+    /// them as dates or, when the format has a time, as date-times
+    pub date_formats: Vec<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            has_headers: true,
+            encoding: TextEncoding::Utf8,
+            null_token: None,
+            decimal_separator: '.',
+            thousands_separator: None,
+            date_formats: Vec::new(),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Connection parameters and source options that set CSV options
+    pub const KEYS: [&'static str; 10] = [
+        "delimiter", "quote", "has_headers", "has_header", "encoding", "null_token",
+        "decimal_separator", "thousands_separator", "date_formats", "date_format",
+    ];
+
+    /// These options overridden by the connection parameters named in `KEYS`
+    pub fn with_params(&self, params: &HashMap<String, String>) -> NirvResult<Self> {
+        let mut options = self.clone();
+        for key in Self::KEYS {
+            if let Some(value) = params.get(key) {
+                options.set(key, value)?;
+            }
+        }
+        options.check_separators()?;
+        Ok(options)
+    }
+
+    /// These options overridden by `key=value` pairs separated by `&`
+    pub fn with_source_options(&self, source_options: &str) -> NirvResult<Self> {
+        let mut options = self.clone();
+        for pair in source_options.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                format!("Invalid source option '{}': expected key=value", pair)
+            ))?;
+            options.set(key.trim(), value.trim())?;
+        }
+        options.check_separators()?;
+        Ok(options)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> NirvResult<()> {
+        let value = value.trim_matches(|c| c == '\'' || c == '"');
+        match key {
+            "delimiter" => self.delimiter = Self::single_byte(key, value)?,
+            "quote" => self.quote = Self::single_byte(key, value)?,
+            "has_headers" | "has_header" => {
+                self.has_headers = value.parse().map_err(|_| ConnectorError::QueryExecutionFailed(
+                    format!("Invalid has_headers '{}': expected true or false", value)
+                ))?;
+            }
+            "encoding" => self.encoding = TextEncoding::parse(value)?,
+            "null_token" => self.null_token = Some(value.to_string()),
+            "decimal_separator" => self.decimal_separator = Self::single_char(key, value)?,
+            "thousands_separator" => self.thousands_separator = match value {
+                "" | "none" => None,
+                _ => Some(Self::single_char(key, value)?),
+            },
+            "date_formats" | "date_format" => {
+                self.date_formats = value.split('|').map(str::trim).filter(|format| !format.is_empty()).map(String::from).collect();
+                if let Some(format) = self.date_formats.iter().find(|format| StrftimeItems::new(format).any(|item| item == Item::Error)) {
+                    return Err(ConnectorError::QueryExecutionFailed(
+                        format!("Invalid date format '{}'", format)
+                    ).into());
+                }
+            }
+            other => return Err(ConnectorError::QueryExecutionFailed(
+                format!("Unknown CSV option '{}'", other)
+            ).into()),
+        }
+        Ok(())
+    }
+
+    /// Whether numbers are read as written in Rust, with only `.` as the decimal separator
+    fn plain_numbers(&self) -> bool {
+        self.decimal_separator == '.' && self.thousands_separator.is_none()
+    }
+
+    /// A field as an integer or float, under these separators. Grouped digits must come in
+    /// threes, so `1.5` is not read as fifteen when `.` groups thousands.
+    fn parse_number(&self, field: &str) -> Option<Value> {
+        if self.plain_numbers() {
+            return field.parse::<i64>().map(Value::Integer).ok()
+                .or_else(|| field.parse::<f64>().map(Value::Float).ok());
+        }
+        if self.decimal_separator != '.' && self.thousands_separator != Some('.') && field.contains('.') {
+            return None;
+        }
+        let (integer, fraction) = match field.split_once(self.decimal_separator) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (field, None),
+        };
+        let integer = match self.thousands_separator {
+            Some(separator) if integer.contains(separator) => ungroup(integer, separator)?,
+            _ => integer.to_string(),
+        };
+        match fraction {
+            Some(fraction) => format!("{}.{}", integer, fraction).parse::<f64>().map(Value::Float).ok(),
+            None => integer.parse::<i64>().map(Value::Integer).ok()
+                .or_else(|| integer.parse::<f64>().map(Value::Float).ok()),
+        }
+    }
+
+    /// A field as a date or date-time by the first of the date formats it matches
+    pub(crate) fn parse_date(&self, field: &str) -> Option<Value> {
+        self.date_formats.iter().find_map(|format| {
+            NaiveDateTime::parse_from_str(field, format).map(|time| Value::DateTime(format_utc(time)))
+                .or_else(|_| NaiveDate::parse_from_str(field, format).map(|date| Value::Date(date.format("%Y-%m-%d").to_string())))
+                .ok()
+        })
+    }
+
+    /// A field typed from its content
+    pub(crate) fn field_value(&self, field: &str) -> Value {
+        if field.is_empty() || self.null_token.as_deref() == Some(field) {
+            Value::Null
+        } else if let Some(number) = self.parse_number(field) {
+            number
+        } else if let Ok(bool_val) = field.parse::<bool>() {
+            Value::Boolean(bool_val)
+        } else if let Some(date) = self.parse_date(field) {
+            date
+        } else {
+            Value::Text(field.to_string())
+        }
+    }
+
+    fn check_separators(&self) -> NirvResult<()> {
+        if self.thousands_separator == Some(self.decimal_separator) {
+            return Err(ConnectorError::QueryExecutionFailed(
+                format!("The decimal and thousands separators are both '{}'", self.decimal_separator)
+            ).into());
+        }
+        Ok(())
+    }
+
+    fn single_char(key: &str, value: &str) -> NirvResult<char> {
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            _ if value == "space" => Ok(' '),
+            (Some(c), None) if !c.is_ascii_digit() => Ok(c),
+            _ => Err(ConnectorError::QueryExecutionFailed(
+                format!("Invalid {} '{}': expected a single character other than a digit", key, value)
+            ).into()),
+        }
+    }
+
+    fn single_byte(key: &str, value: &str) -> NirvResult<u8> {
+        match value {
+            "\\t" | "tab" => Ok(b'\t'),
+            _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+            _ => Err(ConnectorError::QueryExecutionFailed(
+                format!("Invalid {} '{}': expected a single ASCII character", key, value)
+            ).into()),
+        }
+    }
+}
+
+/// Digits grouped in threes by a separator, as `-1.234.567`, without the separators
+fn ungroup(integer: &str, separator: char) -> Option<String> {
+    let digits = integer.trim_start_matches(['-', '+']);
+    let mut groups = digits.split(separator);
+    let first = groups.next()?;
+    let grouped = (1..=3).contains(&first.len())
+        && first.bytes().all(|b| b.is_ascii_digit())
+        && groups.all(|group| group.len() == 3 && group.bytes().all(|b| b.is_ascii_digit()));
+    grouped.then(|| integer.replace(separator, ""))
+}
+
+/// Split `identifier?options` into the identifier and its source options. A `?` followed
+/// by no `key=value` pair is a glob wildcard and stays in the identifier.
+pub fn split_source_options(identifier: &str) -> (&str, Option<&str>) {
+    match identifier.rsplit_once('?') {
+        Some((identifier, options)) if options.split_once('=').is_some_and(|(key, _)| !key.contains('/')) => (identifier, Some(options)),
+        _ => (identifier, None),
+    }
+}
+
+/// Parse CSV bytes, typing each field from its content
+pub(crate) fn parse_csv(content: Vec<u8>, options: &CsvOptions) -> NirvResult<(Vec<ColumnMetadata>, Vec<Row>)> {
+    parse_csv_rows(content, options).map(|(columns, rows)| (columns, rows.into_rows()))
+}
+
+/// Parse CSV bytes into a row buffer, reading every record into the same field buffer
+pub(crate) fn parse_csv_rows(content: Vec<u8>, options: &CsvOptions) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
+    let content = options.encoding.decode(content)?;
+    let mut reader = ReaderBuilder::new()
+        .has_headers(options.has_headers)
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .from_reader(content.as_bytes());
+
+    // Get headers; without a header row this is the first record, which still fixes the width
+    let headers = reader.headers()
+        .map_err(|e| ConnectorError::QueryExecutionFailed(
+            format!("Failed to read CSV headers: {}", e)
+        ))?;
+
+    let columns: Vec<ColumnMetadata> = headers.iter()
+        .enumerate()
+        .map(|(index, header)| ColumnMetadata {
+            name: if options.has_headers { header.to_string() } else { format!("column{}", index + 1) },
+            data_type: DataType::Text, // Default to text, could be improved with type inference
+            nullable: true,
+        })
+        .collect();
+
+    let mut rows = RowBuffer::new(columns.len());
+    let mut record = StringRecord::new();
+    while reader.read_record(&mut record)
+        .map_err(|e| ConnectorError::QueryExecutionFailed(
+            format!("Failed to read CSV record: {}", e)
+        ))?
+    {
+        rows.push_row(record.iter().map(|field| options.field_value(field)));
+    }
+
+    Ok((columns, rows))
+}
+
+/// Number of records in CSV content, counted without converting their fields. Quoted fields
+/// spanning lines count once, as they are read.
+pub(crate) fn count_csv_records(content: Vec<u8>, options: &CsvOptions) -> NirvResult<u64> {
+    let content = options.encoding.decode(content)?;
+    let mut reader = ReaderBuilder::new()
+        .has_headers(options.has_headers)
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .from_reader(content.as_bytes());
+
+    let mut record = ByteRecord::new();
+    let mut count = 0;
+    while reader.read_byte_record(&mut record)
+        .map_err(|e| ConnectorError::QueryExecutionFailed(
+            format!("Failed to read CSV record: {}", e)
+        ))?
+    {
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_csv_records() {
+        let content = b"id,note\n1,\"two\nlines\"\n2,plain\n".to_vec();
+        assert_eq!(count_csv_records(content.clone(), &CsvOptions::default()).unwrap(), 2);
+        let options = CsvOptions { has_headers: false, ..CsvOptions::default() };
+        assert_eq!(count_csv_records(content, &options).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_locale_numbers_and_dates() {
+        let (identifier, options) = split_source_options("export.csv?delimiter=;&decimal_separator=,&thousands_separator=.&date_formats=%d/%m/%Y|%d.%m.%Y %H:%M");
+        assert_eq!(identifier, "export.csv");
+        let options = CsvOptions::default().with_source_options(options.unwrap()).unwrap();
+
+        let (_, rows) = parse_csv("amount;count;day;at;code\n1.234,56;-12.345.678;31/12/2024;01.02.2024 13:45;1.5\n".as_bytes().to_vec(), &options).unwrap();
+        assert_eq!(rows[0].values, vec![
+            Value::Float(1234.56),
+            Value::Integer(-12345678),
+            Value::Date("2024-12-31".to_string()),
+            Value::DateTime("2024-02-01 13:45:00".to_string()),
+            Value::Text("1.5".to_string()),
+        ]);
+
+        // Without the options the same fields stay text
+        let (_, rows) = parse_csv("amount;day\n\"1.234,56\";31/12/2024\n".as_bytes().to_vec(), &CsvOptions { delimiter: b';', ..CsvOptions::default() }).unwrap();
+        assert_eq!(rows[0].values, vec![Value::Text("1.234,56".to_string()), Value::Text("31/12/2024".to_string())]);
+
+        assert!(CsvOptions::default().with_source_options("decimal_separator=,&thousands_separator=,").is_err());
+        assert!(CsvOptions::default().with_source_options("date_formats=%Q").is_err());
+        assert!(CsvOptions::default().with_source_options("decimal_separator=ab").is_err());
+    }
+
+    #[test]
+    fn test_csv_options() {
+        let mut params = HashMap::new();
+        params.insert("delimiter".to_string(), ";".to_string());
+        params.insert("null_token".to_string(), "NA".to_string());
+        let options = CsvOptions::default().with_params(&params).unwrap()
+            .with_source_options("has_headers=false&encoding=latin1").unwrap();
+
+        let (columns, rows) = parse_csv(b"1;Jos\xe9;NA\n2;\"a;b\";3\n".to_vec(), &options).unwrap();
+        assert_eq!(columns.iter().map(|column| column.name.as_str()).collect::<Vec<_>>(), vec!["column1", "column2", "column3"]);
+        assert_eq!(rows[0].values, vec![Value::Integer(1), Value::Text("José".to_string()), Value::Null]);
+        assert_eq!(rows[1].values[1], Value::Text("a;b".to_string()));
+
+        assert_eq!(split_source_options("data.csv?delimiter=|"), ("data.csv", Some("delimiter=|")));
+        assert_eq!(split_source_options("data?.csv"), ("data?.csv", None));
+        assert!(options.with_source_options("delimiter=;;").is_err());
+        assert!(options.with_source_options("colour=red").is_err());
+        assert!(parse_csv(b"name\nJos\xe9\n".to_vec(), &CsvOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::connectors::file_snapshot::hex_digest;
+use crate::utils::error::{ConnectorError, NirvResult};
+
+/// Size budget of a disk cache when none is configured
+pub const DEFAULT_CACHE_MAX_MB: u64 = 256;
+
+/// An entry read back from a disk cache
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskCacheEntry {
+    pub key: String,
+    pub data: JsonValue,
+    pub stored_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredEntry {
+    key: String,
+    stored_at: DateTime<Utc>,
+    data: JsonValue,
+}
+
+#[derive(Debug)]
+struct EntryMeta {
+    key: String,
+    size: u64,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// Entries by file name
+    entries: HashMap<String, EntryMeta>,
+    total_bytes: u64,
+    clock: u64,
+}
+
+/// Cache entries kept as one JSON file each under a directory, so they outlive a restart.
+/// Once the files exceed the size budget the least recently used are deleted; use is
+/// recorded in the files' modification times, which order them again on the next start.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    state: Mutex<CacheState>,
+}
+
+impl DiskCache {
+    /// Open the cache in a directory, creating it if needed. Files that are not cache
+    /// entries are removed, and entries past the budget evicted.
+    pub fn open<P: AsRef<Path>>(dir: P, max_bytes: u64) -> NirvResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| ConnectorError::ConnectionFailed(
+            format!("Failed to create cache directory {}: {}", dir.display(), e)
+        ))?;
+        let listing = fs::read_dir(&dir).map_err(|e| ConnectorError::ConnectionFailed(
+            format!("Failed to read cache directory {}: {}", dir.display(), e)
+        ))?;
+
+        let mut found = Vec::new();
+        for path in listing.filter_map(Result::ok).map(|entry| entry.path()) {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => {}
+                // Left by a write that never finished
+                Some("partial") => {
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+                _ => continue,
+            }
+            match read_entry(&path) {
+                Some(stored) => {
+                    let metadata = fs::metadata(&path).ok();
+                    let size = metadata.as_ref().map_or(0, |metadata| metadata.len());
+                    let used = metadata.and_then(|metadata| metadata.modified().ok()).unwrap_or(SystemTime::UNIX_EPOCH);
+                    found.push((used, file_name(&path), stored.key, size));
+                }
+                None => {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        found.sort();
+
+        let mut state = CacheState::default();
+        for (_, name, key, size) in found {
+            state.clock += 1;
+            state.total_bytes += size;
+            state.entries.insert(name, EntryMeta { key, size, last_used: state.clock });
+        }
+        let cache = Self { dir, max_bytes, state: Mutex::new(state) };
+        cache.evict(&mut cache.state.lock().unwrap());
+        Ok(cache)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The entry stored under a key, marking it used
+    pub fn get(&self, key: &str) -> Option<DiskCacheEntry> {
+        let name = entry_name(key);
+        let path = self.dir.join(&name);
+        let stored = read_entry(&path).filter(|stored| stored.key == key)?;
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        if let Some(meta) = state.entries.get_mut(&name) {
+            meta.last_used = clock;
+        }
+        if let Ok(file) = fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(DiskCacheEntry { key: stored.key, data: stored.data, stored_at: stored.stored_at })
+    }
+
+    /// Store an entry, returning the keys of the entries evicted to make room. Entries
+    /// larger than the whole budget are not stored.
+    pub fn insert(&self, key: &str, data: &JsonValue) -> Vec<String> {
+        let stored = StoredEntry { key: key.to_string(), stored_at: Utc::now(), data: data.clone() };
+        let Ok(content) = serde_json::to_vec(&stored) else {
+            return Vec::new();
+        };
+        let size = content.len() as u64;
+        if size > self.max_bytes {
+            return Vec::new();
+        }
+
+        let name = entry_name(key);
+        let path = self.dir.join(&name);
+        // Written beside the entry and renamed, so readers never see half a file
+        let partial = self.dir.join(format!("{}.partial", name));
+        if fs::write(&partial, &content).and_then(|_| fs::rename(&partial, &path)).is_err() {
+            let _ = fs::remove_file(&partial);
+            return Vec::new();
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let meta = EntryMeta { key: key.to_string(), size, last_used: state.clock };
+        if let Some(previous) = state.entries.insert(name, meta) {
+            state.total_bytes -= previous.size;
+        }
+        state.total_bytes += size;
+        self.evict(&mut state)
+    }
+
+    /// Forget an entry
+    pub fn remove(&self, key: &str) {
+        let name = entry_name(key);
+        let _ = fs::remove_file(self.dir.join(&name));
+        let mut state = self.state.lock().unwrap();
+        if let Some(meta) = state.entries.remove(&name) {
+            state.total_bytes -= meta.size;
+        }
+    }
+
+    /// Every entry, least recently used first, for warming up a cache in memory
+    pub fn entries(&self) -> Vec<DiskCacheEntry> {
+        let mut names: Vec<(u64, String)> = self.state.lock().unwrap().entries.iter()
+            .map(|(name, meta)| (meta.last_used, name.clone()))
+            .collect();
+        names.sort();
+        names.into_iter()
+            .filter_map(|(_, name)| read_entry(&self.dir.join(name)))
+            .map(|stored| DiskCacheEntry { key: stored.key, data: stored.data, stored_at: stored.stored_at })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes the entries take on disk
+    pub fn size_bytes(&self) -> u64 {
+        self.state.lock().unwrap().total_bytes
+    }
+
+    /// Delete the least recently used entries until the rest fit the budget
+    fn evict(&self, state: &mut CacheState) -> Vec<String> {
+        let mut evicted = Vec::new();
+        while state.total_bytes > self.max_bytes {
+            let Some(name) = state.entries.iter().min_by_key(|(_, meta)| meta.last_used).map(|(name, _)| name.clone()) else {
+                break;
+            };
+            let meta = state.entries.remove(&name).expect("entry just found");
+            let _ = fs::remove_file(self.dir.join(&name));
+            state.total_bytes -= meta.size;
+            evicted.push(meta.key);
+        }
+        evicted
+    }
+}
+
+/// Size budget set by a connector's `cache_max_mb` parameter
+pub fn cache_max_bytes(params: &HashMap<String, String>) -> NirvResult<u64> {
+    let megabytes = match params.get("cache_max_mb") {
+        Some(value) => value.parse::<u64>().ok().filter(|mb| *mb > 0)
+            .ok_or_else(|| ConnectorError::ConnectionFailed(
+                format!("Invalid cache_max_mb '{}': expected a positive number", value)
+            ))?,
+        None => DEFAULT_CACHE_MAX_MB,
+    };
+    Ok(megabytes * 1024 * 1024)
+}
+
+fn entry_name(key: &str) -> String {
+    format!("{}.json", hex_digest(key.as_bytes()))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn read_entry(path: &Path) -> Option<StoredEntry> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_entries_survive_reopening_and_evict_least_recently_used() {
+        let dir = TempDir::new().unwrap();
+        let cache = DiskCache::open(dir.path(), 1024 * 1024).unwrap();
+        cache.insert("users", &json!([{ "id": 1 }]));
+        cache.insert("orders", &json!([{ "id": 2 }]));
+        fs::write(dir.path().join("stray.json"), "not an entry").unwrap();
+
+        let reopened = DiskCache::open(dir.path(), 1024 * 1024).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert!(!dir.path().join("stray.json").exists());
+        assert_eq!(reopened.get("users").unwrap().data, json!([{ "id": 1 }]));
+        assert!(reopened.get("missing").is_none());
+
+        // With room for two entries, a third evicts the one used longest ago
+        let entry_size = reopened.size_bytes() / 2;
+        let small = DiskCache::open(dir.path(), entry_size * 2 + entry_size / 2).unwrap();
+        small.get("orders");
+        small.get("users");
+        let evicted = small.insert("items", &json!([{ "id": 3 }]));
+        assert_eq!(evicted, vec!["orders".to_string()]);
+        assert!(small.get("orders").is_none());
+        let keys: Vec<String> = small.entries().into_iter().map(|entry| entry.key).collect();
+        assert_eq!(keys, vec!["users".to_string(), "items".to_string()]);
+
+        small.remove("users");
+        assert_eq!(small.len(), 1);
+    }
+}
@@ -1,24 +1,93 @@
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
 use glob::glob;
-use csv::ReaderBuilder;
 use serde_json;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 
 use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::file_snapshot::{split_snapshot_identifier, SnapshotStore, DEFAULT_SNAPSHOT_RETENTION};
+use crate::connectors::file_index::{FileIndexStore, FileStatistics};
+use crate::connectors::file_watch::{FileChange, FileParseCache, FileWatcher};
+use crate::connectors::disk_cache::{cache_max_bytes, DiskCache};
+use crate::connectors::json_flatten::{infer_json_columns, JsonFlattening};
+use crate::connectors::csv_options::{count_csv_records, parse_csv_rows, split_source_options, CsvOptions};
+use crate::connectors::xml_records::{parse_xml_rows, XmlRecordPath};
+use crate::connectors::fixed_width::{parse_fixed_width_rows, FixedWidthLayout};
+use crate::connectors::parse_pool::{parse_pool, ParsePool};
 use crate::utils::{
     types::{
         ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, DataType, 
         Row, Value, PredicateOperator, PredicateValue
     },
     error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
+    regex_function::regex_matches,
+    row_buffer::RowBuffer,
 };
 
-/// File system connector for CSV, JSON, and other file formats
+/// How a multi-file scan combines files whose columns differ
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaMerge {
+    /// Every file must have the same columns in the same order
+    #[default]
+    Strict,
+    /// The union of all columns, in order of first appearance; files lacking a column
+    /// read it as NULL
+    Union,
+}
+
+impl SchemaMerge {
+    /// Parse a `schema_merge` connection parameter: `strict`, or `union` (also `lenient`)
+    pub fn parse(mode: &str) -> NirvResult<Self> {
+        match mode.to_lowercase().as_str() {
+            "strict" => Ok(SchemaMerge::Strict),
+            "union" | "lenient" => Ok(SchemaMerge::Union),
+            other => Err(ConnectorError::ConnectionFailed(
+                format!("Unknown schema_merge mode '{}'; expected strict or union", other)
+            ).into()),
+        }
+    }
+}
+
+/// How a source's files are read
+#[derive(Debug, Clone)]
+struct FileReadOptions {
+    csv: CsvOptions,
+    /// Elements of XML files that are rows
+    record_path: XmlRecordPath,
+    /// Columns of fixed-width files
+    layout: Option<FixedWidthLayout>,
+}
+
+/// File system connector for CSV, JSON, XML, fixed-width and other file formats
 pub struct FileConnector {
     base_path: Option<PathBuf>,
     supported_extensions: Vec<String>,
     connected: bool,
+    /// Retained file snapshots for `file@date` queries, when enabled
+    snapshots: Option<SnapshotStore>,
+    /// Flattening of nested objects in JSON files, when enabled
+    flattening: Option<JsonFlattening>,
+    identifier_case: IdentifierCase,
+    schema_merge: SchemaMerge,
+    /// How CSV files are read unless a source overrides it
+    csv_options: CsvOptions,
+    /// Elements of XML files that are rows unless a source overrides it
+    xml_record_path: XmlRecordPath,
+    /// Columns of fixed-width files unless a source overrides them
+    fixed_width_layout: Option<FixedWidthLayout>,
+    /// Column statistics of files read, when indexing is enabled
+    index: Option<FileIndexStore>,
+    /// Parsed files kept between queries, when watching is enabled
+    parse_cache: Option<Arc<FileParseCache>>,
+    watcher: Option<(Arc<FileWatcher>, JoinHandle<()>)>,
+    /// Pool files are parsed on; the process-wide pool when unset
+    parse_pool: Option<Arc<ParsePool>>,
 }
 
 impl FileConnector {
@@ -26,11 +95,111 @@ impl FileConnector {
     pub fn new() -> Self {
         Self {
             base_path: None,
-            supported_extensions: vec!["csv".to_string(), "json".to_string()],
+            supported_extensions: vec!["csv".to_string(), "json".to_string(), "xml".to_string(), "dat".to_string()],
             connected: false,
+            snapshots: None,
+            flattening: None,
+            identifier_case: IdentifierCase::default(),
+            schema_merge: SchemaMerge::default(),
+            csv_options: CsvOptions::default(),
+            xml_record_path: XmlRecordPath::default(),
+            fixed_width_layout: None,
+            index: None,
+            parse_cache: None,
+            watcher: None,
+            parse_pool: None,
         }
     }
 
+    /// Parse files on the given pool instead of the process-wide one
+    pub fn with_parse_pool(mut self, parse_pool: Arc<ParsePool>) -> Self {
+        self.parse_pool = Some(parse_pool);
+        self
+    }
+
+    /// Flatten nested objects in JSON files into separate columns
+    pub fn with_flattening(mut self, flattening: JsonFlattening) -> Self {
+        self.flattening = Some(flattening);
+        self
+    }
+
+    /// Combine files whose columns differ in multi-file scans as the given mode allows
+    pub fn with_schema_merge(mut self, schema_merge: SchemaMerge) -> Self {
+        self.schema_merge = schema_merge;
+        self
+    }
+
+    /// Read CSV files with the given options unless a source overrides them
+    pub fn with_csv_options(mut self, csv_options: CsvOptions) -> Self {
+        self.csv_options = csv_options;
+        self
+    }
+
+    /// Read the given elements of XML files as rows unless a source overrides them
+    pub fn with_xml_record_path(mut self, record_path: XmlRecordPath) -> Self {
+        self.xml_record_path = record_path;
+        self
+    }
+
+    /// Read fixed-width files with the given columns unless a source overrides them
+    pub fn with_fixed_width_layout(mut self, layout: FixedWidthLayout) -> Self {
+        self.fixed_width_layout = Some(layout);
+        self
+    }
+
+    /// Store of per-file column statistics used to skip files, if indexing is enabled
+    pub fn index_store(&self) -> Option<&FileIndexStore> {
+        self.index.as_ref()
+    }
+
+    /// Receive the files that change under the base path, if watching is enabled
+    pub fn subscribe(&self) -> Option<broadcast::Receiver<FileChange>> {
+        self.watcher.as_ref().map(|(watcher, _)| watcher.subscribe())
+    }
+
+    /// Parses kept between queries, if watching is enabled
+    pub fn parse_cache(&self) -> Option<&Arc<FileParseCache>> {
+        self.parse_cache.as_ref()
+    }
+
+    /// Snapshot store used for time-travel queries, if snapshotting is enabled
+    pub fn snapshot_store(&self) -> Option<&SnapshotStore> {
+        self.snapshots.as_ref()
+    }
+
+    /// Resolve the files a query reads: a retained snapshot for `file@as_of`
+    /// identifiers, otherwise the live files, snapshotting them when enabled
+    fn resolve_query_files(&self, identifier: &str) -> NirvResult<Vec<PathBuf>> {
+        let Some(store) = self.snapshots.as_ref() else {
+            return self.resolve_file_path(identifier);
+        };
+
+        if let (file_identifier, Some(as_of)) = split_snapshot_identifier(identifier) {
+            if file_identifier.contains('*') || file_identifier.contains('?') {
+                return Err(ConnectorError::UnsupportedOperation(
+                    "Snapshots cannot be queried with file patterns".to_string()
+                ).into());
+            }
+            return Ok(vec![store.resolve(file_identifier, as_of)?]);
+        }
+
+        let paths = self.resolve_file_path(identifier)?;
+        let base_path = self.base_path.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected".to_string()))?;
+        for path in &paths {
+            let relative = path.strip_prefix(base_path).unwrap_or(path);
+            store.record(&relative.to_string_lossy(), path)?;
+        }
+        Ok(paths)
+    }
+
+    /// Whether a path is inside the snapshot or index directory
+    fn is_store_path(&self, path: &Path) -> bool {
+        let snapshot_root = self.snapshots.as_ref().map(SnapshotStore::root);
+        let index_root = self.index.as_ref().map(FileIndexStore::root);
+        snapshot_root.into_iter().chain(index_root).any(|root| path.starts_with(root))
+    }
+
     /// Check if a file extension is supported
     fn is_supported_extension(&self, extension: &str) -> bool {
         self.supported_extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension))
@@ -52,6 +221,8 @@ impl FileConnector {
                 Ok(entries) => {
                     for entry in entries {
                         match entry {
+                            // Snapshot copies and index files are the connector's own
+                            Ok(path) if self.is_store_path(&path) => continue,
                             Ok(path) => {
                                 if path.is_file() {
                                     if let Some(ext) = path.extension() {
@@ -110,63 +281,118 @@ impl FileConnector {
         }
     }
 
+    /// Path of a CSV file to write, which must stay inside the base path
+    fn writable_path(&self, identifier: &str) -> NirvResult<PathBuf> {
+        let base_path = self.base_path.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected".to_string()))?;
+
+        let relative = Path::new(identifier);
+        let escapes = relative.components().any(|component| !matches!(component, std::path::Component::Normal(_)));
+        if escapes || identifier.contains('*') || identifier.contains('?') {
+            return Err(ConnectorError::UnsupportedOperation(
+                format!("Cannot write to '{}': expected a file path inside the base path", identifier)
+            ).into());
+        }
+        if !relative.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv")) {
+            return Err(ConnectorError::UnsupportedOperation(
+                format!("Cannot write to '{}': only CSV files can be written", identifier)
+            ).into());
+        }
+        Ok(base_path.join(relative))
+    }
+
+    /// Columns and rows of a file, from the parse cache when it holds the file's
+    /// current content. Files are read and parsed on the parse pool.
+    async fn read_file(&self, file_path: &Path, options: &FileReadOptions, read_settings: &str) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
+        if let Some(parsed) = self.parse_cache.as_ref().and_then(|cache| cache.get(file_path, read_settings)) {
+            return Ok(parsed);
+        }
+
+        let path = file_path.to_path_buf();
+        let options = options.clone();
+        let flattening = self.flattening.clone();
+        let pool = self.parse_pool.clone().unwrap_or_else(parse_pool);
+        let (columns, rows) = pool.run(move || match path.extension() {
+            Some(ext) => match ext.to_string_lossy().to_lowercase().as_str() {
+                "csv" => Self::parse_csv_file(&path, &options.csv),
+                "json" => Self::parse_json_file(&path, flattening.as_ref()),
+                "xml" => Self::parse_xml_file(&path, &options),
+                "dat" => Self::parse_fixed_width_file(&path, &options),
+                _ => Err(ConnectorError::UnsupportedOperation(
+                    format!("Unsupported file extension: {}", ext.to_string_lossy())
+                ).into()),
+            },
+            None => Err(ConnectorError::UnsupportedOperation(
+                "File has no extension".to_string()
+            ).into()),
+        }).await?;
+
+        if let Some(cache) = &self.parse_cache {
+            cache.insert(file_path, read_settings, &columns, &rows);
+        }
+        Ok((columns, rows))
+    }
+
     /// Parse CSV file and return structured data
-    fn parse_csv_file(&self, file_path: &Path) -> NirvResult<(Vec<ColumnMetadata>, Vec<Row>)> {
-        let content = fs::read_to_string(file_path)
+    fn parse_csv_file(file_path: &Path, options: &CsvOptions) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
+        let content = fs::read(file_path)
             .map_err(|e| ConnectorError::QueryExecutionFailed(
                 format!("Failed to read CSV file: {}", e)
             ))?;
+        parse_csv_rows(content, options)
+    }
 
-        let mut reader = ReaderBuilder::new()
-            .has_headers(true)
-            .from_reader(content.as_bytes());
-
-        // Get headers
-        let headers = reader.headers()
+    /// Parse XML file into a row per record
+    fn parse_xml_file(file_path: &Path, options: &FileReadOptions) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
+        let content = fs::read(file_path)
             .map_err(|e| ConnectorError::QueryExecutionFailed(
-                format!("Failed to read CSV headers: {}", e)
+                format!("Failed to read XML file: {}", e)
             ))?;
+        parse_xml_rows(content, &options.record_path, &options.csv)
+    }
 
-        let columns: Vec<ColumnMetadata> = headers.iter()
-            .map(|header| ColumnMetadata {
-                name: header.to_string(),
-                data_type: DataType::Text, // Default to text, could be improved with type inference
-                nullable: true,
-            })
-            .collect();
-
-        let mut rows = Vec::new();
-        for result in reader.records() {
-            let record = result
-                .map_err(|e| ConnectorError::QueryExecutionFailed(
-                    format!("Failed to read CSV record: {}", e)
-                ))?;
+    /// Parse fixed-width file into a row per line by its layout
+    fn parse_fixed_width_file(file_path: &Path, options: &FileReadOptions) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
+        let layout = options.layout.as_ref()
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                "Fixed-width files need a layout: set fixed_width_layout or fixed_width_layout_file, \
+                 or the layout or layout_file source option".to_string()
+            ))?;
+        let content = fs::read(file_path)
+            .map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Failed to read fixed-width file: {}", e)
+            ))?;
+        parse_fixed_width_rows(content, layout, &options.csv)
+    }
 
-            let values: Vec<Value> = record.iter()
-                .map(|field| {
-                    // Try to infer type from string value
-                    if field.is_empty() {
-                        Value::Null
-                    } else if let Ok(int_val) = field.parse::<i64>() {
-                        Value::Integer(int_val)
-                    } else if let Ok(float_val) = field.parse::<f64>() {
-                        Value::Float(float_val)
-                    } else if let Ok(bool_val) = field.parse::<bool>() {
-                        Value::Boolean(bool_val)
-                    } else {
-                        Value::Text(field.to_string())
-                    }
-                })
-                .collect();
+    /// A layout file named relative to the base path
+    fn load_layout(&self, layout_file: &str) -> NirvResult<FixedWidthLayout> {
+        let base_path = self.base_path.as_deref().unwrap_or(Path::new("."));
+        FixedWidthLayout::load(&base_path.join(layout_file))
+    }
 
-            rows.push(Row::new(values));
+    /// The identifier without its source options, and how its files are read: the
+    /// `record_path` option sets the records of XML files, `layout` and `layout_file` the
+    /// columns of fixed-width files, and the others are CSV options
+    fn source_read_options<'a>(&self, identifier: &'a str) -> NirvResult<(&'a str, FileReadOptions)> {
+        let (identifier, source_options) = split_source_options(identifier);
+        let mut record_path = self.xml_record_path.clone();
+        let mut layout = self.fixed_width_layout.clone();
+        let mut csv_pairs = Vec::new();
+        for pair in source_options.into_iter().flat_map(|options| options.split('&')) {
+            match pair.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("record_path", value)) => record_path = XmlRecordPath::parse(value)?,
+                Some(("layout", value)) => layout = Some(FixedWidthLayout::parse(value)?),
+                Some(("layout_file", value)) => layout = Some(self.load_layout(value)?),
+                _ => csv_pairs.push(pair),
+            }
         }
-
-        Ok((columns, rows))
+        let csv = self.csv_options.with_source_options(&csv_pairs.join("&"))?;
+        Ok((identifier, FileReadOptions { csv, record_path, layout }))
     }
 
     /// Parse JSON file and return structured data
-    fn parse_json_file(&self, file_path: &Path) -> NirvResult<(Vec<ColumnMetadata>, Vec<Row>)> {
+    fn parse_json_file(file_path: &Path, flattening: Option<&JsonFlattening>) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
         let content = fs::read_to_string(file_path)
             .map_err(|e| ConnectorError::QueryExecutionFailed(
                 format!("Failed to read JSON file: {}", e)
@@ -180,33 +406,28 @@ impl FileConnector {
         match json_data {
             serde_json::Value::Array(array) => {
                 if array.is_empty() {
-                    return Ok((Vec::new(), Vec::new()));
+                    return Ok((Vec::new(), RowBuffer::new(0)));
                 }
 
-                // Infer schema from first object
-                let mut columns = Vec::new();
-                if let Some(serde_json::Value::Object(obj)) = array.first() {
-                    for key in obj.keys() {
-                        columns.push(ColumnMetadata {
-                            name: key.clone(),
-                            data_type: DataType::Text, // Default to text
-                            nullable: true,
-                        });
+                let (array, columns) = match flattening {
+                    Some(flattening) => {
+                        let array = flattening.apply(array);
+                        let columns = infer_json_columns(&array);
+                        (array, columns)
                     }
-                }
+                    None => {
+                        let columns = Self::first_object_columns(&array);
+                        (array, columns)
+                    }
+                };
 
                 // Convert array to rows
-                let mut rows = Vec::new();
+                let mut rows = RowBuffer::with_capacity(columns.len(), array.len());
                 for item in array {
                     if let serde_json::Value::Object(obj) = item {
-                        let mut values = Vec::new();
-                        for column in &columns {
-                            let value = obj.get(&column.name)
-                                .map(|v| self.json_value_to_value(v))
-                                .unwrap_or(Value::Null);
-                            values.push(value);
-                        }
-                        rows.push(Row::new(values));
+                        rows.push_row(columns.iter().map(|column| obj.get(&column.name)
+                            .map(Self::json_value_to_value)
+                            .unwrap_or(Value::Null)));
                     }
                 }
 
@@ -218,8 +439,23 @@ impl FileConnector {
         }
     }
 
+    /// Text columns named after the keys of the first object
+    fn first_object_columns(array: &[serde_json::Value]) -> Vec<ColumnMetadata> {
+        let mut columns = Vec::new();
+        if let Some(serde_json::Value::Object(obj)) = array.first() {
+            for key in obj.keys() {
+                columns.push(ColumnMetadata {
+                    name: key.clone(),
+                    data_type: DataType::Text, // Default to text
+                    nullable: true,
+                });
+            }
+        }
+        columns
+    }
+
     /// Convert serde_json::Value to our Value type
-    fn json_value_to_value(&self, json_val: &serde_json::Value) -> Value {
+    fn json_value_to_value(json_val: &serde_json::Value) -> Value {
         match json_val {
             serde_json::Value::Null => Value::Null,
             serde_json::Value::Bool(b) => Value::Boolean(*b),
@@ -249,8 +485,7 @@ impl FileConnector {
             .filter(|row| {
                 predicates.iter().all(|predicate| {
                     // Find column index
-                    let column_index = columns.iter()
-                        .position(|col| col.name == predicate.column);
+                    let column_index = self.identifier_case.resolve(&predicate.column, columns.iter().map(|col| col.name.as_str()));
                     
                     if let Some(index) = column_index {
                         if let Some(value) = row.values.get(index) {
@@ -280,6 +515,7 @@ impl FileConnector {
                 self.value_less_than(value, predicate_value) || self.values_equal(value, predicate_value)
             }
             PredicateOperator::Like => self.value_like(value, predicate_value),
+            PredicateOperator::Regexp => matches!(predicate_value, PredicateValue::String(pattern) if regex_matches(value, pattern)),
             PredicateOperator::In => self.value_in(value, predicate_value),
             PredicateOperator::IsNull => matches!(value, Value::Null),
             PredicateOperator::IsNotNull => !matches!(value, Value::Null),
@@ -355,12 +591,42 @@ impl FileConnector {
     }
 }
 
+/// Add the columns of a file that earlier files lack, padding the rows read so far with
+/// NULL, and return the file's rows reordered to the merged columns
+fn merge_columns(merged: &mut Vec<ColumnMetadata>, merged_rows: &mut [Row], columns: &[ColumnMetadata], rows: Vec<Row>) -> Vec<Row> {
+    for column in columns {
+        if !merged.iter().any(|existing| existing.name == column.name) {
+            merged.push(ColumnMetadata { nullable: true, ..column.clone() });
+        }
+    }
+    for row in merged_rows.iter_mut() {
+        row.values.resize(merged.len(), Value::Null);
+    }
+
+    let positions: Vec<Option<usize>> = merged.iter()
+        .map(|column| columns.iter().position(|c| c.name == column.name))
+        .collect();
+    rows.into_iter()
+        .map(|row| Row::new(positions.iter()
+            .map(|position| position.and_then(|index| row.values.get(index).cloned()).unwrap_or(Value::Null))
+            .collect()))
+        .collect()
+}
+
 impl Default for FileConnector {
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl Drop for FileConnector {
+    fn drop(&mut self) {
+        if let Some((_, task)) = self.watcher.take() {
+            task.abort();
+        }
+    }
+}
+
 #[async_trait]
 impl Connector for FileConnector {
     async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
@@ -391,6 +657,67 @@ impl Connector for FileConnector {
                 .collect();
         }
 
+        if let Some(flattening) = JsonFlattening::from_params(&config.connection_params) {
+            self.flattening = Some(flattening);
+        }
+
+        // Relative snapshot directories live under the base path, left out of file patterns
+        if let Some(snapshot_dir) = config.connection_params.get("snapshot_dir") {
+            let retention = match config.connection_params.get("snapshot_retention") {
+                Some(value) => value.parse::<usize>().ok().filter(|retention| *retention > 0).ok_or_else(|| ConnectorError::ConnectionFailed(
+                    format!("Invalid snapshot_retention '{}': expected a number above zero", value)
+                ))?,
+                None => DEFAULT_SNAPSHOT_RETENTION,
+            };
+            self.snapshots = Some(SnapshotStore::open(base_path.join(snapshot_dir))?.with_retention(retention));
+        }
+        if let Some(index_dir) = config.connection_params.get("index_dir") {
+            self.index = Some(FileIndexStore::open(base_path.join(index_dir))?);
+        }
+
+        self.identifier_case = IdentifierCase::from_params(&config.connection_params);
+        self.csv_options = self.csv_options.with_params(&config.connection_params)?;
+        if let Some(record_path) = config.connection_params.get("xml_record_path") {
+            self.xml_record_path = XmlRecordPath::parse(record_path)?;
+        }
+        if let Some(layout) = config.connection_params.get("fixed_width_layout") {
+            self.fixed_width_layout = Some(FixedWidthLayout::parse(layout)?);
+        }
+        if let Some(layout_file) = config.connection_params.get("fixed_width_layout_file") {
+            self.fixed_width_layout = Some(FixedWidthLayout::load(&base_path.join(layout_file))?);
+        }
+        if let Some(mode) = config.connection_params.get("schema_merge") {
+            self.schema_merge = SchemaMerge::parse(mode)?;
+        }
+
+        // A cache directory keeps parsed files across restarts, valid while the files are unchanged
+        let disk_cache = match config.connection_params.get("cache_dir") {
+            Some(cache_dir) => Some(Arc::new(DiskCache::open(base_path.join(cache_dir), cache_max_bytes(&config.connection_params)?)?)),
+            None => None,
+        };
+        let new_parse_cache = || match &disk_cache {
+            Some(disk_cache) => FileParseCache::new().with_disk(disk_cache.clone()),
+            None => FileParseCache::new(),
+        };
+        if disk_cache.is_some() {
+            self.parse_cache = Some(Arc::new(new_parse_cache()));
+        }
+
+        // Watching keeps parsed files between queries and drops them once they change
+        if let Some(interval) = config.connection_params.get("watch_interval_ms") {
+            let interval = interval.parse::<u64>().ok().filter(|ms| *ms > 0)
+                .ok_or_else(|| ConnectorError::ConnectionFailed(
+                    format!("Invalid watch_interval_ms '{}': expected a positive number", interval)
+                ))?;
+            if let Some((_, task)) = self.watcher.take() {
+                task.abort();
+            }
+            let cache = Arc::new(new_parse_cache());
+            let watcher = Arc::new(FileWatcher::new(&base_path, &self.supported_extensions, cache.clone()));
+            let task = watcher.clone().spawn(Duration::from_millis(interval));
+            self.parse_cache = Some(cache);
+            self.watcher = Some((watcher, task));
+        }
         self.base_path = Some(base_path);
         self.connected = true;
 
@@ -411,37 +738,65 @@ impl Connector for FileConnector {
         }
 
         let source = &query.query.sources[0]; // For now, handle single source
-        let file_paths = self.resolve_file_path(&source.identifier)?;
+        let (identifier, options) = self.source_read_options(&source.identifier)?;
+        let file_paths = self.resolve_query_files(identifier)?;
 
         let mut all_columns: Option<Vec<ColumnMetadata>> = None;
-        let mut all_rows = Vec::new();
+        let mut all_rows: Vec<Row> = Vec::new();
+
+        // Files are read the same way as long as these settings are
+        let read_settings = format!("{:?} {:?}", options, self.flattening);
 
         // Process each file (for pattern matching)
         for file_path in file_paths {
-            let (columns, mut rows) = if let Some(ext) = file_path.extension() {
-                match ext.to_string_lossy().to_lowercase().as_str() {
-                    "csv" => self.parse_csv_file(&file_path)?,
-                    "json" => self.parse_json_file(&file_path)?,
-                    _ => return Err(ConnectorError::UnsupportedOperation(
-                        format!("Unsupported file extension: {}", ext.to_string_lossy())
-                    ).into()),
+            let statistics = self.index.as_ref().and_then(|index| index.load(&file_path, &read_settings));
+            if let Some(statistics) = &statistics {
+                // Indexed files none of whose rows can match are not read at all
+                if !statistics.may_match(&query.query.predicates, self.identifier_case) {
+                    if all_columns.is_none() {
+                        all_columns = Some(statistics.columns.clone());
+                    }
+                    continue;
                 }
-            } else {
-                return Err(ConnectorError::UnsupportedOperation(
-                    "File has no extension".to_string()
-                ).into());
-            };
+            }
+
+            let (columns, mut buffer) = self.read_file(&file_path, &options, &read_settings).await?;
 
-            // Apply WHERE clause predicates (pushdown optimization)
-            rows = self.apply_predicates(&columns, rows, &query.query.predicates);
+            if let (Some(index), None) = (&self.index, &statistics) {
+                // The index only speeds up later queries, so failing to write it never fails this one
+                let _ = index.store(&file_path, &read_settings, FileStatistics::from_rows(&columns, &buffer));
+            }
+
+            // Rows are filtered before each gets its own allocation; predicates on columns
+            // the file lacks wait for the merged rows
+            let mut deferred = false;
+            let predicates: Vec<_> = query.query.predicates.iter()
+                .filter_map(|predicate| {
+                    let index = self.identifier_case.resolve(&predicate.column, columns.iter().map(|col| col.name.as_str()));
+                    deferred |= index.is_none();
+                    index.map(|index| (index, predicate))
+                })
+                .collect();
+            if !predicates.is_empty() {
+                buffer.retain(|row| predicates.iter()
+                    .all(|(index, predicate)| self.evaluate_predicate(&row[*index], &predicate.operator, &predicate.value)));
+            }
+            if let (Some(limit), false) = (query.query.limit, deferred) {
+                buffer.truncate(limit as usize);
+            }
+            let mut rows = buffer.into_rows();
 
             // For multiple files, ensure schema compatibility
-            if let Some(ref existing_columns) = all_columns {
-                if existing_columns.len() != columns.len() || 
-                   existing_columns.iter().zip(columns.iter()).any(|(a, b)| a.name != b.name) {
-                    return Err(ConnectorError::QueryExecutionFailed(
-                        "Schema mismatch between files in pattern".to_string()
-                    ).into());
+            if let Some(ref mut existing_columns) = all_columns {
+                let same_columns = existing_columns.len() == columns.len() &&
+                    existing_columns.iter().zip(columns.iter()).all(|(a, b)| a.name == b.name);
+                if !same_columns {
+                    if self.schema_merge == SchemaMerge::Strict {
+                        return Err(ConnectorError::QueryExecutionFailed(
+                            "Schema mismatch between files in pattern".to_string()
+                        ).into());
+                    }
+                    rows = merge_columns(existing_columns, &mut all_rows, &columns, rows);
                 }
             } else {
                 all_columns = Some(columns);
@@ -452,6 +807,10 @@ impl Connector for FileConnector {
 
         let columns = all_columns.unwrap_or_default();
 
+        // Apply WHERE clause predicates (pushdown optimization) once columns missing
+        // from some files are filled in
+        all_rows = self.apply_predicates(&columns, all_rows, &query.query.predicates);
+
         // Apply LIMIT if specified
         if let Some(limit) = query.query.limit {
             all_rows.truncate(limit as usize);
@@ -462,9 +821,50 @@ impl Connector for FileConnector {
             rows: all_rows,
             affected_rows: None,
             execution_time: std::time::Duration::from_millis(0), // TODO: measure actual time
+            stats: None,
+            warnings: Vec::new(),
         })
     }
 
+    async fn count_rows(&self, query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed(
+                "File connector is not connected".to_string()
+            ).into());
+        }
+        // Only whole files are counted without reading their rows
+        if !query.query.predicates.is_empty() || query.query.sample.is_some() {
+            return Ok(None);
+        }
+        let source = query.query.sources.first()
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed("No data source specified in query".to_string()))?;
+        let (identifier, options) = self.source_read_options(&source.identifier)?;
+        let file_paths = self.resolve_query_files(identifier)?;
+        let read_settings = format!("{:?} {:?}", options, self.flattening);
+
+        // Indexed files know their row count; CSV records are counted without parsing fields
+        let mut count = 0;
+        for file_path in file_paths {
+            if let Some(statistics) = self.index.as_ref().and_then(|index| index.load(&file_path, &read_settings)) {
+                count += statistics.row_count;
+                continue;
+            }
+            if !file_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv")) {
+                return Ok(None);
+            }
+            let options = options.csv.clone();
+            let pool = self.parse_pool.clone().unwrap_or_else(parse_pool);
+            count += pool.run(move || {
+                let content = fs::read(&file_path)
+                    .map_err(|e| ConnectorError::QueryExecutionFailed(
+                        format!("Failed to read CSV file: {}", e)
+                    ))?;
+                count_csv_records(content, &options)
+            }).await?;
+        }
+        Ok(Some(count))
+    }
+
     async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
         if !self.connected {
             return Err(ConnectorError::ConnectionFailed(
@@ -472,7 +872,8 @@ impl Connector for FileConnector {
             ).into());
         }
 
-        let file_paths = self.resolve_file_path(object_name)?;
+        let (object_name, options) = self.source_read_options(object_name)?;
+        let file_paths = self.resolve_query_files(object_name)?;
         
         if file_paths.is_empty() {
             return Err(ConnectorError::SchemaRetrievalFailed(
@@ -480,33 +881,99 @@ impl Connector for FileConnector {
             ).into());
         }
 
-        // Use first file for schema (assuming all files in pattern have same schema)
-        let file_path = &file_paths[0];
-        
-        let (columns, _) = if let Some(ext) = file_path.extension() {
-            match ext.to_string_lossy().to_lowercase().as_str() {
-                "csv" => self.parse_csv_file(file_path)?,
-                "json" => self.parse_json_file(file_path)?,
-                _ => return Err(ConnectorError::UnsupportedOperation(
-                    format!("Unsupported file extension: {}", ext.to_string_lossy())
-                ).into()),
-            }
-        } else {
-            return Err(ConnectorError::UnsupportedOperation(
-                "File has no extension".to_string()
-            ).into());
+        // Use first file for schema (assuming all files in pattern have same schema),
+        // unless the columns of all files are merged
+        let files = match self.schema_merge {
+            SchemaMerge::Strict => &file_paths[..1],
+            SchemaMerge::Union => &file_paths[..],
         };
+        let read_settings = format!("{:?} {:?}", options, self.flattening);
+        let mut columns: Vec<ColumnMetadata> = Vec::new();
+        for file_path in files {
+            let (file_columns, _) = self.read_file(file_path, &options, &read_settings).await?;
+            if columns.is_empty() {
+                columns = file_columns;
+            } else {
+                merge_columns(&mut columns, &mut [], &file_columns, Vec::new());
+            }
+        }
 
         Ok(Schema {
             name: object_name.to_string(),
             columns,
             primary_key: None,
             indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
         })
     }
 
+    async fn create_table(&self, object_name: &str, schema: &Schema) -> NirvResult<()> {
+        let path = self.writable_path(object_name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        let file = fs::OpenOptions::new().write(true).create_new(true).open(&path)
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to create {}: {}", object_name, e)))?;
+
+        let mut writer = csv::Writer::from_writer(file);
+        writer.write_record(schema.columns.iter().map(|column| column.name.as_str()))
+            .and_then(|_| writer.flush().map_err(Into::into))
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to write {}: {}", object_name, e)).into())
+    }
+
+    async fn insert_rows(&self, object_name: &str, _columns: &[ColumnMetadata], rows: &[Row]) -> NirvResult<u64> {
+        let path = self.writable_path(object_name)?;
+        let file = fs::OpenOptions::new().append(true).open(&path)
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to open {}: {}", object_name, e)))?;
+
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+        for row in rows {
+            // Written so that reading the file back infers the same values
+            let fields = row.values.iter().map(|value| match value {
+                Value::Null => String::new(),
+                Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => s.clone(),
+                Value::Integer(i) => i.to_string(),
+                Value::Float(f) => f.to_string(),
+                Value::Boolean(b) => b.to_string(),
+                Value::Binary(bytes) => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            });
+            writer.write_record(fields)
+                .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to write {}: {}", object_name, e)))?;
+        }
+        writer.flush()
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to write {}: {}", object_name, e)))?;
+        Ok(rows.len() as u64)
+    }
+
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        let base_path = self.base_path.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected".to_string()))?;
+
+        let entries = std::fs::read_dir(base_path)
+            .map_err(|e| ConnectorError::SchemaRetrievalFailed(format!("Failed to list {}: {}", base_path.display(), e)))?;
+
+        let mut objects: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| path.extension()
+                .map(|ext| self.is_supported_extension(&ext.to_string_lossy()))
+                .unwrap_or(false))
+            .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .collect();
+        objects.sort();
+        Ok(objects)
+    }
+
     async fn disconnect(&mut self) -> NirvResult<()> {
         self.base_path = None;
+        self.snapshots = None;
+        if let Some((_, task)) = self.watcher.take() {
+            task.abort();
+        }
+        self.parse_cache = None;
         self.connected = false;
         Ok(())
     }
@@ -523,6 +990,11 @@ impl Connector for FileConnector {
         self.connected
     }
 
+    /// Patterns are matched while the file's rows are filtered
+    fn supports_regex_predicates(&self) -> bool {
+        true
+    }
+
     fn get_capabilities(&self) -> ConnectorCapabilities {
         ConnectorCapabilities {
             supports_joins: false, // No cross-file joins for now
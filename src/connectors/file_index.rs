@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::connectors::file_snapshot::hex_digest;
+use crate::utils::{
+    row_buffer::RowBuffer,
+    types::{ColumnMetadata, Predicate, PredicateOperator, PredicateValue, Value},
+    error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
+};
+
+/// Smallest and largest non-null value of a column whose values all have one type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ColumnRange {
+    Integer { min: i64, max: i64 },
+    Float { min: f64, max: f64 },
+    Text { min: String, max: String },
+}
+
+/// Column ranges of one file, valid while the file keeps its size and modification time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStatistics {
+    pub size: u64,
+    pub modified_nanos: u128,
+    pub row_count: u64,
+    pub columns: Vec<ColumnMetadata>,
+    /// Range of each column; columns with mixed types or only nulls have none
+    pub ranges: Vec<Option<ColumnRange>>,
+}
+
+impl FileStatistics {
+    /// Statistics of a file's rows
+    pub fn from_rows(columns: &[ColumnMetadata], rows: &RowBuffer) -> Self {
+        let ranges = (0..columns.len())
+            .map(|index| column_range(rows.rows().filter_map(|row| row.get(index))))
+            .collect();
+        Self {
+            size: 0,
+            modified_nanos: 0,
+            row_count: rows.len() as u64,
+            columns: columns.to_vec(),
+            ranges,
+        }
+    }
+
+    /// Whether rows of the file may satisfy all predicates. Only comparisons against a
+    /// column with a known range can rule a file out.
+    pub fn may_match(&self, predicates: &[Predicate], identifier_case: IdentifierCase) -> bool {
+        predicates.iter().all(|predicate| {
+            let index = identifier_case.resolve(&predicate.column, self.columns.iter().map(|column| column.name.as_str()));
+            match index.and_then(|index| self.ranges.get(index)?.as_ref()) {
+                Some(range) => range_may_match(range, &predicate.operator, &predicate.value),
+                None => true,
+            }
+        })
+    }
+}
+
+fn column_range<'a>(values: impl Iterator<Item = &'a Value>) -> Option<ColumnRange> {
+    let mut range: Option<ColumnRange> = None;
+    for value in values {
+        range = match (range, value) {
+            (range, Value::Null) => range,
+            (None, Value::Integer(i)) => Some(ColumnRange::Integer { min: *i, max: *i }),
+            (None, Value::Float(f)) => Some(ColumnRange::Float { min: *f, max: *f }),
+            (None, Value::Text(s)) => Some(ColumnRange::Text { min: s.clone(), max: s.clone() }),
+            (Some(ColumnRange::Integer { min, max }), Value::Integer(i)) => Some(ColumnRange::Integer { min: min.min(*i), max: max.max(*i) }),
+            (Some(ColumnRange::Float { min, max }), Value::Float(f)) => Some(ColumnRange::Float { min: min.min(*f), max: max.max(*f) }),
+            (Some(ColumnRange::Text { min, max }), Value::Text(s)) => Some(ColumnRange::Text {
+                min: if *s < min { s.clone() } else { min },
+                max: if *s > max { s.clone() } else { max },
+            }),
+            // Mixed types, or types without a useful order
+            _ => return None,
+        };
+    }
+    range
+}
+
+/// Whether a value in the range may satisfy the comparison, following how the file
+/// connector compares values
+fn range_may_match(range: &ColumnRange, operator: &PredicateOperator, value: &PredicateValue) -> bool {
+    use std::cmp::Ordering;
+
+    let (vs_min, vs_max) = match (range, value) {
+        (ColumnRange::Integer { min, max }, PredicateValue::Integer(p)) => (p.cmp(min), p.cmp(max)),
+        (ColumnRange::Integer { min, max }, PredicateValue::Number(p)) => match (p.partial_cmp(&(*min as f64)), p.partial_cmp(&(*max as f64))) {
+            (Some(low), Some(high)) => (low, high),
+            _ => return true,
+        },
+        (ColumnRange::Float { min, max }, PredicateValue::Number(p)) => match (p.partial_cmp(min), p.partial_cmp(max)) {
+            (Some(low), Some(high)) => (low, high),
+            _ => return true,
+        },
+        (ColumnRange::Float { min, max }, PredicateValue::Integer(p)) => match ((*p as f64).partial_cmp(min), (*p as f64).partial_cmp(max)) {
+            (Some(low), Some(high)) => (low, high),
+            _ => return true,
+        },
+        (ColumnRange::Text { min, max }, PredicateValue::String(p)) => (p.as_str().cmp(min.as_str()), p.as_str().cmp(max.as_str())),
+        _ => return true,
+    };
+
+    match operator {
+        PredicateOperator::Equal => vs_min != Ordering::Less && vs_max != Ordering::Greater,
+        // Some value is greater than p exactly when the maximum is
+        PredicateOperator::GreaterThan => vs_max == Ordering::Less,
+        PredicateOperator::GreaterThanOrEqual => vs_max != Ordering::Greater,
+        PredicateOperator::LessThan => vs_min == Ordering::Greater,
+        PredicateOperator::LessThanOrEqual => vs_min != Ordering::Less,
+        _ => true,
+    }
+}
+
+/// Sidecar statistics for the files a connector reads, kept in a directory of their own
+/// and rebuilt whenever a file changes
+#[derive(Debug, Clone)]
+pub struct FileIndexStore {
+    root: PathBuf,
+}
+
+impl FileIndexStore {
+    /// Open an index store rooted at the given directory, creating it if needed
+    pub fn open<P: AsRef<Path>>(root: P) -> NirvResult<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).map_err(|e| ConnectorError::ConnectionFailed(
+            format!("Failed to create index directory {}: {}", root.display(), e)
+        ))?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Statistics of a file read with the given settings, if they were recorded since it
+    /// last changed. Settings that change how a file parses, such as its CSV delimiter,
+    /// keep separate statistics.
+    pub fn load(&self, file_path: &Path, settings: &str) -> Option<FileStatistics> {
+        let (size, modified_nanos) = file_version(file_path)?;
+        let content = fs::read_to_string(self.sidecar_path(file_path, settings)).ok()?;
+        serde_json::from_str::<FileStatistics>(&content).ok()
+            .filter(|statistics| statistics.size == size && statistics.modified_nanos == modified_nanos)
+    }
+
+    /// Record the statistics of a file's current content read with the given settings
+    pub fn store(&self, file_path: &Path, settings: &str, mut statistics: FileStatistics) -> NirvResult<()> {
+        let Some((size, modified_nanos)) = file_version(file_path) else {
+            return Ok(());
+        };
+        statistics.size = size;
+        statistics.modified_nanos = modified_nanos;
+        let content = serde_json::to_string(&statistics)
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to encode file index: {}", e)))?;
+
+        // Written through a temporary file so concurrent readers never see a partial index
+        let path = self.sidecar_path(file_path, settings);
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &path))
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to write file index {}: {}", path.display(), e)).into())
+    }
+
+    fn sidecar_path(&self, file_path: &Path, settings: &str) -> PathBuf {
+        let key = format!("{}\n{}", file_path.to_string_lossy(), settings);
+        self.root.join(format!("{}.json", hex_digest(key.as_bytes())))
+    }
+}
+
+/// Size and modification time identifying a file's content
+pub(crate) fn file_version(file_path: &Path) -> Option<(u64, u128)> {
+    let metadata = fs::metadata(file_path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_nanos()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{DataType, Row};
+    use tempfile::TempDir;
+
+    fn predicate(column: &str, operator: PredicateOperator, value: PredicateValue) -> Predicate {
+        Predicate { column: column.to_string(), operator, value }
+    }
+
+    #[test]
+    fn test_statistics_rule_out_files() {
+        let columns = ["id", "city", "mixed"].iter()
+            .map(|name| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true })
+            .collect::<Vec<_>>();
+        let rows = RowBuffer::from_rows(3, vec![
+            Row::new(vec![Value::Integer(10), Value::Text("Berlin".to_string()), Value::Integer(1)]),
+            Row::new(vec![Value::Integer(20), Value::Null, Value::Text("x".to_string())]),
+        ]);
+        let statistics = FileStatistics::from_rows(&columns, &rows);
+        let case = IdentifierCase::default();
+
+        assert!(statistics.may_match(&[predicate("id", PredicateOperator::Equal, PredicateValue::Integer(15))], case));
+        assert!(!statistics.may_match(&[predicate("id", PredicateOperator::GreaterThan, PredicateValue::Integer(20))], case));
+        assert!(statistics.may_match(&[predicate("id", PredicateOperator::GreaterThanOrEqual, PredicateValue::Number(20.0))], case));
+        assert!(!statistics.may_match(&[predicate("id", PredicateOperator::LessThan, PredicateValue::Integer(10))], case));
+        assert!(!statistics.may_match(&[predicate("CITY", PredicateOperator::Equal, PredicateValue::String("Paris".to_string()))], case));
+        // Columns with mixed types, unknown columns and other operators never rule a file out
+        assert!(statistics.may_match(&[predicate("mixed", PredicateOperator::Equal, PredicateValue::Integer(5))], case));
+        assert!(statistics.may_match(&[predicate("other", PredicateOperator::Equal, PredicateValue::Integer(5))], case));
+        assert!(statistics.may_match(&[predicate("id", PredicateOperator::NotEqual, PredicateValue::Integer(10))], case));
+    }
+
+    #[test]
+    fn test_store_invalidates_changed_files() {
+        let data = TempDir::new().unwrap();
+        let store = FileIndexStore::open(data.path().join(".index")).unwrap();
+        let file = data.path().join("users.csv");
+        fs::write(&file, "id\n1\n").unwrap();
+
+        assert!(store.load(&file, "").is_none());
+        store.store(&file, "", FileStatistics::from_rows(&[], &RowBuffer::new(0))).unwrap();
+        assert!(store.load(&file, "").is_some());
+        assert!(store.load(&file, "delimiter=;").is_none());
+
+        fs::write(&file, "id\n1\n2\n").unwrap();
+        assert!(store.load(&file, "").is_none());
+    }
+}
@@ -0,0 +1,373 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::utils::error::{ConnectorError, NirvResult};
+
+const INDEX_FILE: &str = "index.jsonl";
+const LOCK_FILE: &str = "index.lock";
+
+/// Snapshots kept of each file unless the connector sets `snapshot_retention`
+pub const DEFAULT_SNAPSHOT_RETENTION: usize = 30;
+
+/// A retained copy of a file as it existed at a point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// Identifier the file was queried by, relative to the connector base path
+    pub identifier: String,
+    /// Hex-encoded SHA-256 of the file content
+    pub content_hash: String,
+    /// When this content was first observed
+    pub taken_at: DateTime<Utc>,
+    /// Modification time of the file when it was taken, to tell an unchanged file without
+    /// reading it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    /// Size of the file when it was taken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// Index entries as last read, reread when another process changed the index file
+#[derive(Debug, Default)]
+struct IndexCache {
+    /// Length and modification time of the index file the entries were read from
+    version: Option<(u64, SystemTime)>,
+    entries: Vec<FileSnapshot>,
+    /// Modification time and size each identifier's file had when its content was last
+    /// found to match its latest snapshot
+    verified: HashMap<String, (Option<DateTime<Utc>>, u64)>,
+}
+
+/// Content-addressed store of file snapshots with an append-only index. The oldest snapshots
+/// of a file beyond the retention are dropped, with copies no snapshot refers to any more.
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    root: PathBuf,
+    retention: usize,
+    index: Arc<Mutex<IndexCache>>,
+}
+
+impl SnapshotStore {
+    /// Open a snapshot store rooted at the given directory, creating it if needed
+    pub fn open<P: AsRef<Path>>(root: P) -> NirvResult<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).map_err(|e| ConnectorError::ConnectionFailed(
+            format!("Failed to create snapshot directory {}: {}", root.display(), e)
+        ))?;
+        Ok(Self { root, retention: DEFAULT_SNAPSHOT_RETENTION, index: Arc::default() })
+    }
+
+    /// Keep at most this many snapshots of each file
+    pub fn with_retention(mut self, retention: usize) -> Self {
+        self.retention = retention.max(1);
+        self
+    }
+
+    /// Directory holding snapshot copies and the index
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Record the current content of a file, copying it only if the content changed
+    /// since the latest snapshot of the same identifier. A file whose modification time and
+    /// size match those of its latest snapshot is taken as unchanged without being read.
+    pub fn record(&self, identifier: &str, file_path: &Path) -> NirvResult<FileSnapshot> {
+        let metadata = fs::metadata(file_path).map_err(|e| ConnectorError::QueryExecutionFailed(
+            format!("Failed to read file for snapshot {}: {}", file_path.display(), e)
+        ))?;
+        let stat = (metadata.modified().ok().map(DateTime::<Utc>::from), metadata.len());
+        let unchanged = |index: &IndexCache, latest: &FileSnapshot| {
+            stat.0.is_some() && ((latest.modified, latest.size) == (stat.0, Some(stat.1)) || index.verified.get(identifier) == Some(&stat))
+        };
+        {
+            let index = self.read_index()?;
+            if let Some(latest) = latest(&index.entries, identifier).filter(|latest| unchanged(&index, latest)) {
+                return Ok(latest.clone());
+            }
+        }
+
+        let content = fs::read(file_path).map_err(|e| ConnectorError::QueryExecutionFailed(
+            format!("Failed to read file for snapshot {}: {}", file_path.display(), e)
+        ))?;
+        let content_hash = hex_digest(&content);
+
+        // Other processes may record the same files; the lock keeps the index whole
+        let _lock = self.lock()?;
+        let mut index = self.read_index()?;
+        if let Some(latest) = latest(&index.entries, identifier).filter(|latest| latest.content_hash == content_hash).cloned() {
+            index.verified.insert(identifier.to_string(), stat);
+            return Ok(latest);
+        }
+
+        let copy_path = self.copy_path(&content_hash, file_path);
+        if !copy_path.exists() {
+            fs::write(&copy_path, &content).map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Failed to write snapshot {}: {}", copy_path.display(), e)
+            ))?;
+        }
+
+        let snapshot = FileSnapshot {
+            identifier: identifier.to_string(),
+            content_hash,
+            taken_at: Utc::now(),
+            modified: stat.0,
+            size: Some(stat.1),
+        };
+        self.append_index(&snapshot)?;
+        index.entries.push(snapshot.clone());
+        if index.entries.iter().filter(|entry| entry.identifier == identifier).count() > self.retention {
+            self.prune(&mut index, identifier)?;
+        }
+        index.version = self.index_version();
+        Ok(snapshot)
+    }
+
+    /// All snapshots of an identifier, oldest first
+    pub fn snapshots(&self, identifier: &str) -> NirvResult<Vec<FileSnapshot>> {
+        let mut snapshots: Vec<FileSnapshot> = self.read_index()?.entries.iter()
+            .filter(|snapshot| snapshot.identifier == identifier)
+            .cloned()
+            .collect();
+        snapshots.sort_by_key(|snapshot| snapshot.taken_at);
+        Ok(snapshots)
+    }
+
+    /// Path of the snapshot copy retained for an identifier as of the given point in time.
+    /// Accepts a date (`2024-05-01`, meaning the end of that day in UTC) or an RFC 3339 timestamp.
+    pub fn resolve(&self, identifier: &str, as_of: &str) -> NirvResult<PathBuf> {
+        let cutoff = parse_as_of(as_of)?;
+
+        let snapshot = self.snapshots(identifier)?
+            .into_iter()
+            .rfind(|snapshot| snapshot.taken_at <= cutoff)
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                format!("No snapshot of '{}' retained as of {}", identifier, as_of)
+            ))?;
+
+        let copy_path = self.copy_path(&snapshot.content_hash, Path::new(identifier));
+        if !copy_path.exists() {
+            return Err(ConnectorError::QueryExecutionFailed(
+                format!("Snapshot content {} for '{}' is missing", snapshot.content_hash, identifier)
+            ).into());
+        }
+        Ok(copy_path)
+    }
+
+    /// Snapshot copies keep the original extension so they parse the same way
+    fn copy_path(&self, content_hash: &str, original: &Path) -> PathBuf {
+        match original.extension() {
+            Some(ext) => self.root.join(format!("{}.{}", content_hash, ext.to_string_lossy())),
+            None => self.root.join(content_hash),
+        }
+    }
+
+    fn append_index(&self, snapshot: &FileSnapshot) -> NirvResult<()> {
+        let line = serde_json::to_string(snapshot)
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to encode snapshot: {}", e)))?;
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.root.join(INDEX_FILE))
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to write snapshot index: {}", e)))?;
+        Ok(())
+    }
+
+    /// Drop the oldest snapshots of an identifier beyond the retention, then the copies no
+    /// snapshot refers to any more
+    fn prune(&self, index: &mut IndexCache, identifier: &str) -> NirvResult<()> {
+        let mut kept: Vec<&FileSnapshot> = index.entries.iter().filter(|entry| entry.identifier == identifier).collect();
+        kept.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.taken_at));
+        let dropped: Vec<FileSnapshot> = kept.split_off(self.retention.min(kept.len())).into_iter().cloned().collect();
+        index.entries.retain(|entry| !dropped.contains(entry));
+
+        let mut content = String::new();
+        for entry in &index.entries {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to encode snapshot: {}", e)))?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+        let index_path = self.root.join(INDEX_FILE);
+        let temp_path = self.root.join(format!("{}.tmp", INDEX_FILE));
+        fs::write(&temp_path, content)
+            .and_then(|_| fs::rename(&temp_path, &index_path))
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to write snapshot index: {}", e)))?;
+
+        let referenced: HashSet<PathBuf> = index.entries.iter()
+            .map(|entry| self.copy_path(&entry.content_hash, Path::new(&entry.identifier)))
+            .collect();
+        for snapshot in dropped {
+            let copy_path = self.copy_path(&snapshot.content_hash, Path::new(&snapshot.identifier));
+            if !referenced.contains(&copy_path) {
+                // A copy already gone needs no removing
+                let _ = fs::remove_file(copy_path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Hold the store's lock file until the returned handle is dropped
+    fn lock(&self) -> NirvResult<File> {
+        let path = self.root.join(LOCK_FILE);
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.lock().map(|_| file))
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to lock snapshot index {}: {}", path.display(), e)).into())
+    }
+
+    fn index_version(&self) -> Option<(u64, SystemTime)> {
+        let metadata = fs::metadata(self.root.join(INDEX_FILE)).ok()?;
+        Some((metadata.len(), metadata.modified().ok()?))
+    }
+
+    /// The index, read again only when the file changed since it was last read
+    fn read_index(&self) -> NirvResult<MutexGuard<'_, IndexCache>> {
+        let mut index = self.index.lock().unwrap();
+        let version = self.index_version();
+        if version == index.version {
+            return Ok(index);
+        }
+        index.entries = match version {
+            Some(_) => {
+                let content = fs::read_to_string(self.root.join(INDEX_FILE)).map_err(|e| ConnectorError::QueryExecutionFailed(
+                    format!("Failed to read snapshot index: {}", e)
+                ))?;
+                // A torn final line from an interrupted append is ignored
+                content.lines().filter_map(|line| serde_json::from_str::<FileSnapshot>(line).ok()).collect()
+            }
+            None => Vec::new(),
+        };
+        index.version = version;
+        Ok(index)
+    }
+}
+
+/// The latest snapshot of an identifier
+fn latest<'a>(entries: &'a [FileSnapshot], identifier: &str) -> Option<&'a FileSnapshot> {
+    entries.iter().filter(|entry| entry.identifier == identifier).max_by_key(|entry| entry.taken_at)
+}
+
+/// Split a `file@as_of` identifier into the file identifier and point in time
+pub fn split_snapshot_identifier(identifier: &str) -> (&str, Option<&str>) {
+    match identifier.rsplit_once('@') {
+        Some((file, as_of)) if !file.is_empty() && !as_of.is_empty() => (file, Some(as_of)),
+        _ => (identifier, None),
+    }
+}
+
+fn parse_as_of(as_of: &str) -> NirvResult<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(as_of) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(as_of, "%Y-%m-%d") {
+        if let Some(end_of_day) = date.and_hms_opt(23, 59, 59) {
+            return Ok(end_of_day.and_utc());
+        }
+    }
+    Err(ConnectorError::QueryExecutionFailed(
+        format!("Invalid snapshot point in time '{}': expected YYYY-MM-DD or RFC 3339", as_of)
+    ).into())
+}
+
+pub(crate) fn hex_digest(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_split_snapshot_identifier() {
+        assert_eq!(split_snapshot_identifier("users.csv@2024-05-01"), ("users.csv", Some("2024-05-01")));
+        assert_eq!(split_snapshot_identifier("users.csv"), ("users.csv", None));
+        assert_eq!(split_snapshot_identifier("users.csv@"), ("users.csv@", None));
+    }
+
+    #[test]
+    fn test_record_deduplicates_unchanged_content() {
+        let data = TempDir::new().unwrap();
+        let store = SnapshotStore::open(data.path().join(".snapshots")).unwrap();
+        let file = data.path().join("users.csv");
+
+        fs::write(&file, "id\n1\n").unwrap();
+        let first = store.record("users.csv", &file).unwrap();
+        let again = store.record("users.csv", &file).unwrap();
+        assert_eq!(first, again);
+
+        fs::write(&file, "id\n1\n2\n").unwrap();
+        let changed = store.record("users.csv", &file).unwrap();
+        assert_ne!(first.content_hash, changed.content_hash);
+        assert_eq!(store.snapshots("users.csv").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_unchanged_files_are_not_read() {
+        let data = TempDir::new().unwrap();
+        let store = SnapshotStore::open(data.path().join(".snapshots")).unwrap();
+        let file = data.path().join("users.csv");
+        fs::write(&file, "id\n1\n").unwrap();
+        let first = store.record("users.csv", &file).unwrap();
+
+        // Same size and modification time: taken as unchanged, even by a fresh store
+        let modified = fs::metadata(&file).unwrap().modified().unwrap();
+        fs::write(&file, "id\n2\n").unwrap();
+        File::options().write(true).open(&file).unwrap().set_modified(modified).unwrap();
+        let reopened = SnapshotStore::open(store.root()).unwrap();
+        assert_eq!(reopened.record("users.csv", &file).unwrap(), first);
+
+        // A newer modification time gets the content read and a snapshot taken
+        File::options().write(true).open(&file).unwrap().set_modified(modified + std::time::Duration::from_secs(1)).unwrap();
+        assert_ne!(reopened.record("users.csv", &file).unwrap().content_hash, first.content_hash);
+        assert_eq!(store.snapshots("users.csv").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_retention_drops_oldest_snapshots_and_their_copies() {
+        let data = TempDir::new().unwrap();
+        let store = SnapshotStore::open(data.path().join(".snapshots")).unwrap().with_retention(2);
+        let file = data.path().join("users.csv");
+        let mut hashes = Vec::new();
+        for rows in ["1", "1\n2", "1\n2\n3"] {
+            fs::write(&file, format!("id\n{}\n", rows)).unwrap();
+            hashes.push(store.record("users.csv", &file).unwrap().content_hash);
+        }
+
+        let kept: Vec<String> = store.snapshots("users.csv").unwrap().into_iter().map(|snapshot| snapshot.content_hash).collect();
+        assert_eq!(kept, hashes[1..]);
+        assert!(!store.root().join(format!("{}.csv", hashes[0])).exists());
+        assert!(store.root().join(format!("{}.csv", hashes[2])).exists());
+        // The rewritten index is what a fresh store reads
+        assert_eq!(SnapshotStore::open(store.root()).unwrap().snapshots("users.csv").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_as_of() {
+        let data = TempDir::new().unwrap();
+        let store = SnapshotStore::open(data.path().join(".snapshots")).unwrap();
+        let file = data.path().join("users.csv");
+        fs::write(&file, "id\n1\n").unwrap();
+        let snapshot = store.record("users.csv", &file).unwrap();
+
+        let today = snapshot.taken_at.format("%Y-%m-%d").to_string();
+        let path = store.resolve("users.csv", &today).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "id\n1\n");
+
+        assert!(store.resolve("users.csv", "2000-01-01").is_err());
+        assert!(store.resolve("users.csv", "yesterday").is_err());
+        assert!(store.resolve("other.csv", &today).is_err());
+    }
+}
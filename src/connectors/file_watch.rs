@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use serde::{Deserialize, Serialize};
+
+use crate::connectors::disk_cache::DiskCache;
+use crate::connectors::file_index::file_version;
+use crate::utils::{row_buffer::RowBuffer, types::{ColumnMetadata, Value}};
+
+/// How a watched file changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// A change to a file under a watched directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub kind: FileChangeKind,
+}
+
+/// Parsed content of files, each entry valid while its file keeps its size and
+/// modification time. With a disk cache, parses are also kept on disk for after a restart.
+#[derive(Debug, Default)]
+pub struct FileParseCache {
+    entries: Mutex<HashMap<PathBuf, HashMap<String, CachedParse>>>,
+    disk: Option<Arc<DiskCache>>,
+}
+
+/// A parse as kept in a disk cache
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredParse {
+    size: u64,
+    /// Modification time in nanoseconds, as text since JSON numbers cannot hold it
+    modified: String,
+    columns: Vec<ColumnMetadata>,
+    rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedParse {
+    version: (u64, u128),
+    columns: Vec<ColumnMetadata>,
+    rows: RowBuffer,
+}
+
+impl FileParseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep parses in a disk cache too, and look there for files not parsed since starting
+    pub fn with_disk(mut self, disk: Arc<DiskCache>) -> Self {
+        self.disk = Some(disk);
+        self
+    }
+
+    /// Columns and rows of a file read with the given settings, if parsed since it last changed
+    pub fn get(&self, file_path: &Path, settings: &str) -> Option<(Vec<ColumnMetadata>, RowBuffer)> {
+        let version = file_version(file_path)?;
+        let cached = self.entries.lock().unwrap().get(file_path)
+            .and_then(|parses| parses.get(settings))
+            .filter(|parse| parse.version == version)
+            .map(|parse| (parse.columns.clone(), parse.rows.clone()));
+        if cached.is_some() {
+            return cached;
+        }
+
+        let disk = self.disk.as_ref()?;
+        let stored: StoredParse = serde_json::from_value(disk.get(&disk_key(file_path, settings))?.data).ok()?;
+        if (stored.size, stored.modified.parse().ok()?) != version {
+            return None;
+        }
+        let width = stored.columns.len();
+        let rows = stored.rows.into_iter().fold(RowBuffer::with_capacity(width, 0), |mut rows, row| {
+            rows.push_row(row);
+            rows
+        });
+        self.remember(file_path, settings, version, &stored.columns, &rows);
+        Some((stored.columns, rows))
+    }
+
+    /// Remember the parse of a file's current content read with the given settings
+    pub fn insert(&self, file_path: &Path, settings: &str, columns: &[ColumnMetadata], rows: &RowBuffer) {
+        let Some(version) = file_version(file_path) else {
+            return;
+        };
+        if let Some(disk) = &self.disk {
+            let stored = StoredParse {
+                size: version.0,
+                modified: version.1.to_string(),
+                columns: columns.to_vec(),
+                rows: rows.rows().map(|row| row.to_vec()).collect(),
+            };
+            if let Ok(data) = serde_json::to_value(&stored) {
+                disk.insert(&disk_key(file_path, settings), &data);
+            }
+        }
+        self.remember(file_path, settings, version, columns, rows);
+    }
+
+    fn remember(&self, file_path: &Path, settings: &str, version: (u64, u128), columns: &[ColumnMetadata], rows: &RowBuffer) {
+        self.entries.lock().unwrap()
+            .entry(file_path.to_path_buf())
+            .or_default()
+            .insert(settings.to_string(), CachedParse { version, columns: columns.to_vec(), rows: rows.clone() });
+    }
+
+    /// Forget every parse of a file
+    pub fn invalidate(&self, file_path: &Path) {
+        self.entries.lock().unwrap().remove(file_path);
+    }
+
+    /// Number of files with a cached parse
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn disk_key(file_path: &Path, settings: &str) -> String {
+    format!("parse:{}:{}", file_path.display(), settings)
+}
+
+/// Polls a directory tree for files that were created, modified or removed, evicting
+/// them from a parse cache and announcing each change to subscribers
+pub struct FileWatcher {
+    root: PathBuf,
+    extensions: Vec<String>,
+    cache: Arc<FileParseCache>,
+    versions: Mutex<HashMap<PathBuf, (u64, u128)>>,
+    changes: broadcast::Sender<FileChange>,
+}
+
+impl FileWatcher {
+    /// Watch files with the given extensions under a directory. Hidden directories,
+    /// such as snapshot and index stores, are not watched.
+    pub fn new<P: AsRef<Path>>(root: P, extensions: &[String], cache: Arc<FileParseCache>) -> Self {
+        let (changes, _) = broadcast::channel(256);
+        let watcher = Self {
+            root: root.as_ref().to_path_buf(),
+            extensions: extensions.to_vec(),
+            cache,
+            versions: Mutex::new(HashMap::new()),
+            changes,
+        };
+        *watcher.versions.lock().unwrap() = watcher.current_versions();
+        watcher
+    }
+
+    /// Receive the changes found from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<FileChange> {
+        self.changes.subscribe()
+    }
+
+    /// Compare the files on disk with the previous scan, returning what changed
+    pub fn scan(&self) -> Vec<FileChange> {
+        let current = self.current_versions();
+        let mut versions = self.versions.lock().unwrap();
+
+        let mut changes: Vec<FileChange> = current.iter()
+            .filter_map(|(path, version)| match versions.get(path) {
+                None => Some(FileChangeKind::Created),
+                Some(previous) if previous != version => Some(FileChangeKind::Modified),
+                Some(_) => None,
+            }.map(|kind| FileChange { path: path.clone(), kind }))
+            .chain(versions.keys()
+                .filter(|path| !current.contains_key(*path))
+                .map(|path| FileChange { path: path.clone(), kind: FileChangeKind::Removed }))
+            .collect();
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        *versions = current;
+        drop(versions);
+
+        for change in &changes {
+            self.cache.invalidate(&change.path);
+            // Nobody listening is not an error
+            let _ = self.changes.send(change.clone());
+        }
+        changes
+    }
+
+    /// Scan at the given interval until the returned task is aborted
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.scan();
+            }
+        })
+    }
+
+    fn current_versions(&self) -> HashMap<PathBuf, (u64, u128)> {
+        let mut versions = HashMap::new();
+        let mut directories = vec![self.root.clone()];
+        while let Some(directory) = directories.pop() {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+            for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+                let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+                if path.is_dir() {
+                    if !hidden {
+                        directories.push(path);
+                    }
+                } else if self.is_watched(&path) {
+                    if let Some(version) = file_version(&path) {
+                        versions.insert(path, version);
+                    }
+                }
+            }
+        }
+        versions
+    }
+
+    fn is_watched(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|ext| self.extensions.iter().any(|watched| watched.eq_ignore_ascii_case(&ext.to_string_lossy())))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{Row, Value};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_reports_changes_and_evicts_parses() {
+        let data = TempDir::new().unwrap();
+        let users = data.path().join("users.csv");
+        fs::write(&users, "id\n1\n").unwrap();
+        fs::create_dir(data.path().join(".index")).unwrap();
+
+        let cache = Arc::new(FileParseCache::new());
+        let watcher = FileWatcher::new(data.path(), &["csv".to_string()], cache.clone());
+        let mut changes = watcher.subscribe();
+        cache.insert(&users, "", &[], &RowBuffer::from_rows(1, vec![Row::new(vec![Value::Integer(1)])]));
+        assert!(cache.get(&users, "").is_some());
+        assert!(watcher.scan().is_empty());
+
+        fs::write(&users, "id\n1\n2\n").unwrap();
+        fs::write(data.path().join("orders.csv"), "id\n").unwrap();
+        fs::write(data.path().join("notes.txt"), "ignored").unwrap();
+        fs::write(data.path().join(".index").join("hidden.csv"), "ignored").unwrap();
+        let found = watcher.scan();
+        assert_eq!(found, vec![
+            FileChange { path: data.path().join("orders.csv"), kind: FileChangeKind::Created },
+            FileChange { path: users.clone(), kind: FileChangeKind::Modified },
+        ]);
+        assert!(cache.is_empty());
+        assert_eq!(changes.try_recv().unwrap().kind, FileChangeKind::Created);
+
+        fs::remove_file(&users).unwrap();
+        assert_eq!(watcher.scan(), vec![FileChange { path: users, kind: FileChangeKind::Removed }]);
+    }
+
+    #[test]
+    fn test_parses_outlive_a_restart_on_disk() {
+        let data = TempDir::new().unwrap();
+        let users = data.path().join("users.csv");
+        fs::write(&users, "id\n1\n").unwrap();
+        let columns = vec![ColumnMetadata { name: "id".to_string(), data_type: crate::utils::types::DataType::Integer, nullable: true }];
+        let rows = RowBuffer::from_rows(1, vec![Row::new(vec![Value::Integer(1)])]);
+
+        let disk = || Arc::new(DiskCache::open(data.path().join(".cache"), 1024 * 1024).unwrap());
+        FileParseCache::new().with_disk(disk()).insert(&users, "", &columns, &rows);
+
+        let restarted = FileParseCache::new().with_disk(disk());
+        assert_eq!(restarted.get(&users, ""), Some((columns, rows)));
+        assert!(restarted.get(&users, "delimiter=;").is_none());
+
+        fs::write(&users, "id\n2\n").unwrap();
+        assert!(FileParseCache::new().with_disk(disk()).get(&users, "").is_none());
+    }
+}
@@ -0,0 +1,266 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::connectors::csv_options::CsvOptions;
+use crate::utils::{
+    error::{ConnectorError, NirvResult},
+    row_buffer::RowBuffer,
+    types::{ColumnMetadata, DataType, Value},
+};
+
+/// Date formats of typed date fields when none of the configured date formats match
+const FIXED_WIDTH_DATE_FORMATS: [&str; 2] = ["%Y%m%d", "%Y-%m-%d"];
+
+/// How a fixed-width field's text is read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FixedWidthType {
+    /// Typed from its content as a CSV field is
+    Inferred,
+    Text,
+    Integer,
+    Float,
+    /// A number written without its decimal point, with this many implied decimal places,
+    /// as in COBOL `PIC 9(5)V99` fields
+    Decimal(u32),
+    Date,
+}
+
+impl FixedWidthType {
+    fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        if let Some(scale) = name.strip_prefix("decimal(").and_then(|rest| rest.strip_suffix(')')) {
+            return scale.trim().parse().ok().map(FixedWidthType::Decimal);
+        }
+        match name.as_str() {
+            "" | "auto" => Some(FixedWidthType::Inferred),
+            "text" | "string" => Some(FixedWidthType::Text),
+            "integer" | "int" => Some(FixedWidthType::Integer),
+            "float" | "double" => Some(FixedWidthType::Float),
+            "decimal" => Some(FixedWidthType::Decimal(0)),
+            "date" => Some(FixedWidthType::Date),
+            _ => None,
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            FixedWidthType::Inferred | FixedWidthType::Text => DataType::Text,
+            FixedWidthType::Integer => DataType::Integer,
+            FixedWidthType::Float | FixedWidthType::Decimal(_) => DataType::Float,
+            FixedWidthType::Date => DataType::Date,
+        }
+    }
+}
+
+/// A column of a fixed-width file: `length` characters from the 0-based `offset` of each line
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedWidthField {
+    pub name: String,
+    pub offset: usize,
+    pub length: usize,
+    pub field_type: FixedWidthType,
+}
+
+/// The columns of a fixed-width file, such as a mainframe extract, written inline as
+/// `name:offset:length[:type]` fields separated by commas, or in a layout file with one
+/// `name offset length [type]` field per line and `#` comments
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedWidthLayout {
+    fields: Vec<FixedWidthField>,
+}
+
+impl FixedWidthLayout {
+    pub fn new(fields: Vec<FixedWidthField>) -> NirvResult<Self> {
+        if fields.is_empty() {
+            return Err(invalid_layout("it has no fields").into());
+        }
+        for (index, field) in fields.iter().enumerate() {
+            if field.length == 0 {
+                return Err(invalid_layout(&format!("field '{}' has no length", field.name)).into());
+            }
+            if fields[..index].iter().any(|other| other.name == field.name) {
+                return Err(invalid_layout(&format!("field '{}' appears twice", field.name)).into());
+            }
+        }
+        Ok(Self { fields })
+    }
+
+    /// Parse an inline layout: `id:0:6:integer,name:6:20,amount:26:9:decimal(2)`
+    pub fn parse(layout: &str) -> NirvResult<Self> {
+        let fields = layout.split(',')
+            .filter(|field| !field.trim().is_empty())
+            .map(|field| parse_field(&field.split(':').collect::<Vec<_>>(), field))
+            .collect::<NirvResult<Vec<_>>>()?;
+        Self::new(fields)
+    }
+
+    /// Load a layout file
+    pub fn load(path: &Path) -> NirvResult<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Failed to read layout file {}: {}", path.display(), e)
+            ))?;
+        let fields = content.lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| parse_field(&line.split_whitespace().collect::<Vec<_>>(), line))
+            .collect::<NirvResult<Vec<_>>>()?;
+        Self::new(fields)
+    }
+
+    pub fn fields(&self) -> &[FixedWidthField] {
+        &self.fields
+    }
+}
+
+fn invalid_layout(reason: &str) -> ConnectorError {
+    ConnectorError::QueryExecutionFailed(format!("Invalid fixed-width layout: {}", reason))
+}
+
+fn parse_field(parts: &[&str], written: &str) -> NirvResult<FixedWidthField> {
+    let invalid = || invalid_layout(&format!("expected name, offset, length and an optional type in '{}'", written.trim()));
+    let (name, offset, length, field_type) = match parts {
+        [name, offset, length] => (name, offset, length, ""),
+        [name, offset, length, field_type] => (name, offset, length, *field_type),
+        _ => return Err(invalid().into()),
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(invalid().into());
+    }
+    Ok(FixedWidthField {
+        name: name.to_string(),
+        offset: offset.trim().parse().map_err(|_| invalid())?,
+        length: length.trim().parse().map_err(|_| invalid())?,
+        field_type: FixedWidthType::parse(field_type)
+            .ok_or_else(|| invalid_layout(&format!("unknown type '{}' of field '{}'", field_type.trim(), name)))?,
+    })
+}
+
+/// Parse fixed-width text into one row per non-empty line. Fields are trimmed, a field
+/// past the end of a short line is NULL, and a typed field that does not hold its type is
+/// an error naming its line.
+pub(crate) fn parse_fixed_width_rows(content: Vec<u8>, layout: &FixedWidthLayout, options: &CsvOptions) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
+    let content = options.encoding.decode(content)?;
+    let columns: Vec<ColumnMetadata> = layout.fields.iter()
+        .map(|field| ColumnMetadata { name: field.name.clone(), data_type: field.field_type.data_type(), nullable: true })
+        .collect();
+
+    let mut rows = RowBuffer::new(columns.len());
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let values = layout.fields.iter().map(|field| {
+            let text: String = chars.iter().skip(field.offset).take(field.length).collect();
+            field_value(text.trim(), field, options).ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                format!("Line {}, field '{}': '{}' is not {}", index + 1, field.name, text.trim(), type_name(field.field_type))
+            ))
+        }).collect::<Result<Vec<_>, _>>()?;
+        rows.push_row(values);
+    }
+    Ok((columns, rows))
+}
+
+fn type_name(field_type: FixedWidthType) -> &'static str {
+    match field_type {
+        FixedWidthType::Integer => "an integer",
+        FixedWidthType::Float | FixedWidthType::Decimal(_) => "a number",
+        FixedWidthType::Date => "a date",
+        FixedWidthType::Inferred | FixedWidthType::Text => "text",
+    }
+}
+
+/// A field's value by its type, or None when its text is not of that type
+fn field_value(text: &str, field: &FixedWidthField, options: &CsvOptions) -> Option<Value> {
+    if text.is_empty() || options.null_token.as_deref() == Some(text) {
+        return Some(Value::Null);
+    }
+    match field.field_type {
+        FixedWidthType::Inferred => Some(options.field_value(text)),
+        FixedWidthType::Text => Some(Value::Text(text.to_string())),
+        FixedWidthType::Integer => signed(text).parse().ok().map(Value::Integer),
+        FixedWidthType::Float => signed(text).parse().ok().map(Value::Float),
+        FixedWidthType::Decimal(scale) => {
+            let number = signed(text);
+            if number.contains('.') {
+                number.parse().ok().map(Value::Float)
+            } else {
+                number.parse::<i64>().ok().map(|units| Value::Float(units as f64 / 10f64.powi(scale as i32)))
+            }
+        }
+        FixedWidthType::Date => options.parse_date(text).or_else(|| {
+            FIXED_WIDTH_DATE_FORMATS.iter()
+                .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+                .map(|date| Value::Date(date.format("%Y-%m-%d").to_string()))
+        }),
+    }
+}
+
+/// A number with a trailing sign, as mainframe extracts write them, moved to the front
+fn signed(text: &str) -> String {
+    match text.strip_suffix('-') {
+        Some(digits) => format!("-{}", digits.trim()),
+        None => text.strip_suffix('+').unwrap_or(text).trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTRACT: &str = "\
+000123ACME LTD            0000199920240501
+000124BOLT & SONS         0000050-20240502
+
+000125CRANE
+";
+
+    #[test]
+    fn test_fixed_width_rows() {
+        let layout = FixedWidthLayout::parse("id:0:6:integer,name:6:20,amount:26:8:decimal(2),shipped:34:8:date").unwrap();
+        let (columns, rows) = parse_fixed_width_rows(EXTRACT.as_bytes().to_vec(), &layout, &CsvOptions::default()).unwrap();
+        let types: Vec<DataType> = columns.iter().map(|column| column.data_type.clone()).collect();
+        assert_eq!(types, vec![DataType::Integer, DataType::Text, DataType::Float, DataType::Date]);
+
+        let rows = rows.into_rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].values, vec![
+            Value::Integer(123),
+            Value::Text("ACME LTD".to_string()),
+            Value::Float(19.99),
+            Value::Date("2024-05-01".to_string()),
+        ]);
+        assert_eq!(rows[1].values[2], Value::Float(-0.5));
+        assert_eq!(rows[2].values, vec![
+            Value::Integer(125),
+            Value::Text("CRANE".to_string()),
+            Value::Null,
+            Value::Null,
+        ]);
+
+        let layout = FixedWidthLayout::parse("id:0:6:date").unwrap();
+        let error = parse_fixed_width_rows(EXTRACT.as_bytes().to_vec(), &layout, &CsvOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("Line 1, field 'id': '000123' is not a date"), "{}", error);
+    }
+
+    #[test]
+    fn test_fixed_width_layouts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("orders.layout");
+        fs::write(&path, "# Order extract\nid     0  6 integer\nname   6 20   # padded\namount 26 8 decimal(2)\n").unwrap();
+        let loaded = FixedWidthLayout::load(&path).unwrap();
+        assert_eq!(loaded, FixedWidthLayout::parse("id:0:6:integer, name:6:20, amount:26:8:decimal(2)").unwrap());
+        assert_eq!(loaded.fields()[1].field_type, FixedWidthType::Inferred);
+
+        assert!(FixedWidthLayout::parse("").is_err());
+        assert!(FixedWidthLayout::parse("id:0").is_err());
+        assert!(FixedWidthLayout::parse("id:0:0").is_err());
+        assert!(FixedWidthLayout::parse("id:0:6:money").is_err());
+        assert!(FixedWidthLayout::parse("id:0:6,id:6:2").is_err());
+        assert!(FixedWidthLayout::load(&dir.path().join("missing.layout")).is_err());
+    }
+}
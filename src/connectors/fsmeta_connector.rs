@@ -0,0 +1,335 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use glob::{MatchOptions, Pattern};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::engine::filter_rows;
+use crate::utils::{
+    types::{
+        ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, DataType, Predicate, PredicateOperator,
+        PredicateValue, Row, Value,
+    },
+    error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
+    collation::Collation,
+    time_zone::format_utc,
+};
+
+/// Object type of file metadata sources, as in `source('fsmeta./var/log/**')`
+pub const FSMETA_OBJECT_TYPE: &str = "fsmeta";
+
+/// Columns of every file metadata source, one row per file
+const FILE_COLUMNS: [(&str, DataType); 5] = [
+    ("path", DataType::Text),
+    ("size", DataType::Integer),
+    ("mtime", DataType::DateTime),
+    ("owner", DataType::Text),
+    ("extension", DataType::Text),
+];
+
+/// Files whose rows are filtered together while walking
+const FILTER_BATCH: usize = 512;
+
+/// Connector listing files matched by a glob, with their size, modification time, owner
+/// and extension; contents are never read. The walk starts at the pattern's deepest
+/// directory without wildcards, and skips directories that `path` predicates rule out.
+/// Entries that cannot be read, such as directories without permission, are skipped.
+pub struct FsMetaConnector {
+    /// Directory relative patterns are resolved against
+    base_path: PathBuf,
+    connected: bool,
+}
+
+impl FsMetaConnector {
+    pub fn new() -> Self {
+        Self {
+            base_path: PathBuf::from("."),
+            connected: false,
+        }
+    }
+
+    fn columns() -> Vec<ColumnMetadata> {
+        FILE_COLUMNS.iter()
+            .map(|(name, data_type)| ColumnMetadata { name: name.to_string(), data_type: data_type.clone(), nullable: *name != "path" })
+            .collect()
+    }
+
+    /// Walk the files a pattern matches, keeping those the predicates match, up to a limit
+    fn scan(base_path: &Path, pattern: &str, predicates: &[Predicate], limit: Option<usize>) -> NirvResult<Vec<Row>> {
+        let scan = FileScan::new(base_path, pattern, predicates)?;
+        let mut rows = Vec::new();
+        let mut batch = Vec::new();
+        let walker = WalkDir::new(&scan.root)
+            .follow_links(false)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| !entry.file_type().is_dir() || scan.may_contain_matches(entry.path()));
+        for entry in walker.filter_map(Result::ok) {
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let Some(row) = scan.file_row(&entry) else { continue };
+            batch.push(row);
+            if batch.len() == FILTER_BATCH {
+                rows.extend(Self::matching(std::mem::take(&mut batch), predicates)?);
+                if limit.is_some_and(|limit| rows.len() >= limit) {
+                    break;
+                }
+            }
+        }
+        rows.extend(Self::matching(batch, predicates)?);
+        if let Some(limit) = limit {
+            rows.truncate(limit);
+        }
+        Ok(rows)
+    }
+
+    fn matching(rows: Vec<Row>, predicates: &[Predicate]) -> NirvResult<Vec<Row>> {
+        let result = QueryResult { columns: Self::columns(), rows, ..Default::default() };
+        Ok(filter_rows(result, predicates, IdentifierCase::default(), Collation::default())?.rows)
+    }
+}
+
+impl Default for FsMetaConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A pattern resolved for walking: where to start, what to match, and the path prefixes
+/// predicates require
+struct FileScan {
+    root: PathBuf,
+    /// Prefix stripped from walked paths of relative patterns, so paths read as written
+    base: Option<PathBuf>,
+    pattern: Pattern,
+    required_prefixes: Vec<String>,
+}
+
+impl FileScan {
+    fn new(base_path: &Path, pattern: &str, predicates: &[Predicate]) -> NirvResult<Self> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err(ConnectorError::QueryExecutionFailed("fsmeta source needs a path or glob, as in fsmeta./var/log/**".to_string()).into());
+        }
+        let relative = Path::new(pattern).is_relative();
+        let resolve = |path: &str| if relative { base_path.join(path) } else { PathBuf::from(path) };
+
+        // A directory without wildcards stands for every file under it
+        let has_wildcards = pattern.contains(['*', '?', '[']);
+        let pattern = if !has_wildcards && resolve(pattern).is_dir() {
+            format!("{}/**", pattern.trim_end_matches('/'))
+        } else {
+            pattern.to_string()
+        };
+        let literal: Vec<&str> = pattern.split('/').take_while(|component| !component.contains(['*', '?', '['])).collect();
+        let root = match literal.join("/") {
+            root if root.is_empty() && !relative => "/".to_string(),
+            root if root.is_empty() => ".".to_string(),
+            root => root,
+        };
+
+        Ok(Self {
+            root: resolve(&root),
+            base: relative.then(|| base_path.to_path_buf()),
+            pattern: Pattern::new(&pattern).map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Invalid glob '{}': {}", pattern, e)
+            ))?,
+            required_prefixes: predicates.iter().filter_map(path_prefix).collect(),
+        })
+    }
+
+    /// A walked path as it is reported and matched: relative to the base path for
+    /// relative patterns
+    fn display_path(&self, path: &Path) -> String {
+        let path = self.base.as_ref().and_then(|base| path.strip_prefix(base).ok()).unwrap_or(path);
+        let path = path.strip_prefix(".").unwrap_or(path);
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Whether a directory can hold files every `path` prefix predicate allows
+    fn may_contain_matches(&self, dir: &Path) -> bool {
+        let dir = self.display_path(dir);
+        if dir.is_empty() {
+            return true;
+        }
+        let dir = format!("{}/", dir.trim_end_matches('/'));
+        self.required_prefixes.iter().all(|prefix| prefix.starts_with(&dir) || dir.starts_with(prefix.as_str()))
+    }
+
+    fn file_row(&self, entry: &DirEntry) -> Option<Row> {
+        let path = self.display_path(entry.path());
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        if !self.pattern.matches_with(&path, options) || !self.required_prefixes.iter().all(|prefix| path.starts_with(prefix.as_str())) {
+            return None;
+        }
+        let metadata = entry.metadata().ok()?;
+        let mtime = metadata.modified().ok()
+            .map(|time| format_utc(DateTime::<Utc>::from(time).naive_utc()))
+            .map_or(Value::Null, Value::DateTime);
+        let extension = entry.path().extension()
+            .map_or(Value::Null, |extension| Value::Text(extension.to_string_lossy().into_owned()));
+        Some(Row::new(vec![
+            Value::Text(path),
+            Value::Integer(metadata.len() as i64),
+            mtime,
+            file_owner(&metadata).map_or(Value::Null, Value::Text),
+            extension,
+        ]))
+    }
+}
+
+/// The literal start of the paths a `path` predicate allows
+fn path_prefix(predicate: &Predicate) -> Option<String> {
+    if !predicate.column.rsplit('.').next()?.eq_ignore_ascii_case("path") {
+        return None;
+    }
+    match (&predicate.operator, &predicate.value) {
+        (PredicateOperator::Equal, PredicateValue::String(path)) => Some(path.clone()),
+        (PredicateOperator::Like, PredicateValue::String(pattern)) => {
+            let prefix: String = pattern.chars().take_while(|c| *c != '%' && *c != '_').collect();
+            (!prefix.is_empty()).then_some(prefix)
+        }
+        _ => None,
+    }
+}
+
+/// The name of a file's owner, or its numeric user ID when no account has it
+#[cfg(unix)]
+fn file_owner(metadata: &std::fs::Metadata) -> Option<String> {
+    use std::collections::HashMap;
+    use std::os::unix::fs::MetadataExt;
+    use std::sync::OnceLock;
+
+    static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    let users = USERS.get_or_init(|| {
+        std::fs::read_to_string("/etc/passwd").unwrap_or_default().lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(':').collect();
+                Some((fields.get(2)?.parse().ok()?, fields.first()?.to_string()))
+            })
+            .collect()
+    });
+    let uid = metadata.uid();
+    Some(users.get(&uid).cloned().unwrap_or_else(|| uid.to_string()))
+}
+
+#[cfg(not(unix))]
+fn file_owner(_metadata: &std::fs::Metadata) -> Option<String> {
+    None
+}
+
+#[async_trait]
+impl Connector for FsMetaConnector {
+    async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
+        if let Some(base_path) = config.connection_params.get("base_path") {
+            self.base_path = PathBuf::from(base_path);
+        }
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let source = query.query.sources.first().ok_or_else(|| ConnectorError::QueryExecutionFailed(
+            "No data source specified in query".to_string()
+        ))?;
+        let base_path = self.base_path.clone();
+        let pattern = source.identifier.clone();
+        let predicates = query.query.predicates.clone();
+        let limit = query.query.limit.map(|limit| limit as usize);
+        // Walking directories blocks on the file system
+        let rows = tokio::task::spawn_blocking(move || Self::scan(&base_path, &pattern, &predicates, limit)).await
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("File walk failed: {}", e)))??;
+        Ok(QueryResult { columns: Self::columns(), rows, ..Default::default() })
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns: Self::columns(),
+            primary_key: Some(vec!["path".to_string()]),
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Custom(FSMETA_OBJECT_TYPE.to_string())
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities {
+            max_concurrent_queries: Some(4),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn predicate(column: &str, operator: PredicateOperator, value: &str) -> Predicate {
+        Predicate { column: column.to_string(), operator, value: PredicateValue::String(value.to_string()) }
+    }
+
+    fn paths(rows: &[Row]) -> Vec<String> {
+        rows.iter().map(|row| match &row.values[0] {
+            Value::Text(path) => path.clone(),
+            other => panic!("unexpected path {:?}", other),
+        }).collect()
+    }
+
+    #[test]
+    fn test_scan_by_glob_and_path_prefix() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("log/nginx")).unwrap();
+        fs::create_dir_all(dir.path().join("log/app")).unwrap();
+        fs::write(dir.path().join("log/nginx/access.log"), "GET /").unwrap();
+        fs::write(dir.path().join("log/nginx/error.log.1"), "").unwrap();
+        fs::write(dir.path().join("log/app/app.log"), "started\n").unwrap();
+        fs::write(dir.path().join("log/README"), "").unwrap();
+
+        let rows = FsMetaConnector::scan(dir.path(), "log/**/*.log", &[], None).unwrap();
+        assert_eq!(paths(&rows), vec!["log/app/app.log", "log/nginx/access.log"]);
+        assert_eq!(rows[1].values[1], Value::Integer(5));
+        assert_eq!(rows[1].values[4], Value::Text("log".to_string()));
+        assert!(matches!(rows[1].values[2], Value::DateTime(_)));
+
+        // A directory stands for every file under it; `*` stays within one directory
+        assert_eq!(FsMetaConnector::scan(dir.path(), "log", &[], None).unwrap().len(), 4);
+        assert_eq!(paths(&FsMetaConnector::scan(dir.path(), "log/*", &[], None).unwrap()), vec!["log/README"]);
+
+        let nginx = [predicate("path", PredicateOperator::Like, "log/nginx/%")];
+        assert_eq!(paths(&FsMetaConnector::scan(dir.path(), "log/**", &nginx, None).unwrap()), vec!["log/nginx/access.log", "log/nginx/error.log.1"]);
+        assert_eq!(FsMetaConnector::scan(dir.path(), "log/**", &nginx, Some(1)).unwrap().len(), 1);
+
+        let absolute = format!("{}/log/app/*", dir.path().display());
+        let rows = FsMetaConnector::scan(Path::new("/elsewhere"), &absolute, &[], None).unwrap();
+        assert_eq!(paths(&rows), vec![format!("{}/log/app/app.log", dir.path().display())]);
+
+        let scan = FileScan::new(dir.path(), "log/**", &nginx).unwrap();
+        assert!(scan.may_contain_matches(&dir.path().join("log")));
+        assert!(!scan.may_contain_matches(&dir.path().join("log/app")));
+        assert_eq!(FsMetaConnector::scan(&dir.path().join("log"), "**/*.log", &nginx[..0], None).unwrap().len(), 2);
+        assert!(FsMetaConnector::scan(dir.path(), "", &[], None).is_err());
+    }
+}
@@ -0,0 +1,475 @@
+use async_trait::async_trait;
+use chrono::{Duration as DateDuration, NaiveDate};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::debug_log::connector_debug_log;
+use crate::connectors::imap_protocol::{
+    decode_header, envelope_addresses, first_text_part, internal_date, snippet, FetchedMessage, ImapArg, ImapSession,
+    ImapValue, TextPart,
+};
+use crate::connectors::network::{connect_with_retry, with_timeout};
+use crate::engine::filter_rows;
+use crate::utils::{
+    types::{
+        ConnectorType, ConnectorQuery, QueryResult, QueryWarning, Schema, ColumnMetadata, DataType, InternalQuery,
+        Predicate, PredicateOperator, PredicateValue, Row, Value,
+    },
+    error::{ConnectorError, NirvError, NirvResult},
+    connector_settings::{ImapSettings, issues_error},
+    identifier::IdentifierCase,
+    collation::Collation,
+};
+
+/// Columns of every folder, one row per message
+const MESSAGE_COLUMNS: [(&str, DataType); 8] = [
+    ("uid", DataType::Integer),
+    ("message_id", DataType::Text),
+    ("from", DataType::Text),
+    ("to", DataType::Text),
+    ("subject", DataType::Text),
+    ("date", DataType::DateTime),
+    ("flags", DataType::Text),
+    ("snippet", DataType::Text),
+];
+
+/// Messages fetched per round trip when a query has no limit
+const FETCH_BATCH: usize = 100;
+
+/// Data items fetched for every message; snippets are fetched separately, only when needed
+const MESSAGE_ITEMS: &str = "UID FLAGS INTERNALDATE ENVELOPE";
+
+/// System flags a `flags` predicate can be searched by
+const SYSTEM_FLAGS: [&str; 5] = ["Seen", "Answered", "Flagged", "Deleted", "Draft"];
+
+/// Connector over an IMAP mailbox, queried as `source('imap.INBOX')` with one table per
+/// folder. Predicates on the sender, recipients, subject, date, flags and UID narrow an
+/// IMAP `SEARCH`, and messages are read newest first, at most `max_messages` per query.
+pub struct ImapConnector {
+    settings: Option<ImapSettings>,
+    session: Mutex<Option<ImapSession>>,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    connected: bool,
+}
+
+impl ImapConnector {
+    pub fn new() -> Self {
+        Self {
+            settings: None,
+            session: Mutex::new(None),
+            connect_timeout: Duration::from_secs(30),
+            read_timeout: Duration::from_secs(30),
+            connected: false,
+        }
+    }
+
+    fn settings(&self) -> NirvResult<&ImapSettings> {
+        self.settings.as_ref().filter(|_| self.connected)
+            .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected to IMAP server".to_string()).into())
+    }
+
+    fn columns() -> Vec<ColumnMetadata> {
+        MESSAGE_COLUMNS.iter()
+            .map(|(name, data_type)| ColumnMetadata { name: name.to_string(), data_type: data_type.clone(), nullable: *name != "uid" })
+            .collect()
+    }
+
+    async fn open_session(settings: &ImapSettings) -> NirvResult<ImapSession> {
+        let mut session = ImapSession::open(&settings.host, settings.port, settings.tls, settings.trust_cert).await?;
+        session.login(&settings.username, &settings.password).await?;
+        Ok(session)
+    }
+
+    /// Run an operation on the session, opening a new one if the last was lost. A session
+    /// that fails with a connection error is dropped, so the next query reconnects.
+    async fn with_session<T, F>(&self, description: &str, operation: F) -> NirvResult<T>
+    where
+        F: for<'s> FnOnce(&'s mut ImapSession) -> futures_util::future::BoxFuture<'s, NirvResult<T>>,
+    {
+        let settings = self.settings()?;
+        let mut session = self.session.lock().await;
+        if session.is_none() {
+            let opened = with_timeout(self.connect_timeout, "IMAP connection", Self::open_session(settings)).await?;
+            *session = Some(opened);
+        }
+        let result = with_timeout(self.read_timeout, description, operation(session.as_mut().unwrap())).await;
+        if matches!(&result, Err(NirvError::Connector(ConnectorError::ConnectionFailed(_) | ConnectorError::Timeout(_)))) {
+            *session = None;
+        }
+        result
+    }
+
+    async fn read_folder(&self, query: &InternalQuery, folder: &str) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+        let settings = self.settings()?;
+        let criteria = search_criteria(&query.predicates);
+        let with_snippets = needs_snippets(query);
+
+        let folder_name = folder.to_string();
+        let mut uids = self.with_session("IMAP search", |session| Box::pin(async move {
+            session.examine(&folder_name).await?;
+            session.uid_search(&criteria).await
+        })).await?;
+        uids.sort_unstable_by(|a, b| b.cmp(a));
+
+        let wanted = query.limit.map(|limit| limit as usize);
+        let batch = wanted.map_or(FETCH_BATCH, |limit| limit.clamp(1, FETCH_BATCH));
+        let readable = uids.len().min(settings.max_messages);
+        let mut result = QueryResult { columns: Self::columns(), ..Default::default() };
+        for chunk in uids[..readable].chunks(batch) {
+            let chunk = chunk.to_vec();
+            let snippet_length = settings.snippet_length;
+            let messages = self.with_session("IMAP fetch", |session| Box::pin(async move {
+                fetch_messages(session, &chunk, with_snippets, snippet_length).await
+            })).await?;
+
+            let fetched = QueryResult { columns: Self::columns(), rows: messages, ..Default::default() };
+            let matched = filter_rows(fetched, &query.predicates, IdentifierCase::default(), Collation::default())?;
+            result.rows.extend(matched.rows);
+            if wanted.is_some_and(|limit| result.rows.len() >= limit) {
+                break;
+            }
+        }
+
+        if let Some(limit) = wanted {
+            result.rows.truncate(limit);
+        }
+        if uids.len() > readable && wanted.is_none_or(|limit| result.rows.len() < limit) {
+            result.warnings.push(QueryWarning::capped("", result.rows.len() as u64, "max_messages"));
+        }
+        result.execution_time = start_time.elapsed();
+        connector_debug_log().result("imap", &result);
+        Ok(result)
+    }
+}
+
+impl Default for ImapConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rows for messages, newest first, with snippets from their first text part when asked for
+async fn fetch_messages(session: &mut ImapSession, uids: &[u32], with_snippets: bool, snippet_length: usize) -> NirvResult<Vec<Row>> {
+    let items = if with_snippets { format!("{} BODYSTRUCTURE", MESSAGE_ITEMS) } else { MESSAGE_ITEMS.to_string() };
+    let mut messages = session.uid_fetch(uids, &items).await?;
+    messages.sort_by_key(|message| std::cmp::Reverse(message.uid()));
+
+    let mut snippets = HashMap::new();
+    if with_snippets {
+        // Messages whose text is in the same section are fetched together
+        let mut sections: Vec<(TextPart, Vec<u32>)> = Vec::new();
+        for message in &messages {
+            let (Some(uid), Some(part)) = (message.uid(), message.get("BODYSTRUCTURE").and_then(first_text_part)) else { continue };
+            match sections.iter_mut().find(|(known, _)| known.section == part.section) {
+                Some((_, uids)) => uids.push(uid),
+                None => sections.push((part, vec![uid])),
+            }
+        }
+        // Encodings and markup take more bytes than the text they hold
+        let fetch_bytes = snippet_length * 4 + 256;
+        for (part, section_uids) in sections {
+            let bodies = session.uid_fetch(&section_uids, &format!("BODY.PEEK[{}]<0.{}>", part.section, fetch_bytes)).await?;
+            for body in bodies {
+                let Some(uid) = body.uid() else { continue };
+                let part = messages.iter()
+                    .find(|message| message.uid() == Some(uid))
+                    .and_then(|message| message.get("BODYSTRUCTURE").and_then(first_text_part))
+                    .unwrap_or_else(|| part.clone());
+                if let Some(ImapValue::Bytes(content)) = body.get("BODY[") {
+                    snippets.insert(uid, snippet(content, &part, snippet_length));
+                }
+            }
+        }
+    }
+
+    Ok(messages.iter().filter_map(|message| {
+        let uid = message.uid()?;
+        let snippet = snippets.remove(&uid);
+        Some(message_row(message, uid, snippet))
+    }).collect())
+}
+
+fn message_row(message: &FetchedMessage, uid: u32, snippet: Option<String>) -> Row {
+    let envelope = message.get("ENVELOPE").map(ImapValue::as_list).unwrap_or_default();
+    let text = |value: Option<String>| value.map_or(Value::Null, Value::Text);
+    let flags: Vec<String> = message.get("FLAGS").map(ImapValue::as_list).unwrap_or_default()
+        .iter().filter_map(ImapValue::as_text).collect();
+    Row::new(vec![
+        Value::Integer(uid as i64),
+        text(envelope.get(9).and_then(ImapValue::as_text)),
+        text(envelope.get(2).and_then(envelope_addresses)),
+        text(envelope.get(5).and_then(envelope_addresses)),
+        text(envelope.get(1).and_then(ImapValue::as_text).map(|subject| decode_header(&subject))),
+        message.get("INTERNALDATE").and_then(ImapValue::as_text).and_then(|date| internal_date(&date)).map_or(Value::Null, Value::DateTime),
+        Value::Text(flags.join(" ")),
+        text(snippet),
+    ])
+}
+
+/// Whether a query may read the `snippet` column: any column other than the message's
+/// own fields, such as `*` or an expression, could
+fn needs_snippets(query: &InternalQuery) -> bool {
+    let is_field = |name: &str| {
+        let name = column_name(name);
+        MESSAGE_COLUMNS.iter().any(|(column, _)| *column != "snippet" && column.eq_ignore_ascii_case(name))
+    };
+    query.projections.is_empty()
+        || !query.projections.iter().all(|column| is_field(&column.name))
+        || query.predicates.iter().any(|predicate| !is_field(&predicate.column))
+        || query.ordering.as_ref().is_some_and(|ordering| !ordering.columns.iter().all(|column| is_field(&column.column)))
+}
+
+/// A column name without its table qualifier
+fn column_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// Search keys narrowing a folder to the messages the predicates could match. Each key
+/// matches at least the messages its predicate does; the predicates are then applied
+/// exactly to the messages read.
+fn search_criteria(predicates: &[Predicate]) -> Vec<ImapArg> {
+    let criteria: Vec<ImapArg> = predicates.iter().flat_map(search_keys).collect();
+    if criteria.is_empty() {
+        return vec![ImapArg::atom("ALL")];
+    }
+    criteria
+}
+
+/// The search keys a predicate narrows the folder by, none when it cannot
+fn search_keys(predicate: &Predicate) -> Vec<ImapArg> {
+    let column = column_name(&predicate.column).to_lowercase();
+    let keyword = match column.as_str() {
+        "from" => "FROM",
+        "to" => "TO",
+        "subject" => "SUBJECT",
+        "snippet" => "BODY",
+        "message_id" => return match (&predicate.operator, &predicate.value) {
+            (PredicateOperator::Equal, PredicateValue::String(id)) => vec![
+                ImapArg::atom("HEADER"), ImapArg::Text("Message-ID".to_string()), ImapArg::Text(id.clone()),
+            ],
+            _ => Vec::new(),
+        },
+        "uid" => return uid_keys(&predicate.operator, &predicate.value),
+        "date" => return date_keys(&predicate.operator, &predicate.value),
+        "flags" => return flag_keys(&predicate.operator, &predicate.value),
+        _ => return Vec::new(),
+    };
+    // IMAP matches substrings, so equality and patterns search by their longest literal run
+    let substring = |value: &PredicateValue| match (&predicate.operator, value) {
+        (PredicateOperator::Equal | PredicateOperator::In, PredicateValue::String(text)) => Some(text.clone()),
+        (PredicateOperator::Like, PredicateValue::String(pattern)) => pattern.split(['%', '_'])
+            .max_by_key(|run| run.chars().count())
+            .filter(|run| !run.is_empty())
+            .map(str::to_string),
+        _ => None,
+    };
+    match (&predicate.operator, &predicate.value) {
+        (PredicateOperator::In, PredicateValue::List(values)) => {
+            let Some(texts) = values.iter().map(substring).collect::<Option<Vec<_>>>() else { return Vec::new() };
+            // OR takes two keys, so a list nests as `OR k1 OR k2 k3`
+            let mut keys = Vec::new();
+            for (index, text) in texts.iter().enumerate() {
+                if index + 1 < texts.len() {
+                    keys.push(ImapArg::atom("OR"));
+                }
+                keys.extend([ImapArg::atom(keyword), ImapArg::Text(text.clone())]);
+            }
+            keys
+        }
+        (_, value) => substring(value).map_or_else(Vec::new, |text| vec![ImapArg::atom(keyword), ImapArg::Text(text)]),
+    }
+}
+
+fn uid_keys(operator: &PredicateOperator, value: &PredicateValue) -> Vec<ImapArg> {
+    let set = match (operator, value) {
+        (PredicateOperator::Equal, PredicateValue::Integer(uid)) if *uid > 0 => uid.to_string(),
+        (PredicateOperator::In, PredicateValue::List(values)) => {
+            let uids: Vec<String> = values.iter()
+                .filter_map(|value| match value {
+                    PredicateValue::Integer(uid) if *uid > 0 => Some(uid.to_string()),
+                    _ => None,
+                })
+                .collect();
+            if uids.len() != values.len() || uids.is_empty() {
+                return Vec::new();
+            }
+            uids.join(",")
+        }
+        (PredicateOperator::GreaterThan, PredicateValue::Integer(uid)) => format!("{}:*", (*uid + 1).max(1)),
+        (PredicateOperator::GreaterThanOrEqual, PredicateValue::Integer(uid)) => format!("{}:*", (*uid).max(1)),
+        (PredicateOperator::LessThan, PredicateValue::Integer(uid)) if *uid > 1 => format!("1:{}", uid - 1),
+        (PredicateOperator::LessThanOrEqual, PredicateValue::Integer(uid)) if *uid > 0 => format!("1:{}", uid),
+        _ => return Vec::new(),
+    };
+    vec![ImapArg::atom("UID"), ImapArg::atom(set)]
+}
+
+/// `SINCE` and `BEFORE` compare whole days in the server's time zone, so the range
+/// searched is a day wider on each side than the predicate's
+fn date_keys(operator: &PredicateOperator, value: &PredicateValue) -> Vec<ImapArg> {
+    let PredicateValue::String(text) = value else { return Vec::new() };
+    let Some(date) = text.get(..10).and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()) else { return Vec::new() };
+    let day = |offset: i64| ImapArg::atom((date + DateDuration::days(offset)).format("%d-%b-%Y").to_string());
+    match operator {
+        PredicateOperator::GreaterThan | PredicateOperator::GreaterThanOrEqual => vec![ImapArg::atom("SINCE"), day(-1)],
+        PredicateOperator::LessThan | PredicateOperator::LessThanOrEqual => vec![ImapArg::atom("BEFORE"), day(2)],
+        PredicateOperator::Equal => vec![ImapArg::atom("SINCE"), day(-1), ImapArg::atom("BEFORE"), day(2)],
+        _ => Vec::new(),
+    }
+}
+
+/// Flags are searched by the system flags a pattern or value names, as in `flags LIKE '%\Seen%'`
+fn flag_keys(operator: &PredicateOperator, value: &PredicateValue) -> Vec<ImapArg> {
+    match (operator, value) {
+        (PredicateOperator::Equal | PredicateOperator::Like, PredicateValue::String(text)) => {
+            let text = text.to_lowercase();
+            SYSTEM_FLAGS.iter()
+                .filter(|flag| text.contains(&format!("\\{}", flag.to_lowercase())))
+                .map(|flag| ImapArg::atom(flag.to_uppercase()))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[async_trait]
+impl Connector for ImapConnector {
+    async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
+        let settings = ImapSettings::from_params(&config.connection_params).map_err(issues_error)?;
+        let description = format!("Connecting to IMAP server {}:{}", settings.host, settings.port);
+        let session = connect_with_retry(&config.retry_policy, config.connect_timeout(), &description, || Self::open_session(&settings)).await?;
+
+        *self.session.get_mut() = Some(session);
+        self.connect_timeout = config.connect_timeout();
+        self.read_timeout = config.read_timeout();
+        self.settings = Some(settings);
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let source = query.query.sources.first().ok_or_else(|| ConnectorError::QueryExecutionFailed(
+            "No data source specified in query".to_string()
+        ))?;
+        self.read_folder(&query.query, &source.identifier).await
+    }
+
+    async fn count_rows(&self, query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        // Searches can narrow too widely to count by, so only a whole folder is counted
+        if !query.query.predicates.is_empty() {
+            return Ok(None);
+        }
+        let Some(source) = query.query.sources.first() else { return Ok(None) };
+        let folder = source.identifier.clone();
+        let uids = self.with_session("IMAP search", |session| Box::pin(async move {
+            session.examine(&folder).await?;
+            session.uid_search(&[ImapArg::atom("ALL")]).await
+        })).await?;
+        Ok(Some(uids.len() as u64))
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        let folder = object_name.to_string();
+        self.with_session("IMAP examine", |session| Box::pin(async move { session.examine(&folder).await }))
+            .await
+            .map_err(|e| ConnectorError::SchemaRetrievalFailed(format!("Folder '{}' cannot be read: {}", object_name, e)))?;
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns: Self::columns(),
+            primary_key: Some(vec!["uid".to_string()]),
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        self.with_session("IMAP list", |session| Box::pin(session.list_folders())).await
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        if let Some(mut session) = self.session.get_mut().take() {
+            // The server may already have closed the connection
+            let _ = with_timeout(self.read_timeout, "IMAP logout", session.logout()).await;
+        }
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Imap
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{Column, QueryOperation};
+
+    fn criteria(predicates: &[Predicate]) -> Vec<String> {
+        search_criteria(predicates).into_iter().map(|arg| match arg {
+            ImapArg::Atom(atom) => atom,
+            ImapArg::Text(text) => format!("\"{}\"", text),
+        }).collect()
+    }
+
+    fn predicate(column: &str, operator: PredicateOperator, value: PredicateValue) -> Predicate {
+        Predicate { column: column.to_string(), operator, value }
+    }
+
+    #[test]
+    fn test_search_criteria_from_predicates() {
+        let text = |text: &str| PredicateValue::String(text.to_string());
+        assert_eq!(criteria(&[]), vec!["ALL"]);
+        assert_eq!(criteria(&[
+            predicate("m.from", PredicateOperator::Like, text("%@acme.%")),
+            predicate("subject", PredicateOperator::Equal, text("Ticket #42")),
+            predicate("date", PredicateOperator::GreaterThanOrEqual, text("2024-05-01")),
+            predicate("flags", PredicateOperator::Like, text("%\\Flagged%")),
+            predicate("uid", PredicateOperator::GreaterThan, PredicateValue::Integer(100)),
+        ]), vec!["FROM", "\"@acme.\"", "SUBJECT", "\"Ticket #42\"", "SINCE", "30-Apr-2024", "FLAGGED", "UID", "101:*"]);
+
+        let senders = PredicateValue::List(vec![text("ann@acme.io"), text("bo@acme.io"), text("cy@acme.io")]);
+        assert_eq!(
+            criteria(&[predicate("from", PredicateOperator::In, senders)]),
+            vec!["OR", "FROM", "\"ann@acme.io\"", "OR", "FROM", "\"bo@acme.io\"", "FROM", "\"cy@acme.io\""]
+        );
+        assert_eq!(
+            criteria(&[predicate("date", PredicateOperator::Equal, text("2024-05-01 10:00:00"))]),
+            vec!["SINCE", "30-Apr-2024", "BEFORE", "03-May-2024"]
+        );
+        // Predicates IMAP cannot search by are only applied to the messages read
+        assert_eq!(criteria(&[
+            predicate("subject", PredicateOperator::NotEqual, text("spam")),
+            predicate("from", PredicateOperator::Like, text("%")),
+        ]), vec!["ALL"]);
+    }
+
+    #[test]
+    fn test_snippets_fetched_only_when_read() {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.projections = vec![Column { name: "subject".to_string(), alias: None, source: None }];
+        assert!(!needs_snippets(&query));
+        query.predicates.push(predicate("snippet", PredicateOperator::Like, PredicateValue::String("%refund%".to_string())));
+        assert!(needs_snippets(&query));
+        query.predicates.clear();
+        query.projections.push(Column { name: "*".to_string(), alias: None, source: None });
+        assert!(needs_snippets(&query));
+    }
+}
@@ -0,0 +1,576 @@
+use base64::prelude::*;
+use chrono::DateTime;
+use regex::Regex;
+use std::sync::OnceLock;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio_native_tls::native_tls;
+
+use crate::connectors::debug_log::connector_debug_log;
+use crate::utils::{
+    error::{ConnectorError, NirvError, NirvResult},
+    time_zone::format_utc,
+};
+
+/// A connection an IMAP session runs over, plain or TLS
+pub(crate) trait ImapStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ImapStream for T {}
+
+/// A value of an IMAP response: `NIL`, an atom or number, a quoted or literal string,
+/// or a parenthesized list
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ImapValue {
+    Nil,
+    Atom(String),
+    Bytes(Vec<u8>),
+    List(Vec<ImapValue>),
+}
+
+impl ImapValue {
+    pub(crate) fn as_text(&self) -> Option<String> {
+        match self {
+            ImapValue::Atom(text) => Some(text.clone()),
+            ImapValue::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            ImapValue::Nil | ImapValue::List(_) => None,
+        }
+    }
+
+    pub(crate) fn as_list(&self) -> &[ImapValue] {
+        match self {
+            ImapValue::List(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+/// An argument of a command: an atom sent as written, or a string quoted, or sent as a
+/// literal when it is not plain ASCII
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ImapArg {
+    Atom(String),
+    Text(String),
+}
+
+impl ImapArg {
+    pub(crate) fn atom(atom: impl Into<String>) -> Self {
+        ImapArg::Atom(atom.into())
+    }
+
+    fn is_literal(&self) -> bool {
+        matches!(self, ImapArg::Text(text) if !text.bytes().all(|byte| byte.is_ascii() && byte != b'\r' && byte != b'\n'))
+    }
+}
+
+/// The data items of one message in a `FETCH` response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FetchedMessage {
+    items: Vec<(String, ImapValue)>,
+}
+
+impl FetchedMessage {
+    /// The item named `name`, or for a name ending in `[`, the first body section
+    pub(crate) fn get(&self, name: &str) -> Option<&ImapValue> {
+        self.items.iter()
+            .find(|(key, _)| if name.ends_with('[') { key.to_uppercase().starts_with(name) } else { key.eq_ignore_ascii_case(name) })
+            .map(|(_, value)| value)
+    }
+
+    pub(crate) fn uid(&self) -> Option<u32> {
+        self.get("UID")?.as_text()?.parse().ok()
+    }
+}
+
+/// An IMAP4rev1 client session
+pub(crate) struct ImapSession {
+    stream: BufStream<Box<dyn ImapStream>>,
+    next_tag: u32,
+}
+
+impl ImapSession {
+    /// Connect to a server and read its greeting
+    pub(crate) async fn open(host: &str, port: u16, tls: bool, trust_cert: bool) -> NirvResult<Self> {
+        let tcp = TcpStream::connect((host, port)).await
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to connect to IMAP server {}:{}: {}", host, port, e)))?;
+        let stream: Box<dyn ImapStream> = if tls {
+            let connector = native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(trust_cert)
+                .build()
+                .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to set up TLS: {}", e)))?;
+            Box::new(tokio_native_tls::TlsConnector::from(connector).connect(host, tcp).await
+                .map_err(|e| ConnectorError::ConnectionFailed(format!("TLS handshake with {} failed: {}", host, e)))?)
+        } else {
+            Box::new(tcp)
+        };
+        Self::start(stream).await
+    }
+
+    pub(crate) async fn start(stream: Box<dyn ImapStream>) -> NirvResult<Self> {
+        let mut session = Self { stream: BufStream::new(stream), next_tag: 1 };
+        let greeting = session.read_response().await?;
+        if !greeting.starts_with(b"* OK") && !greeting.starts_with(b"* PREAUTH") {
+            return Err(ConnectorError::ConnectionFailed(
+                format!("IMAP server refused the connection: {}", String::from_utf8_lossy(&greeting).trim())
+            ).into());
+        }
+        Ok(session)
+    }
+
+    pub(crate) async fn login(&mut self, username: &str, password: &str) -> NirvResult<()> {
+        match self.command("LOGIN", &[ImapArg::Text(username.to_string()), ImapArg::Text(password.to_string())]).await {
+            Err(NirvError::Connector(ConnectorError::QueryExecutionFailed(message))) => Err(ConnectorError::ConnectionFailed(message).into()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// Open a folder read-only, so reading messages leaves their flags alone
+    pub(crate) async fn examine(&mut self, folder: &str) -> NirvResult<()> {
+        self.command("EXAMINE", &[ImapArg::Text(folder.to_string())]).await?;
+        Ok(())
+    }
+
+    /// The names of the folders that can be opened
+    pub(crate) async fn list_folders(&mut self) -> NirvResult<Vec<String>> {
+        let responses = self.command("LIST", &[ImapArg::Text(String::new()), ImapArg::atom("*")]).await?;
+        Ok(responses.iter().filter_map(|response| {
+            let values = parse_values(response.strip_prefix(b"LIST ")?);
+            let selectable = !values.first()?.as_list().iter()
+                .any(|flag| flag.as_text().is_some_and(|flag| flag.eq_ignore_ascii_case("\\Noselect")));
+            selectable.then(|| values.get(2)?.as_text()).flatten()
+        }).collect())
+    }
+
+    /// The UIDs of the messages of the open folder matching search keys
+    pub(crate) async fn uid_search(&mut self, criteria: &[ImapArg]) -> NirvResult<Vec<u32>> {
+        let mut args = Vec::new();
+        if criteria.iter().any(ImapArg::is_literal) {
+            args.extend([ImapArg::atom("CHARSET"), ImapArg::atom("UTF-8")]);
+        }
+        args.extend_from_slice(criteria);
+        let responses = self.command("UID SEARCH", &args).await?;
+        Ok(responses.iter()
+            .filter_map(|response| response.strip_prefix(b"SEARCH"))
+            .flat_map(|uids| String::from_utf8_lossy(uids).split_whitespace().filter_map(|uid| uid.parse().ok()).collect::<Vec<_>>())
+            .collect())
+    }
+
+    /// Fetch data items of the messages with the given UIDs
+    pub(crate) async fn uid_fetch(&mut self, uids: &[u32], items: &str) -> NirvResult<Vec<FetchedMessage>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let set = uids.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+        let responses = self.command("UID FETCH", &[ImapArg::atom(set), ImapArg::atom(format!("({})", items))]).await?;
+        Ok(responses.iter().filter_map(|response| {
+            let start = response.windows(7).position(|window| window.eq_ignore_ascii_case(b" FETCH "))?;
+            let values = parse_values(&response[start + 7..]);
+            let mut items = Vec::new();
+            let mut data = values.first()?.as_list().iter();
+            while let (Some(key), Some(value)) = (data.next(), data.next()) {
+                items.push((key.as_text()?, value.clone()));
+            }
+            Some(FetchedMessage { items })
+        }).collect())
+    }
+
+    pub(crate) async fn logout(&mut self) -> NirvResult<()> {
+        self.command("LOGOUT", &[]).await?;
+        Ok(())
+    }
+
+    /// Send a command and collect its untagged responses, without their leading `* `,
+    /// failing when it completes with `NO` or `BAD`
+    async fn command(&mut self, name: &str, args: &[ImapArg]) -> NirvResult<Vec<Vec<u8>>> {
+        let tag = format!("A{}", self.next_tag);
+        self.next_tag += 1;
+        connector_debug_log().request("imap", &describe_command(name, args));
+
+        let mut line = format!("{} {}", tag, name).into_bytes();
+        for arg in args {
+            line.push(b' ');
+            match arg {
+                ImapArg::Atom(atom) => line.extend_from_slice(atom.as_bytes()),
+                ImapArg::Text(text) if arg.is_literal() => {
+                    // The server asks for a literal's bytes with a continuation
+                    line.extend_from_slice(format!("{{{}}}\r\n", text.len()).as_bytes());
+                    self.write(&line).await?;
+                    line.clear();
+                    let response = self.read_response().await?;
+                    if !response.starts_with(b"+") {
+                        return Err(command_failed(name, &response));
+                    }
+                    line.extend_from_slice(text.as_bytes());
+                }
+                ImapArg::Text(text) => line.extend_from_slice(quote(text).as_bytes()),
+            }
+        }
+        line.extend_from_slice(b"\r\n");
+        self.write(&line).await?;
+
+        let mut untagged = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            if let Some(data) = response.strip_prefix(b"* ") {
+                if data.starts_with(b"BYE") && name != "LOGOUT" {
+                    return Err(ConnectorError::ConnectionFailed(
+                        format!("IMAP server closed the session: {}", String::from_utf8_lossy(data).trim())
+                    ).into());
+                }
+                untagged.push(data.to_vec());
+            } else if let Some(status) = response.strip_prefix(format!("{} ", tag).as_bytes()) {
+                connector_debug_log().response("imap", String::from_utf8_lossy(status).trim());
+                if status.len() >= 2 && status[..2].eq_ignore_ascii_case(b"OK") {
+                    return Ok(untagged);
+                }
+                return Err(command_failed(name, status));
+            }
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> NirvResult<()> {
+        self.stream.write_all(bytes).await.map_err(connection_lost)?;
+        self.stream.flush().await.map_err(connection_lost)?;
+        Ok(())
+    }
+
+    /// Read one response line, with the literals it announces
+    async fn read_response(&mut self) -> NirvResult<Vec<u8>> {
+        let mut response = Vec::new();
+        loop {
+            let start = response.len();
+            if self.stream.read_until(b'\n', &mut response).await.map_err(connection_lost)? == 0 {
+                return Err(ConnectorError::ConnectionFailed("IMAP server closed the connection".to_string()).into());
+            }
+            match literal_length(&response[start..]) {
+                Some(length) => {
+                    let mut literal = vec![0; length];
+                    self.stream.read_exact(&mut literal).await.map_err(connection_lost)?;
+                    response.extend_from_slice(&literal);
+                }
+                None => return Ok(response),
+            }
+        }
+    }
+}
+
+fn connection_lost(error: std::io::Error) -> ConnectorError {
+    ConnectorError::ConnectionFailed(format!("IMAP connection lost: {}", error))
+}
+
+fn command_failed(name: &str, status: &[u8]) -> NirvError {
+    ConnectorError::QueryExecutionFailed(format!("IMAP {} failed: {}", name, String::from_utf8_lossy(status).trim())).into()
+}
+
+/// A command as logged, without the password of a `LOGIN`
+fn describe_command(name: &str, args: &[ImapArg]) -> String {
+    let shown = if name == "LOGIN" { &args[..args.len().min(1)] } else { args };
+    let args: Vec<String> = shown.iter().map(|arg| match arg {
+        ImapArg::Atom(atom) => atom.clone(),
+        ImapArg::Text(text) => quote(text),
+    }).collect();
+    format!("{} {}", name, args.join(" ")).trim_end().to_string()
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The length of the literal a line announces by ending in `{n}`
+fn literal_length(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"\r\n").or_else(|| line.strip_suffix(b"\n"))?;
+    let open = line.iter().rposition(|&byte| byte == b'{')?;
+    std::str::from_utf8(line.get(open + 1..line.len().checked_sub(1)?)?).ok()?.parse().ok()
+        .filter(|_| line.ends_with(b"}"))
+}
+
+/// Parse the values of a response line, stopping at its end
+pub(crate) fn parse_values(bytes: &[u8]) -> Vec<ImapValue> {
+    let mut position = 0;
+    parse_list(bytes, &mut position)
+}
+
+fn parse_list(bytes: &[u8], position: &mut usize) -> Vec<ImapValue> {
+    let mut values = Vec::new();
+    while *position < bytes.len() {
+        match bytes[*position] {
+            b' ' => *position += 1,
+            b'\r' | b'\n' => break,
+            b')' => {
+                *position += 1;
+                break;
+            }
+            b'(' => {
+                *position += 1;
+                values.push(ImapValue::List(parse_list(bytes, position)));
+            }
+            b'"' => values.push(ImapValue::Bytes(parse_quoted(bytes, position))),
+            b'{' => match parse_literal(bytes, position) {
+                Some(literal) => values.push(ImapValue::Bytes(literal)),
+                None => break,
+            },
+            _ => {
+                let atom = parse_atom(bytes, position);
+                values.push(if atom.eq_ignore_ascii_case("NIL") { ImapValue::Nil } else { ImapValue::Atom(atom) });
+            }
+        }
+    }
+    values
+}
+
+fn parse_quoted(bytes: &[u8], position: &mut usize) -> Vec<u8> {
+    let mut text = Vec::new();
+    *position += 1;
+    while *position < bytes.len() {
+        let byte = bytes[*position];
+        *position += 1;
+        match byte {
+            b'"' => break,
+            b'\\' if *position < bytes.len() => {
+                text.push(bytes[*position]);
+                *position += 1;
+            }
+            _ => text.push(byte),
+        }
+    }
+    text
+}
+
+fn parse_literal(bytes: &[u8], position: &mut usize) -> Option<Vec<u8>> {
+    let close = *position + bytes[*position..].iter().position(|&byte| byte == b'}')?;
+    let length: usize = std::str::from_utf8(&bytes[*position + 1..close]).ok()?.parse().ok()?;
+    let start = close + 1 + bytes.get(close + 1..)?.iter().take_while(|&&byte| byte == b'\r' || byte == b'\n').count();
+    let literal = bytes.get(start..start + length)?.to_vec();
+    *position = start + length;
+    Some(literal)
+}
+
+/// An atom, which runs on through the brackets of a section such as `BODY[HEADER.FIELDS (TO)]`
+fn parse_atom(bytes: &[u8], position: &mut usize) -> String {
+    let start = *position;
+    let mut depth = 0;
+    while *position < bytes.len() {
+        match bytes[*position] {
+            b'[' => depth += 1,
+            b']' => depth -= 1,
+            b' ' | b'(' | b')' | b'\r' | b'\n' if depth <= 0 => break,
+            _ => {}
+        }
+        *position += 1;
+    }
+    String::from_utf8_lossy(&bytes[start..*position]).into_owned()
+}
+
+/// The addresses of an envelope address list as `mailbox@host`, separated by commas
+pub(crate) fn envelope_addresses(addresses: &ImapValue) -> Option<String> {
+    let addresses: Vec<String> = addresses.as_list().iter().filter_map(|address| {
+        let parts = address.as_list();
+        let mailbox = parts.get(2)?.as_text()?;
+        // A group's start and end have no host
+        let host = parts.get(3)?.as_text()?;
+        Some(format!("{}@{}", mailbox, host))
+    }).collect();
+    (!addresses.is_empty()).then(|| addresses.join(", "))
+}
+
+/// An `INTERNALDATE` such as `01-May-2024 10:00:00 +0200` as the engine's UTC date-time text
+pub(crate) fn internal_date(text: &str) -> Option<String> {
+    DateTime::parse_from_str(text.trim(), "%d-%b-%Y %H:%M:%S %z").ok()
+        .map(|time| format_utc(time.naive_utc()))
+}
+
+/// Header text with its RFC 2047 encoded words, like `=?UTF-8?Q?caf=C3=A9?=`, decoded
+pub(crate) fn decode_header(text: &str) -> String {
+    static ENCODED_WORD: OnceLock<Regex> = OnceLock::new();
+    let encoded_word = ENCODED_WORD.get_or_init(|| Regex::new(r"=\?([^?\s]+)\?([BbQq])\?([^?\s]*)\?=").unwrap());
+
+    let mut decoded = String::new();
+    let mut last = 0;
+    for word in encoded_word.captures_iter(text) {
+        let whole = word.get(0).unwrap();
+        let between = &text[last..whole.start()];
+        // Space between adjacent encoded words is not part of the text
+        if last == 0 || !between.trim().is_empty() {
+            decoded.push_str(between);
+        }
+        let bytes = match &word[2] {
+            "B" | "b" => BASE64_STANDARD.decode(&word[3]).ok(),
+            _ => Some(decode_quoted_printable(word[3].as_bytes(), true)),
+        };
+        match bytes {
+            Some(bytes) => decoded.push_str(&decode_charset(&bytes, &word[1])),
+            None => decoded.push_str(whole.as_str()),
+        }
+        last = whole.end();
+    }
+    decoded.push_str(&text[last..]);
+    decoded
+}
+
+/// Decode quoted-printable bytes; in headers `_` stands for a space
+pub(crate) fn decode_quoted_printable(bytes: &[u8], header: bool) -> Vec<u8> {
+    let hex = |byte: u8| (byte as char).to_digit(16);
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'=' => match (bytes.get(index + 1), bytes.get(index + 2)) {
+                // A soft line break
+                (Some(b'\r'), Some(b'\n')) => index += 3,
+                (Some(b'\n'), _) => index += 2,
+                (Some(&high), Some(&low)) if hex(high).is_some() && hex(low).is_some() => {
+                    decoded.push((hex(high).unwrap() * 16 + hex(low).unwrap()) as u8);
+                    index += 3;
+                }
+                _ => {
+                    decoded.push(b'=');
+                    index += 1;
+                }
+            },
+            b'_' if header => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Text in a MIME charset; Latin-1 family charsets map bytes to code points, and any
+/// other is read as UTF-8
+pub(crate) fn decode_charset(bytes: &[u8], charset: &str) -> String {
+    match charset.to_lowercase().as_str() {
+        "iso-8859-1" | "latin1" | "windows-1252" | "us-ascii" => bytes.iter().map(|&byte| char::from(byte)).collect(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// The first text part of a message, found in its `BODYSTRUCTURE`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TextPart {
+    /// Section number, as in `BODY[1.2]`
+    pub section: String,
+    pub subtype: String,
+    pub encoding: String,
+    pub charset: String,
+}
+
+pub(crate) fn first_text_part(structure: &ImapValue) -> Option<TextPart> {
+    find_text_part(structure.as_list(), "")
+}
+
+fn find_text_part(part: &[ImapValue], path: &str) -> Option<TextPart> {
+    let section = |index: usize| if path.is_empty() { (index + 1).to_string() } else { format!("{}.{}", path, index + 1) };
+    if matches!(part.first(), Some(ImapValue::List(_))) {
+        // A multipart body lists its parts, then its subtype
+        return part.iter().enumerate()
+            .take_while(|(_, child)| matches!(child, ImapValue::List(_)))
+            .find_map(|(index, child)| find_text_part(child.as_list(), &section(index)));
+    }
+    if !part.first()?.as_text()?.eq_ignore_ascii_case("TEXT") {
+        return None;
+    }
+    let params = part.get(2).map(ImapValue::as_list).unwrap_or_default();
+    let charset = params.chunks(2)
+        .find(|pair| pair[0].as_text().is_some_and(|name| name.eq_ignore_ascii_case("CHARSET")))
+        .and_then(|pair| pair.get(1)?.as_text())
+        .unwrap_or_else(|| "utf-8".to_string());
+    Some(TextPart {
+        section: if path.is_empty() { "1".to_string() } else { path.to_string() },
+        subtype: part.get(1)?.as_text()?.to_uppercase(),
+        encoding: part.get(5).and_then(ImapValue::as_text).unwrap_or_default().to_uppercase(),
+        charset,
+    })
+}
+
+/// The start of a text part's content as one line of plain text, at most `length`
+/// characters. The content may be cut short, so a partial last character or escape
+/// is dropped.
+pub(crate) fn snippet(content: &[u8], part: &TextPart, length: usize) -> String {
+    let bytes = match part.encoding.as_str() {
+        "BASE64" => {
+            let mut clean: Vec<u8> = content.iter().copied().filter(|byte| !byte.is_ascii_whitespace()).collect();
+            clean.truncate(clean.len() / 4 * 4);
+            BASE64_STANDARD.decode(&clean).unwrap_or_default()
+        }
+        "QUOTED-PRINTABLE" => {
+            let tail = content.len().saturating_sub(2);
+            let end = content[tail..].iter().position(|&byte| byte == b'=').map_or(content.len(), |index| tail + index);
+            decode_quoted_printable(&content[..end], false)
+        }
+        _ => content.to_vec(),
+    };
+    let mut text = decode_charset(&bytes, &part.charset);
+    if part.subtype == "HTML" {
+        text = strip_html(&text);
+    }
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches('\u{FFFD}')
+        .chars()
+        .take(length)
+        .collect()
+}
+
+fn strip_html(html: &str) -> String {
+    static TAGS: OnceLock<Regex> = OnceLock::new();
+    let tags = TAGS.get_or_init(|| Regex::new(r"(?is)<(style|script)\b.*?</(style|script)>|<[^>]*>").unwrap());
+    tags.replace_all(html, " ")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fetch_response_with_literal() {
+        let response = b"(UID 42 FLAGS (\\Seen $Label1) BODY[1]<0> {11}\r\nHello\r\nBob! ENVELOPE (NIL \"Re: \\\"ticket\\\"\" ((\"Ann\" NIL \"ann\" \"acme.io\")) NIL NIL ((NIL NIL \"team\" NIL)(NIL NIL \"support\" \"acme.io\")(NIL NIL NIL NIL)) NIL NIL NIL \"<1@acme.io>\"))\r\n";
+        let values = parse_values(response);
+        let items = values[0].as_list();
+        assert_eq!(items[0], ImapValue::Atom("UID".to_string()));
+        assert_eq!(items[3], ImapValue::List(vec![ImapValue::Atom("\\Seen".to_string()), ImapValue::Atom("$Label1".to_string())]));
+        assert_eq!(items[4], ImapValue::Atom("BODY[1]<0>".to_string()));
+        assert_eq!(items[5], ImapValue::Bytes(b"Hello\r\nBob!".to_vec()));
+
+        let envelope = items[7].as_list();
+        assert_eq!(envelope[0], ImapValue::Nil);
+        assert_eq!(envelope[1].as_text().as_deref(), Some("Re: \"ticket\""));
+        assert_eq!(envelope_addresses(&envelope[2]).as_deref(), Some("ann@acme.io"));
+        assert_eq!(envelope_addresses(&envelope[5]).as_deref(), Some("support@acme.io"));
+        assert_eq!(envelope_addresses(&envelope[6]), None);
+
+        assert_eq!(literal_length(b"* 1 FETCH (BODY[1] {11}\r\n"), Some(11));
+        assert_eq!(literal_length(b"* OK done\r\n"), None);
+        assert_eq!(parse_values(b"(\\HasNoChildren) \"/\" \"Support/Tickets\"")[2].as_text().as_deref(), Some("Support/Tickets"));
+    }
+
+    #[test]
+    fn test_decode_headers_and_snippets() {
+        assert_eq!(decode_header("=?UTF-8?Q?Caf=C3=A9_order?= =?UTF-8?B?IOKAkyBkZWxheWVk?="), "Café order – delayed");
+        assert_eq!(decode_header("Re: =?ISO-8859-1?Q?=E9t=E9?= plans"), "Re: été plans");
+        assert_eq!(decode_header("Plain subject"), "Plain subject");
+        assert_eq!(internal_date("01-May-2024 12:00:00 +0200").as_deref(), Some("2024-05-01 10:00:00"));
+        assert_eq!(internal_date(" 1-May-2024 10:00:00 +0000").as_deref(), Some("2024-05-01 10:00:00"));
+
+        let structure = parse_values(b"(((\"TEXT\" \"PLAIN\" (\"CHARSET\" \"ISO-8859-1\") NIL NIL \"QUOTED-PRINTABLE\" 120 4)(\"TEXT\" \"HTML\" NIL NIL NIL \"BASE64\" 300 6) \"ALTERNATIVE\")(\"APPLICATION\" \"PDF\" NIL NIL NIL \"BASE64\" 9000) \"MIXED\")");
+        let part = first_text_part(&structure[0]).unwrap();
+        assert_eq!((part.section.as_str(), part.encoding.as_str(), part.charset.as_str()), ("1.1", "QUOTED-PRINTABLE", "ISO-8859-1"));
+        assert_eq!(snippet(b"Caf=E9 order\r\n  is =\r\nlate=", &part, 200), "Café order is late");
+
+        let html = TextPart { section: "1".to_string(), subtype: "HTML".to_string(), encoding: "BASE64".to_string(), charset: "utf-8".to_string() };
+        let content = BASE64_STANDARD.encode("<p>Your&nbsp;order <b>#42</b></p>");
+        assert_eq!(snippet(format!("{}\r\nAAA", content).as_bytes(), &html, 12), "Your order #");
+        let single = parse_values(b"(\"TEXT\" \"PLAIN\" NIL NIL NIL \"7BIT\" 20 1)");
+        assert_eq!(first_text_part(&single[0]).unwrap().section, "1");
+    }
+}
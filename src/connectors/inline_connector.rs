@@ -0,0 +1,179 @@
+use async_trait::async_trait;
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::engine::{evaluate_computed_columns, filter_rows};
+use crate::utils::{
+    types::{ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, DataType, Row, Value},
+    error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
+    collation::Collation,
+};
+
+/// Object type of tables written inline with `(VALUES ...) AS t(a, b)`
+pub const INLINE_OBJECT_TYPE: &str = "inline";
+
+/// Rows written in the query itself. The parser encodes them as the source identifier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InlineTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+impl InlineTable {
+    /// Encode the table as a source identifier
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// The rows of a query result, as a derived table's rows are read by the query around it.
+    /// Dates and binary values become text.
+    pub fn from_result(result: &QueryResult) -> Self {
+        let rows = result.rows.iter().map(|row| row.values.iter().map(|value| match value {
+            Value::Text(s) | Value::Date(s) | Value::DateTime(s) => JsonValue::String(s.clone()),
+            Value::Integer(i) => JsonValue::from(*i),
+            Value::Float(f) => serde_json::Number::from_f64(*f).map_or(JsonValue::Null, JsonValue::Number),
+            Value::Boolean(b) => JsonValue::Bool(*b),
+            Value::Json(json) => serde_json::from_str(json).unwrap_or_else(|_| JsonValue::String(json.clone())),
+            Value::Binary(bytes) => JsonValue::String(BASE64_STANDARD.encode(bytes)),
+            Value::Null => JsonValue::Null,
+        }).collect()).collect();
+        Self { columns: result.columns.iter().map(|col| col.name.clone()).collect(), rows }
+    }
+
+    pub fn decode(identifier: &str) -> NirvResult<Self> {
+        serde_json::from_str(identifier).map_err(|e| ConnectorError::QueryExecutionFailed(
+            format!("Invalid inline table: {}", e)
+        ).into())
+    }
+
+    /// Column metadata, typed by the first non-null value in each column
+    fn column_metadata(&self) -> Vec<ColumnMetadata> {
+        self.columns.iter().enumerate().map(|(index, name)| {
+            let data_type = self.rows.iter()
+                .filter_map(|row| row.get(index))
+                .find(|value| !value.is_null())
+                .map(|value| match value {
+                    JsonValue::Bool(_) => DataType::Boolean,
+                    JsonValue::Number(n) if n.is_i64() => DataType::Integer,
+                    JsonValue::Number(_) => DataType::Float,
+                    _ => DataType::Text,
+                })
+                .unwrap_or(DataType::Text);
+            ColumnMetadata {
+                name: name.clone(),
+                data_type,
+                nullable: true,
+            }
+        }).collect()
+    }
+
+    fn to_result(&self) -> QueryResult {
+        let rows = self.rows.iter().map(|row| Row::new(row.iter().map(|value| match value {
+            JsonValue::Null => Value::Null,
+            JsonValue::Bool(b) => Value::Boolean(*b),
+            JsonValue::Number(n) => n.as_i64().map(Value::Integer).unwrap_or_else(|| Value::Float(n.as_f64().unwrap_or_default())),
+            JsonValue::String(s) => Value::Text(s.clone()),
+            other => Value::Json(other.to_string()),
+        }).collect())).collect();
+        QueryResult { columns: self.column_metadata(), rows, ..Default::default() }
+    }
+}
+
+/// Connector serving inline `VALUES` tables
+#[derive(Debug, Default)]
+pub struct InlineConnector {
+    connected: bool,
+}
+
+impl InlineConnector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Connector for InlineConnector {
+    async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let source = query.query.sources.first().ok_or_else(|| ConnectorError::QueryExecutionFailed(
+            "No data source specified in query".to_string()
+        ))?;
+        let table = InlineTable::decode(&source.identifier)?;
+
+        let result = filter_rows(table.to_result(), &query.query.predicates, IdentifierCase::default(), Collation::default())?;
+        let mut result = evaluate_computed_columns(result, &query.query.projections, IdentifierCase::default())?;
+        if let Some(limit) = query.query.limit {
+            result.rows.truncate(limit as usize);
+        }
+        Ok(result)
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        Ok(Schema {
+            name: INLINE_OBJECT_TYPE.to_string(),
+            columns: InlineTable::decode(object_name)?.column_metadata(),
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Custom(INLINE_OBJECT_TYPE.to_string())
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{DataSource, InternalQuery, QueryOperation};
+
+    #[tokio::test]
+    async fn test_inline_table_round_trip() {
+        let table = InlineTable {
+            columns: vec!["id".to_string(), "score".to_string(), "name".to_string()],
+            rows: vec![
+                vec![serde_json::json!(1), serde_json::json!(2.5), serde_json::json!("a")],
+                vec![serde_json::json!(2), JsonValue::Null, serde_json::json!("b")],
+            ],
+        };
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: INLINE_OBJECT_TYPE.to_string(), identifier: table.encode(), alias: None });
+
+        let connector = InlineConnector::new();
+        let result = connector.execute_query(ConnectorQuery {
+            connector_type: connector.get_connector_type(),
+            query,
+            connection_params: Default::default(),
+        }).await.unwrap();
+        assert_eq!(result.columns[1].data_type, DataType::Float);
+        assert_eq!(result.rows[1].values, vec![Value::Integer(2), Value::Null, Value::Text("b".to_string())]);
+        assert!(InlineTable::decode("not json").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use serde_json::{Map, Value as JsonValue};
+
+use crate::utils::types::{ColumnMetadata, DataType};
+
+/// Flattening of nested JSON records into columns, so `{"address": {"city": ..}}`
+/// yields an `address.city` column instead of a single Json column
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonFlattening {
+    /// Levels of nesting to expand; objects below this depth stay Json
+    pub max_depth: usize,
+    /// Joins parent and child keys in column names
+    pub separator: String,
+    /// Field holding an array of objects to expand into one record per element
+    pub unnest: Option<String>,
+}
+
+impl JsonFlattening {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            separator: ".".to_string(),
+            unnest: None,
+        }
+    }
+
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Expand the array-of-objects field into one record per element, as `UNNEST` does
+    pub fn with_unnest(mut self, field: impl Into<String>) -> Self {
+        self.unnest = Some(field.into());
+        self
+    }
+
+    /// Read `flatten_depth`, `flatten_separator` and `unnest` connection parameters.
+    /// Flattening is enabled when either `flatten_depth` or `unnest` is present.
+    pub fn from_params(params: &HashMap<String, String>) -> Option<Self> {
+        let depth = params.get("flatten_depth").and_then(|d| d.parse::<usize>().ok());
+        let unnest = params.get("unnest").filter(|field| !field.is_empty());
+        if depth.is_none() && unnest.is_none() {
+            return None;
+        }
+
+        let mut flattening = Self::new(depth.unwrap_or(0));
+        if let Some(separator) = params.get("flatten_separator") {
+            flattening = flattening.with_separator(separator.clone());
+        }
+        if let Some(field) = unnest {
+            flattening = flattening.with_unnest(field.clone());
+        }
+        Some(flattening)
+    }
+
+    /// Unnest then flatten records; non-object records pass through unchanged
+    pub fn apply(&self, records: Vec<JsonValue>) -> Vec<JsonValue> {
+        records.into_iter()
+            .flat_map(|record| self.unnest_record(record))
+            .map(|record| match record {
+                JsonValue::Object(obj) => {
+                    let mut flat = Map::new();
+                    self.flatten_into(&mut flat, None, obj, 0);
+                    JsonValue::Object(flat)
+                }
+                other => other,
+            })
+            .collect()
+    }
+
+    /// One record per element of the unnested array, with the element's fields under the
+    /// array's name. Records whose array is empty or missing produce no rows.
+    fn unnest_record(&self, record: JsonValue) -> Vec<JsonValue> {
+        let (field, mut obj) = match (&self.unnest, record) {
+            (Some(field), JsonValue::Object(obj)) => (field, obj),
+            (_, record) => return vec![record],
+        };
+        let Some(JsonValue::Array(elements)) = obj.remove(field) else {
+            return Vec::new();
+        };
+
+        elements.into_iter()
+            .map(|element| {
+                let mut expanded = obj.clone();
+                expanded.insert(field.clone(), element);
+                JsonValue::Object(expanded)
+            })
+            .collect()
+    }
+
+    fn flatten_into(&self, flat: &mut Map<String, JsonValue>, prefix: Option<&str>, obj: Map<String, JsonValue>, depth: usize) {
+        for (key, value) in obj {
+            let name = match prefix {
+                Some(prefix) => format!("{}{}{}", prefix, self.separator, key),
+                None => key,
+            };
+            match value {
+                JsonValue::Object(child) if depth < self.max_depth && !child.is_empty() => {
+                    self.flatten_into(flat, Some(&name), child, depth + 1);
+                }
+                // The unnested element is always expanded, whatever the depth limit
+                JsonValue::Object(child) if depth == 0 && self.unnest.as_deref() == Some(name.as_str()) => {
+                    self.flatten_into(flat, Some(&name), child, depth + 1);
+                }
+                value => {
+                    flat.insert(name, value);
+                }
+            }
+        }
+    }
+}
+
+/// Column type of a JSON value
+pub fn json_data_type(value: &JsonValue) -> DataType {
+    match value {
+        JsonValue::Null | JsonValue::String(_) => DataType::Text,
+        JsonValue::Bool(_) => DataType::Boolean,
+        JsonValue::Number(n) if n.is_i64() => DataType::Integer,
+        JsonValue::Number(_) => DataType::Float,
+        JsonValue::Array(_) | JsonValue::Object(_) => DataType::Json,
+    }
+}
+
+/// Columns covering every key of the records, typed from the first non-null value.
+/// Flattened records often differ in shape, so no single record is representative.
+pub fn infer_json_columns(records: &[JsonValue]) -> Vec<ColumnMetadata> {
+    let mut columns: Vec<ColumnMetadata> = Vec::new();
+    for record in records {
+        let JsonValue::Object(obj) = record else { continue };
+        for (key, value) in obj {
+            match columns.iter_mut().find(|column| &column.name == key) {
+                Some(column) if column.data_type == DataType::Text && !value.is_null() && !value.is_string() => {
+                    column.data_type = json_data_type(value);
+                }
+                Some(_) => {}
+                None => columns.push(ColumnMetadata {
+                    name: key.clone(),
+                    data_type: json_data_type(value),
+                    nullable: true,
+                }),
+            }
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten_with_depth_limit_and_separator() {
+        let records = vec![json!({
+            "id": 1,
+            "address": {"city": "Oslo", "geo": {"lat": 59.9}},
+            "tags": ["a"]
+        })];
+
+        let flat = JsonFlattening::new(1).apply(records.clone());
+        assert_eq!(flat[0], json!({
+            "id": 1,
+            "address.city": "Oslo",
+            "address.geo": {"lat": 59.9},
+            "tags": ["a"]
+        }));
+
+        let deep = JsonFlattening::new(5).with_separator("_").apply(records);
+        assert_eq!(deep[0]["address_geo_lat"], json!(59.9));
+    }
+
+    #[test]
+    fn test_unnest_array_of_objects() {
+        let records = vec![
+            json!({"order": 1, "items": [{"sku": "A", "qty": 2}, {"sku": "B", "qty": 1}]}),
+            json!({"order": 2, "items": []}),
+        ];
+
+        let rows = JsonFlattening::new(0).with_unnest("items").apply(records);
+        assert_eq!(rows, vec![
+            json!({"order": 1, "items.sku": "A", "items.qty": 2}),
+            json!({"order": 1, "items.sku": "B", "items.qty": 1}),
+        ]);
+
+        let columns = infer_json_columns(&rows);
+        let column = |name: &str| columns.iter().find(|c| c.name == name).unwrap().data_type.clone();
+        assert_eq!(column("items.qty"), DataType::Integer);
+        assert_eq!(column("items.sku"), DataType::Text);
+    }
+
+    #[test]
+    fn test_flattening_from_params() {
+        let mut params = HashMap::new();
+        assert_eq!(JsonFlattening::from_params(&params), None);
+
+        params.insert("flatten_depth".to_string(), "2".to_string());
+        params.insert("flatten_separator".to_string(), "__".to_string());
+        let flattening = JsonFlattening::from_params(&params).unwrap();
+        assert_eq!((flattening.max_depth, flattening.separator.as_str(), flattening.unnest), (2, "__", None));
+    }
+}
@@ -1,13 +1,14 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
-use crate::connectors::connector_trait::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::connector_trait::{Connector, ConnectorInitConfig, ConnectorCapabilities, RowSink};
 use crate::utils::{
     types::{
         ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, 
         DataType, Row, Value, Index, QueryOperation, PredicateOperator
     },
     error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
 };
 
 /// Mock connector for testing with deterministic in-memory data
@@ -16,6 +17,8 @@ pub struct MockConnector {
     connected: bool,
     test_data: HashMap<String, TestTable>,
     connection_delay_ms: u64,
+    identifier_case: IdentifierCase,
+    fetch_size: Option<u32>,
 }
 
 /// Test table structure for mock data
@@ -32,6 +35,8 @@ impl MockConnector {
             connected: false,
             test_data: HashMap::new(),
             connection_delay_ms: 10, // Simulate small connection delay
+            identifier_case: IdentifierCase::default(),
+            fetch_size: None,
         };
         
         connector.initialize_test_data();
@@ -84,6 +89,8 @@ impl MockConnector {
             columns,
             primary_key: None,
             indexes: vec![],
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
         };
         
         let table_rows: Vec<Row> = rows.into_iter().map(Row::new).collect();
@@ -126,6 +133,8 @@ impl MockConnector {
             columns,
             primary_key: None,
             indexes: vec![],
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
         };
         
         let table_rows: Vec<Row> = rows.into_iter().map(Row::new).collect();
@@ -176,6 +185,8 @@ impl MockConnector {
                     unique: true,
                 },
             ],
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
         };
         
         let users_rows = vec![
@@ -234,6 +245,8 @@ impl MockConnector {
             ],
             primary_key: Some(vec!["id".to_string()]),
             indexes: vec![],
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
         };
         
         let products_rows = vec![
@@ -257,30 +270,30 @@ impl MockConnector {
         });
     }
     
-    /// Apply WHERE clause filtering to rows
+    /// Apply WHERE clause filtering and LIMIT to rows, copying only the rows returned
     fn apply_filters(&self, rows: &[Row], query: &ConnectorQuery) -> Vec<Row> {
+        let limit = query.query.limit.map_or(usize::MAX, |limit| limit as usize);
         if query.query.predicates.is_empty() {
-            return rows.to_vec();
+            return rows.iter().take(limit).cloned().collect();
         }
         
         let table_name = if let Some(source) = query.query.sources.first() {
             &source.identifier
         } else {
-            return rows.to_vec();
+            return rows.iter().take(limit).cloned().collect();
         };
         
         let schema = if let Some(table) = self.test_data.get(table_name) {
             &table.schema
         } else {
-            return rows.to_vec();
+            return rows.iter().take(limit).cloned().collect();
         };
         
         rows.iter()
             .filter(|row| {
                 query.query.predicates.iter().all(|predicate| {
                     // Find column index
-                    let col_index = schema.columns.iter()
-                        .position(|col| col.name == predicate.column);
+                    let col_index = schema.column_index(&predicate.column, self.identifier_case);
                     
                     if let Some(index) = col_index {
                         if let Some(value) = row.get(index) {
@@ -293,6 +306,7 @@ impl MockConnector {
                     }
                 })
             })
+            .take(limit)
             .cloned()
             .collect()
     }
@@ -345,6 +359,9 @@ impl MockConnector {
                     _ => false,
                 }
             },
+            PredicateOperator::Regexp => {
+                matches!(predicate_value, PredicateValue::String(p) if crate::utils::regex_function::regex_matches(value, p))
+            },
             PredicateOperator::IsNull => matches!(value, Value::Null),
             PredicateOperator::IsNotNull => !matches!(value, Value::Null),
             PredicateOperator::In => {
@@ -356,15 +373,6 @@ impl MockConnector {
             },
         }
     }
-    
-    /// Apply LIMIT clause to rows
-    fn apply_limit(&self, rows: Vec<Row>, limit: Option<u64>) -> Vec<Row> {
-        if let Some(limit_count) = limit {
-            rows.into_iter().take(limit_count as usize).collect()
-        } else {
-            rows
-        }
-    }
 }
 
 impl Default for MockConnector {
@@ -375,12 +383,14 @@ impl Default for MockConnector {
 
 #[async_trait]
 impl Connector for MockConnector {
-    async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+    async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
         // Simulate connection delay
         if self.connection_delay_ms > 0 {
             tokio::time::sleep(Duration::from_millis(self.connection_delay_ms)).await;
         }
         
+        self.identifier_case = IdentifierCase::from_params(&config.connection_params);
+        self.fetch_size = config.fetch_size;
         self.connected = true;
         Ok(())
     }
@@ -399,15 +409,15 @@ impl Connector for MockConnector {
             QueryOperation::Select => {
                 if let Some(source) = query.query.sources.first() {
                     if let Some(table) = self.test_data.get(&source.identifier) {
-                        let filtered_rows = self.apply_filters(&table.rows, &query);
-                        // Apply limit if specified
-                        let limited_rows = self.apply_limit(filtered_rows, query.query.limit);
+                        let rows = self.apply_filters(&table.rows, &query);
                         
                         let result = QueryResult {
                             columns: table.schema.columns.clone(),
-                            rows: limited_rows,
+                            rows,
                             affected_rows: None,
                             execution_time: start_time.elapsed(),
+                            stats: None,
+                            warnings: Vec::new(),
                         };
                         
                         Ok(result)
@@ -428,6 +438,27 @@ impl Connector for MockConnector {
         }
     }
     
+    async fn count_rows(&self, mut query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        // Counted like a database would, without handing over the rows
+        query.query.limit = None;
+        Ok(Some(self.execute_query(query).await?.rows.len() as u64))
+    }
+    
+    async fn execute_query_batched(&self, query: ConnectorQuery, on_batch: &mut RowSink<'_>) -> NirvResult<QueryResult> {
+        let mut result = self.execute_query(query).await?;
+        let rows = std::mem::take(&mut result.rows);
+        match self.fetch_size {
+            // Rows are handed over a fetch size at a time, as a cursor would return them
+            Some(fetch_size) => {
+                for batch in rows.chunks(fetch_size.max(1) as usize) {
+                    on_batch(&result.columns, batch.to_vec())?;
+                }
+            }
+            None => on_batch(&result.columns, rows)?,
+        }
+        Ok(result)
+    }
+    
     async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
         if !self.connected {
             return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
@@ -442,6 +473,16 @@ impl Connector for MockConnector {
         }
     }
     
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
+        }
+
+        let mut objects: Vec<String> = self.test_data.keys().cloned().collect();
+        objects.sort();
+        Ok(objects)
+    }
+    
     async fn disconnect(&mut self) -> NirvResult<()> {
         self.connected = false;
         Ok(())
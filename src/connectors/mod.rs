@@ -1,14 +1,61 @@
 // Connector implementations
 pub mod connector_trait;
+pub mod network;
+pub mod debug_log;
 pub mod mock_connector;
 pub mod postgres_connector;
 pub mod file_connector;
+pub mod parse_pool;
+pub mod csv_options;
+pub mod file_snapshot;
+pub mod file_index;
+pub mod file_watch;
+pub mod disk_cache;
+pub mod json_flatten;
+pub mod xml_records;
+pub mod fixed_width;
+pub mod response_contract;
+pub mod body_template;
 pub mod rest_connector;
 pub mod sqlserver_connector;
+pub mod warehouse;
+pub mod bigquery_connector;
+pub mod snowflake_connector;
+pub mod imap_protocol;
+pub mod imap_connector;
+pub mod cdc_connector;
+pub mod stdin_connector;
+pub mod fsmeta_connector;
+pub mod sys_connector;
+pub mod inline_connector;
+pub mod connector_testkit;
 
 pub use connector_trait::*;
+pub use network::RetryPolicy;
+pub use debug_log::{ConnectorDebugLog, DebugDirection, DebugEntry, connector_debug_log};
 pub use mock_connector::*;
 pub use postgres_connector::*;
 pub use file_connector::*;
+pub use parse_pool::{ParsePool, DEFAULT_PARSE_THREADS, configure_parse_pool, parse_pool};
+pub use csv_options::{CsvOptions, TextEncoding, split_source_options};
+pub use file_snapshot::{FileSnapshot, SnapshotStore};
+pub use file_index::{ColumnRange, FileIndexStore, FileStatistics};
+pub use file_watch::{FileChange, FileChangeKind, FileParseCache, FileWatcher};
+pub use disk_cache::{DiskCache, DiskCacheEntry, DEFAULT_CACHE_MAX_MB, cache_max_bytes};
+pub use json_flatten::{JsonFlattening, infer_json_columns};
+pub use xml_records::XmlRecordPath;
+pub use fixed_width::{FixedWidthField, FixedWidthLayout, FixedWidthType};
+pub use response_contract::{ContractField, ContractMode, ResponseContract};
+pub use body_template::{BodyTemplate, RequestBody, JSON_CONTENT_TYPE};
 pub use rest_connector::*;
-pub use sqlserver_connector::*;
\ No newline at end of file
+pub use sqlserver_connector::*;
+pub use warehouse::RsaSigningKey;
+pub use bigquery_connector::*;
+pub use snowflake_connector::*;
+pub use imap_connector::*;
+pub use cdc_connector::*;
+pub use stdin_connector::*;
+pub use fsmeta_connector::*;
+pub use sys_connector::*;
+pub use inline_connector::*;
+pub use connector_testkit::{ConnectorTestKit, ConformanceFixture, ConformanceReport, ConformanceCheck, FIXTURE_COLUMNS, fixture_rows, fixture_csv};
@@ -0,0 +1,235 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::utils::error::{ConnectorError, NirvError, NirvResult};
+
+/// Retry policy for establishing network connections
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+    /// Base delay for exponential backoff
+    pub base_delay: Duration,
+    /// Upper bound for a single backoff delay
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+
+    /// Policy with the given retry count and base backoff delay
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            ..Self::none()
+        }
+    }
+
+    /// Set the upper bound for a single backoff delay
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Delay before the given retry (1-based): exponential backoff with full jitter
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let ceiling = self.base_delay
+            .saturating_mul(1u32 << exponent)
+            .min(self.max_delay);
+        ceiling.mul_f64(jitter_fraction())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Pseudo-random fraction in [0, 1) for backoff jitter
+fn jitter_fraction() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
+
+/// Check if an error is worth retrying a connection attempt for
+fn is_retryable(error: &NirvError) -> bool {
+    matches!(
+        error,
+        NirvError::Connector(ConnectorError::ConnectionFailed(_)) | NirvError::Connector(ConnectorError::Timeout(_))
+    )
+}
+
+/// Resolve a host name without blocking the runtime, bounded by a timeout
+pub async fn resolve_host(host: &str, port: u16, timeout: Duration) -> NirvResult<Vec<SocketAddr>> {
+    let addresses = tokio::time::timeout(timeout, tokio::net::lookup_host((host, port))).await
+        .map_err(|_| ConnectorError::Timeout(format!(
+            "DNS resolution of '{}' timed out after {}ms", host, timeout.as_millis()
+        )))?
+        .map_err(|e| ConnectorError::ConnectionFailed(format!(
+            "Failed to resolve host '{}': {}", host, e
+        )))?
+        .collect::<Vec<_>>();
+
+    if addresses.is_empty() {
+        return Err(ConnectorError::ConnectionFailed(
+            format!("Host '{}' did not resolve to any address", host)
+        ).into());
+    }
+
+    Ok(addresses)
+}
+
+/// Run a connection attempt under a per-attempt timeout, retrying transient failures
+/// with jittered exponential backoff
+pub async fn connect_with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    timeout: Duration,
+    description: &str,
+    mut attempt: F,
+) -> NirvResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = NirvResult<T>>,
+{
+    let mut retry = 0;
+    loop {
+        let result = match tokio::time::timeout(timeout, attempt()).await {
+            Ok(result) => result,
+            Err(_) => Err(ConnectorError::Timeout(format!(
+                "{} timed out after {}ms", description, timeout.as_millis()
+            )).into()),
+        };
+
+        match result {
+            Err(e) if retry < policy.max_retries && is_retryable(&e) => {
+                retry += 1;
+                tokio::time::sleep(policy.delay_for_attempt(retry)).await;
+            }
+            other => return other,
+        }
+    }
+}
+
+/// Bound an operation on an established connection by a timeout
+pub async fn with_timeout<T, Fut>(timeout: Duration, description: &str, operation: Fut) -> NirvResult<T>
+where
+    Fut: Future<Output = NirvResult<T>>,
+{
+    tokio::time::timeout(timeout, operation).await
+        .map_err(|_| ConnectorError::Timeout(format!(
+            "{} timed out after {}ms", description, timeout.as_millis()
+        )))?
+}
+
+/// Rows a connector converts between giving other tasks a turn on its worker thread
+pub const YIELD_EVERY_ROWS: usize = 1024;
+
+/// Yield to the runtime once every `YIELD_EVERY_ROWS` rows converted, so converting a large
+/// result does not hold a worker thread protocol connections are waiting for
+pub async fn yield_after_rows(rows: usize) {
+    if rows > 0 && rows.is_multiple_of(YIELD_EVERY_ROWS) {
+        tokio::task::yield_now().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_retry_policy_default_never_retries() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.max_retries, 0);
+    }
+
+    #[test]
+    fn test_retry_delay_is_bounded_by_backoff() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(300));
+
+        for _ in 0..20 {
+            assert!(policy.delay_for_attempt(1) <= Duration::from_millis(100));
+            assert!(policy.delay_for_attempt(2) <= Duration::from_millis(200));
+            assert!(policy.delay_for_attempt(10) <= Duration::from_millis(300));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_retries_transient_failures() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3, Duration::from_millis(1));
+
+        let result = connect_with_retry(&policy, Duration::from_secs(1), "test connect", || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(ConnectorError::ConnectionFailed("refused".to_string()).into())
+            } else {
+                Ok(42)
+            }
+        }).await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_does_not_retry_other_errors() {
+        let attempts = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3, Duration::from_millis(1));
+
+        let result: NirvResult<()> = connect_with_retry(&policy, Duration::from_secs(1), "test connect", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ConnectorError::AuthenticationFailed("bad password".to_string()).into())
+        }).await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_times_out_each_attempt() {
+        let policy = RetryPolicy::new(1, Duration::from_millis(1));
+        let started = std::time::Instant::now();
+
+        let result: NirvResult<()> = connect_with_retry(&policy, Duration::from_millis(20), "slow connect", || async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        }).await;
+
+        assert!(matches!(result, Err(NirvError::Connector(ConnectorError::Timeout(_)))));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_localhost() {
+        let addresses = resolve_host("localhost", 5432, Duration::from_secs(5)).await.unwrap();
+        assert!(addresses.iter().all(|a| a.port() == 5432));
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout_expires() {
+        let result: NirvResult<()> = with_timeout(Duration::from_millis(10), "query", async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        }).await;
+        assert!(matches!(result, Err(NirvError::Connector(ConnectorError::Timeout(_)))));
+    }
+}
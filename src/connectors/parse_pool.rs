@@ -0,0 +1,105 @@
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::sync::Semaphore;
+
+use crate::utils::error::{NirvError, NirvResult};
+
+/// Threads parsing files at once unless configured otherwise
+pub const DEFAULT_PARSE_THREADS: usize = 4;
+
+/// Bounded pool for reading and parsing files off the async runtime's worker threads, so a
+/// large file scan cannot starve protocol connections. At most `threads` parses run at once
+/// on the runtime's blocking threads; further parses wait for one to finish.
+#[derive(Debug)]
+pub struct ParsePool {
+    threads: usize,
+    permits: Arc<Semaphore>,
+}
+
+impl ParsePool {
+    /// A pool running up to `threads` parses at once, at least one
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        Self { threads, permits: Arc::new(Semaphore::new(threads)) }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Run a blocking parse once a thread of the pool is free. The thread stays taken until
+    /// the parse returns, even if the caller stops waiting for it.
+    pub async fn run<T, F>(&self, parse: F) -> NirvResult<T>
+    where
+        F: FnOnce() -> NirvResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self.permits.clone().acquire_owned().await
+            .map_err(|_| NirvError::Internal("File parse pool is closed".to_string()))?;
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            parse()
+        })
+        .await
+        .map_err(|e| NirvError::Internal(format!("File parse failed: {}", e)))?
+    }
+}
+
+impl Default for ParsePool {
+    fn default() -> Self {
+        Self::new(DEFAULT_PARSE_THREADS)
+    }
+}
+
+fn shared_pool() -> &'static RwLock<Arc<ParsePool>> {
+    static POOL: OnceLock<RwLock<Arc<ParsePool>>> = OnceLock::new();
+    POOL.get_or_init(|| RwLock::new(Arc::new(ParsePool::default())))
+}
+
+/// The process-wide parse pool used by file connectors not given their own
+pub fn parse_pool() -> Arc<ParsePool> {
+    shared_pool().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Replace the process-wide parse pool with one of the given size. Parses already waiting
+/// finish on the previous pool.
+pub fn configure_parse_pool(threads: usize) {
+    if parse_pool().threads() != threads.max(1) {
+        *shared_pool().write().unwrap_or_else(|e| e.into_inner()) = Arc::new(ParsePool::new(threads));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_parse_pool_bounds_concurrent_parses() {
+        let pool = Arc::new(ParsePool::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+
+        let parses = (0..6).map(|i| {
+            let (pool, running, most) = (pool.clone(), running.clone(), most.clone());
+            tokio::spawn(async move {
+                pool.run(move || {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(i)
+                }).await
+            })
+        });
+        let mut results = Vec::new();
+        for parse in parses.collect::<Vec<_>>() {
+            results.push(parse.await.unwrap().unwrap());
+        }
+
+        assert_eq!(results, (0..6).collect::<Vec<_>>());
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+        assert!(pool.run(|| Err::<(), _>(NirvError::Internal("bad file".to_string()))).await.is_err());
+        assert_eq!(ParsePool::new(0).threads(), 1);
+    }
+}
@@ -1,23 +1,67 @@
 use async_trait::async_trait;
-use deadpool_postgres::{Config, Pool, Runtime};
+use dashmap::DashMap;
+use deadpool_postgres::{
+    Config, Hook, HookError, HookErrorCause, ManagerConfig, Object, Pool, PoolConfig, RecyclingMethod, Runtime, Timeouts,
+};
 
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use tokio_postgres::{NoTls, Row as PgRow};
+use tokio::sync::Mutex;
+use tokio_postgres::{GenericClient, NoTls, Row as PgRow, Statement};
+use tokio_postgres::types::{ToSql, Type};
 
-use crate::connectors::connector_trait::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::connector_trait::{Connector, ConnectorInitConfig, ConnectorCapabilities, RowSink};
+use crate::connectors::network::{connect_with_retry, resolve_host, with_timeout, yield_after_rows};
+use crate::connectors::debug_log::{connector_debug_log, tag_sql, APPLICATION_NAME};
 use crate::utils::{
     types::{
         ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, 
-        DataType, Row, Value, Index, QueryOperation, PredicateOperator
+        DataType, Row, Value, Index, Sample, PredicateValue, Column, ColumnDetails, ForeignKey, QueryOperation, COUNT_ALL
     },
     error::{ConnectorError, NirvResult},
+    connector_settings::{PostgresSettings, issues_error},
+    sql_dialect::{SqlDialect, SqlStatement},
+    identifier::quote_identifier,
 };
 
+/// Rows loaded by a single `INSERT` statement
+const INSERT_BATCH_ROWS: usize = 500;
+
+/// Query run on a pooled connection before it is reused, so connections left dead by a
+/// failover or a dropped network path are replaced instead of failing the next query
+const VALIDATION_QUERY: &str = "SELECT 1";
+
+fn query_failed(e: tokio_postgres::Error) -> ConnectorError {
+    ConnectorError::QueryExecutionFailed(format!("Query execution failed: {}", e))
+}
+
+fn param_refs(params: &[Box<dyn ToSql + Sync + Send>]) -> Vec<&(dyn ToSql + Sync)> {
+    params.iter().map(|param| param.as_ref() as &(dyn ToSql + Sync)).collect()
+}
+
 /// PostgreSQL connector using tokio-postgres with connection pooling
-#[derive(Debug)]
 pub struct PostgresConnector {
     pool: Option<Pool>,
     connected: bool,
+    /// Pooled connections pinned to open transactions
+    transactions: DashMap<u64, Arc<Mutex<Object>>>,
+    next_transaction_id: AtomicU64,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    /// Rows fetched per round trip through a portal; unset, results are read in one go
+    fetch_size: Option<u32>,
+}
+
+impl fmt::Debug for PostgresConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresConnector")
+            .field("connected", &self.connected)
+            .field("open_transactions", &self.transactions.len())
+            .finish()
+    }
 }
 
 impl PostgresConnector {
@@ -26,6 +70,11 @@ impl PostgresConnector {
         Self {
             pool: None,
             connected: false,
+            transactions: DashMap::new(),
+            next_transaction_id: AtomicU64::new(1),
+            read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            fetch_size: None,
         }
     }
     
@@ -41,6 +90,244 @@ impl PostgresConnector {
         Ok(Row::new(values))
     }
     
+    /// Prepare the statement for a query, binding each value as the type PostgreSQL expects
+    /// for it. The SQL names the engine query it runs for, as seen in pg_stat_activity and
+    /// the server log.
+    async fn prepare_query<C: GenericClient + Sync>(&self, client: &C, query: &ConnectorQuery) -> NirvResult<(Statement, Vec<Box<dyn ToSql + Sync + Send>>)> {
+        let mut statement = self.build_sql_statement(&query.query)?;
+        let mut prepared = client.prepare(&tag_sql(&statement.sql())).await.map_err(query_failed)?;
+        // Values the driver cannot bind to the expected type, such as text compared
+        // with a date column, are written as literals instead
+        let unbound: Vec<bool> = prepared.params().iter().zip(statement.params())
+            .map(|(ty, value)| Self::bind_param(ty, value).is_none())
+            .collect();
+        if unbound.contains(&true) {
+            statement = statement.with_literals(|index| unbound[index]);
+            prepared = client.prepare(&tag_sql(&statement.sql())).await.map_err(query_failed)?;
+        }
+        let params = prepared.params().iter().zip(statement.params())
+            .filter_map(|(ty, value)| Self::bind_param(ty, value))
+            .collect();
+        connector_debug_log().request("postgres", &format!("{} {:?}", tag_sql(&statement.sql()), statement.params()));
+        Ok((prepared, params))
+    }
+    
+    /// Run a query on a specific client and convert the results
+    async fn run_query(&self, client: &tokio_postgres::Client, query: &ConnectorQuery) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+        
+        if query.query.operation != QueryOperation::Select {
+            let affected = with_timeout(self.write_timeout, "PostgreSQL write", async {
+                let (prepared, params) = self.prepare_query(client, query).await?;
+                client.execute(&prepared, &param_refs(&params)).await
+                    .map_err(|e| query_failed(e).into())
+            }).await?;
+            return Ok(QueryResult {
+                affected_rows: Some(affected),
+                execution_time: start_time.elapsed(),
+                ..QueryResult::new()
+            });
+        }
+        
+        let pg_rows = with_timeout(self.read_timeout, "PostgreSQL query", async {
+            let (prepared, params) = self.prepare_query(client, query).await?;
+            client.query(&prepared, &param_refs(&params)).await
+                .map_err(|e| query_failed(e).into())
+        }).await?;
+        
+        // Convert results
+        let mut columns = Vec::new();
+        let mut rows = Vec::new();
+        
+        if let Some(first_row) = pg_rows.first() {
+            // Extract column metadata
+            for column in first_row.columns() {
+                columns.push(ColumnMetadata {
+                    name: column.name().to_string(),
+                    data_type: self.pg_type_to_data_type(column.type_().oid()),
+                    nullable: true, // PostgreSQL doesn't provide nullable info in query results
+                });
+            }
+        }
+        
+        // Convert all rows
+        for pg_row in &pg_rows {
+            let row = self.convert_pg_row(pg_row)?;
+            rows.push(row);
+            yield_after_rows(rows.len()).await;
+        }
+        
+        let execution_time = start_time.elapsed();
+        
+        let result = QueryResult {
+            columns,
+            rows,
+            affected_rows: Some(pg_rows.len() as u64),
+            execution_time,
+            stats: None,
+            warnings: Vec::new(),
+        };
+        connector_debug_log().result("postgres", &result);
+        Ok(result)
+    }
+    
+    /// Run a query through a portal in a read-only transaction, fetching `fetch_size` rows
+    /// per round trip and handing each fetch to `on_batch` once converted. Each round trip
+    /// is bounded by the read timeout.
+    async fn fetch_query(&self, client: &mut tokio_postgres::Client, query: &ConnectorQuery, fetch_size: u32, on_batch: &mut RowSink<'_>) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+        let fetch_size = fetch_size.clamp(1, i32::MAX as u32) as i32;
+        
+        let transaction = with_timeout(self.write_timeout, "PostgreSQL BEGIN", async {
+            client.build_transaction().read_only(true).start().await.map_err(|e| query_failed(e).into())
+        }).await?;
+        let (portal, columns) = with_timeout(self.read_timeout, "PostgreSQL query", async {
+            let (prepared, params) = self.prepare_query(&transaction, query).await?;
+            let portal = transaction.bind(&prepared, &param_refs(&params)).await.map_err(query_failed)?;
+            let columns: Vec<ColumnMetadata> = prepared.columns().iter()
+                .map(|column| ColumnMetadata {
+                    name: column.name().to_string(),
+                    data_type: self.pg_type_to_data_type(column.type_().oid()),
+                    nullable: true,
+                })
+                .collect();
+            Ok((portal, columns))
+        }).await?;
+        
+        loop {
+            let pg_rows = with_timeout(self.read_timeout, "PostgreSQL fetch", async {
+                transaction.query_portal(&portal, fetch_size).await.map_err(|e| query_failed(e).into())
+            }).await?;
+            let mut rows = Vec::with_capacity(pg_rows.len());
+            for pg_row in &pg_rows {
+                rows.push(self.convert_pg_row(pg_row)?);
+                yield_after_rows(rows.len()).await;
+            }
+            let last = rows.len() < fetch_size as usize;
+            on_batch(&columns, rows)?;
+            if last {
+                break;
+            }
+        }
+        with_timeout(self.write_timeout, "PostgreSQL COMMIT", async {
+            transaction.commit().await.map_err(|e| query_failed(e).into())
+        }).await?;
+        
+        let result = QueryResult {
+            columns,
+            rows: Vec::new(),
+            affected_rows: None,
+            execution_time: start_time.elapsed(),
+            stats: None,
+            warnings: Vec::new(),
+        };
+        connector_debug_log().result("postgres", &result);
+        Ok(result)
+    }
+    
+    /// Run a query on a pooled connection, through a portal when a fetch size is set. A
+    /// connection that dies before any rows were handed over, as after a failover, is
+    /// evicted from the pool and the query, which only reads, runs once more on a fresh one.
+    async fn query_pooled(&self, query: &ConnectorQuery, mut on_batch: Option<&mut RowSink<'_>>) -> NirvResult<QueryResult> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
+        }
+        
+        let pool = self.pool.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No connection pool available".to_string()))?;
+        
+        // A write is never retried, as it may have been applied before the connection failed
+        if query.query.operation != QueryOperation::Select {
+            let client = pool.get().await
+                .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
+            return self.run_query(&client, query).await;
+        }
+        
+        let mut retried = false;
+        loop {
+            let mut client = pool.get().await
+                .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
+            let mut delivered = false;
+            let outcome = match (self.fetch_size, on_batch.as_deref_mut()) {
+                (Some(fetch_size), Some(on_batch)) => {
+                    let mut deliver = |columns: &[ColumnMetadata], rows: Vec<Row>| {
+                        delivered = true;
+                        on_batch(columns, rows)
+                    };
+                    self.fetch_query(&mut client, query, fetch_size, &mut deliver).await
+                }
+                (Some(fetch_size), None) => {
+                    let mut rows = Vec::new();
+                    let mut collect = |_: &[ColumnMetadata], batch: Vec<Row>| {
+                        rows.extend(batch);
+                        Ok(())
+                    };
+                    self.fetch_query(&mut client, query, fetch_size, &mut collect).await
+                        .map(|result| QueryResult { rows, ..result })
+                }
+                (None, on_batch) => match self.run_query(&client, query).await {
+                    Ok(mut result) => match on_batch {
+                        Some(on_batch) => on_batch(&result.columns, std::mem::take(&mut result.rows)).map(|_| result),
+                        None => Ok(result),
+                    },
+                    Err(e) => Err(e),
+                },
+            };
+            match outcome {
+                Err(_) if client.is_closed() && !delivered && !retried => {
+                    drop(Object::take(client));
+                    retried = true;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+    
+    /// Take an open transaction's pinned client out of the transaction map
+    fn take_transaction(&self, transaction_id: u64) -> NirvResult<Arc<Mutex<Object>>> {
+        self.transactions.remove(&transaction_id)
+            .map(|(_, client)| client)
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                format!("Transaction {} is not open", transaction_id)
+            ).into())
+    }
+    
+    /// Finish a transaction with COMMIT or ROLLBACK and return its client to the pool
+    async fn end_transaction(&self, transaction_id: u64, statement: &str) -> NirvResult<()> {
+        let client = self.take_transaction(transaction_id)?;
+        let client = client.lock().await;
+        self.run_statement(&client, statement).await
+    }
+    
+    /// Run a statement that returns no rows, bounded by the write timeout
+    async fn run_statement(&self, client: &tokio_postgres::Client, statement: &str) -> NirvResult<()> {
+        with_timeout(self.write_timeout, statement, async {
+            client.batch_execute(&tag_sql(statement)).await
+                .map_err(|e| ConnectorError::QueryExecutionFailed(format!("{} failed: {}", statement, e)).into())
+        }).await
+    }
+    
+    /// Run a COMMIT PREPARED or ROLLBACK PREPARED statement on any pooled connection
+    async fn execute_prepared_statement(&self, statement: &str) -> NirvResult<()> {
+        let pool = self.pool.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No connection pool available".to_string()))?;
+        let client = pool.get().await
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
+        self.run_statement(&client, statement).await
+    }
+    
+    /// Ensure a global transaction identifier is safe to embed in SQL
+    fn validate_global_id(global_id: &str) -> NirvResult<&str> {
+        if global_id.is_empty() || global_id.len() > 200
+            || !global_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(ConnectorError::QueryExecutionFailed(
+                format!("Invalid global transaction identifier: '{}'", global_id)
+            ).into());
+        }
+        Ok(global_id)
+    }
+    
     /// Convert PostgreSQL value to internal Value representation
     fn convert_pg_value(&self, row: &PgRow, index: usize) -> NirvResult<Value> {
         let column = &row.columns()[index];
@@ -140,129 +427,85 @@ impl PostgresConnector {
     }
     
     /// Build SQL query from internal query representation
-    fn build_sql_query(&self, query: &crate::utils::types::InternalQuery) -> NirvResult<String> {
-        match query.operation {
-            QueryOperation::Select => {
-                let mut sql = String::from("SELECT ");
-                
-                // Handle projections
-                if query.projections.is_empty() {
-                    sql.push('*');
-                } else {
-                    let projections: Vec<String> = query.projections.iter()
-                        .map(|col| {
-                            if let Some(alias) = &col.alias {
-                                format!("{} AS {}", col.name, alias)
-                            } else {
-                                col.name.clone()
-                            }
-                        })
-                        .collect();
-                    sql.push_str(&projections.join(", "));
-                }
-                
-                // Handle FROM clause
-                if let Some(source) = query.sources.first() {
-                    sql.push_str(" FROM ");
-                    sql.push_str(&source.identifier);
-                    if let Some(alias) = &source.alias {
-                        sql.push_str(" AS ");
-                        sql.push_str(alias);
-                    }
-                } else {
-                    return Err(ConnectorError::QueryExecutionFailed(
-                        "No data source specified in query".to_string()
-                    ).into());
-                }
-                
-                // Handle WHERE clause
-                if !query.predicates.is_empty() {
-                    sql.push_str(" WHERE ");
-                    let predicates: Vec<String> = query.predicates.iter()
-                        .map(|pred| self.build_predicate_sql(pred))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    sql.push_str(&predicates.join(" AND "));
-                }
-                
-                // Handle ORDER BY
-                if let Some(order_by) = &query.ordering {
-                    sql.push_str(" ORDER BY ");
-                    let order_columns: Vec<String> = order_by.columns.iter()
-                        .map(|col| {
-                            let direction = match col.direction {
-                                crate::utils::types::OrderDirection::Ascending => "ASC",
-                                crate::utils::types::OrderDirection::Descending => "DESC",
-                            };
-                            format!("{} {}", col.column, direction)
-                        })
-                        .collect();
-                    sql.push_str(&order_columns.join(", "));
-                }
-                
-                // Handle LIMIT
-                if let Some(limit) = query.limit {
-                    sql.push_str(&format!(" LIMIT {}", limit));
-                }
-                
-                Ok(sql)
-            }
-            _ => Err(ConnectorError::UnsupportedOperation(
-                format!("Operation {:?} not supported by PostgreSQL connector", query.operation)
-            ).into()),
-        }
+    pub fn build_sql_query(&self, query: &crate::utils::types::InternalQuery) -> NirvResult<String> {
+        SqlDialect::PostgreSQL.select_sql(query)
     }
     
-    /// Build SQL for a single predicate
-    fn build_predicate_sql(&self, predicate: &crate::utils::types::Predicate) -> NirvResult<String> {
-        let operator_sql = match predicate.operator {
-            PredicateOperator::Equal => "=",
-            PredicateOperator::NotEqual => "!=",
-            PredicateOperator::GreaterThan => ">",
-            PredicateOperator::GreaterThanOrEqual => ">=",
-            PredicateOperator::LessThan => "<",
-            PredicateOperator::LessThanOrEqual => "<=",
-            PredicateOperator::Like => "LIKE",
-            PredicateOperator::IsNull => "IS NULL",
-            PredicateOperator::IsNotNull => "IS NOT NULL",
-            PredicateOperator::In => "IN",
-        };
-        
-        match predicate.operator {
-            PredicateOperator::IsNull | PredicateOperator::IsNotNull => {
-                Ok(format!("{} {}", predicate.column, operator_sql))
-            }
-            PredicateOperator::In => {
-                if let crate::utils::types::PredicateValue::List(values) = &predicate.value {
-                    let value_strings: Vec<String> = values.iter()
-                        .map(|v| self.format_predicate_value(v))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    Ok(format!("{} IN ({})", predicate.column, value_strings.join(", ")))
-                } else {
-                    Err(ConnectorError::QueryExecutionFailed(
-                        "IN operator requires a list of values".to_string()
-                    ).into())
-                }
-            }
-            _ => {
-                let value_str = self.format_predicate_value(&predicate.value)?;
-                Ok(format!("{} {} {}", predicate.column, operator_sql, value_str))
+    /// Build the SQL statement for a query or write, with values as `$1`, `$2`, ... parameters
+    pub fn build_sql_statement(&self, query: &crate::utils::types::InternalQuery) -> NirvResult<SqlStatement> {
+        SqlDialect::PostgreSQL.statement(query)
+    }
+    
+    /// A predicate value as the Rust type the driver binds to a parameter of the given type
+    fn bind_param(ty: &Type, value: &PredicateValue) -> Option<Box<dyn ToSql + Sync + Send>> {
+        match (ty, value) {
+            (&Type::BOOL, PredicateValue::Boolean(b)) => Some(Box::new(*b)),
+            (&Type::INT2, PredicateValue::Integer(i)) => i16::try_from(*i).ok().map(|i| Box::new(i) as Box<dyn ToSql + Sync + Send>),
+            (&Type::INT4, PredicateValue::Integer(i)) => i32::try_from(*i).ok().map(|i| Box::new(i) as Box<dyn ToSql + Sync + Send>),
+            (&Type::INT8, PredicateValue::Integer(i)) => Some(Box::new(*i)),
+            (&Type::FLOAT4, PredicateValue::Number(n)) => Some(Box::new(*n as f32)),
+            (&Type::FLOAT4, PredicateValue::Integer(i)) => Some(Box::new(*i as f32)),
+            (&Type::FLOAT8, PredicateValue::Number(n)) => Some(Box::new(*n)),
+            (&Type::FLOAT8, PredicateValue::Integer(i)) => Some(Box::new(*i as f64)),
+            (&Type::TEXT | &Type::VARCHAR | &Type::BPCHAR | &Type::NAME | &Type::UNKNOWN, PredicateValue::String(s)) => {
+                Some(Box::new(s.clone()))
             }
+            _ => None,
         }
     }
     
-    /// Format predicate value for SQL
-    fn format_predicate_value(&self, value: &crate::utils::types::PredicateValue) -> NirvResult<String> {
+    /// `CREATE TABLE` statement for an object with the given schema, whose name may be
+    /// qualified by a schema
+    pub fn create_table_sql(&self, object_name: &str, schema: &Schema) -> String {
+        let columns: Vec<String> = schema.columns.iter()
+            .map(|column| {
+                let sql_type = match column.data_type {
+                    DataType::Text => "TEXT",
+                    DataType::Integer => "BIGINT",
+                    DataType::Float => "DOUBLE PRECISION",
+                    DataType::Boolean => "BOOLEAN",
+                    DataType::Date => "DATE",
+                    DataType::DateTime => "TIMESTAMP",
+                    DataType::Json => "JSONB",
+                    DataType::Binary => "BYTEA",
+                };
+                let null = if column.nullable { "" } else { " NOT NULL" };
+                format!("{} {}{}", quote_identifier(&column.name), sql_type, null)
+            })
+            .collect();
+        format!("CREATE TABLE {} ({})", Self::qualified_name(object_name), columns.join(", "))
+    }
+    
+    /// `INSERT` statements loading the rows, at most `INSERT_BATCH_ROWS` per statement
+    pub fn insert_sql(&self, object_name: &str, columns: &[ColumnMetadata], rows: &[Row]) -> Vec<String> {
+        let column_list: Vec<String> = columns.iter().map(|column| quote_identifier(&column.name)).collect();
+        rows.chunks(INSERT_BATCH_ROWS)
+            .map(|batch| {
+                let values: Vec<String> = batch.iter()
+                    .map(|row| format!("({})", row.values.iter().map(Self::value_literal).collect::<Vec<_>>().join(", ")))
+                    .collect();
+                format!(
+                    "INSERT INTO {} ({}) VALUES {}",
+                    Self::qualified_name(object_name), column_list.join(", "), values.join(", ")
+                )
+            })
+            .collect()
+    }
+    
+    /// Quote each part of `schema.table`
+    fn qualified_name(object_name: &str) -> String {
+        object_name.split('.').map(quote_identifier).collect::<Vec<_>>().join(".")
+    }
+    
+    fn value_literal(value: &Value) -> String {
         match value {
-            crate::utils::types::PredicateValue::String(s) => Ok(format!("'{}'", s.replace('\'', "''"))),
-            crate::utils::types::PredicateValue::Number(n) => Ok(n.to_string()),
-            crate::utils::types::PredicateValue::Integer(i) => Ok(i.to_string()),
-            crate::utils::types::PredicateValue::Boolean(b) => Ok(b.to_string()),
-            crate::utils::types::PredicateValue::Null => Ok("NULL".to_string()),
-            crate::utils::types::PredicateValue::List(_) => {
-                Err(ConnectorError::QueryExecutionFailed(
-                    "List values should be handled by IN operator".to_string()
-                ).into())
-            }
+            Value::Null => "NULL".to_string(),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) if f.is_finite() => f.to_string(),
+            Value::Float(f) => format!("'{}'", f),
+            Value::Boolean(b) => b.to_string(),
+            Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => format!("'{}'", s.replace('\'', "''")),
+            Value::Binary(bytes) => format!("'\\x{}'", bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()),
         }
     }
 }
@@ -276,21 +519,14 @@ impl Default for PostgresConnector {
 #[async_trait]
 impl Connector for PostgresConnector {
     async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
-        let host = config.connection_params.get("host")
-            .unwrap_or(&"localhost".to_string()).clone();
-        let port = config.connection_params.get("port")
-            .unwrap_or(&"5432".to_string())
-            .parse::<u16>()
-            .map_err(|e| ConnectorError::ConnectionFailed(format!("Invalid port: {}", e)))?;
-        let user = config.connection_params.get("user")
-            .unwrap_or(&"postgres".to_string()).clone();
-        let password = config.connection_params.get("password")
-            .unwrap_or(&"".to_string()).clone();
-        let dbname = config.connection_params.get("dbname")
-            .unwrap_or(&"postgres".to_string()).clone();
+        let PostgresSettings { host, port, user, password, dbname } =
+            PostgresSettings::from_params(&config.connection_params).map_err(issues_error)?;
         
         let max_size = config.max_connections.unwrap_or(10) as usize;
-        let timeout = Duration::from_secs(config.timeout_seconds.unwrap_or(30));
+        let connect_timeout = config.connect_timeout();
+        
+        // Resolve asynchronously so an unresolvable host fails within the connect timeout
+        resolve_host(&host, port, connect_timeout).await?;
         
         // Clone values for error messages before moving them
         let host_clone = host.clone();
@@ -304,79 +540,72 @@ impl Connector for PostgresConnector {
         pg_config.user = Some(user);
         pg_config.password = Some(password);
         pg_config.dbname = Some(dbname);
-        pg_config.pool = Some(deadpool_postgres::PoolConfig::new(max_size));
-        
-        // Create connection pool
-        let pool = pg_config.create_pool(Some(Runtime::Tokio1), NoTls)
+        pg_config.application_name = Some(APPLICATION_NAME.to_string());
+        pg_config.connect_timeout = Some(connect_timeout);
+        pg_config.manager = Some(ManagerConfig {
+            recycling_method: if config.validate_connections {
+                RecyclingMethod::Custom(VALIDATION_QUERY.to_string())
+            } else {
+                RecyclingMethod::Fast
+            },
+        });
+        // A connection failing or outlasting the check is dropped and the next one tried
+        pg_config.pool = Some(PoolConfig {
+            max_size,
+            timeouts: Timeouts {
+                wait: Some(connect_timeout),
+                create: Some(connect_timeout),
+                recycle: Some(config.validation_timeout()),
+            },
+        });
+        
+        // Create connection pool, closing connections past their lifetime or idle timeout
+        // rather than reusing them
+        let recycling = config.clone();
+        let pool = pg_config.builder(NoTls)
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to create pool: {}", e)))?
+            .pre_recycle(Hook::sync_fn(move |_, metrics| {
+                if recycling.is_connection_stale(metrics.age(), metrics.last_used()) {
+                    return Err(HookError::Continue(Some(HookErrorCause::StaticMessage("Connection expired"))));
+                }
+                Ok(())
+            }))
+            .runtime(Runtime::Tokio1)
+            .build()
             .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to create pool: {}", e)))?;
         
         // Test the connection
-        let _client = tokio::time::timeout(timeout, pool.get()).await
-            .map_err(|_| ConnectorError::Timeout(format!(
-                "Connection timeout after {}s when connecting to PostgreSQL at {}:{}",
-                timeout.as_secs(), host_clone, port
-            )))?
-            .map_err(|e| ConnectorError::ConnectionFailed(format!(
+        let description = format!("Connecting to PostgreSQL at {}:{}", host_clone, port);
+        let _client = connect_with_retry(&config.retry_policy, connect_timeout, &description, || async {
+            pool.get().await.map_err(|e| ConnectorError::ConnectionFailed(format!(
                 "Failed to establish connection to PostgreSQL at {}:{} using username '{}' - Error: {} (Check if server is running, credentials are correct, and database '{}' exists)",
                 host_clone, port, user_clone, e, dbname_clone
-            )))?;
+            )).into())
+        }).await?;
         
         self.pool = Some(pool);
+        self.read_timeout = config.read_timeout();
+        self.write_timeout = config.write_timeout();
+        self.fetch_size = config.fetch_size;
         self.connected = true;
         
         Ok(())
     }
     
     async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
-        if !self.connected {
-            return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
-        }
-        
-        let pool = self.pool.as_ref()
-            .ok_or_else(|| ConnectorError::ConnectionFailed("No connection pool available".to_string()))?;
-        
-        let start_time = Instant::now();
-        
-        // Build SQL query
-        let sql = self.build_sql_query(&query.query)?;
-        
-        // Get connection from pool
-        let client = pool.get().await
-            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
-        
-        // Execute query
-        let pg_rows = client.query(&sql, &[]).await
-            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Query execution failed: {}", e)))?;
-        
-        // Convert results
-        let mut columns = Vec::new();
-        let mut rows = Vec::new();
-        
-        if let Some(first_row) = pg_rows.first() {
-            // Extract column metadata
-            for column in first_row.columns() {
-                columns.push(ColumnMetadata {
-                    name: column.name().to_string(),
-                    data_type: self.pg_type_to_data_type(column.type_().oid()),
-                    nullable: true, // PostgreSQL doesn't provide nullable info in query results
-                });
-            }
-        }
-        
-        // Convert all rows
-        for pg_row in &pg_rows {
-            let row = self.convert_pg_row(pg_row)?;
-            rows.push(row);
-        }
-        
-        let execution_time = start_time.elapsed();
-        
-        Ok(QueryResult {
-            columns,
-            rows,
-            affected_rows: Some(pg_rows.len() as u64),
-            execution_time,
-        })
+        self.query_pooled(&query, None).await
+    }
+    
+    async fn execute_query_batched(&self, query: ConnectorQuery, on_batch: &mut RowSink<'_>) -> NirvResult<QueryResult> {
+        self.query_pooled(&query, Some(on_batch)).await
+    }
+    
+    async fn count_rows(&self, mut query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        // The database counts the rows, so only the count is sent back
+        query.query.projections = vec![Column { name: COUNT_ALL.to_string(), alias: None, source: None }];
+        query.query.ordering = None;
+        query.query.limit = None;
+        Ok(self.execute_query(query).await?.count_value())
     }
     
     async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
@@ -405,7 +634,8 @@ impl Connector for PostgresConnector {
                 data_type,
                 is_nullable,
                 udt_name,
-                ordinal_position
+                ordinal_position,
+                col_description(format('%I.%I', table_schema, table_name)::regclass, ordinal_position::int) AS description
             FROM information_schema.columns 
             WHERE table_schema = $1 AND table_name = $2
             ORDER BY ordinal_position
@@ -421,10 +651,21 @@ impl Connector for PostgresConnector {
         }
         
         let mut columns = Vec::new();
+        let mut column_details = HashMap::new();
         for row in &column_rows {
             let column_name: String = row.get("column_name");
             let data_type_str: String = row.get("data_type");
             let is_nullable: String = row.get("is_nullable");
+            // Arrays and user-defined types are only named by their underlying type
+            let native_type = match data_type_str.as_str() {
+                "ARRAY" | "USER-DEFINED" => row.get::<_, String>("udt_name"),
+                _ => data_type_str.clone(),
+            };
+            column_details.insert(column_name.clone(), ColumnDetails {
+                description: row.get("description"),
+                tags: Vec::new(),
+                native_type: Some(native_type),
+            });
             
             let data_type = match data_type_str.as_str() {
                 "character varying" | "text" | "character" => DataType::Text,
@@ -502,15 +743,116 @@ impl Connector for PostgresConnector {
             });
         }
         
+        // Query foreign keys, with their columns paired in constraint order
+        let foreign_key_query = "
+            SELECT
+                ref_ns.nspname AS referenced_schema,
+                ref.relname AS referenced_table,
+                array_agg(a.attname::text ORDER BY k.ord) AS columns,
+                array_agg(ra.attname::text ORDER BY k.ord) AS referenced_columns
+            FROM pg_constraint con
+            JOIN pg_class c ON c.oid = con.conrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            JOIN pg_class ref ON ref.oid = con.confrelid
+            JOIN pg_namespace ref_ns ON ref_ns.oid = ref.relnamespace
+            CROSS JOIN LATERAL unnest(con.conkey, con.confkey) WITH ORDINALITY AS k(attnum, refattnum, ord)
+            JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+            JOIN pg_attribute ra ON ra.attrelid = con.confrelid AND ra.attnum = k.refattnum
+            WHERE con.contype = 'f' AND n.nspname = $1 AND c.relname = $2
+            GROUP BY con.conname, ref_ns.nspname, ref.relname
+            ORDER BY con.conname
+        ";
+        
+        let foreign_key_rows = client.query(foreign_key_query, &[&schema_name, &table_name]).await
+            .unwrap_or_else(|_| Vec::new()); // Ignore errors for foreign key retrieval
+        
+        let foreign_keys = foreign_key_rows.iter().map(|row| {
+            let referenced_schema: String = row.get("referenced_schema");
+            let referenced_table: String = row.get("referenced_table");
+            // Referenced objects are named the way this one was, with its schema only if it had one
+            let referenced_object = if referenced_schema == schema_name && !object_name.contains('.') {
+                referenced_table
+            } else {
+                format!("{}.{}", referenced_schema, referenced_table)
+            };
+            ForeignKey {
+                columns: row.get("columns"),
+                referenced_object,
+                referenced_columns: row.get("referenced_columns"),
+            }
+        }).collect();
+        
         Ok(Schema {
             name: object_name.to_string(),
             columns,
             primary_key,
             indexes,
+            column_details,
+            foreign_keys,
         })
     }
+
+    async fn create_table(&self, object_name: &str, schema: &Schema) -> NirvResult<()> {
+        let pool = self.pool.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No connection pool available".to_string()))?;
+        let client = pool.get().await
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
+        self.run_statement(&client, &self.create_table_sql(object_name, schema)).await
+    }
+    
+    async fn insert_rows(&self, object_name: &str, columns: &[ColumnMetadata], rows: &[Row]) -> NirvResult<u64> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let pool = self.pool.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No connection pool available".to_string()))?;
+        let client = pool.get().await
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
+        
+        // Sent together, the statements load all rows or none
+        let statements = self.insert_sql(object_name, columns, rows).join(";\n");
+        let description = format!("Loading {} rows into {}", rows.len(), object_name);
+        with_timeout(self.write_timeout, &description, async {
+            client.batch_execute(&tag_sql(&statements)).await
+                .map_err(|e| ConnectorError::QueryExecutionFailed(format!("{} failed: {}", description, e)).into())
+        }).await?;
+        Ok(rows.len() as u64)
+    }
+    
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
+        }
+
+        let pool = self.pool.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No connection pool available".to_string()))?;
+
+        let client = pool.get().await
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
+
+        let rows = client.query("
+            SELECT table_schema, table_name
+            FROM information_schema.tables
+            WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
+            ORDER BY table_schema, table_name
+        ", &[]).await
+            .map_err(|e| ConnectorError::SchemaRetrievalFailed(format!("Failed to list tables: {}", e)))?;
+
+        // Tables in the default schema are addressed without a prefix, matching get_schema
+        Ok(rows.iter().map(|row| {
+            let schema_name: String = row.get(0);
+            let table_name: String = row.get(1);
+            if schema_name == "public" {
+                table_name
+            } else {
+                format!("{}.{}", schema_name, table_name)
+            }
+        }).collect())
+    }
     
     async fn disconnect(&mut self) -> NirvResult<()> {
+        // Dropping pinned clients closes their sessions, which aborts open transactions
+        self.transactions.clear();
         self.pool = None;
         self.connected = false;
         Ok(())
@@ -528,6 +870,21 @@ impl Connector for PostgresConnector {
         self.connected
     }
     
+    fn supports_json_functions(&self) -> bool {
+        true
+    }
+    
+    /// `REGEXP` maps to the `~` operator
+    fn supports_regex_predicates(&self) -> bool {
+        true
+    }
+    
+    /// Percentage samples map to `TABLESAMPLE BERNOULLI`; fixed-size samples need the
+    /// tsm_system_rows extension and are taken by the engine instead
+    fn supports_sample(&self, sample: &Sample) -> bool {
+        matches!(sample, Sample::Percent(_))
+    }
+    
     fn get_capabilities(&self) -> ConnectorCapabilities {
         ConnectorCapabilities {
             supports_joins: true,
@@ -538,4 +895,99 @@ impl Connector for PostgresConnector {
             max_concurrent_queries: Some(10),
         }
     }
-}
\ No newline at end of file
+    
+    async fn begin_transaction(&self) -> NirvResult<u64> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
+        }
+        
+        let pool = self.pool.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No connection pool available".to_string()))?;
+        
+        // Pin one pooled connection for the lifetime of the transaction
+        let client = pool.get().await
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to get connection from pool: {}", e)))?;
+        self.run_statement(&client, "BEGIN").await?;
+        
+        let transaction_id = self.next_transaction_id.fetch_add(1, Ordering::Relaxed);
+        self.transactions.insert(transaction_id, Arc::new(Mutex::new(client)));
+        
+        Ok(transaction_id)
+    }
+    
+    async fn execute_in_transaction(&self, transaction_id: u64, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let client = self.transactions.get(&transaction_id)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                format!("Transaction {} is not open", transaction_id)
+            ))?;
+        
+        let client = client.lock().await;
+        self.run_query(&client, &query).await
+    }
+    
+    async fn commit_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        self.end_transaction(transaction_id, "COMMIT").await
+    }
+    
+    async fn rollback_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        self.end_transaction(transaction_id, "ROLLBACK").await
+    }
+    
+    async fn prepare_transaction(&self, transaction_id: u64, global_id: &str) -> NirvResult<()> {
+        let global_id = Self::validate_global_id(global_id)?;
+        // Once prepared, the transaction survives independently of the session
+        self.end_transaction(transaction_id, &format!("PREPARE TRANSACTION '{}'", global_id)).await
+    }
+    
+    async fn commit_prepared(&self, global_id: &str) -> NirvResult<()> {
+        let global_id = Self::validate_global_id(global_id)?;
+        self.execute_prepared_statement(&format!("COMMIT PREPARED '{}'", global_id)).await
+    }
+    
+    async fn rollback_prepared(&self, global_id: &str) -> NirvResult<()> {
+        let global_id = Self::validate_global_id(global_id)?;
+        self.execute_prepared_statement(&format!("ROLLBACK PREPARED '{}'", global_id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{Column, DataSource, InternalQuery, Predicate, PredicateOperator, PredicateValue, QueryOperation};
+
+    #[test]
+    fn test_json_functions_push_down_to_arrow_operators() {
+        let connector = PostgresConnector::new();
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "postgres".to_string(), identifier: "events".to_string(), alias: None });
+        query.projections.push(Column {
+            name: "json_extract(payload, '$.user.name')".to_string(),
+            alias: Some("name".to_string()),
+            source: None,
+        });
+        query.predicates.push(Predicate {
+            column: "json_extract(payload, '$.user.id')".to_string(),
+            operator: PredicateOperator::GreaterThan,
+            value: PredicateValue::Integer(8),
+        });
+
+        assert_eq!(
+            connector.build_sql_query(&query).unwrap(),
+            "SELECT (payload::jsonb)->'user'->>'name' AS \"name\" FROM events WHERE ((payload::jsonb)->'user'->>'id')::numeric > 8"
+        );
+        assert!(connector.supports_json_functions());
+    }
+
+    #[test]
+    fn test_percent_sample_pushes_down_to_tablesample() {
+        let connector = PostgresConnector::new();
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "postgres".to_string(), identifier: "events".to_string(), alias: Some("e".to_string()) });
+        query.sample = Some(Sample::Percent(1.5));
+
+        assert_eq!(connector.build_sql_query(&query).unwrap(), "SELECT * FROM events AS e TABLESAMPLE BERNOULLI (1.5)");
+        assert!(!connector.supports_sample(&Sample::Rows(100)));
+    }
+}
+
@@ -0,0 +1,303 @@
+use chrono::{DateTime, NaiveDate};
+use serde_json::{Map, Value as JsonValue};
+
+use crate::utils::{
+    types::{ColumnMetadata, DataType, QueryWarning},
+    error::{ConnectorError, NirvResult},
+    time_zone::{format_utc, parse_date_time},
+};
+
+/// What happens when a response does not hold to its contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContractMode {
+    /// Fail the query with a schema mismatch naming the first offending record
+    #[default]
+    Strict,
+    /// Convert values to the declared types where possible, read the rest as NULL and
+    /// report what was changed as warnings
+    Coerce,
+}
+
+/// A field an endpoint's records are expected to hold
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractField {
+    pub name: String,
+    pub data_type: DataType,
+    /// Whether every record must hold a non-null value
+    pub required: bool,
+}
+
+/// The schema an endpoint's response is expected to have, written as
+/// `name:type` fields separated by commas with `!` after required types:
+/// `id:integer!,email:text!,signed_up:datetime`. Its fields are the endpoint's columns
+/// whatever the response holds; fields the contract does not name are dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseContract {
+    fields: Vec<ContractField>,
+    mode: ContractMode,
+}
+
+impl ResponseContract {
+    pub fn new(fields: Vec<ContractField>, mode: ContractMode) -> NirvResult<Self> {
+        if fields.is_empty() {
+            return Err(invalid_contract("it has no fields").into());
+        }
+        for (index, field) in fields.iter().enumerate() {
+            if fields[..index].iter().any(|other| other.name == field.name) {
+                return Err(invalid_contract(&format!("field '{}' appears twice", field.name)).into());
+            }
+        }
+        Ok(Self { fields, mode })
+    }
+
+    /// Parse an inline contract: `id:integer!,name:text,tags:json`
+    pub fn parse(contract: &str, mode: ContractMode) -> NirvResult<Self> {
+        let fields = contract.split(',')
+            .filter(|field| !field.trim().is_empty())
+            .map(parse_field)
+            .collect::<NirvResult<Vec<_>>>()?;
+        Self::new(fields, mode)
+    }
+
+    pub fn fields(&self) -> &[ContractField] {
+        &self.fields
+    }
+
+    pub fn mode(&self) -> ContractMode {
+        self.mode
+    }
+
+    /// The endpoint's columns; required fields are not nullable
+    pub fn columns(&self) -> Vec<ColumnMetadata> {
+        self.fields.iter()
+            .map(|field| ColumnMetadata { name: field.name.clone(), data_type: field.data_type.clone(), nullable: !field.required })
+            .collect()
+    }
+
+    /// Check response records against the contract, keeping only its fields with their
+    /// values in the declared types; dates and times become UTC text as the engine holds it
+    pub fn conform(&self, records: Vec<JsonValue>) -> NirvResult<(Vec<JsonValue>, Vec<QueryWarning>)> {
+        let mut changes = Changes::default();
+        let mut conformed = Vec::with_capacity(records.len());
+        for (index, record) in records.into_iter().enumerate() {
+            let mut record = match record {
+                JsonValue::Object(object) => object,
+                other if self.mode == ContractMode::Strict => {
+                    return Err(mismatch(index, &format!("expected an object, got {}", describe(&other))).into());
+                }
+                _ => {
+                    changes.record("*", "records that are not objects skipped");
+                    continue;
+                }
+            };
+            let mut values = Map::with_capacity(self.fields.len());
+            for field in &self.fields {
+                let value = record.remove(&field.name).unwrap_or(JsonValue::Null);
+                let conformed = match (&value, self.mode) {
+                    (JsonValue::Null, _) if field.required && self.mode == ContractMode::Strict => {
+                        return Err(mismatch(index, &format!("required field '{}' is missing", field.name)).into());
+                    }
+                    (JsonValue::Null, _) => {
+                        if field.required {
+                            changes.record(&field.name, "records without this required field");
+                        }
+                        JsonValue::Null
+                    }
+                    (_, ContractMode::Strict) => conform_value(&value, &field.data_type, false).ok_or_else(|| mismatch(index, &format!(
+                        "field '{}' expected {}, got {}", field.name, type_name(&field.data_type), describe(&value)
+                    )))?,
+                    (_, ContractMode::Coerce) => conform_value(&value, &field.data_type, false)
+                        .or_else(|| {
+                            let coerced = conform_value(&value, &field.data_type, true);
+                            changes.record(&field.name, match coerced {
+                                Some(_) => format!("values converted to {}", type_name(&field.data_type)),
+                                None => format!("values not convertible to {} read as NULL", type_name(&field.data_type)),
+                            });
+                            coerced
+                        })
+                        .unwrap_or(JsonValue::Null),
+                };
+                values.insert(field.name.clone(), conformed);
+            }
+            conformed.push(JsonValue::Object(values));
+        }
+        Ok((conformed, changes.into_warnings()))
+    }
+}
+
+/// Changes made to coerce records, counted per field and kind
+#[derive(Debug, Default)]
+struct Changes(Vec<(String, String, u64)>);
+
+impl Changes {
+    fn record(&mut self, field: &str, change: impl Into<String>) {
+        let change = change.into();
+        match self.0.iter_mut().find(|(name, kind, _)| name == field && *kind == change) {
+            Some((_, _, count)) => *count += 1,
+            None => self.0.push((field.to_string(), change, 1)),
+        }
+    }
+
+    fn into_warnings(self) -> Vec<QueryWarning> {
+        self.0.into_iter()
+            .map(|(field, change, count)| QueryWarning::coercion("", &field, format!("{} {}", count, change)))
+            .collect()
+    }
+}
+
+fn invalid_contract(reason: &str) -> ConnectorError {
+    ConnectorError::QueryExecutionFailed(format!("Invalid response contract: {}", reason))
+}
+
+fn mismatch(index: usize, reason: &str) -> ConnectorError {
+    ConnectorError::SchemaMismatch(format!("record {} of the response: {}", index + 1, reason))
+}
+
+fn parse_field(written: &str) -> NirvResult<ContractField> {
+    let (name, data_type) = written.split_once(':')
+        .ok_or_else(|| invalid_contract(&format!("expected a name and a type in '{}'", written.trim())))?;
+    let name = name.trim();
+    let (data_type, required) = match data_type.trim().strip_suffix('!') {
+        Some(data_type) => (data_type.trim(), true),
+        None => (data_type.trim(), false),
+    };
+    if name.is_empty() {
+        return Err(invalid_contract(&format!("expected a name and a type in '{}'", written.trim())).into());
+    }
+    let data_type = match data_type.to_lowercase().as_str() {
+        "text" | "string" => DataType::Text,
+        "integer" | "int" => DataType::Integer,
+        "float" | "double" | "number" => DataType::Float,
+        "boolean" | "bool" => DataType::Boolean,
+        "date" => DataType::Date,
+        "datetime" | "timestamp" => DataType::DateTime,
+        "json" => DataType::Json,
+        other => return Err(invalid_contract(&format!("unknown type '{}' of field '{}'", other, name)).into()),
+    };
+    Ok(ContractField { name: name.to_string(), data_type, required })
+}
+
+/// A value in the declared type, or None when it does not hold it; coercing converts
+/// between representations, such as numbers written as text
+fn conform_value(value: &JsonValue, data_type: &DataType, coerce: bool) -> Option<JsonValue> {
+    match (data_type, value) {
+        (DataType::Text | DataType::Binary, JsonValue::String(_)) => Some(value.clone()),
+        (DataType::Text, _) if coerce => Some(JsonValue::String(match value {
+            JsonValue::String(text) => text.clone(),
+            other => other.to_string(),
+        })),
+        (DataType::Integer, JsonValue::Number(number)) if number.is_i64() => Some(value.clone()),
+        (DataType::Integer, JsonValue::Number(number)) if coerce => number.as_f64()
+            .filter(|float| float.fract() == 0.0 && float.abs() < i64::MAX as f64)
+            .map(|float| JsonValue::from(float as i64)),
+        (DataType::Integer, JsonValue::String(text)) if coerce => text.trim().parse::<i64>().ok().map(JsonValue::from),
+        (DataType::Float, JsonValue::Number(number)) => number.as_f64().map(JsonValue::from),
+        (DataType::Float, JsonValue::String(text)) if coerce => text.trim().parse::<f64>().ok()
+            .filter(|float| float.is_finite())
+            .map(JsonValue::from),
+        (DataType::Boolean, JsonValue::Bool(_)) => Some(value.clone()),
+        (DataType::Boolean, JsonValue::String(text)) if coerce => match text.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" => Some(JsonValue::Bool(true)),
+            "false" | "0" | "no" => Some(JsonValue::Bool(false)),
+            _ => None,
+        },
+        (DataType::Boolean, JsonValue::Number(number)) if coerce => match number.as_i64() {
+            Some(0) => Some(JsonValue::Bool(false)),
+            Some(1) => Some(JsonValue::Bool(true)),
+            _ => None,
+        },
+        (DataType::Date, JsonValue::String(text)) => NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()
+            .or_else(|| coerce.then(|| parse_date_time(text).map(|(time, _)| time.date())).flatten())
+            .map(|date| JsonValue::String(date.format("%Y-%m-%d").to_string())),
+        (DataType::DateTime, JsonValue::String(text)) => parse_date_time(text)
+            .map(|(time, offset)| JsonValue::String(format_utc(offset.map_or(time, |offset| time - offset)))),
+        // Seconds since the epoch
+        (DataType::DateTime, JsonValue::Number(number)) if coerce => number.as_i64()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .map(|time| JsonValue::String(format_utc(time.naive_utc()))),
+        (DataType::Json, _) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn type_name(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Text => "text",
+        DataType::Integer => "an integer",
+        DataType::Float => "a number",
+        DataType::Boolean => "a boolean",
+        DataType::Date => "a date",
+        DataType::DateTime => "a date-time",
+        DataType::Json => "JSON",
+        DataType::Binary => "binary",
+    }
+}
+
+/// A response value as named in mismatch messages
+fn describe(value: &JsonValue) -> String {
+    let text = value.to_string();
+    let text = match text.char_indices().nth(40) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    };
+    let kind = match value {
+        JsonValue::Null => return "null".to_string(),
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    };
+    format!("{} {}", kind, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_strict_contract_reports_drift() {
+        let contract = ResponseContract::parse("id:integer!, name:text, signed_up:datetime", ContractMode::Strict).unwrap();
+        assert!(!contract.columns()[0].nullable);
+
+        let (records, warnings) = contract.conform(vec![
+            json!({ "id": 1, "name": "ann", "signed_up": "2024-05-01T12:00:00+02:00", "extra": true }),
+            json!({ "id": 2 }),
+        ]).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(records[0], json!({ "id": 1, "name": "ann", "signed_up": "2024-05-01 10:00:00" }));
+        assert_eq!(records[1], json!({ "id": 2, "name": null, "signed_up": null }));
+
+        let error = contract.conform(vec![json!({ "id": 1 }), json!({ "id": "2" })]).unwrap_err();
+        assert!(error.to_string().contains("record 2 of the response: field 'id' expected an integer, got string \"2\""), "{}", error);
+        let error = contract.conform(vec![json!({ "name": "bo" })]).unwrap_err();
+        assert!(error.to_string().contains("required field 'id' is missing"), "{}", error);
+
+        assert!(ResponseContract::parse("id:uuid", ContractMode::Strict).is_err());
+        assert!(ResponseContract::parse("id:integer,id:text", ContractMode::Strict).is_err());
+    }
+
+    #[test]
+    fn test_coerce_contract_converts_and_warns() {
+        let contract = ResponseContract::parse("id:integer!,active:boolean,score:float", ContractMode::Coerce).unwrap();
+        let (records, warnings) = contract.conform(vec![
+            json!({ "id": "7", "active": "yes", "score": 1 }),
+            json!({ "id": 8, "active": "maybe", "score": "2.5" }),
+            json!({ "active": true }),
+        ]).unwrap();
+        assert_eq!(records, vec![
+            json!({ "id": 7, "active": true, "score": 1.0 }),
+            json!({ "id": 8, "active": null, "score": 2.5 }),
+            json!({ "id": null, "active": true, "score": null }),
+        ]);
+        let messages: Vec<String> = warnings.iter().map(|warning| warning.message.clone()).collect();
+        assert_eq!(messages, vec![
+            "column 'id': 1 values converted to an integer",
+            "column 'active': 1 values converted to a boolean",
+            "column 'active': 1 values not convertible to a boolean read as NULL",
+            "column 'score': 1 values converted to a number",
+            "column 'id': 1 records without this required field",
+        ]);
+    }
+}
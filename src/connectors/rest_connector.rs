@@ -5,16 +5,30 @@ use std::sync::Arc;
 use reqwest::{Client, Method, Response};
 use serde_json::Value as JsonValue;
 use url::Url;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use tokio::sync::{Mutex, Semaphore};
 use tokio::time::sleep;
+use futures_util::future::try_join_all;
 
 use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::network::{connect_with_retry, yield_after_rows, RetryPolicy};
+use crate::connectors::debug_log::{connector_debug_log, current_query_tag, REQUEST_ID_HEADER};
+use crate::connectors::json_flatten::{infer_json_columns, JsonFlattening};
+use crate::connectors::response_contract::ResponseContract;
+use crate::connectors::body_template::{BodyTemplate, RequestBody};
+use crate::connectors::disk_cache::{cache_max_bytes, DiskCache, DiskCacheEntry};
 use crate::utils::{
     types::{
         ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, DataType,
-        Row, Value, PredicateOperator, PredicateValue
+        Row, Value, Predicate, PredicateOperator, PredicateValue, CacheStatus, ExecutionStats, SourceStats
     },
-    error::{ConnectorError, NirvResult},
+    json_path::JsonExtract,
+    regex_function::{regex_matches, RegexFunction},
+    hash_function::HashFunction,
+    date_function::DateTrunc,
+    error::{ConnectorError, NirvError, NirvResult},
+    identifier::IdentifierCase,
 };
 
 /// Authentication configuration for REST APIs
@@ -31,20 +45,85 @@ pub enum AuthConfig {
 struct CacheEntry {
     data: JsonValue,
     timestamp: Instant,
-    ttl: Duration,
+    /// An empty or failed response, kept only for the negative TTL
+    negative: bool,
+    /// Error of a failed request
+    failure: Option<String>,
+    /// What the API identified the response by, for revalidating it once expired
+    validators: Validators,
+    /// When the API last sent or confirmed the data
+    fetched_at: DateTime<Utc>,
+}
+
+/// The `ETag` and `Last-Modified` a response was sent with
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn of(response: &Response) -> Self {
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        Self {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        }
+    }
+    
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Records held by the cache, with what it knows of their freshness
+struct Snapshot {
+    records: Vec<JsonValue>,
+    validators: Validators,
+    fetched_at: DateTime<Utc>,
+}
+
+impl Snapshot {
+    /// The records, and the stats of a query answered with them
+    fn answer(self, cache: CacheStatus) -> (Vec<JsonValue>, SourceStats) {
+        let stats = SourceStats {
+            cache: Some(cache),
+            fetched_at: Some(self.fetched_at),
+            cache_age: (cache != CacheStatus::Miss).then(|| (Utc::now() - self.fetched_at).to_std().unwrap_or_default()),
+            last_modified: self.validators.last_modified,
+            ..Default::default()
+        };
+        (self.records, stats)
+    }
+}
+
+/// What the cache holds for a query
+enum Cached {
+    Fresh(Snapshot),
+    /// Past its TTL but within the stale-while-revalidate window
+    Stale(Snapshot),
+    /// Past its TTL, but the API can tell whether it changed
+    Expired(Snapshot),
+    Failed(String),
+    Missing,
 }
 
 impl CacheEntry {
-    fn new(data: JsonValue, ttl: Duration) -> Self {
+    fn new(data: JsonValue) -> Self {
         Self {
             data,
             timestamp: Instant::now(),
-            ttl,
+            negative: false,
+            failure: None,
+            validators: Validators::default(),
+            fetched_at: Utc::now(),
         }
     }
     
-    fn is_expired(&self) -> bool {
-        self.timestamp.elapsed() > self.ttl
+    /// An entry read back from disk, as old as when it was stored
+    fn restored(entry: DiskCacheEntry) -> Option<Self> {
+        let age = (Utc::now() - entry.stored_at).to_std().unwrap_or_default();
+        Some(Self { timestamp: Instant::now().checked_sub(age)?, fetched_at: entry.stored_at, ..Self::new(entry.data) })
     }
 }
 
@@ -112,10 +191,37 @@ pub struct RestConnector {
     base_url: Option<Url>,
     auth_config: AuthConfig,
     cache: Arc<DashMap<String, CacheEntry>>,
+    /// Keeps cached responses across restarts, and bounds the cache in memory to its own entries
+    disk_cache: Option<Arc<DiskCache>>,
     cache_ttl: Duration,
-    rate_limiter: Option<RateLimiter>,
+    /// How long empty and failed responses are cached; they are not cached when unset
+    negative_cache_ttl: Option<Duration>,
+    /// How long past its TTL an entry is still returned while it is refreshed
+    stale_while_revalidate: Option<Duration>,
+    /// Keys of the entries being refreshed in the background
+    refreshing: Arc<DashMap<String, ()>>,
+    /// Shared by the copies requests are made from, so concurrent pages wait their turn
+    rate_limiter: Option<Arc<Mutex<RateLimiter>>>,
     connected: bool,
     endpoint_mappings: HashMap<String, EndpointMapping>,
+    retry_policy: RetryPolicy,
+    request_timeout: Duration,
+    flattening: Option<JsonFlattening>,
+    identifier_case: IdentifierCase,
+    /// Response header endpoints without their own count setting report a total in
+    count_header: Option<String>,
+    /// Pages and shards of one query requested at once
+    fetch_concurrency: usize,
+}
+
+/// Where an endpoint reports how many rows it holds, so `COUNT(*)` need not fetch them
+#[derive(Debug, Clone, PartialEq)]
+pub enum CountSource {
+    /// A header of the endpoint's response, such as `X-Total-Count`
+    Header(String),
+    /// Another endpoint whose response holds the total, at a dotted path into its JSON or
+    /// as the whole response
+    Endpoint { path: String, response_path: Option<String> },
 }
 
 /// Mapping configuration for REST endpoints
@@ -126,6 +232,83 @@ pub struct EndpointMapping {
     pub query_params: HashMap<String, String>,
     pub response_path: Option<String>, // JSONPath to extract data array
     pub id_field: Option<String>,      // Field to use as primary key
+    pub count: Option<CountSource>,
+    /// Schema the endpoint's records must have; inferred from each response when unset
+    pub contract: Option<ResponseContract>,
+    /// Body the endpoint is requested with, filled from each query's filters and limit
+    pub body: Option<BodyTemplate>,
+    /// How the endpoint splits its rows across pages; one request fetches all when unset
+    pub pagination: Option<Pagination>,
+    /// Query parameters of each shard of the endpoint, such as one region each; the rows
+    /// of every shard are fetched and merged in order
+    pub shards: Vec<HashMap<String, String>>,
+}
+
+/// Where a paginated endpoint is told which page to return
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageStyle {
+    /// A page number, counted from `first`
+    Number { param: String, first: u64 },
+    /// The number of rows to skip
+    Offset { param: String },
+}
+
+/// How an endpoint's rows are split across pages. A page with fewer than `page_size` rows
+/// is the last; when the first page reports the total row count the remaining pages are
+/// all requested at once, otherwise they are requested in waves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pagination {
+    pub style: PageStyle,
+    pub page_size: u64,
+    /// Parameter the page size is sent in, if the API takes one
+    pub size_param: Option<String>,
+    /// Dotted path to the total row count in the first page's response
+    pub total_path: Option<String>,
+    /// Pages fetched at most, per shard
+    pub max_pages: u64,
+}
+
+impl Pagination {
+    /// Pages numbered from 1 in the `param` query parameter
+    pub fn page_number(param: &str, page_size: u64) -> Self {
+        Self::new(PageStyle::Number { param: param.to_string(), first: 1 }, page_size)
+    }
+
+    /// Pages starting at the row offset in the `param` query parameter
+    pub fn offset(param: &str, page_size: u64) -> Self {
+        Self::new(PageStyle::Offset { param: param.to_string() }, page_size)
+    }
+
+    fn new(style: PageStyle, page_size: u64) -> Self {
+        Self { style, page_size: page_size.max(1), size_param: None, total_path: None, max_pages: 1000 }
+    }
+
+    pub fn with_size_param(mut self, param: &str) -> Self {
+        self.size_param = Some(param.to_string());
+        self
+    }
+
+    pub fn with_total_path(mut self, path: &str) -> Self {
+        self.total_path = Some(path.to_string());
+        self
+    }
+
+    pub fn with_max_pages(mut self, max_pages: u64) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    /// Query parameters requesting a page, counted from 0
+    fn params(&self, page: u64) -> Vec<(String, String)> {
+        let mut params = vec![match &self.style {
+            PageStyle::Number { param, first } => (param.clone(), (first + page).to_string()),
+            PageStyle::Offset { param } => (param.clone(), (page * self.page_size).to_string()),
+        }];
+        if let Some(size_param) = &self.size_param {
+            params.push((size_param.clone(), self.page_size.to_string()));
+        }
+        params
+    }
 }
 
 impl RestConnector {
@@ -136,10 +319,20 @@ impl RestConnector {
             base_url: None,
             auth_config: AuthConfig::None,
             cache: Arc::new(DashMap::new()),
+            disk_cache: None,
             cache_ttl: Duration::from_secs(300), // 5 minutes default
+            negative_cache_ttl: None,
+            stale_while_revalidate: None,
+            refreshing: Arc::new(DashMap::new()),
             rate_limiter: None,
             connected: false,
             endpoint_mappings: HashMap::new(),
+            retry_policy: RetryPolicy::none(),
+            request_timeout: Duration::from_secs(30),
+            flattening: None,
+            identifier_case: IdentifierCase::default(),
+            count_header: None,
+            fetch_concurrency: 4,
         }
     }
     
@@ -155,9 +348,33 @@ impl RestConnector {
         self
     }
     
+    /// Cache empty and failed responses for a short TTL
+    pub fn with_negative_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_cache_ttl = Some(ttl);
+        self
+    }
+    
+    /// Return entries up to this long past their TTL while refreshing them in the background
+    pub fn with_stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.stale_while_revalidate = Some(window);
+        self
+    }
+    
     /// Configure rate limiting
     pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
-        self.rate_limiter = Some(RateLimiter::new(config));
+        self.rate_limiter = Some(Arc::new(Mutex::new(RateLimiter::new(config))));
+        self
+    }
+    
+    /// Request up to this many pages and shards of a query at once
+    pub fn with_fetch_concurrency(mut self, concurrency: usize) -> Self {
+        self.fetch_concurrency = concurrency.max(1);
+        self
+    }
+    
+    /// Flatten nested response objects into separate columns
+    pub fn with_flattening(mut self, flattening: JsonFlattening) -> Self {
+        self.flattening = Some(flattening);
         self
     }
     
@@ -166,12 +383,93 @@ impl RestConnector {
         self.endpoint_mappings.insert(name, mapping);
     }
     
-    /// Build HTTP request with authentication
-    async fn build_request(&self, method: Method, url: &Url) -> NirvResult<reqwest::RequestBuilder> {
+    /// Mapping for an endpoint name; a full `http(s)://` URL without a mapping is fetched with GET
+    fn resolve_endpoint(&self, name: &str) -> Option<EndpointMapping> {
+        if let Some(mapping) = self.endpoint_mappings.get(name) {
+            return Some(mapping.clone());
+        }
+        let url = Url::parse(name).ok().filter(|url| matches!(url.scheme(), "http" | "https"))?;
+        Some(EndpointMapping {
+            path: url.to_string(),
+            method: Method::GET,
+            query_params: HashMap::new(),
+            response_path: None,
+            id_field: None,
+            count: None,
+            contract: None,
+            body: None,
+            pagination: None,
+            shards: Vec::new(),
+        })
+    }
+    
+    /// URL of an endpoint with its query parameters
+    fn endpoint_url(&self, mapping: &EndpointMapping) -> NirvResult<Url> {
+        let base_url = self.base_url.as_ref()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected".to_string()))?;
+        
+        let mut url = base_url.join(&mapping.path)
+            .map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("Failed to build URL: {}", e)
+            ))?;
+        
+        // Add query parameters
+        {
+            let mut query_pairs = url.query_pairs_mut();
+            for (key, value) in &mapping.query_params {
+                query_pairs.append_pair(key, value);
+            }
+        }
+        Ok(url)
+    }
+    
+    /// A copy of this connector sharing its client and cache, for making requests from `&self`
+    fn detached(&self, cache_ttl: Duration) -> RestConnector {
+        // This is a bit of a hack to get around the borrow checker
+        // We need to make the method call mutable but we can't change the trait
+        RestConnector {
+            client: self.client.clone(),
+            base_url: self.base_url.clone(),
+            auth_config: self.auth_config.clone(),
+            cache: self.cache.clone(),
+            disk_cache: self.disk_cache.clone(),
+            cache_ttl,
+            negative_cache_ttl: self.negative_cache_ttl,
+            stale_while_revalidate: self.stale_while_revalidate,
+            refreshing: self.refreshing.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            connected: self.connected,
+            endpoint_mappings: self.endpoint_mappings.clone(),
+            retry_policy: self.retry_policy.clone(),
+            request_timeout: self.request_timeout,
+            flattening: self.flattening.clone(),
+            identifier_case: self.identifier_case,
+            count_header: self.count_header.clone(),
+            fetch_concurrency: self.fetch_concurrency,
+        }
+    }
+    
+    /// Build HTTP request with authentication, and the engine query it is made for. With
+    /// validators of a cached response, the request asks for the response only if it changed.
+    async fn build_request(&self, method: Method, url: &Url, body: Option<&RequestBody>, conditions: Option<&Validators>) -> NirvResult<reqwest::RequestBuilder> {
         let client = self.client.as_ref()
             .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected".to_string()))?;
         
         let mut request = client.request(method, url.clone());
+        if let Some(tag) = current_query_tag() {
+            request = request.header(REQUEST_ID_HEADER, tag);
+        }
+        if let Some(body) = body {
+            request = request.header(reqwest::header::CONTENT_TYPE, &body.content_type).body(body.text.clone());
+        }
+        if let Some(conditions) = conditions {
+            if let Some(etag) = &conditions.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &conditions.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
         
         // Apply authentication
         match &self.auth_config {
@@ -191,20 +489,40 @@ impl RestConnector {
     }
     
     /// Execute HTTP request with rate limiting
-    async fn execute_request(&mut self, method: Method, url: &Url) -> NirvResult<Response> {
-        // Apply rate limiting
-        if let Some(ref mut limiter) = self.rate_limiter {
-            limiter.acquire().await?;
+    async fn execute_request(&self, method: Method, url: &Url, body: Option<&RequestBody>) -> NirvResult<Response> {
+        self.execute_conditional(method, url, body, None).await
+    }
+    
+    /// Execute HTTP request, accepting `304 Not Modified` when it is conditional
+    async fn execute_conditional(&self, method: Method, url: &Url, body: Option<&RequestBody>, conditions: Option<&Validators>) -> NirvResult<Response> {
+        // Credentials are sent in headers, which are never logged
+        match body {
+            Some(body) => connector_debug_log().request("rest", &format!("{} {} {}", method, url, body.text)),
+            None => connector_debug_log().request("rest", &format!("{} {}", method, url)),
         }
         
-        let request = self.build_request(method, url).await?;
-        
-        let response = request.send().await
-            .map_err(|e| ConnectorError::QueryExecutionFailed(
-                format!("HTTP request failed: {}", e)
-            ))?;
+        // Connection failures and timeouts are retried; other errors are not. Each attempt
+        // takes its own permit, so retries stay within the rate limit too.
+        let response = connect_with_retry(&self.retry_policy, self.request_timeout, "HTTP request", || async {
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.lock().await.acquire().await?;
+            }
+            let request = self.build_request(method.clone(), url, body, conditions).await?;
+            request.send().await.map_err(|e| {
+                let message = format!("HTTP request failed: {}", e);
+                if e.is_connect() {
+                    ConnectorError::ConnectionFailed(message).into()
+                } else if e.is_timeout() {
+                    ConnectorError::Timeout(message).into()
+                } else {
+                    ConnectorError::QueryExecutionFailed(message).into()
+                }
+            })
+        }).await?;
         
-        if !response.status().is_success() {
+        let not_modified = conditions.is_some() && response.status() == reqwest::StatusCode::NOT_MODIFIED;
+        if !response.status().is_success() && !not_modified {
+            connector_debug_log().response("rest", &response.status().to_string());
             return Err(ConnectorError::QueryExecutionFailed(
                 format!("HTTP request failed with status: {}", response.status())
             ).into());
@@ -213,29 +531,260 @@ impl RestConnector {
         Ok(response)
     }
     
-    /// Get data from cache or fetch from API
-    async fn get_cached_or_fetch(&mut self, cache_key: &str, url: &Url, method: Method) -> NirvResult<JsonValue> {
-        // Check cache first
-        if let Some(entry) = self.cache.get(cache_key) {
-            if !entry.is_expired() {
-                return Ok(entry.data.clone());
+    /// Cache the records of a response in memory and on disk, dropping what the disk cache
+    /// evicts. With a negative TTL set, empty responses are kept only for that long.
+    fn store(&self, cache_key: &str, records: &[JsonValue], validators: Validators) {
+        let data = JsonValue::Array(records.to_vec());
+        let negative = records.is_empty() && self.negative_cache_ttl.is_some();
+        if let (Some(disk_cache), false) = (&self.disk_cache, negative) {
+            for evicted in disk_cache.insert(cache_key, &data) {
+                self.cache.remove(&evicted);
             }
         }
-        
-        // Fetch from API
-        let response = self.execute_request(method, url).await?;
+        self.cache.insert(cache_key.to_string(), CacheEntry { negative, validators, ..CacheEntry::new(data) });
+    }
+    
+    /// Restart the TTL of an entry the API reported unchanged
+    fn revalidated(&self, cache_key: &str) {
+        if let Some(mut entry) = self.cache.get_mut(cache_key) {
+            entry.timestamp = Instant::now();
+            entry.fetched_at = Utc::now();
+        }
+    }
+    
+    /// Remember a failed request for the negative TTL, if one is set
+    fn store_failure(&self, cache_key: &str, error: &NirvError) {
+        if self.negative_cache_ttl.is_some() {
+            self.cache.insert(cache_key.to_string(), CacheEntry {
+                negative: true,
+                failure: Some(error.to_string()),
+                ..CacheEntry::new(JsonValue::Null)
+            });
+        }
+    }
+    
+    /// What the cache holds for a key, by the TTLs of the query reading it
+    fn cached(&self, cache_key: &str) -> Cached {
+        let Some(entry) = self.cache.get(cache_key) else {
+            return Cached::Missing;
+        };
+        let age = entry.timestamp.elapsed();
+        let snapshot = || Snapshot {
+            records: match &entry.data {
+                JsonValue::Array(records) => records.clone(),
+                _ => Vec::new(),
+            },
+            validators: entry.validators.clone(),
+            fetched_at: entry.fetched_at,
+        };
+        if entry.negative {
+            return match (self.negative_cache_ttl.is_some_and(|ttl| age <= ttl), &entry.failure) {
+                (true, Some(failure)) => Cached::Failed(failure.clone()),
+                (true, None) => Cached::Fresh(snapshot()),
+                (false, _) => Cached::Missing,
+            };
+        }
+        if age <= self.cache_ttl {
+            Cached::Fresh(snapshot())
+        } else if self.stale_while_revalidate.is_some_and(|window| age <= self.cache_ttl + window) {
+            Cached::Stale(snapshot())
+        } else if !entry.validators.is_empty() {
+            Cached::Expired(snapshot())
+        } else {
+            Cached::Missing
+        }
+    }
+    
+    /// Keys of the cached records and schemas of an endpoint
+    fn endpoint_keys(&self, endpoint_name: &str) -> Vec<String> {
+        let prefix = format!("{}:", endpoint_name);
+        self.cache.iter()
+            .map(|entry| entry.key().clone())
+            .filter(|key| {
+                let key = key.strip_prefix("records:").unwrap_or(key);
+                // Past the row count of records cut short, and the marker of schemas
+                let key = match key.split_once(':') {
+                    Some((enough, rest)) if !enough.is_empty() && enough.bytes().all(|b| b.is_ascii_digit()) => rest,
+                    _ => key,
+                };
+                let key = key.strip_prefix("schema:").unwrap_or(key);
+                key.starts_with(&prefix)
+            })
+            .collect()
+    }
+    
+    /// Fill the memory cache from the disk cache, so a restart does not refetch everything
+    fn warm_up(&self, disk_cache: &DiskCache) {
+        for entry in disk_cache.entries() {
+            let key = entry.key.clone();
+            if let Some(entry) = CacheEntry::restored(entry) {
+                self.cache.insert(key, entry);
+            }
+        }
+    }
+    
+    async fn fetch_json(&self, method: Method, url: &Url, body: Option<&RequestBody>) -> NirvResult<JsonValue> {
+        let response = self.execute_request(method, url, body).await?;
+        Self::read_json(response).await
+    }
+    
+    async fn read_json(response: Response) -> NirvResult<JsonValue> {
         let json_data: JsonValue = response.json().await
             .map_err(|e| ConnectorError::QueryExecutionFailed(
                 format!("Failed to parse JSON response: {}", e)
             ))?;
-        
-        // Cache the result
-        let entry = CacheEntry::new(json_data.clone(), self.cache_ttl);
-        self.cache.insert(cache_key.to_string(), entry);
-        
+        let debug_log = connector_debug_log();
+        if debug_log.is_enabled() {
+            debug_log.response("rest", &json_data.to_string());
+        }
         Ok(json_data)
     }
     
+    /// The records of an endpoint, from the cache or every page of every shard. Once
+    /// `enough` records are fetched no further pages are requested. An entry within the
+    /// stale-while-revalidate window is returned at once and refreshed in the background, and
+    /// an expired entry the API answers `304 Not Modified` for is kept as a cache hit. The
+    /// stats tell whether the records came from the cache and when they were fetched.
+    async fn fetch_records(&self, cache_key: &str, mapping: &EndpointMapping, url: &Url, body: Option<&RequestBody>, enough: Option<usize>) -> NirvResult<(Vec<JsonValue>, SourceStats)> {
+        // Paged records are cached under a key telling whether they were cut short
+        let paged = mapping.pagination.is_some() || !mapping.shards.is_empty();
+        let cache_key = match (paged, enough) {
+            (true, Some(enough)) => format!("records:{}:{}", enough, cache_key),
+            _ => format!("records:{}", cache_key),
+        };
+        let expired = match self.cached(&cache_key) {
+            Cached::Fresh(snapshot) => return Ok(snapshot.answer(CacheStatus::Hit)),
+            Cached::Stale(snapshot) => {
+                self.refresh_in_background(cache_key, mapping, url, body, enough, snapshot.validators.clone());
+                return Ok(snapshot.answer(CacheStatus::Stale));
+            }
+            Cached::Failed(failure) => return Err(ConnectorError::QueryExecutionFailed(
+                format!("{} (cached failure)", failure)
+            ).into()),
+            Cached::Expired(snapshot) => Some(snapshot),
+            Cached::Missing => None,
+        };
+        let conditions = expired.as_ref().map(|snapshot| &snapshot.validators);
+        match self.fetch_uncached(mapping, url, body, enough, conditions).await {
+            Ok(None) => {
+                self.revalidated(&cache_key);
+                let snapshot = expired.expect("only conditional requests are answered not modified");
+                Ok(Snapshot { fetched_at: Utc::now(), ..snapshot }.answer(CacheStatus::Hit))
+            }
+            Ok(Some((records, validators))) => {
+                self.store(&cache_key, &records, validators.clone());
+                Ok(Snapshot { records, validators, fetched_at: Utc::now() }.answer(CacheStatus::Miss))
+            }
+            Err(e) => {
+                self.store_failure(&cache_key, &e);
+                Err(e)
+            }
+        }
+    }
+    
+    /// Refetch a stale entry unless a refresh of it is already running; the stale entry
+    /// is kept when the refresh fails
+    fn refresh_in_background(&self, cache_key: String, mapping: &EndpointMapping, url: &Url, body: Option<&RequestBody>, enough: Option<usize>, validators: Validators) {
+        if self.refreshing.insert(cache_key.clone(), ()).is_some() {
+            return;
+        }
+        let connector = self.detached(self.cache_ttl);
+        let (mapping, url, body) = (mapping.clone(), url.clone(), body.cloned());
+        tokio::spawn(async move {
+            let conditions = Some(&validators).filter(|validators| !validators.is_empty());
+            match connector.fetch_uncached(&mapping, &url, body.as_ref(), enough, conditions).await {
+                Ok(None) => connector.revalidated(&cache_key),
+                Ok(Some((records, validators))) => connector.store(&cache_key, &records, validators),
+                Err(_) => {}
+            }
+            connector.refreshing.remove(&cache_key);
+        });
+    }
+    
+    /// The records of an endpoint fetched from the API, with the validators of its response;
+    /// None when the request was conditional and the API reported no change. Only unpaged
+    /// endpoints are revalidated, as one response holds all their records.
+    async fn fetch_uncached(&self, mapping: &EndpointMapping, url: &Url, body: Option<&RequestBody>, enough: Option<usize>, conditions: Option<&Validators>) -> NirvResult<Option<(Vec<JsonValue>, Validators)>> {
+        if mapping.pagination.is_none() && mapping.shards.is_empty() {
+            let response = self.execute_conditional(mapping.method.clone(), url, body, conditions).await?;
+            if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                connector_debug_log().response("rest", &response.status().to_string());
+                return Ok(None);
+            }
+            let validators = Validators::of(&response);
+            let json_data = Self::read_json(response).await?;
+            return Ok(Some((self.extract_data_array(&json_data, mapping.response_path.as_deref())?, validators)));
+        }
+        let permits = Semaphore::new(self.fetch_concurrency);
+        let shards = match mapping.shards.is_empty() {
+            true => vec![HashMap::new()],
+            false => mapping.shards.clone(),
+        };
+        let shard_records = try_join_all(shards.iter().map(|shard| {
+            let mut shard_url = url.clone();
+            shard_url.query_pairs_mut().extend_pairs(shard);
+            self.fetch_shard(mapping, shard_url, body, enough, &permits)
+        })).await?;
+        Ok(Some((shard_records.into_iter().flatten().collect(), Validators::default())))
+    }
+    
+    /// The records of every page of one shard, in page order
+    async fn fetch_shard(&self, mapping: &EndpointMapping, url: Url, body: Option<&RequestBody>, enough: Option<usize>, permits: &Semaphore) -> NirvResult<Vec<JsonValue>> {
+        let Some(pagination) = &mapping.pagination else {
+            return Ok(self.fetch_page(mapping, &url, body, permits).await?.1);
+        };
+        let page_url = |page: u64| {
+            let mut page_url = url.clone();
+            page_url.query_pairs_mut().extend_pairs(pagination.params(page));
+            page_url
+        };
+        let (first, mut records) = self.fetch_page(mapping, &page_url(0), body, permits).await?;
+        let page_size = pagination.page_size as usize;
+        let done = |records: &[JsonValue]| enough.is_some_and(|enough| records.len() >= enough);
+        if records.len() < page_size || done(&records) {
+            return Ok(records);
+        }
+        
+        // With the total known every page is requested at once, else a wave at a time
+        let total = pagination.total_path.as_deref().and_then(|path| {
+            let total = path.split('.').filter(|part| !part.is_empty()).try_fold(&first, |current, part| current.get(part))?;
+            total.as_u64().or_else(|| total.as_str()?.trim().parse().ok())
+        });
+        let wanted_pages = |rows: u64| rows.div_ceil(pagination.page_size).min(pagination.max_pages);
+        let (last_page, wave) = match total {
+            Some(total) => (wanted_pages(enough.map_or(total, |enough| total.min(enough as u64))), u64::MAX),
+            None => (pagination.max_pages, self.fetch_concurrency as u64),
+        };
+        let mut next = 1;
+        while next < last_page {
+            // No more pages than the rows still wanted could fill
+            let wanted = enough.map_or(u64::MAX, |enough| wanted_pages(enough.saturating_sub(records.len()) as u64));
+            let end = next.saturating_add(wave.min(wanted)).min(last_page);
+            let pages = try_join_all((next..end).map(|page| {
+                let url = page_url(page);
+                async move { self.fetch_page(mapping, &url, body, permits).await.map(|(_, records)| records) }
+            })).await?;
+            for page in pages {
+                let last = page.len() < page_size;
+                records.extend(page);
+                if last || done(&records) {
+                    return Ok(records);
+                }
+            }
+            next = end;
+        }
+        Ok(records)
+    }
+    
+    /// One page's response and the records in it, once a request is free to go
+    async fn fetch_page(&self, mapping: &EndpointMapping, url: &Url, body: Option<&RequestBody>, permits: &Semaphore) -> NirvResult<(JsonValue, Vec<JsonValue>)> {
+        let _permit = permits.acquire().await
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Page fetch cancelled: {}", e)))?;
+        let json_data = self.fetch_json(mapping.method.clone(), url, body).await?;
+        let records = self.extract_data_array(&json_data, mapping.response_path.as_deref())?;
+        Ok((json_data, records))
+    }
+    
     /// Extract data array from JSON response using JSONPath
     fn extract_data_array(&self, json: &JsonValue, path: Option<&str>) -> NirvResult<Vec<JsonValue>> {
         match path {
@@ -286,6 +835,12 @@ impl RestConnector {
             } else {
                 Value::Null
             };
+            // Dates and times of a response contract arrive as text
+            let value = match (value, &column.data_type) {
+                (Value::Text(text), DataType::Date) => Value::Date(text),
+                (Value::Text(text), DataType::DateTime) => Value::DateTime(text),
+                (value, _) => value,
+            };
             values.push(value);
         }
         
@@ -313,11 +868,21 @@ impl RestConnector {
         }
     }
     
+    /// Apply the configured flattening and unnesting to response records
+    fn flatten(&self, data: Vec<JsonValue>) -> Vec<JsonValue> {
+        match &self.flattening {
+            Some(flattening) => flattening.apply(data),
+            None => data,
+        }
+    }
+    
     /// Infer schema from JSON data
     fn infer_schema_from_json(&self, data: &[JsonValue], object_name: &str) -> Schema {
         let mut columns = Vec::new();
         
-        if let Some(JsonValue::Object(obj)) = data.first() {
+        if self.flattening.is_some() {
+            columns = infer_json_columns(data);
+        } else if let Some(JsonValue::Object(obj)) = data.first() {
             for (key, value) in obj {
                 let data_type = match value {
                     JsonValue::Null => DataType::Text,
@@ -346,11 +911,13 @@ impl RestConnector {
             columns,
             primary_key: None,
             indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
         }
     }
     
     /// Apply WHERE clause predicates to filter data
-    fn apply_predicates(&self, data: Vec<JsonValue>, predicates: &[crate::utils::types::Predicate]) -> Vec<JsonValue> {
+    fn apply_predicates(&self, data: Vec<JsonValue>, predicates: &[Predicate]) -> Vec<JsonValue> {
         if predicates.is_empty() {
             return data;
         }
@@ -359,7 +926,9 @@ impl RestConnector {
             .filter(|item| {
                 if let JsonValue::Object(obj) = item {
                     predicates.iter().all(|predicate| {
-                        if let Some(field_value) = obj.get(&predicate.column) {
+                        let field = self.identifier_case.resolve(&predicate.column, obj.keys().map(String::as_str))
+                            .and_then(|index| obj.values().nth(index));
+                        if let Some(field_value) = field {
                             let value = self.json_value_to_value(field_value);
                             self.evaluate_predicate(&value, &predicate.operator, &predicate.value)
                         } else {
@@ -387,6 +956,7 @@ impl RestConnector {
                 self.value_less_than(value, predicate_value) || self.values_equal(value, predicate_value)
             },
             PredicateOperator::Like => self.value_like(value, predicate_value),
+            PredicateOperator::Regexp => matches!(predicate_value, PredicateValue::String(pattern) if regex_matches(value, pattern)),
             PredicateOperator::In => self.value_in(value, predicate_value),
             PredicateOperator::IsNull => matches!(value, Value::Null),
             PredicateOperator::IsNotNull => !matches!(value, Value::Null),
@@ -510,7 +1080,7 @@ impl Connector for RestConnector {
                         ))?.clone();
                     AuthConfig::Basic { username, password }
                 },
-                "none" | _ => AuthConfig::None,
+                _ => AuthConfig::None,
             };
         }
         
@@ -521,6 +1091,21 @@ impl Connector for RestConnector {
             }
         }
         
+        // Persist the cache, starting from what an earlier run left
+        if let Some(cache_dir) = config.connection_params.get("cache_dir") {
+            let disk_cache = Arc::new(DiskCache::open(cache_dir, cache_max_bytes(&config.connection_params)?)?);
+            self.warm_up(&disk_cache);
+            self.disk_cache = Some(disk_cache);
+        }
+        
+        // Configure negative caching and stale-while-revalidate
+        if let Some(seconds) = config.connection_params.get("negative_cache_ttl_seconds").and_then(|s| s.parse::<u64>().ok()) {
+            self.negative_cache_ttl = Some(Duration::from_secs(seconds));
+        }
+        if let Some(seconds) = config.connection_params.get("stale_while_revalidate_seconds").and_then(|s| s.parse::<u64>().ok()) {
+            self.stale_while_revalidate = Some(Duration::from_secs(seconds));
+        }
+        
         // Configure rate limiting
         if let Some(rps_str) = config.connection_params.get("rate_limit_rps") {
             if let Ok(rps) = rps_str.parse::<f64>() {
@@ -532,20 +1117,33 @@ impl Connector for RestConnector {
                     requests_per_second: rps,
                     burst_size,
                 };
-                self.rate_limiter = Some(RateLimiter::new(rate_config));
+                self.rate_limiter = Some(Arc::new(Mutex::new(RateLimiter::new(rate_config))));
             }
         }
         
+        self.identifier_case = IdentifierCase::from_params(&config.connection_params);
+        self.count_header = config.connection_params.get("count_header").cloned();
+        if let Some(concurrency) = config.connection_params.get("fetch_concurrency").and_then(|value| value.parse::<usize>().ok()) {
+            self.fetch_concurrency = concurrency.max(1);
+        }
+        
+        // Configure nested object flattening
+        if let Some(flattening) = JsonFlattening::from_params(&config.connection_params) {
+            self.flattening = Some(flattening);
+        }
+        
         // Create HTTP client
-        let timeout = Duration::from_secs(config.timeout_seconds.unwrap_or(30));
         let client = Client::builder()
-            .timeout(timeout)
+            .connect_timeout(config.connect_timeout())
+            .timeout(config.read_timeout())
             .build()
             .map_err(|e| ConnectorError::ConnectionFailed(
                 format!("Failed to create HTTP client: {}", e)
             ))?;
         
         self.client = Some(client);
+        self.retry_policy = config.retry_policy.clone();
+        self.request_timeout = config.connect_timeout() + config.read_timeout();
         self.base_url = Some(base_url);
         self.connected = true;
         
@@ -567,57 +1165,53 @@ impl Connector for RestConnector {
         let endpoint_name = &source.identifier;
         
         // Get endpoint mapping
-        let mapping = self.endpoint_mappings.get(endpoint_name)
+        let mapping = self.resolve_endpoint(endpoint_name)
             .ok_or_else(|| ConnectorError::QueryExecutionFailed(
                 format!("No endpoint mapping found for '{}'", endpoint_name)
             ))?;
         
-        let base_url = self.base_url.as_ref()
-            .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected".to_string()))?;
+        let url = self.endpoint_url(&mapping)?;
         
-        let mut url = base_url.join(&mapping.path)
-            .map_err(|e| ConnectorError::QueryExecutionFailed(
-                format!("Failed to build URL: {}", e)
-            ))?;
-        
-        // Add query parameters
-        {
-            let mut query_pairs = url.query_pairs_mut();
-            for (key, value) in &mapping.query_params {
-                query_pairs.append_pair(key, value);
-            }
-        }
+        let body = mapping.body.as_ref()
+            .map(|template| template.render(&query.query.predicates, query.query.limit, self.identifier_case));
         
         let start_time = Instant::now();
-        let cache_key = format!("{}:{}", endpoint_name, url.as_str());
-        
-        // This is a bit of a hack to get around the borrow checker
-        // We need to make the method call mutable but we can't change the trait
-        let mut temp_connector = RestConnector {
-            client: self.client.clone(),
-            base_url: self.base_url.clone(),
-            auth_config: self.auth_config.clone(),
-            cache: self.cache.clone(),
-            cache_ttl: self.cache_ttl,
-            rate_limiter: None, // We'll handle rate limiting differently
-            connected: self.connected,
-            endpoint_mappings: self.endpoint_mappings.clone(),
+        let cache_key = match &body {
+            Some(body) => format!("{}:{}:{}", endpoint_name, url.as_str(), body.text),
+            None => format!("{}:{}", endpoint_name, url.as_str()),
         };
         
-        let json_data = temp_connector.get_cached_or_fetch(&cache_key, &url, mapping.method.clone()).await?;
-        let data_array = temp_connector.extract_data_array(&json_data, mapping.response_path.as_deref())?;
+        let mut temp_connector = self.detached(query.query.hints.cache_ttl.unwrap_or(self.cache_ttl));
+        // A query turning the cache off reads live data, not stale responses or cached failures
+        if query.query.hints.cache_ttl == Some(Duration::ZERO) {
+            temp_connector.stale_while_revalidate = None;
+            temp_connector.negative_cache_ttl = None;
+        }
+        
+        // Unfiltered queries need no more pages than their limit
+        let enough = query.query.limit.filter(|_| query.query.predicates.is_empty()).map(|limit| limit as usize);
+        let (data_array, freshness) = temp_connector.fetch_records(&cache_key, &mapping, &url, body.as_ref(), enough).await?;
+        let data_array = temp_connector.flatten(data_array);
+        let (data_array, warnings) = match &mapping.contract {
+            Some(contract) => contract.conform(data_array)?,
+            None => (data_array, Vec::new()),
+        };
         
         // Apply WHERE clause predicates
         let filtered_data = temp_connector.apply_predicates(data_array, &query.query.predicates);
         
-        // Infer schema from data
-        let schema = temp_connector.infer_schema_from_json(&filtered_data, endpoint_name);
+        // The contract's columns, or a schema inferred from the data
+        let schema = match &mapping.contract {
+            Some(contract) => Schema { name: endpoint_name.clone(), columns: contract.columns(), primary_key: None, indexes: Vec::new(), column_details: HashMap::new(), foreign_keys: Vec::new() },
+            None => temp_connector.infer_schema_from_json(&filtered_data, endpoint_name),
+        };
         
         // Convert to rows
         let mut rows = Vec::new();
         for item in &filtered_data {
             let row = temp_connector.json_to_row(item, &schema.columns);
             rows.push(row);
+            yield_after_rows(rows.len()).await;
         }
         
         // Apply LIMIT if specified
@@ -626,25 +1220,103 @@ impl Connector for RestConnector {
         }
         
         let execution_time = start_time.elapsed();
+        let stats = ExecutionStats {
+            sources: vec![SourceStats { source: format!("{}.{}", source.object_type, endpoint_name), ..freshness }],
+            ..Default::default()
+        };
         
         Ok(QueryResult {
             columns: schema.columns,
             rows,
             affected_rows: Some(filtered_data.len() as u64),
             execution_time,
+            stats: Some(stats),
+            warnings,
         })
     }
     
+    async fn refresh(&self, object_name: &str) -> NirvResult<u64> {
+        let keys = self.endpoint_keys(object_name);
+        for key in &keys {
+            self.cache.remove(key);
+            if let Some(disk_cache) = &self.disk_cache {
+                disk_cache.remove(key);
+            }
+        }
+        Ok(keys.len() as u64)
+    }
+    
+    async fn count_rows(&self, query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
+        }
+        // Totals the API reports count every row, before any filtering
+        if !query.query.predicates.is_empty() {
+            return Ok(None);
+        }
+        let endpoint_name = &query.query.sources.first()
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed("No data source specified in query".to_string()))?
+            .identifier;
+        let mapping = self.resolve_endpoint(endpoint_name)
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                format!("No endpoint mapping found for '{}'", endpoint_name)
+            ))?;
+        let Some(count) = mapping.count.clone().or_else(|| self.count_header.clone().map(CountSource::Header)) else {
+            return Ok(None);
+        };
+        
+        let temp_connector = self.detached(self.cache_ttl);
+        match count {
+            // An endpoint not sending the header has its rows counted instead
+            CountSource::Header(header) => {
+                let url = self.endpoint_url(&mapping)?;
+                let body = mapping.body.as_ref().map(|template| template.render(&[], None, self.identifier_case));
+                let response = temp_connector.execute_request(mapping.method.clone(), &url, body.as_ref()).await?;
+                Ok(response.headers().get(header.as_str())
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse().ok()))
+            }
+            CountSource::Endpoint { path, response_path } => {
+                let count_mapping = EndpointMapping { path, method: Method::GET, response_path, body: None, pagination: None, shards: Vec::new(), ..mapping };
+                let url = self.endpoint_url(&count_mapping)?;
+                let response = temp_connector.execute_request(Method::GET, &url, None).await?;
+                let json: JsonValue = response.json().await
+                    .map_err(|e| ConnectorError::QueryExecutionFailed(
+                        format!("Failed to parse JSON response: {}", e)
+                    ))?;
+                let path = count_mapping.response_path.as_deref().unwrap_or("");
+                let total = path.split('.')
+                    .filter(|part| !part.is_empty())
+                    .try_fold(&json, |current, part| current.get(part))
+                    .and_then(|total| total.as_u64().or_else(|| total.as_str()?.trim().parse().ok()))
+                    .ok_or_else(|| ConnectorError::QueryExecutionFailed(
+                        format!("No row count at '{}' in the count response for '{}'", path, endpoint_name)
+                    ))?;
+                Ok(Some(total))
+            }
+        }
+    }
+    
     async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
         if !self.connected {
             return Err(ConnectorError::ConnectionFailed("Not connected".to_string()).into());
         }
         
         // Get endpoint mapping
-        let mapping = self.endpoint_mappings.get(object_name)
+        let mapping = self.resolve_endpoint(object_name)
             .ok_or_else(|| ConnectorError::SchemaRetrievalFailed(
                 format!("No endpoint mapping found for '{}'", object_name)
             ))?;
+        if let Some(contract) = &mapping.contract {
+            return Ok(Schema {
+                name: object_name.to_string(),
+                columns: contract.columns(),
+                primary_key: mapping.id_field.clone().map(|id| vec![id]),
+                indexes: Vec::new(),
+                column_details: HashMap::new(),
+                foreign_keys: Vec::new(),
+            });
+        }
         
         let base_url = self.base_url.as_ref()
             .ok_or_else(|| ConnectorError::ConnectionFailed("Not connected".to_string()))?;
@@ -662,22 +1334,17 @@ impl Connector for RestConnector {
             }
         }
         
-        let cache_key = format!("schema:{}:{}", object_name, url.as_str());
-        
-        // Similar hack for mutability
-        let mut temp_connector = RestConnector {
-            client: self.client.clone(),
-            base_url: self.base_url.clone(),
-            auth_config: self.auth_config.clone(),
-            cache: self.cache.clone(),
-            cache_ttl: self.cache_ttl,
-            rate_limiter: None,
-            connected: self.connected,
-            endpoint_mappings: self.endpoint_mappings.clone(),
+        let body = mapping.body.as_ref().map(|template| template.render(&[], None, self.identifier_case));
+        let cache_key = match &body {
+            Some(body) => format!("schema:{}:{}:{}", object_name, url.as_str(), body.text),
+            None => format!("schema:{}:{}", object_name, url.as_str()),
         };
         
-        let json_data = temp_connector.get_cached_or_fetch(&cache_key, &url, mapping.method.clone()).await?;
-        let data_array = temp_connector.extract_data_array(&json_data, mapping.response_path.as_deref())?;
+        let temp_connector = self.detached(self.cache_ttl);
+        
+        // The first page is enough to infer a paginated endpoint's schema
+        let (data_array, _) = temp_connector.fetch_records(&cache_key, &mapping, &url, body.as_ref(), Some(1)).await?;
+        let data_array = temp_connector.flatten(data_array);
         
         Ok(temp_connector.infer_schema_from_json(&data_array, object_name))
     }
@@ -702,6 +1369,15 @@ impl Connector for RestConnector {
         self.connected
     }
     
+    /// LIKE and REGEXP patterns are matched by the engine, which implements the full SQL semantics
+    fn supports_predicate(&self, predicate: &Predicate) -> bool {
+        !matches!(predicate.operator, PredicateOperator::Like | PredicateOperator::Regexp)
+            && JsonExtract::parse(&predicate.column).is_none()
+            && RegexFunction::parse(&predicate.column).is_none()
+            && HashFunction::parse(&predicate.column).is_none()
+            && DateTrunc::parse(&predicate.column).is_none()
+    }
+    
     fn get_capabilities(&self) -> ConnectorCapabilities {
         ConnectorCapabilities {
             supports_joins: false, // No cross-endpoint joins for now
@@ -0,0 +1,419 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::debug_log::{connector_debug_log, tag_sql, APPLICATION_NAME};
+use crate::connectors::network::with_timeout;
+use crate::connectors::warehouse::{epoch_days_to_date, epoch_seconds_to_datetime, seconds_to_time, send_json, RsaSigningKey, TokenCache};
+use crate::utils::{
+    types::{
+        ConnectorType, ConnectorQuery, DataSource, InternalQuery, QueryOperation, QueryResult, Schema,
+        ColumnMetadata, DataType, Row, Value, PredicateValue, Column, COUNT_ALL
+    },
+    error::{ConnectorError, NirvResult},
+    connector_settings::{SnowflakeSettings, issues_error},
+    sql_dialect::{SqlBuilder, SqlDialect, SqlStatement},
+};
+
+/// Key-pair tokens last at most an hour
+const TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// Longest wait between checks on a statement that is still running
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Snowflake connector over the SQL API, authenticated with a key pair. A query runs whole
+/// as one statement, with its values bound to `?` placeholders.
+pub struct SnowflakeConnector {
+    client: Option<Client>,
+    settings: Option<SnowflakeSettings>,
+    key: Option<RsaSigningKey>,
+    token: TokenCache,
+    read_timeout: Duration,
+    connected: bool,
+}
+
+impl fmt::Debug for SnowflakeConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnowflakeConnector")
+            .field("connected", &self.connected)
+            .finish()
+    }
+}
+
+impl SnowflakeConnector {
+    /// Create a new Snowflake connector
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            settings: None,
+            key: None,
+            token: TokenCache::default(),
+            read_timeout: Duration::from_secs(30),
+            connected: false,
+        }
+    }
+
+    /// Build the SQL statement for a query, with predicate values as `?` parameters
+    pub fn build_sql_statement(&self, query: &InternalQuery) -> NirvResult<SqlStatement> {
+        SqlDialect::Snowflake.select_statement(query)
+    }
+
+    /// Convert a Snowflake SQL API column type to internal DataType
+    pub fn snowflake_type_to_data_type(&self, snowflake_type: &str, scale: i64) -> DataType {
+        match snowflake_type.to_lowercase().as_str() {
+            "fixed" if scale == 0 => DataType::Integer,
+            "fixed" | "real" | "float" | "double" => DataType::Float,
+            "boolean" => DataType::Boolean,
+            "date" => DataType::Date,
+            "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => DataType::DateTime,
+            "binary" => DataType::Binary,
+            "variant" | "object" | "array" => DataType::Json,
+            _ => DataType::Text,
+        }
+    }
+
+    /// Bindings of a statement's parameters, keyed by their 1-based position
+    fn bindings(params: &[PredicateValue]) -> JsonValue {
+        params.iter().enumerate()
+            .map(|(index, value)| {
+                let (binding_type, value) = match value {
+                    PredicateValue::String(text) => ("TEXT", text.clone()),
+                    PredicateValue::Integer(number) => ("FIXED", number.to_string()),
+                    PredicateValue::Number(number) => ("REAL", number.to_string()),
+                    PredicateValue::Boolean(flag) => ("BOOLEAN", flag.to_string()),
+                    // Only scalar values are bound; NULL is written into the statement
+                    PredicateValue::Null | PredicateValue::List(_) => ("TEXT", String::new()),
+                };
+                ((index + 1).to_string(), json!({ "type": binding_type, "value": value }))
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    fn client(&self) -> NirvResult<(&Client, &SnowflakeSettings)> {
+        match (&self.client, &self.settings) {
+            (Some(client), Some(settings)) if self.connected => Ok((client, settings)),
+            _ => Err(ConnectorError::ConnectionFailed("Not connected to Snowflake".to_string()).into()),
+        }
+    }
+
+    /// A key-pair JWT for the user, issued again when the one held is about to expire.
+    /// Account and user are upper case, and a region-qualified account is named without
+    /// its region.
+    async fn access_token(&self) -> NirvResult<String> {
+        let (settings, key) = self.settings.as_ref().zip(self.key.as_ref())
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No Snowflake credentials".to_string()))?;
+        self.token.get(|| async {
+            let account = settings.account.split('.').next().unwrap_or_default().to_uppercase();
+            let qualified_user = format!("{}.{}", account, settings.user.to_uppercase());
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let token = key.sign_jwt(&json!({
+                "iss": format!("{}.{}", qualified_user, key.public_key_fingerprint()?),
+                "sub": qualified_user,
+                "iat": now,
+                "exp": now + TOKEN_LIFETIME.as_secs(),
+            }))?;
+            Ok((token, TOKEN_LIFETIME))
+        }).await
+    }
+
+    fn statements_url(settings: &SnowflakeSettings, path: &str) -> String {
+        format!("{}/api/v2/statements{}", settings.api_url.trim_end_matches('/'), path)
+    }
+
+    fn authorize(request: reqwest::RequestBuilder, token: &str) -> reqwest::RequestBuilder {
+        request.bearer_auth(token)
+            .header("X-Snowflake-Authorization-Token-Type", "KEYPAIR_JWT")
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(reqwest::header::USER_AGENT, APPLICATION_NAME)
+    }
+
+    /// Run a statement, waiting for it to finish, and read every partition of its results
+    async fn run_statement(&self, statement: &SqlStatement) -> NirvResult<QueryResult> {
+        with_timeout(self.read_timeout, "Snowflake query", self.query_partitions(statement)).await
+    }
+
+    async fn query_partitions(&self, statement: &SqlStatement) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+        let (client, settings) = self.client()?;
+        let sql = tag_sql(&statement.sql());
+        let mut body = json!({
+            "statement": sql,
+            "timeout": self.read_timeout.as_secs(),
+            "parameters": { "MULTI_STATEMENT_COUNT": "1" },
+        });
+        for (key, value) in [("warehouse", &settings.warehouse), ("database", &settings.database), ("schema", &settings.schema), ("role", &settings.role)] {
+            if let Some(value) = value {
+                body[key] = json!(value);
+            }
+        }
+        if !statement.params().is_empty() {
+            body["bindings"] = Self::bindings(statement.params());
+        }
+
+        let token = self.access_token().await?;
+        let request = Self::authorize(client.post(Self::statements_url(settings, "")), &token).json(&body);
+        let (mut status, mut response) = send_json("snowflake", &format!("statement {} {:?}", sql, statement.params()), request).await?;
+
+        // 202 means the statement is still running
+        let mut poll_interval = Duration::from_millis(100);
+        while status == StatusCode::ACCEPTED {
+            let handle = Self::statement_handle(&response)?;
+            tokio::time::sleep(poll_interval).await;
+            poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+            let request = Self::authorize(client.get(Self::statements_url(settings, &format!("/{}", handle))), &token);
+            (status, response) = send_json("snowflake", &format!("status of statement {}", handle), request).await?;
+        }
+
+        let row_type = response.pointer("/resultSetMetaData/rowType").and_then(JsonValue::as_array).cloned().unwrap_or_default();
+        let columns: Vec<ColumnMetadata> = row_type.iter().map(|column| self.row_type_column(column)).collect();
+        let mut rows = Vec::new();
+        self.push_rows(&mut rows, &response, &row_type);
+
+        // Large results come in partitions after the first
+        let partitions = response.pointer("/resultSetMetaData/partitionInfo").and_then(JsonValue::as_array).map_or(1, Vec::len);
+        if partitions > 1 {
+            let handle = Self::statement_handle(&response)?;
+            for partition in 1..partitions {
+                let request = Self::authorize(client.get(Self::statements_url(settings, &format!("/{}", handle))), &token)
+                    .query(&[("partition", partition)]);
+                let (_, response) = send_json("snowflake", &format!("partition {} of statement {}", partition, handle), request).await?;
+                self.push_rows(&mut rows, &response, &row_type);
+            }
+        }
+
+        let result = QueryResult {
+            columns,
+            rows,
+            affected_rows: None,
+            execution_time: start_time.elapsed(),
+            stats: None,
+            warnings: Vec::new(),
+        };
+        connector_debug_log().result("snowflake", &result);
+        Ok(result)
+    }
+
+    fn statement_handle(response: &JsonValue) -> NirvResult<String> {
+        response["statementHandle"].as_str().map(str::to_string)
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed("Snowflake response has no statement handle".to_string()).into())
+    }
+
+    fn push_rows(&self, rows: &mut Vec<Row>, response: &JsonValue, row_type: &[JsonValue]) {
+        for row in response["data"].as_array().into_iter().flatten() {
+            let cells = row.as_array().map(Vec::as_slice).unwrap_or_default();
+            rows.push(Row::new(row_type.iter().zip(cells).map(|(column, cell)| Self::cell_value(cell, column)).collect()));
+        }
+    }
+
+    fn row_type_column(&self, column: &JsonValue) -> ColumnMetadata {
+        ColumnMetadata {
+            name: column["name"].as_str().unwrap_or_default().to_string(),
+            data_type: self.snowflake_type_to_data_type(column["type"].as_str().unwrap_or_default(), column["scale"].as_i64().unwrap_or_default()),
+            nullable: column["nullable"].as_bool().unwrap_or(true),
+        }
+    }
+
+    /// A cell's value by its column's type. Values arrive as text: dates as days and
+    /// timestamps as seconds since the epoch, times as seconds since midnight.
+    fn cell_value(cell: &JsonValue, column: &JsonValue) -> Value {
+        let Some(text) = cell.as_str() else {
+            return if cell.is_null() { Value::Null } else { Value::Json(cell.to_string()) };
+        };
+        let scale = column["scale"].as_i64().unwrap_or_default();
+        let typed = match column["type"].as_str().unwrap_or_default().to_lowercase().as_str() {
+            "fixed" if scale == 0 => text.parse().ok().map(Value::Integer)
+                .or_else(|| text.parse().ok().map(Value::Float)),
+            "fixed" | "real" | "float" | "double" => text.parse().ok().map(Value::Float),
+            "boolean" => match text {
+                "true" | "1" => Some(Value::Boolean(true)),
+                "false" | "0" => Some(Value::Boolean(false)),
+                _ => None,
+            },
+            "date" => epoch_days_to_date(text).map(Value::Date),
+            "time" => seconds_to_time(text).map(Value::Text),
+            // A TIMESTAMP_TZ value is followed by its offset; the seconds are already UTC
+            "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => text.split_whitespace().next()
+                .and_then(epoch_seconds_to_datetime)
+                .map(Value::DateTime),
+            "binary" => decode_hex(text).map(Value::Binary),
+            "variant" | "object" | "array" => Some(Value::Json(text.to_string())),
+            _ => None,
+        };
+        typed.unwrap_or_else(|| Value::Text(text.to_string()))
+    }
+
+    /// A statement of fixed SQL text
+    fn sql(sql: &str) -> SqlStatement {
+        let mut builder = SqlBuilder::new(SqlDialect::Snowflake);
+        builder.push(sql);
+        builder.finish()
+    }
+
+    /// A query of no rows from an object, whose result still describes its columns
+    fn empty_query(object_name: &str) -> InternalQuery {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "snowflake".to_string(), identifier: object_name.to_string(), alias: None });
+        query.limit = Some(0);
+        query
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl Default for SnowflakeConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Connector for SnowflakeConnector {
+    async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
+        let settings = SnowflakeSettings::from_params(&config.connection_params).map_err(issues_error)?;
+        let pem = match (&settings.private_key, &settings.private_key_path) {
+            (Some(pem), _) => pem.clone(),
+            (None, Some(path)) => fs::read_to_string(path)
+                .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to read Snowflake private key {}: {}", path, e)))?,
+            (None, None) => unreachable!("settings require a private key"),
+        };
+        let key = RsaSigningKey::from_pem(&pem, settings.private_key_passphrase.as_deref())?;
+        let client = Client::builder()
+            .connect_timeout(config.connect_timeout())
+            .timeout(config.read_timeout())
+            .build()
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Failed to create HTTP client: {}", e)))?;
+
+        self.client = Some(client);
+        self.settings = Some(settings);
+        self.key = Some(key);
+        self.token.clear().await;
+        self.read_timeout = config.read_timeout();
+        self.connected = true;
+
+        // A trivial statement checks the account, key and session settings
+        if let Err(e) = self.run_statement(&Self::sql("SELECT 1")).await {
+            self.connected = false;
+            self.client = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let statement = self.build_sql_statement(&query.query)?;
+        self.run_statement(&statement).await
+    }
+
+    async fn count_rows(&self, mut query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        // Snowflake counts the rows, so only the count is sent back
+        query.query.projections = vec![Column { name: COUNT_ALL.to_string(), alias: None, source: None }];
+        query.query.ordering = None;
+        query.query.limit = None;
+        Ok(self.execute_query(query).await?.count_value())
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        let statement = self.build_sql_statement(&Self::empty_query(object_name))?;
+        let result = self.run_statement(&statement).await
+            .map_err(|e| ConnectorError::SchemaRetrievalFailed(format!("Failed to describe {}: {}", object_name, e)))?;
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns: result.columns,
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    /// Tables and views of the session's schema
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        let result = self.run_statement(&Self::sql(
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = CURRENT_SCHEMA() ORDER BY table_name"
+        )).await?;
+        Ok(result.rows.into_iter()
+            .filter_map(|row| match row.values.into_iter().next() {
+                Some(Value::Text(name)) => Some(name),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.token.clear().await;
+        self.client = None;
+        self.key = None;
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Snowflake
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities {
+            supports_joins: false,
+            supports_aggregations: true,
+            supports_subqueries: false,
+            supports_transactions: false,
+            supports_schema_introspection: true,
+            // A warehouse runs 8 statements at once before queueing them
+            max_concurrent_queries: Some(8),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_values_by_column_type() {
+        let column = |column_type: &str, scale: i64| json!({ "name": "c", "type": column_type, "scale": scale, "nullable": true });
+        assert_eq!(SnowflakeConnector::cell_value(&json!("42"), &column("fixed", 0)), Value::Integer(42));
+        assert_eq!(SnowflakeConnector::cell_value(&json!("12.50"), &column("fixed", 2)), Value::Float(12.5));
+        assert_eq!(SnowflakeConnector::cell_value(&json!("19844"), &column("date", 0)), Value::Date("2024-05-01".to_string()));
+        assert_eq!(
+            SnowflakeConnector::cell_value(&json!("1714557600.000000000 1560"), &column("timestamp_tz", 9)),
+            Value::DateTime("2024-05-01 10:00:00".to_string())
+        );
+        assert_eq!(SnowflakeConnector::cell_value(&json!("6869"), &column("binary", 0)), Value::Binary(b"hi".to_vec()));
+        assert_eq!(SnowflakeConnector::cell_value(&json!("{\"a\":1}"), &column("variant", 0)), Value::Json("{\"a\":1}".to_string()));
+        assert_eq!(SnowflakeConnector::cell_value(&JsonValue::Null, &column("text", 0)), Value::Null);
+
+        let connector = SnowflakeConnector::new();
+        assert_eq!(connector.snowflake_type_to_data_type("FIXED", 0), DataType::Integer);
+        assert_eq!(connector.snowflake_type_to_data_type("fixed", 2), DataType::Float);
+    }
+
+    #[test]
+    fn test_bindings_are_keyed_by_position() {
+        let bindings = SnowflakeConnector::bindings(&[PredicateValue::String("EMEA".to_string()), PredicateValue::Integer(8)]);
+        assert_eq!(bindings, json!({
+            "1": { "type": "TEXT", "value": "EMEA" },
+            "2": { "type": "FIXED", "value": "8" },
+        }));
+    }
+}
@@ -1,26 +1,43 @@
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use tiberius::{Client, Config, AuthMethod, EncryptionLevel};
+use tiberius::{Client, Config, AuthMethod, EncryptionLevel, ColumnData};
+use tiberius::numeric::Numeric;
 use tokio::net::TcpStream;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 use tokio_util::compat::{TokioAsyncWriteCompatExt, Compat};
 use futures_util::TryStreamExt;
 
-use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities, RowSink};
+use crate::connectors::network::{connect_with_retry, resolve_host, with_timeout, yield_after_rows};
+use crate::connectors::debug_log::{connector_debug_log, tag_sql, APPLICATION_NAME};
 use crate::utils::{
     types::{
-        ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, DataType,
-        Row, Value, QueryOperation, PredicateOperator
+        ConnectorType, ConnectorQuery, QueryResult, QueryWarning, Schema, ColumnMetadata, DataType,
+        Row, Value, PredicateValue, Column, QueryOperation, COUNT_ALL
     },
     error::{ConnectorError, NirvResult},
+    connector_settings::{SqlServerSettings, issues_error},
+    sql_dialect::{SqlDialect, SqlStatement},
 };
 
+type SqlServerClient = Client<Compat<TcpStream>>;
+
 /// SQL Server connector using tiberius
 pub struct SqlServerConnector {
-    client: Mutex<Option<Client<Compat<TcpStream>>>>,
+    client: Arc<Mutex<Option<SqlServerClient>>>,
+    /// Open transaction holding exclusive use of the client until it ends
+    transaction: Mutex<Option<(u64, OwnedMutexGuard<Option<SqlServerClient>>)>>,
+    next_transaction_id: AtomicU64,
     connected: bool,
     connection_config: Option<Config>,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    /// Rows handed to a batched reader at a time; unset, results are read in one go
+    fetch_size: Option<u32>,
 }
 
 impl fmt::Debug for SqlServerConnector {
@@ -35,41 +52,21 @@ impl SqlServerConnector {
     /// Create a new SQL Server connector
     pub fn new() -> Self {
         Self {
-            client: Mutex::new(None),
+            client: Arc::new(Mutex::new(None)),
+            transaction: Mutex::new(None),
+            next_transaction_id: AtomicU64::new(1),
             connected: false,
             connection_config: None,
+            read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            fetch_size: None,
         }
     }
     
     /// Build connection string from configuration parameters
     pub fn build_connection_string(&self, config: &ConnectorInitConfig) -> NirvResult<String> {
-        let server = config.connection_params.get("server")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "server parameter is required".to_string()
-            ))?;
-        
-        let default_port = "1433".to_string();
-        let port = config.connection_params.get("port")
-            .unwrap_or(&default_port);
-        
-        let database = config.connection_params.get("database")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "database parameter is required".to_string()
-            ))?;
-        
-        let username = config.connection_params.get("username")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "username parameter is required".to_string()
-            ))?;
-        
-        let password = config.connection_params.get("password")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "password parameter is required".to_string()
-            ))?;
-        
-        let trust_cert = config.connection_params.get("trust_cert")
-            .map(|s| s.parse::<bool>().unwrap_or(false))
-            .unwrap_or(false);
+        let SqlServerSettings { server, port, database, username, password, trust_cert } =
+            SqlServerSettings::from_params(&config.connection_params).map_err(issues_error)?;
         
         let mut connection_string = format!(
             "server={},{};database={};user={};password={}",
@@ -85,135 +82,22 @@ impl SqlServerConnector {
     
     /// Build SQL query from internal query representation
     pub fn build_sql_query(&self, query: &crate::utils::types::InternalQuery) -> NirvResult<String> {
-        match query.operation {
-            QueryOperation::Select => {
-                let mut sql = String::from("SELECT ");
-                
-                // Handle LIMIT with TOP clause (SQL Server style)
-                if let Some(limit) = query.limit {
-                    sql.push_str(&format!("TOP {} ", limit));
-                }
-                
-                // Handle projections
-                if query.projections.is_empty() {
-                    sql.push('*');
-                } else {
-                    let projections: Vec<String> = query.projections.iter()
-                        .map(|col| {
-                            if let Some(alias) = &col.alias {
-                                format!("{} AS {}", col.name, alias)
-                            } else {
-                                col.name.clone()
-                            }
-                        })
-                        .collect();
-                    sql.push_str(&projections.join(", "));
-                }
-                
-                // Handle FROM clause
-                if let Some(source) = query.sources.first() {
-                    sql.push_str(" FROM ");
-                    sql.push_str(&source.identifier);
-                    if let Some(alias) = &source.alias {
-                        sql.push_str(" AS ");
-                        sql.push_str(alias);
-                    }
-                } else {
-                    return Err(ConnectorError::QueryExecutionFailed(
-                        "No data source specified in query".to_string()
-                    ).into());
-                }
-                
-                // Handle WHERE clause
-                if !query.predicates.is_empty() {
-                    sql.push_str(" WHERE ");
-                    let predicates: Vec<String> = query.predicates.iter()
-                        .map(|pred| self.build_predicate_sql(pred))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    sql.push_str(&predicates.join(" AND "));
-                }
-                
-                // Handle ORDER BY
-                if let Some(order_by) = &query.ordering {
-                    sql.push_str(" ORDER BY ");
-                    let order_columns: Vec<String> = order_by.columns.iter()
-                        .map(|col| {
-                            let direction = match col.direction {
-                                crate::utils::types::OrderDirection::Ascending => "ASC",
-                                crate::utils::types::OrderDirection::Descending => "DESC",
-                            };
-                            format!("{} {}", col.column, direction)
-                        })
-                        .collect();
-                    sql.push_str(&order_columns.join(", "));
-                }
-                
-                Ok(sql)
-            }
-            _ => Err(ConnectorError::UnsupportedOperation(
-                format!("Operation {:?} not supported by SQL Server connector", query.operation)
-            ).into()),
-        }
+        SqlDialect::SqlServer.select_sql(query)
+    }
+    
+    /// Build the SQL statement for a query, with predicate values as `@P1`, `@P2`, ... parameters
+    pub fn build_sql_statement(&self, query: &crate::utils::types::InternalQuery) -> NirvResult<SqlStatement> {
+        SqlDialect::SqlServer.statement(query)
     }
     
     /// Build SQL for a single predicate
     pub fn build_predicate_sql(&self, predicate: &crate::utils::types::Predicate) -> NirvResult<String> {
-        let operator_sql = match predicate.operator {
-            PredicateOperator::Equal => "=",
-            PredicateOperator::NotEqual => "!=",
-            PredicateOperator::GreaterThan => ">",
-            PredicateOperator::GreaterThanOrEqual => ">=",
-            PredicateOperator::LessThan => "<",
-            PredicateOperator::LessThanOrEqual => "<=",
-            PredicateOperator::Like => "LIKE",
-            PredicateOperator::IsNull => "IS NULL",
-            PredicateOperator::IsNotNull => "IS NOT NULL",
-            PredicateOperator::In => "IN",
-        };
-        
-        match predicate.operator {
-            PredicateOperator::IsNull | PredicateOperator::IsNotNull => {
-                Ok(format!("{} {}", predicate.column, operator_sql))
-            }
-            PredicateOperator::In => {
-                if let crate::utils::types::PredicateValue::List(values) = &predicate.value {
-                    let value_strings: Vec<String> = values.iter()
-                        .map(|v| self.format_predicate_value(v))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    Ok(format!("{} IN ({})", predicate.column, value_strings.join(", ")))
-                } else {
-                    Err(ConnectorError::QueryExecutionFailed(
-                        "IN operator requires a list of values".to_string()
-                    ).into())
-                }
-            }
-            _ => {
-                let value_str = self.format_predicate_value(&predicate.value)?;
-                Ok(format!("{} {} {}", predicate.column, operator_sql, value_str))
-            }
-        }
+        SqlDialect::SqlServer.predicate(predicate)
     }
     
     /// Format predicate value for SQL
     pub fn format_predicate_value(&self, value: &crate::utils::types::PredicateValue) -> NirvResult<String> {
-        match value {
-            crate::utils::types::PredicateValue::String(s) => {
-                // Escape single quotes by doubling them
-                Ok(format!("'{}'", s.replace('\'', "''")))
-            },
-            crate::utils::types::PredicateValue::Number(n) => Ok(n.to_string()),
-            crate::utils::types::PredicateValue::Integer(i) => Ok(i.to_string()),
-            crate::utils::types::PredicateValue::Boolean(b) => {
-                // SQL Server uses 1/0 for boolean values
-                Ok(if *b { "1".to_string() } else { "0".to_string() })
-            },
-            crate::utils::types::PredicateValue::Null => Ok("NULL".to_string()),
-            crate::utils::types::PredicateValue::List(_) => {
-                Err(ConnectorError::QueryExecutionFailed(
-                    "List values should be handled by IN operator".to_string()
-                ).into())
-            }
-        }
+        SqlDialect::SqlServer.predicate_value(value)
     }
     
     /// Convert SQL Server type to internal DataType
@@ -259,36 +143,246 @@ impl SqlServerConnector {
         }
     }
 
-    /// Convert tiberius row value to internal Value representation
-    fn convert_row_value(&self, row: &tiberius::Row, index: usize) -> NirvResult<Value> {
+    /// Run a statement on the given client and collect its results
+    async fn run_sql(&self, client: &mut SqlServerClient, statement: &SqlStatement) -> NirvResult<QueryResult> {
+        with_timeout(
+            self.read_timeout,
+            "SQL Server query",
+            self.stream_sql(client, &statement.sql(), statement.params(), None),
+        ).await
+    }
+
+    /// Run an INSERT, UPDATE or DELETE, bounded by the write timeout, counting the rows it changed
+    async fn run_write(&self, client: &mut SqlServerClient, statement: &SqlStatement) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+        let sql = tag_sql(&statement.sql());
+        connector_debug_log().request("sqlserver", &format!("{} {:?}", sql, statement.params()));
+        let executed = with_timeout(self.write_timeout, "SQL Server write", async {
+            Self::bind_params(&sql, statement.params()).execute(client).await
+                .map_err(|e| ConnectorError::QueryExecutionFailed(format!("SQL Server write failed: {}", e)).into())
+        }).await?;
+        Ok(QueryResult {
+            affected_rows: Some(executed.rows_affected().iter().sum()),
+            execution_time: start_time.elapsed(),
+            ..QueryResult::new()
+        })
+    }
+
+    /// Run a query, or a write with `run_write`
+    async fn run(&self, client: &mut SqlServerClient, query: &crate::utils::types::InternalQuery, statement: &SqlStatement) -> NirvResult<QueryResult> {
+        match query.operation {
+            QueryOperation::Select => self.run_sql(client, statement).await,
+            _ => self.run_write(client, statement).await,
+        }
+    }
+
+    /// Run a transaction control statement, bounded by the write timeout
+    async fn run_statement(&self, client: &mut SqlServerClient, statement: &str) -> NirvResult<()> {
+        with_timeout(self.write_timeout, statement, self.stream_sql(client, statement, &[], None)).await?;
+        Ok(())
+    }
+
+    /// Stream the results of a SQL batch into a QueryResult. Without parameters the batch
+    /// runs as is, so transaction control statements apply to the session. The batch names
+    /// the engine query it runs for, as seen in sys.dm_exec_requests and traces. Given a
+    /// fetch size and a sink, rows are handed over as each fetch size of them arrives
+    /// instead of being collected into the result.
+    async fn stream_sql(&self, client: &mut SqlServerClient, sql: &str, params: &[PredicateValue], mut batches: Option<(u32, &mut RowSink<'_>)>) -> NirvResult<QueryResult> {
+        let start_time = Instant::now();
+
+        let sql = &tag_sql(sql);
+        let debug_log = connector_debug_log();
+        debug_log.request("sqlserver", &format!("{} {:?}", sql, params));
+        let stream = if params.is_empty() {
+            client.simple_query(sql).await
+        } else {
+            Self::bind_params(sql, params).query(client).await
+        };
+        let mut stream = stream
+            .map_err(|e| ConnectorError::QueryExecutionFailed(
+                format!("SQL Server query execution failed: {}", e)
+            ))?;
+
+        let mut columns: Vec<ColumnMetadata> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+        let mut columns_built = false;
+        let mut coercions = Coercions::default();
+
+        while let Some(item) = stream.try_next().await
+            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Error reading query stream: {}", e)))?
+        {
+            match item {
+                tiberius::QueryItem::Metadata(meta) => {
+                    if !columns_built {
+                        for col in meta.columns() {
+                            columns.push(ColumnMetadata {
+                                name: col.name().to_string(),
+                                data_type: self.sqlserver_type_to_data_type(
+                                    &format!("{:?}", col.column_type())
+                                ),
+                                nullable: true,
+                            });
+                        }
+                        columns_built = true;
+                    }
+                }
+                tiberius::QueryItem::Row(row) => {
+                    let values: Vec<Value> = (0..row.len())
+                        .map(|i| {
+                            let (value, coercion) = self.convert_row_value(&row, i);
+                            if let Some(coercion) = coercion {
+                                coercions.record(i, coercion);
+                            }
+                            value
+                        })
+                        .collect();
+                    rows.push(Row::new(values));
+                    yield_after_rows(rows.len()).await;
+                    if let Some((fetch_size, on_batch)) = batches.as_mut() {
+                        if rows.len() >= *fetch_size as usize {
+                            on_batch(&columns, std::mem::take(&mut rows))?;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some((_, on_batch)) = batches {
+            on_batch(&columns, std::mem::take(&mut rows))?;
+        }
+
+        let warnings = coercions.into_warnings(&columns);
+        let result = QueryResult {
+            columns,
+            rows,
+            affected_rows: None,
+            execution_time: start_time.elapsed(),
+            stats: None,
+            warnings,
+        };
+        debug_log.result("sqlserver", &result);
+        Ok(result)
+    }
+
+    /// A parameterized query with values bound to `@P1`, `@P2`, ...
+    fn bind_params<'a>(sql: &'a str, params: &[PredicateValue]) -> tiberius::Query<'a> {
+        let mut query = tiberius::Query::new(sql);
+        for param in params {
+            match param {
+                PredicateValue::String(s) => query.bind(s.clone()),
+                PredicateValue::Number(n) => query.bind(*n),
+                PredicateValue::Integer(i) => query.bind(*i),
+                PredicateValue::Boolean(b) => query.bind(*b),
+                PredicateValue::Null | PredicateValue::List(_) => query.bind(Option::<i32>::None),
+            }
+        }
+        query
+    }
+
+    /// Finish the open transaction with COMMIT or ROLLBACK and release the client
+    async fn end_transaction(&self, transaction_id: u64, statement: &str) -> NirvResult<()> {
+        let mut transaction = self.transaction.lock().await;
+        match transaction.take() {
+            Some((id, mut client_guard)) if id == transaction_id => {
+                let client = client_guard.as_mut()
+                    .ok_or_else(|| ConnectorError::ConnectionFailed("No active SQL Server client".to_string()))?;
+                self.run_statement(client, statement).await?;
+                Ok(())
+            }
+            other => {
+                *transaction = other;
+                Err(ConnectorError::QueryExecutionFailed(
+                    format!("Transaction {} is not open", transaction_id)
+                ).into())
+            }
+        }
+    }
+
+    /// Convert tiberius row value to internal Value representation, with how it was
+    /// coerced when it could not be represented exactly
+    fn convert_row_value(&self, row: &tiberius::Row, index: usize) -> (Value, Option<Coercion>) {
         // Try different types in order of likelihood
         if let Ok(Some(val)) = row.try_get::<&str, usize>(index) {
-            return Ok(Value::Text(val.to_string()));
+            return (Value::Text(val.to_string()), None);
         }
         if let Ok(Some(val)) = row.try_get::<i32, usize>(index) {
-            return Ok(Value::Integer(val as i64));
+            return (Value::Integer(val as i64), None);
         }
         if let Ok(Some(val)) = row.try_get::<i64, usize>(index) {
-            return Ok(Value::Integer(val));
+            return (Value::Integer(val), None);
+        }
+        if let Ok(Some(val)) = row.try_get::<i16, usize>(index) {
+            return (Value::Integer(val as i64), None);
+        }
+        if let Ok(Some(val)) = row.try_get::<u8, usize>(index) {
+            return (Value::Integer(val as i64), None);
         }
         if let Ok(Some(val)) = row.try_get::<f64, usize>(index) {
-            return Ok(Value::Float(val));
+            return (Value::Float(val), None);
         }
         if let Ok(Some(val)) = row.try_get::<f32, usize>(index) {
-            return Ok(Value::Float(val as f64));
+            return (Value::Float(val as f64), None);
+        }
+        if let Ok(Some(val)) = row.try_get::<Numeric, usize>(index) {
+            // Doubles hold 15 significant digits exactly
+            let rounded = val.value().unsigned_abs() >= 10u128.pow(15);
+            return (Value::Float(f64::from(val)), rounded.then_some(Coercion::Rounded));
         }
         if let Ok(Some(val)) = row.try_get::<bool, usize>(index) {
-            return Ok(Value::Boolean(val));
+            return (Value::Boolean(val), None);
         }
         if let Ok(Some(val)) = row.try_get::<&[u8], usize>(index) {
-            return Ok(Value::Binary(val.to_vec()));
+            return (Value::Binary(val.to_vec()), None);
         }
         
-        // If all else fails, return null
-        Ok(Value::Null)
+        // If all else fails, return null, noting values that were not null to begin with
+        let unsupported = row.cells().nth(index).is_some_and(|(_, data)| !is_null(data));
+        (Value::Null, unsupported.then_some(Coercion::Unsupported))
     }
 }
 
+/// How a value was changed to fit the internal representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Coercion {
+    /// A decimal with more digits than a double holds, rounded
+    Rounded,
+    /// A value of a type without a conversion, read as NULL
+    Unsupported,
+}
+
+/// Coercions counted per column over a result, reported as one warning per column and kind
+#[derive(Debug, Default)]
+struct Coercions(Vec<(usize, Coercion, u64)>);
+
+impl Coercions {
+    fn record(&mut self, index: usize, coercion: Coercion) {
+        match self.0.iter_mut().find(|(i, kind, _)| *i == index && *kind == coercion) {
+            Some((_, _, count)) => *count += 1,
+            None => self.0.push((index, coercion, 1)),
+        }
+    }
+
+    fn into_warnings(self, columns: &[ColumnMetadata]) -> Vec<QueryWarning> {
+        self.0.into_iter().map(|(index, coercion, count)| {
+            let column = columns.get(index).map_or("?", |col| col.name.as_str());
+            let detail = match coercion {
+                Coercion::Rounded => format!("{} decimal values rounded to double precision", count),
+                Coercion::Unsupported => format!("{} values of an unsupported type read as NULL", count),
+            };
+            QueryWarning::coercion("", column, detail)
+        }).collect()
+    }
+}
+
+/// Whether a cell holds SQL NULL, whatever its type
+fn is_null(data: &ColumnData<'_>) -> bool {
+    matches!(data,
+        ColumnData::U8(None) | ColumnData::I16(None) | ColumnData::I32(None) | ColumnData::I64(None)
+        | ColumnData::F32(None) | ColumnData::F64(None) | ColumnData::Bit(None) | ColumnData::String(None)
+        | ColumnData::Guid(None) | ColumnData::Binary(None) | ColumnData::Numeric(None) | ColumnData::Xml(None)
+        | ColumnData::DateTime(None) | ColumnData::SmallDateTime(None) | ColumnData::Time(None)
+        | ColumnData::Date(None) | ColumnData::DateTime2(None) | ColumnData::DateTimeOffset(None))
+}
+
 impl Default for SqlServerConnector {
     fn default() -> Self {
         Self::new()
@@ -298,67 +392,46 @@ impl Default for SqlServerConnector {
 #[async_trait]
 impl Connector for SqlServerConnector {
     async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
-        let server = config.connection_params.get("server")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "server parameter is required".to_string()
-            ))?;
-        
-        let port = config.connection_params.get("port")
-            .unwrap_or(&"1433".to_string())
-            .parse::<u16>()
-            .map_err(|e| ConnectorError::ConnectionFailed(format!("Invalid port: {}", e)))?;
-        
-        let database = config.connection_params.get("database")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "database parameter is required".to_string()
-            ))?;
-        
-        let username = config.connection_params.get("username")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "username parameter is required".to_string()
-            ))?;
-        
-        let password = config.connection_params.get("password")
-            .ok_or_else(|| ConnectorError::ConnectionFailed(
-                "password parameter is required".to_string()
-            ))?;
-        
-        let trust_cert = config.connection_params.get("trust_cert")
-            .map(|s| s.parse::<bool>().unwrap_or(false))
-            .unwrap_or(false);
+        let SqlServerSettings { server, port, database, username, password, trust_cert } =
+            SqlServerSettings::from_params(&config.connection_params).map_err(issues_error)?;
         
         // Create tiberius configuration
         let mut tiberius_config = Config::new();
-        tiberius_config.host(server);
+        tiberius_config.host(&server);
         tiberius_config.port(port);
-        tiberius_config.database(database);
-        tiberius_config.authentication(AuthMethod::sql_server(username, password));
+        tiberius_config.database(&database);
+        tiberius_config.application_name(APPLICATION_NAME);
+        tiberius_config.authentication(AuthMethod::sql_server(&username, &password));
         
         if trust_cert {
             tiberius_config.encryption(EncryptionLevel::NotSupported);
         }
         
-        let timeout = Duration::from_secs(config.timeout_seconds.unwrap_or(30));
+        let connect_timeout = config.connect_timeout();
+        let addresses = resolve_host(&server, port, connect_timeout).await?;
         
         // Connect to SQL Server
-        let tcp = tokio::time::timeout(timeout, TcpStream::connect(tiberius_config.get_addr())).await
-            .map_err(|_| ConnectorError::Timeout(format!(
-                "Connection timeout after {}s when connecting to SQL Server at {}:{}",
-                timeout.as_secs(), server, port
-            )))?
-            .map_err(|e| ConnectorError::ConnectionFailed(format!(
+        let description = format!("Connecting to SQL Server at {}:{}", server, port);
+        let tcp = connect_with_retry(&config.retry_policy, connect_timeout, &description, || async {
+            TcpStream::connect(&addresses[..]).await.map_err(|e| ConnectorError::ConnectionFailed(format!(
                 "Failed to establish TCP connection to SQL Server at {}:{} - Error: {} (Check if server is running and port is accessible)",
                 server, port, e
-            )))?;
+            )).into())
+        }).await?;
         
-        let client = Client::connect(tiberius_config.clone(), tcp.compat_write()).await
-            .map_err(|e| ConnectorError::ConnectionFailed(format!(
-                "Failed to authenticate with SQL Server at {}:{} using username '{}' - Error: {} (Check credentials and server configuration)",
-                server, port, username, e
-            )))?;
+        let client = with_timeout(connect_timeout, &description, async {
+            Client::connect(tiberius_config.clone(), tcp.compat_write()).await
+                .map_err(|e| ConnectorError::ConnectionFailed(format!(
+                    "Failed to authenticate with SQL Server at {}:{} using username '{}' - Error: {} (Check credentials and server configuration)",
+                    server, port, username, e
+                )).into())
+        }).await?;
         
         *self.client.lock().await = Some(client);
         self.connection_config = Some(tiberius_config);
+        self.read_timeout = config.read_timeout();
+        self.write_timeout = config.write_timeout();
+        self.fetch_size = config.fetch_size;
         self.connected = true;
         
         Ok(())
@@ -369,55 +442,44 @@ impl Connector for SqlServerConnector {
             return Err(ConnectorError::ConnectionFailed("Not connected to SQL Server".to_string()).into());
         }
 
-        let start_time = Instant::now();
-        let sql = self.build_sql_query(&query.query)?;
+        let statement = self.build_sql_statement(&query.query)?;
 
         let mut client_guard = self.client.lock().await;
         let client = client_guard.as_mut()
             .ok_or_else(|| ConnectorError::ConnectionFailed("No active SQL Server client".to_string()))?;
 
-        let mut stream = client.simple_query(sql.as_str()).await
-            .map_err(|e| ConnectorError::QueryExecutionFailed(
-                format!("SQL Server query execution failed: {}", e)
-            ))?;
+        self.run(client, &query.query, &statement).await
+    }
+    
+    async fn execute_query_batched(&self, query: ConnectorQuery, on_batch: &mut RowSink<'_>) -> NirvResult<QueryResult> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed("Not connected to SQL Server".to_string()).into());
+        }
+        let (Some(fetch_size), QueryOperation::Select) = (self.fetch_size, &query.query.operation) else {
+            let mut result = self.execute_query(query).await?;
+            on_batch(&result.columns, std::mem::take(&mut result.rows))?;
+            return Ok(result);
+        };
 
-        let mut columns: Vec<ColumnMetadata> = Vec::new();
-        let mut rows: Vec<Row> = Vec::new();
-        let mut columns_built = false;
+        let statement = self.build_sql_statement(&query.query)?;
 
-        while let Some(item) = stream.try_next().await
-            .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Error reading query stream: {}", e)))?
-        {
-            match item {
-                tiberius::QueryItem::Metadata(meta) => {
-                    if !columns_built {
-                        for col in meta.columns() {
-                            columns.push(ColumnMetadata {
-                                name: col.name().to_string(),
-                                data_type: self.sqlserver_type_to_data_type(
-                                    &format!("{:?}", col.column_type())
-                                ),
-                                nullable: true,
-                            });
-                        }
-                        columns_built = true;
-                    }
-                }
-                tiberius::QueryItem::Row(row) => {
-                    let values: Vec<Value> = (0..row.len())
-                        .map(|i| self.convert_row_value(&row, i).unwrap_or(Value::Null))
-                        .collect();
-                    rows.push(Row::new(values));
-                }
-            }
-        }
+        let mut client_guard = self.client.lock().await;
+        let client = client_guard.as_mut()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No active SQL Server client".to_string()))?;
 
-        Ok(QueryResult {
-            columns,
-            rows,
-            affected_rows: None,
-            execution_time: start_time.elapsed(),
-        })
+        with_timeout(
+            self.read_timeout,
+            "SQL Server query",
+            self.stream_sql(client, &statement.sql(), statement.params(), Some((fetch_size.max(1), on_batch))),
+        ).await
+    }
+    
+    async fn count_rows(&self, mut query: ConnectorQuery) -> NirvResult<Option<u64>> {
+        // The database counts the rows, so only the count is sent back
+        query.query.projections = vec![Column { name: COUNT_ALL.to_string(), alias: None, source: None }];
+        query.query.ordering = None;
+        query.query.limit = None;
+        Ok(self.execute_query(query).await?.count_value())
     }
     
     async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
@@ -440,18 +502,16 @@ impl Connector for SqlServerConnector {
             .ok_or_else(|| ConnectorError::ConnectionFailed("No active SQL Server client".to_string()))?;
 
         // Query column information from INFORMATION_SCHEMA
-        let col_sql = format!(
-            "SELECT COLUMN_NAME, DATA_TYPE, IS_NULLABLE, ORDINAL_POSITION \
+        let col_sql = "SELECT COLUMN_NAME, DATA_TYPE, IS_NULLABLE, ORDINAL_POSITION \
              FROM INFORMATION_SCHEMA.COLUMNS \
-             WHERE TABLE_NAME = '{}' AND TABLE_SCHEMA LIKE '{}' \
-             ORDER BY ORDINAL_POSITION",
-            table_name, schema_name
-        );
+             WHERE TABLE_NAME = @P1 AND TABLE_SCHEMA LIKE @P2 \
+             ORDER BY ORDINAL_POSITION";
+        let col_params = [PredicateValue::String(table_name.clone()), PredicateValue::String(schema_name)];
 
         let mut columns: Vec<ColumnMetadata> = Vec::new();
 
         {
-            let mut col_stream = client.simple_query(col_sql.as_str()).await
+            let mut col_stream = Self::bind_params(col_sql, &col_params).query(client).await
                 .map_err(|e| ConnectorError::SchemaRetrievalFailed(
                     format!("Failed to query INFORMATION_SCHEMA.COLUMNS: {}", e)
                 ))?;
@@ -486,22 +546,20 @@ impl Connector for SqlServerConnector {
         }
 
         // Query primary key information
-        let pk_sql = format!(
-            "SELECT kcu.COLUMN_NAME \
+        let pk_sql = "SELECT kcu.COLUMN_NAME \
              FROM INFORMATION_SCHEMA.KEY_COLUMN_USAGE kcu \
              JOIN INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc \
                ON kcu.CONSTRAINT_NAME = tc.CONSTRAINT_NAME \
                AND kcu.TABLE_NAME = tc.TABLE_NAME \
-             WHERE kcu.TABLE_NAME = '{}' \
+             WHERE kcu.TABLE_NAME = @P1 \
                AND tc.CONSTRAINT_TYPE = 'PRIMARY KEY' \
-             ORDER BY kcu.ORDINAL_POSITION",
-            table_name
-        );
+             ORDER BY kcu.ORDINAL_POSITION";
+        let pk_params = [PredicateValue::String(table_name)];
 
         let mut pk_columns: Vec<String> = Vec::new();
 
         {
-            let mut pk_stream = client.simple_query(pk_sql.as_str()).await
+            let mut pk_stream = Self::bind_params(pk_sql, &pk_params).query(client).await
                 .map_err(|e| ConnectorError::SchemaRetrievalFailed(
                     format!("Failed to query primary keys: {}", e)
                 ))?;
@@ -524,10 +582,14 @@ impl Connector for SqlServerConnector {
             columns,
             primary_key,
             indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
         })
     }
     
     async fn disconnect(&mut self) -> NirvResult<()> {
+        // Release any transaction's hold on the client before closing it
+        *self.transaction.lock().await = None;
         *self.client.lock().await = None;
         self.connected = false;
         self.connection_config = None;
@@ -546,6 +608,11 @@ impl Connector for SqlServerConnector {
         self.connected
     }
     
+    /// JSON functions map to `JSON_VALUE`
+    fn supports_json_functions(&self) -> bool {
+        true
+    }
+    
     fn get_capabilities(&self) -> ConnectorCapabilities {
         ConnectorCapabilities {
             supports_joins: true,
@@ -556,4 +623,54 @@ impl Connector for SqlServerConnector {
             max_concurrent_queries: Some(20),
         }
     }
+
+    async fn begin_transaction(&self) -> NirvResult<u64> {
+        if !self.connected {
+            return Err(ConnectorError::ConnectionFailed("Not connected to SQL Server".to_string()).into());
+        }
+
+        // The single client stays locked until the transaction ends, so other
+        // queries on this connector wait rather than joining the transaction
+        let mut client_guard = self.client.clone().lock_owned().await;
+        let client = client_guard.as_mut()
+            .ok_or_else(|| ConnectorError::ConnectionFailed("No active SQL Server client".to_string()))?;
+        self.run_statement(client, "BEGIN TRANSACTION").await?;
+
+        let transaction_id = self.next_transaction_id.fetch_add(1, Ordering::Relaxed);
+        *self.transaction.lock().await = Some((transaction_id, client_guard));
+
+        Ok(transaction_id)
+    }
+
+    async fn execute_in_transaction(&self, transaction_id: u64, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let statement = self.build_sql_statement(&query.query)?;
+
+        let mut transaction = self.transaction.lock().await;
+        match transaction.as_mut() {
+            Some((id, client_guard)) if *id == transaction_id => {
+                let client = client_guard.as_mut()
+                    .ok_or_else(|| ConnectorError::ConnectionFailed("No active SQL Server client".to_string()))?;
+                self.run(client, &query.query, &statement).await
+            }
+            _ => Err(ConnectorError::QueryExecutionFailed(
+                format!("Transaction {} is not open", transaction_id)
+            ).into()),
+        }
+    }
+
+    async fn commit_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        self.end_transaction(transaction_id, "COMMIT TRANSACTION").await
+    }
+
+    async fn rollback_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        self.end_transaction(transaction_id, "ROLLBACK TRANSACTION").await
+    }
+
+    async fn prepare_transaction(&self, _transaction_id: u64, _global_id: &str) -> NirvResult<()> {
+        // Without MSDTC there is no prepare that survives a restart, and a transaction kept
+        // open in memory could be neither committed nor rolled back by recovery
+        Err(ConnectorError::UnsupportedOperation(
+            "SQL Server cannot prepare a transaction durably, so it cannot take part in two-phase commit".to_string()
+        ).into())
+    }
 }
\ No newline at end of file
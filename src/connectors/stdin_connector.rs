@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use tokio::io::AsyncReadExt;
+use tokio::sync::OnceCell;
+
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::csv_options::{parse_csv, split_source_options, CsvOptions};
+use crate::connectors::parse_pool::parse_pool;
+use crate::engine::filter_rows;
+use crate::utils::{
+    types::{ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, Row},
+    error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
+    collation::Collation,
+};
+
+/// Connector over data piped to the process, queried as `source('stdin.csv')`.
+/// Standard input is read once, when a query first needs it.
+pub struct StdinConnector {
+    content: OnceCell<Vec<u8>>,
+    connected: bool,
+}
+
+impl StdinConnector {
+    pub fn new() -> Self {
+        Self {
+            content: OnceCell::new(),
+            connected: false,
+        }
+    }
+
+    /// A connector over the given text instead of standard input
+    pub fn with_content(content: impl Into<String>) -> Self {
+        Self {
+            content: OnceCell::new_with(Some(content.into().into_bytes())),
+            connected: false,
+        }
+    }
+
+    async fn read_table(&self, identifier: &str) -> NirvResult<(Vec<ColumnMetadata>, Vec<Row>)> {
+        let (format, options) = match split_source_options(identifier) {
+            (format, Some(options)) => (format, CsvOptions::default().with_source_options(options)?),
+            (format, None) => (format, CsvOptions::default()),
+        };
+        if !format.eq_ignore_ascii_case("csv") {
+            return Err(ConnectorError::UnsupportedOperation(
+                format!("Unsupported stdin format '{}'; use stdin.csv", format)
+            ).into());
+        }
+
+        let content = self.content.get_or_try_init(|| async {
+            let mut content = Vec::new();
+            tokio::io::stdin().read_to_end(&mut content).await
+                .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to read stdin: {}", e)))?;
+            Ok::<_, ConnectorError>(content)
+        }).await?;
+        if content.iter().all(u8::is_ascii_whitespace) {
+            return Err(ConnectorError::QueryExecutionFailed("No data was piped to stdin".to_string()).into());
+        }
+        let content = content.clone();
+        parse_pool().run(move || parse_csv(content, &options)).await
+    }
+}
+
+impl Default for StdinConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Connector for StdinConnector {
+    async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let source = query.query.sources.first().ok_or_else(|| ConnectorError::QueryExecutionFailed(
+            "No data source specified in query".to_string()
+        ))?;
+        let (columns, rows) = self.read_table(&source.identifier).await?;
+
+        let result = QueryResult { columns, rows, ..Default::default() };
+        let mut result = filter_rows(result, &query.query.predicates, IdentifierCase::default(), Collation::default())?;
+        if let Some(limit) = query.query.limit {
+            result.rows.truncate(limit as usize);
+        }
+        Ok(result)
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        let (columns, _) = self.read_table(object_name).await?;
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns,
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Custom("stdin".to_string())
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{DataSource, InternalQuery, Predicate, PredicateOperator, PredicateValue, QueryOperation, Value};
+
+    #[tokio::test]
+    async fn test_stdin_csv_query() {
+        let connector = StdinConnector::with_content("id,name\n1,Ann\n2,Bo\n3,Cy\n");
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "stdin".to_string(), identifier: "csv".to_string(), alias: Some("s".to_string()) });
+        query.predicates.push(Predicate {
+            column: "id".to_string(),
+            operator: PredicateOperator::GreaterThan,
+            value: PredicateValue::Integer(1),
+        });
+        query.limit = Some(1);
+
+        let result = connector.execute_query(ConnectorQuery {
+            connector_type: connector.get_connector_type(),
+            query,
+            connection_params: Default::default(),
+        }).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values, vec![Value::Integer(2), Value::Text("Bo".to_string())]);
+
+        assert_eq!(connector.get_schema("csv").await.unwrap().columns.len(), 2);
+        assert!(connector.get_schema("parquet").await.is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities};
+use crate::connectors::debug_log::sanitize;
+use crate::engine::filter_rows;
+use crate::utils::{
+    types::{ConnectorType, ConnectorQuery, QueryResult, Schema, ColumnMetadata, DataType, Row, Value},
+    error::{ConnectorError, NirvResult},
+    identifier::IdentifierCase,
+    collation::Collation,
+    time_zone::format_utc,
+};
+
+/// Object type of the runtime information source, as in `source('sys.env')`
+pub const SYS_OBJECT_TYPE: &str = "sys";
+
+/// Tables of the runtime information source
+const SYS_TABLES: [&str; 3] = ["env", "process", "host"];
+
+/// Parts of environment variable names whose values are never shown
+const SECRET_NAME_PARTS: [&str; 7] = ["PASSWORD", "PASSWD", "SECRET", "TOKEN", "KEY", "CREDENTIAL", "AUTH"];
+
+/// When the engine's process started serving, taken the first time the source is created
+static STARTED_AT: OnceLock<DateTime<Utc>> = OnceLock::new();
+
+/// Connector exposing the engine's runtime context: `sys.env` lists environment
+/// variables, `sys.process` the engine's process and `sys.host` the machine it runs on.
+/// Variables whose names look secret are redacted, and credentials in other values are masked.
+pub struct SysConnector {
+    connected: bool,
+}
+
+impl SysConnector {
+    pub fn new() -> Self {
+        STARTED_AT.get_or_init(Utc::now);
+        Self { connected: false }
+    }
+
+    fn table(name: &str) -> NirvResult<QueryResult> {
+        let column = |name: &str, data_type: DataType| ColumnMetadata { name: name.to_string(), data_type, nullable: true };
+        let (columns, rows) = match name.to_lowercase().as_str() {
+            "env" => (
+                vec![column("name", DataType::Text), column("value", DataType::Text)],
+                Self::env_rows(std::env::vars_os().map(|(name, value)| {
+                    (name.to_string_lossy().into_owned(), value.to_string_lossy().into_owned())
+                })),
+            ),
+            "process" => (
+                vec![
+                    column("pid", DataType::Integer),
+                    column("executable", DataType::Text),
+                    column("cwd", DataType::Text),
+                    column("arguments", DataType::Json),
+                    column("started_at", DataType::DateTime),
+                    column("uptime_seconds", DataType::Integer),
+                    column("threads", DataType::Integer),
+                    column("memory_bytes", DataType::Integer),
+                    column("version", DataType::Text),
+                ],
+                vec![Self::process_row()],
+            ),
+            "host" => (
+                vec![
+                    column("hostname", DataType::Text),
+                    column("os", DataType::Text),
+                    column("family", DataType::Text),
+                    column("arch", DataType::Text),
+                    column("cpu_count", DataType::Integer),
+                    column("kernel_version", DataType::Text),
+                    column("now", DataType::DateTime),
+                ],
+                vec![Self::host_row()],
+            ),
+            _ => return Err(ConnectorError::QueryExecutionFailed(format!(
+                "Unknown sys table '{}', expected one of: {}", name, SYS_TABLES.join(", ")
+            )).into()),
+        };
+        Ok(QueryResult { columns, rows, ..Default::default() })
+    }
+
+    /// Environment variables sorted by name, with secrets redacted
+    fn env_rows(vars: impl Iterator<Item = (String, String)>) -> Vec<Row> {
+        let mut vars: Vec<(String, String)> = vars.collect();
+        vars.sort();
+        vars.into_iter()
+            .map(|(name, value)| {
+                let upper = name.to_uppercase();
+                let value = if SECRET_NAME_PARTS.iter().any(|part| upper.contains(part)) {
+                    "[REDACTED]".to_string()
+                } else {
+                    sanitize(&value)
+                };
+                Row::new(vec![Value::Text(name), Value::Text(value)])
+            })
+            .collect()
+    }
+
+    fn process_row() -> Row {
+        let started_at = *STARTED_AT.get_or_init(Utc::now);
+        let text = |value: Option<String>| value.map(Value::Text).unwrap_or(Value::Null);
+        let arguments: Vec<String> = std::env::args_os().skip(1).map(|arg| sanitize(&arg.to_string_lossy())).collect();
+        let (threads, memory_bytes) = process_status();
+        Row::new(vec![
+            Value::Integer(std::process::id() as i64),
+            text(std::env::current_exe().ok().map(|path| path.display().to_string())),
+            text(std::env::current_dir().ok().map(|path| path.display().to_string())),
+            Value::Json(serde_json::to_string(&arguments).unwrap_or_default()),
+            Value::DateTime(format_utc(started_at.naive_utc())),
+            Value::Integer((Utc::now() - started_at).num_seconds()),
+            threads.map(Value::Integer).unwrap_or(Value::Null),
+            memory_bytes.map(Value::Integer).unwrap_or(Value::Null),
+            Value::Text(env!("CARGO_PKG_VERSION").to_string()),
+        ])
+    }
+
+    fn host_row() -> Row {
+        let text = |value: Option<String>| value.map(Value::Text).unwrap_or(Value::Null);
+        Row::new(vec![
+            text(hostname()),
+            Value::Text(std::env::consts::OS.to_string()),
+            Value::Text(std::env::consts::FAMILY.to_string()),
+            Value::Text(std::env::consts::ARCH.to_string()),
+            std::thread::available_parallelism().map(|count| Value::Integer(count.get() as i64)).unwrap_or(Value::Null),
+            text(read_trimmed("/proc/sys/kernel/osrelease")),
+            Value::DateTime(format_utc(Utc::now().naive_utc())),
+        ])
+    }
+}
+
+impl Default for SysConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// The machine's name, from the kernel or the environment
+fn hostname() -> Option<String> {
+    read_trimmed("/proc/sys/kernel/hostname")
+        .or_else(|| read_trimmed("/etc/hostname"))
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+}
+
+/// Thread count and resident memory of the process, where `/proc` reports them
+fn process_status() -> (Option<i64>, Option<i64>) {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let field = |name: &str| status.lines()
+        .find_map(|line| line.strip_prefix(name))
+        .and_then(|rest| rest.trim_start_matches(':').split_whitespace().next()?.parse::<i64>().ok());
+    (field("Threads"), field("VmRSS").map(|kilobytes| kilobytes * 1024))
+}
+
+#[async_trait]
+impl Connector for SysConnector {
+    async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let source = query.query.sources.first().ok_or_else(|| ConnectorError::QueryExecutionFailed(
+            "No sys table in query".to_string()
+        ))?;
+        let mut result = filter_rows(Self::table(&source.identifier)?, &query.query.predicates, IdentifierCase::default(), Collation::default())?;
+        if let Some(limit) = query.query.limit {
+            result.rows.truncate(limit as usize);
+        }
+        Ok(result)
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns: Self::table(object_name)?.columns,
+            primary_key: (object_name.eq_ignore_ascii_case("env")).then(|| vec!["name".to_string()]),
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Custom(SYS_OBJECT_TYPE.to_string())
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities::default()
+    }
+
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        Ok(SYS_TABLES.iter().map(|table| table.to_string()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_rows_are_sorted_and_redacted() {
+        let rows = SysConnector::env_rows(vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("DATABASE_URL".to_string(), "postgres://app:hunter2@db:5432/app".to_string()),
+            ("GITHUB_TOKEN".to_string(), "ghp_abc".to_string()),
+            ("Api_Key".to_string(), "k-123".to_string()),
+        ].into_iter());
+        let values: Vec<(Value, Value)> = rows.into_iter().map(|row| (row.values[0].clone(), row.values[1].clone())).collect();
+        assert_eq!(values, vec![
+            (Value::Text("Api_Key".to_string()), Value::Text("[REDACTED]".to_string())),
+            (Value::Text("DATABASE_URL".to_string()), Value::Text("postgres://app:[REDACTED]@db:5432/app".to_string())),
+            (Value::Text("GITHUB_TOKEN".to_string()), Value::Text("[REDACTED]".to_string())),
+            (Value::Text("PATH".to_string()), Value::Text("/usr/bin".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn test_process_and_host_tables() {
+        let process = SysConnector::table("process").unwrap();
+        assert_eq!(process.rows.len(), 1);
+        assert_eq!(process.rows[0].values[0], Value::Integer(std::process::id() as i64));
+        assert_eq!(process.rows[0].values[8], Value::Text(env!("CARGO_PKG_VERSION").to_string()));
+
+        let host = SysConnector::table("HOST").unwrap();
+        assert_eq!(host.columns.len(), host.rows[0].values.len());
+        assert_eq!(host.rows[0].values[1], Value::Text(std::env::consts::OS.to_string()));
+        assert!(SysConnector::table("users").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use base64::prelude::*;
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha256;
+use openssl::sign::Signer;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value as JsonValue};
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::connectors::debug_log::{connector_debug_log, current_query_tag, REQUEST_ID_HEADER};
+use crate::utils::{
+    error::{ConnectorError, NirvResult},
+    time_zone::format_utc,
+};
+
+/// Tokens are renewed this long before they expire, so none runs out mid-request
+const TOKEN_RENEWAL_MARGIN: Duration = Duration::from_secs(60);
+
+/// An RSA private key that signs the JWTs cloud warehouses authenticate with: Google
+/// service account assertions and Snowflake key-pair tokens
+#[derive(Clone)]
+pub struct RsaSigningKey {
+    key: PKey<Private>,
+}
+
+impl RsaSigningKey {
+    /// Read a PEM private key, PKCS#8 or PKCS#1, encrypted when a passphrase is given
+    pub fn from_pem(pem: &str, passphrase: Option<&str>) -> NirvResult<Self> {
+        let key = match passphrase {
+            Some(passphrase) => PKey::private_key_from_pem_passphrase(pem.as_bytes(), passphrase.as_bytes()),
+            None => PKey::private_key_from_pem(pem.as_bytes()),
+        }.map_err(|e| ConnectorError::ConnectionFailed(format!("Invalid private key: {}", e)))?;
+        if key.rsa().is_err() {
+            return Err(ConnectorError::ConnectionFailed("Invalid private key: not an RSA key".to_string()).into());
+        }
+        Ok(Self { key })
+    }
+
+    /// Sign claims as a JWT with RS256
+    pub fn sign_jwt(&self, claims: &JsonValue) -> NirvResult<String> {
+        let signing_failed = |e: openssl::error::ErrorStack| ConnectorError::ConnectionFailed(format!("Failed to sign token: {}", e));
+        let header = BASE64_URL_SAFE_NO_PAD.encode(json!({ "alg": "RS256", "typ": "JWT" }).to_string());
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        let message = format!("{}.{}", header, payload);
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key).map_err(signing_failed)?;
+        signer.update(message.as_bytes()).map_err(signing_failed)?;
+        let signature = signer.sign_to_vec().map_err(signing_failed)?;
+        Ok(format!("{}.{}", message, BASE64_URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// `SHA256:` and the base64 digest of the DER public key, as Snowflake names keys
+    pub fn public_key_fingerprint(&self) -> NirvResult<String> {
+        let der = self.key.public_key_to_der()
+            .map_err(|e| ConnectorError::ConnectionFailed(format!("Invalid private key: {}", e)))?;
+        Ok(format!("SHA256:{}", BASE64_STANDARD.encode(sha256(&der))))
+    }
+}
+
+impl fmt::Debug for RsaSigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RsaSigningKey(..)")
+    }
+}
+
+/// A bearer token kept until shortly before it expires
+#[derive(Debug, Default)]
+pub(crate) struct TokenCache {
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl TokenCache {
+    /// The cached token, or a new one from `issue`, which gives a token and how long it lasts
+    pub(crate) async fn get<F, Fut>(&self, issue: F) -> NirvResult<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = NirvResult<(String, Duration)>>,
+    {
+        let mut cached = self.token.lock().await;
+        if let Some((token, renew_at)) = cached.as_ref() {
+            if Instant::now() < *renew_at {
+                return Ok(token.clone());
+            }
+        }
+        let (token, lifetime) = issue().await?;
+        *cached = Some((token.clone(), Instant::now() + lifetime.saturating_sub(TOKEN_RENEWAL_MARGIN)));
+        Ok(token)
+    }
+
+    pub(crate) async fn clear(&self) {
+        *self.token.lock().await = None;
+    }
+}
+
+/// Send a warehouse request naming the engine query it is made for, returning the status
+/// and JSON body of a successful response. Credentials are sent in headers, which are
+/// never logged.
+pub(crate) async fn send_json(connector: &str, description: &str, mut request: RequestBuilder) -> NirvResult<(StatusCode, JsonValue)> {
+    if let Some(tag) = current_query_tag() {
+        request = request.header(REQUEST_ID_HEADER, tag);
+    }
+    let debug_log = connector_debug_log();
+    debug_log.request(connector, description);
+
+    let response = request.send().await.map_err(|e| {
+        let message = format!("{} request failed: {}", connector, e);
+        if e.is_connect() {
+            ConnectorError::ConnectionFailed(message)
+        } else if e.is_timeout() {
+            ConnectorError::Timeout(message)
+        } else {
+            ConnectorError::QueryExecutionFailed(message)
+        }
+    })?;
+    let status = response.status();
+    debug_log.response(connector, &status.to_string());
+    let body = read_body(response).await?;
+    if !status.is_success() {
+        let message = error_message(&body).unwrap_or_else(|| format!("status {}", status));
+        let error = format!("{} {} failed: {}", connector, description, message);
+        return Err(match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ConnectorError::ConnectionFailed(error),
+            _ => ConnectorError::QueryExecutionFailed(error),
+        }.into());
+    }
+    Ok((status, body))
+}
+
+async fn read_body(response: Response) -> NirvResult<JsonValue> {
+    let text = response.text().await
+        .map_err(|e| ConnectorError::QueryExecutionFailed(format!("Failed to read response: {}", e)))?;
+    if text.trim().is_empty() {
+        return Ok(JsonValue::Null);
+    }
+    Ok(serde_json::from_str(&text).unwrap_or(JsonValue::String(text)))
+}
+
+/// The message of an error response: Google APIs nest it under `error`, OAuth gives an
+/// `error_description`, and Snowflake a top-level `message`
+fn error_message(body: &JsonValue) -> Option<String> {
+    body.pointer("/error/message")
+        .or_else(|| body.get("error_description"))
+        .or_else(|| body.get("message"))
+        .and_then(JsonValue::as_str)
+        .or_else(|| body.as_str().filter(|text| !text.is_empty()))
+        .map(str::to_string)
+}
+
+/// Whole seconds and nanoseconds of a `seconds.fraction` count, the fraction counting
+/// away from zero as written, so `-1.5` is two seconds back and half a second forward
+fn split_seconds(text: &str) -> Option<(i64, u32)> {
+    let (seconds, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let seconds: i64 = seconds.parse().ok()?;
+    let nanos: u32 = format!("{:0<9}", fraction).get(..9)?.parse().ok()?;
+    Some(if text.starts_with('-') && nanos > 0 { (seconds - 1, 1_000_000_000 - nanos) } else { (seconds, nanos) })
+}
+
+/// A `seconds.fraction` count since the Unix epoch as the engine's UTC date-time text
+pub(crate) fn epoch_seconds_to_datetime(text: &str) -> Option<String> {
+    let (seconds, nanos) = split_seconds(text)?;
+    DateTime::from_timestamp(seconds, nanos).map(|time| format_utc(time.naive_utc()))
+}
+
+/// A count of microseconds since the Unix epoch as the engine's UTC date-time text
+pub(crate) fn epoch_micros_to_datetime(text: &str) -> Option<String> {
+    DateTime::from_timestamp_micros(text.parse().ok()?).map(|time| format_utc(time.naive_utc()))
+}
+
+/// A count of days since the Unix epoch as an ISO date
+pub(crate) fn epoch_days_to_date(text: &str) -> Option<String> {
+    let days: i64 = text.parse().ok()?;
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    epoch.checked_add_signed(chrono::Duration::days(days)).map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// A `seconds.fraction` count since midnight as a time of day
+pub(crate) fn seconds_to_time(text: &str) -> Option<String> {
+    let (seconds, nanos) = split_seconds(text)?;
+    NaiveTime::from_num_seconds_from_midnight_opt(u32::try_from(seconds).ok()?, nanos)
+        .map(|time| time.format("%H:%M:%S%.f").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Verifier;
+
+    #[test]
+    fn test_rs256_jwt_verifies_with_public_key() {
+        let pem = String::from_utf8(Rsa::generate(2048).unwrap().private_key_to_pem().unwrap()).unwrap();
+        let key = RsaSigningKey::from_pem(&pem, None).unwrap();
+        let token = key.sign_jwt(&json!({ "iss": "loader", "exp": 1700000000 })).unwrap();
+
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        let header: JsonValue = serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(parts[0]).unwrap()).unwrap();
+        assert_eq!(header["alg"], "RS256");
+        let claims: JsonValue = serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+        assert_eq!(claims["iss"], "loader");
+
+        let public_key = PKey::public_key_from_der(&key.key.public_key_to_der().unwrap()).unwrap();
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key).unwrap();
+        verifier.update(format!("{}.{}", parts[0], parts[1]).as_bytes()).unwrap();
+        assert!(verifier.verify(&BASE64_URL_SAFE_NO_PAD.decode(parts[2]).unwrap()).unwrap());
+
+        assert!(key.public_key_fingerprint().unwrap().starts_with("SHA256:"));
+        assert!(RsaSigningKey::from_pem("not a key", None).is_err());
+    }
+
+    #[test]
+    fn test_epoch_conversions() {
+        assert_eq!(epoch_seconds_to_datetime("1714557600.250000000").as_deref(), Some("2024-05-01 10:00:00.250"));
+        assert_eq!(epoch_seconds_to_datetime("1714557600").as_deref(), Some("2024-05-01 10:00:00"));
+        assert_eq!(epoch_seconds_to_datetime("-0.5").as_deref(), Some("1969-12-31 23:59:59.500"));
+        assert_eq!(epoch_micros_to_datetime("1714557600000001").as_deref(), Some("2024-05-01 10:00:00.000001"));
+        assert_eq!(epoch_days_to_date("19844").as_deref(), Some("2024-05-01"));
+        assert_eq!(seconds_to_time("3661.500000000").as_deref(), Some("01:01:01.500"));
+        assert_eq!(epoch_seconds_to_datetime("soon"), None);
+    }
+}
@@ -0,0 +1,459 @@
+use std::fmt;
+
+use crate::connectors::csv_options::CsvOptions;
+use crate::utils::{
+    error::{ConnectorError, NirvResult},
+    row_buffer::RowBuffer,
+    types::{ColumnMetadata, DataType, Value},
+};
+
+/// One step of a record path: an element name, or any element for `*`
+#[derive(Debug, Clone, PartialEq)]
+struct PathStep {
+    /// Whether the step follows `//`, so any number of elements may come before it
+    descendant: bool,
+    name: Option<String>,
+}
+
+impl PathStep {
+    /// Names match as written or without their namespace prefix
+    fn accepts(&self, element: &str) -> bool {
+        match &self.name {
+            None => true,
+            Some(name) => name == element || element.rsplit_once(':').is_some_and(|(_, local)| local == name),
+        }
+    }
+}
+
+/// The elements of an XML file that are its rows, written as an XPath location path of
+/// element names, `*` and `//`, such as `/orders/order` or `//order`. Each record's
+/// attributes and child elements are its columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlRecordPath {
+    path: String,
+    steps: Vec<PathStep>,
+}
+
+impl XmlRecordPath {
+    pub fn parse(path: &str) -> NirvResult<Self> {
+        let invalid = |reason: &str| ConnectorError::QueryExecutionFailed(format!("Invalid record path '{}': {}", path, reason));
+        let Some(mut rest) = path.trim().strip_prefix('/') else {
+            return Err(invalid("it must start with /").into());
+        };
+
+        let mut steps = Vec::new();
+        loop {
+            let descendant = match rest.strip_prefix('/') {
+                Some(after) => {
+                    rest = after;
+                    true
+                }
+                None => false,
+            };
+            let end = rest.find('/').unwrap_or(rest.len());
+            let name = &rest[..end];
+            if name.is_empty() {
+                return Err(invalid("it has an empty step").into());
+            }
+            if name != "*" && !name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')) {
+                return Err(invalid("only element names, * and // are supported").into());
+            }
+            steps.push(PathStep { descendant, name: (name != "*").then(|| name.to_string()) });
+            if end == rest.len() {
+                break;
+            }
+            rest = &rest[end + 1..];
+        }
+        Ok(Self { path: path.trim().to_string(), steps })
+    }
+
+    /// Whether the element at the end of a path of element names from the root is a record
+    pub fn matches(&self, elements: &[&str]) -> bool {
+        fn matches_from(steps: &[PathStep], elements: &[&str]) -> bool {
+            match steps.split_first() {
+                None => elements.is_empty(),
+                Some((step, rest)) if step.descendant => (0..elements.len())
+                    .any(|skip| step.accepts(elements[skip]) && matches_from(rest, &elements[skip + 1..])),
+                Some((step, rest)) => elements.first()
+                    .is_some_and(|element| step.accepts(element) && matches_from(rest, &elements[1..])),
+            }
+        }
+        matches_from(&self.steps, elements)
+    }
+}
+
+impl Default for XmlRecordPath {
+    /// Every child of the root element
+    fn default() -> Self {
+        Self::parse("/*/*").expect("valid record path")
+    }
+}
+
+impl fmt::Display for XmlRecordPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)
+    }
+}
+
+/// A record's fields in order of first appearance; child elements that repeat have
+/// several values
+#[derive(Default)]
+struct XmlRecord {
+    fields: Vec<(String, Vec<String>)>,
+}
+
+impl XmlRecord {
+    fn push(&mut self, name: &str, value: String) {
+        match self.fields.iter_mut().find(|(field, _)| field == name) {
+            Some((_, values)) => values.push(value),
+            None => self.fields.push((name.to_string(), vec![value])),
+        }
+    }
+}
+
+/// Parse XML bytes into one row per record, with a column for each attribute and child
+/// element any record has. Fields are typed from their text as CSV fields are, a child
+/// element repeated in a record is a Json array of its texts, and missing fields are NULL.
+pub(crate) fn parse_xml_rows(content: Vec<u8>, record_path: &XmlRecordPath, options: &CsvOptions) -> NirvResult<(Vec<ColumnMetadata>, RowBuffer)> {
+    let content = options.encoding.decode(content)?;
+    let records = XmlReader::new(&content).read_records(record_path)?;
+
+    let mut columns: Vec<ColumnMetadata> = Vec::new();
+    for (name, _) in records.iter().flat_map(|record| &record.fields) {
+        if !columns.iter().any(|column| column.name == *name) {
+            columns.push(ColumnMetadata { name: name.clone(), data_type: DataType::Text, nullable: true });
+        }
+    }
+
+    let mut rows = RowBuffer::with_capacity(columns.len(), records.len());
+    for record in &records {
+        rows.push_row(columns.iter().map(|column| {
+            match record.fields.iter().find(|(name, _)| *name == column.name).map(|(_, values)| values.as_slice()) {
+                None => Value::Null,
+                Some([value]) => options.field_value(value.trim()),
+                Some(values) => Value::Json(serde_json::Value::from(values.iter().map(|value| value.trim()).collect::<Vec<_>>()).to_string()),
+            }
+        }));
+    }
+    Ok((columns, rows))
+}
+
+/// Markup read from an XML document
+enum XmlEvent {
+    Start { name: String, attributes: Vec<(String, String)>, empty: bool },
+    End(String),
+    Text(String),
+}
+
+/// A reader of the XML most data feeds use: elements, attributes, text, CDATA and the
+/// predefined and numeric entities. Comments, processing instructions and the DOCTYPE
+/// are skipped, and entities it declares are not expanded.
+struct XmlReader<'a> {
+    content: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn new(content: &'a str) -> Self {
+        Self { content, position: 0 }
+    }
+
+    fn error(&self, reason: &str) -> ConnectorError {
+        let line = self.content[..self.position.min(self.content.len())].matches('\n').count() + 1;
+        ConnectorError::QueryExecutionFailed(format!("Failed to parse XML at line {}: {}", line, reason))
+    }
+
+    fn read_records(&mut self, record_path: &XmlRecordPath) -> NirvResult<Vec<XmlRecord>> {
+        let mut records = Vec::new();
+        let mut open: Vec<String> = Vec::new();
+        // Depth of the record being read, and of the child element whose text is collected
+        let mut record: Option<(usize, XmlRecord)> = None;
+        let mut field: Option<(usize, String, String)> = None;
+        let mut saw_root = false;
+
+        while let Some(event) = self.next_event()? {
+            match event {
+                XmlEvent::Start { name, attributes, empty } => {
+                    if open.is_empty() && saw_root {
+                        return Err(self.error("more than one root element").into());
+                    }
+                    saw_root = true;
+                    open.push(name);
+                    let depth = open.len();
+                    match &mut record {
+                        None => {
+                            let elements: Vec<&str> = open.iter().map(String::as_str).collect();
+                            if record_path.matches(&elements) {
+                                let mut fields = XmlRecord::default();
+                                for (attribute, value) in attributes {
+                                    fields.push(&attribute, value);
+                                }
+                                record = Some((depth, fields));
+                            }
+                        }
+                        Some((record_depth, _)) if depth == *record_depth + 1 => {
+                            field = Some((depth, open[depth - 1].clone(), String::new()));
+                        }
+                        Some(_) => {}
+                    }
+                    if empty {
+                        self.close(&mut open, &mut record, &mut field, &mut records);
+                    }
+                }
+                XmlEvent::End(name) => {
+                    if open.last() != Some(&name) {
+                        let expected = open.last().map_or("no open element".to_string(), |open| format!("</{}>", open));
+                        return Err(self.error(&format!("</{}> does not match {}", name, expected)).into());
+                    }
+                    self.close(&mut open, &mut record, &mut field, &mut records);
+                }
+                XmlEvent::Text(text) => {
+                    if let Some((_, _, value)) = &mut field {
+                        value.push_str(&text);
+                    } else if open.is_empty() && !text.trim().is_empty() {
+                        return Err(self.error("text outside the root element").into());
+                    }
+                }
+            }
+        }
+        if let Some(name) = open.last() {
+            return Err(self.error(&format!("<{}> is never closed", name)).into());
+        }
+        if !saw_root {
+            return Err(self.error("the document has no root element").into());
+        }
+        Ok(records)
+    }
+
+    /// Close the innermost open element, finishing the field or record it is
+    fn close(&self, open: &mut Vec<String>, record: &mut Option<(usize, XmlRecord)>, field: &mut Option<(usize, String, String)>, records: &mut Vec<XmlRecord>) {
+        let depth = open.len();
+        open.pop();
+        if field.as_ref().is_some_and(|(field_depth, _, _)| *field_depth == depth) {
+            if let (Some((_, name, value)), Some((_, fields))) = (field.take(), record.as_mut()) {
+                fields.push(&name, value);
+            }
+        } else if record.as_ref().is_some_and(|(record_depth, _)| *record_depth == depth) {
+            if let Some((_, fields)) = record.take() {
+                records.push(fields);
+            }
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.content[self.position..]
+    }
+
+    /// Skip past the end of a construct, failing if it never ends
+    fn skip_past(&mut self, end: &str, construct: &str) -> NirvResult<&'a str> {
+        let rest = self.rest();
+        let Some(index) = rest.find(end) else {
+            return Err(self.error(&format!("{} is never closed", construct)).into());
+        };
+        self.position += index + end.len();
+        Ok(&rest[..index])
+    }
+
+    fn next_event(&mut self) -> NirvResult<Option<XmlEvent>> {
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let text = self.decode(&rest[..end])?;
+                self.position += end;
+                return Ok(Some(XmlEvent::Text(text)));
+            }
+            if rest.starts_with("<!--") {
+                self.skip_past("-->", "a comment")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>", "a processing instruction")?;
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                self.skip_past("]]>", "a CDATA section")?;
+                let end = cdata.find("]]>").expect("CDATA end found");
+                return Ok(Some(XmlEvent::Text(cdata[..end].to_string())));
+            } else if rest.starts_with("<!") {
+                // A DOCTYPE, whose internal subset may hold `>` inside its brackets
+                let subset_end = match (rest.find('['), rest.find('>')) {
+                    (Some(open), Some(close)) if open < close => rest.find("]").map(|index| index + 1),
+                    _ => Some(0),
+                };
+                let Some(close) = subset_end.and_then(|start| rest[start..].find('>').map(|index| start + index)) else {
+                    return Err(self.error("the DOCTYPE is never closed").into());
+                };
+                self.position += close + 1;
+            } else if let Some(end_tag) = rest.strip_prefix("</") {
+                let Some(close) = end_tag.find('>') else {
+                    return Err(self.error("an end tag is never closed").into());
+                };
+                self.position += close + 3;
+                return Ok(Some(XmlEvent::End(end_tag[..close].trim().to_string())));
+            } else {
+                return self.start_tag().map(Some);
+            }
+        }
+    }
+
+    fn start_tag(&mut self) -> NirvResult<XmlEvent> {
+        self.position += 1;
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+        loop {
+            self.position += self.rest().len() - self.rest().trim_start().len();
+            let rest = self.rest();
+            if let Some(after) = rest.strip_prefix("/>") {
+                self.position = self.content.len() - after.len();
+                return Ok(XmlEvent::Start { name, attributes, empty: true });
+            }
+            if let Some(after) = rest.strip_prefix('>') {
+                self.position = self.content.len() - after.len();
+                return Ok(XmlEvent::Start { name, attributes, empty: false });
+            }
+            if rest.is_empty() {
+                return Err(self.error(&format!("<{}> is never closed", name)).into());
+            }
+
+            let attribute = self.name()?;
+            self.position += self.rest().len() - self.rest().trim_start().len();
+            let Some(after) = self.rest().strip_prefix('=') else {
+                return Err(self.error(&format!("attribute {} of <{}> has no value", attribute, name)).into());
+            };
+            let after = after.trim_start();
+            self.position = self.content.len() - after.len();
+            let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                return Err(self.error(&format!("the value of attribute {} is not quoted", attribute)).into());
+            };
+            self.position += 1;
+            let raw = self.skip_past(&quote.to_string(), "an attribute value")?;
+            attributes.push((attribute, self.decode(raw)?));
+        }
+    }
+
+    fn name(&mut self) -> NirvResult<String> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(self.error("expected a name").into());
+        }
+        self.position += end;
+        Ok(rest[..end].to_string())
+    }
+
+    /// Text with its entity references replaced
+    fn decode(&self, text: &str) -> NirvResult<String> {
+        if !text.contains('&') {
+            return Ok(text.to_string());
+        }
+        let mut decoded = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('&') {
+            decoded.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find(';') else {
+                return Err(self.error("an entity reference has no closing ;").into());
+            };
+            let entity = &rest[start + 1..start + end];
+            let character = match entity {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => entity.strip_prefix('#').and_then(|digits| digits.parse().ok()).and_then(char::from_u32),
+                },
+            };
+            let Some(character) = character else {
+                return Err(self.error(&format!("unknown entity &{};", entity)).into());
+            };
+            decoded.push(character);
+            rest = &rest[start + end + 1..];
+        }
+        decoded.push_str(rest);
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- Nightly export -->
+<orders region="emea">
+  <order id="1" status="paid">
+    <customer>Ada &amp; Co</customer>
+    <total>12.50</total>
+    <item>pen</item>
+    <item>ink</item>
+  </order>
+  <order id="2">
+    <customer><![CDATA[<Bob>]]></customer>
+    <total/>
+    <note>rush</note>
+  </order>
+</orders>"#;
+
+    #[test]
+    fn test_xml_records() {
+        let path = XmlRecordPath::parse("/orders/order").unwrap();
+        let (columns, rows) = parse_xml_rows(ORDERS.as_bytes().to_vec(), &path, &CsvOptions::default()).unwrap();
+        let names: Vec<&str> = columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "status", "customer", "total", "item", "note"]);
+
+        let rows = rows.into_rows();
+        assert_eq!(rows[0].values, vec![
+            Value::Integer(1),
+            Value::Text("paid".to_string()),
+            Value::Text("Ada & Co".to_string()),
+            Value::Float(12.5),
+            Value::Json(r#"["pen","ink"]"#.to_string()),
+            Value::Null,
+        ]);
+        assert_eq!(rows[1].values, vec![
+            Value::Integer(2),
+            Value::Null,
+            Value::Text("<Bob>".to_string()),
+            Value::Null,
+            Value::Null,
+            Value::Text("rush".to_string()),
+        ]);
+
+        // The default record path reads the children of the root element
+        let (_, default_rows) = parse_xml_rows(ORDERS.as_bytes().to_vec(), &XmlRecordPath::default(), &CsvOptions::default()).unwrap();
+        assert_eq!(default_rows.len(), 2);
+        let (_, items) = parse_xml_rows(ORDERS.as_bytes().to_vec(), &XmlRecordPath::parse("//item").unwrap(), &CsvOptions::default()).unwrap();
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn test_xml_record_path() {
+        let path = XmlRecordPath::parse("/feed//entry").unwrap();
+        assert!(path.matches(&["feed", "entry"]));
+        assert!(path.matches(&["feed", "section", "atom:entry"]));
+        assert!(!path.matches(&["entry"]));
+        assert!(XmlRecordPath::parse("/*/row").unwrap().matches(&["anything", "row"]));
+        assert_eq!(XmlRecordPath::parse("/a/b").unwrap().to_string(), "/a/b");
+
+        assert!(XmlRecordPath::parse("orders/order").is_err());
+        assert!(XmlRecordPath::parse("/orders/order[1]").is_err());
+        assert!(XmlRecordPath::parse("/orders/@id").is_err());
+        assert!(XmlRecordPath::parse("/orders/").is_err());
+    }
+
+    #[test]
+    fn test_malformed_xml() {
+        let path = XmlRecordPath::default();
+        let parse = |xml: &str| parse_xml_rows(xml.as_bytes().to_vec(), &path, &CsvOptions::default()).map(|_| ());
+        assert!(parse("<a><b></a>").unwrap_err().to_string().contains("</a> does not match </b>"));
+        assert!(parse("<a>\n<b>").unwrap_err().to_string().contains("line 2"));
+        assert!(parse("<a x=1/>").is_err());
+        assert!(parse("<a>&bogus;</a>").is_err());
+        assert!(parse("<a/><b/>").is_err());
+        assert!(parse("").is_err());
+        assert!(parse("<!DOCTYPE a [<!ENTITY e \"x\">]><a><b>&#65;&#x42;</b></a>").is_ok());
+    }
+}
@@ -0,0 +1,378 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::utils::{
+    error::{DispatcherError, NirvError, NirvResult, QueryParsingError},
+    types::{DataSource, QueryOperation},
+};
+
+/// What a role may do with the sources a grant covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Privilege {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    /// Run GRANT, REVOKE, CREATE ROLE and DROP ROLE
+    Admin,
+}
+
+impl Privilege {
+    /// Privilege a statement of the operation needs on the sources it reads or writes
+    pub fn of(operation: &QueryOperation) -> Self {
+        match operation {
+            QueryOperation::Select => Privilege::Select,
+            QueryOperation::Insert => Privilege::Insert,
+            QueryOperation::Update => Privilege::Update,
+            QueryOperation::Delete => Privilege::Delete,
+        }
+    }
+
+    /// Privileges named in a GRANT or REVOKE, where ALL stands for every statement type
+    fn parse_list(list: &str) -> NirvResult<Vec<Self>> {
+        let mut privileges = Vec::new();
+        for name in list.split(',').map(|name| name.trim().to_ascii_uppercase()) {
+            match name.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["ALL"] | ["ALL", "PRIVILEGES"] => privileges.extend([
+                    Privilege::Select, Privilege::Insert, Privilege::Update, Privilege::Delete,
+                ]),
+                ["SELECT"] => privileges.push(Privilege::Select),
+                ["INSERT"] => privileges.push(Privilege::Insert),
+                ["UPDATE"] => privileges.push(Privilege::Update),
+                ["DELETE"] => privileges.push(Privilege::Delete),
+                ["ADMIN"] => privileges.push(Privilege::Admin),
+                _ => return Err(QueryParsingError::InvalidSyntax(format!(
+                    "Unknown privilege '{}'; expected SELECT, INSERT, UPDATE, DELETE, ALL or ADMIN", name
+                )).into()),
+            }
+        }
+        Ok(privileges)
+    }
+}
+
+impl fmt::Display for Privilege {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Privilege::Select => "SELECT",
+            Privilege::Insert => "INSERT",
+            Privilege::Update => "UPDATE",
+            Privilege::Delete => "DELETE",
+            Privilege::Admin => "ADMIN",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A privilege on the sources matching a pattern: a data object type such as `api`, a
+/// table of one such as `api.users`, or globs of either such as `postgres.public.*` or `*`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Grant {
+    pub privilege: Privilege,
+    pub sources: String,
+}
+
+impl Grant {
+    /// Whether the grant covers a source
+    pub fn covers(&self, source: &DataSource) -> bool {
+        let options = glob::MatchOptions { case_sensitive: false, ..glob::MatchOptions::new() };
+        let matches = |pattern: &str, text: &str| glob::Pattern::new(pattern)
+            .is_ok_and(|pattern| pattern.matches_with(text, options));
+        match self.sources.split_once('.') {
+            Some((object_type, table)) => matches(object_type, &source.object_type) && matches(table, &source.identifier),
+            None => matches(&self.sources, &source.object_type),
+        }
+    }
+}
+
+/// Access control statements handled by the engine rather than the query parser
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessStatement {
+    /// `CREATE ROLE analyst`
+    CreateRole(String),
+    /// `DROP ROLE analyst`, which also takes it from its users
+    DropRole(String),
+    /// `GRANT SELECT, INSERT ON 'api.*' TO ROLE analyst`, or `GRANT ADMIN TO ROLE dba`
+    Grant { privileges: Vec<Privilege>, sources: String, role: String },
+    /// `REVOKE INSERT ON 'api.*' FROM ROLE analyst`
+    Revoke { privileges: Vec<Privilege>, sources: String, role: String },
+    /// `GRANT ROLE analyst TO USER ann`
+    GrantRole { role: String, user: String },
+    /// `REVOKE ROLE analyst FROM USER ann`
+    RevokeRole { role: String, user: String },
+}
+
+impl AccessStatement {
+    /// Recognize an access control statement, returning None for anything else
+    pub fn parse(sql: &str) -> Option<NirvResult<Self>> {
+        static ROLE: OnceLock<Regex> = OnceLock::new();
+        static MEMBERSHIP: OnceLock<Regex> = OnceLock::new();
+        static PRIVILEGES: OnceLock<Regex> = OnceLock::new();
+        let role = ROLE.get_or_init(|| Regex::new(
+            r"(?is)^\s*(CREATE|DROP)\s+ROLE\s+(\w+)\s*;?\s*$"
+        ).unwrap());
+        let membership = MEMBERSHIP.get_or_init(|| Regex::new(
+            r"(?is)^\s*(GRANT|REVOKE)\s+ROLE\s+(\w+)\s+(?:TO|FROM)\s+(?:USER\s+)?([\w.@-]+)\s*;?\s*$"
+        ).unwrap());
+        let privileges = PRIVILEGES.get_or_init(|| Regex::new(
+            r#"(?is)^\s*(GRANT|REVOKE)\s+(.+?)(?:\s+ON\s+(?:SOURCE\s+)?(?:source\s*\(\s*)?['"]([^'"]+)['"]\s*\)?)?\s+(?:TO|FROM)\s+(?:ROLE\s+)?(\w+)\s*;?\s*$"#
+        ).unwrap());
+
+        if let Some(captures) = role.captures(sql) {
+            let name = captures[2].to_lowercase();
+            return Some(Ok(match captures[1].to_ascii_uppercase().as_str() {
+                "CREATE" => AccessStatement::CreateRole(name),
+                _ => AccessStatement::DropRole(name),
+            }));
+        }
+        if let Some(captures) = membership.captures(sql) {
+            let (role, user) = (captures[2].to_lowercase(), captures[3].to_string());
+            return Some(Ok(match captures[1].to_ascii_uppercase().as_str() {
+                "GRANT" => AccessStatement::GrantRole { role, user },
+                _ => AccessStatement::RevokeRole { role, user },
+            }));
+        }
+        let captures = privileges.captures(sql)?;
+        let granting = captures[1].eq_ignore_ascii_case("GRANT");
+        let role = captures[4].to_lowercase();
+        let sources = captures.get(3).map_or("*", |sources| sources.as_str()).to_string();
+        Some(Privilege::parse_list(&captures[2]).map(|privileges| match granting {
+            true => AccessStatement::Grant { privileges, sources, role },
+            false => AccessStatement::Revoke { privileges, sources, role },
+        }))
+    }
+}
+
+/// Roles, the privileges granted to them and the users holding them. With a path, every
+/// change is written back to the file, so grants outlive the engine.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccessCatalog {
+    /// Grants of each role, by role name
+    #[serde(default)]
+    roles: BTreeMap<String, BTreeSet<Grant>>,
+    /// Roles of each user, by user name
+    #[serde(default)]
+    users: BTreeMap<String, BTreeSet<String>>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl AccessCatalog {
+    /// An empty catalog kept in memory only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the catalog persisted at a path; a missing file is an empty catalog
+    pub fn open(path: impl AsRef<Path>) -> NirvResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut catalog: Self = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| NirvError::Configuration(
+                format!("Invalid access catalog '{}': {}", path.display(), e)
+            ))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(catalog_error("read", &path, e)),
+        };
+        catalog.path = Some(path);
+        Ok(catalog)
+    }
+
+    /// Names of the roles, in order
+    pub fn roles(&self) -> impl Iterator<Item = &str> {
+        self.roles.keys().map(String::as_str)
+    }
+
+    /// Grants of a role, if it exists
+    pub fn grants(&self, role: &str) -> Option<&BTreeSet<Grant>> {
+        self.roles.get(&role.to_lowercase())
+    }
+
+    /// Roles a user holds
+    pub fn roles_of(&self, user: &str) -> impl Iterator<Item = &str> {
+        self.users.get(user).into_iter().flatten().map(String::as_str)
+    }
+
+    /// Whether any of a user's roles was granted the privilege on a source
+    pub fn allows(&self, user: &str, privilege: Privilege, source: &DataSource) -> bool {
+        self.user_grants(user).any(|grant| grant.privilege == privilege && grant.covers(source))
+    }
+
+    /// Whether a user may run access control statements
+    pub fn is_admin(&self, user: &str) -> bool {
+        self.user_grants(user).any(|grant| grant.privilege == Privilege::Admin)
+    }
+
+    /// Refuse a user a privilege on a source none of their roles was granted
+    pub fn check(&self, user: &str, privilege: Privilege, source: &DataSource) -> NirvResult<()> {
+        if self.allows(user, privilege, source) {
+            return Ok(());
+        }
+        Err(NirvError::Dispatcher(DispatcherError::AccessDenied(format!(
+            "user '{}' lacks {} on '{}.{}'", user, privilege, source.object_type, source.identifier
+        ))))
+    }
+
+    /// Apply an access control statement, persisting the catalog when it has a path. The
+    /// statement changes a copy, which replaces the catalog only once it is saved, so a
+    /// failed write leaves the catalog in effect as it is on disk.
+    pub fn apply(&mut self, statement: AccessStatement) -> NirvResult<()> {
+        let mut updated = self.clone();
+        updated.change(statement)?;
+        updated.save()?;
+        *self = updated;
+        Ok(())
+    }
+
+    fn change(&mut self, statement: AccessStatement) -> NirvResult<()> {
+        match statement {
+            AccessStatement::CreateRole(role) => {
+                if self.roles.contains_key(&role) {
+                    return Err(NirvError::Configuration(format!("Role '{}' already exists", role)));
+                }
+                self.roles.insert(role, BTreeSet::new());
+            }
+            AccessStatement::DropRole(role) => {
+                self.roles.remove(&role).ok_or_else(|| unknown_role(&role))?;
+                for roles in self.users.values_mut() {
+                    roles.remove(&role);
+                }
+                self.users.retain(|_, roles| !roles.is_empty());
+            }
+            AccessStatement::Grant { privileges, sources, role } => {
+                let grants = self.roles.get_mut(&role).ok_or_else(|| unknown_role(&role))?;
+                grants.extend(privileges.into_iter().map(|privilege| Grant { privilege, sources: sources.clone() }));
+            }
+            AccessStatement::Revoke { privileges, sources, role } => {
+                let grants = self.roles.get_mut(&role).ok_or_else(|| unknown_role(&role))?;
+                grants.retain(|grant| !(privileges.contains(&grant.privilege) && grant.sources == sources));
+            }
+            AccessStatement::GrantRole { role, user } => {
+                if !self.roles.contains_key(&role) {
+                    return Err(unknown_role(&role));
+                }
+                self.users.entry(user).or_default().insert(role);
+            }
+            AccessStatement::RevokeRole { role, user } => {
+                if let Some(roles) = self.users.get_mut(&user) {
+                    roles.remove(&role);
+                    if roles.is_empty() {
+                        self.users.remove(&user);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn user_grants<'a>(&'a self, user: &str) -> impl Iterator<Item = &'a Grant> {
+        self.users.get(user).into_iter().flatten()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+    }
+
+    /// Replace the file through a temporary one, so a crash never leaves a partial catalog
+    fn save(&self) -> NirvResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| catalog_error("create the directory of", path, e))?;
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(|e| NirvError::Internal(e.to_string()))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, contents)
+            .and_then(|_| fs::rename(&temp, path))
+            .map_err(|e| catalog_error("write", path, e))
+    }
+}
+
+fn unknown_role(role: &str) -> NirvError {
+    NirvError::Configuration(format!("Role '{}' does not exist", role))
+}
+
+fn catalog_error(action: &str, path: &Path, error: std::io::Error) -> NirvError {
+    NirvError::Internal(format!("Failed to {} access catalog '{}': {}", action, path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(object_type: &str, identifier: &str) -> DataSource {
+        DataSource { object_type: object_type.to_string(), identifier: identifier.to_string(), alias: None }
+    }
+
+    #[test]
+    fn test_parse_access_statements() {
+        let parse = |sql: &str| AccessStatement::parse(sql).map(|statement| statement.unwrap());
+        assert_eq!(parse("CREATE ROLE Analyst;"), Some(AccessStatement::CreateRole("analyst".to_string())));
+        assert_eq!(parse("GRANT SELECT, insert ON 'api.*' TO ROLE analyst"), Some(AccessStatement::Grant {
+            privileges: vec![Privilege::Select, Privilege::Insert], sources: "api.*".to_string(), role: "analyst".to_string(),
+        }));
+        assert_eq!(parse("GRANT ADMIN TO dba"), Some(AccessStatement::Grant {
+            privileges: vec![Privilege::Admin], sources: "*".to_string(), role: "dba".to_string(),
+        }));
+        assert_eq!(parse("REVOKE ALL ON source('postgres') FROM analyst"), Some(AccessStatement::Revoke {
+            privileges: vec![Privilege::Select, Privilege::Insert, Privilege::Update, Privilege::Delete],
+            sources: "postgres".to_string(), role: "analyst".to_string(),
+        }));
+        assert_eq!(parse("GRANT ROLE analyst TO USER ann@example.com"), Some(AccessStatement::GrantRole {
+            role: "analyst".to_string(), user: "ann@example.com".to_string(),
+        }));
+        assert!(AccessStatement::parse("GRANT DROP ON 'api' TO analyst").unwrap().is_err());
+        assert!(parse("SELECT * FROM source('api.grants')").is_none());
+    }
+
+    #[test]
+    fn test_catalog_checks_and_persists_grants() {
+        let path = std::env::temp_dir().join(format!("nirv_access_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut catalog = AccessCatalog::open(&path).unwrap();
+        for sql in ["CREATE ROLE analyst", "GRANT SELECT ON 'api' TO analyst", "GRANT ROLE analyst TO ann"] {
+            catalog.apply(AccessStatement::parse(sql).unwrap().unwrap()).unwrap();
+        }
+        assert!(catalog.allows("ann", Privilege::Select, &source("api", "users")));
+        assert!(!catalog.allows("ann", Privilege::Delete, &source("api", "users")));
+        assert!(!catalog.allows("bob", Privilege::Select, &source("api", "users")));
+        assert!(catalog.check("ann", Privilege::Select, &source("postgres", "public.users")).is_err());
+        assert!(!catalog.is_admin("ann"));
+        assert!(catalog.apply(AccessStatement::GrantRole { role: "dba".to_string(), user: "ann".to_string() }).is_err());
+
+        // Grants are read back from the file
+        let reopened = AccessCatalog::open(&path).unwrap();
+        assert_eq!(reopened, catalog);
+        let mut catalog = reopened;
+        catalog.apply(AccessStatement::DropRole("analyst".to_string())).unwrap();
+        assert_eq!(catalog.roles_of("ann").count(), 0);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_failed_save_leaves_catalog_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut catalog = AccessCatalog::open(dir.path().join("access.json")).unwrap();
+        catalog.apply(AccessStatement::CreateRole("analyst".to_string())).unwrap();
+
+        // A directory in the way of the temporary file makes the write fail
+        fs::create_dir(dir.path().join("access.json.tmp")).unwrap();
+        let grant = AccessStatement::parse("GRANT SELECT ON 'api' TO analyst").unwrap().unwrap();
+        assert!(catalog.apply(grant).is_err());
+        assert_eq!(catalog.grants("analyst").map(|grants| grants.len()), Some(0));
+        assert_eq!(AccessCatalog::open(dir.path().join("access.json")).unwrap(), catalog);
+    }
+
+    #[test]
+    fn test_grant_patterns() {
+        let grant = |sources: &str| Grant { privilege: Privilege::Select, sources: sources.to_string() };
+        assert!(grant("*").covers(&source("api", "users")));
+        assert!(grant("API").covers(&source("api", "users")));
+        assert!(grant("postgres.public.*").covers(&source("postgres", "public.users")));
+        assert!(!grant("postgres.public.*").covers(&source("postgres", "audit.log")));
+        assert!(!grant("api.users").covers(&source("api", "orders")));
+    }
+}
@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::utils::{
+    error::{NirvError, NirvResult},
+    identifier::IdentifierCase,
+    types::{Column, ColumnMetadata, DataType, InternalQuery, OrderBy, QueryResult, Row, Value, COUNT_ALL},
+};
+
+/// An approximate aggregate function
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApproxFunction {
+    /// Distinct non-null values, estimated with HyperLogLog
+    CountDistinct,
+    /// Value at a quantile between 0 and 1, estimated with a t-digest
+    Percentile(f64),
+}
+
+/// An `approx_count_distinct(column)` or `approx_percentile(column, quantile)` expression.
+/// Like `JsonExtract`, its canonical text form is stored as the projection's column name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApproxAggregate {
+    pub function: ApproxFunction,
+    pub column: String,
+}
+
+impl ApproxAggregate {
+    /// Recognize the canonical form produced by `Display`
+    pub fn parse(expression: &str) -> Option<Self> {
+        if let Some(column) = expression.strip_prefix("approx_count_distinct(").and_then(|rest| rest.strip_suffix(')')) {
+            return Some(Self { function: ApproxFunction::CountDistinct, column: column.to_string() });
+        }
+        let inner = expression.strip_prefix("approx_percentile(")?.strip_suffix(')')?;
+        let (column, quantile) = inner.rsplit_once(", ")?;
+        Some(Self { function: ApproxFunction::Percentile(quantile.parse().ok()?), column: column.to_string() })
+    }
+
+    fn data_type(&self) -> DataType {
+        match self.function {
+            ApproxFunction::CountDistinct => DataType::Integer,
+            ApproxFunction::Percentile(_) => DataType::Float,
+        }
+    }
+}
+
+impl fmt::Display for ApproxAggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.function {
+            ApproxFunction::CountDistinct => write!(f, "approx_count_distinct({})", self.column),
+            ApproxFunction::Percentile(quantile) => write!(f, "approx_percentile({}, {})", self.column, quantile),
+        }
+    }
+}
+
+/// Whether any projection is an approximate aggregate
+pub fn has_approx_aggregates(projections: &[Column]) -> bool {
+    projections.iter().any(|col| ApproxAggregate::parse(&col.name).is_some())
+}
+
+/// Whether a query reduces its rows to aggregate values: it groups them, or projects
+/// approximate aggregates over all of them
+pub fn is_aggregation(query: &InternalQuery) -> bool {
+    !query.group_by.is_empty() || has_approx_aggregates(&query.projections)
+}
+
+/// Columns the aggregates in the projections and the group keys read, to fetch in their place
+pub fn aggregate_input_columns(projections: &[Column], group_by: &[Column]) -> Vec<Column> {
+    let mut columns: Vec<Column> = Vec::new();
+    for aggregate in projections.iter().filter_map(|col| ApproxAggregate::parse(&col.name)) {
+        if !columns.iter().any(|col| col.name == aggregate.column) {
+            columns.push(Column { name: aggregate.column, alias: None, source: None });
+        }
+    }
+    for key in group_by {
+        if !columns.iter().any(|col| col.name == key.name && col.source == key.source) {
+            columns.push(Column { alias: None, ..key.clone() });
+        }
+    }
+    columns
+}
+
+/// The group key a projection that is not an aggregate selects
+pub(crate) fn group_key(projection: &Column, group_by: &[Column], identifier_case: IdentifierCase) -> Option<usize> {
+    group_by.iter().position(|key| {
+        identifier_case.matches(&key.name, &projection.name)
+            && (key.source.is_none() || projection.source.is_none() || key.source == projection.source)
+    })
+}
+
+/// The error for a projection that is neither an aggregate nor a group key
+pub(crate) fn ungrouped_column_error(projection: &Column, group_by: &[Column]) -> NirvError {
+    NirvError::Internal(match group_by.is_empty() {
+        true => format!("Column '{}' must be used in an aggregate function", projection.name),
+        false => format!("Column '{}' must appear in GROUP BY or be used in an aggregate function", projection.name),
+    })
+}
+
+/// An ORDER BY of aggregated rows, sorting by the output name of each selected expression
+pub(crate) fn aggregated_order(order_by: &OrderBy, projections: &[Column], identifier_case: IdentifierCase) -> OrderBy {
+    let mut order_by = order_by.clone();
+    for order in &mut order_by.columns {
+        let alias = projections.iter()
+            .find(|col| identifier_case.matches(&order.column, &col.name))
+            .and_then(|col| col.alias.clone());
+        if let Some(alias) = alias {
+            order.column = alias;
+        }
+    }
+    order_by
+}
+
+/// What an aggregated output column holds
+enum Output {
+    Approx(ApproxAggregate, usize),
+    Count,
+    Key(usize),
+}
+
+/// Reduce rows to one row of aggregate values per group of rows sharing the values of the
+/// group keys, in the order groups first appear. Without keys all rows form a single group,
+/// and otherwise each projection that is not an aggregate must be a key.
+pub fn aggregate_rows(result: QueryResult, projections: &[Column], group_by: &[Column], identifier_case: IdentifierCase) -> NirvResult<QueryResult> {
+    let find_column = |column: &Column| {
+        let names = || result.columns.iter().map(|col| col.name.as_str());
+        column.source.as_ref()
+            .and_then(|source| identifier_case.resolve(&format!("{}.{}", source, column.name), names()))
+            .or_else(|| identifier_case.resolve(&column.name, names()))
+            .ok_or_else(|| NirvError::Internal(format!("Column '{}' not found in result", column.name)))
+    };
+    let key_indexes = group_by.iter().map(find_column).collect::<NirvResult<Vec<usize>>>()?;
+
+    let mut outputs = Vec::new();
+    let mut columns = Vec::new();
+    for projection in projections {
+        let (output, data_type) = if let Some(aggregate) = ApproxAggregate::parse(&projection.name) {
+            let index = find_column(&Column { name: aggregate.column.clone(), alias: None, source: None })?;
+            let data_type = aggregate.data_type();
+            (Output::Approx(aggregate, index), data_type)
+        } else if projection.name == COUNT_ALL {
+            (Output::Count, DataType::Integer)
+        } else {
+            let key = group_key(projection, group_by, identifier_case)
+                .ok_or_else(|| ungrouped_column_error(projection, group_by))?;
+            (Output::Key(key), result.columns[key_indexes[key]].data_type.clone())
+        };
+        let name = match &output {
+            Output::Count => projection.alias.clone().unwrap_or_else(|| "count".to_string()),
+            Output::Approx(aggregate, _) => projection.alias.clone().unwrap_or_else(|| aggregate.to_string()),
+            Output::Key(_) => projection.alias.clone().unwrap_or_else(|| projection.name.clone()),
+        };
+        columns.push(ColumnMetadata { name, data_type, nullable: !matches!(output, Output::Count) });
+        outputs.push(output);
+    }
+
+    // Rows are grouped by a hash of their key values, then by the values themselves
+    let mut groups: Vec<(Vec<Value>, Vec<&Row>)> = Vec::new();
+    let mut group_index: HashMap<u64, Vec<usize>> = HashMap::new();
+    if group_by.is_empty() {
+        groups.push((Vec::new(), result.rows.iter().collect()));
+    } else {
+        for row in &result.rows {
+            let key: Vec<Value> = key_indexes.iter().map(|index| row.get(*index).cloned().unwrap_or(Value::Null)).collect();
+            let mut hasher = DefaultHasher::new();
+            key.iter().for_each(|value| hash_value(value, &mut hasher));
+            let candidates = group_index.entry(hasher.finish()).or_default();
+            match candidates.iter().find(|index| groups[**index].0 == key) {
+                Some(index) => groups[*index].1.push(row),
+                None => {
+                    candidates.push(groups.len());
+                    groups.push((key, vec![row]));
+                }
+            }
+        }
+    }
+
+    let rows = groups.into_iter().map(|(key, rows)| {
+        let values = outputs.iter().map(|output| match output {
+            Output::Approx(aggregate, index) => approximate(aggregate, rows.iter().filter_map(|row| row.get(*index))),
+            Output::Count => Value::Integer(rows.len() as i64),
+            Output::Key(position) => key[*position].clone(),
+        }).collect();
+        Row::new(values)
+    }).collect();
+
+    Ok(QueryResult {
+        columns,
+        rows,
+        affected_rows: None,
+        execution_time: result.execution_time,
+        stats: result.stats,
+        warnings: result.warnings,
+    })
+}
+
+/// The value of an approximate aggregate over a group's values of its column
+fn approximate<'a>(aggregate: &ApproxAggregate, values: impl Iterator<Item = &'a Value>) -> Value {
+    let inputs = values.filter(|value| **value != Value::Null);
+    match aggregate.function {
+        ApproxFunction::CountDistinct => {
+            let mut sketch = HyperLogLog::new();
+            inputs.for_each(|value| sketch.insert(value));
+            Value::Integer(sketch.estimate() as i64)
+        }
+        ApproxFunction::Percentile(quantile) => {
+            let mut digest = TDigest::new();
+            inputs.filter_map(numeric_value).for_each(|value| digest.insert(value));
+            digest.quantile(quantile).map_or(Value::Null, Value::Float)
+        }
+    }
+}
+
+fn numeric_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        Value::Text(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Registers addressed by the first 14 hash bits, for a standard error of about 0.8%
+const HLL_PRECISION: u32 = 14;
+
+/// HyperLogLog cardinality sketch using a fixed 16 KiB of registers
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self { registers: vec![0; 1 << HLL_PRECISION] }
+    }
+
+    pub fn insert(&mut self, value: &Value) {
+        let mut hasher = DefaultHasher::new();
+        hash_value(value, &mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|rank| 2f64.powi(-i32::from(*rank))).sum();
+        let estimate = alpha * m * m / sum;
+
+        // Linear counting is more accurate while many registers are still empty
+        let zeros = self.registers.iter().filter(|rank| **rank == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hash_value(value: &Value, hasher: &mut impl Hasher) {
+    match value {
+        Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => s.hash(hasher),
+        Value::Integer(i) => i.hash(hasher),
+        // -0.0 and 0.0 are the same value
+        Value::Float(f) => (if *f == 0.0 { 0.0f64 } else { *f }).to_bits().hash(hasher),
+        Value::Boolean(b) => b.hash(hasher),
+        Value::Binary(b) => b.hash(hasher),
+        Value::Null => 0u8.hash(hasher),
+    }
+}
+
+/// Compression of the t-digest: at most about this many centroids are kept
+const TDIGEST_COMPRESSION: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Merging t-digest for quantile estimates, accurate at the tails, in bounded memory
+#[derive(Debug, Clone, Default)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if self.centroids.is_empty() && self.buffer.is_empty() {
+            self.min = value;
+            self.max = value;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() >= 5 * TDIGEST_COMPRESSION as usize {
+            self.compress();
+        }
+    }
+
+    /// Estimated value at quantile `q` (0 to 1); `None` when nothing was inserted
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        self.compress();
+        let first = self.centroids.first()?;
+        let last = self.centroids.last()?;
+        let total: f64 = self.centroids.iter().map(|c| c.weight).sum();
+        let target = q.clamp(0.0, 1.0) * total;
+
+        // Interpolate between centroid centers, and towards min and max at the ends
+        if target <= first.weight / 2.0 {
+            return Some(self.min + (first.mean - self.min) * target / (first.weight / 2.0));
+        }
+        if target >= total - last.weight / 2.0 {
+            let tail = total - target;
+            return Some(self.max - (self.max - last.mean) * tail / (last.weight / 2.0));
+        }
+
+        let mut cumulative = 0.0;
+        for pair in self.centroids.windows(2) {
+            let left_center = cumulative + pair[0].weight / 2.0;
+            let right_center = cumulative + pair[0].weight + pair[1].weight / 2.0;
+            if target <= right_center {
+                let fraction = (target - left_center) / (right_center - left_center);
+                return Some(pair[0].mean + (pair[1].mean - pair[0].mean) * fraction);
+            }
+            cumulative += pair[0].weight;
+        }
+        Some(last.mean)
+    }
+
+    /// Merge buffered values into the centroids, keeping each centroid within the size
+    /// the k1 scale function allows at its quantile
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all: Vec<Centroid> = self.centroids.drain(..)
+            .chain(self.buffer.drain(..).map(|mean| Centroid { mean, weight: 1.0 }))
+            .collect();
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+
+        let scale = |q: f64| TDIGEST_COMPRESSION / (2.0 * std::f64::consts::PI) * (2.0 * q - 1.0).asin();
+        let limit = |q: f64| {
+            let k = scale(q) + 1.0;
+            if k >= TDIGEST_COMPRESSION / 4.0 {
+                1.0
+            } else {
+                ((k * 2.0 * std::f64::consts::PI / TDIGEST_COMPRESSION).sin() + 1.0) / 2.0
+            }
+        };
+
+        let mut merged = Vec::new();
+        let mut current = all[0];
+        let mut weight_before = 0.0;
+        let mut q_limit = limit(0.0);
+        for centroid in all.into_iter().skip(1) {
+            let q = (weight_before + current.weight + centroid.weight) / total;
+            if q <= q_limit {
+                let weight = current.weight + centroid.weight;
+                current.mean += (centroid.mean - current.mean) * centroid.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_before += current.weight;
+                merged.push(current);
+                q_limit = limit(weight_before / total);
+                current = centroid;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_aggregate_canonical_form() {
+        let count = ApproxAggregate { function: ApproxFunction::CountDistinct, column: "u.email".to_string() };
+        assert_eq!(count.to_string(), "approx_count_distinct(u.email)");
+        assert_eq!(ApproxAggregate::parse(&count.to_string()), Some(count));
+
+        let p95 = ApproxAggregate { function: ApproxFunction::Percentile(0.95), column: "latency".to_string() };
+        assert_eq!(p95.to_string(), "approx_percentile(latency, 0.95)");
+        assert_eq!(ApproxAggregate::parse(&p95.to_string()), Some(p95));
+        assert_eq!(ApproxAggregate::parse("latency"), None);
+    }
+
+    #[test]
+    fn test_hyperloglog_estimate_is_close() {
+        let mut sketch = HyperLogLog::new();
+        for i in 0..100_000 {
+            sketch.insert(&Value::Integer(i % 50_000));
+        }
+        let estimate = sketch.estimate() as f64;
+        assert!((estimate - 50_000.0).abs() / 50_000.0 < 0.03, "estimate {}", estimate);
+
+        let mut small = HyperLogLog::new();
+        for name in ["a", "b", "c", "a"] {
+            small.insert(&Value::Text(name.to_string()));
+        }
+        assert_eq!(small.estimate(), 3);
+    }
+
+    #[test]
+    fn test_tdigest_quantiles() {
+        let mut digest = TDigest::new();
+        for i in 1..=100_000 {
+            digest.insert(i as f64);
+        }
+        let median = digest.quantile(0.5).unwrap();
+        assert!((median - 50_000.0).abs() < 500.0, "median {}", median);
+        let p99 = digest.quantile(0.99).unwrap();
+        assert!((p99 - 99_000.0).abs() < 100.0, "p99 {}", p99);
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(1.0), Some(100_000.0));
+
+        let mut small = TDigest::new();
+        (1..=100).for_each(|i| small.insert(i as f64));
+        assert_eq!(small.quantile(0.5), Some(50.5));
+        assert_eq!(TDigest::new().quantile(0.5), None);
+    }
+}
@@ -0,0 +1,334 @@
+use std::cmp::Ordering;
+use std::time::Duration;
+use crate::{
+    engine::{ComputedColumn, predicate_matches, compare_sort_values},
+    utils::{
+        types::{QueryResult, Value, Column, ColumnMetadata, Predicate, OrderBy, NodeStats, ExecutionStats},
+        row_buffer::RowBuffer,
+        identifier::IdentifierCase,
+        collation::Collation,
+        error::{NirvResult, NirvError},
+    },
+};
+
+/// Rows an executor operator processes at a time
+pub const BATCH_SIZE: usize = 1024;
+
+/// A result whose rows are held in batches of up to `BATCH_SIZE` rows. Filters, projections,
+/// sorts and limits pass batches between them, resolving columns once per operator and
+/// moving values instead of cloning rows; the rows of a result become `Row`s again only
+/// when an operator that works on whole results needs them.
+#[derive(Debug, Clone)]
+pub struct RowBatches {
+    /// Columns, counts and statistics of the result; its rows are in `batches`
+    pub result: QueryResult,
+    pub batches: Vec<RowBuffer>,
+}
+
+impl RowBatches {
+    /// Move the rows of a result, such as one returned by a connector, into batches. Rows
+    /// shorter than the widest row or the columns are padded with NULL.
+    pub fn from_result(mut result: QueryResult) -> Self {
+        let rows = std::mem::take(&mut result.rows);
+        let width = rows.iter().map(|row| row.values.len()).max().unwrap_or(0).max(result.columns.len());
+        let mut batches = Vec::with_capacity(rows.len().div_ceil(BATCH_SIZE));
+        let mut rows = rows.into_iter().peekable();
+        while rows.peek().is_some() {
+            let mut batch = RowBuffer::with_capacity(width, BATCH_SIZE);
+            for row in rows.by_ref().take(BATCH_SIZE) {
+                batch.push_row(row.values);
+            }
+            batches.push(batch);
+        }
+        Self { result, batches }
+    }
+
+    /// The result with its rows taken out of the batches
+    pub fn into_result(self) -> QueryResult {
+        let mut result = self.result;
+        result.rows.reserve(self.batches.iter().map(RowBuffer::len).sum());
+        for batch in self.batches {
+            result.rows.extend(batch.into_rows());
+        }
+        result
+    }
+
+    /// Add the batches of another result with the same columns after these
+    pub fn append(&mut self, other: RowBatches) {
+        self.batches.extend(other.batches);
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.batches.iter().map(RowBuffer::len).sum()
+    }
+
+    /// Record a completed execution step and the rows it produced
+    pub fn record_node(&mut self, node: impl Into<String>, duration: Duration) {
+        let rows = self.row_count() as u64;
+        self.result.stats.get_or_insert_with(ExecutionStats::default).nodes.push(NodeStats {
+            node: node.into(),
+            rows,
+            duration,
+        });
+    }
+
+    /// Keep the rows satisfying every predicate, comparing text by a collation. Each batch is
+    /// compacted once, after every predicate has been evaluated over it.
+    pub fn filter(mut self, predicates: &[Predicate], identifier_case: IdentifierCase, collation: Collation) -> NirvResult<Self> {
+        if predicates.is_empty() {
+            return Ok(self);
+        }
+
+        let filters = Filters::resolve(predicates, &self.result.columns, identifier_case, collation)?;
+        let mut selected = Vec::with_capacity(BATCH_SIZE);
+        for batch in &mut self.batches {
+            filters.select(|row| batch.row(row), batch.len(), &mut selected);
+            let mut row = 0;
+            batch.retain(|_| {
+                row += 1;
+                selected[row - 1]
+            });
+        }
+        self.batches.retain(|batch| !batch.is_empty());
+        if self.result.affected_rows.is_some() {
+            self.result.affected_rows = Some(self.row_count() as u64);
+        }
+        Ok(self)
+    }
+
+    /// Filter the rows of a result as they are moved into batches, so rows failing a predicate
+    /// are dropped without being copied
+    pub fn from_filtered_result(mut result: QueryResult, predicates: &[Predicate], identifier_case: IdentifierCase, collation: Collation) -> NirvResult<Self> {
+        if predicates.is_empty() {
+            return Ok(Self::from_result(result));
+        }
+
+        let filters = Filters::resolve(predicates, &result.columns, identifier_case, collation)?;
+        let rows = std::mem::take(&mut result.rows);
+        let width = rows.iter().map(|row| row.values.len()).max().unwrap_or(0).max(result.columns.len());
+        let mut batches: Vec<RowBuffer> = Vec::new();
+        let mut selected = Vec::with_capacity(BATCH_SIZE);
+        let mut rows = rows.into_iter();
+        loop {
+            let chunk: Vec<_> = rows.by_ref().take(BATCH_SIZE).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            filters.select(|row| &chunk[row].values, chunk.len(), &mut selected);
+            for (row, _) in chunk.into_iter().zip(&selected).filter(|(_, keep)| **keep) {
+                match batches.last_mut() {
+                    Some(batch) if batch.len() < BATCH_SIZE => batch.push_row(row.values),
+                    _ => {
+                        let mut batch = RowBuffer::with_capacity(width, BATCH_SIZE);
+                        batch.push_row(row.values);
+                        batches.push(batch);
+                    }
+                }
+            }
+        }
+        let mut filtered = Self { result, batches };
+        if filtered.result.affected_rows.is_some() {
+            filtered.result.affected_rows = Some(filtered.row_count() as u64);
+        }
+        Ok(filtered)
+    }
+
+    /// Keep the given columns, renamed to their aliases, moving each value into the new batch
+    /// unless the column is projected again later
+    pub fn project(mut self, columns: &[Column], identifier_case: IdentifierCase) -> NirvResult<Self> {
+        if columns.is_empty() || columns.iter().any(|col| col.name == "*") {
+            return Ok(self);
+        }
+
+        let mut indexes = Vec::new();
+        let mut metadata = Vec::new();
+        for col in columns {
+            let names = || self.result.columns.iter().map(|c| c.name.as_str());
+            let index = col.alias.as_deref()
+                .and_then(|alias| identifier_case.resolve(alias, names()))
+                .or_else(|| identifier_case.resolve(&col.name, names()))
+                .ok_or_else(|| NirvError::Internal(format!("Column '{}' not found in result", col.name)))?;
+            let mut column = self.result.columns[index].clone();
+            if let Some(alias) = &col.alias {
+                column.name = alias.clone();
+            }
+            indexes.push(index);
+            metadata.push(column);
+        }
+        let last_use: Vec<bool> = (0..indexes.len())
+            .map(|position| !indexes[position + 1..].contains(&indexes[position]))
+            .collect();
+
+        let batches = self.batches.iter_mut()
+            .map(|batch| {
+                let mut projected = RowBuffer::with_capacity(indexes.len(), batch.len());
+                for row in 0..batch.len() {
+                    let values = batch.row_mut(row);
+                    projected.push_row(indexes.iter().zip(&last_use).map(|(index, last)| match last {
+                        true => std::mem::replace(&mut values[*index], Value::Null),
+                        false => values[*index].clone(),
+                    }));
+                }
+                projected
+            })
+            .collect();
+        self.result.columns = metadata;
+        Ok(Self { batches, ..self })
+    }
+
+    /// Sort rows by each ordering column in turn, later columns breaking ties in earlier ones,
+    /// with text ordered by a collation; rows ordered equally by every column keep their order.
+    /// Row positions are sorted and the rows then moved into new batches in that order.
+    pub fn sort(mut self, order_by: &OrderBy, identifier_case: IdentifierCase, collation: Collation) -> NirvResult<Self> {
+        let mut keys = Vec::new();
+        for sort_column in &order_by.columns {
+            let index = identifier_case.resolve(&sort_column.column, self.result.columns.iter().map(|col| col.name.as_str()))
+                .ok_or_else(|| NirvError::Internal(format!("Sort column '{}' not found in result", sort_column.column)))?;
+            keys.push((index, sort_column));
+        }
+        if keys.is_empty() || self.row_count() < 2 {
+            return Ok(self);
+        }
+
+        let mut positions: Vec<(usize, usize)> = self.batches.iter()
+            .enumerate()
+            .flat_map(|(batch, rows)| (0..rows.len()).map(move |row| (batch, row)))
+            .collect();
+        positions.sort_by(|a, b| {
+            let (a, b) = (self.batches[a.0].row(a.1), self.batches[b.0].row(b.1));
+            keys.iter()
+                .map(|(index, sort_column)| compare_sort_values(&a[*index], &b[*index], sort_column, collation))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let width = self.batches[0].width();
+        let mut batches = Vec::with_capacity(self.batches.len());
+        for chunk in positions.chunks(BATCH_SIZE) {
+            let mut sorted = RowBuffer::with_capacity(width, chunk.len());
+            for (batch, row) in chunk {
+                sorted.push_row(self.batches[*batch].row_mut(*row).iter_mut().map(|value| std::mem::replace(value, Value::Null)));
+            }
+            batches.push(sorted);
+        }
+        Ok(Self { batches, ..self })
+    }
+
+    /// Keep the first `count` rows, dropping the batches after them
+    pub fn limit(mut self, count: u64) -> Self {
+        let mut remaining = usize::try_from(count).unwrap_or(usize::MAX);
+        self.batches.retain_mut(|batch| {
+            batch.truncate(remaining);
+            remaining -= batch.len();
+            !batch.is_empty()
+        });
+        self
+    }
+}
+
+/// Predicates with the columns they read resolved
+struct Filters<'a> {
+    filters: Vec<(usize, Option<ComputedColumn>, &'a Predicate)>,
+    collation: Collation,
+}
+
+impl<'a> Filters<'a> {
+    fn resolve(predicates: &'a [Predicate], columns: &[ColumnMetadata], identifier_case: IdentifierCase, collation: Collation) -> NirvResult<Self> {
+        let mut filters = Vec::new();
+        for predicate in predicates {
+            let computed = ComputedColumn::parse(&predicate.column);
+            let column = computed.as_ref().map_or(predicate.column.as_str(), |computed| computed.column());
+            let index = identifier_case.resolve(column, columns.iter().map(|col| col.name.as_str()))
+                .ok_or_else(|| NirvError::Internal(format!("Column '{}' not found in result", column)))?;
+            filters.push((index, computed, predicate));
+        }
+        Ok(Self { filters, collation })
+    }
+
+    /// Mark which of `len` rows satisfy every predicate. Each predicate is evaluated down its
+    /// column, only for the rows still selected.
+    fn select<'r>(&self, row: impl Fn(usize) -> &'r [Value], len: usize, selected: &mut Vec<bool>) {
+        selected.clear();
+        selected.resize(len, true);
+        for (index, computed, predicate) in &self.filters {
+            for (position, keep) in selected.iter_mut().enumerate().filter(|(_, keep)| **keep) {
+                let value = row(position).get(*index).unwrap_or(&Value::Null);
+                *keep = match computed {
+                    Some(computed) => predicate_matches(&computed.evaluate(value), &predicate.operator, &predicate.value, self.collation),
+                    None => predicate_matches(value, &predicate.operator, &predicate.value, self.collation),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{DataType, OrderColumn, OrderDirection, PredicateOperator, PredicateValue, Row};
+
+    fn numbers(rows: usize) -> QueryResult {
+        let mut result = QueryResult::new();
+        for name in ["n", "parity"] {
+            result.columns.push(ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true });
+        }
+        result.rows = (0..rows as i64)
+            .map(|n| Row::new(vec![Value::Integer(n), Value::Text(if n % 2 == 0 { "even" } else { "odd" }.to_string())]))
+            .collect();
+        result
+    }
+
+    fn first_column(result: &QueryResult) -> Vec<i64> {
+        result.rows.iter()
+            .map(|row| match row.values[0] {
+                Value::Integer(n) => n,
+                _ => -1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batches_round_trip() {
+        let batches = RowBatches::from_result(numbers(2500));
+        assert_eq!(batches.batches.iter().map(RowBuffer::len).collect::<Vec<_>>(), vec![1024, 1024, 452]);
+        assert_eq!(batches.row_count(), 2500);
+        assert_eq!(first_column(&batches.into_result()), (0..2500).collect::<Vec<_>>());
+
+        // Short rows are padded to the widest
+        let mut result = QueryResult::new();
+        result.rows = vec![Row::new(vec![Value::Integer(1)]), Row::new(vec![Value::Integer(2), Value::Integer(3)])];
+        let result = RowBatches::from_result(result).into_result();
+        assert_eq!(result.rows[0].values, vec![Value::Integer(1), Value::Null]);
+    }
+
+    #[test]
+    fn test_batched_operators() {
+        let predicates = vec![
+            Predicate { column: "parity".to_string(), operator: PredicateOperator::Equal, value: PredicateValue::String("odd".to_string()) },
+            Predicate { column: "n".to_string(), operator: PredicateOperator::GreaterThan, value: PredicateValue::Integer(1000) },
+        ];
+        let order_by = OrderBy {
+            columns: vec![OrderColumn { column: "n".to_string(), direction: OrderDirection::Descending, nulls: None }],
+        };
+        let columns = vec![
+            Column { name: "n".to_string(), alias: Some("number".to_string()), source: None },
+            Column { name: "n".to_string(), alias: None, source: None },
+        ];
+        let result = RowBatches::from_result(numbers(3000))
+            .filter(&predicates, IdentifierCase::default(), Collation::default()).unwrap()
+            .sort(&order_by, IdentifierCase::default(), Collation::default()).unwrap()
+            .limit(1500)
+            .project(&columns, IdentifierCase::default()).unwrap()
+            .into_result();
+
+        assert_eq!(result.columns.iter().map(|col| col.name.as_str()).collect::<Vec<_>>(), vec!["number", "n"]);
+        assert_eq!(first_column(&result), (1001..3000).rev().step_by(2).collect::<Vec<_>>());
+        assert!(result.rows.iter().all(|row| row.values[0] == row.values[1]));
+
+        let limited = RowBatches::from_result(numbers(3000)).limit(1030);
+        assert_eq!(limited.batches.iter().map(RowBuffer::len).collect::<Vec<_>>(), vec![1024, 6]);
+
+        let missing = RowBatches::from_result(numbers(10)).project(&[Column { name: "missing".to_string(), alias: None, source: None }], IdentifierCase::default());
+        assert!(missing.is_err());
+    }
+}
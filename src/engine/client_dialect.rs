@@ -0,0 +1,225 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use sqlparser::ast::{Expr, FunctionArg, FunctionArgExpr, Ident, JoinConstraint, JoinOperator, ObjectName, SelectItem, SetExpr, Statement};
+use sqlparser::dialect::{Dialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+
+use crate::protocol::ProtocolType;
+use crate::utils::error::{NirvError, NirvResult};
+
+/// Functions SQL Server clients call that the engine knows by another name
+const SQL_SERVER_FUNCTIONS: &[(&str, &str)] = &[
+    ("getdate", "now"),
+    ("getutcdate", "now"),
+    ("sysdatetime", "now"),
+    ("sysutcdatetime", "now"),
+    ("count_big", "count"),
+];
+
+const MYSQL_FUNCTIONS: &[(&str, &str)] = &[
+    ("sysdate", "now"),
+    ("utc_timestamp", "now"),
+    ("localtimestamp", "now"),
+    ("regexp_substr", "regexp_extract"),
+];
+
+const POSTGRES_FUNCTIONS: &[(&str, &str)] = &[
+    ("clock_timestamp", "now"),
+    ("statement_timestamp", "now"),
+    ("transaction_timestamp", "now"),
+    ("localtimestamp", "now"),
+];
+
+const SQLITE_FUNCTIONS: &[(&str, &str)] = &[];
+
+/// SQL dialect a client connection speaks, chosen by the protocol adapter that accepted it.
+/// A connection's queries are parsed in its dialect only, and the dialect's function names
+/// are translated to the engine's, so `GETDATE()` from a SQL Server client is `NOW()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClientDialect {
+    /// Try PostgreSQL, MySQL, SQLite and then generic SQL, translating no functions
+    #[default]
+    Auto,
+    PostgreSQL,
+    MySQL,
+    SQLite,
+    SqlServer,
+}
+
+impl ClientDialect {
+    /// The dialect of clients connecting through a protocol
+    pub fn for_protocol(protocol: &ProtocolType) -> Self {
+        match protocol {
+            ProtocolType::PostgreSQL => ClientDialect::PostgreSQL,
+            ProtocolType::MySQL => ClientDialect::MySQL,
+            ProtocolType::SQLite => ClientDialect::SQLite,
+            ProtocolType::SqlServer => ClientDialect::SqlServer,
+        }
+    }
+
+    pub fn parse(name: &str) -> NirvResult<Self> {
+        match name.to_lowercase().as_str() {
+            "auto" => Ok(ClientDialect::Auto),
+            "postgres" | "postgresql" => Ok(ClientDialect::PostgreSQL),
+            "mysql" => Ok(ClientDialect::MySQL),
+            "sqlite" => Ok(ClientDialect::SQLite),
+            "sqlserver" | "mssql" | "tsql" => Ok(ClientDialect::SqlServer),
+            other => Err(NirvError::Configuration(format!(
+                "Unknown SQL dialect '{}'; expected auto, postgresql, mysql, sqlite or sqlserver", other
+            ))),
+        }
+    }
+
+    /// The dialect a query's own syntax points to: backtick quoting is MySQL's, `TOP n` and
+    /// `[bracket]` quoting SQL Server's, and `::` casts and `ILIKE` PostgreSQL's. Only a hint
+    /// for the order dialects are tried in, so text inside string literals is not told apart.
+    pub fn guess(sql: &str) -> Option<Self> {
+        static PATTERNS: OnceLock<[(Regex, ClientDialect); 3]> = OnceLock::new();
+        let patterns = PATTERNS.get_or_init(|| [
+            (Regex::new(r"`").unwrap(), ClientDialect::MySQL),
+            (Regex::new(r"(?i)\bSELECT\s+(?:DISTINCT\s+)?TOP\b|(?:^|[\s,.(])\[[A-Za-z_]").unwrap(), ClientDialect::SqlServer),
+            (Regex::new(r"(?i)::|\bILIKE\b").unwrap(), ClientDialect::PostgreSQL),
+        ]);
+        patterns.iter().find(|(pattern, _)| pattern.is_match(sql)).map(|(_, dialect)| *dialect)
+    }
+
+    /// The sqlparser dialect queries are parsed with; `None` tries several
+    pub(crate) fn parser_dialect(&self) -> Option<Box<dyn Dialect>> {
+        match self {
+            ClientDialect::Auto => None,
+            ClientDialect::PostgreSQL => Some(Box::new(PostgreSqlDialect {})),
+            ClientDialect::MySQL => Some(Box::new(MySqlDialect {})),
+            ClientDialect::SQLite => Some(Box::new(SQLiteDialect {})),
+            ClientDialect::SqlServer => Some(Box::new(MsSqlDialect {})),
+        }
+    }
+
+    fn functions(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            ClientDialect::Auto => &[],
+            ClientDialect::PostgreSQL => POSTGRES_FUNCTIONS,
+            ClientDialect::MySQL => MYSQL_FUNCTIONS,
+            ClientDialect::SQLite => SQLITE_FUNCTIONS,
+            ClientDialect::SqlServer => SQL_SERVER_FUNCTIONS,
+        }
+    }
+
+    /// The engine's name for a function of this dialect, if it differs
+    pub fn translate_function(&self, name: &str) -> Option<&'static str> {
+        self.functions().iter()
+            .find(|(dialect_name, _)| dialect_name.eq_ignore_ascii_case(name))
+            .map(|(_, engine_name)| *engine_name)
+    }
+
+    /// Rename this dialect's functions to the engine's throughout a query's SELECT list,
+    /// WHERE clause, join conditions and ORDER BY
+    pub(crate) fn translate_functions(&self, statement: &mut Statement) {
+        if self.functions().is_empty() {
+            return;
+        }
+        let Statement::Query(query) = statement else {
+            return;
+        };
+        if let SetExpr::Select(select) = query.body.as_mut() {
+            for item in &mut select.projection {
+                if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+                    self.translate_expr(expr);
+                }
+            }
+            if let Some(selection) = &mut select.selection {
+                self.translate_expr(selection);
+            }
+            for join in select.from.iter_mut().flat_map(|table| table.joins.iter_mut()) {
+                if let JoinOperator::Inner(JoinConstraint::On(expr))
+                    | JoinOperator::LeftOuter(JoinConstraint::On(expr))
+                    | JoinOperator::RightOuter(JoinConstraint::On(expr))
+                    | JoinOperator::FullOuter(JoinConstraint::On(expr)) = &mut join.join_operator {
+                    self.translate_expr(expr);
+                }
+            }
+        }
+        for order in &mut query.order_by {
+            self.translate_expr(&mut order.expr);
+        }
+    }
+
+    fn translate_expr(&self, expr: &mut Expr) {
+        match expr {
+            Expr::Function(func) => {
+                if let Some(name) = self.translate_function(&func.name.to_string()) {
+                    func.name = ObjectName(vec![Ident::new(name)]);
+                }
+                for arg in &mut func.args {
+                    if let FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) | FunctionArg::Named { arg: FunctionArgExpr::Expr(expr), .. } = arg {
+                        self.translate_expr(expr);
+                    }
+                }
+            }
+            Expr::BinaryOp { left, right, .. } => {
+                self.translate_expr(left);
+                self.translate_expr(right);
+            }
+            Expr::UnaryOp { expr, .. } | Expr::Nested(expr) | Expr::IsNull(expr) | Expr::IsNotNull(expr)
+                | Expr::Cast { expr, .. } => self.translate_expr(expr),
+            Expr::InList { expr, list, .. } => {
+                self.translate_expr(expr);
+                list.iter_mut().for_each(|item| self.translate_expr(item));
+            }
+            Expr::Between { expr, low, high, .. } => {
+                self.translate_expr(expr);
+                self.translate_expr(low);
+                self.translate_expr(high);
+            }
+            Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } => {
+                self.translate_expr(expr);
+                self.translate_expr(pattern);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl FromStr for ClientDialect {
+    type Err = NirvError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ClientDialect::parse(name)
+    }
+}
+
+impl fmt::Display for ClientDialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ClientDialect::Auto => "auto",
+            ClientDialect::PostgreSQL => "PostgreSQL",
+            ClientDialect::MySQL => "MySQL",
+            ClientDialect::SQLite => "SQLite",
+            ClientDialect::SqlServer => "SQL Server",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_dialects() {
+        assert_eq!(ClientDialect::for_protocol(&ProtocolType::SqlServer), ClientDialect::SqlServer);
+        assert_eq!(ClientDialect::parse("MSSQL").unwrap(), ClientDialect::SqlServer);
+        assert!(ClientDialect::parse("oracle").is_err());
+
+        assert_eq!(ClientDialect::guess("SELECT `name` FROM t"), Some(ClientDialect::MySQL));
+        assert_eq!(ClientDialect::guess("SELECT TOP 5 [name] FROM t"), Some(ClientDialect::SqlServer));
+        assert_eq!(ClientDialect::guess("SELECT tags[1] FROM t WHERE id::text ILIKE '1%'"), Some(ClientDialect::PostgreSQL));
+        assert_eq!(ClientDialect::guess("SELECT name FROM t"), None);
+
+        assert_eq!(ClientDialect::SqlServer.translate_function("GETDATE"), Some("now"));
+        assert_eq!(ClientDialect::MySQL.translate_function("getdate"), None);
+        assert_eq!(ClientDialect::Auto.translate_function("sysdate"), None);
+    }
+}
@@ -0,0 +1,97 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::utils::{
+    types::{ColumnMetadata, DataSource, DataType, QueryResult, Row, Schema, SourceUri, Value},
+    error::NirvResult,
+};
+
+/// The object of a `DESCRIBE source('type.identifier')` statement, also written `DESC` and
+/// with the bare quoted source, or None for any other statement
+pub fn parse_describe(sql: &str) -> Option<NirvResult<DataSource>> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(
+        r#"(?is)^\s*DESC(?:RIBE)?\s+(?:source\s*\(\s*)?['"]([^'"]+)['"]\s*\)?\s*;?\s*$"#
+    ).unwrap());
+    let source = pattern.captures(sql)?.get(1)?.as_str();
+    Some(SourceUri::parse(source).map(|uri| uri.to_data_source(None)))
+}
+
+/// The result of `DESCRIBE`: one row per column with its type, description, tags and the
+/// type the source names it by
+pub fn describe_schema(schema: &Schema) -> QueryResult {
+    let column = |name: &str, data_type: DataType| ColumnMetadata { name: name.to_string(), data_type, nullable: true };
+    let columns = vec![
+        column("column_name", DataType::Text),
+        column("data_type", DataType::Text),
+        column("nullable", DataType::Boolean),
+        column("native_type", DataType::Text),
+        column("description", DataType::Text),
+        column("tags", DataType::Text),
+    ];
+
+    let text = |value: Option<&String>| value.map_or(Value::Null, |value| Value::Text(value.clone()));
+    let rows: Vec<Row> = schema.columns.iter().map(|column| {
+        let details = schema.column_details(&column.name);
+        let tags = details.map(|details| details.tags.join(", ")).filter(|tags| !tags.is_empty());
+        Row::new(vec![
+            Value::Text(column.name.clone()),
+            Value::Text(format!("{:?}", column.data_type)),
+            Value::Boolean(column.nullable),
+            text(details.and_then(|details| details.native_type.as_ref())),
+            text(details.and_then(|details| details.description.as_ref())),
+            text(tags.as_ref()),
+        ])
+    }).collect();
+
+    QueryResult {
+        columns,
+        affected_rows: Some(rows.len() as u64),
+        rows,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::ColumnDetails;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_describe() {
+        let source = parse_describe("DESCRIBE source('postgres.users');").unwrap().unwrap();
+        assert_eq!((source.object_type.as_str(), source.identifier.as_str()), ("postgres", "users"));
+        assert!(parse_describe("desc 'file.orders.csv'").is_some());
+        assert!(parse_describe("SELECT * FROM source('postgres.users')").is_none());
+    }
+
+    #[test]
+    fn test_describe_schema_with_details() {
+        let column = |name: &str| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true };
+        let schema = Schema {
+            name: "users".to_string(),
+            columns: vec![column("id"), column("email")],
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::from([("email".to_string(), ColumnDetails {
+                description: Some("Where receipts go".to_string()),
+                tags: vec!["pii".to_string()],
+                native_type: Some("varchar(320)".to_string()),
+            })]),
+            foreign_keys: Vec::new(),
+        };
+        let configured = HashMap::from([("email".to_string(), ColumnDetails {
+            tags: vec!["deprecated".to_string(), "PII".to_string()],
+            ..Default::default()
+        })]);
+
+        let result = describe_schema(&schema.with_column_details(&configured));
+        assert_eq!(result.rows[0].values[3..], [Value::Null, Value::Null, Value::Null]);
+        assert_eq!(result.rows[1].values[3..], [
+            Value::Text("varchar(320)".to_string()),
+            Value::Text("Where receipts go".to_string()),
+            Value::Text("pii, deprecated".to_string()),
+        ]);
+    }
+}
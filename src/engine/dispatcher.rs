@@ -1,16 +1,78 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use crate::engine::{
+    filter_rows, sample_rows, sort_rows, join_rows, deduplicate_rows, checksum_rows, computed_input_columns, evaluate_computed_columns, is_engine_function, ComputedColumn, query_planner::{missing_deduplicate_columns, missing_filter_columns, missing_sort_columns, predicate_source, resolve_group_by},
+    approx_aggregate::{aggregate_input_columns, aggregate_rows, aggregated_order, is_aggregation},
+    access_control::{AccessCatalog, Privilege},
+    scheduler::ConnectorQueue,
+};
 use crate::utils::{
-    types::{InternalQuery, ConnectorQuery, QueryResult, DataSource},
-    error::{NirvResult, DispatcherError, NirvError},
+    types::{InternalQuery, ConnectorQuery, QueryResult, QueryWarning, DataSource, Column, ColumnDetails, ColumnMetadata, DataType, Predicate, PredicateOperator, PredicateValue, Schema, QueryOperation, Pushdown, JoinType, COUNT_ALL},
+    error::{NirvResult, DispatcherError, NirvError, QueryParsingError},
+    identifier::{IdentifierCase, split_qualifier},
+    config::SourcePolicy,
+    time_zone::SessionTimeZone,
+    collation::Collation,
 };
-use crate::connectors::{Connector, ConnectorRegistry};
+use crate::connectors::{Connector, ConnectorRegistry, InlineConnector, InlineTable, INLINE_OBJECT_TYPE};
 
 /// Central routing component that manages data object type resolution and connector selection
 #[async_trait]
 pub trait Dispatcher: Send + Sync {
     /// Register a connector for a specific data object type
-    async fn register_connector(&mut self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()>;
+    async fn register_connector(&mut self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()> {
+        self.register_shared_connector(object_type, Arc::from(connector)).await
+    }
+    
+    /// Register a connector instance that may also serve other dispatchers or executors
+    async fn register_shared_connector(&mut self, object_type: &str, connector: Arc<dyn Connector>) -> NirvResult<()>;
+    
+    /// Replace the connector serving a data object type, for example with a reconnected
+    /// instance. Queries already running finish on the previous instance.
+    async fn replace_connector(&mut self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()>;
+    
+    /// Limit what queries may do with a data object type
+    fn set_source_policy(&mut self, object_type: &str, policy: SourcePolicy);
+    
+    /// Check the queries of users against the privileges their roles were granted in the
+    /// catalog; queries without a user are not checked
+    fn set_access_catalog(&mut self, catalog: AccessCatalog);
+    
+    /// Catalog of roles and grants queries are checked against, when access control is on
+    fn access_catalog(&self) -> Option<Arc<RwLock<AccessCatalog>>>;
+    
+    /// Read a data object type's DateTime values without an offset as times in the zone,
+    /// rather than UTC
+    fn set_source_time_zone(&mut self, object_type: &str, time_zone: SessionTimeZone);
+    
+    /// Set the collation a data object type's text is compared by when a query names none
+    fn set_source_collation(&mut self, object_type: &str, collation: Collation);
+    
+    /// Answer joins from the sides that succeeded when the other fails, without a
+    /// `PARTIAL_RESULTS` hint
+    fn set_partial_results(&mut self, enabled: bool);
+    
+    /// Limit how many queries wait for connectors that run all the queries they can, and
+    /// for how long
+    fn set_connector_queue(&mut self, queue: ConnectorQueue);
+    
+    /// Answer queries of a source from its fallback when it fails or is slower than the
+    /// fallback's soft timeout, warning that the result is degraded
+    fn set_source_fallback(&mut self, source: &DataSource, fallback: SourceFallback);
+    
+    /// Describe columns of a data object type's objects beyond what its connector reports,
+    /// by `object.column`
+    fn set_column_details(&mut self, object_type: &str, details: HashMap<String, ColumnDetails>);
+    
+    /// Schema of an object as its connector reports it, with the configured column details
+    /// laid over it
+    async fn describe_object(&self, object_type: &str, identifier: &str) -> NirvResult<Schema>;
+    
+    /// Check a query against the policies of the sources it reads and writes, and its user's
+    /// privileges on them, returning it with its row limit capped where a policy requires
+    fn apply_source_policies(&self, query: &InternalQuery) -> NirvResult<InternalQuery>;
     
     /// Route a query to appropriate connectors based on data object types
     async fn route_query(&self, query: &InternalQuery) -> NirvResult<Vec<ConnectorQuery>>;
@@ -24,8 +86,35 @@ pub trait Dispatcher: Send + Sync {
     /// Check if a data object type is registered
     fn is_type_registered(&self, object_type: &str) -> bool;
     
-    /// Get connector for a specific data object type
-    fn get_connector(&self, object_type: &str) -> Option<&dyn Connector>;
+    /// Get connector for a specific data object type. The handle can be held after the
+    /// dispatcher is released and used by many queries at once.
+    fn get_connector(&self, object_type: &str) -> Option<Arc<dyn Connector>>;
+    
+    /// Create the target object from the result's columns and load its rows into it,
+    /// returning the number of rows written
+    async fn write_result(&self, target: &DataSource, result: &QueryResult) -> NirvResult<u64> {
+        let connector = self.get_connector(&target.object_type).ok_or_else(|| NirvError::Dispatcher(
+            DispatcherError::UnregisteredObjectType(target.object_type.clone())
+        ))?;
+        let schema = Schema {
+            name: target.identifier.clone(),
+            columns: result.columns.clone(),
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        };
+        connector.create_table(&target.identifier, &schema).await?;
+        connector.insert_rows(&target.identifier, &result.columns, &result.rows).await
+    }
+}
+
+/// Source answering for another that failed or was too slow
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFallback {
+    pub source: DataSource,
+    /// How long the primary source may take; without one only its errors count
+    pub soft_timeout: Option<Duration>,
 }
 
 /// Data object type registry that maps types to their corresponding connectors
@@ -106,6 +195,24 @@ pub struct DefaultDispatcher {
     connector_registry: ConnectorRegistry,
     /// Registry for mapping data object types to connectors
     type_registry: DataObjectTypeRegistry,
+    /// Policies of data object types, checked before routing
+    policies: HashMap<String, SourcePolicy>,
+    /// Time zones of data object types whose DateTime values are not in UTC
+    time_zones: HashMap<String, SessionTimeZone>,
+    /// Collations of data object types whose text is not compared by its bytes
+    collations: HashMap<String, Collation>,
+    /// Whether every join answers from the sides that succeeded
+    partial_results: bool,
+    /// Roles and grants the queries of users are checked against
+    access_catalog: Option<Arc<RwLock<AccessCatalog>>>,
+    /// Queries waiting for connectors that run all the queries they can
+    connector_queue: ConnectorQueue,
+    /// Fallbacks of sources, by `type.identifier`
+    fallbacks: HashMap<String, SourceFallback>,
+    /// Configured column details of data object types, by `object.column`
+    column_details: HashMap<String, HashMap<String, ColumnDetails>>,
+    /// Serves the rows of derived tables when no inline connector is registered
+    inline_connector: InlineConnector,
 }
 
 impl DefaultDispatcher {
@@ -114,6 +221,15 @@ impl DefaultDispatcher {
         Self {
             connector_registry: ConnectorRegistry::new(),
             type_registry: DataObjectTypeRegistry::new(),
+            policies: HashMap::new(),
+            time_zones: HashMap::new(),
+            collations: HashMap::new(),
+            partial_results: false,
+            access_catalog: None,
+            connector_queue: ConnectorQueue::default(),
+            fallbacks: HashMap::new(),
+            column_details: HashMap::new(),
+            inline_connector: InlineConnector::new(),
         }
     }
     
@@ -122,14 +238,93 @@ impl DefaultDispatcher {
         Self {
             connector_registry,
             type_registry,
+            policies: HashMap::new(),
+            time_zones: HashMap::new(),
+            collations: HashMap::new(),
+            partial_results: false,
+            access_catalog: None,
+            connector_queue: ConnectorQueue::default(),
+            fallbacks: HashMap::new(),
+            column_details: HashMap::new(),
+            inline_connector: InlineConnector::new(),
         }
     }
     
+    /// Convert the DateTime values a source returned to UTC
+    fn normalize_time_zone(&self, object_type: &str, result: QueryResult) -> QueryResult {
+        self.time_zones.get(object_type).copied().unwrap_or_default().normalize_result(result)
+    }
+    
+    /// Collation text from a data object type is compared by when a query names none
+    fn source_collation(&self, object_type: &str) -> Collation {
+        self.collations.get(object_type).copied().unwrap_or_default()
+    }
+    
+    /// Fallback of the source a single-source query reads, if one is set
+    fn fallback_of(&self, queries: &[ConnectorQuery]) -> Option<&SourceFallback> {
+        let [connector_query] = queries else {
+            return None;
+        };
+        let query = &connector_query.query;
+        let source = query.sources.first().filter(|_| query.joins.is_empty())?;
+        self.fallbacks.get(&format!("{}.{}", source.object_type, source.identifier))
+    }
+    
+    /// Run a query against its source, and against the source's fallback when that fails
+    /// or outlasts the fallback's soft timeout
+    async fn execute_with_fallback(&self, queries: Vec<ConnectorQuery>, fallback: &SourceFallback) -> NirvResult<QueryResult> {
+        let mut query = queries[0].query.clone();
+        let primary = format!("{}.{}", query.sources[0].object_type, query.sources[0].identifier);
+        let attempt = self.execute_capped(queries);
+        let reason = match fallback.soft_timeout {
+            Some(soft_timeout) => match tokio::time::timeout(soft_timeout, attempt).await {
+                Ok(Ok(result)) => return Ok(result),
+                Ok(Err(e)) => format!("failed: {}", e),
+                Err(_) => format!("took longer than {} ms", soft_timeout.as_millis()),
+            },
+            None => match attempt.await {
+                Ok(result) => return Ok(result),
+                Err(e) => format!("failed: {}", e),
+            },
+        };
+        
+        // The fallback is read under the primary's alias and has no fallback of its own
+        query.sources[0] = DataSource { alias: query.sources[0].alias.take(), ..fallback.source.clone() };
+        let query = self.apply_source_policies(&query)?;
+        let mut result = self.execute_capped(self.route_query(&query).await?).await?;
+        let fallback_name = format!("{}.{}", fallback.source.object_type, fallback.source.identifier);
+        result.warnings.push(QueryWarning::degraded(primary, &fallback_name, reason));
+        Ok(result)
+    }
+    
+    /// Run queries, warning when they reached a row limit set by a source policy
+    async fn execute_capped(&self, queries: Vec<ConnectorQuery>) -> NirvResult<QueryResult> {
+        // A LIMIT set by a source policy rather than the query is reported when it was reached
+        let capped = queries.first()
+            .filter(|connector_query| !connector_query.query.is_count_only())
+            .and_then(|connector_query| Some((connector_query.query.capped_by.clone()?, connector_query.query.limit?)));
+        let mut result = self.execute_queries(queries).await?;
+        if let Some((source, limit)) = capped {
+            let warning = QueryWarning::truncated(format!("{}.{}", source.object_type, source.identifier), limit);
+            if result.rows.len() as u64 >= limit && !result.warnings.contains(&warning) {
+                result.warnings.push(warning);
+            }
+        }
+        Ok(result)
+    }
+    
     /// Extract data sources from a query
     fn extract_data_sources<'a>(&self, query: &'a InternalQuery) -> Vec<&'a DataSource> {
         query.sources.iter().collect()
     }
     
+    /// Whether a source is read by the built-in inline connector: a derived table, or rows
+    /// written with VALUES while no inline connector is registered
+    fn reads_inline(&self, query: &InternalQuery, source: &DataSource) -> bool {
+        query.subquery(source).is_some()
+            || (source.object_type == INLINE_OBJECT_TYPE && !self.type_registry.is_type_registered(INLINE_OBJECT_TYPE))
+    }
+    
     /// Validate that all data sources in a query are registered
     fn validate_data_sources(&self, sources: &[&DataSource]) -> NirvResult<()> {
         for source in sources {
@@ -149,19 +344,33 @@ impl DefaultDispatcher {
         let mut connector_queries = Vec::new();
         
         for source in sources {
-            let connector_name = self.type_registry
-                .get_connector_for_type(&source.object_type)
-                .ok_or_else(|| NirvError::Dispatcher(DispatcherError::UnregisteredObjectType(
-                    source.object_type.clone()
-                )))?;
-            
-            let connector = self.connector_registry
-                .get(connector_name)
-                .ok_or_else(|| NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
+            // A derived table is read inline once its query has run
+            let connector_type = if self.reads_inline(query, source) {
+                self.inline_connector.get_connector_type()
+            } else {
+                let connector_name = self.type_registry
+                    .get_connector_for_type(&source.object_type)
+                    .ok_or_else(|| NirvError::Dispatcher(DispatcherError::UnregisteredObjectType(
+                        source.object_type.clone()
+                    )))?;
+                self.connector_registry
+                    .get(connector_name)
+                    .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?
+                    .get_connector_type()
+            };
             
+            // Each side of a join carries the whole query, scoped to its own source
+            let mut query = query.clone();
+            if !query.joins.is_empty() {
+                query.sources = vec![(*source).clone()];
+            }
+            // Directives in the query's comments act as the hints they stand for
+            query.hints = query.hints.with_directives(&query.directives)?;
+            // MAX_ROWS caps the rows like a LIMIT, pushed down where a LIMIT would be
+            query.limit = query.hints.limit(query.limit);
             let connector_query = ConnectorQuery {
-                connector_type: connector.get_connector_type(),
-                query: query.clone(),
+                connector_type,
+                query,
                 connection_params: HashMap::new(),
             };
             
@@ -170,6 +379,459 @@ impl DefaultDispatcher {
         
         Ok(connector_queries)
     }
+    
+    /// Run a single source query, or both sides of a join
+    async fn execute_queries(&self, queries: Vec<ConnectorQuery>) -> NirvResult<QueryResult> {
+        if queries.is_empty() {
+            return Ok(QueryResult::new());
+        }
+        
+        // A derived table is answered by its own query first, then read as inline rows
+        let mut queries = queries;
+        for connector_query in &mut queries {
+            self.materialize_subquery(connector_query).await?;
+        }
+        
+        // Besides single connector queries, only the two sides of a join are handled
+        if queries.len() == 2 && queries[0].query.joins.len() == 1 {
+            return self.execute_join(queries).await;
+        }
+        if queries.len() > 1 {
+            return Err(NirvError::Dispatcher(DispatcherError::CrossConnectorJoinUnsupported));
+        }
+        
+        let connector_query = &queries[0];
+        let object_type = &connector_query.query.sources[0].object_type;
+        let connector = match self.type_registry.get_connector_for_type(object_type) {
+            Some(connector_name) => self.connector_registry
+                .get(connector_name)
+                .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?,
+            None if object_type == INLINE_OBJECT_TYPE => &self.inline_connector,
+            None => return Err(NirvError::Dispatcher(DispatcherError::UnregisteredObjectType(object_type.clone()))),
+        };
+        // A connector running all the queries it can takes more only as slots come free
+        let max_concurrent = self.type_registry.get_connector_for_type(object_type)
+            .and_then(|connector_name| self.type_registry.get_connector_capabilities(connector_name))
+            .and_then(|capabilities| capabilities.max_concurrent_queries);
+        let (_slot, queued) = self.connector_queue.acquire(object_type, max_concurrent).await?;
+        
+        // Predicates the connector cannot evaluate are applied to the rows it returns, as are
+        // text comparisons under a collation the query names or other than the source's bytes,
+        // and every predicate when a hint keeps them from the source
+        let mut connector_query = connector_query.clone();
+        let object_type = connector_query.query.sources[0].object_type.clone();
+        let collation = connector_query.query.collation.unwrap_or_else(|| self.source_collation(&object_type));
+        let collated = connector_query.query.collation.is_some() || collation != Collation::Binary;
+        let hints = connector_query.query.hints.clone();
+        let (pushed, local): (Vec<Predicate>, Vec<Predicate>) = connector_query.query.predicates
+            .drain(..)
+            .partition(|predicate| hints.pushes_down(Pushdown::Predicates)
+                && connector.supports_predicate(predicate)
+                && !(collated && compares_text(predicate)));
+        connector_query.query.predicates = pushed;
+        let deduplicate = connector_query.query.deduplicate.take();
+        let local_sample = match &connector_query.query.sample {
+            Some(sample) if !connector.supports_sample(sample) => connector_query.query.sample.take(),
+            _ => None,
+        };
+        // A lone COUNT(*) counts whole rows, in no particular order
+        let count = connector_query.query.is_count_only().then(|| {
+            connector_query.query.ordering = None;
+            let all = Column { name: "*".to_string(), alias: None, source: None };
+            std::mem::replace(&mut connector_query.query.projections, vec![all]).remove(0)
+        });
+        // So is the ordering under such a collation or a hint, unless the rows are aggregated first
+        let sort = ((collated || !hints.pushes_down(Pushdown::OrderBy)) && !is_aggregation(&connector_query.query))
+            .then(|| connector_query.query.ordering.take())
+            .flatten()
+            .map(|order_by| (order_by, connector_query.query.projections.clone()));
+        // Aggregates and groups are computed here from the columns they read, then sorted
+        let aggregates = match is_aggregation(&connector_query.query) {
+            true => {
+                let group_by = resolve_group_by(&connector_query.query)?;
+                let ordering = connector_query.query.ordering.take();
+                let inputs = aggregate_input_columns(&connector_query.query.projections, &group_by);
+                Some((std::mem::replace(&mut connector_query.query.projections, inputs), group_by, ordering))
+            }
+            false => None,
+        };
+        // So are regular expression and hash functions, including those grouped by, and checksums
+        let checksum = std::mem::take(&mut connector_query.query.checksum);
+        let computed = connector_query.query.projections.iter()
+            .any(|col| is_engine_function(&col.name))
+            .then(|| {
+                let inputs = computed_input_columns(&connector_query.query.projections);
+                std::mem::replace(&mut connector_query.query.projections, inputs)
+            });
+        let source = format!("{}.{}", object_type, connector_query.query.sources[0].identifier);
+        let pushed_count = connector_query.query.predicates.len();
+        let local_limit = connector_query.query.limit.is_some() && !hints.pushes_down(Pushdown::Limit);
+        // Rows left as the source returns them are counted by the source, where it can
+        if let (Some(projection), true) = (&count, local.is_empty() && deduplicate.is_none() && local_sample.is_none()) {
+            let mut count_query = connector_query.clone();
+            count_query.query.limit = None;
+            let started = Instant::now();
+            if let Some(rows) = connector.count_rows(count_query).await? {
+                let mut result = QueryResult::of_count(rows, projection);
+                result.execution_time = started.elapsed();
+                result.record_scanned(&source, 0, 0, pushed_count, 0);
+                result.record_queued(&source, queued);
+                result.record_node(format!("Count {}", source), started.elapsed());
+                if let Some(limit) = connector_query.query.limit {
+                    result.rows.truncate(limit as usize);
+                }
+                return Ok(result);
+            }
+        }
+        if count.is_none() && local.is_empty() && deduplicate.is_none() && local_sample.is_none() && aggregates.is_none() && computed.is_none() && sort.is_none() && !checksum && !local_limit {
+            let started = Instant::now();
+            let mut result = self.normalize_time_zone(&object_type, connector.execute_query(connector_query).await?);
+            result.record_scan(&source, pushed_count, 0);
+            result.record_queued(&source, queued);
+            result.record_node(format!("Scan {}", source), started.elapsed());
+            return Ok(result);
+        }
+        
+        // The limit applies to the rows left after local filtering, deduplication, sorting,
+        // sampling and aggregation
+        let limit = connector_query.query.limit.take();
+        
+        // Columns read only by the local filter, deduplication or sort are fetched, then dropped again
+        let mut fetched_only = missing_filter_columns(&connector_query.query.projections, &local);
+        let mut also_fetched = Vec::new();
+        if let Some(deduplicate) = &deduplicate {
+            also_fetched.extend(missing_deduplicate_columns(&connector_query.query.projections, deduplicate));
+        }
+        if let Some((order_by, _)) = &sort {
+            also_fetched.extend(missing_sort_columns(&connector_query.query.projections, order_by));
+        }
+        for column in also_fetched {
+            if !fetched_only.contains(&column) {
+                fetched_only.push(column);
+            }
+        }
+        connector_query.query.projections.extend(fetched_only.iter()
+            .map(|name| Column { name: name.clone(), alias: None, source: None }));
+        
+        let started = Instant::now();
+        let mut result = self.normalize_time_zone(&object_type, connector.execute_query(connector_query).await?);
+        result.record_scan(&source, pushed_count, local.len());
+        result.record_queued(&source, queued);
+        result.record_node(format!("Scan {}", source), started.elapsed());
+        
+        let started = Instant::now();
+        let mut result = filter_rows(result, &local, IdentifierCase::default(), collation)?;
+        if !local.is_empty() {
+            result.record_node("Filter (local)", started.elapsed());
+        }
+        if let Some(deduplicate) = &deduplicate {
+            let started = Instant::now();
+            result = deduplicate_rows(result, deduplicate, IdentifierCase::default(), collation)?;
+            result.record_node("Deduplicate", started.elapsed());
+        }
+        if let Some(projections) = &computed {
+            result = evaluate_computed_columns(result, projections, IdentifierCase::default())?;
+        }
+        if let Some((mut order_by, projections)) = sort {
+            // A selected column the connector returned under its alias is sorted by that name
+            let case = IdentifierCase::default();
+            for order in &mut order_by.columns {
+                if case.resolve(&order.column, result.columns.iter().map(|c| c.name.as_str())).is_none() {
+                    if let Some(alias) = projections.iter().find(|col| case.matches(&order.column, &col.name)).and_then(|col| col.alias.clone()) {
+                        order.column = alias;
+                    }
+                }
+            }
+            let started = Instant::now();
+            result = sort_rows(result, &order_by, case, collation)?;
+            result.record_node(format!("Sort (collate {})", collation), started.elapsed());
+        }
+        for column in fetched_only {
+            if let Some(index) = IdentifierCase::default().resolve(&column, result.columns.iter().map(|c| c.name.as_str())) {
+                result.columns.remove(index);
+                for row in &mut result.rows {
+                    if index < row.values.len() {
+                        row.values.remove(index);
+                    }
+                }
+            }
+        }
+        if let Some(sample) = &local_sample {
+            let started = Instant::now();
+            result = sample_rows(result, sample);
+            result.record_node(sample.to_string(), started.elapsed());
+        }
+        if let Some(projection) = &count {
+            let started = Instant::now();
+            result = result.counted(projection);
+            result.record_node("Count", started.elapsed());
+        }
+        if let Some((projections, group_by, ordering)) = &aggregates {
+            let started = Instant::now();
+            result = aggregate_rows(result, projections, group_by, IdentifierCase::default())?;
+            result.record_node("Aggregate", started.elapsed());
+            // A single row of aggregates needs no sorting
+            if let (Some(order_by), false) = (ordering, group_by.is_empty()) {
+                let started = Instant::now();
+                let order_by = aggregated_order(order_by, projections, IdentifierCase::default());
+                result = sort_rows(result, &order_by, IdentifierCase::default(), collation)?;
+                result.record_node(format!("Sort (collate {})", collation), started.elapsed());
+            }
+        }
+        if checksum {
+            let started = Instant::now();
+            result = checksum_rows(result);
+            result.record_node("Checksum", started.elapsed());
+        }
+        if let Some(limit) = limit {
+            result.rows.truncate(limit as usize);
+        }
+        Ok(result)
+    }
+    
+    /// Replace a derived table source with the rows its query returns
+    async fn materialize_subquery(&self, connector_query: &mut ConnectorQuery) -> NirvResult<()> {
+        let Some(source) = connector_query.query.sources.first() else {
+            return Ok(());
+        };
+        let Some(subquery) = connector_query.query.subquery(source) else {
+            return Ok(());
+        };
+        let result = self.execute_distributed_query(self.route_query(subquery).await?).await?;
+        let inline = DataSource {
+            object_type: INLINE_OBJECT_TYPE.to_string(),
+            identifier: InlineTable::from_result(&result).encode(),
+            alias: source.alias.clone(),
+        };
+        connector_query.query.sources[0] = inline;
+        connector_query.connector_type = self.inline_connector.get_connector_type();
+        Ok(())
+    }
+    
+    /// Scan both sides of a join, join their rows, then filter, deduplicate, sample,
+    /// aggregate or sort and project, and limit the joined rows
+    async fn execute_join(&self, queries: Vec<ConnectorQuery>) -> NirvResult<QueryResult> {
+        let query = queries[0].query.clone();
+        let join = &query.joins[0];
+        let case = IdentifierCase::default();
+        // Keys from sources collating differently are compared by the looser collation
+        let collation = query.collation.unwrap_or_else(|| queries.iter()
+            .map(|connector_query| self.source_collation(&connector_query.query.sources[0].object_type))
+            .fold(Collation::default(), Collation::looser));
+        
+        // A predicate on one source is pushed to that side's scan when the join keeps every
+        // row of the side, so its rows are filtered before the join; the rest filter the joined rows
+        let sources: Vec<DataSource> = queries.iter().map(|connector_query| connector_query.query.sources[0].clone()).collect();
+        let preserved = [
+            matches!(join.join_type, JoinType::Inner | JoinType::Left),
+            matches!(join.join_type, JoinType::Inner | JoinType::Right),
+        ];
+        let mut side_predicates: [Vec<Predicate>; 2] = Default::default();
+        let mut joined_predicates = Vec::new();
+        for predicate in &query.predicates {
+            let side = predicate_source(&sources, predicate)?
+                .filter(|side| preserved[*side] && ComputedColumn::parse(&predicate.column).is_none());
+            match (side, split_qualifier(&predicate.column)) {
+                (Some(side), Some((_, name))) => side_predicates[side].push(Predicate { column: name.to_string(), ..predicate.clone() }),
+                _ => joined_predicates.push(predicate.clone()),
+            }
+        }
+        
+        let partial = self.partial_results || query.hints.partial_results;
+        let mut sides = Vec::new();
+        let mut failures = Vec::new();
+        for ((connector_query, left), predicates) in queries.into_iter().zip([true, false]).zip(side_predicates) {
+            let source = connector_query.query.sources[0].clone();
+            let mut scan = InternalQuery::new(QueryOperation::Select);
+            scan.limit = self.policies.get(&source.object_type).and_then(|policy| policy.max_limit);
+            scan.capped_by = scan.limit.map(|_| source.clone());
+            scan.hints.cache_ttl = query.hints.cache_ttl;
+            scan.hints.no_pushdown = query.hints.no_pushdown.clone();
+            scan.predicates = predicates;
+            scan.sources.push(source.clone());
+            match self.execute_distributed_query(vec![ConnectorQuery { query: scan, ..connector_query }]).await {
+                Ok(side) => sides.push(side),
+                // A failed side joins as if it had no rows
+                Err(error) if partial => {
+                    sides.push(self.failed_side(&source, &query, left).await);
+                    failures.push((source, error));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        // With no side left to answer from, the query fails as it would without partial results
+        if failures.len() == sides.len() {
+            return Err(failures.swap_remove(0).1);
+        }
+        let (Some(right), Some(left)) = (sides.pop(), sides.pop()) else {
+            return Err(NirvError::Internal("A join needs two sides".to_string()));
+        };
+        
+        let started = Instant::now();
+        let label = match &join.fuzzy_condition {
+            Some(fuzzy) => format!("{:?} Join {}", join.join_type, fuzzy),
+            None => format!("{:?} Join", join.join_type),
+        };
+        let mut result = join_rows(left, right, join, case, collation)?;
+        result.record_node(label, started.elapsed());
+        for predicate in &joined_predicates {
+            check_unambiguous(&predicate.column, &result.columns, case)?;
+        }
+        result = filter_rows(result, &joined_predicates, case, collation)?;
+        if let Some(deduplicate) = &query.deduplicate {
+            result = deduplicate_rows(result, deduplicate, case, collation)?;
+        }
+        if let Some(sample) = &query.sample {
+            result = sample_rows(result, sample);
+        }
+        if is_aggregation(&query) {
+            let group_by = resolve_group_by(&query)?;
+            result = aggregate_rows(result, &query.projections, &group_by, case)?;
+            if let (Some(order_by), false) = (&query.ordering, group_by.is_empty()) {
+                result = sort_rows(result, &aggregated_order(order_by, &query.projections, case), case, collation)?;
+            }
+        } else if let Some(projection) = query.projections.iter().find(|col| col.name == COUNT_ALL) {
+            result = result.counted(projection);
+        } else {
+            // Columns are read as qualified by the query, then named as selected
+            let qualified: Vec<Column> = query.projections.iter()
+                .map(|col| match &col.source {
+                    Some(source) if col.name != "*" => Column { name: format!("{}.{}", source, col.name), alias: None, source: None },
+                    _ => Column { alias: None, ..col.clone() },
+                })
+                .collect();
+            if let Some(order_by) = &query.ordering {
+                // Sorting by an output name sorts by the column it selects
+                let mut order_by = order_by.clone();
+                for order in &mut order_by.columns {
+                    let selected = query.projections.iter().position(|col| {
+                        case.matches(&order.column, col.alias.as_deref().unwrap_or(&col.name)) && col.name != "*"
+                    });
+                    if let Some(index) = selected {
+                        order.column = qualified[index].name.clone();
+                    }
+                }
+                result = sort_rows(result, &order_by, case, collation)?;
+            }
+            let width = result.columns.len();
+            result = evaluate_computed_columns(result, &qualified, case)?;
+            let mut index = 0;
+            for col in &query.projections {
+                if col.name == "*" {
+                    index += width;
+                    continue;
+                }
+                if let Some(column) = result.columns.get_mut(index) {
+                    column.name = col.alias.clone().unwrap_or_else(|| col.name.clone());
+                }
+                index += 1;
+            }
+        }
+        if let Some(limit) = query.limit {
+            result.rows.truncate(limit as usize);
+        }
+        result.warnings.extend(failures.iter().map(|(source, error)| {
+            QueryWarning::source_failed(format!("{}.{}", source.object_type, source.identifier), error)
+        }));
+        Ok(result)
+    }
+    
+    /// An empty stand-in for a join side whose source failed, with the object's columns when
+    /// its connector can still describe it, otherwise those the query reads from the side
+    async fn failed_side(&self, source: &DataSource, query: &InternalQuery, left: bool) -> QueryResult {
+        let schema = match self.get_connector(&source.object_type) {
+            Some(connector) => connector.get_schema(&source.identifier).await.ok(),
+            None => None,
+        };
+        let columns = match schema {
+            Some(schema) => schema.columns,
+            None => referenced_columns(query, left).into_iter()
+                .map(|name| ColumnMetadata { name, data_type: DataType::Text, nullable: true })
+                .collect(),
+        };
+        QueryResult { columns, ..Default::default() }
+    }
+}
+
+/// Columns a join query reads from its left or right side, without the side's qualifier
+fn referenced_columns(query: &InternalQuery, left: bool) -> Vec<String> {
+    let join = &query.joins[0];
+    let side = if left { &join.left_source } else { &join.right_source };
+    let keys = join.on_condition.iter()
+        .map(|condition| if left { &condition.left_column } else { &condition.right_column })
+        .chain(join.fuzzy_condition.iter().map(|fuzzy| if left { &fuzzy.left_column } else { &fuzzy.right_column }))
+        .map(|column| split_qualifier(column).map_or(column.as_str(), |(_, name)| name));
+    let selected = query.projections.iter()
+        .filter(|col| col.name != "*" && col.source.as_ref().is_some_and(|source| source.eq_ignore_ascii_case(side)))
+        .map(|col| col.name.as_str());
+    let qualified = query.predicates.iter().map(|predicate| predicate.column.as_str())
+        .chain(query.ordering.iter().flat_map(|order_by| order_by.columns.iter().map(|order| order.column.as_str())))
+        .filter_map(split_qualifier)
+        .filter(|(qualifier, _)| qualifier.eq_ignore_ascii_case(side))
+        .map(|(_, name)| name);
+    
+    let mut columns: Vec<String> = Vec::new();
+    for name in keys.chain(selected).chain(qualified) {
+        if !columns.iter().any(|column| column == name) {
+            columns.push(name.to_string());
+        }
+    }
+    columns
+}
+
+/// Reject an unqualified column that columns of both sides of a join carry, naming them
+fn check_unambiguous(column: &str, columns: &[ColumnMetadata], case: IdentifierCase) -> NirvResult<()> {
+    let column = ComputedColumn::parse(column).map_or_else(|| column.to_string(), |computed| computed.column().to_string());
+    if split_qualifier(&column).is_some() || columns.iter().any(|col| case.matches(&column, &col.name)) {
+        return Ok(());
+    }
+    let candidates: Vec<&str> = columns.iter()
+        .map(|col| col.name.as_str())
+        .filter(|name| split_qualifier(name).is_some_and(|(_, name)| case.matches(&column, name)))
+        .collect();
+    match candidates.as_slice() {
+        [_, _, ..] => Err(QueryParsingError::AmbiguousColumn(format!(
+            "'{}' could be any of {}; qualify it with its source's alias", column, candidates.join(", ")
+        )).into()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether a table identifier matches any of the glob patterns, ignoring case
+fn matches_table(patterns: &[String], table: &str) -> bool {
+    let options = glob::MatchOptions { case_sensitive: false, ..glob::MatchOptions::new() };
+    patterns.iter()
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .any(|pattern| pattern.matches_with(table, options))
+}
+
+/// Whether a predicate compares a column with text, which a collation changes the outcome of
+fn compares_text(predicate: &Predicate) -> bool {
+    let text = |value: &PredicateValue| matches!(value, PredicateValue::String(_));
+    match &predicate.value {
+        _ if matches!(predicate.operator, PredicateOperator::Regexp | PredicateOperator::IsNull | PredicateOperator::IsNotNull) => false,
+        PredicateValue::List(values) => values.iter().any(text),
+        value => text(value),
+    }
+}
+
+fn policy_violation(message: String) -> NirvError {
+    NirvError::Dispatcher(DispatcherError::PolicyViolation(message))
+}
+
+/// Check that a policy lets a query reach a table, either to read it or to write to it
+fn check_table_access(policy: &SourcePolicy, source: &DataSource, write: bool) -> NirvResult<()> {
+    if write && policy.read_only {
+        return Err(policy_violation(format!("'{}' is read-only", source.object_type)));
+    }
+    if matches_table(&policy.denied_tables, &source.identifier)
+        || (!policy.allowed_tables.is_empty() && !matches_table(&policy.allowed_tables, &source.identifier))
+    {
+        return Err(policy_violation(format!(
+            "table '{}' of '{}' is not allowed", source.identifier, source.object_type
+        )));
+    }
+    Ok(())
 }
 
 impl Default for DefaultDispatcher {
@@ -180,7 +842,7 @@ impl Default for DefaultDispatcher {
 
 #[async_trait]
 impl Dispatcher for DefaultDispatcher {
-    async fn register_connector(&mut self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()> {
+    async fn register_shared_connector(&mut self, object_type: &str, connector: Arc<dyn Connector>) -> NirvResult<()> {
         let connector_name = format!("{}_{}", object_type, self.connector_registry.len());
         let capabilities = ConnectorCapabilities {
             supports_joins: connector.get_capabilities().supports_joins,
@@ -190,7 +852,7 @@ impl Dispatcher for DefaultDispatcher {
         };
         
         // Register the connector in the connector registry
-        self.connector_registry.register(connector_name.clone(), connector)?;
+        self.connector_registry.register_shared(connector_name.clone(), connector)?;
         
         // Register the data object type mapping
         self.type_registry.register_type(object_type, &connector_name, capabilities)?;
@@ -198,7 +860,106 @@ impl Dispatcher for DefaultDispatcher {
         Ok(())
     }
     
+    async fn replace_connector(&mut self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()> {
+        let connector_name = self.type_registry.get_connector_for_type(object_type)
+            .ok_or_else(|| NirvError::Dispatcher(DispatcherError::UnregisteredObjectType(object_type.to_string())))?;
+        self.connector_registry.replace(connector_name, Arc::from(connector))?;
+        Ok(())
+    }
+    
+    fn set_source_policy(&mut self, object_type: &str, policy: SourcePolicy) {
+        self.policies.insert(object_type.to_string(), policy);
+    }
+    
+    fn set_access_catalog(&mut self, catalog: AccessCatalog) {
+        self.access_catalog = Some(Arc::new(RwLock::new(catalog)));
+    }
+    
+    fn access_catalog(&self) -> Option<Arc<RwLock<AccessCatalog>>> {
+        self.access_catalog.clone()
+    }
+    
+    fn set_source_time_zone(&mut self, object_type: &str, time_zone: SessionTimeZone) {
+        self.time_zones.insert(object_type.to_string(), time_zone);
+    }
+    
+    fn set_partial_results(&mut self, enabled: bool) {
+        self.partial_results = enabled;
+    }
+    
+    fn set_connector_queue(&mut self, queue: ConnectorQueue) {
+        self.connector_queue = queue;
+    }
+    
+    fn set_source_fallback(&mut self, source: &DataSource, fallback: SourceFallback) {
+        self.fallbacks.insert(format!("{}.{}", source.object_type, source.identifier), fallback);
+    }
+    
+    fn set_column_details(&mut self, object_type: &str, details: HashMap<String, ColumnDetails>) {
+        self.column_details.insert(object_type.to_string(), details);
+    }
+    
+    async fn describe_object(&self, object_type: &str, identifier: &str) -> NirvResult<Schema> {
+        let connector = self.get_connector(object_type).ok_or_else(|| NirvError::Dispatcher(
+            DispatcherError::UnregisteredObjectType(object_type.to_string())
+        ))?;
+        let schema = connector.get_schema(identifier).await?;
+        let Some(details) = self.column_details.get(object_type) else {
+            return Ok(schema);
+        };
+        let configured: HashMap<String, ColumnDetails> = details.iter()
+            .filter_map(|(key, details)| match split_qualifier(key) {
+                Some((object, column)) if object == identifier => Some((column.to_string(), details.clone())),
+                _ => None,
+            })
+            .collect();
+        Ok(schema.with_column_details(&configured))
+    }
+    
+    fn set_source_collation(&mut self, object_type: &str, collation: Collation) {
+        self.collations.insert(object_type.to_string(), collation);
+    }
+    
+    fn apply_source_policies(&self, query: &InternalQuery) -> NirvResult<InternalQuery> {
+        let mut query = query.clone();
+        let write = query.operation != QueryOperation::Select;
+        for source in &query.sources {
+            if let Some(policy) = self.policies.get(&source.object_type) {
+                check_table_access(policy, source, write)?;
+            }
+        }
+        if let Some(target) = &query.target {
+            if let Some(policy) = self.policies.get(&target.object_type) {
+                check_table_access(policy, target, true)?;
+            }
+        }
+        if let (Some(user), Some(catalog)) = (&query.user, &self.access_catalog) {
+            let catalog = catalog.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let privilege = Privilege::of(&query.operation);
+            for source in query.all_sources() {
+                catalog.check(user, privilege, source)?;
+            }
+            if let Some(target) = &query.target {
+                catalog.check(user, Privilege::Insert, target)?;
+            }
+        }
+        
+        let max_limit = query.sources.iter()
+            .filter_map(|source| Some((self.policies.get(&source.object_type)?.max_limit?, source)))
+            .min_by_key(|(max_limit, _)| *max_limit);
+        if let Some((max_limit, source)) = max_limit {
+            if query.limit.is_none_or(|limit| limit > max_limit) {
+                query.capped_by = Some(source.clone());
+                query.limit = Some(max_limit);
+            }
+        }
+        Ok(query)
+    }
+    
     async fn route_query(&self, query: &InternalQuery) -> NirvResult<Vec<ConnectorQuery>> {
+        // Nothing reaches a connector that its source's policy does not allow
+        let query = &self.apply_source_policies(query)?;
+        
         // Extract data sources from the query
         let sources = self.extract_data_sources(query);
         
@@ -208,40 +969,42 @@ impl Dispatcher for DefaultDispatcher {
             )));
         }
         
-        // Validate that all data sources are registered
-        self.validate_data_sources(&sources)?;
+        // Derived tables are routed by their own queries, so the sources they read are
+        // registered and allowed as well
+        for subquery in &query.subqueries {
+            self.route_query(subquery).await?;
+        }
         
-        // For MVP, we only support single-source queries
-        if sources.len() > 1 {
+        // Validate that all data sources, and the object written to, are registered
+        let registered: Vec<&DataSource> = sources.iter().copied()
+            .filter(|source| !self.reads_inline(query, source))
+            .collect();
+        self.validate_data_sources(&registered)?;
+        if let Some(target) = &query.target {
+            self.validate_data_sources(&[target])?;
+        }
+        
+        // Besides single-source queries, only a join of two sources is supported
+        if sources.len() > 1 && (query.joins.len() != 1 || sources.len() != 2) {
             return Err(NirvError::Dispatcher(DispatcherError::CrossConnectorJoinUnsupported));
         }
+        // Each qualified predicate must name one of the joined sources
+        if sources.len() > 1 {
+            let sources: Vec<DataSource> = sources.iter().map(|source| (*source).clone()).collect();
+            for predicate in &query.predicates {
+                predicate_source(&sources, predicate)?;
+            }
+        }
         
         // Create connector queries for routing
         self.create_connector_queries(query, &sources)
     }
     
     async fn execute_distributed_query(&self, queries: Vec<ConnectorQuery>) -> NirvResult<QueryResult> {
-        if queries.is_empty() {
-            return Ok(QueryResult::new());
-        }
-        
-        // For MVP, we only handle single connector queries
-        if queries.len() > 1 {
-            return Err(NirvError::Dispatcher(DispatcherError::CrossConnectorJoinUnsupported));
+        match self.fallback_of(&queries) {
+            Some(fallback) => self.execute_with_fallback(queries, fallback).await,
+            None => self.execute_capped(queries).await,
         }
-        
-        let connector_query = &queries[0];
-        let connector_name = self.type_registry
-            .get_connector_for_type(&connector_query.query.sources[0].object_type)
-            .ok_or_else(|| NirvError::Dispatcher(DispatcherError::UnregisteredObjectType(
-                connector_query.query.sources[0].object_type.clone()
-            )))?;
-        
-        let connector = self.connector_registry
-            .get(connector_name)
-            .ok_or_else(|| NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
-        
-        connector.execute_query(connector_query.clone()).await
     }
     
     fn list_available_types(&self) -> Vec<String> {
@@ -252,16 +1015,17 @@ impl Dispatcher for DefaultDispatcher {
         self.type_registry.is_type_registered(object_type)
     }
     
-    fn get_connector(&self, object_type: &str) -> Option<&dyn Connector> {
+    fn get_connector(&self, object_type: &str) -> Option<Arc<dyn Connector>> {
         let connector_name = self.type_registry.get_connector_for_type(object_type)?;
-        self.connector_registry.get(connector_name)
+        self.connector_registry.get_shared(connector_name)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::types::{QueryOperation, ConnectorType, Schema, ColumnMetadata, DataType};
+    use crate::utils::types::{QueryOperation, ConnectorType, Schema, ColumnMetadata, DataType, WarningKind};
+    use crate::utils::error::ConnectorError;
     use crate::connectors::{ConnectorInitConfig, ConnectorCapabilities as ConnectorTraitCapabilities};
     use std::time::Duration;
 
@@ -270,6 +1034,8 @@ mod tests {
         connector_type: ConnectorType,
         connected: bool,
         capabilities: ConnectorTraitCapabilities,
+        delay: Duration,
+        failing: bool,
     }
 
     impl TestConnector {
@@ -278,9 +1044,21 @@ mod tests {
                 connector_type,
                 connected: false,
                 capabilities: ConnectorTraitCapabilities::default(),
+                delay: Duration::ZERO,
+                failing: false,
             }
         }
         
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+        
+        fn failing(mut self) -> Self {
+            self.failing = true;
+            self
+        }
+        
         #[allow(dead_code)]
         fn with_capabilities(mut self, capabilities: ConnectorTraitCapabilities) -> Self {
             self.capabilities = capabilities;
@@ -296,6 +1074,10 @@ mod tests {
         }
 
         async fn execute_query(&self, _query: ConnectorQuery) -> NirvResult<QueryResult> {
+            tokio::time::sleep(self.delay).await;
+            if self.failing {
+                return Err(ConnectorError::QueryExecutionFailed("unavailable".to_string()).into());
+            }
             let mut result = QueryResult::new();
             result.execution_time = Duration::from_millis(10);
             Ok(result)
@@ -318,6 +1100,8 @@ mod tests {
                 ],
                 primary_key: Some(vec!["id".to_string()]),
                 indexes: vec![],
+                column_details: HashMap::new(),
+                foreign_keys: Vec::new(),
             })
         }
 
@@ -462,6 +1246,64 @@ mod tests {
         assert_eq!(dispatcher.list_available_types(), vec!["mock".to_string()]);
     }
 
+    #[tokio::test]
+    async fn test_saturated_connectors_queue_queries() {
+        // The test connector runs one query at a time
+        let mut dispatcher = DefaultDispatcher::new();
+        let connector = TestConnector::new(ConnectorType::Mock).with_delay(Duration::from_millis(50));
+        dispatcher.register_connector("mock", Box::new(connector)).await.unwrap();
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "mock".to_string(), identifier: "users".to_string(), alias: None });
+        let run = || async { dispatcher.execute_distributed_query(dispatcher.route_query(&query).await?).await };
+        
+        let (first, second) = tokio::join!(run(), run());
+        let queued: Vec<Duration> = [first, second].into_iter()
+            .map(|result| result.unwrap().stats.unwrap().queue_time())
+            .collect();
+        assert!(queued.contains(&Duration::ZERO));
+        assert!(queued.iter().any(|waited| *waited >= Duration::from_millis(30)), "{:?}", queued);
+        
+        // Without room in the queue the second query fails at once
+        dispatcher.set_connector_queue(ConnectorQueue::new(0, Duration::from_secs(1)));
+        let run = || async { dispatcher.execute_distributed_query(dispatcher.route_query(&query).await?).await };
+        let (first, second) = tokio::join!(run(), run());
+        let error = first.and(second).unwrap_err();
+        assert!(matches!(error, NirvError::Dispatcher(DispatcherError::ConnectorSaturated(_))), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_answers_for_failed_or_slow_source() {
+        let mut dispatcher = DefaultDispatcher::new();
+        let slow = TestConnector::new(ConnectorType::Mock).with_delay(Duration::from_millis(500));
+        dispatcher.register_connector("slow", Box::new(slow)).await.unwrap();
+        dispatcher.register_connector("broken", Box::new(TestConnector::new(ConnectorType::Mock).failing())).await.unwrap();
+        dispatcher.register_connector("snapshot", Box::new(TestConnector::new(ConnectorType::File))).await.unwrap();
+        dispatcher.register_connector("other", Box::new(TestConnector::new(ConnectorType::Mock).failing())).await.unwrap();
+        let source = |object_type: &str| DataSource { object_type: object_type.to_string(), identifier: "users".to_string(), alias: None };
+        let fallback = SourceFallback { source: source("snapshot"), soft_timeout: Some(Duration::from_millis(20)) };
+        dispatcher.set_source_fallback(&source("slow"), fallback.clone());
+        dispatcher.set_source_fallback(&source("broken"), SourceFallback { soft_timeout: None, ..fallback });
+        let run = |object_type: &str| {
+            let mut query = InternalQuery::new(QueryOperation::Select);
+            query.sources.push(source(object_type));
+            let dispatcher = &dispatcher;
+            async move { dispatcher.execute_distributed_query(dispatcher.route_query(&query).await?).await }
+        };
+        
+        let started = Instant::now();
+        let result = run("slow").await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert_eq!(result.warnings, vec![QueryWarning::degraded("slow.users", "snapshot.users", "took longer than 20 ms")]);
+        
+        let result = run("broken").await.unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].kind, WarningKind::Degraded);
+        assert!(result.warnings[0].message.starts_with("failed: "), "{}", result.warnings[0].message);
+        
+        // Sources without a fallback still fail
+        assert!(run("other").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_dispatcher_register_multiple_connectors() {
         let mut dispatcher = DefaultDispatcher::new();
@@ -515,6 +1357,43 @@ mod tests {
         assert_eq!(connector_queries[0].connector_type, ConnectorType::Mock);
     }
 
+    #[tokio::test]
+    async fn test_dispatcher_enforces_source_policies() {
+        let mut dispatcher = DefaultDispatcher::new();
+        dispatcher.register_connector("pg", Box::new(TestConnector::new(ConnectorType::PostgreSQL))).await.unwrap();
+        dispatcher.set_source_policy("pg", SourcePolicy {
+            read_only: true,
+            allowed_tables: vec!["public.*".to_string()],
+            denied_tables: vec!["public.secret*".to_string()],
+            max_limit: Some(100),
+        });
+        let query = |operation: QueryOperation, table: &str, limit: Option<u64>| {
+            let mut query = InternalQuery::new(operation);
+            query.sources.push(DataSource { object_type: "pg".to_string(), identifier: table.to_string(), alias: None });
+            query.limit = limit;
+            query
+        };
+        
+        let routed = dispatcher.route_query(&query(QueryOperation::Select, "public.Users", None)).await.unwrap();
+        assert_eq!(routed[0].query.limit, Some(100));
+        assert_eq!(routed[0].query.capped_by.as_ref().map(|source| source.identifier.as_str()), Some("public.Users"));
+        let routed = dispatcher.route_query(&query(QueryOperation::Select, "public.users", Some(10))).await.unwrap();
+        assert_eq!(routed[0].query.limit, Some(10));
+        assert_eq!(routed[0].query.capped_by, None);
+        
+        for rejected in [
+            query(QueryOperation::Delete, "public.users", None),
+            query(QueryOperation::Select, "public.secrets", None),
+            query(QueryOperation::Select, "audit.log", None),
+        ] {
+            let error = dispatcher.route_query(&rejected).await.unwrap_err();
+            assert!(matches!(error, NirvError::Dispatcher(DispatcherError::PolicyViolation(_))), "{}", error);
+        }
+        let mut copy = query(QueryOperation::Select, "public.users", None);
+        copy.target = Some(DataSource { object_type: "pg".to_string(), identifier: "public.copy".to_string(), alias: None });
+        assert!(dispatcher.route_query(&copy).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_dispatcher_route_query_unregistered_type() {
         let dispatcher = DefaultDispatcher::new();
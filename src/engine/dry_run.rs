@@ -0,0 +1,134 @@
+use std::fmt;
+
+use crate::engine::{ApproxAggregate, ComputedColumn, Dispatcher, ExecutionPlan, QueryPlanner};
+use crate::utils::{
+    types::{InternalQuery, Schema, COUNT_ALL},
+    error::NirvError,
+    identifier::IdentifierCase,
+    hash_function::RowHash,
+    date_function::{is_fetched_at, NOW_FUNCTION},
+};
+
+/// Outcome of validating a query without executing it
+#[derive(Debug, Clone, Default)]
+pub struct DryRunReport {
+    /// Plan the query would execute, when it could be planned
+    pub plan: Option<ExecutionPlan>,
+    /// Problems found while parsing, planning and checking sources and columns
+    pub errors: Vec<String>,
+}
+
+impl DryRunReport {
+    /// A report for a query that failed before it could be planned
+    pub fn failed(error: &NirvError) -> Self {
+        Self {
+            plan: None,
+            errors: vec![error.to_string()],
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for DryRunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(plan) = &self.plan {
+            write!(f, "{}", plan)?;
+        }
+        if self.is_valid() {
+            write!(f, "Query is valid")
+        } else {
+            write!(f, "Errors:")?;
+            for error in &self.errors {
+                write!(f, "\n  - {}", error)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Plan a parsed query and check that its sources exist and every column it references
+/// is present in the source schema, as reported by the connector
+pub async fn dry_run_query(
+    query: &InternalQuery,
+    planner: &dyn QueryPlanner,
+    dispatcher: &dyn Dispatcher,
+    identifier_case: IdentifierCase,
+) -> DryRunReport {
+    let mut report = DryRunReport::default();
+    // A query its sources' policies reject is not looked into any further
+    let query = match dispatcher.apply_source_policies(query) {
+        Ok(query) => query,
+        Err(e) => {
+            report.errors.push(e.to_string());
+            return report;
+        }
+    };
+    let query = &query;
+    match planner.create_execution_plan(query).await {
+        Ok(plan) => report.plan = Some(plan),
+        Err(e) => report.errors.push(e.to_string()),
+    }
+
+    let mut schemas = Vec::new();
+    for source in &query.sources {
+        let name = format!("{}.{}", source.object_type, source.identifier);
+        let Some(connector) = dispatcher.get_connector(&source.object_type) else {
+            report.errors.push(format!("Data object type '{}' is not registered", source.object_type));
+            continue;
+        };
+        // Show which predicates the connector evaluates and which stay local
+        if query.sources.len() == 1 {
+            report.plan = report.plan.take()
+                .map(|plan| plan.with_pushdown(|predicate| connector.supports_predicate(predicate)));
+        }
+        match connector.get_schema(&source.identifier).await {
+            Ok(schema) => schemas.push(schema),
+            Err(e) => report.errors.push(format!("Source '{}' is not available: {}", name, e)),
+        }
+    }
+    // Column checks need the schema of every source
+    if schemas.len() != query.sources.len() {
+        return report;
+    }
+
+    let aliases: Vec<&str> = query.projections.iter().filter_map(|col| col.alias.as_deref()).collect();
+    let row_hashes: Vec<RowHash> = query.projections.iter().filter_map(|col| RowHash::parse(&col.name)).collect();
+    let mut referenced: Vec<&str> = Vec::new();
+    referenced.extend(query.projections.iter()
+        .map(|col| col.name.as_str())
+        .filter(|name| RowHash::parse(name).is_none() && *name != NOW_FUNCTION && *name != COUNT_ALL && !is_fetched_at(name)));
+    referenced.extend(row_hashes.iter().flat_map(|hash| hash.columns.iter().map(String::as_str)));
+    referenced.extend(query.predicates.iter().map(|pred| pred.column.as_str()));
+    if let Some(ordering) = &query.ordering {
+        referenced.extend(ordering.columns.iter()
+            .map(|col| col.column.as_str())
+            .filter(|column| !aliases.iter().any(|alias| identifier_case.matches(column, alias))));
+    }
+
+    for column in referenced {
+        let aggregate = ApproxAggregate::parse(column);
+        let column = aggregate.as_ref().map_or(column, |aggregate| aggregate.column.as_str());
+        let computed = ComputedColumn::parse(column);
+        let column = computed.as_ref().map_or(column, |computed| computed.column());
+        if column == "*" || is_resolvable(column, &schemas, identifier_case) {
+            continue;
+        }
+        let error = format!("Column '{}' does not exist in {}", column, describe_sources(&schemas));
+        if !report.errors.contains(&error) {
+            report.errors.push(error);
+        }
+    }
+
+    report
+}
+
+fn is_resolvable(column: &str, schemas: &[Schema], identifier_case: IdentifierCase) -> bool {
+    schemas.iter().any(|schema| schema.column_index(column, identifier_case).is_some())
+}
+
+fn describe_sources(schemas: &[Schema]) -> String {
+    schemas.iter().map(|schema| format!("'{}'", schema.name)).collect::<Vec<_>>().join(", ")
+}
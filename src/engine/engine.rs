@@ -1,25 +1,47 @@
+use async_trait::async_trait;
+use chrono::Utc;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
 use tokio::signal;
 use tokio::task::JoinHandle;
+use tokio::io::AsyncWriteExt;
 use tokio::net::TcpListener;
 
 use crate::{
     engine::{
         Dispatcher, DefaultDispatcher,
-        QueryParser, DefaultQueryParser,
+        QueryParser, DefaultQueryParser, ClientDialect, split_statements,
         QueryPlanner, DefaultQueryPlanner,
         QueryExecutor, DefaultQueryExecutor,
+        Session, TransactionControl, TimeZoneSetting, CacheControl, with_session_time_zone, with_session_cache_ttl,
+        AccessCatalog, AccessStatement, QueryScheduler, ConnectorQueue, SourceFallback,
+        TwoPhaseCoordinator, TransactionParticipant, RecoveryReport,
+        QueryRewriter, QueryRewriterPipeline,
+        ResultTransformer, ResultTransformerPipeline,
+        DryRunReport, dry_run_query,
+        UsageTracker, SystemConnector, SYSTEM_OBJECT_TYPE,
+        SchemaDriftLog, SchemaWatcher,
+        LineageLog, LineageRecord, QueryLineage, parse_explain_lineage,
+        parse_describe, describe_schema,
+        JoinSuggestion, suggest_joins, parse_suggest_joins, join_suggestions_result,
+        LintFinding, LintMode, lint_query, lint_error, source_facts,
     },
-    protocol::{ProtocolAdapter, ProtocolType},
-    connectors::{ConnectorRegistry, Connector},
+    protocol::{ProtocolAdapter, ProtocolType, Connection, TransactionStatus, StatementResult, HttpApiServer, QueryHandler, SchemaCatalog, CatalogTable},
+    connectors::{ConnectorRegistry, Connector, connector_debug_log, configure_parse_pool},
+    connectors::debug_log::{current_query_tag, parse_debug_toggle, tag_result, with_query_id},
     utils::{
-        config::{EngineConfig, ProtocolConfig, ProtocolType as ConfigProtocolType},
-        error::{NirvResult, NirvError},
-        types::QueryResult,
+        config::{EngineConfig, ProtocolConfig, ProtocolType as ConfigProtocolType, SourcePolicy, LintConfig},
+        error::{NirvResult, NirvError, ConnectorError, DispatcherError, ProtocolError, QueryParsingError},
+        types::{InternalQuery, ConnectorQuery, QueryResult, QueryOperation, QueryWarning, DataSource, SourceUri},
+        time_zone::SessionTimeZone,
+        collation::Collation,
+        identifier::IdentifierCase,
     },
 };
+#[cfg(feature = "web-console")]
+use crate::protocol::{ConsoleHandler, SourceStatus};
 
 /// Main NIRV Engine that coordinates all components
 pub struct Engine {
@@ -38,7 +60,26 @@ pub struct Engine {
     /// Running protocol server tasks
     server_tasks: Vec<JoinHandle<()>>,
     /// Shutdown signal
-    shutdown_signal: Option<tokio::sync::broadcast::Sender<()>>,
+    shutdown_signal: Option<broadcast::Sender<()>>,
+    /// Coordinator for writes spanning several transactional connectors
+    transaction_coordinator: Option<Arc<TwoPhaseCoordinator>>,
+    /// Rewriters applied to every parsed query before planning
+    query_rewriters: QueryRewriterPipeline,
+    /// Transformers applied to every query result before it is returned
+    result_transformers: ResultTransformerPipeline,
+    /// Rows and bytes fetched per user, when quotas are configured
+    usage_tracker: Option<Arc<UsageTracker>>,
+    /// Drift detected in source schemas, when they are watched
+    schema_drift: Option<Arc<SchemaDriftLog>>,
+    /// Column lineage of recent queries, when it is recorded
+    lineage: Option<Arc<LineageLog>>,
+    /// Admits queries to run, interactive ones ahead of batch ones
+    scheduler: Arc<QueryScheduler>,
+    /// Connectors created from the configuration, given to the executor with the registered ones
+    configured_connectors: ConnectorRegistry,
+    /// Whether queries run through the planner and executor rather than across the routed
+    /// connectors, as when the builder selected implementations other than the built-in ones
+    planned_execution: bool,
 }
 
 impl Engine {
@@ -46,19 +87,9 @@ impl Engine {
     pub fn new(config: EngineConfig) -> Self {
         let query_parser = Arc::new(DefaultQueryParser::new().expect("Failed to create query parser"));
         let query_planner = Arc::new(DefaultQueryPlanner::new());
-        let query_executor = Arc::new(RwLock::new(DefaultQueryExecutor::new()));
+        let query_executor = default_query_executor(&config);
         let dispatcher = Arc::new(RwLock::new(DefaultDispatcher::new()));
-        
-        Self {
-            config,
-            query_parser,
-            query_planner,
-            query_executor,
-            dispatcher,
-            protocol_adapters: HashMap::new(),
-            server_tasks: Vec::new(),
-            shutdown_signal: None,
-        }
+        Self::with_components(config, query_parser, query_planner, query_executor, dispatcher)
     }
     
     /// Create an engine with custom components
@@ -69,6 +100,12 @@ impl Engine {
         query_executor: Arc<RwLock<dyn QueryExecutor>>,
         dispatcher: Arc<RwLock<dyn Dispatcher>>,
     ) -> Self {
+        let query_rewriters = QueryRewriterPipeline::from_config(&config.query_rewriters);
+        let result_transformers = ResultTransformerPipeline::from_config(&config.result_transformers);
+        let usage_tracker = config.quotas.as_ref().map(|quotas| Arc::new(UsageTracker::from_config(quotas)));
+        let scheduler = Arc::new(QueryScheduler::from_config(&config.dispatcher));
+        let lineage = config.lineage.as_ref().map(|lineage| Arc::new(LineageLog::new(lineage.max_queries)));
+        
         Self {
             config,
             query_parser,
@@ -78,25 +115,41 @@ impl Engine {
             protocol_adapters: HashMap::new(),
             server_tasks: Vec::new(),
             shutdown_signal: None,
+            transaction_coordinator: None,
+            query_rewriters,
+            result_transformers,
+            usage_tracker,
+            schema_drift: None,
+            lineage,
+            scheduler,
+            configured_connectors: ConnectorRegistry::new(),
+            planned_execution: false,
         }
     }
     
     /// Initialize the engine and start all services
     pub async fn initialize(&mut self) -> NirvResult<()> {
+        // Report every configuration problem at once, before anything starts
+        self.config.validate().into_result()?;
+        configure_parse_pool(self.config.runtime.parse_threads);
+        
         // Initialize connector registry
         let connector_registry = self.initialize_connectors().await?;
         
-        // Set connector registry in query executor
-        {
-            let mut executor = self.query_executor.write().await;
-            executor.set_connector_registry(connector_registry);
+        self.configured_connectors = connector_registry;
+        self.register_system_connector(false).await?;
+        self.sync_executor_connectors().await;
+        self.apply_source_policies().await;
+        self.open_access_catalog().await?;
+        if let Some(drift) = self.config.schema_drift.clone() {
+            self.watch_schemas(Duration::from_secs(drift.interval_secs)).await?;
         }
         
         // Initialize protocol adapters
         self.initialize_protocol_adapters().await?;
         
-        // Start protocol servers (only if we have protocol adapters configured)
-        if !self.config.protocol_adapters.is_empty() {
+        // Start protocol servers (only if we have protocol adapters or the HTTP API configured)
+        if !self.config.protocol_adapters.is_empty() || self.config.http_api.is_some() {
             self.start_protocol_servers().await?;
         }
         
@@ -120,6 +173,40 @@ impl Engine {
         Ok(registry)
     }
     
+    /// Hand each configured connector's policy, time zone, collation and column details to
+    /// the dispatcher, whether joins may return partial results, how queries queue for
+    /// connectors, and the fallbacks of sources
+    async fn apply_source_policies(&self) {
+        let mut dispatcher = self.dispatcher.write().await;
+        dispatcher.set_partial_results(self.config.dispatcher.partial_results);
+        dispatcher.set_connector_queue(ConnectorQueue::new(
+            self.config.dispatcher.max_queued_queries,
+            Duration::from_secs(self.config.dispatcher.queue_timeout),
+        ));
+        for (name, connector_config) in &self.config.connectors {
+            dispatcher.set_source_policy(name, connector_config.policy.clone());
+            // Validation has already rejected unknown time zones
+            if let Some(Ok(time_zone)) = connector_config.time_zone.as_deref().map(SessionTimeZone::parse) {
+                dispatcher.set_source_time_zone(name, time_zone);
+            }
+            if let Some(Ok(collation)) = connector_config.collation.as_deref().map(Collation::parse) {
+                dispatcher.set_source_collation(name, collation);
+            }
+            if !connector_config.columns.is_empty() {
+                dispatcher.set_column_details(name, connector_config.columns.clone());
+            }
+        }
+        for (primary, fallback) in &self.config.fallbacks {
+            // Validation has already rejected sources that do not parse
+            if let (Ok(primary), Ok(source)) = (SourceUri::parse(primary), SourceUri::parse(&fallback.source)) {
+                dispatcher.set_source_fallback(&primary.to_data_source(None), SourceFallback {
+                    source: source.to_data_source(None),
+                    soft_timeout: fallback.soft_timeout_ms.map(Duration::from_millis),
+                });
+            }
+        }
+    }
+    
     /// Create a connector based on configuration
     fn create_connector(&self, _config: &crate::utils::config::ConnectorConfig) -> NirvResult<Box<dyn Connector>> {
         // For MVP, return a mock connector
@@ -136,6 +223,7 @@ impl Engine {
                 ConfigProtocolType::PostgreSQL => ProtocolType::PostgreSQL,
                 ConfigProtocolType::MySQL => ProtocolType::MySQL,
                 ConfigProtocolType::SQLite => ProtocolType::SQLite,
+                ConfigProtocolType::SqlServer => ProtocolType::SqlServer,
             };
             self.protocol_adapters.insert(protocol_type, adapter);
         }
@@ -150,26 +238,35 @@ impl Engine {
                 Ok(Arc::new(PostgreSQLProtocolAdapter::new()))
             }
             ConfigProtocolType::MySQL => {
-                use crate::protocol::MySQLProtocolAdapter;
-                Ok(Arc::new(MySQLProtocolAdapter::new()))
+                use crate::protocol::{MySQLProtocolAdapter, load_tls_acceptor};
+                let mut adapter = MySQLProtocolAdapter::new();
+                if let Some(tls_config) = &config.tls_config {
+                    adapter = adapter.with_tls(load_tls_acceptor(tls_config)?);
+                }
+                Ok(Arc::new(adapter))
             }
             ConfigProtocolType::SQLite => {
                 use crate::protocol::SQLiteProtocolAdapter;
                 Ok(Arc::new(SQLiteProtocolAdapter::new()))
             }
+            ConfigProtocolType::SqlServer => {
+                use crate::protocol::SqlServerProtocol;
+                // BI tools browse the engine's sources through catalog procedures and views
+                Ok(Arc::new(SqlServerProtocol::new().with_catalog(Arc::new(self.engine_ref()))))
+            }
         }
     }
     
     /// Start protocol servers for client connections
     async fn start_protocol_servers(&mut self) -> NirvResult<()> {
-        let (shutdown_tx, _) = tokio::sync::broadcast::channel(1);
-        self.shutdown_signal = Some(shutdown_tx.clone());
+        let shutdown_tx = self.shutdown_sender();
         
         for protocol_config in &self.config.protocol_adapters {
             let protocol_type = match protocol_config.protocol_type {
                 ConfigProtocolType::PostgreSQL => ProtocolType::PostgreSQL,
                 ConfigProtocolType::MySQL => ProtocolType::MySQL,
                 ConfigProtocolType::SQLite => ProtocolType::SQLite,
+                ConfigProtocolType::SqlServer => ProtocolType::SqlServer,
             };
             
             let adapter = self.protocol_adapters
@@ -185,12 +282,7 @@ impl Engine {
                     format!("Failed to bind to {}: {}", bind_address, e)
                 ))?;
             
-            let engine_ref = EngineRef {
-                query_parser: self.query_parser.clone(),
-                query_planner: self.query_planner.clone(),
-                query_executor: self.query_executor.clone(),
-                dispatcher: self.dispatcher.clone(),
-            };
+            let engine_ref = self.engine_ref();
             
             let mut shutdown_rx = shutdown_tx.subscribe();
             let task = tokio::spawn(async move {
@@ -226,51 +318,387 @@ impl Engine {
             self.server_tasks.push(task);
         }
         
+        if let Some(http_config) = self.config.http_api.clone() {
+            let server = HttpApiServer::new(http_config, Arc::new(self.engine_ref()));
+            #[cfg(feature = "web-console")]
+            let server = server.with_console(Arc::new(self.engine_ref()));
+            let listener = server.bind().await?;
+            let mut shutdown_rx = shutdown_tx.subscribe();
+            let task = tokio::spawn(async move {
+                let shutdown = async move {
+                    let _ = shutdown_rx.recv().await;
+                };
+                if let Err(e) = server.serve(listener, shutdown).await {
+                    eprintln!("HTTP API error: {}", e);
+                }
+            });
+            self.server_tasks.push(task);
+        }
+        
         Ok(())
     }
     
+    /// Sender of the signal that stops background tasks, created on first use
+    fn shutdown_sender(&mut self) -> broadcast::Sender<()> {
+        self.shutdown_signal.get_or_insert_with(|| broadcast::channel(1).0).clone()
+    }
+    
+    /// Snapshot of the components needed to execute queries from server tasks
+    fn engine_ref(&self) -> EngineRef {
+        EngineRef {
+            query_parser: self.query_parser.clone(),
+            query_planner: self.query_planner.clone(),
+            query_executor: self.query_executor.clone(),
+            dispatcher: self.dispatcher.clone(),
+            query_rewriters: self.query_rewriters.clone(),
+            result_transformers: self.result_transformers.clone(),
+            scheduler: self.scheduler.clone(),
+            lineage: self.lineage.clone(),
+            identifier_case: self.config.dispatcher.identifier_case,
+            lint: self.config.lint.clone(),
+            usage_tracker: self.usage_tracker.clone(),
+            planned_execution: self.planned_execution,
+        }
+    }
+    
     /// Handle a client connection through a protocol adapter
     async fn handle_client_connection(
         adapter: Arc<dyn ProtocolAdapter>,
-        _engine: EngineRef,
+        engine: EngineRef,
         stream: tokio::net::TcpStream,
     ) -> NirvResult<()> {
         // Accept the connection
         let mut connection = adapter.accept_connection(stream).await?;
         
-        // For MVP, we'll skip authentication
-        // In future tasks, we'll implement proper authentication
-        
-        // Handle queries in a loop
-        loop {
-            // For MVP, we'll implement a simple query handling loop
-            // In future tasks, we'll implement proper protocol message handling
-            break;
-        }
-        
+        // The engine keeps no credentials for protocol clients yet, so they are not
+        // authenticated and their sessions are anonymous
+        let served = engine.serve_connection(adapter.as_ref(), &mut connection).await;
+
         // Terminate the connection
         adapter.terminate_connection(&mut connection).await?;
         
-        Ok(())
+        served
     }
     
     /// Execute a query through the engine
     pub async fn execute_query(&self, query_string: &str) -> NirvResult<QueryResult> {
-        // Parse the query
-        let internal_query = self.query_parser.parse_sql(query_string).await?;
+        self.engine_ref().handle_query(query_string).await
+    }
+    
+    /// Join conditions between two source objects, written as in a query such as
+    /// `postgres.orders`, strongest first
+    pub async fn suggest_joins(&self, left: &str, right: &str) -> NirvResult<Vec<JoinSuggestion>> {
+        let left = SourceUri::parse(left)?.to_data_source(None);
+        let right = SourceUri::parse(right)?.to_data_source(None);
+        self.engine_ref().suggest_joins(&left, &right, &Session::new()).await
+    }
+    
+    /// Execute a query on behalf of a user, under the privileges of their roles when access
+    /// control is on, refusing it once the user has reached a quota and charging what it
+    /// fetched to the user
+    pub async fn execute_query_as(&self, user: &str, query_string: &str) -> NirvResult<QueryResult> {
+        self.engine_ref().handle_for(query_string, &Session::new().with_user(user)).await
+    }
+    
+    /// Track usage with the given tracker and expose it as `system.usage`
+    pub async fn enable_usage_tracking(&mut self, tracker: Arc<UsageTracker>) -> NirvResult<()> {
+        self.usage_tracker = Some(tracker);
+        self.register_system_connector(true).await
+    }
+    
+    /// Scheduler admitting the engine's queries
+    pub fn scheduler(&self) -> &Arc<QueryScheduler> {
+        &self.scheduler
+    }
+    
+    pub fn usage_tracker(&self) -> Option<&Arc<UsageTracker>> {
+        self.usage_tracker.as_ref()
+    }
+    
+    /// Re-introspect the registered sources every interval until shutdown, recording drift
+    /// in `system.schema_drift` and, when audit logging has a file, in the audit log
+    pub async fn watch_schemas(&mut self, interval: Duration) -> NirvResult<Arc<SchemaWatcher>> {
+        let audit = &self.config.security.audit_logging;
+        let log = match (&audit.log_file, audit.enabled) {
+            (Some(path), true) => SchemaDriftLog::new().with_audit_log(path),
+            _ => SchemaDriftLog::new(),
+        };
+        let log = Arc::new(log);
+        self.schema_drift = Some(log.clone());
+        self.register_system_connector(true).await?;
         
-        // Route the query through the dispatcher
+        let watcher = Arc::new(SchemaWatcher::new(self.dispatcher.clone(), log));
+        let shutdown = self.shutdown_sender().subscribe();
+        self.server_tasks.push(watcher.clone().spawn(interval, shutdown));
+        Ok(watcher)
+    }
+    
+    pub fn schema_drift_log(&self) -> Option<&Arc<SchemaDriftLog>> {
+        self.schema_drift.as_ref()
+    }
+    
+    /// Record the column lineage of each query in the log and expose it as `system.lineage`
+    pub async fn enable_lineage(&mut self, log: Arc<LineageLog>) -> NirvResult<()> {
+        self.lineage = Some(log);
+        self.register_system_connector(true).await
+    }
+    
+    pub fn lineage_log(&self) -> Option<&Arc<LineageLog>> {
+        self.lineage.as_ref()
+    }
+    
+    /// Register the system tables once usage is tracked, schemas are watched or lineage is
+    /// recorded. System tables registered before, such as a tenant's scoped ones, are only
+    /// replaced when asked.
+    async fn register_system_connector(&self, replace: bool) -> NirvResult<()> {
+        if self.usage_tracker.is_none() && self.schema_drift.is_none() && self.lineage.is_none() {
+            return Ok(());
+        }
+        let mut connector = SystemConnector::new(self.usage_tracker.clone().unwrap_or_default());
+        if let Some(log) = &self.schema_drift {
+            connector = connector.with_schema_drift(log.clone());
+        }
+        if let Some(log) = &self.lineage {
+            connector = connector.with_lineage(log.clone());
+        }
+        let mut dispatcher = self.dispatcher.write().await;
+        match (dispatcher.is_type_registered(SYSTEM_OBJECT_TYPE), replace) {
+            (false, _) => dispatcher.register_connector(SYSTEM_OBJECT_TYPE, Box::new(connector)).await,
+            (true, true) => dispatcher.replace_connector(SYSTEM_OBJECT_TYPE, Box::new(connector)).await,
+            (true, false) => Ok(()),
+        }
+    }
+    
+    /// Parse, rewrite and plan a query and check its sources and columns against the
+    /// connector schemas, without executing it
+    pub async fn dry_run(&self, query_string: &str) -> DryRunReport {
+        let internal_query = match self.parse_query(query_string).await {
+            Ok(query) => query,
+            Err(e) => return DryRunReport::failed(&e),
+        };
         let dispatcher = self.dispatcher.read().await;
-        let connector_queries = dispatcher.route_query(&internal_query).await?;
-        
-        // Execute the distributed query
-        dispatcher.execute_distributed_query(connector_queries).await
+        dry_run_query(&internal_query, self.query_planner.as_ref(), &*dispatcher, self.config.dispatcher.identifier_case).await
+    }
+
+    /// Parse, rewrite and plan a query and return its execution plan as JSON, for tools
+    /// that display or diff plans
+    pub async fn plan_to_json(&self, query_string: &str) -> NirvResult<String> {
+        let internal_query = self.parse_query(query_string).await?;
+        let internal_query = self.dispatcher.read().await.apply_source_policies(&internal_query)?;
+        let plan = self.query_planner.create_execution_plan(&internal_query).await?;
+        serde_json::to_string_pretty(&plan).map_err(|e| NirvError::Internal(e.to_string()))
+    }
+
+    /// Register a query rewriter to run after those already registered
+    pub fn add_query_rewriter(&mut self, rewriter: Arc<dyn QueryRewriter>) {
+        self.query_rewriters.add(rewriter);
     }
     
+    /// Register a result transformer to run after those already registered
+    pub fn add_result_transformer(&mut self, transformer: Arc<dyn ResultTransformer>) {
+        self.result_transformers.add(transformer);
+    }
+    
+    /// Parse a query and run it through the rewriter pipeline
+    async fn parse_query(&self, query_string: &str) -> NirvResult<InternalQuery> {
+        self.parse_query_as(query_string, ClientDialect::Auto).await
+    }
+    
+    /// Parse a query in a client's dialect and run it through the rewriter pipeline
+    async fn parse_query_as(&self, query_string: &str, dialect: ClientDialect) -> NirvResult<InternalQuery> {
+        let internal_query = self.query_parser.parse_sql_as(query_string, dialect).await?;
+        self.query_rewriters.apply(internal_query).await
+    }
+    
+    /// Execute a statement within a client session, passing BEGIN/COMMIT/ROLLBACK through
+    /// to the single transactional connector the session's statements target. Once the
+    /// session sets a time zone, times are rendered and dates truncated in it, and once it
+    /// sets `nirv.cache` its queries read caches with that TTL.
+    pub async fn execute_session_query(&self, session: &mut Session, query_string: &str) -> NirvResult<QueryResult> {
+        self.engine_ref().execute_session_query(session, query_string).await
+    }
+
+    /// Execute the `;`-separated statements a client sent in one message one after another in
+    /// its session, as `SET ...; SELECT ...`. The first statement that fails ends them, and its
+    /// error is the last result.
+    pub async fn execute_session_statements(&self, session: &mut Session, sql: &str) -> Vec<StatementResult> {
+        self.engine_ref().execute_session_statements(session, sql).await
+    }
+
+    /// Roll back any transaction left open when a client session ends
+    pub async fn close_session(&self, session: &mut Session) -> NirvResult<()> {
+        self.engine_ref().close_session(session).await
+    }
+
+    /// Serve an accepted client connection until the client disconnects, running its
+    /// statements in a session of its own and rolling back a transaction it left open
+    pub async fn serve_connection(&self, adapter: &dyn ProtocolAdapter, connection: &mut Connection) -> NirvResult<()> {
+        self.engine_ref().serve_connection(adapter, connection).await
+    }
+
+    /// Enable two-phase commit for cross-connector writes, journaling to the given path
+    pub fn set_transaction_journal<P: AsRef<std::path::Path>>(&mut self, path: P) {
+        self.transaction_coordinator = Some(Arc::new(TwoPhaseCoordinator::new(path)));
+    }
+
+    /// Execute statements that may target several transactional connectors as one
+    /// best-effort atomic unit using two-phase commit, returning their results in order
+    pub async fn execute_distributed_write(&self, statements: &[&str]) -> NirvResult<Vec<QueryResult>> {
+        let coordinator = self.transaction_coordinator.as_ref()
+            .ok_or_else(|| NirvError::Configuration(
+                "Two-phase commit requires a transaction journal".to_string()
+            ))?;
+
+        // Group statements by target connector, keeping first-seen order, and where each
+        // statement's results fall among its group's
+        let mut groups: Vec<(String, Arc<dyn Connector>, Vec<ConnectorQuery>)> = Vec::new();
+        let mut placements: Vec<(usize, usize)> = Vec::new();
+        {
+            let dispatcher = self.dispatcher.read().await;
+            for statement in statements {
+                let internal_query = self.parse_query(statement).await?;
+                let connector_queries = dispatcher.route_query(&internal_query).await?;
+                let object_type = &internal_query.sources[0].object_type;
+
+                let group = match groups.iter().position(|(name, _, _)| name == object_type) {
+                    Some(group) => group,
+                    None => {
+                        let connector = dispatcher.get_connector(object_type)
+                            .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
+                        groups.push((object_type.clone(), connector, Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+                let queries = &mut groups[group].2;
+                placements.extend((queries.len()..queries.len() + connector_queries.len()).map(|index| (group, index)));
+                queries.extend(connector_queries);
+            }
+        }
+        let participants = groups.iter()
+            .map(|(name, connector, queries)| TransactionParticipant {
+                name: name.clone(),
+                connector: connector.as_ref(),
+                queries: queries.clone(),
+            })
+            .collect();
+
+        // The coordinator answers group by group; results are handed back in statement order
+        let mut grouped: Vec<Option<QueryResult>> = coordinator.execute(participants).await?
+            .into_iter().map(Some).collect();
+        let offsets: Vec<usize> = groups.iter()
+            .scan(0, |offset, (_, _, queries)| {
+                let start = *offset;
+                *offset += queries.len();
+                Some(start)
+            })
+            .collect();
+        let mut results = Vec::new();
+        for (group, index) in placements {
+            let result = grouped[offsets[group] + index].take().ok_or_else(|| DispatcherError::TransactionCoordinationFailed(
+                "Two-phase commit returned fewer results than statements".to_string()
+            ))?;
+            results.push(self.result_transformers.apply(result).await?);
+        }
+        Ok(results)
+    }
+
+    /// Resolve distributed transactions left pending by a previous run
+    pub async fn recover_transactions(&self) -> NirvResult<RecoveryReport> {
+        let Some(coordinator) = self.transaction_coordinator.as_ref() else {
+            return Ok(RecoveryReport::default());
+        };
+
+        let shared: Vec<(String, Arc<dyn Connector>)> = {
+            let dispatcher = self.dispatcher.read().await;
+            dispatcher.list_available_types()
+                .into_iter()
+                .filter_map(|object_type| {
+                    dispatcher.get_connector(&object_type).map(|connector| (object_type, connector))
+                })
+                .collect()
+        };
+        let connectors = shared.iter()
+            .map(|(object_type, connector)| (object_type.clone(), connector.as_ref()))
+            .collect();
+
+        coordinator.recover(&connectors).await
+    }
+
     /// Register a connector with the dispatcher
     pub async fn register_connector(&self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()> {
+        self.dispatcher.write().await.register_connector(object_type, connector).await?;
+        self.sync_executor_connectors().await;
+        Ok(())
+    }
+
+    /// Register a connector instance that is also used outside this engine
+    pub async fn register_shared_connector(&self, object_type: &str, connector: Arc<dyn Connector>) -> NirvResult<()> {
+        self.dispatcher.write().await.register_shared_connector(object_type, connector).await?;
+        self.sync_executor_connectors().await;
+        Ok(())
+    }
+
+    /// Replace the connector serving a data object type, for example with a reconnected
+    /// instance, without waiting for running queries
+    pub async fn replace_connector(&self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()> {
+        self.dispatcher.write().await.replace_connector(object_type, connector).await?;
+        self.sync_executor_connectors().await;
+        Ok(())
+    }
+    
+    /// Give the query executor the configured connectors and, when queries run through it,
+    /// the ones registered with the dispatcher by data object type
+    async fn sync_executor_connectors(&self) {
+        let mut registry = self.configured_connectors.clone();
+        if self.planned_execution {
+            let dispatcher = self.dispatcher.read().await;
+            for object_type in dispatcher.list_available_types() {
+                if let Some(connector) = dispatcher.get_connector(&object_type) {
+                    if !registry.contains(&object_type) {
+                        registry.register_shared(object_type, connector).expect("name checked as free");
+                    }
+                }
+            }
+        }
+        self.query_executor.write().await.set_connector_registry(registry);
+    }
+    
+    /// Limit what queries may do with a data object type
+    pub async fn set_source_policy(&self, object_type: &str, policy: SourcePolicy) {
+        let mut dispatcher = self.dispatcher.write().await;
+        dispatcher.set_source_policy(object_type, policy);
+    }
+    
+    /// Check the queries users run against the roles and grants of a catalog
+    pub async fn set_access_catalog(&self, catalog: AccessCatalog) {
+        let mut dispatcher = self.dispatcher.write().await;
+        dispatcher.set_access_catalog(catalog);
+    }
+    
+    /// Turn access control on when authorization is enabled, with the configured catalog
+    async fn open_access_catalog(&self) -> NirvResult<()> {
+        let authorization = &self.config.security.authorization;
+        if !authorization.enabled {
+            return Ok(());
+        }
+        let catalog = match &authorization.catalog_path {
+            Some(path) => AccessCatalog::open(path)?,
+            None => AccessCatalog::new(),
+        };
+        self.set_access_catalog(catalog).await;
+        Ok(())
+    }
+    
+    /// Read a data object type's DateTime values without an offset as times in the zone
+    pub async fn set_source_time_zone(&self, object_type: &str, time_zone: SessionTimeZone) {
+        let mut dispatcher = self.dispatcher.write().await;
+        dispatcher.set_source_time_zone(object_type, time_zone);
+    }
+    
+    /// Compare a data object type's text by a collation when queries name none
+    pub async fn set_source_collation(&self, object_type: &str, collation: Collation) {
         let mut dispatcher = self.dispatcher.write().await;
-        dispatcher.register_connector(object_type, connector).await
+        dispatcher.set_source_collation(object_type, collation);
     }
     
     /// Initialize the engine for testing (without starting protocol servers)
@@ -278,11 +706,11 @@ impl Engine {
         // Initialize connector registry
         let connector_registry = self.initialize_connectors().await?;
         
-        // Set connector registry in query executor
-        {
-            let mut executor = self.query_executor.write().await;
-            executor.set_connector_registry(connector_registry);
-        }
+        self.configured_connectors = connector_registry;
+        self.register_system_connector(false).await?;
+        self.sync_executor_connectors().await;
+        self.apply_source_policies().await;
+        self.open_access_catalog().await?;
         
         // Initialize protocol adapters but don't start servers
         self.initialize_protocol_adapters().await?;
@@ -330,6 +758,588 @@ struct EngineRef {
     query_planner: Arc<dyn QueryPlanner>,
     query_executor: Arc<RwLock<dyn QueryExecutor>>,
     dispatcher: Arc<RwLock<dyn Dispatcher>>,
+    query_rewriters: QueryRewriterPipeline,
+    result_transformers: ResultTransformerPipeline,
+    scheduler: Arc<QueryScheduler>,
+    lineage: Option<Arc<LineageLog>>,
+    identifier_case: IdentifierCase,
+    lint: LintConfig,
+    usage_tracker: Option<Arc<UsageTracker>>,
+    planned_execution: bool,
+}
+
+#[async_trait]
+impl QueryHandler for EngineRef {
+    async fn handle_query(&self, sql: &str) -> NirvResult<QueryResult> {
+        self.handle_statement(sql, &Session::new()).await
+    }
+
+    async fn handle_query_for(&self, user: Option<&str>, sql: &str) -> NirvResult<QueryResult> {
+        let session = match user {
+            Some(user) => Session::new().with_user(user),
+            None => Session::new().anonymous(),
+        };
+        self.handle_for(sql, &session).await
+    }
+}
+
+impl EngineRef {
+    /// Execute a statement for a client: an anonymous network client is refused while access
+    /// control is on, and a user's statements are refused once they reach a quota and charged
+    /// what they fetched
+    async fn handle_for(&self, sql: &str, session: &Session) -> NirvResult<QueryResult> {
+        self.refuse_anonymous(session).await?;
+        let (Some(user), Some(tracker)) = (session.user(), &self.usage_tracker) else {
+            return self.handle_statement(sql, session).await;
+        };
+        tracker.check(&[user])?;
+        let result = self.handle_statement(sql, session).await?;
+        tracker.record_result(&[user], &result);
+        Ok(result)
+    }
+
+    /// Refuse an anonymous network client's queries while access control is on
+    async fn refuse_anonymous(&self, session: &Session) -> NirvResult<()> {
+        if session.is_anonymous() && self.dispatcher.read().await.access_catalog().is_some() {
+            return Err(DispatcherError::AccessDenied(
+                "access control is on; authenticate as a user to run queries".to_string()
+            ).into());
+        }
+        Ok(())
+    }
+
+    /// Execute a statement outside any client session, where settings would not last
+    async fn handle_statement(&self, sql: &str, session: &Session) -> NirvResult<QueryResult> {
+        if let Some(control) = CacheControl::parse(sql) {
+            return match control? {
+                CacheControl::Refresh(source) => self.refresh_source(&source).await,
+                CacheControl::Set(_) => Err(QueryParsingError::UnsupportedFeature(
+                    "SET nirv.cache lasts for a session; turn the cache off for one query with -- nirv: cache=off".to_string()
+                ).into()),
+            };
+        }
+        if let Some(statement) = AccessStatement::parse(sql) {
+            return self.administer(session, statement?).await;
+        }
+        if let Some(query) = parse_explain_lineage(sql) {
+            return self.explain_lineage(query, session).await;
+        }
+        if let Some(source) = parse_describe(sql) {
+            return self.describe(&source?, session).await;
+        }
+        if let Some(sources) = parse_suggest_joins(sql) {
+            let (left, right) = sources?;
+            let suggestions = self.suggest_joins(&left, &right, session).await?;
+            return Ok(join_suggestions_result(&suggestions, &left, &right));
+        }
+        self.handle_query_in(sql, session).await
+    }
+    
+
+    /// Execute a query for a session, in its time zone and with its cache TTL if it has set
+    /// them, and parsed in its dialect
+    async fn handle_query_in(&self, sql: &str, session: &Session) -> NirvResult<QueryResult> {
+        let debug_log = connector_debug_log();
+        if let Some(enabled) = parse_debug_toggle(sql) {
+            debug_log.set_enabled(enabled);
+            return Ok(QueryResult::new());
+        }
+        // Requests connectors send or log while executing the query carry its id
+        let query_id = debug_log.next_query_id();
+        with_query_id(query_id, self.execute(sql, session)).await.map(|result| tag_result(result, query_id))
+    }
+
+    async fn execute(&self, sql: &str, session: &Session) -> NirvResult<QueryResult> {
+        // Parse and rewrite the query
+        let internal_query = self.query_parser.parse_sql_as(sql, session.dialect()).await?;
+        let mut internal_query = self.query_rewriters.apply(internal_query).await?;
+        let time_zone = session.time_zone();
+        if let Some(time_zone) = time_zone {
+            internal_query = with_session_time_zone(internal_query, time_zone);
+        }
+        if let Some(ttl) = session.cache_ttl() {
+            internal_query = with_session_cache_ttl(internal_query, ttl);
+        }
+        internal_query.user = session.user().map(str::to_string);
+        // Batch queries wait for a slot behind interactive ones
+        let priority = self.scheduler.priority(&internal_query.hints.with_directives(&internal_query.directives)?, session.user());
+        let _permit = self.scheduler.admit(priority).await;
+        
+        // Route the query through the dispatcher
+        let dispatcher = self.dispatcher.read().await;
+        let findings = self.lint(&internal_query, &*dispatcher).await?;
+        let connector_queries = dispatcher.route_query(&internal_query).await?;
+
+        // A write reaches its source whole, never narrowed by predicates applied afterwards
+        if internal_query.operation != QueryOperation::Select {
+            let connector = dispatcher.get_connector(&internal_query.sources[0].object_type)
+                .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
+            let connector_query = connector_queries.into_iter().next()
+                .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
+            return connector.execute_query(connector_query).await;
+        }
+
+        // Execute through the planner and executor, checked against the same policies as
+        // the routed queries, or across the routed connectors
+        let result = if self.planned_execution {
+            let query = dispatcher.apply_source_policies(&internal_query)?;
+            let plan = self.query_planner.create_execution_plan(&query).await?;
+            let plan = self.query_planner.optimize_plan(plan).await?;
+            self.query_executor.read().await.execute_plan(&plan).await?
+        } else {
+            dispatcher.execute_distributed_query(connector_queries).await?
+        };
+        let mut result = self.result_transformers.apply(result).await?;
+        result.warnings.extend(findings.iter().map(QueryWarning::from));
+        self.record_lineage(sql, &internal_query, &*dispatcher, &result).await;
+        match &internal_query.target {
+            Some(target) => {
+                let written = dispatcher.write_result(target, &result).await?;
+                Ok(result.written(written))
+            }
+            None => Ok(render_in(result, time_zone)),
+        }
+    }
+}
+
+impl EngineRef {
+    /// Serve a client connection through its protocol adapter until the client disconnects.
+    /// Its statements run in a session of its own, whose transaction state each response
+    /// reports, and a transaction it leaves open is rolled back.
+    async fn serve_connection(&self, adapter: &dyn ProtocolAdapter, conn: &mut Connection) -> NirvResult<()> {
+        let session = Session::for_protocol(&conn.protocol_type);
+        let mut session = match conn.user() {
+            Some(user) => session.with_user(user),
+            None => session.anonymous(),
+        };
+        let served = self.serve_messages(adapter, conn, &mut session).await;
+        let closed = self.close_session(&mut session).await;
+        served.and(closed)
+    }
+
+    async fn serve_messages(&self, adapter: &dyn ProtocolAdapter, conn: &mut Connection, session: &mut Session) -> NirvResult<()> {
+        while let Some(message) = adapter.read_message(conn).await? {
+            let results = match adapter.parse_message(conn, &message).await {
+                Ok(query) if adapter.ends_connection(&query) => break,
+                Ok(query) => match adapter.answer_query(conn, &query).await {
+                    Ok(Some(result)) => vec![StatementResult::new(query.raw_query, Ok(result))],
+                    Ok(None) => self.execute_session_statements(session, &query.raw_query).await,
+                    Err(e) => vec![StatementResult::new(query.raw_query, Err(e))],
+                },
+                // A message the adapter cannot parse fails on its own, leaving the connection open
+                Err(e) => vec![StatementResult::new("", Err(e))],
+            };
+            conn.transaction_status = session.status();
+            let response = adapter.format_statement_responses(conn, results).await?;
+            conn.stream.write_all(&response).await
+                .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send response: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Execute a statement within a client session
+    async fn execute_session_query(&self, session: &mut Session, query_string: &str) -> NirvResult<QueryResult> {
+        if let Some(control) = TransactionControl::parse(query_string) {
+            return self.execute_transaction_control(session, control).await;
+        }
+        // Neither toggling connector debugging nor setting the time zone touches the open transaction
+        if parse_debug_toggle(query_string).is_some() {
+            return self.handle_query_in(query_string, session).await;
+        }
+        if let Some(setting) = TimeZoneSetting::parse(query_string) {
+            session.set_time_zone(setting?);
+            return Ok(QueryResult::new());
+        }
+        if let Some(control) = CacheControl::parse(query_string) {
+            return match control? {
+                CacheControl::Set(ttl) => {
+                    session.set_cache_ttl(ttl);
+                    Ok(QueryResult::new())
+                }
+                CacheControl::Refresh(source) => self.refresh_source(&source).await,
+            };
+        }
+        if let Some(statement) = AccessStatement::parse(query_string) {
+            return self.administer(session, statement?).await;
+        }
+        if let Some(query) = parse_explain_lineage(query_string) {
+            return self.explain_lineage(query, session).await;
+        }
+        if let Some(source) = parse_describe(query_string) {
+            return self.describe(&source?, session).await;
+        }
+        if let Some(sources) = parse_suggest_joins(query_string) {
+            let (left, right) = sources?;
+            let suggestions = self.suggest_joins(&left, &right, session).await?;
+            return Ok(join_suggestions_result(&suggestions, &left, &right));
+        }
+
+        match session.status() {
+            TransactionStatus::Idle => self.handle_for(query_string, session).await,
+            TransactionStatus::Failed => Err(ConnectorError::QueryExecutionFailed(
+                "Current transaction is aborted, commands ignored until end of transaction block".to_string()
+            ).into()),
+            TransactionStatus::InTransaction => {
+                let query_id = connector_debug_log().next_query_id();
+                let result = with_query_id(query_id, self.execute_in_transaction(session, query_string)).await
+                    .map(|result| tag_result(result, query_id));
+                if result.is_err() {
+                    session.mark_failed();
+                }
+                let result = self.result_transformers.apply(result?).await?;
+                Ok(render_in(result, session.time_zone()))
+            }
+        }
+    }
+
+    /// Execute the statements a client sent in one message in its session, up to the first that fails
+    async fn execute_session_statements(&self, session: &mut Session, sql: &str) -> Vec<StatementResult> {
+        let mut results = Vec::new();
+        for statement in split_statements(sql, session.dialect()) {
+            let result = self.execute_session_query(session, statement).await;
+            let failed = result.is_err();
+            results.push(StatementResult::new(statement, result));
+            if failed {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Roll back any transaction left open when a client session ends
+    async fn close_session(&self, session: &mut Session) -> NirvResult<()> {
+        if session.in_transaction() {
+            self.execute_transaction_control(session, TransactionControl::Rollback).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply a transaction control statement to a session
+    async fn execute_transaction_control(&self, session: &mut Session, control: TransactionControl) -> NirvResult<QueryResult> {
+        match control {
+            TransactionControl::Begin => {
+                // Like PostgreSQL, a nested BEGIN leaves the open transaction untouched
+                if !session.in_transaction() {
+                    session.begin();
+                }
+            }
+            TransactionControl::Commit | TransactionControl::Rollback => {
+                let failed = session.status() == TransactionStatus::Failed;
+                if let Some(transaction) = session.finish() {
+                    let connector = self.dispatcher.read().await.get_connector(&transaction.object_type)
+                        .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
+
+                    // Committing a failed transaction rolls it back
+                    if control == TransactionControl::Commit && !failed {
+                        connector.commit_transaction(transaction.transaction_id).await?;
+                    } else {
+                        connector.rollback_transaction(transaction.transaction_id).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(QueryResult::new())
+    }
+
+    /// Execute a statement inside the session's open transaction block
+    async fn execute_in_transaction(&self, session: &mut Session, query_string: &str) -> NirvResult<QueryResult> {
+        self.refuse_anonymous(session).await?;
+        // Statements in a transaction count against the user's quotas as any others do
+        let quota = session.user().map(str::to_string).zip(self.usage_tracker.clone());
+        if let Some((user, tracker)) = &quota {
+            tracker.check(&[user.as_str()])?;
+        }
+        let internal_query = self.query_parser.parse_sql_as(query_string, session.dialect()).await?;
+        let mut internal_query = self.query_rewriters.apply(internal_query).await?;
+        if let Some(time_zone) = session.time_zone() {
+            internal_query = with_session_time_zone(internal_query, time_zone);
+        }
+        if let Some(ttl) = session.cache_ttl() {
+            internal_query = with_session_cache_ttl(internal_query, ttl);
+        }
+        internal_query.user = session.user().map(str::to_string);
+        let priority = self.scheduler.priority(&internal_query.hints.with_directives(&internal_query.directives)?, session.user());
+        let _permit = self.scheduler.admit(priority).await;
+
+        let (connector_query, connector) = {
+            let dispatcher = self.dispatcher.read().await;
+            let mut connector_queries = dispatcher.route_query(&internal_query).await?;
+            // The bound connector can only answer a statement it receives whole; joins and
+            // derived tables are combined by the engine, outside the transaction
+            if connector_queries.len() != 1 || !internal_query.subqueries.is_empty() {
+                return Err(DispatcherError::CrossConnectorTransactionUnsupported(format!(
+                    "a statement in a transaction must read a single source, but this one reads {}",
+                    internal_query.all_sources().len()
+                )).into());
+            }
+            let connector_query = connector_queries.pop()
+                .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
+            // Routing guarantees a single registered source
+            let connector = dispatcher.get_connector(&internal_query.sources[0].object_type)
+                .ok_or(NirvError::Dispatcher(DispatcherError::NoSuitableConnector))?;
+            (connector_query, connector)
+        };
+        let object_type = internal_query.sources[0].object_type.as_str();
+
+        let transaction_id = match session.transaction() {
+            Some(transaction) if transaction.object_type != object_type => {
+                return Err(DispatcherError::CrossConnectorTransactionUnsupported(format!(
+                    "transaction is bound to '{}' but statement targets '{}'",
+                    transaction.object_type, object_type
+                )).into());
+            }
+            Some(transaction) => transaction.transaction_id,
+            None => {
+                if !connector.supports_transactions() {
+                    return Err(ConnectorError::UnsupportedOperation(
+                        format!("Data object type '{}' does not support transactions", object_type)
+                    ).into());
+                }
+                let transaction_id = connector.begin_transaction().await?;
+                session.bind_transaction(object_type, transaction_id);
+                transaction_id
+            }
+        };
+
+        let result = connector.execute_in_transaction(transaction_id, connector_query).await?;
+        if let Some((user, tracker)) = &quota {
+            tracker.record_result(&[user.as_str()], &result);
+        }
+        let dispatcher = self.dispatcher.read().await;
+        self.record_lineage(query_string, &internal_query, &*dispatcher, &result).await;
+        Ok(result)
+    }
+}
+
+impl EngineRef {
+    /// List the columns of a source object with their details, when a query could read it
+    async fn describe(&self, source: &DataSource, session: &Session) -> NirvResult<QueryResult> {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(source.clone());
+        query.user = session.user().map(str::to_string);
+        let dispatcher = self.dispatcher.read().await;
+        dispatcher.apply_source_policies(&query)?;
+        Ok(describe_schema(&dispatcher.describe_object(&source.object_type, &source.identifier).await?))
+    }
+    
+    /// Join conditions between two source objects, from the keys their schemas declare or
+    /// their column names imply, when a query could read both
+    async fn suggest_joins(&self, left: &DataSource, right: &DataSource, session: &Session) -> NirvResult<Vec<JoinSuggestion>> {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources = vec![left.clone(), right.clone()];
+        query.user = session.user().map(str::to_string);
+        let dispatcher = self.dispatcher.read().await;
+        dispatcher.apply_source_policies(&query)?;
+        let left_schema = dispatcher.describe_object(&left.object_type, &left.identifier).await?;
+        let right_schema = dispatcher.describe_object(&right.object_type, &right.identifier).await?;
+        Ok(suggest_joins(left, &left_schema, right, &right_schema))
+    }
+    
+    /// Trace the output columns of a query to its sources without executing it, checking
+    /// it against their policies and the user's privileges first
+    async fn explain_lineage(&self, sql: &str, session: &Session) -> NirvResult<QueryResult> {
+        let internal_query = self.query_parser.parse_sql_as(sql, session.dialect()).await?;
+        let mut internal_query = self.query_rewriters.apply(internal_query).await?;
+        internal_query.user = session.user().map(str::to_string);
+        let dispatcher = self.dispatcher.read().await;
+        let internal_query = dispatcher.apply_source_policies(&internal_query)?;
+        Ok(self.lineage_of(&internal_query, &*dispatcher, None).await.to_result())
+    }
+    
+    /// Findings of the lint pass over a query as its sources' policies leave it: reported with
+    /// the result, or refusing the query in strict mode
+    async fn lint(&self, query: &InternalQuery, dispatcher: &dyn Dispatcher) -> NirvResult<Vec<LintFinding>> {
+        let mode = self.lint.mode.for_query(query)?;
+        // A query the policies reject fails on routing instead
+        let (LintMode::Warn | LintMode::Strict, Ok(query)) = (mode, dispatcher.apply_source_policies(query)) else {
+            return Ok(Vec::new());
+        };
+        let findings = lint_query(&query, &source_facts(&query, dispatcher).await, &self.lint.allow, self.identifier_case);
+        match mode {
+            LintMode::Strict if !findings.is_empty() => Err(lint_error(&findings)),
+            _ => Ok(findings),
+        }
+    }
+    
+    /// Add an executed query's lineage to the log, when lineage is recorded
+    async fn record_lineage(&self, sql: &str, query: &InternalQuery, dispatcher: &dyn Dispatcher, result: &QueryResult) {
+        let Some(log) = &self.lineage else {
+            return;
+        };
+        log.record(LineageRecord {
+            query_id: current_query_tag(),
+            user: query.user.clone(),
+            sql: sql.trim().to_string(),
+            executed_at: Utc::now(),
+            lineage: self.lineage_of(query, dispatcher, Some(result)).await,
+        });
+    }
+    
+    /// Lineage of a query. The columns of its sources are read from their connectors only
+    /// when tracing needs them and the result of a `SELECT *` from one source cannot tell.
+    async fn lineage_of(&self, query: &InternalQuery, dispatcher: &dyn Dispatcher, result: Option<&QueryResult>) -> QueryLineage {
+        let mut source_columns = HashMap::new();
+        let selects_all = matches!(query.projections.as_slice(), [column] if column.name == "*" && column.source.is_none());
+        match (query.sources.as_slice(), result) {
+            _ if !QueryLineage::needs_source_columns(query) => {}
+            ([source], Some(result)) if selects_all && query.subqueries.is_empty() => {
+                let columns = result.columns.iter().map(|column| column.name.clone()).collect();
+                source_columns.insert(format!("{}.{}", source.object_type, source.identifier), columns);
+            }
+            _ => {
+                for source in QueryLineage::sources(query) {
+                    // Sources whose schema cannot be read are traced as `*`
+                    let schema = match dispatcher.get_connector(&source.object_type) {
+                        Some(connector) => connector.get_schema(&source.identifier).await.ok(),
+                        None => None,
+                    };
+                    if let Some(schema) = schema {
+                        let columns = schema.columns.into_iter().map(|column| column.name).collect();
+                        source_columns.insert(format!("{}.{}", source.object_type, source.identifier), columns);
+                    }
+                }
+            }
+        }
+        QueryLineage::of(query, &source_columns, self.identifier_case)
+    }
+    
+    /// Have the connector of a source drop what it cached for it, reporting the entries dropped
+    async fn refresh_source(&self, source: &DataSource) -> NirvResult<QueryResult> {
+        let connector = self.dispatcher.read().await.get_connector(&source.object_type)
+            .ok_or_else(|| DispatcherError::UnregisteredObjectType(source.object_type.clone()))?;
+        let dropped = connector.refresh(&source.identifier).await?;
+        Ok(QueryResult { affected_rows: Some(dropped), ..QueryResult::new() })
+    }
+    
+    /// Apply an access control statement to the catalog; users other than trusted callers
+    /// need a role granted ADMIN, and anonymous network clients may not run them at all
+    async fn administer(&self, session: &Session, statement: AccessStatement) -> NirvResult<QueryResult> {
+        if session.is_anonymous() {
+            return Err(DispatcherError::AccessDenied(
+                "access control statements need an authenticated user".to_string()
+            ).into());
+        }
+        let user = session.user();
+        let catalog = self.dispatcher.read().await.access_catalog().ok_or_else(|| NirvError::Configuration(
+            "Access control is not enabled; set security.authorization.enabled".to_string()
+        ))?;
+        let mut catalog = catalog.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(user) = user.filter(|user| !catalog.is_admin(user)) {
+            return Err(DispatcherError::AccessDenied(format!("user '{}' lacks ADMIN", user)).into());
+        }
+        catalog.apply(statement)?;
+        Ok(QueryResult::new())
+    }
+}
+
+/// Render a result's times in the session's time zone; without one they are left as sources
+/// returned them, normalized to UTC
+fn render_in(result: QueryResult, time_zone: Option<SessionTimeZone>) -> QueryResult {
+    match time_zone {
+        Some(time_zone) => time_zone.render_result(result),
+        None => result,
+    }
+}
+
+#[async_trait]
+impl QueryHandler for Engine {
+    async fn handle_query(&self, sql: &str) -> NirvResult<QueryResult> {
+        self.execute_query(sql).await
+    }
+
+    async fn handle_query_for(&self, user: Option<&str>, sql: &str) -> NirvResult<QueryResult> {
+        self.engine_ref().handle_query_for(user, sql).await
+    }
+}
+
+#[async_trait]
+impl SchemaCatalog for Engine {
+    async fn list_tables(&self) -> NirvResult<Vec<CatalogTable>> {
+        catalog_tables(&self.dispatcher).await
+    }
+}
+
+#[async_trait]
+impl SchemaCatalog for EngineRef {
+    async fn list_tables(&self) -> NirvResult<Vec<CatalogTable>> {
+        catalog_tables(&self.dispatcher).await
+    }
+}
+
+#[cfg(feature = "web-console")]
+#[async_trait]
+impl ConsoleHandler for Engine {
+    async fn source_status(&self) -> Vec<SourceStatus> {
+        source_status(&self.dispatcher).await
+    }
+}
+
+#[cfg(feature = "web-console")]
+#[async_trait]
+impl ConsoleHandler for EngineRef {
+    async fn source_status(&self) -> Vec<SourceStatus> {
+        source_status(&self.dispatcher).await
+    }
+}
+
+/// Registered connectors ordered by object type
+async fn registered_connectors(dispatcher: &RwLock<dyn Dispatcher>) -> Vec<(String, Arc<dyn Connector>)> {
+    let dispatcher = dispatcher.read().await;
+    let mut object_types = dispatcher.list_available_types();
+    object_types.sort();
+    object_types.into_iter()
+        .filter_map(|object_type| dispatcher.get_connector(&object_type).map(|connector| (object_type, connector)))
+        .collect()
+}
+
+async fn catalog_tables(dispatcher: &RwLock<dyn Dispatcher>) -> NirvResult<Vec<CatalogTable>> {
+    let mut tables = Vec::new();
+    for (object_type, connector) in registered_connectors(dispatcher).await {
+        for name in connector.list_objects().await? {
+            // Objects whose schema cannot be read are left out rather than failing the listing
+            if let Ok(schema) = dispatcher.read().await.describe_object(&object_type, &name).await {
+                tables.push(CatalogTable { owner: object_type.clone(), name, schema });
+            }
+        }
+    }
+    Ok(tables)
+}
+
+/// Each registered source's connection state and whether it can list its objects
+#[cfg(feature = "web-console")]
+async fn source_status(dispatcher: &RwLock<dyn Dispatcher>) -> Vec<SourceStatus> {
+    let mut sources = Vec::new();
+    for (object_type, connector) in registered_connectors(dispatcher).await {
+        let (objects, error) = match connector.list_objects().await {
+            Ok(objects) => (Some(objects.len()), connector.health_error()),
+            Err(e) => (None, Some(connector.health_error().unwrap_or_else(|| e.to_string()))),
+        };
+        sources.push(SourceStatus {
+            object_type,
+            connector_type: connector.get_connector_type(),
+            connected: connector.is_connected(),
+            objects,
+            error,
+        });
+    }
+    sources
+}
+
+/// Name that selects the built-in planner or executor in the configuration
+pub const DEFAULT_COMPONENT: &str = "default";
+
+/// Creates a query planner from the engine configuration
+pub type QueryPlannerFactory = Box<dyn Fn(&EngineConfig) -> Arc<dyn QueryPlanner> + Send + Sync>;
+
+/// Creates a query executor from the engine configuration
+pub type QueryExecutorFactory = Box<dyn Fn(&EngineConfig) -> Arc<RwLock<dyn QueryExecutor>> + Send + Sync>;
+
+/// The built-in executor, matching identifiers as the dispatcher does
+fn default_query_executor(config: &EngineConfig) -> Arc<RwLock<dyn QueryExecutor>> {
+    Arc::new(RwLock::new(
+        DefaultQueryExecutor::new().with_identifier_case(config.dispatcher.identifier_case)
+    ))
 }
 
 /// Builder for creating Engine instances
@@ -339,6 +1349,13 @@ pub struct EngineBuilder {
     query_planner: Option<Arc<dyn QueryPlanner>>,
     query_executor: Option<Arc<RwLock<dyn QueryExecutor>>>,
     dispatcher: Option<Arc<RwLock<dyn Dispatcher>>>,
+    transaction_journal: Option<std::path::PathBuf>,
+    query_rewriters: Vec<Arc<dyn QueryRewriter>>,
+    result_transformers: Vec<Arc<dyn ResultTransformer>>,
+    /// Planners the configuration can select by name
+    query_planners: HashMap<String, QueryPlannerFactory>,
+    /// Executors the configuration can select by name
+    query_executors: HashMap<String, QueryExecutorFactory>,
 }
 
 impl EngineBuilder {
@@ -350,6 +1367,11 @@ impl EngineBuilder {
             query_planner: None,
             query_executor: None,
             dispatcher: None,
+            transaction_journal: None,
+            query_rewriters: Vec::new(),
+            result_transformers: Vec::new(),
+            query_planners: HashMap::new(),
+            query_executors: HashMap::new(),
         }
     }
     
@@ -365,41 +1387,120 @@ impl EngineBuilder {
         self
     }
     
-    /// Set a custom query planner
+    /// Set a custom query planner, used whatever planner the configuration selects
     pub fn with_query_planner(mut self, planner: Arc<dyn QueryPlanner>) -> Self {
         self.query_planner = Some(planner);
         self
     }
     
-    /// Set a custom query executor
+    /// Set a custom query executor, used whatever executor the configuration selects
     pub fn with_query_executor(mut self, executor: Arc<RwLock<dyn QueryExecutor>>) -> Self {
         self.query_executor = Some(executor);
         self
     }
     
+    /// Make a query planner selectable as `components.planner = "<name>"`
+    pub fn register_query_planner<F>(mut self, name: &str, factory: F) -> Self
+    where
+        F: Fn(&EngineConfig) -> Arc<dyn QueryPlanner> + Send + Sync + 'static,
+    {
+        self.query_planners.insert(name.to_string(), Box::new(factory));
+        self
+    }
+    
+    /// Make a query executor selectable as `components.executor = "<name>"`
+    pub fn register_query_executor<F>(mut self, name: &str, factory: F) -> Self
+    where
+        F: Fn(&EngineConfig) -> Arc<RwLock<dyn QueryExecutor>> + Send + Sync + 'static,
+    {
+        self.query_executors.insert(name.to_string(), Box::new(factory));
+        self
+    }
+    
     /// Set a custom dispatcher
     pub fn with_dispatcher(mut self, dispatcher: Arc<RwLock<dyn Dispatcher>>) -> Self {
         self.dispatcher = Some(dispatcher);
         self
     }
     
-    /// Build the engine
+    /// Enable two-phase commit for cross-connector writes
+    pub fn with_transaction_journal<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
+        self.transaction_journal = Some(path.as_ref().to_path_buf());
+        self
+    }
+    
+    /// Add a query rewriter, run after any configured rewriters
+    pub fn with_query_rewriter(mut self, rewriter: Arc<dyn QueryRewriter>) -> Self {
+        self.query_rewriters.push(rewriter);
+        self
+    }
+    
+    /// Add a result transformer, run after any configured transformers
+    pub fn with_result_transformer(mut self, transformer: Arc<dyn ResultTransformer>) -> Self {
+        self.result_transformers.push(transformer);
+        self
+    }
+    
+    /// Build the engine. Components not set on the builder are the ones the configuration
+    /// selects by name, or the built-in ones; naming an unregistered planner or executor fails.
     pub fn build(self) -> NirvResult<Engine> {
-        let config = self.config.unwrap_or_else(EngineConfig::default);
+        let config = self.config.unwrap_or_default();
         
-        if let (Some(parser), Some(planner), Some(executor), Some(dispatcher)) = (
-            self.query_parser,
-            self.query_planner,
-            self.query_executor,
-            self.dispatcher,
-        ) {
-            Ok(Engine::with_components(config, parser, planner, executor, dispatcher))
-        } else {
-            Ok(Engine::new(config))
+        let query_parser = match self.query_parser {
+            Some(parser) => parser,
+            None => Arc::new(DefaultQueryParser::new()?),
+        };
+        let query_planner = match (self.query_planner, config.components.planner.as_deref()) {
+            (Some(planner), _) => Some(planner),
+            (None, None | Some(DEFAULT_COMPONENT)) => None,
+            (None, Some(name)) => {
+                let factory = self.query_planners.get(name)
+                    .ok_or_else(|| unknown_component("query planner", name, self.query_planners.keys()))?;
+                Some(factory(&config))
+            }
+        };
+        let query_executor = match (self.query_executor, config.components.executor.as_deref()) {
+            (Some(executor), _) => Some(executor),
+            (None, None | Some(DEFAULT_COMPONENT)) => None,
+            (None, Some(name)) => {
+                let factory = self.query_executors.get(name)
+                    .ok_or_else(|| unknown_component("query executor", name, self.query_executors.keys()))?;
+                Some(factory(&config))
+            }
+        };
+        // Queries run through the planner and executor once either is not the built-in one
+        let planned_execution = query_planner.is_some() || query_executor.is_some();
+        let query_planner = query_planner.unwrap_or_else(|| Arc::new(DefaultQueryPlanner::new()));
+        let query_executor = query_executor.unwrap_or_else(|| default_query_executor(&config));
+        let dispatcher = self.dispatcher
+            .unwrap_or_else(|| Arc::new(RwLock::new(DefaultDispatcher::new())));
+        
+        let mut engine = Engine::with_components(config, query_parser, query_planner, query_executor, dispatcher);
+        engine.planned_execution = planned_execution;
+        
+        if let Some(path) = self.transaction_journal {
+            engine.set_transaction_journal(path);
+        }
+        
+        for rewriter in self.query_rewriters {
+            engine.add_query_rewriter(rewriter);
         }
+        
+        for transformer in self.result_transformers {
+            engine.add_result_transformer(transformer);
+        }
+        
+        Ok(engine)
     }
 }
 
+/// Error for a configured component no implementation was registered under
+fn unknown_component<'a>(kind: &str, name: &str, registered: impl Iterator<Item = &'a String>) -> NirvError {
+    let mut names: Vec<&str> = std::iter::once(DEFAULT_COMPONENT).chain(registered.map(String::as_str)).collect();
+    names.sort();
+    NirvError::Configuration(format!("Unknown {} '{}'; expected one of: {}", kind, name, names.join(", ")))
+}
+
 impl Default for EngineBuilder {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,271 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::utils::{
+    types::{ColumnMetadata, DataSource, DataType, ForeignKey, QueryResult, Row, Schema, SourceUri, Value},
+    error::NirvResult,
+};
+
+/// Why two objects look joinable on a pair of column lists, strongest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JoinBasis {
+    /// A declared foreign key of one object references the other
+    ForeignKey,
+    /// A column named after the other object, like `user_id`, holds its key
+    KeyName,
+    /// Both objects have a key-like column of this name and type
+    SharedColumn,
+}
+
+impl JoinBasis {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JoinBasis::ForeignKey => "foreign_key",
+            JoinBasis::KeyName => "key_name",
+            JoinBasis::SharedColumn => "shared_column",
+        }
+    }
+}
+
+/// Columns of the left object to equate with those of the right one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinSuggestion {
+    pub left_columns: Vec<String>,
+    pub right_columns: Vec<String>,
+    pub basis: JoinBasis,
+}
+
+impl JoinSuggestion {
+    /// The `ON` condition with the objects under the given aliases
+    pub fn condition(&self, left_alias: &str, right_alias: &str) -> String {
+        self.left_columns.iter().zip(&self.right_columns)
+            .map(|(left, right)| format!("{}.{} = {}.{}", left_alias, left, right_alias, right))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+
+    /// Columns for `JOIN ... USING`, when both objects name them alike
+    pub fn using_columns(&self) -> Option<&[String]> {
+        (self.left_columns == self.right_columns).then_some(self.left_columns.as_slice())
+    }
+}
+
+/// Join conditions between two objects, from their declared foreign keys first, then from
+/// columns named after the other object's key, then from key-like columns they share
+pub fn suggest_joins(left: &DataSource, left_schema: &Schema, right: &DataSource, right_schema: &Schema) -> Vec<JoinSuggestion> {
+    let mut suggestions = Vec::new();
+    for foreign_key in references(left, left_schema, right) {
+        suggestions.push(JoinSuggestion {
+            left_columns: foreign_key.columns.clone(),
+            right_columns: foreign_key.referenced_columns.clone(),
+            basis: JoinBasis::ForeignKey,
+        });
+    }
+    for foreign_key in references(right, right_schema, left) {
+        suggestions.push(JoinSuggestion {
+            left_columns: foreign_key.referenced_columns.clone(),
+            right_columns: foreign_key.columns.clone(),
+            basis: JoinBasis::ForeignKey,
+        });
+    }
+    for (column, key) in named_after(left_schema, right, right_schema) {
+        suggestions.push(JoinSuggestion { left_columns: vec![column], right_columns: vec![key], basis: JoinBasis::KeyName });
+    }
+    for (column, key) in named_after(right_schema, left, left_schema) {
+        suggestions.push(JoinSuggestion { left_columns: vec![key], right_columns: vec![column], basis: JoinBasis::KeyName });
+    }
+    for column in shared_key_columns(left_schema, right_schema) {
+        suggestions.push(JoinSuggestion { left_columns: vec![column.clone()], right_columns: vec![column], basis: JoinBasis::SharedColumn });
+    }
+
+    // The same columns found on several grounds are suggested once, on the strongest
+    let mut unique: Vec<JoinSuggestion> = Vec::new();
+    for suggestion in suggestions {
+        let same_columns = |other: &JoinSuggestion| other.left_columns == suggestion.left_columns && other.right_columns == suggestion.right_columns;
+        if !unique.iter().any(same_columns) {
+            unique.push(suggestion);
+        }
+    }
+    unique
+}
+
+/// Foreign keys of an object that reference the other one, which must be in the same source
+fn references<'a>(source: &DataSource, schema: &'a Schema, other: &DataSource) -> impl Iterator<Item = &'a ForeignKey> {
+    let same_source = source.object_type.eq_ignore_ascii_case(&other.object_type);
+    let other = other.identifier.clone();
+    schema.foreign_keys.iter().filter(move |foreign_key| {
+        same_source
+            && foreign_key.referenced_object.eq_ignore_ascii_case(&other)
+            && foreign_key.columns.len() == foreign_key.referenced_columns.len()
+    })
+}
+
+/// Columns of a schema named after the other object and its single-column key, like
+/// `user_id` or `users_id` for the `id` of `users`, paired with that key
+fn named_after(schema: &Schema, other: &DataSource, other_schema: &Schema) -> Vec<(String, String)> {
+    let Some(key) = single_key(other_schema) else {
+        return Vec::new();
+    };
+    let names: Vec<String> = object_names(&other.identifier).into_iter()
+        .flat_map(|name| [format!("{}_{}", singular(&name), key), format!("{}_{}", name, key)])
+        .collect();
+    schema.columns.iter()
+        .filter(|column| names.iter().any(|name| column.name.eq_ignore_ascii_case(name)))
+        .map(|column| (column.name.clone(), key.to_string()))
+        .collect()
+}
+
+/// Columns both schemas have with the same type that look like keys: ending in `_id`, or
+/// the key of just one of them, since two objects rarely join on their own keys
+fn shared_key_columns(left: &Schema, right: &Schema) -> Vec<String> {
+    let is_key = |schema: &Schema, name: &str| match &schema.primary_key {
+        Some(key) => key.iter().any(|column| column.eq_ignore_ascii_case(name)),
+        None => single_key(schema).is_some_and(|key| key.eq_ignore_ascii_case(name)),
+    };
+    left.columns.iter()
+        .filter(|column| right.columns.iter().any(|other| other.name == column.name && other.data_type == column.data_type))
+        .filter(|column| {
+            column.name.to_ascii_lowercase().ends_with("_id") || is_key(left, &column.name) != is_key(right, &column.name)
+        })
+        .map(|column| column.name.clone())
+        .collect()
+}
+
+/// The single column of a schema's primary key, or its `id` column when it declares none
+fn single_key(schema: &Schema) -> Option<&str> {
+    match &schema.primary_key {
+        Some(key) => match key.as_slice() {
+            [column] => Some(column.as_str()),
+            _ => None,
+        },
+        None => schema.columns.iter().find(|column| column.name.eq_ignore_ascii_case("id")).map(|column| column.name.as_str()),
+    }
+}
+
+/// Names an identifier could call its object by: the parts of its last path segment, so
+/// `public.users` and `users.csv` both give `users`
+fn object_names(identifier: &str) -> Vec<String> {
+    let segment = identifier.rsplit(['/', '\\']).next().unwrap_or(identifier);
+    segment.split('.').filter(|part| !part.is_empty()).map(str::to_ascii_lowercase).collect()
+}
+
+fn singular(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if let Some(stem) = name.strip_suffix('s').filter(|stem| !stem.ends_with('s')) {
+        stem.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// The objects of a `SUGGEST JOINS source('left') [alias], source('right') [alias]`
+/// statement, or None for any other statement
+pub fn parse_suggest_joins(sql: &str) -> Option<NirvResult<(DataSource, DataSource)>> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(
+        r#"(?is)^\s*SUGGEST\s+JOINS?\s+(?:source\s*\(\s*)?['"]([^'"]+)['"]\s*\)?(?:\s*(?:AS\s+)?(\w+))?\s*,\s*(?:source\s*\(\s*)?['"]([^'"]+)['"]\s*\)?(?:\s*(?:AS\s+)?(\w+))?\s*;?\s*$"#
+    ).unwrap());
+    let captures = pattern.captures(sql)?;
+    let source = |uri: usize, alias: usize| -> NirvResult<DataSource> {
+        let alias = captures.get(alias).map(|alias| alias.as_str().to_string());
+        Ok(SourceUri::parse(&captures[uri])?.to_data_source(alias))
+    };
+    Some(source(1, 2).and_then(|left| Ok((left, source(3, 4)?))))
+}
+
+/// Alias a suggested condition qualifies an object's columns with: its own, or the name of
+/// the object
+pub fn join_alias(source: &DataSource) -> String {
+    source.alias.clone().unwrap_or_else(|| {
+        object_names(&source.identifier).into_iter()
+            .find(|name| *name != "public")
+            .unwrap_or_else(|| source.identifier.clone())
+    })
+}
+
+/// The result of `SUGGEST JOINS`: one row per suggestion with its condition and, when both
+/// sides name the columns alike, the `USING` list
+pub fn join_suggestions_result(suggestions: &[JoinSuggestion], left: &DataSource, right: &DataSource) -> QueryResult {
+    let column = |name: &str| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true };
+    let columns = vec![
+        column("left_columns"),
+        column("right_columns"),
+        column("basis"),
+        column("condition"),
+        column("using"),
+    ];
+
+    let (left_alias, right_alias) = (join_alias(left), join_alias(right));
+    let rows: Vec<Row> = suggestions.iter().map(|suggestion| Row::new(vec![
+        Value::Text(suggestion.left_columns.join(", ")),
+        Value::Text(suggestion.right_columns.join(", ")),
+        Value::Text(suggestion.basis.as_str().to_string()),
+        Value::Text(suggestion.condition(&left_alias, &right_alias)),
+        suggestion.using_columns().map_or(Value::Null, |columns| Value::Text(columns.join(", "))),
+    ])).collect();
+
+    QueryResult {
+        columns,
+        affected_rows: Some(rows.len() as u64),
+        rows,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn schema(name: &str, columns: &[&str], primary_key: &[&str], foreign_keys: Vec<ForeignKey>) -> Schema {
+        Schema {
+            name: name.to_string(),
+            columns: columns.iter().map(|column| ColumnMetadata { name: column.to_string(), data_type: DataType::Integer, nullable: true }).collect(),
+            primary_key: (!primary_key.is_empty()).then(|| primary_key.iter().map(|column| column.to_string()).collect()),
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys,
+        }
+    }
+
+    fn source(object_type: &str, identifier: &str) -> DataSource {
+        DataSource { object_type: object_type.to_string(), identifier: identifier.to_string(), alias: None }
+    }
+
+    #[test]
+    fn test_suggest_joins_from_foreign_keys_and_names() {
+        let orders = schema("orders", &["id", "customer_ref", "user_id", "region_id"], &["id"], vec![ForeignKey {
+            columns: vec!["customer_ref".to_string()],
+            referenced_object: "customers".to_string(),
+            referenced_columns: vec!["id".to_string()],
+        }]);
+        let customers = schema("customers", &["id", "region_id"], &["id"], Vec::new());
+
+        let suggestions = suggest_joins(&source("postgres", "orders"), &orders, &source("postgres", "customers"), &customers);
+        assert_eq!(suggestions.iter().map(|s| (s.left_columns[0].as_str(), s.right_columns[0].as_str(), s.basis)).collect::<Vec<_>>(), vec![
+            ("customer_ref", "id", JoinBasis::ForeignKey),
+            ("region_id", "region_id", JoinBasis::SharedColumn),
+        ]);
+        assert_eq!(suggestions[0].condition("o", "c"), "o.customer_ref = c.id");
+        assert_eq!(suggestions[1].using_columns(), Some(&["region_id".to_string()][..]));
+
+        // Foreign keys only hold within a source; across sources the names still tell
+        let users = schema("users", &["id", "name"], &[], Vec::new());
+        let suggestions = suggest_joins(&source("file", "users.csv"), &users, &source("postgres", "orders"), &orders);
+        assert_eq!(suggestions, vec![JoinSuggestion {
+            left_columns: vec!["id".to_string()],
+            right_columns: vec!["user_id".to_string()],
+            basis: JoinBasis::KeyName,
+        }]);
+    }
+
+    #[test]
+    fn test_parse_suggest_joins() {
+        let (left, right) = parse_suggest_joins("SUGGEST JOINS source('postgres.orders') o, source('file.users.csv');").unwrap().unwrap();
+        assert_eq!((left.identifier.as_str(), left.alias.as_deref()), ("orders", Some("o")));
+        assert_eq!((right.object_type.as_str(), right.alias.as_deref()), ("file", None));
+        assert_eq!(join_alias(&right), "users");
+        assert!(parse_suggest_joins("SELECT * FROM source('postgres.orders')").is_none());
+    }
+}
@@ -0,0 +1,424 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use crate::engine::{ApproxAggregate, ComputedColumn};
+use crate::utils::{
+    types::{Column, ColumnMetadata, DataSource, DataType, InternalQuery, QueryResult, Row, Value, COUNT_ALL, SUBQUERY_OBJECT_TYPE},
+    identifier::{IdentifierCase, split_qualifier},
+    hash_function::RowHash,
+    date_function::{is_fetched_at, NOW_FUNCTION},
+};
+
+/// Queries kept for the `system.lineage` table when no other limit is configured
+pub const DEFAULT_LINEAGE_QUERIES: usize = 1000;
+
+/// The query of an `EXPLAIN LINEAGE <query>` statement, or None for any other statement
+pub fn parse_explain_lineage(sql: &str) -> Option<&str> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(r"(?is)^\s*EXPLAIN\s+LINEAGE\s+(.+)$").unwrap());
+    pattern.captures(sql).and_then(|captures| captures.get(1)).map(|query| query.as_str())
+}
+
+/// A source column read to compute an output column; `*` when the whole row counts, as
+/// for `COUNT(*)` or a source whose columns are not known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineageInput {
+    pub object_type: String,
+    pub object: String,
+    pub column: String,
+}
+
+/// Source columns one output column was computed from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnLineage {
+    pub output: String,
+    pub inputs: Vec<LineageInput>,
+}
+
+/// Where each output column of a query came from, traced through its derived tables.
+/// Columns that only filter, join or order rows are not part of it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QueryLineage {
+    pub columns: Vec<ColumnLineage>,
+}
+
+impl QueryLineage {
+    /// Lineage of a query, given the column names of its sources by `type.identifier`.
+    /// Sources missing from `source_columns` are read as `*`, and unqualified columns of
+    /// a join are attributed to every source that may have them.
+    pub fn of(query: &InternalQuery, source_columns: &HashMap<String, Vec<String>>, identifier_case: IdentifierCase) -> Self {
+        let subqueries: Vec<QueryLineage> = query.subqueries.iter()
+            .map(|subquery| Self::of(subquery, source_columns, identifier_case))
+            .collect();
+        let tracer = Tracer { query, source_columns, subqueries, identifier_case };
+        let mut columns = Vec::new();
+        for projection in &query.projections {
+            if projection.name == "*" {
+                columns.extend(tracer.expand_all(projection.source.as_deref()));
+                continue;
+            }
+            let mut inputs = Vec::new();
+            for (qualifier, column) in input_columns(projection) {
+                for input in tracer.trace(qualifier.as_deref(), &column) {
+                    if !inputs.contains(&input) {
+                        inputs.push(input);
+                    }
+                }
+            }
+            columns.push(ColumnLineage { output: projection.alias.clone().unwrap_or_else(|| projection.name.clone()), inputs });
+        }
+        Self { columns }
+    }
+
+    /// Whether tracing the query needs the columns of its sources: it selects `*` or
+    /// reads several sources, here or in a derived table
+    pub fn needs_source_columns(query: &InternalQuery) -> bool {
+        query.sources.len() > 1
+            || query.projections.iter().any(|col| col.name == "*")
+            || query.subqueries.iter().any(Self::needs_source_columns)
+    }
+
+    /// Sources a query reads, including those of its derived tables
+    pub fn sources(query: &InternalQuery) -> Vec<&DataSource> {
+        let mut sources: Vec<&DataSource> = query.sources.iter()
+            .filter(|source| source.object_type != SUBQUERY_OBJECT_TYPE)
+            .collect();
+        sources.extend(query.subqueries.iter().flat_map(Self::sources));
+        sources
+    }
+
+    /// The result of `EXPLAIN LINEAGE`: one row per output column and source column
+    pub fn to_result(&self) -> QueryResult {
+        let columns = vec![
+            text_column("output_column"),
+            text_column("object_type"),
+            text_column("object"),
+            text_column("column_name"),
+        ];
+        let rows: Vec<Row> = self.rows().map(Row::new).collect();
+        QueryResult {
+            columns,
+            affected_rows: Some(rows.len() as u64),
+            rows,
+            ..Default::default()
+        }
+    }
+
+    /// Output column and source fields of each row; columns computed from no source, such
+    /// as `NOW()`, have one row with NULL source fields
+    fn rows(&self) -> impl Iterator<Item = Vec<Value>> + '_ {
+        self.columns.iter().flat_map(|column| {
+            let output = Value::Text(column.output.clone());
+            let rows: Vec<Vec<Value>> = match column.inputs.as_slice() {
+                [] => vec![vec![output, Value::Null, Value::Null, Value::Null]],
+                inputs => inputs.iter().map(|input| vec![
+                    output.clone(),
+                    Value::Text(input.object_type.clone()),
+                    Value::Text(input.object.clone()),
+                    Value::Text(input.column.clone()),
+                ]).collect(),
+            };
+            rows
+        })
+    }
+}
+
+/// Columns a projection reads, with the qualifier they were written with
+fn input_columns(projection: &Column) -> Vec<(Option<String>, String)> {
+    let qualified = |name: &str| match split_qualifier(name) {
+        Some((qualifier, column)) => (Some(qualifier.to_string()), column.to_string()),
+        None => (projection.source.clone(), name.to_string()),
+    };
+    let name = projection.name.as_str();
+    if name == NOW_FUNCTION || is_fetched_at(name) {
+        return Vec::new();
+    }
+    if name == COUNT_ALL {
+        return vec![qualified("*")];
+    }
+    if let Some(aggregate) = ApproxAggregate::parse(name) {
+        return vec![qualified(&aggregate.column)];
+    }
+    if let Some(computed) = ComputedColumn::parse(name) {
+        return vec![qualified(computed.column())];
+    }
+    match RowHash::parse(name) {
+        Some(hash) if hash.columns.is_empty() => vec![qualified("*")],
+        Some(hash) => hash.columns.iter().map(|column| qualified(column)).collect(),
+        None => vec![qualified(name)],
+    }
+}
+
+/// Traces the columns of one query to the sources it reads
+struct Tracer<'a> {
+    query: &'a InternalQuery,
+    source_columns: &'a HashMap<String, Vec<String>>,
+    /// Lineage of the derived tables, by position
+    subqueries: Vec<QueryLineage>,
+    identifier_case: IdentifierCase,
+}
+
+impl Tracer<'_> {
+    /// Column names of a source, when known
+    fn columns_of(&self, source: &DataSource) -> Option<Vec<String>> {
+        if source.object_type == SUBQUERY_OBJECT_TYPE {
+            let lineage = self.subquery(source)?;
+            let columns: Vec<String> = lineage.columns.iter().map(|column| column.output.clone()).collect();
+            // A derived table selecting `*` of an unknown source has unknown columns too
+            return (!columns.iter().any(|column| column == "*")).then_some(columns);
+        }
+        self.source_columns.get(&format!("{}.{}", source.object_type, source.identifier)).cloned()
+    }
+
+    fn subquery(&self, source: &DataSource) -> Option<&QueryLineage> {
+        source.identifier.parse::<usize>().ok().and_then(|index| self.subqueries.get(index))
+    }
+
+    /// Sources a column written with the qualifier may come from
+    fn candidates(&self, qualifier: Option<&str>, column: &str) -> Vec<&DataSource> {
+        let sources = &self.query.sources;
+        if let Some(qualifier) = qualifier {
+            return sources.iter()
+                .filter(|source| source.alias.as_deref().unwrap_or(&source.identifier) == qualifier
+                    || self.identifier_case.matches(qualifier, &source.identifier))
+                .collect();
+        }
+        if sources.len() <= 1 || column == "*" {
+            return sources.iter().collect();
+        }
+        let having: Vec<&DataSource> = sources.iter()
+            .filter(|source| self.columns_of(source).is_some_and(|columns| columns.iter().any(|name| self.identifier_case.matches(column, name))))
+            .collect();
+        if !having.is_empty() {
+            return having;
+        }
+        sources.iter().filter(|source| self.columns_of(source).is_none()).collect()
+    }
+
+    /// Source columns a column of the query's sources comes from
+    fn trace(&self, qualifier: Option<&str>, column: &str) -> Vec<LineageInput> {
+        self.candidates(qualifier, column).into_iter()
+            .flat_map(|source| self.trace_in(source, column))
+            .collect()
+    }
+
+    fn trace_in(&self, source: &DataSource, column: &str) -> Vec<LineageInput> {
+        if source.object_type != SUBQUERY_OBJECT_TYPE {
+            return vec![LineageInput {
+                object_type: source.object_type.clone(),
+                object: source.identifier.clone(),
+                column: column.to_string(),
+            }];
+        }
+        let Some(lineage) = self.subquery(source) else {
+            return Vec::new();
+        };
+        if column == "*" {
+            return lineage.columns.iter().flat_map(|output| output.inputs.iter().cloned()).collect();
+        }
+        if let Some(output) = lineage.columns.iter().find(|output| self.identifier_case.matches(column, &output.output)) {
+            return output.inputs.clone();
+        }
+        // The derived table selected `*` of a source whose columns are not known
+        lineage.columns.iter()
+            .filter(|output| output.output == "*")
+            .flat_map(|output| output.inputs.iter())
+            .map(|input| match input.column.as_str() {
+                "*" => LineageInput { column: column.to_string(), ..input.clone() },
+                _ => input.clone(),
+            })
+            .collect()
+    }
+
+    /// Output columns of `*` or `qualifier.*`, one per source column when they are known
+    fn expand_all(&self, qualifier: Option<&str>) -> Vec<ColumnLineage> {
+        let mut columns = Vec::new();
+        for source in self.candidates(qualifier, "*") {
+            match self.columns_of(source) {
+                Some(names) => columns.extend(names.into_iter().map(|name| ColumnLineage {
+                    inputs: self.trace_in(source, &name),
+                    output: name,
+                })),
+                None => columns.push(ColumnLineage { output: "*".to_string(), inputs: self.trace_in(source, "*") }),
+            }
+        }
+        columns
+    }
+}
+
+/// Lineage of an executed query, as recorded in `system.lineage`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineageRecord {
+    /// Tag the query's sources saw it under
+    pub query_id: Option<String>,
+    pub user: Option<String>,
+    pub sql: String,
+    pub executed_at: DateTime<Utc>,
+    pub lineage: QueryLineage,
+}
+
+/// Lineage of the most recent queries, served as `system.lineage`
+#[derive(Debug)]
+pub struct LineageLog {
+    records: Mutex<VecDeque<LineageRecord>>,
+    max_queries: usize,
+}
+
+impl LineageLog {
+    /// A log keeping the lineage of at most this many queries
+    pub fn new(max_queries: usize) -> Self {
+        Self {
+            records: Mutex::new(VecDeque::new()),
+            max_queries,
+        }
+    }
+
+    pub fn record(&self, record: LineageRecord) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.push_back(record);
+        while records.len() > self.max_queries {
+            records.pop_front();
+        }
+    }
+
+    /// Recorded lineage, oldest first
+    pub fn records(&self) -> Vec<LineageRecord> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    /// The `system.lineage` table: one row per query, output column and source column
+    pub fn lineage_table(&self) -> QueryResult {
+        let columns = vec![
+            text_column("query_id"),
+            text_column("executed_at"),
+            text_column("user_name"),
+            text_column("query"),
+            text_column("output_column"),
+            text_column("object_type"),
+            text_column("object"),
+            text_column("column_name"),
+        ];
+
+        let text = |value: &Option<String>| value.clone().map_or(Value::Null, Value::Text);
+        let mut rows = Vec::new();
+        for record in self.records() {
+            for values in record.lineage.rows() {
+                let mut row = vec![
+                    text(&record.query_id),
+                    Value::Text(record.executed_at.to_rfc3339()),
+                    text(&record.user),
+                    Value::Text(record.sql.clone()),
+                ];
+                row.extend(values);
+                rows.push(Row::new(row));
+            }
+        }
+
+        QueryResult {
+            columns,
+            affected_rows: Some(rows.len() as u64),
+            rows,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for LineageLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_LINEAGE_QUERIES)
+    }
+}
+
+fn text_column(name: &str) -> ColumnMetadata {
+    ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{DefaultQueryParser, QueryParser};
+
+    async fn lineage(sql: &str, source_columns: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        let query = DefaultQueryParser::new().unwrap().parse_sql(sql).await.unwrap();
+        let source_columns = source_columns.iter()
+            .map(|(source, columns)| (source.to_string(), columns.iter().map(|column| column.to_string()).collect()))
+            .collect();
+        QueryLineage::of(&query, &source_columns, IdentifierCase::default()).columns.into_iter()
+            .map(|column| (
+                column.output,
+                column.inputs.iter().map(|input| format!("{}.{}.{}", input.object_type, input.object, input.column)).collect(),
+            ))
+            .collect()
+    }
+
+    fn traced(output: &str, inputs: &[&str]) -> (String, Vec<String>) {
+        (output.to_string(), inputs.iter().map(|input| input.to_string()).collect())
+    }
+
+    #[test]
+    fn test_parse_explain_lineage() {
+        assert_eq!(parse_explain_lineage("explain  lineage SELECT id FROM source('mock.users')"), Some("SELECT id FROM source('mock.users')"));
+        assert_eq!(parse_explain_lineage("EXPLAIN SELECT 1"), None);
+        assert_eq!(parse_explain_lineage("SELECT lineage FROM source('mock.users')"), None);
+    }
+
+    #[tokio::test]
+    async fn test_lineage_of_single_source() {
+        let columns = lineage("SELECT id AS user_id, MD5(email), NOW() FROM source('mock.users')", &[]).await;
+        assert_eq!(columns[0], traced("user_id", &["mock.users.id"]));
+        assert_eq!(columns[1].1, vec!["mock.users.email"]);
+        assert!(columns[2].1.is_empty());
+        let columns = lineage("SELECT COUNT(*) AS users FROM source('mock.users')", &[]).await;
+        assert_eq!(columns, vec![traced("users", &["mock.users.*"])]);
+
+        // Without the source's columns `*` stays whole
+        let columns = lineage("SELECT * FROM source('mock.users')", &[]).await;
+        assert_eq!(columns, vec![traced("*", &["mock.users.*"])]);
+    }
+
+    #[tokio::test]
+    async fn test_lineage_of_join_and_derived_table() {
+        let sources: &[(&str, &[&str])] = &[("mock.users", &["id", "name"]), ("mock.orders", &["id", "user_id", "total"])];
+        let columns = lineage(
+            "SELECT u.name, total, id FROM source('mock.users') u JOIN source('mock.orders') o ON u.id = o.user_id",
+            sources,
+        ).await;
+        assert_eq!(columns, vec![
+            traced("name", &["mock.users.name"]),
+            traced("total", &["mock.orders.total"]),
+            // Both sources have an id
+            traced("id", &["mock.users.id", "mock.orders.id"]),
+        ]);
+
+        let columns = lineage("SELECT o.* FROM source('mock.users') u JOIN source('mock.orders') o ON u.id = o.user_id", sources).await;
+        let outputs: Vec<&str> = columns.iter().map(|(output, _)| output.as_str()).collect();
+        assert_eq!(outputs, vec!["id", "user_id", "total"]);
+
+        let columns = lineage(
+            "SELECT big.amount FROM (SELECT total AS amount FROM source('mock.orders') WHERE total > 10) AS big",
+            &[],
+        ).await;
+        assert_eq!(columns, vec![traced("amount", &["mock.orders.total"])]);
+    }
+
+    #[test]
+    fn test_lineage_log_keeps_most_recent_queries() {
+        let log = LineageLog::new(2);
+        for sql in ["SELECT 1", "SELECT 2", "SELECT 3"] {
+            log.record(LineageRecord {
+                query_id: None,
+                user: Some("ann".to_string()),
+                sql: sql.to_string(),
+                executed_at: Utc::now(),
+                lineage: QueryLineage { columns: vec![ColumnLineage { output: "now()".to_string(), inputs: Vec::new() }] },
+            });
+        }
+        let table = log.lineage_table();
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0].values[3], Value::Text("SELECT 2".to_string()));
+        assert_eq!(table.rows[1].values[7], Value::Null);
+    }
+}
@@ -1,12 +1,47 @@
 // Core engine components
 pub mod query_parser;
+pub mod client_dialect;
 pub mod query_planner;
 pub mod query_executor;
+pub mod batch;
 pub mod dispatcher;
+pub mod session;
+pub mod access_control;
+pub mod scheduler;
+pub mod transaction_coordinator;
+pub mod query_rewriter;
+pub mod result_transformer;
+pub mod dry_run;
+pub mod approx_aggregate;
+pub mod tenant;
+pub mod usage;
+pub mod schema_drift;
+pub mod lineage;
+pub mod describe;
+pub mod join_suggestion;
+pub mod query_lint;
+#[allow(clippy::module_inception)]
 pub mod engine;
 
 pub use query_parser::*;
+pub use client_dialect::*;
 pub use query_planner::*;
 pub use query_executor::*;
+pub use batch::*;
 pub use dispatcher::*;
+pub use session::*;
+pub use access_control::*;
+pub use scheduler::*;
+pub use transaction_coordinator::*;
+pub use query_rewriter::*;
+pub use result_transformer::*;
+pub use dry_run::*;
+pub use approx_aggregate::*;
+pub use tenant::*;
+pub use usage::*;
+pub use schema_drift::*;
+pub use lineage::*;
+pub use describe::*;
+pub use join_suggestion::*;
+pub use query_lint::*;
 pub use engine::*;
\ No newline at end of file
@@ -1,10 +1,20 @@
 use async_trait::async_trait;
+use rand::Rng;
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
+use chrono::Utc;
 use crate::{
-    engine::{ExecutionPlan, PlanNode},
-    connectors::ConnectorRegistry,
+    engine::{ExecutionPlan, PlanNode, RowBatches, aggregate_rows},
+    connectors::{Connector, ConnectorRegistry},
     utils::{
-        types::{QueryResult, Value, ColumnMetadata, DataType, InternalQuery, QueryOperation, ConnectorQuery},
+        types::{QueryResult, Row, Value, ColumnMetadata, DataType, InternalQuery, QueryOperation, ConnectorQuery, Column, Predicate, PredicateOperator, PredicateValue, Sample, Deduplicate, NodeStats, ExecutionStats, DedupKeep, OrderBy, OrderColumn, OrderDirection, NullsOrder, Join, JoinType},
+        json_path::JsonExtract,
+        regex_function::{regex_matches, RegexFunction},
+        hash_function::{HashFunction, RowHash, TableChecksum, row_hash},
+        date_function::{current_timestamp, is_fetched_at, DateTrunc, FETCHED_AT_COLUMN, NOW_FUNCTION},
+        time_zone::{compare_date_times, format_utc},
+        identifier::{split_qualifier, IdentifierCase},
+        collation::Collation,
         error::{NirvResult, NirvError},
     },
 };
@@ -26,6 +36,8 @@ pub trait QueryExecutor: Send + Sync {
 pub struct DefaultQueryExecutor {
     /// Registry of available connectors
     connector_registry: Option<ConnectorRegistry>,
+    /// Matching of query identifiers against result columns
+    identifier_case: IdentifierCase,
 }
 
 impl DefaultQueryExecutor {
@@ -33,6 +45,7 @@ impl DefaultQueryExecutor {
     pub fn new() -> Self {
         Self {
             connector_registry: None,
+            identifier_case: IdentifierCase::default(),
         }
     }
     
@@ -40,9 +53,16 @@ impl DefaultQueryExecutor {
     pub fn with_connector_registry(registry: ConnectorRegistry) -> Self {
         Self {
             connector_registry: Some(registry),
+            identifier_case: IdentifierCase::default(),
         }
     }
     
+    /// Configure how column identifiers in queries are matched
+    pub fn with_identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+        self.identifier_case = identifier_case;
+        self
+    }
+    
     /// Get a reference to the connector registry
     fn get_connector_registry(&self) -> NirvResult<&ConnectorRegistry> {
         self.connector_registry.as_ref().ok_or_else(|| {
@@ -50,13 +70,8 @@ impl DefaultQueryExecutor {
         })
     }
     
-    /// Execute a table scan operation
-    async fn execute_table_scan(
-        &self,
-        source: &crate::utils::types::DataSource,
-        projections: &[crate::utils::types::Column],
-        predicates: &[crate::utils::types::Predicate],
-    ) -> NirvResult<QueryResult> {
+    /// Find the connector serving a data source
+    fn find_connector(&self, source: &crate::utils::types::DataSource) -> NirvResult<&dyn Connector> {
         let registry = self.get_connector_registry()?;
         
         // Try different naming patterns to find the connector
@@ -74,15 +89,41 @@ impl DefaultQueryExecutor {
             }
         }
         
-        let connector = connector.ok_or_else(|| {
+        connector.ok_or_else(|| {
             NirvError::Internal(format!("No connector found for type: {}", source.object_type))
-        })?;
+        })
+    }
+    
+    /// Execute a table scan operation, sampling in the connector when a sample is given
+    async fn execute_table_scan(
+        &self,
+        source: &crate::utils::types::DataSource,
+        projections: &[crate::utils::types::Column],
+        predicates: &[crate::utils::types::Predicate],
+        sample: Option<&Sample>,
+    ) -> NirvResult<QueryResult> {
+        let connector = self.find_connector(source)?;
+        
+        // Regular expression and hash functions are evaluated here, and JSON functions too
+        // unless the connector can push them down
+        let uses_json = projections.iter().any(|col| JsonExtract::parse(&col.name).is_some())
+            || predicates.iter().any(|pred| JsonExtract::parse(&pred.column).is_some());
+        let uses_engine_functions = projections.iter().any(|col| is_engine_function(&col.name))
+            || predicates.iter().any(|pred| is_engine_function(&pred.column));
+        let evaluate_locally = uses_engine_functions || (uses_json && !connector.supports_json_functions());
+        let (scan_projections, scan_predicates, local_predicates) = if evaluate_locally {
+            self.split_computed_columns(projections, predicates)
+        } else {
+            (projections.to_vec(), predicates.to_vec(), Vec::new())
+        };
+        let pushed_count = scan_predicates.len();
         
         // Create a connector query
         let mut internal_query = InternalQuery::new(QueryOperation::Select);
         internal_query.sources.push(source.clone());
-        internal_query.projections = projections.to_vec();
-        internal_query.predicates = predicates.to_vec();
+        internal_query.projections = scan_projections;
+        internal_query.predicates = scan_predicates;
+        internal_query.sample = sample.cloned();
         
         let connector_query = ConnectorQuery {
             connector_type: connector.get_connector_type(),
@@ -91,78 +132,127 @@ impl DefaultQueryExecutor {
         };
         
         // Execute the query through the connector
-        connector.execute_query(connector_query).await
-    }
-    
-    /// Apply a limit to query results
-    fn apply_limit(&self, mut result: QueryResult, count: u64) -> QueryResult {
-        let limit = count as usize;
-        if result.rows.len() > limit {
-            result.rows.truncate(limit);
+        let mut result = connector.execute_query(connector_query).await?;
+        let name = format!("{}.{}", source.object_type, source.identifier);
+        result.record_scan(&name, pushed_count, local_predicates.len());
+        if evaluate_locally {
+            let result = filter_rows(result, &local_predicates, self.identifier_case, Collation::default())?;
+            evaluate_computed_columns(result, projections, self.identifier_case)
+        } else {
+            Ok(result)
         }
-        result
     }
     
-    /// Apply sorting to query results
-    fn apply_sort(&self, mut result: QueryResult, order_by: &crate::utils::types::OrderBy) -> NirvResult<QueryResult> {
-        if order_by.columns.is_empty() {
-            return Ok(result);
-        }
-        
-        // For MVP, we'll implement simple single-column sorting
-        let sort_column = &order_by.columns[0];
-        
-        // Find the column index
-        let column_index = result.columns.iter()
-            .position(|col| col.name == sort_column.column)
-            .ok_or_else(|| {
-                NirvError::Internal(format!("Sort column '{}' not found in result", sort_column.column))
-            })?;
-        
-        // Sort the rows based on the column value
-        result.rows.sort_by(|a, b| {
-            let val_a = a.get(column_index).unwrap_or(&Value::Null);
-            let val_b = b.get(column_index).unwrap_or(&Value::Null);
-            
-            let comparison = self.compare_values(val_a, val_b);
-            
-            match sort_column.direction {
-                crate::utils::types::OrderDirection::Ascending => comparison,
-                crate::utils::types::OrderDirection::Descending => comparison.reverse(),
+    /// Scan a source in the batches its connector fetches rows in, filtering each batch as it
+    /// arrives so rows failing the filter are dropped before the rest of the scan is read.
+    /// Gives `None` when computed columns need the whole scan evaluated first.
+    async fn filter_table_scan(&self, scan: &PlanNode, predicates: &[Predicate]) -> NirvResult<Option<RowBatches>> {
+        let PlanNode::TableScan { source, projections, predicates: scan_predicates } = scan else {
+            return Ok(None);
+        };
+        let computed = projections.iter().any(|col| ComputedColumn::parse(&col.name).is_some())
+            || scan_predicates.iter().any(|pred| ComputedColumn::parse(&pred.column).is_some());
+        if computed {
+            return Ok(None);
+        }
+        let connector = self.find_connector(source)?;
+        let started = Instant::now();
+        
+        let mut internal_query = InternalQuery::new(QueryOperation::Select);
+        internal_query.sources.push(source.clone());
+        internal_query.projections = projections.to_vec();
+        internal_query.predicates = scan_predicates.to_vec();
+        let connector_query = ConnectorQuery {
+            connector_type: connector.get_connector_type(),
+            query: internal_query,
+            connection_params: std::collections::HashMap::new(),
+        };
+        
+        let (mut scanned, mut bytes) = (0u64, 0u64);
+        let mut filtered: Option<RowBatches> = None;
+        let mut on_batch = |columns: &[ColumnMetadata], rows: Vec<Row>| {
+            let mut batch = QueryResult::new();
+            batch.columns = columns.to_vec();
+            batch.rows = rows;
+            scanned += batch.rows.len() as u64;
+            bytes += batch.estimated_bytes();
+            let batch = RowBatches::from_filtered_result(batch, predicates, self.identifier_case, Collation::default())?;
+            match filtered.as_mut() {
+                Some(filtered) => filtered.append(batch),
+                None => filtered = Some(batch),
             }
-        });
+            Ok(())
+        };
+        let result = connector.execute_query_batched(connector_query, &mut on_batch).await?;
         
-        Ok(result)
+        let mut batches = filtered.unwrap_or_else(|| RowBatches::from_result(QueryResult::new()));
+        let QueryResult { columns, affected_rows, stats, warnings, .. } = result;
+        if !columns.is_empty() {
+            batches.result.columns = columns;
+        }
+        batches.result.affected_rows = affected_rows;
+        batches.result.stats = stats;
+        batches.result.warnings = warnings;
+        let name = format!("{}.{}", source.object_type, source.identifier);
+        batches.result.record_scanned(&name, scanned, bytes, scan_predicates.len(), 0);
+        batches.result.stats.get_or_insert_with(ExecutionStats::default).nodes.push(NodeStats {
+            node: scan.label(),
+            rows: scanned,
+            duration: started.elapsed(),
+        });
+        Ok(Some(batches))
     }
     
-    /// Compare two values for sorting
-    fn compare_values(&self, a: &Value, b: &Value) -> std::cmp::Ordering {
-        use std::cmp::Ordering;
-        
-        match (a, b) {
-            (Value::Null, Value::Null) => Ordering::Equal,
-            (Value::Null, _) => Ordering::Less,
-            (_, Value::Null) => Ordering::Greater,
-            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
-            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-            (Value::Text(a), Value::Text(b)) => a.cmp(b),
-            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
-            (Value::Date(a), Value::Date(b)) => a.cmp(b),
-            (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
-            // For mixed types, convert to string and compare
-            _ => format!("{:?}", a).cmp(&format!("{:?}", b)),
+    /// Have a source's connector count the rows matching the predicates without fetching them.
+    /// Gives `None` when the connector cannot, or computed columns need the rows evaluated.
+    async fn count_table_scan(&self, source: &crate::utils::types::DataSource, predicates: &[Predicate], column: &Column) -> NirvResult<Option<QueryResult>> {
+        if predicates.iter().any(|pred| ComputedColumn::parse(&pred.column).is_some()) {
+            return Ok(None);
         }
+        let connector = self.find_connector(source)?;
+        
+        let mut internal_query = InternalQuery::new(QueryOperation::Select);
+        internal_query.sources.push(source.clone());
+        internal_query.projections = vec![column.clone()];
+        internal_query.predicates = predicates.to_vec();
+        let connector_query = ConnectorQuery {
+            connector_type: connector.get_connector_type(),
+            query: internal_query,
+            connection_params: std::collections::HashMap::new(),
+        };
+        
+        let started = Instant::now();
+        let Some(count) = connector.count_rows(connector_query).await? else {
+            return Ok(None);
+        };
+        let mut result = QueryResult::of_count(count, column);
+        result.execution_time = started.elapsed();
+        result.record_scanned(&format!("{}.{}", source.object_type, source.identifier), 0, 0, predicates.len(), 0);
+        Ok(Some(result))
     }
     
-    /// Apply projection to query results
-    fn apply_projection(&self, result: QueryResult, columns: &[crate::utils::types::Column]) -> NirvResult<QueryResult> {
-        if columns.is_empty() {
-            return Ok(result);
+    /// Replace JSON, regular expression and hash function projections with the columns they read,
+    /// and separate predicates on those functions from those the connector can evaluate
+    fn split_computed_columns(&self, projections: &[Column], predicates: &[Predicate]) -> (Vec<Column>, Vec<Predicate>, Vec<Predicate>) {
+        let (local_predicates, scan_predicates): (Vec<Predicate>, Vec<Predicate>) = predicates.iter()
+            .cloned()
+            .partition(|pred| ComputedColumn::parse(&pred.column).is_some());
+        
+        if projections.is_empty() || projections.iter().any(|col| col.name == "*") {
+            return (projections.to_vec(), scan_predicates, local_predicates);
         }
         
-        // For MVP, we'll assume projections are already handled in the table scan
-        // This is a placeholder for future enhancement
-        Ok(result)
+        let mut scan_projections = computed_input_columns(projections);
+        for pred in &local_predicates {
+            if let Some(computed) = ComputedColumn::parse(&pred.column) {
+                let column = Column { name: computed.unqualified_column().to_string(), alias: None, source: None };
+                if !scan_projections.contains(&column) {
+                    scan_projections.push(column);
+                }
+            }
+        }
+        
+        (scan_projections, scan_predicates, local_predicates)
     }
     
     /// Aggregate results from multiple operations
@@ -189,21 +279,9 @@ impl DefaultQueryExecutor {
         if result.columns.is_empty() && !result.rows.is_empty() {
             let first_row = &result.rows[0];
             for (i, value) in first_row.values.iter().enumerate() {
-                let data_type = match value {
-                    Value::Integer(_) => DataType::Integer,
-                    Value::Float(_) => DataType::Float,
-                    Value::Text(_) => DataType::Text,
-                    Value::Boolean(_) => DataType::Boolean,
-                    Value::Date(_) => DataType::Date,
-                    Value::DateTime(_) => DataType::DateTime,
-                    Value::Json(_) => DataType::Json,
-                    Value::Binary(_) => DataType::Binary,
-                    Value::Null => DataType::Text, // Default for null values
-                };
-                
                 result.columns.push(ColumnMetadata {
                     name: format!("column_{}", i),
-                    data_type,
+                    data_type: value_data_type(value),
                     nullable: true,
                 });
             }
@@ -213,6 +291,614 @@ impl DefaultQueryExecutor {
     }
 }
 
+/// Evaluate a predicate against a value, comparing text by a collation; comparisons with
+/// NULL are false
+pub fn predicate_matches(value: &Value, operator: &PredicateOperator, expected: &PredicateValue, collation: Collation) -> bool {
+    use std::cmp::Ordering;
+    
+    let compare = |expected: &PredicateValue| -> Option<Ordering> {
+        match (value, expected) {
+            (Value::Null, _) | (_, PredicateValue::Null) => None,
+            (Value::Integer(a), PredicateValue::Integer(b)) => Some(a.cmp(b)),
+            (Value::Integer(a), PredicateValue::Number(b)) => (*a as f64).partial_cmp(b),
+            (Value::Float(a), PredicateValue::Number(b)) => a.partial_cmp(b),
+            (Value::Float(a), PredicateValue::Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::Boolean(a), PredicateValue::Boolean(b)) => Some(a.cmp(b)),
+            (Value::Text(a) | Value::Json(a), PredicateValue::String(b)) => Some(collation.compare(a, b)),
+            (Value::Date(a) | Value::DateTime(a), PredicateValue::String(b)) => Some(compare_date_times(a, b)),
+            // Numeric strings in JSON compare with numeric literals
+            (Value::Text(a), PredicateValue::Integer(b)) => a.parse::<f64>().ok()?.partial_cmp(&(*b as f64)),
+            (Value::Text(a), PredicateValue::Number(b)) => a.parse::<f64>().ok()?.partial_cmp(b),
+            _ => None,
+        }
+    };
+    
+    match operator {
+        PredicateOperator::Equal => compare(expected) == Some(Ordering::Equal),
+        PredicateOperator::NotEqual => matches!(compare(expected), Some(Ordering::Less | Ordering::Greater)),
+        PredicateOperator::GreaterThan => compare(expected) == Some(Ordering::Greater),
+        PredicateOperator::GreaterThanOrEqual => matches!(compare(expected), Some(Ordering::Greater | Ordering::Equal)),
+        PredicateOperator::LessThan => compare(expected) == Some(Ordering::Less),
+        PredicateOperator::LessThanOrEqual => matches!(compare(expected), Some(Ordering::Less | Ordering::Equal)),
+        PredicateOperator::In => match expected {
+            PredicateValue::List(values) => values.iter().any(|v| compare(v) == Some(Ordering::Equal)),
+            _ => false,
+        },
+        PredicateOperator::Like => match (value, expected) {
+            (Value::Text(text), PredicateValue::String(pattern)) => like_match(&collation.key(text), &collation.key(pattern)),
+            _ => false,
+        },
+        PredicateOperator::Regexp => matches!(expected, PredicateValue::String(pattern) if regex_matches(value, pattern)),
+        PredicateOperator::IsNull => *value == Value::Null,
+        PredicateOperator::IsNotNull => *value != Value::Null,
+    }
+}
+
+/// A column computed from another by a JSON, regular expression, hash or date function,
+/// named by the function's canonical text
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComputedColumn {
+    Json(JsonExtract),
+    Regex(RegexFunction),
+    Hash(HashFunction),
+    DateTrunc(DateTrunc),
+}
+
+impl ComputedColumn {
+    pub fn parse(name: &str) -> Option<Self> {
+        JsonExtract::parse(name).map(ComputedColumn::Json)
+            .or_else(|| RegexFunction::parse(name).map(ComputedColumn::Regex))
+            .or_else(|| HashFunction::parse(name).map(ComputedColumn::Hash))
+            .or_else(|| DateTrunc::parse(name).map(ComputedColumn::DateTrunc))
+    }
+    
+    /// The column the function reads
+    pub fn column(&self) -> &str {
+        match self {
+            ComputedColumn::Json(extract) => &extract.column,
+            ComputedColumn::Regex(function) => &function.column,
+            ComputedColumn::Hash(function) => &function.column,
+            ComputedColumn::DateTrunc(trunc) => &trunc.column,
+        }
+    }
+    
+    pub fn unqualified_column(&self) -> &str {
+        match self {
+            ComputedColumn::Json(extract) => extract.unqualified_column(),
+            ComputedColumn::Regex(function) => function.unqualified_column(),
+            ComputedColumn::Hash(function) => function.unqualified_column(),
+            ComputedColumn::DateTrunc(trunc) => trunc.unqualified_column(),
+        }
+    }
+    
+    pub fn evaluate(&self, value: &Value) -> Value {
+        match self {
+            ComputedColumn::Json(extract) => extract.evaluate(value),
+            ComputedColumn::Regex(function) => function.evaluate(value),
+            ComputedColumn::Hash(function) => function.evaluate(value),
+            ComputedColumn::DateTrunc(trunc) => trunc.evaluate(value),
+        }
+    }
+}
+
+/// Whether a column is computed by a function connectors never evaluate: regular
+/// expression, hash and date functions, and row hashes
+pub fn is_engine_function(name: &str) -> bool {
+    RegexFunction::parse(name).is_some() || HashFunction::parse(name).is_some() || RowHash::parse(name).is_some()
+        || DateTrunc::parse(name).is_some() || name == NOW_FUNCTION || is_fetched_at(name)
+}
+
+/// Projections with each computed column replaced by the columns it reads. A row hash over
+/// every column reads the whole row; `now()` and `_nirv_fetched_at` read none.
+pub fn computed_input_columns(projections: &[Column]) -> Vec<Column> {
+    let all_columns = Column { name: "*".to_string(), alias: None, source: None };
+    let mut inputs: Vec<Column> = Vec::new();
+    for col in projections.iter().filter(|col| col.name != NOW_FUNCTION && !is_fetched_at(&col.name)) {
+        let columns = match (ComputedColumn::parse(&col.name), RowHash::parse(&col.name)) {
+            (Some(computed), _) => vec![Column { name: computed.unqualified_column().to_string(), alias: None, source: col.source.clone() }],
+            (None, Some(hash)) if hash.columns.is_empty() => return vec![all_columns],
+            (None, Some(hash)) => hash.columns.iter()
+                .map(|name| Column { name: name.rsplit('.').next().unwrap_or(name).to_string(), alias: None, source: col.source.clone() })
+                .collect(),
+            (None, None) => vec![col.clone()],
+        };
+        for column in columns {
+            if !inputs.iter().any(|existing| existing.name == column.name && existing.alias == column.alias) {
+                inputs.push(column);
+            }
+        }
+    }
+    inputs
+}
+
+/// Column indexes in name order, ignoring case, so results listing the same columns in
+/// different orders hash alike
+fn name_order(columns: &[ColumnMetadata]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..columns.len()).collect();
+    order.sort_by_key(|index| columns[*index].name.to_lowercase());
+    order
+}
+
+/// Build the requested projection from rows holding the columns computed columns read
+pub fn evaluate_computed_columns(result: QueryResult, projections: &[Column], identifier_case: IdentifierCase) -> NirvResult<QueryResult> {
+    let column_names = || result.columns.iter().map(|col| col.name.as_str());
+    let find_column = |name: &str| {
+        identifier_case.resolve(name, column_names())
+            .ok_or_else(|| NirvError::Internal(format!("Column '{}' not found in result", name)))
+    };
+    
+    // Each output column is either copied from the result or computed by a function
+    enum Output {
+        Copy(usize),
+        Computed(usize, ComputedColumn),
+        RowHash(Vec<usize>),
+        Constant(Value),
+    }
+    let mut outputs = Vec::new();
+    let mut columns = Vec::new();
+    let projections: Vec<Column> = if projections.is_empty() {
+        vec![Column { name: "*".to_string(), alias: None, source: None }]
+    } else {
+        projections.to_vec()
+    };
+    for col in &projections {
+        if col.name == "*" {
+            outputs.extend((0..result.columns.len()).map(Output::Copy));
+            columns.extend(result.columns.iter().cloned());
+        } else if let Some(computed) = ComputedColumn::parse(&col.name) {
+            outputs.push(Output::Computed(find_column(computed.column())?, computed));
+            columns.push(ColumnMetadata {
+                name: col.alias.clone().unwrap_or_else(|| col.name.clone()),
+                data_type: DataType::Text,
+                nullable: true,
+            });
+        } else if let Some(hash) = RowHash::parse(&col.name) {
+            let indexes = match hash.columns.is_empty() {
+                true => name_order(&result.columns),
+                false => hash.columns.iter().map(|name| find_column(name)).collect::<NirvResult<Vec<usize>>>()?,
+            };
+            outputs.push(Output::RowHash(indexes));
+            columns.push(ColumnMetadata {
+                name: col.alias.clone().unwrap_or_else(|| col.name.clone()),
+                data_type: DataType::Text,
+                nullable: false,
+            });
+        } else if col.name == NOW_FUNCTION {
+            // Every row sees the same time, as within a statement in PostgreSQL
+            outputs.push(Output::Constant(Value::DateTime(current_timestamp())));
+            columns.push(ColumnMetadata {
+                name: col.alias.clone().unwrap_or_else(|| col.name.clone()),
+                data_type: DataType::DateTime,
+                nullable: false,
+            });
+        } else if is_fetched_at(&col.name) {
+            // In a join, the rows fetched longest ago
+            let fetched_at = result.stats.as_ref().and_then(ExecutionStats::fetched_at).unwrap_or_else(Utc::now);
+            outputs.push(Output::Constant(Value::DateTime(format_utc(fetched_at.naive_utc()))));
+            columns.push(ColumnMetadata {
+                name: col.alias.clone().unwrap_or_else(|| FETCHED_AT_COLUMN.to_string()),
+                data_type: DataType::DateTime,
+                nullable: false,
+            });
+        } else {
+            let index = col.alias.as_deref()
+                .and_then(|alias| identifier_case.resolve(alias, column_names()))
+                .map_or_else(|| find_column(&col.name), Ok)?;
+            outputs.push(Output::Copy(index));
+            let mut metadata = result.columns[index].clone();
+            if let Some(alias) = &col.alias {
+                metadata.name = alias.clone();
+            }
+            columns.push(metadata);
+        }
+    }
+    
+    let rows: Vec<Row> = result.rows.iter()
+        .map(|row| Row::new(outputs.iter().map(|output| match output {
+            Output::Copy(index) => row.get(*index).cloned().unwrap_or(Value::Null),
+            Output::Computed(index, computed) => computed.evaluate(row.get(*index).unwrap_or(&Value::Null)),
+            Output::RowHash(indexes) => Value::Text(row_hash(indexes.iter().map(|index| row.get(*index).unwrap_or(&Value::Null)))),
+            Output::Constant(value) => value.clone(),
+        }).collect()))
+        .collect();
+    
+    // Computed columns take the type of their first non-null value
+    for (i, output) in outputs.iter().enumerate() {
+        if let Output::Computed(..) = output {
+            if let Some(value) = rows.iter().map(|row| &row.values[i]).find(|v| **v != Value::Null) {
+                columns[i].data_type = value_data_type(value);
+            }
+        }
+    }
+    
+    Ok(QueryResult {
+        columns,
+        affected_rows: Some(rows.len() as u64),
+        rows,
+        execution_time: result.execution_time,
+        stats: result.stats.clone(),
+        warnings: result.warnings.clone(),
+    })
+}
+
+/// Column type for a value; NULL defaults to text
+fn value_data_type(value: &Value) -> DataType {
+    match value {
+        Value::Integer(_) => DataType::Integer,
+        Value::Float(_) => DataType::Float,
+        Value::Boolean(_) => DataType::Boolean,
+        Value::Date(_) => DataType::Date,
+        Value::DateTime(_) => DataType::DateTime,
+        Value::Json(_) => DataType::Json,
+        Value::Binary(_) => DataType::Binary,
+        Value::Text(_) | Value::Null => DataType::Text,
+    }
+}
+
+/// Keep the rows satisfying every predicate, comparing text by a collation. Used for
+/// predicates a connector could not evaluate; JSON and regular expression function
+/// predicates are computed from the column they read.
+pub fn filter_rows(mut result: QueryResult, predicates: &[Predicate], identifier_case: IdentifierCase, collation: Collation) -> NirvResult<QueryResult> {
+    if predicates.is_empty() {
+        return Ok(result);
+    }
+    
+    let mut filters = Vec::new();
+    for predicate in predicates {
+        let computed = ComputedColumn::parse(&predicate.column);
+        let column = computed.as_ref().map_or(predicate.column.as_str(), |computed| computed.column());
+        let index = identifier_case.resolve(column, result.columns.iter().map(|col| col.name.as_str()))
+            .ok_or_else(|| NirvError::Internal(format!("Column '{}' not found in result", column)))?;
+        filters.push((index, computed, predicate));
+    }
+    
+    result.rows.retain(|row| filters.iter().all(|(index, computed, predicate)| {
+        let value = row.get(*index).unwrap_or(&Value::Null);
+        match computed {
+            Some(computed) => predicate_matches(&computed.evaluate(value), &predicate.operator, &predicate.value, collation),
+            None => predicate_matches(value, &predicate.operator, &predicate.value, collation),
+        }
+    }));
+    if result.affected_rows.is_some() {
+        result.affected_rows = Some(result.rows.len() as u64);
+    }
+    Ok(result)
+}
+
+/// Sample rows locally: each row is kept with the given probability for `PERCENT`,
+/// and `ROWS` takes a uniform reservoir sample. Sampled rows keep their order.
+pub fn sample_rows(mut result: QueryResult, sample: &Sample) -> QueryResult {
+    let mut rng = rand::rng();
+    match sample {
+        Sample::Percent(percent) => {
+            let probability = (percent / 100.0).clamp(0.0, 1.0);
+            result.rows.retain(|_| rng.random_bool(probability));
+        }
+        Sample::Rows(count) => {
+            let count = *count as usize;
+            if result.rows.len() > count {
+                let mut reservoir: Vec<usize> = (0..count).collect();
+                for i in count..result.rows.len() {
+                    let j = rng.random_range(0..=i);
+                    if j < count {
+                        reservoir[j] = i;
+                    }
+                }
+                let mut keep = vec![false; result.rows.len()];
+                for i in reservoir {
+                    keep[i] = true;
+                }
+                let mut index = 0;
+                result.rows.retain(|_| {
+                    index += 1;
+                    keep[index - 1]
+                });
+            }
+        }
+    }
+    if result.affected_rows.is_some() {
+        result.affected_rows = Some(result.rows.len() as u64);
+    }
+    result
+}
+
+/// Compare two values for sorting: NULL first, then by value, integers and floats by their
+/// numeric value and other mixed types by their text
+pub fn compare_values(a: &Value, b: &Value) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+        (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+        (Value::Integer(a), Value::Float(b)) => (*a as f64).total_cmp(b),
+        (Value::Float(a), Value::Integer(b)) => a.total_cmp(&(*b as f64)),
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+        (Value::Date(a), Value::Date(b)) => a.cmp(b),
+        (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+        // For mixed types, convert to string and compare
+        _ => format!("{:?}", a).cmp(&format!("{:?}", b)),
+    }
+}
+
+/// Compare two values like `compare_values`, text by a collation
+fn compare_collated(a: &Value, b: &Value, collation: Collation) -> std::cmp::Ordering {
+    match (a, b) {
+        (Value::Text(a), Value::Text(b)) => collation.compare(a, b),
+        _ => compare_values(a, b),
+    }
+}
+
+/// Key rows are grouped by for deduplication and joins: values equal under a collation
+/// have equal keys
+fn equality_key(value: &Value, collation: Collation) -> String {
+    match value {
+        Value::Text(text) => format!("Text({:?})", collation.key(text)),
+        value => format!("{:?}", value),
+    }
+}
+
+/// Reduce rows to their order-independent checksum and count, hashing each row's columns
+/// in name order like `hash_row()`
+pub fn checksum_rows(result: QueryResult) -> QueryResult {
+    let order = name_order(&result.columns);
+    let mut checksum = TableChecksum::new();
+    for row in &result.rows {
+        checksum.add_row(order.iter().map(|index| row.get(*index).unwrap_or(&Value::Null)));
+    }
+    QueryResult {
+        columns: vec![
+            ColumnMetadata { name: "checksum".to_string(), data_type: DataType::Text, nullable: false },
+            ColumnMetadata { name: "row_count".to_string(), data_type: DataType::Integer, nullable: false },
+        ],
+        rows: vec![Row::new(vec![Value::Text(checksum.hex()), Value::Integer(checksum.rows() as i64)])],
+        affected_rows: None,
+        execution_time: result.execution_time,
+        stats: result.stats,
+        warnings: result.warnings,
+    }
+}
+
+/// Keep one row of each set of rows with key columns equal under a collation: the first or
+/// last in the deduplication's order, or in arrival order without one. Kept rows stay in
+/// their order.
+pub fn deduplicate_rows(mut result: QueryResult, deduplicate: &Deduplicate, identifier_case: IdentifierCase, collation: Collation) -> NirvResult<QueryResult> {
+    let resolve = |column: &str| identifier_case.resolve(column, result.columns.iter().map(|col| col.name.as_str()))
+        .ok_or_else(|| NirvError::Internal(format!("Deduplication column '{}' not found in result", column)));
+    let keys = deduplicate.columns.iter().map(|column| resolve(column)).collect::<NirvResult<Vec<usize>>>()?;
+    let order = deduplicate.order_by.as_ref()
+        .map(|order| resolve(&order.column).map(|index| (index, &order.direction)))
+        .transpose()?;
+    
+    let value = |row: usize, index: usize| result.rows[row].get(index).unwrap_or(&Value::Null);
+    let mut kept: HashMap<Vec<String>, usize> = HashMap::new();
+    for row in 0..result.rows.len() {
+        let key: Vec<String> = keys.iter().map(|index| equality_key(value(row, *index), collation)).collect();
+        let Some(current) = kept.get(&key).copied() else {
+            kept.insert(key, row);
+            continue;
+        };
+        // Rows ordered equally are ordered as they arrived
+        let ordering = match order {
+            Some((index, direction)) => {
+                let ordering = compare_collated(value(row, index), value(current, index), collation);
+                if *direction == OrderDirection::Descending { ordering.reverse() } else { ordering }
+            }
+            None => std::cmp::Ordering::Greater,
+        };
+        let replace = match deduplicate.keep {
+            DedupKeep::First => ordering == std::cmp::Ordering::Less,
+            DedupKeep::Last => ordering != std::cmp::Ordering::Less,
+        };
+        if replace {
+            kept.insert(key, row);
+        }
+    }
+    
+    let mut keep = vec![false; result.rows.len()];
+    for row in kept.into_values() {
+        keep[row] = true;
+    }
+    let mut index = 0;
+    result.rows.retain(|_| {
+        index += 1;
+        keep[index - 1]
+    });
+    if result.affected_rows.is_some() {
+        result.affected_rows = Some(result.rows.len() as u64);
+    }
+    Ok(result)
+}
+
+/// Sort rows by each ordering column in turn, later columns breaking ties in earlier ones,
+/// with text ordered by a collation; rows ordered equally by every column keep their order
+pub fn sort_rows(mut result: QueryResult, order_by: &OrderBy, identifier_case: IdentifierCase, collation: Collation) -> NirvResult<QueryResult> {
+    let mut keys = Vec::new();
+    for sort_column in &order_by.columns {
+        let index = identifier_case.resolve(&sort_column.column, result.columns.iter().map(|col| col.name.as_str()))
+            .ok_or_else(|| NirvError::Internal(format!("Sort column '{}' not found in result", sort_column.column)))?;
+        keys.push((index, sort_column));
+    }
+    
+    result.rows.sort_by(|a, b| {
+        keys.iter()
+            .map(|(index, sort_column)| {
+                compare_sort_values(a.get(*index).unwrap_or(&Value::Null), b.get(*index).unwrap_or(&Value::Null), sort_column, collation)
+            })
+            .find(|ordering| *ordering != std::cmp::Ordering::Equal)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    Ok(result)
+}
+
+/// Compare two values of an ordering column, in its direction, with NULLs placed as it asks
+pub(crate) fn compare_sort_values(a: &Value, b: &Value, sort_column: &OrderColumn, collation: Collation) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    
+    match (a, b, sort_column.nulls) {
+        (Value::Null, Value::Null, _) => Ordering::Equal,
+        (Value::Null, _, Some(NullsOrder::First)) | (_, Value::Null, Some(NullsOrder::Last)) => Ordering::Less,
+        (Value::Null, _, Some(NullsOrder::Last)) | (_, Value::Null, Some(NullsOrder::First)) => Ordering::Greater,
+        _ => match sort_column.direction {
+            OrderDirection::Ascending => compare_collated(a, b, collation),
+            OrderDirection::Descending => compare_collated(a, b, collation).reverse(),
+        },
+    }
+}
+
+/// Join two results. Columns are named after their source (`u.name`). Rows pair when
+/// every equality holds under a collation and, for a fuzzy join, their trimmed, lower-cased keys are at least
+/// as similar as the threshold; NULL keys never pair. Outer joins keep unpaired rows,
+/// padded with NULLs.
+pub fn join_rows(left: QueryResult, right: QueryResult, join: &Join, identifier_case: IdentifierCase, collation: Collation) -> NirvResult<QueryResult> {
+    let left_keys = join.on_condition.iter()
+        .map(|condition| join_key(&condition.left_column, &left, &join.left_source, identifier_case))
+        .collect::<NirvResult<Vec<usize>>>()?;
+    let right_keys = join.on_condition.iter()
+        .map(|condition| join_key(&condition.right_column, &right, &join.right_source, identifier_case))
+        .collect::<NirvResult<Vec<usize>>>()?;
+    let fuzzy = join.fuzzy_condition.as_ref()
+        .map(|fuzzy| NirvResult::Ok((
+            join_key(&fuzzy.left_column, &left, &join.left_source, identifier_case)?,
+            join_key(&fuzzy.right_column, &right, &join.right_source, identifier_case)?,
+            fuzzy,
+        )))
+        .transpose()?;
+    
+    // Right rows are grouped by their equality keys, so only rows in the same group are compared
+    let equality_key = |row: &Row, keys: &[usize]| -> Option<Vec<String>> {
+        keys.iter().map(|index| match row.get(*index) {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(equality_key(value, collation)),
+        }).collect()
+    };
+    let mut groups: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for (index, row) in right.rows.iter().enumerate() {
+        if let Some(key) = equality_key(row, &right_keys) {
+            groups.entry(key).or_default().push(index);
+        }
+    }
+    let right_texts: Vec<Option<String>> = match &fuzzy {
+        Some((_, right_index, _)) => right.rows.iter().map(|row| fuzzy_text(row.get(*right_index))).collect(),
+        None => Vec::new(),
+    };
+    
+    let left_width = left.columns.len();
+    let right_width = right.columns.len();
+    let pad = |width: usize| std::iter::repeat_n(Value::Null, width);
+    let mut rows = Vec::new();
+    let mut right_paired = vec![false; right.rows.len()];
+    for left_row in &left.rows {
+        let candidates = equality_key(left_row, &left_keys)
+            .and_then(|key| groups.get(&key))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let left_text = fuzzy.as_ref().and_then(|(left_index, _, _)| fuzzy_text(left_row.get(*left_index)));
+        let mut paired = false;
+        for &index in candidates {
+            if let Some((_, _, condition)) = &fuzzy {
+                let similar = match (&left_text, &right_texts[index]) {
+                    (Some(a), Some(b)) => condition.metric.similarity(a, b) >= condition.threshold,
+                    _ => false,
+                };
+                if !similar {
+                    continue;
+                }
+            }
+            paired = true;
+            right_paired[index] = true;
+            rows.push(Row::new(left_row.values.iter().cloned().chain(right.rows[index].values.iter().cloned()).collect()));
+        }
+        if !paired && matches!(join.join_type, JoinType::Left | JoinType::Full) {
+            rows.push(Row::new(left_row.values.iter().cloned().chain(pad(right_width)).collect()));
+        }
+    }
+    if matches!(join.join_type, JoinType::Right | JoinType::Full) {
+        for (right_row, _) in right.rows.iter().zip(&right_paired).filter(|(_, paired)| !**paired) {
+            rows.push(Row::new(pad(left_width).chain(right_row.values.iter().cloned()).collect()));
+        }
+    }
+    
+    let qualify = |columns: &[ColumnMetadata], source: &str, nullable: bool| -> Vec<ColumnMetadata> {
+        columns.iter().map(|col| ColumnMetadata {
+            name: format!("{}.{}", source, col.name),
+            data_type: col.data_type.clone(),
+            nullable: col.nullable || nullable,
+        }).collect()
+    };
+    let mut columns = qualify(&left.columns, &join.left_source, matches!(join.join_type, JoinType::Right | JoinType::Full));
+    columns.extend(qualify(&right.columns, &join.right_source, matches!(join.join_type, JoinType::Left | JoinType::Full)));
+    
+    let mut stats = left.stats;
+    if let Some(right_stats) = right.stats {
+        stats.get_or_insert_with(Default::default).merge(right_stats);
+    }
+    Ok(QueryResult {
+        columns,
+        affected_rows: Some(rows.len() as u64),
+        rows,
+        execution_time: left.execution_time.max(right.execution_time),
+        stats,
+        warnings: [left.warnings, right.warnings].concat(),
+    })
+}
+
+/// Position in one side of a join of a column qualified with that side's source, or unqualified
+fn join_key(column: &str, side: &QueryResult, source: &str, identifier_case: IdentifierCase) -> NirvResult<usize> {
+    let name = match split_qualifier(column) {
+        Some((qualifier, name)) if qualifier.eq_ignore_ascii_case(source) => name,
+        Some(_) => return Err(NirvError::Internal(format!("Join column '{}' does not belong to '{}'", column, source))),
+        None => column,
+    };
+    identifier_case.resolve(name, side.columns.iter().map(|col| col.name.as_str()))
+        .ok_or_else(|| NirvError::Internal(format!("Join column '{}' not found in '{}'", column, source)))
+}
+
+/// Text a fuzzy join compares, trimmed and lower-cased
+fn fuzzy_text(value: Option<&Value>) -> Option<String> {
+    let text = match value? {
+        Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => s.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Null | Value::Binary(_) => return None,
+    };
+    Some(text.trim().to_lowercase())
+}
+
+/// SQL LIKE matching with `%` and `_` wildcards
+fn like_match(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '_' || pattern[p] == text[t]) {
+            t += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '%' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+/// Whether a node passes batches of rows to its parent
+fn is_batched(node: &PlanNode) -> bool {
+    matches!(node, PlanNode::Limit { .. } | PlanNode::Sort { .. } | PlanNode::Projection { .. } | PlanNode::Filter { .. })
+}
+
 impl Default for DefaultQueryExecutor {
     fn default() -> Self {
         Self::new()
@@ -242,27 +928,106 @@ impl QueryExecutor for DefaultQueryExecutor {
     }
     
     async fn execute_node(&self, node: &PlanNode) -> NirvResult<QueryResult> {
+        let started = Instant::now();
+        let mut result = self.run_node(node).await?;
+        // Inputs record their own time, which is not counted again here
+        let input_time = result.stats.as_ref().map_or(Duration::ZERO, |stats| stats.node_time());
+        result.record_node(node.label(), started.elapsed().saturating_sub(input_time));
+        Ok(result)
+    }
+    
+    fn set_connector_registry(&mut self, registry: ConnectorRegistry) {
+        self.connector_registry = Some(registry);
+    }
+}
+
+impl DefaultQueryExecutor {
+    /// Execute a node without recording its statistics
+    async fn run_node(&self, node: &PlanNode) -> NirvResult<QueryResult> {
         match node {
             PlanNode::TableScan { source, projections, predicates } => {
-                self.execute_table_scan(source, projections, predicates).await
+                self.execute_table_scan(source, projections, predicates, None).await
             }
-            PlanNode::Limit { count, input } => {
+            PlanNode::Limit { .. } | PlanNode::Sort { .. } | PlanNode::Projection { .. } | PlanNode::Filter { .. } => {
+                Ok(self.run_batches(node).await?.into_result())
+            }
+            PlanNode::Sample { sample, input } => {
+                if let PlanNode::TableScan { source, projections, predicates } = input.as_ref() {
+                    if self.find_connector(source)?.supports_sample(sample) {
+                        return self.execute_table_scan(source, projections, predicates, Some(sample)).await;
+                    }
+                }
                 let input_result = self.execute_node(input).await?;
-                Ok(self.apply_limit(input_result, *count))
+                Ok(sample_rows(input_result, sample))
             }
-            PlanNode::Sort { order_by, input } => {
+            PlanNode::Aggregate { aggregates, group_by, input } => {
                 let input_result = self.execute_node(input).await?;
-                self.apply_sort(input_result, order_by)
+                aggregate_rows(input_result, aggregates, group_by, self.identifier_case)
             }
-            PlanNode::Projection { columns, input } => {
+            PlanNode::Deduplicate { deduplicate, input } => {
+                let input_result = self.execute_node(input).await?;
+                deduplicate_rows(input_result, deduplicate, self.identifier_case, Collation::default())
+            }
+            PlanNode::Checksum { input } => {
                 let input_result = self.execute_node(input).await?;
-                self.apply_projection(input_result, columns)
+                Ok(checksum_rows(input_result))
+            }
+            PlanNode::Count { column, input } => {
+                if let PlanNode::TableScan { source, predicates, .. } = input.as_ref() {
+                    if let Some(result) = self.count_table_scan(source, predicates, column).await? {
+                        return Ok(result);
+                    }
+                }
+                Ok(self.execute_node(input).await?.counted(column))
             }
         }
     }
     
-    fn set_connector_registry(&mut self, registry: ConnectorRegistry) {
-        self.connector_registry = Some(registry);
+    /// Execute a limit, sort, projection or filter over batches of rows, without recording its
+    /// statistics. Chains of these operators hand batches to each other; any other input is
+    /// executed as a whole result and then split into batches.
+    async fn run_batches(&self, node: &PlanNode) -> NirvResult<RowBatches> {
+        match node {
+            PlanNode::Limit { count, input } => {
+                Ok(self.execute_batches(input).await?.limit(*count))
+            }
+            PlanNode::Sort { order_by, input } => {
+                self.execute_batches(input).await?.sort(order_by, self.identifier_case, Collation::default())
+            }
+            PlanNode::Projection { columns, input } => {
+                self.execute_batches(input).await?.project(columns, self.identifier_case)
+            }
+            PlanNode::Filter { predicates, input } => {
+                let mut batches = match input.as_ref() {
+                    PlanNode::TableScan { .. } => match self.filter_table_scan(input, predicates).await? {
+                        Some(batches) => batches,
+                        None => RowBatches::from_filtered_result(self.execute_node(input).await?, predicates, self.identifier_case, Collation::default())?,
+                    },
+                    _ if is_batched(input) => {
+                        self.execute_batches(input).await?.filter(predicates, self.identifier_case, Collation::default())?
+                    }
+                    _ => RowBatches::from_filtered_result(self.execute_node(input).await?, predicates, self.identifier_case, Collation::default())?,
+                };
+                // A local filter sits above the scan of a single source
+                if let Some([source]) = batches.result.stats.as_mut().map(|stats| stats.sources.as_mut_slice()) {
+                    source.local_predicates += predicates.len();
+                }
+                Ok(batches)
+            }
+            _ => Ok(RowBatches::from_result(Box::pin(self.run_node(node)).await?)),
+        }
+    }
+    
+    /// Execute a node as batches of rows, recording its statistics
+    async fn execute_batches(&self, node: &PlanNode) -> NirvResult<RowBatches> {
+        if !is_batched(node) {
+            return Ok(RowBatches::from_result(self.execute_node(node).await?));
+        }
+        let started = Instant::now();
+        let mut batches = Box::pin(self.run_batches(node)).await?;
+        let input_time = batches.result.stats.as_ref().map_or(Duration::ZERO, |stats| stats.node_time());
+        batches.record_node(node.label(), started.elapsed().saturating_sub(input_time));
+        Ok(batches)
     }
 }
 
@@ -349,8 +1114,6 @@ mod tests {
 
     #[test]
     fn test_apply_limit() {
-        let executor = DefaultQueryExecutor::new();
-        
         let mut result = QueryResult::new();
         result.rows = vec![
             Row::new(vec![Value::Integer(1)]),
@@ -360,7 +1123,7 @@ mod tests {
             Row::new(vec![Value::Integer(5)]),
         ];
         
-        let limited_result = executor.apply_limit(result, 3);
+        let limited_result = RowBatches::from_result(result).limit(3).into_result();
         assert_eq!(limited_result.row_count(), 3);
         
         // Check that the first 3 rows are preserved
@@ -371,51 +1134,45 @@ mod tests {
 
     #[test]
     fn test_apply_limit_no_truncation() {
-        let executor = DefaultQueryExecutor::new();
-        
         let mut result = QueryResult::new();
         result.rows = vec![
             Row::new(vec![Value::Integer(1)]),
             Row::new(vec![Value::Integer(2)]),
         ];
         
-        let limited_result = executor.apply_limit(result, 5);
+        let limited_result = RowBatches::from_result(result).limit(5).into_result();
         assert_eq!(limited_result.row_count(), 2); // No truncation needed
     }
 
     #[test]
     fn test_compare_values() {
-        let executor = DefaultQueryExecutor::new();
-        
         // Test integer comparison
         assert_eq!(
-            executor.compare_values(&Value::Integer(1), &Value::Integer(2)),
+            compare_values(&Value::Integer(1), &Value::Integer(2)),
             std::cmp::Ordering::Less
         );
         
         // Test string comparison
         assert_eq!(
-            executor.compare_values(&Value::Text("apple".to_string()), &Value::Text("banana".to_string())),
+            compare_values(&Value::Text("apple".to_string()), &Value::Text("banana".to_string())),
             std::cmp::Ordering::Less
         );
         
         // Test null comparison
         assert_eq!(
-            executor.compare_values(&Value::Null, &Value::Integer(1)),
+            compare_values(&Value::Null, &Value::Integer(1)),
             std::cmp::Ordering::Less
         );
         
         // Test equal values
         assert_eq!(
-            executor.compare_values(&Value::Integer(5), &Value::Integer(5)),
+            compare_values(&Value::Integer(5), &Value::Integer(5)),
             std::cmp::Ordering::Equal
         );
     }
 
     #[test]
     fn test_apply_sort_ascending() {
-        let executor = DefaultQueryExecutor::new();
-        
         let mut result = QueryResult::new();
         result.columns = vec![
             ColumnMetadata {
@@ -434,10 +1191,11 @@ mod tests {
             columns: vec![OrderColumn {
                 column: "value".to_string(),
                 direction: OrderDirection::Ascending,
+                nulls: None,
             }],
         };
         
-        let sorted_result = executor.apply_sort(result, &order_by).unwrap();
+        let sorted_result = sort_rows(result, &order_by, IdentifierCase::default(), Collation::default()).unwrap();
         
         assert_eq!(sorted_result.rows[0].get(0), Some(&Value::Integer(1)));
         assert_eq!(sorted_result.rows[1].get(0), Some(&Value::Integer(2)));
@@ -446,8 +1204,6 @@ mod tests {
 
     #[test]
     fn test_apply_sort_descending() {
-        let executor = DefaultQueryExecutor::new();
-        
         let mut result = QueryResult::new();
         result.columns = vec![
             ColumnMetadata {
@@ -466,10 +1222,11 @@ mod tests {
             columns: vec![OrderColumn {
                 column: "name".to_string(),
                 direction: OrderDirection::Descending,
+                nulls: None,
             }],
         };
         
-        let sorted_result = executor.apply_sort(result, &order_by).unwrap();
+        let sorted_result = sort_rows(result, &order_by, IdentifierCase::default(), Collation::default()).unwrap();
         
         assert_eq!(sorted_result.rows[0].get(0), Some(&Value::Text("Charlie".to_string())));
         assert_eq!(sorted_result.rows[1].get(0), Some(&Value::Text("Bob".to_string())));
@@ -477,9 +1234,43 @@ mod tests {
     }
 
     #[test]
-    fn test_apply_sort_nonexistent_column() {
-        let executor = DefaultQueryExecutor::new();
+    fn test_apply_sort_multiple_columns_and_nulls() {
+        let mut result = QueryResult::new();
+        result.columns = ["region", "score", "id"].iter()
+            .map(|name| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true })
+            .collect();
+        let row = |region: &str, score: Value, id: i64| Row::new(vec![Value::Text(region.to_string()), score, Value::Integer(id)]);
+        result.rows = vec![
+            row("eu", Value::Integer(10), 1),
+            row("us", Value::Null, 2),
+            row("eu", Value::Float(2.5), 3),
+            row("eu", Value::Null, 4),
+            row("us", Value::Integer(7), 5),
+            row("eu", Value::Integer(10), 6),
+        ];
+        let order = |column: &str, direction: OrderDirection, nulls: Option<NullsOrder>| OrderColumn { column: column.to_string(), direction, nulls };
+        let ids = |result: &QueryResult| -> Vec<Value> { result.rows.iter().map(|row| row.values[2].clone()).collect() };
+        
+        // Ties on the first key are broken by the second; equal rows keep their order
+        let order_by = OrderBy { columns: vec![
+            order("region", OrderDirection::Ascending, None),
+            order("score", OrderDirection::Descending, Some(NullsOrder::First)),
+        ] };
+        let sorted = sort_rows(result.clone(), &order_by, IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(ids(&sorted), [4, 1, 6, 3, 2, 5].map(Value::Integer));
         
+        // Without NULLS FIRST or LAST, NULLs sort before every value; integers and floats
+        // compare as numbers
+        let order_by = OrderBy { columns: vec![order("score", OrderDirection::Ascending, None)] };
+        let sorted = sort_rows(result.clone(), &order_by, IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(ids(&sorted), [2, 4, 3, 5, 1, 6].map(Value::Integer));
+        let order_by = OrderBy { columns: vec![order("score", OrderDirection::Ascending, Some(NullsOrder::Last))] };
+        let sorted = sort_rows(result, &order_by, IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(ids(&sorted), [3, 5, 1, 6, 2, 4].map(Value::Integer));
+    }
+
+    #[test]
+    fn test_apply_sort_nonexistent_column() {
         let mut result = QueryResult::new();
         result.columns = vec![
             ColumnMetadata {
@@ -494,10 +1285,11 @@ mod tests {
             columns: vec![OrderColumn {
                 column: "nonexistent".to_string(),
                 direction: OrderDirection::Ascending,
+                nulls: None,
             }],
         };
         
-        let result = executor.apply_sort(result, &order_by);
+        let result = sort_rows(result, &order_by, IdentifierCase::default(), Collation::default());
         assert!(result.is_err());
         
         match result.unwrap_err() {
@@ -585,4 +1377,224 @@ mod tests {
         assert_eq!(result.row_count(), 1);
         assert_eq!(result.rows[0].get(0), Some(&Value::Integer(1)));
     }
+    
+    #[test]
+    fn test_sample_rows_keeps_order() {
+        let mut result = QueryResult::new();
+        result.rows = (0..50).map(|i| Row::new(vec![Value::Integer(i)])).collect();
+        
+        let sampled = sample_rows(result.clone(), &Sample::Rows(10));
+        assert_eq!(sampled.rows.len(), 10);
+        let ids: Vec<&Value> = sampled.rows.iter().map(|row| &row.values[0]).collect();
+        assert!(ids.windows(2).all(|pair| matches!((pair[0], pair[1]), (Value::Integer(a), Value::Integer(b)) if a < b)));
+        
+        assert_eq!(sample_rows(result.clone(), &Sample::Rows(100)).rows.len(), 50);
+        assert_eq!(sample_rows(result.clone(), &Sample::Percent(100.0)).rows.len(), 50);
+        assert!(sample_rows(result, &Sample::Percent(0.0)).rows.is_empty());
+    }
+
+    #[test]
+    fn test_deduplicate_rows() {
+        let mut result = QueryResult::new();
+        result.columns = ["id", "version"].iter()
+            .map(|name| ColumnMetadata { name: name.to_string(), data_type: DataType::Integer, nullable: false })
+            .collect();
+        result.rows = [(1, 2), (2, 1), (1, 3), (1, 1), (2, 5)].iter()
+            .map(|(id, version)| Row::new(vec![Value::Integer(*id), Value::Integer(*version)]))
+            .collect();
+        let versions = |result: QueryResult| result.rows.iter().map(|row| row.values[1].clone()).collect::<Vec<_>>();
+        let deduplicate = |keep, order_by| Deduplicate { columns: vec!["ID".to_string()], keep, order_by };
+        let by_version = Some(crate::utils::types::OrderColumn { column: "version".to_string(), direction: OrderDirection::Ascending, nulls: None });
+        
+        // Arrival order, then ordered by version; kept rows stay where they were
+        let first = deduplicate_rows(result.clone(), &deduplicate(DedupKeep::First, None), IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(versions(first), vec![Value::Integer(2), Value::Integer(1)]);
+        let last = deduplicate_rows(result.clone(), &deduplicate(DedupKeep::Last, None), IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(versions(last), vec![Value::Integer(1), Value::Integer(5)]);
+        let newest = deduplicate_rows(result.clone(), &deduplicate(DedupKeep::Last, by_version.clone()), IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(versions(newest), vec![Value::Integer(3), Value::Integer(5)]);
+        let oldest = deduplicate_rows(result.clone(), &deduplicate(DedupKeep::First, by_version), IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(versions(oldest), vec![Value::Integer(1), Value::Integer(1)]);
+        
+        assert!(deduplicate_rows(result, &Deduplicate { columns: vec!["missing".to_string()], keep: DedupKeep::First, order_by: None }, IdentifierCase::default(), Collation::default()).is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_join_rows() {
+        let table = |names: &[&str], rows: &[(i64, &str)]| {
+            let mut result = QueryResult::new();
+            result.columns = names.iter()
+                .map(|name| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: false })
+                .collect();
+            result.rows = rows.iter()
+                .map(|(id, name)| Row::new(vec![Value::Integer(*id), Value::Text(name.to_string())]))
+                .collect();
+            result
+        };
+        let users = table(&["id", "name"], &[(1, "Jonathan Smith"), (2, "Maria Garcia"), (3, "Wei Chen")]);
+        let customers = table(&["region", "customer_name"], &[(1, "jonathon smith "), (1, "Maria García"), (2, "Bob Stone")]);
+        let mut join = Join {
+            join_type: JoinType::Inner,
+            left_source: "u".to_string(),
+            right_source: "c".to_string(),
+            on_condition: Vec::new(),
+            fuzzy_condition: Some(crate::utils::types::FuzzyCondition {
+                left_column: "u.name".to_string(),
+                right_column: "c.customer_name".to_string(),
+                threshold: 0.9,
+                metric: crate::utils::similarity::SimilarityMetric::JaroWinkler,
+            }),
+        };
+        let pairs = |result: QueryResult| result.rows.iter()
+            .map(|row| (row.values[0].clone(), row.values[3].clone()))
+            .collect::<Vec<_>>();
+        let text = |s: &str| Value::Text(s.to_string());
+        
+        let inner = join_rows(users.clone(), customers.clone(), &join, IdentifierCase::default(), Collation::default()).unwrap();
+        let names: Vec<&str> = inner.columns.iter().map(|col| col.name.as_str()).collect();
+        assert_eq!(names, vec!["u.id", "u.name", "c.region", "c.customer_name"]);
+        assert_eq!(pairs(inner), vec![
+            (Value::Integer(1), text("jonathon smith ")),
+            (Value::Integer(2), text("Maria García")),
+        ]);
+        
+        // Equalities narrow the candidates, and outer joins keep unpaired rows
+        join.join_type = JoinType::Full;
+        join.on_condition.push(crate::utils::types::JoinCondition { left_column: "id".to_string(), right_column: "c.region".to_string() });
+        let full = join_rows(users.clone(), customers.clone(), &join, IdentifierCase::default(), Collation::default()).unwrap();
+        assert_eq!(pairs(full), vec![
+            (Value::Integer(1), text("jonathon smith ")),
+            (Value::Integer(2), Value::Null),
+            (Value::Integer(3), Value::Null),
+            (Value::Null, text("Maria García")),
+            (Value::Null, text("Bob Stone")),
+        ]);
+        
+        join.on_condition[0].left_column = "x.id".to_string();
+        assert!(join_rows(users, customers, &join, IdentifierCase::default(), Collation::default()).is_err());
+    }
+
+    #[test]
+    fn test_row_hashes_and_checksum() {
+        let table = |names: [&str; 2], rows: &[(i64, &str)], swap: bool| {
+            let mut result = QueryResult::new();
+            let names = if swap { [names[1], names[0]] } else { names };
+            result.columns = names.iter()
+                .map(|name| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: false })
+                .collect();
+            result.rows = rows.iter()
+                .map(|(id, name)| {
+                    let values = vec![Value::Integer(*id), Value::Text(name.to_string())];
+                    Row::new(if swap { values.into_iter().rev().collect() } else { values })
+                })
+                .collect();
+            result
+        };
+        let origin = table(["id", "name"], &[(1, "Ann"), (2, "Bo")], false);
+        let migrated = table(["ID", "name"], &[(2, "Bo"), (1, "Ann")], true);
+        
+        // Column and row order do not change the checksum, a changed value does
+        let checksum = |result: QueryResult| checksum_rows(result).rows[0].values.clone();
+        assert_eq!(checksum(origin.clone()), checksum(migrated));
+        assert_eq!(checksum(origin.clone())[1], Value::Integer(2));
+        assert_ne!(checksum(origin.clone()), checksum(table(["id", "name"], &[(1, "Ann"), (2, "Bob")], false)));
+        
+        let projections = vec![
+            Column { name: "id".to_string(), alias: None, source: None },
+            Column { name: "md5(name)".to_string(), alias: None, source: None },
+            Column { name: "hash_row()".to_string(), alias: Some("row".to_string()), source: None },
+        ];
+        assert_eq!(computed_input_columns(&projections), vec![Column { name: "*".to_string(), alias: None, source: None }]);
+        let hashed = evaluate_computed_columns(origin, &projections, IdentifierCase::default()).unwrap();
+        let names: Vec<&str> = hashed.columns.iter().map(|col| col.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "md5(name)", "row"]);
+        assert_eq!(hashed.rows[0].values[1], Value::Text("82e335f7ac0e0e412b2ae15abe662110".to_string()));
+        assert_ne!(hashed.rows[0].values[2], hashed.rows[1].values[2]);
+    }
+    
+    #[test]
+    fn test_date_functions() {
+        let mut result = QueryResult::new();
+        result.columns = vec![ColumnMetadata { name: "created_at".to_string(), data_type: DataType::DateTime, nullable: true }];
+        result.rows = vec![
+            Row::new(vec![Value::DateTime("2024-03-01 02:30:00".to_string())]),
+            Row::new(vec![Value::Null]),
+        ];
+        let projections = vec![
+            Column { name: "date_trunc('day', created_at, '-05:00')".to_string(), alias: Some("day".to_string()), source: None },
+            Column { name: "now()".to_string(), alias: None, source: None },
+        ];
+        assert_eq!(computed_input_columns(&projections), vec![Column { name: "created_at".to_string(), alias: None, source: None }]);
+        
+        let computed = evaluate_computed_columns(result, &projections, IdentifierCase::default()).unwrap();
+        assert_eq!(computed.columns[0].data_type, DataType::DateTime);
+        assert_eq!(computed.columns[1].name, "now()");
+        assert_eq!(computed.rows[0].values[0], Value::DateTime("2024-02-29 05:00:00".to_string()));
+        assert_eq!(computed.rows[1].values[0], Value::Null);
+        assert!(matches!(&computed.rows[0].values[1], Value::DateTime(_)));
+        assert_eq!(computed.rows[0].values[1], computed.rows[1].values[1]);
+    }
+    
+    #[test]
+    fn test_fetched_at_column() {
+        let mut result = QueryResult::new();
+        result.columns = vec![ColumnMetadata { name: "id".to_string(), data_type: DataType::Integer, nullable: false }];
+        result.rows = vec![Row::new(vec![Value::Integer(1)])];
+        let fetched_at = chrono::DateTime::parse_from_rfc3339("2026-10-14T08:00:00Z").unwrap().with_timezone(&Utc);
+        result.stats = Some(ExecutionStats::default());
+        result.stats.as_mut().unwrap().source_mut("api.users").fetched_at = Some(fetched_at);
+        let projections = vec![
+            Column { name: "id".to_string(), alias: None, source: None },
+            Column { name: "u._NIRV_FETCHED_AT".to_string(), alias: None, source: None },
+        ];
+        assert!(is_engine_function(&projections[1].name));
+        assert_eq!(computed_input_columns(&projections), vec![projections[0].clone()]);
+        
+        let computed = evaluate_computed_columns(result, &projections, IdentifierCase::default()).unwrap();
+        assert_eq!(computed.columns[1].name, "_nirv_fetched_at");
+        assert_eq!(computed.rows[0].values[1], Value::DateTime("2026-10-14 08:00:00".to_string()));
+    }
+    #[test]
+    fn test_collated_rows() {
+        let text = |s: &str| Value::Text(s.to_string());
+        let table = |names: &[&str]| {
+            let mut result = QueryResult::new();
+            result.columns = vec![ColumnMetadata { name: "name".to_string(), data_type: DataType::Text, nullable: false }];
+            result.rows = names.iter().map(|name| Row::new(vec![Value::Text(name.to_string())])).collect();
+            result
+        };
+        let names = table(&["acme", "Émile", "ACME", "Zoe", "emile"]);
+        let names_of = |result: &QueryResult| result.rows.iter().map(|row| row.values[0].clone()).collect::<Vec<_>>();
+        
+        let equals_acme = vec![Predicate {
+            column: "name".to_string(),
+            operator: PredicateOperator::Equal,
+            value: PredicateValue::String("Acme".to_string()),
+        }];
+        assert!(filter_rows(names.clone(), &equals_acme, IdentifierCase::default(), Collation::Binary).unwrap().rows.is_empty());
+        let filtered = filter_rows(names.clone(), &equals_acme, IdentifierCase::default(), Collation::CaseInsensitive).unwrap();
+        assert_eq!(names_of(&filtered), vec![text("acme"), text("ACME")]);
+        assert!(predicate_matches(&text("ÉMILE"), &PredicateOperator::Like, &PredicateValue::String("em%".to_string()), Collation::Unicode));
+        
+        let by_name = OrderBy { columns: vec![OrderColumn { column: "name".to_string(), direction: OrderDirection::Ascending, nulls: None }] };
+        let binary = sort_rows(names.clone(), &by_name, IdentifierCase::default(), Collation::Binary).unwrap();
+        assert_eq!(names_of(&binary), vec![text("ACME"), text("Zoe"), text("acme"), text("emile"), text("Émile")]);
+        let unicode = sort_rows(names.clone(), &by_name, IdentifierCase::default(), Collation::Unicode).unwrap();
+        assert_eq!(names_of(&unicode), vec![text("acme"), text("ACME"), text("Émile"), text("emile"), text("Zoe")]);
+        
+        let deduplicate = Deduplicate { columns: vec!["name".to_string()], keep: DedupKeep::First, order_by: None };
+        let distinct = deduplicate_rows(names.clone(), &deduplicate, IdentifierCase::default(), Collation::CaseInsensitive).unwrap();
+        assert_eq!(names_of(&distinct), vec![text("acme"), text("Émile"), text("Zoe"), text("emile")]);
+        
+        let accounts = table(&["ACME", "Globex"]);
+        let join = Join {
+            join_type: JoinType::Inner,
+            left_source: "c".to_string(),
+            right_source: "a".to_string(),
+            on_condition: vec![crate::utils::types::JoinCondition { left_column: "c.name".to_string(), right_column: "a.name".to_string() }],
+            fuzzy_condition: None,
+        };
+        assert_eq!(join_rows(names.clone(), accounts.clone(), &join, IdentifierCase::default(), Collation::Binary).unwrap().rows.len(), 1);
+        assert_eq!(join_rows(names, accounts, &join, IdentifierCase::default(), Collation::CaseInsensitive).unwrap().rows.len(), 2);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::engine::Dispatcher;
+use crate::utils::{
+    error::{NirvError, NirvResult, QueryParsingError},
+    identifier::{split_qualifier, IdentifierCase},
+    types::{ConnectorType, DataSource, DataType, InternalQuery, Predicate, PredicateOperator, PredicateValue, Pushdown, QueryWarning, Schema},
+};
+
+/// Whether lint findings are left out, reported as warnings or stop the query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintMode {
+    Off,
+    #[default]
+    Warn,
+    Strict,
+}
+
+impl LintMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "off" => Some(LintMode::Off),
+            "warn" => Some(LintMode::Warn),
+            "strict" => Some(LintMode::Strict),
+            _ => None,
+        }
+    }
+
+    /// The mode a query's `lint` directive sets, or this one
+    pub fn for_query(self, query: &InternalQuery) -> NirvResult<Self> {
+        let Some(value) = query.directives.get("lint") else { return Ok(self) };
+        Self::parse(value).ok_or_else(|| QueryParsingError::InvalidSyntax(format!(
+            "Invalid directive: lint expects off, warn or strict, not '{}'", value
+        )).into())
+    }
+}
+
+/// Foot-guns the lint pass looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    /// `SELECT *` from a REST source without a LIMIT, which fetches every page
+    UnboundedRestScan,
+    /// Predicates of which none can be evaluated by the source, which returns every row
+    FullScan,
+    /// Sources listed without a join condition between them
+    CrossJoin,
+    /// A comparison of values of different types, converted before comparing
+    ImplicitCoercion,
+}
+
+impl LintRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintRule::UnboundedRestScan => "unbounded_rest_scan",
+            LintRule::FullScan => "full_scan",
+            LintRule::CrossJoin => "cross_join",
+            LintRule::ImplicitCoercion => "implicit_coercion",
+        }
+    }
+}
+
+/// Something a query does that is likely to be slow or wrong
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    /// Source as `type.identifier`
+    pub source: String,
+    /// What is wrong, phrased to follow the source name
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.source, self.message, self.rule.as_str())
+    }
+}
+
+impl From<&LintFinding> for QueryWarning {
+    fn from(finding: &LintFinding) -> Self {
+        QueryWarning::lint(finding.source.clone(), finding.rule.as_str(), &finding.message)
+    }
+}
+
+/// The error for a query strict mode stops, listing every finding
+pub fn lint_error(findings: &[LintFinding]) -> NirvError {
+    NirvError::Lint(findings.iter().map(LintFinding::to_string).collect::<Vec<_>>().join("; "))
+}
+
+/// What the lint rules need to know of one source of a query
+#[derive(Debug, Clone)]
+pub struct SourceFacts {
+    pub connector_type: ConnectorType,
+    /// Whether the connector evaluates each of the query's predicates, in order
+    pub pushable: Vec<bool>,
+    /// Columns of the source, when it could describe them
+    pub schema: Option<Schema>,
+}
+
+/// Gather the facts of each source of a query from the connectors serving them. Schemas
+/// are read only when the query compares columns, and sources that cannot tell are skipped.
+pub async fn source_facts(query: &InternalQuery, dispatcher: &dyn Dispatcher) -> Vec<Option<SourceFacts>> {
+    let compares = !query.predicates.is_empty() || query.joins.iter().any(|join| !join.on_condition.is_empty());
+    let mut facts = Vec::new();
+    for source in &query.sources {
+        let Some(connector) = dispatcher.get_connector(&source.object_type) else {
+            facts.push(None);
+            continue;
+        };
+        // Files type the columns they cannot tell as text, which compares with numbers as numbers
+        let connector_type = connector.get_connector_type();
+        let schema = match compares && connector_type != ConnectorType::File {
+            true => dispatcher.describe_object(&source.object_type, &source.identifier).await.ok(),
+            false => None,
+        };
+        facts.push(Some(SourceFacts {
+            connector_type,
+            pushable: query.predicates.iter().map(|predicate| connector.supports_predicate(predicate)).collect(),
+            schema,
+        }));
+    }
+    facts
+}
+
+/// Check a query against every rule but the allowed ones, given the facts of its sources
+/// in the order it lists them
+pub fn lint_query(query: &InternalQuery, facts: &[Option<SourceFacts>], allowed: &[LintRule], case: IdentifierCase) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    let name = |source: &DataSource| format!("{}.{}", source.object_type, source.identifier);
+    let sources = || query.sources.iter().zip(facts).filter_map(|(source, facts)| Some((source, facts.as_ref()?)));
+
+    let selects_all = query.projections.iter().any(|column| column.name == "*");
+    if selects_all && query.limit.is_none() {
+        for (source, _) in sources().filter(|(_, facts)| facts.connector_type == ConnectorType::Rest) {
+            findings.push(LintFinding {
+                rule: LintRule::UnboundedRestScan,
+                source: name(source),
+                message: "is read page by page until it runs out: SELECT * without a LIMIT".to_string(),
+            });
+        }
+    }
+
+    // A hint keeping predicates from the source asked for the scan
+    if let ([source], [Some(facts)]) = (query.sources.as_slice(), facts) {
+        if !query.predicates.is_empty() && query.hints.pushes_down(Pushdown::Predicates) && !facts.pushable.contains(&true) {
+            findings.push(LintFinding {
+                rule: LintRule::FullScan,
+                source: name(source),
+                message: format!("returns every row to be filtered locally: it cannot evaluate {}", describe_predicates(&query.predicates)),
+            });
+        }
+    }
+
+    let joined: Vec<&str> = query.joins.iter()
+        .filter(|join| !join.on_condition.is_empty() || join.fuzzy_condition.is_some())
+        .flat_map(|join| [join.left_source.as_str(), join.right_source.as_str()])
+        .collect();
+    if query.sources.len() > 1 {
+        for source in query.sources.iter().skip(1).filter(|source| !joined.contains(&label(source).as_str())) {
+            findings.push(LintFinding {
+                rule: LintRule::CrossJoin,
+                source: name(source),
+                message: format!("is paired with every row of {} without a join condition", name(&query.sources[0])),
+            });
+        }
+    }
+
+    let schemas: Vec<(&DataSource, &Schema)> = sources().filter_map(|(source, facts)| Some((source, facts.schema.as_ref()?))).collect();
+    for predicate in &query.predicates {
+        let Some((source, data_type)) = column_type(&predicate.column, &schemas, case) else { continue };
+        if let Some(value) = coerced_value(&predicate.operator, &predicate.value, &data_type) {
+            findings.push(LintFinding {
+                rule: LintRule::ImplicitCoercion,
+                source: name(source),
+                message: format!("column '{}' of type {:?} is compared with {}", predicate.column, data_type, value),
+            });
+        }
+    }
+    for condition in query.joins.iter().flat_map(|join| &join.on_condition) {
+        let left = column_type(&condition.left_column, &schemas, case);
+        let right = column_type(&condition.right_column, &schemas, case);
+        if let (Some((source, left_type)), Some((_, right_type))) = (left, right) {
+            if !comparable(&left_type, &right_type) {
+                findings.push(LintFinding {
+                    rule: LintRule::ImplicitCoercion,
+                    source: name(source),
+                    message: format!(
+                        "column '{}' of type {:?} is joined with '{}' of type {:?}",
+                        condition.left_column, left_type, condition.right_column, right_type
+                    ),
+                });
+            }
+        }
+    }
+
+    findings.retain(|finding| !allowed.contains(&finding.rule));
+    findings
+}
+
+/// Label a query's columns and joins qualify a source by
+fn label(source: &DataSource) -> String {
+    source.alias.clone().unwrap_or_else(|| source.identifier.clone())
+}
+
+fn describe_predicates(predicates: &[Predicate]) -> String {
+    let columns: Vec<String> = predicates.iter().map(|predicate| format!("'{}'", predicate.column)).collect();
+    format!("the predicates on {}", columns.join(", "))
+}
+
+/// Source and type of the column a query identifier refers to, looked up in the source its
+/// qualifier names or else in the first that has it
+fn column_type<'a>(column: &str, schemas: &[(&'a DataSource, &'a Schema)], case: IdentifierCase) -> Option<(&'a DataSource, DataType)> {
+    let qualifier = split_qualifier(column).map(|(qualifier, _)| qualifier);
+    schemas.iter()
+        .filter(|(source, _)| qualifier.is_none_or(|qualifier| case.matches(qualifier, &label(source))))
+        .find_map(|(source, schema)| {
+            let index = schema.column_index(column, case)?;
+            Some((*source, schema.columns[index].data_type.clone()))
+        })
+}
+
+/// The literal a predicate compares a column of this type with, when it is of a type the
+/// column's values must be converted to or from first
+fn coerced_value(operator: &PredicateOperator, value: &PredicateValue, data_type: &DataType) -> Option<String> {
+    let numeric = matches!(data_type, DataType::Integer | DataType::Float);
+    match (operator, value) {
+        (PredicateOperator::IsNull | PredicateOperator::IsNotNull, _) | (_, PredicateValue::Null) => None,
+        (PredicateOperator::Like | PredicateOperator::Regexp, PredicateValue::String(pattern)) => {
+            (numeric || *data_type == DataType::Boolean).then(|| format!("the pattern '{}'", pattern))
+        }
+        (_, PredicateValue::List(values)) => values.iter().find_map(|value| coerced_value(operator, value, data_type)),
+        (_, PredicateValue::String(text)) => {
+            (numeric || *data_type == DataType::Boolean).then(|| format!("the text '{}'", text))
+        }
+        (_, PredicateValue::Number(number)) => {
+            matches!(data_type, DataType::Text | DataType::Boolean).then(|| format!("the number {}", number))
+        }
+        (_, PredicateValue::Integer(number)) => {
+            matches!(data_type, DataType::Text | DataType::Boolean).then(|| format!("the number {}", number))
+        }
+        (_, PredicateValue::Boolean(flag)) => {
+            (*data_type != DataType::Boolean).then(|| format!("the boolean {}", flag))
+        }
+    }
+}
+
+/// Whether values of the two types compare without converting either
+fn comparable(left: &DataType, right: &DataType) -> bool {
+    let numeric = |data_type: &DataType| matches!(data_type, DataType::Integer | DataType::Float);
+    let temporal = |data_type: &DataType| matches!(data_type, DataType::Date | DataType::DateTime);
+    left == right || (numeric(left) && numeric(right)) || (temporal(left) && temporal(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{DefaultQueryParser, QueryParser};
+    use crate::utils::types::ColumnMetadata;
+    use std::collections::HashMap;
+
+    fn facts(connector_type: ConnectorType, pushable: bool, query: &InternalQuery) -> Option<SourceFacts> {
+        let column = |name: &str, data_type: DataType| ColumnMetadata { name: name.to_string(), data_type, nullable: true };
+        Some(SourceFacts {
+            connector_type,
+            pushable: vec![pushable; query.predicates.len()],
+            schema: Some(Schema {
+                name: "users".to_string(),
+                columns: vec![column("id", DataType::Integer), column("name", DataType::Text), column("owner", DataType::Text)],
+                primary_key: None,
+                indexes: Vec::new(),
+                column_details: HashMap::new(),
+                foreign_keys: Vec::new(),
+            }),
+        })
+    }
+
+    fn rules(findings: &[LintFinding]) -> Vec<&'static str> {
+        findings.iter().map(|finding| finding.rule.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_lint_rules() {
+        let parser = DefaultQueryParser::new().unwrap();
+        let case = IdentifierCase::default();
+
+        let query = parser.parse_sql("SELECT * FROM source('api.users') WHERE id = '7'").await.unwrap();
+        let found = lint_query(&query, &[facts(ConnectorType::Rest, false, &query)], &[], case);
+        assert_eq!(rules(&found), ["unbounded_rest_scan", "full_scan", "implicit_coercion"]);
+        assert_eq!(found[2].to_string(), "api.users column 'id' of type Integer is compared with the text '7' (implicit_coercion)");
+        assert!(lint_query(&query, &[facts(ConnectorType::Rest, false, &query)], &[LintRule::FullScan, LintRule::ImplicitCoercion, LintRule::UnboundedRestScan], case).is_empty());
+
+        let query = parser.parse_sql("SELECT * FROM source('api.users') WHERE name IN ('a', 'b') LIMIT 10").await.unwrap();
+        assert!(lint_query(&query, &[facts(ConnectorType::Rest, true, &query)], &[], case).is_empty());
+
+        let query = parser.parse_sql("SELECT u.name FROM source('db.users') u, source('db.teams') t").await.unwrap();
+        let facts_of = |query: &InternalQuery| vec![facts(ConnectorType::PostgreSQL, true, query), facts(ConnectorType::PostgreSQL, true, query)];
+        assert_eq!(rules(&lint_query(&query, &facts_of(&query), &[], case)), ["cross_join"]);
+
+        let query = parser.parse_sql("SELECT u.name FROM source('db.users') u CROSS JOIN source('db.teams') t").await.unwrap();
+        assert_eq!(rules(&lint_query(&query, &facts_of(&query), &[], case)), ["cross_join"]);
+
+        let query = parser.parse_sql("SELECT u.name FROM source('db.users') u JOIN source('db.teams') t ON u.id = t.owner").await.unwrap();
+        assert_eq!(rules(&lint_query(&query, &facts_of(&query), &[], case)), ["implicit_coercion"]);
+    }
+
+    #[test]
+    fn test_lint_mode_directive() {
+        let mut query = InternalQuery::new(crate::utils::types::QueryOperation::Select);
+        assert_eq!(LintMode::Warn.for_query(&query).unwrap(), LintMode::Warn);
+        query.directives = crate::utils::directives::QueryDirectives::parse(&["nirv: lint=strict"]).unwrap();
+        assert_eq!(LintMode::Off.for_query(&query).unwrap(), LintMode::Strict);
+        query.directives = crate::utils::directives::QueryDirectives::parse(&["nirv: lint=loud"]).unwrap();
+        assert!(LintMode::Warn.for_query(&query).is_err());
+    }
+}
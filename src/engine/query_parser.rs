@@ -1,11 +1,17 @@
 use async_trait::async_trait;
-use crate::utils::{InternalQuery, QueryOperation, DataSource, Column, Predicate, PredicateOperator, PredicateValue, OrderBy, OrderColumn, OrderDirection};
+use crate::utils::{Assignment, Collation, QueryDirectives, InternalQuery, QueryOperation, DataSource, Column, Predicate, PredicateOperator, PredicateValue, OrderBy, OrderColumn, OrderDirection, NullsOrder, Sample, Deduplicate, DedupKeep, QueryHints, JsonExtract, JsonPath, RegexFunction, RegexOperation, compile_regex, HashAlgorithm, HashFunction, RowHash, DateTrunc, TruncUnit, SessionTimeZone, NOW_FUNCTION, COUNT_ALL, current_timestamp, current_date, Interval, format_utc, parse_date_time, SourceUri, quote_identifier, split_qualifier, Join, JoinType, JoinCondition, FuzzyCondition, SimilarityMetric, GroupByItem, SUBQUERY_OBJECT_TYPE};
 use crate::utils::error::{QueryParsingError, NirvResult};
-use sqlparser::ast::{Statement, Query, SelectItem, Expr, BinaryOperator, Value as SqlValue, OrderByExpr, FunctionArg, FunctionArgExpr};
-use sqlparser::dialect::{PostgreSqlDialect, MySqlDialect, SQLiteDialect, GenericDialect};
+use crate::engine::approx_aggregate::{ApproxAggregate, ApproxFunction};
+use crate::engine::client_dialect::ClientDialect;
+use crate::connectors::{InlineTable, INLINE_OBJECT_TYPE};
+use sqlparser::ast::{Statement, Query, SelectItem, Expr, BinaryOperator, DataType as SqlDataType, Value as SqlValue, OrderByExpr, FunctionArg, FunctionArgExpr, Ident, JoinOperator, JoinConstraint, GroupByExpr, SetExpr, TableFactor, TableWithJoins};
+use sqlparser::dialect::{Dialect, PostgreSqlDialect, MySqlDialect, SQLiteDialect, GenericDialect, MsSqlDialect};
 use sqlparser::parser::Parser;
 use regex::Regex;
 
+/// Name standing in for the `source(...)` an INSERT, UPDATE or DELETE writes while it is parsed
+const WRITE_TARGET: &str = "nirv_write_target";
+
 /// Trait for SQL query parsing functionality
 #[async_trait]
 pub trait QueryParser: Send + Sync {
@@ -15,8 +21,53 @@ pub trait QueryParser: Send + Sync {
     /// Validate SQL syntax without full parsing
     async fn validate_syntax(&self, sql: &str) -> NirvResult<bool>;
     
-    /// Extract source specifications from SQL
+    /// Extract source specifications from SQL, including those inside joins and derived tables
     async fn extract_sources(&self, sql: &str) -> NirvResult<Vec<String>>;
+    
+    /// Parse SQL written in a client connection's dialect. Parsers that know no dialects
+    /// parse it like any other SQL.
+    async fn parse_sql_as(&self, sql: &str, _dialect: ClientDialect) -> NirvResult<InternalQuery> {
+        self.parse_sql(sql).await
+    }
+}
+
+/// A parse error from one dialect, with where in the SQL it stopped
+struct SyntaxError {
+    dialect: String,
+    message: String,
+    /// Line and column, both from 1; errors without one rank before the start of the SQL
+    position: Option<(u64, u64)>,
+}
+
+impl SyntaxError {
+    /// Split sqlparser's ` at Line: 1, Column 8` suffix off its message. Errors at the end of
+    /// the SQL carry no position, so they are placed just past its last character.
+    fn new(dialect: String, error: &str, sql: &str) -> Self {
+        let error = error.strip_prefix("sql parser error: ").unwrap_or(error);
+        let located = error.rsplit_once(" at Line: ").and_then(|(message, location)| {
+            let (line, column) = location.split_once(", Column ")?;
+            Some((message, (line.trim().parse().ok()?, column.trim().parse().ok()?)))
+        });
+        match located {
+            Some((message, position)) => Self { dialect, message: message.to_string(), position: Some(position) },
+            None => {
+                let position = error.ends_with("found: EOF").then(|| {
+                    let last_line = sql.trim_end().rsplit('\n').next().unwrap_or("");
+                    (sql.trim_end().lines().count().max(1) as u64, last_line.chars().count() as u64 + 1)
+                });
+                Self { dialect, message: error.to_string(), position }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "Failed to parse SQL as {} at line {}, column {}: {}", self.dialect, line, column, self.message),
+            None => write!(f, "Failed to parse SQL as {}: {}", self.dialect, self.message),
+        }
+    }
 }
 
 /// Default SQL Query Parser that converts SQL statements to internal representation
@@ -25,8 +76,17 @@ pub struct DefaultQueryParser {
     mysql_dialect: MySqlDialect,
     sqlite_dialect: SQLiteDialect,
     generic_dialect: GenericDialect,
+    mssql_dialect: MsSqlDialect,
     #[allow(dead_code)]
     source_regex: Regex,
+    sample_regex: Regex,
+    deduplicate_regex: Regex,
+    create_table_regex: Regex,
+    write_regex: Regex,
+    checksum_regex: Regex,
+    cache_select_regex: Regex,
+    collate_regex: Regex,
+    hints_regex: Regex,
 }
 
 impl DefaultQueryParser {
@@ -34,58 +94,385 @@ impl DefaultQueryParser {
     pub fn new() -> NirvResult<Self> {
         let source_regex = Regex::new(r#"source\s*\(\s*['"]([^'"]+)['"]\s*\)"#)
             .map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile source regex: {}", e)))?;
+        let sample_regex = Regex::new(
+            r"(?i)\b(?:TABLESAMPLE\s+(?:BERNOULLI|SYSTEM)\s*\(\s*([0-9]+(?:\.[0-9]+)?)\s*\)|SAMPLE\s+([0-9]+(?:\.[0-9]+)?)\s+(PERCENT|ROWS)\b)"
+        ).map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile sample regex: {}", e)))?;
+        let deduplicate_regex = Regex::new(
+            r#"(?i)\bDEDUPLICATE\s+BY\s*\(([^()]*)\)(?:\s+KEEP\s+(FIRST|LAST)\b(?:\s+ORDER\s+BY\s+("[^"]*"|[A-Za-z_][A-Za-z0-9_.]*)(?:\s+(ASC|DESC)\b)?)?)?"#
+        ).map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile deduplicate regex: {}", e)))?;
+        let create_table_regex = Regex::new(r#"(?is)^\s*CREATE\s+TABLE\s+source\s*\(\s*['"]([^'"]+)['"]\s*\)\s+AS\s+(.+)$"#)
+            .map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile create table regex: {}", e)))?;
+        let write_regex = Regex::new(r#"(?is)^\s*(INSERT\s+INTO|UPDATE|DELETE\s+FROM)\s+source\s*\(\s*['"]([^'"]+)['"]\s*\)(.*)$"#)
+            .map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile write regex: {}", e)))?;
+        let checksum_regex = Regex::new(r"(?is)^\s*CHECKSUM\s+TABLE\s+(.+?)\s*;?\s*$")
+            .map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile checksum regex: {}", e)))?;
+        let cache_select_regex = Regex::new(r"(?is)^\s*CACHE\s+(.+?)\s+FOR\s+([0-9]+)\s+(SECONDS?|MINUTES?|HOURS?)\s*;?\s*$")
+            .map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile cache select regex: {}", e)))?;
+        let collate_regex = Regex::new(r#"(?i)\s+COLLATE\s+("[^"]*"|'[^']*'|[A-Za-z_][A-Za-z0-9_-]*)"#)
+            .map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile collate regex: {}", e)))?;
+        let hints_regex = Regex::new(r"(?is)\bSELECT\s+(/\*\+(.*?)\*/)")
+            .map_err(|e| QueryParsingError::InvalidSyntax(format!("Failed to compile hints regex: {}", e)))?;
         
         Ok(Self {
             postgres_dialect: PostgreSqlDialect {},
             mysql_dialect: MySqlDialect {},
             sqlite_dialect: SQLiteDialect {},
             generic_dialect: GenericDialect {},
+            mssql_dialect: MsSqlDialect {},
             source_regex,
+            sample_regex,
+            deduplicate_regex,
+            create_table_regex,
+            write_regex,
+            checksum_regex,
+            cache_select_regex,
+            collate_regex,
+            hints_regex,
         })
     }
 
     /// Parse SQL query string into internal representation
     pub fn parse(&self, sql: &str) -> NirvResult<InternalQuery> {
-        // Try parsing with different dialects
-        let statement = self.try_parse_with_dialects(sql)?;
+        self.parse_as(sql, ClientDialect::Auto)
+    }
+
+    /// Parse SQL written in a client's dialect, with its functions under the engine's names
+    pub fn parse_as(&self, sql: &str, dialect: ClientDialect) -> NirvResult<InternalQuery> {
+        // `CREATE TABLE source(...) AS` is not SQL the parser accepts, so the SELECT is parsed on its own
+        if let Some(captures) = self.create_table_regex.captures(sql) {
+            let uri = SourceUri::parse(&captures[1])?;
+            let mut query = self.parse_as(&captures[2], dialect)?;
+            if query.operation != QueryOperation::Select {
+                return Err(QueryParsingError::UnsupportedFeature("CREATE TABLE ... AS expects a SELECT".to_string()).into());
+            }
+            query.target = Some(DataSource {
+                object_type: uri.object_type().to_string(),
+                identifier: uri.identifier(),
+                alias: None,
+            });
+            return Ok(query);
+        }
+        
+        if let Some(captures) = self.write_regex.captures(sql) {
+            return self.parse_write(&captures, dialect);
+        }
         
-        match statement {
-            Statement::Query(query) => self.convert_query(*query),
-            _ => Err(QueryParsingError::UnsupportedFeature("Only SELECT queries are currently supported".to_string()).into()),
+        // `CHECKSUM TABLE source(...) [WHERE ...]` reads like the SELECT of every column it checksums
+        if let Some(captures) = self.checksum_regex.captures(sql) {
+            let mut query = self.parse_as(&format!("SELECT * FROM {}", &captures[1]), dialect)?;
+            if query.sources.len() != 1 || query.ordering.is_some() || query.limit.is_some() {
+                return Err(QueryParsingError::InvalidSyntax(
+                    "CHECKSUM TABLE expects one source, optionally with a WHERE clause".to_string()
+                ).into());
+            }
+            query.checksum = true;
+            return Ok(query);
         }
+        
+        // `CACHE SELECT ... FOR 10 MINUTES` lets connectors answer the SELECT from responses
+        // cached that long, as a `CACHE_TTL` hint would
+        if let Some(captures) = self.cache_select_regex.captures(sql) {
+            let amount: u64 = captures[2].parse().map_err(|_| QueryParsingError::InvalidSyntax(
+                format!("Invalid cache duration '{}'", &captures[2])
+            ))?;
+            let seconds = match captures[3].to_ascii_uppercase().trim_end_matches('S') {
+                "SECOND" => amount,
+                "MINUTE" => amount * 60,
+                _ => amount * 3600,
+            };
+            let mut query = self.parse_as(&captures[1], dialect)?;
+            query.hints.cache_ttl = Some(std::time::Duration::from_secs(seconds));
+            return Ok(query);
+        }
+        
+        // The SQL parser knows no sampling or deduplication clause, so they are taken out first
+        let (sql, hints) = self.extract_hints(sql)?;
+        let (sql, sample) = self.extract_sample_clause(&sql)?;
+        let (sql, deduplicate) = self.extract_deduplicate_clause(&sql)?;
+        let (sql, collation) = self.extract_collations(&sql)?;
+        
+        // Try parsing with different dialects
+        let mut statement = self.try_parse_with_dialects(&sql, dialect)?;
+        dialect.translate_functions(&mut statement);
+        
+        let mut query = match statement {
+            Statement::Query(query) => self.convert_query(*query)?,
+            _ => return Err(QueryParsingError::UnsupportedFeature("Only SELECT queries are currently supported".to_string()).into()),
+        };
+        query.sample = sample;
+        query.deduplicate = deduplicate;
+        query.collation = collation;
+        query.hints = hints;
+        query.comments = sql_comments(&sql, dialect);
+        query.directives = QueryDirectives::parse(&query.comments)?;
+        Ok(query)
     }
 
-    /// Try parsing with multiple SQL dialects
-    fn try_parse_with_dialects(&self, sql: &str) -> NirvResult<Statement> {
-        // Try PostgreSQL dialect first
-        if let Ok(statements) = Parser::parse_sql(&self.postgres_dialect, sql) {
-            if let Some(statement) = statements.into_iter().next() {
-                return Ok(statement);
+    /// Parse an `INSERT INTO`, `UPDATE` or `DELETE FROM` of one `source(...)`. That is no
+    /// table name the SQL parser accepts after INSERT INTO, so a plain name stands in for it.
+    fn parse_write(&self, captures: &regex::Captures, dialect: ClientDialect) -> NirvResult<InternalQuery> {
+        let uri = SourceUri::parse(&captures[2])?;
+        let sql = format!("{} {}{}", &captures[1], WRITE_TARGET, &captures[3]);
+        let statement = self.try_parse_with_dialects(&sql, dialect)?;
+        let unsupported = |clause: &str| -> NirvResult<InternalQuery> {
+            Err(QueryParsingError::UnsupportedFeature(format!("{} is not supported in writes", clause)).into())
+        };
+
+        let mut query = match statement {
+            Statement::Insert { columns, source, on, returning, .. } => {
+                if on.is_some() {
+                    return unsupported("ON CONFLICT");
+                }
+                if returning.is_some() {
+                    return unsupported("RETURNING");
+                }
+                let SetExpr::Values(values) = source.body.as_ref() else {
+                    return unsupported("INSERT ... SELECT");
+                };
+                let width = if columns.is_empty() { values.rows.first().map_or(0, Vec::len) } else { columns.len() };
+                if values.rows.iter().any(|row| row.len() != width) {
+                    return Err(QueryParsingError::InvalidSyntax(
+                        format!("Every VALUES row must have {} values", width)
+                    ).into());
+                }
+                let mut query = InternalQuery::new(QueryOperation::Insert);
+                query.projections = columns.iter()
+                    .map(|column| Column { name: identifier_name(column), alias: None, source: None })
+                    .collect();
+                query.values = values.rows.iter()
+                    .map(|row| row.iter().map(|expr| self.extract_write_value(expr)).collect::<NirvResult<Vec<_>>>())
+                    .collect::<NirvResult<Vec<_>>>()?;
+                query
+            }
+            Statement::Update { table, assignments, from, selection, returning } => {
+                if !table.joins.is_empty() || from.is_some() {
+                    return unsupported("UPDATE ... FROM");
+                }
+                if returning.is_some() {
+                    return unsupported("RETURNING");
+                }
+                let mut query = InternalQuery::new(QueryOperation::Update);
+                for assignment in &assignments {
+                    let column = assignment.id.last().map(identifier_name).unwrap_or_default();
+                    let value = self.extract_write_value(&assignment.value)?;
+                    query.assignments.push(Assignment { column, value });
+                }
+                if let Some(selection) = &selection {
+                    query.predicates = self.extract_predicates(selection)?;
+                }
+                query
             }
+            Statement::Delete { tables, from, using, selection, returning, order_by, limit } => {
+                if !tables.is_empty() || using.is_some() || from.len() != 1 || !from[0].joins.is_empty() {
+                    return unsupported("Deleting from several tables");
+                }
+                if returning.is_some() {
+                    return unsupported("RETURNING");
+                }
+                if !order_by.is_empty() || limit.is_some() {
+                    return unsupported("DELETE ... ORDER BY or LIMIT");
+                }
+                let mut query = InternalQuery::new(QueryOperation::Delete);
+                if let Some(selection) = &selection {
+                    query.predicates = self.extract_predicates(selection)?;
+                }
+                query
+            }
+            _ => return Err(QueryParsingError::InvalidSyntax(format!("'{}' is not a write of one source", sql)).into()),
+        };
+        query.sources = vec![DataSource {
+            object_type: uri.object_type().to_string(),
+            identifier: uri.identifier(),
+            alias: None,
+        }];
+        query.comments = sql_comments(&sql, dialect);
+        query.directives = QueryDirectives::parse(&query.comments)?;
+        Ok(query)
+    }
+
+    /// A value written by an INSERT or UPDATE: a constant, including a negative number
+    fn extract_write_value(&self, expr: &Expr) -> NirvResult<PredicateValue> {
+        match expr {
+            Expr::UnaryOp { op: sqlparser::ast::UnaryOperator::Minus, expr } => match self.extract_write_value(expr)? {
+                PredicateValue::Integer(i) => Ok(PredicateValue::Integer(-i)),
+                PredicateValue::Number(n) => Ok(PredicateValue::Number(-n)),
+                _ => Err(QueryParsingError::InvalidSyntax(format!("'-{}' is not a number", expr)).into()),
+            },
+            expr => self.extract_predicate_value_from_expr(expr),
+        }
+    }
+
+    /// Remove the `/*+ ... */` hint comment following SELECT, returning the remaining SQL
+    /// and the hints it gives
+    fn extract_hints(&self, sql: &str) -> NirvResult<(String, QueryHints)> {
+        let masked = mask_quoted(sql);
+        let mut matches = self.hints_regex.captures_iter(&masked);
+        let Some(captures) = matches.next() else {
+            return Ok((sql.to_string(), QueryHints::default()));
+        };
+        if matches.next().is_some() {
+            return Err(QueryParsingError::UnsupportedFeature("Only one hint comment is supported".to_string()).into());
         }
 
-        // Try MySQL dialect
-        if let Ok(statements) = Parser::parse_sql(&self.mysql_dialect, sql) {
-            if let Some(statement) = statements.into_iter().next() {
-                return Ok(statement);
-            }
+        let hints = QueryHints::parse(&sql[captures.get(2).expect("hint text").range()])?;
+        let range = captures.get(1).expect("hint comment").range();
+        let mut remaining = String::with_capacity(sql.len());
+        remaining.push_str(&sql[..range.start]);
+        remaining.push_str(&sql[range.end..]);
+        Ok((remaining, hints))
+    }
+
+    /// Remove a `SAMPLE n PERCENT`, `SAMPLE n ROWS` or `TABLESAMPLE BERNOULLI|SYSTEM (n)` clause
+    /// following the FROM clause, returning the remaining SQL and the requested sample
+    fn extract_sample_clause(&self, sql: &str) -> NirvResult<(String, Option<Sample>)> {
+        let masked = mask_quoted(sql);
+        let mut matches = self.sample_regex.captures_iter(&masked);
+        let Some(captures) = matches.next() else {
+            return Ok((sql.to_string(), None));
+        };
+        if matches.next().is_some() {
+            return Err(QueryParsingError::UnsupportedFeature("Only one sampling clause is supported".to_string()).into());
         }
 
-        // Try SQLite dialect
-        if let Ok(statements) = Parser::parse_sql(&self.sqlite_dialect, sql) {
-            if let Some(statement) = statements.into_iter().next() {
-                return Ok(statement);
+        let invalid = |reason: &str| QueryParsingError::InvalidSyntax(format!("Invalid sampling clause: {}", reason));
+        let sample = match (captures.get(1), captures.get(2), captures.get(3)) {
+            (Some(percent), _, _) => Sample::Percent(percent.as_str().parse().map_err(|_| invalid("bad percentage"))?),
+            (None, Some(amount), Some(unit)) if unit.as_str().eq_ignore_ascii_case("rows") => {
+                Sample::Rows(amount.as_str().parse().map_err(|_| invalid("row count must be a whole number"))?)
             }
+            (None, Some(amount), _) => Sample::Percent(amount.as_str().parse().map_err(|_| invalid("bad percentage"))?),
+            _ => return Err(invalid("missing amount").into()),
+        };
+        if let Sample::Percent(percent) = sample {
+            if percent > 100.0 {
+                return Err(invalid("percentage must be between 0 and 100").into());
+            }
+        }
+
+        let range = captures.get(0).expect("whole match").range();
+        let mut remaining = String::with_capacity(sql.len());
+        remaining.push_str(&sql[..range.start]);
+        remaining.push_str(&sql[range.end..]);
+        Ok((remaining, Some(sample)))
+    }
+
+    /// Remove a `DEDUPLICATE BY (col, ...) [KEEP FIRST|LAST [ORDER BY col [ASC|DESC]]]` clause,
+    /// returning the remaining SQL and the requested deduplication
+    fn extract_deduplicate_clause(&self, sql: &str) -> NirvResult<(String, Option<Deduplicate>)> {
+        let masked = mask_quoted(sql);
+        let mut matches = self.deduplicate_regex.captures_iter(&masked);
+        let Some(captures) = matches.next() else {
+            return Ok((sql.to_string(), None));
+        };
+        if matches.next().is_some() {
+            return Err(QueryParsingError::UnsupportedFeature("Only one deduplication clause is supported".to_string()).into());
         }
 
-        // Try generic dialect as fallback
-        if let Ok(statements) = Parser::parse_sql(&self.generic_dialect, sql) {
-            if let Some(statement) = statements.into_iter().next() {
-                return Ok(statement);
+        // Quoted names are blanked in the masked text, so they are read from the original
+        let original = |index: usize| captures.get(index).map(|m| &sql[m.range()]);
+        let unquote = |name: &str| name.trim().trim_matches('"').to_string();
+        let columns: Vec<String> = original(1).unwrap_or_default().split(',').map(unquote).collect();
+        if columns.iter().any(|column| column.is_empty()) {
+            return Err(QueryParsingError::InvalidSyntax(
+                "Invalid deduplication clause: expected a list of key columns".to_string()
+            ).into());
+        }
+        let keep = match original(2) {
+            Some(keep) if keep.eq_ignore_ascii_case("last") => DedupKeep::Last,
+            _ => DedupKeep::First,
+        };
+        let order_by = original(3).map(|column| OrderColumn {
+            column: unquote(column),
+            direction: match original(4) {
+                Some(direction) if direction.eq_ignore_ascii_case("desc") => OrderDirection::Descending,
+                _ => OrderDirection::Ascending,
+            },
+            nulls: None,
+        });
+
+        let range = captures.get(0).expect("whole match").range();
+        let mut remaining = String::with_capacity(sql.len());
+        remaining.push_str(&sql[..range.start]);
+        remaining.push_str(&sql[range.end..]);
+        Ok((remaining, Some(Deduplicate { columns, keep, order_by })))
+    }
+
+    /// Remove every `COLLATE name` from ORDER BY, WHERE and JOIN ON expressions, returning the
+    /// remaining SQL and the collation they name. The collation applies to the whole query, so
+    /// naming two different ones is an error.
+    fn extract_collations(&self, sql: &str) -> NirvResult<(String, Option<Collation>)> {
+        let masked = mask_quoted(sql);
+        let mut collation = None;
+        let mut remaining = String::with_capacity(sql.len());
+        let mut end = 0;
+        for captures in self.collate_regex.captures_iter(&masked) {
+            // Quoted names are blanked in the masked text, so they are read from the original
+            let name = &sql[captures.get(1).expect("collation name").range()];
+            let named = Collation::parse(name)?;
+            if collation.is_some_and(|collation| collation != named) {
+                return Err(QueryParsingError::UnsupportedFeature(
+                    "A query can only use one collation".to_string()
+                ).into());
             }
+            collation = Some(named);
+            let range = captures.get(0).expect("whole match").range();
+            remaining.push_str(&sql[end..range.start]);
+            end = range.end;
         }
+        remaining.push_str(&sql[end..]);
+        Ok((remaining, collation))
+    }
 
-        Err(QueryParsingError::InvalidSyntax("Failed to parse SQL with any supported dialect".to_string()).into())
+    /// Parse in the client's dialect, or else try the dialect the SQL looks like and then
+    /// PostgreSQL, MySQL, SQLite and generic SQL. When every dialect fails, the error reported
+    /// is the one from the dialect that got furthest into the SQL, with its position.
+    fn try_parse_with_dialects(&self, sql: &str, dialect: ClientDialect) -> NirvResult<Statement> {
+        let fixed = dialect.parser_dialect();
+        let mut candidates: Vec<(String, &dyn Dialect)> = Vec::new();
+        if let Some(parser_dialect) = &fixed {
+            candidates.push((dialect.to_string(), parser_dialect.as_ref()));
+        } else {
+            let mut order: Vec<ClientDialect> = ClientDialect::guess(sql).into_iter().collect();
+            for fallback in [ClientDialect::PostgreSQL, ClientDialect::MySQL, ClientDialect::SQLite] {
+                if !order.contains(&fallback) {
+                    order.push(fallback);
+                }
+            }
+            for client_dialect in order {
+                let parser_dialect: &dyn Dialect = match client_dialect {
+                    ClientDialect::MySQL => &self.mysql_dialect,
+                    ClientDialect::SQLite => &self.sqlite_dialect,
+                    ClientDialect::SqlServer => &self.mssql_dialect,
+                    ClientDialect::PostgreSQL | ClientDialect::Auto => &self.postgres_dialect,
+                };
+                candidates.push((client_dialect.to_string(), parser_dialect));
+            }
+            candidates.push(("generic SQL".to_string(), &self.generic_dialect));
+        }
+        
+        let mut best: Option<SyntaxError> = None;
+        for (name, parser_dialect) in candidates {
+            match Parser::parse_sql(parser_dialect, sql) {
+                Ok(statements) if statements.len() > 1 => {
+                    return Err(QueryParsingError::UnsupportedFeature(format!(
+                        "{} statements given where one was expected; split them with split_statements()", statements.len()
+                    )).into());
+                }
+                Ok(statements) => {
+                    return statements.into_iter().next()
+                        .ok_or_else(|| QueryParsingError::InvalidSyntax("No SQL statement given".to_string()).into());
+                }
+                Err(e) => {
+                    let error = SyntaxError::new(name, &e.to_string(), sql);
+                    // The first dialect to get furthest wins ties, so the client's or the guessed one
+                    if best.as_ref().is_none_or(|best| error.position > best.position) {
+                        best = Some(error);
+                    }
+                }
+            }
+        }
+        let message = best.map(|error| error.to_string()).unwrap_or_else(|| "No SQL dialect to parse with".to_string());
+        Err(QueryParsingError::InvalidSyntax(message).into())
     }
 
     /// Convert sqlparser Query to internal representation
@@ -97,22 +484,49 @@ impl DefaultQueryParser {
             internal_query.projections = self.extract_projections(&body.projection)?;
             
             // Extract data sources from FROM clause
-            internal_query.sources = self.extract_sources(&body.from)?;
+            let (sources, joins) = self.extract_sources(&body.from, &mut internal_query.subqueries)?;
+            internal_query.sources = sources;
+            internal_query.joins = joins;
             
             // Extract WHERE clause predicates
             if let Some(selection) = &body.selection {
                 internal_query.predicates = self.extract_predicates(selection)?;
             }
             
+            // Extract GROUP BY items, left for the planner to resolve against the projections
+            internal_query.group_by = self.extract_group_by(&body.group_by)?;
+            // Without GROUP BY, a row count is the only column
+            let projections = &internal_query.projections;
+            if internal_query.group_by.is_empty() && projections.len() > 1 && projections.iter().any(|col| col.name == COUNT_ALL) {
+                return Err(QueryParsingError::UnsupportedFeature(
+                    "COUNT(*) cannot be selected with other columns without GROUP BY".to_string()
+                ).into());
+            }
+            
             // Extract ORDER BY clause
             if !query.order_by.is_empty() {
                 internal_query.ordering = Some(self.extract_order_by(&query.order_by)?);
             }
             
-            // Extract LIMIT clause
+            // Extract LIMIT clause, or SQL Server's TOP n and the standard FETCH FIRST n ROWS ONLY
             if let Some(limit) = &query.limit {
                 internal_query.limit = Some(self.extract_limit(limit)?);
             }
+            let fetch = body.top.as_ref().map(|top| (top.quantity.as_ref(), top.percent, top.with_ties))
+                .or(query.fetch.as_ref().map(|fetch| (fetch.quantity.as_ref(), fetch.percent, fetch.with_ties)));
+            if let Some((quantity, percent, with_ties)) = fetch {
+                let Some(quantity) = quantity.filter(|_| !percent && !with_ties) else {
+                    return Err(QueryParsingError::UnsupportedFeature("Only TOP n and FETCH FIRST n ROWS ONLY row limits are supported".to_string()).into());
+                };
+                let limit = self.extract_limit(quantity)?;
+                internal_query.limit = Some(internal_query.limit.map_or(limit, |other| other.min(limit)));
+            }
+            // Skipping rows is not supported, and never silently ignored
+            if let Some(offset) = &query.offset {
+                if self.extract_limit(&offset.value)? != 0 {
+                    return Err(QueryParsingError::UnsupportedFeature("OFFSET is not supported".to_string()).into());
+                }
+            }
         } else {
             return Err(QueryParsingError::UnsupportedFeature("Only SELECT queries are supported".to_string()).into());
         }
@@ -154,12 +568,50 @@ impl DefaultQueryParser {
         Ok(columns)
     }
 
+    /// Extract GROUP BY items: columns, SELECT list aliases, positions such as `GROUP BY 1`,
+    /// and the computed expressions the SELECT list supports
+    fn extract_group_by(&self, group_by: &GroupByExpr) -> NirvResult<Vec<GroupByItem>> {
+        let GroupByExpr::Expressions(exprs) = group_by else {
+            return Err(QueryParsingError::UnsupportedFeature("GROUP BY ALL is not supported".to_string()).into());
+        };
+        let mut items = Vec::new();
+        for mut expr in exprs {
+            while let Expr::Nested(inner) = expr {
+                expr = inner;
+            }
+            let item = match expr {
+                Expr::Value(SqlValue::Number(n, _)) => match n.parse::<usize>() {
+                    Ok(position) if position > 0 => GroupByItem::Position(position),
+                    _ => return Err(QueryParsingError::InvalidSyntax(
+                        format!("GROUP BY position {} is not a SELECT list position", n)
+                    ).into()),
+                },
+                _ => {
+                    let column = self.extract_column_from_expr(expr, None)?;
+                    if column.name == "expr" {
+                        return Err(QueryParsingError::UnsupportedFeature(
+                            format!("GROUP BY {} is not supported; group by a column, alias or position", expr)
+                        ).into());
+                    }
+                    if column.name == COUNT_ALL || ApproxAggregate::parse(&column.name).is_some() {
+                        return Err(QueryParsingError::InvalidSyntax(
+                            format!("Aggregate functions are not allowed in GROUP BY: {}", expr)
+                        ).into());
+                    }
+                    GroupByItem::Column(column)
+                }
+            };
+            items.push(item);
+        }
+        Ok(items)
+    }
+
     /// Extract column information from expression
     fn extract_column_from_expr(&self, expr: &Expr, alias: Option<String>) -> NirvResult<Column> {
         match expr {
             Expr::Identifier(ident) => {
                 Ok(Column {
-                    name: ident.value.clone(),
+                    name: identifier_name(ident),
                     alias,
                     source: None,
                 })
@@ -167,13 +619,13 @@ impl DefaultQueryParser {
             Expr::CompoundIdentifier(idents) => {
                 if idents.len() == 2 {
                     Ok(Column {
-                        name: idents[1].value.clone(),
+                        name: identifier_name(&idents[1]),
                         alias,
                         source: Some(idents[0].value.clone()),
                     })
                 } else {
                     Ok(Column {
-                        name: idents.last().unwrap().value.clone(),
+                        name: identifier_name(idents.last().unwrap()),
                         alias,
                         source: None,
                     })
@@ -185,6 +637,70 @@ impl DefaultQueryParser {
                     return Err(QueryParsingError::InvalidSourceFormat("source() function should be used in FROM clause, not SELECT".to_string()).into());
                 }
                 
+                if let Some(extract) = self.extract_json_function(func)? {
+                    return Ok(Column {
+                        name: extract.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
+                if let Some(function) = self.extract_regex_function(func)? {
+                    return Ok(Column {
+                        name: function.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
+                if let Some(function) = self.extract_hash_function(func)? {
+                    return Ok(Column {
+                        name: function.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
+                if let Some(hash) = self.extract_row_hash(func)? {
+                    return Ok(Column {
+                        name: hash.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
+                if let Some(trunc) = self.extract_date_trunc(func)? {
+                    return Ok(Column {
+                        name: trunc.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
+                if is_now_function(func) {
+                    return Ok(Column {
+                        name: NOW_FUNCTION.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
+                if is_count_all(func) {
+                    return Ok(Column {
+                        name: COUNT_ALL.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
+                if let Some(aggregate) = self.extract_approx_aggregate(func)? {
+                    return Ok(Column {
+                        name: aggregate.to_string(),
+                        alias,
+                        source: None,
+                    });
+                }
+                
                 Ok(Column {
                     name: func.name.to_string(),
                     alias,
@@ -201,20 +717,145 @@ impl DefaultQueryParser {
         }
     }
 
-    /// Extract data sources from FROM clause
-    fn extract_sources(&self, from: &[sqlparser::ast::TableWithJoins]) -> NirvResult<Vec<DataSource>> {
+    /// Extract data sources from FROM clause, walking into joins, parenthesized joins and
+    /// derived tables, whose queries are added to `subqueries`
+    fn extract_sources(&self, from: &[TableWithJoins], subqueries: &mut Vec<InternalQuery>) -> NirvResult<(Vec<DataSource>, Vec<Join>)> {
         let mut sources = Vec::new();
+        let mut joins = Vec::new();
 
         for table_with_joins in from {
-            let source = self.extract_source_from_table(&table_with_joins.relation)?;
-            sources.push(source);
+            self.extract_table_with_joins(table_with_joins, &mut sources, &mut joins, subqueries)?;
+        }
+        if joins.len() > 1 {
+            return Err(QueryParsingError::UnsupportedFeature("Only one JOIN per query is supported".to_string()).into());
         }
 
         if sources.is_empty() {
             return Err(QueryParsingError::MissingSource.into());
         }
 
-        Ok(sources)
+        Ok((sources, joins))
+    }
+
+    /// Extract the sources of a table and the tables joined to it, returning the label the
+    /// table is joined by
+    fn extract_table_with_joins(&self, table_with_joins: &TableWithJoins, sources: &mut Vec<DataSource>,
+        joins: &mut Vec<Join>, subqueries: &mut Vec<InternalQuery>) -> NirvResult<String> {
+        let label = self.extract_relation(&table_with_joins.relation, sources, joins, subqueries)?;
+        for join in &table_with_joins.joins {
+            let joined = self.extract_relation(&join.relation, sources, joins, subqueries)?;
+            joins.push(self.extract_join(join, label.clone(), joined)?);
+        }
+        Ok(label)
+    }
+
+    /// Extract the sources of one FROM item. A parenthesized join adds each source in it, and
+    /// a derived table is a `subquery` source naming its query's position in `subqueries`.
+    fn extract_relation(&self, relation: &TableFactor, sources: &mut Vec<DataSource>,
+        joins: &mut Vec<Join>, subqueries: &mut Vec<InternalQuery>) -> NirvResult<String> {
+        let source = match relation {
+            TableFactor::NestedJoin { table_with_joins, alias: None } => {
+                return self.extract_table_with_joins(table_with_joins, sources, joins, subqueries);
+            }
+            TableFactor::NestedJoin { alias: Some(_), .. } => {
+                return Err(QueryParsingError::UnsupportedFeature("An alias for a parenthesized join is not supported".to_string()).into());
+            }
+            TableFactor::Derived { subquery, alias, .. } if !matches!(*subquery.body, SetExpr::Values(_)) => {
+                subqueries.push(self.convert_query(subquery.as_ref().clone())?);
+                DataSource {
+                    object_type: SUBQUERY_OBJECT_TYPE.to_string(),
+                    identifier: (subqueries.len() - 1).to_string(),
+                    alias: alias.as_ref().map(|a| a.name.value.clone()),
+                }
+            }
+            _ => self.extract_source_from_table(relation)?,
+        };
+        let label = source_label(&source);
+        sources.push(source);
+        Ok(label)
+    }
+
+    /// Extract a JOIN whose ON condition combines column equalities and at most one
+    /// `FUZZY(left, right, threshold[, 'metric'])` with AND, or a CROSS JOIN, which has none
+    fn extract_join(&self, join: &sqlparser::ast::Join, left_source: String, right_source: String) -> NirvResult<Join> {
+        let (join_type, constraint) = match &join.join_operator {
+            // Without conditions every row of one source pairs with every row of the other
+            JoinOperator::CrossJoin => {
+                return Ok(Join { join_type: JoinType::Inner, left_source, right_source, on_condition: Vec::new(), fuzzy_condition: None });
+            }
+            JoinOperator::Inner(constraint) => (JoinType::Inner, constraint),
+            JoinOperator::LeftOuter(constraint) => (JoinType::Left, constraint),
+            JoinOperator::RightOuter(constraint) => (JoinType::Right, constraint),
+            JoinOperator::FullOuter(constraint) => (JoinType::Full, constraint),
+            other => return Err(QueryParsingError::UnsupportedFeature(format!("Join {:?} not supported", other)).into()),
+        };
+        let JoinConstraint::On(condition) = constraint else {
+            return Err(QueryParsingError::UnsupportedFeature("JOIN requires an ON condition".to_string()).into());
+        };
+        
+        let mut join = Join { join_type, left_source, right_source, on_condition: Vec::new(), fuzzy_condition: None };
+        self.extract_join_conditions(condition, &mut join)?;
+        Ok(join)
+    }
+
+    fn extract_join_conditions(&self, expr: &Expr, join: &mut Join) -> NirvResult<()> {
+        match expr {
+            Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+                self.extract_join_conditions(left, join)?;
+                self.extract_join_conditions(right, join)
+            }
+            Expr::BinaryOp { left, op: BinaryOperator::Eq, right } => {
+                let (left_column, right_column) = self.join_columns(join, left, right)?;
+                join.on_condition.push(JoinCondition { left_column, right_column });
+                Ok(())
+            }
+            Expr::Nested(expr) => self.extract_join_conditions(expr, join),
+            Expr::Function(func) if func.name.to_string().eq_ignore_ascii_case("fuzzy") => {
+                if join.fuzzy_condition.is_some() {
+                    return Err(QueryParsingError::UnsupportedFeature("Only one FUZZY condition per JOIN is supported".to_string()).into());
+                }
+                let args: Vec<&Expr> = func.args.iter()
+                    .filter_map(|arg| match arg {
+                        FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                        _ => None,
+                    })
+                    .collect();
+                let (left, right, threshold, metric) = match args.as_slice() {
+                    [left, right, Expr::Value(SqlValue::Number(threshold, _))] => (left, right, threshold, None),
+                    [left, right, Expr::Value(SqlValue::Number(threshold, _)), Expr::Value(SqlValue::SingleQuotedString(metric))] => {
+                        (left, right, threshold, Some(metric))
+                    }
+                    _ => return Err(QueryParsingError::InvalidSyntax(
+                        "FUZZY() expects two columns, a threshold and an optional metric".to_string()
+                    ).into()),
+                };
+                let threshold = threshold.parse::<f64>().ok()
+                    .filter(|threshold| (0.0..=1.0).contains(threshold))
+                    .ok_or_else(|| QueryParsingError::InvalidSyntax("FUZZY() threshold must be between 0 and 1".to_string()))?;
+                let metric = match metric {
+                    Some(name) => SimilarityMetric::parse(name)
+                        .ok_or_else(|| QueryParsingError::InvalidSyntax(format!("Unknown similarity metric '{}'", name)))?,
+                    None => SimilarityMetric::default(),
+                };
+                let (left_column, right_column) = self.join_columns(join, left, right)?;
+                join.fuzzy_condition = Some(FuzzyCondition { left_column, right_column, threshold, metric });
+                Ok(())
+            }
+            _ => Err(QueryParsingError::UnsupportedFeature(format!("Join condition '{}' not supported", expr)).into()),
+        }
+    }
+
+    /// Columns of a join condition, ordered so the first belongs to the left source
+    fn join_columns(&self, join: &Join, left: &Expr, right: &Expr) -> NirvResult<(String, String)> {
+        let left = self.extract_column_name_from_expr(left)?;
+        let right = self.extract_column_name_from_expr(right)?;
+        let belongs_to_right = |column: &str| split_qualifier(column)
+            .is_some_and(|(qualifier, _)| qualifier.eq_ignore_ascii_case(&join.right_source));
+        if belongs_to_right(&left) && !belongs_to_right(&right) {
+            Ok((right, left))
+        } else {
+            Ok((left, right))
+        }
     }
 
     /// Extract data source from table reference
@@ -240,11 +881,11 @@ impl DefaultQueryParser {
                     })
                 }
             }
-            sqlparser::ast::TableFactor::Derived { alias, .. } => {
-                // Handle subqueries - for now, just create a placeholder
+            sqlparser::ast::TableFactor::Derived { subquery, alias, .. } if matches!(*subquery.body, sqlparser::ast::SetExpr::Values(_)) => {
+                let sqlparser::ast::SetExpr::Values(values) = &*subquery.body else { unreachable!() };
                 Ok(DataSource {
-                    object_type: "subquery".to_string(),
-                    identifier: "derived".to_string(),
+                    object_type: INLINE_OBJECT_TYPE.to_string(),
+                    identifier: self.extract_inline_table(values, alias.as_ref())?.encode(),
                     alias: alias.as_ref().map(|a| a.name.value.clone()),
                 })
             }
@@ -270,15 +911,8 @@ impl DefaultQueryParser {
     fn extract_source_function(&self, table_name: &str) -> NirvResult<Option<(String, String)>> {
         if let Some(captures) = self.source_regex.captures(table_name) {
             if let Some(source_spec) = captures.get(1) {
-                let spec = source_spec.as_str();
-                if let Some(dot_pos) = spec.find('.') {
-                    let object_type = spec[..dot_pos].to_string();
-                    let identifier = spec[dot_pos + 1..].to_string();
-                    Ok(Some((object_type, identifier)))
-                } else {
-                    // No dot found, treat entire spec as identifier with default type
-                    Ok(Some(("table".to_string(), spec.to_string())))
-                }
+                let uri = SourceUri::parse(source_spec.as_str())?;
+                Ok(Some((uri.object_type().to_string(), uri.identifier())))
             } else {
                 Err(QueryParsingError::InvalidSourceFormat("Empty source specification".to_string()).into())
             }
@@ -287,6 +921,29 @@ impl DefaultQueryParser {
         }
     }
 
+    /// Extract the rows of `(VALUES (1, 'a'), (2, 'b')) AS t(id, name)`. Columns not named
+    /// by the alias are called column1, column2, ...
+    fn extract_inline_table(&self, values: &sqlparser::ast::Values, alias: Option<&sqlparser::ast::TableAlias>) -> NirvResult<InlineTable> {
+        let width = values.rows.first().map(|row| row.len()).unwrap_or_default();
+        if values.rows.iter().any(|row| row.len() != width) {
+            return Err(QueryParsingError::InvalidSyntax("All VALUES rows must have the same number of columns".to_string()).into());
+        }
+        let names: Vec<String> = alias.map(|a| a.columns.iter().map(identifier_name).collect()).unwrap_or_default();
+        if names.len() > width {
+            return Err(QueryParsingError::InvalidSyntax(
+                format!("VALUES has {} columns but {} column names were given", width, names.len())
+            ).into());
+        }
+        let columns = (0..width)
+            .map(|index| names.get(index).cloned().unwrap_or_else(|| format!("column{}", index + 1)))
+            .collect();
+
+        let rows = values.rows.iter()
+            .map(|row| row.iter().map(inline_value).collect::<NirvResult<Vec<_>>>())
+            .collect::<NirvResult<Vec<_>>>()?;
+        Ok(InlineTable { columns, rows })
+    }
+
     /// Extract source specification from function arguments
     fn extract_source_from_function_args(&self, args: &[FunctionArg]) -> NirvResult<(String, String)> {
         if args.len() != 1 {
@@ -294,14 +951,8 @@ impl DefaultQueryParser {
         }
 
         if let FunctionArg::Unnamed(FunctionArgExpr::Expr(Expr::Value(SqlValue::SingleQuotedString(spec)))) = &args[0] {
-            if let Some(dot_pos) = spec.find('.') {
-                let object_type = spec[..dot_pos].to_string();
-                let identifier = spec[dot_pos + 1..].to_string();
-                Ok((object_type, identifier))
-            } else {
-                // No dot found, treat entire spec as identifier with default type
-                Ok(("table".to_string(), spec.to_string()))
-            }
+            let uri = SourceUri::parse(spec)?;
+            Ok((uri.object_type().to_string(), uri.identifier()))
         } else {
             Err(QueryParsingError::InvalidSourceFormat("source() function argument must be a string literal".to_string()).into())
         }
@@ -352,6 +1003,21 @@ impl DefaultQueryParser {
                     value: PredicateValue::Null,
                 });
             }
+            Expr::RLike { negated, expr, pattern, .. } => {
+                if *negated {
+                    return Err(QueryParsingError::UnsupportedFeature("NOT REGEXP is not supported".to_string()).into());
+                }
+                let column = self.extract_column_name_from_expr(expr)?;
+                let pattern = self.extract_predicate_value_from_expr(pattern)?;
+                predicates.push(self.create_regexp_predicate(column, pattern)?);
+            }
+            // A bare REGEXP_MATCH(column, 'pattern') filters like column REGEXP 'pattern'
+            Expr::Function(func) => match self.extract_regex_function(func)? {
+                Some(RegexFunction { column, pattern, operation: RegexOperation::Match }) => {
+                    predicates.push(self.create_regexp_predicate(column, PredicateValue::String(pattern))?);
+                }
+                _ => return Err(QueryParsingError::UnsupportedFeature(format!("Function {} not supported as a predicate", func.name)).into()),
+            },
             _ => {
                 // For other expression types, we'll skip for now
             }
@@ -364,6 +1030,9 @@ impl DefaultQueryParser {
         let column = self.extract_column_name_from_expr(left)?;
         let operator = self.convert_binary_operator(op)?;
         let value = self.extract_predicate_value_from_expr(right)?;
+        if operator == PredicateOperator::Regexp {
+            return self.create_regexp_predicate(column, value);
+        }
 
         Ok(Predicate {
             column,
@@ -372,15 +1041,201 @@ impl DefaultQueryParser {
         })
     }
 
+    /// Create a `column REGEXP 'pattern'` predicate, rejecting patterns that do not compile
+    fn create_regexp_predicate(&self, column: String, pattern: PredicateValue) -> NirvResult<Predicate> {
+        let PredicateValue::String(pattern) = pattern else {
+            return Err(QueryParsingError::InvalidSyntax("REGEXP expects a string pattern".to_string()).into());
+        };
+        compile_regex(&pattern)?;
+        Ok(Predicate {
+            column,
+            operator: PredicateOperator::Regexp,
+            value: PredicateValue::String(pattern),
+        })
+    }
+
+    /// Recognize `JSON_EXTRACT(column, 'path')` and its `JSON_GET`/`JSON_VALUE` spellings.
+    /// The column may be given as an identifier or a string literal.
+    fn extract_json_function(&self, func: &sqlparser::ast::Function) -> NirvResult<Option<JsonExtract>> {
+        let name = func.name.to_string().to_lowercase();
+        if !matches!(name.as_str(), "json_extract" | "json_get" | "json_value") {
+            return Ok(None);
+        }
+        
+        let args: Vec<&Expr> = func.args.iter()
+            .filter_map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                _ => None,
+            })
+            .collect();
+        let [column, Expr::Value(SqlValue::SingleQuotedString(path))] = args.as_slice() else {
+            return Err(QueryParsingError::InvalidSyntax(format!("{}() expects a column and a JSON path", name)).into());
+        };
+        
+        let column = match column {
+            Expr::Value(SqlValue::SingleQuotedString(column)) => column.clone(),
+            other => self.extract_column_name_from_expr(other)?,
+        };
+        Ok(Some(JsonExtract::new(column, JsonPath::parse(path)?)))
+    }
+
+    /// Recognize `REGEXP_MATCH(column, 'pattern')`, `REGEXP_EXTRACT(column, 'pattern'[, group])`
+    /// and `REGEXP_REPLACE(column, 'pattern', 'replacement')`
+    fn extract_regex_function(&self, func: &sqlparser::ast::Function) -> NirvResult<Option<RegexFunction>> {
+        let name = func.name.to_string().to_lowercase();
+        if !matches!(name.as_str(), "regexp_match" | "regexp_extract" | "regexp_replace") {
+            return Ok(None);
+        }
+        
+        let args: Vec<&Expr> = func.args.iter()
+            .filter_map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                _ => None,
+            })
+            .collect();
+        let (column, pattern, operation) = match (name.as_str(), args.as_slice()) {
+            ("regexp_match", [column, Expr::Value(SqlValue::SingleQuotedString(pattern))]) => {
+                (column, pattern, RegexOperation::Match)
+            }
+            ("regexp_extract", [column, Expr::Value(SqlValue::SingleQuotedString(pattern))]) => {
+                (column, pattern, RegexOperation::Extract { group: 0 })
+            }
+            ("regexp_extract", [column, Expr::Value(SqlValue::SingleQuotedString(pattern)), Expr::Value(SqlValue::Number(group, _))]) => {
+                let group = group.parse().map_err(|_| QueryParsingError::InvalidSyntax(format!("{}() group must be a non-negative integer", name)))?;
+                (column, pattern, RegexOperation::Extract { group })
+            }
+            ("regexp_replace", [column, Expr::Value(SqlValue::SingleQuotedString(pattern)), Expr::Value(SqlValue::SingleQuotedString(replacement))]) => {
+                (column, pattern, RegexOperation::Replace { replacement: replacement.clone() })
+            }
+            ("regexp_match", _) => return Err(QueryParsingError::InvalidSyntax(format!("{}() expects a column and a pattern", name)).into()),
+            ("regexp_extract", _) => return Err(QueryParsingError::InvalidSyntax(format!("{}() expects a column, a pattern and an optional group", name)).into()),
+            _ => return Err(QueryParsingError::InvalidSyntax(format!("{}() expects a column, a pattern and a replacement", name)).into()),
+        };
+        
+        let column = self.extract_column_name_from_expr(column)?;
+        Ok(Some(RegexFunction::new(column, pattern.clone(), operation)?))
+    }
+
+    /// Recognize `MD5(column)` and `SHA256(column)` (also spelled `SHA2`)
+    fn extract_hash_function(&self, func: &sqlparser::ast::Function) -> NirvResult<Option<HashFunction>> {
+        let name = func.name.to_string().to_lowercase();
+        let Some(algorithm) = HashAlgorithm::parse(&name) else {
+            return Ok(None);
+        };
+        
+        let args: Vec<&Expr> = func.args.iter()
+            .filter_map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                _ => None,
+            })
+            .collect();
+        let [column] = args.as_slice() else {
+            return Err(QueryParsingError::InvalidSyntax(format!("{}() expects a column", name)).into());
+        };
+        let column = self.extract_column_name_from_expr(column)?;
+        Ok(Some(HashFunction { algorithm, column }))
+    }
+
+    /// Recognize `HASH_ROW()` over every column and `HASH_ROW(column, ...)` over the listed ones
+    fn extract_row_hash(&self, func: &sqlparser::ast::Function) -> NirvResult<Option<RowHash>> {
+        if !func.name.to_string().eq_ignore_ascii_case("hash_row") {
+            return Ok(None);
+        }
+        
+        let mut columns = Vec::new();
+        for arg in &func.args {
+            match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr @ (Expr::Identifier(_) | Expr::CompoundIdentifier(_)))) => {
+                    columns.push(self.extract_column_name_from_expr(expr)?);
+                }
+                FunctionArg::Unnamed(FunctionArgExpr::Wildcard) if func.args.len() == 1 => {}
+                _ => return Err(QueryParsingError::InvalidSyntax("hash_row() expects column names".to_string()).into()),
+            }
+        }
+        Ok(Some(RowHash { columns }))
+    }
+
+    /// Recognize `DATE_TRUNC('unit', column)` and `DATE_TRUNC('unit', column, 'time zone')`
+    fn extract_date_trunc(&self, func: &sqlparser::ast::Function) -> NirvResult<Option<DateTrunc>> {
+        if !func.name.to_string().eq_ignore_ascii_case("date_trunc") {
+            return Ok(None);
+        }
+        
+        let args: Vec<&Expr> = func.args.iter()
+            .filter_map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                _ => None,
+            })
+            .collect();
+        let (unit, column, time_zone) = match args.as_slice() {
+            [Expr::Value(SqlValue::SingleQuotedString(unit)), column] => (unit, column, None),
+            [Expr::Value(SqlValue::SingleQuotedString(unit)), column, Expr::Value(SqlValue::SingleQuotedString(zone))] => {
+                (unit, column, Some(SessionTimeZone::parse(zone)?))
+            }
+            _ => return Err(QueryParsingError::InvalidSyntax("date_trunc() expects a unit, a column and an optional time zone".to_string()).into()),
+        };
+        let unit = TruncUnit::parse(unit)
+            .ok_or_else(|| QueryParsingError::InvalidSyntax(format!("Unknown date_trunc() unit '{}'", unit)))?;
+        let column = self.extract_column_name_from_expr(column)?;
+        Ok(Some(DateTrunc { unit, column, time_zone }))
+    }
+
+    /// Recognize `approx_count_distinct(column)` and `approx_percentile(column, quantile)`
+    /// (also spelled `percentile_approx`)
+    fn extract_approx_aggregate(&self, func: &sqlparser::ast::Function) -> NirvResult<Option<ApproxAggregate>> {
+        let name = func.name.to_string().to_lowercase();
+        if !matches!(name.as_str(), "approx_count_distinct" | "approx_percentile" | "percentile_approx") {
+            return Ok(None);
+        }
+        
+        let args: Vec<&Expr> = func.args.iter()
+            .filter_map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                _ => None,
+            })
+            .collect();
+        let function = match (name.as_str(), args.as_slice()) {
+            ("approx_count_distinct", [_]) => ApproxFunction::CountDistinct,
+            (_, [_, Expr::Value(SqlValue::Number(quantile, _))]) => {
+                match quantile.parse::<f64>() {
+                    Ok(quantile) if (0.0..=1.0).contains(&quantile) => ApproxFunction::Percentile(quantile),
+                    _ => return Err(QueryParsingError::InvalidSyntax(format!("{}() quantile must be between 0 and 1", name)).into()),
+                }
+            }
+            ("approx_count_distinct", _) => {
+                return Err(QueryParsingError::InvalidSyntax(format!("{}() expects a column", name)).into());
+            }
+            _ => return Err(QueryParsingError::InvalidSyntax(format!("{}() expects a column and a quantile", name)).into()),
+        };
+        
+        let column = self.extract_column_name_from_expr(args[0])?;
+        Ok(Some(ApproxAggregate { function, column }))
+    }
+
     /// Extract column name from expression
     fn extract_column_name_from_expr(&self, expr: &Expr) -> NirvResult<String> {
         match expr {
-            Expr::Identifier(ident) => Ok(ident.value.clone()),
+            Expr::Function(func) => {
+                if let Some(extract) = self.extract_json_function(func)? {
+                    return Ok(extract.to_string());
+                }
+                if let Some(function) = self.extract_regex_function(func)? {
+                    return Ok(function.to_string());
+                }
+                if let Some(function) = self.extract_hash_function(func)? {
+                    return Ok(function.to_string());
+                }
+                match self.extract_date_trunc(func)? {
+                    Some(trunc) => Ok(trunc.to_string()),
+                    None => Err(QueryParsingError::UnsupportedFeature(format!("Function {} not supported in predicates", func.name)).into()),
+                }
+            }
+            Expr::Identifier(ident) => Ok(identifier_name(ident)),
             Expr::CompoundIdentifier(idents) => {
                 if idents.len() >= 2 {
-                    Ok(format!("{}.{}", idents[0].value, idents[1].value))
+                    Ok(format!("{}.{}", idents[0].value, identifier_name(&idents[1])))
                 } else {
-                    Ok(idents[0].value.clone())
+                    Ok(identifier_name(&idents[0]))
                 }
             }
             _ => Err(QueryParsingError::InvalidSyntax("Expected column identifier in predicate".to_string()).into()),
@@ -396,6 +1251,7 @@ impl DefaultQueryParser {
             BinaryOperator::GtEq => Ok(PredicateOperator::GreaterThanOrEqual),
             BinaryOperator::Lt => Ok(PredicateOperator::LessThan),
             BinaryOperator::LtEq => Ok(PredicateOperator::LessThanOrEqual),
+            BinaryOperator::PGRegexMatch => Ok(PredicateOperator::Regexp),
             // Note: LIKE operator handling will be added when we determine the correct variant name
             _ => Err(QueryParsingError::UnsupportedFeature(format!("Operator {:?} not supported", op)).into()),
         }
@@ -406,10 +1262,64 @@ impl DefaultQueryParser {
         match expr {
             Expr::Value(sql_value) => self.convert_sql_value(sql_value),
             Expr::Identifier(ident) => Ok(PredicateValue::String(ident.value.clone())),
+            // The current time is fixed when the statement is parsed
+            Expr::Function(func) if is_now_function(func) => Ok(PredicateValue::String(current_timestamp())),
+            Expr::Function(func) if is_current_date(func) => Ok(PredicateValue::String(current_date())),
+            // DATE '2024-01-01' and TIMESTAMP '2024-01-01 10:00:00' compare as their text
+            Expr::TypedString { data_type: SqlDataType::Date | SqlDataType::Timestamp(..) | SqlDataType::Datetime(_), value } => {
+                Ok(PredicateValue::String(value.clone()))
+            }
+            Expr::BinaryOp { left, op: op @ (BinaryOperator::Plus | BinaryOperator::Minus), right } => {
+                self.evaluate_time_arithmetic(left, op, right)
+            }
+            Expr::Nested(expr) => self.extract_predicate_value_from_expr(expr),
             _ => Err(QueryParsingError::UnsupportedFeature("Complex expressions in predicates not yet supported".to_string()).into()),
         }
     }
 
+    /// A constant time moved by an interval, such as `NOW() - INTERVAL '7 days'` or
+    /// `DATE '2024-01-31' + INTERVAL '1 month'`, evaluated when the statement is parsed so
+    /// sources are given a concrete time to compare with. Dates moved by whole days, or by a
+    /// number of days as in `CURRENT_DATE - 7`, stay dates; other results are UTC times.
+    fn evaluate_time_arithmetic(&self, left: &Expr, op: &BinaryOperator, right: &Expr) -> NirvResult<PredicateValue> {
+        let (time, interval) = match (op, left) {
+            (BinaryOperator::Plus, Expr::Interval(_)) => (right, left),
+            _ => (left, right),
+        };
+        let invalid = || QueryParsingError::InvalidSyntax(format!("'{} {} {}' is not a time moved by an interval", left, op, right));
+        let PredicateValue::String(text) = self.extract_predicate_value_from_expr(time)? else {
+            return Err(invalid().into());
+        };
+        let (time, offset) = parse_date_time(&text).ok_or_else(invalid)?;
+        let date_only = text.trim().len() == "2024-01-01".len();
+        let interval = match interval {
+            Expr::Interval(interval) => self.extract_interval(interval)?,
+            Expr::Value(SqlValue::Number(days, _)) if date_only => Interval::parse(days, Some("day")).ok_or_else(invalid)?,
+            _ => return Err(invalid().into()),
+        };
+        let out_of_range = || QueryParsingError::InvalidSyntax(format!("'{} {} {}' is out of range", left, op, right));
+        let interval = match op {
+            BinaryOperator::Minus => interval.negated().ok_or_else(out_of_range)?,
+            _ => interval,
+        };
+        let moved = interval.add_to(offset.map_or(time, |offset| time - offset)).ok_or_else(out_of_range)?;
+        Ok(PredicateValue::String(match date_only && interval.is_whole_days() {
+            true => moved.format("%Y-%m-%d").to_string(),
+            false => format_utc(moved),
+        }))
+    }
+
+    /// The span of an `INTERVAL '7 days'` or `INTERVAL '7' DAY` literal
+    fn extract_interval(&self, interval: &sqlparser::ast::Interval) -> NirvResult<Interval> {
+        let text = match interval.value.as_ref() {
+            Expr::Value(SqlValue::SingleQuotedString(text) | SqlValue::Number(text, _)) if interval.last_field.is_none() => text,
+            _ => return Err(QueryParsingError::UnsupportedFeature(format!("Interval {} not supported", interval)).into()),
+        };
+        let unit = interval.leading_field.map(|field| field.to_string());
+        Interval::parse(text, unit.as_deref())
+            .ok_or_else(|| QueryParsingError::InvalidSyntax(format!("Invalid or out of range interval {}", interval)).into())
+    }
+
     /// Convert sqlparser Value to internal PredicateValue
     fn convert_sql_value(&self, value: &SqlValue) -> NirvResult<PredicateValue> {
         match value {
@@ -442,10 +1352,12 @@ impl DefaultQueryParser {
             } else {
                 OrderDirection::Descending
             };
+            let nulls = order_expr.nulls_first.map(|first| if first { NullsOrder::First } else { NullsOrder::Last });
 
             columns.push(OrderColumn {
                 column: column_name,
                 direction,
+                nulls,
             });
         }
 
@@ -464,6 +1376,168 @@ impl DefaultQueryParser {
     }
 }
 
+/// Column name for an identifier; quoted identifiers keep their quotes so they resolve exactly
+/// A literal in an inline `VALUES` table
+fn inline_value(expr: &Expr) -> NirvResult<serde_json::Value> {
+    match expr {
+        Expr::Value(SqlValue::Number(n, _)) => n.parse::<i64>().map(serde_json::Value::from)
+            .or_else(|_| n.parse::<f64>().map(serde_json::Value::from))
+            .map_err(|_| QueryParsingError::InvalidSyntax(format!("Invalid number '{}' in VALUES", n)).into()),
+        Expr::Value(SqlValue::SingleQuotedString(s)) | Expr::Value(SqlValue::DoubleQuotedString(s)) => Ok(serde_json::Value::from(s.clone())),
+        Expr::Value(SqlValue::Boolean(b)) => Ok(serde_json::Value::from(*b)),
+        Expr::Value(SqlValue::Null) => Ok(serde_json::Value::Null),
+        Expr::UnaryOp { op: sqlparser::ast::UnaryOperator::Minus, expr } => match inline_value(expr)? {
+            serde_json::Value::Number(n) => Ok(n.as_i64().map(|i| serde_json::Value::from(-i))
+                .unwrap_or_else(|| serde_json::Value::from(-n.as_f64().unwrap_or_default()))),
+            _ => Err(QueryParsingError::InvalidSyntax(format!("Invalid value '{}' in VALUES", expr)).into()),
+        },
+        Expr::Nested(expr) => inline_value(expr),
+        _ => Err(QueryParsingError::UnsupportedFeature(format!("Only literals are supported in VALUES, found '{}'", expr)).into()),
+    }
+}
+
+/// Name columns of a source are qualified with: its alias, or else its identifier
+fn source_label(source: &DataSource) -> String {
+    source.alias.clone().unwrap_or_else(|| source.identifier.clone())
+}
+
+/// `COUNT(*)`, or `COUNT(1)` which counts the same rows
+fn is_count_all(func: &sqlparser::ast::Function) -> bool {
+    let counts_rows = match func.args.as_slice() {
+        [FunctionArg::Unnamed(FunctionArgExpr::Wildcard)] => true,
+        [FunctionArg::Unnamed(FunctionArgExpr::Expr(Expr::Value(SqlValue::Number(n, _))))] => n == "1",
+        _ => false,
+    };
+    func.name.to_string().eq_ignore_ascii_case("count") && counts_rows && !func.distinct && func.over.is_none()
+}
+
+/// `NOW()` or `CURRENT_TIMESTAMP`, which takes no arguments
+fn is_now_function(func: &sqlparser::ast::Function) -> bool {
+    let name = func.name.to_string();
+    (name.eq_ignore_ascii_case("now") || name.eq_ignore_ascii_case("current_timestamp")) && func.args.is_empty()
+}
+
+/// `CURRENT_DATE`, written without parentheses
+fn is_current_date(func: &sqlparser::ast::Function) -> bool {
+    func.name.to_string().eq_ignore_ascii_case("current_date") && func.args.is_empty()
+}
+
+fn identifier_name(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => quote_identifier(&ident.value),
+        None => ident.value.clone(),
+    }
+}
+
+/// Blank out string literals and quoted identifiers so their contents never match a clause.
+/// Blanks have the same byte width, so offsets into the masked text apply to the original.
+fn mask_quoted(sql: &str) -> String {
+    let mut quote = None;
+    let mut masked = String::with_capacity(sql.len());
+    for c in sql.chars() {
+        match quote {
+            Some(q) if c == q => {
+                quote = None;
+                masked.push(c);
+            }
+            Some(_) => masked.push_str(&" ".repeat(c.len_utf8())),
+            None => {
+                if c == '\'' || c == '"' {
+                    quote = Some(c);
+                }
+                masked.push(c);
+            }
+        }
+    }
+    masked
+}
+
+/// Split the statements a client sent in one message, as `SET ...; SELECT ...`, at the
+/// semicolons ending them. Semicolons in quotes, brackets and comments are left alone, a
+/// doubled closing quote is part of the quoted text, as is a backslash-escaped quote in
+/// MySQL, and empty statements are dropped.
+pub fn split_statements(sql: &str, dialect: ClientDialect) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_code = false;
+    scan_sql(sql, dialect, |piece| match piece {
+        SqlPiece::Code(i, ';') => {
+            if has_code {
+                statements.push(sql[start..i].trim());
+            }
+            start = i + 1;
+            has_code = false;
+        }
+        SqlPiece::Code(_, c) if c.is_whitespace() => {}
+        SqlPiece::Code(..) | SqlPiece::Quoted => has_code = true,
+        SqlPiece::Comment(_) => {}
+    });
+    if has_code {
+        statements.push(sql[start..].trim());
+    }
+    statements
+}
+
+/// The text of each comment in the SQL, without its `--` or `/* */`
+fn sql_comments(sql: &str, dialect: ClientDialect) -> Vec<String> {
+    let mut comments = Vec::new();
+    scan_sql(sql, dialect, |piece| {
+        if let SqlPiece::Comment(range) = piece {
+            comments.push(sql[range].trim().to_string());
+        }
+    });
+    comments
+}
+
+/// Pieces of SQL told apart by `scan_sql`
+enum SqlPiece {
+    /// A character outside quotes and comments, at its byte offset
+    Code(usize, char),
+    /// A quoted literal or name
+    Quoted,
+    /// The byte range of a comment's text
+    Comment(std::ops::Range<usize>),
+}
+
+/// Walk SQL, telling quoted text and comments from the rest
+fn scan_sql(sql: &str, dialect: ClientDialect, mut visit: impl FnMut(SqlPiece)) {
+    let mut chars = sql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                // MySQL strings escape the character after a backslash
+                let escapes = dialect == ClientDialect::MySQL && (c == '\'' || c == '"');
+                while let Some((_, next)) = chars.next() {
+                    if escapes && next == '\\' {
+                        chars.next();
+                    } else if next == close && chars.next_if(|&(_, c)| c == close).is_none() {
+                        break;
+                    }
+                }
+                visit(SqlPiece::Quoted);
+            }
+            '-' if chars.next_if(|&(_, c)| c == '-').is_some() => {
+                let end = chars.by_ref().find(|&(_, c)| c == '\n').map_or(sql.len(), |(end, _)| end);
+                visit(SqlPiece::Comment(i + 2..end));
+            }
+            '/' if chars.next_if(|&(_, c)| c == '*').is_some() => {
+                let mut end = sql.len();
+                let mut previous = ' ';
+                for (j, next) in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        end = j - 1;
+                        break;
+                    }
+                    previous = next;
+                }
+                visit(SqlPiece::Comment((i + 2).min(end)..end));
+            }
+            c => visit(SqlPiece::Code(i, c)),
+        }
+    }
+}
+
 impl Default for DefaultQueryParser {
     fn default() -> Self {
         Self::new().expect("Failed to create default QueryParser")
@@ -476,8 +1550,21 @@ impl QueryParser for DefaultQueryParser {
         self.parse(sql)
     }
     
+    async fn parse_sql_as(&self, sql: &str, dialect: ClientDialect) -> NirvResult<InternalQuery> {
+        self.parse_as(sql, dialect)
+    }
+    
     async fn validate_syntax(&self, sql: &str) -> NirvResult<bool> {
-        match self.try_parse_with_dialects(sql) {
+        let Ok((sql, _)) = self.extract_hints(sql) else {
+            return Ok(false);
+        };
+        let Ok((sql, _)) = self.extract_sample_clause(&sql) else {
+            return Ok(false);
+        };
+        let Ok((sql, _)) = self.extract_deduplicate_clause(&sql) else {
+            return Ok(false);
+        };
+        match self.try_parse_with_dialects(&sql, ClientDialect::Auto) {
             Ok(_) => Ok(true),
             Err(_) => Ok(false),
         }
@@ -485,7 +1572,7 @@ impl QueryParser for DefaultQueryParser {
     
     async fn extract_sources(&self, sql: &str) -> NirvResult<Vec<String>> {
         let query = self.parse(sql)?;
-        Ok(query.sources.into_iter()
+        Ok(query.all_sources().into_iter()
             .map(|source| format!("{}.{}", source.object_type, source.identifier))
             .collect())
     }
@@ -494,7 +1581,7 @@ impl QueryParser for DefaultQueryParser {
 (test)]
 mod tests {
     use super::*;
-    use crate::utils::{QueryOperation, OrderDirection};
+    use crate::utils::{QueryOperation, OrderDirection, Pushdown};
 
     fn create_parser() -> DefaultQueryParser {
         DefaultQueryParser::new().expect("Failed to create parser")
@@ -565,6 +1652,9 @@ mod tests {
             ("SELECT * FROM source('file.data.csv')", "file", "data.csv"),
             ("SELECT * FROM source('api.endpoint')", "api", "endpoint"),
             ("SELECT * FROM source('users')", "table", "users"), // Default type
+            ("SELECT * FROM source('postgres://analytics/public/users')", "postgres", "public.users"),
+            ("SELECT * FROM source('file:///data/*.csv')", "file", "/data/*.csv"),
+            ("SELECT * FROM source('https://api.example.com/v1/users')", "api", "https://api.example.com/v1/users"),
         ];
 
         for (sql, expected_type, expected_id) in test_cases {
@@ -578,6 +1668,122 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_comments_and_directives() {
+        let parser = create_parser();
+        let sql = "-- nirv: cache=off\nSELECT /*+ MAX_ROWS(5) */ id -- the key\nFROM source('api.orders') /* nirv: tag=nightly */ WHERE note = '-- not a comment'";
+        let query = parser.parse(sql).unwrap();
+        
+        // The hint comment is taken as hints; every other comment is kept
+        assert_eq!(query.comments, vec!["nirv: cache=off", "the key", "nirv: tag=nightly"]);
+        assert_eq!(query.directives.get("tag"), Some("nightly"));
+        assert_eq!(query.directives.cache_ttl().unwrap(), Some(std::time::Duration::ZERO));
+        assert_eq!(query.hints.max_rows, Some(5));
+        
+        assert!(parser.parse("SELECT id FROM source('api.orders') -- nirv: tag='open").is_err());
+    }
+
+    #[test]
+    fn test_sample_clause_parsing() {
+        let parser = create_parser();
+        let sample = |sql: &str| parser.parse(sql).map(|query| query.sample);
+        
+        assert_eq!(sample("SELECT * FROM source('file.big.csv') SAMPLE 1 PERCENT").unwrap(), Some(Sample::Percent(1.0)));
+        assert_eq!(sample("SELECT * FROM source('file.big.csv') sample 1000 rows WHERE id > 3 LIMIT 10").unwrap(), Some(Sample::Rows(1000)));
+        assert_eq!(sample("SELECT * FROM source('postgres.users') u TABLESAMPLE BERNOULLI (2.5)").unwrap(), Some(Sample::Percent(2.5)));
+        assert_eq!(sample("SELECT * FROM source('postgres.users') WHERE note = 'SAMPLE 5 ROWS'").unwrap(), None);
+        
+        let query = parser.parse("SELECT id FROM source('file.big.csv') SAMPLE 10 ROWS WHERE id > 3 LIMIT 5").unwrap();
+        assert_eq!((query.predicates.len(), query.limit), (1, Some(5)));
+        
+        assert!(sample("SELECT * FROM source('file.big.csv') SAMPLE 150 PERCENT").is_err());
+        assert!(sample("SELECT * FROM source('file.big.csv') SAMPLE 1.5 ROWS").is_err());
+    }
+
+    #[test]
+    fn test_query_hints_parsing() {
+        let parser = create_parser();
+
+        let query = parser.parse("SELECT /*+ NO_PUSHDOWN(predicates), MAX_ROWS(1000), CACHE_TTL(60s) */ id FROM source('api.orders') WHERE id > 3 LIMIT 5000").unwrap();
+        assert_eq!(query.hints.no_pushdown, vec![Pushdown::Predicates]);
+        assert_eq!(query.hints.max_rows, Some(1000));
+        assert_eq!(query.hints.cache_ttl, Some(std::time::Duration::from_secs(60)));
+        assert_eq!((query.projections.len(), query.predicates.len(), query.limit), (1, 1, Some(5000)));
+
+        // Plain comments and hint-like text in strings are left alone
+        assert!(parser.parse("SELECT /* MAX_ROWS(1) */ * FROM source('api.orders')").unwrap().hints.is_empty());
+        assert!(parser.parse("SELECT * FROM source('api.orders') WHERE note = 'SELECT /*+ MAX_ROWS(1) */'").unwrap().hints.is_empty());
+
+        assert!(parser.parse("SELECT /*+ FULL_SCAN */ * FROM source('api.orders')").is_err());
+    }
+
+    #[test]
+    fn test_deduplicate_clause_parsing() {
+        let parser = create_parser();
+        let deduplicate = |sql: &str| parser.parse(sql).map(|query| query.deduplicate);
+        
+        let query = parser.parse("SELECT * FROM source('file.pages/*.csv') WHERE id > 3 DEDUPLICATE BY (id, \"Region\") KEEP LAST ORDER BY updated_at DESC ORDER BY id LIMIT 5").unwrap();
+        assert_eq!(query.deduplicate, Some(Deduplicate {
+            columns: vec!["id".to_string(), "Region".to_string()],
+            keep: DedupKeep::Last,
+            order_by: Some(OrderColumn { column: "updated_at".to_string(), direction: OrderDirection::Descending, nulls: None }),
+        }));
+        assert_eq!(query.ordering.unwrap().columns[0].column, "id");
+        assert_eq!((query.predicates.len(), query.limit), (1, Some(5)));
+        
+        let plain = deduplicate("SELECT * FROM source('api.orders') deduplicate by (order_id)").unwrap().unwrap();
+        assert_eq!((plain.keep, plain.order_by), (DedupKeep::First, None));
+        assert_eq!(deduplicate("SELECT * FROM source('api.orders') WHERE note = 'DEDUPLICATE BY (id)'").unwrap(), None);
+        assert!(deduplicate("SELECT * FROM source('api.orders') DEDUPLICATE BY ()").is_err());
+    }
+
+    #[test]
+    fn test_approx_aggregate_parsing() {
+        let parser = create_parser();
+        let query = parser.parse(
+            "SELECT APPROX_COUNT_DISTINCT(email) AS emails, approx_percentile(latency, 0.95) FROM source('file.big.csv')"
+        ).unwrap();
+        
+        assert_eq!(query.projections[0].name, "approx_count_distinct(email)");
+        assert_eq!(query.projections[0].alias, Some("emails".to_string()));
+        assert_eq!(query.projections[1].name, "approx_percentile(latency, 0.95)");
+        
+        assert!(parser.parse("SELECT approx_percentile(latency, 95) FROM source('file.big.csv')").is_err());
+        assert!(parser.parse("SELECT approx_count_distinct(a, b) FROM source('file.big.csv')").is_err());
+    }
+
+    #[test]
+    fn test_count_all_parsing() {
+        let parser = create_parser();
+        let query = parser.parse("SELECT COUNT(*) AS total FROM source('file.big.csv') WHERE id > 3").unwrap();
+        assert_eq!(query.projections, vec![Column { name: COUNT_ALL.to_string(), alias: Some("total".to_string()), source: None }]);
+        assert!(query.is_count_only());
+        assert_eq!(parser.parse("SELECT count(1) FROM source('file.big.csv')").unwrap().projections[0].name, COUNT_ALL);
+        
+        // Counting a column or distinct values is not a row count
+        assert_ne!(parser.parse("SELECT COUNT(id) FROM source('file.big.csv')").unwrap().projections[0].name, COUNT_ALL);
+        assert!(parser.parse("SELECT id, COUNT(*) FROM source('file.big.csv')").is_err());
+    }
+
+    #[test]
+    fn test_group_by_parsing() {
+        let parser = create_parser();
+        let query = parser.parse(
+            "SELECT status AS state, DATE_TRUNC('month', created), COUNT(*) FROM source('file.orders.csv') GROUP BY state, (2), o.region"
+        ).unwrap();
+        assert_eq!(query.group_by, vec![
+            GroupByItem::Column(Column { name: "state".to_string(), alias: None, source: None }),
+            GroupByItem::Position(2),
+            GroupByItem::Column(Column { name: "region".to_string(), alias: None, source: Some("o".to_string()) }),
+        ]);
+        // With GROUP BY a row count is counted per group
+        assert!(!query.is_count_only());
+        
+        assert!(parser.parse("SELECT status FROM source('file.orders.csv') GROUP BY 0").is_err());
+        assert!(parser.parse("SELECT status FROM source('file.orders.csv') GROUP BY COUNT(*)").is_err());
+        assert!(parser.parse("SELECT status FROM source('file.orders.csv') GROUP BY status + 1").is_err());
+    }
+
     #[test]
     fn test_where_clause_parsing() {
         let parser = create_parser();
@@ -661,6 +1867,27 @@ mod tests {
         
         assert_eq!(ordering.columns[1].column, "age");
         assert_eq!(ordering.columns[1].direction, OrderDirection::Descending);
+        assert_eq!(ordering.columns[1].nulls, None);
+
+        let query = parser.parse("SELECT * FROM source('postgres.users') ORDER BY age DESC NULLS LAST, name NULLS FIRST").unwrap();
+        let nulls: Vec<Option<NullsOrder>> = query.ordering.unwrap().columns.iter().map(|col| col.nulls).collect();
+        assert_eq!(nulls, vec![Some(NullsOrder::Last), Some(NullsOrder::First)]);
+    }
+
+    #[test]
+    fn test_collate() {
+        let parser = create_parser();
+        let query = parser.parse("SELECT * FROM source('postgres.users') WHERE name COLLATE nocase = 'ACME collate' ORDER BY name COLLATE nocase").unwrap();
+        assert_eq!(query.collation, Some(Collation::CaseInsensitive));
+        assert_eq!(query.predicates[0].column, "name");
+        assert_eq!(query.predicates[0].value, PredicateValue::String("ACME collate".to_string()));
+        assert_eq!(query.ordering.unwrap().columns[0].column, "name");
+
+        let query = parser.parse("SELECT * FROM source('a.t') ORDER BY city COLLATE \"de-DE\"").unwrap();
+        assert_eq!(query.collation, Some(Collation::Unicode));
+        assert_eq!(parser.parse("SELECT * FROM source('a.t')").unwrap().collation, None);
+        assert!(parser.parse("SELECT * FROM source('a.t') WHERE a COLLATE nocase = 'x' ORDER BY b COLLATE binary").is_err());
+        assert!(parser.parse("SELECT * FROM source('a.t') ORDER BY b COLLATE klingon").is_err());
     }
 
     #[test]
@@ -726,6 +1953,79 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_client_dialects() {
+        let parser = create_parser();
+        
+        // SQL Server clients write TOP n, bracketed names and GETDATE()
+        let query = parser.parse_as("SELECT TOP 5 [id], GETDATE() AS asof FROM source('mssql.dbo.users') WHERE [id] > 1", ClientDialect::SqlServer).unwrap();
+        assert_eq!(query.limit, Some(5));
+        assert_eq!(query.projections[1].name, NOW_FUNCTION);
+        assert_eq!(query.predicates[0].column, "\"id\"");
+        
+        // MySQL clients quote with backticks; the function names of other dialects stay as they are
+        let query = parser.parse_as("SELECT `name`, SYSDATE() FROM source('mysql.users') LIMIT 3", ClientDialect::MySQL).unwrap();
+        assert_eq!(query.limit, Some(3));
+        assert_eq!(query.projections[1].name, NOW_FUNCTION);
+        assert_eq!(parser.parse_as("SELECT GETDATE() FROM source('mysql.users')", ClientDialect::MySQL).unwrap().projections[0].name, "GETDATE");
+        
+        // PostgreSQL reads brackets as an array; a fixed dialect does not fall back to the others
+        assert_eq!(parser.parse_as("SELECT [name] FROM source('mssql.users')", ClientDialect::PostgreSQL).unwrap().projections[0].name, "expr");
+        assert_eq!(parser.parse_as("SELECT [name] FROM source('mssql.users')", ClientDialect::SqlServer).unwrap().projections[0].name, "\"name\"");
+        let error = parser.parse_as("SELECT name FROM source('pg.users') LIMIT 0, 5", ClientDialect::PostgreSQL).unwrap_err();
+        assert!(error.to_string().contains("as PostgreSQL"), "{}", error);
+        
+        // FETCH FIRST is a LIMIT; skipping rows is refused rather than ignored
+        let query = parser.parse("SELECT * FROM source('pg.users') FETCH FIRST 2 ROWS ONLY").unwrap();
+        assert_eq!(query.limit, Some(2));
+        assert!(parser.parse_as("SELECT * FROM source('mysql.users') LIMIT 10, 5", ClientDialect::MySQL).is_err());
+        assert!(parser.parse("SELECT * FROM source('pg.users') LIMIT 5 OFFSET 10").is_err());
+        assert_eq!(parser.parse("SELECT * FROM source('pg.users') LIMIT 5 OFFSET 0").unwrap().limit, Some(5));
+        assert!(parser.parse_as("SELECT TOP 10 PERCENT * FROM source('mssql.users')", ClientDialect::SqlServer).is_err());
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("SET TIME ZONE 'UTC'; SELECT * FROM source('file.a.csv');", ClientDialect::Auto),
+            vec!["SET TIME ZONE 'UTC'", "SELECT * FROM source('file.a.csv')"]
+        );
+        
+        // Semicolons inside literals, quoted names and comments do not end a statement
+        let sql = "SELECT 'a;''b', \"c;d\", `e;f`, [g;h] FROM t -- one; two\n; /* three; */ ;  ; SELECT 2";
+        assert_eq!(split_statements(sql, ClientDialect::Auto), vec!["SELECT 'a;''b', \"c;d\", `e;f`, [g;h] FROM t -- one; two", "SELECT 2"]);
+        assert!(split_statements("  ; -- nothing", ClientDialect::Auto).is_empty());
+        
+        // MySQL escapes quotes in strings with a backslash, where other dialects end the string
+        let sql = r#"SELECT 'O\'Brien; x', "a\\\"; b"; SELECT 2"#;
+        assert_eq!(split_statements(sql, ClientDialect::MySQL), vec![r#"SELECT 'O\'Brien; x', "a\\\"; b""#, "SELECT 2"]);
+        assert_eq!(split_statements(r"SELECT 'C:\'; SELECT 2", ClientDialect::PostgreSQL), vec![r"SELECT 'C:\'", "SELECT 2"]);
+        
+        // The parser takes one statement at a time
+        let parser = create_parser();
+        assert!(parser.parse("SELECT * FROM source('pg.a'); SELECT * FROM source('pg.b')").is_err());
+        assert!(parser.parse("SELECT * FROM source('pg.a');").is_ok());
+    }
+
+    #[test]
+    fn test_dialect_selection_and_syntax_errors() {
+        let parser = create_parser();
+        
+        // Without a dialect, the one the SQL looks like is tried first
+        let query = parser.parse("SELECT TOP 2 [name] FROM source('mssql.users')").unwrap();
+        assert_eq!(query.limit, Some(2));
+        assert_eq!(query.projections[0].name, "\"name\"");
+        
+        // The error reported is the one that got furthest, with its position
+        let error = parser.parse("SELECT name\nFROM source('pg.users')\nWHERE id = = 1").unwrap_err().to_string();
+        assert!(error.contains("at line 3, column 12"), "{}", error);
+        assert!(error.contains("as PostgreSQL"), "{}", error);
+        let error = parser.parse("SELECT `name` FROM source('mysql.users') WHERE").unwrap_err().to_string();
+        assert!(error.contains("as MySQL at line 1"), "{}", error);
+        let error = parser.parse_as("SELECT name FROM source('pg.users') WHERE", ClientDialect::SQLite).unwrap_err().to_string();
+        assert!(error.contains("Invalid SQL syntax: Failed to parse SQL as SQLite at line 1, column 42"), "{}", error);
+    }
+
     #[test]
     fn test_sqlite_dialect() {
         let parser = create_parser();
@@ -788,6 +2088,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_write_statements() {
+        let parser = create_parser();
+        let insert = parser.parse("INSERT INTO source('postgres.accounts') (id, \"Owner\") VALUES (1, 'ann'), (2, NULL)").unwrap();
+        assert_eq!(insert.operation, QueryOperation::Insert);
+        assert_eq!(insert.sources[0].object_type, "postgres");
+        assert_eq!(insert.sources[0].identifier, "accounts");
+        let columns: Vec<&str> = insert.projections.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(columns, vec!["id", "\"Owner\""]);
+        assert_eq!(insert.values[1], vec![PredicateValue::Integer(2), PredicateValue::Null]);
+
+        let update = parser.parse("UPDATE source('mssql.accounts') SET balance = -5 WHERE id = 2").unwrap();
+        assert_eq!(update.operation, QueryOperation::Update);
+        assert_eq!(update.assignments, vec![Assignment { column: "balance".to_string(), value: PredicateValue::Integer(-5) }]);
+        assert_eq!(update.predicates[0].column, "id");
+
+        let delete = parser.parse("DELETE FROM source('postgres.accounts')").unwrap();
+        assert_eq!(delete.operation, QueryOperation::Delete);
+        assert!(delete.predicates.is_empty());
+
+        assert!(parser.parse("INSERT INTO source('postgres.accounts') (id) VALUES (1, 2)").is_err());
+        assert!(parser.parse("INSERT INTO source('postgres.accounts') SELECT * FROM source('mssql.accounts')").is_err());
+        assert!(parser.parse("DELETE FROM source('postgres.accounts') RETURNING id").is_err());
+        assert!(parser.parse("CREATE TABLE source('file.x.csv') AS DELETE FROM source('postgres.accounts')").is_err());
+    }
+
     #[test]
     fn test_source_function_in_select_clause() {
         let parser = create_parser();
@@ -822,6 +2148,17 @@ mod tests {
         assert_eq!(query.predicates[0].column, "u.age");
     }
 
+    #[test]
+    fn test_quoted_identifiers_keep_quotes() {
+        let parser = create_parser();
+        let query = parser.parse("SELECT \"Name\", u.\"Zip Code\" FROM source('file.users') u WHERE \"Name\" = 'x' ORDER BY Age").unwrap();
+
+        assert_eq!(query.projections[0].name, "\"Name\"");
+        assert_eq!(query.projections[1].name, "\"Zip Code\"");
+        assert_eq!(query.predicates[0].column, "\"Name\"");
+        assert_eq!(query.ordering.unwrap().columns[0].column, "Age");
+    }
+
     #[test]
     fn test_various_value_types() {
         let parser = create_parser();
@@ -829,7 +2166,7 @@ mod tests {
         let test_cases = vec![
             ("SELECT * FROM source('test') WHERE str_col = 'text'", PredicateValue::String("text".to_string())),
             ("SELECT * FROM source('test') WHERE int_col = 42", PredicateValue::Integer(42)),
-            ("SELECT * FROM source('test') WHERE float_col = 3.14", PredicateValue::Number(3.14)),
+            ("SELECT * FROM source('test') WHERE float_col = 2.5", PredicateValue::Number(2.5)),
             ("SELECT * FROM source('test') WHERE bool_col = true", PredicateValue::Boolean(true)),
             ("SELECT * FROM source('test') WHERE null_col = NULL", PredicateValue::Null),
         ];
@@ -857,6 +2194,36 @@ mod tests {
         assert_eq!(query.predicates[0].value, PredicateValue::String("John".to_string()));
     }
 
+    #[test]
+    fn test_create_table_as_select() {
+        let parser = create_parser();
+        let query = parser.parse("CREATE TABLE source('file.cache/report.csv') AS\nSELECT name FROM source('postgres.users') WHERE age > 30").unwrap();
+        
+        let target = query.target.unwrap();
+        assert_eq!(target.object_type, "file");
+        assert_eq!(target.identifier, "cache/report.csv");
+        assert_eq!(query.sources[0].identifier, "users");
+        assert_eq!(query.predicates.len(), 1);
+        assert!(parser.parse("SELECT * FROM source('postgres.users')").unwrap().target.is_none());
+        assert!(parser.parse("CREATE TABLE source('file.x.csv') AS DELETE FROM users").is_err());
+    }
+    
+    #[test]
+    fn test_inline_values_table() {
+        let parser = create_parser();
+        let query = parser.parse("SELECT * FROM (VALUES (1, 'a', -2.5), (2, NULL, 3)) AS t(id, name)").unwrap();
+        
+        assert_eq!(query.sources[0].object_type, INLINE_OBJECT_TYPE);
+        assert_eq!(query.sources[0].alias, Some("t".to_string()));
+        let table = InlineTable::decode(&query.sources[0].identifier).unwrap();
+        assert_eq!(table.columns, vec!["id", "name", "column3"]);
+        assert_eq!(table.rows[0], vec![serde_json::json!(1), serde_json::json!("a"), serde_json::json!(-2.5)]);
+        assert!(table.rows[1][1].is_null());
+        
+        assert!(parser.parse("SELECT * FROM (VALUES (1), (2, 3)) AS t").is_err());
+        assert!(parser.parse("SELECT * FROM (VALUES (1 + 1)) AS t").is_err());
+    }
+    
     #[test]
     fn test_qualified_wildcard() {
         let parser = create_parser();
@@ -869,4 +2236,171 @@ mod tests {
         assert_eq!(query.projections[0].name, "*");
         assert_eq!(query.projections[0].source, Some("u".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_json_functions() {
+        let parser = create_parser();
+        let sql = "SELECT JSON_EXTRACT(payload, '$.user.id') AS user_id, json_get('payload', '$.tags[0]') \
+                   FROM source('api.events') WHERE JSON_VALUE(e.payload, '$.user.name') = 'Ann'";
+        let query = parser.parse(sql).unwrap();
+
+        assert_eq!(query.projections[0].name, "json_extract(payload, '$.user.id')");
+        assert_eq!(query.projections[0].alias, Some("user_id".to_string()));
+        assert_eq!(query.projections[1].name, "json_extract(payload, '$.tags[0]')");
+        assert_eq!(query.predicates[0].column, "json_extract(e.payload, '$.user.name')");
+        assert_eq!(query.predicates[0].value, PredicateValue::String("Ann".to_string()));
+
+        assert!(parser.parse("SELECT JSON_EXTRACT(payload) FROM source('api.events')").is_err());
+        assert!(parser.parse("SELECT JSON_EXTRACT(payload, 'user.id') FROM source('api.events')").is_err());
+    }
+
+    #[test]
+    fn test_regex_functions() {
+        let parser = create_parser();
+        let sql = "SELECT REGEXP_EXTRACT(line, 'code=(\\d+)', 1) AS code, regexp_replace(line, 'user=\\w+', 'user=?') \
+                   FROM source('file.app.log') WHERE line REGEXP 'ERROR|WARN' AND host ~ '^web-' AND REGEXP_MATCH(path, '^/api/')";
+        let query = parser.parse(sql).unwrap();
+
+        assert_eq!(query.projections[0].name, "regexp_extract(line, 'code=(\\d+)', 1)");
+        assert_eq!(query.projections[0].alias, Some("code".to_string()));
+        assert_eq!(query.projections[1].name, "regexp_replace(line, 'user=\\w+', 'user=?')");
+        let operators: Vec<(&str, &PredicateOperator)> = query.predicates.iter()
+            .map(|pred| (pred.column.as_str(), &pred.operator))
+            .collect();
+        assert_eq!(operators, vec![
+            ("line", &PredicateOperator::Regexp),
+            ("host", &PredicateOperator::Regexp),
+            ("path", &PredicateOperator::Regexp),
+        ]);
+        assert_eq!(query.predicates[2].value, PredicateValue::String("^/api/".to_string()));
+
+        assert!(parser.parse("SELECT * FROM source('file.app.log') WHERE line REGEXP '(unclosed'").is_err());
+        assert!(parser.parse("SELECT REGEXP_REPLACE(line, 'a') FROM source('file.app.log')").is_err());
+    }
+
+    #[test]
+    fn test_hash_functions_and_checksum() {
+        let parser = create_parser();
+        let query = parser.parse("SELECT MD5(u.email), sha2(name) AS digest, HASH_ROW() AS row_hash, hash_row(id, name) \
+                                  FROM source('postgres.users') u WHERE md5(email) = 'abc'").unwrap();
+        let names: Vec<&str> = query.projections.iter().map(|col| col.name.as_str()).collect();
+        assert_eq!(names, vec!["md5(u.email)", "sha256(name)", "hash_row()", "hash_row(id, name)"]);
+        assert_eq!(query.predicates[0].column, "md5(email)");
+        assert!(!query.checksum);
+
+        let query = parser.parse("CHECKSUM TABLE source('postgres.users') WHERE region = 'eu';").unwrap();
+        assert!(query.checksum);
+        assert_eq!(query.sources[0].identifier, "users");
+        assert_eq!(query.projections[0].name, "*");
+        assert_eq!(query.predicates.len(), 1);
+
+        assert!(parser.parse("CHECKSUM TABLE source('postgres.users') LIMIT 5").is_err());
+        assert!(parser.parse("SELECT md5(a, b) FROM source('postgres.users')").is_err());
+        assert!(parser.parse("SELECT hash_row('a') FROM source('postgres.users')").is_err());
+    }
+
+    #[test]
+    fn test_cache_select() {
+        let parser = create_parser();
+        let query = parser.parse("CACHE SELECT /*+ MAX_ROWS(10) */ id FROM source('api.users') WHERE id > 5 FOR 10 MINUTES;").unwrap();
+        assert_eq!(query.hints.cache_ttl, Some(std::time::Duration::from_secs(600)));
+        assert_eq!(query.hints.max_rows, Some(10));
+        assert_eq!(query.predicates.len(), 1);
+        assert_eq!(parser.parse("cache select * from source('api.users') for 1 hour").unwrap().hints.cache_ttl, Some(std::time::Duration::from_secs(3600)));
+        assert!(parser.parse("CACHE SELECT * FROM source('api.users') FOR 10 DAYS").is_err());
+    }
+
+    #[test]
+    fn test_date_functions() {
+        let parser = create_parser();
+        let query = parser.parse("SELECT DATE_TRUNC('Month', o.created_at), date_trunc('day', created_at, '+02:00') AS day, NOW(), CURRENT_TIMESTAMP \
+                                  FROM source('postgres.orders') o WHERE date_trunc('hour', created_at) = '2024-03-01 10:00:00' AND created_at < now()").unwrap();
+        let names: Vec<&str> = query.projections.iter().map(|col| col.name.as_str()).collect();
+        assert_eq!(names, vec!["date_trunc('month', o.created_at)", "date_trunc('day', created_at, '+02:00')", "now()", "now()"]);
+        assert_eq!(query.predicates[0].column, "date_trunc('hour', created_at)");
+        assert!(matches!(&query.predicates[1].value, PredicateValue::String(now) if now.starts_with("20")));
+
+        assert!(parser.parse("SELECT date_trunc('fortnight', created_at) FROM source('postgres.orders')").is_err());
+        assert!(parser.parse("SELECT date_trunc('day', created_at, 'Mars/Olympus') FROM source('postgres.orders')").is_err());
+    }
+
+    #[test]
+    fn test_interval_arithmetic_in_predicates() {
+        let parser = create_parser();
+        let value = |sql: &str| match parser.parse(sql).unwrap().predicates.remove(0).value {
+            PredicateValue::String(text) => text,
+            other => panic!("expected a time, got {:?}", other),
+        };
+
+        let week_ago = value("SELECT * FROM source('postgres.orders') WHERE created_at > NOW() - INTERVAL '7 days'");
+        let (week_ago, _) = parse_date_time(&week_ago).unwrap();
+        let elapsed = chrono::Utc::now().naive_utc() - week_ago;
+        assert!(elapsed >= chrono::Duration::days(7) && elapsed < chrono::Duration::days(7) + chrono::Duration::minutes(1));
+
+        assert_eq!(value("SELECT * FROM source('postgres.orders') WHERE day >= DATE '2024-01-31' + INTERVAL '1 month'"), "2024-02-29");
+        assert_eq!(value("SELECT * FROM source('postgres.orders') WHERE day < INTERVAL '1' DAY + '2024-03-01'"), "2024-03-02");
+        assert_eq!(value("SELECT * FROM source('postgres.orders') WHERE day < DATE '2024-03-01' - 7"), "2024-02-23");
+        assert_eq!(value("SELECT * FROM source('postgres.orders') WHERE ts < (TIMESTAMP '2024-03-01 00:00:00' - INTERVAL '90 minutes')"), "2024-02-29 22:30:00");
+        assert_eq!(value("SELECT * FROM source('postgres.orders') WHERE ts < '2024-03-01T10:00:00+02:00' + INTERVAL '1 hour'"), "2024-03-01 09:00:00");
+        assert_eq!(value("SELECT * FROM source('postgres.orders') WHERE day = CURRENT_DATE"), current_date());
+
+        assert!(parser.parse("SELECT * FROM source('postgres.orders') WHERE ts > NOW() - INTERVAL '7 fortnights'").is_err());
+        assert!(parser.parse("SELECT * FROM source('postgres.orders') WHERE ts > NOW() - 7").is_err());
+        assert!(parser.parse("SELECT * FROM source('postgres.orders') WHERE ts > 'soon' + INTERVAL '1 day'").is_err());
+        assert!(parser.parse("SELECT * FROM source('postgres.orders') WHERE ts > NOW() - INTERVAL '2000000000 months 2000000000 months'").is_err());
+        assert!(parser.parse("SELECT * FROM source('postgres.orders') WHERE day < DATE '2024-03-01' + INTERVAL '2000000000 years'").is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_join_parsing() {
+        let parser = create_parser();
+        let query = parser.parse(
+            "SELECT u.name, c.customer_name FROM source('postgres.users') u \
+             LEFT JOIN source('crm.customers') c ON c.country = u.country AND FUZZY(c.customer_name, u.name, 0.85, 'levenshtein')"
+        ).unwrap();
+
+        assert_eq!(query.sources.len(), 2);
+        assert_eq!(query.sources[0].object_type, "postgres");
+        assert_eq!(query.sources[1].alias, Some("c".to_string()));
+        let join = &query.joins[0];
+        assert_eq!(join.join_type, JoinType::Left);
+        assert_eq!((join.left_source.as_str(), join.right_source.as_str()), ("u", "c"));
+        assert_eq!(join.on_condition, vec![JoinCondition { left_column: "u.country".to_string(), right_column: "c.country".to_string() }]);
+        let fuzzy = join.fuzzy_condition.as_ref().unwrap();
+        assert_eq!((fuzzy.left_column.as_str(), fuzzy.right_column.as_str()), ("u.name", "c.customer_name"));
+        assert_eq!((fuzzy.threshold, fuzzy.metric), (0.85, SimilarityMetric::Levenshtein));
+
+        let join_sql = |on: &str| format!("SELECT * FROM source('a.x') a JOIN source('b.y') b ON {}", on);
+        assert!(parser.parse(&join_sql("FUZZY(a.name, b.name, 1.5)")).is_err());
+        assert!(parser.parse(&join_sql("FUZZY(a.name, b.name, 0.9, 'soundex')")).is_err());
+        assert!(parser.parse(&join_sql("a.id > b.id")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_nested_source_parsing() {
+        let parser = create_parser();
+        
+        // Parentheses around a join leave its sources and condition as they are
+        let query = parser.parse("SELECT * FROM (source('postgres.users') u JOIN source('crm.customers') c ON u.id = c.user_id)").unwrap();
+        assert_eq!(query.sources.len(), 2);
+        assert_eq!((query.joins[0].left_source.as_str(), query.joins[0].right_source.as_str()), ("u", "c"));
+        
+        // A derived table, here the right side of a join, is a source whose query is parsed too
+        let sql = "SELECT u.name, o.total FROM source('postgres.users') u \
+                   JOIN (SELECT user_id, total FROM source('api.orders') WHERE total > 10) AS o ON u.id = o.user_id";
+        let query = parser.parse(sql).unwrap();
+        assert_eq!(query.sources[1], DataSource { object_type: SUBQUERY_OBJECT_TYPE.to_string(), identifier: "0".to_string(), alias: Some("o".to_string()) });
+        let subquery = query.subquery(&query.sources[1]).unwrap();
+        assert_eq!(subquery.sources[0].identifier, "orders");
+        assert_eq!(subquery.predicates.len(), 1);
+        assert_eq!(query.joins[0].right_source, "o");
+        assert_eq!(QueryParser::extract_sources(&parser, sql).await.unwrap(), vec!["postgres.users", "api.orders"]);
+        
+        // Derived tables nest, and every source inside them is found
+        let nested = "SELECT * FROM (SELECT * FROM (SELECT id FROM source('file.a.csv')) AS inner_t) AS outer_t";
+        assert_eq!(QueryParser::extract_sources(&parser, nested).await.unwrap(), vec!["file.a.csv"]);
+        
+        assert!(parser.parse("SELECT * FROM (source('a.x') a JOIN source('b.y') b ON a.id = b.id) AS ab").is_err());
+        assert!(parser.parse("SELECT * FROM (source('a.x') a JOIN source('b.y') b ON a.id = b.id) JOIN source('c.z') c ON a.id = c.id").is_err());
+    }
+}
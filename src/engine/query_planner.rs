@@ -1,11 +1,18 @@
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::connectors::Connector;
+use crate::engine::approx_aggregate::{ApproxAggregate, aggregate_input_columns, aggregated_order, group_key, is_aggregation, ungrouped_column_error};
+use crate::engine::query_executor::ComputedColumn;
 use crate::utils::{
-    types::{InternalQuery, DataSource, Column, Predicate, OrderBy},
-    error::{NirvResult, NirvError},
+    types::{InternalQuery, DataSource, Column, Predicate, PredicateOperator, PredicateValue, OrderBy, Sample, Deduplicate, Pushdown, GroupByItem, COUNT_ALL},
+    error::{NirvResult, NirvError, QueryParsingError},
+    identifier::{IdentifierCase, split_qualifier},
 };
 
-/// Execution plan node types
-#[derive(Debug, Clone)]
+/// Execution plan node types, serialized tagged with their snake_case name under `node`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "node", rename_all = "snake_case")]
 pub enum PlanNode {
     /// Scan a table/data source
     TableScan {
@@ -28,10 +35,41 @@ pub enum PlanNode {
         columns: Vec<Column>,
         input: Box<PlanNode>,
     },
+    /// Filter rows locally with predicates the source connector cannot evaluate
+    Filter {
+        predicates: Vec<Predicate>,
+        input: Box<PlanNode>,
+    },
+    /// Sample rows, in the source connector when it supports the sampling method
+    Sample {
+        sample: Sample,
+        input: Box<PlanNode>,
+    },
+    /// Reduce rows to one row of aggregate values per group of the resolved GROUP BY keys
+    Aggregate {
+        aggregates: Vec<Column>,
+        group_by: Vec<Column>,
+        input: Box<PlanNode>,
+    },
+    /// Keep one row of each set of rows repeating the key columns
+    Deduplicate {
+        deduplicate: Deduplicate,
+        input: Box<PlanNode>,
+    },
+    /// Reduce rows to their order-independent checksum and count
+    Checksum {
+        input: Box<PlanNode>,
+    },
+    /// Reduce rows to their count for `COUNT(*)`, counted by the source connector when the
+    /// input is a scan it can count
+    Count {
+        column: Column,
+        input: Box<PlanNode>,
+    },
 }
 
 /// Complete execution plan for a query
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionPlan {
     pub nodes: Vec<PlanNode>,
     pub estimated_cost: f64,
@@ -65,6 +103,331 @@ impl ExecutionPlan {
     pub fn is_empty(&self) -> bool {
         self.nodes.is_empty()
     }
+    
+    /// Keep only the predicates `supports` accepts in table scans, moving the others into
+    /// local Filter nodes above the scans
+    pub fn with_pushdown(self, supports: impl Fn(&Predicate) -> bool) -> Self {
+        Self {
+            nodes: self.nodes.iter().map(|node| node.with_pushdown(&supports)).collect(),
+            estimated_cost: self.estimated_cost,
+        }
+    }
+    
+    /// Predicates evaluated by connectors
+    pub fn pushed_predicates(&self) -> Vec<&Predicate> {
+        let mut predicates = Vec::new();
+        if let Some(root) = self.root_node() {
+            root.collect_predicates(&mut predicates, true);
+        }
+        predicates
+    }
+    
+    /// Predicates evaluated by the engine after fetching rows
+    pub fn local_predicates(&self) -> Vec<&Predicate> {
+        let mut predicates = Vec::new();
+        if let Some(root) = self.root_node() {
+            root.collect_predicates(&mut predicates, false);
+        }
+        predicates
+    }
+}
+
+impl fmt::Display for ExecutionPlan {
+    /// Render the plan as an indented tree, root first
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Plan (estimated cost {:.2}):", self.estimated_cost)?;
+        match self.root_node() {
+            Some(root) => root.write_tree(f, 1),
+            None => writeln!(f, "  (empty)"),
+        }
+    }
+}
+
+impl PlanNode {
+    fn with_pushdown(&self, supports: &impl Fn(&Predicate) -> bool) -> PlanNode {
+        let wrap = |input: &PlanNode| Box::new(input.with_pushdown(supports));
+        match self {
+            PlanNode::TableScan { source, projections, predicates } => {
+                let (pushed, local): (Vec<Predicate>, Vec<Predicate>) = predicates.iter()
+                    .cloned()
+                    .partition(|predicate| supports(predicate));
+                if local.is_empty() {
+                    return self.clone();
+                }
+                
+                // Columns the local filter reads must be fetched even when not selected
+                let mut scan_projections = projections.clone();
+                scan_projections.extend(missing_filter_columns(projections, &local).into_iter()
+                    .map(|name| Column { name, alias: None, source: None }));
+                
+                let filter = PlanNode::Filter {
+                    predicates: local,
+                    input: Box::new(PlanNode::TableScan {
+                        source: source.clone(),
+                        projections: scan_projections.clone(),
+                        predicates: pushed,
+                    }),
+                };
+                if scan_projections.len() == projections.len() {
+                    filter
+                } else {
+                    PlanNode::Projection { columns: projections.clone(), input: Box::new(filter) }
+                }
+            }
+            PlanNode::Limit { count, input } => PlanNode::Limit { count: *count, input: wrap(input) },
+            PlanNode::Sort { order_by, input } => PlanNode::Sort { order_by: order_by.clone(), input: wrap(input) },
+            PlanNode::Projection { columns, input } => PlanNode::Projection { columns: columns.clone(), input: wrap(input) },
+            PlanNode::Sample { sample, input } => PlanNode::Sample { sample: sample.clone(), input: wrap(input) },
+            PlanNode::Aggregate { aggregates, group_by, input } => PlanNode::Aggregate { aggregates: aggregates.clone(), group_by: group_by.clone(), input: wrap(input) },
+            PlanNode::Deduplicate { deduplicate, input } => PlanNode::Deduplicate { deduplicate: deduplicate.clone(), input: wrap(input) },
+            PlanNode::Checksum { input } => PlanNode::Checksum { input: wrap(input) },
+            PlanNode::Count { column, input } => PlanNode::Count { column: column.clone(), input: wrap(input) },
+            PlanNode::Filter { predicates, input } => PlanNode::Filter { predicates: predicates.clone(), input: wrap(input) },
+        }
+    }
+    
+    fn collect_predicates<'a>(&'a self, predicates: &mut Vec<&'a Predicate>, pushed: bool) {
+        match self {
+            PlanNode::TableScan { predicates: scan, .. } => {
+                if pushed {
+                    predicates.extend(scan);
+                }
+            }
+            PlanNode::Filter { predicates: local, input } => {
+                if !pushed {
+                    predicates.extend(local);
+                }
+                input.collect_predicates(predicates, pushed);
+            }
+            PlanNode::Limit { input, .. } | PlanNode::Sort { input, .. } | PlanNode::Projection { input, .. }
+            | PlanNode::Sample { input, .. } | PlanNode::Aggregate { input, .. } | PlanNode::Deduplicate { input, .. }
+            | PlanNode::Checksum { input } | PlanNode::Count { input, .. } => {
+                input.collect_predicates(predicates, pushed);
+            }
+        }
+    }
+
+    /// One-line name of the node, without its inputs
+    pub fn label(&self) -> String {
+        match self {
+            PlanNode::TableScan { source, .. } => format!("TableScan {}.{}", source.object_type, source.identifier),
+            PlanNode::Limit { count, .. } => format!("Limit {}", count),
+            PlanNode::Sort { .. } => "Sort".to_string(),
+            PlanNode::Projection { .. } => "Projection".to_string(),
+            PlanNode::Filter { .. } => "Filter (local)".to_string(),
+            PlanNode::Sample { sample, .. } => sample.to_string(),
+            PlanNode::Aggregate { .. } => "Aggregate".to_string(),
+            PlanNode::Deduplicate { .. } => "Deduplicate".to_string(),
+            PlanNode::Checksum { .. } => "Checksum".to_string(),
+            PlanNode::Count { .. } => "Count".to_string(),
+        }
+    }
+
+    fn write_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        match self {
+            PlanNode::TableScan { source, projections, predicates } => {
+                let columns: Vec<String> = projections.iter()
+                    .map(|col| match &col.alias {
+                        Some(alias) => format!("{} AS {}", col.name, alias),
+                        None => col.name.clone(),
+                    })
+                    .collect();
+                write!(f, "{}TableScan {}.{} [{}]", indent, source.object_type, source.identifier, columns.join(", "))?;
+                if !predicates.is_empty() {
+                    let filters: Vec<String> = predicates.iter().map(describe_predicate).collect();
+                    write!(f, " WHERE {}", filters.join(" AND "))?;
+                }
+                writeln!(f)
+            }
+            PlanNode::Limit { count, input } => {
+                writeln!(f, "{}Limit {}", indent, count)?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Sort { order_by, input } => {
+                let keys: Vec<String> = order_by.columns.iter().map(|col| col.to_string()).collect();
+                writeln!(f, "{}Sort {}", indent, keys.join(", "))?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Projection { columns, input } => {
+                let names: Vec<&str> = columns.iter().map(|col| col.name.as_str()).collect();
+                writeln!(f, "{}Projection [{}]", indent, names.join(", "))?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Filter { predicates, input } => {
+                let filters: Vec<String> = predicates.iter().map(describe_predicate).collect();
+                writeln!(f, "{}Filter (local) {}", indent, filters.join(" AND "))?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Sample { sample, input } => {
+                writeln!(f, "{}{}", indent, sample)?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Aggregate { aggregates, group_by, input } => {
+                let outputs: Vec<String> = aggregates.iter()
+                    .map(|col| match &col.alias {
+                        Some(alias) => format!("{} AS {}", col.name, alias),
+                        None => col.name.clone(),
+                    })
+                    .collect();
+                write!(f, "{}Aggregate {}", indent, outputs.join(", "))?;
+                if !group_by.is_empty() {
+                    let keys: Vec<&str> = group_by.iter().map(|col| col.name.as_str()).collect();
+                    write!(f, " GROUP BY {}", keys.join(", "))?;
+                }
+                writeln!(f)?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Deduplicate { deduplicate, input } => {
+                writeln!(f, "{}{}", indent, deduplicate)?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Checksum { input } => {
+                writeln!(f, "{}Checksum", indent)?;
+                input.write_tree(f, depth + 1)
+            }
+            PlanNode::Count { column, input } => {
+                match &column.alias {
+                    Some(alias) => writeln!(f, "{}Count AS {}", indent, alias)?,
+                    None => writeln!(f, "{}Count", indent)?,
+                }
+                input.write_tree(f, depth + 1)
+            }
+        }
+    }
+}
+
+/// Resolve a query's GROUP BY items against its SELECT list: `GROUP BY 2` is the second
+/// projection's expression, a name no projection selects but one is aliased as is that
+/// projection's expression, and any other column or expression groups by itself
+pub fn resolve_group_by(query: &InternalQuery) -> NirvResult<Vec<Column>> {
+    let case = IdentifierCase::default();
+    let invalid = |message: String| NirvError::QueryParsing(QueryParsingError::InvalidSyntax(message));
+    let mut keys: Vec<Column> = Vec::new();
+    for item in &query.group_by {
+        let (projection, written) = match item {
+            GroupByItem::Position(position) => {
+                let projection = query.projections.get(position - 1).ok_or_else(|| invalid(format!(
+                    "GROUP BY position {} is not in the SELECT list of {} columns", position, query.projections.len()
+                )))?;
+                (Some(projection), position.to_string())
+            }
+            GroupByItem::Column(column) => {
+                let selected = query.projections.iter().find(|col| {
+                    case.matches(&column.name, &col.name) && (column.source.is_none() || column.source == col.source)
+                });
+                let aliased = || column.source.is_none().then(|| query.projections.iter()
+                    .find(|col| col.alias.as_deref().is_some_and(|alias| case.matches(&column.name, alias))))
+                    .flatten();
+                match selected.or_else(aliased) {
+                    Some(projection) => (Some(projection), column.name.clone()),
+                    None => {
+                        keys.push(Column { alias: None, ..column.clone() });
+                        continue;
+                    }
+                }
+            }
+        };
+        if let Some(projection) = projection {
+            if projection.name == "*" || projection.name == COUNT_ALL || ApproxAggregate::parse(&projection.name).is_some() {
+                return Err(invalid(format!("GROUP BY {} refers to {}, which cannot be grouped by", written, projection.name)));
+            }
+            let key = Column { alias: None, ..projection.clone() };
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// Which of a query's sources a predicate reads, by the qualifier of its column: `u.age`
+/// reads the source aliased `u`, or named `u` when it has no alias. A single source is read
+/// by every predicate; in a join an unqualified column is `None`, left for the joined rows
+/// to resolve, and a qualifier naming no source is an error.
+pub fn predicate_source(sources: &[DataSource], predicate: &Predicate) -> NirvResult<Option<usize>> {
+    let column = ComputedColumn::parse(&predicate.column)
+        .map_or_else(|| predicate.column.clone(), |computed| computed.column().to_string());
+    let single = (sources.len() == 1).then_some(0);
+    let Some((qualifier, _)) = split_qualifier(&column) else {
+        return Ok(single);
+    };
+    let label = |source: &DataSource| source.alias.clone().unwrap_or_else(|| source.identifier.clone());
+    let named = sources.iter().position(|source| IdentifierCase::default().matches(qualifier, &label(source)));
+    match named.or(single) {
+        Some(index) => Ok(Some(index)),
+        None => {
+            let labels: Vec<String> = sources.iter().map(label).collect();
+            Err(QueryParsingError::InvalidSyntax(format!(
+                "'{}' in the predicate on {} names none of the query's sources, which are {}", qualifier, column, labels.join(" and ")
+            )).into())
+        }
+    }
+}
+
+/// Columns read by the predicates that an explicit projection list does not fetch
+pub(crate) fn missing_filter_columns(projections: &[Column], predicates: &[Predicate]) -> Vec<String> {
+    missing_columns(projections, predicates.iter().map(|predicate| ComputedColumn::parse(&predicate.column)
+        .map_or_else(|| predicate.column.clone(), |computed| computed.column().to_string())))
+}
+
+/// Key and ordering columns of a deduplication that an explicit projection list does not fetch
+pub(crate) fn missing_deduplicate_columns(projections: &[Column], deduplicate: &Deduplicate) -> Vec<String> {
+    missing_columns(projections, deduplicate.columns.iter().cloned()
+        .chain(deduplicate.order_by.iter().map(|order| order.column.clone())))
+}
+
+/// Ordering columns that an explicit projection list neither fetches nor names as an alias
+pub(crate) fn missing_sort_columns(projections: &[Column], order_by: &OrderBy) -> Vec<String> {
+    let case = IdentifierCase::default();
+    missing_columns(projections, order_by.columns.iter()
+        .filter(|order| !projections.iter().any(|col| col.alias.as_deref().is_some_and(|alias| case.matches(&order.column, alias))))
+        .map(|order| order.column.clone()))
+}
+
+fn missing_columns(projections: &[Column], columns: impl Iterator<Item = String>) -> Vec<String> {
+    if projections.is_empty() || projections.iter().any(|col| col.name == "*") {
+        return Vec::new();
+    }
+    
+    let mut missing: Vec<String> = Vec::new();
+    for column in columns {
+        let fetched = projections.iter().any(|col| IdentifierCase::default().matches(&column, &col.name))
+            || missing.contains(&column);
+        if !fetched {
+            missing.push(column);
+        }
+    }
+    missing
+}
+
+/// A predicate as SQL text
+pub(crate) fn describe_predicate(predicate: &Predicate) -> String {
+    let operator = match predicate.operator {
+        PredicateOperator::Equal => "=",
+        PredicateOperator::NotEqual => "<>",
+        PredicateOperator::GreaterThan => ">",
+        PredicateOperator::GreaterThanOrEqual => ">=",
+        PredicateOperator::LessThan => "<",
+        PredicateOperator::LessThanOrEqual => "<=",
+        PredicateOperator::Like => "LIKE",
+        PredicateOperator::Regexp => "REGEXP",
+        PredicateOperator::In => "IN",
+        PredicateOperator::IsNull => return format!("{} IS NULL", predicate.column),
+        PredicateOperator::IsNotNull => return format!("{} IS NOT NULL", predicate.column),
+    };
+    format!("{} {} {}", predicate.column, operator, describe_value(&predicate.value))
+}
+
+fn describe_value(value: &PredicateValue) -> String {
+    match value {
+        PredicateValue::String(s) => format!("'{}'", s.replace('\'', "''")),
+        PredicateValue::Number(n) => n.to_string(),
+        PredicateValue::Integer(i) => i.to_string(),
+        PredicateValue::Boolean(b) => b.to_string(),
+        PredicateValue::Null => "NULL".to_string(),
+        PredicateValue::List(values) => format!("({})", values.iter().map(describe_value).collect::<Vec<_>>().join(", ")),
+    }
 }
 
 impl Default for ExecutionPlan {
@@ -84,6 +447,13 @@ pub trait QueryPlanner: Send + Sync {
     
     /// Optimize an execution plan
     async fn optimize_plan(&self, plan: ExecutionPlan) -> NirvResult<ExecutionPlan>;
+    
+    /// Create a plan for a query against the given connector, pushing down only the
+    /// predicates the connector supports and filtering the rest locally
+    async fn create_pushdown_plan(&self, query: &InternalQuery, connector: &dyn Connector) -> NirvResult<ExecutionPlan> {
+        let plan = self.create_execution_plan(query).await?;
+        Ok(plan.with_pushdown(|predicate| connector.supports_predicate(predicate)))
+    }
 }
 
 /// Default implementation of QueryPlanner
@@ -139,13 +509,28 @@ impl DefaultQueryPlanner {
             ));
         }
         
+        // Aggregates can only be mixed with the columns the rows are grouped by
+        if is_aggregation(query) {
+            let group_by = resolve_group_by(query)?;
+            let ungrouped = query.projections.iter().find(|col| {
+                ApproxAggregate::parse(&col.name).is_none() && col.name != COUNT_ALL
+                    && group_key(col, &group_by, IdentifierCase::default()).is_none()
+            });
+            if let Some(column) = ungrouped {
+                return Err(ungrouped_column_error(column, &group_by));
+            }
+        }
+        
         Ok(())
     }
     
     /// Create a table scan node for a data source
-    fn create_table_scan_node(&self, query: &InternalQuery) -> PlanNode {
+    fn create_table_scan_node(&self, query: &InternalQuery, group_by: &[Column]) -> PlanNode {
         let source = query.sources[0].clone();
-        let projections = if query.projections.is_empty() {
+        let projections = if is_aggregation(query) {
+            // Aggregates are computed above the scan from the columns they and the group keys read
+            aggregate_input_columns(&query.projections, group_by)
+        } else if query.projections.is_empty() || query.is_count_only() {
             // Default to selecting all columns
             vec![Column {
                 name: "*".to_string(),
@@ -155,6 +540,12 @@ impl DefaultQueryPlanner {
         } else {
             query.projections.clone()
         };
+        // Deduplication reads its key and ordering columns even when not selected
+        let mut projections = projections;
+        if let Some(deduplicate) = &query.deduplicate {
+            let missing = missing_deduplicate_columns(&projections, deduplicate);
+            projections.extend(missing.into_iter().map(|name| Column { name, alias: None, source: None }));
+        }
         
         PlanNode::TableScan {
             source,
@@ -163,9 +554,75 @@ impl DefaultQueryPlanner {
         }
     }
     
+    /// Add deduplication node above the table scan, dropping the columns fetched only for it
+    fn add_deduplicate_node(&self, mut plan: ExecutionPlan, query: &InternalQuery) -> ExecutionPlan {
+        if let (Some(deduplicate), Some(last_node)) = (&query.deduplicate, plan.nodes.last()) {
+            let mut node = PlanNode::Deduplicate {
+                deduplicate: deduplicate.clone(),
+                input: Box::new(last_node.clone()),
+            };
+            let reduced = is_aggregation(query) || query.is_count_only();
+            if !reduced && !missing_deduplicate_columns(&query.projections, deduplicate).is_empty() {
+                node = PlanNode::Projection { columns: query.projections.clone(), input: Box::new(node) };
+            }
+            plan.add_node(node);
+        }
+        plan
+    }
+    
+    /// Add sample node above the table scan if query samples its source
+    fn add_sample_node(&self, mut plan: ExecutionPlan, query: &InternalQuery) -> ExecutionPlan {
+        if let Some(sample) = &query.sample {
+            if let Some(last_node) = plan.nodes.last() {
+                let sample_node = PlanNode::Sample {
+                    sample: sample.clone(),
+                    input: Box::new(last_node.clone()),
+                };
+                plan.add_node(sample_node);
+            }
+        }
+        plan
+    }
+    
+    /// Add aggregate node if query groups rows or projects approximate aggregates
+    fn add_aggregate_node(&self, mut plan: ExecutionPlan, query: &InternalQuery, group_by: &[Column]) -> ExecutionPlan {
+        if is_aggregation(query) {
+            if let Some(last_node) = plan.nodes.last() {
+                let aggregate_node = PlanNode::Aggregate {
+                    aggregates: query.projections.clone(),
+                    group_by: group_by.to_vec(),
+                    input: Box::new(last_node.clone()),
+                };
+                plan.add_node(aggregate_node);
+            }
+        }
+        plan
+    }
+    
+    /// Add count node for a lone `COUNT(*)`
+    fn add_count_node(&self, mut plan: ExecutionPlan, query: &InternalQuery) -> ExecutionPlan {
+        if let (true, Some(last_node)) = (query.is_count_only(), plan.nodes.last()) {
+            let count_node = PlanNode::Count {
+                column: query.projections[0].clone(),
+                input: Box::new(last_node.clone()),
+            };
+            plan.add_node(count_node);
+        }
+        plan
+    }
+    
+    /// Add checksum node for `CHECKSUM TABLE`
+    fn add_checksum_node(&self, mut plan: ExecutionPlan, query: &InternalQuery) -> ExecutionPlan {
+        if let (true, Some(last_node)) = (query.checksum, plan.nodes.last()) {
+            let checksum_node = PlanNode::Checksum { input: Box::new(last_node.clone()) };
+            plan.add_node(checksum_node);
+        }
+        plan
+    }
+    
     /// Add limit node if query has a limit clause
     fn add_limit_node(&self, mut plan: ExecutionPlan, query: &InternalQuery) -> ExecutionPlan {
-        if let Some(limit) = query.limit {
+        if let Some(limit) = query.hints.limit(query.limit) {
             if let Some(last_node) = plan.nodes.last() {
                 let limit_node = PlanNode::Limit {
                     count: limit,
@@ -178,12 +635,17 @@ impl DefaultQueryPlanner {
         plan
     }
     
-    /// Add sort node if query has ordering
+    /// Add sort node if query has ordering, which a row count leaves nothing to apply to
     fn add_sort_node(&self, mut plan: ExecutionPlan, query: &InternalQuery) -> ExecutionPlan {
-        if let Some(order_by) = &query.ordering {
+        if let (Some(order_by), false) = (&query.ordering, query.is_count_only()) {
             if let Some(last_node) = plan.nodes.last() {
+                // Aggregated rows are sorted by the names they are output under
+                let order_by = match is_aggregation(query) {
+                    true => aggregated_order(order_by, &query.projections, IdentifierCase::default()),
+                    false => order_by.clone(),
+                };
                 let sort_node = PlanNode::Sort {
-                    order_by: order_by.clone(),
+                    order_by,
                     input: Box::new(last_node.clone()),
                 };
                 plan.add_node(sort_node);
@@ -227,20 +689,33 @@ impl QueryPlanner for DefaultQueryPlanner {
         self.validate_query(query)?;
         
         let mut plan = ExecutionPlan::new();
+        let group_by = resolve_group_by(query)?;
         
         // Create the base table scan node
-        let table_scan = self.create_table_scan_node(query);
+        let table_scan = self.create_table_scan_node(query, &group_by);
         plan.add_node(table_scan);
         
         // Calculate base cost
         plan.estimated_cost = self.calculate_cost(query);
         
+        // Deduplication and sampling apply to source rows, before sort and limit
+        plan = self.add_deduplicate_node(plan, query);
+        plan = self.add_sample_node(plan, query);
+        plan = self.add_aggregate_node(plan, query, &group_by);
+        plan = self.add_count_node(plan, query);
+        plan = self.add_checksum_node(plan, query);
+        
         // Add sort node if needed (before limit)
         plan = self.add_sort_node(plan, query);
         
         // Add limit node if needed (after sort)
         plan = self.add_limit_node(plan, query);
         
+        // A NO_PUSHDOWN(predicates) hint or `pushdown=off` directive filters every row the scan returns
+        if !query.hints.with_directives(&query.directives)?.pushes_down(Pushdown::Predicates) {
+            plan = plan.with_pushdown(|_| false);
+        }
+        
         Ok(plan)
     }
     
@@ -495,6 +970,7 @@ mod tests {
             columns: vec![OrderColumn {
                 column: "name".to_string(),
                 direction: OrderDirection::Ascending,
+                nulls: None,
             }],
         });
         
@@ -528,6 +1004,7 @@ mod tests {
             columns: vec![OrderColumn {
                 column: "created_at".to_string(),
                 direction: OrderDirection::Descending,
+                nulls: None,
             }],
         });
         query.limit = Some(5);
@@ -572,6 +1049,7 @@ mod tests {
             columns: vec![OrderColumn {
                 column: "name".to_string(),
                 direction: OrderDirection::Ascending,
+                nulls: None,
             }],
         });
         query.limit = Some(10);
@@ -609,4 +1087,150 @@ mod tests {
         assert_eq!(optimized_plan.nodes.len(), plan.nodes.len());
         assert_eq!(optimized_plan.estimated_cost, plan.estimated_cost);
     }
+    
+    #[tokio::test]
+    async fn test_pushdown_keeps_unsupported_predicates_local() {
+        let planner = DefaultQueryPlanner::new();
+        
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource {
+            object_type: "api".to_string(),
+            identifier: "users".to_string(),
+            alias: None,
+        });
+        query.projections.push(Column { name: "id".to_string(), alias: None, source: None });
+        query.predicates.push(Predicate {
+            column: "name".to_string(),
+            operator: PredicateOperator::Like,
+            value: PredicateValue::String("A%".to_string()),
+        });
+        query.predicates.push(Predicate {
+            column: "id".to_string(),
+            operator: PredicateOperator::GreaterThan,
+            value: PredicateValue::Integer(1),
+        });
+        
+        let plan = planner.create_execution_plan(&query).await.unwrap()
+            .with_pushdown(|predicate| predicate.operator != PredicateOperator::Like);
+        
+        let pushed: Vec<&str> = plan.pushed_predicates().iter().map(|p| p.column.as_str()).collect();
+        let local: Vec<&str> = plan.local_predicates().iter().map(|p| p.column.as_str()).collect();
+        assert_eq!((pushed, local), (vec!["id"], vec!["name"]));
+        
+        // The scan fetches the filtered column and the projection drops it again
+        let rendered = plan.to_string();
+        assert!(rendered.contains("Projection [id]"), "{}", rendered);
+        assert!(rendered.contains("Filter (local) name LIKE 'A%'"), "{}", rendered);
+        assert!(rendered.contains("TableScan api.users [id, name] WHERE id > 1"), "{}", rendered);
+    }
+    
+    #[tokio::test]
+    async fn test_query_planner_with_approx_aggregates() {
+        let planner = DefaultQueryPlanner::new();
+        
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource {
+            object_type: "file".to_string(),
+            identifier: "big.csv".to_string(),
+            alias: None,
+        });
+        query.projections.push(Column { name: "approx_count_distinct(email)".to_string(), alias: Some("emails".to_string()), source: None });
+        query.projections.push(Column { name: "approx_percentile(latency, 0.9)".to_string(), alias: None, source: None });
+        
+        let rendered = planner.create_execution_plan(&query).await.unwrap().to_string();
+        assert!(rendered.contains("Aggregate approx_count_distinct(email) AS emails, approx_percentile(latency, 0.9)"), "{}", rendered);
+        assert!(rendered.contains("TableScan file.big.csv [email, latency]"), "{}", rendered);
+        
+        query.projections.push(Column { name: "id".to_string(), alias: None, source: None });
+        assert!(planner.create_execution_plan(&query).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_query_planner_resolves_group_by() {
+        let planner = DefaultQueryPlanner::new();
+        let column = |name: &str, alias: Option<&str>| Column { name: name.to_string(), alias: alias.map(str::to_string), source: None };
+        
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource {
+            object_type: "file".to_string(),
+            identifier: "orders.csv".to_string(),
+            alias: None,
+        });
+        query.projections = vec![
+            column("status", Some("state")),
+            column("date_trunc('month', created)", Some("month")),
+            column(COUNT_ALL, Some("orders")),
+            column("approx_count_distinct(customer)", None),
+        ];
+        // An alias, a position and a column not selected
+        query.group_by = vec![
+            GroupByItem::Column(column("state", None)),
+            GroupByItem::Position(2),
+            GroupByItem::Column(column("region", None)),
+        ];
+        let keys: Vec<String> = resolve_group_by(&query).unwrap().into_iter().map(|col| col.name).collect();
+        assert_eq!(keys, vec!["status", "date_trunc('month', created)", "region"]);
+        
+        query.ordering = Some(OrderBy { columns: vec![OrderColumn { column: "status".to_string(), direction: OrderDirection::Descending, nulls: None }] });
+        let rendered = planner.create_execution_plan(&query).await.unwrap().to_string();
+        assert!(rendered.contains("Sort state DESC"), "{}", rendered);
+        assert!(rendered.contains("GROUP BY status, date_trunc('month', created), region"), "{}", rendered);
+        assert!(rendered.contains("TableScan file.orders.csv [customer, status, date_trunc('month', created), region]"), "{}", rendered);
+        
+        // Positions name a grouped expression in the SELECT list, never an aggregate
+        query.group_by = vec![GroupByItem::Position(5)];
+        assert!(resolve_group_by(&query).is_err());
+        query.group_by = vec![GroupByItem::Position(3)];
+        assert!(resolve_group_by(&query).is_err());
+        // and every column selected outside an aggregate is grouped by
+        query.group_by = vec![GroupByItem::Position(1)];
+        assert!(planner.create_execution_plan(&query).await.is_err());
+    }
+    
+    #[test]
+    fn test_predicate_source() {
+        let source = |identifier: &str, alias: Option<&str>| DataSource {
+            object_type: "file".to_string(),
+            identifier: identifier.to_string(),
+            alias: alias.map(str::to_string),
+        };
+        let predicate = |column: &str| Predicate { column: column.to_string(), operator: PredicateOperator::IsNull, value: PredicateValue::Null };
+        let joined = [source("users.csv", Some("u")), source("orders", None)];
+        
+        assert_eq!(predicate_source(&joined, &predicate("U.age")).unwrap(), Some(0));
+        assert_eq!(predicate_source(&joined, &predicate("orders.total")).unwrap(), Some(1));
+        assert_eq!(predicate_source(&joined, &predicate("md5(u.email)")).unwrap(), Some(0));
+        assert_eq!(predicate_source(&joined, &predicate("age")).unwrap(), None);
+        assert!(predicate_source(&joined, &predicate("x.age")).is_err());
+        // A single source reads every predicate, whatever qualifies it
+        assert_eq!(predicate_source(&joined[..1], &predicate("x.age")).unwrap(), Some(0));
+    }
+    
+    #[tokio::test]
+    async fn test_query_planner_with_count_all() {
+        let planner = DefaultQueryPlanner::new();
+        
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource {
+            object_type: "file".to_string(),
+            identifier: "big.csv".to_string(),
+            alias: None,
+        });
+        query.projections.push(Column { name: COUNT_ALL.to_string(), alias: Some("total".to_string()), source: None });
+        query.predicates.push(Predicate {
+            column: "name".to_string(),
+            operator: PredicateOperator::Like,
+            value: PredicateValue::String("A%".to_string()),
+        });
+        query.ordering = Some(OrderBy { columns: vec![OrderColumn { column: "id".to_string(), direction: OrderDirection::Ascending, nulls: None }] });
+        
+        // The scan alone is counted, by the source when it evaluates every predicate
+        let plan = planner.create_execution_plan(&query).await.unwrap();
+        let rendered = plan.to_string();
+        assert!(rendered.contains("  Count AS total\n    TableScan file.big.csv [*] WHERE name LIKE 'A%'"), "{}", rendered);
+        assert!(!rendered.contains("Sort"), "{}", rendered);
+        
+        let rendered = plan.with_pushdown(|_| false).to_string();
+        assert!(rendered.contains("  Count AS total\n    Filter (local) name LIKE 'A%'"), "{}", rendered);
+    }
 }
\ No newline at end of file
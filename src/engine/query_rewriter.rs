@@ -0,0 +1,310 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::utils::{
+    config::QueryRewriterConfig,
+    error::{NirvError, NirvResult},
+    types::{InternalQuery, Predicate, PredicateOperator, PredicateValue},
+};
+
+/// Middleware that inspects or rewrites a parsed query before it is planned
+#[async_trait]
+pub trait QueryRewriter: Send + Sync {
+    /// Name used in error messages and diagnostics
+    fn name(&self) -> &str;
+
+    /// Rewrite the query, or reject it with an error
+    async fn rewrite(&self, query: InternalQuery) -> NirvResult<InternalQuery>;
+}
+
+/// Ordered chain of query rewriters
+#[derive(Clone, Default)]
+pub struct QueryRewriterPipeline {
+    rewriters: Vec<Arc<dyn QueryRewriter>>,
+}
+
+impl QueryRewriterPipeline {
+    /// Create an empty pipeline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a pipeline from deployment configuration, preserving order
+    pub fn from_config(configs: &[QueryRewriterConfig]) -> Self {
+        let mut pipeline = Self::new();
+        for config in configs {
+            let rewriter: Arc<dyn QueryRewriter> = match config {
+                QueryRewriterConfig::TableRemap { mappings } => {
+                    Arc::new(TableRemapRewriter::new(mappings.clone()))
+                }
+                QueryRewriterConfig::Denylist { sources } => {
+                    Arc::new(SourceDenylistRewriter::new(sources.clone()))
+                }
+                QueryRewriterConfig::InjectPredicate { column, value, object_types } => {
+                    Arc::new(
+                        PredicateInjectionRewriter::new(column, json_to_predicate_value(value))
+                            .for_object_types(object_types.clone())
+                    )
+                }
+            };
+            pipeline.add(rewriter);
+        }
+        pipeline
+    }
+
+    /// Append a rewriter to the end of the pipeline
+    pub fn add(&mut self, rewriter: Arc<dyn QueryRewriter>) {
+        self.rewriters.push(rewriter);
+    }
+
+    /// Number of registered rewriters
+    pub fn len(&self) -> usize {
+        self.rewriters.len()
+    }
+
+    /// Check if the pipeline has no rewriters
+    pub fn is_empty(&self) -> bool {
+        self.rewriters.is_empty()
+    }
+
+    /// Names of the registered rewriters in execution order
+    pub fn names(&self) -> Vec<String> {
+        self.rewriters.iter().map(|r| r.name().to_string()).collect()
+    }
+
+    /// Run the query through every rewriter in order
+    pub async fn apply(&self, mut query: InternalQuery) -> NirvResult<InternalQuery> {
+        for rewriter in &self.rewriters {
+            query = rewriter.rewrite(query).await?;
+        }
+        Ok(query)
+    }
+}
+
+/// Convert a configured JSON value into a predicate value
+fn json_to_predicate_value(value: &serde_json::Value) -> PredicateValue {
+    match value {
+        serde_json::Value::Null => PredicateValue::Null,
+        serde_json::Value::Bool(b) => PredicateValue::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => PredicateValue::Integer(i),
+            None => PredicateValue::Number(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => PredicateValue::String(s.clone()),
+        serde_json::Value::Array(items) => {
+            PredicateValue::List(items.iter().map(json_to_predicate_value).collect())
+        }
+        serde_json::Value::Object(_) => PredicateValue::String(value.to_string()),
+    }
+}
+
+/// Remaps source names, keyed by `object_type.identifier`
+#[derive(Debug, Clone)]
+pub struct TableRemapRewriter {
+    mappings: HashMap<String, String>,
+}
+
+impl TableRemapRewriter {
+    /// Create a rewriter from `object_type.identifier` to `object_type.identifier` mappings
+    pub fn new(mappings: HashMap<String, String>) -> Self {
+        Self { mappings }
+    }
+}
+
+#[async_trait]
+impl QueryRewriter for TableRemapRewriter {
+    fn name(&self) -> &str {
+        "table_remap"
+    }
+
+    async fn rewrite(&self, mut query: InternalQuery) -> NirvResult<InternalQuery> {
+        for source in &mut query.sources {
+            let key = format!("{}.{}", source.object_type, source.identifier);
+            if let Some(target) = self.mappings.get(&key) {
+                match target.split_once('.') {
+                    Some((object_type, identifier)) => {
+                        source.object_type = object_type.to_string();
+                        source.identifier = identifier.to_string();
+                    }
+                    None => source.identifier = target.clone(),
+                }
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// Rejects queries touching denied sources; `object_type.*` denies a whole type
+#[derive(Debug, Clone)]
+pub struct SourceDenylistRewriter {
+    denied: Vec<String>,
+}
+
+impl SourceDenylistRewriter {
+    /// Create a denylist of `object_type.identifier` or `object_type.*` entries
+    pub fn new(denied: Vec<String>) -> Self {
+        Self { denied }
+    }
+
+    fn is_denied(&self, object_type: &str, identifier: &str) -> bool {
+        self.denied.iter().any(|entry| match entry.split_once('.') {
+            Some((denied_type, "*")) => denied_type == object_type,
+            Some((denied_type, denied_identifier)) => {
+                denied_type == object_type && denied_identifier == identifier
+            }
+            None => entry == object_type,
+        })
+    }
+}
+
+#[async_trait]
+impl QueryRewriter for SourceDenylistRewriter {
+    fn name(&self) -> &str {
+        "denylist"
+    }
+
+    async fn rewrite(&self, query: InternalQuery) -> NirvResult<InternalQuery> {
+        if let Some(source) = query.sources.iter().find(|s| self.is_denied(&s.object_type, &s.identifier)) {
+            return Err(NirvError::QueryRewrite(format!(
+                "access to '{}.{}' is denied", source.object_type, source.identifier
+            )));
+        }
+        Ok(query)
+    }
+}
+
+/// Adds an equality predicate to every query, e.g. for tenant isolation
+#[derive(Debug, Clone)]
+pub struct PredicateInjectionRewriter {
+    column: String,
+    value: PredicateValue,
+    object_types: Vec<String>,
+}
+
+impl PredicateInjectionRewriter {
+    /// Inject `column = value` into queries on any source
+    pub fn new(column: &str, value: PredicateValue) -> Self {
+        Self {
+            column: column.to_string(),
+            value,
+            object_types: Vec::new(),
+        }
+    }
+
+    /// Restrict injection to sources of the given object types
+    pub fn for_object_types(mut self, object_types: Vec<String>) -> Self {
+        self.object_types = object_types;
+        self
+    }
+}
+
+#[async_trait]
+impl QueryRewriter for PredicateInjectionRewriter {
+    fn name(&self) -> &str {
+        "inject_predicate"
+    }
+
+    async fn rewrite(&self, mut query: InternalQuery) -> NirvResult<InternalQuery> {
+        let qualify = query.sources.len() > 1;
+        let mut injected = Vec::new();
+
+        for source in &query.sources {
+            if !self.object_types.is_empty() && !self.object_types.contains(&source.object_type) {
+                continue;
+            }
+            // Joined sources need the column qualified by alias
+            let column = if qualify {
+                let qualifier = source.alias.as_deref().unwrap_or(&source.identifier);
+                format!("{}.{}", qualifier, self.column)
+            } else {
+                self.column.clone()
+            };
+            injected.push(Predicate {
+                column,
+                operator: PredicateOperator::Equal,
+                value: self.value.clone(),
+            });
+        }
+
+        query.predicates.extend(injected);
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{DataSource, QueryOperation};
+
+    fn query_on(object_type: &str, identifier: &str) -> InternalQuery {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource {
+            object_type: object_type.to_string(),
+            identifier: identifier.to_string(),
+            alias: None,
+        });
+        query
+    }
+
+    #[tokio::test]
+    async fn test_table_remap() {
+        let mut mappings = HashMap::new();
+        mappings.insert("postgres.users".to_string(), "postgres.tenant_users".to_string());
+        mappings.insert("mock.orders".to_string(), "archive".to_string());
+        let rewriter = TableRemapRewriter::new(mappings);
+
+        let query = rewriter.rewrite(query_on("postgres", "users")).await.unwrap();
+        assert_eq!(query.sources[0].identifier, "tenant_users");
+
+        let query = rewriter.rewrite(query_on("mock", "orders")).await.unwrap();
+        assert_eq!(query.sources[0].object_type, "mock");
+        assert_eq!(query.sources[0].identifier, "archive");
+
+        let query = rewriter.rewrite(query_on("postgres", "products")).await.unwrap();
+        assert_eq!(query.sources[0].identifier, "products");
+    }
+
+    #[tokio::test]
+    async fn test_denylist() {
+        let rewriter = SourceDenylistRewriter::new(vec![
+            "postgres.secrets".to_string(),
+            "file.*".to_string(),
+        ]);
+
+        assert!(rewriter.rewrite(query_on("postgres", "users")).await.is_ok());
+        assert!(matches!(
+            rewriter.rewrite(query_on("postgres", "secrets")).await,
+            Err(NirvError::QueryRewrite(_))
+        ));
+        assert!(rewriter.rewrite(query_on("file", "data.csv")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_predicate_injection_respects_object_types() {
+        let rewriter = PredicateInjectionRewriter::new("tenant_id", PredicateValue::Integer(7))
+            .for_object_types(vec!["postgres".to_string()]);
+
+        let query = rewriter.rewrite(query_on("postgres", "users")).await.unwrap();
+        assert_eq!(query.predicates.len(), 1);
+        assert_eq!(query.predicates[0].column, "tenant_id");
+        assert_eq!(query.predicates[0].value, PredicateValue::Integer(7));
+
+        let query = rewriter.rewrite(query_on("mock", "users")).await.unwrap();
+        assert!(query.predicates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_runs_in_order() {
+        let configs: Vec<QueryRewriterConfig> = serde_json::from_str(r#"[
+            {"type": "table_remap", "mappings": {"postgres.users": "postgres.secrets"}},
+            {"type": "denylist", "sources": ["postgres.secrets"]}
+        ]"#).unwrap();
+        let pipeline = QueryRewriterPipeline::from_config(&configs);
+
+        assert_eq!(pipeline.names(), vec!["table_remap", "denylist"]);
+        // The remap runs first, so the denylist sees the rewritten source
+        assert!(pipeline.apply(query_on("postgres", "users")).await.is_err());
+        assert!(pipeline.apply(query_on("postgres", "products")).await.is_ok());
+    }
+}
@@ -0,0 +1,210 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::utils::{
+    config::ResultTransformerConfig,
+    error::{NirvError, NirvResult},
+    types::{DataType, QueryResult, Value},
+};
+
+/// Post-processing hook that runs on a query result before it is returned
+#[async_trait]
+pub trait ResultTransformer: Send + Sync {
+    /// Name used in error messages and diagnostics
+    fn name(&self) -> &str;
+
+    /// Transform the result, or fail the query with an error
+    async fn transform(&self, result: QueryResult) -> NirvResult<QueryResult>;
+}
+
+/// Ordered chain of result transformers
+#[derive(Clone, Default)]
+pub struct ResultTransformerPipeline {
+    transformers: Vec<Arc<dyn ResultTransformer>>,
+}
+
+impl ResultTransformerPipeline {
+    /// Create an empty pipeline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a pipeline from deployment configuration, preserving order
+    pub fn from_config(configs: &[ResultTransformerConfig]) -> Self {
+        let mut pipeline = Self::new();
+        for config in configs {
+            let transformer: Arc<dyn ResultTransformer> = match config {
+                ResultTransformerConfig::RenameColumns { mappings } => {
+                    Arc::new(ColumnRenameTransformer::new(mappings.clone()))
+                }
+                ResultTransformerConfig::ScaleColumn { column, factor } => {
+                    Arc::new(ColumnScaleTransformer::new(column, *factor))
+                }
+            };
+            pipeline.add(transformer);
+        }
+        pipeline
+    }
+
+    /// Append a transformer to the end of the pipeline
+    pub fn add(&mut self, transformer: Arc<dyn ResultTransformer>) {
+        self.transformers.push(transformer);
+    }
+
+    /// Number of registered transformers
+    pub fn len(&self) -> usize {
+        self.transformers.len()
+    }
+
+    /// Check if the pipeline has no transformers
+    pub fn is_empty(&self) -> bool {
+        self.transformers.is_empty()
+    }
+
+    /// Names of the registered transformers in execution order
+    pub fn names(&self) -> Vec<String> {
+        self.transformers.iter().map(|t| t.name().to_string()).collect()
+    }
+
+    /// Run the result through every transformer in order
+    pub async fn apply(&self, mut result: QueryResult) -> NirvResult<QueryResult> {
+        for transformer in &self.transformers {
+            result = transformer.transform(result).await?;
+        }
+        Ok(result)
+    }
+}
+
+/// Renames result columns using an old-name to new-name map
+#[derive(Debug, Clone)]
+pub struct ColumnRenameTransformer {
+    mappings: HashMap<String, String>,
+}
+
+impl ColumnRenameTransformer {
+    /// Create a transformer from old-name to new-name mappings
+    pub fn new(mappings: HashMap<String, String>) -> Self {
+        Self { mappings }
+    }
+}
+
+#[async_trait]
+impl ResultTransformer for ColumnRenameTransformer {
+    fn name(&self) -> &str {
+        "rename_columns"
+    }
+
+    async fn transform(&self, mut result: QueryResult) -> NirvResult<QueryResult> {
+        for column in &mut result.columns {
+            if let Some(new_name) = self.mappings.get(&column.name) {
+                column.name = new_name.clone();
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Multiplies a numeric column by a constant factor, e.g. for currency or unit conversion
+#[derive(Debug, Clone)]
+pub struct ColumnScaleTransformer {
+    column: String,
+    factor: f64,
+}
+
+impl ColumnScaleTransformer {
+    /// Scale values of the named column by the given factor
+    pub fn new(column: &str, factor: f64) -> Self {
+        Self {
+            column: column.to_string(),
+            factor,
+        }
+    }
+}
+
+#[async_trait]
+impl ResultTransformer for ColumnScaleTransformer {
+    fn name(&self) -> &str {
+        "scale_column"
+    }
+
+    async fn transform(&self, mut result: QueryResult) -> NirvResult<QueryResult> {
+        let Some(index) = result.columns.iter().position(|c| c.name == self.column) else {
+            return Ok(result);
+        };
+
+        for row in &mut result.rows {
+            if let Some(value) = row.values.get_mut(index) {
+                *value = match value {
+                    Value::Integer(i) => Value::Float(*i as f64 * self.factor),
+                    Value::Float(f) => Value::Float(*f * self.factor),
+                    Value::Null => Value::Null,
+                    other => return Err(NirvError::Internal(format!(
+                        "Cannot scale non-numeric value {:?} in column '{}'", other, self.column
+                    ))),
+                };
+            }
+        }
+
+        // Scaled integers are no longer integral
+        result.columns[index].data_type = DataType::Float;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{ColumnMetadata, Row};
+
+    fn prices() -> QueryResult {
+        let mut result = QueryResult::new();
+        result.columns = vec![
+            ColumnMetadata { name: "name".to_string(), data_type: DataType::Text, nullable: false },
+            ColumnMetadata { name: "price".to_string(), data_type: DataType::Integer, nullable: true },
+        ];
+        result.rows = vec![
+            Row::new(vec![Value::Text("a".to_string()), Value::Integer(10)]),
+            Row::new(vec![Value::Text("b".to_string()), Value::Null]),
+        ];
+        result
+    }
+
+    #[tokio::test]
+    async fn test_rename_columns() {
+        let mut mappings = HashMap::new();
+        mappings.insert("price".to_string(), "price_usd".to_string());
+
+        let result = ColumnRenameTransformer::new(mappings).transform(prices()).await.unwrap();
+        assert_eq!(result.columns[0].name, "name");
+        assert_eq!(result.columns[1].name, "price_usd");
+    }
+
+    #[tokio::test]
+    async fn test_scale_column() {
+        let result = ColumnScaleTransformer::new("price", 0.5).transform(prices()).await.unwrap();
+        assert_eq!(result.columns[1].data_type, DataType::Float);
+        assert_eq!(result.rows[0].values[1], Value::Float(5.0));
+        assert_eq!(result.rows[1].values[1], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_scale_non_numeric_column_fails() {
+        let result = ColumnScaleTransformer::new("name", 2.0).transform(prices()).await;
+        assert!(matches!(result, Err(NirvError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_runs_in_order() {
+        let configs: Vec<ResultTransformerConfig> = serde_json::from_str(r#"[
+            {"type": "scale_column", "column": "price", "factor": 2.0},
+            {"type": "rename_columns", "mappings": {"price": "price_eur"}}
+        ]"#).unwrap();
+        let pipeline = ResultTransformerPipeline::from_config(&configs);
+
+        assert_eq!(pipeline.names(), vec!["scale_column", "rename_columns"]);
+        let result = pipeline.apply(prices()).await.unwrap();
+        assert_eq!(result.columns[1].name, "price_eur");
+        assert_eq!(result.rows[0].values[1], Value::Float(20.0));
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use crate::utils::{
+    config::DispatcherConfig,
+    error::{DispatcherError, NirvError, NirvResult},
+    types::{QueryHints, QueryPriority},
+};
+
+/// Queries running, and interactive queries waiting, for slots
+#[derive(Debug, Default)]
+struct Slots {
+    interactive: usize,
+    batch: usize,
+    waiting_interactive: usize,
+}
+
+/// Admission control for queries. At most `max_queries` run at once, batch queries hold at
+/// most `max_batch` of the slots, and a batch query is not admitted while an interactive
+/// one waits, so long exports queue behind dashboard queries rather than ahead of them.
+#[derive(Debug)]
+pub struct QueryScheduler {
+    max_queries: usize,
+    max_batch: usize,
+    user_priorities: HashMap<String, QueryPriority>,
+    slots: Mutex<Slots>,
+    released: Notify,
+}
+
+impl QueryScheduler {
+    pub fn new(max_queries: usize, max_batch: usize) -> Self {
+        let max_queries = max_queries.max(1);
+        Self {
+            max_queries,
+            max_batch: max_batch.clamp(1, max_queries),
+            user_priorities: HashMap::new(),
+            slots: Mutex::new(Slots::default()),
+            released: Notify::new(),
+        }
+    }
+
+    pub fn from_config(config: &DispatcherConfig) -> Self {
+        let max_queries = config.max_concurrent_queries as usize;
+        let max_batch = config.max_batch_queries.map_or(max_queries.div_ceil(2), |max| max as usize);
+        let mut scheduler = Self::new(max_queries, max_batch);
+        scheduler.user_priorities = config.user_priorities.clone();
+        scheduler
+    }
+
+    /// Schedule a user's queries in a class unless they name another
+    pub fn with_user_priority(mut self, user: &str, priority: QueryPriority) -> Self {
+        self.user_priorities.insert(user.to_string(), priority);
+        self
+    }
+
+    /// Class of a query: its `PRIORITY` hint or `priority` directive, else its user's
+    pub fn priority(&self, hints: &QueryHints, user: Option<&str>) -> QueryPriority {
+        hints.priority
+            .or_else(|| self.user_priorities.get(user?).copied())
+            .unwrap_or_default()
+    }
+
+    /// Queries of a class running now
+    pub fn running(&self, priority: QueryPriority) -> usize {
+        let slots = self.lock();
+        match priority {
+            QueryPriority::Interactive => slots.interactive,
+            QueryPriority::Batch => slots.batch,
+        }
+    }
+
+    /// Wait for a slot for a query of the class; it is freed when the permit is dropped
+    pub async fn admit(self: &Arc<Self>, priority: QueryPriority) -> QueryPermit {
+        let mut waiting = None;
+        loop {
+            let released = {
+                let mut slots = self.lock();
+                if self.admits(&slots, priority) {
+                    match priority {
+                        QueryPriority::Interactive => slots.interactive += 1,
+                        QueryPriority::Batch => slots.batch += 1,
+                    }
+                    return QueryPermit { scheduler: self.clone(), priority };
+                }
+                if priority == QueryPriority::Interactive && waiting.is_none() {
+                    slots.waiting_interactive += 1;
+                    waiting = Some(WaitingInteractive(self.as_ref()));
+                }
+                // Registered before the lock is released, so no release is missed
+                self.released.notified()
+            };
+            released.await;
+        }
+    }
+
+    fn admits(&self, slots: &Slots, priority: QueryPriority) -> bool {
+        if slots.interactive + slots.batch >= self.max_queries {
+            return false;
+        }
+        match priority {
+            QueryPriority::Interactive => true,
+            QueryPriority::Batch => slots.batch < self.max_batch && slots.waiting_interactive == 0,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Slots> {
+        self.slots.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// An interactive query counted as waiting, until it is admitted or given up on
+struct WaitingInteractive<'a>(&'a QueryScheduler);
+
+impl Drop for WaitingInteractive<'_> {
+    fn drop(&mut self) {
+        self.0.lock().waiting_interactive -= 1;
+        self.0.released.notify_waiters();
+    }
+}
+
+/// A query's slot, freed when dropped
+#[derive(Debug)]
+pub struct QueryPermit {
+    scheduler: Arc<QueryScheduler>,
+    priority: QueryPriority,
+}
+
+impl QueryPermit {
+    pub fn priority(&self) -> QueryPriority {
+        self.priority
+    }
+}
+
+impl Drop for QueryPermit {
+    fn drop(&mut self) {
+        {
+            let mut slots = self.scheduler.lock();
+            match self.priority {
+                QueryPriority::Interactive => slots.interactive -= 1,
+                QueryPriority::Batch => slots.batch -= 1,
+            }
+        }
+        self.scheduler.released.notify_waiters();
+    }
+}
+
+/// Queries that may wait for each connector unless configured otherwise
+pub const DEFAULT_MAX_QUEUED_QUERIES: usize = 100;
+/// Seconds a query may wait for a connector slot unless configured otherwise
+pub const DEFAULT_QUEUE_TIMEOUT_SECS: u64 = 30;
+
+/// Queries running on, and waiting for, one connector
+#[derive(Debug)]
+struct ConnectorSlots {
+    permits: Arc<Semaphore>,
+    queued: Mutex<usize>,
+}
+
+/// Bounded wait for connectors whose concurrency slots, their `max_concurrent_queries`, are
+/// all taken. Queries wait in arrival order; one arriving to a full queue, or waiting past
+/// the timeout, fails.
+#[derive(Debug)]
+pub struct ConnectorQueue {
+    max_queued: usize,
+    timeout: Duration,
+    connectors: Mutex<HashMap<String, Arc<ConnectorSlots>>>,
+}
+
+impl Default for ConnectorQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_QUEUED_QUERIES, Duration::from_secs(DEFAULT_QUEUE_TIMEOUT_SECS))
+    }
+}
+
+impl ConnectorQueue {
+    pub fn new(max_queued: usize, timeout: Duration) -> Self {
+        Self { max_queued, timeout, connectors: Mutex::new(HashMap::new()) }
+    }
+
+    /// Wait for a slot of the connector serving a data object type, returning it with the
+    /// time waited. Connectors without a concurrency limit need no slot.
+    pub async fn acquire(&self, object_type: &str, max_concurrent: Option<u32>) -> NirvResult<(Option<OwnedSemaphorePermit>, Duration)> {
+        let Some(max_concurrent) = max_concurrent else {
+            return Ok((None, Duration::ZERO));
+        };
+        let slots = self.connectors.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(object_type.to_string())
+            .or_insert_with(|| Arc::new(ConnectorSlots {
+                permits: Arc::new(Semaphore::new(max_concurrent.max(1) as usize)),
+                queued: Mutex::new(0),
+            }))
+            .clone();
+        if let Ok(permit) = slots.permits.clone().try_acquire_owned() {
+            return Ok((Some(permit), Duration::ZERO));
+        }
+
+        let _queued = {
+            let mut queued = slots.queued.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if *queued >= self.max_queued {
+                return Err(saturated(format!(
+                    "'{}' runs {} queries and {} more are queued", object_type, max_concurrent, queued
+                )));
+            }
+            *queued += 1;
+            Queued(&slots.queued)
+        };
+        let started = Instant::now();
+        match tokio::time::timeout(self.timeout, slots.permits.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => Ok((Some(permit), started.elapsed())),
+            Ok(Err(_)) => Err(saturated(format!("the slots of '{}' were closed", object_type))),
+            Err(_) => Err(saturated(format!(
+                "no slot of '{}' came free within {}s", object_type, self.timeout.as_secs_f64()
+            ))),
+        }
+    }
+}
+
+/// A query counted as queued for a connector, until it gets a slot, times out or is dropped
+struct Queued<'a>(&'a Mutex<usize>);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) -= 1;
+    }
+}
+
+fn saturated(message: String) -> NirvError {
+    NirvError::Dispatcher(DispatcherError::ConnectorSaturated(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn pending(scheduler: &Arc<QueryScheduler>, priority: QueryPriority) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), scheduler.admit(priority)).await.is_err()
+    }
+
+    #[tokio::test]
+    async fn test_batch_queries_hold_part_of_the_slots() {
+        let scheduler = Arc::new(QueryScheduler::new(3, 1));
+        let _batch = scheduler.admit(QueryPriority::Batch).await;
+        assert!(pending(&scheduler, QueryPriority::Batch).await);
+        let _first = scheduler.admit(QueryPriority::Interactive).await;
+        let _second = scheduler.admit(QueryPriority::Interactive).await;
+        assert_eq!((scheduler.running(QueryPriority::Interactive), scheduler.running(QueryPriority::Batch)), (2, 1));
+        assert!(pending(&scheduler, QueryPriority::Interactive).await);
+    }
+
+    #[tokio::test]
+    async fn test_interactive_queries_are_admitted_first() {
+        let scheduler = Arc::new(QueryScheduler::new(1, 1));
+        let running = scheduler.admit(QueryPriority::Interactive).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let queue = |priority: QueryPriority| {
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            tokio::spawn(async move {
+                let _permit = scheduler.admit(priority).await;
+                order.lock().unwrap().push(priority);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            })
+        };
+        let batch = queue(QueryPriority::Batch);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let interactive = queue(QueryPriority::Interactive);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The batch query queued first, yet the interactive one runs before it
+        drop(running);
+        interactive.await.unwrap();
+        batch.await.unwrap();
+        assert_eq!(*order.lock().unwrap(), vec![QueryPriority::Interactive, QueryPriority::Batch]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_interactive_queries_stop_holding_back_batch_queries() {
+        let scheduler = Arc::new(QueryScheduler::new(1, 1));
+        let running = scheduler.admit(QueryPriority::Interactive).await;
+        assert!(pending(&scheduler, QueryPriority::Interactive).await);
+        drop(running);
+        assert_eq!(scheduler.admit(QueryPriority::Batch).await.priority(), QueryPriority::Batch);
+    }
+
+    #[tokio::test]
+    async fn test_connector_queue() {
+        let queue = ConnectorQueue::new(1, Duration::from_millis(100));
+        assert!(queue.acquire("api", None).await.unwrap().0.is_none());
+        let (running, waited) = queue.acquire("api", Some(1)).await.unwrap();
+        assert_eq!(waited, Duration::ZERO);
+
+        // One query waits for the slot; the next finds the queue full
+        let waiting = queue.acquire("api", Some(1));
+        let full = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let error = queue.acquire("api", Some(1)).await.unwrap_err();
+            drop(running);
+            error
+        };
+        let (waited, full) = tokio::join!(waiting, full);
+        assert!(full.to_string().contains("1 more are queued"), "{}", full);
+        let (slot, waited) = waited.unwrap();
+        assert!(waited >= Duration::from_millis(20));
+
+        // Nobody frees the slot this time
+        let error = queue.acquire("api", Some(1)).await.unwrap_err();
+        assert!(error.to_string().contains("no slot of 'api' came free"), "{}", error);
+        drop(slot);
+        assert!(queue.acquire("api", Some(1)).await.is_ok());
+    }
+
+    #[test]
+    fn test_query_priority() {
+        let scheduler = QueryScheduler::new(4, 2).with_user_priority("exporter", QueryPriority::Batch);
+        let hinted = QueryHints { priority: Some(QueryPriority::Interactive), ..QueryHints::default() };
+        assert_eq!(scheduler.priority(&QueryHints::default(), Some("exporter")), QueryPriority::Batch);
+        assert_eq!(scheduler.priority(&hinted, Some("exporter")), QueryPriority::Interactive);
+        assert_eq!(scheduler.priority(&QueryHints::default(), Some("ann")), QueryPriority::Interactive);
+        assert_eq!(scheduler.priority(&QueryHints::default(), None), QueryPriority::Interactive);
+    }
+}
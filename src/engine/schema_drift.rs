@@ -0,0 +1,339 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::engine::{Dispatcher, SYSTEM_OBJECT_TYPE};
+use crate::utils::types::{ColumnMetadata, DataType, QueryResult, Row, Schema, Value};
+
+/// Drift events kept for the `system.schema_drift` table
+const MAX_DRIFT_EVENTS: usize = 1000;
+
+/// How one column changed between two introspections of a source object
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ColumnChange {
+    Added { column: String, data_type: DataType },
+    Removed { column: String, data_type: DataType },
+    Retyped { column: String, from: DataType, to: DataType },
+}
+
+impl fmt::Display for ColumnChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnChange::Added { column, data_type } => write!(f, "added {} ({:?})", column, data_type),
+            ColumnChange::Removed { column, data_type } => write!(f, "removed {} ({:?})", column, data_type),
+            ColumnChange::Retyped { column, from, to } => write!(f, "retyped {} from {:?} to {:?}", column, from, to),
+        }
+    }
+}
+
+/// Column changes found in one source object
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaDrift {
+    pub object_type: String,
+    pub object: String,
+    pub detected_at: DateTime<Utc>,
+    pub changes: Vec<ColumnChange>,
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changes: Vec<String> = self.changes.iter().map(ToString::to_string).collect();
+        write!(f, "Schema drift in {}.{}: {}", self.object_type, self.object, changes.join(", "))
+    }
+}
+
+/// Columns added, removed or retyped from one schema to the next, matched by name
+pub fn diff_schemas(previous: &Schema, current: &Schema) -> Vec<ColumnChange> {
+    let find = |schema: &Schema, name: &str| schema.columns.iter().find(|column| column.name == name).cloned();
+    let mut changes = Vec::new();
+    for column in &previous.columns {
+        match find(current, &column.name) {
+            None => changes.push(ColumnChange::Removed { column: column.name.clone(), data_type: column.data_type.clone() }),
+            Some(now) if now.data_type != column.data_type => changes.push(ColumnChange::Retyped {
+                column: column.name.clone(),
+                from: column.data_type.clone(),
+                to: now.data_type,
+            }),
+            Some(_) => {}
+        }
+    }
+    for column in &current.columns {
+        if find(previous, &column.name).is_none() {
+            changes.push(ColumnChange::Added { column: column.name.clone(), data_type: column.data_type.clone() });
+        }
+    }
+    changes
+}
+
+/// Told about each drift the watcher detects, for example to invalidate results derived
+/// from the drifted object
+#[async_trait]
+pub trait SchemaDriftListener: Send + Sync {
+    async fn schema_drifted(&self, drift: &SchemaDrift);
+}
+
+/// Recent drift events, served as `system.schema_drift` and appended to the audit log
+#[derive(Debug, Default)]
+pub struct SchemaDriftLog {
+    events: Mutex<VecDeque<SchemaDrift>>,
+    audit_log: Option<PathBuf>,
+}
+
+impl SchemaDriftLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also append each drift to this audit log file as a JSON line
+    pub fn with_audit_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.audit_log = Some(path.into());
+        self
+    }
+
+    pub fn record(&self, drift: SchemaDrift) {
+        eprintln!("{}", drift);
+        if let Some(path) = &self.audit_log {
+            let line = serde_json::json!({ "event": "schema_drift", "drift": &drift });
+            let written = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line));
+            if let Err(e) = written {
+                eprintln!("Failed to write schema drift to audit log {}: {}", path.display(), e);
+            }
+        }
+
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        events.push_back(drift);
+        if events.len() > MAX_DRIFT_EVENTS {
+            events.pop_front();
+        }
+    }
+
+    /// Recorded drift, oldest first
+    pub fn events(&self) -> Vec<SchemaDrift> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    /// The `system.schema_drift` table: one row per changed column
+    pub fn drift_table(&self) -> QueryResult {
+        let column = |name: &str| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true };
+        let columns = vec![
+            column("detected_at"),
+            column("object_type"),
+            column("object"),
+            column("change"),
+            column("column_name"),
+            column("old_type"),
+            column("new_type"),
+        ];
+
+        let data_type = |data_type: &DataType| Value::Text(format!("{:?}", data_type));
+        let mut rows = Vec::new();
+        for drift in self.events() {
+            for change in &drift.changes {
+                let (kind, name, old_type, new_type) = match change {
+                    ColumnChange::Added { column, data_type: to } => ("added", column, Value::Null, data_type(to)),
+                    ColumnChange::Removed { column, data_type: from } => ("removed", column, data_type(from), Value::Null),
+                    ColumnChange::Retyped { column, from, to } => ("retyped", column, data_type(from), data_type(to)),
+                };
+                rows.push(Row::new(vec![
+                    Value::Text(drift.detected_at.to_rfc3339()),
+                    Value::Text(drift.object_type.clone()),
+                    Value::Text(drift.object.clone()),
+                    Value::Text(kind.to_string()),
+                    Value::Text(name.clone()),
+                    old_type,
+                    new_type,
+                ]));
+            }
+        }
+
+        QueryResult {
+            columns,
+            affected_rows: Some(rows.len() as u64),
+            rows,
+            ..Default::default()
+        }
+    }
+}
+
+/// Periodically re-introspects the objects of every registered source and records how
+/// their schemas changed since the previous check. The first check only learns the schemas.
+pub struct SchemaWatcher {
+    dispatcher: Arc<RwLock<dyn Dispatcher>>,
+    log: Arc<SchemaDriftLog>,
+    known: Mutex<HashMap<(String, String), Schema>>,
+    listeners: Vec<Arc<dyn SchemaDriftListener>>,
+}
+
+impl SchemaWatcher {
+    pub fn new(dispatcher: Arc<RwLock<dyn Dispatcher>>, log: Arc<SchemaDriftLog>) -> Self {
+        Self {
+            dispatcher,
+            log,
+            known: Mutex::new(HashMap::new()),
+            listeners: Vec::new(),
+        }
+    }
+
+    pub fn with_listener(mut self, listener: Arc<dyn SchemaDriftListener>) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    /// Introspect all sources once, returning the drift found. Sources and objects that
+    /// cannot be introspected right now are skipped and keep their known schema.
+    pub async fn check(&self) -> Vec<SchemaDrift> {
+        let connectors = {
+            let dispatcher = self.dispatcher.read().await;
+            let mut types = dispatcher.list_available_types();
+            types.sort();
+            types.into_iter()
+                .filter(|object_type| object_type != SYSTEM_OBJECT_TYPE)
+                .filter_map(|object_type| dispatcher.get_connector(&object_type).map(|connector| (object_type, connector)))
+                .collect::<Vec<_>>()
+        };
+
+        let mut drifts = Vec::new();
+        for (object_type, connector) in connectors {
+            let Ok(objects) = connector.list_objects().await else { continue };
+            for object in objects {
+                let Ok(schema) = connector.get_schema(&object).await else { continue };
+                let key = (object_type.clone(), object.clone());
+                let previous = self.known.lock().unwrap_or_else(|e| e.into_inner()).insert(key, schema.clone());
+                let changes = previous.map(|previous| diff_schemas(&previous, &schema)).unwrap_or_default();
+                if !changes.is_empty() {
+                    drifts.push(SchemaDrift { object_type: object_type.clone(), object, detected_at: Utc::now(), changes });
+                }
+            }
+        }
+
+        for drift in &drifts {
+            for listener in &self.listeners {
+                listener.schema_drifted(drift).await;
+            }
+            self.log.record(drift.clone());
+        }
+        drifts
+    }
+
+    /// Check every interval until shutdown is signalled
+    pub fn spawn(self: Arc<Self>, interval: Duration, mut shutdown: broadcast::Receiver<()>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => {
+                        self.check().await;
+                    }
+                    _ = shutdown.recv() => break,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::{Connector, ConnectorCapabilities, ConnectorInitConfig};
+    use crate::engine::DefaultDispatcher;
+    use crate::utils::{error::NirvResult, types::{ConnectorQuery, ConnectorType}};
+
+    fn schema(columns: &[(&str, DataType)]) -> Schema {
+        Schema {
+            name: "users".to_string(),
+            columns: columns.iter()
+                .map(|(name, data_type)| ColumnMetadata { name: name.to_string(), data_type: data_type.clone(), nullable: true })
+                .collect(),
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        }
+    }
+
+    /// Connector with one object whose schema the test changes
+    struct ChangingConnector {
+        schema: Arc<Mutex<Schema>>,
+    }
+
+    #[async_trait]
+    impl Connector for ChangingConnector {
+        async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+            Ok(())
+        }
+        async fn execute_query(&self, _query: ConnectorQuery) -> NirvResult<QueryResult> {
+            Ok(QueryResult::new())
+        }
+        async fn get_schema(&self, _object_name: &str) -> NirvResult<Schema> {
+            Ok(self.schema.lock().unwrap().clone())
+        }
+        async fn disconnect(&mut self) -> NirvResult<()> {
+            Ok(())
+        }
+        fn get_connector_type(&self) -> ConnectorType {
+            ConnectorType::Mock
+        }
+        fn supports_transactions(&self) -> bool {
+            false
+        }
+        fn is_connected(&self) -> bool {
+            true
+        }
+        fn get_capabilities(&self) -> ConnectorCapabilities {
+            ConnectorCapabilities::default()
+        }
+        async fn list_objects(&self) -> NirvResult<Vec<String>> {
+            Ok(vec!["users".to_string()])
+        }
+    }
+
+    #[test]
+    fn test_diff_schemas() {
+        let previous = schema(&[("id", DataType::Integer), ("name", DataType::Text), ("age", DataType::Integer)]);
+        let current = schema(&[("id", DataType::Text), ("name", DataType::Text), ("email", DataType::Text)]);
+        assert_eq!(diff_schemas(&previous, &current), vec![
+            ColumnChange::Retyped { column: "id".to_string(), from: DataType::Integer, to: DataType::Text },
+            ColumnChange::Removed { column: "age".to_string(), data_type: DataType::Integer },
+            ColumnChange::Added { column: "email".to_string(), data_type: DataType::Text },
+        ]);
+        assert!(diff_schemas(&previous, &previous).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_records_drift() {
+        let shared = Arc::new(Mutex::new(schema(&[("id", DataType::Integer)])));
+        let mut dispatcher = DefaultDispatcher::new();
+        dispatcher.register_connector("db", Box::new(ChangingConnector { schema: shared.clone() })).await.unwrap();
+        let audit = tempfile::NamedTempFile::new().unwrap();
+        let log = Arc::new(SchemaDriftLog::new().with_audit_log(audit.path()));
+        let watcher = SchemaWatcher::new(Arc::new(RwLock::new(dispatcher)), log.clone());
+
+        assert!(watcher.check().await.is_empty());
+        *shared.lock().unwrap() = schema(&[("id", DataType::Text), ("email", DataType::Text)]);
+        let drifts = watcher.check().await;
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(drifts[0].to_string(), "Schema drift in db.users: retyped id from Integer to Text, added email (Text)");
+        assert!(watcher.check().await.is_empty());
+
+        let table = log.drift_table();
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[1].values[3], Value::Text("added".to_string()));
+        let audit_line = std::fs::read_to_string(audit.path()).unwrap();
+        assert!(audit_line.contains("\"event\":\"schema_drift\""), "{}", audit_line);
+        assert!(audit_line.contains("\"change\":\"retyped\""), "{}", audit_line);
+    }
+}
@@ -0,0 +1,350 @@
+use regex::Regex;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::engine::client_dialect::ClientDialect;
+use crate::protocol::{ProtocolType, TransactionStatus};
+use crate::utils::{parse_hint_duration, Column, DataSource, DateTrunc, InternalQuery, NirvResult, Predicate, QueryParsingError, SessionTimeZone, SourceUri};
+
+/// Transaction control statements handled by the engine rather than the query parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionControl {
+    Begin,
+    Commit,
+    Rollback,
+}
+
+impl TransactionControl {
+    /// Recognize a transaction control statement, returning None for anything else
+    pub fn parse(sql: &str) -> Option<Self> {
+        let normalized = sql.trim().trim_end_matches(';').trim().to_uppercase();
+        let words: Vec<&str> = normalized.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["BEGIN"] | ["BEGIN", "TRANSACTION" | "TRAN" | "WORK"] | ["START", "TRANSACTION"] => {
+                Some(TransactionControl::Begin)
+            }
+            ["COMMIT"] | ["COMMIT", "TRANSACTION" | "TRAN" | "WORK"] | ["END"] | ["END", "TRANSACTION" | "WORK"] => {
+                Some(TransactionControl::Commit)
+            }
+            ["ROLLBACK"] | ["ROLLBACK", "TRANSACTION" | "TRAN" | "WORK"] | ["ABORT"] => {
+                Some(TransactionControl::Rollback)
+            }
+            _ => None,
+        }
+    }
+
+    /// Command tag reported back to clients
+    pub fn command_tag(&self) -> &'static str {
+        match self {
+            TransactionControl::Begin => "BEGIN",
+            TransactionControl::Commit => "COMMIT",
+            TransactionControl::Rollback => "ROLLBACK",
+        }
+    }
+}
+
+/// `SET TIME ZONE` statements handled by the engine rather than the query parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeZoneSetting {
+    /// Render times in, and truncate dates in, the given zone
+    Set(SessionTimeZone),
+    /// Go back to UTC, with times rendered as sources return them
+    Reset,
+}
+
+impl TimeZoneSetting {
+    /// Recognize `SET TIME ZONE 'zone'`, `SET timezone = 'zone'` (or `TO`), `SET TIME ZONE
+    /// DEFAULT` and `RESET timezone`, returning None for anything else
+    pub fn parse(sql: &str) -> Option<NirvResult<Self>> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| Regex::new(
+            r"(?is)^\s*(?:SET\s+(?:SESSION\s+)?(?:TIME\s+ZONE\s+|TIMEZONE\s*(?:=|\s+TO\s)\s*)(.+?)|RESET\s+(?:TIME\s+ZONE|TIMEZONE))\s*;?\s*$"
+        ).unwrap());
+        let captures = pattern.captures(sql)?;
+        let Some(zone) = captures.get(1) else {
+            return Some(Ok(TimeZoneSetting::Reset));
+        };
+        let zone = zone.as_str().trim_matches(|c| c == '\'' || c == '"');
+        if zone.eq_ignore_ascii_case("DEFAULT") {
+            return Some(Ok(TimeZoneSetting::Reset));
+        }
+        Some(SessionTimeZone::parse(zone).map(TimeZoneSetting::Set))
+    }
+}
+
+/// Cache control statements handled by the engine rather than the query parser
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheControl {
+    /// `SET nirv.cache = off | on | <ttl>` or `RESET nirv.cache`: how long cached responses
+    /// may be when the session's queries read them, zero for off, None for each connector's TTL
+    Set(Option<Duration>),
+    /// `REFRESH SOURCE 'api.users'`: drop what the source's connector cached for it
+    Refresh(DataSource),
+}
+
+impl CacheControl {
+    /// Recognize a cache control statement, returning None for anything else
+    pub fn parse(sql: &str) -> Option<NirvResult<Self>> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| Regex::new(
+            r#"(?is)^\s*(?:SET\s+(?:SESSION\s+)?NIRV\.CACHE\s*(?:=|\s+TO\s)\s*(.+?)|RESET\s+NIRV\.CACHE|REFRESH\s+SOURCE\s+(?:source\s*\(\s*)?['"]([^'"]+)['"]\s*\)?)\s*;?\s*$"#
+        ).unwrap());
+        let captures = pattern.captures(sql)?;
+        if let Some(source) = captures.get(2) {
+            return Some(SourceUri::parse(source.as_str()).map(|uri| CacheControl::Refresh(uri.to_data_source(None))));
+        }
+        let Some(value) = captures.get(1) else {
+            return Some(Ok(CacheControl::Set(None)));
+        };
+        let value = value.as_str().trim_matches(|c| c == '\'' || c == '"');
+        Some(match value.to_ascii_lowercase().as_str() {
+            "off" | "false" => Ok(CacheControl::Set(Some(Duration::ZERO))),
+            "on" | "true" | "default" => Ok(CacheControl::Set(None)),
+            _ => parse_hint_duration(value).map(|ttl| CacheControl::Set(Some(ttl))).ok_or_else(|| QueryParsingError::InvalidSyntax(
+                format!("nirv.cache expects on, off or a duration such as 10m, not '{}'", value)
+            ).into()),
+        })
+    }
+}
+
+/// Give queries without a cache hint or directive of their own the session's cache TTL
+pub fn with_session_cache_ttl(mut query: InternalQuery, ttl: Duration) -> InternalQuery {
+    if query.hints.cache_ttl.is_none() && query.directives.get("cache").is_none() {
+        query.hints.cache_ttl = Some(ttl);
+    }
+    query
+}
+
+/// Give `date_trunc()` columns without a time zone of their own the session's
+pub fn with_session_time_zone(mut query: InternalQuery, time_zone: SessionTimeZone) -> InternalQuery {
+    let in_zone = |name: &mut String| {
+        if let Some(trunc) = DateTrunc::parse(name).filter(|trunc| trunc.time_zone.is_none()) {
+            *name = DateTrunc { time_zone: Some(time_zone), ..trunc }.to_string();
+        }
+    };
+    query.projections.iter_mut().for_each(|Column { name, .. }| in_zone(name));
+    query.predicates.iter_mut().for_each(|Predicate { column, .. }| in_zone(column));
+    query
+}
+
+/// Connector transaction a session is pinned to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionTransaction {
+    /// Data object type whose connector owns the transaction
+    pub object_type: String,
+    /// Connector-assigned transaction identifier
+    pub transaction_id: u64,
+}
+
+/// Per-client session state tracked across statements
+#[derive(Debug, Default)]
+pub struct Session {
+    status: TransactionStatus,
+    transaction: Option<SessionTransaction>,
+    time_zone: Option<SessionTimeZone>,
+    cache_ttl: Option<Duration>,
+    dialect: ClientDialect,
+    user: Option<String>,
+    anonymous: bool,
+}
+
+impl Session {
+    /// Create a new idle session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a session for a client connected through a protocol, whose queries are
+    /// parsed in that protocol's SQL dialect
+    pub fn for_protocol(protocol: &ProtocolType) -> Self {
+        Self { dialect: ClientDialect::for_protocol(protocol), ..Self::default() }
+    }
+
+    /// Run the session's queries for an authenticated user, under the privileges of their roles
+    pub fn with_user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
+
+    /// Run the session's queries for a network client that did not authenticate as a user.
+    /// Unlike a trusted local session it may not run access control statements, nor query
+    /// while access control is on.
+    pub fn anonymous(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+
+    /// Whether the session is a network client's that did not name its user
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous && self.user.is_none()
+    }
+
+    /// User the session's queries run for, if it is not a trusted local session
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// SQL dialect the session's queries are parsed in
+    pub fn dialect(&self) -> ClientDialect {
+        self.dialect
+    }
+
+    /// Current transaction status of the session
+    pub fn status(&self) -> TransactionStatus {
+        self.status
+    }
+
+    /// Check if a transaction block is open (including a failed one)
+    pub fn in_transaction(&self) -> bool {
+        self.status != TransactionStatus::Idle
+    }
+
+    /// Connector transaction the session is pinned to, if any statement has run yet
+    pub fn transaction(&self) -> Option<&SessionTransaction> {
+        self.transaction.as_ref()
+    }
+
+    /// Time zone set with `SET TIME ZONE`, if any
+    pub fn time_zone(&self) -> Option<SessionTimeZone> {
+        self.time_zone
+    }
+
+    /// Apply a `SET TIME ZONE` statement
+    pub fn set_time_zone(&mut self, setting: TimeZoneSetting) {
+        self.time_zone = match setting {
+            TimeZoneSetting::Set(time_zone) => Some(time_zone),
+            TimeZoneSetting::Reset => None,
+        };
+    }
+
+    /// Cache TTL set with `SET nirv.cache`, if any
+    pub fn cache_ttl(&self) -> Option<Duration> {
+        self.cache_ttl
+    }
+
+    pub fn set_cache_ttl(&mut self, ttl: Option<Duration>) {
+        self.cache_ttl = ttl;
+    }
+
+    /// Open a transaction block; the connector is bound by the first statement
+    pub fn begin(&mut self) {
+        self.status = TransactionStatus::InTransaction;
+    }
+
+    /// Pin the open transaction block to a connector transaction
+    pub fn bind_transaction(&mut self, object_type: &str, transaction_id: u64) {
+        self.transaction = Some(SessionTransaction {
+            object_type: object_type.to_string(),
+            transaction_id,
+        });
+    }
+
+    /// Mark the open transaction block as failed
+    pub fn mark_failed(&mut self) {
+        if self.in_transaction() {
+            self.status = TransactionStatus::Failed;
+        }
+    }
+
+    /// Close the transaction block, returning the pinned connector transaction
+    pub fn finish(&mut self) -> Option<SessionTransaction> {
+        self.status = TransactionStatus::Idle;
+        self.transaction.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_control_parse() {
+        assert_eq!(TransactionControl::parse("BEGIN"), Some(TransactionControl::Begin));
+        assert_eq!(TransactionControl::parse("begin transaction;"), Some(TransactionControl::Begin));
+        assert_eq!(TransactionControl::parse("  START TRANSACTION "), Some(TransactionControl::Begin));
+        assert_eq!(TransactionControl::parse("COMMIT"), Some(TransactionControl::Commit));
+        assert_eq!(TransactionControl::parse("end;"), Some(TransactionControl::Commit));
+        assert_eq!(TransactionControl::parse("ROLLBACK WORK"), Some(TransactionControl::Rollback));
+        assert_eq!(TransactionControl::parse("abort"), Some(TransactionControl::Rollback));
+    }
+
+    #[test]
+    fn test_transaction_control_parse_ignores_other_statements() {
+        assert_eq!(TransactionControl::parse("SELECT * FROM source('mock.users')"), None);
+        assert_eq!(TransactionControl::parse("ROLLBACK TO SAVEPOINT sp1"), None);
+        assert_eq!(TransactionControl::parse("START"), None);
+        assert_eq!(TransactionControl::parse(""), None);
+    }
+
+    #[test]
+    fn test_time_zone_setting_parse() {
+        let set = |sql: &str| TimeZoneSetting::parse(sql).map(|setting| setting.unwrap());
+        let plus_two = SessionTimeZone::parse("+02:00").unwrap();
+        assert_eq!(set("SET TIME ZONE '+02:00'"), Some(TimeZoneSetting::Set(plus_two)));
+        assert_eq!(set("set timezone = 'UTC+2';"), Some(TimeZoneSetting::Set(plus_two)));
+        assert_eq!(set("SET SESSION TIMEZONE TO 'utc'"), Some(TimeZoneSetting::Set(SessionTimeZone::Utc)));
+        assert_eq!(set("SET TIME ZONE LOCAL"), Some(TimeZoneSetting::Set(SessionTimeZone::Local)));
+        assert_eq!(set("SET TIME ZONE DEFAULT"), Some(TimeZoneSetting::Reset));
+        assert_eq!(set("RESET timezone"), Some(TimeZoneSetting::Reset));
+        assert!(TimeZoneSetting::parse("SET TIME ZONE 'Mars/Olympus'").unwrap().is_err());
+        assert!(TimeZoneSetting::parse("SET connector_debug = on").is_none());
+        assert!(TimeZoneSetting::parse("SELECT * FROM source('mock.users')").is_none());
+
+        let mut query = InternalQuery::new(crate::utils::QueryOperation::Select);
+        query.projections.push(Column { name: "date_trunc('day', ts)".to_string(), alias: None, source: None });
+        query.projections.push(Column { name: "date_trunc('day', ts, 'UTC')".to_string(), alias: None, source: None });
+        let query = with_session_time_zone(query, plus_two);
+        assert_eq!(query.projections[0].name, "date_trunc('day', ts, '+02:00')");
+        assert_eq!(query.projections[1].name, "date_trunc('day', ts, 'UTC')");
+    }
+
+    #[test]
+    fn test_cache_control_parse() {
+        let parse = |sql: &str| CacheControl::parse(sql).map(|control| control.unwrap());
+        assert_eq!(parse("SET nirv.cache = off;"), Some(CacheControl::Set(Some(Duration::ZERO))));
+        assert_eq!(parse("set NIRV.CACHE to '10m'"), Some(CacheControl::Set(Some(Duration::from_secs(600)))));
+        assert_eq!(parse("SET nirv.cache = on"), Some(CacheControl::Set(None)));
+        assert_eq!(parse("RESET nirv.cache"), Some(CacheControl::Set(None)));
+        let users = DataSource { object_type: "api".to_string(), identifier: "users".to_string(), alias: None };
+        assert_eq!(parse("REFRESH SOURCE 'api.users'"), Some(CacheControl::Refresh(users.clone())));
+        assert_eq!(parse("refresh source source('api.users');"), Some(CacheControl::Refresh(users)));
+        assert!(CacheControl::parse("SET nirv.cache = sometimes").unwrap().is_err());
+        assert!(CacheControl::parse("SET connector_debug = on").is_none());
+
+        let mut query = InternalQuery::new(crate::utils::QueryOperation::Select);
+        query.hints.cache_ttl = Some(Duration::from_secs(60));
+        assert_eq!(with_session_cache_ttl(query, Duration::ZERO).hints.cache_ttl, Some(Duration::from_secs(60)));
+        let query = InternalQuery::new(crate::utils::QueryOperation::Select);
+        assert_eq!(with_session_cache_ttl(query, Duration::ZERO).hints.cache_ttl, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let mut session = Session::new();
+        assert_eq!(session.status(), TransactionStatus::Idle);
+        assert!(!session.in_transaction());
+
+        session.begin();
+        assert_eq!(session.status(), TransactionStatus::InTransaction);
+        assert!(session.transaction().is_none());
+
+        session.bind_transaction("postgres", 7);
+        assert_eq!(session.transaction().unwrap().transaction_id, 7);
+
+        session.mark_failed();
+        assert_eq!(session.status(), TransactionStatus::Failed);
+
+        let transaction = session.finish().unwrap();
+        assert_eq!(transaction.object_type, "postgres");
+        assert_eq!(session.status(), TransactionStatus::Idle);
+        assert!(session.transaction().is_none());
+    }
+
+    #[test]
+    fn test_mark_failed_outside_transaction() {
+        let mut session = Session::new();
+        session.mark_failed();
+        assert_eq!(session.status(), TransactionStatus::Idle);
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{RwLock, Semaphore};
+
+use crate::{
+    engine::{
+        Engine, DefaultDispatcher, DefaultQueryExecutor,
+        QueryParser, DefaultQueryParser,
+        QueryPlanner, DefaultQueryPlanner,
+        UsageTracker, SystemConnector, SYSTEM_OBJECT_TYPE,
+    },
+    connectors::Connector,
+    utils::{
+        config::EngineConfig,
+        error::{NirvError, NirvResult},
+        types::QueryResult,
+    },
+};
+
+/// Limits on what one tenant may use; `None` is unlimited
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TenantQuota {
+    /// Data object types the tenant may register
+    pub max_connectors: Option<usize>,
+    /// Queries the tenant may run at the same time
+    pub max_concurrent_queries: Option<usize>,
+    /// Rows a single query may return
+    pub max_result_rows: Option<usize>,
+}
+
+/// One tenant's engine. Its connectors, and the caches they hold, are visible only to it.
+pub struct Tenant {
+    id: String,
+    engine: Engine,
+    quota: TenantQuota,
+    query_slots: Option<Semaphore>,
+    /// Shared with the other tenants; usage is charged to the tenant and to `tenant/user`
+    usage: Option<Arc<UsageTracker>>,
+}
+
+impl Tenant {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn quota(&self) -> &TenantQuota {
+        &self.quota
+    }
+
+    /// The tenant's engine, for operations not covered by the tenant's quota checks
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Register a connector for this tenant only
+    pub async fn register_connector(&self, object_type: &str, connector: Box<dyn Connector>) -> NirvResult<()> {
+        if let Some(max) = self.quota.max_connectors {
+            let registered = self.engine.list_available_types().await.into_iter()
+                .filter(|object_type| object_type != SYSTEM_OBJECT_TYPE)
+                .count();
+            if registered >= max {
+                return Err(NirvError::QuotaExceeded(
+                    format!("Tenant '{}' may register at most {} connectors", self.id, max)
+                ));
+            }
+        }
+        self.engine.register_connector(object_type, connector).await
+    }
+
+    /// Execute a query against the tenant's sources within its quota
+    pub async fn execute_query(&self, sql: &str) -> NirvResult<QueryResult> {
+        self.execute_for(&[&self.id], sql).await
+    }
+
+    /// Execute a query on behalf of one of the tenant's users, charging its usage to both
+    pub async fn execute_query_as(&self, user: &str, sql: &str) -> NirvResult<QueryResult> {
+        self.execute_for(&[&self.id, &format!("{}/{}", self.id, user)], sql).await
+    }
+
+    async fn execute_for(&self, principals: &[&str], sql: &str) -> NirvResult<QueryResult> {
+        if let Some(usage) = &self.usage {
+            usage.check(principals)?;
+        }
+        let _slot = match &self.query_slots {
+            Some(slots) => Some(slots.try_acquire().map_err(|_| NirvError::QuotaExceeded(format!(
+                "Tenant '{}' already runs {} queries", self.id, self.quota.max_concurrent_queries.unwrap_or_default()
+            )))?),
+            None => None,
+        };
+
+        let result = self.engine.execute_query(sql).await?;
+        if let Some(usage) = &self.usage {
+            usage.record_result(principals, &result);
+        }
+        match self.quota.max_result_rows {
+            Some(max) if result.rows.len() > max => Err(NirvError::QuotaExceeded(format!(
+                "Query for tenant '{}' returned {} rows, more than the {} allowed", self.id, result.rows.len(), max
+            ))),
+            _ => Ok(result),
+        }
+    }
+
+    pub async fn list_available_types(&self) -> Vec<String> {
+        self.engine.list_available_types().await
+    }
+}
+
+/// Creates and removes per-tenant engines. Tenants share the parser and planner, which hold
+/// no tenant state, so creating one only allocates its own dispatcher and executor.
+pub struct TenantManager {
+    /// Settings every tenant engine starts from
+    config: EngineConfig,
+    query_parser: Arc<dyn QueryParser>,
+    query_planner: Arc<dyn QueryPlanner>,
+    default_quota: TenantQuota,
+    usage: Option<Arc<UsageTracker>>,
+    tenants: RwLock<HashMap<String, Arc<Tenant>>>,
+}
+
+impl TenantManager {
+    /// Create a manager whose tenants use the given configuration. Tenant engines are
+    /// embedded, so protocol adapters and the HTTP API in it are ignored, and its quotas
+    /// are tracked once across all tenants.
+    pub fn new(mut config: EngineConfig) -> NirvResult<Self> {
+        config.protocol_adapters.clear();
+        config.http_api = None;
+        let usage = config.quotas.take().map(|quotas| Arc::new(UsageTracker::from_config(&quotas)));
+        Ok(Self {
+            config,
+            query_parser: Arc::new(DefaultQueryParser::new()?),
+            query_planner: Arc::new(DefaultQueryPlanner::new()),
+            default_quota: TenantQuota::default(),
+            usage,
+            tenants: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Track usage of tenants created from now on with the given tracker
+    pub fn with_usage_tracker(mut self, tracker: Arc<UsageTracker>) -> Self {
+        self.usage = Some(tracker);
+        self
+    }
+
+    /// Quota for tenants created without one
+    pub fn with_default_quota(mut self, quota: TenantQuota) -> Self {
+        self.default_quota = quota;
+        self
+    }
+
+    /// Create a tenant with the default quota
+    pub async fn create_tenant(&self, id: &str) -> NirvResult<Arc<Tenant>> {
+        self.create_tenant_with_quota(id, self.default_quota.clone()).await
+    }
+
+    pub async fn create_tenant_with_quota(&self, id: &str, quota: TenantQuota) -> NirvResult<Arc<Tenant>> {
+        if id.is_empty() {
+            return Err(NirvError::Configuration("Tenant id must not be empty".to_string()));
+        }
+        let mut tenants = self.tenants.write().await;
+        if tenants.contains_key(id) {
+            return Err(NirvError::Configuration(format!("Tenant '{}' already exists", id)));
+        }
+
+        let engine = Engine::with_components(
+            self.config.clone(),
+            self.query_parser.clone(),
+            self.query_planner.clone(),
+            Arc::new(RwLock::new(
+                DefaultQueryExecutor::new().with_identifier_case(self.config.dispatcher.identifier_case)
+            )),
+            Arc::new(RwLock::new(DefaultDispatcher::new())),
+        );
+        // Each tenant sees only its own usage in `system.usage`
+        if let Some(usage) = &self.usage {
+            let mut connector = SystemConnector::new(usage.clone()).with_scope(id);
+            if let Some(log) = engine.lineage_log() {
+                connector = connector.with_lineage(log.clone());
+            }
+            engine.register_connector(SYSTEM_OBJECT_TYPE, Box::new(connector)).await?;
+        }
+        let tenant = Arc::new(Tenant {
+            id: id.to_string(),
+            engine,
+            query_slots: quota.max_concurrent_queries.map(Semaphore::new),
+            quota,
+            usage: self.usage.clone(),
+        });
+        tenants.insert(id.to_string(), tenant.clone());
+        Ok(tenant)
+    }
+
+    pub async fn get_tenant(&self, id: &str) -> Option<Arc<Tenant>> {
+        self.tenants.read().await.get(id).cloned()
+    }
+
+    /// Remove a tenant; its engine is dropped once queries still holding it finish
+    pub async fn remove_tenant(&self, id: &str) -> Option<Arc<Tenant>> {
+        self.tenants.write().await.remove(id)
+    }
+
+    /// Ids of all tenants, sorted
+    pub async fn tenant_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.tenants.read().await.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::{ConnectorInitConfig, MockConnector};
+    use crate::utils::{config::UsageLimits, types::Value};
+
+    async fn mock_connector() -> Box<dyn Connector> {
+        let mut connector = MockConnector::new();
+        connector.connect(ConnectorInitConfig::new()).await.unwrap();
+        Box::new(connector)
+    }
+
+    #[tokio::test]
+    async fn test_tenants_do_not_see_each_others_sources() {
+        let manager = TenantManager::new(EngineConfig::default()).unwrap();
+        let acme = manager.create_tenant("acme").await.unwrap();
+        let globex = manager.create_tenant("globex").await.unwrap();
+        acme.register_connector("mock", mock_connector().await).await.unwrap();
+
+        let result = acme.execute_query("SELECT * FROM source('mock.users')").await.unwrap();
+        assert!(!result.rows.is_empty());
+        assert!(globex.list_available_types().await.is_empty());
+        assert!(globex.execute_query("SELECT * FROM source('mock.users')").await.is_err());
+
+        assert!(manager.create_tenant("acme").await.is_err());
+        assert_eq!(manager.tenant_ids().await, vec!["acme".to_string(), "globex".to_string()]);
+        assert!(manager.remove_tenant("acme").await.is_some());
+        assert!(manager.get_tenant("acme").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_quotas() {
+        let manager = TenantManager::new(EngineConfig::default()).unwrap()
+            .with_default_quota(TenantQuota { max_connectors: Some(1), max_result_rows: Some(2), ..Default::default() });
+        let tenant = manager.create_tenant("acme").await.unwrap();
+        tenant.register_connector("mock", mock_connector().await).await.unwrap();
+
+        let error = tenant.register_connector("other", mock_connector().await).await.unwrap_err();
+        assert!(matches!(error, NirvError::QuotaExceeded(_)));
+        let error = tenant.execute_query("SELECT * FROM source('mock.users')").await.unwrap_err();
+        assert!(matches!(error, NirvError::QuotaExceeded(_)));
+        assert_eq!(tenant.execute_query("SELECT * FROM source('mock.users') LIMIT 2").await.unwrap().rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_usage_quotas_and_system_table() {
+        let tracker = Arc::new(UsageTracker::new(UsageLimits { rows_per_hour: Some(3), ..Default::default() }));
+        let manager = TenantManager::new(EngineConfig::default()).unwrap().with_usage_tracker(tracker.clone());
+        let acme = manager.create_tenant("acme").await.unwrap();
+        let globex = manager.create_tenant("globex").await.unwrap();
+        acme.register_connector("mock", mock_connector().await).await.unwrap();
+        globex.register_connector("mock", mock_connector().await).await.unwrap();
+
+        acme.execute_query_as("alice", "SELECT * FROM source('mock.users')").await.unwrap();
+        assert_eq!(tracker.usage("acme").rows_last_hour, 3);
+        assert_eq!(tracker.usage("acme/alice").queries_last_hour, 1);
+        let error = acme.execute_query("SELECT * FROM source('mock.users')").await.unwrap_err();
+        assert!(matches!(error, NirvError::QuotaExceeded(_)));
+        globex.execute_query("SELECT * FROM source('mock.users') LIMIT 1").await.unwrap();
+
+        // The system table is read without any quota left, and shows only the tenant's principals
+        let usage = globex.engine().execute_query("SELECT principal, rows_fetched FROM source('system.usage') WHERE window = 'hour'").await.unwrap();
+        assert_eq!(usage.rows.len(), 1);
+        assert_eq!(usage.rows[0].values[0], Value::Text("globex".to_string()));
+    }
+}
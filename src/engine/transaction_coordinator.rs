@@ -0,0 +1,569 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::connectors::Connector;
+use crate::utils::{
+    types::{ConnectorQuery, QueryResult},
+    error::{NirvResult, NirvError, DispatcherError},
+};
+
+/// State of a distributed transaction as recorded in the recovery journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalState {
+    /// Participants are executing and preparing
+    Preparing,
+    /// Every participant prepared; the decision to commit has been made
+    Committing,
+    /// Every participant committed
+    Committed,
+    /// The transaction was rolled back before the commit decision
+    Aborted,
+}
+
+/// Snapshot of a distributed transaction; the latest entry per global id wins
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub global_id: String,
+    pub state: JournalState,
+    /// Participant names in the order they were enlisted
+    pub participants: Vec<String>,
+    /// Participants that have prepared
+    pub prepared: Vec<String>,
+    /// Participants that have committed
+    pub committed: Vec<String>,
+}
+
+impl JournalEntry {
+    fn new(global_id: &str, participants: Vec<String>) -> Self {
+        Self {
+            global_id: global_id.to_string(),
+            state: JournalState::Preparing,
+            participants,
+            prepared: Vec::new(),
+            committed: Vec::new(),
+        }
+    }
+
+    /// Check if the transaction still needs recovery work
+    pub fn is_pending(&self) -> bool {
+        matches!(self.state, JournalState::Preparing | JournalState::Committing)
+    }
+}
+
+/// Append-only JSON lines journal of distributed transaction states
+#[derive(Debug)]
+pub struct RecoveryJournal {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl RecoveryJournal {
+    /// Open (or lazily create) a journal at the given path
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Durably append an entry to the journal
+    pub fn record(&self, entry: &JournalEntry) -> NirvResult<()> {
+        let _guard = self.write_lock.lock()
+            .map_err(|_| NirvError::Internal("Recovery journal lock poisoned".to_string()))?;
+
+        let line = serde_json::to_string(entry)
+            .map_err(|e| NirvError::Internal(format!("Failed to serialize journal entry: {}", e)))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| NirvError::Internal(format!("Failed to open recovery journal {}: {}", self.path.display(), e)))?;
+
+        writeln!(file, "{}", line)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| NirvError::Internal(format!("Failed to write recovery journal {}: {}", self.path.display(), e)))?;
+
+        Ok(())
+    }
+
+    /// Read the latest state of every transaction in the journal
+    pub fn entries(&self) -> NirvResult<Vec<JournalEntry>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(NirvError::Internal(
+                format!("Failed to open recovery journal {}: {}", self.path.display(), e)
+            )),
+        };
+
+        let mut order = Vec::new();
+        let mut latest: HashMap<String, JournalEntry> = HashMap::new();
+
+        for line in BufReader::new(file).lines() {
+            let line = line
+                .map_err(|e| NirvError::Internal(format!("Failed to read recovery journal: {}", e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A torn final line from a crash mid-write carries no decision; skip it
+            let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) else {
+                continue;
+            };
+            if !latest.contains_key(&entry.global_id) {
+                order.push(entry.global_id.clone());
+            }
+            latest.insert(entry.global_id.clone(), entry);
+        }
+
+        Ok(order.into_iter().filter_map(|id| latest.remove(&id)).collect())
+    }
+
+    /// Transactions that were interrupted before reaching a final state
+    pub fn pending(&self) -> NirvResult<Vec<JournalEntry>> {
+        Ok(self.entries()?.into_iter().filter(|e| e.is_pending()).collect())
+    }
+}
+
+/// A connector enlisted in a distributed write along with the statements to run on it
+pub struct TransactionParticipant<'a> {
+    pub name: String,
+    pub connector: &'a dyn Connector,
+    pub queries: Vec<ConnectorQuery>,
+}
+
+/// Outcome of replaying the recovery journal
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecoveryReport {
+    /// Transactions whose commit was resumed to completion
+    pub committed: Vec<String>,
+    /// Transactions rolled back because no commit decision was recorded
+    pub aborted: Vec<String>,
+    /// Transactions that still could not be resolved, with the reason
+    pub unresolved: Vec<(String, String)>,
+}
+
+/// Best-effort two-phase commit coordinator for writes spanning transactional connectors
+#[derive(Debug)]
+pub struct TwoPhaseCoordinator {
+    journal: RecoveryJournal,
+    sequence: AtomicU64,
+}
+
+impl TwoPhaseCoordinator {
+    /// Create a coordinator journaling to the given path
+    pub fn new<P: AsRef<Path>>(journal_path: P) -> Self {
+        Self {
+            journal: RecoveryJournal::new(journal_path),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Access the recovery journal
+    pub fn journal(&self) -> &RecoveryJournal {
+        &self.journal
+    }
+
+    /// Generate a global transaction identifier
+    fn next_global_id(&self) -> String {
+        format!(
+            "nirv_{}_{}_{}",
+            chrono::Utc::now().timestamp_millis(),
+            std::process::id(),
+            self.sequence.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    /// Run each participant's statements in its own transaction and commit them atomically
+    /// as far as the backends allow
+    pub async fn execute(&self, participants: Vec<TransactionParticipant<'_>>) -> NirvResult<Vec<QueryResult>> {
+        let global_id = self.next_global_id();
+        let mut entry = JournalEntry::new(
+            &global_id,
+            participants.iter().map(|p| p.name.clone()).collect(),
+        );
+        self.journal.record(&entry)?;
+
+        // Phase 0: execute every participant's statements inside a local transaction
+        let mut open: Vec<(&TransactionParticipant<'_>, u64)> = Vec::new();
+        let mut results = Vec::new();
+        for participant in &participants {
+            let outcome = self.execute_participant(participant, &mut open, &mut results).await;
+            if let Err(e) = outcome {
+                self.abort(&mut entry, &open, &[]).await?;
+                return Err(e);
+            }
+        }
+
+        // Phase 1: prepare every participant
+        let mut prepared: Vec<&TransactionParticipant<'_>> = Vec::new();
+        for (index, (participant, transaction_id)) in open.iter().enumerate() {
+            if let Err(e) = participant.connector.prepare_transaction(*transaction_id, &global_id).await {
+                self.abort(&mut entry, &open[index..], &prepared).await?;
+                return Err(DispatcherError::TransactionCoordinationFailed(format!(
+                    "Prepare failed on '{}' for transaction {}: {}", participant.name, global_id, e
+                )).into());
+            }
+            prepared.push(participant);
+            entry.prepared.push(participant.name.clone());
+            self.journal.record(&entry)?;
+        }
+
+        // Commit point: once recorded, recovery must drive every participant to commit
+        entry.state = JournalState::Committing;
+        self.journal.record(&entry)?;
+
+        // Phase 2: commit every participant
+        for participant in prepared {
+            if let Err(e) = participant.connector.commit_prepared(&global_id).await {
+                return Err(DispatcherError::TransactionCoordinationFailed(format!(
+                    "Transaction {} partially committed ({:?} of {:?}); commit on '{}' failed: {}. \
+                     Run recovery to resume the commit",
+                    global_id, entry.committed, entry.participants, participant.name, e
+                )).into());
+            }
+            entry.committed.push(participant.name.clone());
+            self.journal.record(&entry)?;
+        }
+
+        entry.state = JournalState::Committed;
+        self.journal.record(&entry)?;
+
+        Ok(results)
+    }
+
+    /// Begin a participant's transaction and run its statements
+    async fn execute_participant<'p, 'c>(
+        &self,
+        participant: &'p TransactionParticipant<'c>,
+        open: &mut Vec<(&'p TransactionParticipant<'c>, u64)>,
+        results: &mut Vec<QueryResult>,
+    ) -> NirvResult<()> {
+        if !participant.connector.supports_transactions() {
+            return Err(DispatcherError::TransactionCoordinationFailed(
+                format!("Participant '{}' does not support transactions", participant.name)
+            ).into());
+        }
+
+        let transaction_id = participant.connector.begin_transaction().await?;
+        open.push((participant, transaction_id));
+
+        for query in &participant.queries {
+            results.push(participant.connector.execute_in_transaction(transaction_id, query.clone()).await?);
+        }
+        Ok(())
+    }
+
+    /// Roll back open and prepared participants and record the abort
+    async fn abort(
+        &self,
+        entry: &mut JournalEntry,
+        open: &[(&TransactionParticipant<'_>, u64)],
+        prepared: &[&TransactionParticipant<'_>],
+    ) -> NirvResult<()> {
+        // Rollback failures are tolerated; recovery retries prepared participants
+        for (participant, transaction_id) in open {
+            let _ = participant.connector.rollback_transaction(*transaction_id).await;
+        }
+        for participant in prepared {
+            let _ = participant.connector.rollback_prepared(&entry.global_id).await;
+        }
+
+        entry.state = JournalState::Aborted;
+        self.journal.record(entry)
+    }
+
+    /// Resolve transactions left pending by a crash: resume commits past the commit point
+    /// and roll back everything else
+    pub async fn recover(&self, connectors: &HashMap<String, &dyn Connector>) -> NirvResult<RecoveryReport> {
+        let mut report = RecoveryReport::default();
+
+        for mut entry in self.journal.pending()? {
+            let committing = entry.state == JournalState::Committing;
+            let mut failure = None;
+
+            for name in entry.participants.clone() {
+                // Unprepared participants were rolled back when their session ended
+                let needs_resolution = if committing {
+                    !entry.committed.contains(&name)
+                } else {
+                    entry.prepared.contains(&name)
+                };
+                if !needs_resolution {
+                    continue;
+                }
+
+                let Some(connector) = connectors.get(&name) else {
+                    failure = Some(format!("participant '{}' is not available", name));
+                    break;
+                };
+
+                if committing {
+                    match connector.commit_prepared(&entry.global_id).await {
+                        Ok(()) => {
+                            entry.committed.push(name.clone());
+                            self.journal.record(&entry)?;
+                        }
+                        Err(e) => {
+                            failure = Some(format!("commit on '{}' failed: {}", name, e));
+                            break;
+                        }
+                    }
+                } else if let Err(e) = connector.rollback_prepared(&entry.global_id).await {
+                    failure = Some(format!("rollback on '{}' failed: {}", name, e));
+                    break;
+                }
+            }
+
+            match failure {
+                Some(reason) => report.unresolved.push((entry.global_id.clone(), reason)),
+                None if committing => {
+                    entry.state = JournalState::Committed;
+                    self.journal.record(&entry)?;
+                    report.committed.push(entry.global_id.clone());
+                }
+                None => {
+                    entry.state = JournalState::Aborted;
+                    self.journal.record(&entry)?;
+                    report.aborted.push(entry.global_id.clone());
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::connectors::{ConnectorCapabilities, ConnectorInitConfig};
+    use crate::utils::types::{ConnectorType, InternalQuery, QueryOperation, Schema};
+    use std::sync::Arc;
+
+    /// Connector that records two-phase commit calls and can fail on demand
+    struct TwoPhaseConnector {
+        log: Arc<Mutex<Vec<String>>>,
+        name: &'static str,
+        fail_prepare: bool,
+        fail_commit: bool,
+    }
+
+    impl TwoPhaseConnector {
+        fn new(name: &'static str, log: Arc<Mutex<Vec<String>>>) -> Self {
+            Self { log, name, fail_prepare: false, fail_commit: false }
+        }
+
+        fn record(&self, action: &str) {
+            self.log.lock().unwrap().push(format!("{} {}", self.name, action));
+        }
+
+        fn failure(&self, action: &str) -> NirvError {
+            NirvError::Internal(format!("{} {} failed", self.name, action))
+        }
+    }
+
+    #[async_trait]
+    impl Connector for TwoPhaseConnector {
+        async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> { Ok(()) }
+        async fn execute_query(&self, _query: ConnectorQuery) -> NirvResult<QueryResult> { Ok(QueryResult::new()) }
+        async fn get_schema(&self, _object_name: &str) -> NirvResult<Schema> {
+            Err(NirvError::Internal("no schema".to_string()))
+        }
+        async fn disconnect(&mut self) -> NirvResult<()> { Ok(()) }
+        fn get_connector_type(&self) -> ConnectorType { ConnectorType::Mock }
+        fn supports_transactions(&self) -> bool { true }
+        fn is_connected(&self) -> bool { true }
+        fn get_capabilities(&self) -> ConnectorCapabilities { ConnectorCapabilities::default() }
+
+        async fn begin_transaction(&self) -> NirvResult<u64> {
+            self.record("begin");
+            Ok(1)
+        }
+
+        async fn execute_in_transaction(&self, _transaction_id: u64, _query: ConnectorQuery) -> NirvResult<QueryResult> {
+            self.record("execute");
+            Ok(QueryResult::new())
+        }
+
+        async fn rollback_transaction(&self, _transaction_id: u64) -> NirvResult<()> {
+            self.record("rollback");
+            Ok(())
+        }
+
+        async fn prepare_transaction(&self, _transaction_id: u64, _global_id: &str) -> NirvResult<()> {
+            if self.fail_prepare {
+                return Err(self.failure("prepare"));
+            }
+            self.record("prepare");
+            Ok(())
+        }
+
+        async fn commit_prepared(&self, _global_id: &str) -> NirvResult<()> {
+            if self.fail_commit {
+                return Err(self.failure("commit"));
+            }
+            self.record("commit");
+            Ok(())
+        }
+
+        async fn rollback_prepared(&self, _global_id: &str) -> NirvResult<()> {
+            self.record("rollback_prepared");
+            Ok(())
+        }
+    }
+
+    fn write_query() -> ConnectorQuery {
+        ConnectorQuery {
+            connector_type: ConnectorType::Mock,
+            query: InternalQuery::new(QueryOperation::Insert),
+            connection_params: HashMap::new(),
+        }
+    }
+
+    fn participant<'a>(name: &str, connector: &'a TwoPhaseConnector) -> TransactionParticipant<'a> {
+        TransactionParticipant {
+            name: name.to_string(),
+            connector,
+            queries: vec![write_query()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_phase_commit_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let coordinator = TwoPhaseCoordinator::new(dir.path().join("2pc.journal"));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let pg = TwoPhaseConnector::new("pg", log.clone());
+        let mssql = TwoPhaseConnector::new("mssql", log.clone());
+
+        let results = coordinator.execute(vec![participant("pg", &pg), participant("mssql", &mssql)]).await.unwrap();
+        assert_eq!(results.len(), 2);
+
+        assert_eq!(*log.lock().unwrap(), vec![
+            "pg begin", "pg execute", "mssql begin", "mssql execute",
+            "pg prepare", "mssql prepare", "pg commit", "mssql commit",
+        ]);
+
+        let entries = coordinator.journal().entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].state, JournalState::Committed);
+        assert_eq!(entries[0].committed, vec!["pg", "mssql"]);
+        assert!(coordinator.journal().pending().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_two_phase_prepare_failure_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let coordinator = TwoPhaseCoordinator::new(dir.path().join("2pc.journal"));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let pg = TwoPhaseConnector::new("pg", log.clone());
+        let mut mssql = TwoPhaseConnector::new("mssql", log.clone());
+        mssql.fail_prepare = true;
+
+        let result = coordinator.execute(vec![participant("pg", &pg), participant("mssql", &mssql)]).await;
+        assert!(matches!(
+            result,
+            Err(NirvError::Dispatcher(DispatcherError::TransactionCoordinationFailed(_)))
+        ));
+
+        let log = log.lock().unwrap().clone();
+        assert!(log.contains(&"pg rollback_prepared".to_string()));
+        assert!(log.contains(&"mssql rollback".to_string()));
+        assert!(!log.iter().any(|l| l.ends_with(" commit")));
+
+        let entries = coordinator.journal().entries().unwrap();
+        assert_eq!(entries[0].state, JournalState::Aborted);
+    }
+
+    #[tokio::test]
+    async fn test_partial_commit_is_detectable_and_resumable() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal_path = dir.path().join("2pc.journal");
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let pg = TwoPhaseConnector::new("pg", log.clone());
+        let mut mssql = TwoPhaseConnector::new("mssql", log.clone());
+        mssql.fail_commit = true;
+
+        let coordinator = TwoPhaseCoordinator::new(&journal_path);
+        let error = coordinator.execute(vec![participant("pg", &pg), participant("mssql", &mssql)]).await.unwrap_err();
+        assert!(error.to_string().contains("partially committed"));
+
+        let pending = coordinator.journal().pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].state, JournalState::Committing);
+        assert_eq!(pending[0].committed, vec!["pg"]);
+
+        // Simulate a restart with the backend healthy again
+        mssql.fail_commit = false;
+        let restarted = TwoPhaseCoordinator::new(&journal_path);
+        let mut connectors: HashMap<String, &dyn Connector> = HashMap::new();
+        connectors.insert("pg".to_string(), &pg);
+        connectors.insert("mssql".to_string(), &mssql);
+
+        let report = restarted.recover(&connectors).await.unwrap();
+        assert_eq!(report.committed, vec![pending[0].global_id.clone()]);
+        assert!(report.unresolved.is_empty());
+        assert!(restarted.journal().pending().unwrap().is_empty());
+        assert_eq!(log.lock().unwrap().last().unwrap(), "mssql commit");
+    }
+
+    #[tokio::test]
+    async fn test_recovery_rolls_back_undecided_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = RecoveryJournal::new(dir.path().join("2pc.journal"));
+        let mut entry = JournalEntry::new("nirv_test_1", vec!["pg".to_string(), "mssql".to_string()]);
+        entry.prepared.push("pg".to_string());
+        journal.record(&entry).unwrap();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let pg = TwoPhaseConnector::new("pg", log.clone());
+        let mut connectors: HashMap<String, &dyn Connector> = HashMap::new();
+        connectors.insert("pg".to_string(), &pg);
+
+        let coordinator = TwoPhaseCoordinator::new(journal.path());
+        let report = coordinator.recover(&connectors).await.unwrap();
+        assert_eq!(report.aborted, vec!["nirv_test_1".to_string()]);
+        assert_eq!(*log.lock().unwrap(), vec!["pg rollback_prepared"]);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_reports_missing_participant() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = RecoveryJournal::new(dir.path().join("2pc.journal"));
+        let mut entry = JournalEntry::new("nirv_test_2", vec!["pg".to_string()]);
+        entry.state = JournalState::Committing;
+        journal.record(&entry).unwrap();
+
+        let coordinator = TwoPhaseCoordinator::new(journal.path());
+        let report = coordinator.recover(&HashMap::new()).await.unwrap();
+        assert_eq!(report.unresolved.len(), 1);
+        assert_eq!(coordinator.journal().pending().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_journal_skips_torn_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2pc.journal");
+        let journal = RecoveryJournal::new(&path);
+        journal.record(&JournalEntry::new("nirv_test_3", vec!["pg".to_string()])).unwrap();
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"global_id\":\"nirv_te").unwrap();
+
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].global_id, "nirv_test_3");
+    }
+}
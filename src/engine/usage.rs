@@ -0,0 +1,388 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    connectors::{Connector, ConnectorInitConfig, ConnectorCapabilities},
+    engine::{LineageLog, SchemaDriftLog},
+    utils::{
+        config::{QuotaConfig, UsageLimits},
+        error::{ConnectorError, NirvError, NirvResult},
+        identifier::IdentifierCase,
+        types::{ColumnMetadata, ConnectorQuery, ConnectorType, DataType, Predicate, QueryResult, Row, Schema, Value},
+    },
+};
+
+/// Data object type of the connector serving the system tables
+pub const SYSTEM_OBJECT_TYPE: &str = "system";
+
+/// Rows and bytes fetched by one user or tenant over the last hour and day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub queries_last_hour: u64,
+    pub rows_last_hour: u64,
+    pub bytes_last_hour: u64,
+    pub queries_last_day: u64,
+    pub rows_last_day: u64,
+    pub bytes_last_day: u64,
+}
+
+/// One query's fetch volume
+#[derive(Debug, Clone, Copy)]
+struct UsageRecord {
+    at: DateTime<Utc>,
+    rows: u64,
+    bytes: u64,
+}
+
+/// Rows and bytes fetched from sources per user or tenant, checked against rolling hourly
+/// and daily limits. A query is refused once a limit has been reached; the query that
+/// crosses a limit still completes, as its volume is only known afterwards.
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    default_limits: UsageLimits,
+    limits: HashMap<String, UsageLimits>,
+    records: Mutex<HashMap<String, VecDeque<UsageRecord>>>,
+}
+
+impl UsageTracker {
+    /// A tracker with the same limits for everyone
+    pub fn new(default_limits: UsageLimits) -> Self {
+        Self {
+            default_limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_config(config: &QuotaConfig) -> Self {
+        Self {
+            default_limits: config.default.clone(),
+            limits: config.principals.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Limits for a user or tenant, replacing the defaults
+    pub fn with_limits(mut self, principal: &str, limits: UsageLimits) -> Self {
+        self.limits.insert(principal.to_string(), limits);
+        self
+    }
+
+    pub fn limits(&self, principal: &str) -> &UsageLimits {
+        self.limits.get(principal).unwrap_or(&self.default_limits)
+    }
+
+    /// Refuse a query when any of the principals has reached a limit
+    pub fn check(&self, principals: &[&str]) -> NirvResult<()> {
+        self.check_at(principals, Utc::now())
+    }
+
+    pub fn check_at(&self, principals: &[&str], now: DateTime<Utc>) -> NirvResult<()> {
+        for principal in principals {
+            let usage = self.usage_at(principal, now);
+            let limits = self.limits(principal);
+            let exceeded = [
+                ("rows per hour", usage.rows_last_hour, limits.rows_per_hour),
+                ("bytes per hour", usage.bytes_last_hour, limits.bytes_per_hour),
+                ("rows per day", usage.rows_last_day, limits.rows_per_day),
+                ("bytes per day", usage.bytes_last_day, limits.bytes_per_day),
+            ];
+            if let Some((name, used, Some(limit))) = exceeded.into_iter().find(|(_, used, limit)| limit.is_some_and(|limit| *used >= limit)) {
+                return Err(NirvError::QuotaExceeded(
+                    format!("'{}' has used {} of {} {}", principal, used, limit, name)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Charge the rows and bytes a result fetched from its sources to each principal
+    pub fn record_result(&self, principals: &[&str], result: &QueryResult) {
+        let (rows, bytes) = match &result.stats {
+            Some(stats) if !stats.sources.is_empty() => (stats.rows_scanned(), stats.bytes_fetched()),
+            _ => (result.rows.len() as u64, result.estimated_bytes()),
+        };
+        self.record_at(principals, Utc::now(), rows, bytes);
+    }
+
+    pub fn record_at(&self, principals: &[&str], at: DateTime<Utc>, rows: u64, bytes: u64) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        for principal in principals {
+            let history = records.entry(principal.to_string()).or_default();
+            history.push_back(UsageRecord { at, rows, bytes });
+            // Only the daily window looks back more than an hour
+            while history.front().is_some_and(|record| at - record.at > Duration::days(1)) {
+                history.pop_front();
+            }
+        }
+    }
+
+    pub fn usage(&self, principal: &str) -> Usage {
+        self.usage_at(principal, Utc::now())
+    }
+
+    pub fn usage_at(&self, principal: &str, now: DateTime<Utc>) -> Usage {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let mut usage = Usage::default();
+        for record in records.get(principal).into_iter().flatten() {
+            let age = now - record.at;
+            if age <= Duration::days(1) {
+                usage.queries_last_day += 1;
+                usage.rows_last_day += record.rows;
+                usage.bytes_last_day += record.bytes;
+            }
+            if age <= Duration::hours(1) {
+                usage.queries_last_hour += 1;
+                usage.rows_last_hour += record.rows;
+                usage.bytes_last_hour += record.bytes;
+            }
+        }
+        usage
+    }
+
+    /// Principals with recorded usage, sorted
+    pub fn principals(&self) -> Vec<String> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let mut principals: Vec<String> = records.keys().cloned().collect();
+        principals.sort();
+        principals
+    }
+
+    /// The `system.usage` table: one row per principal and window with usage and limits
+    pub fn usage_table(&self, visible: impl Fn(&str) -> bool) -> QueryResult {
+        let column = |name: &str, data_type: DataType| ColumnMetadata { name: name.to_string(), data_type, nullable: true };
+        let columns = vec![
+            column("principal", DataType::Text),
+            column("window", DataType::Text),
+            column("queries", DataType::Integer),
+            column("rows_fetched", DataType::Integer),
+            column("bytes_fetched", DataType::Integer),
+            column("row_limit", DataType::Integer),
+            column("byte_limit", DataType::Integer),
+        ];
+
+        let now = Utc::now();
+        let limit = |limit: Option<u64>| limit.map_or(Value::Null, |limit| Value::Integer(limit as i64));
+        let mut rows = Vec::new();
+        for principal in self.principals().into_iter().filter(|principal| visible(principal)) {
+            let usage = self.usage_at(&principal, now);
+            let limits = self.limits(&principal);
+            for (window, queries, used_rows, used_bytes, row_limit, byte_limit) in [
+                ("hour", usage.queries_last_hour, usage.rows_last_hour, usage.bytes_last_hour, limits.rows_per_hour, limits.bytes_per_hour),
+                ("day", usage.queries_last_day, usage.rows_last_day, usage.bytes_last_day, limits.rows_per_day, limits.bytes_per_day),
+            ] {
+                rows.push(Row::new(vec![
+                    Value::Text(principal.clone()),
+                    Value::Text(window.to_string()),
+                    Value::Integer(queries as i64),
+                    Value::Integer(used_rows as i64),
+                    Value::Integer(used_bytes as i64),
+                    limit(row_limit),
+                    limit(byte_limit),
+                ]));
+            }
+        }
+
+        QueryResult {
+            columns,
+            affected_rows: Some(rows.len() as u64),
+            rows,
+            ..Default::default()
+        }
+    }
+}
+
+/// Connector serving the engine's system tables, registered as the `system` type.
+/// `system.usage` reports usage tracked for the principals in scope,
+/// `system.schema_drift` the drift detected in source schemas when they are watched, and
+/// `system.lineage` the column lineage of recent queries when it is recorded.
+pub struct SystemConnector {
+    usage: Arc<UsageTracker>,
+    schema_drift: Option<Arc<SchemaDriftLog>>,
+    lineage: Option<Arc<LineageLog>>,
+    /// Tenant whose principals are visible; everyone when unset
+    scope: Option<String>,
+    connected: bool,
+}
+
+impl SystemConnector {
+    pub fn new(usage: Arc<UsageTracker>) -> Self {
+        Self {
+            usage,
+            schema_drift: None,
+            lineage: None,
+            scope: None,
+            connected: true,
+        }
+    }
+
+    /// Serve the drift recorded in the log as `system.schema_drift`
+    pub fn with_schema_drift(mut self, log: Arc<SchemaDriftLog>) -> Self {
+        self.schema_drift = Some(log);
+        self
+    }
+
+    /// Serve the lineage recorded in the log as `system.lineage`
+    pub fn with_lineage(mut self, log: Arc<LineageLog>) -> Self {
+        self.lineage = Some(log);
+        self
+    }
+
+    /// Show only the tenant and its users (`tenant/user`)
+    pub fn with_scope(mut self, tenant: &str) -> Self {
+        self.scope = Some(tenant.to_string());
+        self
+    }
+
+    fn is_visible(&self, principal: &str) -> bool {
+        match &self.scope {
+            Some(tenant) => principal == tenant || principal.strip_prefix(tenant.as_str()).is_some_and(|rest| rest.starts_with('/')),
+            None => true,
+        }
+    }
+
+    fn table(&self, name: &str) -> NirvResult<QueryResult> {
+        match (name.to_lowercase().as_str(), &self.schema_drift, &self.lineage) {
+            ("usage", _, _) => Ok(self.usage.usage_table(|principal| self.is_visible(principal))),
+            ("schema_drift", Some(log), _) => Ok(log.drift_table()),
+            ("lineage", _, Some(log)) => Ok(log.lineage_table()),
+            _ => Err(ConnectorError::QueryExecutionFailed(format!("Unknown system table '{}'", name)).into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Connector for SystemConnector {
+    async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let source = query.query.sources.first()
+            .ok_or_else(|| ConnectorError::QueryExecutionFailed("No system table in query".to_string()))?;
+        let mut result = self.table(&source.identifier)?;
+
+        let projections = &query.query.projections;
+        if !projections.is_empty() && !projections.iter().any(|col| col.name == "*") {
+            let mut indexes = Vec::new();
+            let mut columns = Vec::new();
+            for col in projections {
+                let index = IdentifierCase::default().resolve(&col.name, result.columns.iter().map(|c| c.name.as_str()))
+                    .ok_or_else(|| ConnectorError::QueryExecutionFailed(format!("Column '{}' not found in system table", col.name)))?;
+                let mut column = result.columns[index].clone();
+                if let Some(alias) = &col.alias {
+                    column.name = alias.clone();
+                }
+                indexes.push(index);
+                columns.push(column);
+            }
+            result.rows = result.rows.into_iter()
+                .map(|row| Row::new(indexes.iter().map(|i| row.get(*i).cloned().unwrap_or(Value::Null)).collect()))
+                .collect();
+            result.columns = columns;
+        }
+        if let Some(limit) = query.query.limit {
+            result.rows.truncate(limit as usize);
+        }
+        Ok(result)
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        Ok(Schema {
+            name: object_name.to_string(),
+            columns: self.table(object_name)?.columns,
+            primary_key: None,
+            indexes: Vec::new(),
+            column_details: HashMap::new(),
+            foreign_keys: Vec::new(),
+        })
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Custom(SYSTEM_OBJECT_TYPE.to_string())
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities::default()
+    }
+
+    /// Predicates are applied by the engine to the table's rows
+    fn supports_predicate(&self, _predicate: &Predicate) -> bool {
+        false
+    }
+
+    async fn list_objects(&self) -> NirvResult<Vec<String>> {
+        let mut objects = vec!["usage".to_string()];
+        if self.schema_drift.is_some() {
+            objects.push("schema_drift".to_string());
+        }
+        if self.lineage.is_some() {
+            objects.push("lineage".to_string());
+        }
+        Ok(objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_windows_and_limits() {
+        let tracker = UsageTracker::new(UsageLimits { rows_per_day: Some(1000), ..Default::default() })
+            .with_limits("acme", UsageLimits { rows_per_hour: Some(100), ..Default::default() });
+        let now = Utc::now();
+
+        tracker.record_at(&["acme", "acme/alice"], now - Duration::hours(30), 500, 5000);
+        tracker.record_at(&["acme", "acme/alice"], now - Duration::hours(2), 80, 800);
+        tracker.record_at(&["acme"], now - Duration::minutes(5), 60, 600);
+
+        assert_eq!(tracker.usage_at("acme", now), Usage {
+            queries_last_hour: 1,
+            rows_last_hour: 60,
+            bytes_last_hour: 600,
+            queries_last_day: 2,
+            rows_last_day: 140,
+            bytes_last_day: 1400,
+        });
+        // The 30 hour old record was pruned when newer ones were added
+        assert_eq!(tracker.usage_at("acme/alice", now).queries_last_day, 1);
+        assert!(tracker.check_at(&["acme"], now).is_ok());
+
+        tracker.record_at(&["acme"], now, 40, 400);
+        let error = tracker.check_at(&["acme/alice", "acme"], now).unwrap_err();
+        assert!(matches!(error, NirvError::QuotaExceeded(_)));
+        assert!(error.to_string().contains("100 of 100 rows per hour"));
+        // The hourly limit frees up as the window rolls forward
+        assert!(tracker.check_at(&["acme"], now + Duration::hours(2)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_system_usage_table_is_scoped_to_tenant() {
+        let tracker = Arc::new(UsageTracker::default());
+        tracker.record_at(&["acme", "acme/alice"], Utc::now(), 3, 30);
+        tracker.record_at(&["acmecorp"], Utc::now(), 1, 10);
+
+        let connector = SystemConnector::new(tracker.clone()).with_scope("acme");
+        let result = connector.table("usage").unwrap();
+        let principals: Vec<&Value> = result.rows.iter().map(|row| &row.values[0]).collect();
+        assert_eq!(principals.len(), 4);
+        assert!(principals.iter().all(|p| matches!(p, Value::Text(name) if name.starts_with("acme") && name != "acmecorp")));
+        assert_eq!(SystemConnector::new(tracker).table("usage").unwrap().rows.len(), 6);
+    }
+}
@@ -0,0 +1,261 @@
+//! C API for embedding the engine in services written in other languages, declared in
+//! `include/nirv.h`. Every string crossing it is NUL-terminated UTF-8, and every string
+//! it returns is freed with `nirv_free_result`.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use serde_json::json;
+use tokio::runtime::Runtime;
+
+use crate::cli::OutputFormatter;
+use crate::engine::Engine;
+use crate::utils::{
+    config::EngineConfig,
+    error::{NirvError, NirvResult},
+};
+
+/// An engine and the runtime its queries run on, behind the opaque `NirvEngine *` handle
+pub struct NirvEngine {
+    runtime: Runtime,
+    engine: Engine,
+}
+
+impl NirvEngine {
+    /// Start an engine with a JSON configuration, or without one with the defaults but no
+    /// protocol servers, as an embedded engine is queried through its handle. Without one it
+    /// also tracks usage, so `system.usage` answers before any source is configured.
+    fn start(config_json: Option<&str>) -> NirvResult<Self> {
+        let config = match config_json {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| NirvError::Configuration(format!("Invalid configuration: {}", e)))?,
+            None => {
+                let mut config = EngineConfig::default();
+                config.protocol_adapters.clear();
+                config.quotas = Some(Default::default());
+                config
+            }
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| NirvError::Internal(format!("Failed to start the runtime: {}", e)))?;
+        let mut engine = Engine::new(config);
+        runtime.block_on(engine.initialize())?;
+        Ok(Self { runtime, engine })
+    }
+
+    /// A query's result in the JSON of `nirv query --format json`, or `{"error": "..."}`
+    fn query_json(&self, sql: &str) -> String {
+        match self.runtime.block_on(self.engine.execute_query(sql)) {
+            Ok(result) => OutputFormatter::format_json(&result),
+            Err(e) => error_json(&e.to_string()),
+        }
+    }
+}
+
+fn error_json(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
+
+/// A string handed to C; interior NULs, which C could not see past, are dropped
+fn into_c_string(text: String) -> *mut c_char {
+    let text = CString::new(text).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|byte| *byte != 0);
+        CString::new(bytes).expect("NULs removed")
+    });
+    text.into_raw()
+}
+
+/// # Safety
+/// `text` must be NULL or point to a NUL-terminated string
+unsafe fn str_arg<'a>(text: *const c_char, name: &str) -> Result<Option<&'a str>, String> {
+    if text.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(text).to_str().map(Some).map_err(|_| format!("{} is not UTF-8", name))
+}
+
+/// Create an engine from a JSON configuration, in the format of `nirv --config`, or with the
+/// defaults, no protocol servers and usage tracked in `system.usage` when `config_json` is
+/// NULL. Returns NULL on failure, with the reason in `*error` when `error` is not NULL.
+///
+/// # Safety
+/// `config_json` must be NULL or a NUL-terminated string, and `error` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn nirv_engine_new(config_json: *const c_char, error: *mut *mut c_char) -> *mut NirvEngine {
+    if !error.is_null() {
+        *error = ptr::null_mut();
+    }
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let config_json = str_arg(config_json, "config_json")?;
+        NirvEngine::start(config_json).map_err(|e| e.to_string())
+    })).unwrap_or_else(|_| Err("Engine panicked while starting".to_string()));
+
+    match outcome {
+        Ok(engine) => Box::into_raw(Box::new(engine)),
+        Err(message) => {
+            if !error.is_null() {
+                *error = into_c_string(message);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Execute a query and return its result as JSON: `{"data": [...], "metadata": {...}}`, or
+/// `{"error": "..."}` when it fails. Never returns NULL.
+///
+/// # Safety
+/// `engine` must come from `nirv_engine_new` and not have been freed, and `sql` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nirv_query_json(engine: *const NirvEngine, sql: *const c_char) -> *mut c_char {
+    let json = catch_unwind(AssertUnwindSafe(|| {
+        let Some(engine) = engine.as_ref() else {
+            return error_json("engine is NULL");
+        };
+        match str_arg(sql, "sql") {
+            Ok(Some(sql)) => engine.query_json(sql),
+            Ok(None) => error_json("sql is NULL"),
+            Err(message) => error_json(&message),
+        }
+    })).unwrap_or_else(|_| error_json("Engine panicked while executing the query"));
+    into_c_string(json)
+}
+
+/// Free a string returned by `nirv_query_json` or `nirv_engine_new`; NULL is ignored.
+///
+/// # Safety
+/// `result` must be NULL or a string this library returned that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn nirv_free_result(result: *mut c_char) {
+    if !result.is_null() {
+        drop(CString::from_raw(result));
+    }
+}
+
+/// Shut an engine down and free it; NULL is ignored.
+///
+/// # Safety
+/// `engine` must be NULL or come from `nirv_engine_new` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn nirv_engine_free(engine: *mut NirvEngine) {
+    if engine.is_null() {
+        return;
+    }
+    let mut engine = Box::from_raw(engine);
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let NirvEngine { runtime, engine } = &mut *engine;
+        let _ = runtime.block_on(engine.shutdown());
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn take_string(text: *mut c_char) -> String {
+        let owned = CStr::from_ptr(text).to_str().unwrap().to_string();
+        nirv_free_result(text);
+        owned
+    }
+
+    #[test]
+    fn test_c_api() {
+        unsafe {
+            // The README's example: the defaults serve the usage table
+            let mut error = ptr::null_mut();
+            let engine = nirv_engine_new(ptr::null(), &mut error);
+            assert!(!engine.is_null() && error.is_null());
+            let sql = CString::new("SELECT * FROM source('system.usage')").unwrap();
+            let result: serde_json::Value = serde_json::from_str(&take_string(nirv_query_json(engine, sql.as_ptr()))).unwrap();
+            assert!(result["data"].is_array(), "{}", result);
+            nirv_engine_free(engine);
+
+            // So do quotas in a configuration
+            let mut config = EngineConfig::default();
+            config.protocol_adapters.clear();
+            config.quotas = Some(Default::default());
+            let config = CString::new(serde_json::to_string(&config).unwrap()).unwrap();
+            let engine = nirv_engine_new(config.as_ptr(), ptr::null_mut());
+            assert!(!engine.is_null());
+
+            let sql = CString::new("SELECT * FROM source('system.usage')").unwrap();
+            let result: serde_json::Value = serde_json::from_str(&take_string(nirv_query_json(engine, sql.as_ptr()))).unwrap();
+            assert!(result["data"].is_array(), "{}", result);
+            assert!(result["metadata"]["columns"].is_array());
+
+            let sql = CString::new("SELEC nonsense").unwrap();
+            let result: serde_json::Value = serde_json::from_str(&take_string(nirv_query_json(engine, sql.as_ptr()))).unwrap();
+            assert!(result["error"].is_string());
+            let result: serde_json::Value = serde_json::from_str(&take_string(nirv_query_json(engine, ptr::null()))).unwrap();
+            assert_eq!(result["error"], "sql is NULL");
+            nirv_engine_free(engine);
+
+            let mut error = ptr::null_mut();
+            let config = CString::new("{\"connectors\": 1}").unwrap();
+            assert!(nirv_engine_new(config.as_ptr(), &mut error).is_null());
+            assert!(take_string(error).starts_with("Configuration error"));
+
+            nirv_free_result(ptr::null_mut());
+            nirv_engine_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_engine_handle_is_shareable_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<NirvEngine>();
+    }
+
+    /// The C spelling of a parameter or return type of an exported function, ready to be
+    /// followed by a name
+    fn c_type(rust_type: &str) -> String {
+        let rust_type = rust_type.trim();
+        if let Some(pointee) = rust_type.strip_prefix("*const ") {
+            return format!("const {}*", c_type(pointee));
+        }
+        if let Some(pointee) = rust_type.strip_prefix("*mut ") {
+            return format!("{}*", c_type(pointee));
+        }
+        match rust_type {
+            "c_char" => "char ".to_string(),
+            "NirvEngine" => "NirvEngine ".to_string(),
+            "" => "void ".to_string(),
+            other => panic!("no C spelling for {}", other),
+        }
+    }
+
+    /// The declaration include/nirv.h should hold for an exported function's signature
+    fn c_declaration(signature: &str) -> String {
+        let (name, rest) = signature.split_once('(').unwrap();
+        let (parameters, return_type) = rest.split_once(')').unwrap();
+        let return_type = return_type.trim().trim_end_matches('{').trim().trim_start_matches("->");
+        let parameters: Vec<String> = parameters.split(',')
+            .map(|parameter| {
+                let (name, rust_type) = parameter.split_once(':').unwrap();
+                format!("{}{}", c_type(rust_type), name.trim())
+            })
+            .collect();
+        format!("{}{}({});", c_type(return_type), name, parameters.join(", "))
+    }
+
+    #[test]
+    fn test_header_matches_exported_functions() {
+        let header = include_str!("../include/nirv.h");
+        let source = include_str!("ffi.rs");
+        let declarations: Vec<String> = source.lines()
+            .filter_map(|line| line.strip_prefix("pub unsafe extern \"C\" fn "))
+            .map(c_declaration)
+            .collect();
+        assert_eq!(declarations.len(), 4);
+        for declaration in &declarations {
+            assert!(header.lines().any(|line| line == declaration), "include/nirv.h does not declare `{}`", declaration);
+        }
+        let declared = header.lines().filter(|line| line.starts_with(|c: char| c.is_alphabetic()) && line.ends_with(");")).count();
+        assert_eq!(declared, declarations.len(), "include/nirv.h declares functions src/ffi.rs does not export");
+    }
+}
@@ -106,10 +106,12 @@ pub mod connectors;
 pub mod protocol;
 pub mod cli;
 pub mod utils;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 // Re-export main modules
-pub use engine::{Engine, EngineBuilder, QueryParser, DefaultQueryParser, QueryPlanner, DefaultQueryPlanner, QueryExecutor, DefaultQueryExecutor, Dispatcher, DefaultDispatcher};
-pub use connectors::{Connector, ConnectorRegistry, ConnectorInitConfig, SqlServerConnector, PostgresConnector, RestConnector, FileConnector, MockConnector};
-pub use protocol::{ProtocolAdapter, Connection, Credentials, ProtocolQuery, ProtocolResponse, PostgresProtocol, SqlServerProtocol, MySQLProtocolAdapter, SQLiteProtocolAdapter};
+pub use engine::{Engine, EngineBuilder, QueryParser, DefaultQueryParser, QueryPlanner, DefaultQueryPlanner, QueryExecutor, DefaultQueryExecutor, Dispatcher, DefaultDispatcher, Session, ClientDialect, TransactionControl, QueryRewriter, ResultTransformer};
+pub use connectors::{Connector, ConnectorRegistry, ConnectorInitConfig, SqlServerConnector, PostgresConnector, RestConnector, BigQueryConnector, SnowflakeConnector, ImapConnector, FileConnector, MockConnector, CdcConnector};
+pub use protocol::{ProtocolAdapter, Connection, Credentials, ProtocolQuery, ProtocolResponse, TransactionStatus, PostgresProtocol, SqlServerProtocol, MySQLProtocolAdapter, SQLiteProtocolAdapter};
 pub use cli::{CliRunner, CliArgs, OutputFormatter};
 pub use utils::{NirvResult, NirvError, QueryResult, Row, Value, DataType, ColumnMetadata};
\ No newline at end of file
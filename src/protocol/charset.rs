@@ -0,0 +1,112 @@
+/// Byte encoding of text exchanged with a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, one byte per code point up to U+00FF
+    Latin1,
+}
+
+impl TextEncoding {
+    /// Encode text for the wire; characters outside Latin-1 become `?` as MySQL and Postgres do
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            TextEncoding::Utf8 => text.as_bytes().to_vec(),
+            TextEncoding::Latin1 => text.chars()
+                .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                .collect(),
+        }
+    }
+
+    /// Decode text received from the client
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+            TextEncoding::Latin1 => bytes.iter().map(|b| char::from(*b)).collect(),
+        }
+    }
+}
+
+/// A MySQL collation, identified on the wire by its one-byte id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MySQLCollation {
+    pub id: u8,
+    pub name: &'static str,
+    pub charset: &'static str,
+    pub encoding: TextEncoding,
+}
+
+/// Collation id of the `binary` pseudo charset, used for non-text columns
+pub const MYSQL_BINARY_COLLATION: u8 = 63;
+
+/// Collation advertised in the server handshake
+pub const MYSQL_DEFAULT_COLLATION: MySQLCollation = MYSQL_COLLATIONS[2];
+
+const MYSQL_COLLATIONS: &[MySQLCollation] = &[
+    MySQLCollation { id: 33, name: "utf8mb3_general_ci", charset: "utf8mb3", encoding: TextEncoding::Utf8 },
+    MySQLCollation { id: 83, name: "utf8mb3_bin", charset: "utf8mb3", encoding: TextEncoding::Utf8 },
+    MySQLCollation { id: 45, name: "utf8mb4_general_ci", charset: "utf8mb4", encoding: TextEncoding::Utf8 },
+    MySQLCollation { id: 46, name: "utf8mb4_bin", charset: "utf8mb4", encoding: TextEncoding::Utf8 },
+    MySQLCollation { id: 224, name: "utf8mb4_unicode_ci", charset: "utf8mb4", encoding: TextEncoding::Utf8 },
+    MySQLCollation { id: 255, name: "utf8mb4_0900_ai_ci", charset: "utf8mb4", encoding: TextEncoding::Utf8 },
+    MySQLCollation { id: 8, name: "latin1_swedish_ci", charset: "latin1", encoding: TextEncoding::Latin1 },
+    MySQLCollation { id: 47, name: "latin1_bin", charset: "latin1", encoding: TextEncoding::Latin1 },
+    MySQLCollation { id: 11, name: "ascii_general_ci", charset: "ascii", encoding: TextEncoding::Latin1 },
+];
+
+impl MySQLCollation {
+    /// Look up a collation by the id a client sends in its handshake response
+    pub fn from_id(id: u8) -> Option<Self> {
+        MYSQL_COLLATIONS.iter().find(|c| c.id == id).copied()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        MYSQL_COLLATIONS.iter().find(|c| c.name.eq_ignore_ascii_case(name)).copied()
+    }
+
+    /// Default collation of a charset, as used by `SET NAMES <charset>`
+    pub fn for_charset(charset: &str) -> Option<Self> {
+        let charset = charset.trim_matches(|c| c == '\'' || c == '"' || c == '`').to_lowercase();
+        let charset = if charset == "utf8" { "utf8mb3".to_string() } else { charset };
+        MYSQL_COLLATIONS.iter().find(|c| c.charset == charset).copied()
+    }
+}
+
+/// Resolve a Postgres `client_encoding` name to its canonical name and encoding.
+/// Names are matched case-insensitively ignoring punctuation, as Postgres does.
+pub fn postgres_client_encoding(name: &str) -> Option<(&'static str, TextEncoding)> {
+    let normalized: String = name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    match normalized.as_str() {
+        "utf8" | "unicode" => Some(("UTF8", TextEncoding::Utf8)),
+        "latin1" | "iso88591" => Some(("LATIN1", TextEncoding::Latin1)),
+        // No conversion is performed for SQL_ASCII clients
+        "sqlascii" => Some(("SQL_ASCII", TextEncoding::Utf8)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latin1_round_trip() {
+        let encoded = TextEncoding::Latin1.encode("Café ☕");
+        assert_eq!(encoded, vec![b'C', b'a', b'f', 0xe9, b' ', b'?']);
+        assert_eq!(TextEncoding::Latin1.decode(&encoded[..4]), "Café");
+        assert_eq!(TextEncoding::Utf8.encode("Café").len(), 5);
+    }
+
+    #[test]
+    fn test_charset_lookup() {
+        assert_eq!(MySQLCollation::from_id(255).unwrap().charset, "utf8mb4");
+        assert_eq!(MySQLCollation::from_id(8).unwrap().encoding, TextEncoding::Latin1);
+        assert_eq!(MySQLCollation::for_charset("'utf8'").unwrap().id, 33);
+        assert_eq!(postgres_client_encoding("utf-8"), Some(("UTF8", TextEncoding::Utf8)));
+        assert_eq!(postgres_client_encoding("ISO_8859_1").unwrap().0, "LATIN1");
+        assert_eq!(postgres_client_encoding("EUC_JP"), None);
+    }
+}
@@ -0,0 +1,651 @@
+//! Compression of protocol responses: zlib streams for MySQL `CLIENT_COMPRESS` and gzip for
+//! HTTP `Content-Encoding`. The encoder emits fixed-Huffman DEFLATE blocks, falling back to
+//! stored blocks for data that does not compress; the decoder reads every block type.
+
+use crate::utils::{NirvResult, ProtocolError};
+
+/// Largest distance back an LZ77 match may refer to
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Candidates examined per position; more finds longer matches, slower
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: usize = 15;
+
+/// Largest payload of a stored block
+const MAX_STORED_BLOCK: usize = 65535;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+/// Order code length code lengths are sent in by dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const END_OF_BLOCK: u16 = 256;
+
+/// Compress data into a zlib stream
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // CMF: deflate with a 32K window; FLG: fastest compression, check bits making it a multiple of 31
+    let mut out = vec![0x78, 0x01];
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Decompress a zlib stream, refusing output longer than `max_len`
+pub fn zlib_decompress(data: &[u8], max_len: usize) -> NirvResult<Vec<u8>> {
+    if data.len() < 6 {
+        return Err(invalid("zlib stream too short"));
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0f != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(invalid("not a zlib stream"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(invalid("zlib preset dictionaries are not supported"));
+    }
+
+    let (output, consumed) = inflate(&data[2..], max_len)?;
+    let trailer = data.get(2 + consumed..2 + consumed + 4).ok_or_else(|| invalid("zlib stream truncated"))?;
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != adler32(&output) {
+        return Err(invalid("zlib checksum mismatch"));
+    }
+    Ok(output)
+}
+
+/// Compress data into a single-member gzip stream
+pub fn gzip_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = GZIP_HEADER.to_vec();
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&crc32(0, data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Decompress a gzip stream, refusing output longer than `max_len`. Members after the first
+/// are decompressed and appended, as gzip readers do.
+pub fn gzip_decompress(data: &[u8], max_len: usize) -> NirvResult<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.len() < 18 || rest[..3] != [0x1f, 0x8b, 8] {
+            return Err(invalid("not a gzip stream"));
+        }
+        let flags = rest[3];
+        let mut position = 10;
+        if flags & 0x04 != 0 {
+            let extra = rest.get(position..position + 2).ok_or_else(|| invalid("gzip header truncated"))?;
+            position += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+        }
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 {
+                let end = rest.get(position..).and_then(|tail| tail.iter().position(|b| *b == 0))
+                    .ok_or_else(|| invalid("gzip header truncated"))?;
+                position += end + 1;
+            }
+        }
+        if flags & 0x02 != 0 {
+            position += 2;
+        }
+
+        let body = rest.get(position..).ok_or_else(|| invalid("gzip header truncated"))?;
+        let (member, consumed) = inflate(body, max_len.saturating_sub(output.len()))?;
+        let trailer = body.get(consumed..consumed + 8).ok_or_else(|| invalid("gzip stream truncated"))?;
+        if u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != crc32(0, &member)
+            || u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) != member.len() as u32
+        {
+            return Err(invalid("gzip checksum mismatch"));
+        }
+        output.extend_from_slice(&member);
+        rest = &body[consumed + 8..];
+    }
+    Ok(output)
+}
+
+/// Header of gzip streams written here: deflate, no flags, no modification time, unknown OS
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+
+/// A gzip stream written a chunk at a time, for bodies sent while they are produced. Each
+/// chunk is flushed to a byte boundary so clients can decompress it as soon as it arrives.
+#[derive(Debug, Default)]
+pub struct GzipStream {
+    crc: u32,
+    size: u32,
+    started: bool,
+}
+
+impl GzipStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress the next chunk of the stream
+    pub fn write(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        if !self.started {
+            out.extend_from_slice(&GZIP_HEADER);
+            self.started = true;
+        }
+        self.crc = crc32(self.crc, data);
+        self.size = self.size.wrapping_add(data.len() as u32);
+
+        let mut writer = BitWriter::new(out);
+        write_block(&mut writer, data, false);
+        // An empty stored block aligns the stream to a byte, as zlib's sync flush does
+        writer.write_bits(0, 3);
+        writer.align();
+        writer.out.extend_from_slice(&[0x00, 0x00, 0xff, 0xff]);
+        writer.finish()
+    }
+
+    /// End the stream: a final empty block and the gzip trailer
+    pub fn finish(mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if !self.started {
+            out.extend_from_slice(&GZIP_HEADER);
+            self.started = true;
+        }
+        let mut writer = BitWriter::new(out);
+        write_block(&mut writer, &[], true);
+        let mut out = writer.finish();
+        out.extend_from_slice(&self.crc.to_le_bytes());
+        out.extend_from_slice(&self.size.to_le_bytes());
+        out
+    }
+}
+
+/// Compress data into raw DEFLATE blocks
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new(Vec::new());
+    write_block(&mut writer, data, true);
+    writer.finish()
+}
+
+/// Decompress raw DEFLATE blocks, returning the output and the number of input bytes read
+pub fn inflate(data: &[u8], max_len: usize) -> NirvResult<(Vec<u8>, usize)> {
+    let mut reader = BitReader { data, position: 0, bits: 0, bit_count: 0 };
+    let mut output = Vec::new();
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = reader.bytes(4)?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(invalid("stored block length mismatch"));
+                }
+                if output.len() + length as usize > max_len {
+                    return Err(invalid("decompressed data too large"));
+                }
+                output.extend_from_slice(reader.bytes(length as usize)?);
+            }
+            1 => {
+                let (literals, distances) = fixed_tables();
+                inflate_block(&mut reader, &mut output, &literals, &distances, max_len)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances, max_len)?;
+            }
+            _ => return Err(invalid("invalid deflate block type")),
+        }
+        if last {
+            return Ok((output, reader.position));
+        }
+    }
+}
+
+/// Adler-32 checksum of zlib streams
+pub fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // Sums stay below u32::MAX for 5552 bytes between reductions
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += u32::from(*byte);
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    b << 16 | a
+}
+
+/// CRC-32 of gzip streams, continuing from a previous value (0 to start)
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    static TABLE: std::sync::OnceLock<[u32; 256]> = std::sync::OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut value = i as u32;
+            for _ in 0..8 {
+                value = if value & 1 != 0 { 0xedb8_8320 ^ (value >> 1) } else { value >> 1 };
+            }
+            *entry = value;
+        }
+        table
+    });
+    !data.iter().fold(!crc, |crc, byte| table[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8))
+}
+
+fn invalid(message: &str) -> crate::utils::NirvError {
+    ProtocolError::InvalidMessageFormat(format!("Invalid compressed data: {}", message)).into()
+}
+
+/// A literal byte or a reference back to earlier output
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Literal(u8),
+    Match { length: usize, distance: usize },
+}
+
+/// Positions of earlier three-byte prefixes, most recent first, within the window
+struct HashChains {
+    head: Vec<usize>,
+    previous: Vec<usize>,
+}
+
+impl HashChains {
+    fn new() -> Self {
+        Self { head: vec![usize::MAX; 1 << HASH_BITS], previous: vec![usize::MAX; WINDOW_SIZE] }
+    }
+
+    fn hash(data: &[u8], i: usize) -> usize {
+        ((usize::from(data[i]) << 10) ^ (usize::from(data[i + 1]) << 5) ^ usize::from(data[i + 2])) & ((1 << HASH_BITS) - 1)
+    }
+
+    fn insert(&mut self, data: &[u8], i: usize) {
+        if i + MIN_MATCH <= data.len() {
+            let hash = Self::hash(data, i);
+            self.previous[i % WINDOW_SIZE] = self.head[hash];
+            self.head[hash] = i;
+        }
+    }
+
+    /// Longest earlier match for the bytes at `i`, as (length, distance)
+    fn longest_match(&self, data: &[u8], i: usize) -> (usize, usize) {
+        let mut best = (0, 0);
+        if i + MIN_MATCH > data.len() {
+            return best;
+        }
+        let limit = (data.len() - i).min(MAX_MATCH);
+        let mut candidate = self.head[Self::hash(data, i)];
+        for _ in 0..MAX_CHAIN {
+            if candidate >= i || i - candidate >= WINDOW_SIZE {
+                break;
+            }
+            let length = data[candidate..].iter().zip(&data[i..i + limit]).take_while(|(a, b)| a == b).count();
+            if length > best.0 {
+                best = (length, i - candidate);
+                if length == limit {
+                    break;
+                }
+            }
+            let next = self.previous[candidate % WINDOW_SIZE];
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+        best
+    }
+}
+
+/// Split data into literals and LZ77 matches
+fn tokenize(data: &[u8]) -> Vec<Token> {
+    let mut chains = HashChains::new();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let (length, distance) = chains.longest_match(data, i);
+        if length >= MIN_MATCH {
+            tokens.push(Token::Match { length, distance });
+            for position in i..i + length {
+                chains.insert(data, position);
+            }
+            i += length;
+        } else {
+            tokens.push(Token::Literal(data[i]));
+            chains.insert(data, i);
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// Index of the table entry whose base is the largest not above the value
+fn code_index(bases: &[u16], value: usize) -> usize {
+    bases.partition_point(|base| usize::from(*base) <= value) - 1
+}
+
+/// Fixed Huffman code and bit length of a literal/length symbol
+fn fixed_literal_code(symbol: u16) -> (u16, u8) {
+    match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    }
+}
+
+/// Write data as one fixed-Huffman block, or as stored blocks when that is smaller
+fn write_block(writer: &mut BitWriter, data: &[u8], last: bool) {
+    let tokens = tokenize(data);
+    let fixed_bits: usize = tokens.iter().map(|token| match *token {
+        Token::Literal(byte) => usize::from(fixed_literal_code(u16::from(byte)).1),
+        Token::Match { length, distance } => {
+            let length_index = code_index(&LENGTH_BASE, length);
+            let distance_index = code_index(&DISTANCE_BASE, distance);
+            usize::from(fixed_literal_code(257 + length_index as u16).1)
+                + usize::from(LENGTH_EXTRA[length_index])
+                + 5
+                + usize::from(DISTANCE_EXTRA[distance_index])
+        }
+    }).sum();
+
+    if !data.is_empty() && fixed_bits > data.len() * 8 + data.len().div_ceil(MAX_STORED_BLOCK) * 40 {
+        let blocks = data.chunks(MAX_STORED_BLOCK).count();
+        for (i, block) in data.chunks(MAX_STORED_BLOCK).enumerate() {
+            writer.write_bits(u32::from(last && i + 1 == blocks), 1);
+            writer.write_bits(0, 2);
+            writer.align();
+            writer.out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            writer.out.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+            writer.out.extend_from_slice(block);
+        }
+        return;
+    }
+
+    writer.write_bits(u32::from(last), 1);
+    writer.write_bits(1, 2);
+    for token in tokens {
+        match token {
+            Token::Literal(byte) => writer.write_code(fixed_literal_code(u16::from(byte))),
+            Token::Match { length, distance } => {
+                let length_index = code_index(&LENGTH_BASE, length);
+                writer.write_code(fixed_literal_code(257 + length_index as u16));
+                writer.write_bits((length - usize::from(LENGTH_BASE[length_index])) as u32, LENGTH_EXTRA[length_index]);
+                let distance_index = code_index(&DISTANCE_BASE, distance);
+                writer.write_code((distance_index as u16, 5));
+                writer.write_bits((distance - usize::from(DISTANCE_BASE[distance_index])) as u32, DISTANCE_EXTRA[distance_index]);
+            }
+        }
+    }
+    writer.write_code(fixed_literal_code(END_OF_BLOCK));
+}
+
+/// Writes bits least significant first, as DEFLATE packs them
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    bit_count: u8,
+}
+
+impl BitWriter {
+    fn new(out: Vec<u8>) -> Self {
+        Self { out, bits: 0, bit_count: 0 }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u8) {
+        self.bits |= u64::from(value) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Huffman codes are packed most significant bit first
+    fn write_code(&mut self, (code, length): (u16, u8)) {
+        let reversed = code.reverse_bits() >> (16 - length);
+        self.write_bits(u32::from(reversed), length);
+    }
+
+    fn align(&mut self) {
+        if self.bit_count > 0 {
+            self.write_bits(0, 8 - self.bit_count);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.out
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bits: u32,
+    bit_count: u8,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u8) -> NirvResult<u32> {
+        while self.bit_count < count {
+            let byte = *self.data.get(self.position).ok_or_else(|| invalid("unexpected end of data"))?;
+            self.bits |= u32::from(byte) << self.bit_count;
+            self.position += 1;
+            self.bit_count += 8;
+        }
+        let value = self.bits & ((1u32 << count) - 1);
+        self.bits >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// Skip to the next byte boundary; fewer than eight bits are ever buffered
+    fn align(&mut self) {
+        self.bits = 0;
+        self.bit_count = 0;
+    }
+
+    fn bytes(&mut self, count: usize) -> NirvResult<&[u8]> {
+        let bytes = self.data.get(self.position..self.position + count).ok_or_else(|| invalid("unexpected end of data"))?;
+        self.position += count;
+        Ok(bytes)
+    }
+}
+
+/// Canonical Huffman code: how many codes have each length, and symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[usize::from(*length)] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[usize::from(offsets[usize::from(*length)])] = symbol as u16;
+                offsets[usize::from(*length)] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader<'_>) -> NirvResult<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(self.counts[length]);
+            if code - first < count {
+                return self.symbols.get((index + code - first) as usize).copied()
+                    .ok_or_else(|| invalid("invalid Huffman code"));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let literal_lengths: Vec<u8> = (0..288u16).map(|symbol| fixed_literal_code(symbol).1).collect();
+    (Huffman::new(&literal_lengths), Huffman::new(&[5; 30]))
+}
+
+/// Read the code length tables at the start of a dynamic block
+fn dynamic_tables(reader: &mut BitReader<'_>) -> NirvResult<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for position in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[*position] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or_else(|| invalid("length repeat with no previous length"))?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err(invalid("invalid code length symbol")),
+        };
+        if lengths.len() + repeat as usize > literal_count + distance_count {
+            return Err(invalid("code lengths overflow"));
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths[usize::from(END_OF_BLOCK)] == 0 {
+        return Err(invalid("block has no end-of-block code"));
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+fn inflate_block(
+    reader: &mut BitReader<'_>,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    max_len: usize,
+) -> NirvResult<()> {
+    loop {
+        let symbol = literals.decode(reader)?;
+        let length = match symbol {
+            0..=255 => {
+                if output.len() >= max_len {
+                    return Err(invalid("decompressed data too large"));
+                }
+                output.push(symbol as u8);
+                continue;
+            }
+            END_OF_BLOCK => return Ok(()),
+            257..=285 => {
+                let index = usize::from(symbol - 257);
+                usize::from(LENGTH_BASE[index]) + reader.bits(LENGTH_EXTRA[index])? as usize
+            }
+            _ => return Err(invalid("invalid length symbol")),
+        };
+
+        let index = usize::from(distances.decode(reader)?);
+        if index >= DISTANCE_BASE.len() {
+            return Err(invalid("invalid distance symbol"));
+        }
+        let distance = usize::from(DISTANCE_BASE[index]) + reader.bits(DISTANCE_EXTRA[index])? as usize;
+        if distance > output.len() {
+            return Err(invalid("distance before start of data"));
+        }
+        if output.len() + length > max_len {
+            return Err(invalid("decompressed data too large"));
+        }
+        // Matches may overlap the bytes they produce, so copy one at a time
+        let start = output.len() - distance;
+        for i in 0..length {
+            output.push(output[start + i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        (0..2000).map(|i| format!("{{\"id\":{},\"name\":\"user {}\"}}\n", i, i % 7)).collect::<String>().into_bytes()
+    }
+
+    #[test]
+    fn test_round_trips() {
+        for data in [Vec::new(), b"a".to_vec(), b"abcabcabcabcabc".to_vec(), sample()] {
+            assert_eq!(zlib_decompress(&zlib_compress(&data), usize::MAX).unwrap(), data);
+            assert_eq!(gzip_decompress(&gzip_compress(&data), usize::MAX).unwrap(), data);
+        }
+        let data = sample();
+        assert!(zlib_compress(&data).len() < data.len() / 4);
+
+        // Data that does not compress is stored, in blocks of at most 64K
+        let noise: Vec<u8> = (0..100_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        let compressed = zlib_compress(&noise);
+        assert!(compressed.len() < noise.len() + 32);
+        assert_eq!(zlib_decompress(&compressed, usize::MAX).unwrap(), noise);
+
+        assert!(zlib_decompress(&zlib_compress(&data), 100).is_err());
+        let mut corrupt = zlib_compress(&data);
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        assert!(zlib_decompress(&corrupt, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_decodes_dynamic_blocks() {
+        // A dynamic-Huffman stream from CPython: zlib.compress(rows, 9), where rows is
+        // "".join(f"row {i}: {['alpha', 'beta', 'gamma', 'delta'][i % 4] * (i % 3 + 1)}\n" for i in range(20))
+        let stream = [
+            0x78, 0xda, 0x6d, 0x8f, 0x4b, 0x0a, 0x80, 0x30, 0x0c, 0x05, 0xf7, 0x9e, 0x22, 0x47, 0xf0, 0xf9,
+            0xab, 0xf6, 0x36, 0x11, 0x45, 0x17, 0x16, 0x45, 0x04, 0xaf, 0xaf, 0xb6, 0xc5, 0xc4, 0xcf, 0x22,
+            0x81, 0x17, 0x92, 0x21, 0xb3, 0xce, 0x3b, 0xa5, 0x96, 0x78, 0x5a, 0x46, 0x4e, 0xd6, 0x33, 0xc0,
+            0x52, 0xdb, 0x6f, 0x7c, 0x95, 0xcf, 0x99, 0xa5, 0x81, 0x9d, 0x63, 0x69, 0x7e, 0x9c, 0x5b, 0xea,
+            0xfa, 0x29, 0xee, 0x14, 0x11, 0x20, 0x94, 0x52, 0x28, 0x37, 0xa9, 0x8a, 0x24, 0x1f, 0x4c, 0xbc,
+            0x17, 0x48, 0xad, 0x21, 0x42, 0x6a, 0x02, 0x29, 0xfc, 0x96, 0xea, 0x67, 0xc2, 0x08, 0x1a, 0x24,
+            0x34, 0x64, 0x0f, 0xa9, 0xfc, 0x65, 0x85, 0xe2, 0x5f, 0x0b, 0xa5, 0xf6, 0x42, 0xf5, 0x11, 0x83,
+            0xf9, 0x31, 0x43, 0xad, 0xd5, 0xd0, 0x3c, 0xdc, 0x0e, 0xe7, 0x27, 0x75, 0x97,
+        ];
+        assert_eq!((stream[2] >> 1) & 3, 2);
+        let words = ["alpha", "beta", "gamma", "delta"];
+        let expected: String = (0..20).map(|i| format!("row {}: {}\n", i, words[i % 4].repeat(i % 3 + 1))).collect();
+        assert_eq!(zlib_decompress(&stream, usize::MAX).unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_gzip_stream() {
+        let data = sample();
+        let mut stream = GzipStream::new();
+        let mut out = Vec::new();
+        for chunk in data.chunks(1000) {
+            out.extend_from_slice(&stream.write(chunk));
+        }
+        out.extend_from_slice(&stream.finish());
+        assert_eq!(gzip_decompress(&out, usize::MAX).unwrap(), data);
+        assert_eq!(gzip_decompress(&GzipStream::new().finish(), usize::MAX).unwrap(), b"");
+        assert_eq!(crc32(0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+}
@@ -0,0 +1,500 @@
+use async_trait::async_trait;
+use base64::prelude::*;
+use futures_util::stream;
+use hyper::body::HttpBody;
+use hyper::header::{ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE, VARY, WARNING, WWW_AUTHENTICATE};
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value as JsonValue};
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+use crate::cli::OutputFormatter;
+use crate::connectors::connector_debug_log;
+use crate::connectors::debug_log::parse_switch;
+use crate::protocol::compression::{gzip_compress, GzipStream};
+#[cfg(feature = "web-console")]
+use crate::protocol::web_console::{ConsoleHandler, WebConsole};
+use crate::utils::{
+    config::{HttpApiConfig, HttpAuthConfig},
+    error::{DispatcherError, NirvError, NirvResult},
+    types::{QueryResult, Value},
+};
+
+/// Executes SQL on behalf of the HTTP API
+#[async_trait]
+pub trait QueryHandler: Send + Sync {
+    /// Execute a SQL statement and return its result
+    async fn handle_query(&self, sql: &str) -> NirvResult<QueryResult>;
+
+    /// Execute a SQL statement for a network client: for the user it authenticated as, under
+    /// their privileges and quotas, or for an anonymous client without a user
+    async fn handle_query_for(&self, user: Option<&str>, sql: &str) -> NirvResult<QueryResult>;
+}
+
+/// Who a request authenticated as
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Caller {
+    /// A configured user, by basic credentials or one of their tokens
+    User(String),
+    /// No credentials are configured, or a token of no user was sent
+    Anonymous,
+}
+
+impl Caller {
+    fn user(&self) -> Option<&str> {
+        match self {
+            Caller::User(user) => Some(user),
+            Caller::Anonymous => None,
+        }
+    }
+}
+
+/// Response formats supported by `POST /query`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpResponseFormat {
+    Json,
+    NdJson,
+    Csv,
+}
+
+impl HttpResponseFormat {
+    /// Pick a format from an Accept header, defaulting to JSON; `None` if nothing acceptable
+    pub fn negotiate(accept: Option<&str>) -> Option<Self> {
+        let Some(accept) = accept else {
+            return Some(HttpResponseFormat::Json);
+        };
+
+        accept.split(',')
+            .map(|media| media.split(';').next().unwrap_or("").trim())
+            .find_map(|media| match media {
+                "application/json" | "application/*" | "*/*" => Some(HttpResponseFormat::Json),
+                "application/x-ndjson" | "application/ndjson" => Some(HttpResponseFormat::NdJson),
+                "text/csv" | "text/*" => Some(HttpResponseFormat::Csv),
+                _ => None,
+            })
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            HttpResponseFormat::Json => "application/json",
+            HttpResponseFormat::NdJson => "application/x-ndjson",
+            HttpResponseFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/// NDJSON rows compressed and flushed together when the response is gzipped
+const NDJSON_GZIP_BATCH_ROWS: usize = 256;
+
+/// Whether an Accept-Encoding header allows gzip
+fn accepts_gzip(accept_encoding: Option<&str>) -> bool {
+    accept_encoding.is_some_and(|header| header.split(',').any(|coding| {
+        let mut parts = coding.split(';');
+        let name = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let refused = parts.any(|param| param.trim().strip_prefix("q=")
+            .is_some_and(|q| q.trim().parse::<f32>().is_ok_and(|q| q == 0.0)));
+        matches!(name.as_str(), "gzip" | "x-gzip") && !refused
+    }))
+}
+
+/// HTTP server exposing `POST /query` and `GET /health`, and the admin console under
+/// `/console` when built with the `web-console` feature
+pub struct HttpApiServer {
+    state: HttpApiState,
+}
+
+/// What the server answers requests with
+struct HttpApiState {
+    config: HttpApiConfig,
+    handler: Arc<dyn QueryHandler>,
+    #[cfg(feature = "web-console")]
+    console: Option<WebConsole>,
+}
+
+impl HttpApiServer {
+    /// Create a server executing queries through the given handler
+    pub fn new(config: HttpApiConfig, handler: Arc<dyn QueryHandler>) -> Self {
+        Self {
+            state: HttpApiState {
+                config,
+                handler,
+                #[cfg(feature = "web-console")]
+                console: None,
+            },
+        }
+    }
+
+    /// Serve the admin console, showing the sources and catalog of the given handler
+    #[cfg(feature = "web-console")]
+    pub fn with_console(mut self, handler: Arc<dyn ConsoleHandler>) -> Self {
+        self.state.console = Some(WebConsole::new(handler));
+        self
+    }
+
+    /// Bind to the configured address
+    pub async fn bind(&self) -> NirvResult<TcpListener> {
+        let bind_address = format!("{}:{}", self.state.config.bind_address, self.state.config.port);
+        TcpListener::bind(&bind_address).await
+            .map_err(|e| NirvError::Internal(format!("Failed to bind to {}: {}", bind_address, e)))
+    }
+
+    /// Serve requests on the listener until the shutdown future completes
+    pub async fn serve<F>(self, listener: TcpListener, shutdown: F) -> NirvResult<()>
+    where
+        F: Future<Output = ()>,
+    {
+        let incoming = AddrIncoming::from_listener(listener)
+            .map_err(|e| NirvError::Internal(format!("Failed to accept HTTP connections: {}", e)))?;
+
+        let state = Arc::new(self.state);
+        let make_service = make_service_fn(move |_| {
+            let state = state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(handle_request(&state, request).await) }
+                }))
+            }
+        });
+
+        Server::builder(incoming)
+            .serve(make_service)
+            .with_graceful_shutdown(shutdown)
+            .await
+            .map_err(|e| NirvError::Internal(format!("HTTP server error: {}", e)))
+    }
+}
+
+/// Route a single HTTP request
+async fn handle_request(state: &HttpApiState, request: Request<Body>) -> Response<Body> {
+    let config = &state.config;
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/health") => text_response(StatusCode::OK, "ok"),
+        (&Method::POST, "/query") => handle_query(state, request).await,
+        (_, "/query") => error_response(StatusCode::METHOD_NOT_ALLOWED, "Use POST for /query"),
+        #[cfg(feature = "web-console")]
+        (&Method::GET, path) if path == "/console" || path.starts_with("/console/") => handle_console(state, request).await,
+        (&Method::GET | &Method::PUT, "/admin/connector-debug") => handle_connector_debug(config, request).await,
+        (_, "/admin/connector-debug") => error_response(StatusCode::METHOD_NOT_ALLOWED, "Use GET or PUT for /admin/connector-debug"),
+        _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
+
+async fn handle_query(state: &HttpApiState, request: Request<Body>) -> Response<Body> {
+    let config = &state.config;
+    let header = |name| request.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+
+    let Some(caller) = authenticate(config, &request) else {
+        return unauthorized(config);
+    };
+
+    let Some(format) = HttpResponseFormat::negotiate(header(ACCEPT).as_deref()) else {
+        return error_response(
+            StatusCode::NOT_ACCEPTABLE,
+            "Supported formats: application/json, application/x-ndjson, text/csv",
+        );
+    };
+    let is_json_body = header(CONTENT_TYPE).is_some_and(|ct| ct.starts_with("application/json"));
+    let gzip = accepts_gzip(header(ACCEPT_ENCODING).as_deref());
+
+    let body = match read_body(request.into_body(), config.max_body_bytes).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let sql = match extract_sql(&body, is_json_body) {
+        Ok(sql) => sql,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+
+    let result = state.handler.handle_query_for(caller.user(), &sql).await;
+    #[cfg(feature = "web-console")]
+    if let Some(console) = &state.console {
+        console.history().record(&sql, &result);
+    }
+    match result {
+        Ok(result) => result_response(result, format, gzip),
+        Err(error) => error_response(error_status(&error), &error.to_string()),
+    }
+}
+
+/// Serve the console page, or with credentials the data it shows
+#[cfg(feature = "web-console")]
+async fn handle_console(state: &HttpApiState, request: Request<Body>) -> Response<Body> {
+    let Some(console) = &state.console else {
+        return error_response(StatusCode::NOT_FOUND, "Not found");
+    };
+    let path = request.uri().path();
+    if !WebConsole::is_page(path) && authenticate(&state.config, &request).is_none() {
+        return unauthorized(&state.config);
+    }
+    console.handle(path).await
+}
+
+/// Report connector debug logging with GET, or switch it with PUT and a body of `on` or `off`
+async fn handle_connector_debug(config: &HttpApiConfig, request: Request<Body>) -> Response<Body> {
+    if authenticate(config, &request).is_none() {
+        return unauthorized(config);
+    }
+
+    let debug_log = connector_debug_log();
+    if request.method() == Method::PUT {
+        let body = match read_body(request.into_body(), config.max_body_bytes).await {
+            Ok(body) => body,
+            Err(response) => return response,
+        };
+        match parse_switch(&String::from_utf8_lossy(&body)) {
+            Some(enabled) => debug_log.set_enabled(enabled),
+            None => return error_response(StatusCode::BAD_REQUEST, "Expected a body of 'on' or 'off'"),
+        }
+    }
+    text_response(StatusCode::OK, if debug_log.is_enabled() { "on" } else { "off" })
+}
+
+/// Who a request authenticated as, or None when it lacks valid credentials
+fn authenticate(config: &HttpApiConfig, request: &Request<Body>) -> Option<Caller> {
+    let authorization = request.headers().get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+    caller(&config.auth, authorization)
+}
+
+/// The 401 response for a request lacking valid credentials
+fn unauthorized(config: &HttpApiConfig) -> Response<Body> {
+    let challenge = if config.auth.basic_users.is_empty() { "Bearer" } else { "Basic realm=\"nirv\"" };
+    let mut response = error_response(StatusCode::UNAUTHORIZED, "Authentication required");
+    response.headers_mut().insert(WWW_AUTHENTICATE, challenge.parse().expect("static header value"));
+    response
+}
+
+/// Read the request body, rejecting bodies over the limit without buffering them
+async fn read_body(mut body: Body, limit: usize) -> Result<Vec<u8>, Response<Body>> {
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| error_response(StatusCode::BAD_REQUEST, &format!("Failed to read body: {}", e)))?;
+        if buffer.len() + chunk.len() > limit {
+            return Err(error_response(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large"));
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(buffer)
+}
+
+/// SQL comes either as a plain text body or as `{"sql": "..."}`
+fn extract_sql(body: &[u8], is_json: bool) -> Result<String, String> {
+    let sql = if is_json {
+        let value: JsonValue = serde_json::from_slice(body).map_err(|e| format!("Invalid JSON body: {}", e))?;
+        value.get("sql")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| "JSON body must contain a \"sql\" string".to_string())?
+            .to_string()
+    } else {
+        String::from_utf8(body.to_vec()).map_err(|_| "Request body must be UTF-8 SQL".to_string())?
+    };
+
+    if sql.trim().is_empty() {
+        return Err("Request body must contain a SQL statement".to_string());
+    }
+    Ok(sql)
+}
+
+/// Who an Authorization header authenticates as, or None when its credentials are not valid
+fn caller(auth: &HttpAuthConfig, authorization: Option<&str>) -> Option<Caller> {
+    if !auth.is_enabled() {
+        return Some(Caller::Anonymous);
+    }
+    let authorization = authorization?;
+
+    if let Some(token) = authorization.strip_prefix("Bearer ") {
+        let token = token.trim().as_bytes();
+        if let Some((user, _)) = auth.token_users.iter().find(|(_, expected)| constant_time_eq(expected.as_bytes(), token)) {
+            return Some(Caller::User(user.clone()));
+        }
+        return auth.tokens.iter().any(|t| constant_time_eq(t.as_bytes(), token)).then_some(Caller::Anonymous);
+    }
+
+    let encoded = authorization.strip_prefix("Basic ")?;
+    let decoded = BASE64_STANDARD.decode(encoded.trim()).ok().and_then(|bytes| String::from_utf8(bytes).ok())?;
+    let (username, password) = decoded.split_once(':')?;
+    auth.basic_users.get(username)
+        .is_some_and(|expected| constant_time_eq(expected.as_bytes(), password.as_bytes()))
+        .then(|| Caller::User(username.to_string()))
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Map engine errors to HTTP status codes
+fn error_status(error: &NirvError) -> StatusCode {
+    match error {
+        NirvError::QueryParsing(_) => StatusCode::BAD_REQUEST,
+        NirvError::QueryRewrite(_) => StatusCode::FORBIDDEN,
+        NirvError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+        NirvError::Lint(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        NirvError::Dispatcher(DispatcherError::AccessDenied(_)) => StatusCode::FORBIDDEN,
+        NirvError::Dispatcher(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        NirvError::Connector(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// A result in the negotiated format, gzipped when the client accepts it
+fn result_response(result: QueryResult, format: HttpResponseFormat, gzip: bool) -> Response<Body> {
+    // Sources missing from a partial result, whatever the body's format, escaped to ASCII
+    let warnings: Vec<String> = result.warnings.iter()
+        .map(|warning| format!("199 nirv \"{}\"", warning.to_string().escape_default()))
+        .collect();
+    let encode = |bytes: Vec<u8>| Body::from(if gzip { gzip_compress(&bytes) } else { bytes });
+    let body = match format {
+        HttpResponseFormat::Json => encode(OutputFormatter::format_json(&result).into_bytes()),
+        HttpResponseFormat::NdJson => {
+            // One JSON object per row, serialized as the body is streamed
+            let names: Vec<String> = result.columns.iter().map(|c| c.name.clone()).collect();
+            let mut lines = result.rows.into_iter().map(move |row| {
+                let object: serde_json::Map<String, JsonValue> = names.iter().cloned()
+                    .zip(row.values.iter().map(OutputFormatter::value_to_json))
+                    .collect();
+                format!("{}\n", JsonValue::Object(object))
+            });
+            if gzip {
+                // Each batch is flushed, so clients can read rows before the body ends
+                let mut gzip_stream = Some(GzipStream::new());
+                let chunks = std::iter::from_fn(move || {
+                    let batch: String = lines.by_ref().take(NDJSON_GZIP_BATCH_ROWS).collect();
+                    if batch.is_empty() {
+                        gzip_stream.take().map(GzipStream::finish)
+                    } else {
+                        gzip_stream.as_mut().map(|gzip_stream| gzip_stream.write(batch.as_bytes()))
+                    }
+                });
+                Body::wrap_stream(stream::iter(chunks.map(Ok::<_, Infallible>)))
+            } else {
+                Body::wrap_stream(stream::iter(lines.map(Ok::<_, Infallible>)))
+            }
+        }
+        HttpResponseFormat::Csv => encode(format_csv(&result)),
+    };
+
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, format.content_type())
+        .header(VARY, "Accept-Encoding");
+    if gzip {
+        response = response.header(CONTENT_ENCODING, "gzip");
+    }
+    for warning in warnings {
+        response = response.header(WARNING, warning);
+    }
+    response.body(body).expect("valid response")
+}
+
+/// CSV with a header row; NULL is an empty field and numbers keep full precision
+fn format_csv(result: &QueryResult) -> Vec<u8> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let _ = writer.write_record(result.columns.iter().map(|c| c.name.as_str()));
+    for row in &result.rows {
+        let _ = writer.write_record(row.values.iter().map(csv_field));
+    }
+    writer.into_inner().unwrap_or_default()
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => s.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Binary(b) => BASE64_STANDARD.encode(b),
+        Value::Null => String::new(),
+    }
+}
+
+fn text_response(status: StatusCode, text: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain")
+        .body(Body::from(text))
+        .expect("valid response")
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "error": message }).to_string()))
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_format() {
+        assert_eq!(HttpResponseFormat::negotiate(None), Some(HttpResponseFormat::Json));
+        assert_eq!(HttpResponseFormat::negotiate(Some("text/csv")), Some(HttpResponseFormat::Csv));
+        assert_eq!(HttpResponseFormat::negotiate(Some("application/x-ndjson")), Some(HttpResponseFormat::NdJson));
+        assert_eq!(
+            HttpResponseFormat::negotiate(Some("text/html, text/csv;q=0.9, */*;q=0.1")),
+            Some(HttpResponseFormat::Csv)
+        );
+        assert_eq!(HttpResponseFormat::negotiate(Some("image/png")), None);
+    }
+
+    #[test]
+    fn test_accepts_gzip() {
+        assert!(accepts_gzip(Some("gzip, deflate, br")));
+        assert!(accepts_gzip(Some("br;q=1.0, GZIP;q=0.5")));
+        assert!(!accepts_gzip(Some("gzip;q=0, identity")));
+        assert!(!accepts_gzip(Some("deflate")));
+        assert!(!accepts_gzip(None));
+    }
+
+    #[test]
+    fn test_authorization() {
+        let mut auth = HttpAuthConfig::default();
+        assert_eq!(caller(&auth, None), Some(Caller::Anonymous));
+
+        auth.tokens.push("secret".to_string());
+        auth.basic_users.insert("alice".to_string(), "pw".to_string());
+        auth.token_users.insert("bob".to_string(), "bobs-token".to_string());
+        assert_eq!(caller(&auth, None), None);
+        assert_eq!(caller(&auth, Some("Bearer secret")), Some(Caller::Anonymous));
+        assert_eq!(caller(&auth, Some("Bearer secreT")), None);
+        assert_eq!(caller(&auth, Some("Bearer bobs-token")), Some(Caller::User("bob".to_string())));
+
+        let basic = format!("Basic {}", BASE64_STANDARD.encode("alice:pw"));
+        assert_eq!(caller(&auth, Some(&basic)), Some(Caller::User("alice".to_string())));
+        let wrong = format!("Basic {}", BASE64_STANDARD.encode("alice:nope"));
+        assert_eq!(caller(&auth, Some(&wrong)), None);
+    }
+
+    #[test]
+    fn test_extract_sql() {
+        assert_eq!(extract_sql(b"SELECT 1", false).unwrap(), "SELECT 1");
+        assert_eq!(extract_sql(br#"{"sql": "SELECT 1"}"#, true).unwrap(), "SELECT 1");
+        assert!(extract_sql(br#"{"query": "SELECT 1"}"#, true).is_err());
+        assert!(extract_sql(b"   ", false).is_err());
+    }
+
+    #[test]
+    fn test_csv_format() {
+        use crate::utils::types::{ColumnMetadata, DataType, Row};
+
+        let mut result = QueryResult::new();
+        result.columns = vec![
+            ColumnMetadata { name: "name".to_string(), data_type: DataType::Text, nullable: false },
+            ColumnMetadata { name: "score".to_string(), data_type: DataType::Float, nullable: true },
+        ];
+        result.rows = vec![
+            Row::new(vec![Value::Text("a, b".to_string()), Value::Float(1.125)]),
+            Row::new(vec![Value::Text("c".to_string()), Value::Null]),
+        ];
+
+        let csv = String::from_utf8(format_csv(&result)).unwrap();
+        assert_eq!(csv, "name,score\n\"a, b\",1.125\nc,\n");
+    }
+}
@@ -1,15 +1,36 @@
 // Protocol adapter implementations
 pub mod protocol_trait;
+pub mod charset;
+pub mod tls;
 pub mod postgres_protocol;
+pub mod postgres_copy;
 pub mod mysql_protocol;
+pub mod mysql_prepared;
+pub mod mysql_auth;
+pub mod compression;
 pub mod sqlite_protocol;
 pub mod sqlserver_protocol;
+pub mod http_api;
+#[cfg(feature = "web-console")]
+pub mod web_console;
+pub mod schema_catalog;
+pub mod sqlserver_catalog;
 
 pub use protocol_trait::*;
+pub use charset::{TextEncoding, MySQLCollation, postgres_client_encoding};
+pub use tls::*;
 pub use postgres_protocol::*;
+pub use postgres_copy::{CopyOutRequest, CopyFormat, parse_copy_to_stdout};
 pub use mysql_protocol::*;
+pub use mysql_auth::{MySQLAuthPlugin, CachingSha2Cache, native_password_token, caching_sha2_token};
+pub use mysql_prepared::{MySQLPreparedStatement, infer_parameter_types, bind_parameters, bind_named_parameters};
 pub use sqlite_protocol::*;
 pub use sqlserver_protocol::*;
+pub use http_api::*;
+#[cfg(feature = "web-console")]
+pub use web_console::*;
+pub use schema_catalog::*;
+pub use sqlserver_catalog::answer_catalog_query;
 
 // Type aliases for convenience
 pub type PostgreSQLProtocolAdapter = postgres_protocol::PostgresProtocol;
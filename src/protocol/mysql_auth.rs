@@ -0,0 +1,216 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::protocol::http_api::constant_time_eq;
+
+/// Length of the authentication challenge sent in the handshake
+pub const SCRAMBLE_LENGTH: usize = 20;
+
+/// Authentication plugins the MySQL adapter can negotiate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MySQLAuthPlugin {
+    /// SHA-1 challenge-response used by MySQL 5.x clients
+    NativePassword,
+    /// SHA-256 challenge-response with a full-authentication fallback; the MySQL 8 default
+    CachingSha2Password,
+}
+
+impl MySQLAuthPlugin {
+    /// Plugin name as sent on the wire
+    pub fn name(&self) -> &'static str {
+        match self {
+            MySQLAuthPlugin::NativePassword => "mysql_native_password",
+            MySQLAuthPlugin::CachingSha2Password => "caching_sha2_password",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mysql_native_password" => Some(MySQLAuthPlugin::NativePassword),
+            "caching_sha2_password" => Some(MySQLAuthPlugin::CachingSha2Password),
+            _ => None,
+        }
+    }
+}
+
+/// Generate a challenge of printable characters, as clients expect no NUL bytes
+pub fn generate_scramble() -> Vec<u8> {
+    let mut rng = rand::rng();
+    (0..SCRAMBLE_LENGTH)
+        .map(|_| rng.random_range(b'0'..=b'z'))
+        .collect()
+}
+
+/// Client response for `mysql_native_password`:
+/// `SHA1(password) XOR SHA1(scramble + SHA1(SHA1(password)))`
+pub fn native_password_token(password: &str, scramble: &[u8]) -> Vec<u8> {
+    if password.is_empty() {
+        return Vec::new();
+    }
+
+    let stage1 = sha1(password.as_bytes());
+    let stage2 = sha1(&stage1);
+    let mut salted = scramble.to_vec();
+    salted.extend_from_slice(&stage2);
+    xor(&stage1, &sha1(&salted))
+}
+
+/// Client response for `caching_sha2_password` fast authentication:
+/// `SHA256(password) XOR SHA256(SHA256(SHA256(password)) + scramble)`
+pub fn caching_sha2_token(password: &str, scramble: &[u8]) -> Vec<u8> {
+    if password.is_empty() {
+        return Vec::new();
+    }
+
+    let stage1 = Sha256::digest(password.as_bytes());
+    let stage2 = Sha256::digest(stage1);
+    let mut hasher = Sha256::new();
+    hasher.update(stage2);
+    hasher.update(scramble);
+    xor(&stage1, &hasher.finalize())
+}
+
+/// Check a `mysql_native_password` response against the expected password
+pub fn verify_native_password(password: &str, scramble: &[u8], response: &[u8]) -> bool {
+    constant_time_eq(&native_password_token(password, scramble), response)
+}
+
+/// Password hashes of users who completed full `caching_sha2_password` authentication,
+/// enabling the fast challenge-response path on later connections
+#[derive(Debug, Default)]
+pub struct CachingSha2Cache {
+    entries: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl CachingSha2Cache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a user after full authentication with their plaintext password
+    pub fn insert(&self, username: &str, password: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(username.to_string(), double_sha256(password));
+        }
+    }
+
+    /// Verify a fast-authentication response. `None` when the user is not cached, or was
+    /// cached under a different password, so full authentication is required.
+    pub fn verify(&self, username: &str, password: &str, scramble: &[u8], response: &[u8]) -> Option<bool> {
+        let entries = self.entries.lock().ok()?;
+        let cached = entries.get(username)?;
+        if *cached != double_sha256(password) {
+            return None;
+        }
+        Some(constant_time_eq(&caching_sha2_token(password, scramble), response))
+    }
+}
+
+fn double_sha256(password: &str) -> Vec<u8> {
+    Sha256::digest(Sha256::digest(password.as_bytes())).to_vec()
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+/// SHA-1 (RFC 3174), needed only by the `mysql_native_password` scheme
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut schedule = [0u32; 80];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            schedule[i] = (schedule[i - 3] ^ schedule[i - 8] ^ schedule[i - 14] ^ schedule[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in schedule.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_sha1_vectors() {
+        assert_eq!(hex(&sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(&sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn test_native_password_verification() {
+        let scramble = generate_scramble();
+        assert_eq!(scramble.len(), SCRAMBLE_LENGTH);
+        assert!(!scramble.contains(&0));
+
+        let token = native_password_token("secret", &scramble);
+        assert_eq!(token.len(), 20);
+        assert!(verify_native_password("secret", &scramble, &token));
+        assert!(!verify_native_password("other", &scramble, &token));
+        assert!(verify_native_password("", &scramble, &[]));
+    }
+
+    #[test]
+    fn test_caching_sha2_cache() {
+        let cache = CachingSha2Cache::new();
+        let scramble = generate_scramble();
+        let token = caching_sha2_token("secret", &scramble);
+
+        assert_eq!(cache.verify("app", "secret", &scramble, &token), None);
+
+        cache.insert("app", "secret");
+        assert_eq!(cache.verify("app", "secret", &scramble, &token), Some(true));
+        assert_eq!(cache.verify("app", "secret", &scramble, &[0; 32]), Some(false));
+        // A changed password invalidates the cached entry
+        assert_eq!(cache.verify("app", "rotated", &scramble, &token), None);
+    }
+}
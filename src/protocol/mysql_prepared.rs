@@ -0,0 +1,590 @@
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::protocol::MySQLFieldType;
+use crate::utils::{NirvResult, ProtocolError, Value};
+
+/// Flag set on a bound parameter type when the value is unsigned
+const UNSIGNED_FLAG: u8 = 0x80;
+
+/// A statement registered through COM_STMT_PREPARE
+#[derive(Debug, Clone)]
+pub struct MySQLPreparedStatement {
+    pub id: u32,
+    pub sql: String,
+    /// Types reported to the client in the prepare response, inferred from the SQL
+    pub param_types: Vec<MySQLFieldType>,
+    /// Types the client last bound, as (type byte, unsigned) pairs; reused when an
+    /// execute does not rebind
+    pub bound_types: Option<Vec<(u8, bool)>>,
+    /// Parameter data streamed with COM_STMT_SEND_LONG_DATA since the last execute
+    pub long_data: HashMap<u16, Vec<u8>>,
+}
+
+impl MySQLPreparedStatement {
+    /// Prepare a statement, inferring its parameter types from the SQL
+    pub fn new(id: u32, sql: &str) -> Self {
+        Self {
+            id,
+            sql: sql.to_string(),
+            param_types: infer_parameter_types(sql),
+            bound_types: None,
+            long_data: HashMap::new(),
+        }
+    }
+
+    pub fn param_count(&self) -> usize {
+        self.param_types.len()
+    }
+
+    /// Decode the parameters of a COM_STMT_EXECUTE payload (after the statement id)
+    /// and return the statement SQL with the values bound in place of the placeholders
+    pub fn bind_execute(&mut self, payload: &[u8]) -> NirvResult<(String, Vec<Value>)> {
+        let values = self.decode_parameters(payload)?;
+        self.long_data.clear();
+        Ok((bind_parameters(&self.sql, &values)?, values))
+    }
+
+    fn decode_parameters(&mut self, payload: &[u8]) -> NirvResult<Vec<Value>> {
+        let count = self.param_count();
+        let mut reader = PacketReader::new(payload);
+        let _flags = reader.u8()?;
+        let _iteration_count = reader.bytes(4)?;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let null_bitmap = reader.bytes(count.div_ceil(8))?.to_vec();
+        if reader.u8()? == 1 {
+            let mut types = Vec::with_capacity(count);
+            for _ in 0..count {
+                let field_type = reader.u8()?;
+                let flags = reader.u8()?;
+                types.push((field_type, flags & UNSIGNED_FLAG != 0));
+            }
+            self.bound_types = Some(types);
+        }
+        let types = self.bound_types.clone().ok_or_else(|| ProtocolError::InvalidMessageFormat(
+            "Statement executed without parameter types".to_string()
+        ))?;
+
+        let mut values = Vec::with_capacity(count);
+        for (index, (field_type, unsigned)) in types.into_iter().enumerate() {
+            if null_bitmap[index / 8] & (1 << (index % 8)) != 0 {
+                values.push(Value::Null);
+            } else if let Some(data) = self.long_data.get(&(index as u16)) {
+                values.push(bytes_value(field_type, data.clone()));
+            } else {
+                values.push(decode_binary_value(&mut reader, field_type, unsigned)?);
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Minimal little-endian reader over a packet payload
+pub(crate) struct PacketReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub(crate) fn bytes(&mut self, length: usize) -> NirvResult<&'a [u8]> {
+        let end = self.position.checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| ProtocolError::InvalidMessageFormat("Packet ended unexpectedly".to_string()))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> NirvResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> NirvResult<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> NirvResult<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> NirvResult<u64> {
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(buffer))
+    }
+
+    fn length_encoded_integer(&mut self) -> NirvResult<u64> {
+        match self.u8()? {
+            0xfc => Ok(self.u16()? as u64),
+            0xfd => {
+                let bytes = self.bytes(3)?;
+                Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]) as u64)
+            }
+            0xfe => self.u64(),
+            value => Ok(value as u64),
+        }
+    }
+
+    pub(crate) fn length_encoded_bytes(&mut self) -> NirvResult<&'a [u8]> {
+        let length = self.length_encoded_integer()? as usize;
+        self.bytes(length)
+    }
+
+    pub(crate) fn remaining(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+}
+
+/// Decode one parameter value in the binary protocol encoding of the given type
+fn decode_binary_value(reader: &mut PacketReader<'_>, field_type: u8, unsigned: bool) -> NirvResult<Value> {
+    const TINY: u8 = MySQLFieldType::Tiny as u8;
+    const SHORT: u8 = MySQLFieldType::Short as u8;
+    const YEAR: u8 = MySQLFieldType::Year as u8;
+    const LONG: u8 = MySQLFieldType::Long as u8;
+    const INT24: u8 = MySQLFieldType::Int24 as u8;
+    const LONGLONG: u8 = MySQLFieldType::LongLong as u8;
+    const FLOAT: u8 = MySQLFieldType::Float as u8;
+    const DOUBLE: u8 = MySQLFieldType::Double as u8;
+    const NULL: u8 = MySQLFieldType::Null as u8;
+    const DATE: u8 = MySQLFieldType::Date as u8;
+    const DATETIME: u8 = MySQLFieldType::DateTime as u8;
+    const TIMESTAMP: u8 = MySQLFieldType::Timestamp as u8;
+    const TIME: u8 = MySQLFieldType::Time as u8;
+
+    let value = match field_type {
+        NULL => Value::Null,
+        TINY if unsigned => Value::Integer(reader.u8()? as i64),
+        TINY => Value::Integer(reader.u8()? as i8 as i64),
+        SHORT | YEAR if unsigned => Value::Integer(reader.u16()? as i64),
+        SHORT | YEAR => Value::Integer(reader.u16()? as i16 as i64),
+        LONG | INT24 if unsigned => Value::Integer(reader.u32()? as i64),
+        LONG | INT24 => Value::Integer(reader.u32()? as i32 as i64),
+        LONGLONG => {
+            let raw = reader.u64()?;
+            match i64::try_from(raw) {
+                Ok(value) if unsigned => Value::Integer(value),
+                Err(_) if unsigned => Value::Text(raw.to_string()),
+                _ => Value::Integer(raw as i64),
+            }
+        }
+        FLOAT => Value::Float(f32::from_bits(reader.u32()?) as f64),
+        DOUBLE => Value::Float(f64::from_bits(reader.u64()?)),
+        DATE | DATETIME | TIMESTAMP => decode_datetime(reader, field_type == DATE)?,
+        TIME => decode_time(reader)?,
+        _ => bytes_value(field_type, reader.length_encoded_bytes()?.to_vec()),
+    };
+    Ok(value)
+}
+
+/// Interpret string-encoded parameter data: decimals become numbers, blobs stay binary
+fn bytes_value(field_type: u8, data: Vec<u8>) -> Value {
+    let is_blob = (MySQLFieldType::TinyBlob as u8..=MySQLFieldType::Blob as u8).contains(&field_type);
+    let is_decimal = field_type == MySQLFieldType::Decimal as u8 || field_type == MySQLFieldType::NewDecimal as u8;
+
+    match String::from_utf8(data) {
+        Ok(text) if is_decimal => text.parse::<f64>().map(Value::Float).unwrap_or(Value::Text(text)),
+        Ok(text) if !is_blob => Value::Text(text),
+        Ok(text) => Value::Binary(text.into_bytes()),
+        Err(error) => Value::Binary(error.into_bytes()),
+    }
+}
+
+fn decode_datetime(reader: &mut PacketReader<'_>, date_only: bool) -> NirvResult<Value> {
+    let length = reader.u8()? as usize;
+    let fields = reader.bytes(length)?;
+    let field = |index: usize| fields.get(index).copied().unwrap_or(0) as u32;
+
+    let year = if length >= 2 { u16::from_le_bytes([fields[0], fields[1]]) as i32 } else { 0 };
+    let date = format!("{:04}-{:02}-{:02}", year, field(2), field(3));
+    if date_only {
+        return Ok(Value::Date(date));
+    }
+
+    let mut datetime = format!("{} {:02}:{:02}:{:02}", date, field(4), field(5), field(6));
+    if length >= 11 {
+        let micros = u32::from_le_bytes([fields[7], fields[8], fields[9], fields[10]]);
+        datetime.push_str(&format!(".{:06}", micros));
+    }
+    Ok(Value::DateTime(datetime))
+}
+
+fn decode_time(reader: &mut PacketReader<'_>) -> NirvResult<Value> {
+    let length = reader.u8()? as usize;
+    if length == 0 {
+        return Ok(Value::Text("00:00:00".to_string()));
+    }
+
+    let fields = reader.bytes(length)?;
+    if length < 8 {
+        return Err(ProtocolError::InvalidMessageFormat("Invalid TIME parameter".to_string()).into());
+    }
+    let negative = fields[0] == 1;
+    let days = u32::from_le_bytes([fields[1], fields[2], fields[3], fields[4]]);
+    let hours = days * 24 + fields[5] as u32;
+    let mut time = format!("{}{:02}:{:02}:{:02}", if negative { "-" } else { "" }, hours, fields[6], fields[7]);
+    if length >= 12 {
+        let micros = u32::from_le_bytes([fields[8], fields[9], fields[10], fields[11]]);
+        time.push_str(&format!(".{:06}", micros));
+    }
+    Ok(Value::Text(time))
+}
+
+/// Lexical token of a statement, as far as placeholder handling needs
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Keyword or identifier, uppercased
+    Word(String),
+    Placeholder,
+    /// `:name` placeholder, holding the name
+    NamedPlaceholder(String),
+    Symbol(char),
+    /// String literals, quoted identifiers and numbers
+    Literal,
+}
+
+/// Split SQL into tokens with their byte ranges, skipping whitespace and comments
+fn tokenize(sql: &str) -> Vec<(Token, Range<usize>)> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let start = index;
+        let c = bytes[index];
+        match c {
+            b'\'' | b'"' | b'`' => {
+                index += 1;
+                while index < bytes.len() {
+                    if bytes[index] == b'\\' && c != b'`' {
+                        index += 2;
+                        continue;
+                    }
+                    if bytes[index] == c {
+                        // A doubled quote is an escaped quote inside the literal
+                        if bytes.get(index + 1) == Some(&c) {
+                            index += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    index += 1;
+                }
+                index = (index + 1).min(bytes.len());
+                tokens.push((Token::Literal, start..index));
+            }
+            b'-' if bytes.get(index + 1) == Some(&b'-') => {
+                while index < bytes.len() && bytes[index] != b'\n' {
+                    index += 1;
+                }
+            }
+            b'#' => {
+                while index < bytes.len() && bytes[index] != b'\n' {
+                    index += 1;
+                }
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'*') => {
+                index += 2;
+                while index < bytes.len() && !(bytes[index] == b'*' && bytes.get(index + 1) == Some(&b'/')) {
+                    index += 1;
+                }
+                index = (index + 2).min(bytes.len());
+            }
+            b'?' => {
+                index += 1;
+                tokens.push((Token::Placeholder, start..index));
+            }
+            // `:name`, but not the `::` of a Postgres cast
+            b':' if start.checked_sub(1).map(|prev| bytes[prev]) != Some(b':')
+                && bytes.get(index + 1).is_some_and(|next| next.is_ascii_alphabetic() || *next == b'_') =>
+            {
+                index += 1;
+                while index < bytes.len() && (bytes[index].is_ascii_alphanumeric() || bytes[index] == b'_') {
+                    index += 1;
+                }
+                tokens.push((Token::NamedPlaceholder(sql[start + 1..index].to_string()), start..index));
+            }
+            c if c.is_ascii_whitespace() => index += 1,
+            c if c.is_ascii_digit() => {
+                while index < bytes.len() && (bytes[index].is_ascii_alphanumeric() || bytes[index] == b'.') {
+                    index += 1;
+                }
+                tokens.push((Token::Literal, start..index));
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' || c == b'@' || c == b'$' || !c.is_ascii() => {
+                while index < bytes.len()
+                    && (bytes[index].is_ascii_alphanumeric() || matches!(bytes[index], b'_' | b'@' | b'$') || !bytes[index].is_ascii())
+                {
+                    index += 1;
+                }
+                tokens.push((Token::Word(sql[start..index].to_ascii_uppercase()), start..index));
+            }
+            _ => {
+                index += 1;
+                tokens.push((Token::Symbol(c as char), start..index));
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Infer the type of each `?` placeholder from its context. Placeholders in LIMIT/OFFSET
+/// are integers and CAST/CONVERT targets give their type; anything else is reported as a
+/// string, which clients accept for any bound value.
+pub fn infer_parameter_types(sql: &str) -> Vec<MySQLFieldType> {
+    let tokens: Vec<Token> = tokenize(sql).into_iter().map(|(token, _)| token).collect();
+    let word = |index: Option<usize>| match index.and_then(|index| tokens.get(index)) {
+        Some(Token::Word(word)) => word.as_str(),
+        _ => "",
+    };
+    let symbol = |index: Option<usize>, expected: char| {
+        matches!(index.and_then(|index| tokens.get(index)), Some(Token::Symbol(c)) if *c == expected)
+    };
+
+    tokens.iter()
+        .enumerate()
+        .filter(|(_, token)| **token == Token::Placeholder)
+        .map(|(index, _)| {
+            let back = |distance: usize| index.checked_sub(distance);
+            let ahead = |distance: usize| Some(index + distance);
+
+            if matches!(word(back(1)), "LIMIT" | "OFFSET")
+                || (symbol(back(1), ',') && word(back(3)) == "LIMIT")
+            {
+                MySQLFieldType::LongLong
+            } else if symbol(back(1), '(')
+                && ((word(back(2)) == "CAST" && word(ahead(1)) == "AS")
+                    || (word(back(2)) == "CONVERT" && symbol(ahead(1), ',')))
+            {
+                cast_target_type(word(ahead(2)))
+            } else {
+                MySQLFieldType::VarString
+            }
+        })
+        .collect()
+}
+
+fn cast_target_type(target: &str) -> MySQLFieldType {
+    match target {
+        "SIGNED" | "UNSIGNED" | "INT" | "INTEGER" | "BIGINT" => MySQLFieldType::LongLong,
+        "DECIMAL" | "DOUBLE" | "FLOAT" | "REAL" => MySQLFieldType::Double,
+        "DATE" => MySQLFieldType::Date,
+        "DATETIME" | "TIMESTAMP" => MySQLFieldType::DateTime,
+        "TIME" => MySQLFieldType::Time,
+        _ => MySQLFieldType::VarString,
+    }
+}
+
+/// Substitute placeholders with SQL literals for the bound values
+pub fn bind_parameters(sql: &str, values: &[Value]) -> NirvResult<String> {
+    let placeholders: Vec<Range<usize>> = tokenize(sql).into_iter()
+        .filter(|(token, _)| *token == Token::Placeholder)
+        .map(|(_, range)| range)
+        .collect();
+    if placeholders.len() != values.len() {
+        return Err(ProtocolError::InvalidMessageFormat(format!(
+            "Statement has {} parameters but {} values were bound", placeholders.len(), values.len()
+        )).into());
+    }
+
+    let mut bound = String::with_capacity(sql.len());
+    let mut last = 0;
+    for (range, value) in placeholders.into_iter().zip(values) {
+        bound.push_str(&sql[last..range.start]);
+        bound.push_str(&sql_literal(value));
+        last = range.end;
+    }
+    bound.push_str(&sql[last..]);
+    Ok(bound)
+}
+
+/// Substitute `:name` placeholders with SQL literals for the named values. Every
+/// placeholder must have a value and every value must be used, so typos surface as errors.
+pub fn bind_named_parameters(sql: &str, values: &HashMap<String, Value>) -> NirvResult<String> {
+    let mut bound = String::with_capacity(sql.len());
+    let mut last = 0;
+    let mut used = HashSet::new();
+    for (token, range) in tokenize(sql) {
+        let Token::NamedPlaceholder(name) = token else { continue };
+        let value = values.get(&name).ok_or_else(|| ProtocolError::InvalidMessageFormat(
+            format!("No value bound for parameter :{}", name)
+        ))?;
+        bound.push_str(&sql[last..range.start]);
+        bound.push_str(&sql_literal(value));
+        last = range.end;
+        used.insert(name);
+    }
+    bound.push_str(&sql[last..]);
+
+    let mut unused: Vec<&String> = values.keys().filter(|name| !used.contains(*name)).collect();
+    unused.sort();
+    if let Some(name) = unused.first() {
+        return Err(ProtocolError::InvalidMessageFormat(
+            format!("Parameter :{} does not appear in the statement", name)
+        ).into());
+    }
+    Ok(bound)
+}
+
+fn sql_literal(value: &Value) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) if f.is_finite() => f.to_string(),
+        Value::Float(_) => "NULL".to_string(),
+        Value::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => quote(s),
+        Value::Binary(bytes) => format!("X'{}'", bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>()),
+    }
+}
+
+/// Binary protocol encoding of a DATE value: length byte then year, month, day
+pub(crate) fn encode_binary_date(value: &str) -> Vec<u8> {
+    match NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d") {
+        Ok(date) => {
+            use chrono::Datelike;
+            let mut encoded = vec![4];
+            encoded.extend_from_slice(&(date.year() as u16).to_le_bytes());
+            encoded.push(date.month() as u8);
+            encoded.push(date.day() as u8);
+            encoded
+        }
+        Err(_) => vec![0],
+    }
+}
+
+/// Binary protocol encoding of a DATETIME value, with microseconds only when non-zero
+pub(crate) fn encode_binary_datetime(value: &str) -> Vec<u8> {
+    use chrono::{Datelike, NaiveDateTime, Timelike};
+
+    let normalized = value.replace('T', " ");
+    let normalized = normalized.trim_end_matches('Z');
+    let parsed = NaiveDateTime::parse_from_str(normalized, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDate::parse_from_str(normalized, "%Y-%m-%d").map(|date| date.and_time(Default::default())));
+    let Ok(datetime) = parsed else {
+        return vec![0];
+    };
+
+    let micros = datetime.nanosecond() / 1_000;
+    let mut encoded = vec![if micros > 0 { 11 } else { 7 }];
+    encoded.extend_from_slice(&(datetime.year() as u16).to_le_bytes());
+    encoded.extend_from_slice(&[
+        datetime.month() as u8,
+        datetime.day() as u8,
+        datetime.hour() as u8,
+        datetime.minute() as u8,
+        datetime.second() as u8,
+    ]);
+    if micros > 0 {
+        encoded.extend_from_slice(&micros.to_le_bytes());
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_parameter_types() {
+        let types = infer_parameter_types(
+            "SELECT * FROM users WHERE name = ? AND note <> '?' AND created > CAST(? AS DATETIME) LIMIT ?, ?"
+        );
+        assert_eq!(types, vec![
+            MySQLFieldType::VarString,
+            MySQLFieldType::DateTime,
+            MySQLFieldType::LongLong,
+            MySQLFieldType::LongLong,
+        ]);
+
+        assert_eq!(infer_parameter_types("SELECT CONVERT(?, SIGNED) -- ?\n/* ? */"), vec![MySQLFieldType::LongLong]);
+        assert!(infer_parameter_types("SELECT 'it''s ?'").is_empty());
+    }
+
+    #[test]
+    fn test_bind_parameters() {
+        let sql = bind_parameters(
+            "SELECT * FROM t WHERE a = ? AND b = '?' AND c IN (?, ?)",
+            &[Value::Text("O'Brien".to_string()), Value::Integer(7), Value::Null],
+        ).unwrap();
+        assert_eq!(sql, "SELECT * FROM t WHERE a = 'O''Brien' AND b = '?' AND c IN (7, NULL)");
+
+        assert!(bind_parameters("SELECT ?", &[]).is_err());
+    }
+
+    #[test]
+    fn test_bind_named_parameters() {
+        let values = HashMap::from([
+            ("id".to_string(), Value::Integer(42)),
+            ("name".to_string(), Value::Text("O'Brien".to_string())),
+        ]);
+        let sql = bind_named_parameters(
+            "SELECT x::text FROM t WHERE id = :id AND note <> ':id' AND (name = :name OR alias = :name)",
+            &values,
+        ).unwrap();
+        assert_eq!(sql, "SELECT x::text FROM t WHERE id = 42 AND note <> ':id' AND (name = 'O''Brien' OR alias = 'O''Brien')");
+
+        assert!(bind_named_parameters("SELECT :missing", &values).is_err());
+        assert!(bind_named_parameters("SELECT :id", &values).is_err());
+        assert!(infer_parameter_types("SELECT :id").is_empty());
+    }
+
+    #[test]
+    fn test_decode_execute_parameters() {
+        let mut statement = MySQLPreparedStatement::new(1, "SELECT ? , ?, ?, ?");
+        statement.long_data.insert(3, b"streamed".to_vec());
+
+        let mut payload = vec![0x00, 1, 0, 0, 0];
+        payload.push(0b0000_0010); // second parameter is NULL
+        payload.push(1); // new parameter types bound
+        payload.extend_from_slice(&[
+            MySQLFieldType::LongLong as u8, 0,
+            MySQLFieldType::VarString as u8, 0,
+            MySQLFieldType::DateTime as u8, 0,
+            MySQLFieldType::Blob as u8, 0,
+        ]);
+        payload.extend_from_slice(&(-5i64).to_le_bytes());
+        payload.extend_from_slice(&[7, 0xe8, 0x07, 5, 1, 13, 30, 0]);
+
+        let (sql, values) = statement.bind_execute(&payload).unwrap();
+        assert_eq!(values, vec![
+            Value::Integer(-5),
+            Value::Null,
+            Value::DateTime("2024-05-01 13:30:00".to_string()),
+            Value::Binary(b"streamed".to_vec()),
+        ]);
+        assert_eq!(sql, "SELECT -5 , NULL, '2024-05-01 13:30:00', X'73747265616D6564'");
+        assert!(statement.long_data.is_empty());
+
+        // A later execute may reuse the previously bound types
+        let mut payload = vec![0x00, 1, 0, 0, 0, 0, 0];
+        payload.extend_from_slice(&1i64.to_le_bytes());
+        payload.extend_from_slice(&[1, b'x']);
+        payload.extend_from_slice(&[4, 0xe8, 0x07, 1, 2]);
+        payload.extend_from_slice(&[1, b'y']);
+        let (_, values) = statement.bind_execute(&payload).unwrap();
+        assert_eq!(values[1], Value::Text("x".to_string()));
+        assert_eq!(values[2], Value::DateTime("2024-01-02 00:00:00".to_string()));
+    }
+
+    #[test]
+    fn test_encode_binary_temporal_values() {
+        assert_eq!(encode_binary_date("2024-05-01"), vec![4, 0xe8, 0x07, 5, 1]);
+        assert_eq!(encode_binary_datetime("2024-05-01T13:30:00Z"), vec![7, 0xe8, 0x07, 5, 1, 13, 30, 0]);
+        assert_eq!(encode_binary_datetime("2024-05-01 13:30:00.5")[0], 11);
+        assert_eq!(encode_binary_datetime("not a date"), vec![0]);
+    }
+}
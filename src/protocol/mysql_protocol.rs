@@ -1,9 +1,18 @@
 use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-use crate::protocol::{ProtocolAdapter, ProtocolType, Connection, Credentials, ProtocolQuery, ProtocolResponse};
-use crate::utils::{NirvResult, ProtocolError, QueryResult, ColumnMetadata, Row, Value, DataType};
+use crate::protocol::{ProtocolAdapter, ProtocolType, Connection, Credentials, ProtocolQuery, ProtocolResponse, StatementResult, read_body, read_header};
+use crate::protocol::mysql_prepared::{MySQLPreparedStatement, PacketReader, encode_binary_date, encode_binary_datetime};
+use crate::protocol::mysql_auth::{MySQLAuthPlugin, CachingSha2Cache, generate_scramble, verify_native_password};
+use crate::protocol::http_api::constant_time_eq;
+use crate::protocol::charset::{MySQLCollation, MYSQL_BINARY_COLLATION, MYSQL_DEFAULT_COLLATION};
+use crate::protocol::compression::{zlib_compress, zlib_decompress};
+use tokio_native_tls::TlsAcceptor;
+use crate::utils::{NirvError, NirvResult, ProtocolError, QueryResult, ColumnMetadata, Row, Value, DataType};
 
 /// MySQL protocol version
 const MYSQL_PROTOCOL_VERSION: u8 = 10;
@@ -14,7 +23,6 @@ const CLIENT_FOUND_ROWS: u32 = 0x00000002;
 const CLIENT_LONG_FLAG: u32 = 0x00000004;
 const CLIENT_CONNECT_WITH_DB: u32 = 0x00000008;
 const CLIENT_NO_SCHEMA: u32 = 0x00000010;
-#[allow(dead_code)]
 const CLIENT_COMPRESS: u32 = 0x00000020;
 #[allow(dead_code)]
 const CLIENT_ODBC: u32 = 0x00000040;
@@ -25,7 +33,6 @@ const CLIENT_IGNORE_SPACE: u32 = 0x00000100;
 const CLIENT_PROTOCOL_41: u32 = 0x00000200;
 #[allow(dead_code)]
 const CLIENT_INTERACTIVE: u32 = 0x00000400;
-#[allow(dead_code)]
 const CLIENT_SSL: u32 = 0x00000800;
 #[allow(dead_code)]
 const CLIENT_IGNORE_SIGPIPE: u32 = 0x00001000;
@@ -35,6 +42,41 @@ const CLIENT_RESERVED: u32 = 0x00004000;
 const CLIENT_SECURE_CONNECTION: u32 = 0x00008000;
 const CLIENT_MULTI_STATEMENTS: u32 = 0x00010000;
 const CLIENT_MULTI_RESULTS: u32 = 0x00020000;
+const CLIENT_PLUGIN_AUTH: u32 = 0x00080000;
+const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA: u32 = 0x00200000;
+
+/// Status flag on the OK or EOF packet ending a result that another result follows
+const SERVER_MORE_RESULTS_EXISTS: u16 = 0x0008;
+
+/// Connection parameter holding the handshake challenge until authentication
+const SCRAMBLE_PARAMETER: &str = "mysql_auth_scramble";
+
+/// `caching_sha2_password` status bytes sent after the client's scrambled password
+const FAST_AUTH_SUCCESS: u8 = 0x03;
+const PERFORM_FULL_AUTHENTICATION: u8 = 0x04;
+
+/// Connection parameter holding the collation negotiated in the handshake
+const COLLATION_PARAMETER: &str = "collation_connection";
+
+/// Connection parameter set when the client negotiated `CLIENT_COMPRESS`
+pub const COMPRESSION_PARAMETER: &str = "compression";
+
+/// Payloads shorter than this are sent uncompressed in compressed frames, as MySQL does
+const MIN_COMPRESS_LENGTH: usize = 50;
+
+/// Largest payload of a packet, plain or compressed
+const MAX_PACKET_PAYLOAD: usize = 0xff_ffff;
+
+/// Parsed HandshakeResponse41 packet
+#[derive(Debug, Clone)]
+struct HandshakeResponse {
+    capabilities: u32,
+    collation: MySQLCollation,
+    username: String,
+    auth_response: Vec<u8>,
+    database: String,
+    auth_plugin: Option<String>,
+}
 
 /// MySQL command types
 #[derive(Debug, Clone, PartialEq)]
@@ -103,11 +145,30 @@ pub enum MySQLFieldType {
 }
 
 /// MySQL protocol adapter implementation
-#[derive(Debug)]
 pub struct MySQLProtocolAdapter {
     server_version: String,
     connection_id: u32,
     capabilities: u32,
+    /// Plugin clients are asked to authenticate with
+    auth_plugin: MySQLAuthPlugin,
+    /// Users eligible for `caching_sha2_password` fast authentication
+    sha2_cache: CachingSha2Cache,
+    /// Acceptor for clients that request TLS; TLS is not offered when unset
+    tls_acceptor: Option<TlsAcceptor>,
+    /// Statements registered with COM_STMT_PREPARE, by statement id
+    statements: Mutex<HashMap<u32, MySQLPreparedStatement>>,
+    next_statement_id: AtomicU32,
+}
+
+impl std::fmt::Debug for MySQLProtocolAdapter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MySQLProtocolAdapter")
+            .field("server_version", &self.server_version)
+            .field("connection_id", &self.connection_id)
+            .field("auth_plugin", &self.auth_plugin)
+            .field("tls", &self.tls_acceptor.is_some())
+            .finish()
+    }
 }
 
 impl MySQLProtocolAdapter {
@@ -125,12 +186,108 @@ impl MySQLProtocolAdapter {
                 | CLIENT_TRANSACTIONS
                 | CLIENT_SECURE_CONNECTION
                 | CLIENT_MULTI_STATEMENTS
-                | CLIENT_MULTI_RESULTS,
+                | CLIENT_MULTI_RESULTS
+                | CLIENT_COMPRESS,
+            auth_plugin: MySQLAuthPlugin::CachingSha2Password,
+            sha2_cache: CachingSha2Cache::new(),
+            tls_acceptor: None,
+            statements: Mutex::new(HashMap::new()),
+            next_statement_id: AtomicU32::new(1),
         }
     }
+
+    /// Set the authentication plugin requested from clients
+    pub fn with_auth_plugin(mut self, auth_plugin: MySQLAuthPlugin) -> Self {
+        self.auth_plugin = auth_plugin;
+        self
+    }
+
+    /// Offer TLS to clients, enabling `caching_sha2_password` full authentication
+    pub fn with_tls(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls_acceptor = Some(acceptor);
+        self
+    }
+
+    /// Offer or stop offering `CLIENT_COMPRESS`, which is offered by default
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.capabilities |= CLIENT_COMPRESS;
+        } else {
+            self.capabilities &= !CLIENT_COMPRESS;
+        }
+        self
+    }
+
+    /// Whether the client negotiated compressed packets for the connection
+    pub fn is_compressed(&self, conn: &Connection) -> bool {
+        conn.parameters.contains_key(COMPRESSION_PARAMETER)
+    }
+
+    /// Frame packets for a connection: as they are, or inside compressed packets once
+    /// compression was negotiated. Compressed packets carry their own sequence ids, starting
+    /// at 1 in replies as the client's command was compressed packet 0.
+    pub fn frame_packets(&self, conn: &Connection, packets: Vec<u8>) -> Vec<u8> {
+        if !self.is_compressed(conn) {
+            return packets;
+        }
+        let mut framed = Vec::new();
+        for (i, chunk) in packets.chunks(MAX_PACKET_PAYLOAD).enumerate() {
+            let compressed = (chunk.len() >= MIN_COMPRESS_LENGTH).then(|| zlib_compress(chunk))
+                .filter(|compressed| compressed.len() < chunk.len());
+            let (payload, uncompressed_length) = match &compressed {
+                Some(compressed) => (compressed.as_slice(), chunk.len()),
+                // A zero uncompressed length marks a payload sent as it is
+                None => (chunk, 0),
+            };
+            framed.extend_from_slice(&(payload.len() as u32).to_le_bytes()[..3]);
+            framed.push((i + 1) as u8);
+            framed.extend_from_slice(&(uncompressed_length as u32).to_le_bytes()[..3]);
+            framed.extend_from_slice(payload);
+        }
+        framed
+    }
+
+    /// The packets inside the compressed packets a client sent, or the data unchanged when
+    /// compression was not negotiated
+    pub fn unframe_packets(&self, conn: &Connection, data: &[u8]) -> NirvResult<Vec<u8>> {
+        if !self.is_compressed(conn) {
+            return Ok(data.to_vec());
+        }
+        let mut packets = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            if rest.len() < 7 {
+                return Err(ProtocolError::InvalidMessageFormat("Compressed packet header too short".to_string()).into());
+            }
+            let length = u32::from_le_bytes([rest[0], rest[1], rest[2], 0]) as usize;
+            let uncompressed_length = u32::from_le_bytes([rest[4], rest[5], rest[6], 0]) as usize;
+            let payload = rest.get(7..7 + length)
+                .ok_or_else(|| ProtocolError::InvalidMessageFormat("Compressed packet truncated".to_string()))?;
+            if uncompressed_length == 0 {
+                packets.extend_from_slice(payload);
+            } else {
+                let inflated = zlib_decompress(payload, uncompressed_length)?;
+                if inflated.len() != uncompressed_length {
+                    return Err(ProtocolError::InvalidMessageFormat("Compressed packet length mismatch".to_string()).into());
+                }
+                packets.extend_from_slice(&inflated);
+            }
+            rest = &rest[7 + length..];
+        }
+        Ok(packets)
+    }
+
+    /// Capabilities advertised in the handshake
+    fn server_capabilities(&self) -> u32 {
+        let mut capabilities = self.capabilities | CLIENT_PLUGIN_AUTH | CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA;
+        if self.tls_acceptor.is_some() {
+            capabilities |= CLIENT_SSL;
+        }
+        capabilities
+    }
     
-    /// Create initial handshake packet
-    fn create_handshake_packet(&self) -> Vec<u8> {
+    /// Create initial handshake packet carrying the authentication challenge
+    fn create_handshake_packet(&self, scramble: &[u8]) -> Vec<u8> {
         let mut packet = Vec::new();
         
         // Protocol version
@@ -144,22 +301,23 @@ impl MySQLProtocolAdapter {
         packet.extend_from_slice(&self.connection_id.to_le_bytes());
         
         // Auth plugin data part 1 (8 bytes)
-        packet.extend_from_slice(b"12345678");
+        packet.extend_from_slice(&scramble[..8]);
         
         // Filler (1 byte)
         packet.push(0);
         
         // Capability flags lower 2 bytes
-        packet.extend_from_slice(&(self.capabilities as u16).to_le_bytes());
+        let capabilities = self.server_capabilities();
+        packet.extend_from_slice(&(capabilities as u16).to_le_bytes());
         
-        // Character set (1 byte) - UTF-8
-        packet.push(0x21);
+        // Character set (1 byte)
+        packet.push(MYSQL_DEFAULT_COLLATION.id);
         
         // Status flags (2 bytes)
         packet.extend_from_slice(&0u16.to_le_bytes());
         
         // Capability flags upper 2 bytes
-        packet.extend_from_slice(&((self.capabilities >> 16) as u16).to_le_bytes());
+        packet.extend_from_slice(&((capabilities >> 16) as u16).to_le_bytes());
         
         // Auth plugin data length (1 byte)
         packet.push(21);
@@ -168,11 +326,11 @@ impl MySQLProtocolAdapter {
         packet.extend_from_slice(&[0; 10]);
         
         // Auth plugin data part 2 (12 bytes + null terminator)
-        packet.extend_from_slice(b"123456789012");
+        packet.extend_from_slice(&scramble[8..]);
         packet.push(0);
         
         // Auth plugin name (null-terminated)
-        packet.extend_from_slice(b"mysql_native_password");
+        packet.extend_from_slice(self.auth_plugin.name().as_bytes());
         packet.push(0);
         
         self.wrap_packet(&packet, 0)
@@ -197,71 +355,126 @@ impl MySQLProtocolAdapter {
         packet
     }
     
-    /// Parse handshake response from client
-    fn parse_handshake_response(&self, data: &[u8]) -> NirvResult<(String, String, String)> {
-        if data.len() < 32 {
-            return Err(ProtocolError::InvalidMessageFormat("Handshake response too short".to_string()).into());
-        }
-        
-        let mut pos = 4; // Skip packet header
-        
-        // Client capabilities (4 bytes)
-        let _client_capabilities = u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
-        pos += 4;
-        
-        // Max packet size (4 bytes)
-        let _max_packet_size = u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
-        pos += 4;
-        
-        // Character set (1 byte)
-        let _charset = data[pos];
-        pos += 1;
-        
-        // Reserved (23 bytes)
-        pos += 23;
-        
-        // Username (null-terminated)
-        let username_start = pos;
-        while pos < data.len() && data[pos] != 0 {
-            pos += 1;
-        }
-        let username = String::from_utf8_lossy(&data[username_start..pos]).to_string();
-        pos += 1; // Skip null terminator
-        
-        // Password length (1 byte)
-        if pos >= data.len() {
-            return Err(ProtocolError::InvalidMessageFormat("Missing password length".to_string()).into());
-        }
-        let password_len = data[pos] as usize;
-        pos += 1;
-        
-        // Password (password_len bytes)
-        let password = if password_len > 0 {
-            if pos + password_len > data.len() {
-                return Err(ProtocolError::InvalidMessageFormat("Password data truncated".to_string()).into());
-            }
-            String::from_utf8_lossy(&data[pos..pos + password_len]).to_string()
+    /// Parse a HandshakeResponse41 payload from the client
+    fn parse_handshake_response(&self, data: &[u8]) -> NirvResult<HandshakeResponse> {
+        let mut reader = PacketReader::new(data);
+        let client_capabilities = reader.u32()?;
+        let _max_packet_size = reader.u32()?;
+        // Clients may request any collation; unknown ones fall back to the server default
+        let collation = MySQLCollation::from_id(reader.u8()?).unwrap_or(MYSQL_DEFAULT_COLLATION);
+        reader.bytes(23)?;
+
+        let null_terminated = |reader: &mut PacketReader<'_>| -> NirvResult<String> {
+            let rest = reader.remaining();
+            let end = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+            let value = collation.encoding.decode(&rest[..end]);
+            *reader = PacketReader::new(&rest[(end + 1).min(rest.len())..]);
+            Ok(value)
+        };
+
+        let username = null_terminated(&mut reader)?;
+        let auth_response = if client_capabilities & CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA != 0 {
+            reader.length_encoded_bytes()?.to_vec()
+        } else if client_capabilities & CLIENT_SECURE_CONNECTION != 0 {
+            let length = reader.u8()? as usize;
+            reader.bytes(length)?.to_vec()
         } else {
-            String::new()
+            null_terminated(&mut reader)?.into_bytes()
         };
-        pos += password_len;
-        
-        // Database (null-terminated, optional)
-        let database = if pos < data.len() {
-            let db_start = pos;
-            while pos < data.len() && data[pos] != 0 {
-                pos += 1;
-            }
-            String::from_utf8_lossy(&data[db_start..pos]).to_string()
+
+        let database = if client_capabilities & CLIENT_CONNECT_WITH_DB != 0 {
+            null_terminated(&mut reader)?
         } else {
             String::new()
         };
-        
-        Ok((username, password, database))
+        let auth_plugin = if client_capabilities & CLIENT_PLUGIN_AUTH != 0 {
+            Some(null_terminated(&mut reader)?).filter(|name| !name.is_empty())
+        } else {
+            None
+        };
+
+        Ok(HandshakeResponse { capabilities: client_capabilities, collation, username, auth_response, database, auth_plugin })
+    }
+
+    /// Read one packet, returning its sequence id and payload
+    async fn read_packet(&self, conn: &mut Connection) -> NirvResult<(u8, Vec<u8>)> {
+        let mut header = [0u8; 4];
+        conn.stream.read_exact(&mut header).await
+            .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to read packet: {}", e)))?;
+        let length = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+
+        let mut payload = vec![0u8; length];
+        conn.stream.read_exact(&mut payload).await
+            .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to read packet: {}", e)))?;
+        Ok((header[3], payload))
+    }
+
+    async fn send_packet(&self, conn: &mut Connection, payload: &[u8], sequence_id: u8) -> NirvResult<()> {
+        conn.stream.write_all(&self.wrap_packet(payload, sequence_id)).await
+            .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send packet: {}", e)).into())
+    }
+
+    /// Send an error packet and fail authentication with the same message
+    async fn reject(&self, conn: &mut Connection, sequence_id: u8, error_code: u16, message: &str) -> NirvResult<()> {
+        let error_packet = self.create_error_packet_with_sequence(error_code, message, sequence_id);
+        conn.stream.write_all(&error_packet).await
+            .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send error: {}", e)))?;
+        Err(ProtocolError::AuthenticationFailed(message.to_string()).into())
+    }
+
+    /// Verify a `caching_sha2_password` exchange: fast authentication for cached users,
+    /// otherwise full authentication with the plaintext password, which requires TLS
+    async fn authenticate_caching_sha2(
+        &self,
+        conn: &mut Connection,
+        sequence_id: &mut u8,
+        username: &str,
+        password: &str,
+        scramble: &[u8],
+        auth_response: &[u8],
+    ) -> NirvResult<bool> {
+        if password.is_empty() {
+            return Ok(auth_response.iter().all(|b| *b == 0));
+        }
+
+        if let Some(verified) = self.sha2_cache.verify(username, password, scramble, auth_response) {
+            if verified {
+                *sequence_id = sequence_id.wrapping_add(1);
+                self.send_packet(conn, &[0x01, FAST_AUTH_SUCCESS], *sequence_id).await?;
+            }
+            return Ok(verified);
+        }
+
+        *sequence_id = sequence_id.wrapping_add(1);
+        self.send_packet(conn, &[0x01, PERFORM_FULL_AUTHENTICATION], *sequence_id).await?;
+        let (client_sequence, full_response) = self.read_packet(conn).await?;
+        *sequence_id = client_sequence;
+
+        if !conn.stream.is_secure() {
+            // The client is asking for the RSA public key or sent an encrypted password
+            self.reject(
+                conn,
+                sequence_id.wrapping_add(1),
+                1045,
+                "caching_sha2_password full authentication requires a TLS connection",
+            ).await?;
+        }
+
+        let plaintext = full_response.strip_suffix(&[0]).unwrap_or(&full_response);
+        let verified = constant_time_eq(plaintext, password.as_bytes());
+        if verified {
+            self.sha2_cache.insert(username, password);
+        }
+        Ok(verified)
     }
     
-    /// Create OK packet
-    fn create_ok_packet(&self, affected_rows: u64, last_insert_id: u64) -> Vec<u8> {
+    /// Create OK packet with an explicit sequence id
+    fn create_ok_packet_with_sequence(&self, affected_rows: u64, last_insert_id: u64, sequence_id: u8) -> Vec<u8> {
+        self.create_ok_packet_with_status(affected_rows, last_insert_id, 0, sequence_id)
+    }
+
+    /// Create OK packet with the given status flags
+    fn create_ok_packet_with_status(&self, affected_rows: u64, last_insert_id: u64, status: u16, sequence_id: u8) -> Vec<u8> {
         let mut packet = Vec::new();
         
         // OK packet header
@@ -274,16 +487,21 @@ impl MySQLProtocolAdapter {
         self.write_length_encoded_integer(&mut packet, last_insert_id);
         
         // Status flags (2 bytes)
-        packet.extend_from_slice(&0u16.to_le_bytes());
+        packet.extend_from_slice(&status.to_le_bytes());
         
         // Warnings (2 bytes)
         packet.extend_from_slice(&0u16.to_le_bytes());
         
-        self.wrap_packet(&packet, 2)
+        self.wrap_packet(&packet, sequence_id)
     }
     
     /// Create error packet
     fn create_error_packet(&self, error_code: u16, message: &str) -> Vec<u8> {
+        self.create_error_packet_with_sequence(error_code, message, 1)
+    }
+
+    /// Create error packet with an explicit sequence id
+    fn create_error_packet_with_sequence(&self, error_code: u16, message: &str, sequence_id: u8) -> Vec<u8> {
         let mut packet = Vec::new();
         
         // Error packet header
@@ -301,7 +519,7 @@ impl MySQLProtocolAdapter {
         // Error message
         packet.extend_from_slice(message.as_bytes());
         
-        self.wrap_packet(&packet, 1)
+        self.wrap_packet(&packet, sequence_id)
     }
     
     /// Create result set header
@@ -315,7 +533,20 @@ impl MySQLProtocolAdapter {
     }
     
     /// Create column definition packet
-    fn create_column_definition(&self, column: &ColumnMetadata, sequence_id: u8) -> Vec<u8> {
+    fn create_column_definition(&self, column: &ColumnMetadata, collation: &MySQLCollation, sequence_id: u8) -> Vec<u8> {
+        let field_type = self.nirv_type_to_mysql_type(&column.data_type);
+        self.create_field_definition(&column.name, field_type, column.nullable, collation, sequence_id)
+    }
+
+    /// Create a column definition packet for a field of the given MySQL type
+    fn create_field_definition(
+        &self,
+        name: &str,
+        field_type: MySQLFieldType,
+        nullable: bool,
+        collation: &MySQLCollation,
+        sequence_id: u8,
+    ) -> Vec<u8> {
         let mut packet = Vec::new();
         
         // Catalog (length-encoded string)
@@ -331,26 +562,27 @@ impl MySQLProtocolAdapter {
         self.write_length_encoded_string(&mut packet, "");
         
         // Name (length-encoded string)
-        self.write_length_encoded_string(&mut packet, &column.name);
+        self.write_length_encoded_text(&mut packet, name, collation);
         
         // Original name (length-encoded string)
-        self.write_length_encoded_string(&mut packet, &column.name);
+        self.write_length_encoded_text(&mut packet, name, collation);
         
         // Length of fixed-length fields (1 byte)
         packet.push(0x0c);
         
-        // Character set (2 bytes)
-        packet.extend_from_slice(&0x21u16.to_le_bytes()); // UTF-8
+        // Character set (2 bytes) - the connection collation for text, binary otherwise
+        let is_text = matches!(field_type, MySQLFieldType::VarString | MySQLFieldType::VarChar | MySQLFieldType::String);
+        let charset = if is_text { collation.id } else { MYSQL_BINARY_COLLATION };
+        packet.extend_from_slice(&(charset as u16).to_le_bytes());
         
         // Column length (4 bytes)
         packet.extend_from_slice(&0u32.to_le_bytes());
         
         // Column type
-        let field_type = self.nirv_type_to_mysql_type(&column.data_type);
         packet.push(field_type as u8);
         
         // Flags (2 bytes)
-        let flags: u16 = if column.nullable { 0 } else { 1 }; // NOT_NULL flag
+        let flags: u16 = if nullable { 0 } else { 1 }; // NOT_NULL flag
         packet.extend_from_slice(&flags.to_le_bytes());
         
         // Decimals (1 byte)
@@ -364,22 +596,32 @@ impl MySQLProtocolAdapter {
     
     /// Create EOF packet
     fn create_eof_packet(&self, sequence_id: u8) -> Vec<u8> {
+        self.create_eof_packet_with_warnings(0, sequence_id)
+    }
+    
+    /// Create EOF packet counting the warnings the statement raised
+    fn create_eof_packet_with_warnings(&self, warnings: u16, sequence_id: u8) -> Vec<u8> {
+        self.create_eof_packet_with_status(warnings, 0, sequence_id)
+    }
+    
+    /// Create EOF packet with the given status flags
+    fn create_eof_packet_with_status(&self, warnings: u16, status: u16, sequence_id: u8) -> Vec<u8> {
         let mut packet = Vec::new();
         
         // EOF packet header
         packet.push(0xfe);
         
         // Warnings (2 bytes)
-        packet.extend_from_slice(&0u16.to_le_bytes());
+        packet.extend_from_slice(&warnings.to_le_bytes());
         
         // Status flags (2 bytes)
-        packet.extend_from_slice(&0u16.to_le_bytes());
+        packet.extend_from_slice(&status.to_le_bytes());
         
         self.wrap_packet(&packet, sequence_id)
     }
     
     /// Create row data packet
-    fn create_row_packet(&self, row: &Row, sequence_id: u8) -> Vec<u8> {
+    fn create_row_packet(&self, row: &Row, collation: &MySQLCollation, sequence_id: u8) -> Vec<u8> {
         let mut packet = Vec::new();
         
         for value in &row.values {
@@ -387,9 +629,13 @@ impl MySQLProtocolAdapter {
                 Value::Null => {
                     packet.push(0xfb); // NULL value
                 }
+                Value::Binary(bytes) => {
+                    self.write_length_encoded_integer(&mut packet, bytes.len() as u64);
+                    packet.extend_from_slice(bytes);
+                }
                 _ => {
                     let value_str = self.value_to_string(value);
-                    self.write_length_encoded_string(&mut packet, &value_str);
+                    self.write_length_encoded_text(&mut packet, &value_str, collation);
                 }
             }
         }
@@ -397,6 +643,50 @@ impl MySQLProtocolAdapter {
         self.wrap_packet(&packet, sequence_id)
     }
     
+    /// Create a binary protocol row packet, encoding each value as its column's type
+    fn create_binary_row_packet(&self, columns: &[ColumnMetadata], row: &Row, collation: &MySQLCollation, sequence_id: u8) -> Vec<u8> {
+        let mut packet = vec![0x00];
+
+        // NULL bitmap with the binary protocol's offset of two bits
+        let mut null_bitmap = vec![0u8; (columns.len() + 7 + 2) / 8];
+        for (i, value) in row.values.iter().enumerate() {
+            if *value == Value::Null {
+                null_bitmap[(i + 2) / 8] |= 1 << ((i + 2) % 8);
+            }
+        }
+        packet.extend_from_slice(&null_bitmap);
+
+        for (column, value) in columns.iter().zip(&row.values) {
+            if *value == Value::Null {
+                continue;
+            }
+            match (&column.data_type, value) {
+                (DataType::Integer, Value::Integer(i)) => packet.extend_from_slice(&i.to_le_bytes()),
+                (DataType::Integer, Value::Float(f)) => packet.extend_from_slice(&(*f as i64).to_le_bytes()),
+                (DataType::Integer, Value::Boolean(b)) => packet.extend_from_slice(&(*b as i64).to_le_bytes()),
+                (DataType::Float, Value::Float(f)) => packet.extend_from_slice(&f.to_le_bytes()),
+                (DataType::Float, Value::Integer(i)) => packet.extend_from_slice(&(*i as f64).to_le_bytes()),
+                (DataType::Boolean, Value::Boolean(b)) => packet.push(*b as u8),
+                (DataType::Boolean, Value::Integer(i)) => packet.push((*i != 0) as u8),
+                (DataType::Date, Value::Date(d) | Value::DateTime(d) | Value::Text(d)) => {
+                    packet.extend_from_slice(&encode_binary_date(d));
+                }
+                (DataType::DateTime, Value::DateTime(d) | Value::Date(d) | Value::Text(d)) => {
+                    packet.extend_from_slice(&encode_binary_datetime(d));
+                }
+                (_, Value::Binary(bytes)) => {
+                    self.write_length_encoded_integer(&mut packet, bytes.len() as u64);
+                    packet.extend_from_slice(bytes);
+                }
+                // Numeric and temporal columns carrying other values, and all string
+                // columns, are sent as length-encoded strings
+                _ => self.write_length_encoded_text(&mut packet, &self.value_to_string(value), collation),
+            }
+        }
+
+        self.wrap_packet(&packet, sequence_id)
+    }
+
     /// Write length-encoded integer
     fn write_length_encoded_integer(&self, buffer: &mut Vec<u8>, value: u64) {
         if value < 251 {
@@ -421,6 +711,45 @@ impl MySQLProtocolAdapter {
         self.write_length_encoded_integer(buffer, bytes.len() as u64);
         buffer.extend_from_slice(bytes);
     }
+
+    /// Write length-encoded text in the connection charset; the length prefix counts bytes
+    fn write_length_encoded_text(&self, buffer: &mut Vec<u8>, value: &str, collation: &MySQLCollation) {
+        let bytes = collation.encoding.encode(value);
+        self.write_length_encoded_integer(buffer, bytes.len() as u64);
+        buffer.extend_from_slice(&bytes);
+    }
+
+    /// Apply `SET NAMES <charset> [COLLATE <collation>]` or `SET CHARACTER SET <charset>` to the
+    /// connection. Returns false for other statements.
+    pub fn apply_set_names(&self, conn: &mut Connection, sql: &str) -> NirvResult<bool> {
+        let words: Vec<&str> = sql.trim().trim_end_matches(';').split_whitespace().collect();
+        let charset_at = match words.as_slice() {
+            [set, names, ..] if set.eq_ignore_ascii_case("SET") && names.eq_ignore_ascii_case("NAMES") => 2,
+            [set, character, set_kw, ..] if set.eq_ignore_ascii_case("SET")
+                && character.eq_ignore_ascii_case("CHARACTER")
+                && set_kw.eq_ignore_ascii_case("SET") => 3,
+            _ => return Ok(false),
+        };
+
+        let charset = words.get(charset_at).copied().unwrap_or_default();
+        let collation = match words.get(charset_at + 1..) {
+            Some([collate, name, ..]) if collate.eq_ignore_ascii_case("COLLATE") => {
+                MySQLCollation::from_name(name.trim_matches(|c| c == '\'' || c == '"' || c == '`'))
+            }
+            _ => MySQLCollation::for_charset(charset),
+        }.ok_or_else(|| ProtocolError::UnsupportedFeature(format!("Unknown character set: '{}'", charset)))?;
+
+        conn.parameters.insert("character_set_client".to_string(), collation.charset.to_string());
+        conn.parameters.insert(COLLATION_PARAMETER.to_string(), collation.name.to_string());
+        Ok(true)
+    }
+
+    /// Collation negotiated for the connection, or the server default before the handshake
+    fn connection_collation(&self, conn: &Connection) -> MySQLCollation {
+        conn.parameters.get(COLLATION_PARAMETER)
+            .and_then(|name| MySQLCollation::from_name(name))
+            .unwrap_or(MYSQL_DEFAULT_COLLATION)
+    }
     
     /// Convert NIRV data type to MySQL field type
     fn nirv_type_to_mysql_type(&self, data_type: &DataType) -> MySQLFieldType {
@@ -473,11 +802,156 @@ impl MySQLProtocolAdapter {
             0x02 => MySQLCommand::InitDB,
             0x03 => MySQLCommand::Query,
             0x0e => MySQLCommand::Ping,
+            0x16 => MySQLCommand::StmtPrepare,
+            0x17 => MySQLCommand::StmtExecute,
+            0x18 => MySQLCommand::StmtSendLongData,
+            0x19 => MySQLCommand::StmtClose,
+            0x1a => MySQLCommand::StmtReset,
             _ => return Err(ProtocolError::UnsupportedFeature(format!("Command {} not supported", command_byte)).into()),
         };
         
         Ok((command, command_data.to_vec()))
     }
+
+    /// Handle COM_STMT_PREPARE, COM_STMT_SEND_LONG_DATA, COM_STMT_CLOSE and COM_STMT_RESET,
+    /// returning the reply packets if the command has a reply. COM_STMT_EXECUTE goes through
+    /// `parse_message` and its result through `format_binary_response`.
+    pub fn handle_statement_command(&self, conn: &Connection, data: &[u8]) -> NirvResult<Option<Vec<u8>>> {
+        let response = self.statement_command_response(conn, &self.unframe_packets(conn, data)?)?;
+        Ok(response.map(|packets| self.frame_packets(conn, packets)))
+    }
+
+    fn statement_command_response(&self, conn: &Connection, data: &[u8]) -> NirvResult<Option<Vec<u8>>> {
+        let (command, command_data) = self.parse_command(data)?;
+        let collation = self.connection_collation(conn);
+        let mut statements = self.statements.lock()
+            .map_err(|_| ProtocolError::ConnectionFailed("Prepared statement registry poisoned".to_string()))?;
+
+        match command {
+            MySQLCommand::StmtPrepare => {
+                let sql = collation.encoding.decode(&command_data);
+                let id = self.next_statement_id.fetch_add(1, Ordering::SeqCst);
+                let statement = MySQLPreparedStatement::new(id, &sql);
+                let response = self.create_prepare_ok(&statement, &collation);
+                statements.insert(id, statement);
+                Ok(Some(response))
+            }
+            MySQLCommand::StmtSendLongData => {
+                let mut reader = PacketReader::new(&command_data);
+                let id = reader.u32()?;
+                let param_id = reader.u16()?;
+                // Unknown statements are reported when executed, as the command has no reply
+                if let Some(statement) = statements.get_mut(&id) {
+                    statement.long_data.entry(param_id).or_default().extend_from_slice(reader.remaining());
+                }
+                Ok(None)
+            }
+            MySQLCommand::StmtClose => {
+                let id = PacketReader::new(&command_data).u32()?;
+                statements.remove(&id);
+                Ok(None)
+            }
+            MySQLCommand::StmtReset => {
+                let id = PacketReader::new(&command_data).u32()?;
+                match statements.get_mut(&id) {
+                    Some(statement) => {
+                        statement.long_data.clear();
+                        Ok(Some(self.create_ok_packet_with_sequence(0, 0, 1)))
+                    }
+                    None => Ok(Some(self.create_error_packet(1243, "Unknown prepared statement handler"))),
+                }
+            }
+            _ => Err(ProtocolError::UnsupportedFeature(format!("Command {:?} is not a statement command", command)).into()),
+        }
+    }
+
+    /// Create the COM_STMT_PREPARE response: statement header, then parameter definitions.
+    /// Result columns are only known once executed, so they are sent with the execute result.
+    fn create_prepare_ok(&self, statement: &MySQLPreparedStatement, collation: &MySQLCollation) -> Vec<u8> {
+        let mut header = vec![0x00];
+        header.extend_from_slice(&statement.id.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // Column count
+        header.extend_from_slice(&(statement.param_count() as u16).to_le_bytes());
+        header.push(0x00); // Reserved
+        header.extend_from_slice(&0u16.to_le_bytes()); // Warnings
+
+        let mut response = self.wrap_packet(&header, 1);
+        if statement.param_count() > 0 {
+            for (i, param_type) in statement.param_types.iter().enumerate() {
+                response.extend_from_slice(&self.create_field_definition("?", param_type.clone(), true, collation, (i + 2) as u8));
+            }
+            response.extend_from_slice(&self.create_eof_packet((statement.param_count() + 2) as u8));
+        }
+        response
+    }
+
+    /// Format a COM_STMT_EXECUTE result using the binary row protocol
+    pub fn format_binary_response(&self, conn: &Connection, result: &QueryResult) -> Vec<u8> {
+        self.frame_packets(conn, self.encode_result_set(result, &self.connection_collation(conn), true))
+    }
+
+    /// Encode an OK packet or a result set, with text or binary rows
+    fn encode_result_set(&self, result: &QueryResult, collation: &MySQLCollation, binary: bool) -> Vec<u8> {
+        self.encode_result_set_with_status(result, collation, binary, 0)
+    }
+    
+    /// Encode an OK packet or a result set whose final packet carries the given status flags
+    fn encode_result_set_with_status(&self, result: &QueryResult, collation: &MySQLCollation, binary: bool, status: u16) -> Vec<u8> {
+        let mut response = Vec::new();
+        
+        if result.columns.is_empty() {
+            // OK packet for non-SELECT queries
+            let ok_packet = self.create_ok_packet_with_status(result.affected_rows.unwrap_or(0), 0, status, 2);
+            response.extend_from_slice(&ok_packet);
+        } else {
+            // Result set for SELECT queries
+            
+            // Result set header
+            let header = self.create_result_set_header(result.columns.len());
+            response.extend_from_slice(&header);
+            
+            // Column definitions
+            for (i, column) in result.columns.iter().enumerate() {
+                let col_def = self.create_column_definition(column, collation, (i + 2) as u8);
+                response.extend_from_slice(&col_def);
+            }
+            
+            // EOF packet after column definitions
+            let eof1 = self.create_eof_packet((result.columns.len() + 2) as u8);
+            response.extend_from_slice(&eof1);
+            
+            // Row data
+            for (i, row) in result.rows.iter().enumerate() {
+                let sequence_id = (result.columns.len() + 3 + i) as u8;
+                let row_packet = if binary {
+                    self.create_binary_row_packet(&result.columns, row, collation, sequence_id)
+                } else {
+                    self.create_row_packet(row, collation, sequence_id)
+                };
+                response.extend_from_slice(&row_packet);
+            }
+            
+            // EOF packet after rows, counting the sources missing from a partial result
+            let warnings = result.warnings.len().min(u16::MAX as usize) as u16;
+            let eof2 = self.create_eof_packet_with_status(warnings, status, (result.columns.len() + 3 + result.rows.len()) as u8);
+            response.extend_from_slice(&eof2);
+        }
+        
+        response
+    }
+    
+    /// Number the packets of a response consecutively from 1, following the client's command
+    fn renumber_packets(&self, mut packets: Vec<u8>) -> Vec<u8> {
+        let mut offset = 0;
+        let mut sequence_id = 1u8;
+        while offset + 4 <= packets.len() {
+            let length = u32::from_le_bytes([packets[offset], packets[offset + 1], packets[offset + 2], 0]) as usize;
+            packets[offset + 3] = sequence_id;
+            sequence_id = sequence_id.wrapping_add(1);
+            offset += 4 + length;
+        }
+        packets
+    }
 }
 
 impl Default for MySQLProtocolAdapter {
@@ -491,51 +965,77 @@ impl ProtocolAdapter for MySQLProtocolAdapter {
     async fn accept_connection(&self, stream: TcpStream) -> NirvResult<Connection> {
         let mut connection = Connection::new(stream, ProtocolType::MySQL);
         
-        // Send initial handshake packet
-        let handshake = self.create_handshake_packet();
+        // Send initial handshake packet with a fresh challenge
+        let scramble = generate_scramble();
+        let handshake = self.create_handshake_packet(&scramble);
         connection.stream.write_all(&handshake).await
             .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send handshake: {}", e)))?;
+        connection.parameters.insert(SCRAMBLE_PARAMETER.to_string(), String::from_utf8_lossy(&scramble).to_string());
         
         Ok(connection)
     }
     
     async fn authenticate(&self, conn: &mut Connection, credentials: Credentials) -> NirvResult<()> {
-        // Read handshake response
-        let mut buffer = vec![0u8; 8192];
-        let bytes_read = conn.stream.read(&mut buffer).await
-            .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to read handshake response: {}", e)))?;
-        
-        if bytes_read < 32 {
-            return Err(ProtocolError::InvalidMessageFormat("Handshake response too short".to_string()).into());
+        let scramble = conn.parameters.remove(SCRAMBLE_PARAMETER)
+            .ok_or_else(|| ProtocolError::AuthenticationFailed("Handshake was not sent".to_string()))?
+            .into_bytes();
+
+        // Read handshake response, upgrading to TLS first if the client sent an SSL request
+        let (mut sequence_id, mut payload) = self.read_packet(conn).await?;
+        let client_capabilities = PacketReader::new(&payload).u32()?;
+        if payload.len() == 32 && client_capabilities & CLIENT_SSL != 0 {
+            let acceptor = self.tls_acceptor.as_ref()
+                .ok_or_else(|| ProtocolError::AuthenticationFailed("Client requested TLS, which is not configured".to_string()))?;
+            conn.stream.upgrade_to_tls(acceptor).await?;
+            (sequence_id, payload) = self.read_packet(conn).await?;
         }
-        
-        // Parse handshake response
-        let (username, _password, database) = self.parse_handshake_response(&buffer[..bytes_read])?;
-        
-        // Validate credentials
-        if username != credentials.username {
-            let error_packet = self.create_error_packet(1045, "Access denied for user");
-            conn.stream.write_all(&error_packet).await
-                .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send error: {}", e)))?;
-            return Err(ProtocolError::AuthenticationFailed("Username mismatch".to_string()).into());
+        let response = self.parse_handshake_response(&payload)?;
+
+        // Ask the client to switch plugins if it answered with a different one
+        let mut auth_response = response.auth_response.clone();
+        let client_plugin = response.auth_plugin.as_deref().and_then(MySQLAuthPlugin::from_name);
+        if client_plugin != Some(self.auth_plugin) {
+            let mut switch_request = vec![0xfe];
+            switch_request.extend_from_slice(self.auth_plugin.name().as_bytes());
+            switch_request.push(0);
+            switch_request.extend_from_slice(&scramble);
+            switch_request.push(0);
+            self.send_packet(conn, &switch_request, sequence_id.wrapping_add(1)).await?;
+            (sequence_id, auth_response) = self.read_packet(conn).await?;
+        }
+
+        let password = credentials.password.clone().unwrap_or_default();
+        let verified = response.username == credentials.username && match self.auth_plugin {
+            MySQLAuthPlugin::NativePassword => verify_native_password(&password, &scramble, &auth_response),
+            MySQLAuthPlugin::CachingSha2Password => self.authenticate_caching_sha2(
+                conn, &mut sequence_id, &response.username, &password, &scramble, &auth_response,
+            ).await?,
+        };
+        if !verified {
+            let message = format!("Access denied for user '{}'", response.username);
+            return self.reject(conn, sequence_id.wrapping_add(1), 1045, &message).await;
         }
         
-        if !database.is_empty() && database != credentials.database {
-            let error_packet = self.create_error_packet(1049, "Unknown database");
-            conn.stream.write_all(&error_packet).await
-                .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send error: {}", e)))?;
-            return Err(ProtocolError::AuthenticationFailed("Database mismatch".to_string()).into());
+        if !response.database.is_empty() && response.database != credentials.database {
+            let message = format!("Unknown database '{}'", response.database);
+            return self.reject(conn, sequence_id.wrapping_add(1), 1049, &message).await;
         }
         
         // Send OK packet
-        let ok_packet = self.create_ok_packet(0, 0);
+        let ok_packet = self.create_ok_packet_with_sequence(0, 0, sequence_id.wrapping_add(1));
         conn.stream.write_all(&ok_packet).await
             .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send OK packet: {}", e)))?;
         
         // Update connection state
         conn.authenticated = true;
-        conn.database = if database.is_empty() { credentials.database } else { database };
-        conn.parameters.insert("user".to_string(), username);
+        conn.database = if response.database.is_empty() { credentials.database } else { response.database };
+        conn.parameters.insert("user".to_string(), response.username);
+        conn.parameters.insert("character_set_client".to_string(), response.collation.charset.to_string());
+        conn.parameters.insert(COLLATION_PARAMETER.to_string(), response.collation.name.to_string());
+        // Packets after the OK are compressed when both sides offered compression
+        if response.capabilities & self.server_capabilities() & CLIENT_COMPRESS != 0 {
+            conn.parameters.insert(COMPRESSION_PARAMETER.to_string(), "zlib".to_string());
+        }
         
         Ok(())
     }
@@ -566,6 +1066,8 @@ impl ProtocolAdapter for MySQLProtocolAdapter {
             rows,
             affected_rows: Some(2),
             execution_time: std::time::Duration::from_millis(10),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         Ok(ProtocolResponse::new(result, ProtocolType::MySQL))
@@ -575,12 +1077,32 @@ impl ProtocolAdapter for MySQLProtocolAdapter {
         ProtocolType::MySQL
     }
     
-    async fn parse_message(&self, _conn: &Connection, data: &[u8]) -> NirvResult<ProtocolQuery> {
-        let (command, command_data) = self.parse_command(data)?;
+    /// Read one packet, or one compressed packet once compression was negotiated, header included
+    async fn read_message(&self, conn: &mut Connection) -> NirvResult<Option<Vec<u8>>> {
+        let header = if self.is_compressed(conn) {
+            read_header::<7>(conn).await?.map(|header| header.to_vec())
+        } else {
+            read_header::<4>(conn).await?.map(|header| header.to_vec())
+        };
+        let Some(mut message) = header else {
+            return Ok(None);
+        };
+        let length = u32::from_le_bytes([message[0], message[1], message[2], 0]) as usize;
+        message.extend(read_body(conn, length).await?);
+        Ok(Some(message))
+    }
+    
+    fn ends_connection(&self, query: &ProtocolQuery) -> bool {
+        query.raw_query == "QUIT"
+    }
+    
+    async fn parse_message(&self, conn: &Connection, data: &[u8]) -> NirvResult<ProtocolQuery> {
+        let (command, command_data) = self.parse_command(&self.unframe_packets(conn, data)?)?;
+        let encoding = self.connection_collation(conn).encoding;
         
         match command {
             MySQLCommand::Query => {
-                let query_string = String::from_utf8_lossy(&command_data).to_string();
+                let query_string = encoding.decode(&command_data);
                 Ok(ProtocolQuery::new(query_string, ProtocolType::MySQL))
             }
             MySQLCommand::Quit => {
@@ -590,51 +1112,53 @@ impl ProtocolAdapter for MySQLProtocolAdapter {
                 Ok(ProtocolQuery::new("PING".to_string(), ProtocolType::MySQL))
             }
             MySQLCommand::InitDB => {
-                let db_name = String::from_utf8_lossy(&command_data).to_string();
+                let db_name = encoding.decode(&command_data);
                 Ok(ProtocolQuery::new(format!("USE {}", db_name), ProtocolType::MySQL))
             }
+            MySQLCommand::StmtExecute => {
+                let mut reader = PacketReader::new(&command_data);
+                let id = reader.u32()?;
+                let mut statements = self.statements.lock()
+                    .map_err(|_| ProtocolError::ConnectionFailed("Prepared statement registry poisoned".to_string()))?;
+                let statement = statements.get_mut(&id).ok_or_else(|| ProtocolError::InvalidMessageFormat(
+                    format!("Unknown prepared statement handler ({})", id)
+                ))?;
+                let (sql, _values) = statement.bind_execute(reader.remaining())?;
+                Ok(ProtocolQuery::new(sql, ProtocolType::MySQL))
+            }
             _ => {
                 Err(ProtocolError::UnsupportedFeature(format!("Command {:?} not supported", command)).into())
             }
         }
     }
     
-    async fn format_response(&self, _conn: &Connection, result: QueryResult) -> NirvResult<Vec<u8>> {
-        let mut response = Vec::new();
-        
-        if result.columns.is_empty() {
-            // OK packet for non-SELECT queries
-            let ok_packet = self.create_ok_packet(result.affected_rows.unwrap_or(0), 0);
-            response.extend_from_slice(&ok_packet);
-        } else {
-            // Result set for SELECT queries
-            
-            // Result set header
-            let header = self.create_result_set_header(result.columns.len());
-            response.extend_from_slice(&header);
-            
-            // Column definitions
-            for (i, column) in result.columns.iter().enumerate() {
-                let col_def = self.create_column_definition(column, (i + 2) as u8);
-                response.extend_from_slice(&col_def);
-            }
-            
-            // EOF packet after column definitions
-            let eof1 = self.create_eof_packet((result.columns.len() + 2) as u8);
-            response.extend_from_slice(&eof1);
-            
-            // Row data
-            for (i, row) in result.rows.iter().enumerate() {
-                let row_packet = self.create_row_packet(row, (result.columns.len() + 3 + i) as u8);
-                response.extend_from_slice(&row_packet);
+    async fn format_response(&self, conn: &Connection, result: QueryResult) -> NirvResult<Vec<u8>> {
+        Ok(self.frame_packets(conn, self.encode_result_set(&result, &self.connection_collation(conn), false)))
+    }
+    
+    /// Send each statement's result set or OK packet in turn, flagging all but the last with
+    /// `SERVER_MORE_RESULTS_EXISTS`, as servers answer `CLIENT_MULTI_STATEMENTS` queries.
+    /// A failed statement ends the response with an error packet.
+    async fn format_statement_responses(&self, conn: &Connection, results: Vec<StatementResult>) -> NirvResult<Vec<u8>> {
+        let collation = self.connection_collation(conn);
+        let mut packets = Vec::new();
+        for (i, statement) in results.iter().enumerate() {
+            match &statement.result {
+                Ok(result) => {
+                    let status = if i + 1 < results.len() { SERVER_MORE_RESULTS_EXISTS } else { 0 };
+                    packets.extend(self.encode_result_set_with_status(result, &collation, false, status));
+                }
+                Err(e) => {
+                    // ER_PARSE_ERROR for SQL the engine cannot parse, ER_UNKNOWN_ERROR otherwise
+                    let error_code = if matches!(e, NirvError::QueryParsing(_)) { 1064 } else { 1105 };
+                    packets.extend(self.create_error_packet(error_code, &e.to_string()));
+                }
             }
-            
-            // EOF packet after rows
-            let eof2 = self.create_eof_packet((result.columns.len() + 3 + result.rows.len()) as u8);
-            response.extend_from_slice(&eof2);
         }
-        
-        Ok(response)
+        if results.is_empty() {
+            packets = self.create_error_packet(1065, "Query was empty");
+        }
+        Ok(self.frame_packets(conn, self.renumber_packets(packets)))
     }
     
     async fn terminate_connection(&self, conn: &mut Connection) -> NirvResult<()> {
@@ -0,0 +1,322 @@
+use crate::utils::{NirvResult, ProtocolError};
+
+/// Row encoding of a COPY data stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFormat {
+    Text,
+    Csv,
+}
+
+/// A parsed `COPY ... TO STDOUT` statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyOutRequest {
+    /// Query producing the rows to export
+    pub query: String,
+    pub format: CopyFormat,
+    /// Emit a header line with the column names
+    pub header: bool,
+    pub delimiter: char,
+    /// Representation of NULL values
+    pub null_string: String,
+    /// CSV quote character
+    pub quote: char,
+}
+
+impl CopyOutRequest {
+    fn new(query: String) -> Self {
+        Self {
+            query,
+            format: CopyFormat::Text,
+            header: false,
+            delimiter: '\t',
+            null_string: "\\N".to_string(),
+            quote: '"',
+        }
+    }
+
+    fn set_format(&mut self, format: CopyFormat) {
+        self.format = format;
+        if format == CopyFormat::Csv {
+            self.delimiter = ',';
+            self.null_string = String::new();
+        }
+    }
+
+    /// Encode one line of the data stream, including the trailing newline
+    pub fn encode_line(&self, fields: &[Option<String>]) -> String {
+        let mut line = String::new();
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                line.push(self.delimiter);
+            }
+            match (field, self.format) {
+                (None, _) => line.push_str(&self.null_string),
+                (Some(value), CopyFormat::Text) => self.escape_text(value, &mut line),
+                (Some(value), CopyFormat::Csv) => self.quote_csv(value, &mut line),
+            }
+        }
+        line.push('\n');
+        line
+    }
+
+    fn escape_text(&self, value: &str, out: &mut String) {
+        for c in value.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if c == self.delimiter => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn quote_csv(&self, value: &str, out: &mut String) {
+        let needs_quotes = value == self.null_string
+            || value.contains([self.delimiter, self.quote, '\n', '\r'])
+            || value.starts_with("\\.");
+        if !needs_quotes {
+            out.push_str(value);
+            return;
+        }
+
+        out.push(self.quote);
+        for c in value.chars() {
+            if c == self.quote {
+                out.push(self.quote);
+            }
+            out.push(c);
+        }
+        out.push(self.quote);
+    }
+}
+
+/// Parse `COPY (query) TO STDOUT [WITH] [options]` or `COPY table [(columns)] TO STDOUT ...`.
+/// Returns `None` for statements that are not a COPY to the client.
+pub fn parse_copy_to_stdout(sql: &str) -> NirvResult<Option<CopyOutRequest>> {
+    let tokens = tokenize(sql.trim().trim_end_matches(';'));
+    let mut tokens = tokens.iter().peekable();
+
+    if !matches!(tokens.next(), Some(Token::Word(w)) if w.eq_ignore_ascii_case("COPY")) {
+        return Ok(None);
+    }
+
+    let query = match tokens.next() {
+        Some(Token::Group(inner)) => inner.trim().to_string(),
+        Some(Token::Word(table)) => {
+            let columns = match tokens.peek() {
+                Some(Token::Group(columns)) => {
+                    let columns = columns.trim().to_string();
+                    tokens.next();
+                    columns
+                }
+                _ => "*".to_string(),
+            };
+            format!("SELECT {} FROM {}", columns, table)
+        }
+        _ => return Ok(None),
+    };
+
+    let is_word = |token: Option<&Token>, expected: &str| matches!(token, Some(Token::Word(w)) if w.eq_ignore_ascii_case(expected));
+    if !is_word(tokens.next(), "TO") || !is_word(tokens.next(), "STDOUT") {
+        return Ok(None);
+    }
+
+    let mut request = CopyOutRequest::new(query);
+    let mut options: Vec<&Token> = Vec::new();
+    for token in tokens {
+        match token {
+            // Parenthesized option list: `WITH (FORMAT csv, HEADER)`
+            Token::Group(inner) => {
+                for option in inner.split(',') {
+                    apply_option(&mut request, &tokenize(option).iter().collect::<Vec<_>>())?;
+                }
+            }
+            Token::Word(w) if w.eq_ignore_ascii_case("WITH") => {}
+            _ => options.push(token),
+        }
+    }
+
+    // Legacy option syntax: `CSV HEADER DELIMITER ','`
+    let mut i = 0;
+    while i < options.len() {
+        let takes_value = matches!(options[i], Token::Word(w) if ["DELIMITER", "NULL", "QUOTE"].iter().any(|k| w.eq_ignore_ascii_case(k)));
+        let end = if takes_value { (i + 2).min(options.len()) } else { i + 1 };
+        apply_option(&mut request, &options[i..end])?;
+        i = end;
+    }
+
+    Ok(Some(request))
+}
+
+fn apply_option(request: &mut CopyOutRequest, option: &[&Token]) -> NirvResult<()> {
+    let Some(Token::Word(name)) = option.first() else {
+        return Ok(());
+    };
+    let value = option.get(1).map(|token| match token {
+        Token::Word(w) | Token::Literal(w) => w.clone(),
+        Token::Group(g) => g.clone(),
+    });
+
+    let single_char = |value: Option<String>| -> NirvResult<char> {
+        let value = value.unwrap_or_default();
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(ProtocolError::InvalidMessageFormat(
+                format!("COPY {} must be a single one-byte character", name.to_uppercase())
+            ).into()),
+        }
+    };
+
+    match name.to_uppercase().as_str() {
+        "FORMAT" => match value.unwrap_or_default().to_lowercase().as_str() {
+            "text" => request.set_format(CopyFormat::Text),
+            "csv" => request.set_format(CopyFormat::Csv),
+            other => return Err(ProtocolError::UnsupportedFeature(format!("COPY format \"{}\"", other)).into()),
+        },
+        "CSV" => request.set_format(CopyFormat::Csv),
+        "BINARY" => return Err(ProtocolError::UnsupportedFeature("COPY format \"binary\"".to_string()).into()),
+        "HEADER" => {
+            request.header = !matches!(
+                value.map(|v| v.to_lowercase()).as_deref(),
+                Some("false" | "off" | "0")
+            );
+        }
+        "DELIMITER" => request.delimiter = single_char(value)?,
+        "QUOTE" => request.quote = single_char(value)?,
+        "NULL" => request.null_string = value.unwrap_or_default(),
+        other => return Err(ProtocolError::UnsupportedFeature(format!("COPY option \"{}\"", other.to_lowercase())).into()),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// Single-quoted string with quotes removed
+    Literal(String),
+    /// Contents of a balanced parenthesized group
+    Group(String),
+}
+
+fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() || c == ',' {
+            i += 1;
+        } else if c == '\'' {
+            let mut literal = String::new();
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    if chars.get(i + 1) == Some(&'\'') {
+                        literal.push('\'');
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                literal.push(chars[i]);
+                i += 1;
+            }
+            tokens.push(Token::Literal(literal));
+            i += 1;
+        } else if c == '(' {
+            let start = i + 1;
+            let mut depth = 0;
+            let mut quote: Option<char> = None;
+            while i < chars.len() {
+                match (chars[i], quote) {
+                    (q, Some(open)) if q == open => quote = None,
+                    (_, Some(_)) => {}
+                    ('\'' | '"', None) => quote = Some(chars[i]),
+                    ('(', None) => depth += 1,
+                    (')', None) => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            tokens.push(Token::Group(chars[start..i.min(chars.len())].iter().collect()));
+            i += 1;
+        } else {
+            let start = i;
+            let mut in_quotes = false;
+            while i < chars.len() {
+                let c = chars[i];
+                if c == '"' {
+                    in_quotes = !in_quotes;
+                } else if !in_quotes && (c.is_whitespace() || c == ',' || c == '(' || c == '\'') {
+                    break;
+                }
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_copy_query_with_options() {
+        let request = parse_copy_to_stdout(
+            "COPY (SELECT id, name FROM source('postgres.users') WHERE id > 1) TO STDOUT WITH (FORMAT csv, HEADER, DELIMITER ';');"
+        ).unwrap().unwrap();
+        assert_eq!(request.query, "SELECT id, name FROM source('postgres.users') WHERE id > 1");
+        assert_eq!(request.format, CopyFormat::Csv);
+        assert!(request.header);
+        assert_eq!(request.delimiter, ';');
+        assert_eq!(request.null_string, "");
+
+        let legacy = parse_copy_to_stdout("copy users (id, name) to stdout with csv header null 'NA'").unwrap().unwrap();
+        assert_eq!(legacy.query, "SELECT id, name FROM users");
+        assert_eq!(legacy.format, CopyFormat::Csv);
+        assert!(legacy.header);
+        assert_eq!(legacy.null_string, "NA");
+
+        let text = parse_copy_to_stdout("COPY users TO STDOUT").unwrap().unwrap();
+        assert_eq!(text.query, "SELECT * FROM users");
+        assert_eq!((text.format, text.delimiter, text.null_string.as_str()), (CopyFormat::Text, '\t', "\\N"));
+    }
+
+    #[test]
+    fn test_parse_non_stdout_copy() {
+        assert_eq!(parse_copy_to_stdout("SELECT 1").unwrap(), None);
+        assert_eq!(parse_copy_to_stdout("COPY users FROM STDIN").unwrap(), None);
+        assert_eq!(parse_copy_to_stdout("COPY users TO '/tmp/users.csv'").unwrap(), None);
+        assert!(parse_copy_to_stdout("COPY users TO STDOUT (FORMAT binary)").is_err());
+    }
+
+    #[test]
+    fn test_encode_text_and_csv_lines() {
+        let text = parse_copy_to_stdout("COPY t TO STDOUT").unwrap().unwrap();
+        assert_eq!(
+            text.encode_line(&[Some("a\tb\\c\nd".to_string()), None]),
+            "a\\tb\\\\c\\nd\t\\N\n"
+        );
+
+        let csv = parse_copy_to_stdout("COPY t TO STDOUT (FORMAT csv)").unwrap().unwrap();
+        assert_eq!(
+            csv.encode_line(&[Some("plain".to_string()), Some("say \"hi\", bye".to_string()), Some(String::new()), None]),
+            "plain,\"say \"\"hi\"\", bye\",\"\",\n"
+        );
+    }
+}
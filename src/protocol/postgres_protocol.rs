@@ -3,7 +3,9 @@ use std::collections::HashMap;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-use crate::protocol::{ProtocolAdapter, ProtocolType, Connection, Credentials, ProtocolQuery, ProtocolResponse};
+use crate::protocol::{ProtocolAdapter, ProtocolType, Connection, Credentials, ProtocolQuery, ProtocolResponse, StatementResult, TransactionStatus, read_body, read_header};
+use crate::protocol::postgres_copy::CopyOutRequest;
+use crate::protocol::charset::{TextEncoding, postgres_client_encoding};
 use crate::utils::{NirvResult, ProtocolError, QueryResult, ColumnMetadata, Row, Value, DataType};
 
 /// PostgreSQL protocol version 3.0
@@ -28,6 +30,10 @@ pub enum PostgresResponseType {
     DataRow = b'D' as isize,
     CommandComplete = b'C' as isize,
     ErrorResponse = b'E' as isize,
+    NoticeResponse = b'N' as isize,
+    CopyOutResponse = b'H' as isize,
+    CopyData = b'd' as isize,
+    CopyDone = b'c' as isize,
 }
 
 /// PostgreSQL protocol adapter implementation
@@ -110,31 +116,105 @@ impl PostgresProtocol {
     }
     
     /// Create a ready for query message
-    fn create_ready_for_query(&self) -> Vec<u8> {
+    fn create_ready_for_query(&self, status: TransactionStatus) -> Vec<u8> {
         let mut response = Vec::new();
         response.push(b'Z'); // Ready for query
         response.extend_from_slice(&5u32.to_be_bytes()); // Message length
-        response.push(b'I'); // Transaction status: Idle
+        response.push(match status {
+            TransactionStatus::Idle => b'I',
+            TransactionStatus::InTransaction => b'T',
+            TransactionStatus::Failed => b'E',
+        });
         response
     }
     
+    /// Format the response to a command that returns no rows (e.g. BEGIN, COMMIT, ROLLBACK)
+    pub fn format_command_response(&self, conn: &Connection, tag: &str) -> Vec<u8> {
+        let mut response = self.create_command_complete(tag);
+        response.extend_from_slice(&self.create_ready_for_query(conn.transaction_status));
+        response
+    }
+    
+    /// Format the result of a `COPY ... TO STDOUT` statement as a COPY OUT data stream
+    pub fn format_copy_response(&self, conn: &Connection, request: &CopyOutRequest, result: &QueryResult) -> Vec<u8> {
+        let encoding = self.client_encoding(conn);
+        let mut response = self.create_copy_out_response(result.columns.len());
+
+        if request.header {
+            let names: Vec<Option<String>> = result.columns.iter()
+                .map(|col| Some(col.name.clone()))
+                .collect();
+            response.extend_from_slice(&self.create_copy_data(&encoding.encode(&request.encode_line(&names))));
+        }
+
+        for row in &result.rows {
+            let fields: Vec<Option<String>> = row.values.iter()
+                .map(|value| match value {
+                    Value::Null => None,
+                    _ => Some(self.value_to_string(value)),
+                })
+                .collect();
+            response.extend_from_slice(&self.create_copy_data(&encoding.encode(&request.encode_line(&fields))));
+        }
+
+        response.extend_from_slice(&[b'c', 0, 0, 0, 4]); // Copy done
+        response.extend_from_slice(&self.create_command_complete(&format!("COPY {}", result.rows.len())));
+        response.extend_from_slice(&self.create_ready_for_query(conn.transaction_status));
+        response
+    }
+
+    /// Create a copy out response message announcing the stream format
+    fn create_copy_out_response(&self, column_count: usize) -> Vec<u8> {
+        let mut response = Vec::new();
+        response.push(b'H'); // Copy out response
+
+        let content_len = 1 + 2 + 2 * column_count; // Overall format + column count + per-column formats
+        response.extend_from_slice(&(content_len as u32 + 4).to_be_bytes());
+        response.push(0); // Text and CSV are both textual (format 0)
+        response.extend_from_slice(&(column_count as u16).to_be_bytes());
+        for _ in 0..column_count {
+            response.extend_from_slice(&0u16.to_be_bytes());
+        }
+
+        response
+    }
+
+    /// Create a copy data message carrying one encoded line
+    fn create_copy_data(&self, data: &[u8]) -> Vec<u8> {
+        let mut response = Vec::with_capacity(data.len() + 5);
+        response.push(b'd'); // Copy data
+        response.extend_from_slice(&(data.len() as u32 + 4).to_be_bytes());
+        response.extend_from_slice(data);
+        response
+    }
+    
+    /// Encoding the client selected with `client_encoding`, UTF-8 by default
+    fn client_encoding(&self, conn: &Connection) -> TextEncoding {
+        conn.parameters.get("client_encoding")
+            .and_then(|name| postgres_client_encoding(name))
+            .map(|(_, encoding)| encoding)
+            .unwrap_or_default()
+    }
+
     /// Create a row description message
-    fn create_row_description(&self, columns: &[ColumnMetadata]) -> Vec<u8> {
+    fn create_row_description(&self, columns: &[ColumnMetadata], encoding: TextEncoding) -> Vec<u8> {
         let mut response = Vec::new();
         response.push(b'T'); // Row description
         
+        let names: Vec<Vec<u8>> = columns.iter().map(|col| encoding.encode(&col.name)).collect();
+        
         // Calculate message length
         let mut content_len = 2; // Field count (2 bytes)
-        for col in columns {
-            content_len += col.name.len() + 1; // Name + null terminator
+        for name in &names {
+            content_len += name.len() + 1; // Name + null terminator
             content_len += 18; // Table OID (4) + Column attr (2) + Type OID (4) + Type size (2) + Type modifier (4) + Format code (2)
         }
         
         response.extend_from_slice(&(content_len as u32 + 4).to_be_bytes());
         response.extend_from_slice(&(columns.len() as u16).to_be_bytes()); // Field count
         
-        for col in columns {
-            response.extend_from_slice(col.name.as_bytes());
+        for (col, name) in columns.iter().zip(&names) {
+            response.extend_from_slice(name);
             response.push(0); // Null terminator
             response.extend_from_slice(&0u32.to_be_bytes()); // Table OID
             response.extend_from_slice(&0u16.to_be_bytes()); // Column attribute number
@@ -161,34 +241,35 @@ impl PostgresProtocol {
     }
     
     /// Create a data row message
-    fn create_data_row(&self, row: &Row) -> Vec<u8> {
+    fn create_data_row(&self, row: &Row, encoding: TextEncoding) -> Vec<u8> {
         let mut response = Vec::new();
         response.push(b'D'); // Data row
         
+        // Encode values first so lengths count bytes in the client encoding
+        let fields: Vec<Option<Vec<u8>>> = row.values.iter()
+            .map(|value| match value {
+                Value::Null => None,
+                _ => Some(encoding.encode(&self.value_to_string(value))),
+            })
+            .collect();
+        
         // Calculate message length
         let mut content_len = 2; // Field count (2 bytes)
-        for value in &row.values {
-            match value {
-                Value::Null => content_len += 4, // Length field only
-                _ => {
-                    let value_str = self.value_to_string(value);
-                    content_len += 4 + value_str.len(); // Length field + data
-                }
-            }
+        for field in &fields {
+            content_len += 4 + field.as_ref().map_or(0, |bytes| bytes.len()); // Length field + data
         }
         
         response.extend_from_slice(&(content_len as u32 + 4).to_be_bytes());
         response.extend_from_slice(&(row.values.len() as u16).to_be_bytes()); // Field count
         
-        for value in &row.values {
-            match value {
-                Value::Null => {
+        for field in &fields {
+            match field {
+                None => {
                     response.extend_from_slice(&(-1i32).to_be_bytes()); // NULL value
                 }
-                _ => {
-                    let value_str = self.value_to_string(value);
-                    response.extend_from_slice(&(value_str.len() as u32).to_be_bytes());
-                    response.extend_from_slice(value_str.as_bytes());
+                Some(bytes) => {
+                    response.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+                    response.extend_from_slice(bytes);
                 }
             }
         }
@@ -209,25 +290,35 @@ impl PostgresProtocol {
         response
     }
     
+    /// Create a warning notice message, sent before a query completes
+    fn create_notice_response(&self, message: &[u8]) -> Vec<u8> {
+        let mut fields = Vec::new();
+        for (field, value) in [(b'S', &b"WARNING"[..]), (b'V', b"WARNING"), (b'C', b"01000"), (b'M', message)] {
+            fields.push(field);
+            fields.extend_from_slice(value);
+            fields.push(0); // Null terminator
+        }
+        fields.push(0); // End of notice message
+        
+        let mut response = vec![b'N'];
+        response.extend_from_slice(&(fields.len() as u32 + 4).to_be_bytes());
+        response.extend_from_slice(&fields);
+        response
+    }
+    
     /// Create an error response message
-    #[allow(dead_code)]
     fn create_error_response(&self, message: &str) -> Vec<u8> {
-        let mut response = Vec::new();
-        response.push(b'E'); // Error response
-        
-        let content_len = 1 + message.len() + 1 + 1; // Severity + message + null + terminator
-        response.extend_from_slice(&(content_len as u32 + 4).to_be_bytes());
-        
-        response.push(b'S'); // Severity field
-        response.extend_from_slice(b"ERROR");
-        response.push(0); // Null terminator
-        
-        response.push(b'M'); // Message field
-        response.extend_from_slice(message.as_bytes());
-        response.push(0); // Null terminator
-        
-        response.push(0); // End of error message
+        let mut fields = Vec::new();
+        for (field, value) in [(b'S', &b"ERROR"[..]), (b'M', message.as_bytes())] {
+            fields.push(field);
+            fields.extend_from_slice(value);
+            fields.push(0); // Null terminator
+        }
+        fields.push(0); // End of error message
         
+        let mut response = vec![b'E'];
+        response.extend_from_slice(&(fields.len() as u32 + 4).to_be_bytes());
+        response.extend_from_slice(&fields);
         response
     }
     
@@ -294,6 +385,20 @@ impl ProtocolAdapter for PostgresProtocol {
             }
         }
         
+        // Resolve the requested client encoding before accepting the connection
+        let (client_encoding, _) = match parameters.get("client_encoding") {
+            Some(requested) => match postgres_client_encoding(requested) {
+                Some(encoding) => encoding,
+                None => {
+                    let message = format!("invalid value for parameter \"client_encoding\": \"{}\"", requested);
+                    conn.stream.write_all(&self.create_error_response(&message)).await
+                        .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send error response: {}", e)))?;
+                    return Err(ProtocolError::UnsupportedFeature(message).into());
+                }
+            },
+            None => ("UTF8", TextEncoding::Utf8),
+        };
+        
         // Send authentication OK
         let auth_response = self.create_auth_ok_response();
         conn.stream.write_all(&auth_response).await
@@ -304,12 +409,16 @@ impl ProtocolAdapter for PostgresProtocol {
         conn.stream.write_all(&param_status).await
             .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send parameter status: {}", e)))?;
         
-        let encoding_status = self.create_parameter_status("client_encoding", "UTF8");
+        let server_encoding_status = self.create_parameter_status("server_encoding", "UTF8");
+        conn.stream.write_all(&server_encoding_status).await
+            .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send encoding status: {}", e)))?;
+        
+        let encoding_status = self.create_parameter_status("client_encoding", client_encoding);
         conn.stream.write_all(&encoding_status).await
             .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send encoding status: {}", e)))?;
         
         // Send ready for query
-        let ready_response = self.create_ready_for_query();
+        let ready_response = self.create_ready_for_query(conn.transaction_status);
         conn.stream.write_all(&ready_response).await
             .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send ready response: {}", e)))?;
         
@@ -317,6 +426,7 @@ impl ProtocolAdapter for PostgresProtocol {
         conn.authenticated = true;
         conn.database = credentials.database;
         conn.parameters = parameters;
+        conn.parameters.insert("client_encoding".to_string(), client_encoding.to_string());
         
         Ok(())
     }
@@ -347,6 +457,8 @@ impl ProtocolAdapter for PostgresProtocol {
             rows,
             affected_rows: Some(2),
             execution_time: std::time::Duration::from_millis(10),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         Ok(ProtocolResponse::new(result, ProtocolType::PostgreSQL))
@@ -356,7 +468,25 @@ impl ProtocolAdapter for PostgresProtocol {
         ProtocolType::PostgreSQL
     }
     
-    async fn parse_message(&self, _conn: &Connection, data: &[u8]) -> NirvResult<ProtocolQuery> {
+    async fn read_message(&self, conn: &mut Connection) -> NirvResult<Option<Vec<u8>>> {
+        // Message type (1 byte) and length (4 bytes), which counts itself but not the type
+        let Some(header) = read_header::<5>(conn).await? else {
+            return Ok(None);
+        };
+        let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if length < 4 {
+            return Err(ProtocolError::InvalidMessageFormat(format!("Invalid message length: {}", length)).into());
+        }
+        let mut message = header.to_vec();
+        message.extend(read_body(conn, length - 4).await?);
+        Ok(Some(message))
+    }
+    
+    fn ends_connection(&self, query: &ProtocolQuery) -> bool {
+        query.raw_query == "TERMINATE"
+    }
+    
+    async fn parse_message(&self, conn: &Connection, data: &[u8]) -> NirvResult<ProtocolQuery> {
         if data.is_empty() {
             return Err(ProtocolError::InvalidMessageFormat("Empty message".to_string()).into());
         }
@@ -377,7 +507,7 @@ impl ProtocolAdapter for PostgresProtocol {
                 let query_end = query_data.iter().position(|&b| b == 0)
                     .unwrap_or(query_data.len());
                 
-                let query_string = String::from_utf8_lossy(&query_data[..query_end]).to_string();
+                let query_string = self.client_encoding(conn).decode(&query_data[..query_end]);
                 
                 Ok(ProtocolQuery::new(query_string, ProtocolType::PostgreSQL))
             }
@@ -391,31 +521,71 @@ impl ProtocolAdapter for PostgresProtocol {
         }
     }
     
-    async fn format_response(&self, _conn: &Connection, result: QueryResult) -> NirvResult<Vec<u8>> {
+    async fn format_response(&self, conn: &Connection, result: QueryResult) -> NirvResult<Vec<u8>> {
         let mut response = Vec::new();
         
         // Send row description
-        let row_desc = self.create_row_description(&result.columns);
+        let encoding = self.client_encoding(conn);
+        let row_desc = self.create_row_description(&result.columns, encoding);
         response.extend_from_slice(&row_desc);
         
         // Send data rows
         for row in &result.rows {
-            let data_row = self.create_data_row(row);
+            let data_row = self.create_data_row(row, encoding);
             response.extend_from_slice(&data_row);
         }
         
+        // Sources missing from a partial result are reported as notices
+        for warning in &result.warnings {
+            response.extend_from_slice(&self.create_notice_response(&encoding.encode(&warning.to_string())));
+        }
+        
         // Send command complete
         let tag = format!("SELECT {}", result.rows.len());
         let cmd_complete = self.create_command_complete(&tag);
         response.extend_from_slice(&cmd_complete);
         
         // Send ready for query
-        let ready = self.create_ready_for_query();
+        let ready = self.create_ready_for_query(conn.transaction_status);
         response.extend_from_slice(&ready);
         
         Ok(response)
     }
     
+    /// Answer a simple query holding several statements as PostgreSQL does: each statement's
+    /// rows and command completion in turn, an error ending them, then one ready for query
+    async fn format_statement_responses(&self, conn: &Connection, results: Vec<StatementResult>) -> NirvResult<Vec<u8>> {
+        let encoding = self.client_encoding(conn);
+        let mut response = Vec::new();
+        if results.is_empty() {
+            response.extend_from_slice(&[b'I', 0, 0, 0, 4]); // Empty query response
+        }
+        for statement in &results {
+            let result = match &statement.result {
+                Ok(result) => result,
+                Err(e) => {
+                    response.extend_from_slice(&self.create_error_response(&e.to_string()));
+                    break;
+                }
+            };
+            // Statements returning no columns, such as SET, complete under their own name
+            if result.columns.is_empty() {
+                response.extend_from_slice(&self.create_command_complete(&statement.command()));
+                continue;
+            }
+            response.extend_from_slice(&self.create_row_description(&result.columns, encoding));
+            for row in &result.rows {
+                response.extend_from_slice(&self.create_data_row(row, encoding));
+            }
+            for warning in &result.warnings {
+                response.extend_from_slice(&self.create_notice_response(&encoding.encode(&warning.to_string())));
+            }
+            response.extend_from_slice(&self.create_command_complete(&format!("SELECT {}", result.rows.len())));
+        }
+        response.extend_from_slice(&self.create_ready_for_query(conn.transaction_status));
+        Ok(response)
+    }
+    
     async fn terminate_connection(&self, conn: &mut Connection) -> NirvResult<()> {
         conn.stream.shutdown().await
             .map_err(|_e| ProtocolError::ConnectionClosed)?;
@@ -1,7 +1,9 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
+use tokio::io::AsyncReadExt;
 use tokio::net::TcpStream;
-use crate::utils::{NirvResult, QueryResult};
+use crate::protocol::ConnectionStream;
+use crate::utils::{NirvResult, ProtocolError, QueryResult};
 
 /// Protocol types supported by NIRV Engine
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -12,28 +14,66 @@ pub enum ProtocolType {
     SqlServer,
 }
 
+/// Transaction state of a client session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransactionStatus {
+    #[default]
+    Idle,
+    InTransaction,
+    Failed,
+}
+
 /// Connection state for protocol adapters
 #[derive(Debug)]
 pub struct Connection {
-    pub stream: TcpStream,
+    pub stream: ConnectionStream,
     pub authenticated: bool,
     pub database: String,
     pub parameters: HashMap<String, String>,
     pub protocol_type: ProtocolType,
+    pub transaction_status: TransactionStatus,
 }
 
 impl Connection {
     pub fn new(stream: TcpStream, protocol_type: ProtocolType) -> Self {
         Self {
-            stream,
+            stream: ConnectionStream::Plain(stream),
             authenticated: false,
             database: String::new(),
             parameters: HashMap::new(),
             protocol_type,
+            transaction_status: TransactionStatus::Idle,
+        }
+    }
+    
+    /// User the client authenticated as, if it did
+    pub fn user(&self) -> Option<&str> {
+        if !self.authenticated {
+            return None;
         }
+        self.parameters.get("user").or_else(|| self.parameters.get("username")).map(String::as_str)
+    }
+}
+
+/// Read the fixed-size header of a client message, or `None` when the client closed the
+/// connection before sending another
+pub(crate) async fn read_header<const N: usize>(conn: &mut Connection) -> NirvResult<Option<[u8; N]>> {
+    let mut header = [0u8; N];
+    match conn.stream.read_exact(&mut header).await {
+        Ok(_) => Ok(Some(header)),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(ProtocolError::ConnectionFailed(format!("Failed to read message: {}", e)).into()),
     }
 }
 
+/// Read the body of a client message whose header announced its length
+pub(crate) async fn read_body(conn: &mut Connection, length: usize) -> NirvResult<Vec<u8>> {
+    let mut body = vec![0u8; length];
+    conn.stream.read_exact(&mut body).await
+        .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to read message: {}", e)))?;
+    Ok(body)
+}
+
 /// Authentication credentials
 #[derive(Debug, Clone)]
 pub struct Credentials {
@@ -117,6 +157,27 @@ impl ProtocolResponse {
     }
 }
 
+/// The outcome of one of the statements a client sent in one message
+#[derive(Debug)]
+pub struct StatementResult {
+    pub sql: String,
+    pub result: NirvResult<QueryResult>,
+}
+
+impl StatementResult {
+    pub fn new(sql: impl Into<String>, result: NirvResult<QueryResult>) -> Self {
+        Self {
+            sql: sql.into(),
+            result,
+        }
+    }
+    
+    /// The statement's first keyword in upper case, as command tags name statements
+    pub fn command(&self) -> String {
+        self.sql.split_whitespace().next().unwrap_or("").to_uppercase()
+    }
+}
+
 /// Main trait for database protocol adapters
 #[async_trait]
 pub trait ProtocolAdapter: Send + Sync {
@@ -132,12 +193,37 @@ pub trait ProtocolAdapter: Send + Sync {
     /// Get the protocol type this adapter handles
     fn get_protocol_type(&self) -> ProtocolType;
     
+    /// Read the next message an authenticated client sent, or `None` once it has closed the
+    /// connection
+    async fn read_message(&self, conn: &mut Connection) -> NirvResult<Option<Vec<u8>>>;
+    
+    /// Whether a parsed message asks to end the connection
+    fn ends_connection(&self, _query: &ProtocolQuery) -> bool {
+        false
+    }
+    
+    /// Answer a query the adapter can answer itself, such as a catalog query, or `None` when
+    /// the engine should execute it
+    async fn answer_query(&self, _conn: &Connection, _query: &ProtocolQuery) -> NirvResult<Option<QueryResult>> {
+        Ok(None)
+    }
+    
     /// Parse protocol-specific message into internal representation
     async fn parse_message(&self, conn: &Connection, data: &[u8]) -> NirvResult<ProtocolQuery>;
     
     /// Format internal query result into protocol-specific response
     async fn format_response(&self, conn: &Connection, result: QueryResult) -> NirvResult<Vec<u8>>;
     
+    /// Format the results of the statements a client sent in one message, in order. A failed
+    /// statement ends them, so an error can only be the last result. Adapters without multiple
+    /// result sets send the last statement's result or error.
+    async fn format_statement_responses(&self, conn: &Connection, results: Vec<StatementResult>) -> NirvResult<Vec<u8>> {
+        match results.into_iter().last() {
+            Some(statement) => self.format_response(conn, statement.result?).await,
+            None => self.format_response(conn, QueryResult::new()).await,
+        }
+    }
+    
     /// Handle connection termination
     async fn terminate_connection(&self, conn: &mut Connection) -> NirvResult<()>;
 }
\ No newline at end of file
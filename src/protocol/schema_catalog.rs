@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+
+use crate::utils::{error::NirvResult, types::Schema};
+
+/// A table visible to clients browsing the catalog
+#[derive(Debug, Clone)]
+pub struct CatalogTable {
+    /// Owning data object type, presented to clients as the schema
+    pub owner: String,
+    /// Object identifier within the data object type
+    pub name: String,
+    /// Column and key information for the table
+    pub schema: Schema,
+}
+
+/// Source of table and column metadata for protocol-level catalog queries
+#[async_trait]
+pub trait SchemaCatalog: Send + Sync {
+    /// All tables currently visible, ordered by owner and name
+    async fn list_tables(&self) -> NirvResult<Vec<CatalogTable>>;
+}
@@ -379,6 +379,8 @@ impl ProtocolAdapter for SQLiteProtocolAdapter {
             rows,
             affected_rows: Some(2),
             execution_time: std::time::Duration::from_millis(5),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         Ok(ProtocolResponse::new(result, ProtocolType::SQLite))
@@ -388,6 +390,22 @@ impl ProtocolAdapter for SQLiteProtocolAdapter {
         ProtocolType::SQLite
     }
     
+    /// Commands are not length-prefixed, so each read carries one
+    async fn read_message(&self, conn: &mut Connection) -> NirvResult<Option<Vec<u8>>> {
+        let mut buffer = vec![0u8; 8192];
+        let bytes_read = conn.stream.read(&mut buffer).await
+            .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to read command: {}", e)))?;
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+        buffer.truncate(bytes_read);
+        Ok(Some(buffer))
+    }
+    
+    fn ends_connection(&self, query: &ProtocolQuery) -> bool {
+        query.raw_query == "CLOSE"
+    }
+    
     async fn parse_message(&self, _conn: &Connection, data: &[u8]) -> NirvResult<ProtocolQuery> {
         let (command, command_data) = self.parse_command(data)?;
         
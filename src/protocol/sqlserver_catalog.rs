@@ -0,0 +1,1200 @@
+use sqlparser::ast::{
+    BinaryOperator, Expr, FunctionArg, FunctionArgExpr, JoinConstraint, JoinOperator, ObjectName,
+    OrderByExpr, Query, Select, SelectItem, SetExpr, Statement, TableFactor, UnaryOperator,
+    Value as SqlValue,
+};
+use sqlparser::dialect::MsSqlDialect;
+use sqlparser::parser::Parser;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::protocol::schema_catalog::{CatalogTable, SchemaCatalog};
+use crate::utils::{
+    error::{NirvResult, ProtocolError},
+    types::{ColumnMetadata, DataType, QueryResult, Row, Value},
+};
+
+/// First object id handed out to catalog tables
+const FIRST_OBJECT_ID: i64 = 1000;
+/// First schema id; lower ids are reserved for built-in schemas such as dbo
+const FIRST_SCHEMA_ID: i64 = 5;
+
+/// Answer a catalog procedure call (`sp_tables`, `sp_columns`, `sp_pkeys`) or a query against
+/// the `sys` / `INFORMATION_SCHEMA` catalog views. Returns `None` if `sql` is neither.
+pub async fn answer_catalog_query(
+    sql: &str,
+    database: &str,
+    catalog: &dyn SchemaCatalog,
+) -> NirvResult<Option<QueryResult>> {
+    if let Some(call) = ProcedureCall::parse(sql) {
+        let context = CatalogContext::new(database, catalog.list_tables().await?);
+        return call.execute(&context).map(Some);
+    }
+
+    if let Some(query) = parse_catalog_select(sql) {
+        let context = CatalogContext::new(database, catalog.list_tables().await?);
+        return context.execute_select(&query).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// Catalog stored procedures understood by the adapter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CatalogProcedure {
+    Tables,
+    Columns,
+    PrimaryKeys,
+}
+
+impl CatalogProcedure {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sp_tables" => Some(Self::Tables),
+            "sp_columns" => Some(Self::Columns),
+            "sp_pkeys" => Some(Self::PrimaryKeys),
+            _ => None,
+        }
+    }
+
+    /// Parameter names in positional order
+    fn parameters(&self) -> &'static [&'static str] {
+        match self {
+            Self::Tables => &["table_name", "table_owner", "table_qualifier", "table_type"],
+            Self::Columns => &["table_name", "table_owner", "table_qualifier", "column_name"],
+            Self::PrimaryKeys => &["table_name", "table_owner", "table_qualifier"],
+        }
+    }
+}
+
+/// A parsed `EXEC sp_xxx ...` call; arguments passed as NULL are stored as `None`
+#[derive(Debug, Clone)]
+struct ProcedureCall {
+    procedure: CatalogProcedure,
+    arguments: HashMap<String, Option<String>>,
+}
+
+impl ProcedureCall {
+    fn parse(sql: &str) -> Option<Self> {
+        let sql = sql.trim().trim_end_matches(';').trim();
+        let (first, rest) = split_first_word(sql);
+        let call = if first.eq_ignore_ascii_case("exec") || first.eq_ignore_ascii_case("execute") {
+            rest
+        } else {
+            sql
+        };
+
+        let (name, argument_list) = split_first_word(call);
+        let name = name.rsplit('.').next()?
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_ascii_lowercase();
+        let procedure = CatalogProcedure::from_name(&name)?;
+
+        let mut arguments = HashMap::new();
+        for (position, argument) in split_arguments(argument_list).into_iter().enumerate() {
+            match argument.strip_prefix('@').and_then(|named| named.split_once('=')) {
+                Some((key, value)) => {
+                    arguments.insert(key.trim().to_ascii_lowercase(), parse_argument_value(value));
+                }
+                None => {
+                    let key = procedure.parameters().get(position)?;
+                    arguments.insert(key.to_string(), parse_argument_value(&argument));
+                }
+            }
+        }
+
+        Some(Self { procedure, arguments })
+    }
+
+    fn argument(&self, name: &str) -> Option<&str> {
+        self.arguments.get(name).and_then(|value| value.as_deref())
+    }
+
+    /// ODBC passes an empty string for arguments that must not restrict the result
+    fn is_blank(&self, name: &str) -> bool {
+        self.argument(name) == Some("")
+    }
+
+    fn required_argument(&self, name: &str) -> NirvResult<&str> {
+        self.argument(name).ok_or_else(|| ProtocolError::InvalidMessageFormat(
+            format!("Catalog procedure expects parameter '@{}'", name)
+        ).into())
+    }
+
+    /// Whether a table passes the name, owner and qualifier arguments
+    fn matches_table(&self, context: &CatalogContext<'_>, table: &CatalogTable, exact_name: bool) -> bool {
+        let name_matches = match self.argument("table_name") {
+            Some(name) if exact_name => name.eq_ignore_ascii_case(&table.name),
+            Some(pattern) => like_match(pattern, &table.name),
+            None => true,
+        };
+        name_matches
+            && self.argument("table_owner").is_none_or(|pattern| like_match(pattern, &table.owner))
+            && self.argument("table_qualifier").is_none_or(|pattern| like_match(pattern, context.database))
+    }
+
+    fn execute(&self, context: &CatalogContext<'_>) -> NirvResult<QueryResult> {
+        match self.procedure {
+            CatalogProcedure::Tables => Ok(self.execute_tables(context)),
+            CatalogProcedure::Columns => self.execute_columns(context),
+            CatalogProcedure::PrimaryKeys => self.execute_primary_keys(context),
+        }
+    }
+
+    fn execute_tables(&self, context: &CatalogContext<'_>) -> QueryResult {
+        let columns = vec![
+            text_column("TABLE_QUALIFIER", true),
+            text_column("TABLE_OWNER", true),
+            text_column("TABLE_NAME", true),
+            text_column("TABLE_TYPE", true),
+            text_column("REMARKS", true),
+        ];
+
+        // The ODBC SQLTables enumeration forms: '%' in one argument with the others blank
+        let enumerate = |name: &str, others: [&str; 3]| {
+            self.argument(name) == Some("%") && others.iter().all(|other| self.is_blank(other))
+        };
+        let rows = if enumerate("table_qualifier", ["table_owner", "table_name", "table_type"]) {
+            vec![vec![text(context.database), Value::Null, Value::Null, Value::Null, Value::Null]]
+        } else if enumerate("table_owner", ["table_qualifier", "table_name", "table_type"]) {
+            context.schemas.iter()
+                .map(|(_, owner)| vec![Value::Null, text(owner), Value::Null, Value::Null, Value::Null])
+                .collect()
+        } else if enumerate("table_type", ["table_qualifier", "table_owner", "table_name"]) {
+            vec![vec![Value::Null, Value::Null, Value::Null, text("TABLE"), Value::Null]]
+        } else if !self.includes_table_type() {
+            Vec::new()
+        } else {
+            context.entries().iter()
+                .filter(|entry| self.matches_table(context, entry.table, false))
+                .map(|entry| vec![
+                    text(context.database),
+                    text(&entry.table.owner),
+                    text(&entry.table.name),
+                    text("TABLE"),
+                    Value::Null,
+                ])
+                .collect()
+        };
+
+        query_result(columns, rows)
+    }
+
+    /// `@table_type` is a quoted list such as `'TABLE','VIEW'`; only tables are exposed
+    fn includes_table_type(&self) -> bool {
+        match self.argument("table_type") {
+            None | Some("") => true,
+            Some(types) => types.split(',')
+                .map(|table_type| table_type.trim().trim_matches('\''))
+                .any(|table_type| table_type.eq_ignore_ascii_case("TABLE")),
+        }
+    }
+
+    fn execute_columns(&self, context: &CatalogContext<'_>) -> NirvResult<QueryResult> {
+        self.required_argument("table_name")?;
+
+        let columns = vec![
+            text_column("TABLE_QUALIFIER", true),
+            text_column("TABLE_OWNER", true),
+            text_column("TABLE_NAME", false),
+            text_column("COLUMN_NAME", false),
+            integer_column("DATA_TYPE", false),
+            text_column("TYPE_NAME", false),
+            integer_column("PRECISION", true),
+            integer_column("LENGTH", true),
+            integer_column("SCALE", true),
+            integer_column("RADIX", true),
+            integer_column("NULLABLE", false),
+            text_column("REMARKS", true),
+            text_column("COLUMN_DEF", true),
+            integer_column("SQL_DATA_TYPE", false),
+            integer_column("SQL_DATETIME_SUB", true),
+            integer_column("CHAR_OCTET_LENGTH", true),
+            integer_column("ORDINAL_POSITION", false),
+            text_column("IS_NULLABLE", true),
+            integer_column("SS_DATA_TYPE", true),
+        ];
+
+        let mut rows = Vec::new();
+        for entry in context.entries().iter().filter(|entry| self.matches_table(context, entry.table, false)) {
+            for (position, column) in entry.table.schema.columns.iter().enumerate() {
+                if !self.argument("column_name").is_none_or(|pattern| like_match(pattern, &column.name)) {
+                    continue;
+                }
+
+                let info = SqlTypeInfo::for_data_type(&column.data_type);
+                let (sql_data_type, datetime_sub) = match column.data_type {
+                    DataType::Date => (9, Value::Integer(1)),
+                    DataType::DateTime => (9, Value::Integer(3)),
+                    _ => (info.odbc_type, Value::Null),
+                };
+                let is_character = matches!(column.data_type, DataType::Text | DataType::Json | DataType::Binary);
+
+                rows.push(vec![
+                    text(context.database),
+                    text(&entry.table.owner),
+                    text(&entry.table.name),
+                    text(&column.name),
+                    Value::Integer(info.odbc_type),
+                    text(info.type_name),
+                    Value::Integer(info.precision),
+                    Value::Integer(info.length),
+                    optional_integer(info.scale),
+                    optional_integer(info.radix),
+                    Value::Integer(column.nullable as i64),
+                    Value::Null,
+                    Value::Null,
+                    Value::Integer(sql_data_type),
+                    datetime_sub,
+                    if is_character { Value::Integer(info.length) } else { Value::Null },
+                    Value::Integer(position as i64 + 1),
+                    text(if column.nullable { "YES" } else { "NO" }),
+                    Value::Integer(info.ss_data_type),
+                ]);
+            }
+        }
+
+        Ok(query_result(columns, rows))
+    }
+
+    fn execute_primary_keys(&self, context: &CatalogContext<'_>) -> NirvResult<QueryResult> {
+        self.required_argument("table_name")?;
+
+        let columns = vec![
+            text_column("TABLE_QUALIFIER", true),
+            text_column("TABLE_OWNER", true),
+            text_column("TABLE_NAME", false),
+            text_column("COLUMN_NAME", false),
+            integer_column("KEY_SEQ", false),
+            text_column("PK_NAME", true),
+        ];
+
+        let mut rows = Vec::new();
+        for entry in context.entries().iter().filter(|entry| self.matches_table(context, entry.table, true)) {
+            let Some(primary_key) = entry.table.schema.primary_key.as_ref() else {
+                continue;
+            };
+            for (sequence, column) in primary_key.iter().enumerate() {
+                rows.push(vec![
+                    text(context.database),
+                    text(&entry.table.owner),
+                    text(&entry.table.name),
+                    text(column),
+                    Value::Integer(sequence as i64 + 1),
+                    text(&format!("PK_{}", entry.table.name)),
+                ]);
+            }
+        }
+
+        Ok(query_result(columns, rows))
+    }
+}
+
+/// SQL Server type information reported for an engine data type
+struct SqlTypeInfo {
+    odbc_type: i64,
+    type_name: &'static str,
+    system_type_id: i64,
+    ss_data_type: i64,
+    precision: i64,
+    length: i64,
+    /// Storage size as reported by `sys.columns`, -1 for `max` types
+    max_length: i64,
+    scale: Option<i64>,
+    radix: Option<i64>,
+}
+
+impl SqlTypeInfo {
+    fn for_data_type(data_type: &DataType) -> Self {
+        let (odbc_type, type_name, system_type_id, ss_data_type, precision, length, max_length, scale, radix) = match data_type {
+            DataType::Text => (-9, "nvarchar", 231, 39, 4000, 8000, 8000, None, None),
+            DataType::Integer => (-5, "bigint", 127, 63, 19, 8, 8, Some(0), Some(10)),
+            DataType::Float => (8, "float", 62, 109, 15, 8, 8, None, Some(10)),
+            DataType::Boolean => (-7, "bit", 104, 50, 1, 1, 1, None, None),
+            DataType::Date => (91, "date", 40, 0, 10, 6, 3, None, None),
+            DataType::DateTime => (93, "datetime2", 42, 0, 27, 16, 8, Some(7), None),
+            DataType::Json => (-10, "nvarchar", 231, 39, 1073741823, 2147483646, -1, None, None),
+            DataType::Binary => (-3, "varbinary", 165, 37, 8000, 8000, 8000, None, None),
+        };
+        Self { odbc_type, type_name, system_type_id, ss_data_type, precision, length, max_length, scale, radix }
+    }
+
+    /// `INFORMATION_SCHEMA.COLUMNS.CHARACTER_MAXIMUM_LENGTH`
+    fn character_maximum_length(&self) -> Value {
+        match self.type_name {
+            "nvarchar" | "varbinary" if self.max_length < 0 => Value::Integer(-1),
+            "nvarchar" => Value::Integer(self.max_length / 2),
+            "varbinary" => Value::Integer(self.max_length),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// A catalog table with the identifiers it is exposed under
+struct CatalogEntry<'a> {
+    object_id: i64,
+    schema_id: i64,
+    table: &'a CatalogTable,
+}
+
+/// Catalog snapshot used to answer a single query
+struct CatalogContext<'a> {
+    database: &'a str,
+    /// Tables ordered by owner and name; object ids follow this order
+    tables: Vec<CatalogTable>,
+    /// Schema id and owner name, ordered by name
+    schemas: Vec<(i64, String)>,
+}
+
+impl<'a> CatalogContext<'a> {
+    fn new(database: &'a str, mut tables: Vec<CatalogTable>) -> Self {
+        tables.sort_by(|a, b| (&a.owner, &a.name).cmp(&(&b.owner, &b.name)));
+
+        let mut owners: Vec<String> = tables.iter().map(|table| table.owner.clone()).collect();
+        owners.dedup();
+        let schemas = owners.into_iter()
+            .enumerate()
+            .map(|(index, owner)| (FIRST_SCHEMA_ID + index as i64, owner))
+            .collect();
+
+        Self { database, tables, schemas }
+    }
+
+    fn entries(&self) -> Vec<CatalogEntry<'_>> {
+        self.tables.iter()
+            .enumerate()
+            .map(|(index, table)| CatalogEntry {
+                object_id: FIRST_OBJECT_ID + index as i64,
+                schema_id: self.schema_id(&table.owner),
+                table,
+            })
+            .collect()
+    }
+
+    fn schema_id(&self, owner: &str) -> i64 {
+        self.schemas.iter()
+            .find(|(_, name)| name == owner)
+            .map(|(id, _)| *id)
+            .unwrap_or(1)
+    }
+
+    fn schema_name(&self, schema_id: i64) -> Value {
+        self.schemas.iter()
+            .find(|(id, _)| *id == schema_id)
+            .map(|(_, name)| text(name))
+            .unwrap_or(Value::Null)
+    }
+
+    fn object_name(&self, object_id: i64) -> Value {
+        self.entries().iter()
+            .find(|entry| entry.object_id == object_id)
+            .map(|entry| text(&entry.table.name))
+            .unwrap_or(Value::Null)
+    }
+
+    /// Rows of a catalog view, or `None` if the name is not a supported view
+    fn view(&self, name: &ObjectName, alias: Option<&str>) -> Option<Relation> {
+        let parts: Vec<String> = name.0.iter().map(|ident| ident.value.to_ascii_lowercase()).collect();
+        let view = match parts.as_slice() {
+            [.., schema, view] => format!("{}.{}", schema, view),
+            _ => return None,
+        };
+        let qualifier = alias.map(str::to_ascii_lowercase).unwrap_or_else(|| parts[parts.len() - 1].clone());
+        let entries = self.entries();
+
+        let (columns, rows): (Vec<ColumnMetadata>, Vec<Vec<Value>>) = match view.as_str() {
+            "sys.schemas" => (
+                vec![text_column("name", false), integer_column("schema_id", false), integer_column("principal_id", true)],
+                self.schemas.iter()
+                    .map(|(id, name)| vec![text(name), Value::Integer(*id), Value::Integer(1)])
+                    .collect(),
+            ),
+            "sys.objects" | "sys.tables" => (
+                vec![
+                    text_column("name", false),
+                    integer_column("object_id", false),
+                    integer_column("principal_id", true),
+                    integer_column("schema_id", false),
+                    integer_column("parent_object_id", false),
+                    text_column("type", false),
+                    text_column("type_desc", false),
+                    boolean_column("is_ms_shipped", false),
+                ],
+                entries.iter()
+                    .map(|entry| vec![
+                        text(&entry.table.name),
+                        Value::Integer(entry.object_id),
+                        Value::Null,
+                        Value::Integer(entry.schema_id),
+                        Value::Integer(0),
+                        text("U"),
+                        text("USER_TABLE"),
+                        Value::Boolean(false),
+                    ])
+                    .collect(),
+            ),
+            "sys.columns" => (
+                vec![
+                    integer_column("object_id", false),
+                    text_column("name", false),
+                    integer_column("column_id", false),
+                    integer_column("system_type_id", false),
+                    integer_column("user_type_id", false),
+                    integer_column("max_length", false),
+                    integer_column("precision", false),
+                    integer_column("scale", false),
+                    boolean_column("is_nullable", false),
+                ],
+                entries.iter()
+                    .flat_map(|entry| entry.table.schema.columns.iter().enumerate().map(move |(index, column)| {
+                        let info = SqlTypeInfo::for_data_type(&column.data_type);
+                        let numeric_precision = if info.radix.is_some() { info.precision } else { 0 };
+                        vec![
+                            Value::Integer(entry.object_id),
+                            text(&column.name),
+                            Value::Integer(index as i64 + 1),
+                            Value::Integer(info.system_type_id),
+                            Value::Integer(info.system_type_id),
+                            Value::Integer(info.max_length),
+                            Value::Integer(numeric_precision),
+                            Value::Integer(info.scale.unwrap_or(0)),
+                            Value::Boolean(column.nullable),
+                        ]
+                    }))
+                    .collect(),
+            ),
+            "information_schema.tables" => (
+                vec![
+                    text_column("TABLE_CATALOG", true),
+                    text_column("TABLE_SCHEMA", true),
+                    text_column("TABLE_NAME", false),
+                    text_column("TABLE_TYPE", true),
+                ],
+                entries.iter()
+                    .map(|entry| vec![
+                        text(self.database),
+                        text(&entry.table.owner),
+                        text(&entry.table.name),
+                        text("BASE TABLE"),
+                    ])
+                    .collect(),
+            ),
+            "information_schema.columns" => (
+                vec![
+                    text_column("TABLE_CATALOG", true),
+                    text_column("TABLE_SCHEMA", true),
+                    text_column("TABLE_NAME", false),
+                    text_column("COLUMN_NAME", true),
+                    integer_column("ORDINAL_POSITION", true),
+                    text_column("COLUMN_DEFAULT", true),
+                    text_column("IS_NULLABLE", true),
+                    text_column("DATA_TYPE", true),
+                    integer_column("CHARACTER_MAXIMUM_LENGTH", true),
+                    integer_column("NUMERIC_PRECISION", true),
+                    integer_column("NUMERIC_SCALE", true),
+                ],
+                entries.iter()
+                    .flat_map(|entry| entry.table.schema.columns.iter().enumerate().map(move |(index, column)| {
+                        let info = SqlTypeInfo::for_data_type(&column.data_type);
+                        vec![
+                            text(self.database),
+                            text(&entry.table.owner),
+                            text(&entry.table.name),
+                            text(&column.name),
+                            Value::Integer(index as i64 + 1),
+                            Value::Null,
+                            text(if column.nullable { "YES" } else { "NO" }),
+                            text(info.type_name),
+                            info.character_maximum_length(),
+                            info.radix.map_or(Value::Null, |_| Value::Integer(info.precision)),
+                            optional_integer(info.scale.filter(|_| info.radix.is_some())),
+                        ]
+                    }))
+                    .collect(),
+            ),
+            _ => return None,
+        };
+
+        Some(Relation {
+            columns: columns.into_iter()
+                .map(|metadata| RelationColumn { qualifier: qualifier.clone(), metadata })
+                .collect(),
+            rows,
+        })
+    }
+
+    fn relation(&self, factor: &TableFactor) -> NirvResult<Relation> {
+        match factor {
+            TableFactor::Table { name, alias, .. } => {
+                self.view(name, alias.as_ref().map(|alias| alias.name.value.as_str()))
+                    .ok_or_else(|| unsupported(format!("'{}' cannot be combined with catalog views", name)))
+            }
+            other => Err(unsupported(format!("'{}' is not supported in catalog queries", other))),
+        }
+    }
+
+    fn execute_select(&self, query: &Query) -> NirvResult<QueryResult> {
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return Err(unsupported("Only simple SELECT statements are supported on catalog views".to_string()));
+        };
+
+        let mut source: Option<Relation> = None;
+        for table in &select.from {
+            let mut relation = self.relation(&table.relation)?;
+            for join in &table.joins {
+                let right = self.relation(&join.relation)?;
+                relation = match &join.join_operator {
+                    JoinOperator::Inner(JoinConstraint::On(on)) => self.join(relation, right, Some(on), false)?,
+                    JoinOperator::LeftOuter(JoinConstraint::On(on)) => self.join(relation, right, Some(on), true)?,
+                    JoinOperator::CrossJoin => self.join(relation, right, None, false)?,
+                    other => return Err(unsupported(format!("Join {:?} is not supported in catalog queries", other))),
+                };
+            }
+            source = Some(match source {
+                Some(left) => self.join(left, relation, None, false)?,
+                None => relation,
+            });
+        }
+        let source = source.ok_or_else(|| unsupported("Catalog query has no FROM clause".to_string()))?;
+
+        let mut rows = Vec::new();
+        for row in &source.rows {
+            let keep = match &select.selection {
+                Some(selection) => is_true(&self.evaluate(selection, &source.columns, row)?),
+                None => true,
+            };
+            if keep {
+                rows.push(row.clone());
+            }
+        }
+
+        let projection = self.projection(select, &source)?;
+        let mut projected = Vec::with_capacity(rows.len());
+        for row in rows {
+            let values = projection.iter()
+                .map(|(_, expr)| self.evaluate(expr, &source.columns, &row))
+                .collect::<NirvResult<Vec<Value>>>()?;
+            projected.push((row, values));
+        }
+
+        let names: Vec<&str> = projection.iter().map(|(name, _)| name.as_str()).collect();
+        self.sort(&mut projected, &query.order_by, &source, &names)?;
+
+        let mut output: Vec<Vec<Value>> = projected.into_iter().map(|(_, values)| values).collect();
+        if select.distinct.is_some() {
+            let mut distinct: Vec<Vec<Value>> = Vec::new();
+            for row in output {
+                if !distinct.iter().any(|seen| rows_equal(seen, &row)) {
+                    distinct.push(row);
+                }
+            }
+            output = distinct;
+        }
+
+        let limit = select.top.as_ref().and_then(|top| top.quantity.as_ref()).or(query.limit.as_ref());
+        if let Some(limit) = limit {
+            match self.evaluate(limit, &[], &[])? {
+                Value::Integer(count) if count >= 0 => output.truncate(count as usize),
+                _ => return Err(unsupported(format!("Row limit '{}' is not supported in catalog queries", limit))),
+            }
+        }
+
+        let columns = projection.iter()
+            .enumerate()
+            .map(|(index, (name, expr))| {
+                let data_type = match source.resolve(expr) {
+                    Some(column) => source.columns[column].metadata.data_type.clone(),
+                    None => output.iter()
+                        .map(|row| &row[index])
+                        .find(|value| **value != Value::Null)
+                        .map(data_type_of)
+                        .unwrap_or(DataType::Text),
+                };
+                ColumnMetadata { name: name.clone(), data_type, nullable: true }
+            })
+            .collect();
+
+        Ok(query_result(columns, output))
+    }
+
+    /// Output column names and the expressions producing them
+    fn projection(&self, select: &Select, source: &Relation) -> NirvResult<Vec<(String, Expr)>> {
+        let column_expr = |column: &RelationColumn| Expr::CompoundIdentifier(vec![
+            column.qualifier.as_str().into(),
+            column.metadata.name.as_str().into(),
+        ]);
+
+        let mut projection = Vec::new();
+        for item in &select.projection {
+            match item {
+                SelectItem::Wildcard(_) => {
+                    for column in &source.columns {
+                        projection.push((column.metadata.name.clone(), column_expr(column)));
+                    }
+                }
+                SelectItem::QualifiedWildcard(name, _) => {
+                    let qualifier = name.0.last().map(|ident| ident.value.to_ascii_lowercase()).unwrap_or_default();
+                    for column in source.columns.iter().filter(|column| column.qualifier == qualifier) {
+                        projection.push((column.metadata.name.clone(), column_expr(column)));
+                    }
+                }
+                SelectItem::UnnamedExpr(expr) => {
+                    let name = match expr {
+                        Expr::Identifier(ident) => ident.value.clone(),
+                        Expr::CompoundIdentifier(idents) => idents.last().map(|ident| ident.value.clone()).unwrap_or_default(),
+                        _ => String::new(),
+                    };
+                    projection.push((name, expr.clone()));
+                }
+                SelectItem::ExprWithAlias { expr, alias } => projection.push((alias.value.clone(), expr.clone())),
+            }
+        }
+        Ok(projection)
+    }
+
+    /// Order rows by source expressions, output column names or 1-based output positions
+    fn sort(
+        &self,
+        rows: &mut [(Vec<Value>, Vec<Value>)],
+        order_by: &[OrderByExpr],
+        source: &Relation,
+        names: &[&str],
+    ) -> NirvResult<()> {
+        if order_by.is_empty() {
+            return Ok(());
+        }
+
+        let mut keyed = Vec::with_capacity(rows.len());
+        for (index, (row, values)) in rows.iter().enumerate() {
+            let mut keys = Vec::with_capacity(order_by.len());
+            for order in order_by {
+                let key = match &order.expr {
+                    Expr::Value(SqlValue::Number(position, _)) => position.parse::<usize>().ok()
+                        .and_then(|position| values.get(position.wrapping_sub(1)).cloned())
+                        .ok_or_else(|| unsupported(format!("ORDER BY position {} is out of range", position)))?,
+                    Expr::Identifier(ident) if source.resolve(&order.expr).is_none() => names.iter()
+                        .position(|name| name.eq_ignore_ascii_case(&ident.value))
+                        .map(|position| values[position].clone())
+                        .ok_or_else(|| unsupported(format!("Unknown column '{}' in ORDER BY", ident.value)))?,
+                    expr => self.evaluate(expr, &source.columns, row)?,
+                };
+                keys.push(key);
+            }
+            keyed.push((keys, index));
+        }
+
+        keyed.sort_by(|(a, _), (b, _)| {
+            order_by.iter().zip(a.iter().zip(b.iter()))
+                .map(|(order, (a, b))| {
+                    let ordering = sort_values(a, b);
+                    if order.asc == Some(false) { ordering.reverse() } else { ordering }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let sorted: Vec<(Vec<Value>, Vec<Value>)> = keyed.iter().map(|(_, index)| rows[*index].clone()).collect();
+        rows.clone_from_slice(&sorted);
+        Ok(())
+    }
+
+    /// Nested-loop join; a left join pads unmatched rows with NULLs
+    fn join(&self, left: Relation, right: Relation, on: Option<&Expr>, left_outer: bool) -> NirvResult<Relation> {
+        let mut columns = left.columns;
+        columns.extend(right.columns);
+
+        let mut rows = Vec::new();
+        for left_row in &left.rows {
+            let mut matched = false;
+            for right_row in &right.rows {
+                let mut row = left_row.clone();
+                row.extend(right_row.iter().cloned());
+                let keep = match on {
+                    Some(on) => is_true(&self.evaluate(on, &columns, &row)?),
+                    None => true,
+                };
+                if keep {
+                    matched = true;
+                    rows.push(row);
+                }
+            }
+            if left_outer && !matched {
+                let mut row = left_row.clone();
+                row.resize(columns.len(), Value::Null);
+                rows.push(row);
+            }
+        }
+
+        Ok(Relation { columns, rows })
+    }
+
+    fn evaluate(&self, expr: &Expr, columns: &[RelationColumn], row: &[Value]) -> NirvResult<Value> {
+        match expr {
+            Expr::Identifier(_) | Expr::CompoundIdentifier(_) => {
+                resolve_column(columns, expr)
+                    .map(|index| row[index].clone())
+                    .ok_or_else(|| unsupported(format!("Unknown column '{}' in catalog query", expr)))
+            }
+            Expr::Value(value) => literal(value),
+            Expr::Nested(inner) => self.evaluate(inner, columns, row),
+            Expr::UnaryOp { op: UnaryOperator::Not, expr } => Ok(match self.evaluate(expr, columns, row)? {
+                Value::Boolean(value) => Value::Boolean(!value),
+                _ => Value::Null,
+            }),
+            Expr::IsNull(inner) => Ok(Value::Boolean(self.evaluate(inner, columns, row)? == Value::Null)),
+            Expr::IsNotNull(inner) => Ok(Value::Boolean(self.evaluate(inner, columns, row)? != Value::Null)),
+            Expr::BinaryOp { left, op, right } => {
+                let left = self.evaluate(left, columns, row)?;
+                let right = self.evaluate(right, columns, row)?;
+                match op {
+                    BinaryOperator::And => Ok(Value::Boolean(is_true(&left) && is_true(&right))),
+                    BinaryOperator::Or => Ok(Value::Boolean(is_true(&left) || is_true(&right))),
+                    _ => {
+                        let Some(ordering) = compare_values(&left, &right) else {
+                            return Ok(Value::Null);
+                        };
+                        let result = match op {
+                            BinaryOperator::Eq => ordering == Ordering::Equal,
+                            BinaryOperator::NotEq => ordering != Ordering::Equal,
+                            BinaryOperator::Lt => ordering == Ordering::Less,
+                            BinaryOperator::LtEq => ordering != Ordering::Greater,
+                            BinaryOperator::Gt => ordering == Ordering::Greater,
+                            BinaryOperator::GtEq => ordering != Ordering::Less,
+                            other => return Err(unsupported(format!("Operator {} is not supported in catalog queries", other))),
+                        };
+                        Ok(Value::Boolean(result))
+                    }
+                }
+            }
+            Expr::Like { negated, expr, pattern, .. } => {
+                let value = self.evaluate(expr, columns, row)?;
+                let pattern = self.evaluate(pattern, columns, row)?;
+                match (value_text(&value), value_text(&pattern)) {
+                    (Some(value), Some(pattern)) => Ok(Value::Boolean(like_match(&pattern, &value) != *negated)),
+                    _ => Ok(Value::Null),
+                }
+            }
+            Expr::InList { expr, list, negated } => {
+                let value = self.evaluate(expr, columns, row)?;
+                let mut found = false;
+                for item in list {
+                    let item = self.evaluate(item, columns, row)?;
+                    if compare_values(&value, &item) == Some(Ordering::Equal) {
+                        found = true;
+                        break;
+                    }
+                }
+                Ok(Value::Boolean(found != *negated))
+            }
+            Expr::Function(function) => {
+                let mut arguments = Vec::new();
+                for argument in &function.args {
+                    match argument {
+                        FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => arguments.push(self.evaluate(expr, columns, row)?),
+                        other => return Err(unsupported(format!("Function argument '{}' is not supported in catalog queries", other))),
+                    }
+                }
+
+                match (function.name.to_string().to_ascii_lowercase().as_str(), arguments.as_slice()) {
+                    ("schema_name", [Value::Integer(id)]) => Ok(self.schema_name(*id)),
+                    ("schema_name", []) => Ok(self.schemas.first().map(|(_, name)| text(name)).unwrap_or(Value::Null)),
+                    ("object_name", [Value::Integer(id)]) => Ok(self.object_name(*id)),
+                    ("db_name", []) => Ok(text(self.database)),
+                    ("schema_name" | "object_name", [_]) => Ok(Value::Null),
+                    (name, _) => Err(unsupported(format!("Function {} is not supported in catalog queries", name))),
+                }
+            }
+            other => Err(unsupported(format!("Expression '{}' is not supported in catalog queries", other))),
+        }
+    }
+}
+
+/// Intermediate rows while evaluating a catalog view query
+struct Relation {
+    columns: Vec<RelationColumn>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl Relation {
+    fn resolve(&self, expr: &Expr) -> Option<usize> {
+        resolve_column(&self.columns, expr)
+    }
+}
+
+/// A column together with the view alias it is reachable through
+#[derive(Clone)]
+struct RelationColumn {
+    qualifier: String,
+    metadata: ColumnMetadata,
+}
+
+/// Index of the column an identifier refers to; unqualified names take the first match
+fn resolve_column(columns: &[RelationColumn], expr: &Expr) -> Option<usize> {
+    let (qualifier, name) = match expr {
+        Expr::Identifier(ident) => (None, &ident.value),
+        Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [.., qualifier, name] => (Some(qualifier.value.to_ascii_lowercase()), &name.value),
+            [name] => (None, &name.value),
+            [] => return None,
+        },
+        _ => return None,
+    };
+
+    columns.iter().position(|column| {
+        column.metadata.name.eq_ignore_ascii_case(name)
+            && qualifier.as_ref().is_none_or(|qualifier| *qualifier == column.qualifier)
+    })
+}
+
+/// Parse a SELECT whose FROM clause references a catalog view
+fn parse_catalog_select(sql: &str) -> Option<Query> {
+    let lowered = sql.to_ascii_lowercase();
+    if !lowered.contains("sys.") && !lowered.contains("information_schema.") {
+        return None;
+    }
+
+    let statements = Parser::parse_sql(&MsSqlDialect {}, sql).ok()?;
+    let [Statement::Query(query)] = statements.as_slice() else {
+        return None;
+    };
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return None;
+    };
+
+    let is_catalog_view = |factor: &TableFactor| match factor {
+        TableFactor::Table { name, .. } => {
+            let parts: Vec<String> = name.0.iter().map(|ident| ident.value.to_ascii_lowercase()).collect();
+            matches!(parts.as_slice(), [.., schema, _] if schema == "sys" || schema == "information_schema")
+        }
+        _ => false,
+    };
+    let references_catalog = select.from.iter().any(|table| {
+        is_catalog_view(&table.relation) || table.joins.iter().any(|join| is_catalog_view(&join.relation))
+    });
+
+    references_catalog.then(|| query.as_ref().clone())
+}
+
+fn split_first_word(input: &str) -> (&str, &str) {
+    match input.find(char::is_whitespace) {
+        Some(index) => (&input[..index], input[index..].trim_start()),
+        None => (input, ""),
+    }
+}
+
+/// Split a procedure argument list on commas outside quoted strings
+fn split_arguments(input: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '\'' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ',' if !in_quotes => arguments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    arguments.push(current);
+
+    let arguments: Vec<String> = arguments.into_iter().map(|argument| argument.trim().to_string()).collect();
+    if arguments.len() == 1 && arguments[0].is_empty() {
+        Vec::new()
+    } else {
+        arguments
+    }
+}
+
+fn parse_argument_value(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.eq_ignore_ascii_case("null") {
+        return None;
+    }
+
+    let unprefixed = match raw.strip_prefix(['N', 'n']) {
+        Some(rest) if rest.starts_with('\'') => rest,
+        _ => raw,
+    };
+    match unprefixed.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\'')) {
+        Some(quoted) => Some(quoted.replace("''", "'")),
+        None => Some(unprefixed.to_string()),
+    }
+}
+
+/// Case-insensitive T-SQL LIKE supporting `%`, `_` and `[...]` character classes
+fn like_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let value: Vec<char> = value.to_lowercase().chars().collect();
+    like_match_from(&pattern, &value)
+}
+
+fn like_match_from(pattern: &[char], value: &[char]) -> bool {
+    match pattern.first() {
+        None => value.is_empty(),
+        Some('%') => (0..=value.len()).any(|skip| like_match_from(&pattern[1..], &value[skip..])),
+        Some('_') => !value.is_empty() && like_match_from(&pattern[1..], &value[1..]),
+        Some('[') => {
+            let Some(end) = pattern.iter().position(|c| *c == ']') else {
+                return value.first() == Some(&'[') && like_match_from(&pattern[1..], &value[1..]);
+            };
+            let Some(c) = value.first() else {
+                return false;
+            };
+            let (negated, class) = match &pattern[1..end] {
+                ['^', rest @ ..] => (true, rest),
+                class => (false, class),
+            };
+            let mut matched = false;
+            let mut index = 0;
+            while index < class.len() {
+                if index + 2 < class.len() && class[index + 1] == '-' {
+                    matched |= class[index] <= *c && *c <= class[index + 2];
+                    index += 3;
+                } else {
+                    matched |= class[index] == *c;
+                    index += 1;
+                }
+            }
+            matched != negated && like_match_from(&pattern[end + 1..], &value[1..])
+        }
+        Some(c) => value.first() == Some(c) && like_match_from(&pattern[1..], &value[1..]),
+    }
+}
+
+fn literal(value: &SqlValue) -> NirvResult<Value> {
+    match value {
+        SqlValue::SingleQuotedString(s) | SqlValue::NationalStringLiteral(s) => Ok(Value::Text(s.clone())),
+        SqlValue::Number(n, _) => n.parse::<i64>()
+            .map(Value::Integer)
+            .or_else(|_| n.parse::<f64>().map(Value::Float))
+            .map_err(|_| unsupported(format!("Invalid number '{}'", n))),
+        SqlValue::Boolean(b) => Ok(Value::Boolean(*b)),
+        SqlValue::Null => Ok(Value::Null),
+        other => Err(unsupported(format!("Literal {} is not supported in catalog queries", other))),
+    }
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Boolean(b) => Some((*b as i64).to_string()),
+        Value::Binary(_) | Value::Null => None,
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        Value::Boolean(b) => Some(*b as i64 as f64),
+        Value::Text(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// SQL comparison: `None` when either side is NULL; text compares case-insensitively
+/// ignoring trailing spaces, as under SQL Server's default collation
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Text(_), Value::Text(_)) | (Value::Date(_), _) | (Value::DateTime(_), _) | (Value::Json(_), _) => {
+            let left = value_text(left)?.trim_end().to_lowercase();
+            let right = value_text(right)?.trim_end().to_lowercase();
+            Some(left.cmp(&right))
+        }
+        _ => match (as_number(left), as_number(right)) {
+            (Some(left), Some(right)) => left.partial_cmp(&right),
+            _ => Some(value_text(left)?.to_lowercase().cmp(&value_text(right)?.to_lowercase())),
+        },
+    }
+}
+
+/// Ordering for ORDER BY, with NULLs first as in SQL Server
+fn sort_values(left: &Value, right: &Value) -> Ordering {
+    match (left, right) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        _ => compare_values(left, right).unwrap_or(Ordering::Equal),
+    }
+}
+
+fn rows_equal(left: &[Value], right: &[Value]) -> bool {
+    left.iter().zip(right).all(|(left, right)| sort_values(left, right) == Ordering::Equal)
+}
+
+fn is_true(value: &Value) -> bool {
+    matches!(value, Value::Boolean(true))
+}
+
+fn data_type_of(value: &Value) -> DataType {
+    match value {
+        Value::Integer(_) => DataType::Integer,
+        Value::Float(_) => DataType::Float,
+        Value::Boolean(_) => DataType::Boolean,
+        Value::Date(_) => DataType::Date,
+        Value::DateTime(_) => DataType::DateTime,
+        Value::Json(_) => DataType::Json,
+        Value::Binary(_) => DataType::Binary,
+        Value::Text(_) | Value::Null => DataType::Text,
+    }
+}
+
+fn unsupported(message: String) -> crate::utils::error::NirvError {
+    ProtocolError::UnsupportedFeature(message).into()
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+fn optional_integer(value: Option<i64>) -> Value {
+    value.map_or(Value::Null, Value::Integer)
+}
+
+fn text_column(name: &str, nullable: bool) -> ColumnMetadata {
+    ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable }
+}
+
+fn integer_column(name: &str, nullable: bool) -> ColumnMetadata {
+    ColumnMetadata { name: name.to_string(), data_type: DataType::Integer, nullable }
+}
+
+fn boolean_column(name: &str, nullable: bool) -> ColumnMetadata {
+    ColumnMetadata { name: name.to_string(), data_type: DataType::Boolean, nullable }
+}
+
+fn query_result(columns: Vec<ColumnMetadata>, rows: Vec<Vec<Value>>) -> QueryResult {
+    QueryResult {
+        columns,
+        rows: rows.into_iter().map(Row::new).collect(),
+        affected_rows: None,
+        execution_time: std::time::Duration::from_millis(0),
+        stats: None,
+        warnings: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::utils::types::Schema;
+
+    struct StaticCatalog(Vec<CatalogTable>);
+
+    #[async_trait]
+    impl SchemaCatalog for StaticCatalog {
+        async fn list_tables(&self) -> NirvResult<Vec<CatalogTable>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn catalog() -> StaticCatalog {
+        let table = |owner: &str, name: &str, columns: Vec<ColumnMetadata>| CatalogTable {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            schema: Schema {
+                name: name.to_string(),
+                columns,
+                primary_key: Some(vec!["id".to_string()]),
+                indexes: vec![],
+                column_details: HashMap::new(),
+                foreign_keys: Vec::new(),
+            },
+        };
+        StaticCatalog(vec![
+            table("mock", "users", vec![integer_column("id", false), text_column("name", true)]),
+            table("file", "sales.csv", vec![integer_column("id", false)]),
+        ])
+    }
+
+    async fn answer(sql: &str) -> QueryResult {
+        answer_catalog_query(sql, "nirv", &catalog()).await.unwrap().expect("catalog query")
+    }
+
+    fn column(result: &QueryResult, name: &str) -> Vec<Value> {
+        let index = result.columns.iter().position(|c| c.name == name).expect("column");
+        result.rows.iter().map(|row| row.values[index].clone()).collect()
+    }
+
+    #[test]
+    fn test_procedure_call_parsing() {
+        let call = ProcedureCall::parse("EXEC [sys].[sp_columns] @table_name = N'user''s', @table_owner = NULL;").unwrap();
+        assert_eq!(call.procedure, CatalogProcedure::Columns);
+        assert_eq!(call.argument("table_name"), Some("user's"));
+        assert_eq!(call.argument("table_owner"), None);
+
+        let call = ProcedureCall::parse("sp_tables 'users', 'mock'").unwrap();
+        assert_eq!(call.argument("table_name"), Some("users"));
+        assert_eq!(call.argument("table_owner"), Some("mock"));
+
+        assert!(ProcedureCall::parse("EXEC sp_who").is_none());
+        assert!(ProcedureCall::parse("SELECT 1").is_none());
+    }
+
+    #[test]
+    fn test_like_match() {
+        assert!(like_match("%", "users"));
+        assert!(like_match("US_RS", "users"));
+        assert!(like_match("[a-v]sers", "users"));
+        assert!(!like_match("[^u]sers", "users"));
+        assert!(!like_match("user", "users"));
+    }
+
+    #[tokio::test]
+    async fn test_sp_tables_filters() {
+        let all = answer("EXEC sp_tables @table_type = '''TABLE'''").await;
+        assert_eq!(column(&all, "TABLE_NAME"), vec![text("sales.csv"), text("users")]);
+
+        let owned = answer("sp_tables @table_name = 'u%', @table_owner = 'mock'").await;
+        assert_eq!(owned.row_count(), 1);
+
+        let views = answer("sp_tables @table_type = '''VIEW'''").await;
+        assert_eq!(views.row_count(), 0);
+
+        let owners = answer("sp_tables @table_qualifier = '', @table_owner = '%', @table_name = '', @table_type = ''").await;
+        assert_eq!(column(&owners, "TABLE_OWNER"), vec![text("file"), text("mock")]);
+    }
+
+    #[tokio::test]
+    async fn test_sp_columns_and_pkeys() {
+        let columns = answer("exec sp_columns @table_name = N'users'").await;
+        assert_eq!(column(&columns, "COLUMN_NAME"), vec![text("id"), text("name")]);
+        assert_eq!(column(&columns, "TYPE_NAME"), vec![text("bigint"), text("nvarchar")]);
+        assert_eq!(column(&columns, "NULLABLE"), vec![Value::Integer(0), Value::Integer(1)]);
+
+        let keys = answer("sp_pkeys users").await;
+        assert_eq!(column(&keys, "COLUMN_NAME"), vec![text("id")]);
+
+        assert!(answer_catalog_query("sp_columns", "nirv", &catalog()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_catalog_view_queries() {
+        let result = answer(
+            "SELECT s.name AS schema_name, t.name FROM sys.tables t \
+             INNER JOIN sys.schemas s ON t.schema_id = s.schema_id \
+             WHERE t.type = 'U' AND s.name IN ('mock') ORDER BY t.name"
+        ).await;
+        assert_eq!(result.columns[0].name, "schema_name");
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values, vec![text("mock"), text("users")]);
+
+        let result = answer(
+            "SELECT COLUMN_NAME, DATA_TYPE FROM INFORMATION_SCHEMA.COLUMNS \
+             WHERE TABLE_NAME = 'users' ORDER BY ORDINAL_POSITION DESC"
+        ).await;
+        assert_eq!(column(&result, "COLUMN_NAME"), vec![text("name"), text("id")]);
+
+        let result = answer("SELECT TOP 1 SCHEMA_NAME(schema_id) AS owner FROM sys.objects ORDER BY 1 DESC").await;
+        assert_eq!(result.rows[0].values, vec![text("mock")]);
+
+        assert!(answer_catalog_query("SELECT * FROM users", "nirv", &catalog()).await.unwrap().is_none());
+    }
+}
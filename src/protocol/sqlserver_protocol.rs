@@ -1,8 +1,10 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::net::TcpStream;
 
-use crate::protocol::{ProtocolAdapter, ProtocolType, Connection, Credentials, ProtocolQuery, ProtocolResponse};
+use crate::protocol::{ProtocolAdapter, ProtocolType, Connection, Credentials, ProtocolQuery, ProtocolResponse, StatementResult, read_body, read_header};
+use crate::protocol::{SchemaCatalog, answer_catalog_query};
 use crate::utils::{NirvResult, ProtocolError, QueryResult, ColumnMetadata, Row, Value, DataType};
 
 /// SQL Server TDS (Tabular Data Stream) protocol version
@@ -63,15 +65,29 @@ pub enum TdsDataType {
 }
 
 /// SQL Server protocol adapter implementation
-#[derive(Debug)]
 pub struct SqlServerProtocol {
-    // Configuration and state can be added here
+    /// Catalog answering `sp_tables`, `sp_columns` and catalog view queries from BI tools
+    catalog: Option<Arc<dyn SchemaCatalog>>,
+}
+
+impl std::fmt::Debug for SqlServerProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqlServerProtocol")
+            .field("catalog", &self.catalog.is_some())
+            .finish()
+    }
 }
 
 impl SqlServerProtocol {
     /// Create a new SQL Server protocol adapter
     pub fn new() -> Self {
-        Self {}
+        Self { catalog: None }
+    }
+
+    /// Answer catalog procedures and catalog view queries from the given schema catalog
+    pub fn with_catalog(mut self, catalog: Arc<dyn SchemaCatalog>) -> Self {
+        self.catalog = Some(catalog);
+        self
     }
     
     /// Parse a TDS login packet
@@ -111,7 +127,7 @@ impl SqlServerProtocol {
         }
         
         // SQL Server sends SQL text as UTF-16LE
-        if data.len() % 2 != 0 {
+        if !data.len().is_multiple_of(2) {
             return Err(ProtocolError::InvalidMessageFormat("Invalid UTF-16 data length".to_string()).into());
         }
         
@@ -318,52 +334,66 @@ impl SqlServerProtocol {
         token
     }
     
-    /// Create an error response
-    pub fn create_error_response(&self, error_number: u32, message: &str, severity: u8) -> Vec<u8> {
-        let mut response = Vec::new();
-        
-        // TDS header
-        let header = self.create_tds_header(TdsPacketType::TabularResult, 0);
-        response.extend_from_slice(&header);
-        
-        // Error token
-        response.push(TdsTokenType::Error as u8);
-        
-        // Token length (placeholder)
-        let length_pos = response.len();
-        response.extend_from_slice(&0u16.to_le_bytes());
-        
-        // Error number
-        response.extend_from_slice(&error_number.to_le_bytes());
-        
-        // State
-        response.push(1);
-        
-        // Severity
-        response.push(severity);
-        
-        // Message length and text
-        response.extend_from_slice(&(message.len() as u16).to_le_bytes());
-        response.extend_from_slice(message.as_bytes());
-        
-        // Server name (empty)
-        response.push(0);
-        
-        // Procedure name (empty)
-        response.push(0);
+    /// Create an INFO token carrying a warning, as `PRINT` and severity 10 messages do
+    pub fn create_info(&self, message: &str) -> Vec<u8> {
+        let message_utf16: Vec<u16> = message.encode_utf16().collect();
+        let mut fields = Vec::new();
+        
+        // Message number, state and severity
+        fields.extend_from_slice(&0u32.to_le_bytes());
+        fields.push(1);
+        fields.push(10);
+        
+        // Message length in characters and text
+        fields.extend_from_slice(&(message_utf16.len() as u16).to_le_bytes());
+        for unit in message_utf16 {
+            fields.extend_from_slice(&unit.to_le_bytes());
+        }
         
-        // Line number
-        response.extend_from_slice(&0u32.to_le_bytes());
+        // Server and procedure names (empty), line number
+        fields.push(0);
+        fields.push(0);
+        fields.extend_from_slice(&0u32.to_le_bytes());
         
-        // Update token length
-        let token_length = (response.len() - length_pos - 2) as u16;
-        response[length_pos..length_pos + 2].copy_from_slice(&token_length.to_le_bytes());
+        let mut token = vec![TdsTokenType::Info as u8];
+        token.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+        token.extend_from_slice(&fields);
+        token
+    }
+    
+    /// Create an error response
+    pub fn create_error_response(&self, error_number: u32, message: &str, severity: u8) -> Vec<u8> {
+        let token = self.create_error_token(error_number, message, severity);
+        let mut response = self.create_tds_header(TdsPacketType::TabularResult, (token.len() + 8) as u16);
+        response.extend_from_slice(&token);
+        response
+    }
+    
+    /// Create an ERROR token
+    pub fn create_error_token(&self, error_number: u32, message: &str, severity: u8) -> Vec<u8> {
+        let message_utf16: Vec<u16> = message.encode_utf16().collect();
+        let mut fields = Vec::new();
+        
+        // Error number, state and severity
+        fields.extend_from_slice(&error_number.to_le_bytes());
+        fields.push(1);
+        fields.push(severity);
+        
+        // Message length in characters and text
+        fields.extend_from_slice(&(message_utf16.len() as u16).to_le_bytes());
+        for unit in message_utf16 {
+            fields.extend_from_slice(&unit.to_le_bytes());
+        }
         
-        // Update TDS header length
-        let total_length = response.len() as u16;
-        response[2..4].copy_from_slice(&total_length.to_be_bytes());
+        // Server and procedure names (empty), line number
+        fields.push(0);
+        fields.push(0);
+        fields.extend_from_slice(&0u32.to_le_bytes());
         
-        response
+        let mut token = vec![TdsTokenType::Error as u8];
+        token.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+        token.extend_from_slice(&fields);
+        token
     }
     
     /// Convert internal DataType to TDS type code
@@ -451,10 +481,14 @@ impl ProtocolAdapter for SqlServerProtocol {
         Ok(())
     }
     
-    async fn handle_query(&self, conn: &Connection, _query: ProtocolQuery) -> NirvResult<ProtocolResponse> {
+    async fn handle_query(&self, conn: &Connection, query: ProtocolQuery) -> NirvResult<ProtocolResponse> {
         if !conn.authenticated {
             return Err(ProtocolError::AuthenticationFailed("Connection not authenticated".to_string()).into());
         }
+
+        if let Some(result) = self.answer_query(conn, &query).await? {
+            return Ok(ProtocolResponse::new(result, ProtocolType::SqlServer));
+        }
         
         // For testing, return a mock result
         let mock_result = QueryResult {
@@ -475,6 +509,8 @@ impl ProtocolAdapter for SqlServerProtocol {
             ],
             affected_rows: Some(1),
             execution_time: std::time::Duration::from_millis(5),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         Ok(ProtocolResponse::new(mock_result, ProtocolType::SqlServer))
@@ -484,6 +520,29 @@ impl ProtocolAdapter for SqlServerProtocol {
         ProtocolType::SqlServer
     }
     
+    /// Answer catalog procedures and catalog view queries from the catalog, when there is one
+    async fn answer_query(&self, conn: &Connection, query: &ProtocolQuery) -> NirvResult<Option<QueryResult>> {
+        let Some(catalog) = &self.catalog else {
+            return Ok(None);
+        };
+        let database = if conn.database.is_empty() { "master" } else { conn.database.as_str() };
+        answer_catalog_query(&query.raw_query, database, catalog.as_ref()).await
+    }
+    
+    async fn read_message(&self, conn: &mut Connection) -> NirvResult<Option<Vec<u8>>> {
+        // The TDS header's length counts the header itself
+        let Some(header) = read_header::<8>(conn).await? else {
+            return Ok(None);
+        };
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        if length < 8 {
+            return Err(ProtocolError::InvalidMessageFormat(format!("Invalid TDS packet length: {}", length)).into());
+        }
+        let mut packet = header.to_vec();
+        packet.extend(read_body(conn, length - 8).await?);
+        Ok(Some(packet))
+    }
+    
     async fn parse_message(&self, _conn: &Connection, data: &[u8]) -> NirvResult<ProtocolQuery> {
         if data.len() < 8 {
             return Err(ProtocolError::InvalidMessageFormat("TDS packet too short".to_string()).into());
@@ -524,10 +583,13 @@ impl ProtocolAdapter for SqlServerProtocol {
         // Create DONE token
         let done = self.create_done(0x0010, 0xC1, result.rows.len() as u64); // DONE_COUNT
         
-        // Combine all tokens
+        // Combine all tokens, with sources missing from a partial result as messages
         let mut tokens = Vec::new();
         tokens.extend_from_slice(&colmetadata);
         tokens.extend_from_slice(&rows_data);
+        for warning in &result.warnings {
+            tokens.extend_from_slice(&self.create_info(&warning.to_string()));
+        }
         tokens.extend_from_slice(&done);
         
         // Create TDS header
@@ -539,6 +601,42 @@ impl ProtocolAdapter for SqlServerProtocol {
         Ok(response)
     }
     
+    /// Answer a batch with each statement's rows followed by a DONE token, all but the last
+    /// flagged DONE_MORE. A failed statement ends the batch with an error and DONE_ERROR.
+    async fn format_statement_responses(&self, _conn: &Connection, results: Vec<StatementResult>) -> NirvResult<Vec<u8>> {
+        let mut tokens = Vec::new();
+        for (i, statement) in results.iter().enumerate() {
+            let more = if i + 1 < results.len() { 0x0001 } else { 0 }; // DONE_MORE
+            match &statement.result {
+                Ok(result) if result.columns.is_empty() => {
+                    let count = result.affected_rows.unwrap_or(0);
+                    tokens.extend_from_slice(&self.create_done(more | 0x0010, 0, count)); // DONE_COUNT
+                }
+                Ok(result) => {
+                    tokens.extend_from_slice(&self.create_colmetadata(&result.columns));
+                    for row in &result.rows {
+                        tokens.extend_from_slice(&self.create_row(row, &result.columns));
+                    }
+                    for warning in &result.warnings {
+                        tokens.extend_from_slice(&self.create_info(&warning.to_string()));
+                    }
+                    tokens.extend_from_slice(&self.create_done(more | 0x0010, 0xC1, result.rows.len() as u64));
+                }
+                Err(e) => {
+                    tokens.extend_from_slice(&self.create_error_token(50000, &e.to_string(), 16));
+                    tokens.extend_from_slice(&self.create_done(0x0002, 0, 0)); // DONE_ERROR
+                }
+            }
+        }
+        if results.is_empty() {
+            tokens.extend_from_slice(&self.create_done(0, 0, 0));
+        }
+        
+        let mut response = self.create_tds_header(TdsPacketType::TabularResult, (tokens.len() + 8) as u16);
+        response.extend_from_slice(&tokens);
+        Ok(response)
+    }
+    
     async fn terminate_connection(&self, conn: &mut Connection) -> NirvResult<()> {
         conn.authenticated = false;
         conn.database.clear();
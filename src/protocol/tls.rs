@@ -0,0 +1,98 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_native_tls::native_tls::{self, Identity};
+use tokio_native_tls::{TlsAcceptor, TlsStream};
+
+use crate::utils::{NirvResult, ProtocolError, TlsConfig};
+
+/// Transport of a client connection, upgraded in place when the client negotiates TLS
+#[derive(Debug)]
+pub enum ConnectionStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+    /// Transient state while a TLS handshake owns the socket
+    Upgrading,
+}
+
+impl ConnectionStream {
+    /// Whether traffic on this stream is encrypted
+    pub fn is_secure(&self) -> bool {
+        matches!(self, ConnectionStream::Tls(_))
+    }
+
+    /// Perform a server-side TLS handshake over the plain stream
+    pub async fn upgrade_to_tls(&mut self, acceptor: &TlsAcceptor) -> NirvResult<()> {
+        let ConnectionStream::Plain(stream) = std::mem::replace(self, ConnectionStream::Upgrading) else {
+            return Err(ProtocolError::ConnectionFailed("Connection is not a plain TCP stream".to_string()).into());
+        };
+
+        let tls_stream = acceptor.accept(stream).await
+            .map_err(|e| ProtocolError::ConnectionFailed(format!("TLS handshake failed: {}", e)))?;
+        *self = ConnectionStream::Tls(Box::new(tls_stream));
+        Ok(())
+    }
+}
+
+impl From<TcpStream> for ConnectionStream {
+    fn from(stream: TcpStream) -> Self {
+        ConnectionStream::Plain(stream)
+    }
+}
+
+fn upgrading_error() -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "connection is being upgraded to TLS")
+}
+
+impl AsyncRead for ConnectionStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            ConnectionStream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+            ConnectionStream::Upgrading => Poll::Ready(Err(upgrading_error())),
+        }
+    }
+}
+
+impl AsyncWrite for ConnectionStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            ConnectionStream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+            ConnectionStream::Upgrading => Poll::Ready(Err(upgrading_error())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            ConnectionStream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+            ConnectionStream::Upgrading => Poll::Ready(Err(upgrading_error())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ConnectionStream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            ConnectionStream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+            ConnectionStream::Upgrading => Poll::Ready(Err(upgrading_error())),
+        }
+    }
+}
+
+/// Build a TLS acceptor from PEM certificate and PKCS#8 key files.
+/// Client certificate verification (`ca_file`, `require_client_cert`) is not supported.
+pub fn load_tls_acceptor(config: &TlsConfig) -> NirvResult<TlsAcceptor> {
+    let read = |path: &str| std::fs::read(path)
+        .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to read {}: {}", path, e)));
+    let certificate = read(&config.cert_file)?;
+    let key = read(&config.key_file)?;
+
+    let identity = Identity::from_pkcs8(&certificate, &key)
+        .map_err(|e| ProtocolError::ConnectionFailed(format!("Invalid TLS certificate or key: {}", e)))?;
+    let acceptor = native_tls::TlsAcceptor::new(identity)
+        .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to create TLS acceptor: {}", e)))?;
+    Ok(TlsAcceptor::from(acceptor))
+}
@@ -0,0 +1,176 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::protocol::schema_catalog::SchemaCatalog;
+use crate::utils::{
+    error::NirvResult,
+    types::{ConnectorType, QueryResult},
+};
+
+/// Queries kept for the console's history
+pub const CONSOLE_HISTORY_LIMIT: usize = 100;
+
+/// The console page, with its script and styles inline
+const CONSOLE_PAGE: &str = include_str!("web_console.html");
+
+/// A registered source as the console lists it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceStatus {
+    pub object_type: String,
+    pub connector_type: ConnectorType,
+    pub connected: bool,
+    /// Data objects the source lists, when listing them succeeded
+    pub objects: Option<usize>,
+    /// Why the source is failing in the background, or else why listing its objects failed
+    pub error: Option<String>,
+}
+
+/// Engine state the console shows besides the catalog
+#[async_trait]
+pub trait ConsoleHandler: SchemaCatalog {
+    /// Every registered source with its health, ordered by object type
+    async fn source_status(&self) -> Vec<SourceStatus>;
+}
+
+/// A query run through the HTTP API
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecentQuery {
+    pub sql: String,
+    pub finished_at: DateTime<Utc>,
+    /// Execution time of a query that succeeded
+    pub duration_ms: Option<u64>,
+    pub rows: Option<usize>,
+    pub error: Option<String>,
+}
+
+/// The latest queries run through the HTTP API; the oldest is dropped once it is full
+#[derive(Debug)]
+pub struct QueryHistoryLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<RecentQuery>>,
+}
+
+impl QueryHistoryLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a query that has just finished
+    pub fn record(&self, sql: &str, result: &NirvResult<QueryResult>) {
+        let (duration_ms, rows, error) = match result {
+            Ok(result) => (Some(result.execution_time.as_millis() as u64), Some(result.rows.len()), None),
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+        let mut entries = self.entries.lock().expect("query history lock");
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(RecentQuery { sql: sql.to_string(), finished_at: Utc::now(), duration_ms, rows, error });
+    }
+
+    /// Recorded queries, newest first
+    pub fn recent(&self) -> Vec<RecentQuery> {
+        self.entries.lock().expect("query history lock").iter().rev().cloned().collect()
+    }
+}
+
+impl Default for QueryHistoryLog {
+    fn default() -> Self {
+        Self::new(CONSOLE_HISTORY_LIMIT)
+    }
+}
+
+/// Operator console served by the HTTP API: `GET /console` is the page, which reads
+/// `/console/sources`, `/console/tables` and `/console/history` and runs SQL with `POST /query`
+pub struct WebConsole {
+    handler: Arc<dyn ConsoleHandler>,
+    history: QueryHistoryLog,
+}
+
+impl WebConsole {
+    pub fn new(handler: Arc<dyn ConsoleHandler>) -> Self {
+        Self {
+            handler,
+            history: QueryHistoryLog::default(),
+        }
+    }
+
+    pub fn history(&self) -> &QueryHistoryLog {
+        &self.history
+    }
+
+    /// Whether a path is the page, which is served without credentials so that the page
+    /// can ask for them
+    pub fn is_page(path: &str) -> bool {
+        matches!(path, "/console" | "/console/")
+    }
+
+    /// The response to a GET of a console path
+    pub async fn handle(&self, path: &str) -> Response<Body> {
+        match path {
+            "/console" | "/console/" => Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, "text/html; charset=utf-8")
+                .body(Body::from(CONSOLE_PAGE))
+                .expect("valid response"),
+            "/console/sources" => json_response(StatusCode::OK, json!(self.handler.source_status().await)),
+            "/console/tables" => match self.handler.list_tables().await {
+                Ok(tables) => json_response(StatusCode::OK, tables.iter().map(|table| json!({
+                    "source": table.owner,
+                    "name": table.name,
+                    "columns": table.schema.columns.iter().map(|column| {
+                        let details = table.schema.column_details(&column.name).cloned().unwrap_or_default();
+                        json!({
+                            "name": column.name,
+                            "type": format!("{:?}", column.data_type),
+                            "nullable": column.nullable,
+                            "native_type": details.native_type,
+                            "description": details.description,
+                            "tags": details.tags,
+                        })
+                    }).collect::<Vec<_>>(),
+                })).collect()),
+                Err(e) => json_response(StatusCode::BAD_GATEWAY, json!({ "error": e.to_string() })),
+            },
+            "/console/history" => json_response(StatusCode::OK, json!(self.history.recent())),
+            _ => json_response(StatusCode::NOT_FOUND, json!({ "error": "Not found" })),
+        }
+    }
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::error::{NirvError, QueryParsingError};
+
+    #[test]
+    fn test_query_history_log() {
+        let log = QueryHistoryLog::new(2);
+        log.record("SELECT 1", &Ok(QueryResult::new()));
+        log.record("SELEC 2", &Err(NirvError::QueryParsing(QueryParsingError::InvalidSyntax("bad".to_string()))));
+        log.record("SELECT 3", &Ok(QueryResult::new()));
+
+        let recent = log.recent();
+        assert_eq!(recent.iter().map(|query| query.sql.as_str()).collect::<Vec<_>>(), vec!["SELECT 3", "SELEC 2"]);
+        assert_eq!(recent[0].rows, Some(0));
+        assert!(recent[1].error.as_deref().is_some_and(|error| error.contains("bad")));
+        assert_eq!(recent[1].duration_ms, None);
+    }
+}
@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::utils::error::{NirvResult, QueryParsingError};
+
+/// How text values are compared by ORDER BY, equality predicates, deduplication keys and
+/// join keys, chosen with `COLLATE name` in a query or a source's `collation` setting.
+/// Collations are ordered from strictest to loosest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Collation {
+    /// Text compares by its bytes
+    #[default]
+    Binary,
+    /// `Name`, `NAME` and `name` are equal
+    CaseInsensitive,
+    /// Letters compare by their base letter, ignoring case and accents, so `Émile`, `emile`
+    /// and `EMILE` are equal and sort with the `e`s. Locale tags such as `en-US` select it
+    /// too: every locale is given the Unicode root order.
+    Unicode,
+}
+
+impl Collation {
+    /// Parse `binary`, `nocase`, `unicode` or a locale tag such as `en-US` or `de_DE`
+    pub fn parse(name: &str) -> NirvResult<Self> {
+        let name = name.trim().trim_matches(['"', '\'']);
+        match name.to_lowercase().as_str() {
+            "binary" | "c" | "posix" | "ucs_basic" => return Ok(Collation::Binary),
+            "nocase" | "ci" | "case_insensitive" | "case-insensitive" => return Ok(Collation::CaseInsensitive),
+            "unicode" | "icu" | "und" | "root" => return Ok(Collation::Unicode),
+            _ => {}
+        }
+        if is_locale_tag(name) {
+            return Ok(Collation::Unicode);
+        }
+        Err(QueryParsingError::InvalidSyntax(format!(
+            "Unknown collation '{}': expected binary, nocase, unicode or a locale such as en-US", name
+        )).into())
+    }
+
+    /// Text two values compare equal by when their keys are equal
+    pub fn key<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            Collation::Binary => Cow::Borrowed(text),
+            Collation::CaseInsensitive => Cow::Owned(text.to_lowercase()),
+            Collation::Unicode => Cow::Owned(text.nfd().filter(|c| !is_combining_mark(*c)).collect::<String>().to_lowercase()),
+        }
+    }
+
+    /// Order two texts, equal exactly when their keys are
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            Collation::Binary => a.cmp(b),
+            collation => collation.key(a).cmp(&collation.key(b)),
+        }
+    }
+
+    /// The looser of two collations, which a join of sources using them compares keys by
+    pub fn looser(self, other: Self) -> Self {
+        self.max(other)
+    }
+}
+
+impl fmt::Display for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Collation::Binary => write!(f, "binary"),
+            Collation::CaseInsensitive => write!(f, "nocase"),
+            Collation::Unicode => write!(f, "unicode"),
+        }
+    }
+}
+
+/// Whether a name looks like `en-US` or `de_DE`: a language, then a region or script
+fn is_locale_tag(name: &str) -> bool {
+    let mut parts = name.split(['-', '_']);
+    let language = parts.next().unwrap_or_default();
+    let rest: Vec<&str> = parts.collect();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && !rest.is_empty()
+        && rest.iter().all(|part| (2..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_collations() {
+        assert_eq!(Collation::parse("BINARY").unwrap(), Collation::Binary);
+        assert_eq!(Collation::parse("nocase").unwrap(), Collation::CaseInsensitive);
+        assert_eq!(Collation::parse("\"en-US\"").unwrap(), Collation::Unicode);
+        assert_eq!(Collation::parse("de_DE").unwrap(), Collation::Unicode);
+        assert_eq!(Collation::parse("und").unwrap().to_string(), "unicode");
+        assert!(Collation::parse("binray").is_err());
+        assert!(Collation::parse("e-x").is_err());
+        assert_eq!(Collation::Binary.looser(Collation::CaseInsensitive), Collation::CaseInsensitive);
+        assert_eq!(Collation::Unicode.looser(Collation::Binary), Collation::Unicode);
+    }
+
+    #[test]
+    fn test_compare_and_keys() {
+        assert_eq!(Collation::Binary.compare("Zoe", "adam"), Ordering::Less);
+        assert_eq!(Collation::CaseInsensitive.compare("Zoe", "adam"), Ordering::Greater);
+        assert_eq!(Collation::CaseInsensitive.compare("ACME", "acme"), Ordering::Equal);
+        assert_ne!(Collation::CaseInsensitive.compare("Émile", "emile"), Ordering::Equal);
+
+        assert_eq!(Collation::Unicode.compare("Émile", "EMILE"), Ordering::Equal);
+        assert_eq!(Collation::Unicode.compare("éclair", "fig"), Ordering::Less);
+        assert_eq!(Collation::Binary.compare("éclair", "fig"), Ordering::Greater);
+        // Precomposed and decomposed accents are the same text
+        assert_eq!(Collation::Unicode.key("Cafe\u{301}"), Collation::Unicode.key("café"));
+        assert_eq!(Collation::Binary.key("Acme"), "Acme");
+    }
+}
@@ -1,6 +1,18 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::utils::types::ConnectorType;
+use crate::utils::types::{ColumnDetails, ConnectorType, QueryPriority, SourceUri};
+use crate::utils::identifier::{IdentifierCase, split_qualifier};
+use crate::utils::error::{NirvError, NirvResult};
+use crate::utils::connector_settings::{ConfigIssue, ConnectorSettings, ValidationReport};
+use crate::utils::time_zone::SessionTimeZone;
+use crate::utils::collation::Collation;
+use crate::connectors::parse_pool::DEFAULT_PARSE_THREADS;
+use crate::engine::scheduler::{DEFAULT_MAX_QUEUED_QUERIES, DEFAULT_QUEUE_TIMEOUT_SECS};
+use crate::engine::lineage::DEFAULT_LINEAGE_QUERIES;
+use crate::engine::query_lint::{LintMode, LintRule};
+
+/// Environment variable selecting the source profile when none is given explicitly
+pub const PROFILE_ENV_VAR: &str = "NIRV_PROFILE";
 
 /// Main engine configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -9,6 +21,257 @@ pub struct EngineConfig {
     pub connectors: HashMap<String, ConnectorConfig>,
     pub dispatcher: DispatcherConfig,
     pub security: SecurityConfig,
+    /// Query rewriters applied in order before planning
+    #[serde(default)]
+    pub query_rewriters: Vec<QueryRewriterConfig>,
+    /// Result transformers applied in order before results are returned
+    #[serde(default)]
+    pub result_transformers: Vec<ResultTransformerConfig>,
+    /// Optional HTTP query API served alongside the wire protocols
+    #[serde(default)]
+    pub http_api: Option<HttpApiConfig>,
+    /// Per-environment connection settings (e.g. `dev`, `stage`, `prod`) for the connectors above
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileConfig>,
+    /// Local history of queries run from the CLI
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Limits on rows and bytes fetched per user or tenant; usage is not tracked when unset
+    #[serde(default)]
+    pub quotas: Option<QuotaConfig>,
+    /// Periodic re-introspection of sources to detect schema drift; off when unset
+    #[serde(default)]
+    pub schema_drift: Option<SchemaDriftConfig>,
+    /// Recording of where each query's output columns came from; off when unset
+    #[serde(default)]
+    pub lineage: Option<LineageConfig>,
+    /// Threads the engine's blocking work runs on
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+    /// Query planner and executor implementations to use
+    #[serde(default)]
+    pub components: ComponentsConfig,
+    /// Sources answering for others, by the `type.identifier` of the source they stand in for
+    #[serde(default)]
+    pub fallbacks: HashMap<String, FallbackConfig>,
+    /// Warnings about queries likely to be slow or wrong
+    #[serde(default)]
+    pub lint: LintConfig,
+}
+
+/// A source queried instead of another when that one fails or is slow, such as a Parquet
+/// snapshot of a REST endpoint
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FallbackConfig {
+    /// The fallback as `type.identifier`
+    pub source: String,
+    /// Milliseconds the primary source may take before the fallback is queried instead;
+    /// without one only errors switch to the fallback
+    #[serde(default)]
+    pub soft_timeout_ms: Option<u64>,
+}
+
+/// Planner and executor implementations, by the names they were registered under with the
+/// engine builder; the built-in ones when unset or `default`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ComponentsConfig {
+    #[serde(default)]
+    pub planner: Option<String>,
+    #[serde(default)]
+    pub executor: Option<String>,
+}
+
+/// Threads set aside for blocking work, kept off the async runtime's worker threads
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RuntimeConfig {
+    /// Files read and parsed at once by file connectors; further scans wait for a thread
+    #[serde(default = "default_parse_threads")]
+    pub parse_threads: usize,
+}
+
+fn default_parse_threads() -> usize {
+    DEFAULT_PARSE_THREADS
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self { parse_threads: DEFAULT_PARSE_THREADS }
+    }
+}
+
+/// How often the schemas of registered sources are checked for drift
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SchemaDriftConfig {
+    pub interval_secs: u64,
+}
+
+/// Whether queries are checked for foot-guns, and which are tolerated
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LintConfig {
+    /// `warn` to report findings with the result, `strict` to refuse the query, or `off`
+    #[serde(default)]
+    pub mode: LintMode,
+    /// Rules not checked
+    #[serde(default)]
+    pub allow: Vec<LintRule>,
+}
+
+/// How much column lineage `system.lineage` keeps
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LineageConfig {
+    /// Most recent queries whose lineage is kept
+    #[serde(default = "default_lineage_queries")]
+    pub max_queries: usize,
+}
+
+fn default_lineage_queries() -> usize {
+    DEFAULT_LINEAGE_QUERIES
+}
+
+impl Default for LineageConfig {
+    fn default() -> Self {
+        Self { max_queries: DEFAULT_LINEAGE_QUERIES }
+    }
+}
+
+/// Rows and bytes a user or tenant may fetch from sources over rolling windows
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct UsageLimits {
+    pub rows_per_hour: Option<u64>,
+    pub bytes_per_hour: Option<u64>,
+    pub rows_per_day: Option<u64>,
+    pub bytes_per_day: Option<u64>,
+}
+
+/// Usage limits for everyone, with overrides for named users or tenants
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct QuotaConfig {
+    #[serde(default)]
+    pub default: UsageLimits,
+    #[serde(default)]
+    pub principals: HashMap<String, UsageLimits>,
+}
+
+/// Where executed queries are recorded and how long they are kept
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryConfig {
+    #[serde(default = "default_history_enabled")]
+    pub enabled: bool,
+    /// History file; defaults to `NIRV_HISTORY`, then `~/.nirv/history.jsonl`
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Most recent entries kept
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+    /// Entries older than this are removed
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+}
+
+fn default_history_enabled() -> bool {
+    true
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+            max_entries: Some(1000),
+            max_age_days: Some(90),
+        }
+    }
+}
+
+/// Connection settings that replace those of named connectors when the profile is selected
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProfileConfig {
+    #[serde(default)]
+    pub connectors: HashMap<String, ConnectorProfile>,
+}
+
+/// Overrides for one connector within a profile
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ConnectorProfile {
+    pub connection_string: Option<String>,
+    /// Merged into the connector's parameters, replacing values of the same key
+    #[serde(default)]
+    pub parameters: HashMap<String, String>,
+}
+
+/// HTTP query API configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpApiConfig {
+    pub bind_address: String,
+    pub port: u16,
+    /// Largest accepted request body in bytes
+    #[serde(default = "default_http_max_body_bytes")]
+    pub max_body_bytes: usize,
+    #[serde(default)]
+    pub auth: HttpAuthConfig,
+}
+
+fn default_http_max_body_bytes() -> usize {
+    1024 * 1024
+}
+
+/// HTTP API credentials; requests are unauthenticated when none are configured
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HttpAuthConfig {
+    /// Accepted bearer tokens
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// Accepted basic auth username to password pairs
+    #[serde(default)]
+    pub basic_users: HashMap<String, String>,
+    /// Accepted bearer tokens of users, by user name; queries sent with one run for its user
+    #[serde(default)]
+    pub token_users: HashMap<String, String>,
+}
+
+impl HttpAuthConfig {
+    /// Check if any credentials are configured
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty() || !self.basic_users.is_empty() || !self.token_users.is_empty()
+    }
+}
+
+impl Default for HttpApiConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: "127.0.0.1".to_string(),
+            port: 8080,
+            max_body_bytes: default_http_max_body_bytes(),
+            auth: HttpAuthConfig::default(),
+        }
+    }
+}
+
+/// Built-in query rewriter configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueryRewriterConfig {
+    /// Map `object_type.identifier` sources to other sources
+    TableRemap { mappings: HashMap<String, String> },
+    /// Reject queries on `object_type.identifier` or `object_type.*` sources
+    Denylist { sources: Vec<String> },
+    /// Add `column = value` to queries, optionally only for some object types
+    InjectPredicate {
+        column: String,
+        value: serde_json::Value,
+        #[serde(default)]
+        object_types: Vec<String>,
+    },
+}
+
+/// Built-in result transformer configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResultTransformerConfig {
+    /// Rename result columns from old to new names
+    RenameColumns { mappings: HashMap<String, String> },
+    /// Multiply a numeric column by a constant factor
+    ScaleColumn { column: String, factor: f64 },
 }
 
 /// Protocol adapter configuration
@@ -28,6 +291,7 @@ pub enum ProtocolType {
     PostgreSQL,
     MySQL,
     SQLite,
+    SqlServer,
 }
 
 /// TLS configuration for protocols
@@ -47,6 +311,43 @@ pub struct ConnectorConfig {
     pub parameters: HashMap<String, String>,
     pub pool_config: Option<PoolConfig>,
     pub timeout_config: Option<TimeoutConfig>,
+    /// What queries may do with this source, checked before they are routed to it
+    #[serde(default)]
+    pub policy: SourcePolicy,
+    /// Time zone of the source's DateTime values that carry no offset, such as `+02:00`;
+    /// they are converted to UTC as they are read. UTC when unset.
+    #[serde(default)]
+    pub time_zone: Option<String>,
+    /// Collation the source's text is compared by when a query names none, such as `nocase`
+    /// or `en-US`. Binary when unset.
+    #[serde(default)]
+    pub collation: Option<String>,
+    /// Rows the PostgreSQL and SQL Server connectors read per round trip, through a cursor,
+    /// handing each fetch on as it arrives. Whole results are read at once when unset.
+    #[serde(default)]
+    pub fetch_size: Option<u32>,
+    /// Descriptions, tags and source types of columns, by `object.column`, laid over what
+    /// the source reports about them
+    #[serde(default)]
+    pub columns: HashMap<String, ColumnDetails>,
+}
+
+/// Limits on the queries routed to one source
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SourcePolicy {
+    /// Reject INSERT, UPDATE and DELETE, and writing query results into the source
+    #[serde(default)]
+    pub read_only: bool,
+    /// Glob patterns of the tables that may be queried, such as `public.*`; all tables
+    /// when empty
+    #[serde(default)]
+    pub allowed_tables: Vec<String>,
+    /// Glob patterns of tables that may never be queried, even when also allowed
+    #[serde(default)]
+    pub denied_tables: Vec<String>,
+    /// Most rows a query may return; queries without a smaller LIMIT are capped to it
+    #[serde(default)]
+    pub max_limit: Option<u64>,
 }
 
 /// Connection pool configuration
@@ -57,6 +358,20 @@ pub struct PoolConfig {
     pub connection_timeout: u64,    // seconds
     pub idle_timeout: u64,          // seconds
     pub max_lifetime: Option<u64>,  // seconds
+    /// Check pooled connections with `SELECT 1` before reusing them
+    #[serde(default = "default_validate_connections")]
+    pub validate_connections: bool,
+    /// Seconds the check may take before the connection is discarded
+    #[serde(default = "default_validation_timeout")]
+    pub validation_timeout: u64,
+}
+
+fn default_validate_connections() -> bool {
+    true
+}
+
+fn default_validation_timeout() -> u64 {
+    5
 }
 
 /// Timeout configuration for connectors
@@ -74,6 +389,33 @@ pub struct DispatcherConfig {
     pub query_cache_size: Option<u64>,  // MB
     pub enable_cross_connector_joins: bool,
     pub default_timeout: u64,           // seconds
+    /// Matching of unquoted column identifiers against source columns
+    #[serde(default)]
+    pub identifier_case: IdentifierCase,
+    /// Answer a join from the sources that succeeded, with a warning for each that failed
+    #[serde(default)]
+    pub partial_results: bool,
+    /// Most batch queries running at once, keeping the other slots for interactive queries;
+    /// half of `max_concurrent_queries` when unset
+    #[serde(default)]
+    pub max_batch_queries: Option<u32>,
+    /// Scheduling class of each user's queries; other users' queries are interactive
+    #[serde(default)]
+    pub user_priorities: HashMap<String, QueryPriority>,
+    /// Queries that may wait for a connector running its `max_concurrent_queries`; more fail
+    #[serde(default = "default_max_queued_queries")]
+    pub max_queued_queries: usize,
+    /// Seconds a query may wait for a free slot of a connector before it fails
+    #[serde(default = "default_queue_timeout")]
+    pub queue_timeout: u64,
+}
+
+fn default_max_queued_queries() -> usize {
+    DEFAULT_MAX_QUEUED_QUERIES
+}
+
+fn default_queue_timeout() -> u64 {
+    DEFAULT_QUEUE_TIMEOUT_SECS
 }
 
 /// Security configuration
@@ -119,6 +461,10 @@ pub struct AuthorizationConfig {
     pub enabled: bool,
     pub default_permissions: Vec<Permission>,
     pub role_mappings: HashMap<String, Vec<Permission>>,
+    /// File the roles and grants administered with GRANT and REVOKE are kept in; without
+    /// one they last until the engine stops
+    #[serde(default)]
+    pub catalog_path: Option<String>,
 }
 
 /// Permission types
@@ -140,6 +486,168 @@ pub struct AuditConfig {
     pub log_errors: bool,
 }
 
+impl EngineConfig {
+    /// Load a JSON configuration file and apply the selected profile, if any
+    pub fn load(path: &str, profile: Option<&str>) -> NirvResult<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| NirvError::Configuration(format!("Failed to read '{}': {}", path, e)))?;
+        let config: Self = serde_json::from_str(&contents)
+            .map_err(|e| NirvError::Configuration(format!("Invalid configuration in '{}': {}", path, e)))?;
+        match profile {
+            Some(profile) => config.with_profile(profile),
+            None => Ok(config),
+        }
+    }
+
+    /// Apply a profile's connection settings to the connectors it names. Unknown profiles and
+    /// connectors are errors, so a mistyped profile never falls back to other settings.
+    pub fn with_profile(mut self, name: &str) -> NirvResult<Self> {
+        let profile = self.profiles.get(name).cloned().ok_or_else(|| {
+            let mut known: Vec<&String> = self.profiles.keys().collect();
+            known.sort();
+            NirvError::Configuration(format!("Unknown profile '{}'. Available profiles: {:?}", name, known))
+        })?;
+
+        for (connector_name, overrides) in profile.connectors {
+            let connector = self.connectors.get_mut(&connector_name).ok_or_else(|| NirvError::Configuration(
+                format!("Profile '{}' configures unknown connector '{}'", name, connector_name)
+            ))?;
+            if overrides.connection_string.is_some() {
+                connector.connection_string = overrides.connection_string;
+            }
+            connector.parameters.extend(overrides.parameters);
+        }
+        Ok(self)
+    }
+
+    /// Check the whole configuration, including each connector's typed parameters,
+    /// and report every problem found
+    pub fn validate(&self) -> ValidationReport {
+        let mut issues = Vec::new();
+
+        let mut ports = HashMap::new();
+        for (index, adapter) in self.protocol_adapters.iter().enumerate() {
+            let location = format!("protocol_adapters[{}]", index);
+            if adapter.port == 0 {
+                issues.push(ConfigIssue::new(format!("{}.port", location), "port must be between 1 and 65535"));
+            } else if let Some(other) = ports.insert((adapter.bind_address.as_str(), adapter.port), index) {
+                issues.push(ConfigIssue::new(format!("{}.port", location), format!(
+                    "{}:{} is also used by protocol_adapters[{}]", adapter.bind_address, adapter.port, other
+                )));
+            }
+            if adapter.max_connections == Some(0) {
+                issues.push(ConfigIssue::new(format!("{}.max_connections", location), "must be greater than zero"));
+            }
+        }
+        if let Some(http_api) = &self.http_api {
+            if http_api.port == 0 {
+                issues.push(ConfigIssue::new("http_api.port", "port must be between 1 and 65535"));
+            }
+        }
+        if self.dispatcher.max_concurrent_queries == 0 {
+            issues.push(ConfigIssue::new("dispatcher.max_concurrent_queries", "must be greater than zero"));
+        }
+        if self.dispatcher.max_batch_queries == Some(0) {
+            issues.push(ConfigIssue::new("dispatcher.max_batch_queries", "must be greater than zero"));
+        }
+        if self.schema_drift.as_ref().is_some_and(|drift| drift.interval_secs == 0) {
+            issues.push(ConfigIssue::new("schema_drift.interval_secs", "must be greater than zero"));
+        }
+        if self.lineage.as_ref().is_some_and(|lineage| lineage.max_queries == 0) {
+            issues.push(ConfigIssue::new("lineage.max_queries", "must be greater than zero"));
+        }
+        if self.runtime.parse_threads == 0 {
+            issues.push(ConfigIssue::new("runtime.parse_threads", "must be greater than zero"));
+        }
+
+        let mut names: Vec<&String> = self.connectors.keys().collect();
+        names.sort();
+        for name in names {
+            let connector = &self.connectors[name];
+            if let Err(connector_issues) = ConnectorSettings::from_config(name, connector) {
+                issues.extend(connector_issues);
+            }
+            if let Some(pool) = &connector.pool_config {
+                if pool.max_connections == 0 {
+                    issues.push(ConfigIssue::new(format!("connectors.{}.pool_config.max_connections", name), "must be greater than zero"));
+                } else if pool.min_connections > pool.max_connections {
+                    issues.push(ConfigIssue::new(format!("connectors.{}.pool_config.min_connections", name), format!(
+                        "{} is more than max_connections ({})", pool.min_connections, pool.max_connections
+                    )));
+                }
+                if pool.validate_connections && pool.validation_timeout == 0 {
+                    issues.push(ConfigIssue::new(format!("connectors.{}.pool_config.validation_timeout", name), "must be greater than zero"));
+                }
+            }
+            if let Some(timeouts) = &connector.timeout_config {
+                for (key, value) in [("connect_timeout", timeouts.connect_timeout), ("query_timeout", timeouts.query_timeout)] {
+                    if value == 0 {
+                        issues.push(ConfigIssue::new(format!("connectors.{}.timeout_config.{}", name, key), "must be greater than zero"));
+                    }
+                }
+            }
+            let policy = &connector.policy;
+            for (key, patterns) in [("allowed_tables", &policy.allowed_tables), ("denied_tables", &policy.denied_tables)] {
+                for (index, pattern) in patterns.iter().enumerate() {
+                    if let Err(e) = glob::Pattern::new(pattern) {
+                        issues.push(ConfigIssue::new(format!("connectors.{}.policy.{}[{}]", name, key, index), format!("invalid pattern '{}': {}", pattern, e)));
+                    }
+                }
+            }
+            if policy.max_limit == Some(0) {
+                issues.push(ConfigIssue::new(format!("connectors.{}.policy.max_limit", name), "must be greater than zero"));
+            }
+            if let Some(Err(e)) = connector.time_zone.as_deref().map(SessionTimeZone::parse) {
+                issues.push(ConfigIssue::new(format!("connectors.{}.time_zone", name), e.to_string()));
+            }
+            if let Some(Err(e)) = connector.collation.as_deref().map(Collation::parse) {
+                issues.push(ConfigIssue::new(format!("connectors.{}.collation", name), e.to_string()));
+            }
+            if connector.fetch_size == Some(0) {
+                issues.push(ConfigIssue::new(format!("connectors.{}.fetch_size", name), "must be greater than zero"));
+            }
+            let mut columns: Vec<&String> = connector.columns.keys().collect();
+            columns.sort();
+            for column in columns.into_iter().filter(|column| split_qualifier(column).is_none()) {
+                issues.push(ConfigIssue::new(format!("connectors.{}.columns.{}", name, column), "must name a column as object.column"));
+            }
+        }
+
+        let mut fallbacks: Vec<&String> = self.fallbacks.keys().collect();
+        fallbacks.sort();
+        for primary in fallbacks {
+            let fallback = &self.fallbacks[primary];
+            for (location, source) in [(format!("fallbacks.{}", primary), primary), (format!("fallbacks.{}.source", primary), &fallback.source)] {
+                if let Err(e) = SourceUri::parse(source) {
+                    issues.push(ConfigIssue::new(location, e.to_string()));
+                }
+            }
+            if fallback.soft_timeout_ms == Some(0) {
+                issues.push(ConfigIssue::new(format!("fallbacks.{}.soft_timeout_ms", primary), "must be greater than zero"));
+            }
+        }
+
+        let mut profiles: Vec<&String> = self.profiles.keys().collect();
+        profiles.sort();
+        for profile in profiles {
+            for connector in self.profiles[profile].connectors.keys() {
+                if !self.connectors.contains_key(connector) {
+                    issues.push(ConfigIssue::new(format!("profiles.{}.connectors.{}", profile, connector), "names an unknown connector"));
+                }
+            }
+        }
+
+        ValidationReport { issues }
+    }
+}
+
+/// The profile given on the command line, or else the one named by `NIRV_PROFILE`
+pub fn select_profile(explicit: Option<&str>) -> Option<String> {
+    explicit.map(str::to_string)
+        .or_else(|| std::env::var(PROFILE_ENV_VAR).ok())
+        .filter(|profile| !profile.is_empty())
+}
+
 impl Default for EngineConfig {
     fn default() -> Self {
         Self {
@@ -156,6 +664,18 @@ impl Default for EngineConfig {
             connectors: HashMap::new(),
             dispatcher: DispatcherConfig::default(),
             security: SecurityConfig::default(),
+            query_rewriters: Vec::new(),
+            result_transformers: Vec::new(),
+            http_api: None,
+            profiles: HashMap::new(),
+            history: HistoryConfig::default(),
+            quotas: None,
+            schema_drift: None,
+            lineage: None,
+            runtime: RuntimeConfig::default(),
+            components: ComponentsConfig::default(),
+            fallbacks: HashMap::new(),
+            lint: LintConfig::default(),
         }
     }
 }
@@ -167,6 +687,12 @@ impl Default for DispatcherConfig {
             query_cache_size: Some(256), // 256 MB
             enable_cross_connector_joins: false,
             default_timeout: 300, // 5 minutes
+            identifier_case: IdentifierCase::default(),
+            partial_results: false,
+            max_batch_queries: None,
+            user_priorities: HashMap::new(),
+            max_queued_queries: DEFAULT_MAX_QUEUED_QUERIES,
+            queue_timeout: DEFAULT_QUEUE_TIMEOUT_SECS,
         }
     }
 }
@@ -184,6 +710,7 @@ impl Default for SecurityConfig {
                 enabled: false,
                 default_permissions: vec![Permission::Read],
                 role_mappings: HashMap::new(),
+                catalog_path: None,
             },
             audit_logging: AuditConfig {
                 enabled: true,
@@ -204,6 +731,8 @@ impl Default for PoolConfig {
             connection_timeout: 30,
             idle_timeout: 600,
             max_lifetime: Some(3600),
+            validate_connections: default_validate_connections(),
+            validation_timeout: default_validation_timeout(),
         }
     }
 }
@@ -231,6 +760,7 @@ mod tests {
         assert_eq!(config.protocol_adapters[0].bind_address, "127.0.0.1");
         assert_eq!(config.protocol_adapters[0].port, 5432);
         assert!(config.connectors.is_empty());
+        assert_eq!(config.runtime.parse_threads, DEFAULT_PARSE_THREADS);
     }
 
     #[test]
@@ -262,6 +792,11 @@ mod tests {
             parameters,
             pool_config: Some(PoolConfig::default()),
             timeout_config: Some(TimeoutConfig::default()),
+            policy: SourcePolicy::default(),
+            time_zone: None,
+            collation: None,
+            fetch_size: None,
+            columns: HashMap::new(),
         };
         
         assert_eq!(config.connector_type, ConnectorType::PostgreSQL);
@@ -302,6 +837,8 @@ mod tests {
         assert_eq!(config.connection_timeout, 30);
         assert_eq!(config.idle_timeout, 600);
         assert_eq!(config.max_lifetime, Some(3600));
+        assert!(config.validate_connections);
+        assert_eq!(config.validation_timeout, 5);
     }
 
     #[test]
@@ -315,7 +852,7 @@ mod tests {
 
     #[test]
     fn test_auth_method_variants() {
-        let methods = vec![
+        let methods = [
             AuthMethod::None,
             AuthMethod::Password,
             AuthMethod::Certificate,
@@ -330,7 +867,7 @@ mod tests {
 
     #[test]
     fn test_permission_variants() {
-        let permissions = vec![
+        let permissions = [
             Permission::Read,
             Permission::Write,
             Permission::Admin,
@@ -341,4 +878,82 @@ mod tests {
         assert_eq!(permissions[0], Permission::Read);
         assert_eq!(permissions[1], Permission::Write);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_profile_overrides_connector_settings() {
+        let config: EngineConfig = serde_json::from_value(serde_json::json!({
+            "protocol_adapters": [],
+            "connectors": {
+                "analytics": {
+                    "connector_type": "PostgreSQL",
+                    "connection_string": "postgres://localhost/analytics_dev",
+                    "parameters": {"schema": "public", "pool": "2"},
+                    "pool_config": null,
+                    "timeout_config": null
+                }
+            },
+            "dispatcher": {"max_concurrent_queries": 10, "query_cache_size": null, "enable_cross_connector_joins": false, "default_timeout": 30},
+            "security": serde_json::to_value(SecurityConfig::default()).unwrap(),
+            "profiles": {
+                "prod": {"connectors": {"analytics": {
+                    "connection_string": "postgres://db.internal/analytics",
+                    "parameters": {"pool": "20"}
+                }}},
+                "broken": {"connectors": {"missing": {}}}
+            }
+        })).unwrap();
+
+        let prod = config.clone().with_profile("prod").unwrap();
+        let analytics = &prod.connectors["analytics"];
+        assert_eq!(analytics.connection_string.as_deref(), Some("postgres://db.internal/analytics"));
+        assert_eq!(analytics.parameters["pool"], "20");
+        assert_eq!(analytics.parameters["schema"], "public");
+
+        assert!(config.clone().with_profile("staging").is_err());
+        assert!(config.with_profile("broken").is_err());
+        assert_eq!(select_profile(Some("dev")), Some("dev".to_string()));
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let mut config = EngineConfig::default();
+        config.dispatcher.max_concurrent_queries = 0;
+        config.runtime.parse_threads = 0;
+        config.connectors.insert("api".to_string(), ConnectorConfig {
+            connector_type: ConnectorType::Rest,
+            connection_string: None,
+            parameters: HashMap::from([("rate_limit_burst".to_string(), "0".to_string())]),
+            pool_config: Some(PoolConfig { min_connections: 20, ..PoolConfig::default() }),
+            timeout_config: None,
+            policy: SourcePolicy { denied_tables: vec!["secret[".to_string()], max_limit: Some(0), ..SourcePolicy::default() },
+            time_zone: Some("Mars/Olympus".to_string()),
+            collation: Some("klingon".to_string()),
+            fetch_size: Some(0),
+            columns: HashMap::from([("email".to_string(), ColumnDetails::default())]),
+        });
+        config.profiles.insert("prod".to_string(), ProfileConfig {
+            connectors: HashMap::from([("missing".to_string(), ConnectorProfile::default())]),
+        });
+        config.fallbacks.insert("api.users".to_string(), FallbackConfig { source: "file://".to_string(), soft_timeout_ms: Some(0) });
+
+        let report = config.validate();
+        let locations: Vec<&str> = report.issues.iter().map(|issue| issue.location.as_str()).collect();
+        assert_eq!(locations, vec![
+            "dispatcher.max_concurrent_queries",
+            "runtime.parse_threads",
+            "connectors.api.parameters.base_url",
+            "connectors.api.parameters.rate_limit_burst",
+            "connectors.api.pool_config.min_connections",
+            "connectors.api.policy.denied_tables[0]",
+            "connectors.api.policy.max_limit",
+            "connectors.api.time_zone",
+            "connectors.api.collation",
+            "connectors.api.fetch_size",
+            "connectors.api.columns.email",
+            "fallbacks.api.users.source",
+            "fallbacks.api.users.soft_timeout_ms",
+            "profiles.prod.connectors.missing",
+        ]);
+        assert!(EngineConfig::default().validate().is_valid());
+    }
+}
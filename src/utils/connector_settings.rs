@@ -0,0 +1,666 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::connectors::{CsvOptions, SchemaMerge, DEFAULT_CACHE_MAX_MB};
+use crate::utils::config::ConnectorConfig;
+use crate::utils::error::{ConnectorError, NirvError, NirvResult};
+use crate::utils::types::ConnectorType;
+
+/// One problem found in a configuration, with where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Dotted location, as in `connectors.analytics.parameters.port`
+    pub location: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    pub fn new(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { location: location.into(), message: message.into() }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.location.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.location, self.message)
+        }
+    }
+}
+
+/// Every problem found in a configuration, reported together rather than one at a time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// A configuration error listing all issues, if there are any
+    pub fn into_result(self) -> NirvResult<()> {
+        if self.is_valid() {
+            Ok(())
+        } else {
+            Err(NirvError::Configuration(self.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "Configuration is valid");
+        }
+        let noun = if self.issues.len() == 1 { "problem" } else { "problems" };
+        write!(f, "{} {} found:", self.issues.len(), noun)?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads typed values out of a connector's string parameters, collecting every
+/// problem instead of stopping at the first
+struct ParamReader<'a> {
+    params: &'a HashMap<String, String>,
+    prefix: String,
+    issues: Vec<ConfigIssue>,
+}
+
+impl<'a> ParamReader<'a> {
+    fn new(params: &'a HashMap<String, String>, prefix: &str) -> Self {
+        Self { params, prefix: prefix.to_string(), issues: Vec::new() }
+    }
+
+    fn location(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.prefix, key)
+        }
+    }
+
+    fn issue(&mut self, key: &str, message: impl Into<String>) {
+        let location = self.location(key);
+        self.issues.push(ConfigIssue::new(location, message));
+    }
+
+    fn required(&mut self, key: &str) -> String {
+        match self.params.get(key).filter(|value| !value.is_empty()) {
+            Some(value) => value.clone(),
+            None => {
+                self.issue(key, "required parameter is missing");
+                String::new()
+            }
+        }
+    }
+
+    fn text(&self, key: &str, default: &str) -> String {
+        self.params.get(key).cloned().unwrap_or_else(|| default.to_string())
+    }
+
+    fn optional<T: FromStr>(&mut self, key: &str) -> Option<T>
+    where
+        T::Err: fmt::Display,
+    {
+        let value = self.params.get(key)?;
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                self.issue(key, format!("invalid value '{}': {}", value, e));
+                None
+            }
+        }
+    }
+
+    fn parse<T: FromStr>(&mut self, key: &str, default: T) -> T
+    where
+        T::Err: fmt::Display,
+    {
+        self.optional(key).unwrap_or(default)
+    }
+
+    /// A number that must be greater than zero
+    fn positive<T: FromStr + PartialOrd + Default + Copy>(&mut self, key: &str, default: T) -> T
+    where
+        T::Err: fmt::Display,
+    {
+        let value = self.parse(key, default);
+        if value <= T::default() {
+            self.issue(key, "must be greater than zero");
+        }
+        value
+    }
+
+    fn check(&mut self, key: &str, result: NirvResult<()>) {
+        if let Err(e) = result {
+            self.issue(key, reason(e));
+        }
+    }
+
+    fn finish<T>(self, settings: T) -> Result<T, Vec<ConfigIssue>> {
+        if self.issues.is_empty() {
+            Ok(settings)
+        } else {
+            Err(self.issues)
+        }
+    }
+}
+
+/// The message of a connector error, without the error kind prefixes
+fn reason(error: NirvError) -> String {
+    match error {
+        NirvError::Connector(
+            ConnectorError::ConnectionFailed(message)
+            | ConnectorError::QueryExecutionFailed(message)
+            | ConnectorError::UnsupportedOperation(message)
+        ) => message,
+        NirvError::Configuration(message) => message,
+        other => other.to_string(),
+    }
+}
+
+/// Join issues into one connection error, for connectors given invalid parameters
+pub fn issues_error(issues: Vec<ConfigIssue>) -> NirvError {
+    let message = issues.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+    ConnectorError::ConnectionFailed(format!("Invalid connector parameters: {}", message)).into()
+}
+
+/// PostgreSQL connector parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostgresSettings {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub dbname: String,
+}
+
+impl Default for PostgresSettings {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 5432,
+            user: "postgres".to_string(),
+            password: String::new(),
+            dbname: "postgres".to_string(),
+        }
+    }
+}
+
+impl PostgresSettings {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, Vec<ConfigIssue>> {
+        Self::read(params, "")
+    }
+
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let defaults = Self::default();
+        let mut reader = ParamReader::new(params, prefix);
+        let settings = Self {
+            host: reader.text("host", &defaults.host),
+            port: reader.positive("port", defaults.port),
+            user: reader.text("user", &defaults.user),
+            password: reader.text("password", &defaults.password),
+            dbname: reader.text("dbname", &defaults.dbname),
+        };
+        reader.finish(settings)
+    }
+}
+
+/// SQL Server connector parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SqlServerSettings {
+    pub server: String,
+    /// Defaults to 1433
+    pub port: u16,
+    pub database: String,
+    pub username: String,
+    pub password: String,
+    /// Accept the server certificate without validating it; defaults to false
+    pub trust_cert: bool,
+}
+
+impl SqlServerSettings {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, Vec<ConfigIssue>> {
+        Self::read(params, "")
+    }
+
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let mut reader = ParamReader::new(params, prefix);
+        let settings = Self {
+            server: reader.required("server"),
+            port: reader.positive("port", 1433),
+            database: reader.required("database"),
+            username: reader.required("username"),
+            password: reader.required("password"),
+            trust_cert: reader.parse("trust_cert", false),
+        };
+        reader.finish(settings)
+    }
+}
+
+/// File connector parameters. CSV and flattening parameters are checked but stay in the
+/// parameter map, where the connector reads them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSettings {
+    /// Existing directory that file identifiers are relative to
+    pub base_path: String,
+    /// Extensions served; empty means the connector's defaults
+    pub file_extensions: Vec<String>,
+    pub snapshot_dir: Option<String>,
+    pub index_dir: Option<String>,
+    /// `strict` (the default) or `union`
+    pub schema_merge: String,
+    /// How often files are checked for changes; files are not watched when unset
+    pub watch_interval_ms: Option<u64>,
+    /// Directory parsed files are cached in across restarts, relative to the base path
+    pub cache_dir: Option<String>,
+    /// Size budget of the cache directory; defaults to 256
+    pub cache_max_mb: u64,
+}
+
+impl FileSettings {
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let mut reader = ParamReader::new(params, prefix);
+        let base_path = reader.required("base_path");
+        if !base_path.is_empty() && !Path::new(&base_path).is_dir() {
+            reader.issue("base_path", format!("'{}' is not an existing directory", base_path));
+        }
+        let schema_merge = reader.text("schema_merge", "strict");
+        reader.check("schema_merge", SchemaMerge::parse(&schema_merge).map(|_| ()));
+        for key in CsvOptions::KEYS {
+            if let Some(value) = params.get(key) {
+                let single = HashMap::from([(key.to_string(), value.clone())]);
+                reader.check(key, CsvOptions::default().with_params(&single).map(|_| ()));
+            }
+        }
+        reader.optional::<usize>("flatten_depth");
+        let watch_interval_ms = reader.optional::<u64>("watch_interval_ms");
+        if watch_interval_ms == Some(0) {
+            reader.issue("watch_interval_ms", "must be greater than zero");
+        }
+
+        let settings = Self {
+            base_path,
+            file_extensions: params.get("file_extensions")
+                .map(|extensions| extensions.split(',').map(|ext| ext.trim().to_lowercase()).collect())
+                .unwrap_or_default(),
+            snapshot_dir: params.get("snapshot_dir").cloned(),
+            index_dir: params.get("index_dir").cloned(),
+            schema_merge,
+            watch_interval_ms,
+            cache_dir: params.get("cache_dir").cloned(),
+            cache_max_mb: reader.positive("cache_max_mb", DEFAULT_CACHE_MAX_MB),
+        };
+        reader.finish(settings)
+    }
+}
+
+/// How the REST connector authenticates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "auth_type", rename_all = "snake_case")]
+pub enum RestAuth {
+    None,
+    ApiKey { header: String, key: String },
+    Bearer { token: String },
+    Basic { username: String, password: String },
+}
+
+/// REST connector parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestSettings {
+    pub base_url: String,
+    pub auth: RestAuth,
+    pub cache_ttl_seconds: Option<u64>,
+    /// How long empty and failed responses are cached
+    pub negative_cache_ttl_seconds: Option<u64>,
+    /// How long past its TTL a response is served while it is refreshed
+    pub stale_while_revalidate_seconds: Option<u64>,
+    /// Requests per second; requests are not limited when unset
+    pub rate_limit_rps: Option<f64>,
+    /// Defaults to 10
+    pub rate_limit_burst: u32,
+    /// Response header holding an endpoint's total row count, such as `X-Total-Count`
+    pub count_header: Option<String>,
+    /// Pages and shards of one query requested at once; defaults to 4
+    pub fetch_concurrency: usize,
+    /// Directory responses are cached in across restarts
+    pub cache_dir: Option<String>,
+    /// Size budget of the cache directory; defaults to 256
+    pub cache_max_mb: u64,
+}
+
+impl RestSettings {
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let mut reader = ParamReader::new(params, prefix);
+        let base_url = reader.required("base_url");
+        if !base_url.is_empty() {
+            if let Err(e) = url::Url::parse(&base_url) {
+                reader.issue("base_url", format!("invalid URL '{}': {}", base_url, e));
+            }
+        }
+        let auth = match reader.text("auth_type", "none").as_str() {
+            "none" => RestAuth::None,
+            "api_key" => RestAuth::ApiKey {
+                header: reader.text("auth_header", "X-API-Key"),
+                key: reader.required("api_key"),
+            },
+            "bearer" => RestAuth::Bearer { token: reader.required("bearer_token") },
+            "basic" => RestAuth::Basic {
+                username: reader.required("username"),
+                password: reader.required("password"),
+            },
+            other => {
+                reader.issue("auth_type", format!("unknown auth type '{}'; expected none, api_key, bearer or basic", other));
+                RestAuth::None
+            }
+        };
+        let rate_limit_rps = reader.optional::<f64>("rate_limit_rps");
+        if rate_limit_rps.is_some_and(|rps| rps <= 0.0) {
+            reader.issue("rate_limit_rps", "must be greater than zero");
+        }
+
+        let settings = Self {
+            base_url,
+            auth,
+            cache_ttl_seconds: reader.optional("cache_ttl_seconds"),
+            negative_cache_ttl_seconds: reader.optional("negative_cache_ttl_seconds"),
+            stale_while_revalidate_seconds: reader.optional("stale_while_revalidate_seconds"),
+            rate_limit_rps,
+            rate_limit_burst: reader.positive("rate_limit_burst", 10),
+            count_header: reader.optional("count_header"),
+            fetch_concurrency: reader.positive("fetch_concurrency", 4),
+            cache_dir: reader.optional("cache_dir"),
+            cache_max_mb: reader.positive("cache_max_mb", DEFAULT_CACHE_MAX_MB),
+        };
+        reader.finish(settings)
+    }
+}
+
+/// BigQuery connector parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BigQuerySettings {
+    /// Project queries run in and are billed to
+    pub project_id: String,
+    /// Dataset of table names written without one
+    pub dataset: Option<String>,
+    /// Location jobs run in, such as `EU`; BigQuery's default when unset
+    pub location: Option<String>,
+    /// Service account JSON key file
+    pub credentials_path: Option<String>,
+    /// Service account JSON key, instead of a file
+    pub credentials_json: Option<String>,
+    /// Defaults to https://bigquery.googleapis.com
+    pub api_url: String,
+}
+
+impl BigQuerySettings {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, Vec<ConfigIssue>> {
+        Self::read(params, "")
+    }
+
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let mut reader = ParamReader::new(params, prefix);
+        let settings = Self {
+            project_id: reader.required("project_id"),
+            dataset: reader.optional("dataset"),
+            location: reader.optional("location"),
+            credentials_path: reader.optional("credentials_path"),
+            credentials_json: reader.optional("credentials_json"),
+            api_url: reader.text("api_url", "https://bigquery.googleapis.com"),
+        };
+        if settings.credentials_path.is_none() && settings.credentials_json.is_none() {
+            reader.issue("credentials_path", "required parameter is missing; set it or credentials_json");
+        }
+        check_url(&mut reader, "api_url", &settings.api_url);
+        reader.finish(settings)
+    }
+}
+
+/// Snowflake connector parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnowflakeSettings {
+    /// Account identifier, as in `myorg-myaccount`
+    pub account: String,
+    pub user: String,
+    /// PEM file of the RSA private key registered for the user
+    pub private_key_path: Option<String>,
+    /// PEM private key, instead of a file
+    pub private_key: Option<String>,
+    /// Passphrase of an encrypted private key
+    pub private_key_passphrase: Option<String>,
+    pub warehouse: Option<String>,
+    pub database: Option<String>,
+    pub schema: Option<String>,
+    pub role: Option<String>,
+    /// Defaults to https://<account>.snowflakecomputing.com
+    pub api_url: String,
+}
+
+impl SnowflakeSettings {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, Vec<ConfigIssue>> {
+        Self::read(params, "")
+    }
+
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let mut reader = ParamReader::new(params, prefix);
+        let account = reader.required("account");
+        let default_url = format!("https://{}.snowflakecomputing.com", account);
+        let settings = Self {
+            user: reader.required("user"),
+            private_key_path: reader.optional("private_key_path"),
+            private_key: reader.optional("private_key"),
+            private_key_passphrase: reader.optional("private_key_passphrase"),
+            warehouse: reader.optional("warehouse"),
+            database: reader.optional("database"),
+            schema: reader.optional("schema"),
+            role: reader.optional("role"),
+            api_url: reader.text("api_url", &default_url),
+            account,
+        };
+        if settings.private_key_path.is_none() && settings.private_key.is_none() {
+            reader.issue("private_key_path", "required parameter is missing; set it or private_key");
+        }
+        check_url(&mut reader, "api_url", &settings.api_url);
+        reader.finish(settings)
+    }
+}
+
+fn check_url(reader: &mut ParamReader<'_>, key: &str, value: &str) {
+    if let Err(e) = url::Url::parse(value) {
+        reader.issue(key, format!("invalid URL '{}': {}", value, e));
+    }
+}
+
+/// IMAP mailbox connector parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImapSettings {
+    pub host: String,
+    /// Defaults to 993 with TLS and 143 without
+    pub port: u16,
+    /// Connect with implicit TLS; defaults to true
+    pub tls: bool,
+    /// Accept the server certificate without validating it; defaults to false
+    pub trust_cert: bool,
+    pub username: String,
+    pub password: String,
+    /// Most messages a query reads from a folder, newest first; defaults to 500
+    pub max_messages: usize,
+    /// Characters of body text kept in `snippet`; defaults to 200
+    pub snippet_length: usize,
+}
+
+impl ImapSettings {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, Vec<ConfigIssue>> {
+        Self::read(params, "")
+    }
+
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let mut reader = ParamReader::new(params, prefix);
+        let host = reader.required("host");
+        let tls = reader.parse("tls", true);
+        let settings = Self {
+            host,
+            port: reader.positive("port", if tls { 993 } else { 143 }),
+            tls,
+            trust_cert: reader.parse("trust_cert", false),
+            username: reader.required("username"),
+            password: reader.required("password"),
+            max_messages: reader.positive("max_messages", 500),
+            snippet_length: reader.parse("snippet_length", 200),
+        };
+        reader.finish(settings)
+    }
+}
+
+/// Change data capture connector parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CdcSettings {
+    pub postgres: PostgresSettings,
+    pub slot: String,
+    /// Changes kept for replay; defaults to 10000
+    pub retention: usize,
+    /// Changes fetched per poll; defaults to 1000
+    pub batch_size: u64,
+    /// Defaults to 1000
+    pub poll_interval_ms: u64,
+    pub create_slot: bool,
+    pub checkpoint_path: Option<String>,
+}
+
+impl CdcSettings {
+    fn read(params: &HashMap<String, String>, prefix: &str) -> Result<Self, Vec<ConfigIssue>> {
+        let postgres = PostgresSettings::read(params, prefix);
+        let mut reader = ParamReader::new(params, prefix);
+        let slot = reader.required("slot");
+        let plugin = reader.text("plugin", "test_decoding");
+        if plugin != "test_decoding" {
+            reader.issue("plugin", format!("unsupported logical decoding plugin '{}'; expected test_decoding", plugin));
+        }
+        let retention = reader.positive("retention", 10_000);
+        let batch_size = reader.positive("batch_size", 1_000);
+        let poll_interval_ms = reader.positive("poll_interval_ms", 1_000);
+        let create_slot = reader.parse("create_slot", false);
+
+        match postgres {
+            Ok(postgres) => reader.finish(Self {
+                postgres,
+                slot,
+                retention,
+                batch_size,
+                poll_interval_ms,
+                create_slot,
+                checkpoint_path: params.get("checkpoint_path").cloned(),
+            }),
+            Err(mut issues) => {
+                issues.extend(reader.issues);
+                Err(issues)
+            }
+        }
+    }
+}
+
+/// Typed parameters of a configured connector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "connector_type")]
+pub enum ConnectorSettings {
+    PostgreSQL(PostgresSettings),
+    SqlServer(SqlServerSettings),
+    File(FileSettings),
+    Rest(RestSettings),
+    Cdc(CdcSettings),
+    BigQuery(BigQuerySettings),
+    Snowflake(SnowflakeSettings),
+    Imap(ImapSettings),
+    /// Connectors whose parameters are not checked
+    Untyped,
+}
+
+impl ConnectorSettings {
+    /// Read and check the parameters of the named connector, reporting every problem
+    pub fn from_config(name: &str, config: &ConnectorConfig) -> Result<Self, Vec<ConfigIssue>> {
+        let prefix = format!("connectors.{}.parameters", name);
+        let params = &config.parameters;
+        match config.connector_type {
+            ConnectorType::PostgreSQL => PostgresSettings::read(params, &prefix).map(Self::PostgreSQL),
+            ConnectorType::SqlServer => SqlServerSettings::read(params, &prefix).map(Self::SqlServer),
+            ConnectorType::File => FileSettings::read(params, &prefix).map(Self::File),
+            ConnectorType::Rest => RestSettings::read(params, &prefix).map(Self::Rest),
+            ConnectorType::Cdc => CdcSettings::read(params, &prefix).map(Self::Cdc),
+            ConnectorType::BigQuery => BigQuerySettings::read(params, &prefix).map(Self::BigQuery),
+            ConnectorType::Snowflake => SnowflakeSettings::read(params, &prefix).map(Self::Snowflake),
+            ConnectorType::Imap => ImapSettings::read(params, &prefix).map(Self::Imap),
+            _ => Ok(Self::Untyped),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_settings_defaults_and_issues() {
+        let postgres = PostgresSettings::from_params(&params(&[("host", "db"), ("port", "6543")])).unwrap();
+        assert_eq!(postgres, PostgresSettings { host: "db".to_string(), port: 6543, ..Default::default() });
+
+        // Every problem is reported, each at its own location
+        let issues = SqlServerSettings::read(&params(&[("server", "sql"), ("port", "99999"), ("trust_cert", "maybe")]), "connectors.mssql.parameters")
+            .unwrap_err();
+        let locations: Vec<&str> = issues.iter().map(|issue| issue.location.as_str()).collect();
+        assert_eq!(locations, vec![
+            "connectors.mssql.parameters.port",
+            "connectors.mssql.parameters.database",
+            "connectors.mssql.parameters.username",
+            "connectors.mssql.parameters.password",
+            "connectors.mssql.parameters.trust_cert",
+        ]);
+
+        let issues = RestSettings::read(&params(&[("base_url", "not a url"), ("auth_type", "bearer"), ("rate_limit_rps", "0")]), "").unwrap_err();
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[1].to_string(), "bearer_token: required parameter is missing");
+
+        let issues = FileSettings::read(&params(&[("base_path", "/no/such/dir"), ("schema_merge", "loose"), ("delimiter", ";;")]), "").unwrap_err();
+        assert_eq!(issues.len(), 3);
+
+        let bigquery = BigQuerySettings::from_params(&params(&[("project_id", "analytics"), ("credentials_path", "/keys/sa.json")])).unwrap();
+        assert_eq!(bigquery.api_url, "https://bigquery.googleapis.com");
+        let issues = BigQuerySettings::read(&params(&[]), "").unwrap_err();
+        let keys: Vec<&str> = issues.iter().map(|issue| issue.location.as_str()).collect();
+        assert_eq!(keys, vec!["project_id", "credentials_path"]);
+
+        let snowflake = SnowflakeSettings::from_params(&params(&[("account", "acme-eu"), ("user", "loader"), ("private_key_path", "/keys/rsa.p8")])).unwrap();
+        assert_eq!(snowflake.api_url, "https://acme-eu.snowflakecomputing.com");
+        assert_eq!(SnowflakeSettings::read(&params(&[("account", "acme-eu"), ("user", "loader")]), "").unwrap_err().len(), 1);
+
+        let imap = ImapSettings::from_params(&params(&[("host", "mail"), ("tls", "false"), ("username", "support"), ("password", "secret")])).unwrap();
+        assert_eq!((imap.port, imap.max_messages, imap.snippet_length), (143, 500, 200));
+        assert_eq!(ImapSettings::read(&params(&[("host", "mail"), ("max_messages", "0")]), "").unwrap_err().len(), 3);
+    }
+
+    #[test]
+    fn test_validation_report_display() {
+        let report = ValidationReport {
+            issues: vec![
+                ConfigIssue::new("connectors.api.parameters.base_url", "required parameter is missing"),
+                ConfigIssue::new("dispatcher.max_concurrent_queries", "must be greater than zero"),
+            ],
+        };
+        assert_eq!(report.to_string(), "2 problems found:\n  - connectors.api.parameters.base_url: required parameter is missing\n  - dispatcher.max_concurrent_queries: must be greater than zero");
+        assert!(report.into_result().is_err());
+        assert!(ValidationReport::default().into_result().is_ok());
+    }
+}
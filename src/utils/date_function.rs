@@ -0,0 +1,318 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Timelike, Utc};
+use std::fmt;
+
+use crate::utils::{
+    time_zone::{format_utc, parse_date_time, SessionTimeZone},
+    types::Value,
+};
+
+/// Canonical text of `NOW()` and `CURRENT_TIMESTAMP`, used as the column name
+pub const NOW_FUNCTION: &str = "now()";
+
+/// Virtual column giving when the rows of a source were fetched, cached or live
+pub const FETCHED_AT_COLUMN: &str = "_nirv_fetched_at";
+
+/// Whether a column, qualified or not, is the fetch time of its source
+pub fn is_fetched_at(name: &str) -> bool {
+    name.rsplit('.').next().is_some_and(|column| column.eq_ignore_ascii_case(FETCHED_AT_COLUMN))
+}
+
+/// Text of the current time in UTC
+pub fn current_timestamp() -> String {
+    format_utc(Utc::now().naive_utc())
+}
+
+/// Text of the current date in UTC
+pub fn current_date() -> String {
+    Utc::now().date_naive().format("%Y-%m-%d").to_string()
+}
+
+/// Span of an `INTERVAL` literal: calendar months, whose length depends on where they are
+/// counted from, and a fixed duration
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Interval {
+    pub months: i32,
+    pub duration: Duration,
+}
+
+impl Interval {
+    /// Parse the text of an interval literal such as `7 days`, `1 hour 30 minutes`, `-2 weeks`
+    /// or `01:30:00`, or a bare number with the unit written after it, as in `INTERVAL '7' DAY`
+    pub fn parse(text: &str, unit: Option<&str>) -> Option<Self> {
+        let mut interval = Interval::default();
+        let mut tokens = text.split_whitespace().peekable();
+        tokens.peek()?;
+        while let Some(token) = tokens.next() {
+            if token.contains(':') {
+                interval.duration = interval.duration.checked_add(&parse_clock(token)?)?;
+                continue;
+            }
+            let amount: f64 = token.parse().ok()?;
+            let unit = match tokens.next() {
+                Some(unit) => unit,
+                None => unit?,
+            };
+            interval = interval.plus(amount, unit)?;
+        }
+        Some(interval)
+    }
+
+    /// The interval with an amount of a unit added, or None for an unknown unit or a span
+    /// too large to hold
+    fn plus(self, amount: f64, unit: &str) -> Option<Self> {
+        let unit = unit.trim().to_lowercase();
+        let months = |per_unit: f64| {
+            let months = amount * per_unit;
+            let months = (months.fract() == 0.0 && months.abs() <= i32::MAX as f64).then_some(months as i32)?;
+            Some(Self { months: self.months.checked_add(months)?, ..self })
+        };
+        let duration = |unit: Duration| {
+            let micros = unit.num_microseconds()? as f64 * amount;
+            let micros = (micros.is_finite() && micros.abs() < i64::MAX as f64).then_some(micros.round() as i64)?;
+            Some(Self { duration: self.duration.checked_add(&Duration::microseconds(micros))?, ..self })
+        };
+        match unit.strip_suffix('s').unwrap_or(&unit) {
+            "year" | "yr" => months(12.0),
+            "quarter" => months(3.0),
+            "month" | "mon" => months(1.0),
+            "week" => duration(Duration::weeks(1)),
+            "day" => duration(Duration::days(1)),
+            "hour" | "hr" => duration(Duration::hours(1)),
+            "minute" | "min" => duration(Duration::minutes(1)),
+            "second" | "sec" => duration(Duration::seconds(1)),
+            "millisecond" => duration(Duration::milliseconds(1)),
+            "microsecond" => duration(Duration::microseconds(1)),
+            _ => None,
+        }
+    }
+
+    /// The interval pointing the other way, or None when its months cannot be negated
+    pub fn negated(self) -> Option<Self> {
+        Some(Self { months: self.months.checked_neg()?, duration: -self.duration })
+    }
+
+    /// Whether the interval moves a date to another date, with no time of day
+    pub fn is_whole_days(&self) -> bool {
+        self.duration.num_microseconds().is_some_and(|micros| micros % Duration::days(1).num_microseconds().unwrap() == 0)
+    }
+
+    /// Move a time by the interval, months first with the day kept within the month reached,
+    /// so a month after January 31st is the last day of February
+    pub fn add_to(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let months = Months::new(self.months.unsigned_abs());
+        let time = match self.months < 0 {
+            true => time.checked_sub_months(months)?,
+            false => time.checked_add_months(months)?,
+        };
+        time.checked_add_signed(self.duration)
+    }
+}
+
+/// A duration written as `hours:minutes[:seconds]`, negative with a leading `-`
+fn parse_clock(text: &str) -> Option<Duration> {
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, text),
+    };
+    let parts: Vec<f64> = text.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let seconds = match parts.as_slice() {
+        [hours, minutes] => hours * 3600.0 + minutes * 60.0,
+        [hours, minutes, seconds] => hours * 3600.0 + minutes * 60.0 + seconds,
+        _ => return None,
+    };
+    let micros = sign * seconds * 1_000_000.0;
+    (micros.is_finite() && micros.abs() < i64::MAX as f64).then(|| Duration::microseconds(micros.round() as i64))
+}
+
+/// Precision `DATE_TRUNC` truncates to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl TruncUnit {
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        Some(match name.strip_suffix('s').unwrap_or(&name) {
+            "second" => TruncUnit::Second,
+            "minute" => TruncUnit::Minute,
+            "hour" => TruncUnit::Hour,
+            "day" => TruncUnit::Day,
+            "week" => TruncUnit::Week,
+            "month" => TruncUnit::Month,
+            "quarter" => TruncUnit::Quarter,
+            "year" => TruncUnit::Year,
+            _ => return None,
+        })
+    }
+
+    /// Start of the unit containing a time; weeks start on Monday
+    pub fn truncate(&self, time: NaiveDateTime) -> NaiveDateTime {
+        let date = time.date();
+        let start_of = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap();
+        match self {
+            TruncUnit::Second => time.with_nanosecond(0).unwrap_or(time),
+            TruncUnit::Minute => date.and_hms_opt(time.hour(), time.minute(), 0).unwrap(),
+            TruncUnit::Hour => date.and_hms_opt(time.hour(), 0, 0).unwrap(),
+            TruncUnit::Day => start_of(date),
+            TruncUnit::Week => start_of(date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)),
+            TruncUnit::Month => start_of(date.with_day(1).unwrap()),
+            TruncUnit::Quarter => start_of(NaiveDate::from_ymd_opt(date.year(), (date.month() - 1) / 3 * 3 + 1, 1).unwrap()),
+            TruncUnit::Year => start_of(NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap()),
+        }
+    }
+}
+
+impl fmt::Display for TruncUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TruncUnit::Second => "second",
+            TruncUnit::Minute => "minute",
+            TruncUnit::Hour => "hour",
+            TruncUnit::Day => "day",
+            TruncUnit::Week => "week",
+            TruncUnit::Month => "month",
+            TruncUnit::Quarter => "quarter",
+            TruncUnit::Year => "year",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A `date_trunc('day', column)` expression truncating a DateTime column in a time zone, so
+/// days start at midnight in that zone. Without an explicit zone, queries run in a session take
+/// the session's; otherwise UTC is used. Like `JsonExtract`, its canonical text form is what the
+/// query parser stores as the column name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateTrunc {
+    pub unit: TruncUnit,
+    pub column: String,
+    pub time_zone: Option<SessionTimeZone>,
+}
+
+impl DateTrunc {
+    /// Recognize the canonical form produced by `Display`
+    pub fn parse(expression: &str) -> Option<Self> {
+        let rest = expression.strip_prefix("date_trunc('")?.strip_suffix(')')?;
+        let (unit, rest) = rest.split_once("', ")?;
+        let (column, time_zone) = match rest.split_once(", '") {
+            Some((column, zone)) => (column, Some(SessionTimeZone::parse(zone.strip_suffix('\'')?).ok()?)),
+            None => (rest, None),
+        };
+        if column.is_empty() || column.contains(['(', ',', '\'']) {
+            return None;
+        }
+        Some(Self { unit: TruncUnit::parse(unit)?, column: column.to_string(), time_zone })
+    }
+
+    /// Column name without a table qualifier
+    pub fn unqualified_column(&self) -> &str {
+        self.column.rsplit('.').next().unwrap_or(&self.column)
+    }
+
+    /// Truncate a UTC DateTime, returning the start of its unit as a UTC DateTime. Dates are
+    /// truncated as they are; NULL and values that are not times yield NULL.
+    pub fn evaluate(&self, value: &Value) -> Value {
+        match value {
+            Value::DateTime(text) | Value::Text(text) => match parse_date_time(text) {
+                Some((time, offset)) => {
+                    let zone = self.time_zone.unwrap_or_default();
+                    let utc = offset.map_or(time, |offset| time - offset);
+                    let truncated = self.unit.truncate(zone.from_utc(utc).naive_local());
+                    Value::DateTime(format_utc(zone.to_utc(truncated)))
+                }
+                None => Value::Null,
+            },
+            Value::Date(text) => match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+                Ok(date) => Value::Date(self.unit.truncate(date.and_hms_opt(0, 0, 0).unwrap()).format("%Y-%m-%d").to_string()),
+                Err(_) => Value::Null,
+            },
+            _ => Value::Null,
+        }
+    }
+}
+
+impl fmt::Display for DateTrunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.time_zone {
+            Some(zone) => write!(f, "date_trunc('{}', {}, '{}')", self.unit, self.column, zone),
+            None => write!(f, "date_trunc('{}', {})", self.unit, self.column),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(text: &str) -> Value {
+        Value::DateTime(text.to_string())
+    }
+
+    #[test]
+    fn test_interval_arithmetic() {
+        let time = |text: &str| parse_date_time(text).unwrap().0;
+        let week = Interval::parse("7 days", None).unwrap();
+        assert_eq!(week.negated().unwrap().add_to(time("2024-03-05 12:00:00")), Some(time("2024-02-27 12:00:00")));
+        assert!(week.is_whole_days());
+
+        let mixed = Interval::parse("1 hour 30 minutes 2.5 secs", None).unwrap();
+        assert_eq!(mixed.duration, Duration::milliseconds(5_402_500));
+        assert!(!mixed.is_whole_days());
+        assert_eq!(Interval::parse("01:30:00", None).unwrap().duration, Duration::minutes(90));
+        assert_eq!(Interval::parse("3", Some("WEEK")), Some(Interval { months: 0, duration: Duration::weeks(3) }));
+
+        // Months keep the day within the month they reach
+        let month = Interval::parse("1 month", None).unwrap();
+        assert_eq!(month.add_to(time("2024-01-31 00:00:00")), Some(time("2024-02-29 00:00:00")));
+        assert_eq!(Interval::parse("-1 year", None).unwrap().add_to(time("2024-02-29 08:00:00")), Some(time("2023-02-28 08:00:00")));
+
+        assert_eq!(Interval::parse("1.5 months", None), None);
+        assert_eq!(Interval::parse("7 fortnights", None), None);
+        assert_eq!(Interval::parse("", None), None);
+        assert_eq!(Interval::parse("7", None), None);
+
+        // Spans too large to hold are rejected rather than wrapped
+        assert_eq!(Interval::parse("2000000000 months 2000000000 months", None), None);
+        assert_eq!(Interval::parse("1e300 days", None), None);
+        assert_eq!(Interval::parse("99999999999999:00:00", None), None);
+    }
+
+    #[test]
+    fn test_date_trunc_canonical_form() {
+        let trunc = DateTrunc::parse("date_trunc('day', o.created_at)").unwrap();
+        assert_eq!(trunc, DateTrunc { unit: TruncUnit::Day, column: "o.created_at".to_string(), time_zone: None });
+        assert_eq!(trunc.unqualified_column(), "created_at");
+
+        let zoned = DateTrunc { time_zone: Some(SessionTimeZone::parse("-05:00").unwrap()), ..trunc };
+        assert_eq!(zoned.to_string(), "date_trunc('day', o.created_at, '-05:00')");
+        assert_eq!(DateTrunc::parse(&zoned.to_string()), Some(zoned));
+        assert_eq!(DateTrunc::parse("date_trunc('fortnight', ts)"), None);
+        assert_eq!(DateTrunc::parse("md5(ts)"), None);
+    }
+
+    #[test]
+    fn test_date_trunc_in_time_zone() {
+        let day = DateTrunc::parse("date_trunc('day', ts)").unwrap();
+        assert_eq!(day.evaluate(&datetime("2024-03-01 02:30:00")), datetime("2024-03-01 00:00:00"));
+        let new_york = DateTrunc::parse("date_trunc('day', ts, '-05:00')").unwrap();
+        assert_eq!(new_york.evaluate(&datetime("2024-03-01 02:30:00")), datetime("2024-02-29 05:00:00"));
+        assert_eq!(new_york.evaluate(&datetime("2024-03-01T02:30:00-05:00")), datetime("2024-03-01 05:00:00"));
+
+        let week = DateTrunc::parse("date_trunc('week', ts)").unwrap();
+        assert_eq!(week.evaluate(&datetime("2024-03-03 12:00:00")), datetime("2024-02-26 00:00:00"));
+        let quarter = DateTrunc::parse("date_trunc('quarter', ts)").unwrap();
+        assert_eq!(quarter.evaluate(&Value::Date("2024-08-15".to_string())), Value::Date("2024-07-01".to_string()));
+        let minute = DateTrunc::parse("date_trunc('minute', ts)").unwrap();
+        assert_eq!(minute.evaluate(&datetime("2024-03-01 10:30:59.75")), datetime("2024-03-01 10:30:00"));
+        assert_eq!(minute.evaluate(&Value::Null), Value::Null);
+        assert_eq!(minute.evaluate(&Value::Integer(3)), Value::Null);
+    }
+}
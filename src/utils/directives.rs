@@ -0,0 +1,156 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::utils::error::{NirvResult, QueryParsingError};
+use crate::utils::types::{parse_hint_duration, QueryPriority};
+
+/// Prefix marking a comment as carrying directives, as in `-- nirv: cache=off`
+pub const DIRECTIVE_PREFIX: &str = "nirv:";
+
+/// Settings carried in `-- nirv: key=value ...` or `/* nirv: key=value */` comments, for
+/// components to consult by key. Pairs are separated by commas or spaces, a key without a
+/// value is `on`, values may be quoted, and a key given again replaces its earlier value.
+/// Keys the engine reads: `cache` (`off` or a TTL such as `30s`), `pushdown` (`off`) and
+/// `priority` (`interactive` or `batch`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryDirectives {
+    entries: Vec<(String, String)>,
+}
+
+impl QueryDirectives {
+    /// Collect the directives of the comments that start with `nirv:`; other comments are ignored
+    pub fn parse<S: AsRef<str>>(comments: &[S]) -> NirvResult<Self> {
+        let mut directives = QueryDirectives::default();
+        for comment in comments {
+            let comment = comment.as_ref().trim();
+            let Some(prefix) = comment.get(..DIRECTIVE_PREFIX.len()) else { continue };
+            if prefix.eq_ignore_ascii_case(DIRECTIVE_PREFIX) {
+                directives.parse_pairs(&comment[DIRECTIVE_PREFIX.len()..])?;
+            }
+        }
+        Ok(directives)
+    }
+
+    fn parse_pairs(&mut self, text: &str) -> NirvResult<()> {
+        let invalid = |reason: String| QueryParsingError::InvalidSyntax(format!("Invalid directive: {}", reason));
+        let is_separator = |c: char| c.is_whitespace() || c == ',';
+        let mut rest = text.trim_start_matches(is_separator);
+        while !rest.is_empty() {
+            let key_end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')).unwrap_or(rest.len());
+            if key_end == 0 {
+                return Err(invalid(format!("unexpected '{}'", rest)).into());
+            }
+            let key = rest[..key_end].to_ascii_lowercase();
+            rest = &rest[key_end..];
+            let value = match rest.strip_prefix('=') {
+                Some(quoted) if quoted.starts_with(['\'', '"']) => {
+                    let quote = quoted.chars().next().expect("quote");
+                    let close = quoted[1..].find(quote).ok_or_else(|| invalid(format!("{} is missing its closing quote", key)))?;
+                    rest = &quoted[close + 2..];
+                    quoted[1..close + 1].to_string()
+                }
+                Some(value) => {
+                    let value_end = value.find(is_separator).unwrap_or(value.len());
+                    rest = &value[value_end..];
+                    value[..value_end].to_string()
+                }
+                None => "on".to_string(),
+            };
+            if !rest.is_empty() && !rest.starts_with(is_separator) {
+                return Err(invalid(format!("expected a space or comma after {}", key)).into());
+            }
+            self.entries.retain(|(existing, _)| *existing != key);
+            self.entries.push((key, value));
+            rest = rest.trim_start_matches(is_separator);
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Directives in the order they were given
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// The value of a directive; keys are case-insensitive
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|(existing, _)| existing.eq_ignore_ascii_case(key)).map(|(_, value)| value)
+    }
+
+    /// A directive given as `on`/`off`, `true`/`false` or `yes`/`no`
+    pub fn flag(&self, key: &str) -> NirvResult<Option<bool>> {
+        let Some(value) = self.get(key) else { return Ok(None) };
+        match value.to_ascii_lowercase().as_str() {
+            "on" | "true" | "yes" => Ok(Some(true)),
+            "off" | "false" | "no" => Ok(Some(false)),
+            _ => Err(QueryParsingError::InvalidSyntax(format!(
+                "Invalid directive: {} expects on or off, not '{}'", key, value
+            )).into()),
+        }
+    }
+
+    /// How long the `cache` directive lets connectors answer from their caches: zero for
+    /// `cache=off`, or the TTL it gives
+    pub fn cache_ttl(&self) -> NirvResult<Option<Duration>> {
+        let Some(value) = self.get("cache") else { return Ok(None) };
+        if let Ok(Some(enabled)) = self.flag("cache") {
+            return match enabled {
+                false => Ok(Some(Duration::ZERO)),
+                true => Ok(None),
+            };
+        }
+        parse_hint_duration(value).map(Some).ok_or_else(|| QueryParsingError::InvalidSyntax(format!(
+            "Invalid directive: cache expects off or a duration such as 30s, not '{}'", value
+        )).into())
+    }
+
+    /// Scheduling class the `priority` directive gives, `interactive` or `batch`
+    pub fn priority(&self) -> NirvResult<Option<QueryPriority>> {
+        let Some(value) = self.get("priority") else { return Ok(None) };
+        QueryPriority::parse(value).map(Some).ok_or_else(|| QueryParsingError::InvalidSyntax(format!(
+            "Invalid directive: priority expects interactive or batch, not '{}'", value
+        )).into())
+    }
+}
+
+impl fmt::Display for QueryDirectives {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self.iter().map(|(key, value)| {
+            if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == ',') {
+                format!("{}='{}'", key, value)
+            } else {
+                format!("{}={}", key, value)
+            }
+        }).collect();
+        write!(f, "{} {}", DIRECTIVE_PREFIX, pairs.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_directives() {
+        let comments = [" nirv: cache=off, pushdown=off tag='nightly load'", "just a note", "NIRV: verbose cache=30s"];
+        let directives = QueryDirectives::parse(&comments).unwrap();
+        assert_eq!(directives.get("tag"), Some("nightly load"));
+        assert_eq!(directives.flag("pushdown").unwrap(), Some(false));
+        assert_eq!(directives.flag("VERBOSE").unwrap(), Some(true));
+        assert_eq!(directives.flag("missing").unwrap(), None);
+        // The later cache directive replaces the earlier one
+        assert_eq!(directives.cache_ttl().unwrap(), Some(Duration::from_secs(30)));
+        assert_eq!(directives.to_string(), "nirv: pushdown=off tag='nightly load' verbose=on cache=30s");
+
+        assert_eq!(QueryDirectives::parse(&["nirv: cache=off"]).unwrap().cache_ttl().unwrap(), Some(Duration::ZERO));
+        assert!(QueryDirectives::parse(&["nirv: cache=soon"]).unwrap().cache_ttl().is_err());
+        assert_eq!(QueryDirectives::parse(&["nirv: priority=batch"]).unwrap().priority().unwrap(), Some(QueryPriority::Batch));
+        assert!(QueryDirectives::parse(&["nirv: priority=urgent"]).unwrap().priority().is_err());
+        assert!(QueryDirectives::parse(&["nirv: tag='open"]).is_err());
+        assert!(QueryDirectives::parse(&["nirv: =off"]).is_err());
+        assert!(QueryDirectives::parse(&["no directives here"]).unwrap().is_empty());
+    }
+}
@@ -18,6 +18,15 @@ pub enum NirvError {
     #[error("Configuration error: {0}")]
     Configuration(String),
     
+    #[error("Query rewrite error: {0}")]
+    QueryRewrite(String),
+    
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+    
+    #[error("Query blocked by lint: {0}")]
+    Lint(String),
+    
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -83,6 +92,9 @@ pub enum ConnectorError {
     
     #[error("Authentication failed: {0}")]
     AuthenticationFailed(String),
+    
+    #[error("Response schema mismatch: {0}")]
+    SchemaMismatch(String),
 }
 
 /// Dispatcher errors
@@ -100,8 +112,23 @@ pub enum DispatcherError {
     #[error("Cross-connector join not supported")]
     CrossConnectorJoinUnsupported,
     
+    #[error("Cross-connector transaction not supported: {0}")]
+    CrossConnectorTransactionUnsupported(String),
+    
+    #[error("Transaction coordination failed: {0}")]
+    TransactionCoordinationFailed(String),
+    
     #[error("Connector registration failed: {0}")]
     RegistrationFailed(String),
+    
+    #[error("Source policy violation: {0}")]
+    PolicyViolation(String),
+    
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+    
+    #[error("Connector saturated: {0}")]
+    ConnectorSaturated(String),
 }
 
 /// Result type alias for NIRV operations
@@ -0,0 +1,219 @@
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::utils::types::Value;
+
+/// Digest algorithm of a hash function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "md5" => Some(HashAlgorithm::Md5),
+            "sha256" | "sha2" => Some(HashAlgorithm::Sha256),
+            _ => None,
+        }
+    }
+
+    /// Lowercase hexadecimal digest of the bytes
+    pub fn hex_digest(&self, bytes: &[u8]) -> String {
+        match self {
+            HashAlgorithm::Md5 => to_hex(&Md5::digest(bytes)),
+            HashAlgorithm::Sha256 => to_hex(&Sha256::digest(bytes)),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashAlgorithm::Md5 => write!(f, "md5"),
+            HashAlgorithm::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+/// An `md5(column)` or `sha256(column)` expression hashing a value's canonical text, so
+/// `md5(name)` equals PostgreSQL's `md5(name)` for text. Like `JsonExtract`, its canonical
+/// text form is what the query parser stores as the column name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashFunction {
+    pub algorithm: HashAlgorithm,
+    pub column: String,
+}
+
+impl HashFunction {
+    /// Recognize the canonical form produced by `Display`
+    pub fn parse(expression: &str) -> Option<Self> {
+        let (name, rest) = expression.split_once('(')?;
+        let algorithm = match name {
+            "md5" => HashAlgorithm::Md5,
+            "sha256" => HashAlgorithm::Sha256,
+            _ => return None,
+        };
+        let column = rest.strip_suffix(')')?;
+        if column.is_empty() || column.contains(['(', ',']) {
+            return None;
+        }
+        Some(Self { algorithm, column: column.to_string() })
+    }
+
+    /// Column name without a table qualifier
+    pub fn unqualified_column(&self) -> &str {
+        self.column.rsplit('.').next().unwrap_or(&self.column)
+    }
+
+    /// Hash a column value; NULL yields NULL
+    pub fn evaluate(&self, value: &Value) -> Value {
+        canonical_text(value).map_or(Value::Null, |text| Value::Text(self.algorithm.hex_digest(text.as_bytes())))
+    }
+}
+
+impl fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.algorithm, self.column)
+    }
+}
+
+/// A `hash_row()` expression hashing every column of a row, in column name order so sources
+/// listing the same columns differently agree, or `hash_row(a, b)` hashing the listed columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowHash {
+    /// Columns hashed, in order; empty for every column
+    pub columns: Vec<String>,
+}
+
+impl RowHash {
+    /// Recognize the canonical form produced by `Display`
+    pub fn parse(expression: &str) -> Option<Self> {
+        let inner = expression.strip_prefix("hash_row(")?.strip_suffix(')')?;
+        let columns = match inner {
+            "" => Vec::new(),
+            inner => inner.split(", ").map(str::to_string).collect(),
+        };
+        Some(Self { columns })
+    }
+}
+
+impl fmt::Display for RowHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hash_row({})", self.columns.join(", "))
+    }
+}
+
+/// MD5 of a row's values. Each value is length-prefixed so `('a', 'bc')` and `('ab', 'c')`
+/// differ, and NULL differs from the text `'NULL'`.
+pub fn row_digest<'a>(values: impl IntoIterator<Item = &'a Value>) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    for value in values {
+        match canonical_text(value) {
+            Some(text) => hasher.update(format!("{}:{}", text.len(), text)),
+            None => hasher.update("-"),
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// `row_digest` as 32 hexadecimal digits
+pub fn row_hash<'a>(values: impl IntoIterator<Item = &'a Value>) -> String {
+    to_hex(&row_digest(values))
+}
+
+/// Order-independent checksum of a set of rows: the wrapping sum of their digests, so
+/// rows may arrive in any order and a duplicated row still changes the checksum
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableChecksum {
+    sum: u128,
+    rows: u64,
+}
+
+impl TableChecksum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_row<'a>(&mut self, values: impl IntoIterator<Item = &'a Value>) {
+        self.sum = self.sum.wrapping_add(u128::from_be_bytes(row_digest(values)));
+        self.rows += 1;
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// The checksum as 32 hexadecimal digits
+    pub fn hex(&self) -> String {
+        format!("{:032x}", self.sum)
+    }
+}
+
+/// Text form values are compared and hashed by across sources: whole floats print as
+/// integers and JSON is normalized. NULL has none.
+pub fn canonical_text(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Text(s) | Value::Date(s) | Value::DateTime(s) => s.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => (*f as i64).to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Json(j) => serde_json::from_str::<serde_json::Value>(j)
+            .map(|json| json.to_string())
+            .unwrap_or_else(|_| j.clone()),
+        Value::Binary(b) => to_hex(b),
+        Value::Null => return None,
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_functions() {
+        let md5 = HashFunction::parse("md5(u.email)").unwrap();
+        assert_eq!(md5, HashFunction { algorithm: HashAlgorithm::Md5, column: "u.email".to_string() });
+        assert_eq!(md5.unqualified_column(), "email");
+        assert_eq!(md5.evaluate(&Value::Text("abc".to_string())), Value::Text("900150983cd24fb0d6963f7d28e17f72".to_string()));
+        assert_eq!(md5.evaluate(&Value::Null), Value::Null);
+        let sha = HashFunction { algorithm: HashAlgorithm::Sha256, column: "id".to_string() };
+        assert_eq!(HashFunction::parse(&sha.to_string()), Some(sha));
+        assert_eq!(HashFunction::parse("regexp_match(a, 'b')"), None);
+
+        assert_eq!(RowHash::parse("hash_row()"), Some(RowHash { columns: vec![] }));
+        assert_eq!(RowHash::parse("hash_row(a, b)").unwrap().to_string(), "hash_row(a, b)");
+    }
+
+    #[test]
+    fn test_table_checksum_ignores_row_order() {
+        let text = |s: &str| Value::Text(s.to_string());
+        let rows = [
+            vec![Value::Integer(1), text("ab"), text("c")],
+            vec![Value::Float(2.0), text("a"), text("bc")],
+            vec![Value::Integer(3), Value::Null, text("NULL")],
+        ];
+        let mut forward = TableChecksum::new();
+        rows.iter().for_each(|row| forward.add_row(row));
+        let mut backward = TableChecksum::new();
+        rows.iter().rev().for_each(|row| backward.add_row(row));
+        assert_eq!(forward, backward);
+        assert_eq!(forward.rows(), 3);
+        assert_eq!(forward.hex().len(), 32);
+
+        assert_ne!(row_digest(&rows[0][1..]), row_digest(&rows[1][1..]));
+        assert_ne!(row_digest(&[Value::Null]), row_digest(&[text("NULL")]));
+        assert_eq!(row_digest(&[Value::Integer(2)]), row_digest(&[Value::Float(2.0)]));
+
+        let mut duplicated = forward.clone();
+        duplicated.add_row(&rows[0]);
+        assert_ne!(duplicated.hex(), forward.hex());
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How unquoted identifiers in a query are matched against column names.
+/// Quoted identifiers (`"Name"`) always match exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentifierCase {
+    /// `Name`, `NAME` and `name` refer to the same column
+    #[default]
+    Insensitive,
+    /// Identifiers must match column names exactly
+    Sensitive,
+}
+
+impl IdentifierCase {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "insensitive" => Some(IdentifierCase::Insensitive),
+            "sensitive" => Some(IdentifierCase::Sensitive),
+            _ => None,
+        }
+    }
+
+    /// Read the `identifier_case` connection parameter, defaulting to case-insensitive
+    pub fn from_params(params: &HashMap<String, String>) -> Self {
+        params.get("identifier_case")
+            .and_then(|name| Self::from_name(name))
+            .unwrap_or_default()
+    }
+
+    /// Whether a query identifier refers to a column name
+    pub fn matches(&self, identifier: &str, column: &str) -> bool {
+        match unquote_identifier(identifier) {
+            Some(exact) => exact == column,
+            None if *self == IdentifierCase::Insensitive => identifier.eq_ignore_ascii_case(column),
+            None => identifier == column,
+        }
+    }
+
+    /// Position of the column a query identifier refers to. An exact match wins over a
+    /// case-insensitive one, and a qualified identifier (`u.name`) falls back to its
+    /// unqualified name when no column carries the qualifier. An unqualified identifier
+    /// falls back to the one qualified column of that name, as in a join's result.
+    pub fn resolve<'a>(&self, identifier: &str, columns: impl IntoIterator<Item = &'a str>) -> Option<usize> {
+        let columns: Vec<&str> = columns.into_iter().collect();
+        let find = |identifier: &str| {
+            let exact = unquote_identifier(identifier).unwrap_or_else(|| identifier.to_string());
+            columns.iter().position(|column| *column == exact)
+                .or_else(|| columns.iter().position(|column| self.matches(identifier, column)))
+        };
+
+        find(identifier).or_else(|| match split_qualifier(identifier) {
+            Some((_, name)) => find(name),
+            None => {
+                let mut qualified = columns.iter().enumerate().filter(|(_, column)| {
+                    split_qualifier(column).is_some_and(|(_, name)| self.matches(identifier, name))
+                });
+                match (qualified.next(), qualified.next()) {
+                    (Some((index, _)), None) => Some(index),
+                    _ => None,
+                }
+            }
+        })
+    }
+}
+
+/// Quote an identifier so it resolves exactly, doubling embedded quotes
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The exact name of a double-quoted identifier, or `None` when it is unquoted
+pub fn unquote_identifier(identifier: &str) -> Option<String> {
+    let inner = identifier.strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.replace("\"\"", "\""))
+}
+
+/// Split `qualifier.name` at the last dot outside quotes
+pub fn split_qualifier(identifier: &str) -> Option<(&str, &str)> {
+    let mut in_quotes = false;
+    let mut split = None;
+    for (i, c) in identifier.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '.' if !in_quotes => split = Some(i),
+            _ => {}
+        }
+    }
+    split.map(|i| (&identifier[..i], &identifier[i + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_matching() {
+        let case = IdentifierCase::default();
+        assert!(case.matches("Name", "name"));
+        assert!(case.matches("\"name\"", "name"));
+        assert!(!case.matches("\"Name\"", "name"));
+        assert!(!IdentifierCase::Sensitive.matches("Name", "name"));
+        assert_eq!(unquote_identifier(&quote_identifier("say \"hi\"")), Some("say \"hi\"".to_string()));
+    }
+
+    #[test]
+    fn test_identifier_resolution() {
+        let columns = ["id", "Name", "name", "address.city"];
+        let resolve = |identifier: &str| IdentifierCase::Insensitive.resolve(identifier, columns);
+
+        // Exact matches take precedence over case-insensitive ones
+        assert_eq!(resolve("name"), Some(2));
+        assert_eq!(resolve("Name"), Some(1));
+        assert_eq!(resolve("NAME"), Some(1));
+        assert_eq!(resolve("\"NAME\""), None);
+        assert_eq!(resolve("u.ID"), Some(0));
+        assert_eq!(resolve("Address.City"), Some(3));
+        assert_eq!(resolve("u.\"address.city\""), Some(3));
+        assert_eq!(IdentifierCase::Sensitive.resolve("ID", columns), None);
+
+        // Unqualified names find a qualified column when only one carries the name
+        let joined = ["u.id", "u.name", "c.id", "c.customer_name"];
+        assert_eq!(IdentifierCase::Insensitive.resolve("customer_name", joined), Some(3));
+        assert_eq!(IdentifierCase::Insensitive.resolve("id", joined), None);
+        assert_eq!(IdentifierCase::Insensitive.resolve("c.ID", joined), Some(2));
+    }
+}
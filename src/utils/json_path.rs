@@ -0,0 +1,271 @@
+use serde_json::Value as JsonValue;
+use std::fmt;
+
+use crate::utils::{
+    error::{NirvResult, QueryParsingError},
+    types::Value,
+};
+
+/// One step of a JSON path
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonPathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// A JSONPath subset: `$` followed by `.key`, `."quoted key"`, `['key']` and `[index]` steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<JsonPathSegment>,
+}
+
+impl JsonPath {
+    /// Parse a path such as `$.user.addresses[0].city`
+    pub fn parse(path: &str) -> NirvResult<Self> {
+        let invalid = || QueryParsingError::InvalidSyntax(format!("Invalid JSON path: {}", path));
+        let chars: Vec<char> = path.trim().chars().collect();
+        if chars.first() != Some(&'$') {
+            return Err(invalid().into());
+        }
+
+        let mut segments = Vec::new();
+        let mut i = 1;
+        while i < chars.len() {
+            match chars[i] {
+                '.' => {
+                    i += 1;
+                    if chars.get(i) == Some(&'"') {
+                        let (key, next) = read_quoted(&chars, i).ok_or_else(invalid)?;
+                        segments.push(JsonPathSegment::Key(key));
+                        i = next;
+                    } else {
+                        let start = i;
+                        while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                            i += 1;
+                        }
+                        if start == i {
+                            return Err(invalid().into());
+                        }
+                        segments.push(JsonPathSegment::Key(chars[start..i].iter().collect()));
+                    }
+                }
+                '[' => {
+                    i += 1;
+                    if matches!(chars.get(i), Some('\'' | '"')) {
+                        let (key, next) = read_quoted(&chars, i).ok_or_else(invalid)?;
+                        segments.push(JsonPathSegment::Key(key));
+                        i = next;
+                    } else {
+                        let start = i;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        let index = chars[start..i].iter().collect::<String>().parse().map_err(|_| invalid())?;
+                        segments.push(JsonPathSegment::Index(index));
+                    }
+                    if chars.get(i) != Some(&']') {
+                        return Err(invalid().into());
+                    }
+                    i += 1;
+                }
+                _ => return Err(invalid().into()),
+            }
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[JsonPathSegment] {
+        &self.segments
+    }
+
+    /// Follow the path into a JSON document
+    pub fn extract<'a>(&self, json: &'a JsonValue) -> Option<&'a JsonValue> {
+        self.segments.iter().try_fold(json, |current, segment| match segment {
+            JsonPathSegment::Key(key) => current.get(key),
+            JsonPathSegment::Index(index) => current.get(index),
+        })
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                JsonPathSegment::Key(key) if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                    write!(f, ".{}", key)?
+                }
+                JsonPathSegment::Key(key) => write!(f, ".\"{}\"", key.replace('"', "\\\""))?,
+                JsonPathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a quoted path key starting at the opening quote, returning the key and the next position
+fn read_quoted(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let quote = chars[start];
+    let mut key = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                key.push(chars[i + 1]);
+                i += 2;
+            }
+            c if c == quote => return Some((key, i + 1)),
+            c => {
+                key.push(c);
+                i += 1;
+            }
+        }
+    }
+    None
+}
+
+/// A `json_extract(column, 'path')` expression. Its canonical text form is what the query
+/// parser stores as the column name of projections and predicates that use it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonExtract {
+    /// Column holding the JSON document, optionally qualified
+    pub column: String,
+    pub path: JsonPath,
+}
+
+impl JsonExtract {
+    pub fn new(column: impl Into<String>, path: JsonPath) -> Self {
+        Self { column: column.into(), path }
+    }
+
+    /// Recognize the canonical form produced by `Display`
+    pub fn parse(expression: &str) -> Option<Self> {
+        let inner = expression.strip_prefix("json_extract(")?.strip_suffix("')")?;
+        let (column, path) = inner.split_once(", '")?;
+        let path = JsonPath::parse(&path.replace("''", "'")).ok()?;
+        Some(Self::new(column, path))
+    }
+
+    /// Column name without a table qualifier
+    pub fn unqualified_column(&self) -> &str {
+        self.column.rsplit('.').next().unwrap_or(&self.column)
+    }
+
+    /// Evaluate against a column value holding JSON text; missing paths and non-JSON values yield NULL
+    pub fn evaluate(&self, value: &Value) -> Value {
+        let document = match value {
+            Value::Json(text) | Value::Text(text) => match serde_json::from_str::<JsonValue>(text) {
+                Ok(document) => document,
+                Err(_) => return Value::Null,
+            },
+            _ => return Value::Null,
+        };
+
+        self.path.extract(&document).map(json_to_value).unwrap_or(Value::Null)
+    }
+
+    /// Render as a Postgres expression using the `->` and `->>` operators, yielding text
+    pub fn to_postgres_sql(&self) -> String {
+        let Some((last, init)) = self.path.segments().split_last() else {
+            return format!("{}::text", self.column);
+        };
+
+        let step = |segment: &JsonPathSegment| match segment {
+            JsonPathSegment::Key(key) => format!("'{}'", key.replace('\'', "''")),
+            JsonPathSegment::Index(index) => index.to_string(),
+        };
+        let mut sql = format!("({}::jsonb)", self.column);
+        for segment in init {
+            sql.push_str("->");
+            sql.push_str(&step(segment));
+        }
+        sql.push_str("->>");
+        sql.push_str(&step(last));
+        sql
+    }
+
+    /// Render as a SQL Server `JSON_VALUE` call, yielding text
+    pub fn to_sqlserver_sql(&self) -> String {
+        format!("JSON_VALUE({}, '{}')", self.column, self.path.to_string().replace('\'', "''"))
+    }
+
+    /// Render as a BigQuery `JSON_VALUE` call, yielding text
+    pub fn to_bigquery_sql(&self) -> String {
+        format!("JSON_VALUE({}, '{}')", self.column, self.path.to_string().replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+
+    /// Render as a Snowflake `JSON_EXTRACT_PATH_TEXT` call, whose paths have no leading `$.`
+    pub fn to_snowflake_sql(&self) -> String {
+        let path = self.path.to_string();
+        let path = path.trim_start_matches('$').trim_start_matches('.');
+        format!("JSON_EXTRACT_PATH_TEXT({}, '{}')", self.column, path.replace('\\', "\\\\").replace('\'', "''"))
+    }
+}
+
+impl fmt::Display for JsonExtract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "json_extract({}, '{}')", self.column, self.path.to_string().replace('\'', "''"))
+    }
+}
+
+/// Convert a JSON value to a query value: scalars map to their SQL counterparts,
+/// objects and arrays stay JSON
+pub fn json_to_value(json: &JsonValue) -> Value {
+    match json {
+        JsonValue::Null => Value::Null,
+        JsonValue::Bool(b) => Value::Boolean(*b),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(s) => Value::Text(s.clone()),
+        JsonValue::Array(_) | JsonValue::Object(_) => Value::Json(json.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_path_parsing() {
+        let path = JsonPath::parse("$.user.addresses[1]['zip code']").unwrap();
+        assert_eq!(path.segments(), &[
+            JsonPathSegment::Key("user".to_string()),
+            JsonPathSegment::Key("addresses".to_string()),
+            JsonPathSegment::Index(1),
+            JsonPathSegment::Key("zip code".to_string()),
+        ]);
+        assert_eq!(path.to_string(), "$.user.addresses[1].\"zip code\"");
+        assert_eq!(JsonPath::parse(&path.to_string()).unwrap(), path);
+
+        assert!(JsonPath::parse("user.id").is_err());
+        assert!(JsonPath::parse("$.items[x]").is_err());
+        assert!(JsonPath::parse("$.").is_err());
+    }
+
+    #[test]
+    fn test_json_extract_evaluation() {
+        let payload = Value::Json(r#"{"user": {"id": 42, "tags": ["a", "b"], "name": "Ann"}}"#.to_string());
+        let extract = |path: &str| JsonExtract::new("payload", JsonPath::parse(path).unwrap()).evaluate(&payload);
+
+        assert_eq!(extract("$.user.id"), Value::Integer(42));
+        assert_eq!(extract("$.user.name"), Value::Text("Ann".to_string()));
+        assert_eq!(extract("$.user.tags[1]"), Value::Text("b".to_string()));
+        assert_eq!(extract("$.user.tags"), Value::Json(r#"["a","b"]"#.to_string()));
+        assert_eq!(extract("$.user.missing"), Value::Null);
+        assert_eq!(JsonExtract::new("p", JsonPath::parse("$.a").unwrap()).evaluate(&Value::Text("not json".to_string())), Value::Null);
+    }
+
+    #[test]
+    fn test_json_extract_canonical_form_and_postgres_sql() {
+        let extract = JsonExtract::new("e.payload", JsonPath::parse("$.user['o\\'brien'][0]").unwrap());
+        let canonical = extract.to_string();
+        assert_eq!(canonical, "json_extract(e.payload, '$.user.\"o''brien\"[0]')");
+        assert_eq!(JsonExtract::parse(&canonical), Some(extract.clone()));
+        assert_eq!(extract.unqualified_column(), "payload");
+        assert_eq!(extract.to_postgres_sql(), "(e.payload::jsonb)->'user'->'o''brien'->>0");
+        assert_eq!(JsonExtract::parse("payload"), None);
+    }
+}
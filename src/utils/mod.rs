@@ -1,8 +1,33 @@
 // Utility modules
 pub mod error;
 pub mod config;
+pub mod connector_settings;
 pub mod types;
+pub mod row_buffer;
+pub mod json_path;
+pub mod regex_function;
+pub mod hash_function;
+pub mod time_zone;
+pub mod date_function;
+pub mod collation;
+pub mod similarity;
+pub mod identifier;
+pub mod sql_dialect;
+pub mod directives;
 
 pub use error::*;
 pub use config::*;
-pub use types::*;
\ No newline at end of file
+pub use connector_settings::*;
+pub use types::*;
+pub use row_buffer::*;
+pub use json_path::*;
+pub use regex_function::*;
+pub use hash_function::*;
+pub use time_zone::*;
+pub use date_function::*;
+pub use collation::*;
+pub use similarity::*;
+pub use identifier::*;
+pub use sql_dialect::*;
+pub use directives::*;
+
@@ -0,0 +1,193 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use crate::utils::{
+    error::{NirvResult, QueryParsingError},
+    types::Value,
+};
+
+/// Compiled patterns kept for reuse; the cache is cleared once it holds this many
+const MAX_CACHED_PATTERNS: usize = 256;
+
+/// What a regular expression function computes
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegexOperation {
+    /// Whether the pattern matches anywhere in the value
+    Match,
+    /// The text of a capture group of the first match; group 0 is the whole match
+    Extract { group: usize },
+    /// The value with every match replaced; `$1` refers to a capture group
+    Replace { replacement: String },
+}
+
+/// A `regexp_match(column, 'pattern')`, `regexp_extract(column, 'pattern'[, group])` or
+/// `regexp_replace(column, 'pattern', 'replacement')` expression. Like `JsonExtract`, its
+/// canonical text form is what the query parser stores as the column name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegexFunction {
+    pub column: String,
+    pub pattern: String,
+    pub operation: RegexOperation,
+}
+
+impl RegexFunction {
+    /// Create the expression, rejecting patterns that do not compile
+    pub fn new(column: impl Into<String>, pattern: impl Into<String>, operation: RegexOperation) -> NirvResult<Self> {
+        let pattern = pattern.into();
+        compile_regex(&pattern)?;
+        Ok(Self { column: column.into(), pattern, operation })
+    }
+
+    /// Recognize the canonical form produced by `Display`
+    pub fn parse(expression: &str) -> Option<Self> {
+        let (name, rest) = expression.split_once('(')?;
+        let (column, rest) = rest.split_once(", '")?;
+        let (pattern, rest) = read_literal(rest)?;
+        let operation = match (name, rest) {
+            ("regexp_match", ")") => RegexOperation::Match,
+            ("regexp_extract", rest) => RegexOperation::Extract {
+                group: rest.strip_prefix(", ")?.strip_suffix(')')?.parse().ok()?,
+            },
+            ("regexp_replace", rest) => {
+                let (replacement, rest) = read_literal(rest.strip_prefix(", '")?)?;
+                if rest != ")" {
+                    return None;
+                }
+                RegexOperation::Replace { replacement }
+            }
+            _ => return None,
+        };
+        Some(Self { column: column.to_string(), pattern, operation })
+    }
+
+    /// Column name without a table qualifier
+    pub fn unqualified_column(&self) -> &str {
+        self.column.rsplit('.').next().unwrap_or(&self.column)
+    }
+
+    /// Evaluate against a column value, read as text; NULL yields NULL
+    pub fn evaluate(&self, value: &Value) -> Value {
+        let Some(text) = value_text(value) else {
+            return Value::Null;
+        };
+        let Ok(regex) = compile_regex(&self.pattern) else {
+            return Value::Null;
+        };
+        match &self.operation {
+            RegexOperation::Match => Value::Boolean(regex.is_match(&text)),
+            RegexOperation::Extract { group } => regex.captures(&text)
+                .and_then(|captures| captures.get(*group))
+                .map(|found| Value::Text(found.as_str().to_string()))
+                .unwrap_or(Value::Null),
+            RegexOperation::Replace { replacement } => Value::Text(regex.replace_all(&text, replacement.as_str()).into_owned()),
+        }
+    }
+}
+
+impl fmt::Display for RegexFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pattern = self.pattern.replace('\'', "''");
+        match &self.operation {
+            RegexOperation::Match => write!(f, "regexp_match({}, '{}')", self.column, pattern),
+            RegexOperation::Extract { group } => write!(f, "regexp_extract({}, '{}', {})", self.column, pattern, group),
+            RegexOperation::Replace { replacement } => {
+                write!(f, "regexp_replace({}, '{}', '{}')", self.column, pattern, replacement.replace('\'', "''"))
+            }
+        }
+    }
+}
+
+/// Whether a value, read as text, matches a pattern. NULL and invalid patterns never match.
+pub fn regex_matches(value: &Value, pattern: &str) -> bool {
+    match (value_text(value), compile_regex(pattern)) {
+        (Some(text), Ok(regex)) => regex.is_match(&text),
+        _ => false,
+    }
+}
+
+/// Compile a pattern, reusing an earlier compilation of the same pattern
+pub fn compile_regex(pattern: &str) -> NirvResult<Regex> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(regex) = cache.lock().unwrap().get(pattern) {
+        return Ok(regex.clone());
+    }
+
+    let regex = Regex::new(pattern)
+        .map_err(|e| QueryParsingError::InvalidSyntax(format!("Invalid regular expression '{}': {}", pattern, e)))?;
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= MAX_CACHED_PATTERNS {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+/// Text a pattern is matched against; binary values and NULL have none
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Boolean(b) => Some(b.to_string()),
+        Value::Null | Value::Binary(_) => None,
+    }
+}
+
+/// Read a single-quoted literal whose opening quote was already consumed, returning its
+/// text and what follows the closing quote
+fn read_literal(text: &str) -> Option<(String, &str)> {
+    let mut literal = String::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c == '\'' {
+            if matches!(chars.peek(), Some((_, '\''))) {
+                chars.next();
+                literal.push('\'');
+            } else {
+                return Some((literal, &text[index + 1..]));
+            }
+        } else {
+            literal.push(c);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_form_round_trips() {
+        let functions = [
+            RegexFunction::new("log.message", r"code=(\d+)", RegexOperation::Extract { group: 1 }).unwrap(),
+            RegexFunction::new("message", "it's, 'quoted'", RegexOperation::Match).unwrap(),
+            RegexFunction::new("message", r"(\w+)@", RegexOperation::Replace { replacement: "$1 at '".to_string() }).unwrap(),
+        ];
+        for function in functions {
+            assert_eq!(RegexFunction::parse(&function.to_string()), Some(function));
+        }
+        assert_eq!(RegexFunction::parse("json_extract(payload, '$.a')"), None);
+        assert!(RegexFunction::new("message", "(unclosed", RegexOperation::Match).is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let line = Value::Text("2024-05-01 ERROR code=503 user=bob".to_string());
+        let extract = RegexFunction::new("line", r"code=(\d+)", RegexOperation::Extract { group: 1 }).unwrap();
+        assert_eq!(extract.evaluate(&line), Value::Text("503".to_string()));
+        assert_eq!(extract.evaluate(&Value::Text("no code".to_string())), Value::Null);
+        assert_eq!(extract.evaluate(&Value::Null), Value::Null);
+
+        let replace = RegexFunction::new("line", r"user=(\w+)", RegexOperation::Replace { replacement: "user=[$1]".to_string() }).unwrap();
+        assert_eq!(replace.evaluate(&line), Value::Text("2024-05-01 ERROR code=503 user=[bob]".to_string()));
+
+        let matches = RegexFunction::new("line", "ERROR|WARN", RegexOperation::Match).unwrap();
+        assert_eq!(matches.evaluate(&line), Value::Boolean(true));
+        assert!(regex_matches(&Value::Integer(503), r"^5\d\d$"));
+        assert!(!regex_matches(&Value::Null, ".*"));
+    }
+}
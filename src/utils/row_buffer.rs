@@ -0,0 +1,139 @@
+use crate::utils::types::{Row, Value};
+
+/// Rows of a fixed width stored in one contiguous allocation. Connectors scanning many rows
+/// push values here instead of allocating a `Vec` per row, filter and truncate in place, and
+/// only turn the rows that remain into `Row`s. Text values still own their strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RowBuffer {
+    width: usize,
+    len: usize,
+    values: Vec<Value>,
+}
+
+impl RowBuffer {
+    pub fn new(width: usize) -> Self {
+        Self { width, len: 0, values: Vec::new() }
+    }
+
+    /// A buffer with room for `rows` rows before it reallocates
+    pub fn with_capacity(width: usize, rows: usize) -> Self {
+        Self { width, len: 0, values: Vec::with_capacity(width * rows) }
+    }
+
+    /// Buffer holding the given rows, each padded with NULL or cut to the width
+    pub fn from_rows(width: usize, rows: Vec<Row>) -> Self {
+        let mut buffer = Self::with_capacity(width, rows.len());
+        for row in rows {
+            buffer.push_row(row.values);
+        }
+        buffer
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append a row, padding it with NULL or cutting it to the width
+    pub fn push_row(&mut self, values: impl IntoIterator<Item = Value>) {
+        let start = self.values.len();
+        self.values.extend(values.into_iter().take(self.width));
+        self.values.resize(start + self.width, Value::Null);
+        self.len += 1;
+    }
+
+    /// Values of the row at an index
+    pub fn row(&self, index: usize) -> &[Value] {
+        &self.values[index * self.width..(index + 1) * self.width]
+    }
+
+    /// Mutable values of the row at an index, for moving values out of the buffer
+    pub fn row_mut(&mut self, index: usize) -> &mut [Value] {
+        &mut self.values[index * self.width..(index + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Value]> + '_ {
+        (0..self.len).map(|index| self.row(index))
+    }
+
+    /// Keep only the rows for which `keep` returns true, in their order, without reallocating
+    pub fn retain(&mut self, mut keep: impl FnMut(&[Value]) -> bool) {
+        let mut kept = 0;
+        for index in 0..self.len {
+            if !keep(self.row(index)) {
+                continue;
+            }
+            if kept != index {
+                for column in 0..self.width {
+                    self.values.swap(kept * self.width + column, index * self.width + column);
+                }
+            }
+            kept += 1;
+        }
+        self.truncate(kept);
+    }
+
+    /// Drop the rows after the first `rows`
+    pub fn truncate(&mut self, rows: usize) {
+        if rows < self.len {
+            self.values.truncate(rows * self.width);
+            self.len = rows;
+        }
+    }
+
+    /// The rows as `Row`s, allocating one `Vec` per row
+    pub fn into_rows(self) -> Vec<Row> {
+        let mut values = self.values.into_iter();
+        (0..self.len).map(|_| Row::new(values.by_ref().take(self.width).collect())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integers(buffer: &RowBuffer) -> Vec<Vec<i64>> {
+        buffer.rows()
+            .map(|row| row.iter().map(|value| match value {
+                Value::Integer(i) => *i,
+                _ => -1,
+            }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_row_buffer() {
+        let mut buffer = RowBuffer::with_capacity(2, 4);
+        for i in 0..5 {
+            buffer.push_row([Value::Integer(i), Value::Integer(i * 10)]);
+        }
+        buffer.push_row([Value::Integer(5)]);
+        buffer.push_row([Value::Integer(6), Value::Integer(60), Value::Integer(600)]);
+        assert_eq!(buffer.len(), 7);
+        assert_eq!(buffer.row(5), &[Value::Integer(5), Value::Null]);
+        assert_eq!(buffer.row(6), &[Value::Integer(6), Value::Integer(60)]);
+
+        buffer.retain(|row| matches!(row[0], Value::Integer(i) if i % 2 == 0));
+        assert_eq!(integers(&buffer), vec![vec![0, 0], vec![2, 20], vec![4, 40], vec![6, 60]]);
+        buffer.truncate(3);
+        assert_eq!(buffer.len(), 3);
+
+        let rows = buffer.clone().into_rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2].values, vec![Value::Integer(4), Value::Integer(40)]);
+        assert_eq!(RowBuffer::from_rows(2, rows), buffer);
+
+        // Rows without columns are still counted
+        let mut empty = RowBuffer::new(0);
+        empty.push_row([]);
+        empty.push_row([Value::Integer(1)]);
+        assert_eq!(empty.into_rows().len(), 2);
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+/// How closely two strings resemble each other, from 0.0 (nothing in common) to 1.0 (equal)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SimilarityMetric {
+    /// Jaro-Winkler similarity, which favours strings sharing a prefix; suited to names
+    #[default]
+    JaroWinkler,
+    /// One minus the Levenshtein edit distance divided by the longer string's length
+    Levenshtein,
+}
+
+impl SimilarityMetric {
+    /// Parse `jaro_winkler` (also `jaro-winkler`, `jarowinkler`) or `levenshtein`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "jaro_winkler" | "jarowinkler" => Some(SimilarityMetric::JaroWinkler),
+            "levenshtein" => Some(SimilarityMetric::Levenshtein),
+            _ => None,
+        }
+    }
+
+    pub fn similarity(&self, a: &str, b: &str) -> f64 {
+        match self {
+            SimilarityMetric::JaroWinkler => jaro_winkler_similarity(a, b),
+            SimilarityMetric::Levenshtein => levenshtein_similarity(a, b),
+        }
+    }
+}
+
+impl fmt::Display for SimilarityMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimilarityMetric::JaroWinkler => write!(f, "jaro_winkler"),
+            SimilarityMetric::Levenshtein => write!(f, "levenshtein"),
+        }
+    }
+}
+
+/// Number of single-character insertions, deletions and substitutions turning one string
+/// into the other
+pub fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Edit distance scaled to a similarity; two empty strings are equal
+pub fn levenshtein_similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein_distance(a, b) as f64 / longest as f64
+}
+
+/// Jaro similarity: the share of matching characters, penalised for transpositions
+pub fn jaro_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    // Characters match when equal and no further apart than half the longer length
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0;
+    for (i, ca) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+        for j in start..end {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let a_sequence = a.iter().zip(&a_matched).filter(|(_, matched)| **matched).map(|(c, _)| c);
+    let b_sequence = b.iter().zip(&b_matched).filter(|(_, matched)| **matched).map(|(c, _)| c);
+    let transpositions = a_sequence.zip(b_sequence).filter(|(x, y)| x != y).count() / 2;
+
+    let matches = matches as f64;
+    (matches / a.len() as f64 + matches / b.len() as f64 + (matches - transpositions as f64) / matches) / 3.0
+}
+
+/// Jaro similarity boosted by up to four characters of common prefix
+pub fn jaro_winkler_similarity(a: &str, b: &str) -> f64 {
+    let jaro = jaro_similarity(a, b);
+    let prefix = a.chars().zip(b.chars()).take(4).take_while(|(x, y)| x == y).count();
+    jaro + prefix as f64 * 0.1 * (1.0 - jaro)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein_distance("kitten", "sitting"), 3);
+        assert_eq!(levenshtein_distance("", "abc"), 3);
+        assert_eq!(levenshtein_similarity("flaw", "lawn"), 0.5);
+        assert_eq!(levenshtein_similarity("", ""), 1.0);
+    }
+
+    #[test]
+    fn test_jaro_winkler() {
+        assert!((jaro_similarity("MARTHA", "MARHTA") - 0.9444).abs() < 1e-4);
+        assert!((jaro_winkler_similarity("MARTHA", "MARHTA") - 0.9611).abs() < 1e-4);
+        assert!((jaro_winkler_similarity("DIXON", "DICKSONX") - 0.8133).abs() < 1e-4);
+        assert_eq!(jaro_winkler_similarity("same", "same"), 1.0);
+        assert_eq!(jaro_winkler_similarity("abc", "xyz"), 0.0);
+        assert_eq!(SimilarityMetric::parse("Jaro-Winkler"), Some(SimilarityMetric::JaroWinkler));
+        assert_eq!(SimilarityMetric::parse("soundex"), None);
+    }
+}
@@ -0,0 +1,741 @@
+use crate::utils::{
+    types::{InternalQuery, QueryOperation, Column, COUNT_ALL, Predicate, PredicateOperator, PredicateValue, OrderDirection, NullsOrder, Sample},
+    error::{ConnectorError, NirvResult},
+    json_path::JsonExtract,
+    identifier::unquote_identifier,
+};
+
+/// SQL syntax of a database that queries are pushed down to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    PostgreSQL,
+    SqlServer,
+    BigQuery,
+    Snowflake,
+}
+
+impl SqlDialect {
+    /// Quote a name so it resolves exactly: `"name"` in PostgreSQL and Snowflake, `[name]`
+    /// in SQL Server, `` `name` `` in BigQuery
+    pub fn quote_identifier(&self, name: &str) -> String {
+        match self {
+            SqlDialect::PostgreSQL | SqlDialect::Snowflake => format!("\"{}\"", name.replace('"', "\"\"")),
+            SqlDialect::SqlServer => format!("[{}]", name.replace(']', "]]")),
+            SqlDialect::BigQuery => format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`")),
+        }
+    }
+
+    /// An identifier from a query, safe to embed in this dialect's SQL
+    pub fn identifier(&self, identifier: &str) -> String {
+        QuotedIdentifier::parse(identifier).to_sql(*self)
+    }
+
+    pub fn boolean_literal(&self, value: bool) -> &'static str {
+        match (self, value) {
+            // SQL Server has no boolean literals; BIT columns compare against 1 and 0
+            (SqlDialect::SqlServer, true) => "1",
+            (SqlDialect::SqlServer, false) => "0",
+            (_, true) => "true",
+            (_, false) => "false",
+        }
+    }
+
+    pub fn string_literal(&self, value: &str) -> String {
+        match self {
+            SqlDialect::PostgreSQL | SqlDialect::SqlServer => format!("'{}'", value.replace('\'', "''")),
+            // Backslashes escape in BigQuery and Snowflake strings, and BigQuery has no `''`
+            SqlDialect::BigQuery => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+            SqlDialect::Snowflake => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''")),
+        }
+    }
+
+    /// Format a predicate value as a literal
+    pub fn predicate_value(&self, value: &PredicateValue) -> NirvResult<String> {
+        match value {
+            PredicateValue::String(s) => Ok(self.string_literal(s)),
+            PredicateValue::Number(n) => Ok(n.to_string()),
+            PredicateValue::Integer(i) => Ok(i.to_string()),
+            PredicateValue::Boolean(b) => Ok(self.boolean_literal(*b).to_string()),
+            PredicateValue::Null => Ok("NULL".to_string()),
+            PredicateValue::List(_) => Err(ConnectorError::QueryExecutionFailed(
+                "List values should be handled by IN operator".to_string()
+            ).into()),
+        }
+    }
+
+    /// Placeholder of the parameter at a zero-based position: `$1` in PostgreSQL, `@P1` in
+    /// SQL Server, and `?` for the positional parameters of BigQuery and Snowflake
+    fn placeholder(&self, index: usize) -> String {
+        match self {
+            SqlDialect::PostgreSQL => format!("${}", index + 1),
+            SqlDialect::SqlServer => format!("@P{}", index + 1),
+            SqlDialect::BigQuery | SqlDialect::Snowflake => "?".to_string(),
+        }
+    }
+
+    /// A JSON function as a text-valued expression
+    fn json_extract(&self, extract: &JsonExtract) -> String {
+        let extract = JsonExtract::new(self.identifier(&extract.column), extract.path.clone());
+        match self {
+            SqlDialect::PostgreSQL => extract.to_postgres_sql(),
+            SqlDialect::SqlServer => extract.to_sqlserver_sql(),
+            SqlDialect::BigQuery => extract.to_bigquery_sql(),
+            SqlDialect::Snowflake => extract.to_snowflake_sql(),
+        }
+    }
+
+    /// A JSON function compared against a value. JSON functions yield text, so they are
+    /// cast to match numeric and boolean comparisons.
+    fn json_comparison(&self, extract: &JsonExtract, value: &PredicateValue) -> String {
+        let sample = match value {
+            PredicateValue::List(values) => values.first(),
+            value => Some(value),
+        };
+        let text = self.json_extract(extract);
+        match (self, sample) {
+            (SqlDialect::PostgreSQL, Some(PredicateValue::Integer(_) | PredicateValue::Number(_))) => format!("({})::numeric", text),
+            (SqlDialect::PostgreSQL, Some(PredicateValue::Boolean(_))) => format!("({})::boolean", text),
+            (SqlDialect::SqlServer, Some(PredicateValue::Integer(_) | PredicateValue::Number(_))) => format!("CAST({} AS float)", text),
+            (SqlDialect::SqlServer, Some(PredicateValue::Boolean(_))) => format!("CAST({} AS bit)", text),
+            (SqlDialect::BigQuery, Some(PredicateValue::Integer(_) | PredicateValue::Number(_))) => format!("CAST({} AS FLOAT64)", text),
+            (SqlDialect::BigQuery, Some(PredicateValue::Boolean(_))) => format!("CAST({} AS BOOL)", text),
+            (SqlDialect::Snowflake, Some(PredicateValue::Integer(_) | PredicateValue::Number(_))) => format!("CAST({} AS FLOAT)", text),
+            (SqlDialect::Snowflake, Some(PredicateValue::Boolean(_))) => format!("CAST({} AS BOOLEAN)", text),
+            _ => text,
+        }
+    }
+
+    /// Build SQL for a single predicate, with values written as literals
+    pub fn predicate(&self, predicate: &Predicate) -> NirvResult<String> {
+        let mut builder = SqlBuilder::new(*self);
+        self.write_predicate(&mut builder, predicate)?;
+        Ok(builder.finish().to_literal_sql())
+    }
+
+    fn write_predicate(&self, builder: &mut SqlBuilder, predicate: &Predicate) -> NirvResult<()> {
+        let operator_sql = match predicate.operator {
+            PredicateOperator::Equal => "=",
+            PredicateOperator::NotEqual => "!=",
+            PredicateOperator::GreaterThan => ">",
+            PredicateOperator::GreaterThanOrEqual => ">=",
+            PredicateOperator::LessThan => "<",
+            PredicateOperator::LessThanOrEqual => "<=",
+            PredicateOperator::Like => "LIKE",
+            PredicateOperator::Regexp => match self {
+                SqlDialect::PostgreSQL => "~",
+                dialect => return Err(ConnectorError::UnsupportedOperation(
+                    format!("{} has no regular expression operator", dialect.name())
+                ).into()),
+            },
+            PredicateOperator::IsNull => "IS NULL",
+            PredicateOperator::IsNotNull => "IS NOT NULL",
+            PredicateOperator::In => "IN",
+        };
+
+        match JsonExtract::parse(&predicate.column) {
+            Some(extract) => builder.push(&self.json_comparison(&extract, &predicate.value)),
+            None => builder.push_identifier(&predicate.column),
+        };
+
+        match predicate.operator {
+            PredicateOperator::IsNull | PredicateOperator::IsNotNull => {
+                builder.push(" ").push(operator_sql);
+            }
+            PredicateOperator::In => {
+                let PredicateValue::List(values) = &predicate.value else {
+                    return Err(ConnectorError::QueryExecutionFailed(
+                        "IN operator requires a list of values".to_string()
+                    ).into());
+                };
+                builder.push(" IN (");
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        builder.push(", ");
+                    }
+                    builder.push_value(value)?;
+                }
+                builder.push(")");
+            }
+            _ => {
+                builder.push(" ").push(operator_sql).push(" ");
+                builder.push_value(&predicate.value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_projection(&self, builder: &mut SqlBuilder, column: &Column) {
+        if let Some(extract) = JsonExtract::parse(&column.name) {
+            // Keep the expression text as the result column name
+            let name = column.alias.clone().unwrap_or_else(|| column.name.clone());
+            builder.push(&self.json_extract(&extract)).push(" AS ").push(&self.quote_identifier(&name));
+            return;
+        }
+        if column.name == COUNT_ALL {
+            builder.push("COUNT(*) AS ");
+            match &column.alias {
+                Some(alias) => builder.push_identifier(alias),
+                None => builder.push(&self.quote_identifier("count")),
+            };
+            return;
+        }
+        if column.name == "*" {
+            builder.push("*");
+        } else {
+            builder.push_identifier(&column.name);
+        }
+        if let Some(alias) = &column.alias {
+            builder.push(" AS ").push_identifier(alias);
+        }
+    }
+
+    /// Build a single-source `SELECT` with values written as literals
+    pub fn select_sql(&self, query: &InternalQuery) -> NirvResult<String> {
+        Ok(self.select_statement(query)?.to_literal_sql())
+    }
+
+    /// Build a single-source `SELECT`, pushing down predicates, ordering and the row limit,
+    /// with predicate values bound as parameters. The limit is `LIMIT n` but in SQL Server,
+    /// which uses `TOP n`, or `OFFSET 0 ROWS FETCH NEXT n ROWS ONLY` after an `ORDER BY`.
+    pub fn select_statement(&self, query: &InternalQuery) -> NirvResult<SqlStatement> {
+        if query.operation != QueryOperation::Select {
+            return Err(ConnectorError::UnsupportedOperation(
+                format!("Operation {:?} not supported by {} connector", query.operation, self.name())
+            ).into());
+        }
+        let source = query.sources.first().ok_or_else(|| ConnectorError::QueryExecutionFailed(
+            "No data source specified in query".to_string()
+        ))?;
+
+        let mut builder = SqlBuilder::new(*self);
+        builder.push("SELECT ");
+        let fetch_after_order = *self == SqlDialect::SqlServer && query.ordering.is_some();
+        if let (SqlDialect::SqlServer, Some(limit), false) = (self, query.limit, fetch_after_order) {
+            builder.push(&format!("TOP {} ", limit));
+        }
+
+        if query.projections.is_empty() {
+            builder.push("*");
+        }
+        for (i, column) in query.projections.iter().enumerate() {
+            if i > 0 {
+                builder.push(", ");
+            }
+            self.write_projection(&mut builder, column);
+        }
+
+        builder.push(" FROM ").push_identifier(&source.identifier);
+        if let Some(alias) = &source.alias {
+            builder.push(" AS ").push_identifier(alias);
+        }
+        if let (SqlDialect::PostgreSQL, Some(Sample::Percent(percent))) = (self, &query.sample) {
+            builder.push(&format!(" TABLESAMPLE BERNOULLI ({})", percent));
+        }
+
+        for (i, predicate) in query.predicates.iter().enumerate() {
+            builder.push(if i == 0 { " WHERE " } else { " AND " });
+            self.write_predicate(&mut builder, predicate)?;
+        }
+
+        if let Some(order_by) = &query.ordering {
+            builder.push(" ORDER BY ");
+            for (i, col) in order_by.columns.iter().enumerate() {
+                if i > 0 {
+                    builder.push(", ");
+                }
+                let direction = match col.direction {
+                    OrderDirection::Ascending => " ASC",
+                    OrderDirection::Descending => " DESC",
+                };
+                match (self, col.nulls) {
+                    (SqlDialect::PostgreSQL | SqlDialect::BigQuery | SqlDialect::Snowflake, Some(nulls)) => {
+                        let nulls = if nulls == NullsOrder::First { " NULLS FIRST" } else { " NULLS LAST" };
+                        builder.push_identifier(&col.column).push(direction).push(nulls);
+                    }
+                    // SQL Server has no NULLS FIRST or LAST, so NULLs are ordered by a leading key
+                    (SqlDialect::SqlServer, Some(nulls)) => {
+                        let null_rank = if nulls == NullsOrder::First { " IS NULL THEN 0 ELSE 1 END, " } else { " IS NULL THEN 1 ELSE 0 END, " };
+                        builder.push("CASE WHEN ").push_identifier(&col.column).push(null_rank);
+                        builder.push_identifier(&col.column).push(direction);
+                    }
+                    (_, None) => {
+                        builder.push_identifier(&col.column).push(direction);
+                    }
+                }
+            }
+        }
+
+        match (self, query.limit) {
+            (SqlDialect::SqlServer, Some(limit)) if fetch_after_order => {
+                builder.push(&format!(" OFFSET 0 ROWS FETCH NEXT {} ROWS ONLY", limit));
+            }
+            (SqlDialect::SqlServer, _) => {}
+            (_, Some(limit)) => {
+                builder.push(&format!(" LIMIT {}", limit));
+            }
+            _ => {}
+        }
+
+        Ok(builder.finish())
+    }
+
+    /// Build the statement for a query: its `SELECT`, or the `INSERT`, `UPDATE` or `DELETE`
+    /// it makes
+    pub fn statement(&self, query: &InternalQuery) -> NirvResult<SqlStatement> {
+        match query.operation {
+            QueryOperation::Select => self.select_statement(query),
+            _ => self.write_statement(query),
+        }
+    }
+
+    /// Build the `INSERT`, `UPDATE` or `DELETE` of a single source, with values bound as
+    /// parameters. Every predicate is written, since rows must never be changed on the
+    /// strength of a filter applied afterwards.
+    pub fn write_statement(&self, query: &InternalQuery) -> NirvResult<SqlStatement> {
+        let source = query.sources.first().ok_or_else(|| ConnectorError::QueryExecutionFailed(
+            "No data source specified in query".to_string()
+        ))?;
+
+        let mut builder = SqlBuilder::new(*self);
+        match query.operation {
+            QueryOperation::Select => return self.select_statement(query),
+            QueryOperation::Insert => {
+                if query.values.is_empty() {
+                    return Err(ConnectorError::QueryExecutionFailed("INSERT without rows".to_string()).into());
+                }
+                builder.push("INSERT INTO ").push_identifier(&source.identifier);
+                if !query.projections.is_empty() {
+                    builder.push(" (");
+                    for (i, column) in query.projections.iter().enumerate() {
+                        if i > 0 {
+                            builder.push(", ");
+                        }
+                        builder.push_identifier(&column.name);
+                    }
+                    builder.push(")");
+                }
+                builder.push(" VALUES ");
+                for (i, row) in query.values.iter().enumerate() {
+                    builder.push(if i == 0 { "(" } else { ", (" });
+                    for (j, value) in row.iter().enumerate() {
+                        if j > 0 {
+                            builder.push(", ");
+                        }
+                        builder.push_value(value)?;
+                    }
+                    builder.push(")");
+                }
+                return Ok(builder.finish());
+            }
+            QueryOperation::Update => {
+                if query.assignments.is_empty() {
+                    return Err(ConnectorError::QueryExecutionFailed("UPDATE without SET".to_string()).into());
+                }
+                builder.push("UPDATE ").push_identifier(&source.identifier).push(" SET ");
+                for (i, assignment) in query.assignments.iter().enumerate() {
+                    if i > 0 {
+                        builder.push(", ");
+                    }
+                    builder.push_identifier(&assignment.column).push(" = ");
+                    builder.push_value(&assignment.value)?;
+                }
+            }
+            QueryOperation::Delete => {
+                builder.push("DELETE FROM ").push_identifier(&source.identifier);
+            }
+        }
+
+        for (i, predicate) in query.predicates.iter().enumerate() {
+            builder.push(if i == 0 { " WHERE " } else { " AND " });
+            self.write_predicate(&mut builder, predicate)?;
+        }
+        Ok(builder.finish())
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SqlDialect::PostgreSQL => "PostgreSQL",
+            SqlDialect::SqlServer => "SQL Server",
+            SqlDialect::BigQuery => "BigQuery",
+            SqlDialect::Snowflake => "Snowflake",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum IdentifierPart {
+    /// A word written as is, so the database applies its usual case rules
+    Plain(String),
+    /// A name matched exactly, written quoted
+    Exact(String),
+}
+
+/// A possibly qualified identifier taken from a query. Parts that are plain words stay
+/// unquoted; quoted parts (`"Name"` or `[Name]`) and any other text are quoted, so no
+/// name can end the identifier and run on as SQL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotedIdentifier {
+    parts: Vec<IdentifierPart>,
+}
+
+impl QuotedIdentifier {
+    pub fn parse(identifier: &str) -> Self {
+        let parts = split_parts(identifier).into_iter()
+            .map(|part| {
+                if let Some(exact) = unquote_identifier(part) {
+                    IdentifierPart::Exact(exact)
+                } else if let Some(inner) = part.strip_prefix('[').and_then(|part| part.strip_suffix(']')) {
+                    IdentifierPart::Exact(inner.replace("]]", "]"))
+                } else if is_plain_word(part) {
+                    IdentifierPart::Plain(part.to_string())
+                } else {
+                    IdentifierPart::Exact(part.to_string())
+                }
+            })
+            .collect();
+        Self { parts }
+    }
+
+    pub fn to_sql(&self, dialect: SqlDialect) -> String {
+        self.parts.iter()
+            .map(|part| match part {
+                IdentifierPart::Plain(word) => word.clone(),
+                IdentifierPart::Exact(name) => dialect.quote_identifier(name),
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+/// Split an identifier at dots outside `"..."` and `[...]` quoting
+fn split_parts(identifier: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut closing = None;
+    let mut start = 0;
+    for (i, c) in identifier.char_indices() {
+        match (closing, c) {
+            (None, '"') => closing = Some('"'),
+            (None, '[') => closing = Some(']'),
+            (None, '.') => {
+                parts.push(&identifier[start..i]);
+                start = i + 1;
+            }
+            (Some(end), c) if c == end => closing = None,
+            _ => {}
+        }
+    }
+    parts.push(&identifier[start..]);
+    parts
+}
+
+fn is_plain_word(word: &str) -> bool {
+    let mut chars = word.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SqlPart {
+    Text(String),
+    Param(usize),
+}
+
+/// A SQL statement in a given dialect with its values kept apart as parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlStatement {
+    dialect: SqlDialect,
+    parts: Vec<SqlPart>,
+    params: Vec<PredicateValue>,
+}
+
+impl SqlStatement {
+    /// The statement text with numbered placeholders for its parameters
+    pub fn sql(&self) -> String {
+        self.render(|_| false)
+    }
+
+    /// Values bound to the placeholders, in order
+    pub fn params(&self) -> &[PredicateValue] {
+        &self.params
+    }
+
+    /// The statement text with every value written as a literal
+    pub fn to_literal_sql(&self) -> String {
+        self.render(|_| true)
+    }
+
+    /// This statement with the chosen parameters written as literals instead, for values
+    /// a driver cannot bind to the type the database expects
+    pub fn with_literals(&self, inline: impl Fn(usize) -> bool) -> Self {
+        let mut builder = SqlBuilder::new(self.dialect);
+        for part in &self.parts {
+            match part {
+                SqlPart::Text(text) => {
+                    builder.push(text);
+                }
+                SqlPart::Param(index) if inline(*index) => {
+                    builder.push(&self.literal(*index));
+                }
+                SqlPart::Param(index) => {
+                    builder.bind(self.params[*index].clone());
+                }
+            }
+        }
+        builder.finish()
+    }
+
+    fn render(&self, inline: impl Fn(usize) -> bool) -> String {
+        let mut sql = String::new();
+        let mut next_placeholder = 0;
+        for part in &self.parts {
+            match part {
+                SqlPart::Text(text) => sql.push_str(text),
+                SqlPart::Param(index) if inline(*index) => sql.push_str(&self.literal(*index)),
+                SqlPart::Param(_) => {
+                    sql.push_str(&self.dialect.placeholder(next_placeholder));
+                    next_placeholder += 1;
+                }
+            }
+        }
+        sql
+    }
+
+    fn literal(&self, index: usize) -> String {
+        // Parameters only ever hold scalar values
+        self.dialect.predicate_value(&self.params[index]).unwrap_or_else(|_| "NULL".to_string())
+    }
+}
+
+/// Builds a `SqlStatement` from trusted SQL text, identifiers and bound values
+#[derive(Debug)]
+pub struct SqlBuilder {
+    dialect: SqlDialect,
+    parts: Vec<SqlPart>,
+    params: Vec<PredicateValue>,
+}
+
+impl SqlBuilder {
+    pub fn new(dialect: SqlDialect) -> Self {
+        Self { dialect, parts: Vec::new(), params: Vec::new() }
+    }
+
+    /// Append SQL text as is; it must not contain anything taken from a query
+    pub fn push(&mut self, sql: &str) -> &mut Self {
+        match self.parts.last_mut() {
+            Some(SqlPart::Text(text)) => text.push_str(sql),
+            _ => self.parts.push(SqlPart::Text(sql.to_string())),
+        }
+        self
+    }
+
+    /// Append an identifier, quoted as needed
+    pub fn push_identifier(&mut self, identifier: &str) -> &mut Self {
+        let sql = self.dialect.identifier(identifier);
+        self.push(&sql)
+    }
+
+    /// Append a value as a parameter. NULL is written as is, since it binds to no type.
+    pub fn push_value(&mut self, value: &PredicateValue) -> NirvResult<&mut Self> {
+        match value {
+            PredicateValue::Null => Ok(self.push("NULL")),
+            PredicateValue::List(_) => Err(ConnectorError::QueryExecutionFailed(
+                "List values should be handled by IN operator".to_string()
+            ).into()),
+            value => Ok(self.bind(value.clone())),
+        }
+    }
+
+    fn bind(&mut self, value: PredicateValue) -> &mut Self {
+        self.parts.push(SqlPart::Param(self.params.len()));
+        self.params.push(value);
+        self
+    }
+
+    pub fn finish(self) -> SqlStatement {
+        SqlStatement { dialect: self.dialect, parts: self.parts, params: self.params }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{Assignment, DataSource, OrderBy, OrderColumn};
+
+    fn query() -> InternalQuery {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "db".to_string(), identifier: "users".to_string(), alias: Some("u".to_string()) });
+        query.projections.push(Column { name: "u.\"Full Name\"".to_string(), alias: None, source: None });
+        query.predicates.push(Predicate {
+            column: "active".to_string(),
+            operator: PredicateOperator::Equal,
+            value: PredicateValue::Boolean(true),
+        });
+        query.limit = Some(10);
+        query
+    }
+
+    #[test]
+    fn test_limit_and_literals_per_dialect() {
+        let mut query = query();
+        assert_eq!(
+            SqlDialect::PostgreSQL.select_sql(&query).unwrap(),
+            "SELECT u.\"Full Name\" FROM users AS u WHERE active = true LIMIT 10"
+        );
+        assert_eq!(
+            SqlDialect::SqlServer.select_sql(&query).unwrap(),
+            "SELECT TOP 10 u.[Full Name] FROM users AS u WHERE active = 1"
+        );
+
+        query.ordering = Some(OrderBy { columns: vec![OrderColumn { column: "\"Age\"".to_string(), direction: OrderDirection::Descending, nulls: None }] });
+        assert_eq!(
+            SqlDialect::SqlServer.select_sql(&query).unwrap(),
+            "SELECT u.[Full Name] FROM users AS u WHERE active = 1 ORDER BY [Age] DESC OFFSET 0 ROWS FETCH NEXT 10 ROWS ONLY"
+        );
+        assert_eq!(SqlDialect::SqlServer.quote_identifier("odd]name"), "[odd]]name]");
+    }
+
+    #[test]
+    fn test_count_all_per_dialect() {
+        let mut query = query();
+        query.projections = vec![Column { name: COUNT_ALL.to_string(), alias: None, source: None }];
+        query.limit = None;
+        assert_eq!(
+            SqlDialect::PostgreSQL.select_sql(&query).unwrap(),
+            "SELECT COUNT(*) AS \"count\" FROM users AS u WHERE active = true"
+        );
+        query.projections[0].alias = Some("total".to_string());
+        assert_eq!(
+            SqlDialect::SqlServer.select_sql(&query).unwrap(),
+            "SELECT COUNT(*) AS total FROM users AS u WHERE active = 1"
+        );
+    }
+
+    #[test]
+    fn test_writes_per_dialect() {
+        let mut insert = InternalQuery::new(QueryOperation::Insert);
+        insert.sources.push(DataSource { object_type: "db".to_string(), identifier: "accounts".to_string(), alias: None });
+        insert.projections = ["id", "\"Owner\""].iter()
+            .map(|name| Column { name: name.to_string(), alias: None, source: None })
+            .collect();
+        insert.values = vec![
+            vec![PredicateValue::Integer(1), PredicateValue::String("ann".to_string())],
+            vec![PredicateValue::Integer(2), PredicateValue::Null],
+        ];
+        let statement = SqlDialect::PostgreSQL.statement(&insert).unwrap();
+        assert_eq!(statement.sql(), "INSERT INTO accounts (id, \"Owner\") VALUES ($1, $2), ($3, NULL)");
+        assert_eq!(statement.params().len(), 3);
+
+        let mut update = query();
+        update.operation = QueryOperation::Update;
+        update.assignments.push(Assignment { column: "balance".to_string(), value: PredicateValue::Number(-5.5) });
+        assert_eq!(
+            SqlDialect::SqlServer.statement(&update).unwrap().to_literal_sql(),
+            "UPDATE users SET balance = -5.5 WHERE active = 1"
+        );
+
+        let mut delete = query();
+        delete.operation = QueryOperation::Delete;
+        assert_eq!(SqlDialect::PostgreSQL.statement(&delete).unwrap().sql(), "DELETE FROM users WHERE active = $1");
+        delete.operation = QueryOperation::Insert;
+        assert!(SqlDialect::PostgreSQL.statement(&delete).is_err());
+        assert!(SqlDialect::PostgreSQL.select_statement(&update).is_err());
+    }
+
+    #[test]
+    fn test_nulls_ordering_per_dialect() {
+        let mut query = query();
+        query.limit = None;
+        query.ordering = Some(OrderBy { columns: vec![
+            OrderColumn { column: "score".to_string(), direction: OrderDirection::Descending, nulls: Some(NullsOrder::Last) },
+            OrderColumn { column: "id".to_string(), direction: OrderDirection::Ascending, nulls: None },
+        ] });
+        assert_eq!(
+            SqlDialect::PostgreSQL.select_sql(&query).unwrap(),
+            "SELECT u.\"Full Name\" FROM users AS u WHERE active = true ORDER BY score DESC NULLS LAST, id ASC"
+        );
+        assert_eq!(
+            SqlDialect::SqlServer.select_sql(&query).unwrap(),
+            "SELECT u.[Full Name] FROM users AS u WHERE active = 1 ORDER BY CASE WHEN score IS NULL THEN 1 ELSE 0 END, score DESC, id ASC"
+        );
+    }
+
+    #[test]
+    fn test_json_functions_per_dialect() {
+        let predicate = Predicate {
+            column: "json_extract(payload, '$.user.id')".to_string(),
+            operator: PredicateOperator::GreaterThan,
+            value: PredicateValue::Integer(8),
+        };
+        assert_eq!(SqlDialect::PostgreSQL.predicate(&predicate).unwrap(), "((payload::jsonb)->'user'->>'id')::numeric > 8");
+        assert_eq!(SqlDialect::SqlServer.predicate(&predicate).unwrap(), "CAST(JSON_VALUE(payload, '$.user.id') AS float) > 8");
+    }
+
+    #[test]
+    fn test_warehouse_dialects() {
+        let mut query = query();
+        query.predicates.push(Predicate {
+            column: "note".to_string(),
+            operator: PredicateOperator::Equal,
+            value: PredicateValue::String("it's \\n".to_string()),
+        });
+        query.ordering = Some(OrderBy { columns: vec![OrderColumn { column: "score".to_string(), direction: OrderDirection::Descending, nulls: Some(NullsOrder::Last) }] });
+        assert_eq!(
+            SqlDialect::BigQuery.select_statement(&query).unwrap().sql(),
+            "SELECT u.`Full Name` FROM users AS u WHERE active = ? AND note = ? ORDER BY score DESC NULLS LAST LIMIT 10"
+        );
+        assert_eq!(
+            SqlDialect::BigQuery.select_sql(&query).unwrap(),
+            "SELECT u.`Full Name` FROM users AS u WHERE active = true AND note = 'it\\'s \\\\n' ORDER BY score DESC NULLS LAST LIMIT 10"
+        );
+        assert_eq!(
+            SqlDialect::Snowflake.select_sql(&query).unwrap(),
+            "SELECT u.\"Full Name\" FROM users AS u WHERE active = true AND note = 'it''s \\\\n' ORDER BY score DESC NULLS LAST LIMIT 10"
+        );
+        assert_eq!(SqlDialect::BigQuery.identifier("my-project.sales.orders"), "`my-project`.sales.orders");
+
+        let predicate = Predicate {
+            column: "json_extract(payload, '$.user.id')".to_string(),
+            operator: PredicateOperator::Equal,
+            value: PredicateValue::Integer(8),
+        };
+        assert_eq!(SqlDialect::BigQuery.predicate(&predicate).unwrap(), "CAST(JSON_VALUE(payload, '$.user.id') AS FLOAT64) = 8");
+        assert_eq!(SqlDialect::Snowflake.predicate(&predicate).unwrap(), "CAST(JSON_EXTRACT_PATH_TEXT(payload, 'user.id') AS FLOAT) = 8");
+    }
+
+    #[test]
+    fn test_regexp_predicate_per_dialect() {
+        let predicate = Predicate {
+            column: "message".to_string(),
+            operator: PredicateOperator::Regexp,
+            value: PredicateValue::String("^ERROR .*timeout".to_string()),
+        };
+        assert_eq!(SqlDialect::PostgreSQL.predicate(&predicate).unwrap(), "message ~ '^ERROR .*timeout'");
+        assert!(SqlDialect::SqlServer.predicate(&predicate).is_err());
+    }
+
+    #[test]
+    fn test_identifiers_are_quoted_and_values_bound() {
+        let identifier = QuotedIdentifier::parse("dbo.\"Order Lines\".[x]]y]");
+        assert_eq!(identifier.to_sql(SqlDialect::SqlServer), "dbo.[Order Lines].[x]]y]");
+        assert_eq!(identifier.to_sql(SqlDialect::PostgreSQL), "dbo.\"Order Lines\".\"x]y\"");
+        assert_eq!(SqlDialect::PostgreSQL.identifier("id; DROP TABLE users"), "\"id; DROP TABLE users\"");
+
+        let mut query = query();
+        query.predicates.push(Predicate {
+            column: "name".to_string(),
+            operator: PredicateOperator::In,
+            value: PredicateValue::List(vec![PredicateValue::String("a' OR '1'='1".to_string()), PredicateValue::Integer(2)]),
+        });
+        let statement = SqlDialect::PostgreSQL.select_statement(&query).unwrap();
+        assert_eq!(statement.sql(), "SELECT u.\"Full Name\" FROM users AS u WHERE active = $1 AND name IN ($2, $3) LIMIT 10");
+        assert_eq!(statement.params().len(), 3);
+        assert_eq!(
+            SqlDialect::SqlServer.select_statement(&query).unwrap().sql(),
+            "SELECT TOP 10 u.[Full Name] FROM users AS u WHERE active = @P1 AND name IN (@P2, @P3)"
+        );
+
+        let partly_bound = statement.with_literals(|index| index == 1);
+        assert_eq!(partly_bound.sql(), "SELECT u.\"Full Name\" FROM users AS u WHERE active = $1 AND name IN ('a'' OR ''1''=''1', $2) LIMIT 10");
+        assert_eq!(partly_bound.params(), &[PredicateValue::Boolean(true), PredicateValue::Integer(2)]);
+    }
+}
@@ -0,0 +1,223 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use std::fmt;
+
+use crate::utils::{
+    error::{NirvResult, QueryParsingError},
+    types::{QueryResult, Value},
+};
+
+/// Time zone a session renders DateTime values in and truncates dates in, or a source's
+/// naive DateTime values are read in. Only UTC, fixed offsets and the engine host's own zone
+/// are known: named zones would need a time zone database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionTimeZone {
+    #[default]
+    Utc,
+    Fixed(FixedOffset),
+    /// The time zone of the machine running the engine, with its daylight saving rules
+    Local,
+}
+
+impl SessionTimeZone {
+    /// Parse `UTC`, `LOCAL` or an offset such as `+02:00`, `-0530`, `+2` or `UTC+01:00`
+    pub fn parse(name: &str) -> NirvResult<Self> {
+        let upper = name.trim().to_uppercase();
+        match upper.as_str() {
+            "UTC" | "GMT" | "Z" | "ZULU" | "ETC/UTC" | "ETC/GMT" => return Ok(SessionTimeZone::Utc),
+            "LOCAL" => return Ok(SessionTimeZone::Local),
+            _ => {}
+        }
+        let offset = upper.strip_prefix("UTC").or_else(|| upper.strip_prefix("GMT")).unwrap_or(&upper);
+        parse_offset(offset).map(SessionTimeZone::Fixed).ok_or_else(|| QueryParsingError::InvalidSyntax(format!(
+            "Unknown time zone '{}': expected UTC, LOCAL or an offset such as +02:00", name.trim()
+        )).into())
+    }
+
+    /// Offset from UTC at a UTC instant
+    pub fn offset_at(&self, utc: &NaiveDateTime) -> FixedOffset {
+        match self {
+            SessionTimeZone::Utc => FixedOffset::east_opt(0).unwrap(),
+            SessionTimeZone::Fixed(offset) => *offset,
+            SessionTimeZone::Local => Local.offset_from_utc_datetime(utc).fix(),
+        }
+    }
+
+    /// A UTC instant as a time in this zone
+    pub fn from_utc(&self, utc: NaiveDateTime) -> DateTime<FixedOffset> {
+        self.offset_at(&utc).from_utc_datetime(&utc)
+    }
+
+    /// The UTC instant of a wall clock time in this zone. Times repeated when clocks go back
+    /// take the earlier instant; times skipped when they go forward take the offset before.
+    pub fn to_utc(&self, local: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            SessionTimeZone::Local => match Local.from_local_datetime(&local).earliest() {
+                Some(time) => time.naive_utc(),
+                None => local - self.offset_at(&local),
+            },
+            zone => local - zone.offset_at(&local),
+        }
+    }
+
+    /// Convert a DateTime value read from a source in this zone to UTC. Values carrying an
+    /// offset are converted whatever the zone; naive UTC values are returned unchanged.
+    pub fn normalize(&self, value: Value) -> Value {
+        let Value::DateTime(text) = &value else {
+            return value;
+        };
+        match parse_date_time(text) {
+            Some((time, Some(offset))) => Value::DateTime(format_utc(time - offset)),
+            Some((time, None)) if *self != SessionTimeZone::Utc => Value::DateTime(format_utc(self.to_utc(time))),
+            _ => value,
+        }
+    }
+
+    /// Normalize every DateTime value of a source's result to UTC
+    pub fn normalize_result(&self, mut result: QueryResult) -> QueryResult {
+        for row in &mut result.rows {
+            for value in &mut row.values {
+                if matches!(value, Value::DateTime(_)) {
+                    *value = self.normalize(std::mem::replace(value, Value::Null));
+                }
+            }
+        }
+        result
+    }
+
+    /// Render a UTC DateTime value as a time in this zone, with its offset
+    pub fn render(&self, value: Value) -> Value {
+        let Value::DateTime(text) = &value else {
+            return value;
+        };
+        match parse_date_time(text) {
+            Some((time, offset)) => {
+                let utc = offset.map_or(time, |offset| time - offset);
+                Value::DateTime(self.from_utc(utc).format("%Y-%m-%d %H:%M:%S%.f%:z").to_string())
+            }
+            None => value,
+        }
+    }
+
+    /// Render every DateTime value of a result in this zone
+    pub fn render_result(&self, mut result: QueryResult) -> QueryResult {
+        for row in &mut result.rows {
+            for value in &mut row.values {
+                if matches!(value, Value::DateTime(_)) {
+                    *value = self.render(std::mem::replace(value, Value::Null));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Display for SessionTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionTimeZone::Utc => write!(f, "UTC"),
+            SessionTimeZone::Fixed(offset) => write!(f, "{}", offset),
+            SessionTimeZone::Local => write!(f, "LOCAL"),
+        }
+    }
+}
+
+/// Parse `+HH`, `+HHMM` or `+HH:MM`, with a sign of `+` or `-`
+fn parse_offset(text: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match text.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if digits.len() > 2 => digits.split_at(digits.len() - 2),
+        None => (digits, "0"),
+    };
+    if hours.is_empty() || !hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if hours > 15 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parse a DateTime value's text, `2024-03-01 10:30:00` or `2024-03-01T10:30:00.5+02:00`,
+/// returning the time written and the offset it carries, if any
+pub fn parse_date_time(text: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+    let text = text.trim();
+    let text = match text.get(10..11) {
+        Some("T") => format!("{} {}", &text[..10], &text[11..]),
+        _ => text.to_string(),
+    };
+    let text = match text.strip_suffix('Z') {
+        Some(rest) => format!("{}+00:00", rest),
+        None => text,
+    };
+    if let Ok(time) = DateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f%#z") {
+        return Some((time.naive_local(), Some(*time.offset())));
+    }
+    NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M"))
+        .or_else(|_| NaiveDate::parse_from_str(&text, "%Y-%m-%d").map(|date| date.and_time(Default::default())))
+        .ok()
+        .map(|time| (time, None))
+}
+
+/// Order two times written as text by the instants they denote, so offsets and `T`
+/// separators do not matter; text that is not a time compares as text
+pub fn compare_date_times(a: &str, b: &str) -> std::cmp::Ordering {
+    let instant = |text: &str| parse_date_time(text).map(|(time, offset)| offset.map_or(time, |offset| time - offset));
+    match (instant(a), instant(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Text of a UTC time as the engine holds it internally: `2024-03-01 08:30:00`, with
+/// fractional seconds only when there are any
+pub fn format_utc(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%d %H:%M:%S%.f").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(text: &str) -> Value {
+        Value::DateTime(text.to_string())
+    }
+
+    #[test]
+    fn test_parse_time_zones() {
+        let hours = |h: i32| SessionTimeZone::Fixed(FixedOffset::east_opt(h * 3600).unwrap());
+        assert_eq!(SessionTimeZone::parse("utc").unwrap(), SessionTimeZone::Utc);
+        assert_eq!(SessionTimeZone::parse("Etc/UTC").unwrap(), SessionTimeZone::Utc);
+        assert_eq!(SessionTimeZone::parse("local").unwrap(), SessionTimeZone::Local);
+        assert_eq!(SessionTimeZone::parse("+02:00").unwrap(), hours(2));
+        assert_eq!(SessionTimeZone::parse("-8").unwrap(), hours(-8));
+        assert_eq!(SessionTimeZone::parse("UTC+0530").unwrap().to_string(), "+05:30");
+        assert!(SessionTimeZone::parse("Europe/Paris").is_err());
+        assert!(SessionTimeZone::parse("+25:00").is_err());
+        assert!(SessionTimeZone::parse("+02:75").is_err());
+    }
+
+    #[test]
+    fn test_normalize_and_render() {
+        let paris = SessionTimeZone::parse("+01:00").unwrap();
+        assert_eq!(paris.normalize(datetime("2024-03-01 10:30:00")), datetime("2024-03-01 09:30:00"));
+        assert_eq!(SessionTimeZone::Utc.normalize(datetime("2024-03-01T10:30:00")), datetime("2024-03-01T10:30:00"));
+        assert_eq!(SessionTimeZone::Utc.normalize(datetime("2024-03-01T10:30:00.250-05:00")), datetime("2024-03-01 15:30:00.250"));
+        assert_eq!(paris.normalize(datetime("2024-03-01T23:30:00Z")), datetime("2024-03-01 23:30:00"));
+        assert_eq!(paris.normalize(Value::Text("10:30".to_string())), Value::Text("10:30".to_string()));
+        assert_eq!(paris.normalize(datetime("soon")), datetime("soon"));
+
+        assert_eq!(paris.render(datetime("2024-03-01 23:30:00")), datetime("2024-03-02 00:30:00+01:00"));
+        assert_eq!(SessionTimeZone::Utc.render(datetime("2024-03-01 23:30:00")), datetime("2024-03-01 23:30:00+00:00"));
+        assert_eq!(paris.render(Value::Date("2024-03-01".to_string())), Value::Date("2024-03-01".to_string()));
+
+        assert_eq!(compare_date_times("2024-03-01T10:00:00+02:00", "2024-03-01 08:00:00"), std::cmp::Ordering::Equal);
+        assert_eq!(compare_date_times("2024-03-01", "2024-03-01 00:00:01"), std::cmp::Ordering::Less);
+    }
+}
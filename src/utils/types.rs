@@ -1,6 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::time::Duration;
+use chrono::{DateTime, Utc};
+
+use crate::utils::{
+    collation::Collation,
+    directives::QueryDirectives,
+    error::{NirvResult, QueryParsingError},
+    identifier::IdentifierCase,
+    similarity::SimilarityMetric,
+    time_zone::format_utc,
+};
 
 /// Internal representation of a parsed SQL query
 #[derive(Debug, Clone, PartialEq)]
@@ -10,8 +21,38 @@ pub struct InternalQuery {
     pub projections: Vec<Column>,
     pub predicates: Vec<Predicate>,
     pub joins: Vec<Join>,
+    /// Queries of the derived tables among the sources, which name them by position as
+    /// `subquery.0`, `subquery.1`, ...
+    pub subqueries: Vec<InternalQuery>,
+    /// GROUP BY items as written; the planner resolves them against the projections
+    pub group_by: Vec<GroupByItem>,
     pub ordering: Option<OrderBy>,
     pub limit: Option<u64>,
+    /// Sampling of source rows, applied before ORDER BY and LIMIT
+    pub sample: Option<Sample>,
+    /// Removal of duplicate rows, applied before sampling, ORDER BY and LIMIT
+    pub deduplicate: Option<Deduplicate>,
+    /// Reduce the rows to their order-independent checksum, for `CHECKSUM TABLE source(...)`
+    pub checksum: bool,
+    /// Collation named with `COLLATE`, overriding the sources' own for text comparisons
+    pub collation: Option<Collation>,
+    /// Object the result is written to by `CREATE TABLE source(...) AS SELECT ...`
+    pub target: Option<DataSource>,
+    /// Rows of an INSERT, in the order of the columns named as its projections
+    pub values: Vec<Vec<PredicateValue>>,
+    /// Columns an UPDATE sets, with their new values
+    pub assignments: Vec<Assignment>,
+    /// Planning overrides from a `/*+ ... */` hint comment
+    pub hints: QueryHints,
+    /// Text of the query's other comments, without their `--` or `/* */`
+    pub comments: Vec<String>,
+    /// Settings from the comments starting with `nirv:`
+    pub directives: QueryDirectives,
+    /// Source whose policy max_limit set or lowered the LIMIT
+    pub capped_by: Option<DataSource>,
+    /// User the query runs for, whose roles' privileges the dispatcher checks; None for
+    /// trusted callers such as the CLI
+    pub user: Option<String>,
 }
 
 /// Types of SQL operations supported
@@ -23,32 +64,156 @@ pub enum QueryOperation {
     Delete,
 }
 
+/// Row sampling requested with `SAMPLE n PERCENT`, `SAMPLE n ROWS` or `TABLESAMPLE`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Sample {
+    /// Each row is kept with this probability, in percent
+    Percent(f64),
+    /// A uniform sample of at most this many rows
+    Rows(u64),
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sample::Percent(percent) => write!(f, "SAMPLE {} PERCENT", percent),
+            Sample::Rows(rows) => write!(f, "SAMPLE {} ROWS", rows),
+        }
+    }
+}
+
+/// Object type of derived tables, `(SELECT ...) AS t`, whose queries are kept in
+/// `InternalQuery::subqueries`
+pub const SUBQUERY_OBJECT_TYPE: &str = "subquery";
+
 /// Data source specification in a query
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DataSource {
     pub object_type: String,      // e.g., "postgres", "file", "api"
     pub identifier: String,       // e.g., "users", "data.csv", "endpoint"
     pub alias: Option<String>,
 }
 
-/// Column specification in projections
+/// Location of a data object, written either as a URI (`postgres://analytics/public/users`,
+/// `file:///data/*.csv`, `https://api.example.com/v1/users`) or in the dotted
+/// `type.identifier` form (`postgres.users`)
 #[derive(Debug, Clone, PartialEq)]
+pub struct SourceUri {
+    pub scheme: String,
+    /// Host or database named after `//`; absent in the dotted form
+    pub authority: Option<String>,
+    /// Everything after the authority. File paths keep their leading slash.
+    pub path: String,
+}
+
+impl SourceUri {
+    pub fn parse(spec: &str) -> NirvResult<Self> {
+        let invalid = |reason: &str| QueryParsingError::InvalidSourceFormat(format!("{} in source '{}'", reason, spec));
+
+        let Some((scheme, rest)) = spec.split_once("://") else {
+            // Dotted form; a bare name refers to a table
+            let (scheme, path) = spec.split_once('.').unwrap_or(("table", spec));
+            if scheme.is_empty() || path.is_empty() {
+                return Err(invalid("Missing object type or identifier").into());
+            }
+            return Ok(Self { scheme: scheme.to_string(), authority: None, path: path.to_string() });
+        };
+
+        if scheme.is_empty() || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+            return Err(invalid("Invalid scheme").into());
+        }
+        let scheme = scheme.to_lowercase();
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, ""),
+        };
+        let path = if scheme == "file" { path } else { path.trim_matches('/') };
+        if path.is_empty() && !scheme.starts_with("http") {
+            return Err(invalid("Missing path").into());
+        }
+
+        Ok(Self {
+            scheme,
+            authority: (!authority.is_empty()).then(|| authority.to_string()),
+            path: path.to_string(),
+        })
+    }
+
+    /// Data object type the source is routed by; web URLs are served by the `api` type
+    pub fn object_type(&self) -> &str {
+        match self.scheme.as_str() {
+            "http" | "https" => "api",
+            scheme => scheme,
+        }
+    }
+
+    /// Identifier passed to the connector: the full URL for web sources, the path for
+    /// files, globs and mail folders, and the schema-qualified name (`public.users`) for databases
+    pub fn identifier(&self) -> String {
+        match self.scheme.as_str() {
+            "http" | "https" => self.to_string(),
+            "file" | "fsmeta" | "imap" => self.path.clone(),
+            _ => self.path.replace('/', "."),
+        }
+    }
+
+    pub fn to_data_source(&self, alias: Option<String>) -> DataSource {
+        DataSource {
+            object_type: self.object_type().to_string(),
+            identifier: self.identifier(),
+            alias,
+        }
+    }
+}
+
+impl fmt::Display for SourceUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}", self.scheme, self.authority.as_deref().unwrap_or(""))?;
+        if self.path.is_empty() || self.path.starts_with('/') {
+            write!(f, "{}", self.path)
+        } else {
+            write!(f, "/{}", self.path)
+        }
+    }
+}
+
+/// Projection name of `COUNT(*)`
+pub const COUNT_ALL: &str = "count(*)";
+
+/// Column specification in projections
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Column {
     pub name: String,
     pub alias: Option<String>,
     pub source: Option<String>,   // Source table/object alias
 }
 
-/// WHERE clause predicates
+/// An item of GROUP BY as written
 #[derive(Debug, Clone, PartialEq)]
+pub enum GroupByItem {
+    /// A column, a computed expression in its canonical form, or a SELECT list alias
+    Column(Column),
+    /// A 1-based position in the SELECT list, as in `GROUP BY 1`
+    Position(usize),
+}
+
+/// WHERE clause predicates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Predicate {
     pub column: String,
     pub operator: PredicateOperator,
     pub value: PredicateValue,
 }
 
+/// A column an UPDATE sets, as in `SET balance = 100`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub column: String,
+    pub value: PredicateValue,
+}
+
 /// Predicate operators
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PredicateOperator {
     Equal,
     NotEqual,
@@ -57,13 +222,15 @@ pub enum PredicateOperator {
     LessThan,
     LessThanOrEqual,
     Like,
+    /// Matches a regular expression anywhere in the value
+    Regexp,
     In,
     IsNull,
     IsNotNull,
 }
 
 /// Values in predicates
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PredicateValue {
     String(String),
     Number(f64),
@@ -80,6 +247,8 @@ pub struct Join {
     pub left_source: String,
     pub right_source: String,
     pub on_condition: Vec<JoinCondition>,
+    /// Similarity the join keys must reach, from `ON FUZZY(left, right, threshold)`
+    pub fuzzy_condition: Option<FuzzyCondition>,
 }
 
 /// Types of JOINs
@@ -98,21 +267,295 @@ pub struct JoinCondition {
     pub right_column: String,
 }
 
-/// ORDER BY specification
+/// Join condition pairing rows whose key values are similar rather than equal
 #[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyCondition {
+    pub left_column: String,
+    pub right_column: String,
+    /// Lowest similarity, between 0 and 1, at which rows match
+    pub threshold: f64,
+    pub metric: SimilarityMetric,
+}
+
+impl fmt::Display for FuzzyCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FUZZY({}, {}, {}, '{}')", self.left_column, self.right_column, self.threshold, self.metric)
+    }
+}
+
+/// ORDER BY specification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderBy {
     pub columns: Vec<OrderColumn>,
 }
 
+/// Which row of each set of duplicates `DEDUPLICATE BY` keeps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DedupKeep {
+    #[default]
+    First,
+    Last,
+}
+
+/// Removal of rows repeating the values of key columns, requested with
+/// `DEDUPLICATE BY (col, ...) [KEEP FIRST|LAST [ORDER BY col [ASC|DESC]]]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Deduplicate {
+    pub columns: Vec<String>,
+    pub keep: DedupKeep,
+    /// Column ordering the duplicates; without one, they are ordered as they arrive
+    pub order_by: Option<OrderColumn>,
+}
+
+impl fmt::Display for Deduplicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keep = match self.keep {
+            DedupKeep::First => "FIRST",
+            DedupKeep::Last => "LAST",
+        };
+        write!(f, "Deduplicate BY ({}) KEEP {}", self.columns.join(", "), keep)?;
+        if let Some(order) = &self.order_by {
+            write!(f, " ORDER BY {}", order)?;
+        }
+        Ok(())
+    }
+}
+
+/// Part of a query that `NO_PUSHDOWN` keeps the engine from handing to the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pushdown {
+    Predicates,
+    OrderBy,
+    Limit,
+}
+
+impl Pushdown {
+    const ALL: [Pushdown; 3] = [Pushdown::Predicates, Pushdown::OrderBy, Pushdown::Limit];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "predicates" | "filters" => Some(Pushdown::Predicates),
+            "order_by" | "ordering" => Some(Pushdown::OrderBy),
+            "limit" => Some(Pushdown::Limit),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Pushdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pushdown::Predicates => write!(f, "predicates"),
+            Pushdown::OrderBy => write!(f, "order_by"),
+            Pushdown::Limit => write!(f, "limit"),
+        }
+    }
+}
+
+/// Scheduling class of a query: interactive queries are admitted ahead of batch ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryPriority {
+    #[default]
+    Interactive,
+    Batch,
+}
+
+impl QueryPriority {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "interactive" | "high" => Some(QueryPriority::Interactive),
+            "batch" | "low" => Some(QueryPriority::Batch),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QueryPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryPriority::Interactive => write!(f, "interactive"),
+            QueryPriority::Batch => write!(f, "batch"),
+        }
+    }
+}
+
+/// Overrides of the engine's own decisions, given in a `/*+ ... */` comment after SELECT:
+/// `NO_PUSHDOWN(predicates, order_by, limit)`, `MAX_ROWS(n)`, `CACHE_TTL(60s)`,
+/// `PARTIAL_RESULTS` and `PRIORITY(batch)`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryHints {
+    /// Parts of the query evaluated by the engine even when the source could
+    pub no_pushdown: Vec<Pushdown>,
+    /// Most rows the query returns, whatever its LIMIT
+    pub max_rows: Option<u64>,
+    /// How long a connector may answer from responses it cached
+    pub cache_ttl: Option<Duration>,
+    /// Whether a join answers from the sides that succeeded when another fails
+    pub partial_results: bool,
+    /// Scheduling class, overriding the one of the query's user
+    pub priority: Option<QueryPriority>,
+}
+
+impl QueryHints {
+    /// Parse the hints between `/*+` and `*/`, separated by commas or spaces
+    pub fn parse(text: &str) -> NirvResult<Self> {
+        let invalid = |reason: String| QueryParsingError::InvalidSyntax(format!("Invalid query hint: {}", reason));
+        let mut hints = QueryHints::default();
+        let mut rest = text.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        while !rest.is_empty() {
+            let name_end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            if name_end == 0 {
+                return Err(invalid(format!("unexpected '{}'", rest)).into());
+            }
+            let name = &rest[..name_end];
+            rest = rest[name_end..].trim_start();
+            let arguments = match rest.strip_prefix('(') {
+                Some(inner) => {
+                    let close = inner.find(')').ok_or_else(|| invalid(format!("{} is missing ')'", name)))?;
+                    rest = &inner[close + 1..];
+                    Some(inner[..close].trim())
+                }
+                None => None,
+            };
+
+            match (name.to_ascii_uppercase().as_str(), arguments) {
+                ("NO_PUSHDOWN", None) => hints.no_pushdown = Pushdown::ALL.to_vec(),
+                ("NO_PUSHDOWN", Some(arguments)) => {
+                    for argument in arguments.split(',').map(str::trim) {
+                        let pushdown = Pushdown::parse(argument).ok_or_else(|| invalid(format!(
+                            "NO_PUSHDOWN expects predicates, order_by or limit, not '{}'", argument
+                        )))?;
+                        if !hints.no_pushdown.contains(&pushdown) {
+                            hints.no_pushdown.push(pushdown);
+                        }
+                    }
+                }
+                ("MAX_ROWS", Some(rows)) => {
+                    hints.max_rows = Some(rows.parse().map_err(|_| invalid(format!("MAX_ROWS expects a row count, not '{}'", rows)))?);
+                }
+                ("CACHE_TTL", Some(ttl)) => {
+                    hints.cache_ttl = Some(parse_hint_duration(ttl)
+                        .ok_or_else(|| invalid(format!("CACHE_TTL expects a duration such as 60s, not '{}'", ttl)))?);
+                }
+                ("PARTIAL_RESULTS", None) => hints.partial_results = true,
+                ("PRIORITY", Some(priority)) => {
+                    hints.priority = Some(QueryPriority::parse(priority)
+                        .ok_or_else(|| invalid(format!("PRIORITY expects interactive or batch, not '{}'", priority)))?);
+                }
+                ("MAX_ROWS" | "CACHE_TTL" | "PRIORITY", None) => return Err(invalid(format!("{} needs a value", name)).into()),
+                _ => return Err(invalid(format!("unknown hint '{}'", name)).into()),
+            }
+            rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        }
+        Ok(hints)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == QueryHints::default()
+    }
+
+    /// Whether the engine may hand this part of the query to the source
+    pub fn pushes_down(&self, pushdown: Pushdown) -> bool {
+        !self.no_pushdown.contains(&pushdown)
+    }
+
+    /// These hints with those the query's directives give: `pushdown=off` keeps every part
+    /// of the query from the source, `cache` sets the cache TTL a `CACHE_TTL` hint did not,
+    /// and `priority` the scheduling class a `PRIORITY` hint did not
+    pub fn with_directives(&self, directives: &QueryDirectives) -> NirvResult<Self> {
+        let mut hints = self.clone();
+        if directives.flag("pushdown")? == Some(false) {
+            hints.no_pushdown = Pushdown::ALL.to_vec();
+        }
+        if hints.cache_ttl.is_none() {
+            hints.cache_ttl = directives.cache_ttl()?;
+        }
+        if hints.priority.is_none() {
+            hints.priority = directives.priority()?;
+        }
+        Ok(hints)
+    }
+
+    /// The tighter of a query's LIMIT and `MAX_ROWS`
+    pub fn limit(&self, limit: Option<u64>) -> Option<u64> {
+        match (limit, self.max_rows) {
+            (Some(limit), Some(max_rows)) => Some(limit.min(max_rows)),
+            (limit, max_rows) => limit.or(max_rows),
+        }
+    }
+}
+
+/// A duration written as a number followed by `ms`, `s`, `m` or `h`; seconds without a unit
+pub(crate) fn parse_hint_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let amount: u64 = text[..split].parse().ok()?;
+    match text[split..].trim().to_ascii_lowercase().as_str() {
+        "ms" => Some(Duration::from_millis(amount)),
+        "" | "s" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_secs(amount * 60)),
+        "h" => Some(Duration::from_secs(amount * 3600)),
+        _ => None,
+    }
+}
+
+impl fmt::Display for QueryHints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hints = Vec::new();
+        if !self.no_pushdown.is_empty() {
+            let parts: Vec<String> = self.no_pushdown.iter().map(Pushdown::to_string).collect();
+            hints.push(format!("NO_PUSHDOWN({})", parts.join(", ")));
+        }
+        if let Some(rows) = self.max_rows {
+            hints.push(format!("MAX_ROWS({})", rows));
+        }
+        if let Some(ttl) = self.cache_ttl {
+            hints.push(format!("CACHE_TTL({}ms)", ttl.as_millis()));
+        }
+        if self.partial_results {
+            hints.push("PARTIAL_RESULTS".to_string());
+        }
+        if let Some(priority) = self.priority {
+            hints.push(format!("PRIORITY({})", priority));
+        }
+        write!(f, "/*+ {} */", hints.join(", "))
+    }
+}
+
 /// Column ordering specification
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderColumn {
     pub column: String,
     pub direction: OrderDirection,
+    /// Where NULLs go; without `NULLS FIRST` or `NULLS LAST` they sort before every
+    /// value, so first ascending and last descending
+    pub nulls: Option<NullsOrder>,
+}
+
+impl fmt::Display for OrderColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            OrderDirection::Ascending => "ASC",
+            OrderDirection::Descending => "DESC",
+        };
+        write!(f, "{} {}", self.column, direction)?;
+        match self.nulls {
+            Some(NullsOrder::First) => write!(f, " NULLS FIRST"),
+            Some(NullsOrder::Last) => write!(f, " NULLS LAST"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Placement of NULLs requested with `NULLS FIRST` or `NULLS LAST`, whatever the direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NullsOrder {
+    First,
+    Last,
 }
 
 /// Sort direction
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum OrderDirection {
     Ascending,
     Descending,
@@ -125,10 +568,241 @@ pub struct QueryResult {
     pub rows: Vec<Row>,
     pub affected_rows: Option<u64>,
     pub execution_time: Duration,
+    /// Work done to produce the result, when it was collected
+    pub stats: Option<ExecutionStats>,
+    /// Sources left out of a partial result
+    pub warnings: Vec<QueryWarning>,
 }
 
-/// Metadata for result columns
+/// Why a result is not exactly what the query asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A source failed and its rows are missing
+    SourceFailed,
+    /// A row cap cut the result short
+    Truncated,
+    /// Values could not be represented exactly
+    Coercion,
+    /// A predicate was not applied
+    PredicateIgnored,
+    /// A fallback source answered for a failed or slow one
+    Degraded,
+    /// The query does something likely to be slow or wrong
+    Lint,
+}
+
+impl WarningKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningKind::SourceFailed => "source_failed",
+            WarningKind::Truncated => "truncated",
+            WarningKind::Coercion => "coercion",
+            WarningKind::PredicateIgnored => "predicate_ignored",
+            WarningKind::Degraded => "degraded",
+            WarningKind::Lint => "lint",
+        }
+    }
+}
+
+/// Something that made a result differ from what the query asked for, without failing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryWarning {
+    pub kind: WarningKind,
+    /// Source as `type.identifier`, left empty by connectors for the dispatcher to fill in
+    pub source: String,
+    /// What happened, phrased to follow the source name
+    pub message: String,
+}
+
+impl QueryWarning {
+    pub fn source_failed(source: impl Into<String>, error: &impl fmt::Display) -> Self {
+        Self::new(WarningKind::SourceFailed, source, format!("failed and is missing from the result: {}", error))
+    }
+
+    pub fn truncated(source: impl Into<String>, rows: u64) -> Self {
+        Self::new(WarningKind::Truncated, source, format!("returned only the first {} rows, the max_limit of its source policy", rows))
+    }
+
+    pub fn capped(source: impl Into<String>, rows: u64, setting: &str) -> Self {
+        Self::new(WarningKind::Truncated, source, format!("returned only {} rows, the {} of its connector", rows, setting))
+    }
+
+    pub fn coercion(source: impl Into<String>, column: &str, detail: impl fmt::Display) -> Self {
+        Self::new(WarningKind::Coercion, source, format!("column '{}': {}", column, detail))
+    }
+
+    pub fn predicate_ignored(source: impl Into<String>, predicate: impl fmt::Display) -> Self {
+        Self::new(WarningKind::PredicateIgnored, source, format!("ignored the predicate {}", predicate))
+    }
+
+    pub fn degraded(source: impl Into<String>, fallback: &str, reason: impl fmt::Display) -> Self {
+        Self::new(WarningKind::Degraded, source, format!("{}, so its fallback {} answered instead", reason, fallback))
+    }
+
+    pub fn stale(source: impl Into<String>, failure: impl fmt::Display) -> Self {
+        Self::new(WarningKind::Degraded, source, format!("returned what it captured before its feed failed: {}", failure))
+    }
+
+    pub fn lint(source: impl Into<String>, rule: &str, message: impl fmt::Display) -> Self {
+        Self::new(WarningKind::Lint, source, format!("{} ({})", message, rule))
+    }
+
+    fn new(kind: WarningKind, source: impl Into<String>, message: String) -> Self {
+        Self { kind, source: source.into(), message }
+    }
+}
+
+impl fmt::Display for QueryWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.source, self.message)
+    }
+}
+
+/// Whether a connector answered from its response cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    /// An entry past its TTL, returned while it is refreshed
+    Stale,
+    Miss,
+}
+
+/// Work done against one source of a query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceStats {
+    /// Source as `type.identifier`
+    pub source: String,
+    /// Rows returned by the connector, before local filtering
+    pub rows_scanned: u64,
+    /// Approximate size of the rows returned by the connector
+    pub bytes_fetched: u64,
+    /// Set by connectors that cache responses
+    pub cache: Option<CacheStatus>,
+    /// Predicates evaluated by the connector
+    pub pushed_predicates: usize,
+    /// Predicates evaluated by the engine on the fetched rows
+    pub local_predicates: usize,
+    /// When the rows were fetched from the source, or last confirmed unchanged
+    pub fetched_at: Option<DateTime<Utc>>,
+    /// How old cached rows were when the query read them
+    pub cache_age: Option<Duration>,
+    /// When the source reports the data last changed, as it sent it
+    pub last_modified: Option<String>,
+    /// How long the query waited for a free slot of the source's connector
+    pub queue_time: Duration,
+}
+
+/// Time spent in one step of query execution, excluding its inputs
 #[derive(Debug, Clone, PartialEq)]
+pub struct NodeStats {
+    pub node: String,
+    /// Rows the step produced
+    pub rows: u64,
+    pub duration: Duration,
+}
+
+/// Statistics collected while executing a query, to explain where its time went
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionStats {
+    pub sources: Vec<SourceStats>,
+    /// Steps in the order they completed
+    pub nodes: Vec<NodeStats>,
+    /// Tag the query was sent to its sources under, to find it in their logs
+    pub query_id: Option<String>,
+}
+
+impl ExecutionStats {
+    /// Entry for a source, added when it is not recorded yet
+    pub fn source_mut(&mut self, source: &str) -> &mut SourceStats {
+        let index = match self.sources.iter().position(|stats| stats.source == source) {
+            Some(index) => index,
+            None => {
+                self.sources.push(SourceStats { source: source.to_string(), ..Default::default() });
+                self.sources.len() - 1
+            }
+        };
+        &mut self.sources[index]
+    }
+
+    pub fn rows_scanned(&self) -> u64 {
+        self.sources.iter().map(|source| source.rows_scanned).sum()
+    }
+
+    pub fn bytes_fetched(&self) -> u64 {
+        self.sources.iter().map(|source| source.bytes_fetched).sum()
+    }
+
+    /// When the oldest rows of the result were fetched
+    pub fn fetched_at(&self) -> Option<DateTime<Utc>> {
+        self.sources.iter().filter_map(|source| source.fetched_at).min()
+    }
+
+    /// Total time the query waited for connector slots
+    pub fn queue_time(&self) -> Duration {
+        self.sources.iter().map(|source| source.queue_time).sum()
+    }
+
+    /// Total time of the recorded steps
+    pub fn node_time(&self) -> Duration {
+        self.nodes.iter().map(|node| node.duration).sum()
+    }
+
+    /// Add the stats of another result, such as the other side of a join
+    pub fn merge(&mut self, other: ExecutionStats) {
+        for source in other.sources {
+            let entry = self.source_mut(&source.source);
+            entry.rows_scanned += source.rows_scanned;
+            entry.bytes_fetched += source.bytes_fetched;
+            entry.cache = entry.cache.or(source.cache);
+            entry.pushed_predicates += source.pushed_predicates;
+            entry.local_predicates += source.local_predicates;
+            entry.fetched_at = entry.fetched_at.or(source.fetched_at);
+            entry.cache_age = entry.cache_age.or(source.cache_age);
+            entry.last_modified = entry.last_modified.take().or(source.last_modified);
+            entry.queue_time += source.queue_time;
+        }
+        self.nodes.extend(other.nodes);
+        self.query_id = self.query_id.take().or(other.query_id);
+    }
+}
+
+impl fmt::Display for ExecutionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rows scanned: {}, bytes fetched: {}", self.rows_scanned(), self.bytes_fetched())?;
+        if let Some(query_id) = &self.query_id {
+            write!(f, ", query id: {}", query_id)?;
+        }
+        for source in &self.sources {
+            write!(f, "\n  {}: {} rows, {} bytes, predicates: {} pushed down, {} local",
+                source.source, source.rows_scanned, source.bytes_fetched, source.pushed_predicates, source.local_predicates)?;
+            match source.cache {
+                Some(CacheStatus::Hit) => write!(f, ", cache hit")?,
+                Some(CacheStatus::Stale) => write!(f, ", stale cache hit")?,
+                Some(CacheStatus::Miss) => write!(f, ", cache miss")?,
+                None => {}
+            }
+            if let Some(cache_age) = source.cache_age {
+                write!(f, " ({}s old)", cache_age.as_secs())?;
+            }
+            if let Some(fetched_at) = source.fetched_at {
+                write!(f, ", fetched at {}", format_utc(fetched_at.naive_utc()))?;
+            }
+            if let Some(last_modified) = &source.last_modified {
+                write!(f, ", last modified {}", last_modified)?;
+            }
+            if !source.queue_time.is_zero() {
+                write!(f, ", queued {:.3} ms", source.queue_time.as_secs_f64() * 1000.0)?;
+            }
+        }
+        for node in &self.nodes {
+            write!(f, "\n  {} -> {} rows in {:.3} ms", node.node, node.rows, node.duration.as_secs_f64() * 1000.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Metadata for result columns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ColumnMetadata {
     pub name: String,
     pub data_type: DataType,
@@ -136,7 +810,7 @@ pub struct ColumnMetadata {
 }
 
 /// Supported data types
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DataType {
     Text,
     Integer,
@@ -155,7 +829,7 @@ pub struct Row {
 }
 
 /// Individual cell values
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     Text(String),
     Integer(i64),
@@ -175,6 +849,45 @@ pub struct Schema {
     pub columns: Vec<ColumnMetadata>,
     pub primary_key: Option<Vec<String>>,
     pub indexes: Vec<Index>,
+    /// Descriptions, tags and source types of the columns that have any, by column name
+    pub column_details: HashMap<String, ColumnDetails>,
+    /// Columns that reference the key of another object
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// What a catalog tells about a column beyond its name and type
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnDetails {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Labels such as `pii` or `deprecated`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Type as the source names it, such as `character varying(64)`
+    #[serde(default)]
+    pub native_type: Option<String>,
+}
+
+impl ColumnDetails {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.tags.is_empty() && self.native_type.is_none()
+    }
+
+    /// These details with the fields the others set taken from them, and their tags added
+    pub fn merged(mut self, other: &ColumnDetails) -> Self {
+        if other.description.is_some() {
+            self.description = other.description.clone();
+        }
+        if other.native_type.is_some() {
+            self.native_type = other.native_type.clone();
+        }
+        for tag in &other.tags {
+            if !self.tags.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+                self.tags.push(tag.clone());
+            }
+        }
+        self
+    }
 }
 
 /// Index information
@@ -185,6 +898,14 @@ pub struct Index {
     pub unique: bool,
 }
 
+/// Columns of an object that hold the key of another object of the same source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub referenced_object: String,
+    pub referenced_columns: Vec<String>,
+}
+
 /// Connector types
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConnectorType {
@@ -195,6 +916,10 @@ pub enum ConnectorType {
     SqlServer,
     File,
     Rest,
+    Cdc,
+    BigQuery,
+    Snowflake,
+    Imap,
     LLM,
     Custom(String),
 }
@@ -216,9 +941,53 @@ impl InternalQuery {
             projections: Vec::new(),
             predicates: Vec::new(),
             joins: Vec::new(),
+            subqueries: Vec::new(),
+            group_by: Vec::new(),
             ordering: None,
             limit: None,
+            sample: None,
+            deduplicate: None,
+            checksum: false,
+            collation: None,
+            target: None,
+            values: Vec::new(),
+            assignments: Vec::new(),
+            hints: QueryHints::default(),
+            comments: Vec::new(),
+            directives: QueryDirectives::default(),
+            capped_by: None,
+            user: None,
+        }
+    }
+    
+    /// The query of a derived table among the sources
+    pub fn subquery(&self, source: &DataSource) -> Option<&InternalQuery> {
+        if source.object_type != SUBQUERY_OBJECT_TYPE {
+            return None;
+        }
+        self.subqueries.get(source.identifier.parse::<usize>().ok()?)
+    }
+    
+    /// Every source the query reads, including those its derived tables read, at any depth
+    pub fn all_sources(&self) -> Vec<&DataSource> {
+        let mut sources = Vec::new();
+        for source in &self.sources {
+            match self.subquery(source) {
+                Some(subquery) => sources.extend(subquery.all_sources()),
+                None => sources.push(source),
+            }
         }
+        sources
+    }
+    
+    /// Whether the query only counts the rows of one source: a lone `COUNT(*)` without a join
+    /// or GROUP BY
+    pub fn is_count_only(&self) -> bool {
+        self.operation == QueryOperation::Select
+            && self.sources.len() == 1
+            && self.joins.is_empty()
+            && self.group_by.is_empty()
+            && matches!(self.projections.as_slice(), [column] if column.name == COUNT_ALL)
     }
 }
 
@@ -230,9 +999,113 @@ impl QueryResult {
             rows: Vec::new(),
             affected_rows: None,
             execution_time: Duration::from_millis(0),
+            stats: None,
+            warnings: Vec::new(),
+        }
+    }
+    
+    /// The result of writing this result's rows to a data object: no rows of its own,
+    /// only the number written, how long the query took, its statistics and warnings
+    pub fn written(self, rows: u64) -> QueryResult {
+        QueryResult {
+            affected_rows: Some(rows),
+            execution_time: self.execution_time,
+            stats: self.stats,
+            warnings: self.warnings,
+            ..Default::default()
+        }
+    }
+    
+    /// A single row holding a row count, named by the `COUNT(*)` projection's alias or, as
+    /// PostgreSQL names it, `count`
+    pub fn of_count(count: u64, projection: &Column) -> QueryResult {
+        QueryResult {
+            columns: vec![ColumnMetadata {
+                name: projection.alias.clone().unwrap_or_else(|| "count".to_string()),
+                data_type: DataType::Integer,
+                nullable: false,
+            }],
+            rows: vec![Row::new(vec![Value::Integer(count as i64)])],
+            ..Default::default()
+        }
+    }
+    
+    /// The count of this result's rows for a `COUNT(*)` projection, keeping how long the
+    /// query took, its statistics and warnings
+    pub fn counted(self, projection: &Column) -> QueryResult {
+        QueryResult {
+            execution_time: self.execution_time,
+            stats: self.stats,
+            warnings: self.warnings,
+            ..QueryResult::of_count(self.rows.len() as u64, projection)
+        }
+    }
+    
+    /// The count a source returned for a `COUNT(*)` query
+    pub fn count_value(&self) -> Option<u64> {
+        match self.rows.first()?.values.first()? {
+            Value::Integer(count) => u64::try_from(*count).ok(),
+            Value::Float(count) if *count >= 0.0 => Some(*count as u64),
+            Value::Text(count) => count.trim().parse().ok(),
+            _ => None,
+        }
+    }
+    
+    /// Approximate in-memory size of the row values
+    pub fn estimated_bytes(&self) -> u64 {
+        self.rows.iter()
+            .flat_map(|row| &row.values)
+            .map(|value| match value {
+                Value::Text(s) | Value::Date(s) | Value::DateTime(s) | Value::Json(s) => s.len() as u64,
+                Value::Binary(b) => b.len() as u64,
+                Value::Integer(_) | Value::Float(_) => 8,
+                Value::Boolean(_) => 1,
+                Value::Null => 0,
+            })
+            .sum()
+    }
+    
+    /// Record the rows of this result as scanned from a source, keeping anything the
+    /// connector already reported for it
+    pub fn record_scan(&mut self, source: &str, pushed_predicates: usize, local_predicates: usize) {
+        let (rows, bytes) = (self.rows.len() as u64, self.estimated_bytes());
+        self.record_scanned(source, rows, bytes, pushed_predicates, local_predicates);
+    }
+    
+    /// Record rows scanned from a source that were handed over in batches rather than kept
+    /// in this result. Warnings the connector left unattributed are attributed to the source.
+    pub fn record_scanned(&mut self, source: &str, rows: u64, bytes: u64, pushed_predicates: usize, local_predicates: usize) {
+        for warning in self.warnings.iter_mut().filter(|warning| warning.source.is_empty()) {
+            warning.source = source.to_string();
+        }
+        let stats = self.stats.get_or_insert_with(ExecutionStats::default).source_mut(source);
+        stats.rows_scanned = rows;
+        if stats.bytes_fetched == 0 {
+            stats.bytes_fetched = bytes;
+        }
+        stats.pushed_predicates = pushed_predicates;
+        stats.local_predicates += local_predicates;
+        // Sources without a cache are read live
+        stats.fetched_at.get_or_insert_with(Utc::now);
+    }
+    
+    /// Record a completed execution step and the rows it produced
+    /// Record how long the query waited for a slot of a source's connector
+    pub fn record_queued(&mut self, source: &str, waited: Duration) {
+        if !waited.is_zero() {
+            self.stats.get_or_insert_with(ExecutionStats::default).source_mut(source).queue_time += waited;
         }
     }
     
+    pub fn record_node(&mut self, node: impl Into<String>, duration: Duration) {
+        let rows = self.rows.len() as u64;
+        self.stats.get_or_insert_with(ExecutionStats::default).nodes.push(NodeStats {
+            node: node.into(),
+            rows,
+            duration,
+        });
+    }
+    
     /// Get the number of rows in the result
     pub fn row_count(&self) -> usize {
         self.rows.len()
@@ -244,6 +1117,30 @@ impl QueryResult {
     }
 }
 
+impl Schema {
+    /// Position of the column a query identifier refers to
+    pub fn column_index(&self, identifier: &str, case: IdentifierCase) -> Option<usize> {
+        case.resolve(identifier, self.columns.iter().map(|column| column.name.as_str()))
+    }
+
+    /// Details of a column, if it has any
+    pub fn column_details(&self, column: &str) -> Option<&ColumnDetails> {
+        self.column_details.get(column)
+    }
+
+    /// The schema with configured details laid over those its source reported, for the
+    /// columns it has
+    pub fn with_column_details(mut self, details: &HashMap<String, ColumnDetails>) -> Self {
+        for column in &self.columns {
+            if let Some(configured) = details.get(&column.name) {
+                let merged = self.column_details.remove(&column.name).unwrap_or_default().merged(configured);
+                self.column_details.insert(column.name.clone(), merged);
+            }
+        }
+        self
+    }
+}
+
 impl Row {
     /// Create a new row with the given values
     pub fn new(values: Vec<Value>) -> Self {
@@ -277,6 +1174,46 @@ mod tests {
         assert!(query.limit.is_none());
     }
 
+    #[test]
+    fn test_execution_stats_recording() {
+        let mut result = QueryResult::new();
+        result.rows = vec![Row::new(vec![Value::Integer(1), Value::Text("abc".to_string())])];
+        result.stats = Some(ExecutionStats::default());
+        let fetched_at = DateTime::parse_from_rfc3339("2026-10-14T08:00:00Z").unwrap().with_timezone(&Utc);
+        let source = result.stats.as_mut().unwrap().source_mut("api.users");
+        source.cache = Some(CacheStatus::Hit);
+        source.fetched_at = Some(fetched_at);
+        source.cache_age = Some(Duration::from_secs(90));
+        result.record_scan("api.users", 2, 1);
+        result.record_node("TableScan api.users", Duration::from_millis(3));
+        result.record_queued("api.users", Duration::from_millis(250));
+
+        let mut stats = result.stats.unwrap();
+        assert_eq!(stats.sources, vec![SourceStats {
+            source: "api.users".to_string(),
+            rows_scanned: 1,
+            bytes_fetched: 11,
+            cache: Some(CacheStatus::Hit),
+            pushed_predicates: 2,
+            local_predicates: 1,
+            fetched_at: Some(fetched_at),
+            cache_age: Some(Duration::from_secs(90)),
+            last_modified: None,
+            queue_time: Duration::from_millis(250),
+        }]);
+        assert!(stats.to_string().contains("cache hit (90s old), fetched at 2026-10-14 08:00:00, queued 250.000 ms"), "{}", stats);
+
+        stats.merge(ExecutionStats {
+            sources: vec![SourceStats { source: "file.orders".to_string(), rows_scanned: 4, ..Default::default() }],
+            nodes: Vec::new(),
+            query_id: None,
+        });
+        assert_eq!((stats.rows_scanned(), stats.node_time(), stats.queue_time()), (5, Duration::from_millis(3), Duration::from_millis(250)));
+        // The oldest rows decide when a joined result was fetched
+        stats.source_mut("file.orders").fetched_at = Some(Utc::now());
+        assert_eq!(stats.fetched_at(), Some(fetched_at));
+    }
+
     #[test]
     fn test_data_source_creation() {
         let source = DataSource {
@@ -289,6 +1226,95 @@ mod tests {
         assert_eq!(source.alias, Some("u".to_string()));
     }
 
+    #[test]
+    fn test_source_uri_parsing() {
+        let uri = SourceUri::parse("postgres://analytics/public/users").unwrap();
+        assert_eq!((uri.object_type(), uri.authority.as_deref(), uri.identifier().as_str()), ("postgres", Some("analytics"), "public.users"));
+
+        let uri = SourceUri::parse("file:///data/*.csv").unwrap();
+        assert_eq!((uri.object_type(), uri.authority.as_deref(), uri.identifier().as_str()), ("file", None, "/data/*.csv"));
+
+        let uri = SourceUri::parse("https://api.example.com/v1/users").unwrap();
+        assert_eq!((uri.object_type(), uri.identifier().as_str()), ("api", "https://api.example.com/v1/users"));
+
+        // Dotted form keeps working
+        let uri = SourceUri::parse("file.data.csv").unwrap();
+        assert_eq!((uri.object_type(), uri.identifier().as_str()), ("file", "data.csv"));
+        assert_eq!(SourceUri::parse("users").unwrap().to_data_source(None).object_type, "table");
+        assert_eq!(SourceUri::parse("imap.Support/Tickets").unwrap().identifier(), "Support/Tickets");
+        assert_eq!(SourceUri::parse("fsmeta./var/log/**").unwrap().identifier(), "/var/log/**");
+
+        assert!(SourceUri::parse("postgres://analytics").is_err());
+        assert!(SourceUri::parse("://users").is_err());
+        assert!(SourceUri::parse("postgres.").is_err());
+    }
+
+    #[test]
+    fn test_count_results() {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "file".to_string(), identifier: "big.csv".to_string(), alias: None });
+        query.projections.push(Column { name: COUNT_ALL.to_string(), alias: None, source: None });
+        assert!(query.is_count_only());
+        query.projections.push(Column { name: "id".to_string(), alias: None, source: None });
+        assert!(!query.is_count_only());
+
+        let projection = Column { name: COUNT_ALL.to_string(), alias: Some("total".to_string()), source: None };
+        let mut rows = QueryResult::new();
+        rows.rows = vec![Row::new(vec![Value::Integer(7)]); 3];
+        let counted = rows.counted(&projection);
+        assert_eq!(counted.columns[0].name, "total");
+        assert_eq!(counted.count_value(), Some(3));
+        assert_eq!(QueryResult::of_count(12, &Column { alias: None, ..projection }).columns[0].name, "count");
+    }
+
+    #[test]
+    fn test_query_warnings() {
+        let failed = QueryWarning::source_failed("api.orders", &"HTTP 500");
+        assert_eq!(failed.kind, WarningKind::SourceFailed);
+        assert_eq!(failed.to_string(), "api.orders failed and is missing from the result: HTTP 500");
+        assert_eq!(QueryWarning::predicate_ignored("cdc.orders", "id = 1").to_string(), "cdc.orders ignored the predicate id = 1");
+        assert_eq!(WarningKind::Coercion.as_str(), "coercion");
+
+        // Connectors leave the source for the scan to name
+        let mut result = QueryResult::new();
+        result.warnings = vec![QueryWarning::coercion("", "total", "2 values rounded"), failed.clone()];
+        result.record_scan("mssql.dbo.orders", 0, 0);
+        assert_eq!(result.warnings[0].to_string(), "mssql.dbo.orders column 'total': 2 values rounded");
+        assert_eq!(result.warnings[1], failed);
+    }
+
+    #[test]
+    fn test_query_hints_parsing() {
+        let hints = QueryHints::parse(" NO_PUSHDOWN(predicates), MAX_ROWS(1000), CACHE_TTL(60s) ").unwrap();
+        assert_eq!(hints.no_pushdown, vec![Pushdown::Predicates]);
+        assert_eq!(hints.max_rows, Some(1000));
+        assert_eq!(hints.cache_ttl, Some(Duration::from_secs(60)));
+        assert!(!hints.pushes_down(Pushdown::Predicates));
+        assert!(hints.pushes_down(Pushdown::Limit));
+        assert_eq!(hints.limit(Some(50)), Some(50));
+        assert_eq!(hints.limit(Some(5000)), Some(1000));
+        assert_eq!(hints.limit(None), Some(1000));
+        assert_eq!(hints.to_string(), "/*+ NO_PUSHDOWN(predicates), MAX_ROWS(1000), CACHE_TTL(60000ms) */");
+
+        // Spaces separate hints as well as commas; a bare NO_PUSHDOWN covers everything
+        let hints = QueryHints::parse("no_pushdown cache_ttl(250ms)").unwrap();
+        assert_eq!(hints.no_pushdown, vec![Pushdown::Predicates, Pushdown::OrderBy, Pushdown::Limit]);
+        assert_eq!(hints.cache_ttl, Some(Duration::from_millis(250)));
+        assert_eq!(QueryHints::parse("CACHE_TTL(5m)").unwrap().cache_ttl, Some(Duration::from_secs(300)));
+        assert!(QueryHints::parse("").unwrap().is_empty());
+        assert_eq!(QueryHints::parse("partial_results").unwrap().to_string(), "/*+ PARTIAL_RESULTS */");
+        assert_eq!(QueryHints::parse("PRIORITY(Batch)").unwrap().priority, Some(QueryPriority::Batch));
+        assert_eq!(QueryHints::parse("priority(interactive)").unwrap().to_string(), "/*+ PRIORITY(interactive) */");
+
+        assert!(QueryHints::parse("FULL_SCAN").is_err());
+        assert!(QueryHints::parse("NO_PUSHDOWN(joins)").is_err());
+        assert!(QueryHints::parse("MAX_ROWS(many)").is_err());
+        assert!(QueryHints::parse("MAX_ROWS").is_err());
+        assert!(QueryHints::parse("CACHE_TTL(60 days)").is_err());
+        assert!(QueryHints::parse("MAX_ROWS(10").is_err());
+        assert!(QueryHints::parse("PRIORITY(urgent)").is_err());
+    }
+
     #[test]
     fn test_query_result_creation() {
         let result = QueryResult::new();
@@ -317,7 +1343,7 @@ mod tests {
     #[test]
     fn test_predicate_value_types() {
         let string_val = PredicateValue::String("test".to_string());
-        let number_val = PredicateValue::Number(3.14);
+        let number_val = PredicateValue::Number(2.5);
         let int_val = PredicateValue::Integer(42);
         let bool_val = PredicateValue::Boolean(true);
         let null_val = PredicateValue::Null;
@@ -328,7 +1354,7 @@ mod tests {
         }
         
         match number_val {
-            PredicateValue::Number(n) => assert_eq!(n, 3.14),
+            PredicateValue::Number(n) => assert_eq!(n, 2.5),
             _ => panic!("Expected number value"),
         }
         
@@ -5,10 +5,18 @@ use std::str;
 
 /// Test helper to run CLI commands and capture output
 fn run_cli_command(args: &[&str]) -> (String, String, i32) {
+    // Keep queries run by the tests out of the user's history
+    let history = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli_tests_history.jsonl");
+    run_cli_command_with_history(args, &history)
+}
+
+/// Test helper to run CLI commands recording queries in the given history file
+fn run_cli_command_with_history(args: &[&str], history: &std::path::Path) -> (String, String, i32) {
     let output = Command::new("cargo")
         .arg("run")
         .arg("--")
         .args(args)
+        .env("NIRV_HISTORY", history)
         .output()
         .expect("Failed to execute CLI command");
     
@@ -177,6 +185,323 @@ fn test_cli_query_with_where_clause() {
     assert_output_contains(&stdout, "2 rows");
 }
 
+#[test]
+fn test_cli_query_with_bound_parameters() {
+    let (stdout, _stderr, exit_code) = run_cli_command(&[
+        "query",
+        "SELECT name, age FROM source('mock.users') WHERE age > :min_age AND name <> :skip",
+        "--param", "min_age=25",
+        "-p", "skip:text=Charlie Brown",
+    ]);
+    
+    assert_eq!(exit_code, 0);
+    assert_output_contains(&stdout, "Alice Johnson");
+    assert!(!stdout.contains("Charlie Brown"));
+    assert_output_contains(&stdout, "1 row in");
+    
+    // Every placeholder needs a value
+    let (_stdout, stderr, exit_code) = run_cli_command(&[
+        "query",
+        "SELECT * FROM source('mock.users') WHERE age > :min_age",
+    ]);
+    assert_ne!(exit_code, 0);
+    assert_output_contains(&stderr, ":min_age");
+}
+
+#[test]
+fn test_cli_query_dry_run() {
+    let (stdout, _stderr, exit_code) = run_cli_command(&[
+        "query",
+        "SELECT name FROM source('mock.users') WHERE age > :min_age LIMIT 2",
+        "--param", "min_age=25",
+        "--dry-run",
+    ]);
+    
+    assert_eq!(exit_code, 0);
+    assert_output_contains(&stdout, "TableScan mock.users [name] WHERE age > 25");
+    assert_output_contains(&stdout, "Query is valid");
+    assert!(!stdout.contains("Alice Johnson"));
+    
+    let (_stdout, stderr, exit_code) = run_cli_command(&[
+        "query",
+        "SELECT nickname FROM source('mock.users')",
+        "--dry-run",
+    ]);
+    assert_ne!(exit_code, 0);
+    assert_output_contains(&stderr, "Column 'nickname' does not exist");
+}
+
+#[test]
+fn test_cli_query_with_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("nirv.json");
+    let config = r#"{
+        "protocol_adapters": [],
+        "connectors": {},
+        "dispatcher": {"max_concurrent_queries": 10, "query_cache_size": null, "enable_cross_connector_joins": false, "default_timeout": 30},
+        "security": {
+            "authentication": {"enabled": false, "auth_method": "None", "user_database": null, "ldap_config": null},
+            "authorization": {"enabled": false, "default_permissions": [], "role_mappings": {}},
+            "audit_logging": {"enabled": false, "log_file": null, "log_queries": false, "log_connections": false, "log_errors": false}
+        },
+        "profiles": {"dev": {}}
+    }"#;
+    std::fs::write(&config_path, config).unwrap();
+    let config_path = config_path.to_str().unwrap();
+    
+    let (_stdout, stderr, exit_code) = run_cli_command(&[
+        "query", "SELECT * FROM source('mock.users')", "--config", config_path, "--profile", "dev", "--verbose",
+    ]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    assert_output_contains(&stderr, "Using profile 'dev'");
+    
+    // A mistyped profile is rejected rather than falling back to other settings
+    let (_stdout, stderr, exit_code) = run_cli_command(&[
+        "--profile", "prdo", "query", "SELECT * FROM source('mock.users')", "--config", config_path,
+    ]);
+    assert_ne!(exit_code, 0);
+    assert_output_contains(&stderr, "Unknown profile 'prdo'");
+}
+
+#[test]
+fn test_cli_config_validate() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("nirv.json");
+    let config = r#"{
+        "protocol_adapters": [],
+        "connectors": {
+            "files": {"connector_type": "File", "connection_string": null, "parameters": {"base_path": "BASE"}, "pool_config": null, "timeout_config": null},
+            "warehouse": {"connector_type": "SqlServer", "connection_string": null, "parameters": {"server": "sql", "port": "none"}, "pool_config": null, "timeout_config": null}
+        },
+        "dispatcher": {"max_concurrent_queries": 10, "query_cache_size": null, "enable_cross_connector_joins": false, "default_timeout": 30},
+        "security": {
+            "authentication": {"enabled": false, "auth_method": "None", "user_database": null, "ldap_config": null},
+            "authorization": {"enabled": false, "default_permissions": [], "role_mappings": {}},
+            "audit_logging": {"enabled": false, "log_file": null, "log_queries": false, "log_connections": false, "log_errors": false}
+        },
+        "profiles": {"fixed": {"connectors": {"warehouse": {"parameters": {"port": "1433", "database": "dw", "username": "nirv", "password": "secret"}}}}}
+    }"#.replace("BASE", &dir.path().to_string_lossy());
+    std::fs::write(&config_path, config).unwrap();
+    let config_path = config_path.to_str().unwrap();
+    
+    // Every problem is listed, not just the first
+    let (_stdout, stderr, exit_code) = run_cli_command(&["config", "validate", config_path]);
+    assert_ne!(exit_code, 0);
+    assert_output_contains(&stderr, "4 problems found");
+    assert_output_contains(&stderr, "connectors.warehouse.parameters.port: invalid value 'none'");
+    assert_output_contains(&stderr, "connectors.warehouse.parameters.password: required parameter is missing");
+    
+    let (stdout, stderr, exit_code) = run_cli_command(&["--profile", "fixed", "config", "validate", config_path]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    assert_output_contains(&stdout, "is valid (2 connectors, 0 protocol adapters)");
+}
+
+#[test]
+fn test_cli_query_enforces_source_policies() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("nirv.json");
+    let config = r#"{
+        "protocol_adapters": [],
+        "connectors": {
+            "mock": {"connector_type": "Mock", "connection_string": null, "parameters": {}, "pool_config": null, "timeout_config": null,
+                     "policy": {"read_only": true, "denied_tables": ["secret*"], "max_limit": 2}}
+        },
+        "dispatcher": {"max_concurrent_queries": 10, "query_cache_size": null, "enable_cross_connector_joins": false, "default_timeout": 30},
+        "security": {
+            "authentication": {"enabled": false, "auth_method": "None", "user_database": null, "ldap_config": null},
+            "authorization": {"enabled": false, "default_permissions": [], "role_mappings": {}},
+            "audit_logging": {"enabled": false, "log_file": null, "log_queries": false, "log_connections": false, "log_errors": false}
+        }
+    }"#;
+    std::fs::write(&config_path, config).unwrap();
+    let config_path = config_path.to_str().unwrap();
+    
+    let (stdout, stderr, exit_code) = run_cli_command(&["query", "--config", config_path, "SELECT * FROM source('mock.users')"]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    assert_output_contains(&stdout, "2 rows");
+    
+    let (_stdout, stderr, exit_code) = run_cli_command(&["query", "--config", config_path, "SELECT * FROM source('mock.secrets')"]);
+    assert_ne!(exit_code, 0);
+    assert_output_contains(&stderr, "table 'secrets' of 'mock' is not allowed");
+}
+
+#[test]
+fn test_cli_diff_command() {
+    let (stdout, _stderr, exit_code) = run_cli_command(&[
+        "diff", "SELECT * FROM source('mock.users')", "SELECT * FROM source('mock.users')",
+    ]);
+    assert_eq!(exit_code, 0);
+    assert_output_contains(&stdout, "0 missing, 0 extra, 0 changed");
+    
+    let (stdout, _stderr, exit_code) = run_cli_command(&[
+        "diff", "SELECT * FROM source('mock.users')", "SELECT * FROM source('mock.users') WHERE id > 1", "--key", "id",
+    ]);
+    assert_eq!(exit_code, 1);
+    assert_output_contains(&stdout, "1 missing");
+    assert_output_contains(&stdout, "- id=1 missing from right");
+}
+
+#[test]
+fn test_cli_profile_command() {
+    let (stdout, stderr, exit_code) = run_cli_command(&["profile", "source('mock.users')", "--format", "json"]);
+    
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(output["source"], "mock.users");
+    assert_eq!(output["rows"], 3);
+    let email = &output["columns"][2];
+    assert_eq!(email["name"], "email");
+    assert_eq!(email["nulls"], 1);
+    assert_eq!(email["distinct"], 2);
+    assert_eq!(output["columns"][3]["min"], 25);
+    assert_eq!(output["columns"][3]["max"], 35);
+    
+    let (stdout, _stderr, exit_code) = run_cli_command(&["profile", "mock.users", "--buckets", "2"]);
+    assert_eq!(exit_code, 0);
+    assert_output_contains(&stdout, "Profile of mock.users: 3 rows");
+    assert_output_contains(&stdout, "Histogram of age");
+    
+    let (_stdout, _stderr, exit_code) = run_cli_command(&["profile", "mock.missing"]);
+    assert_ne!(exit_code, 0);
+}
+
+#[test]
+fn test_cli_query_with_sample() {
+    let (stdout, stderr, exit_code) = run_cli_command(&[
+        "query", "SELECT * FROM source('mock.users') SAMPLE 2 ROWS", "--format", "json",
+    ]);
+    
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(output["data"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_cli_query_with_approximate_aggregates() {
+    let (stdout, stderr, exit_code) = run_cli_command(&[
+        "query",
+        "SELECT approx_count_distinct(name) AS names, approx_percentile(age, 0.5) AS median_age FROM source('mock.users')",
+        "--format", "json",
+    ]);
+    
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(output["data"][0]["names"], 3);
+    assert_eq!(output["data"][0]["median_age"], 30.0);
+    
+    let (stdout, _stderr, exit_code) = run_cli_command(&[
+        "query", "SELECT approx_count_distinct(nickname) FROM source('mock.users')", "--dry-run",
+    ]);
+    assert_ne!(exit_code, 0, "{}", stdout);
+}
+
+#[test]
+fn test_cli_query_with_stats() {
+    let (stdout, stderr, exit_code) = run_cli_command(&[
+        "query", "SELECT name FROM source('mock.users') WHERE age > 26", "--format", "json", "--stats",
+    ]);
+    
+    assert_eq!(exit_code, 0, "{}", stderr);
+    // Statistics go to stderr so the formatted result stays parseable
+    let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(!output["data"].as_array().unwrap().is_empty());
+    assert!(stderr.contains("Rows scanned: 2"), "{}", stderr);
+    // along with the id sources saw the query under
+    assert!(stderr.contains(", query id: nirv-"), "{}", stderr);
+    assert!(stderr.contains("mock.users: 2 rows"), "{}", stderr);
+    assert!(stderr.contains("predicates: 1 pushed down, 0 local"), "{}", stderr);
+    assert!(stderr.contains("Scan mock.users -> 2 rows"), "{}", stderr);
+}
+
+#[test]
+fn test_cli_query_piped_csv_and_inline_values() {
+    use std::io::Write;
+    
+    let history = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli_tests_history.jsonl");
+    let mut child = Command::new("cargo")
+        .args(["run", "--", "query", "SELECT name FROM source('stdin.csv') WHERE amount > 10", "--format", "json"])
+        .env("NIRV_HISTORY", &history)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute CLI command");
+    child.stdin.take().unwrap().write_all(b"name,amount\nann,5\nbo,20\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["data"].as_array().unwrap().len(), 1);
+    assert_eq!(result["data"][0]["name"], "bo");
+    
+    let (stdout, stderr, exit_code) = run_cli_command(&[
+        "query", "SELECT label FROM (VALUES (1, 'low'), (2, 'high')) AS t(id, label) WHERE id = 2", "--format", "json",
+    ]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let result: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(result["data"].as_array().unwrap().len(), 1);
+    assert_eq!(result["data"][0]["label"], "high");
+}
+
+#[test]
+fn test_cli_query_file_metadata() {
+    let (stdout, stderr, exit_code) = run_cli_command(&[
+        "query", "SELECT path, extension FROM source('fsmeta.src/cli/*.rs') WHERE size > 0", "--format", "json",
+    ]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let result: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let rows = result["data"].as_array().unwrap();
+    assert!(rows.iter().any(|row| row["path"] == "src/cli/cli_runner.rs" && row["extension"] == "rs"), "{}", stdout);
+    assert!(rows.iter().all(|row| row["path"].as_str().unwrap().starts_with("src/cli/")), "{}", stdout);
+}
+
+#[test]
+fn test_cli_query_runtime_information() {
+    let (stdout, stderr, exit_code) = run_cli_command(&[
+        "query", "SELECT name FROM source('sys.env') WHERE name = 'PATH'", "--format", "json",
+    ]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let result: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(result["data"].as_array().unwrap().len(), 1, "{}", stdout);
+
+    let (stdout, stderr, exit_code) = run_cli_command(&["query", "SELECT * FROM source('sys.host')", "--format", "json"]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let result: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(result["data"][0]["os"], std::env::consts::OS, "{}", stdout);
+}
+
+#[test]
+fn test_cli_history_list_search_and_rerun() {
+    let dir = tempfile::tempdir().unwrap();
+    let history = dir.path().join("history.jsonl");
+    let run = |args: &[&str]| run_cli_command_with_history(args, &history);
+    
+    let (stdout, _stderr, exit_code) = run(&["history", "list"]);
+    assert_eq!(exit_code, 0);
+    assert_output_contains(&stdout, "No queries in history");
+    
+    run(&["query", "SELECT * FROM source('mock.users') LIMIT 2"]);
+    run(&["query", "SELECT * FROM source('mock.missing')"]);
+    
+    let (stdout, stderr, exit_code) = run(&["history", "list"]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    assert_output_contains(&stdout, "2 rows  SELECT * FROM source('mock.users') LIMIT 2");
+    assert_output_contains(&stdout, "failed  SELECT * FROM source('mock.missing')");
+    
+    let (stdout, _stderr, _) = run(&["history", "search", "MOCK.USERS"]);
+    assert_output_contains(&stdout, "mock.users");
+    assert!(!stdout.contains("mock.missing"), "{}", stdout);
+    
+    let (stdout, stderr, exit_code) = run(&["history", "rerun", "1", "--format", "json"]);
+    assert_eq!(exit_code, 0, "{}", stderr);
+    let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(output["data"].as_array().unwrap().len(), 2);
+    
+    let (_stdout, stderr, exit_code) = run(&["history", "rerun", "42"]);
+    assert_ne!(exit_code, 0);
+    assert_output_contains(&stderr, "No query with id 42");
+}
+
 #[test]
 fn test_cli_query_with_limit() {
     let (stdout, _stderr, exit_code) = run_cli_command(&[
@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use nirv_engine::connectors::{
+    Connector, ConnectorCapabilities, ConnectorInitConfig, ConnectorTestKit, ConformanceFixture, FileConnector,
+    fixture_csv, fixture_rows,
+};
+use nirv_engine::utils::{
+    ColumnMetadata, ConnectorError, ConnectorQuery, ConnectorType, DataType, NirvResult, QueryResult, Schema,
+};
+
+fn file_fixture(dir: &tempfile::TempDir) -> ConformanceFixture {
+    std::fs::write(dir.path().join("people.csv"), fixture_csv()).unwrap();
+    let config = ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap());
+    ConformanceFixture::new(config, "file", "people.csv", "missing.csv")
+}
+
+#[tokio::test]
+async fn test_file_connector_conforms() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = ConnectorTestKit::new(file_fixture(&dir)).run(&mut FileConnector::new()).await;
+    report.assert_passed();
+    assert!(report.checks.len() > 15);
+}
+
+/// Claims to evaluate predicates and limits, but returns every row regardless
+struct CarelessConnector {
+    connected: bool,
+}
+
+#[async_trait]
+impl Connector for CarelessConnector {
+    async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        if query.query.sources[0].identifier != "people" {
+            return Err(ConnectorError::QueryExecutionFailed("no such table".to_string()).into());
+        }
+        let mut result = QueryResult::new();
+        result.columns = ["id", "name", "score"].iter()
+            .map(|name| ColumnMetadata { name: name.to_string(), data_type: DataType::Text, nullable: true })
+            .collect();
+        result.rows = fixture_rows();
+        Ok(result)
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        Err(ConnectorError::SchemaRetrievalFailed(object_name.to_string()).into())
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.connected = false;
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Mock
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities::default()
+    }
+}
+
+#[tokio::test]
+async fn test_careless_connector_fails_conformance() {
+    let fixture = ConformanceFixture::new(ConnectorInitConfig::new(), "careless", "people", "missing");
+    let report = ConnectorTestKit::new(fixture).run(&mut CarelessConnector { connected: false }).await;
+    assert!(!report.passed());
+    let failed: Vec<&str> = report.failures().map(|check| check.name.as_str()).collect();
+    assert!(failed.contains(&"query before connect fails"), "{}", report);
+    assert!(failed.contains(&"schema"), "{}", report);
+    assert!(failed.contains(&"predicate id = 2"), "{}", report);
+    assert!(failed.contains(&"limit 2"), "{}", report);
+    assert!(!failed.contains(&"scan") && !failed.contains(&"limit 10"), "{}", report);
+    assert!(report.to_string().contains("FAIL predicate id = 2: returned ids [1, 2, 3, 4], expected [2]"));
+}
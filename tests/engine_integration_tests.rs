@@ -1,13 +1,19 @@
 #![allow(unused)]
 
 use nirv_engine::{
-    Engine, EngineBuilder,
+    Engine, EngineBuilder, Session,
     DefaultQueryParser, DefaultQueryPlanner, DefaultQueryExecutor, DefaultDispatcher,
     MockConnector, ConnectorInitConfig, Connector,
     NirvResult, NirvError,
 };
-use nirv_engine::utils::{EngineConfig, ProtocolConfig, DispatcherConfig, SecurityConfig, ConnectorConfig, ConnectorType, ProtocolType as ConfigProtocolType};
+use nirv_engine::utils::{EngineConfig, LineageConfig, LintConfig, ProtocolConfig, DispatcherConfig, SecurityConfig, ConnectorConfig, ConnectorType, ProtocolType as ConfigProtocolType, SourcePolicy, Value, ConnectorError, DispatcherError};
+use nirv_engine::utils::types::{ColumnDetails, InternalQuery, QueryResult, QueryPriority, WarningKind};
+use nirv_engine::engine::{ExecutionPlan, LintRule, PlanNode, QueryPlanner, QueryExecutor, QueryParser};
+use nirv_engine::connectors::ConnectorRegistry;
+use nirv_engine::protocol::ProtocolType;
+use async_trait::async_trait;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::RwLock;
 use std::collections::HashMap;
 
@@ -50,6 +56,11 @@ async fn test_engine_initialization_custom_config() -> NirvResult<()> {
         parameters: HashMap::new(),
         pool_config: None,
         timeout_config: None,
+        policy: Default::default(),
+        time_zone: None,
+        collation: None,
+        fetch_size: None,
+        columns: HashMap::new(),
     });
     
     let mut engine = Engine::new(config);
@@ -93,6 +104,123 @@ async fn test_engine_builder() -> NirvResult<()> {
     Ok(())
 }
 
+/// Planner that marks its plans with a fixed cost
+struct MarkedPlanner(DefaultQueryPlanner);
+
+#[async_trait]
+impl QueryPlanner for MarkedPlanner {
+    async fn create_execution_plan(&self, query: &InternalQuery) -> NirvResult<ExecutionPlan> {
+        let mut plan = self.0.create_execution_plan(query).await?;
+        plan.set_estimated_cost(42.0);
+        Ok(plan)
+    }
+    
+    async fn estimate_cost(&self, _query: &InternalQuery) -> NirvResult<f64> {
+        Ok(42.0)
+    }
+    
+    async fn optimize_plan(&self, plan: ExecutionPlan) -> NirvResult<ExecutionPlan> {
+        Ok(plan)
+    }
+}
+
+/// Executor that counts the connector registries it is given and the plans it executes
+struct CountingExecutor(DefaultQueryExecutor, Arc<AtomicUsize>, Arc<AtomicUsize>);
+
+#[async_trait]
+impl QueryExecutor for CountingExecutor {
+    async fn execute_plan(&self, plan: &ExecutionPlan) -> NirvResult<QueryResult> {
+        self.2.fetch_add(1, Ordering::SeqCst);
+        self.0.execute_plan(plan).await
+    }
+    
+    async fn execute_node(&self, node: &PlanNode) -> NirvResult<QueryResult> {
+        self.0.execute_node(node).await
+    }
+    
+    fn set_connector_registry(&mut self, registry: ConnectorRegistry) {
+        self.1.fetch_add(1, Ordering::SeqCst);
+        self.0.set_connector_registry(registry);
+    }
+}
+
+/// Test planner and executor implementations selected by configuration
+#[tokio::test]
+async fn test_engine_builder_selects_configured_components() -> NirvResult<()> {
+    let registries = Arc::new(AtomicUsize::new(0));
+    let plans = Arc::new(AtomicUsize::new(0));
+    let builder = |config: EngineConfig| {
+        let (registries, plans) = (registries.clone(), plans.clone());
+        EngineBuilder::new()
+            .with_config(config)
+            .register_query_planner("marked", |_| Arc::new(MarkedPlanner(DefaultQueryPlanner::new())))
+            .register_query_executor("counting", move |_| {
+                Arc::new(RwLock::new(CountingExecutor(DefaultQueryExecutor::new(), registries.clone(), plans.clone())))
+            })
+    };
+    let mock_connector = || async {
+        let mut connector = Box::new(MockConnector::new());
+        connector.connect(ConnectorInitConfig::new()).await.map(|_| connector)
+    };
+    
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.components.planner = Some("marked".to_string());
+    config.components.executor = Some("counting".to_string());
+    let mut engine = builder(config.clone()).build()?;
+    engine.initialize_for_testing().await?;
+    let report = engine.dry_run("SELECT * FROM source('system.usage')").await;
+    assert_eq!(report.plan.map(|plan| plan.estimated_cost), Some(42.0));
+    assert_eq!(registries.load(Ordering::SeqCst), 1);
+    
+    // Queries run through the selected executor, which is given connectors as they register
+    engine.register_connector("mock", mock_connector().await?).await?;
+    assert_eq!(registries.load(Ordering::SeqCst), 2);
+    let result = engine.execute_query("SELECT * FROM source('mock.users') LIMIT 5").await?;
+    assert!(!result.is_empty());
+    assert_eq!(plans.load(Ordering::SeqCst), 1);
+    
+    // Unset or `default` names select the built-in implementations
+    config.components.planner = Some("default".to_string());
+    config.components.executor = None;
+    let mut engine = builder(config.clone()).build()?;
+    engine.initialize_for_testing().await?;
+    let report = engine.dry_run("SELECT * FROM source('system.usage')").await;
+    assert_ne!(report.plan.map(|plan| plan.estimated_cost), Some(42.0));
+    engine.register_connector("mock", mock_connector().await?).await?;
+    engine.execute_query("SELECT * FROM source('mock.users') LIMIT 5").await?;
+    assert_eq!(registries.load(Ordering::SeqCst), 2);
+    assert_eq!(plans.load(Ordering::SeqCst), 1);
+    
+    config.components.executor = Some("datafusion".to_string());
+    match builder(config).build() {
+        Err(NirvError::Configuration(message)) => {
+            assert!(message.contains("Unknown query executor 'datafusion'"), "{}", message);
+            assert!(message.contains("counting, default"), "{}", message);
+        }
+        _ => panic!("Expected a configuration error for an unregistered executor"),
+    }
+    
+    Ok(())
+}
+
+/// Test rows written inline with VALUES, read without registering an inline connector
+#[tokio::test]
+async fn test_engine_reads_inline_values() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    
+    let result = engine.execute_query("SELECT * FROM (VALUES (1, 'a'), (2, 'b')) t(id, v)").await?;
+    let names: Vec<&str> = result.columns.iter().map(|column| column.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "v"]);
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[1].values[1], Value::Text("b".to_string()));
+    
+    Ok(())
+}
+
 /// Test engine query execution end-to-end
 #[tokio::test]
 async fn test_engine_query_execution() -> NirvResult<()> {
@@ -126,6 +254,981 @@ async fn test_engine_query_execution() -> NirvResult<()> {
     Ok(())
 }
 
+/// Test per-user quotas configured on the engine, and usage reported in system.usage
+#[tokio::test]
+async fn test_engine_usage_quotas() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.quotas = Some(serde_json::from_str(r#"{
+        "default": {"rows_per_day": 1000},
+        "principals": {"alice": {"rows_per_hour": 2}}
+    }"#).unwrap());
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+    
+    let sql = "SELECT * FROM source('mock.users') LIMIT 2";
+    engine.execute_query_as("alice", sql).await?;
+    let error = engine.execute_query_as("alice", sql).await.unwrap_err();
+    assert!(matches!(error, NirvError::QuotaExceeded(_)), "{}", error);
+    engine.execute_query_as("bob", sql).await?;
+    
+    let usage = engine.execute_query("SELECT principal, rows_fetched, row_limit FROM source('system.usage') WHERE window = 'day'").await?;
+    let rows: Vec<String> = usage.rows.iter().map(|row| format!("{:?}", row.values)).collect();
+    assert_eq!(rows, vec![
+        "[Text(\"alice\"), Integer(2), Null]".to_string(),
+        "[Text(\"bob\"), Integer(2), Integer(1000)]".to_string(),
+    ]);
+    
+    Ok(())
+}
+
+/// Test watching source schemas alongside usage tracking in the system tables
+#[tokio::test]
+async fn test_engine_schema_watcher() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.quotas = Some(serde_json::from_str(r#"{"default": {}}"#).unwrap());
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+    
+    let watcher = engine.watch_schemas(std::time::Duration::from_secs(3600)).await?;
+    watcher.check().await;
+    assert!(watcher.check().await.is_empty(), "Unchanged schemas should not drift");
+    
+    let drift = engine.execute_query("SELECT * FROM source('system.schema_drift')").await?;
+    assert_eq!(drift.columns.len(), 7);
+    assert!(drift.rows.is_empty());
+    // The replaced system tables still report usage
+    engine.execute_query("SELECT * FROM source('system.usage')").await?;
+    
+    engine.shutdown().await
+}
+
+/// Test caching a federated query result in a file with CREATE TABLE AS
+#[tokio::test]
+async fn test_engine_create_table_as_select() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let written = engine.execute_query("CREATE TABLE source('file.cache/adults.csv') AS SELECT * FROM source('mock.users') WHERE age > 26").await?;
+    assert_eq!(written.affected_rows, Some(2));
+    assert!(written.rows.is_empty());
+    
+    let cached = engine.execute_query("SELECT * FROM source('file.cache/adults.csv')").await?;
+    assert_eq!(cached.rows.len(), 2);
+    
+    // The target must be registered before anything is fetched
+    let error = engine.execute_query("CREATE TABLE source('sqlite.cache.report') AS SELECT * FROM source('mock.users')").await.unwrap_err();
+    assert!(error.to_string().contains("sqlite"), "{}", error);
+    
+    Ok(())
+}
+
+/// Test removing rows duplicated across globbed files
+#[tokio::test]
+async fn test_engine_deduplicate_by() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("page1.csv"), "id,status,updated\n1,new,1\n2,new,1\n").unwrap();
+    std::fs::write(dir.path().join("page2.csv"), "id,status,updated\n2,paid,3\n1,new,1\n3,new,2\n").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    // Key columns need not be selected, and the limit counts deduplicated rows
+    let result = engine.execute_query(
+        "SELECT status FROM source('file.page*.csv') DEDUPLICATE BY (id) KEEP LAST ORDER BY updated LIMIT 2"
+    ).await?;
+    assert_eq!(result.columns.len(), 1);
+    let statuses: Vec<&Value> = result.rows.iter().map(|row| &row.values[0]).collect();
+    assert_eq!(statuses, vec![&Value::Text("paid".to_string()), &Value::Text("new".to_string())]);
+    
+    let all = engine.execute_query("SELECT * FROM source('file.page*.csv') DEDUPLICATE BY (id)").await?;
+    assert_eq!(all.rows.len(), 3);
+    
+    Ok(())
+}
+
+/// Test REGEXP predicates and regular expression functions over a file source
+#[tokio::test]
+async fn test_engine_regex_functions() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("app.csv"),
+        "id,line\n1,ERROR code=503 user=bob\n2,INFO user=ann\n3,WARN code=429 user=eve\n",
+    ).unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let result = engine.execute_query(
+        "SELECT id, REGEXP_EXTRACT(line, 'code=(\\d+)', 1) AS code, REGEXP_REPLACE(line, 'user=\\w+', 'user=?') AS redacted \
+         FROM source('file.app.csv') WHERE line REGEXP '^(ERROR|WARN) '"
+    ).await?;
+    let names: Vec<&str> = result.columns.iter().map(|col| col.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "code", "redacted"]);
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].values[1], Value::Text("503".to_string()));
+    assert_eq!(result.rows[1].values[2], Value::Text("WARN code=429 user=?".to_string()));
+    
+    // Predicates on a function's result are evaluated by the engine
+    let result = engine.execute_query(
+        "SELECT id FROM source('file.app.csv') WHERE REGEXP_EXTRACT(line, 'user=(\\w+)', 1) = 'ann'"
+    ).await?;
+    assert_eq!(result.columns.len(), 1);
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].values[0], Value::Integer(2));
+    
+    Ok(())
+}
+
+/// Test reconciling two sources without a shared key through a fuzzy join
+#[tokio::test]
+async fn test_engine_fuzzy_join() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("users.csv"), "id,name\n1,Jonathan Smith\n2,Maria Garcia\n3,Wei Chen\n").unwrap();
+    std::fs::write(dir.path().join("customers.csv"), "customer_name,tier\nMARIA GARCIA,gold\njonathon smith,silver\n").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let result = engine.execute_query(
+        "SELECT u.id, c.tier AS level FROM source('file.users.csv') u \
+         LEFT JOIN source('file.customers.csv') c ON FUZZY(u.name, c.customer_name, 0.9) ORDER BY u.id"
+    ).await?;
+    let names: Vec<&str> = result.columns.iter().map(|col| col.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "level"]);
+    let rows: Vec<(Value, Value)> = result.rows.iter().map(|row| (row.values[0].clone(), row.values[1].clone())).collect();
+    assert_eq!(rows, vec![
+        (Value::Integer(1), Value::Text("silver".to_string())),
+        (Value::Integer(2), Value::Text("gold".to_string())),
+        (Value::Integer(3), Value::Null),
+    ]);
+    
+    let matched = engine.execute_query(
+        "SELECT name FROM source('file.users.csv') u JOIN source('file.customers.csv') c \
+         ON FUZZY(u.name, c.customer_name, 0.9) WHERE c.tier = 'gold'"
+    ).await?;
+    assert_eq!(matched.rows.len(), 1);
+    assert_eq!(matched.rows[0].values[0], Value::Text("Maria Garcia".to_string()));
+    
+    Ok(())
+}
+
+/// Test pushing each join predicate to the source its alias names
+#[tokio::test]
+async fn test_engine_join_predicate_routing() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("users.csv"), "id,name,age\n1,Ann,25\n2,Bo,35\n3,Cy,40\n").unwrap();
+    std::fs::write(dir.path().join("orders.csv"), "id,user_id,total\n1,2,50\n2,3,5\n3,2,20\n").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let sql = "SELECT u.name, o.total FROM source('file.users.csv') u JOIN source('file.orders.csv') o ON u.id = o.user_id \
+               WHERE u.age > 30 AND o.total > 10";
+    let inner = engine.execute_query(sql).await?;
+    assert_eq!(inner.rows.len(), 2);
+    let stats = inner.stats.expect("statistics");
+    let pushed: Vec<(&str, usize)> = stats.sources.iter().map(|source| (source.source.as_str(), source.pushed_predicates)).collect();
+    assert_eq!(pushed, vec![("file.users.csv", 1), ("file.orders.csv", 1)]);
+    
+    // A LEFT JOIN keeps users without orders, so the orders predicate filters the joined rows
+    let left = engine.execute_query(&sql.replace(" JOIN", " LEFT JOIN")).await?;
+    assert_eq!(left.rows.len(), 2);
+    let stats = left.stats.expect("statistics");
+    assert_eq!(stats.sources[1].pushed_predicates, 0);
+    
+    let ambiguous = engine.execute_query(&sql.replace("u.age > 30", "id = 1")).await.unwrap_err().to_string();
+    assert!(ambiguous.contains("u.id") && ambiguous.contains("o.id"), "{}", ambiguous);
+    let unknown = engine.execute_query(&sql.replace("u.age", "x.age")).await.unwrap_err().to_string();
+    assert!(unknown.contains("'x'") && unknown.contains("u and o"), "{}", unknown);
+    
+    Ok(())
+}
+
+/// Test answering a join from the side that succeeded when the other source fails
+#[tokio::test]
+async fn test_engine_partial_results() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("users.csv"), "id,name\n1,Ann\n2,Bo\n").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config.clone());
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let sql = "SELECT u.id, o.total FROM source('file.users.csv') u \
+               LEFT JOIN source('file.orders.csv') o ON u.id = o.user_id ORDER BY u.id";
+    assert!(engine.execute_query(sql).await.is_err());
+    
+    // The missing side joins with no rows, and the result says which source is missing
+    let partial = engine.execute_query(&sql.replace("SELECT", "SELECT /*+ PARTIAL_RESULTS */")).await?;
+    let names: Vec<&str> = partial.columns.iter().map(|col| col.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "total"]);
+    let rows: Vec<(Value, Value)> = partial.rows.iter().map(|row| (row.values[0].clone(), row.values[1].clone())).collect();
+    assert_eq!(rows, vec![(Value::Integer(1), Value::Null), (Value::Integer(2), Value::Null)]);
+    assert_eq!(partial.warnings.len(), 1);
+    assert_eq!(partial.warnings[0].source, "file.orders.csv");
+    
+    // Configured for every query; a query with no source left still fails
+    config.dispatcher.partial_results = true;
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    let inner = engine.execute_query(&sql.replace("LEFT JOIN", "JOIN")).await?;
+    assert!(inner.rows.is_empty());
+    assert_eq!(inner.warnings.len(), 1);
+    assert!(engine.execute_query(&sql.replace("users.csv", "people.csv")).await.is_err());
+    
+    Ok(())
+}
+
+/// Test that a result cut short by a source policy's max_limit says so
+#[tokio::test]
+async fn test_engine_truncation_warning() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("users.csv"), "id,name\n1,Ann\n2,Bo\n3,Cy\n").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    engine.set_source_policy("file", SourcePolicy { max_limit: Some(2), ..SourcePolicy::default() }).await;
+    
+    let capped = engine.execute_query("SELECT * FROM source('file.users.csv')").await?;
+    assert_eq!(capped.rows.len(), 2);
+    assert_eq!(capped.warnings.len(), 1);
+    assert_eq!(capped.warnings[0].kind, WarningKind::Truncated);
+    assert_eq!(capped.warnings[0].to_string(), "file.users.csv returned only the first 2 rows, the max_limit of its source policy");
+    
+    // A LIMIT of the query's own, or fewer rows than the cap, is no surprise
+    assert!(engine.execute_query("SELECT * FROM source('file.users.csv') LIMIT 2").await?.warnings.is_empty());
+    assert!(engine.execute_query("SELECT * FROM source('file.users.csv') WHERE id = 3").await?.warnings.is_empty());
+    
+    Ok(())
+}
+
+/// Test that a session parses queries in the dialect of the protocol its client connected through
+#[tokio::test]
+async fn test_engine_session_dialect() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("users.csv"), "id,name\n1,Ann\n2,Bo\n3,Cy\n").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let sql = "SELECT TOP 2 [name] FROM source('file.users.csv') WHERE [id] > 1";
+    let mut session = Session::for_protocol(&ProtocolType::SqlServer);
+    let result = engine.execute_session_query(&mut session, sql).await?;
+    let index = result.columns.iter().position(|col| col.name == "name").unwrap();
+    let names: Vec<&Value> = result.rows.iter().map(|row| &row.values[index]).collect();
+    assert_eq!(names, vec![&Value::Text("Bo".to_string()), &Value::Text("Cy".to_string())]);
+    let result = engine.execute_session_query(&mut session, "SELECT TOP 1 * FROM source('file.users.csv')").await?;
+    assert_eq!(result.rows.len(), 1);
+    
+    // Other clients' SQL is parsed as before
+    assert!(engine.execute_session_query(&mut Session::for_protocol(&ProtocolType::MySQL), sql).await.is_err());
+    
+    Ok(())
+}
+
+/// Test running the statements a client sends in one message one after another
+#[tokio::test]
+async fn test_engine_session_statements() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+    
+    let mut session = Session::for_protocol(&ProtocolType::MySQL);
+    let sql = "SET TIME ZONE '+02:00'; SELECT * FROM source('mock.users') LIMIT 2; SELECT * FROM nowhere; SELECT * FROM source('mock.users')";
+    let results = engine.execute_session_statements(&mut session, sql).await;
+    
+    // The failed third statement ends the batch
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].command(), "SET");
+    assert!(session.time_zone().is_some());
+    assert_eq!(results[1].sql, "SELECT * FROM source('mock.users') LIMIT 2");
+    assert_eq!(results[1].result.as_ref().unwrap().rows.len(), 2);
+    assert!(results[2].result.is_err());
+    
+    Ok(())
+}
+
+/// Test joining, filtering and sorting keys that two systems spell in different cases
+#[tokio::test]
+async fn test_engine_collation() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("accounts.csv"), "account,owner
+ACME Corp,Émile
+Globex,zoe
+Initech,Anna
+").unwrap();
+    std::fs::write(dir.path().join("invoices.csv"), "customer,amount
+acme corp,100
+GLOBEX,250
+").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let join = "SELECT a.account, i.amount FROM source('file.accounts.csv') a \
+                JOIN source('file.invoices.csv') i ON a.account = i.customer ORDER BY a.account";
+    assert!(engine.execute_query(join).await?.rows.is_empty());
+    let collated = engine.execute_query(&join.replace("= i.customer", "= i.customer COLLATE nocase")).await?;
+    let accounts: Vec<&Value> = collated.rows.iter().map(|row| &row.values[0]).collect();
+    assert_eq!(accounts, vec![&Value::Text("ACME Corp".to_string()), &Value::Text("Globex".to_string())]);
+    
+    let filtered = engine.execute_query(
+        "SELECT owner FROM source('file.accounts.csv') WHERE account COLLATE nocase = 'initech'"
+    ).await?;
+    assert_eq!(filtered.rows.len(), 1);
+    assert_eq!(filtered.rows[0].values[0], Value::Text("Anna".to_string()));
+    
+    // A source's collation applies when the query names none
+    engine.set_source_collation("file", nirv_engine::utils::Collation::Unicode).await;
+    assert_eq!(engine.execute_query(join).await?.rows.len(), 2);
+    let sorted = engine.execute_query("SELECT account, owner FROM source('file.accounts.csv') ORDER BY owner").await?;
+    let owners: Vec<&Value> = sorted.rows.iter().map(|row| &row.values[1]).collect();
+    assert_eq!(owners, vec![
+        &Value::Text("Anna".to_string()),
+        &Value::Text("Émile".to_string()),
+        &Value::Text("zoe".to_string()),
+    ]);
+    let binary = engine.execute_query("SELECT account, owner FROM source('file.accounts.csv') ORDER BY owner COLLATE binary").await?;
+    assert_eq!(binary.rows[2].values[1], Value::Text("Émile".to_string()));
+    
+    Ok(())
+}
+
+/// Test verifying a migrated table against its origin with one checksum query per side
+#[tokio::test]
+async fn test_engine_checksum_table() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("origin.csv"), "id,name,score\n1,Ann,10\n2,Bo,20\n3,Cy,30\n").unwrap();
+    std::fs::write(dir.path().join("migrated.csv"), "name,id,score\nCy,3,30\nAnn,1,10\nBo,2,20\n").unwrap();
+    std::fs::write(dir.path().join("drifted.csv"), "id,name,score\n1,Ann,10\n2,Bo,21\n3,Cy,30\n").unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut file_connector = Box::new(nirv_engine::connectors::FileConnector::new());
+    file_connector.connect(ConnectorInitConfig::new().with_param("base_path", dir.path().to_str().unwrap())).await?;
+    engine.register_connector("file", file_connector).await?;
+    
+    let origin = engine.execute_query("CHECKSUM TABLE source('file.origin.csv')").await?;
+    let names: Vec<&str> = origin.columns.iter().map(|col| col.name.as_str()).collect();
+    assert_eq!(names, vec!["checksum", "row_count"]);
+    assert_eq!(origin.rows[0].values[1], Value::Integer(3));
+    
+    // Row and column order do not matter, a changed value does
+    let migrated = engine.execute_query("CHECKSUM TABLE source('file.migrated.csv')").await?;
+    assert_eq!(migrated.rows[0].values, origin.rows[0].values);
+    let drifted = engine.execute_query("CHECKSUM TABLE source('file.drifted.csv')").await?;
+    assert_ne!(drifted.rows[0].values[0], origin.rows[0].values[0]);
+    
+    // Row hashes locate the rows that differ
+    let hashes = engine.execute_query(
+        "SELECT id, MD5(name) AS name_hash FROM source('file.drifted.csv') WHERE MD5(name) = 'b0d8913d4dfb85c2325c0773ff52ae98'"
+    ).await?;
+    assert_eq!(hashes.rows.len(), 1);
+    assert_eq!(hashes.rows[0].values[0], Value::Integer(2));
+    let hashes = engine.execute_query("SELECT id, hash_row() AS row_hash FROM source('file.origin.csv') WHERE id = 2").await?;
+    let drifted = engine.execute_query("SELECT id, hash_row() AS row_hash FROM source('file.drifted.csv') WHERE id = 2").await?;
+    assert_eq!(hashes.rows.len(), 1);
+    assert_ne!(hashes.rows[0].values[1], drifted.rows[0].values[1]);
+    
+    Ok(())
+}
+
+/// Test a session time zone rendering times and truncating dates, over a source whose
+/// times are stored without an offset in another zone
+#[tokio::test]
+async fn test_engine_session_time_zone() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data_with_columns("events", vec!["id", "created_at"], vec![
+        vec![Value::Integer(1), Value::DateTime("2024-03-01 02:30:00".to_string())],
+        vec![Value::Integer(2), Value::DateTime("2024-03-01T12:00:00+02:00".to_string())],
+    ]);
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("ledger", Box::new(connector)).await?;
+    engine.set_source_time_zone("ledger", nirv_engine::utils::SessionTimeZone::parse("+01:00")?).await;
+    
+    // Without a session time zone, times are read into UTC and shown as such
+    let sql = "SELECT id, created_at, DATE_TRUNC('day', created_at) AS day FROM source('ledger.events')";
+    let utc = engine.execute_query(sql).await?;
+    assert_eq!(utc.rows[0].values[1], Value::DateTime("2024-03-01 01:30:00".to_string()));
+    assert_eq!(utc.rows[1].values[1], Value::DateTime("2024-03-01 10:00:00".to_string()));
+    assert_eq!(utc.rows[0].values[2], Value::DateTime("2024-03-01 00:00:00".to_string()));
+    
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "SET TIME ZONE '-05:00'").await?;
+    let local = engine.execute_session_query(&mut session, sql).await?;
+    assert_eq!(local.rows[0].values[1], Value::DateTime("2024-02-29 20:30:00-05:00".to_string()));
+    assert_eq!(local.rows[0].values[2], Value::DateTime("2024-02-29 00:00:00-05:00".to_string()));
+    assert_eq!(local.rows[1].values[2], Value::DateTime("2024-03-01 00:00:00-05:00".to_string()));
+    let now = engine.execute_session_query(&mut session, "SELECT NOW() FROM source('ledger.events')").await?;
+    assert!(matches!(&now.rows[0].values[0], Value::DateTime(now) if now.ends_with("-05:00")));
+    
+    assert!(engine.execute_session_query(&mut session, "SET TIME ZONE 'Mars/Olympus'").await.is_err());
+    engine.execute_session_query(&mut session, "RESET timezone").await?;
+    let reset = engine.execute_session_query(&mut session, sql).await?;
+    assert_eq!(reset.rows[0].values, utc.rows[0].values);
+    
+    Ok(())
+}
+
+/// Test cache control statements setting a session cache TTL and refreshing sources
+#[tokio::test]
+async fn test_engine_cache_control() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data("users", vec![vec![Value::Integer(1)]]);
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+    
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "SET nirv.cache = off").await?;
+    assert_eq!(session.cache_ttl(), Some(std::time::Duration::ZERO));
+    engine.execute_session_query(&mut session, "SET nirv.cache TO '10m'").await?;
+    assert_eq!(session.cache_ttl(), Some(std::time::Duration::from_secs(600)));
+    assert!(engine.execute_session_query(&mut session, "SET nirv.cache = 'soon'").await.is_err());
+    engine.execute_session_query(&mut session, "RESET nirv.cache").await?;
+    assert_eq!(session.cache_ttl(), None);
+    
+    // The mock connector caches nothing, so refreshing drops no entries
+    let refreshed = engine.execute_session_query(&mut session, "REFRESH SOURCE 'mock.users'").await?;
+    assert_eq!(refreshed.affected_rows, Some(0));
+    assert!(engine.execute_query("REFRESH SOURCE 'nowhere.users'").await.is_err());
+    // Outside a session there is nothing for the setting to last for
+    assert!(engine.execute_query("SET nirv.cache = off").await.is_err());
+    
+    let cached = engine.execute_query("CACHE SELECT * FROM source('mock.users') FOR 10 MINUTES").await?;
+    assert_eq!(cached.rows.len(), 1);
+    
+    Ok(())
+}
+
+/// Test roles granted with GRANT and REVOKE limiting what users may query
+#[tokio::test]
+async fn test_engine_role_based_access() -> NirvResult<()> {
+    let path = std::env::temp_dir().join(format!("nirv_engine_access_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.security.authorization.enabled = true;
+    config.security.authorization.catalog_path = Some(path.to_string_lossy().to_string());
+    
+    let mut engine = Engine::new(config.clone());
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data("users", vec![vec![Value::Integer(1)]]);
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+    
+    // Trusted callers administer the catalog until an admin role exists
+    for sql in [
+        "CREATE ROLE analyst", "GRANT SELECT ON 'mock' TO ROLE analyst", "GRANT ROLE analyst TO USER ann",
+        "CREATE ROLE dba", "GRANT ALL ON '*' TO dba", "GRANT ADMIN TO dba", "GRANT ROLE dba TO USER root",
+    ] {
+        engine.execute_query(sql).await?;
+    }
+    
+    let select = "SELECT * FROM source('mock.users')";
+    assert_eq!(engine.execute_query_as("ann", select).await?.rows.len(), 1);
+    let denied = engine.execute_query_as("bob", select).await.unwrap_err();
+    assert!(denied.to_string().contains("Access denied"), "{}", denied);
+    let delete = "DELETE FROM source('mock.users') WHERE id = 1";
+    let denied = engine.execute_query_as("ann", delete).await.unwrap_err();
+    assert!(matches!(denied, NirvError::Dispatcher(DispatcherError::AccessDenied(_))), "{}", denied);
+    // A user granted DELETE gets past the check to the connector, which does not write
+    let unsupported = engine.execute_query_as("root", delete).await.unwrap_err();
+    assert!(matches!(unsupported, NirvError::Connector(ConnectorError::UnsupportedOperation(_))), "{}", unsupported);
+    assert!(engine.execute_query_as("ann", "GRANT ROLE analyst TO USER bob").await.is_err());
+    
+    let mut session = Session::new().with_user("root");
+    engine.execute_session_query(&mut session, "GRANT ROLE analyst TO USER bob").await?;
+    engine.execute_session_query(&mut session, "REVOKE SELECT ON 'mock' FROM ROLE analyst").await?;
+    assert!(engine.execute_query_as("ann", select).await.is_err());
+    
+    // Grants outlive the engine
+    let mut restarted = Engine::new(config);
+    restarted.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data("users", vec![vec![Value::Integer(1)]]);
+    connector.connect(ConnectorInitConfig::new()).await?;
+    restarted.register_connector("mock", Box::new(connector)).await?;
+    assert!(restarted.execute_query_as("bob", select).await.is_err());
+    assert_eq!(restarted.execute_query_as("root", select).await?.rows.len(), 1);
+    
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test column lineage explained for a query and recorded for executed ones
+#[tokio::test]
+async fn test_engine_column_lineage() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.lineage = Some(LineageConfig::default());
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+    
+    let explained = engine.execute_query("EXPLAIN LINEAGE SELECT name AS customer, price FROM source('mock.users') u JOIN source('mock.products') p ON u.id = p.id").await?;
+    let text = |value: &Value| match value {
+        Value::Text(text) => text.clone(),
+        other => format!("{:?}", other),
+    };
+    let rows: Vec<Vec<String>> = explained.rows.iter().map(|row| row.values.iter().map(text).collect()).collect();
+    assert_eq!(rows, vec![
+        vec!["customer", "mock", "users", "name"],
+        vec!["customer", "mock", "products", "name"],
+        vec!["price", "mock", "products", "price"],
+    ]);
+    assert!(engine.lineage_log().unwrap().records().is_empty());
+    
+    engine.execute_query_as("ann", "SELECT * FROM source('mock.users')").await?;
+    let lineage = engine.execute_query("SELECT user_name, output_column, column_name FROM source('system.lineage')").await?;
+    assert_eq!(lineage.rows.len(), 5);
+    assert_eq!(lineage.rows[0].values, vec![Value::Text("ann".to_string()), Value::Text("id".to_string()), Value::Text("id".to_string())]);
+    Ok(())
+}
+
+/// Test DESCRIBE listing configured column descriptions and tags
+#[tokio::test]
+async fn test_engine_describe_columns() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.connectors.insert("mock".to_string(), ConnectorConfig {
+        connector_type: ConnectorType::Mock,
+        connection_string: None,
+        parameters: HashMap::new(),
+        pool_config: None,
+        timeout_config: None,
+        policy: SourcePolicy { denied_tables: vec!["products".to_string()], ..Default::default() },
+        time_zone: None,
+        collation: None,
+        fetch_size: None,
+        columns: HashMap::from([("users.email".to_string(), ColumnDetails {
+            description: Some("Where receipts go".to_string()),
+            tags: vec!["pii".to_string()],
+            native_type: None,
+        })]),
+    });
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+    
+    let described = engine.execute_query("DESCRIBE source('mock.users')").await?;
+    let email = described.rows.iter().find(|row| row.values[0] == Value::Text("email".to_string())).unwrap();
+    assert_eq!(email.values[4], Value::Text("Where receipts go".to_string()));
+    assert_eq!(email.values[5], Value::Text("pii".to_string()));
+    assert_eq!(described.rows.iter().filter(|row| row.values[4] != Value::Null).count(), 1);
+    
+    // Objects a query could not read cannot be described either
+    assert!(engine.execute_query("DESCRIBE source('mock.products')").await.is_err());
+    Ok(())
+}
+
+/// Test join conditions suggested from the keys and column names of two sources
+#[tokio::test]
+async fn test_engine_suggest_joins() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data_with_columns("orders", vec!["order_id", "user_id", "total"], vec![
+        vec![Value::Integer(10), Value::Integer(1), Value::Float(99.5)],
+    ]);
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+    
+    let result = engine.execute_query("SUGGEST JOINS source('mock.orders') o, source('mock.users') u").await?;
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].values[2], Value::Text("key_name".to_string()));
+    assert_eq!(result.rows[0].values[3], Value::Text("o.user_id = u.id".to_string()));
+    assert_eq!(result.rows[0].values[4], Value::Null);
+    
+    let suggestions = engine.suggest_joins("mock.users", "mock.orders").await?;
+    assert_eq!((suggestions[0].left_columns[0].as_str(), suggestions[0].right_columns[0].as_str()), ("id", "user_id"));
+    assert!(engine.suggest_joins("mock.users", "mock.missing").await.is_err());
+    Ok(())
+}
+
+/// Test lint findings reported with the result, refused in strict mode and tolerated when allowed
+#[tokio::test]
+async fn test_engine_query_lint() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.lint.allow.push(LintRule::FullScan);
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+    
+    let result = engine.execute_query("SELECT name FROM source('mock.users') WHERE age >= '30'").await?;
+    let lint: Vec<_> = result.warnings.iter().filter(|warning| warning.kind == WarningKind::Lint).collect();
+    assert_eq!(lint.len(), 1);
+    assert!(lint[0].message.ends_with("(implicit_coercion)"), "{}", lint[0]);
+    assert!(engine.execute_query("SELECT name FROM source('mock.users') WHERE age >= 30").await?.warnings.is_empty());
+    
+    let error = engine.execute_query(
+        "-- nirv: lint=strict\nSELECT u.name FROM source('mock.users') u, source('mock.products') p"
+    ).await.unwrap_err();
+    assert!(matches!(&error, NirvError::Lint(message) if message.contains("cross_join")), "{}", error);
+    // Allowed rules do not stop a strict query
+    engine.execute_query("-- nirv: lint=strict\nSELECT name FROM source('mock.users') WHERE MD5(name) = 'x'").await?;
+    
+    // A CROSS JOIN runs, pairing every row of both sources, and is flagged
+    let users = engine.execute_query("SELECT * FROM source('mock.users')").await?.row_count();
+    let products = engine.execute_query("SELECT * FROM source('mock.products')").await?.row_count();
+    let result = engine.execute_query("SELECT u.name, p.name FROM source('mock.users') u CROSS JOIN source('mock.products') p").await?;
+    assert!(users > 1 && products > 1);
+    assert_eq!(result.row_count(), users * products);
+    assert!(result.warnings.iter().any(|warning| warning.message.ends_with("(cross_join)")), "{:?}", result.warnings);
+    Ok(())
+}
+
+/// Test queries scheduled by the priority of their user, hint or directive
+#[tokio::test]
+async fn test_engine_query_priorities() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.dispatcher.max_concurrent_queries = 2;
+    config.dispatcher.user_priorities.insert("exporter".to_string(), QueryPriority::Batch);
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data("users", vec![vec![Value::Integer(1)]]);
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+    
+    let scheduler = engine.scheduler().clone();
+    let hints = |sql: &str| DefaultQueryParser::new().unwrap().parse(sql).unwrap().hints;
+    assert_eq!(scheduler.priority(&hints("SELECT * FROM source('mock.users')"), Some("exporter")), QueryPriority::Batch);
+    assert_eq!(scheduler.priority(&hints("SELECT /*+ PRIORITY(interactive) */ * FROM source('mock.users')"), Some("exporter")), QueryPriority::Interactive);
+    
+    // With the only batch slot taken, batch queries wait while interactive ones run
+    let held = scheduler.admit(QueryPriority::Batch).await;
+    assert_eq!(engine.execute_query("SELECT * FROM source('mock.users')").await?.rows.len(), 1);
+    let export = "-- nirv: priority=batch\nSELECT * FROM source('mock.users')";
+    let waiting = tokio::time::timeout(std::time::Duration::from_millis(100), engine.execute_query(export)).await;
+    assert!(waiting.is_err());
+    drop(held);
+    assert_eq!(engine.execute_query(export).await?.rows.len(), 1);
+    assert_eq!(scheduler.running(QueryPriority::Batch), 0);
+    
+    Ok(())
+}
+
+/// Test validating queries against connector schemas without executing them
+#[tokio::test]
+async fn test_engine_dry_run() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+    
+    let report = engine.dry_run("SELECT name AS n FROM source('mock.users') WHERE Age > 18 ORDER BY n LIMIT 5").await;
+    assert!(report.is_valid(), "unexpected errors: {:?}", report.errors);
+    let plan = report.plan.expect("plan").to_string();
+    assert!(plan.contains("Limit 5"));
+    assert!(plan.contains("TableScan mock.users [name AS n] WHERE Age > 18"));
+    
+    let report = engine.dry_run("SELECT nickname FROM source('mock.users') WHERE \"Age\" > 18").await;
+    assert_eq!(report.errors, vec![
+        "Column 'nickname' does not exist in 'users'".to_string(),
+        "Column '\"Age\"' does not exist in 'users'".to_string(),
+    ]);
+    assert!(report.plan.is_some());
+    
+    let report = engine.dry_run("SELECT * FROM source('missing.users')").await;
+    assert!(!report.is_valid());
+    assert!(report.errors[0].contains("missing"));
+    
+    let report = engine.dry_run("SELEC * FROM").await;
+    assert!(!report.is_valid());
+    assert!(report.plan.is_none());
+
+    Ok(())
+}
+
+/// Test plans serialized to JSON and read back
+#[tokio::test]
+async fn test_engine_plan_to_json() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+
+    let json = engine.plan_to_json("SELECT name AS n FROM source('mock.users') WHERE age > 18 ORDER BY n LIMIT 5").await?;
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let root = value["nodes"].as_array().and_then(|nodes| nodes.last()).expect("root node");
+    assert_eq!(root["node"], "limit");
+    assert_eq!(root["count"], 5);
+
+    let plan: ExecutionPlan = serde_json::from_str(&json).unwrap();
+    let scan = plan.nodes.iter().find_map(|node| match node {
+        PlanNode::TableScan { source, predicates, .. } => Some((source, predicates)),
+        _ => None,
+    }).expect("table scan");
+    assert_eq!(scan.0.identifier, "users");
+    assert_eq!(scan.1[0].column, "age");
+    let report = engine.dry_run("SELECT name AS n FROM source('mock.users') WHERE age > 18 ORDER BY n LIMIT 5").await;
+    assert_eq!(plan.to_string(), report.plan.expect("plan").to_string());
+
+    assert!(engine.plan_to_json("SELEC * FROM").await.is_err());
+
+    Ok(())
+}
+
+/// Test hints overriding predicate pushdown and capping the rows of a query
+#[tokio::test]
+async fn test_engine_query_hints() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data_with_columns("orders", vec!["id", "status"], (1..=10)
+        .map(|i| vec![Value::Integer(i), Value::Text(if i % 2 == 0 { "paid" } else { "new" }.to_string())])
+        .collect());
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+
+    let sql = "SELECT id FROM source('mock.orders') WHERE status = 'paid' ORDER BY id";
+    let pushed = engine.execute_query(sql).await?;
+    let stats = pushed.stats.expect("statistics");
+    assert_eq!((stats.sources[0].pushed_predicates, stats.sources[0].local_predicates), (1, 0));
+
+    let hinted = engine.execute_query(&sql.replace("SELECT", "SELECT /*+ NO_PUSHDOWN(predicates), MAX_ROWS(3) */")).await?;
+    let ids: Vec<&Value> = hinted.rows.iter().map(|row| &row.values[0]).collect();
+    assert_eq!(ids, vec![&Value::Integer(2), &Value::Integer(4), &Value::Integer(6)]);
+    let stats = hinted.stats.expect("statistics");
+    assert_eq!((stats.sources[0].pushed_predicates, stats.sources[0].local_predicates), (0, 1));
+    assert_eq!(stats.sources[0].rows_scanned, 10);
+
+    // A LIMIT tighter than MAX_ROWS still applies, and the plan shows the hints' effect
+    let limited = engine.execute_query("SELECT /*+ MAX_ROWS(3) */ id FROM source('mock.orders') LIMIT 2").await?;
+    assert_eq!(limited.rows.len(), 2);
+    let report = engine.dry_run(&sql.replace("SELECT", "SELECT /*+ NO_PUSHDOWN(predicates), MAX_ROWS(3) */")).await;
+    let plan = report.plan.expect("plan").to_string();
+    assert!(plan.contains("Limit 3"));
+    assert!(plan.contains("Filter (local) status = 'paid'"));
+
+    assert!(engine.execute_query("SELECT /*+ MAX_ROWS(lots) */ id FROM source('mock.orders')").await.is_err());
+    
+    // A `pushdown=off` directive in a comment keeps predicates from the source like the hint
+    let directed = engine.execute_query(&format!("-- nirv: pushdown=off\n{}", sql)).await?;
+    assert_eq!(directed.rows.len(), 5);
+    let stats = directed.stats.expect("statistics");
+    assert_eq!((stats.sources[0].pushed_predicates, stats.sources[0].local_predicates), (0, 1));
+    let plan = engine.dry_run(&format!("{} /* nirv: pushdown=off */", sql)).await.plan.expect("plan").to_string();
+    assert!(plan.contains("Filter (local) status = 'paid'"));
+    assert!(engine.execute_query(&format!("{} -- nirv: pushdown=maybe", sql)).await.is_err());
+
+    Ok(())
+}
+
+/// Test answering COUNT(*) from the source's own count, or by counting fetched rows
+#[tokio::test]
+async fn test_engine_count_all() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data_with_columns("orders", vec!["id", "status"], (1..=10)
+        .map(|i| vec![Value::Integer(i), Value::Text(if i % 2 == 0 { "paid" } else { "new" }.to_string())])
+        .collect());
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+
+    let counted = engine.execute_query("SELECT COUNT(*) AS paid FROM source('mock.orders') WHERE status = 'paid' ORDER BY id LIMIT 1").await?;
+    assert_eq!(counted.columns[0].name, "paid");
+    assert_eq!(counted.count_value(), Some(5));
+    let stats = counted.stats.expect("statistics");
+    assert_eq!(stats.sources[0].rows_scanned, 0);
+
+    // Predicates the source cannot evaluate fall back to counting the fetched rows
+    let fetched = engine.execute_query("SELECT /*+ NO_PUSHDOWN(predicates) */ COUNT(*) FROM source('mock.orders') WHERE status = 'paid'").await?;
+    assert_eq!(fetched.columns[0].name, "count");
+    assert_eq!(fetched.count_value(), Some(5));
+    assert_eq!(fetched.stats.expect("statistics").sources[0].rows_scanned, 10);
+
+    assert!(engine.execute_query("SELECT id, COUNT(*) FROM source('mock.orders')").await.is_err());
+
+    Ok(())
+}
+
+/// Test GROUP BY by a select-list alias, a position and an expression
+#[tokio::test]
+async fn test_engine_group_by() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data_with_columns("orders", vec!["id", "status", "created"], (1..=10)
+        .map(|i| vec![
+            Value::Integer(i),
+            Value::Text(if i % 2 == 0 { "paid" } else { "new" }.to_string()),
+            Value::Text(format!("2024-0{}-15", if i <= 3 { 1 } else { 2 })),
+        ])
+        .collect());
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+
+    let values = |result: &QueryResult| -> Vec<Vec<Value>> { result.rows.iter().map(|row| row.values.clone()).collect() };
+    let by_alias = engine.execute_query(
+        "SELECT status AS state, COUNT(*) AS orders FROM source('mock.orders') GROUP BY state ORDER BY status DESC"
+    ).await?;
+    let names: Vec<&str> = by_alias.columns.iter().map(|col| col.name.as_str()).collect();
+    assert_eq!(names, vec!["state", "orders"]);
+    assert_eq!(values(&by_alias), vec![
+        vec![Value::Text("paid".to_string()), Value::Integer(5)],
+        vec![Value::Text("new".to_string()), Value::Integer(5)],
+    ]);
+
+    let by_position = engine.execute_query(
+        "SELECT DATE_TRUNC('month', created) AS month, approx_count_distinct(status) AS statuses FROM source('mock.orders') WHERE id > 1 GROUP BY 1"
+    ).await?;
+    assert_eq!(by_position.rows.len(), 2);
+    assert_eq!(by_position.rows[0].values[1], Value::Integer(2));
+
+    // An expression groups by itself, whether or not it is selected
+    let by_expression = engine.execute_query(
+        "SELECT COUNT(*) FROM source('mock.orders') GROUP BY DATE_TRUNC('month', created)"
+    ).await?;
+    assert_eq!(values(&by_expression), vec![vec![Value::Integer(3)], vec![Value::Integer(7)]]);
+
+    assert!(engine.execute_query("SELECT status, id FROM source('mock.orders') GROUP BY status").await.is_err());
+    assert!(engine.execute_query("SELECT status, COUNT(*) FROM source('mock.orders') GROUP BY 3").await.is_err());
+
+    Ok(())
+}
+
+/// Test routing the sources of derived tables, alone and joined
+#[tokio::test]
+async fn test_engine_derived_tables() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let mut connector = MockConnector::new();
+    connector.add_test_data_with_columns("orders", vec!["id", "user_id", "total"], vec![
+        vec![Value::Integer(1), Value::Integer(1), Value::Integer(50)],
+        vec![Value::Integer(2), Value::Integer(2), Value::Integer(5)],
+        vec![Value::Integer(3), Value::Integer(3), Value::Integer(70)],
+    ]);
+    connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", Box::new(connector)).await?;
+
+    let derived = engine.execute_query(
+        "SELECT name FROM (SELECT id, name FROM source('mock.users') WHERE id > 1) AS t WHERE id < 3"
+    ).await?;
+    assert_eq!(derived.rows.len(), 1);
+    assert_eq!(derived.rows[0].values, vec![Value::Text("Bob Smith".to_string())]);
+
+    let joined = engine.execute_query(
+        "SELECT u.name, o.total FROM source('mock.users') u \
+         JOIN (SELECT user_id, total FROM source('mock.orders') WHERE total > 10) AS o ON u.id = o.user_id"
+    ).await?;
+    let names: Vec<&Value> = joined.rows.iter().map(|row| &row.values[0]).collect();
+    assert_eq!(names, vec![&Value::Text("Alice Johnson".to_string()), &Value::Text("Charlie Brown".to_string())]);
+
+    // A source inside a derived table is routed like any other
+    let unregistered = engine.execute_query("SELECT * FROM (SELECT * FROM source('nowhere.t')) AS t").await;
+    assert!(unregistered.unwrap_err().to_string().contains("nowhere"));
+
+    Ok(())
+}
+
 /// Test engine query execution with invalid SQL
 #[tokio::test]
 async fn test_engine_query_execution_invalid_sql() -> NirvResult<()> {
@@ -231,6 +1334,18 @@ async fn test_engine_configuration_validation() -> NirvResult<()> {
         connectors: HashMap::new(),
         dispatcher: DispatcherConfig::default(),
         security: SecurityConfig::default(),
+        query_rewriters: Vec::new(),
+        result_transformers: Vec::new(),
+        http_api: None,
+        profiles: HashMap::new(),
+        history: Default::default(),
+        quotas: None,
+        schema_drift: None,
+        lineage: None,
+        runtime: Default::default(),
+        components: Default::default(),
+        fallbacks: HashMap::new(),
+        lint: LintConfig::default(),
     };
     
     let mut engine = Engine::new(minimal_config);
@@ -307,7 +1422,7 @@ async fn test_engine_component_integration() -> NirvResult<()> {
     
     // Verify the result structure
     assert!(!result.is_empty(), "Query should return results");
-    assert!(result.columns.len() > 0, "Result should have column metadata");
+    assert!(!result.columns.is_empty(), "Result should have column metadata");
     assert!(result.execution_time.as_millis() > 0, "Execution time should be recorded");
     
     // Verify that the query was processed through all components
@@ -362,30 +1477,72 @@ async fn test_engine_concurrent_queries() -> NirvResult<()> {
     Ok(())
 }
 
+/// Test one connector instance serving two engines while queries run and a connector is replaced
+#[tokio::test]
+async fn test_engine_shared_connector_instances() -> NirvResult<()> {
+    let mut mock_connector = MockConnector::new();
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    let shared: Arc<dyn Connector> = Arc::new(mock_connector);
+    
+    let mut engines = Vec::new();
+    for _ in 0..2 {
+        let mut config = EngineConfig::default();
+        config.protocol_adapters.clear();
+        let mut engine = Engine::new(config);
+        engine.initialize_for_testing().await?;
+        engine.register_shared_connector("mock", shared.clone()).await?;
+        engines.push(Arc::new(engine));
+    }
+    assert_eq!(Arc::strong_count(&shared), 3);
+    
+    let mut handles = Vec::new();
+    for i in 0..20 {
+        let engine = engines[i % 2].clone();
+        handles.push(tokio::spawn(async move {
+            engine.execute_query("SELECT * FROM source('mock.users') LIMIT 2").await
+        }));
+    }
+    
+    // Replacing the first engine's connector leaves running queries and the other engine untouched
+    let mut reconnected = Box::new(MockConnector::new());
+    reconnected.connect(ConnectorInitConfig::new()).await?;
+    engines[0].replace_connector("mock", reconnected).await?;
+    
+    for handle in handles {
+        let result = handle.await.expect("Task should complete")?;
+        assert_eq!(result.row_count(), 2);
+    }
+    assert_eq!(Arc::strong_count(&shared), 2);
+    assert!(engines[0].replace_connector("unknown", Box::new(MockConnector::new())).await.is_err());
+    
+    Ok(())
+}
+
 /// Test engine with different protocol configurations
 #[tokio::test]
 async fn test_engine_protocol_configurations() -> NirvResult<()> {
-    let mut config = EngineConfig::default();
-    
     // Add multiple protocol adapters with different ports to avoid conflicts
-    config.protocol_adapters = vec![
-        ProtocolConfig {
-            protocol_type: ConfigProtocolType::PostgreSQL,
-            bind_address: "127.0.0.1".to_string(),
-            port: 15432, // Use different port
-            tls_config: None,
-            max_connections: Some(100),
-            connection_timeout: Some(30),
-        },
-        ProtocolConfig {
-            protocol_type: ConfigProtocolType::MySQL,
-            bind_address: "127.0.0.1".to_string(),
-            port: 13306, // Use different port
-            tls_config: None,
-            max_connections: Some(50),
-            connection_timeout: Some(30),
-        },
-    ];
+    let config = EngineConfig {
+        protocol_adapters: vec![
+            ProtocolConfig {
+                protocol_type: ConfigProtocolType::PostgreSQL,
+                bind_address: "127.0.0.1".to_string(),
+                port: 15432, // Use different port
+                tls_config: None,
+                max_connections: Some(100),
+                connection_timeout: Some(30),
+            },
+            ProtocolConfig {
+                protocol_type: ConfigProtocolType::MySQL,
+                bind_address: "127.0.0.1".to_string(),
+                port: 13306, // Use different port
+                tls_config: None,
+                max_connections: Some(50),
+                connection_timeout: Some(30),
+            },
+        ],
+        ..Default::default()
+    };
     
     let mut engine = Engine::new(config);
     
@@ -396,4 +1553,60 @@ async fn test_engine_protocol_configurations() -> NirvResult<()> {
     engine.shutdown().await?;
     
     Ok(())
-}
\ No newline at end of file
+}
+/// Send a SQL batch to a SQL Server protocol server and return the tokens of its response
+async fn sql_batch(client: &mut tokio::net::TcpStream, sql: &str) -> Vec<u8> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let text: Vec<u8> = sql.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut packet = vec![0x01, 0x01];
+    packet.extend_from_slice(&(text.len() as u16 + 8).to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 1, 0]);
+    packet.extend_from_slice(&text);
+    client.write_all(&packet).await.unwrap();
+
+    let mut header = [0u8; 8];
+    client.read_exact(&mut header).await.unwrap();
+    let mut tokens = vec![0u8; u16::from_be_bytes([header[2], header[3]]) as usize - 8];
+    client.read_exact(&mut tokens).await.unwrap();
+    tokens
+}
+
+/// Whether TDS tokens hold a name or text value, which are sent as UTF-16
+fn holds_text(tokens: &[u8], text: &str) -> bool {
+    let text: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    tokens.windows(text.len()).any(|window| window == text.as_slice())
+}
+
+/// Test catalog procedures reaching the engine's sources through a SQL Server server
+#[tokio::test]
+async fn test_sqlserver_protocol_answers_catalog_queries() -> NirvResult<()> {
+    let config = EngineConfig {
+        protocol_adapters: vec![ProtocolConfig {
+            protocol_type: ConfigProtocolType::SqlServer,
+            bind_address: "127.0.0.1".to_string(),
+            port: 14433, // Use different port
+            tls_config: None,
+            max_connections: Some(10),
+            connection_timeout: Some(30),
+        }],
+        ..Default::default()
+    };
+    let mut engine = Engine::new(config);
+    engine.initialize().await?;
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+
+    let mut client = tokio::net::TcpStream::connect("127.0.0.1:14433").await.unwrap();
+    let tables = sql_batch(&mut client, "EXEC sp_tables @table_name = 'users'").await;
+    assert!(holds_text(&tables, "TABLE_OWNER") && holds_text(&tables, "mock") && holds_text(&tables, "users"));
+
+    let columns = sql_batch(&mut client, "SELECT COLUMN_NAME FROM INFORMATION_SCHEMA.COLUMNS WHERE TABLE_NAME = 'users'").await;
+    assert!(holds_text(&columns, "COLUMN_NAME") && holds_text(&columns, "email"));
+
+    drop(client);
+    engine.shutdown().await?;
+
+    Ok(())
+}
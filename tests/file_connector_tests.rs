@@ -1,13 +1,15 @@
 #![allow(unused)]
 
-use nirv_engine::connectors::{Connector, ConnectorInitConfig, FileConnector};
+use nirv_engine::connectors::{Connector, ConnectorInitConfig, FileConnector, ParsePool};
 use nirv_engine::utils::{
-    types::{ConnectorType, ConnectorQuery, QueryOperation, DataSource, InternalQuery, Value, DataType, Predicate, PredicateOperator, PredicateValue},
+    types::{ConnectorType, ConnectorQuery, QueryOperation, DataSource, InternalQuery, Value, DataType, Predicate, PredicateOperator, PredicateValue, Schema},
     error::{ConnectorError, NirvError},
 };
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tempfile::TempDir;
 
 /// Helper function to create a temporary directory with test files
@@ -517,6 +519,321 @@ mod tests {
         let connector = FileConnector::new();
         assert_eq!(connector.get_connector_type(), ConnectorType::File);
     }
+
+    #[tokio::test]
+    async fn test_snapshot_time_travel_query() {
+        let temp_dir = create_test_files();
+        let mut connector = FileConnector::new();
+        let config = create_file_config(temp_dir.path())
+            .with_param("snapshot_dir", ".snapshots");
+        connector.connect(config).await.expect("connect");
+
+        // Querying the live file records a snapshot of its current content
+        let result = connector.execute_query(create_file_query("users.csv")).await.unwrap();
+        assert_eq!(result.rows.len(), 3);
+
+        fs::write(temp_dir.path().join("users.csv"), "id,name,age,active\n4,Dan,40,true\n").unwrap();
+        let result = connector.execute_query(create_file_query("users.csv")).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+
+        let snapshots = connector.snapshot_store().unwrap().snapshots("users.csv").unwrap();
+        assert_eq!(snapshots.len(), 2);
+
+        // The latest snapshot as of today reflects the newest content
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let result = connector.execute_query(create_file_query(&format!("users.csv@{}", today))).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+
+        // The first snapshot is still queryable by its exact timestamp
+        let first = snapshots[0].taken_at.to_rfc3339();
+        let result = connector.execute_query(create_file_query(&format!("users.csv@{}", first))).await.unwrap();
+        assert_eq!(result.rows.len(), 3);
+
+        let result = connector.execute_query(create_file_query("users.csv@2000-01-01")).await;
+        assert!(matches!(result, Err(NirvError::Connector(ConnectorError::QueryExecutionFailed(_)))));
+
+        // Snapshot copies are not matched by patterns reaching into their directory
+        assert!(connector.execute_query(create_file_query(".snapshots/*.csv")).await.is_err());
+
+        let _ = connector.disconnect().await;
+    }
+
+    #[tokio::test]
+    async fn test_column_identifier_case_resolution() {
+        let temp_dir = create_test_files();
+        let mut connector = FileConnector::new();
+        connector.connect(create_file_config(temp_dir.path())).await.unwrap();
+
+        let by_column = |column: &str| create_file_query_with_where("users.csv", column, PredicateOperator::Equal, PredicateValue::String("Jane".to_string()));
+
+        // Unquoted identifiers match the `name` header regardless of case
+        let result = connector.execute_query(by_column("Name")).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+
+        // Quoted identifiers must match exactly
+        let result = connector.execute_query(by_column("\"Name\"")).await.unwrap();
+        assert_eq!(result.rows.len(), 0);
+        let result = connector.execute_query(by_column("\"name\"")).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+
+        let mut sensitive = FileConnector::new();
+        let config = create_file_config(temp_dir.path()).with_param("identifier_case", "sensitive");
+        sensitive.connect(config).await.unwrap();
+        let result = sensitive.execute_query(by_column("Name")).await.unwrap();
+        assert_eq!(result.rows.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_csv_parsing_options() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("export.csv"), b"id;city;score\n1;M\xfcnchen;NA\n2;Z\xfcrich;7\n").unwrap();
+        fs::write(temp_dir.path().join("raw.csv"), "1|x\n2|y\n").unwrap();
+        
+        let mut connector = FileConnector::new();
+        let config = create_file_config(temp_dir.path())
+            .with_param("delimiter", ";")
+            .with_param("encoding", "latin1")
+            .with_param("null_token", "NA");
+        connector.connect(config).await.unwrap();
+        
+        let result = connector.execute_query(create_file_query("export.csv")).await.unwrap();
+        assert_eq!(result.columns.len(), 3);
+        assert_eq!(result.rows[0].values, vec![Value::Integer(1), Value::Text("München".to_string()), Value::Null]);
+        
+        // Options after `?` override the connection parameters for one source
+        let result = connector.execute_query(create_file_query("raw.csv?delimiter=|&has_headers=false&encoding=utf-8")).await.unwrap();
+        assert_eq!(result.columns[1].name, "column2");
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(connector.get_schema("raw.csv?delimiter=|").await.unwrap().columns.len(), 2);
+        assert!(connector.execute_query(create_file_query("raw.csv?delimiter=||")).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_index_skips_files_outside_predicate_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let logs = temp_dir.path().join("logs");
+        fs::create_dir(&logs).unwrap();
+        fs::write(logs.join("day1.csv"), "id,level\n1,info\n2,warn\n").unwrap();
+        fs::write(logs.join("day2.csv"), "id,level\n3,info\n4,error\n").unwrap();
+        
+        let mut connector = FileConnector::new();
+        connector.connect(create_file_config(temp_dir.path()).with_param("index_dir", ".index")).await.unwrap();
+        let by_id = || create_file_query_with_where("logs/*.csv", "id", PredicateOperator::GreaterThan, PredicateValue::Integer(2));
+        
+        // The first read builds the index
+        assert_eq!(connector.execute_query(by_id()).await.unwrap().rows.len(), 2);
+        let store = connector.index_store().unwrap();
+        let day1 = logs.join("day1.csv");
+        assert!(fs::read_dir(store.root()).unwrap().count() >= 2);
+        
+        // Replace day1.csv with unreadable content the index still considers current:
+        // the filtered query never opens it, while an unfiltered one does
+        let modified = fs::metadata(&day1).unwrap().modified().unwrap();
+        let length = fs::metadata(&day1).unwrap().len() as usize;
+        fs::write(&day1, vec![0xff; length]).unwrap();
+        fs::File::options().write(true).open(&day1).unwrap().set_modified(modified).unwrap();
+        
+        let result = connector.execute_query(by_id()).await.unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.columns.len(), 2);
+        assert!(connector.execute_query(create_file_query("logs/*.csv")).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_watched_files_reflect_current_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let latest = temp_dir.path().join("latest.csv");
+        fs::write(&latest, "id,status\n1,open\n").unwrap();
+        
+        let mut connector = FileConnector::new();
+        connector.connect(create_file_config(temp_dir.path()).with_param("watch_interval_ms", "20")).await.unwrap();
+        let mut changes = connector.subscribe().unwrap();
+        
+        assert_eq!(connector.execute_query(create_file_query("latest.csv")).await.unwrap().rows.len(), 1);
+        assert_eq!(connector.parse_cache().unwrap().len(), 1);
+        
+        // The change is announced and the next query reads the new content
+        fs::write(&latest, "id,status\n1,open\n2,closed\n").unwrap();
+        let change = tokio::time::timeout(std::time::Duration::from_secs(5), changes.recv()).await.unwrap().unwrap();
+        assert_eq!(change.path, latest);
+        assert_eq!(connector.execute_query(create_file_query("latest.csv")).await.unwrap().rows.len(), 2);
+        
+        connector.disconnect().await.unwrap();
+        assert!(connector.subscribe().is_none());
+        
+        let mut invalid = FileConnector::new();
+        assert!(invalid.connect(create_file_config(temp_dir.path()).with_param("watch_interval_ms", "0")).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_multi_file_schema_merge() {
+        let temp_dir = TempDir::new().unwrap();
+        let exports = temp_dir.path().join("exports");
+        fs::create_dir(&exports).unwrap();
+        fs::write(exports.join("2024-01.csv"), "id,name\n1,John\n2,Jane\n").unwrap();
+        fs::write(exports.join("2024-02.csv"), "id,region,name\n3,EU,Bob\n").unwrap();
+        
+        let mut strict = FileConnector::new();
+        strict.connect(create_file_config(temp_dir.path())).await.unwrap();
+        assert!(strict.execute_query(create_file_query("exports/*.csv")).await.is_err());
+        
+        let mut connector = FileConnector::new();
+        connector.connect(create_file_config(temp_dir.path()).with_param("schema_merge", "union")).await.unwrap();
+        let result = connector.execute_query(create_file_query("exports/*.csv")).await.unwrap();
+        let names: Vec<&str> = result.columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "name", "region"]);
+        assert_eq!(result.rows[0].values, vec![Value::Integer(1), Value::Text("John".to_string()), Value::Null]);
+        assert_eq!(result.rows[2].values, vec![Value::Integer(3), Value::Text("Bob".to_string()), Value::Text("EU".to_string())]);
+        
+        let query = create_file_query_with_where("exports/*.csv", "region", PredicateOperator::Equal, PredicateValue::String("EU".to_string()));
+        assert_eq!(connector.execute_query(query).await.unwrap().rows.len(), 1);
+        assert_eq!(connector.get_schema("exports/*.csv").await.unwrap().columns.len(), 3);
+        
+        let mut invalid = FileConnector::new();
+        assert!(invalid.connect(create_file_config(temp_dir.path()).with_param("schema_merge", "loose")).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_create_table_and_insert_rows() {
+        let temp_dir = create_test_files();
+        let mut connector = FileConnector::new();
+        connector.connect(create_file_config(temp_dir.path())).await.unwrap();
+        
+        let source = connector.execute_query(create_file_query("users.csv")).await.unwrap();
+        let schema = Schema { name: "report".to_string(), columns: source.columns.clone(), primary_key: None, indexes: Vec::new(), column_details: HashMap::new(), foreign_keys: Vec::new() };
+        connector.create_table("cache/report.csv", &schema).await.unwrap();
+        assert_eq!(connector.insert_rows("cache/report.csv", &source.columns, &source.rows).await.unwrap(), 3);
+        
+        // The written file reads back as the rows it was loaded from
+        let result = connector.execute_query(create_file_query("cache/report.csv")).await.unwrap();
+        assert_eq!(result.columns.len(), 4);
+        assert_eq!(result.rows[1].values, source.rows[1].values);
+        
+        assert!(connector.create_table("cache/report.csv", &schema).await.is_err());
+        assert!(connector.create_table("../outside.csv", &schema).await.is_err());
+        assert!(connector.create_table("report.json", &schema).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_nested_json_flattening_and_unnest() {
+        let temp_dir = create_test_files();
+        let orders = r#"[
+            {"id": 1, "address": {"city": "Oslo", "zip": 150}, "items": [{"sku": "A", "qty": 2}, {"sku": "B", "qty": 1}]},
+            {"id": 2, "address": {"city": "Bergen", "zip": 5003}, "items": [{"sku": "C", "qty": 4}]}
+        ]"#;
+        fs::write(temp_dir.path().join("orders.json"), orders).unwrap();
+
+        let mut connector = FileConnector::new();
+        let config = create_file_config(temp_dir.path())
+            .with_param("flatten_depth", "1")
+            .with_param("unnest", "items");
+        connector.connect(config).await.expect("connect");
+
+        let schema = connector.get_schema("orders.json").await.unwrap();
+        let column_type = |name: &str| schema.columns.iter().find(|c| c.name == name).map(|c| c.data_type.clone());
+        assert_eq!(column_type("address.city"), Some(DataType::Text));
+        assert_eq!(column_type("address.zip"), Some(DataType::Integer));
+        assert_eq!(column_type("items.qty"), Some(DataType::Integer));
+        assert_eq!(column_type("address"), None);
+
+        // One row per unnested item, filterable on flattened columns
+        let result = connector.execute_query(create_file_query("orders.json")).await.unwrap();
+        assert_eq!(result.rows.len(), 3);
+
+        let query = create_file_query_with_where("orders.json", "address.city", PredicateOperator::Equal, PredicateValue::String("Oslo".to_string()));
+        let result = connector.execute_query(query).await.unwrap();
+        assert_eq!(result.rows.len(), 2);
+        let sku = result.columns.iter().position(|c| c.name == "items.sku").unwrap();
+        assert_eq!(result.rows[1].values[sku], Value::Text("B".to_string()));
+
+        // Without flattening, nested objects stay a single column
+        let mut plain = FileConnector::new();
+        plain.connect(create_file_config(temp_dir.path())).await.unwrap();
+        let schema = plain.get_schema("orders.json").await.unwrap();
+        assert!(schema.columns.iter().any(|c| c.name == "address"));
+    }
+
+    #[tokio::test]
+    async fn test_xml_records_by_path() {
+        let temp_dir = create_test_files();
+        let feed = r#"<?xml version="1.0"?>
+<export>
+  <meta><generated>2024-05-01</generated></meta>
+  <orders>
+    <order id="7" currency="EUR"><customer>Ada</customer><total>19.90</total></order>
+    <order id="8" currency="USD"><customer>Bob</customer><total>5</total></order>
+  </orders>
+</export>"#;
+        fs::write(temp_dir.path().join("feed.xml"), feed).unwrap();
+
+        let mut connector = FileConnector::new();
+        let config = create_file_config(temp_dir.path())
+            .with_param("file_extensions", "xml")
+            .with_param("xml_record_path", "/export/orders/order");
+        connector.connect(config).await.unwrap();
+
+        let schema = connector.get_schema("feed.xml").await.unwrap();
+        let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "currency", "customer", "total"]);
+
+        let query = create_file_query_with_where("feed.xml", "currency", PredicateOperator::Equal, PredicateValue::String("USD".to_string()));
+        let result = connector.execute_query(query).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values, vec![
+            Value::Integer(8),
+            Value::Text("USD".to_string()),
+            Value::Text("Bob".to_string()),
+            Value::Integer(5),
+        ]);
+
+        // A source's record path overrides the connector's
+        let result = connector.execute_query(create_file_query("feed.xml?record_path=//meta")).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values, vec![Value::Text("2024-05-01".to_string())]);
+
+        assert!(connector.execute_query(create_file_query("feed.xml?record_path=/export/order[1]")).await.is_err());
+        fs::write(temp_dir.path().join("broken.xml"), "<export><order></export>").unwrap();
+        assert!(connector.execute_query(create_file_query("broken.xml")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fixed_width_files_by_layout() {
+        let temp_dir = create_test_files();
+        let extract = "00042WIDGET    0001250-\n00043GADGET    0000999 \n";
+        fs::write(temp_dir.path().join("stock.dat"), extract).unwrap();
+        fs::write(temp_dir.path().join("stock.layout"), "# Stock extract\nsku 0 5 integer\nname 5 10\nqty 15 8 decimal(2)\n").unwrap();
+
+        let mut connector = FileConnector::new();
+        let config = create_file_config(temp_dir.path())
+            .with_param("file_extensions", "dat")
+            .with_param("fixed_width_layout_file", "stock.layout");
+        connector.connect(config).await.unwrap();
+
+        let schema = connector.get_schema("stock.dat").await.unwrap();
+        let types: Vec<DataType> = schema.columns.iter().map(|c| c.data_type.clone()).collect();
+        assert_eq!(types, vec![DataType::Integer, DataType::Text, DataType::Float]);
+
+        let query = create_file_query_with_where("stock.dat", "sku", PredicateOperator::Equal, PredicateValue::Integer(42));
+        let result = connector.execute_query(query).await.unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values, vec![
+            Value::Integer(42),
+            Value::Text("WIDGET".to_string()),
+            Value::Float(-12.5),
+        ]);
+
+        // A source's inline layout overrides the connector's
+        let result = connector.execute_query(create_file_query("stock.dat?layout=sku:0:5,name:5:3")).await.unwrap();
+        assert_eq!(result.columns.len(), 2);
+        assert_eq!(result.rows[1].values, vec![Value::Integer(43), Value::Text("GAD".to_string())]);
+
+        assert!(connector.execute_query(create_file_query("stock.dat?layout=sku:0:5:date")).await.is_err());
+        assert!(connector.execute_query(create_file_query("stock.dat?layout_file=missing.layout")).await.is_err());
+
+        let mut unconfigured = FileConnector::new();
+        unconfigured.connect(create_file_config(temp_dir.path()).with_param("file_extensions", "dat")).await.unwrap();
+        assert!(unconfigured.execute_query(create_file_query("stock.dat")).await.is_err());
+    }
 }
 
 /// Performance tests for file connector optimization
@@ -564,4 +881,52 @@ mod performance_tests {
         
         let _ = connector.disconnect().await;
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_large_file_parse_does_not_block_runtime() {
+        let temp_dir = create_test_files();
+        let mut large_csv_content = "id,name,category,value\n".to_string();
+        for i in 1..=200_000 {
+            large_csv_content.push_str(&format!("{},Item{},Category{},{}\n", i, i, i % 10, i * 10));
+        }
+        fs::write(temp_dir.path().join("large_data.csv"), large_csv_content).expect("Failed to write large CSV");
+
+        let mut connector = FileConnector::new().with_parse_pool(Arc::new(ParsePool::new(1)));
+        connector.connect(create_file_config(temp_dir.path())).await.unwrap();
+
+        // The test runtime has a single thread, which a parse running on it would hold
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    tokio::task::yield_now().await;
+                    ticks.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+        let result = connector.execute_query(create_file_query("large_data.csv")).await.unwrap();
+        let ticks_during_scan = ticks.load(Ordering::Relaxed);
+        ticker.abort();
+
+        assert_eq!(result.rows.len(), 200_000);
+        assert!(ticks_during_scan > 0, "Other tasks did not run while the file was parsed");
+    }
+
+    #[tokio::test]
+    async fn test_count_rows_without_parsing() {
+        let temp_dir = create_test_files();
+        fs::write(temp_dir.path().join("notes.csv"), "id,note\n1,\"two\nlines\"\n2,plain\n").expect("Failed to write notes CSV");
+        let mut connector = FileConnector::new();
+        connector.connect(create_file_config(temp_dir.path())).await.unwrap();
+
+        assert_eq!(connector.count_rows(create_file_query("users.csv")).await.unwrap(), Some(3));
+        assert_eq!(connector.count_rows(create_file_query("notes.csv")).await.unwrap(), Some(2));
+
+        // Filtered counts and formats without a record count need the rows
+        let filtered = create_file_query_with_where("users.csv", "age", PredicateOperator::GreaterThan, PredicateValue::Integer(26));
+        assert_eq!(connector.count_rows(filtered).await.unwrap(), None);
+        assert_eq!(connector.count_rows(create_file_query("users.json")).await.unwrap(), None);
+    }
+
+}
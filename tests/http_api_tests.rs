@@ -0,0 +1,336 @@
+use nirv_engine::{Engine, MockConnector, ConnectorInitConfig, Connector, NirvResult};
+use nirv_engine::protocol::HttpApiServer;
+use nirv_engine::protocol::compression::gzip_decompress;
+use nirv_engine::utils::{EngineConfig, HttpApiConfig};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Start an HTTP API backed by an engine with a mock connector, returning its base URL
+async fn start_server(config: HttpApiConfig) -> NirvResult<String> {
+    let mut engine_config = EngineConfig::default();
+    engine_config.protocol_adapters.clear();
+    serve_engine(mock_engine(engine_config).await?, config).await
+}
+
+/// An initialized engine with a mock connector registered as `mock`
+async fn mock_engine(engine_config: EngineConfig) -> NirvResult<Engine> {
+    let mut engine = Engine::new(engine_config);
+    engine.initialize_for_testing().await?;
+
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+    Ok(engine)
+}
+
+/// Serve the engine over the HTTP API, returning its base URL
+async fn serve_engine(engine: Engine, config: HttpApiConfig) -> NirvResult<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = HttpApiServer::new(config, Arc::new(engine));
+    tokio::spawn(server.serve(listener, std::future::pending()));
+
+    Ok(format!("http://{}", address))
+}
+
+#[tokio::test]
+async fn test_query_returns_json() -> NirvResult<()> {
+    let base_url = start_server(HttpApiConfig::default()).await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/query", base_url))
+        .body("SELECT * FROM source('mock.users')")
+        .send().await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["metadata"]["row_count"], 3);
+    assert_eq!(body["data"].as_array().unwrap().len(), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_content_negotiation() -> NirvResult<()> {
+    let base_url = start_server(HttpApiConfig::default()).await?;
+    let client = reqwest::Client::new();
+
+    let csv = client.post(format!("{}/query", base_url))
+        .header("Accept", "text/csv")
+        .header("Content-Type", "application/json")
+        .body(r#"{"sql": "SELECT * FROM source('mock.users')"}"#)
+        .send().await.unwrap();
+    assert_eq!(csv.headers()["content-type"], "text/csv; charset=utf-8");
+    let csv = csv.text().await.unwrap();
+    assert_eq!(csv.lines().count(), 4);
+    assert!(csv.starts_with("id,name,email,age,active\n"));
+
+    let ndjson = client.post(format!("{}/query", base_url))
+        .header("Accept", "application/x-ndjson")
+        .body("SELECT * FROM source('mock.users')")
+        .send().await.unwrap()
+        .text().await.unwrap();
+    let rows: Vec<serde_json::Value> = ndjson.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["name"], "Alice Johnson");
+
+    let unacceptable = client.post(format!("{}/query", base_url))
+        .header("Accept", "image/png")
+        .body("SELECT * FROM source('mock.users')")
+        .send().await.unwrap();
+    assert_eq!(unacceptable.status(), 406);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_gzip_encoding() -> NirvResult<()> {
+    let base_url = start_server(HttpApiConfig::default()).await?;
+    let client = reqwest::Client::new();
+
+    for accept in ["application/json", "application/x-ndjson", "text/csv"] {
+        let plain = client.post(format!("{}/query", base_url))
+            .header("Accept", accept)
+            .body("SELECT * FROM source('mock.users')")
+            .send().await.unwrap();
+        assert!(plain.headers().get("content-encoding").is_none());
+        let plain = plain.bytes().await.unwrap();
+
+        let gzipped = client.post(format!("{}/query", base_url))
+            .header("Accept", accept)
+            .header("Accept-Encoding", "gzip")
+            .body("SELECT * FROM source('mock.users')")
+            .send().await.unwrap();
+        assert_eq!(gzipped.headers()["content-encoding"], "gzip");
+        assert_eq!(gzipped.headers()["vary"], "Accept-Encoding");
+        let body = gzip_decompress(&gzipped.bytes().await.unwrap(), usize::MAX)?;
+        if accept == "application/json" {
+            // Metadata carries the execution time, which differs between the two queries
+            let data = |body: &[u8]| serde_json::from_slice::<serde_json::Value>(body).unwrap()["data"].clone();
+            assert_eq!(data(&body), data(&plain));
+        } else {
+            assert_eq!(body, plain);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_errors() -> NirvResult<()> {
+    let base_url = start_server(HttpApiConfig::default()).await?;
+    let client = reqwest::Client::new();
+
+    let invalid = client.post(format!("{}/query", base_url))
+        .body("SELEC nonsense")
+        .send().await.unwrap();
+    assert_eq!(invalid.status(), 400);
+    let body: serde_json::Value = invalid.json().await.unwrap();
+    assert!(body["error"].is_string());
+
+    let wrong_method = client.get(format!("{}/query", base_url)).send().await.unwrap();
+    assert_eq!(wrong_method.status(), 405);
+
+    let health = client.get(format!("{}/health", base_url)).send().await.unwrap();
+    assert_eq!(health.status(), 200);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_requires_configured_auth() -> NirvResult<()> {
+    let mut config = HttpApiConfig::default();
+    config.auth.tokens.push("s3cret".to_string());
+    config.auth.basic_users.insert("analyst".to_string(), "pw".to_string());
+    let base_url = start_server(config).await?;
+    let client = reqwest::Client::new();
+    let query = "SELECT * FROM source('mock.users')";
+
+    let anonymous = client.post(format!("{}/query", base_url)).body(query).send().await.unwrap();
+    assert_eq!(anonymous.status(), 401);
+    assert!(anonymous.headers().contains_key("www-authenticate"));
+
+    let bearer = client.post(format!("{}/query", base_url))
+        .bearer_auth("s3cret")
+        .body(query)
+        .send().await.unwrap();
+    assert_eq!(bearer.status(), 200);
+
+    let basic = client.post(format!("{}/query", base_url))
+        .basic_auth("analyst", Some("pw"))
+        .body(query)
+        .send().await.unwrap();
+    assert_eq!(basic.status(), 200);
+
+    let wrong = client.post(format!("{}/query", base_url))
+        .basic_auth("analyst", Some("nope"))
+        .body(query)
+        .send().await.unwrap();
+    assert_eq!(wrong.status(), 401);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connector_debug_toggle() -> NirvResult<()> {
+    let mut config = HttpApiConfig::default();
+    config.auth.tokens.push("s3cret".to_string());
+    let base_url = start_server(config).await?;
+    let client = reqwest::Client::new();
+    let admin = format!("{}/admin/connector-debug", base_url);
+
+    let anonymous = client.put(&admin).body("on").send().await.unwrap();
+    assert_eq!(anonymous.status(), 401);
+
+    let enabled = client.put(&admin).bearer_auth("s3cret").body("on").send().await.unwrap();
+    assert_eq!(enabled.status(), 200);
+    assert_eq!(enabled.text().await.unwrap(), "on");
+
+    let invalid = client.put(&admin).bearer_auth("s3cret").body("maybe").send().await.unwrap();
+    assert_eq!(invalid.status(), 400);
+
+    // The same switch is reachable as a SQL statement
+    let disabled = client.post(format!("{}/query", base_url))
+        .bearer_auth("s3cret")
+        .body("SET connector_debug = off")
+        .send().await.unwrap();
+    assert_eq!(disabled.status(), 200);
+    let state = client.get(&admin).bearer_auth("s3cret").send().await.unwrap();
+    assert_eq!(state.text().await.unwrap(), "off");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_reports_partial_result_warnings() -> NirvResult<()> {
+    let base_url = start_server(HttpApiConfig::default()).await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/query", base_url))
+        .body("SELECT /*+ PARTIAL_RESULTS */ u.name, m.note FROM source('mock.users') u \
+               LEFT JOIN source('mock.missing') m ON u.id = m.user_id")
+        .send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let warning = response.headers()["warning"].to_str().unwrap().to_string();
+    assert!(warning.starts_with("199 nirv \"mock.missing failed"), "{}", warning);
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["metadata"]["row_count"], 3);
+    assert_eq!(body["metadata"]["warnings"][0]["source"], "mock.missing");
+
+    Ok(())
+}
+
+#[cfg(feature = "web-console")]
+#[tokio::test]
+async fn test_web_console() -> NirvResult<()> {
+    let mut engine_config = EngineConfig::default();
+    engine_config.protocol_adapters.clear();
+    let mut engine = Engine::new(engine_config);
+    engine.initialize_for_testing().await?;
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+    let engine = Arc::new(engine);
+
+    let mut config = HttpApiConfig::default();
+    config.auth.tokens.push("s3cret".to_string());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let server = HttpApiServer::new(config, engine.clone()).with_console(engine);
+    tokio::spawn(server.serve(listener, std::future::pending()));
+    let client = reqwest::Client::new();
+
+    // The page asks for credentials itself; the data it shows needs them
+    let page = client.get(format!("{}/console", base_url)).send().await.unwrap();
+    assert_eq!(page.status(), 200);
+    assert!(page.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+    assert!(page.text().await.unwrap().contains("NIRV Console"));
+    let anonymous = client.get(format!("{}/console/sources", base_url)).send().await.unwrap();
+    assert_eq!(anonymous.status(), 401);
+
+    let get = |path: &str| client.get(format!("{}{}", base_url, path)).bearer_auth("s3cret").send();
+    let sources: serde_json::Value = get("/console/sources").await.unwrap().json().await.unwrap();
+    let mock = sources.as_array().unwrap().iter().find(|source| source["object_type"] == "mock").expect("mock source");
+    assert_eq!(mock["connected"], true);
+    assert_eq!(mock["connector_type"], "Mock");
+
+    let tables: serde_json::Value = get("/console/tables").await.unwrap().json().await.unwrap();
+    let users = tables.as_array().unwrap().iter().find(|table| table["name"] == "users").expect("users table");
+    assert_eq!(users["source"], "mock");
+    assert!(users["columns"].as_array().unwrap().iter().any(|column| column["name"] == "email"));
+
+    for sql in ["SELECT * FROM source('mock.users')", "SELEC nonsense"] {
+        client.post(format!("{}/query", base_url)).bearer_auth("s3cret").body(sql).send().await.unwrap();
+    }
+    let history: serde_json::Value = get("/console/history").await.unwrap().json().await.unwrap();
+    assert_eq!(history[0]["sql"], "SELEC nonsense");
+    assert!(history[0]["error"].is_string());
+    assert_eq!(history[1]["rows"], 3);
+
+    assert_eq!(get("/console/nothing").await.unwrap().status(), 404);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_queries_run_for_the_authenticated_user() -> NirvResult<()> {
+    let path = std::env::temp_dir().join(format!("nirv_http_access_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut engine_config = EngineConfig::default();
+    engine_config.protocol_adapters.clear();
+    engine_config.security.authorization.enabled = true;
+    engine_config.security.authorization.catalog_path = Some(path.to_string_lossy().to_string());
+    let engine = mock_engine(engine_config).await?;
+    for sql in [
+        "CREATE ROLE analyst", "GRANT SELECT ON 'mock' TO ROLE analyst", "GRANT ROLE analyst TO USER ann",
+        "CREATE ROLE dba", "GRANT ADMIN TO dba", "GRANT ROLE dba TO USER root",
+    ] {
+        engine.execute_query(sql).await?;
+    }
+
+    let mut config = HttpApiConfig::default();
+    config.auth.tokens.push("shared".to_string());
+    config.auth.basic_users.insert("ann".to_string(), "pw".to_string());
+    config.auth.token_users.insert("root".to_string(), "root-token".to_string());
+    let base_url = serve_engine(engine, config).await?;
+    let client = reqwest::Client::new();
+    let post = |sql: &'static str| client.post(format!("{}/query", base_url)).body(sql);
+    let select = "SELECT * FROM source('mock.users')";
+
+    // A token of no user may neither query nor administer while access control is on
+    assert_eq!(post(select).bearer_auth("shared").send().await.unwrap().status(), 403);
+    assert_eq!(post("GRANT ROLE dba TO USER mallory").bearer_auth("shared").send().await.unwrap().status(), 403);
+
+    // Users query and administer under the privileges of their roles
+    assert_eq!(post(select).basic_auth("ann", Some("pw")).send().await.unwrap().status(), 200);
+    assert_eq!(post("GRANT ROLE dba TO USER ann").basic_auth("ann", Some("pw")).send().await.unwrap().status(), 403);
+    assert_eq!(post("GRANT ROLE analyst TO USER bob").bearer_auth("root-token").send().await.unwrap().status(), 200);
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_users_are_held_to_their_quotas() -> NirvResult<()> {
+    let mut engine_config = EngineConfig::default();
+    engine_config.protocol_adapters.clear();
+    engine_config.quotas = Some(serde_json::from_str(r#"{"principals": {"ann": {"rows_per_hour": 2}}}"#).unwrap());
+    let mut config = HttpApiConfig::default();
+    config.auth.basic_users.insert("ann".to_string(), "pw".to_string());
+    config.auth.basic_users.insert("bob".to_string(), "pw".to_string());
+    let base_url = serve_engine(mock_engine(engine_config).await?, config).await?;
+    let client = reqwest::Client::new();
+    let query = |user: &str| client.post(format!("{}/query", base_url))
+        .basic_auth(user, Some("pw"))
+        .body("SELECT * FROM source('mock.users') LIMIT 2")
+        .send();
+
+    assert_eq!(query("ann").await.unwrap().status(), 200);
+    let refused = query("ann").await.unwrap();
+    assert_eq!(refused.status(), 429);
+    assert!(refused.text().await.unwrap().contains("rows per hour"));
+    // Usage is charged to each user apart
+    assert_eq!(query("bob").await.unwrap().status(), 200);
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+use nirv_engine::connectors::{Connector, ConnectorInitConfig, ImapConnector};
+use nirv_engine::utils::types::{
+    ConnectorQuery, ConnectorType, DataSource, InternalQuery, Predicate, PredicateOperator, PredicateValue,
+    QueryOperation, Value,
+};
+
+/// Messages of the mock INBOX: UID, sender, subject, internal date, flags and plain body
+const MESSAGES: [(u32, &str, &str, &str, &str, &str); 3] = [
+    (7, "ann", "Ticket #41 resolved", "01-May-2024 09:00:00 +0000", "\\Seen", "Thanks, all fixed."),
+    (8, "bo", "=?UTF-8?Q?Refund_for_ticket_#42?=", "02-May-2024 12:30:00 +0200", "\\Seen \\Flagged", "Please refund\r\norder 42."),
+    (9, "ann", "Ticket #43 opened", "03-May-2024 08:15:00 +0000", "", "The export fails."),
+];
+
+fn fetch_response(uid: u32, items: &str) -> String {
+    let (_, sender, subject, date, flags, body) = MESSAGES.iter().find(|message| message.0 == uid).unwrap();
+    let index = MESSAGES.iter().position(|message| message.0 == uid).unwrap() + 1;
+    if items.starts_with("BODY.PEEK[1]") {
+        return format!("* {} FETCH (UID {} BODY[1]<0> {{{}}}\r\n{})\r\n", index, uid, body.len(), body);
+    }
+    let mut response = format!(
+        "* {} FETCH (UID {} FLAGS ({}) INTERNALDATE \"{}\" ENVELOPE (\"{}\" \"{}\" ((NIL NIL \"{}\" \"acme.io\")) NIL NIL ((\"Support\" NIL \"support\" \"example.com\")) NIL NIL NIL \"<{}@acme.io>\")",
+        index, uid, flags, date, date, subject, sender, uid,
+    );
+    if items.contains("BODYSTRUCTURE") {
+        response.push_str(&format!(" BODYSTRUCTURE (\"TEXT\" \"PLAIN\" (\"CHARSET\" \"UTF-8\") NIL NIL \"7BIT\" {} 1)", body.len()));
+    }
+    response + ")\r\n"
+}
+
+/// Serve a scripted IMAP mailbox, recording every command after its tag
+async fn start_mock_imap() -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let commands = Arc::new(Mutex::new(Vec::new()));
+    let log = commands.clone();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let log = log.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = socket.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                writer.write_all(b"* OK [CAPABILITY IMAP4rev1] Mock ready\r\n").await.unwrap();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let (tag, command) = line.split_once(' ').unwrap();
+                    log.lock().unwrap().push(command.to_string());
+                    let upper = command.to_uppercase();
+                    let response = if upper.starts_with("LOGIN") && !command.ends_with("\"secret\"") {
+                        format!("{} NO [AUTHENTICATIONFAILED] Invalid credentials\r\n", tag)
+                    } else if upper.starts_with("EXAMINE") && command != "EXAMINE \"INBOX\"" {
+                        format!("{} NO Mailbox doesn't exist\r\n", tag)
+                    } else if upper.starts_with("EXAMINE") {
+                        format!("* 3 EXISTS\r\n* OK [UIDVALIDITY 1] UIDs valid\r\n{} OK [READ-ONLY] Examine completed\r\n", tag)
+                    } else if upper.starts_with("UID SEARCH") {
+                        format!("* SEARCH 7 8 9\r\n{} OK Search completed\r\n", tag)
+                    } else if let Some(rest) = command.strip_prefix("UID FETCH ") {
+                        let (set, items) = rest.split_once(' ').unwrap();
+                        let items = items.trim_start_matches('(').trim_end_matches(')');
+                        let fetched: String = set.split(',').map(|uid| fetch_response(uid.parse().unwrap(), items)).collect();
+                        format!("{}{} OK Fetch completed\r\n", fetched, tag)
+                    } else if upper.starts_with("LIST") {
+                        format!("* LIST (\\HasNoChildren) \"/\" \"INBOX\"\r\n* LIST (\\Noselect) \"/\" \"Archive\"\r\n* LIST (\\HasNoChildren) \"/\" \"Archive/2023\"\r\n{} OK List completed\r\n", tag)
+                    } else if upper.starts_with("LOGOUT") {
+                        format!("* BYE Logging out\r\n{} OK Logout completed\r\n", tag)
+                    } else {
+                        format!("{} OK\r\n", tag)
+                    };
+                    writer.write_all(response.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    });
+    (port, commands)
+}
+
+fn imap_config(port: u16, password: &str) -> ConnectorInitConfig {
+    ConnectorInitConfig::new()
+        .with_param("host", "127.0.0.1")
+        .with_param("port", &port.to_string())
+        .with_param("tls", "false")
+        .with_param("username", "support")
+        .with_param("password", password)
+}
+
+fn inbox_query(predicates: Vec<Predicate>, limit: Option<u64>) -> ConnectorQuery {
+    let mut query = InternalQuery::new(QueryOperation::Select);
+    query.sources.push(DataSource { object_type: "imap".to_string(), identifier: "INBOX".to_string(), alias: None });
+    query.predicates = predicates;
+    query.limit = limit;
+    ConnectorQuery { connector_type: ConnectorType::Imap, query, connection_params: HashMap::new() }
+}
+
+#[tokio::test]
+async fn test_imap_folder_query_searches_and_filters() {
+    let (port, commands) = start_mock_imap().await;
+    let mut connector = ImapConnector::new();
+    connector.connect(imap_config(port, "secret")).await.unwrap();
+
+    let result = connector.execute_query(inbox_query(vec![
+        Predicate { column: "from".to_string(), operator: PredicateOperator::Like, value: PredicateValue::String("bo@%".to_string()) },
+    ], None)).await.unwrap();
+    assert_eq!(result.rows.len(), 1);
+    let names: Vec<&str> = result.columns.iter().map(|column| column.name.as_str()).collect();
+    assert_eq!(names, vec!["uid", "message_id", "from", "to", "subject", "date", "flags", "snippet"]);
+    assert_eq!(result.rows[0].values, vec![
+        Value::Integer(8),
+        Value::Text("<8@acme.io>".to_string()),
+        Value::Text("bo@acme.io".to_string()),
+        Value::Text("support@example.com".to_string()),
+        Value::Text("Refund for ticket #42".to_string()),
+        Value::DateTime("2024-05-02 10:30:00".to_string()),
+        Value::Text("\\Seen \\Flagged".to_string()),
+        Value::Text("Please refund order 42.".to_string()),
+    ]);
+
+    // The newest messages come first, and a limit stops the fetching
+    let result = connector.execute_query(inbox_query(Vec::new(), Some(2))).await.unwrap();
+    let uids: Vec<Value> = result.rows.iter().map(|row| row.values[0].clone()).collect();
+    assert_eq!(uids, vec![Value::Integer(9), Value::Integer(8)]);
+
+    let commands = commands.lock().unwrap();
+    assert_eq!(commands[0], "LOGIN \"support\" \"secret\"");
+    assert!(commands.contains(&"UID SEARCH FROM \"bo@\"".to_string()), "{:?}", commands);
+    assert!(commands.contains(&"UID FETCH 9,8 (UID FLAGS INTERNALDATE ENVELOPE BODYSTRUCTURE)".to_string()), "{:?}", commands);
+    assert_eq!(commands.last().unwrap(), "UID FETCH 9,8 (BODY.PEEK[1]<0.1056>)");
+}
+
+#[tokio::test]
+async fn test_imap_folders_and_errors() {
+    let (port, _) = start_mock_imap().await;
+    let mut connector = ImapConnector::new();
+    let error = connector.connect(imap_config(port, "wrong")).await.unwrap_err();
+    assert!(error.to_string().contains("Invalid credentials"), "{}", error);
+
+    connector.connect(imap_config(port, "secret")).await.unwrap();
+    assert_eq!(connector.list_objects().await.unwrap(), vec!["INBOX", "Archive/2023"]);
+    assert_eq!(connector.get_schema("INBOX").await.unwrap().primary_key, Some(vec!["uid".to_string()]));
+    assert!(connector.get_schema("Spam").await.is_err());
+    assert_eq!(connector.count_rows(inbox_query(Vec::new(), None)).await.unwrap(), Some(3));
+
+    let mut capped = ImapConnector::new();
+    capped.connect(imap_config(port, "secret").with_param("max_messages", "2")).await.unwrap();
+    let result = capped.execute_query(inbox_query(Vec::new(), None)).await.unwrap();
+    assert_eq!(result.rows.len(), 2);
+    assert!(result.warnings[0].message.contains("max_messages"), "{}", result.warnings[0]);
+
+    connector.disconnect().await.unwrap();
+    assert!(!connector.is_connected());
+}
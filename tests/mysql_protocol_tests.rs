@@ -1,7 +1,9 @@
 #![allow(unused)]
 
-use nirv_engine::protocol::{MySQLProtocolAdapter, ProtocolAdapter, ProtocolType, Connection, ProtocolQuery};
-use nirv_engine::utils::{QueryResult, ColumnMetadata, Row, Value, DataType};
+use nirv_engine::protocol::{MySQLProtocolAdapter, ProtocolAdapter, ProtocolType, Connection, ProtocolQuery, Credentials, StatementResult};
+use nirv_engine::protocol::{MySQLAuthPlugin, native_password_token, COMPRESSION_PARAMETER};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use nirv_engine::utils::{NirvError, QueryResult, QueryWarning, ColumnMetadata, Row, Value, DataType};
 use tokio::net::{TcpListener, TcpStream};
 use std::time::Duration;
 use std::env;
@@ -167,6 +169,8 @@ mod tests {
             rows,
             affected_rows: Some(2),
             execution_time: Duration::from_millis(10),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         // Format response
@@ -183,6 +187,61 @@ mod tests {
         assert_eq!(response_bytes[4], 0x02); // Column count (2)
     }
 
+    #[tokio::test]
+    async fn test_mysql_partial_result_warning_count() {
+        let protocol = MySQLProtocolAdapter::new();
+        let connection = create_mock_connection().await;
+        
+        let mut result = QueryResult::new();
+        result.columns = vec![ColumnMetadata { name: "id".to_string(), data_type: DataType::Integer, nullable: false }];
+        result.rows = vec![Row::new(vec![Value::Integer(1)])];
+        result.warnings = vec![QueryWarning::source_failed("api.orders", &"HTTP 500")];
+        
+        // The EOF packet closing the rows counts the missing source
+        let response_bytes = protocol.format_response(&connection, result).await.unwrap();
+        let eof = &response_bytes[response_bytes.len() - 5..];
+        assert_eq!(eof, &[0xfe, 0x01, 0x00, 0x00, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn test_mysql_multi_statement_response() {
+        let protocol = MySQLProtocolAdapter::new();
+        let connection = create_mock_connection().await;
+        
+        let mut rows = QueryResult::new();
+        rows.columns = vec![ColumnMetadata { name: "id".to_string(), data_type: DataType::Integer, nullable: false }];
+        rows.rows = vec![Row::new(vec![Value::Integer(1)])];
+        let results = vec![
+            StatementResult::new("SET TIME ZONE 'UTC'", Ok(QueryResult::new())),
+            StatementResult::new("SELECT id FROM t", Ok(rows)),
+        ];
+        let response = protocol.format_statement_responses(&connection, results).await.unwrap();
+        
+        // Packets are numbered on from the OK packet across both results
+        let mut packets = Vec::new();
+        let mut offset = 0;
+        while offset < response.len() {
+            let length = u32::from_le_bytes([response[offset], response[offset + 1], response[offset + 2], 0]) as usize;
+            packets.push((response[offset + 3], &response[offset + 4..offset + 4 + length]));
+            offset += 4 + length;
+        }
+        let sequence_ids: Vec<u8> = packets.iter().map(|(id, _)| *id).collect();
+        assert_eq!(sequence_ids, (1..=packets.len() as u8).collect::<Vec<_>>());
+        
+        // The OK packet announces the result set that follows; the final EOF does not
+        let (_, ok) = packets[0];
+        assert_eq!(ok[0], 0x00);
+        assert_eq!(u16::from_le_bytes([ok[3], ok[4]]), 0x0008);
+        let (_, eof) = packets[packets.len() - 1];
+        assert_eq!(eof, &[0xfe, 0x00, 0x00, 0x00, 0x00]);
+        
+        // A failed statement ends the response with an error packet
+        let results = vec![StatementResult::new("SELECT", Err(NirvError::Internal("boom".to_string())))];
+        let response = protocol.format_statement_responses(&connection, results).await.unwrap();
+        assert_eq!(response[4], 0xff);
+        assert_eq!(u16::from_le_bytes([response[5], response[6]]), 1105);
+    }
+
     #[tokio::test]
     async fn test_mysql_handle_query() {
         let protocol = MySQLProtocolAdapter::new();
@@ -214,6 +273,8 @@ mod tests {
             rows: vec![],
             affected_rows: Some(1),
             execution_time: Duration::from_millis(5),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         // Format response
@@ -268,6 +329,8 @@ mod tests {
             rows,
             affected_rows: Some(1),
             execution_time: Duration::from_millis(1),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         // Create a mock connection
@@ -308,6 +371,245 @@ mod tests {
         let result = protocol.parse_message(&connection, &unsupported_packet).await;
         assert!(result.is_err());
     }
+    /// Wrap a command payload in a packet header with sequence id 0
+    fn command_packet(command: u8, payload: &[u8]) -> Vec<u8> {
+        let length = (payload.len() + 1) as u32;
+        let mut packet = length.to_le_bytes()[..3].to_vec();
+        packet.push(0x00);
+        packet.push(command);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[tokio::test]
+    async fn test_mysql_prepared_statement_lifecycle() {
+        let protocol = MySQLProtocolAdapter::new();
+        let connection = create_mock_connection().await;
+
+        // COM_STMT_PREPARE replies with the statement id and parameter definitions
+        let sql = b"SELECT * FROM users WHERE name = ? LIMIT ?";
+        let reply = protocol.handle_statement_command(&connection, &command_packet(0x16, sql)).unwrap().unwrap();
+        assert_eq!(reply[4], 0x00);
+        let statement_id = u32::from_le_bytes([reply[5], reply[6], reply[7], reply[8]]);
+        assert_eq!(u16::from_le_bytes([reply[11], reply[12]]), 2);
+
+        // COM_STMT_EXECUTE binds the parameters into the statement
+        let mut execute = statement_id.to_le_bytes().to_vec();
+        execute.extend_from_slice(&[0x00, 1, 0, 0, 0, 0x00, 1, 0xfd, 0, 0x08, 0]);
+        execute.extend_from_slice(&[5, b'A', b'l', b'i', b'c', b'e']);
+        execute.extend_from_slice(&10i64.to_le_bytes());
+        let query = protocol.parse_message(&connection, &command_packet(0x17, &execute)).await.unwrap();
+        assert_eq!(query.raw_query, "SELECT * FROM users WHERE name = 'Alice' LIMIT 10");
+
+        // Results are encoded as binary rows
+        let result = QueryResult {
+            columns: vec![
+                ColumnMetadata { name: "id".to_string(), data_type: DataType::Integer, nullable: false },
+                ColumnMetadata { name: "email".to_string(), data_type: DataType::Text, nullable: true },
+            ],
+            rows: vec![Row::new(vec![Value::Integer(7), Value::Null])],
+            affected_rows: None,
+            execution_time: Duration::from_millis(1),
+            stats: None,
+            warnings: Vec::new(),
+        };
+        let response = protocol.format_binary_response(&connection, &result);
+        let expected_row = [0x00, 0b0000_1000, 7, 0, 0, 0, 0, 0, 0, 0];
+        assert!(response.windows(expected_row.len()).any(|window| window == expected_row));
+
+        // COM_STMT_CLOSE has no reply and forgets the statement
+        let close = protocol.handle_statement_command(&connection, &command_packet(0x19, &statement_id.to_le_bytes())).unwrap();
+        assert!(close.is_none());
+        assert!(protocol.parse_message(&connection, &command_packet(0x17, &execute)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mysql_connection_charset() {
+        let protocol = MySQLProtocolAdapter::new();
+        let mut connection = create_mock_connection().await;
+        let result = QueryResult {
+            columns: vec![ColumnMetadata { name: "café".to_string(), data_type: DataType::Text, nullable: true }],
+            rows: vec![Row::new(vec![Value::Text("Zoë 🍰".to_string())])],
+            affected_rows: None,
+            execution_time: Duration::from_millis(1),
+            stats: None,
+            warnings: Vec::new(),
+        };
+
+        // utf8mb4 by default: four-byte characters survive and lengths count bytes
+        let response = protocol.format_response(&connection, result.clone()).await.unwrap();
+        let utf8_value = "Zoë 🍰".as_bytes();
+        let mut expected = vec![utf8_value.len() as u8];
+        expected.extend_from_slice(utf8_value);
+        assert!(response.windows(expected.len()).any(|window| window == expected));
+
+        // After SET NAMES latin1, text is sent and received in Latin-1
+        assert!(protocol.apply_set_names(&mut connection, "SET NAMES latin1").unwrap());
+        assert_eq!(connection.parameters.get("character_set_client").unwrap(), "latin1");
+        let response = protocol.format_response(&connection, result).await.unwrap();
+        assert!(response.windows(6).any(|window| window == [5, b'Z', b'o', 0xeb, b' ', b'?']));
+        assert!(response.windows(5).any(|window| window == [4, b'c', b'a', b'f', 0xe9]));
+
+        let query = protocol.parse_message(&connection, &command_packet(0x03, b"SELECT 'Cr\xe8me'")).await.unwrap();
+        assert_eq!(query.raw_query, "SELECT 'Crème'");
+
+        assert!(!protocol.apply_set_names(&mut connection, "SELECT 1").unwrap());
+        assert!(protocol.apply_set_names(&mut connection, "SET NAMES klingon").is_err());
+    }
+
+    /// Read one packet from the server side of a test connection
+    async fn read_server_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut header = [0u8; 4];
+        stream.read_exact(&mut header).await.unwrap();
+        let mut payload = vec![0u8; u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        (header[3], payload)
+    }
+
+    async fn write_client_packet(stream: &mut TcpStream, payload: &[u8], sequence_id: u8) {
+        let mut packet = (payload.len() as u32).to_le_bytes()[..3].to_vec();
+        packet.push(sequence_id);
+        packet.extend_from_slice(payload);
+        stream.write_all(&packet).await.unwrap();
+    }
+
+    /// Extract the 20-byte challenge from an initial handshake packet
+    fn handshake_scramble(handshake: &[u8]) -> Vec<u8> {
+        let version_end = 1 + handshake[1..].iter().position(|b| *b == 0).unwrap();
+        let part1 = version_end + 1 + 4;
+        let part2 = part1 + 8 + 1 + 2 + 1 + 2 + 2 + 1 + 10;
+        [&handshake[part1..part1 + 8], &handshake[part2..part2 + 12]].concat()
+    }
+
+    /// HandshakeResponse41 with secure connection, database and plugin name
+    fn handshake_response(user: &str, auth_response: &[u8], database: &str, plugin: &str) -> Vec<u8> {
+        let capabilities: u32 = 0x0000_0200 | 0x0000_8000 | 0x0000_0008 | 0x0008_0000;
+        let mut payload = capabilities.to_le_bytes().to_vec();
+        payload.extend_from_slice(&16_777_216u32.to_le_bytes());
+        payload.push(0x21);
+        payload.extend_from_slice(&[0; 23]);
+        payload.extend_from_slice(user.as_bytes());
+        payload.push(0);
+        payload.push(auth_response.len() as u8);
+        payload.extend_from_slice(auth_response);
+        payload.extend_from_slice(database.as_bytes());
+        payload.push(0);
+        payload.extend_from_slice(plugin.as_bytes());
+        payload.push(0);
+        payload
+    }
+
+    /// Accept one connection on the adapter and authenticate it with the given credentials
+    async fn spawn_authenticating_server(
+        protocol: MySQLProtocolAdapter,
+        credentials: Credentials,
+    ) -> (TcpStream, tokio::task::JoinHandle<nirv_engine::utils::NirvResult<Connection>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut connection = protocol.accept_connection(stream).await?;
+            protocol.authenticate(&mut connection, credentials).await?;
+            Ok(connection)
+        });
+        (TcpStream::connect(addr).await.unwrap(), server)
+    }
+
+    #[tokio::test]
+    async fn test_mysql_native_password_authentication() {
+        let protocol = MySQLProtocolAdapter::new().with_auth_plugin(MySQLAuthPlugin::NativePassword);
+        let credentials = Credentials::new("app".to_string(), "testdb".to_string())
+            .with_password("secret".to_string());
+        let (mut client, server) = spawn_authenticating_server(protocol, credentials).await;
+
+        let (_, handshake) = read_server_packet(&mut client).await;
+        assert!(handshake.windows(21).any(|window| window == b"mysql_native_password"));
+        let token = native_password_token("secret", &handshake_scramble(&handshake));
+        write_client_packet(&mut client, &handshake_response("app", &token, "testdb", "mysql_native_password"), 1).await;
+
+        let (sequence_id, ok) = read_server_packet(&mut client).await;
+        assert_eq!((sequence_id, ok[0]), (2, 0x00));
+        let connection = server.await.unwrap().unwrap();
+        assert!(connection.authenticated);
+        assert_eq!(connection.database, "testdb");
+    }
+
+    #[tokio::test]
+    async fn test_mysql_compressed_protocol() {
+        let protocol = MySQLProtocolAdapter::new().with_auth_plugin(MySQLAuthPlugin::NativePassword);
+        let credentials = Credentials::new("app".to_string(), "testdb".to_string())
+            .with_password("secret".to_string());
+        let (mut client, server) = spawn_authenticating_server(protocol, credentials).await;
+
+        let (_, handshake) = read_server_packet(&mut client).await;
+        let token = native_password_token("secret", &handshake_scramble(&handshake));
+        let mut response = handshake_response("app", &token, "testdb", "mysql_native_password");
+        response[0] |= 0x20; // CLIENT_COMPRESS
+        write_client_packet(&mut client, &response, 1).await;
+
+        // The OK packet is sent before compression starts
+        let (sequence_id, ok) = read_server_packet(&mut client).await;
+        assert_eq!((sequence_id, ok[0]), (2, 0x00));
+        let connection = server.await.unwrap().unwrap();
+        let protocol = MySQLProtocolAdapter::new();
+        assert!(protocol.is_compressed(&connection));
+
+        // Short commands come in compressed frames carrying them as they are
+        let query = command_packet(0x03, b"SELECT 1");
+        let mut frame = (query.len() as u32).to_le_bytes()[..3].to_vec();
+        frame.extend_from_slice(&[0, 0, 0, 0]);
+        frame.extend_from_slice(&query);
+        assert_eq!(protocol.parse_message(&connection, &frame).await.unwrap().raw_query, "SELECT 1");
+
+        let result = QueryResult {
+            columns: vec![ColumnMetadata { name: "name".to_string(), data_type: DataType::Text, nullable: false }],
+            rows: (0..500).map(|i| Row::new(vec![Value::Text(format!("customer {}", i % 10))])).collect(),
+            affected_rows: None,
+            execution_time: Duration::from_millis(1),
+            stats: None,
+            warnings: Vec::new(),
+        };
+        let mut uncompressed_connection = create_mock_connection().await;
+        uncompressed_connection.parameters = connection.parameters.clone();
+        uncompressed_connection.parameters.remove(COMPRESSION_PARAMETER);
+        let plain = protocol.format_response(&uncompressed_connection, result.clone()).await.unwrap();
+        let compressed = protocol.format_response(&connection, result).await.unwrap();
+        assert!(compressed.len() < plain.len() / 2);
+
+        let compressed_length = u32::from_le_bytes([compressed[0], compressed[1], compressed[2], 0]) as usize;
+        let uncompressed_length = u32::from_le_bytes([compressed[4], compressed[5], compressed[6], 0]) as usize;
+        assert_eq!((compressed.len(), compressed[3], uncompressed_length), (7 + compressed_length, 1, plain.len()));
+        let inflated = nirv_engine::protocol::compression::zlib_decompress(&compressed[7..], usize::MAX).unwrap();
+        assert_eq!(inflated, plain);
+        assert_eq!(protocol.unframe_packets(&connection, &compressed).unwrap(), plain);
+    }
+
+    #[tokio::test]
+    async fn test_mysql_caching_sha2_full_authentication_requires_tls() {
+        let protocol = MySQLProtocolAdapter::new();
+        let credentials = Credentials::new("app".to_string(), "testdb".to_string())
+            .with_password("secret".to_string());
+        let (mut client, server) = spawn_authenticating_server(protocol, credentials).await;
+
+        // A client answering with mysql_native_password is switched to caching_sha2_password
+        let (_, handshake) = read_server_packet(&mut client).await;
+        let token = native_password_token("secret", &handshake_scramble(&handshake));
+        write_client_packet(&mut client, &handshake_response("app", &token, "testdb", "mysql_native_password"), 1).await;
+        let (sequence_id, switch_request) = read_server_packet(&mut client).await;
+        assert_eq!(switch_request[0], 0xfe);
+        assert!(switch_request.starts_with(b"\xfecaching_sha2_password\0"));
+
+        // The user is not cached yet, so full authentication is requested and refused over plain TCP
+        write_client_packet(&mut client, &[0u8; 32], sequence_id + 1).await;
+        let (sequence_id, more_data) = read_server_packet(&mut client).await;
+        assert_eq!(more_data, vec![0x01, 0x04]);
+        write_client_packet(&mut client, b"secret\0", sequence_id + 1).await;
+
+        let (_, error) = read_server_packet(&mut client).await;
+        assert_eq!(error[0], 0xff);
+        assert_eq!(u16::from_le_bytes([error[1], error[2]]), 1045);
+        assert!(server.await.unwrap().is_err());
+    }
 }
 
 /// Integration tests that require a running MySQL instance
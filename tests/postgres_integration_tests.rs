@@ -42,6 +42,8 @@ mod integration_tests {
             ],
             affected_rows: Some(2),
             execution_time: std::time::Duration::from_millis(5),
+            stats: None,
+            warnings: Vec::new(),
         };
         
         // Test that the protocol can format the result
@@ -459,7 +459,7 @@ mod resource_cleanup_completion_tests {
         #[cfg(not(target_os = "windows"))]
         {
             let output = Command::new("netstat")
-                .args(&["-ln"])
+                .args(["-ln"])
                 .output();
             
             if let Ok(output) = output {
@@ -502,7 +502,6 @@ mod resource_cleanup_completion_tests {
                 
                 // The property we're testing is that cleanup completes successfully,
                 // not that no processes exist (since service containers may persist)
-                assert!(true); // Cleanup completion is verified by successful test execution
             }
         }
     }
@@ -536,7 +535,6 @@ mod resource_cleanup_completion_tests {
                 }
                 
                 // The cleanup completion property is satisfied if tests complete successfully
-                assert!(true);
             }
         }
     }
@@ -614,8 +612,6 @@ mod resource_cleanup_completion_tests {
                     assert!(!connection_params.is_empty());
                     
                     // Cleanup is implicit when variables go out of scope
-                    drop(connection_params);
-                    drop(adapter);
                 }
                 ProtocolType::PostgreSQL => {
                     let adapter = PostgresProtocol::new();
@@ -624,7 +620,6 @@ mod resource_cleanup_completion_tests {
                     assert_eq!(adapter.get_protocol_type(), ProtocolType::PostgreSQL);
                     
                     // Cleanup is implicit when adapter goes out of scope
-                    drop(adapter);
                 }
                 ProtocolType::SqlServer => {
                     let adapter = SqlServerProtocol::new();
@@ -633,7 +628,6 @@ mod resource_cleanup_completion_tests {
                     assert_eq!(adapter.get_protocol_type(), ProtocolType::SqlServer);
                     
                     // Cleanup is implicit when adapter goes out of scope
-                    drop(adapter);
                 }
                 ProtocolType::SQLite => {
                     let adapter = SQLiteProtocolAdapter::new();
@@ -642,7 +636,6 @@ mod resource_cleanup_completion_tests {
                     assert_eq!(adapter.get_protocol_type(), ProtocolType::SQLite);
                     
                     // Cleanup is implicit when adapter goes out of scope
-                    drop(adapter);
                 }
             }
         }
@@ -650,8 +643,7 @@ mod resource_cleanup_completion_tests {
         // Allow some time for cleanup to complete
         sleep(Duration::from_millis(100)).await;
         
-        // Verify that cleanup completed successfully (no panics or errors)
-        assert!(true);
+        // Cleanup completed successfully if we got here without panics or errors
     }
 
     /// Property test: Cleanup should complete within reasonable time bounds
@@ -668,20 +660,16 @@ mod resource_cleanup_completion_tests {
             // Simulate resource creation and cleanup
             match config.protocol_type {
                 ProtocolType::MySQL => {
-                    let adapter = MySQLProtocolAdapter::new();
-                    drop(adapter);
+                    let _adapter = MySQLProtocolAdapter::new();
                 }
                 ProtocolType::PostgreSQL => {
-                    let adapter = PostgresProtocol::new();
-                    drop(adapter);
+                    let _adapter = PostgresProtocol::new();
                 }
                 ProtocolType::SqlServer => {
-                    let adapter = SqlServerProtocol::new();
-                    drop(adapter);
+                    let _adapter = SqlServerProtocol::new();
                 }
                 ProtocolType::SQLite => {
-                    let adapter = SQLiteProtocolAdapter::new();
-                    drop(adapter);
+                    let _adapter = SQLiteProtocolAdapter::new();
                 }
             }
             
@@ -4,7 +4,7 @@ use nirv_engine::{
     engine::{QueryExecutor, ExecutionPlan, PlanNode, DefaultQueryExecutor},
     connectors::{MockConnector, ConnectorRegistry, Connector, ConnectorInitConfig},
     utils::{
-        types::{InternalQuery, QueryOperation, DataSource, Column, QueryResult, ConnectorType, Value, Row, ColumnMetadata, DataType},
+        types::{InternalQuery, QueryOperation, DataSource, Column, QueryResult, ConnectorType, Value, Row, ColumnMetadata, DataType, Predicate, PredicateOperator, PredicateValue, COUNT_ALL},
         error::{NirvResult, NirvError},
     },
 };
@@ -286,4 +286,226 @@ async fn test_query_executor_empty_plan() {
     let query_result = result.unwrap();
     assert_eq!(query_result.row_count(), 0);
     assert!(query_result.is_empty());
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_query_executor_evaluates_json_functions() {
+    let mut executor = DefaultQueryExecutor::new();
+    
+    let mut mock_connector = MockConnector::new();
+    mock_connector.add_test_data_with_columns("events", vec!["id", "payload"], vec![
+        vec![Value::Integer(1), Value::Json(r#"{"user": {"id": 7, "name": "Ann"}}"#.to_string())],
+        vec![Value::Integer(2), Value::Json(r#"{"user": {"id": 9, "name": "Bo"}}"#.to_string())],
+        vec![Value::Integer(3), Value::Json(r#"{"user": null}"#.to_string())],
+    ]);
+    mock_connector.connect(ConnectorInitConfig::new()).await.unwrap();
+    
+    let mut connector_registry = ConnectorRegistry::new();
+    connector_registry.register("mock_0".to_string(), Box::new(mock_connector)).unwrap();
+    executor.set_connector_registry(connector_registry);
+    
+    // SELECT id, JSON_EXTRACT(payload, '$.user.name') AS name FROM events WHERE JSON_EXTRACT(payload, '$.user.id') > 8
+    let plan = ExecutionPlan {
+        nodes: vec![
+            PlanNode::TableScan {
+                source: DataSource {
+                    object_type: "mock".to_string(),
+                    identifier: "events".to_string(),
+                    alias: None,
+                },
+                projections: vec![
+                    Column { name: "id".to_string(), alias: None, source: None },
+                    Column { name: "json_extract(payload, '$.user.name')".to_string(), alias: Some("name".to_string()), source: None },
+                ],
+                predicates: vec![Predicate {
+                    column: "json_extract(payload, '$.user.id')".to_string(),
+                    operator: PredicateOperator::GreaterThan,
+                    value: PredicateValue::Integer(8),
+                }],
+            }
+        ],
+        estimated_cost: 1.0,
+    };
+    
+    let result = executor.execute_plan(&plan).await.unwrap();
+    let names: Vec<&str> = result.columns.iter().map(|col| col.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "name"]);
+    assert_eq!(result.columns[1].data_type, DataType::Text);
+    assert_eq!(result.row_count(), 1);
+    assert_eq!(result.rows[0].values, vec![Value::Integer(2), Value::Text("Bo".to_string())]);
+}
+
+#[tokio::test]
+async fn test_query_executor_filters_locally_what_connector_cannot_push_down() {
+    let mut executor = DefaultQueryExecutor::new();
+    
+    let mut mock_connector = MockConnector::new();
+    mock_connector.add_test_data_with_columns("people", vec!["id", "name"], vec![
+        vec![Value::Integer(1), Value::Text("Alice".to_string())],
+        vec![Value::Integer(2), Value::Text("Bob".to_string())],
+        vec![Value::Integer(3), Value::Text("Anna".to_string())],
+    ]);
+    mock_connector.connect(ConnectorInitConfig::new()).await.unwrap();
+    
+    let mut connector_registry = ConnectorRegistry::new();
+    connector_registry.register("mock_0".to_string(), Box::new(mock_connector)).unwrap();
+    executor.set_connector_registry(connector_registry);
+    
+    // SELECT id FROM people WHERE name LIKE 'A%' AND id > 1, for a connector without LIKE support
+    let plan = ExecutionPlan {
+        nodes: vec![
+            PlanNode::TableScan {
+                source: DataSource {
+                    object_type: "mock".to_string(),
+                    identifier: "people".to_string(),
+                    alias: None,
+                },
+                projections: vec![Column { name: "id".to_string(), alias: None, source: None }],
+                predicates: vec![
+                    Predicate {
+                        column: "name".to_string(),
+                        operator: PredicateOperator::Like,
+                        value: PredicateValue::String("A%".to_string()),
+                    },
+                    Predicate {
+                        column: "id".to_string(),
+                        operator: PredicateOperator::GreaterThan,
+                        value: PredicateValue::Integer(1),
+                    },
+                ],
+            }
+        ],
+        estimated_cost: 1.0,
+    }.with_pushdown(|predicate| predicate.operator != PredicateOperator::Like);
+    
+    assert_eq!(plan.local_predicates().len(), 1);
+    assert_eq!(plan.pushed_predicates()[0].column, "id");
+    
+    let result = executor.execute_plan(&plan).await.unwrap();
+    let names: Vec<&str> = result.columns.iter().map(|col| col.name.as_str()).collect();
+    assert_eq!(names, vec!["id"]);
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].values, vec![Value::Integer(3)]);
+    
+    // The scan is recorded before the local filter, and each node after its input
+    let stats = result.stats.expect("executor records statistics");
+    assert_eq!(stats.sources.len(), 1);
+    assert_eq!(stats.sources[0].source, "mock.people");
+    assert_eq!(stats.sources[0].rows_scanned, 2);
+    assert_eq!((stats.sources[0].pushed_predicates, stats.sources[0].local_predicates), (1, 1));
+    let nodes: Vec<(&str, u64)> = stats.nodes.iter().map(|node| (node.node.as_str(), node.rows)).collect();
+    assert_eq!(nodes, vec![("TableScan mock.people", 2), ("Filter (local)", 1), ("Projection", 1)]);
+}
+
+#[tokio::test]
+async fn test_query_executor_filters_fetched_batches() {
+    let mut executor = DefaultQueryExecutor::new();
+    
+    let mut mock_connector = MockConnector::new();
+    mock_connector.add_test_data_with_columns("people", vec!["id", "name"], (1..=5)
+        .map(|id| vec![Value::Integer(id), Value::Text(if id % 2 == 0 { "Bob" } else { "Anna" }.to_string())])
+        .collect());
+    mock_connector.connect(ConnectorInitConfig::new().with_fetch_size(2)).await.unwrap();
+    
+    // The connector hands its rows over two at a time
+    let query = nirv_engine::utils::types::ConnectorQuery {
+        connector_type: ConnectorType::Mock,
+        query: {
+            let mut query = InternalQuery::new(QueryOperation::Select);
+            query.sources.push(DataSource { object_type: "mock".to_string(), identifier: "people".to_string(), alias: None });
+            query
+        },
+        connection_params: std::collections::HashMap::new(),
+    };
+    let mut sizes = Vec::new();
+    let result = mock_connector.execute_query_batched(query, &mut |_: &[ColumnMetadata], rows: Vec<Row>| {
+        sizes.push(rows.len());
+        Ok(())
+    }).await.unwrap();
+    assert_eq!(sizes, vec![2, 2, 1]);
+    assert!(result.rows.is_empty());
+    
+    let mut connector_registry = ConnectorRegistry::new();
+    connector_registry.register("mock_0".to_string(), Box::new(mock_connector)).unwrap();
+    executor.set_connector_registry(connector_registry);
+    
+    // SELECT id FROM people WHERE name LIKE 'A%', filtered as each fetch arrives
+    let plan = ExecutionPlan {
+        nodes: vec![
+            PlanNode::TableScan {
+                source: DataSource {
+                    object_type: "mock".to_string(),
+                    identifier: "people".to_string(),
+                    alias: None,
+                },
+                projections: vec![Column { name: "id".to_string(), alias: None, source: None }],
+                predicates: vec![Predicate {
+                    column: "name".to_string(),
+                    operator: PredicateOperator::Like,
+                    value: PredicateValue::String("A%".to_string()),
+                }],
+            }
+        ],
+        estimated_cost: 1.0,
+    }.with_pushdown(|_| false);
+    
+    let result = executor.execute_plan(&plan).await.unwrap();
+    let ids: Vec<&Value> = result.rows.iter().map(|row| &row.values[0]).collect();
+    assert_eq!(ids, vec![&Value::Integer(1), &Value::Integer(3), &Value::Integer(5)]);
+    
+    let stats = result.stats.expect("executor records statistics");
+    assert_eq!(stats.sources[0].rows_scanned, 5);
+    assert_eq!((stats.sources[0].pushed_predicates, stats.sources[0].local_predicates), (0, 1));
+    let nodes: Vec<(&str, u64)> = stats.nodes.iter().map(|node| (node.node.as_str(), node.rows)).collect();
+    assert_eq!(nodes, vec![("TableScan mock.people", 5), ("Filter (local)", 3), ("Projection", 3)]);
+}
+
+
+#[tokio::test]
+async fn test_query_executor_counts_at_source() {
+    let mut executor = DefaultQueryExecutor::new();
+    
+    let mut mock_connector = MockConnector::new();
+    mock_connector.add_test_data_with_columns("people", vec!["id", "name"], (1..=5)
+        .map(|id| vec![Value::Integer(id), Value::Text(if id % 2 == 0 { "Bob" } else { "Anna" }.to_string())])
+        .collect());
+    mock_connector.connect(ConnectorInitConfig::new()).await.unwrap();
+    
+    let mut connector_registry = ConnectorRegistry::new();
+    connector_registry.register("mock_0".to_string(), Box::new(mock_connector)).unwrap();
+    executor.set_connector_registry(connector_registry);
+    
+    // SELECT COUNT(*) AS total FROM people WHERE name LIKE 'A%'
+    let plan = ExecutionPlan {
+        nodes: vec![
+            PlanNode::Count {
+                column: Column { name: COUNT_ALL.to_string(), alias: Some("total".to_string()), source: None },
+                input: Box::new(PlanNode::TableScan {
+                    source: DataSource {
+                        object_type: "mock".to_string(),
+                        identifier: "people".to_string(),
+                        alias: None,
+                    },
+                    projections: vec![Column { name: "*".to_string(), alias: None, source: None }],
+                    predicates: vec![Predicate {
+                        column: "name".to_string(),
+                        operator: PredicateOperator::Like,
+                        value: PredicateValue::String("A%".to_string()),
+                    }],
+                }),
+            }
+        ],
+        estimated_cost: 1.0,
+    };
+    
+    // The connector answers the count without handing over its rows
+    let result = executor.execute_plan(&plan).await.unwrap();
+    assert_eq!(result.columns[0].name, "total");
+    assert_eq!(result.count_value(), Some(3));
+    assert_eq!(result.stats.expect("executor records statistics").sources[0].rows_scanned, 0);
+    
+    // A predicate evaluated locally means the rows are fetched and counted here
+    let result = executor.execute_plan(&plan.with_pushdown(|_| false)).await.unwrap();
+    assert_eq!(result.count_value(), Some(3));
+    assert_eq!(result.stats.expect("executor records statistics").sources[0].rows_scanned, 5);
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use nirv_engine::{
+    Engine, EngineBuilder, MockConnector, ConnectorInitConfig, Connector,
+    NirvResult, NirvError,
+};
+use nirv_engine::engine::QueryRewriter;
+use nirv_engine::utils::{EngineConfig, InternalQuery, QueryRewriterConfig};
+use std::sync::Arc;
+
+async fn create_engine(config: EngineConfig) -> NirvResult<Engine> {
+    let mut engine = EngineBuilder::new().with_config(config).build()?;
+    engine.initialize_for_testing().await?;
+
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+
+    Ok(engine)
+}
+
+fn config_with_rewriters(json: &str) -> EngineConfig {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.query_rewriters = serde_json::from_str::<Vec<QueryRewriterConfig>>(json).unwrap();
+    config
+}
+
+#[tokio::test]
+async fn test_configured_predicate_injection() -> NirvResult<()> {
+    let engine = create_engine(config_with_rewriters(
+        r#"[{"type": "inject_predicate", "column": "age", "value": 25}]"#
+    )).await?;
+
+    let result = engine.execute_query("SELECT * FROM source('mock.users')").await?;
+    assert_eq!(result.row_count(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_configured_remap_and_denylist() -> NirvResult<()> {
+    let engine = create_engine(config_with_rewriters(r#"[
+        {"type": "table_remap", "mappings": {"mock.people": "mock.users"}},
+        {"type": "denylist", "sources": ["mock.products"]}
+    ]"#)).await?;
+
+    let result = engine.execute_query("SELECT * FROM source('mock.people')").await?;
+    assert_eq!(result.row_count(), 3);
+
+    let denied = engine.execute_query("SELECT * FROM source('mock.products')").await;
+    assert!(matches!(denied, Err(NirvError::QueryRewrite(_))));
+
+    Ok(())
+}
+
+/// Rewriter that caps every query at a fixed row limit
+struct LimitRewriter(u64);
+
+#[async_trait]
+impl QueryRewriter for LimitRewriter {
+    fn name(&self) -> &str {
+        "limit"
+    }
+
+    async fn rewrite(&self, mut query: InternalQuery) -> NirvResult<InternalQuery> {
+        query.limit = Some(query.limit.map_or(self.0, |limit| limit.min(self.0)));
+        Ok(query)
+    }
+}
+
+#[tokio::test]
+async fn test_embedder_rewriter_runs_after_configured_rewriters() -> NirvResult<()> {
+    let mut engine = create_engine(config_with_rewriters(
+        r#"[{"type": "table_remap", "mappings": {"mock.people": "mock.users"}}]"#
+    )).await?;
+    engine.add_query_rewriter(Arc::new(LimitRewriter(2)));
+
+    let result = engine.execute_query("SELECT * FROM source('mock.people')").await?;
+    assert_eq!(result.row_count(), 2);
+
+    Ok(())
+}
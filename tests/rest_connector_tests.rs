@@ -2,17 +2,16 @@
 
 use std::collections::HashMap;
 use std::time::Duration;
-use tokio_test;
 use serde_json::json;
 use reqwest::Method;
 
 use nirv_engine::connectors::{
-    RestConnector, EndpointMapping, AuthConfig, RateLimitConfig,
-    Connector, ConnectorInitConfig
+    RestConnector, EndpointMapping, AuthConfig, RateLimitConfig, JsonFlattening,
+    Connector, ConnectorInitConfig, RetryPolicy, CountSource, ContractMode, ResponseContract, BodyTemplate, Pagination
 };
 use nirv_engine::utils::types::{
     ConnectorQuery, ConnectorType, InternalQuery, QueryOperation,
-    DataSource, Predicate, PredicateOperator, PredicateValue
+    DataSource, Predicate, PredicateOperator, PredicateValue, Value, CacheStatus
 };
 
 /// Mock HTTP server for testing
@@ -71,6 +70,14 @@ async fn test_rest_connector_with_cache_ttl() {
     assert_eq!(connector.get_connector_type(), ConnectorType::Rest);
 }
 
+#[tokio::test]
+async fn test_rest_connector_with_flattening() {
+    let connector = RestConnector::new()
+        .with_flattening(JsonFlattening::new(2).with_separator("_").with_unnest("items"));
+    
+    assert_eq!(connector.get_connector_type(), ConnectorType::Rest);
+}
+
 #[tokio::test]
 async fn test_connector_init_config_for_rest() {
     let mut config = ConnectorInitConfig::new()
@@ -96,6 +103,11 @@ async fn test_endpoint_mapping_creation() {
         },
         response_path: Some("data".to_string()),
         id_field: Some("id".to_string()),
+        count: None,
+        contract: None,
+        body: None,
+        pagination: None,
+        shards: Vec::new(),
     };
     
     assert_eq!(mapping.path, "/api/users");
@@ -248,4 +260,441 @@ async fn test_data_source_for_rest_endpoint() {
     assert_eq!(data_source.object_type, "rest");
     assert_eq!(data_source.identifier, "users");
     assert_eq!(data_source.alias, Some("u".to_string()));
-}
\ No newline at end of file
+}
+
+/// Serve a single canned HTTP response on a local port
+fn serve_once(response: String) -> String {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let _ = stream.read(&mut request);
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_count_rows_from_api_totals() {
+    let count_query = |predicates: Vec<Predicate>| {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "api".to_string(), identifier: "users".to_string(), alias: None });
+        query.predicates = predicates;
+        ConnectorQuery { connector_type: ConnectorType::Rest, query, connection_params: HashMap::new() }
+    };
+    let mapping = |count: Option<CountSource>| EndpointMapping {
+        path: "/users".to_string(),
+        method: Method::GET,
+        query_params: HashMap::new(),
+        response_path: None,
+        id_field: None,
+        count,
+        contract: None,
+        body: None,
+        pagination: None,
+        shards: Vec::new(),
+    };
+
+    // The connector-wide count header is read from the endpoint's own response
+    let url = serve_once("HTTP/1.1 200 OK\r\nX-Total-Count: 42\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n[]".to_string());
+    let mut connector = RestConnector::new();
+    connector.add_endpoint_mapping("users".to_string(), mapping(None));
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url).with_param("count_header", "X-Total-Count")).await.unwrap();
+    assert_eq!(connector.count_rows(count_query(Vec::new())).await.unwrap(), Some(42));
+
+    // API totals ignore filters
+    let filtered = count_query(vec![Predicate {
+        column: "active".to_string(),
+        operator: PredicateOperator::Equal,
+        value: PredicateValue::Boolean(true),
+    }]);
+    assert_eq!(connector.count_rows(filtered).await.unwrap(), None);
+
+    let body = r#"{"meta":{"total":"17"}}"#;
+    let url = serve_once(format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body));
+    let mut connector = RestConnector::new();
+    connector.add_endpoint_mapping("users".to_string(), mapping(Some(CountSource::Endpoint {
+        path: "/users/count".to_string(),
+        response_path: Some("meta.total".to_string()),
+    })));
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+    assert_eq!(connector.count_rows(count_query(Vec::new())).await.unwrap(), Some(17));
+}
+
+#[tokio::test]
+async fn test_response_contract_catches_drift() {
+    let body = r#"[{"id":1,"email":"ann@acme.io","signed_up":"2024-05-01T08:00:00Z"},{"id":"2","email":"bo@acme.io","signed_up":null}]"#;
+    let response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+    let users_query = || {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "api".to_string(), identifier: "users".to_string(), alias: None });
+        ConnectorQuery { connector_type: ConnectorType::Rest, query, connection_params: HashMap::new() }
+    };
+    let connector_for = |mode: ContractMode, url: &str| {
+        let url = url.to_string();
+        async move {
+            let mut connector = RestConnector::new();
+            connector.add_endpoint_mapping("users".to_string(), EndpointMapping {
+                path: "/users".to_string(),
+                method: Method::GET,
+                query_params: HashMap::new(),
+                response_path: None,
+                id_field: Some("id".to_string()),
+                count: None,
+                contract: Some(ResponseContract::parse("id:integer!,email:text!,signed_up:datetime", mode).unwrap()),
+                body: None,
+                pagination: None,
+                shards: Vec::new(),
+            });
+            connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+            connector
+        }
+    };
+
+    // A strict contract fails on the record whose ID became text
+    let connector = connector_for(ContractMode::Strict, &serve_once(response.clone())).await;
+    let schema = connector.get_schema("users").await.unwrap();
+    assert_eq!(schema.columns.len(), 3);
+    assert_eq!(schema.primary_key, Some(vec!["id".to_string()]));
+    let error = connector.execute_query(users_query()).await.unwrap_err();
+    assert!(error.to_string().contains("Response schema mismatch: record 2 of the response: field 'id' expected an integer"), "{}", error);
+
+    // Coercing converts it and says so
+    let connector = connector_for(ContractMode::Coerce, &serve_once(response)).await;
+    let result = connector.execute_query(users_query()).await.unwrap();
+    assert_eq!(result.rows[0].values, vec![
+        Value::Integer(1),
+        Value::Text("ann@acme.io".to_string()),
+        Value::DateTime("2024-05-01 08:00:00".to_string()),
+    ]);
+    assert_eq!(result.rows[1].values[0], Value::Integer(2));
+    assert_eq!(result.warnings.len(), 1);
+    assert!(result.warnings[0].message.contains("column 'id': 1 values converted to an integer"), "{}", result.warnings[0]);
+}
+
+/// Serve one canned JSON response, handing back the request it answered
+fn serve_recording(body: &'static str) -> (String, std::sync::mpsc::Receiver<String>) {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        // Read the headers, then as much body as they announce
+        loop {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text.lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|value| value.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if read == 0 || request.len() >= end + 4 + length {
+                    break;
+                }
+            }
+        }
+        let response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+        stream.write_all(response.as_bytes()).unwrap();
+        sender.send(String::from_utf8_lossy(&request).to_string()).unwrap();
+    });
+    (url, receiver)
+}
+
+#[tokio::test]
+async fn test_post_search_body_from_query() {
+    let (url, requests) = serve_recording(r#"{"hits":[{"id":3,"status":"open"}]}"#);
+    let mut connector = RestConnector::new();
+    connector.add_endpoint_mapping("tickets".to_string(), EndpointMapping {
+        path: "/search".to_string(),
+        method: Method::POST,
+        query_params: HashMap::new(),
+        response_path: Some("hits".to_string()),
+        id_field: None,
+        count: None,
+        contract: None,
+        body: Some(BodyTemplate::json(r#"{"query": {"status": "{{status}}", "team": "{{team}}"}, "size": "{{limit|50}}"}"#).unwrap()),
+        pagination: None,
+        shards: Vec::new(),
+    });
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+
+    let mut query = InternalQuery::new(QueryOperation::Select);
+    query.sources.push(DataSource { object_type: "api".to_string(), identifier: "tickets".to_string(), alias: None });
+    query.predicates.push(Predicate {
+        column: "status".to_string(),
+        operator: PredicateOperator::Equal,
+        value: PredicateValue::String("open".to_string()),
+    });
+    query.limit = Some(5);
+    let result = connector.execute_query(ConnectorQuery { connector_type: ConnectorType::Rest, query, connection_params: HashMap::new() }).await.unwrap();
+    assert_eq!(result.rows.len(), 1);
+
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("POST /search"), "{}", request);
+    assert!(request.to_lowercase().contains("content-type: application/json"), "{}", request);
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(body).unwrap(), json!({ "query": { "status": "open" }, "size": 5 }));
+}
+
+/// Serve `rows` numbered records per region in pages of `?page=N&size=M`, each answered
+/// after a pause, counting the requests and the most ever in flight at once
+async fn start_paged_api(rows: u64) -> (String, std::sync::Arc<std::sync::Mutex<(usize, usize, usize)>>) {
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server};
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+
+    // Requests served, requests in flight, most in flight
+    let stats = Arc::new(Mutex::new((0usize, 0usize, 0usize)));
+    let log = stats.clone();
+    let make_service = make_service_fn(move |_| {
+        let log = log.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let log = log.clone();
+                async move {
+                    {
+                        let mut stats = log.lock().unwrap();
+                        stats.0 += 1;
+                        stats.1 += 1;
+                        stats.2 = stats.2.max(stats.1);
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    let params: HashMap<String, String> = url::form_urlencoded::parse(request.uri().query().unwrap_or("").as_bytes()).into_owned().collect();
+                    let page: u64 = params["page"].parse().unwrap();
+                    let size: u64 = params["size"].parse().unwrap();
+                    let region = params.get("region").cloned().unwrap_or_default();
+                    let items: Vec<serde_json::Value> = ((page - 1) * size + 1..=(page * size).min(rows))
+                        .map(|id| json!({ "id": id, "region": region }))
+                        .collect();
+                    log.lock().unwrap().1 -= 1;
+                    Ok::<_, Infallible>(Response::new(Body::from(json!({ "items": items, "total": rows }).to_string())))
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+    (url, stats)
+}
+
+#[tokio::test]
+async fn test_pages_and_shards_are_fetched_concurrently_in_order() {
+    let (url, stats) = start_paged_api(9).await;
+    let paged_mapping = |pagination: Pagination, shards: Vec<HashMap<String, String>>| EndpointMapping {
+        path: "/items".to_string(),
+        method: Method::GET,
+        query_params: HashMap::new(),
+        response_path: Some("items".to_string()),
+        id_field: None,
+        count: None,
+        contract: None,
+        body: None,
+        pagination: Some(pagination),
+        shards,
+    };
+    let items_query = |limit: Option<u64>| {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "api".to_string(), identifier: "items".to_string(), alias: None });
+        query.limit = limit;
+        ConnectorQuery { connector_type: ConnectorType::Rest, query, connection_params: HashMap::new() }
+    };
+    let ids = |result: &nirv_engine::utils::types::QueryResult| -> Vec<Value> {
+        result.rows.iter().map(|row| row.values.iter().find(|value| matches!(value, Value::Integer(_))).unwrap().clone()).collect()
+    };
+
+    // The total in the first page has the other four requested at once, two at a time
+    let mut connector = RestConnector::new().with_fetch_concurrency(2);
+    let shards = ["eu", "us"].iter()
+        .map(|region| HashMap::from([("region".to_string(), region.to_string())]))
+        .collect();
+    connector.add_endpoint_mapping("items".to_string(), paged_mapping(
+        Pagination::page_number("page", 2).with_size_param("size").with_total_path("total"),
+        shards,
+    ));
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+    let result = connector.execute_query(items_query(None)).await.unwrap();
+    let expected: Vec<Value> = (1..=9).chain(1..=9).map(Value::Integer).collect();
+    assert_eq!(ids(&result), expected);
+    {
+        let stats = stats.lock().unwrap();
+        assert_eq!(stats.0, 10);
+        assert_eq!(stats.2, 2);
+    }
+
+    // Without a total, waves stop at the short page, or once the limit is met
+    let mut connector = RestConnector::new().with_fetch_concurrency(3);
+    connector.add_endpoint_mapping("items".to_string(), paged_mapping(Pagination::page_number("page", 2).with_size_param("size"), Vec::new()));
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+    let result = connector.execute_query(items_query(None)).await.unwrap();
+    assert_eq!(ids(&result), (1..=9).map(Value::Integer).collect::<Vec<_>>());
+    // The first page, then two waves of three
+    assert_eq!(stats.lock().unwrap().0, 17);
+
+    let result = connector.execute_query(items_query(Some(3))).await.unwrap();
+    assert_eq!(ids(&result), (1..=3).map(Value::Integer).collect::<Vec<_>>());
+    assert_eq!(stats.lock().unwrap().0, 19);
+}
+
+#[tokio::test]
+async fn test_disk_cache_survives_restart() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let cache_dir = dir.path().join("rest-cache");
+    let body = r#"[{"id":1,"name":"ann"}]"#;
+    let url = serve_once(format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body));
+    let config = ConnectorInitConfig::new()
+        .with_param("base_url", &url)
+        .with_param("cache_dir", cache_dir.to_str().unwrap());
+    let users_query = || {
+        let mut query = InternalQuery::new(QueryOperation::Select);
+        query.sources.push(DataSource { object_type: "api".to_string(), identifier: format!("{}/users", url), alias: None });
+        ConnectorQuery { connector_type: ConnectorType::Rest, query, connection_params: HashMap::new() }
+    };
+
+    let mut connector = RestConnector::new();
+    connector.connect(config.clone()).await.unwrap();
+    assert_eq!(connector.execute_query(users_query()).await.unwrap().rows.len(), 1);
+    connector.disconnect().await.unwrap();
+
+    // The server answered once; a restarted connector is served from the cache directory
+    let mut restarted = RestConnector::new();
+    restarted.connect(config).await.unwrap();
+    let result = restarted.execute_query(users_query()).await.unwrap();
+    assert_eq!(result.rows[0].values[1], Value::Text("ann".to_string()));
+}
+
+/// Serve each response to one request in turn, recording the requests answered. A status
+/// may be followed by header lines.
+fn serve_in_turn(responses: Vec<(&'static str, &'static str)>) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let served = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = served.clone();
+    std::thread::spawn(move || {
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let response = format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+            stream.write_all(response.as_bytes()).unwrap();
+            recorded.lock().unwrap().push(String::from_utf8_lossy(&request[..read]).to_string());
+        }
+    });
+    (url, served)
+}
+
+fn users_query(url: &str) -> ConnectorQuery {
+    let mut query = InternalQuery::new(QueryOperation::Select);
+    query.sources.push(DataSource { object_type: "api".to_string(), identifier: format!("{}/users", url), alias: None });
+    ConnectorQuery { connector_type: ConnectorType::Rest, query, connection_params: HashMap::new() }
+}
+
+#[tokio::test]
+async fn test_failed_responses_are_cached_for_the_negative_ttl() {
+    let (url, served) = serve_in_turn(vec![
+        ("500 Internal Server Error", "{}"),
+        ("200 OK", r#"[{"id":1}]"#),
+    ]);
+    let mut connector = RestConnector::new().with_negative_cache_ttl(Duration::from_secs(60));
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+
+    assert!(connector.execute_query(users_query(&url)).await.is_err());
+    // The failure is answered from the cache instead of hitting the API again
+    let error = connector.execute_query(users_query(&url)).await.unwrap_err();
+    assert!(error.to_string().contains("cached failure"), "{}", error);
+    assert_eq!(served.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_stale_responses_are_served_while_refreshed() {
+    let (url, served) = serve_in_turn(vec![
+        ("200 OK", r#"[{"id":1,"name":"ann"}]"#),
+        ("200 OK", r#"[{"id":1,"name":"bob"}]"#),
+    ]);
+    let mut connector = RestConnector::new()
+        .with_cache_ttl(Duration::ZERO)
+        .with_stale_while_revalidate(Duration::from_secs(60));
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+    let name = |result: nirv_engine::utils::types::QueryResult| result.rows[0].values[1].clone();
+
+    assert_eq!(name(connector.execute_query(users_query(&url)).await.unwrap()), Value::Text("ann".to_string()));
+    // Past its TTL the last snapshot comes back at once, and a refresh runs behind it
+    assert_eq!(name(connector.execute_query(users_query(&url)).await.unwrap()), Value::Text("ann".to_string()));
+    for _ in 0..100 {
+        if served.lock().unwrap().len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(name(connector.execute_query(users_query(&url)).await.unwrap()), Value::Text("bob".to_string()));
+}
+
+#[tokio::test]
+async fn test_expired_responses_are_revalidated() {
+    let (url, served) = serve_in_turn(vec![
+        ("200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 14 Oct 2026 08:00:00 GMT", r#"[{"id":1,"name":"ann"}]"#),
+        ("304 Not Modified", ""),
+    ]);
+    let mut connector = RestConnector::new().with_cache_ttl(Duration::ZERO);
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+
+    connector.execute_query(users_query(&url)).await.unwrap();
+    // The expired response is asked for only if it changed, and kept when it did not
+    let result = connector.execute_query(users_query(&url)).await.unwrap();
+    assert_eq!(result.rows[0].values[1], Value::Text("ann".to_string()));
+    let freshness = &result.stats.unwrap().sources[0];
+    assert_eq!(freshness.cache, Some(CacheStatus::Hit));
+    assert!(freshness.fetched_at.is_some() && freshness.cache_age.is_some());
+    assert_eq!(freshness.last_modified.as_deref(), Some("Wed, 14 Oct 2026 08:00:00 GMT"));
+    let requests = served.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let revalidation = requests[1].to_lowercase();
+    assert!(revalidation.contains("if-none-match: \"v1\""), "{}", requests[1]);
+    assert!(revalidation.contains("if-modified-since: wed, 14 oct 2026 08:00:00 gmt"), "{}", requests[1]);
+    assert!(!requests[0].to_lowercase().contains("if-none-match"));
+}
+
+#[tokio::test]
+async fn test_refresh_drops_cached_responses() {
+    let (url, served) = serve_in_turn(vec![
+        ("200 OK", r#"[{"id":1,"name":"ann"}]"#),
+        ("200 OK", r#"[{"id":1,"name":"bob"}]"#),
+    ]);
+    let mut connector = RestConnector::new().with_cache_ttl(Duration::from_secs(300));
+    connector.connect(ConnectorInitConfig::new().with_param("base_url", &url)).await.unwrap();
+
+    connector.execute_query(users_query(&url)).await.unwrap();
+    assert_eq!(connector.refresh(&format!("{}/orders", url)).await.unwrap(), 0);
+    assert_eq!(connector.refresh(&format!("{}/users", url)).await.unwrap(), 1);
+    // With its entry dropped the endpoint is fetched again despite the TTL
+    let result = connector.execute_query(users_query(&url)).await.unwrap();
+    assert_eq!(result.rows[0].values[1], Value::Text("bob".to_string()));
+    assert_eq!(served.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_retries_wait_for_the_rate_limit() {
+    // Nothing listens on the port, so every attempt fails to connect and is retried
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let url = format!("http://127.0.0.1:{}", port);
+    let retries = RetryPolicy { max_retries: 3, base_delay: Duration::ZERO, max_delay: Duration::ZERO };
+    let mut connector = RestConnector::new();
+    connector.connect(ConnectorInitConfig::new()
+        .with_param("base_url", &url)
+        .with_param("rate_limit_rps", "10")
+        .with_param("rate_limit_burst", "1")
+        .with_retry_policy(retries)).await.unwrap();
+
+    let started = std::time::Instant::now();
+    assert!(connector.execute_query(users_query(&url)).await.is_err());
+    // Four attempts at ten a second take three waits of a tenth of a second
+    assert!(started.elapsed() >= Duration::from_millis(300), "{:?}", started.elapsed());
+}
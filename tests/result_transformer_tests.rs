@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use nirv_engine::{
+    Engine, EngineBuilder, MockConnector, ConnectorInitConfig, Connector,
+    NirvResult, QueryResult, Value,
+};
+use nirv_engine::engine::ResultTransformer;
+use nirv_engine::utils::{EngineConfig, ResultTransformerConfig};
+use std::sync::Arc;
+
+async fn create_engine(builder: EngineBuilder) -> NirvResult<Engine> {
+    let mut engine = builder.build()?;
+    engine.initialize_for_testing().await?;
+
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+
+    Ok(engine)
+}
+
+fn config_with_transformers(json: &str) -> EngineConfig {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.result_transformers = serde_json::from_str::<Vec<ResultTransformerConfig>>(json).unwrap();
+    config
+}
+
+#[tokio::test]
+async fn test_configured_transformers_apply_to_results() -> NirvResult<()> {
+    let config = config_with_transformers(r#"[
+        {"type": "scale_column", "column": "age", "factor": 12},
+        {"type": "rename_columns", "mappings": {"age": "age_months"}}
+    ]"#);
+    let engine = create_engine(EngineBuilder::new().with_config(config)).await?;
+
+    let result = engine.execute_query("SELECT * FROM source('mock.users')").await?;
+    let index = result.columns.iter().position(|c| c.name == "age_months").expect("renamed column");
+    assert_eq!(result.rows[0].values[index], Value::Float(360.0));
+
+    Ok(())
+}
+
+/// Transformer that drops every row after the first
+struct FirstRowOnly;
+
+#[async_trait]
+impl ResultTransformer for FirstRowOnly {
+    fn name(&self) -> &str {
+        "first_row_only"
+    }
+
+    async fn transform(&self, mut result: QueryResult) -> NirvResult<QueryResult> {
+        result.rows.truncate(1);
+        Ok(result)
+    }
+}
+
+#[tokio::test]
+async fn test_embedder_transformer_from_builder() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    let engine = create_engine(
+        EngineBuilder::new()
+            .with_config(config)
+            .with_result_transformer(Arc::new(FirstRowOnly))
+    ).await?;
+
+    let result = engine.execute_query("SELECT * FROM source('mock.users')").await?;
+    assert_eq!(result.row_count(), 1);
+
+    Ok(())
+}
@@ -288,17 +288,17 @@ mod tests {
         let _protocol = SQLiteProtocolAdapter::new();
         
         // Test SQLite value types
-        let values = vec![
+        let values = [
             SQLiteValue::Null,
             SQLiteValue::Integer(42),
-            SQLiteValue::Real(3.14159),
+            SQLiteValue::Real(2.5),
             SQLiteValue::Text("Hello, SQLite!".to_string()),
             SQLiteValue::Blob(vec![0x01, 0x02, 0x03, 0x04]),
         ];
         
         assert_eq!(values[0], SQLiteValue::Null);
         assert_eq!(values[1], SQLiteValue::Integer(42));
-        assert_eq!(values[2], SQLiteValue::Real(3.14159));
+        assert_eq!(values[2], SQLiteValue::Real(2.5));
         assert_eq!(values[3], SQLiteValue::Text("Hello, SQLite!".to_string()));
         assert_eq!(values[4], SQLiteValue::Blob(vec![0x01, 0x02, 0x03, 0x04]));
     }
@@ -365,7 +365,7 @@ mod tests {
         let _protocol = SQLiteProtocolAdapter::new();
         
         // Test column metadata
-        let columns = vec![
+        let columns = [
             SQLiteColumn {
                 name: "id".to_string(),
                 type_name: "INTEGER".to_string(),
@@ -593,16 +593,13 @@ mod tests {
         ];
         
         for operation in client_operations {
-            match operation {
-                SQLiteMessage::Query { sql } => {
-                    assert!(sql.len() > 0);
-                    assert!(sql.to_uppercase().contains("SELECT") || 
-                           sql.to_uppercase().contains("INSERT") || 
-                           sql.to_uppercase().contains("UPDATE") || 
-                           sql.to_uppercase().contains("DELETE") || 
-                           sql.to_uppercase().contains("CREATE"));
-                }
-                _ => {}
+            if let SQLiteMessage::Query { sql } = operation {
+                assert!(!sql.is_empty());
+                assert!(sql.to_uppercase().contains("SELECT") || 
+                       sql.to_uppercase().contains("INSERT") || 
+                       sql.to_uppercase().contains("UPDATE") || 
+                       sql.to_uppercase().contains("DELETE") || 
+                       sql.to_uppercase().contains("CREATE"));
             }
         }
         
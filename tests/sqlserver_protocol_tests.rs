@@ -4,8 +4,9 @@ use tokio::net::{TcpListener, TcpStream};
 
 use nirv_engine::protocol::{
     SqlServerProtocol, ProtocolAdapter, ProtocolType, Connection, Credentials,
-    ProtocolQuery, ProtocolResponse, ResponseFormat
+    ProtocolQuery, ProtocolResponse, ResponseFormat, StatementResult
 };
+use nirv_engine::utils::NirvError;
 use nirv_engine::utils::types::{QueryResult, ColumnMetadata, Row, Value, DataType};
 
 #[tokio::test]
@@ -87,6 +88,8 @@ async fn test_sqlserver_protocol_response_creation() {
         ],
         affected_rows: Some(2),
         execution_time: std::time::Duration::from_millis(10),
+        stats: None,
+        warnings: Vec::new(),
     };
     
     let response = ProtocolResponse::new(query_result.clone(), ProtocolType::SqlServer)
@@ -163,6 +166,8 @@ async fn test_sqlserver_response_formatting() {
         ],
         affected_rows: Some(1),
         execution_time: std::time::Duration::from_millis(5),
+        stats: None,
+        warnings: Vec::new(),
     };
     
     // Create a mock connection
@@ -188,6 +193,22 @@ async fn test_sqlserver_response_formatting() {
     assert_eq!(bytes[1], 0x01); // Status: End of message
 }
 
+#[tokio::test]
+async fn test_sqlserver_info_token() {
+    let protocol = SqlServerProtocol::new();
+    
+    let info = protocol.create_info("api.orders failed");
+    assert_eq!(info[0], 0xAB); // INFO token
+    let length = u16::from_le_bytes([info[1], info[2]]) as usize;
+    assert_eq!(length, info.len() - 3);
+    assert_eq!(info[8], 10); // Severity of an informational message
+    
+    // Message length in characters, then UTF-16 text
+    assert_eq!(u16::from_le_bytes([info[9], info[10]]), 17);
+    let text: Vec<u16> = info[11..11 + 34].chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    assert_eq!(String::from_utf16(&text).unwrap(), "api.orders failed");
+}
+
 #[tokio::test]
 async fn test_sqlserver_error_response_formatting() {
     let protocol = SqlServerProtocol::new();
@@ -200,6 +221,31 @@ async fn test_sqlserver_error_response_formatting() {
     assert_eq!(error_response[1], 0x01); // Status: End of message
 }
 
+#[tokio::test]
+async fn test_sqlserver_batch_of_statements() {
+    let protocol = SqlServerProtocol::new();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+    let connection = Connection::new(stream, ProtocolType::SqlServer);
+    
+    // Every statement but the last ends with DONE_MORE
+    let results = vec![
+        StatementResult::new("SET TIME ZONE 'UTC'", Ok(QueryResult::new())),
+        StatementResult::new("SET TIME ZONE 'UTC'", Ok(QueryResult::new())),
+    ];
+    let bytes = protocol.format_statement_responses(&connection, results).await.unwrap();
+    assert_eq!(u16::from_be_bytes([bytes[2], bytes[3]]) as usize, bytes.len());
+    assert_eq!((bytes[8], u16::from_le_bytes([bytes[9], bytes[10]])), (0xFD, 0x0011));
+    assert_eq!((bytes[21], u16::from_le_bytes([bytes[22], bytes[23]])), (0xFD, 0x0010));
+    
+    // A failed statement sends an error and DONE_ERROR, ending the batch
+    let results = vec![StatementResult::new("SELECT * FROM nowhere", Err(NirvError::Internal("boom".to_string())))];
+    let bytes = protocol.format_statement_responses(&connection, results).await.unwrap();
+    assert_eq!(bytes[8], 0xAA);
+    let done = &bytes[bytes.len() - 13..];
+    assert_eq!((done[0], u16::from_le_bytes([done[1], done[2]])), (0xFD, 0x0002));
+}
+
 #[tokio::test]
 async fn test_sqlserver_data_type_conversion() {
     let protocol = SqlServerProtocol::new();
@@ -208,7 +254,7 @@ async fn test_sqlserver_data_type_conversion() {
     assert_eq!(protocol.value_to_tds_type(&Value::Integer(42)), 0x26); // INTN
     assert_eq!(protocol.value_to_tds_type(&Value::Text("test".to_string())), 0xE7); // NVARCHAR
     assert_eq!(protocol.value_to_tds_type(&Value::Boolean(true)), 0x68); // BITN
-    assert_eq!(protocol.value_to_tds_type(&Value::Float(3.14)), 0x6D); // FLOATN
+    assert_eq!(protocol.value_to_tds_type(&Value::Float(2.5)), 0x6D); // FLOATN
     assert_eq!(protocol.value_to_tds_type(&Value::Null), 0x1F); // NULL
 }
 
@@ -235,4 +281,48 @@ async fn test_sqlserver_authentication_flow() {
     let auth_result = protocol.authenticate(&mut connection, credentials).await;
     assert!(auth_result.is_ok());
     assert!(connection.authenticated);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_sqlserver_catalog_procedures_use_engine_catalog() {
+    use nirv_engine::{Engine, MockConnector, ConnectorInitConfig, Connector};
+    use nirv_engine::utils::EngineConfig;
+    use std::sync::Arc;
+
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await.unwrap();
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await.unwrap();
+    engine.register_connector("mock", mock_connector).await.unwrap();
+
+    let protocol = SqlServerProtocol::new().with_catalog(Arc::new(engine));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        drop(stream);
+    });
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let mut connection = Connection::new(stream, ProtocolType::SqlServer);
+    connection.authenticated = true;
+    connection.database = "analytics".to_string();
+
+    let tables = protocol.handle_query(&connection, ProtocolQuery::new(
+        "EXEC sp_tables @table_owner = N'mock', @table_type = N'''TABLE'',''VIEW'''".to_string(),
+        ProtocolType::SqlServer,
+    )).await.unwrap();
+    let names: Vec<&Value> = tables.result.rows.iter().map(|row| &row.values[2]).collect();
+    assert!(names.contains(&&Value::Text("users".to_string())));
+    assert!(tables.result.rows.iter().all(|row| row.values[0] == Value::Text("analytics".to_string())));
+
+    let columns = protocol.handle_query(&connection, ProtocolQuery::new(
+        "SELECT c.name FROM sys.columns c JOIN sys.objects o ON c.object_id = o.object_id \
+         WHERE o.name = 'users' ORDER BY c.column_id".to_string(),
+        ProtocolType::SqlServer,
+    )).await.unwrap();
+    let column_names: Vec<Value> = columns.result.rows.iter().map(|row| row.values[0].clone()).collect();
+    assert_eq!(column_names, ["id", "name", "email", "age", "active"]
+        .iter().map(|name| Value::Text(name.to_string())).collect::<Vec<_>>());
+}
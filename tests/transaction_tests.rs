@@ -0,0 +1,642 @@
+use async_trait::async_trait;
+use nirv_engine::{
+    Engine, EngineBuilder, Session, TransactionStatus,
+    MockConnector, ConnectorInitConfig, Connector,
+    NirvResult, NirvError,
+};
+use nirv_engine::connectors::ConnectorCapabilities;
+use nirv_engine::protocol::{PostgresProtocol, ProtocolAdapter};
+use nirv_engine::utils::{
+    EngineConfig, ConnectorError, DispatcherError, ConnectorQuery, ConnectorType, QueryResult, Schema,
+    InternalQuery, QueryOperation, PredicateOperator, PredicateValue,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Mock connector that records transaction calls
+struct TransactionalConnector {
+    inner: MockConnector,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl TransactionalConnector {
+    async fn connected(log: Arc<Mutex<Vec<String>>>) -> NirvResult<Box<Self>> {
+        let mut inner = MockConnector::new();
+        inner.connect(ConnectorInitConfig::new()).await?;
+        Ok(Box::new(Self { inner, log }))
+    }
+
+    fn record(&self, entry: String) {
+        self.log.lock().unwrap().push(entry);
+    }
+}
+
+#[async_trait]
+impl Connector for TransactionalConnector {
+    async fn connect(&mut self, config: ConnectorInitConfig) -> NirvResult<()> {
+        self.inner.connect(config).await
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        self.record("autocommit".to_string());
+        self.inner.execute_query(query).await
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        self.inner.get_schema(object_name).await
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        self.inner.disconnect().await
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Mock
+    }
+
+    fn supports_transactions(&self) -> bool {
+        true
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities {
+            supports_transactions: true,
+            ..self.inner.get_capabilities()
+        }
+    }
+
+    async fn begin_transaction(&self) -> NirvResult<u64> {
+        self.record("begin 1".to_string());
+        Ok(1)
+    }
+
+    async fn execute_in_transaction(&self, transaction_id: u64, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        self.record(format!("execute {}", transaction_id));
+        self.inner.execute_query(query).await
+    }
+
+    async fn commit_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        self.record(format!("commit {}", transaction_id));
+        Ok(())
+    }
+
+    async fn rollback_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        self.record(format!("rollback {}", transaction_id));
+        Ok(())
+    }
+
+    async fn prepare_transaction(&self, transaction_id: u64, _global_id: &str) -> NirvResult<()> {
+        self.record(format!("prepare {}", transaction_id));
+        Ok(())
+    }
+
+    async fn commit_prepared(&self, _global_id: &str) -> NirvResult<()> {
+        self.record("commit prepared".to_string());
+        Ok(())
+    }
+}
+
+/// Accounts as `(id, balance)` rows by table name
+type Tables = HashMap<String, Vec<(i64, i64)>>;
+
+/// Transactional connector keeping accounts tables in memory. A transaction writes to its own
+/// copy of the tables, which replaces the committed ones when it commits.
+struct AccountsConnector {
+    committed: Arc<Mutex<Tables>>,
+    open: Mutex<HashMap<u64, Tables>>,
+    prepared: Mutex<HashMap<String, Tables>>,
+    next_transaction_id: AtomicU64,
+    refuse_prepare: bool,
+}
+
+impl AccountsConnector {
+    fn new(committed: Arc<Mutex<Tables>>) -> Box<Self> {
+        Box::new(Self {
+            committed,
+            open: Mutex::new(HashMap::new()),
+            prepared: Mutex::new(HashMap::new()),
+            next_transaction_id: AtomicU64::new(1),
+            refuse_prepare: false,
+        })
+    }
+
+    fn failed(message: String) -> NirvError {
+        ConnectorError::QueryExecutionFailed(message).into()
+    }
+
+    fn matches(query: &InternalQuery, (id, balance): (i64, i64)) -> NirvResult<bool> {
+        for predicate in &query.predicates {
+            let value = match predicate.column.as_str() {
+                "id" => id,
+                "balance" => balance,
+                column => return Err(Self::failed(format!("No column {}", column))),
+            };
+            match (&predicate.operator, &predicate.value) {
+                (PredicateOperator::Equal, PredicateValue::Integer(expected)) if value == *expected => {}
+                (PredicateOperator::Equal, PredicateValue::Integer(_)) => return Ok(false),
+                _ => return Err(Self::failed(format!("Unsupported predicate {:?}", predicate))),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Apply a write to the tables, returning the number of rows it changed
+    fn write(tables: &mut Tables, query: &InternalQuery) -> NirvResult<u64> {
+        let table = &query.sources[0].identifier;
+        let rows = tables.get_mut(table).ok_or_else(|| Self::failed(format!("No table {}", table)))?;
+        let integer = |value: &PredicateValue| match value {
+            PredicateValue::Integer(i) => Ok(*i),
+            value => Err(Self::failed(format!("{:?} is not an integer", value))),
+        };
+        match query.operation {
+            QueryOperation::Insert => {
+                for row in &query.values {
+                    rows.push((integer(&row[0])?, integer(&row[1])?));
+                }
+                Ok(query.values.len() as u64)
+            }
+            QueryOperation::Update => {
+                let balance = integer(&query.assignments[0].value)?;
+                let mut changed = 0;
+                for row in rows.iter_mut() {
+                    if Self::matches(query, *row)? {
+                        row.1 = balance;
+                        changed += 1;
+                    }
+                }
+                Ok(changed)
+            }
+            QueryOperation::Delete => {
+                let before = rows.len();
+                let mut kept = Vec::new();
+                for row in rows.drain(..) {
+                    if !Self::matches(query, row)? {
+                        kept.push(row);
+                    }
+                }
+                *rows = kept;
+                Ok((before - rows.len()) as u64)
+            }
+            QueryOperation::Select => Err(Self::failed("Only writes are supported".to_string())),
+        }
+    }
+
+    fn written(affected: u64) -> QueryResult {
+        QueryResult { affected_rows: Some(affected), ..QueryResult::new() }
+    }
+}
+
+#[async_trait]
+impl Connector for AccountsConnector {
+    async fn connect(&mut self, _config: ConnectorInitConfig) -> NirvResult<()> {
+        Ok(())
+    }
+
+    async fn execute_query(&self, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let affected = Self::write(&mut self.committed.lock().unwrap(), &query.query)?;
+        Ok(Self::written(affected))
+    }
+
+    async fn get_schema(&self, object_name: &str) -> NirvResult<Schema> {
+        Err(Self::failed(format!("No schema for {}", object_name)))
+    }
+
+    async fn disconnect(&mut self) -> NirvResult<()> {
+        Ok(())
+    }
+
+    fn get_connector_type(&self) -> ConnectorType {
+        ConnectorType::Mock
+    }
+
+    fn supports_transactions(&self) -> bool {
+        true
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn get_capabilities(&self) -> ConnectorCapabilities {
+        ConnectorCapabilities {
+            supports_joins: false,
+            supports_aggregations: false,
+            supports_subqueries: false,
+            supports_transactions: true,
+            supports_schema_introspection: false,
+            max_concurrent_queries: None,
+        }
+    }
+
+    async fn begin_transaction(&self) -> NirvResult<u64> {
+        let transaction_id = self.next_transaction_id.fetch_add(1, Ordering::Relaxed);
+        let tables = self.committed.lock().unwrap().clone();
+        self.open.lock().unwrap().insert(transaction_id, tables);
+        Ok(transaction_id)
+    }
+
+    async fn execute_in_transaction(&self, transaction_id: u64, query: ConnectorQuery) -> NirvResult<QueryResult> {
+        let mut open = self.open.lock().unwrap();
+        let tables = open.get_mut(&transaction_id)
+            .ok_or_else(|| Self::failed(format!("Transaction {} is not open", transaction_id)))?;
+        Ok(Self::written(Self::write(tables, &query.query)?))
+    }
+
+    async fn commit_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        let tables = self.open.lock().unwrap().remove(&transaction_id)
+            .ok_or_else(|| Self::failed(format!("Transaction {} is not open", transaction_id)))?;
+        *self.committed.lock().unwrap() = tables;
+        Ok(())
+    }
+
+    async fn rollback_transaction(&self, transaction_id: u64) -> NirvResult<()> {
+        self.open.lock().unwrap().remove(&transaction_id);
+        Ok(())
+    }
+
+    async fn prepare_transaction(&self, transaction_id: u64, global_id: &str) -> NirvResult<()> {
+        if self.refuse_prepare {
+            return Err(ConnectorError::UnsupportedOperation("No durable prepare".to_string()).into());
+        }
+        let tables = self.open.lock().unwrap().remove(&transaction_id)
+            .ok_or_else(|| Self::failed(format!("Transaction {} is not open", transaction_id)))?;
+        self.prepared.lock().unwrap().insert(global_id.to_string(), tables);
+        Ok(())
+    }
+
+    async fn commit_prepared(&self, global_id: &str) -> NirvResult<()> {
+        let tables = self.prepared.lock().unwrap().remove(global_id)
+            .ok_or_else(|| Self::failed(format!("Transaction '{}' is not prepared", global_id)))?;
+        *self.committed.lock().unwrap() = tables;
+        Ok(())
+    }
+
+    async fn rollback_prepared(&self, global_id: &str) -> NirvResult<()> {
+        self.prepared.lock().unwrap().remove(global_id);
+        Ok(())
+    }
+}
+
+fn accounts(rows: &[(i64, i64)]) -> Arc<Mutex<Tables>> {
+    Arc::new(Mutex::new(HashMap::from([("accounts".to_string(), rows.to_vec())])))
+}
+
+async fn create_engine_with_journal(dir: &std::path::Path) -> NirvResult<Engine> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = EngineBuilder::new()
+        .with_config(config)
+        .with_transaction_journal(dir.join("2pc.journal"))
+        .build()?;
+    engine.initialize_for_testing().await?;
+    Ok(engine)
+}
+
+async fn create_engine() -> NirvResult<Engine> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    Ok(engine)
+}
+
+#[tokio::test]
+async fn test_transaction_pass_through_commit() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "BEGIN").await?;
+    assert_eq!(session.status(), TransactionStatus::InTransaction);
+
+    let result = engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await?;
+    assert_eq!(result.row_count(), 3);
+    engine.execute_session_query(&mut session, "SELECT * FROM source('pg.products')").await?;
+
+    engine.execute_session_query(&mut session, "COMMIT;").await?;
+    assert_eq!(session.status(), TransactionStatus::Idle);
+
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log, vec!["begin 1", "execute 1", "execute 1", "commit 1"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transaction_rollback() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "START TRANSACTION").await?;
+    engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await?;
+    engine.execute_session_query(&mut session, "ROLLBACK").await?;
+
+    assert!(!session.in_transaction());
+    assert_eq!(log.lock().unwrap().last().unwrap(), "rollback 1");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_statements_outside_transaction_autocommit() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await?;
+
+    // An empty transaction block never touches the connector
+    engine.execute_session_query(&mut session, "BEGIN").await?;
+    engine.execute_session_query(&mut session, "COMMIT").await?;
+
+    assert_eq!(log.lock().unwrap().clone(), vec!["autocommit"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cross_connector_transaction_rejected() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+    engine.register_connector("mssql", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "BEGIN").await?;
+    engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await?;
+
+    let result = engine.execute_session_query(&mut session, "SELECT * FROM source('mssql.users')").await;
+    match result {
+        Err(NirvError::Dispatcher(DispatcherError::CrossConnectorTransactionUnsupported(msg))) => {
+            assert!(msg.contains("pg"));
+            assert!(msg.contains("mssql"));
+        }
+        other => panic!("Expected cross-connector transaction error, got {:?}", other.map(|r| r.row_count())),
+    }
+
+    // The transaction is aborted until the client ends it
+    assert_eq!(session.status(), TransactionStatus::Failed);
+    assert!(engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await.is_err());
+
+    // COMMIT of a failed transaction rolls back
+    engine.execute_session_query(&mut session, "COMMIT").await?;
+    assert_eq!(log.lock().unwrap().last().unwrap(), "rollback 1");
+    assert_eq!(session.status(), TransactionStatus::Idle);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transaction_on_non_transactional_connector() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let mut mock_connector = Box::new(MockConnector::new());
+    mock_connector.connect(ConnectorInitConfig::new()).await?;
+    engine.register_connector("mock", mock_connector).await?;
+
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "BEGIN").await?;
+    let result = engine.execute_session_query(&mut session, "SELECT * FROM source('mock.users')").await;
+
+    assert!(matches!(result, Err(NirvError::Connector(ConnectorError::UnsupportedOperation(_)))));
+    assert_eq!(session.status(), TransactionStatus::Failed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transaction_rejects_joins() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+    engine.register_connector("ms", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    for sql in [
+        "SELECT u.name, p.name FROM source('pg.users') u JOIN source('ms.products') p ON u.id = p.id",
+        "SELECT u.name, p.name FROM source('pg.users') u JOIN source('pg.products') p ON u.id = p.id",
+    ] {
+        let mut session = Session::new();
+        engine.execute_session_query(&mut session, "BEGIN").await?;
+        let result = engine.execute_session_query(&mut session, sql).await;
+        assert!(matches!(result, Err(NirvError::Dispatcher(DispatcherError::CrossConnectorTransactionUnsupported(_)))), "{:?}", result);
+        assert_eq!(session.status(), TransactionStatus::Failed);
+        engine.execute_session_query(&mut session, "ROLLBACK").await?;
+    }
+    assert!(log.lock().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transaction_statements_count_against_quotas() -> NirvResult<()> {
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+    config.quotas = Some(serde_json::from_str(r#"{"principals": {"ann": {"rows_per_hour": 2}}}"#).unwrap());
+    let mut engine = Engine::new(config);
+    engine.initialize_for_testing().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let mut session = Session::new().with_user("ann");
+    engine.execute_session_query(&mut session, "BEGIN").await?;
+    engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await?;
+    let result = engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await;
+    assert!(matches!(result, Err(NirvError::QuotaExceeded(_))), "{:?}", result);
+    engine.execute_session_query(&mut session, "ROLLBACK").await?;
+
+    // What the transaction fetched was charged
+    let result = engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await;
+    assert!(matches!(result, Err(NirvError::QuotaExceeded(_))), "{:?}", result);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_close_session_rolls_back_open_transaction() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let mut session = Session::new();
+    engine.execute_session_query(&mut session, "BEGIN").await?;
+    engine.execute_session_query(&mut session, "SELECT * FROM source('pg.users')").await?;
+    engine.close_session(&mut session).await?;
+
+    assert!(!session.in_transaction());
+    assert_eq!(log.lock().unwrap().last().unwrap(), "rollback 1");
+
+    Ok(())
+}
+
+/// Send a PostgreSQL simple query and return the transaction status of the ReadyForQuery ending its response
+async fn simple_query(client: &mut TcpStream, sql: &str) -> u8 {
+    let mut message = vec![b'Q'];
+    message.extend_from_slice(&(sql.len() as u32 + 5).to_be_bytes());
+    message.extend_from_slice(sql.as_bytes());
+    message.push(0);
+    client.write_all(&message).await.unwrap();
+
+    loop {
+        let message_type = client.read_u8().await.unwrap();
+        let length = client.read_u32().await.unwrap() as usize;
+        let mut body = vec![0u8; length - 4];
+        client.read_exact(&mut body).await.unwrap();
+        if message_type == b'Z' {
+            return body[0];
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_protocol_connection_runs_in_session() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+    let (stream, _) = listener.accept().await.unwrap();
+    let adapter = PostgresProtocol::new();
+    let mut connection = adapter.accept_connection(stream).await?;
+
+    let client = tokio::spawn(async move {
+        let statuses = vec![
+            simple_query(&mut client, "BEGIN").await,
+            simple_query(&mut client, "SELECT * FROM source('pg.users')").await,
+            simple_query(&mut client, "SELECT FROM; COMMIT").await,
+            simple_query(&mut client, "ROLLBACK; BEGIN").await,
+            simple_query(&mut client, "SELECT * FROM source('pg.users')").await,
+        ];
+        // Disconnect with the transaction still open
+        drop(client);
+        statuses
+    });
+    engine.serve_connection(&adapter, &mut connection).await?;
+
+    assert_eq!(client.await.unwrap(), vec![b'T', b'T', b'E', b'T', b'T']);
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log, vec!["begin 1", "execute 1", "rollback 1", "begin 1", "execute 1", "rollback 1"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distributed_write_two_phase_commit() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = EngineConfig::default();
+    config.protocol_adapters.clear();
+
+    let mut engine = EngineBuilder::new()
+        .with_config(config)
+        .with_transaction_journal(dir.path().join("2pc.journal"))
+        .build()?;
+    engine.initialize_for_testing().await?;
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    engine.register_connector("pg", TransactionalConnector::connected(log.clone()).await?).await?;
+    engine.register_connector("mssql", TransactionalConnector::connected(log.clone()).await?).await?;
+
+    let results = engine.execute_distributed_write(&[
+        "SELECT * FROM source('pg.users')",
+        "SELECT * FROM source('mssql.products')",
+        "SELECT * FROM source('pg.products')",
+    ]).await?;
+    assert_eq!(results.len(), 3);
+
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log.iter().filter(|l| l.starts_with("begin")).count(), 2);
+    assert_eq!(log.iter().filter(|l| l.starts_with("prepare")).count(), 2);
+    assert_eq!(log.iter().filter(|l| *l == "commit prepared").count(), 2);
+
+    let report = engine.recover_transactions().await?;
+    assert!(report.committed.is_empty() && report.aborted.is_empty() && report.unresolved.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distributed_write_requires_journal() -> NirvResult<()> {
+    let engine = create_engine().await?;
+    let result = engine.execute_distributed_write(&["SELECT * FROM source('pg.users')"]).await;
+    assert!(matches!(result, Err(NirvError::Configuration(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distributed_write_commits_on_every_connector() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = create_engine_with_journal(dir.path()).await?;
+    let pg = accounts(&[(1, 100)]);
+    let mssql = accounts(&[(2, 0)]);
+    engine.register_connector("pg", AccountsConnector::new(pg.clone())).await?;
+    engine.register_connector("mssql", AccountsConnector::new(mssql.clone())).await?;
+
+    let results = engine.execute_distributed_write(&[
+        "UPDATE source('pg.accounts') SET balance = 60 WHERE id = 1",
+        "UPDATE source('mssql.accounts') SET balance = 40 WHERE id = 2",
+        "INSERT INTO source('pg.accounts') (id, balance) VALUES (3, 0), (4, 0)",
+    ]).await?;
+    let affected: Vec<Option<u64>> = results.iter().map(|result| result.affected_rows).collect();
+    assert_eq!(affected, vec![Some(1), Some(1), Some(2)]);
+
+    assert_eq!(pg.lock().unwrap()["accounts"], vec![(1, 60), (3, 0), (4, 0)]);
+    assert_eq!(mssql.lock().unwrap()["accounts"], vec![(2, 40)]);
+    let report = engine.recover_transactions().await?;
+    assert!(report.committed.is_empty() && report.aborted.is_empty() && report.unresolved.is_empty());
+
+    // Outside a distributed write, a write runs on its source as is
+    let deleted = engine.execute_query("DELETE FROM source('pg.accounts') WHERE balance = 0").await?;
+    assert_eq!(deleted.affected_rows, Some(2));
+    assert_eq!(pg.lock().unwrap()["accounts"], vec![(1, 60)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distributed_write_aborts_on_every_connector() -> NirvResult<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = create_engine_with_journal(dir.path()).await?;
+    let pg = accounts(&[(1, 100)]);
+    let mssql = accounts(&[(2, 0)]);
+    engine.register_connector("pg", AccountsConnector::new(pg.clone())).await?;
+    engine.register_connector("mssql", AccountsConnector::new(mssql.clone())).await?;
+
+    // A statement failing on one connector undoes those already run on the other
+    let result = engine.execute_distributed_write(&[
+        "UPDATE source('pg.accounts') SET balance = 60 WHERE id = 1",
+        "DELETE FROM source('mssql.missing') WHERE id = 2",
+    ]).await;
+    assert!(matches!(result, Err(NirvError::Connector(ConnectorError::QueryExecutionFailed(_)))));
+    assert_eq!(pg.lock().unwrap()["accounts"], vec![(1, 100)]);
+
+    // So does a connector that cannot prepare, as SQL Server cannot
+    let refusing = accounts(&[(5, 0)]);
+    let mut connector = AccountsConnector::new(refusing.clone());
+    connector.refuse_prepare = true;
+    engine.register_connector("mssql2", connector).await?;
+    let result = engine.execute_distributed_write(&[
+        "UPDATE source('pg.accounts') SET balance = 60 WHERE id = 1",
+        "INSERT INTO source('mssql2.accounts') VALUES (6, 0)",
+    ]).await;
+    assert!(matches!(result, Err(NirvError::Dispatcher(DispatcherError::TransactionCoordinationFailed(_)))));
+    assert_eq!(pg.lock().unwrap()["accounts"], vec![(1, 100)]);
+    assert_eq!(refusing.lock().unwrap()["accounts"], vec![(5, 0)]);
+    assert_eq!(mssql.lock().unwrap()["accounts"], vec![(2, 0)]);
+
+    let report = engine.recover_transactions().await?;
+    assert!(report.committed.is_empty() && report.unresolved.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use openssl::rsa::Rsa;
+use serde_json::{json, Value as JsonValue};
+
+use nirv_engine::connectors::{BigQueryConnector, Connector, ConnectorInitConfig, SnowflakeConnector};
+use nirv_engine::utils::types::{
+    ConnectorQuery, ConnectorType, DataSource, InternalQuery, Predicate, PredicateOperator, PredicateValue,
+    QueryOperation, Value,
+};
+
+/// A request the mock warehouse received: method, path with query string, and JSON body
+type Received = (String, String, JsonValue);
+
+/// Serve warehouse API responses from `respond`, recording every request
+async fn start_mock<F>(respond: F) -> (SocketAddr, Arc<Mutex<Vec<Received>>>)
+where
+    F: Fn(&str, &str) -> (StatusCode, JsonValue) + Send + Sync + 'static,
+{
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let respond = Arc::new(respond);
+    let log = received.clone();
+    let make_service = make_service_fn(move |_| {
+        let (respond, log) = (respond.clone(), log.clone());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let (respond, log) = (respond.clone(), log.clone());
+                async move {
+                    let method = request.method().to_string();
+                    let path = request.uri().path_and_query().map(|path| path.to_string()).unwrap_or_default();
+                    let bytes = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+                    let body = serde_json::from_slice(&bytes)
+                        .unwrap_or_else(|_| JsonValue::String(String::from_utf8_lossy(&bytes).to_string()));
+                    let (status, response) = respond(&method, &path);
+                    log.lock().unwrap().push((method, path, body));
+                    Ok::<_, Infallible>(Response::builder()
+                        .status(status)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(response.to_string()))
+                        .unwrap())
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    (addr, received)
+}
+
+fn private_key_pem() -> String {
+    String::from_utf8(Rsa::generate(2048).unwrap().private_key_to_pem().unwrap()).unwrap()
+}
+
+fn orders_query(connector_type: ConnectorType, identifier: &str) -> ConnectorQuery {
+    let mut query = InternalQuery::new(QueryOperation::Select);
+    query.sources.push(DataSource { object_type: "warehouse".to_string(), identifier: identifier.to_string(), alias: None });
+    query.predicates.push(Predicate {
+        column: "region".to_string(),
+        operator: PredicateOperator::Equal,
+        value: PredicateValue::String("EMEA".to_string()),
+    });
+    query.limit = Some(10);
+    ConnectorQuery { connector_type, query, connection_params: HashMap::new() }
+}
+
+#[tokio::test]
+async fn test_bigquery_query_follows_pages() {
+    let (addr, received) = start_mock(|method, path| match (method, path) {
+        ("POST", "/token") => (StatusCode::OK, json!({ "access_token": "ya29.token", "expires_in": 3600 })),
+        ("POST", _) => (StatusCode::OK, json!({
+            "jobReference": { "jobId": "job_1", "location": "EU" },
+            "jobComplete": true,
+            "schema": { "fields": [
+                { "name": "id", "type": "INTEGER", "mode": "REQUIRED" },
+                { "name": "ordered_at", "type": "TIMESTAMP", "mode": "NULLABLE" },
+            ] },
+            "rows": [{ "f": [{ "v": "1" }, { "v": "1714557600000000" }] }],
+            "pageToken": "page-2",
+        })),
+        _ => (StatusCode::OK, json!({
+            "jobReference": { "jobId": "job_1", "location": "EU" },
+            "jobComplete": true,
+            "rows": [{ "f": [{ "v": "2" }, { "v": null }] }],
+        })),
+    }).await;
+
+    let credentials = json!({
+        "type": "service_account",
+        "client_email": "loader@analytics.iam.gserviceaccount.com",
+        "private_key": private_key_pem(),
+        "token_uri": format!("http://{}/token", addr),
+    });
+    let mut connector = BigQueryConnector::new();
+    connector.connect(ConnectorInitConfig::new()
+        .with_param("project_id", "analytics")
+        .with_param("dataset", "sales")
+        .with_param("credentials_json", &credentials.to_string())
+        .with_param("api_url", &format!("http://{}", addr))
+    ).await.unwrap();
+
+    let result = connector.execute_query(orders_query(ConnectorType::BigQuery, "orders")).await.unwrap();
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].values, vec![Value::Integer(1), Value::DateTime("2024-05-01 10:00:00".to_string())]);
+    assert_eq!(result.rows[1].values, vec![Value::Integer(2), Value::Null]);
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 3);
+    let (_, path, body) = &received[1];
+    assert_eq!(path, "/bigquery/v2/projects/analytics/queries");
+    assert!(body["query"].as_str().unwrap().ends_with("SELECT * FROM orders WHERE region = ? LIMIT 10"), "{}", body["query"]);
+    assert_eq!(body["queryParameters"][0]["parameterValue"]["value"], "EMEA");
+    assert_eq!(body["defaultDataset"]["datasetId"], "sales");
+    let (_, path, _) = &received[2];
+    assert!(path.starts_with("/bigquery/v2/projects/analytics/queries/job_1?"), "{}", path);
+    assert!(path.contains("pageToken=page-2") && path.contains("location=EU"), "{}", path);
+}
+
+#[tokio::test]
+async fn test_snowflake_statement_waits_and_reads_partitions() {
+    let (addr, received) = start_mock(|method, path| {
+        let row_type = json!([
+            { "name": "ID", "type": "fixed", "scale": 0, "nullable": false },
+            { "name": "REGION", "type": "text", "scale": null, "nullable": true },
+        ]);
+        match (method, path) {
+            ("POST", _) => (StatusCode::ACCEPTED, json!({ "statementHandle": "01b2", "message": "Asynchronous execution in progress." })),
+            (_, "/api/v2/statements/01b2") => (StatusCode::OK, json!({
+                "statementHandle": "01b2",
+                "resultSetMetaData": { "rowType": row_type, "partitionInfo": [{ "rowCount": 1 }, { "rowCount": 1 }] },
+                "data": [["1", "EMEA"]],
+            })),
+            _ => (StatusCode::OK, json!({ "data": [["2", null]] })),
+        }
+    }).await;
+
+    let mut connector = SnowflakeConnector::new();
+    connector.connect(ConnectorInitConfig::new()
+        .with_param("account", "acme-eu.eu-west-1")
+        .with_param("user", "loader")
+        .with_param("private_key", &private_key_pem())
+        .with_param("warehouse", "REPORTING")
+        .with_param("api_url", &format!("http://{}", addr))
+    ).await.unwrap();
+
+    let result = connector.execute_query(orders_query(ConnectorType::Snowflake, "orders")).await.unwrap();
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].values, vec![Value::Integer(1), Value::Text("EMEA".to_string())]);
+    assert_eq!(result.rows[1].values, vec![Value::Integer(2), Value::Null]);
+
+    let received = received.lock().unwrap();
+    // SELECT 1 on connect, then the query: each posted, polled and read in two partitions
+    assert_eq!(received.len(), 6);
+    let (_, _, body) = &received[3];
+    assert!(body["statement"].as_str().unwrap().ends_with("SELECT * FROM orders WHERE region = ? LIMIT 10"), "{}", body["statement"]);
+    assert_eq!(body["bindings"]["1"], json!({ "type": "TEXT", "value": "EMEA" }));
+    assert_eq!(body["warehouse"], "REPORTING");
+    assert_eq!(received[5].1, "/api/v2/statements/01b2?partition=1");
+}
+
+#[tokio::test]
+async fn test_warehouse_errors_are_reported() {
+    let (addr, _) = start_mock(|_, _| (StatusCode::UNAUTHORIZED, json!({ "code": "390144", "message": "JWT token is invalid." }))).await;
+
+    let mut connector = SnowflakeConnector::new();
+    let error = connector.connect(ConnectorInitConfig::new()
+        .with_param("account", "acme")
+        .with_param("user", "loader")
+        .with_param("private_key", &private_key_pem())
+        .with_param("api_url", &format!("http://{}", addr))
+    ).await.unwrap_err();
+    assert!(error.to_string().contains("JWT token is invalid."), "{}", error);
+    assert!(!connector.is_connected());
+
+    let mut connector = BigQueryConnector::new();
+    let error = connector.connect(ConnectorInitConfig::new().with_param("project_id", "analytics")).await.unwrap_err();
+    assert!(error.to_string().contains("credentials_path"), "{}", error);
+}